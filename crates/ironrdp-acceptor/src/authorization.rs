@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use ironrdp_pdu::nego::SecurityProtocol;
+
+/// Metadata attached by the [`Authorizer`] to an authorized session
+pub type SessionMetadata = BTreeMap<String, String>;
+
+/// Decides whether an authenticated user is allowed to open a session
+///
+/// The authorizer is called once per connection, after the user is authenticated and before the session resources
+/// are allocated:
+///
+/// - at the end of CredSSP when HYBRID_EX is negotiated, a denial being reported to the client with an
+///   Early User Authorization Result PDU (access denied);
+/// - once the Client Info PDU is received otherwise, a denial being reported to the client with a
+///   Set Error Info PDU (server denied connection).
+///
+/// It is not called again on deactivation-reactivation.
+pub type Authorizer = dyn Fn(&AuthContext) -> AuthDecision + Send + Sync;
+
+/// Information on the connection being authorized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthContext {
+    /// Account name of the authenticated user.
+    pub username: String,
+    /// Domain of the authenticated user, if it provided one.
+    pub domain: Option<String>,
+    /// Address of the client, when known by the server.
+    pub client_addr: Option<SocketAddr>,
+    /// Security protocol selected for the connection.
+    pub protocol: SecurityProtocol,
+}
+
+/// Decision of the [`Authorizer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthDecision {
+    /// The session is opened, with the given metadata reported in [`AcceptorResult`](crate::AcceptorResult).
+    Allow(SessionMetadata),
+    /// The connection is refused for the given reason, which is logged but not sent to the client.
+    Deny(String),
+}
+
+impl AuthDecision {
+    /// Allows the session without any metadata.
+    pub fn allow() -> Self {
+        Self::Allow(SessionMetadata::new())
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 use ironrdp_connector::{reason_err, ConnectorError, ConnectorErrorExt, ConnectorResult, Sequence, State, Written};
 use ironrdp_core::WriteBuf;
@@ -10,7 +10,8 @@ use pdu::mcs;
 pub struct ChannelConnectionSequence {
     state: ChannelConnectionState,
     user_channel_id: u16,
-    channel_ids: Option<HashSet<u16>>,
+    /// Channels announced to the client, which it must all join.
+    channel_ids: Option<BTreeSet<u16>>,
 }
 
 #[derive(Default, Debug)]
@@ -22,10 +23,10 @@ pub enum ChannelConnectionState {
     WaitAttachUserRequest,
     SendAttachUserConfirm,
     WaitChannelJoinRequest {
-        remaining: HashSet<u16>,
+        remaining: BTreeSet<u16>,
     },
     SendChannelJoinConfirm {
-        remaining: HashSet<u16>,
+        remaining: BTreeSet<u16>,
         channel_id: u16,
     },
     AllJoined,
@@ -103,8 +104,10 @@ impl Sequence for ChannelConnectionSequence {
                 let written =
                     ironrdp_core::encode_buf(&X224(attach_user_confirm), output).map_err(ConnectorError::encode)?;
 
-                let next_state = match self.channel_ids.take() {
-                    Some(channel_ids) => ChannelConnectionState::WaitChannelJoinRequest { remaining: channel_ids },
+                let next_state = match &self.channel_ids {
+                    Some(channel_ids) => ChannelConnectionState::WaitChannelJoinRequest {
+                        remaining: channel_ids.clone(),
+                    },
                     None => ChannelConnectionState::AllJoined,
                 };
 
@@ -112,29 +115,44 @@ impl Sequence for ChannelConnectionSequence {
             }
 
             ChannelConnectionState::WaitChannelJoinRequest { mut remaining } => {
-                let channel_request = ironrdp_core::decode::<X224<mcs::ChannelJoinRequest>>(input)
+                let message = ironrdp_core::decode::<X224<mcs::McsMessage<'_>>>(input)
                     .map_err(ConnectorError::decode)
                     .map(|p| p.0)?;
 
-                debug!(message = ?channel_request, "Received");
-
-                let is_expected = remaining.remove(&channel_request.channel_id);
-
-                if !is_expected {
+                let mcs::McsMessage::ChannelJoinRequest(channel_request) = message else {
                     return Err(reason_err!(
-                        "ChannelJoinConfirm",
-                        "unexpected channel_id in MCS Channel Join Request: got {}, expected one of: {:?}",
-                        channel_request.channel_id,
-                        remaining,
+                        "ChannelJoinRequest",
+                        "client did not join the channels {remaining:?}, and sent a {} instead",
+                        mcs::McsPdu::name(&message),
                     ));
+                };
+
+                debug!(message = ?channel_request, "Received");
+
+                let channel_id = channel_request.channel_id;
+
+                if !remaining.remove(&channel_id) {
+                    let is_announced = self
+                        .channel_ids
+                        .as_ref()
+                        .is_some_and(|channel_ids| channel_ids.contains(&channel_id));
+
+                    return Err(if is_announced {
+                        reason_err!(
+                            "ChannelJoinRequest",
+                            "channel {channel_id} is joined twice, remaining channels: {remaining:?}",
+                        )
+                    } else {
+                        reason_err!(
+                            "ChannelJoinRequest",
+                            "channel {channel_id} was never announced, expected one of: {remaining:?}",
+                        )
+                    });
                 }
 
                 (
                     Written::Nothing,
-                    ChannelConnectionState::SendChannelJoinConfirm {
-                        remaining,
-                        channel_id: channel_request.channel_id,
-                    },
+                    ChannelConnectionState::SendChannelJoinConfirm { remaining, channel_id },
                 )
             }
 
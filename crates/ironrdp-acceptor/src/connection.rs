@@ -1,8 +1,10 @@
 use core::mem;
+use std::net::SocketAddr;
+use std::sync::Arc;
 
 use ironrdp_connector::{
-    encode_x224_packet, reason_err, ConnectorError, ConnectorErrorExt, ConnectorResult, DesktopSize, Sequence, State,
-    Written,
+    encode_x224_packet, reason_err, ConnectorError, ConnectorErrorExt, ConnectorErrorKind, ConnectorResult,
+    DesktopSize, Sequence, State, Written,
 };
 use ironrdp_core::{decode, WriteBuf};
 use ironrdp_pdu as pdu;
@@ -10,30 +12,52 @@ use ironrdp_pdu::nego::SecurityProtocol;
 use ironrdp_pdu::x224::X224;
 use ironrdp_svc::{StaticChannelSet, SvcServerProcessor};
 use pdu::rdp::capability_sets::CapabilitySet;
-use pdu::rdp::client_info::Credentials;
+use pdu::rdp::client_info::{ClientInfo, ClientInfoFlags, CompressionType, Credentials, TimezoneInfo};
 use pdu::rdp::headers::ShareControlPdu;
 use pdu::rdp::server_error_info::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
-use pdu::rdp::server_license::{LicensePdu, LicensingErrorMessage};
+use pdu::rdp::server_license::LicensePdu;
 use pdu::{gcc, mcs, nego, rdp};
 
 use super::channel_connection::ChannelConnectionSequence;
 use super::finalization::FinalizationSequence;
 use crate::util::{self, wrap_share_data};
+use crate::{
+    AcceptorPolicy, AuthContext, AuthDecision, Authorizer, AutoValid, ClientTimeZone, LicensingStep,
+    ServerLicensingHandler, SessionMetadata,
+};
 
 const IO_CHANNEL_ID: u16 = 1003;
 const USER_CHANNEL_ID: u16 = 1002;
 
+/// Color depth reported when the server capabilities are missing the Bitmap capability set
+const DEFAULT_COLOR_DEPTH: u32 = 32;
+
 pub struct Acceptor {
     pub(crate) state: AcceptorState,
     security: SecurityProtocol,
     io_channel_id: u16,
     user_channel_id: u16,
     desktop_size: DesktopSize,
+    color_depth: u32,
     server_capabilities: Vec<CapabilitySet>,
     static_channels: StaticChannelSet,
+    static_channel_ids: Vec<(gcc::ChannelName, u16)>,
     saved_for_reactivation: AcceptorState,
     pub(crate) creds: Option<Credentials>,
     reactivation: bool,
+    policy: Option<Box<dyn AcceptorPolicy>>,
+    compression_type: Option<CompressionType>,
+    early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
+    keyboard: Option<KeyboardInfo>,
+    client_blocks: Option<gcc::ClientGccBlocks>,
+    environment: ClientEnvironment,
+    negotiated_channels: Vec<NegotiatedChannel>,
+    authorizer: Option<Arc<Authorizer>>,
+    licensing: Box<dyn ServerLicensingHandler>,
+    client_addr: Option<SocketAddr>,
+    /// User authenticated by CredSSP, as account name and domain.
+    pub(crate) credssp_user: Option<(String, Option<String>)>,
+    session_metadata: SessionMetadata,
 }
 
 #[derive(Debug)]
@@ -44,6 +68,109 @@ pub struct AcceptorResult {
     pub user_channel_id: u16,
     pub io_channel_id: u16,
     pub reactivation: bool,
+    /// Desktop size announced to the client.
+    pub desktop_size: DesktopSize,
+    /// Color depth, in bits per pixel, announced to the client.
+    pub color_depth: u32,
+    /// Static channels joined by the client, along with the definition it sent for them.
+    pub channels: Vec<(u16, gcc::ChannelDef)>,
+    /// IDs assigned to all the static channels requested by the client, including the ones without a processor,
+    /// in the order of its channel definitions.
+    pub static_channel_ids: Vec<(gcc::ChannelName, u16)>,
+    /// Highest bulk compression type supported by the client, if it supports compression.
+    pub compression_type: Option<CompressionType>,
+    /// Early capabilities advertised by the client in its core data, if any.
+    pub early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
+    /// Keyboard declared by the client in its core data.
+    pub keyboard: Option<KeyboardInfo>,
+    /// Time zone and language sent by the client in its Client Info PDU.
+    pub environment: ClientEnvironment,
+    /// Metadata attached to the session by the [`Authorizer`], empty when there is none.
+    pub session_metadata: SessionMetadata,
+    /// Settings of the session, as negotiated with the client.
+    pub negotiated: NegotiatedSession,
+}
+
+/// Settings of a session, as negotiated with the client during the connection sequence
+///
+/// The client data is kept as decoded, except for the credentials which are not exposed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedSession {
+    pub client_core: gcc::ClientCoreData,
+    pub client_cluster: Option<gcc::ClientClusterData>,
+    pub client_security: gcc::ClientSecurityData,
+    /// Time zone sent by the client in its Client Info PDU, if any.
+    pub client_time_zone: Option<TimezoneInfo>,
+    /// Capability sets confirmed by the client, after the [`AcceptorPolicy`] was applied.
+    ///
+    /// Capability sets unknown to IronRDP are kept as [`CapabilitySet::Unknown`].
+    pub client_capabilities: Vec<CapabilitySet>,
+    /// Capability sets sent to the client in the Demand Active PDU.
+    pub server_capabilities: Vec<CapabilitySet>,
+    /// Desktop size granted to the client.
+    pub desktop_size: DesktopSize,
+    /// Color depth, in bits per pixel, granted to the client.
+    pub color_depth: u32,
+    /// Static channels requested by the client, in the order of its channel definitions.
+    pub static_channels: Vec<NegotiatedChannel>,
+}
+
+/// Static channel requested by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedChannel {
+    pub name: gcc::ChannelName,
+    /// ID assigned to the channel, whether or not a processor is attached to it.
+    pub id: u16,
+    /// Options requested by the client for the channel.
+    pub options: gcc::ChannelOptions,
+}
+
+/// Keyboard declared by the client in its core data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardInfo {
+    /// Active input locale identifier, e.g.: 0x0409 for US English.
+    pub layout: u32,
+    pub keyboard_type: gcc::KeyboardType,
+    /// Original equipment manufacturer dependent value.
+    pub subtype: u32,
+    pub functional_keys_count: u32,
+    /// Input Method Editor file name, empty when there is none.
+    pub ime_file_name: String,
+}
+
+/// Time zone and language of the client, to set up the environment of the session
+///
+/// The active input locale is declared along with the keyboard, see [`KeyboardInfo::layout`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientEnvironment {
+    /// Time zone of the client, if it sent one.
+    pub time_zone: Option<ClientTimeZone>,
+    /// Language identifier of the active input locale, e.g.: 0x0409 for en-US.
+    ///
+    /// Not known when the client does not support Unicode, the `CodePage` field of the Client Info PDU then being
+    /// an ANSI code page.
+    pub language_id: Option<u16>,
+}
+
+impl ClientEnvironment {
+    pub fn from_client_info(client_info: &ClientInfo) -> Self {
+        let optional_data = &client_info.extra_info.optional_data;
+
+        Self {
+            time_zone: optional_data
+                .timezone()
+                .map(|info| ClientTimeZone::new(info.clone(), optional_data.dynamic_time_zone())),
+            language_id: client_info
+                .flags
+                .contains(ClientInfoFlags::UNICODE)
+                .then(|| {
+                    // Some clients send the whole input locale identifier, whose low word is the language identifier.
+                    let [low, high, _, _] = client_info.code_page.to_le_bytes();
+                    u16::from_le_bytes([low, high])
+                })
+                .filter(|&language_id| language_id != 0),
+        }
+    }
 }
 
 impl Acceptor {
@@ -53,20 +180,70 @@ impl Acceptor {
         capabilities: Vec<CapabilitySet>,
         creds: Option<Credentials>,
     ) -> Self {
+        let color_depth = capabilities
+            .iter()
+            .find_map(|cap| match cap {
+                CapabilitySet::Bitmap(cap) => Some(u32::from(cap.pref_bits_per_pix)),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_COLOR_DEPTH);
+
         Self {
             security,
             state: AcceptorState::InitiationWaitRequest,
             user_channel_id: USER_CHANNEL_ID,
             io_channel_id: IO_CHANNEL_ID,
             desktop_size,
+            color_depth,
             server_capabilities: capabilities,
             static_channels: StaticChannelSet::new(),
+            static_channel_ids: Vec::new(),
             saved_for_reactivation: Default::default(),
             creds,
             reactivation: false,
+            policy: None,
+            compression_type: None,
+            early_capability: None,
+            keyboard: None,
+            client_blocks: None,
+            environment: ClientEnvironment::default(),
+            negotiated_channels: Vec::new(),
+            authorizer: None,
+            licensing: Box::new(AutoValid),
+            client_addr: None,
+            credssp_user: None,
+            session_metadata: SessionMetadata::new(),
         }
     }
 
+    /// Constrains the settings proposed by the client with the given policy.
+    #[must_use]
+    pub fn with_policy(mut self, policy: impl AcceptorPolicy + 'static) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Lets the given authorizer refuse the authenticated users before the session is opened.
+    #[must_use]
+    pub fn with_authorizer(mut self, authorizer: Arc<Authorizer>) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+
+    /// Performs the licensing exchange with the given handler, instead of [`AutoValid`].
+    #[must_use]
+    pub fn with_licensing_handler(mut self, handler: impl ServerLicensingHandler + 'static) -> Self {
+        self.licensing = Box::new(handler);
+        self
+    }
+
+    /// Sets the address of the client, reported to the [`Authorizer`].
+    #[must_use]
+    pub fn with_client_addr(mut self, client_addr: SocketAddr) -> Self {
+        self.client_addr = Some(client_addr);
+        self
+    }
+
     pub fn new_deactivation_reactivation(
         mut consumed: Acceptor,
         static_channels: StaticChannelSet,
@@ -100,11 +277,25 @@ impl Acceptor {
             user_channel_id: consumed.user_channel_id,
             io_channel_id: consumed.io_channel_id,
             desktop_size,
+            color_depth: consumed.color_depth,
             server_capabilities: consumed.server_capabilities,
             static_channels,
+            static_channel_ids: consumed.static_channel_ids,
             saved_for_reactivation,
             creds: consumed.creds,
             reactivation: true,
+            policy: consumed.policy,
+            compression_type: consumed.compression_type,
+            early_capability: consumed.early_capability,
+            keyboard: consumed.keyboard,
+            client_blocks: consumed.client_blocks,
+            environment: consumed.environment,
+            negotiated_channels: consumed.negotiated_channels,
+            authorizer: consumed.authorizer,
+            licensing: consumed.licensing,
+            client_addr: consumed.client_addr,
+            credssp_user: consumed.credssp_user,
+            session_metadata: consumed.session_metadata,
         }
     }
 
@@ -139,20 +330,123 @@ impl Acceptor {
         assert_eq!(res, Written::Nothing);
     }
 
+    /// Asks the authorizer, if any, whether the user can open a session, and returns the reason of a denial.
+    pub(crate) fn authorize(
+        &mut self,
+        username: String,
+        domain: Option<String>,
+        protocol: SecurityProtocol,
+    ) -> Result<(), String> {
+        let Some(authorizer) = &self.authorizer else {
+            return Ok(());
+        };
+
+        let context = AuthContext {
+            username,
+            domain,
+            client_addr: self.client_addr,
+            protocol,
+        };
+
+        match authorizer(&context) {
+            AuthDecision::Allow(metadata) => {
+                debug!(?context, ?metadata, "User authorized");
+                self.session_metadata = metadata;
+                Ok(())
+            }
+            AuthDecision::Deny(reason) => {
+                info!(?context, reason, "User denied");
+                Err(reason)
+            }
+        }
+    }
+
+    /// Sends the licensing PDU of the handler, and returns the state following it.
+    fn send_licensing_step(
+        &mut self,
+        step: LicensingStep,
+        early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
+        channels: Vec<(u16, gcc::ChannelDef)>,
+        output: &mut WriteBuf,
+    ) -> ConnectorResult<(Written, AcceptorState)> {
+        let (license, next_state) = match step {
+            LicensingStep::Continue(license) => (
+                license,
+                AcceptorState::LicensingWaitClient {
+                    early_capability,
+                    channels,
+                },
+            ),
+            LicensingStep::Complete(license) => {
+                self.saved_for_reactivation = AcceptorState::CapabilitiesSendServer {
+                    early_capability,
+                    channels: channels.clone(),
+                };
+
+                (
+                    license,
+                    AcceptorState::CapabilitiesSendServer {
+                        early_capability,
+                        channels,
+                    },
+                )
+            }
+        };
+
+        debug!(message = ?license, "Send");
+
+        let written = util::encode_send_data_indication(self.user_channel_id, self.io_channel_id, &license, output)?;
+
+        Ok((Written::from_size(written)?, next_state))
+    }
+
     pub fn get_result(&mut self) -> Option<AcceptorResult> {
         match mem::take(&mut self.state) {
             AcceptorState::Accepted {
-                channels: _channels, // TODO: what about ChannelDef?
+                channels,
                 client_capabilities,
                 input_events,
-            } => Some(AcceptorResult {
-                static_channels: mem::take(&mut self.static_channels),
-                capabilities: client_capabilities,
-                input_events,
-                user_channel_id: self.user_channel_id,
-                io_channel_id: self.io_channel_id,
-                reactivation: self.reactivation,
-            }),
+            } => {
+                let client_blocks = self
+                    .client_blocks
+                    .clone()
+                    .expect("client GCC blocks are received before the session is accepted");
+
+                let negotiated = NegotiatedSession {
+                    client_core: client_blocks.core,
+                    client_cluster: client_blocks.cluster,
+                    client_security: client_blocks.security,
+                    client_time_zone: self
+                        .environment
+                        .time_zone
+                        .as_ref()
+                        .map(|time_zone| time_zone.info.clone()),
+                    client_capabilities: client_capabilities.clone(),
+                    server_capabilities: self.server_capabilities.clone(),
+                    desktop_size: self.desktop_size,
+                    color_depth: self.color_depth,
+                    static_channels: self.negotiated_channels.clone(),
+                };
+
+                Some(AcceptorResult {
+                    static_channels: mem::take(&mut self.static_channels),
+                    capabilities: client_capabilities,
+                    input_events,
+                    user_channel_id: self.user_channel_id,
+                    io_channel_id: self.io_channel_id,
+                    reactivation: self.reactivation,
+                    desktop_size: self.desktop_size,
+                    color_depth: self.color_depth,
+                    channels,
+                    static_channel_ids: self.static_channel_ids.clone(),
+                    compression_type: self.compression_type,
+                    early_capability: self.early_capability,
+                    keyboard: self.keyboard.clone(),
+                    environment: self.environment.clone(),
+                    session_metadata: self.session_metadata.clone(),
+                    negotiated,
+                })
+            }
             previous_state => {
                 self.state = previous_state;
                 None
@@ -208,6 +502,10 @@ pub enum AcceptorState {
         early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
         channels: Vec<(u16, gcc::ChannelDef)>,
     },
+    LicensingWaitClient {
+        early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
+        channels: Vec<(u16, gcc::ChannelDef)>,
+    },
     CapabilitiesSendServer {
         early_capability: Option<gcc::ClientEarlyCapabilityFlags>,
         channels: Vec<(u16, gcc::ChannelDef)>,
@@ -228,6 +526,10 @@ pub enum AcceptorState {
         client_capabilities: Vec<CapabilitySet>,
         input_events: Vec<Vec<u8>>,
     },
+    /// The connection was refused after notifying the client, the next step returning the error.
+    Denied {
+        error: ConnectorError,
+    },
 }
 
 impl State for AcceptorState {
@@ -244,11 +546,13 @@ impl State for AcceptorState {
             Self::RdpSecurityCommencement { .. } => "RdpSecurityCommencement",
             Self::SecureSettingsExchange { .. } => "SecureSettingsExchange",
             Self::LicensingExchange { .. } => "LicensingExchange",
+            Self::LicensingWaitClient { .. } => "LicensingWaitClient",
             Self::CapabilitiesSendServer { .. } => "CapabilitiesSendServer",
             Self::MonitorLayoutSend { .. } => "MonitorLayoutSend",
             Self::CapabilitiesWaitConfirm { .. } => "CapabilitiesWaitConfirm",
             Self::ConnectionFinalization { .. } => "ConnectionFinalization",
             Self::Accepted { .. } => "Connected",
+            Self::Denied { .. } => "Denied",
         }
     }
 
@@ -275,11 +579,13 @@ impl Sequence for Acceptor {
             AcceptorState::RdpSecurityCommencement { .. } => None,
             AcceptorState::SecureSettingsExchange { .. } => Some(&pdu::X224_HINT),
             AcceptorState::LicensingExchange { .. } => None,
+            AcceptorState::LicensingWaitClient { .. } => Some(&pdu::X224_HINT),
             AcceptorState::CapabilitiesSendServer { .. } => None,
             AcceptorState::MonitorLayoutSend { .. } => None,
             AcceptorState::CapabilitiesWaitConfirm { .. } => Some(&pdu::X224_HINT),
             AcceptorState::ConnectionFinalization { finalization, .. } => finalization.next_pdu_hint(),
             AcceptorState::Accepted { .. } => None,
+            AcceptorState::Denied { .. } => None,
         }
     }
 
@@ -380,42 +686,124 @@ impl Sequence for Acceptor {
 
                 debug!(message = ?settings_initial, "Received");
 
+                if let Some(policy) = self.policy.as_deref() {
+                    let core = &settings_initial.conference_create_request.gcc_blocks.core;
+
+                    let requested_size = DesktopSize {
+                        width: core.desktop_width,
+                        height: core.desktop_height,
+                    };
+                    let desktop_size = policy.adjust_desktop_size(requested_size);
+                    if desktop_size.width == 0 || desktop_size.height == 0 {
+                        return Err(reason_err!(
+                            "BasicSettingsExchange",
+                            "desktop size {}x{} refused by policy",
+                            requested_size.width,
+                            requested_size.height,
+                        ));
+                    }
+
+                    let requested_color_depth = match core.client_color_depth() {
+                        gcc::ClientColorDepth::Bpp4 => 4,
+                        gcc::ClientColorDepth::Bpp8 => 8,
+                        gcc::ClientColorDepth::Rgb555Bpp16 => 15,
+                        gcc::ClientColorDepth::Rgb565Bpp16 => 16,
+                        gcc::ClientColorDepth::Bpp24 => 24,
+                        gcc::ClientColorDepth::Bpp32 => 32,
+                    };
+                    let color_depth = policy.color_depth(requested_color_depth);
+                    let pref_bits_per_pix = match color_depth {
+                        8 | 15 | 16 | 24 | 32 => u16::try_from(color_depth).expect("color depth fits in u16"),
+                        _ => {
+                            return Err(reason_err!(
+                                "BasicSettingsExchange",
+                                "color depth {requested_color_depth} refused by policy",
+                            ))
+                        }
+                    };
+
+                    debug!(
+                        ?requested_size,
+                        ?desktop_size,
+                        requested_color_depth,
+                        color_depth,
+                        "Applied policy"
+                    );
+
+                    for cap in self.server_capabilities.iter_mut() {
+                        if let CapabilitySet::Bitmap(cap) = cap {
+                            cap.desktop_width = desktop_size.width;
+                            cap.desktop_height = desktop_size.height;
+                            cap.pref_bits_per_pix = pref_bits_per_pix;
+                        }
+                    }
+                    self.desktop_size = desktop_size;
+                    self.color_depth = color_depth;
+                }
+
                 let early_capability = settings_initial
                     .conference_create_request
                     .gcc_blocks
                     .core
                     .optional_data
                     .early_capability_flags;
+                self.early_capability = early_capability;
+
+                let core = &settings_initial.conference_create_request.gcc_blocks.core;
+                self.keyboard = Some(KeyboardInfo {
+                    layout: core.keyboard_layout,
+                    keyboard_type: core.keyboard_type,
+                    subtype: core.keyboard_subtype,
+                    functional_keys_count: core.keyboard_functional_keys_count,
+                    ime_file_name: core.ime_file_name.clone(),
+                });
 
-                let joined: Vec<_> = settings_initial
+                self.client_blocks = Some(settings_initial.conference_create_request.gcc_blocks.clone());
+
+                let requested = settings_initial
                     .conference_create_request
                     .gcc_blocks
                     .network
-                    .map(|network| {
-                        network
-                            .channels
-                            .into_iter()
-                            .map(|c| {
-                                self.static_channels
-                                    .get_by_channel_name(&c.name)
-                                    .map(|(type_id, _)| (type_id, c))
-                            })
-                            .collect()
-                    })
+                    .map(|network| network.channels)
                     .unwrap_or_default();
 
+                // The channel IDs are assigned in the order of the channel definitions sent by the client, the
+                // channels without a processor being announced as well.
                 #[allow(clippy::arithmetic_side_effects)] // IO channel ID is not big enough for overflowing.
-                let channels = joined
+                let channels: Vec<_> = requested
                     .into_iter()
                     .enumerate()
-                    .map(|(i, channel)| {
+                    .map(|(i, c)| {
                         let channel_id = u16::try_from(i).unwrap() + self.io_channel_id + 1;
-                        if let Some((type_id, c)) = channel {
+                        let type_id = self
+                            .static_channels
+                            .get_by_channel_name(&c.name)
+                            .map(|(type_id, _)| type_id);
+                        (channel_id, type_id, c)
+                    })
+                    .collect();
+
+                self.static_channel_ids = channels
+                    .iter()
+                    .map(|(channel_id, _, c)| (c.name.clone(), *channel_id))
+                    .collect();
+                self.negotiated_channels = channels
+                    .iter()
+                    .map(|(channel_id, _, c)| NegotiatedChannel {
+                        name: c.name.clone(),
+                        id: *channel_id,
+                        options: c.options,
+                    })
+                    .collect();
+
+                let channels = channels
+                    .into_iter()
+                    .map(|(channel_id, type_id, c)| match type_id {
+                        Some(type_id) => {
                             self.static_channels.attach_channel_id(type_id, channel_id);
                             (channel_id, Some(c))
-                        } else {
-                            (channel_id, None)
                         }
+                        None => (channel_id, None),
                     })
                     .collect();
 
@@ -528,23 +916,49 @@ impl Sequence for Acceptor {
 
                 debug!(message = ?client_info, "Received");
 
-                if !protocol.intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX) {
+                self.compression_type = client_info
+                    .client_info
+                    .flags
+                    .contains(ClientInfoFlags::COMPRESSION)
+                    .then_some(client_info.client_info.compression_type);
+                self.environment = ClientEnvironment::from_client_info(&client_info.client_info);
+
+                let denied = if !protocol.intersects(SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX) {
                     let creds = client_info.client_info.credentials;
 
                     if self.creds.as_ref().map_or(true, |srv_creds| srv_creds != &creds) {
-                        // FIXME: How authorization should be denied with standard RDP security?
-                        // Since standard RDP security is not a priority, we just send a ServerDeniedConnection ServerSetErrorInfo PDU.
-                        let info = ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
-                            ProtocolIndependentCode::ServerDeniedConnection,
-                        ));
+                        Some(ConnectorError::general("invalid credentials"))
+                    } else {
+                        self.authorize(creds.username, creds.domain, protocol)
+                            .err()
+                            .map(|_| ConnectorError::new("authorization", ConnectorErrorKind::AccessDenied))
+                    }
+                } else if let Some((username, domain)) = self.credssp_user.take() {
+                    // The user authenticated by CredSSP is authorized here when HYBRID_EX is not negotiated.
+                    self.authorize(username, domain, protocol)
+                        .err()
+                        .map(|_| ConnectorError::new("authorization", ConnectorErrorKind::AccessDenied))
+                } else {
+                    None
+                };
 
-                        debug!(message = ?info, "Send");
+                if let Some(error) = denied {
+                    // FIXME: How authorization should be denied with standard RDP security?
+                    // Since standard RDP security is not a priority, we just send a ServerDeniedConnection ServerSetErrorInfo PDU.
+                    let info = ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
+                        ProtocolIndependentCode::ServerDeniedConnection,
+                    ));
 
+                    debug!(message = ?info, "Send");
+
+                    let written =
                         util::encode_send_data_indication(self.user_channel_id, self.io_channel_id, &info, output)?;
 
-                        return Err(ConnectorError::general("invalid credentials"));
-                    }
+                    self.state = AcceptorState::Denied { error };
+
+                    return Written::from_size(written);
                 }
+
                 (
                     Written::Nothing,
                     AcceptorState::LicensingExchange {
@@ -558,27 +972,40 @@ impl Sequence for Acceptor {
                 early_capability,
                 channels,
             } => {
-                let license: LicensePdu = LicensingErrorMessage::new_valid_client()
-                    .map_err(ConnectorError::encode)?
-                    .into();
+                let step = self.licensing.start()?;
+                self.send_licensing_step(step, early_capability, channels, output)?
+            }
 
-                debug!(message = ?license, "Send");
+            AcceptorState::LicensingWaitClient {
+                early_capability,
+                channels,
+            } => {
+                let data: X224<mcs::SendDataRequest<'_>> = decode(input).map_err(ConnectorError::decode)?;
+                let license: LicensePdu = decode(data.0.user_data.as_ref()).map_err(ConnectorError::decode)?;
 
-                let written =
-                    util::encode_send_data_indication(self.user_channel_id, self.io_channel_id, &license, output)?;
+                debug!(message = ?license, "Received");
 
-                self.saved_for_reactivation = AcceptorState::CapabilitiesSendServer {
-                    early_capability,
-                    channels: channels.clone(),
+                let step = match license {
+                    LicensePdu::ClientNewLicenseRequest(request) => self.licensing.on_new_license_request(request)?,
+                    LicensePdu::ClientLicenseInfo(info) => self.licensing.on_license_info(info)?,
+                    LicensePdu::ClientPlatformChallengeResponse(response) => {
+                        self.licensing.on_platform_challenge_response(response)?
+                    }
+                    LicensePdu::LicensingErrorMessage(message) => {
+                        return Err(reason_err!(
+                            "LicensingExchange",
+                            "client reported a licensing error: {:?}",
+                            message.error_code
+                        ));
+                    }
+                    _ => {
+                        return Err(ConnectorError::general(
+                            "unexpected PDU received during the licensing exchange",
+                        ))
+                    }
                 };
 
-                (
-                    Written::from_size(written)?,
-                    AcceptorState::CapabilitiesSendServer {
-                        early_capability,
-                        channels,
-                    },
-                )
+                self.send_licensing_step(step, early_capability, channels, output)?
             }
 
             AcceptorState::CapabilitiesSendServer {
@@ -680,12 +1107,21 @@ impl Sequence for Acceptor {
                             return Err(ConnectorError::general("expected client confirm active"));
                         };
 
+                        let mut client_capabilities = confirm.pdu.capability_sets;
+                        if let Some(policy) = self.policy.as_deref() {
+                            for cap in client_capabilities.iter_mut() {
+                                if let CapabilitySet::BitmapCodecs(codecs) = cap {
+                                    *codecs = policy.allowed_codecs(codecs);
+                                }
+                            }
+                        }
+
                         (
                             Written::Nothing,
                             AcceptorState::ConnectionFinalization {
                                 channels: channels.clone(),
                                 finalization: FinalizationSequence::new(self.user_channel_id, self.io_channel_id),
-                                client_capabilities: confirm.pdu.capability_sets,
+                                client_capabilities,
                             },
                         )
                     }
@@ -726,6 +1162,8 @@ impl Sequence for Acceptor {
                 (written, state)
             }
 
+            AcceptorState::Denied { error } => return Err(error),
+
             _ => unreachable!(),
         };
 
@@ -756,5 +1194,6 @@ fn create_gcc_blocks(
         },
         message_channel: None,
         multi_transport_channel: None,
+        unknown: Vec::new(),
     }
 }
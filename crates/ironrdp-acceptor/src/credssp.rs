@@ -13,7 +13,8 @@ use ironrdp_pdu::PduHint;
 #[derive(Debug)]
 pub(crate) enum CredsspState {
     Ongoing,
-    Finished,
+    /// Holds the user authenticated by the client credentials.
+    Finished(Username),
     ServerError(sspi::Error),
 }
 
@@ -69,7 +70,7 @@ impl<'a> CredsspSequence<'a> {
     pub(crate) fn next_pdu_hint(&self) -> ConnectorResult<Option<&dyn PduHint>> {
         match &self.state {
             CredsspState::Ongoing => Ok(Some(&CREDSSP_TS_REQUEST_HINT)),
-            CredsspState::Finished => Ok(None),
+            CredsspState::Finished(_) => Ok(None),
             CredsspState::ServerError(err) => Err(custom_err!("Credssp server error", err.clone())),
         }
     }
@@ -109,6 +110,14 @@ impl<'a> CredsspSequence<'a> {
         Ok(sequence)
     }
 
+    /// Returns the authenticated user, once the sequence is finished.
+    pub(crate) fn authenticated_user(&self) -> Option<&Username> {
+        match &self.state {
+            CredsspState::Finished(username) => Some(username),
+            _ => None,
+        }
+    }
+
     /// Returns Some(ts_request) when a TS request is received from client,
     pub(crate) fn decode_client_message(&mut self, input: &[u8]) -> ConnectorResult<Option<TsRequest>> {
         match self.state {
@@ -134,7 +143,7 @@ impl<'a> CredsspSequence<'a> {
     ) -> ConnectorResult<Written> {
         let (ts_request, next_state) = match result {
             Ok(ServerState::ReplyNeeded(ts_request)) => (Some(ts_request), CredsspState::Ongoing),
-            Ok(ServerState::Finished(_id)) => (None, CredsspState::Finished),
+            Ok(ServerState::Finished(identity)) => (None, CredsspState::Finished(identity.username)),
             Err(err) => (Some(err.ts_request), CredsspState::ServerError(err.error)),
         };
 
@@ -4,25 +4,37 @@
 #[macro_use]
 extern crate tracing;
 
-use ironrdp_async::{single_sequence_step, Framed, FramedRead, FramedWrite, StreamWrapper};
+use ironrdp_async::{single_sequence_step, Framed, FramedRead, FramedWrite, StreamWrapper, TraceDirection, TraceEvent};
 use ironrdp_connector::credssp::KerberosConfig;
 use ironrdp_connector::sspi::credssp::EarlyUserAuthResult;
 use ironrdp_connector::sspi::{AuthIdentity, Username};
-use ironrdp_connector::{custom_err, general_err, ConnectorResult, ServerName};
+use ironrdp_connector::{
+    custom_err, general_err, ConnectorError, ConnectorErrorKind, ConnectorResult, ServerName, State as _,
+};
 use ironrdp_core::WriteBuf;
 
+mod authorization;
 mod channel_connection;
 mod connection;
 mod credssp;
 mod finalization;
+mod licensing;
+mod policy;
+mod time_zone;
 mod util;
 
 pub use ironrdp_connector::DesktopSize;
 use ironrdp_pdu::nego;
 
+pub use self::authorization::{AuthContext, AuthDecision, Authorizer, SessionMetadata};
 pub use self::channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
-pub use self::connection::{Acceptor, AcceptorResult, AcceptorState};
+pub use self::connection::{
+    Acceptor, AcceptorResult, AcceptorState, ClientEnvironment, KeyboardInfo, NegotiatedChannel, NegotiatedSession,
+};
 pub use self::finalization::{FinalizationSequence, FinalizationState};
+pub use self::licensing::{AutoValid, LicensingStep, ServerLicensingHandler};
+pub use self::policy::AcceptorPolicy;
+pub use self::time_zone::{windows_to_iana, ClientTimeZone, IanaTimeZone};
 
 pub enum BeginResult<S>
 where
@@ -121,7 +133,7 @@ where
         client_computer_name: ServerName,
         public_key: Vec<u8>,
         kerberos_config: Option<KerberosConfig>,
-    ) -> ConnectorResult<()>
+    ) -> ConnectorResult<Username>
     where
         S: FramedRead + FramedWrite,
     {
@@ -156,6 +168,13 @@ where
 
             trace!(length = pdu.len(), "PDU received");
 
+            framed.trace_pdu(TraceEvent {
+                direction: TraceDirection::Received,
+                state: acceptor.state.name(),
+                hint: Some(next_pdu_hint),
+                bytes: &pdu,
+            });
+
             let Some(ts_request) = sequence.decode_client_message(&pdu)? else {
                 break;
             };
@@ -167,16 +186,41 @@ where
             if let Some(response_len) = written.size() {
                 let response = &buf[..response_len];
                 trace!(response_len, "Send response");
+                framed.trace_pdu(TraceEvent {
+                    direction: TraceDirection::Sent,
+                    state: acceptor.state.name(),
+                    hint: None,
+                    bytes: response,
+                });
                 framed
                     .write_all(response)
                     .await
                     .map_err(|e| ironrdp_connector::custom_err!("write all", e))?;
             }
         }
-        Ok(())
+
+        sequence
+            .authenticated_user()
+            .cloned()
+            .ok_or_else(|| general_err!("CredSSP finished without an authenticated user"))
     }
 
-    let result = credssp_loop(framed, acceptor, buf, client_computer_name, public_key, kerberos_config).await;
+    let result = credssp_loop(framed, acceptor, buf, client_computer_name, public_key, kerberos_config)
+        .await
+        .and_then(|user| {
+            let user = (user.account_name().to_owned(), user.domain_name().map(str::to_owned));
+
+            // With HYBRID_EX, the authorization is part of the result reported to the client. Otherwise, it is
+            // deferred until the client can be notified with a Set Error Info PDU.
+            if protocol.intersects(nego::SecurityProtocol::HYBRID_EX) {
+                acceptor
+                    .authorize(user.0, user.1, protocol)
+                    .map_err(|_| ConnectorError::new("CredSSP", ConnectorErrorKind::AccessDenied))
+            } else {
+                acceptor.credssp_user = Some(user);
+                Ok(())
+            }
+        });
 
     if protocol.intersects(nego::SecurityProtocol::HYBRID_EX) {
         trace!(?result, "HYBRID_EX");
@@ -192,6 +236,12 @@ where
             .to_buffer(&mut *buf)
             .map_err(|e| ironrdp_connector::custom_err!("to_buffer", e))?;
         let response = &buf[..result.buffer_len()];
+        framed.trace_pdu(TraceEvent {
+            direction: TraceDirection::Sent,
+            state: acceptor.state.name(),
+            hint: None,
+            bytes: response,
+        });
         framed
             .write_all(response)
             .await
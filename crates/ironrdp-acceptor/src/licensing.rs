@@ -0,0 +1,77 @@
+use ironrdp_connector::{reason_err, ConnectorError, ConnectorErrorExt as _, ConnectorResult};
+use ironrdp_pdu::rdp::server_license::{
+    ClientLicenseInfo, ClientNewLicenseRequest, ClientPlatformChallengeResponse, LicensePdu, LicensingErrorMessage,
+};
+
+/// Server side of the licensing exchange
+///
+/// The handler provides the licensing PDUs sent to the client, from the first one, sent once the Client Info PDU is
+/// received, to the one ending the exchange:
+///
+/// - a Server License Request, holding the server certificate, to which the client answers with a Client New License
+///   Request or a Client License Info;
+/// - a Server Platform Challenge, to which the client answers with a Client Platform Challenge Response;
+/// - a Server New License or Server Upgrade License, issuing a license to the client;
+/// - a Licensing Error Message, usually with STATUS_VALID_CLIENT.
+///
+/// The handler is responsible for the cryptography of the exchange, decrypting the premaster secret sent by the
+/// client with the private key of its certificate, and deriving the licensing keys with
+/// [`LicenseEncryptionData::new`](ironrdp_pdu::rdp::server_license::LicenseEncryptionData::new).
+///
+/// Every method handling a client PDU defaults to refusing it.
+///
+/// The licensing exchange is not performed again on deactivation-reactivation.
+pub trait ServerLicensingHandler: Send {
+    /// Returns the first licensing PDU sent to the client.
+    fn start(&mut self) -> ConnectorResult<LicensingStep>;
+
+    /// Handles the Client New License Request, sent by a client without a license for this server.
+    fn on_new_license_request(&mut self, request: ClientNewLicenseRequest) -> ConnectorResult<LicensingStep> {
+        let _ = request;
+        Err(reason_err!(
+            "LicensingExchange",
+            "unexpected client new license request"
+        ))
+    }
+
+    /// Handles the Client License Info, sent by a client presenting a license previously issued to it.
+    fn on_license_info(&mut self, info: ClientLicenseInfo) -> ConnectorResult<LicensingStep> {
+        let _ = info;
+        Err(reason_err!("LicensingExchange", "unexpected client license info"))
+    }
+
+    /// Handles the Client Platform Challenge Response.
+    fn on_platform_challenge_response(
+        &mut self,
+        response: ClientPlatformChallengeResponse,
+    ) -> ConnectorResult<LicensingStep> {
+        let _ = response;
+        Err(reason_err!(
+            "LicensingExchange",
+            "unexpected client platform challenge response"
+        ))
+    }
+}
+
+/// Licensing PDU sent to the client by the [`ServerLicensingHandler`]
+#[derive(Debug)]
+pub enum LicensingStep {
+    /// The PDU is sent, and the licensing exchange continues with the next PDU of the client.
+    Continue(LicensePdu),
+    /// The PDU is sent, and the licensing exchange is over.
+    Complete(LicensePdu),
+}
+
+/// Default [`ServerLicensingHandler`], which does not issue licenses
+///
+/// The client is told right away that it does not need a license, with a Licensing Error Message holding
+/// STATUS_VALID_CLIENT.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoValid;
+
+impl ServerLicensingHandler for AutoValid {
+    fn start(&mut self) -> ConnectorResult<LicensingStep> {
+        let valid_client = LicensingErrorMessage::new_valid_client().map_err(ConnectorError::encode)?;
+        Ok(LicensingStep::Complete(valid_client.into()))
+    }
+}
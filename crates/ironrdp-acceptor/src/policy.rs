@@ -0,0 +1,34 @@
+use ironrdp_connector::DesktopSize;
+use ironrdp_pdu::rdp::capability_sets::BitmapCodecs;
+
+/// Constraints applied by the server on the settings proposed by the client
+///
+/// The policy is consulted during the connection sequence:
+///
+/// - the desktop size and color depth requested in the client core data are adjusted before the server capability
+///   sets are sent, so the Bitmap capability set announces the values returned by the policy;
+/// - the codecs advertised in the client confirm active PDU are filtered once received.
+///
+/// The agreed values are reported in [`AcceptorResult`](crate::AcceptorResult).
+///
+/// Every method defaults to accepting the proposed value as is.
+pub trait AcceptorPolicy: Send {
+    /// Returns the desktop size to use for the session.
+    ///
+    /// Returning a size with a zero width or height refuses the connection.
+    fn adjust_desktop_size(&self, requested: DesktopSize) -> DesktopSize {
+        requested
+    }
+
+    /// Returns the codecs the server is allowed to use among the ones supported by the client.
+    fn allowed_codecs(&self, client: &BitmapCodecs) -> BitmapCodecs {
+        client.clone()
+    }
+
+    /// Returns the color depth, in bits per pixel, to use for the session.
+    ///
+    /// Returning a value other than 8, 15, 16, 24 or 32 (e.g.: 0) refuses the connection.
+    fn color_depth(&self, requested: u32) -> u32 {
+        requested
+    }
+}
@@ -0,0 +1,211 @@
+use ironrdp_pdu::rdp::client_info::{DynamicTimeZone, TimezoneInfo};
+
+/// Time zone sent by the client in its Client Info PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientTimeZone {
+    /// Bias, names and transition dates of the time zone.
+    pub info: TimezoneInfo,
+    /// Name of the time zone in the Windows registry, e.g.: "Pacific Standard Time", when sent by the client.
+    ///
+    /// Unlike the names of the [`TimezoneInfo`], it is not localized.
+    pub key_name: Option<String>,
+    /// Whether the user disabled the automatic adjustment for the daylight saving time.
+    pub dynamic_daylight_time_disabled: bool,
+}
+
+/// Time zone of the client, as an IANA time zone when it is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IanaTimeZone<'a> {
+    /// Name of the IANA time zone, e.g.: "America/Los_Angeles".
+    Name(&'static str),
+    /// The time zone is not known, its bias and transition dates are to be used as is.
+    Unmapped(&'a TimezoneInfo),
+}
+
+impl ClientTimeZone {
+    pub fn new(info: TimezoneInfo, dynamic_time_zone: Option<&DynamicTimeZone>) -> Self {
+        Self {
+            info,
+            key_name: dynamic_time_zone
+                .map(|dynamic_time_zone| dynamic_time_zone.key_name.clone())
+                .filter(|key_name| !key_name.is_empty()),
+            dynamic_daylight_time_disabled: dynamic_time_zone
+                .is_some_and(|dynamic_time_zone| dynamic_time_zone.dynamic_daylight_time_disabled),
+        }
+    }
+
+    /// Converts the time zone to an IANA time zone, on a best-effort basis.
+    ///
+    /// The time zone is looked up by its registry key name, then by its standard name, which is only the same as the
+    /// key name for English clients. A time zone without daylight saving time is otherwise converted to the
+    /// `Etc/GMT` time zone of the same offset, if any.
+    pub fn to_iana(&self) -> IanaTimeZone<'_> {
+        self.key_name
+            .as_deref()
+            .and_then(windows_to_iana)
+            .or_else(|| windows_to_iana(&self.info.standard_name))
+            .or_else(|| fixed_offset_to_iana(&self.info))
+            .map_or(IanaTimeZone::Unmapped(&self.info), IanaTimeZone::Name)
+    }
+}
+
+/// Returns the IANA time zone matching the name of a Windows time zone, e.g.: "America/Los_Angeles" for
+/// "Pacific Standard Time".
+///
+/// Only the common time zones are known, mapped to the main location of the zone.
+pub fn windows_to_iana(windows_name: &str) -> Option<&'static str> {
+    WINDOWS_TIME_ZONES
+        .iter()
+        .find(|(name, _)| *name == windows_name)
+        .map(|(_, iana_name)| *iana_name)
+}
+
+fn fixed_offset_to_iana(info: &TimezoneInfo) -> Option<&'static str> {
+    if info.observes_daylight_time() {
+        return None;
+    }
+
+    let offset = info.standard_offset_minutes();
+    if offset % 60 != 0 {
+        return None;
+    }
+
+    let index = usize::try_from(offset / 60 + 12).ok()?;
+    ETC_GMT_TIME_ZONES.get(index).copied()
+}
+
+/// `Etc/GMT` time zones from UTC-12 to UTC+14, whose signs are inverted.
+const ETC_GMT_TIME_ZONES: [&str; 27] = [
+    "Etc/GMT+12",
+    "Etc/GMT+11",
+    "Etc/GMT+10",
+    "Etc/GMT+9",
+    "Etc/GMT+8",
+    "Etc/GMT+7",
+    "Etc/GMT+6",
+    "Etc/GMT+5",
+    "Etc/GMT+4",
+    "Etc/GMT+3",
+    "Etc/GMT+2",
+    "Etc/GMT+1",
+    "Etc/UTC",
+    "Etc/GMT-1",
+    "Etc/GMT-2",
+    "Etc/GMT-3",
+    "Etc/GMT-4",
+    "Etc/GMT-5",
+    "Etc/GMT-6",
+    "Etc/GMT-7",
+    "Etc/GMT-8",
+    "Etc/GMT-9",
+    "Etc/GMT-10",
+    "Etc/GMT-11",
+    "Etc/GMT-12",
+    "Etc/GMT-13",
+    "Etc/GMT-14",
+];
+
+/// Windows time zone names, and the IANA time zone of their main location.
+const WINDOWS_TIME_ZONES: &[(&str, &str)] = &[
+    ("Dateline Standard Time", "Etc/GMT+12"),
+    ("UTC-11", "Etc/GMT+11"),
+    ("Hawaiian Standard Time", "Pacific/Honolulu"),
+    ("Alaskan Standard Time", "America/Anchorage"),
+    ("Pacific Standard Time (Mexico)", "America/Tijuana"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("US Mountain Standard Time", "America/Phoenix"),
+    ("Mountain Standard Time (Mexico)", "America/Mazatlan"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Central America Standard Time", "America/Guatemala"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Central Standard Time (Mexico)", "America/Mexico_City"),
+    ("Canada Central Standard Time", "America/Regina"),
+    ("SA Pacific Standard Time", "America/Bogota"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("US Eastern Standard Time", "America/Indiana/Indianapolis"),
+    ("Venezuela Standard Time", "America/Caracas"),
+    ("Paraguay Standard Time", "America/Asuncion"),
+    ("Atlantic Standard Time", "America/Halifax"),
+    ("Central Brazilian Standard Time", "America/Cuiaba"),
+    ("SA Western Standard Time", "America/La_Paz"),
+    ("Pacific SA Standard Time", "America/Santiago"),
+    ("Newfoundland Standard Time", "America/St_Johns"),
+    ("E. South America Standard Time", "America/Sao_Paulo"),
+    ("Argentina Standard Time", "America/Argentina/Buenos_Aires"),
+    ("SA Eastern Standard Time", "America/Cayenne"),
+    ("Greenland Standard Time", "America/Nuuk"),
+    ("Montevideo Standard Time", "America/Montevideo"),
+    ("UTC-02", "Etc/GMT+2"),
+    ("Azores Standard Time", "Atlantic/Azores"),
+    ("Cape Verde Standard Time", "Atlantic/Cape_Verde"),
+    ("UTC", "Etc/UTC"),
+    ("Coordinated Universal Time", "Etc/UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("Greenwich Standard Time", "Atlantic/Reykjavik"),
+    ("Morocco Standard Time", "Africa/Casablanca"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("W. Central Africa Standard Time", "Africa/Lagos"),
+    ("Jordan Standard Time", "Asia/Amman"),
+    ("GTB Standard Time", "Europe/Bucharest"),
+    ("Middle East Standard Time", "Asia/Beirut"),
+    ("Egypt Standard Time", "Africa/Cairo"),
+    ("Syria Standard Time", "Asia/Damascus"),
+    ("South Africa Standard Time", "Africa/Johannesburg"),
+    ("FLE Standard Time", "Europe/Kyiv"),
+    ("Israel Standard Time", "Asia/Jerusalem"),
+    ("Kaliningrad Standard Time", "Europe/Kaliningrad"),
+    ("Libya Standard Time", "Africa/Tripoli"),
+    ("Namibia Standard Time", "Africa/Windhoek"),
+    ("E. Europe Standard Time", "Europe/Chisinau"),
+    ("Arabic Standard Time", "Asia/Baghdad"),
+    ("Turkey Standard Time", "Europe/Istanbul"),
+    ("Arab Standard Time", "Asia/Riyadh"),
+    ("Belarus Standard Time", "Europe/Minsk"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("E. Africa Standard Time", "Africa/Nairobi"),
+    ("Iran Standard Time", "Asia/Tehran"),
+    ("Arabian Standard Time", "Asia/Dubai"),
+    ("Azerbaijan Standard Time", "Asia/Baku"),
+    ("Mauritius Standard Time", "Indian/Mauritius"),
+    ("Georgian Standard Time", "Asia/Tbilisi"),
+    ("Caucasus Standard Time", "Asia/Yerevan"),
+    ("Afghanistan Standard Time", "Asia/Kabul"),
+    ("West Asia Standard Time", "Asia/Tashkent"),
+    ("Ekaterinburg Standard Time", "Asia/Yekaterinburg"),
+    ("Pakistan Standard Time", "Asia/Karachi"),
+    ("India Standard Time", "Asia/Kolkata"),
+    ("Sri Lanka Standard Time", "Asia/Colombo"),
+    ("Nepal Standard Time", "Asia/Kathmandu"),
+    ("Central Asia Standard Time", "Asia/Almaty"),
+    ("Bangladesh Standard Time", "Asia/Dhaka"),
+    ("Myanmar Standard Time", "Asia/Yangon"),
+    ("SE Asia Standard Time", "Asia/Bangkok"),
+    ("N. Central Asia Standard Time", "Asia/Novosibirsk"),
+    ("North Asia Standard Time", "Asia/Krasnoyarsk"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("North Asia East Standard Time", "Asia/Irkutsk"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("W. Australia Standard Time", "Australia/Perth"),
+    ("Taipei Standard Time", "Asia/Taipei"),
+    ("Ulaanbaatar Standard Time", "Asia/Ulaanbaatar"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("Yakutsk Standard Time", "Asia/Yakutsk"),
+    ("Cen. Australia Standard Time", "Australia/Adelaide"),
+    ("AUS Central Standard Time", "Australia/Darwin"),
+    ("E. Australia Standard Time", "Australia/Brisbane"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+    ("West Pacific Standard Time", "Pacific/Port_Moresby"),
+    ("Tasmania Standard Time", "Australia/Hobart"),
+    ("Vladivostok Standard Time", "Asia/Vladivostok"),
+    ("Magadan Standard Time", "Asia/Magadan"),
+    ("UTC+12", "Etc/GMT-12"),
+    ("New Zealand Standard Time", "Pacific/Auckland"),
+    ("Fiji Standard Time", "Pacific/Fiji"),
+    ("Tonga Standard Time", "Pacific/Tongatapu"),
+    ("Samoa Standard Time", "Pacific/Apia"),
+    ("Line Islands Standard Time", "Pacific/Kiritimati"),
+];
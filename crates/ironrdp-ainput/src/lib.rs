@@ -6,6 +6,7 @@ use ironrdp_core::{
     ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
 use ironrdp_dvc::DvcEncode;
+use ironrdp_pdu_derive::{PduDecode, PduEncode};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive as _, ToPrimitive as _};
 // Advanced Input channel as defined from Freerdp, [here]:
@@ -35,17 +36,14 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode, PduDecode)]
+#[pdu(name = "AInputVersionPdu")]
 pub struct VersionPdu {
     major_version: u32,
     minor_version: u32,
 }
 
 impl VersionPdu {
-    const NAME: &'static str = "AInputVersionPdu";
-
-    const FIXED_PART_SIZE: usize = 4 /* MajorVersion */ + 4 /* MinorVersion */;
-
     pub fn new() -> Self {
         Self {
             major_version: VERSION_MAJOR,
@@ -60,39 +58,6 @@ impl Default for VersionPdu {
     }
 }
 
-impl Encode for VersionPdu {
-    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
-        ensure_fixed_part_size!(in: dst);
-
-        dst.write_u32(self.major_version);
-        dst.write_u32(self.minor_version);
-
-        Ok(())
-    }
-
-    fn name(&self) -> &'static str {
-        Self::NAME
-    }
-
-    fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE
-    }
-}
-
-impl<'de> Decode<'de> for VersionPdu {
-    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
-        ensure_fixed_part_size!(in: src);
-
-        let major_version = src.read_u32();
-        let minor_version = src.read_u32();
-
-        Ok(Self {
-            major_version,
-            minor_version,
-        })
-    }
-}
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum ServerPduType {
     Version = 0x01,
@@ -1,29 +1,45 @@
+use core::mem;
+
 use ironrdp_connector::credssp::{CredsspProcessGenerator, CredsspSequence, KerberosConfig};
+use ironrdp_connector::redirection::ServerRedirection;
 use ironrdp_connector::sspi::credssp::ClientState;
 use ironrdp_connector::sspi::generator::GeneratorState;
 use ironrdp_connector::{
-    custom_err, general_err, ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorResult,
-    ServerName, State as _,
+    general_err, ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorErrorKind,
+    ConnectorResult, ServerName, State as _,
 };
 use ironrdp_core::WriteBuf;
 
 use crate::framed::{Framed, FramedRead, FramedWrite};
-use crate::{single_sequence_step, AsyncNetworkClient};
+use crate::timer::{AsyncTimer, ConnectTimer};
+use crate::{single_sequence_step, AsyncNetworkClient, TraceDirection, TraceEvent};
 
 #[non_exhaustive]
 pub struct ShouldUpgrade;
 
+/// Performs the connection sequence up to the security upgrade.
+///
+/// The [`ConnectTimeouts`](ironrdp_connector::ConnectTimeouts) of the connector configuration are enforced using
+/// `timer`, which should be passed to [`connect_finalize`] afterwards.
 #[instrument(skip_all)]
-pub async fn connect_begin<S>(framed: &mut Framed<S>, connector: &mut ClientConnector) -> ConnectorResult<ShouldUpgrade>
+pub async fn connect_begin<S, T>(
+    framed: &mut Framed<S>,
+    connector: &mut ClientConnector,
+    timer: &mut ConnectTimer<T>,
+) -> ConnectorResult<ShouldUpgrade>
 where
     S: Sync + FramedRead + FramedWrite,
+    T: AsyncTimer,
 {
     let mut buf = WriteBuf::new();
 
     info!("Begin connection procedure");
 
     while !connector.should_perform_security_upgrade() {
-        single_sequence_step(framed, connector, &mut buf).await?;
+        let state_name = timer.arm(&connector.config.timeouts, &connector.state);
+        timer
+            .run(state_name, single_sequence_step(framed, connector, &mut buf))
+            .await?;
     }
 
     Ok(ShouldUpgrade)
@@ -44,23 +60,86 @@ pub fn mark_as_upgraded(_: ShouldUpgrade, connector: &mut ClientConnector) -> Up
     Upgraded
 }
 
+/// Outcome of the connection sequence, see [`connect_finalize_or_redirect`]
+pub enum ConnectionOutcome {
+    Connected(ConnectionResult),
+    /// The server redirected the client to another server.
+    ///
+    /// The connector is returned, so that the connection sequence can be run again against the target server after
+    /// calling [`ClientConnector::redirect`].
+    Redirected {
+        connector: Box<ClientConnector>,
+        redirection: ServerRedirection,
+    },
+}
+
+/// Performs the rest of the connection sequence, once the security upgrade is done.
+///
+/// The [`ConnectTimeouts`](ironrdp_connector::ConnectTimeouts) of the connector configuration are enforced using
+/// `timer`, which should be the one passed to [`connect_begin`], if called.
+///
+/// A server redirection is reported as a [`ConnectorErrorKind::Redirected`] error, use
+/// [`connect_finalize_or_redirect`] to follow it.
+#[instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn connect_finalize<S, T>(
+    upgraded: Upgraded,
+    framed: &mut Framed<S>,
+    connector: ClientConnector,
+    timer: &mut ConnectTimer<T>,
+    server_name: ServerName,
+    server_public_key: Vec<u8>,
+    network_client: Option<&mut dyn AsyncNetworkClient>,
+    kerberos_config: Option<KerberosConfig>,
+) -> ConnectorResult<ConnectionResult>
+where
+    S: FramedRead + FramedWrite,
+    T: AsyncTimer,
+{
+    let outcome = connect_finalize_or_redirect(
+        upgraded,
+        framed,
+        connector,
+        timer,
+        server_name,
+        server_public_key,
+        network_client,
+        kerberos_config,
+    )
+    .await?;
+
+    match outcome {
+        ConnectionOutcome::Connected(result) => Ok(result),
+        ConnectionOutcome::Redirected { redirection, .. } => Err(ConnectorError::new(
+            "connect_finalize",
+            ConnectorErrorKind::Redirected(Box::new(redirection)),
+        )),
+    }
+}
+
+/// Performs the rest of the connection sequence like [`connect_finalize`], the server redirections being returned
+/// instead of failing.
 #[instrument(skip_all)]
-pub async fn connect_finalize<S>(
+#[allow(clippy::too_many_arguments)]
+pub async fn connect_finalize_or_redirect<S, T>(
     _: Upgraded,
     framed: &mut Framed<S>,
     mut connector: ClientConnector,
+    timer: &mut ConnectTimer<T>,
     server_name: ServerName,
     server_public_key: Vec<u8>,
     network_client: Option<&mut dyn AsyncNetworkClient>,
     kerberos_config: Option<KerberosConfig>,
-) -> ConnectorResult<ConnectionResult>
+) -> ConnectorResult<ConnectionOutcome>
 where
     S: FramedRead + FramedWrite,
+    T: AsyncTimer,
 {
     let mut buf = WriteBuf::new();
 
     if connector.should_perform_credssp() {
-        perform_credssp_step(
+        let state_name = timer.arm(&connector.config.timeouts, &connector.state);
+        let credssp = perform_credssp_step(
             framed,
             &mut connector,
             &mut buf,
@@ -68,27 +147,37 @@ where
             server_public_key,
             network_client,
             kerberos_config,
-        )
-        .await?;
+        );
+        timer.run(state_name, credssp).await?;
     }
 
-    let result = loop {
-        single_sequence_step(framed, &mut connector, &mut buf).await?;
+    while !connector.state.is_terminal() {
+        let state_name = timer.arm(&connector.config.timeouts, &connector.state);
+        timer
+            .run(state_name, single_sequence_step(framed, &mut connector, &mut buf))
+            .await?;
+    }
 
-        if let ClientConnectorState::Connected { result } = connector.state {
-            break result;
+    match mem::take(&mut connector.state) {
+        ClientConnectorState::Connected { result } => {
+            info!("Connected with success");
+            Ok(ConnectionOutcome::Connected(result))
         }
-    };
-
-    info!("Connected with success");
-
-    Ok(result)
+        ClientConnectorState::Redirected { redirection } => {
+            info!(?redirection.target, "Redirected to another server");
+            Ok(ConnectionOutcome::Redirected {
+                connector: Box::new(connector),
+                redirection,
+            })
+        }
+        _ => Err(general_err!("invalid terminal state (this is a bug)")),
+    }
 }
 
 async fn resolve_generator(
     generator: &mut CredsspProcessGenerator<'_>,
     network_client: &mut dyn AsyncNetworkClient,
-) -> ConnectorResult<ClientState> {
+) -> ConnectorResult<ironrdp_connector::sspi::Result<ClientState>> {
     let mut state = generator.start();
 
     loop {
@@ -97,10 +186,7 @@ async fn resolve_generator(
                 let response = network_client.send(&request).await?;
                 state = generator.resume(Ok(response));
             }
-            GeneratorState::Completed(client_state) => {
-                break client_state
-                    .map_err(|e| ConnectorError::new("CredSSP", ironrdp_connector::ConnectorErrorKind::Credssp(e)))
-            }
+            GeneratorState::Completed(client_state) => break Ok(client_state),
         }
     }
 }
@@ -128,6 +214,7 @@ where
     let (mut sequence, mut ts_request) = CredsspSequence::init(
         connector.config.credentials.clone(),
         connector.config.domain.as_deref(),
+        connector.config.credential_delegation,
         selected_protocol,
         server_name,
         server_public_key,
@@ -142,11 +229,10 @@ where
                 trace!("resolving network");
                 resolve_generator(&mut generator, network_client_ref).await?
             } else {
-                generator
-                    .resolve_to_result()
-                    .map_err(|e| custom_err!("resolve without network client", e))?
+                generator.resolve_to_result()
             }
-        }; // drop generator
+        } // drop generator
+        .map_err(|e| sequence.handle_process_error(e))?;
 
         buf.clear();
         let written = sequence.handle_process_result(client_state, buf)?;
@@ -154,6 +240,12 @@ where
         if let Some(response_len) = written.size() {
             let response = &buf[..response_len];
             trace!(response_len, "Send response");
+            framed.trace_pdu(TraceEvent {
+                direction: TraceDirection::Sent,
+                state: connector.state.name(),
+                hint: None,
+                bytes: response,
+            });
             framed
                 .write_all(response)
                 .await
@@ -177,6 +269,13 @@ where
 
         trace!(length = pdu.len(), "PDU received");
 
+        framed.trace_pdu(TraceEvent {
+            direction: TraceDirection::Received,
+            state: connector.state.name(),
+            hint: Some(next_pdu_hint),
+            bytes: &pdu,
+        });
+
         if let Some(next_request) = sequence.decode_server_message(&pdu)? {
             ts_request = next_request;
         } else {
@@ -5,6 +5,8 @@ use ironrdp_connector::{ConnectorResult, Sequence, Written};
 use ironrdp_core::WriteBuf;
 use ironrdp_pdu::PduHint;
 
+use crate::trace::{PduTraceSink, TraceDirection, TraceEvent};
+
 // TODO: investigate if we could use static async fn / return position impl trait in traits when stabilized:
 // https://github.com/rust-lang/rust/issues/91611
 
@@ -55,12 +57,44 @@ pub trait StreamWrapper: Sized {
 pub struct Framed<S> {
     stream: S,
     buf: BytesMut,
+    trace_sink: Option<PduTraceSink>,
 }
 
 impl<S> Framed<S> {
+    /// Sets a sink called with the PDUs exchanged by the sequence drivers, such as [`single_sequence_step`] and the
+    /// CredSSP loops, replacing the previous one.
+    ///
+    /// See [`pdu_log_sink`](crate::pdu_log_sink) for a sink writing the PDUs into a log file.
+    pub fn set_pdu_trace_sink(&mut self, sink: impl Fn(TraceEvent<'_>) + Send + Sync + 'static) {
+        self.trace_sink = Some(Box::new(sink));
+    }
+
+    pub fn clear_pdu_trace_sink(&mut self) {
+        self.trace_sink = None;
+    }
+
+    /// Reports a PDU exchanged by a sequence driver to the trace sink, if any.
+    pub fn trace_pdu(&self, event: TraceEvent<'_>) {
+        if let Some(sink) = &self.trace_sink {
+            sink(event);
+        }
+    }
+
     pub fn peek(&self) -> &[u8] {
         &self.buf
     }
+
+    /// Returns the information about the standard RDP PDU at the front of the internal buffer, if it has been
+    /// received entirely.
+    ///
+    /// Nothing is consumed, and `None` is returned when the buffer only holds the beginning of a PDU.
+    pub fn peek_frame(&self) -> io::Result<Option<ironrdp_pdu::PduInfo>> {
+        match ironrdp_pdu::find_size(self.peek()) {
+            Ok(Some(pdu_info)) if self.buf.len() >= pdu_info.length => Ok(Some(pdu_info)),
+            Ok(_) => Ok(None),
+            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+        }
+    }
 }
 
 impl<S> Framed<S>
@@ -75,6 +109,7 @@ where
         Self {
             stream: S::from_inner(stream),
             buf: leftover,
+            trace_sink: None,
         }
     }
 
@@ -157,6 +192,35 @@ where
         }
     }
 
+    /// Reads standard RDP PDU frames, up to `max` of them.
+    ///
+    /// Waits for at least one frame, like [`Framed::read_pdu`], and then returns along with it all the frames
+    /// already received entirely, without reading from the stream again. A partially received trailing frame is
+    /// kept in the internal buffer for the next call. At least one frame is returned, even if `max` is zero.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `tokio::select!` statement and some other branch
+    /// completes first, then it is safe to drop the future and re-create it later.
+    /// Data may have been read, but it will be stored in the internal buffer.
+    pub async fn read_frames_batch(&mut self, max: usize) -> io::Result<Vec<(ironrdp_pdu::Action, Bytes)>> {
+        let (action, frame) = self.read_pdu().await?;
+        let mut frames = vec![(action, frame.freeze())];
+
+        while frames.len() < max {
+            // An invalid frame is left in the buffer, and reported by the next call instead of the frames
+            // already split.
+            let Ok(Some(pdu_info)) = self.peek_frame() else {
+                break;
+            };
+
+            frames.push((pdu_info.action, self.buf.split_to(pdu_info.length).freeze()));
+        }
+
+        Ok(frames)
+    }
+
     /// Reads a frame using the provided PduHint.
     ///
     /// # Cancel safety
@@ -235,8 +299,9 @@ where
     S: FramedWrite + FramedRead,
 {
     buf.clear();
+    let state = sequence.state().name();
     let written = single_sequence_step_read(framed, sequence, buf).await?;
-    single_sequence_step_write(framed, buf, written).await
+    single_sequence_step_write(framed, state, buf, written).await
 }
 
 pub async fn single_sequence_step_read<S>(
@@ -263,6 +328,13 @@ where
 
         trace!(length = pdu.len(), "PDU received");
 
+        framed.trace_pdu(TraceEvent {
+            direction: TraceDirection::Received,
+            state: sequence.state().name(),
+            hint: Some(next_pdu_hint),
+            bytes: &pdu,
+        });
+
         sequence.step(&pdu, buf)
     } else {
         sequence.step_no_input(buf)
@@ -271,6 +343,7 @@ where
 
 async fn single_sequence_step_write<S>(
     framed: &mut Framed<S>,
+    state: &'static str,
     buf: &mut WriteBuf,
     written: Written,
 ) -> ConnectorResult<()>
@@ -281,6 +354,12 @@ where
         debug_assert_eq!(buf.filled_len(), response_len);
         let response = buf.filled();
         trace!(response_len, "Send response");
+        framed.trace_pdu(TraceEvent {
+            direction: TraceDirection::Sent,
+            state,
+            hint: None,
+            bytes: response,
+        });
         framed
             .write_all(response)
             .await
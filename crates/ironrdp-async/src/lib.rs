@@ -9,6 +9,8 @@ pub use bytes;
 mod connector;
 mod framed;
 mod session;
+mod timer;
+mod trace;
 
 use core::future::Future;
 use core::pin::Pin;
@@ -18,7 +20,11 @@ use ironrdp_connector::ConnectorResult;
 
 pub use self::connector::*;
 pub use self::framed::*;
-// pub use self::session::*;
+pub use self::session::{FrameSender, SendError, SessionDriver, WriterTask, DEFAULT_OUTBOUND_CAPACITY};
+pub use self::timer::{timeout, AsyncTimer, ConnectTimer, Elapsed, NoTimer};
+pub use self::trace::{
+    pdu_log_sink, PduLogReader, PduLogRecord, PduLogWriter, TraceDirection, TraceEvent, PDU_LOG_MAGIC,
+};
 
 pub trait AsyncNetworkClient {
     fn send<'a>(
@@ -1 +1,192 @@
-// TODO: active session async helpers
+use core::future::poll_fn;
+use core::pin::Pin;
+use std::io;
+
+use bytes::Bytes;
+use futures_channel::mpsc;
+use futures_core::Stream as _;
+
+use crate::framed::{Framed, FramedRead, FramedWrite};
+
+/// Number of outbound frames queued by [`SessionDriver::new`] callers not needing a specific capacity
+pub const DEFAULT_OUTBOUND_CAPACITY: usize = 64;
+
+/// Drives an active session over a split transport, so that reading and writing never wait for each other
+///
+/// The session loop owns the read half and reads the frames sent by the server, while the write half is owned by a
+/// [`WriterTask`] to be spawned on the runtime of the caller. The frames to send, such as the response frames output
+/// by the active stage and the input event frames, go through a bounded queue of outbound frames, from any number of
+/// [`FrameSender`]s.
+///
+/// # Backpressure
+///
+/// At most `capacity` frames, plus one for each [`FrameSender`], are queued for the writer task. Once the queue is
+/// full, [`FrameSender::send`] waits for the writer task to make room, so a peer not reading its socket anymore
+/// eventually stalls the session loop rather than letting the queue grow without bound. The producers which must not
+/// wait, such as the UI threads, use [`FrameSender::try_send`] instead, and drop or coalesce the frames refused with
+/// [`SendError::Full`]. The response frames of the active stage must not be dropped, as the server expects them.
+///
+/// # Shutdown
+///
+/// The writer task completes once all the [`FrameSender`]s, including the one of the driver, are dropped, after
+/// writing the frames already queued. When writing fails, the writer task completes with the error and the queued
+/// frames are dropped, the senders then failing with [`SendError::Disconnected`] instead of waiting forever.
+pub struct SessionDriver<R> {
+    reader: Framed<R>,
+    sender: FrameSender,
+}
+
+impl<R> SessionDriver<R> {
+    /// Creates the driver of the session, and the writer task to spawn for `writer`.
+    pub fn new<W>(reader: Framed<R>, writer: Framed<W>, capacity: usize) -> (Self, WriterTask<W>) {
+        let (tx, rx) = mpsc::channel(capacity);
+
+        let driver = Self {
+            reader,
+            sender: FrameSender { tx },
+        };
+
+        (driver, WriterTask { writer, rx })
+    }
+
+    /// Returns a new sender of outbound frames, for the tasks sending frames on their own.
+    pub fn sender(&self) -> FrameSender {
+        self.sender.clone()
+    }
+
+    /// Queues `frame` for the writer task, waiting for room in the queue if it is full.
+    pub async fn send(&mut self, frame: impl Into<Bytes>) -> Result<(), SendError> {
+        self.sender.send(frame).await
+    }
+
+    pub fn reader(&self) -> &Framed<R> {
+        &self.reader
+    }
+
+    pub fn reader_mut(&mut self) -> &mut Framed<R> {
+        &mut self.reader
+    }
+
+    /// Drops the sender of the driver, and returns the read half.
+    ///
+    /// The writer task completes once the other senders, if any, are dropped too.
+    pub fn into_reader(self) -> Framed<R> {
+        self.reader
+    }
+}
+
+impl<R> SessionDriver<R>
+where
+    R: FramedRead,
+{
+    /// Reads standard RDP PDU frames, up to `max` of them, see [`Framed::read_frames_batch`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `tokio::select!` statement and some other branch
+    /// completes first, then it is safe to drop the future and re-create it later.
+    /// Data may have been read, but it will be stored in the internal buffer.
+    pub async fn read_frames_batch(&mut self, max: usize) -> io::Result<Vec<(ironrdp_pdu::Action, Bytes)>> {
+        self.reader.read_frames_batch(max).await
+    }
+}
+
+/// Sender of outbound frames to the [`WriterTask`] of a [`SessionDriver`]
+#[derive(Clone)]
+pub struct FrameSender {
+    tx: mpsc::Sender<Bytes>,
+}
+
+impl FrameSender {
+    /// Queues `frame` for the writer task, waiting for room in the queue if it is full.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If you use it as the event in a
+    /// `tokio::select!` statement and some other branch
+    /// completes first, then it is guaranteed that the frame was not queued.
+    pub async fn send(&mut self, frame: impl Into<Bytes>) -> Result<(), SendError> {
+        let frame = frame.into();
+
+        if poll_fn(|cx| self.tx.poll_ready(cx)).await.is_err() {
+            return Err(SendError::Disconnected(frame));
+        }
+
+        // A slot is reserved for this sender once it is ready.
+        self.try_send(frame)
+    }
+
+    /// Queues `frame` for the writer task, unless the queue is full.
+    pub fn try_send(&mut self, frame: impl Into<Bytes>) -> Result<(), SendError> {
+        self.tx.try_send(frame.into()).map_err(|e| {
+            if e.is_full() {
+                SendError::Full(e.into_inner())
+            } else {
+                SendError::Disconnected(e.into_inner())
+            }
+        })
+    }
+
+    /// Returns whether the writer task stopped, the frames sent being dropped.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// Error returned when a frame can't be queued for the [`WriterTask`], holding the frame back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendError {
+    /// The queue is full, see [`FrameSender::try_send`].
+    Full(Bytes),
+    /// The writer task stopped, because writing failed or it was dropped.
+    Disconnected(Bytes),
+}
+
+impl SendError {
+    pub fn into_frame(self) -> Bytes {
+        match self {
+            Self::Full(frame) | Self::Disconnected(frame) => frame,
+        }
+    }
+}
+
+impl core::fmt::Display for SendError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("outbound frame queue is full"),
+            Self::Disconnected(_) => f.write_str("writer task stopped"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// Task writing the outbound frames of a [`SessionDriver`], to be spawned on the runtime of the caller
+pub struct WriterTask<W> {
+    writer: Framed<W>,
+    rx: mpsc::Receiver<Bytes>,
+}
+
+impl<W> WriterTask<W>
+where
+    W: FramedWrite,
+{
+    /// Writes the queued frames until all the senders are dropped, and returns the write half.
+    pub async fn run(mut self) -> io::Result<Framed<W>> {
+        debug!("Writer task started");
+
+        while let Some(frame) = poll_fn(|cx| Pin::new(&mut self.rx).poll_next(cx)).await {
+            trace!(frame_length = frame.len(), "Write frame");
+
+            if let Err(error) = self.writer.write_all(&frame).await {
+                debug!(%error, "Writer task stopped");
+                return Err(error);
+            }
+        }
+
+        debug!("Writer task ended, all the senders are dropped");
+
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,141 @@
+use core::future::{poll_fn, Future};
+use core::pin::{pin, Pin};
+use core::task::Poll;
+use core::time::Duration;
+
+use ironrdp_connector::{
+    ClientConnectorState, ConnectStage, ConnectTimeouts, ConnectorError, ConnectorErrorKind, ConnectorResult,
+    State as _,
+};
+
+/// Timer of the async runtime, used to enforce the [`ConnectTimeouts`] of the connection sequence and by [`timeout`]
+pub trait AsyncTimer {
+    type Sleep: Future<Output = ()>;
+
+    /// Returns a future completing once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+/// Error returned by [`timeout`] when the duration elapsed before the future completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl core::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Runs `future` to completion, unless `duration` elapses first.
+///
+/// The `future` is dropped when the duration elapses.
+pub async fn timeout<T, F>(timer: &T, duration: Duration, future: F) -> Result<F::Output, Elapsed>
+where
+    T: AsyncTimer,
+    F: Future,
+{
+    let mut future = pin!(future);
+    let mut sleep = pin!(timer.sleep(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        sleep.as_mut().poll(cx).map(|()| Err(Elapsed))
+    })
+    .await
+}
+
+/// Timer which never expires, for the drivers not enforcing the [`ConnectTimeouts`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTimer;
+
+impl AsyncTimer for NoTimer {
+    type Sleep = core::future::Pending<()>;
+
+    fn sleep(&self, _: Duration) -> Self::Sleep {
+        core::future::pending()
+    }
+}
+
+/// Tracks the time budgets of a connection sequence
+///
+/// The same `ConnectTimer` must be used for all the connect helpers of a given connection, so that the overall
+/// deadline covers all of them. It starts when the first helper is called.
+pub struct ConnectTimer<T: AsyncTimer> {
+    timer: T,
+    overall: Option<Pin<Box<T::Sleep>>>,
+    overall_started: bool,
+    stage: Option<ConnectStage>,
+    stage_sleep: Option<Pin<Box<T::Sleep>>>,
+}
+
+impl<T: AsyncTimer> ConnectTimer<T> {
+    pub fn new(timer: T) -> Self {
+        Self {
+            timer,
+            overall: None,
+            overall_started: false,
+            stage: None,
+            stage_sleep: None,
+        }
+    }
+
+    /// Starts the budgets applying to `state`, and returns the name of the state.
+    ///
+    /// The budget of a stage starts when the connector enters the first state of the stage.
+    pub(crate) fn arm(&mut self, timeouts: &ConnectTimeouts, state: &ClientConnectorState) -> &'static str {
+        if !self.overall_started {
+            self.overall_started = true;
+            self.overall = timeouts.overall.map(|duration| Box::pin(self.timer.sleep(duration)));
+        }
+
+        let current_stage = ConnectStage::of(state);
+
+        if current_stage != self.stage {
+            self.stage = current_stage;
+            self.stage_sleep = current_stage
+                .and_then(|stage| timeouts.get(stage))
+                .map(|duration| Box::pin(self.timer.sleep(duration)));
+        }
+
+        state.name()
+    }
+
+    /// Runs `future` to completion, unless the budget of the current stage or the overall deadline expires first.
+    pub(crate) async fn run<F, O>(&mut self, state_name: &'static str, future: F) -> ConnectorResult<O>
+    where
+        F: Future<Output = ConnectorResult<O>>,
+    {
+        let mut future = pin!(future);
+
+        poll_fn(|cx| {
+            if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                return Poll::Ready(output);
+            }
+
+            let context = if is_expired(&mut self.stage_sleep, cx) {
+                "stage budget exceeded"
+            } else if is_expired(&mut self.overall, cx) {
+                "connection deadline exceeded"
+            } else {
+                return Poll::Pending;
+            };
+
+            warn!(stage = state_name, reason = context, "Connection timed out");
+
+            Poll::Ready(Err(ConnectorError::new(
+                context,
+                ConnectorErrorKind::Timeout { stage: state_name },
+            )))
+        })
+        .await
+    }
+}
+
+fn is_expired<S: Future<Output = ()>>(sleep: &mut Option<Pin<Box<S>>>, cx: &mut core::task::Context<'_>) -> bool {
+    sleep.as_mut().is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready())
+}
@@ -0,0 +1,223 @@
+use core::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ironrdp_pdu::PduHint;
+
+/// Direction of a traced PDU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    Sent,
+    Received,
+}
+
+/// PDU exchanged by a sequence driver, reported to the sink set with [`Framed::set_pdu_trace_sink`]
+///
+/// [`Framed::set_pdu_trace_sink`]: crate::Framed::set_pdu_trace_sink
+#[derive(Clone, Copy)]
+pub struct TraceEvent<'a> {
+    pub direction: TraceDirection,
+    /// Name of the sequence state at the time the PDU was exchanged.
+    pub state: &'static str,
+    /// Hint which matched the received PDU, `None` for sent PDUs.
+    pub hint: Option<&'a dyn PduHint>,
+    pub bytes: &'a [u8],
+}
+
+impl fmt::Debug for TraceEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceEvent")
+            .field("direction", &self.direction)
+            .field("state", &self.state)
+            .field("hint", &self.hint)
+            .field("length", &self.bytes.len())
+            .finish()
+    }
+}
+
+pub(crate) type PduTraceSink = Box<dyn Fn(TraceEvent<'_>) + Send + Sync>;
+
+/// Magic number at the beginning of a PDU log.
+pub const PDU_LOG_MAGIC: [u8; 8] = *b"IRDPLOG1";
+
+/// Writes [`TraceEvent`]s into a length-prefixed binary log
+///
+/// The log starts with [`PDU_LOG_MAGIC`], followed by one record per event. All the integers are little-endian:
+///
+/// | Field     | Size               | Description                                          |
+/// |-----------|--------------------|------------------------------------------------------|
+/// | timestamp | 8                  | Microseconds since the UNIX epoch                    |
+/// | direction | 1                  | 0 if sent, 1 if received                             |
+/// | state     | 2 + length         | Name of the sequence state                           |
+/// | hint      | 2 + length         | Debug representation of the hint, empty if none      |
+/// | bytes     | 4 + length         | Raw PDU                                              |
+#[derive(Debug)]
+pub struct PduLogWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> PduLogWriter<W> {
+    /// Writes the log header, and returns a writer for the records.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&PDU_LOG_MAGIC)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_event(&mut self, event: &TraceEvent<'_>) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX))
+            .unwrap_or(0);
+        let hint = event.hint.map(|hint| format!("{hint:?}")).unwrap_or_default();
+
+        let record = PduLogRecord {
+            timestamp,
+            direction: event.direction,
+            state: event.state.to_owned(),
+            hint,
+            bytes: event.bytes.to_vec(),
+        };
+
+        // Each record is written at once, so that an unbuffered writer does not issue one syscall per field.
+        self.writer.write_all(&record.to_bytes()?)?;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Returns a sink writing the events into `writer` as a [`PduLogWriter`] log.
+///
+/// Errors while writing a record are logged, and the record is dropped.
+pub fn pdu_log_sink<W>(writer: W) -> io::Result<impl Fn(TraceEvent<'_>) + Send + Sync>
+where
+    W: Write + Send + 'static,
+{
+    let writer = Mutex::new(PduLogWriter::new(writer)?);
+
+    Ok(move |event: TraceEvent<'_>| {
+        let mut writer = writer.lock().expect("poisoned");
+        if let Err(error) = writer.write_event(&event) {
+            warn!(%error, "Failed to write a PDU log record");
+        }
+    })
+}
+
+/// Record read from a PDU log by a [`PduLogReader`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PduLogRecord {
+    /// Microseconds since the UNIX epoch.
+    pub timestamp: u64,
+    pub direction: TraceDirection,
+    pub state: String,
+    /// Debug representation of the hint, empty for sent PDUs.
+    pub hint: String,
+    pub bytes: Vec<u8>,
+}
+
+impl PduLogRecord {
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let state_len = u16::try_from(self.state.len()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let hint_len = u16::try_from(self.hint.len()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let bytes_len = u32::try_from(self.bytes.len()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut record = Vec::with_capacity(8 + 1 + 2 + self.state.len() + 2 + self.hint.len() + 4 + self.bytes.len());
+        record.extend_from_slice(&self.timestamp.to_le_bytes());
+        record.push(match self.direction {
+            TraceDirection::Sent => 0,
+            TraceDirection::Received => 1,
+        });
+        record.extend_from_slice(&state_len.to_le_bytes());
+        record.extend_from_slice(self.state.as_bytes());
+        record.extend_from_slice(&hint_len.to_le_bytes());
+        record.extend_from_slice(self.hint.as_bytes());
+        record.extend_from_slice(&bytes_len.to_le_bytes());
+        record.extend_from_slice(&self.bytes);
+
+        Ok(record)
+    }
+}
+
+/// Reads the records of a log written by a [`PduLogWriter`]
+#[derive(Debug)]
+pub struct PduLogReader<R> {
+    reader: R,
+}
+
+impl<R: Read> PduLogReader<R> {
+    /// Checks the log header, and returns a reader for the records.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; PDU_LOG_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic != PDU_LOG_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a PDU log"));
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Returns the next record, or `None` at the end of the log.
+    pub fn read_record(&mut self) -> io::Result<Option<PduLogRecord>> {
+        let mut timestamp = [0; 8];
+        match self.reader.read_exact(&mut timestamp) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let direction = match self.read_array::<1>()? {
+            [0] => TraceDirection::Sent,
+            [1] => TraceDirection::Received,
+            [other] => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid direction: {other}"),
+                ))
+            }
+        };
+
+        let state_len = u16::from_le_bytes(self.read_array()?);
+        let state = self.read_string(usize::from(state_len))?;
+        let hint_len = u16::from_le_bytes(self.read_array()?);
+        let hint = self.read_string(usize::from(hint_len))?;
+        let bytes_len = u32::from_le_bytes(self.read_array()?);
+        let bytes =
+            self.read_vec(usize::try_from(bytes_len).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)?;
+
+        Ok(Some(PduLogRecord {
+            timestamp: u64::from_le_bytes(timestamp),
+            direction,
+            state,
+            hint,
+            bytes,
+        }))
+    }
+
+    fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut array = [0; N];
+        self.reader.read_exact(&mut array)?;
+        Ok(array)
+    }
+
+    fn read_vec(&mut self, length: usize) -> io::Result<Vec<u8>> {
+        let mut vec = vec![0; length];
+        self.reader.read_exact(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn read_string(&mut self, length: usize) -> io::Result<String> {
+        String::from_utf8(self.read_vec(length)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<R: Read> Iterator for PduLogReader<R> {
+    type Item = io::Result<PduLogRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
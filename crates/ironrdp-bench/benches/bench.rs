@@ -1,12 +1,19 @@
 use std::num::NonZero;
+use std::sync::Arc;
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use ironrdp_graphics::color_conversion::to_64x64_ycbcr_tile;
+use ironrdp_graphics::color_conversion::{rdp_16bit_to_rgba, to_64x64_ycbcr_tile, ycbcr_to_bgra, YCbCrBuffer};
+use ironrdp_graphics::scaling::{Scaler, ScalingMode};
 use ironrdp_pdu::codecs::rfx;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_pdu::rdp::capability_sets::CmdFlags;
 use ironrdp_server::{
     bench::encoder::rfx::{rfx_enc, rfx_enc_tile},
-    BitmapUpdate,
+    bench::encoder::FrameEncoder,
+    BitmapUpdate, MappedFrame, SharedFrame,
 };
+// Used by the other benchmarks of this package.
+use {ironrdp_core as _, ironrdp_dvc as _, ironrdp_svc as _};
 
 pub fn rfx_enc_tile_bench(c: &mut Criterion) {
     let quant = rfx::Quant::default();
@@ -40,6 +47,48 @@ pub fn rfx_enc_bench(c: &mut Criterion) {
     c.bench_function("rfx_enc", |b| b.iter(|| rfx_enc(&bitmap, &quant, algo)));
 }
 
+pub fn frame_enc_bench(c: &mut Criterion) {
+    const WIDTH: u16 = 1920;
+    const HEIGHT: u16 = 1080;
+    let stride = usize::from(WIDTH) * 4;
+    // Mapped buffer handed out by the capture source.
+    let captured: Arc<dyn MappedFrame> = Arc::new(vec![0x7f; stride * usize::from(HEIGHT)]);
+    let mut encoder = FrameEncoder::new(CmdFlags::SET_SURFACE_BITS, None);
+
+    let mut group = c.benchmark_group("frame_enc");
+    group.bench_function("copied", |b| {
+        b.iter(|| {
+            let bitmap = BitmapUpdate {
+                top: 0,
+                left: 0,
+                width: NonZero::new(WIDTH).unwrap(),
+                height: NonZero::new(HEIGHT).unwrap(),
+                format: ironrdp_server::PixelFormat::BgrX32,
+                order: ironrdp_server::PixelOrder::BottomToTop,
+                data: captured.data().to_vec(),
+                stride,
+            };
+            encoder.encode(bitmap.as_frame_ref())
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            let frame = SharedFrame {
+                top: 0,
+                left: 0,
+                width: NonZero::new(WIDTH).unwrap(),
+                height: NonZero::new(HEIGHT).unwrap(),
+                format: ironrdp_server::PixelFormat::BgrX32,
+                order: ironrdp_server::PixelOrder::BottomToTop,
+                buffer: Arc::clone(&captured),
+                stride,
+            };
+            encoder.encode(frame.as_frame_ref())
+        })
+    });
+    group.finish();
+}
+
 pub fn to_ycbcr_bench(c: &mut Criterion) {
     const WIDTH: usize = 64;
     const HEIGHT: usize = 64;
@@ -54,5 +103,62 @@ pub fn to_ycbcr_bench(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, rfx_enc_tile_bench, rfx_enc_bench, to_ycbcr_bench);
+pub fn scaling_bench(c: &mut Criterion) {
+    const SOURCE_SIZE: (u16, u16) = (1920, 1080);
+    const DESTINATION_SIZE: (u16, u16) = (2560, 1440);
+
+    let source = vec![0x00c0_8040; usize::from(SOURCE_SIZE.0) * usize::from(SOURCE_SIZE.1)];
+    let mut destination = vec![0; usize::from(DESTINATION_SIZE.0) * usize::from(DESTINATION_SIZE.1)];
+    let region = InclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: SOURCE_SIZE.0 - 1,
+        bottom: SOURCE_SIZE.1 - 1,
+    };
+
+    let mut group = c.benchmark_group("scaling_1080p_to_1440p");
+    for mode in [ScalingMode::Nearest, ScalingMode::Bilinear, ScalingMode::Lanczos3] {
+        let mut scaler = Scaler::new(mode, SOURCE_SIZE, DESTINATION_SIZE);
+        group.bench_function(format!("{mode:?}"), |b| {
+            b.iter(|| scaler.scale(&source, &mut destination, &region))
+        });
+    }
+    group.finish();
+}
+
+pub fn color_conversion_bench(c: &mut Criterion) {
+    const PIXELS: usize = 1920 * 1080;
+
+    let y = vec![0x0400i16; PIXELS];
+    let cb = vec![-0x0100i16; PIXELS];
+    let cr = vec![0x0200i16; PIXELS];
+    let rgb565 = vec![0x84u8; PIXELS * 2];
+    let mut output = vec![0; PIXELS * 4];
+
+    let mut group = c.benchmark_group("color_conversion_1080p");
+    group.bench_function("ycbcr_to_bgra", |b| {
+        b.iter(|| {
+            let input = YCbCrBuffer {
+                y: &y,
+                cb: &cb,
+                cr: &cr,
+            };
+            ycbcr_to_bgra(input, &mut output)
+        })
+    });
+    group.bench_function("rdp_16bit_to_rgba", |b| {
+        b.iter(|| rdp_16bit_to_rgba(&rgb565, &mut output))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    rfx_enc_tile_bench,
+    rfx_enc_bench,
+    frame_enc_bench,
+    to_ycbcr_bench,
+    scaling_bench,
+    color_conversion_bench
+);
 criterion_main!(benches);
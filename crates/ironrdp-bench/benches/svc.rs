@@ -0,0 +1,145 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ironrdp_core::{encode_vec, ensure_size, impl_as_any, Encode, EncodeResult, WriteCursor};
+use ironrdp_dvc::pdu::{CreateRequestPdu, DataFirstPdu, DataPdu, DrdynvcDataPdu, DrdynvcServerPdu};
+use ironrdp_dvc::{DrdynvcClient, DvcMessage, DvcProcessor};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::{StaticVirtualChannel, SvcEncode, SvcMessage, SvcProcessor as _};
+// Used by the other benchmarks of this package.
+use {ironrdp_graphics as _, ironrdp_server as _};
+
+/// A small PDU, such as a device I/O completion.
+struct SmallPdu {
+    completion_id: u32,
+    io_status: u32,
+}
+
+impl Encode for SmallPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_u32(0x4344_4344);
+        dst.write_u32(1);
+        dst.write_u32(self.completion_id);
+        dst.write_u32(self.io_status);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "SmallPdu"
+    }
+
+    fn size(&self) -> usize {
+        16
+    }
+}
+
+impl SvcEncode for SmallPdu {}
+
+const MESSAGES: usize = 32;
+
+fn small_pdu(completion_id: usize) -> SmallPdu {
+    SmallPdu {
+        completion_id: u32::try_from(completion_id).unwrap(),
+        io_status: 0,
+    }
+}
+
+fn boxed_messages() -> Vec<SvcMessage> {
+    (0..MESSAGES).map(|i| SvcMessage::boxed(small_pdu(i))).collect()
+}
+
+fn inline_messages() -> Vec<SvcMessage> {
+    (0..MESSAGES).map(|i| SvcMessage::from(small_pdu(i))).collect()
+}
+
+pub fn svc_message_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("svc_message");
+    group.bench_function("boxed", |b| {
+        b.iter(|| StaticVirtualChannel::chunkify(boxed_messages()).unwrap())
+    });
+    group.bench_function("inline", |b| {
+        b.iter(|| StaticVirtualChannel::chunkify(inline_messages()).unwrap())
+    });
+    group.finish();
+}
+
+const DVC_CHANNEL_ID: u32 = 1;
+const DVC_TRANSFER_SIZE: usize = 2 * 1024 * 1024;
+
+/// Drops the reassembled messages.
+struct SinkDvc {
+    reserve_limit: usize,
+}
+
+impl_as_any!(SinkDvc);
+
+impl DvcProcessor for SinkDvc {
+    fn channel_name(&self) -> &str {
+        "Bench::Sink"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        black_box(payload);
+        Ok(Vec::new())
+    }
+
+    fn reassembly_reserve_limit(&self) -> usize {
+        self.reserve_limit
+    }
+}
+
+fn opened_drdynvc_client(reserve_limit: usize) -> DrdynvcClient {
+    let mut client = DrdynvcClient::new().with_dynamic_channel(SinkDvc { reserve_limit });
+    let create = DrdynvcServerPdu::Create(CreateRequestPdu::new(DVC_CHANNEL_ID, "Bench::Sink".to_owned()));
+    client.process(&encode_vec(&create).unwrap()).unwrap();
+    client
+}
+
+/// DVC Data First and Data PDUs of a single message, as sent by the server.
+fn dvc_transfer() -> Vec<Vec<u8>> {
+    let message = vec![0xA5; DVC_TRANSFER_SIZE];
+
+    message
+        .chunks(DrdynvcDataPdu::MAX_DATA_SIZE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let pdu = if i == 0 {
+                DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+                    DVC_CHANNEL_ID,
+                    u32::try_from(DVC_TRANSFER_SIZE).unwrap(),
+                    chunk.to_vec(),
+                ))
+            } else {
+                DrdynvcDataPdu::Data(DataPdu::new(DVC_CHANNEL_ID, chunk.to_vec()))
+            };
+            encode_vec(&DrdynvcServerPdu::Data(pdu)).unwrap()
+        })
+        .collect()
+}
+
+fn receive_dvc_transfer(client: &mut DrdynvcClient, transfer: &[Vec<u8>]) {
+    for pdu in transfer {
+        client.process(pdu).unwrap();
+    }
+}
+
+pub fn dvc_reassembly_bench(c: &mut Criterion) {
+    let transfer = dvc_transfer();
+
+    let mut group = c.benchmark_group("dvc_reassembly");
+    group.bench_function("reserved", |b| {
+        let mut client = opened_drdynvc_client(DVC_TRANSFER_SIZE);
+        b.iter(|| receive_dvc_transfer(&mut client, &transfer))
+    });
+    group.bench_function("unreserved", |b| {
+        let mut client = opened_drdynvc_client(0);
+        b.iter(|| receive_dvc_transfer(&mut client, &transfer))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, svc_message_bench, dvc_reassembly_bench);
+criterion_main!(benches);
@@ -5,8 +5,8 @@ use ironrdp_connector::sspi::credssp::ClientState;
 use ironrdp_connector::sspi::generator::GeneratorState;
 use ironrdp_connector::sspi::network_client::NetworkClient;
 use ironrdp_connector::{
-    general_err, ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorResult,
-    Sequence as _, ServerName, State as _,
+    general_err, ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorErrorKind,
+    ConnectorResult, Sequence as _, ServerName, State as _,
 };
 use ironrdp_core::WriteBuf;
 
@@ -80,8 +80,15 @@ where
     let result = loop {
         single_sequence_step(framed, &mut connector, &mut buf)?;
 
-        if let ClientConnectorState::Connected { result } = connector.state {
-            break result;
+        match connector.state {
+            ClientConnectorState::Connected { result } => break result,
+            ClientConnectorState::Redirected { redirection } => {
+                return Err(ConnectorError::new(
+                    "connect_finalize",
+                    ConnectorErrorKind::Redirected(Box::new(redirection)),
+                ));
+            }
+            _ => {}
         }
     };
 
@@ -93,7 +100,7 @@ where
 fn resolve_generator(
     generator: &mut CredsspProcessGenerator<'_>,
     network_client: &mut impl NetworkClient,
-) -> ConnectorResult<ClientState> {
+) -> ConnectorResult<ironrdp_connector::sspi::Result<ClientState>> {
     let mut state = generator.start();
 
     loop {
@@ -102,10 +109,7 @@ fn resolve_generator(
                 let response = network_client.send(&request).unwrap();
                 state = generator.resume(Ok(response));
             }
-            GeneratorState::Completed(client_state) => {
-                break client_state
-                    .map_err(|e| ConnectorError::new("CredSSP", ironrdp_connector::ConnectorErrorKind::Credssp(e)))
-            }
+            GeneratorState::Completed(client_state) => break Ok(client_state),
         }
     }
 }
@@ -133,6 +137,7 @@ where
     let (mut sequence, mut ts_request) = CredsspSequence::init(
         connector.config.credentials.clone(),
         connector.config.domain.as_deref(),
+        connector.config.credential_delegation,
         selected_protocol,
         server_name,
         server_public_key,
@@ -143,7 +148,8 @@ where
         let client_state = {
             let mut generator = sequence.process_ts_request(ts_request);
             resolve_generator(&mut generator, network_client)?
-        }; // drop generator
+        } // drop generator
+        .map_err(|e| sequence.handle_process_error(e))?;
 
         buf.clear();
         let written = sequence.handle_process_result(client_state, buf)?;
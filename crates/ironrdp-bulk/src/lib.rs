@@ -0,0 +1,67 @@
+#![doc = include_str!("../README.md")]
+#![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::std_instead_of_alloc)]
+#![warn(clippy::std_instead_of_core)]
+
+extern crate alloc;
+
+pub mod mppc;
+
+use core::fmt;
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// Flags describing how a packet was produced by the bulk compressor.
+    ///
+    /// These are the high bits of the compression flags, as found in the Share Data Header or, shifted by
+    /// 16 bits, in the Channel PDU Header (`CHANNEL_PACKET_COMPRESSED`, `CHANNEL_PACKET_AT_FRONT` and
+    /// `CHANNEL_PACKET_FLUSHED`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PacketFlags: u8 {
+        /// PACKET_COMPRESSED
+        const COMPRESSED = 0x20;
+        /// PACKET_AT_FRONT
+        const AT_FRONT = 0x40;
+        /// PACKET_FLUSHED
+        const FLUSHED = 0x80;
+    }
+}
+
+/// Error returned when decompressing malformed data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The data ends in the middle of a token.
+    UnexpectedEnd,
+    /// A copy token refers to data located before the start of the history buffer.
+    InvalidCopyOffset {
+        /// Offset of the copy token.
+        offset: usize,
+        /// Position in the history buffer where the copy token was found.
+        position: usize,
+    },
+    /// The encoding of a length-of-match is longer than allowed by the history buffer size.
+    InvalidMatchLength,
+    /// The decompressed data does not fit into the history buffer.
+    HistoryOverflow,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecompressError {}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "compressed data ends in the middle of a token"),
+            Self::InvalidCopyOffset { offset, position } => {
+                write!(
+                    f,
+                    "copy offset {offset} is out of the history buffer at position {position}"
+                )
+            }
+            Self::InvalidMatchLength => write!(f, "invalid length-of-match encoding"),
+            Self::HistoryOverflow => write!(f, "decompressed data does not fit into the history buffer"),
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! MPPC-based bulk compression, as specified in section 3.1.8.4 of MS-RDPBCGR
+//!
+//! The RDP 4.0 bulk compressor uses an 8 KB history buffer, and the RDP 5.0 bulk compressor a 64 KB one.
+//! Apart from the encoding of the copy-offsets, both share the same format.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{DecompressError, PacketFlags};
+
+/// Number of bits of the hash of the 3-byte sequences, used to look up the matches in the history buffer
+const HASH_BITS: u32 = 12;
+
+/// Size of the history buffer, as selected by the compression type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistorySize {
+    /// 8 KB history buffer, used by the RDP 4.0 bulk compressor (`PACKET_COMPR_TYPE_8K`).
+    K8,
+    /// 64 KB history buffer, used by the RDP 5.0 bulk compressor (`PACKET_COMPR_TYPE_64K`).
+    K64,
+}
+
+impl HistorySize {
+    /// Returns the size of the history buffer, in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            Self::K8 => 8 * 1024,
+            Self::K64 => 64 * 1024,
+        }
+    }
+
+    /// Returns the compression type (`PACKET_COMPR_TYPE_8K` or `PACKET_COMPR_TYPE_64K`) using this history size.
+    pub const fn compression_type(self) -> u8 {
+        match self {
+            Self::K8 => 0,
+            Self::K64 => 1,
+        }
+    }
+
+    /// Returns the history size used by the given compression type, if it is based on MPPC.
+    pub const fn from_compression_type(compression_type: u8) -> Option<Self> {
+        match compression_type {
+            0 => Some(Self::K8),
+            1 => Some(Self::K64),
+            _ => None,
+        }
+    }
+
+    /// Returns the longest length-of-match which can be encoded.
+    const fn max_match_length(self) -> usize {
+        self.size() - 1
+    }
+}
+
+/// MPPC bulk compressor
+///
+/// A compressor must be kept for the whole lifetime of the flow of data it compresses, since the receiver
+/// decompresses each packet according to the history built from the previous ones.
+#[derive(Debug, Clone)]
+pub struct MppcCompressor {
+    history_size: HistorySize,
+    history: Vec<u8>,
+    offset: usize,
+    /// Last position in the history buffer of the 3-byte sequences, indexed by their hash.
+    positions: Vec<u16>,
+}
+
+impl MppcCompressor {
+    pub fn new(history_size: HistorySize) -> Self {
+        Self {
+            history_size,
+            history: vec![0; history_size.size()],
+            offset: 0,
+            positions: vec![0; 1 << HASH_BITS],
+        }
+    }
+
+    pub fn history_size(&self) -> HistorySize {
+        self.history_size
+    }
+
+    /// Compresses `src`, appending the packet to be sent to `dst`.
+    ///
+    /// Returns the flags to be sent along the packet. When the compressed form is not smaller than `src`, the
+    /// packet holds `src` as is and the flags do not contain [`PacketFlags::COMPRESSED`].
+    pub fn compress(&mut self, src: &[u8], dst: &mut Vec<u8>) -> PacketFlags {
+        if src.is_empty() || src.len() > self.history.len() {
+            // The receiver leaves its history untouched for the uncompressed packets not flushing it.
+            dst.extend_from_slice(src);
+            return PacketFlags::empty();
+        }
+
+        let mut flags = PacketFlags::COMPRESSED;
+
+        if self.history.len() - self.offset < src.len() {
+            self.offset = 0;
+            flags |= PacketFlags::AT_FRONT;
+        }
+
+        let start = self.offset;
+        let end = start + src.len();
+        self.history[start..end].copy_from_slice(src);
+
+        let dst_start = dst.len();
+        let mut writer = BitWriter::new(dst);
+        let mut position = start;
+
+        while position < end {
+            match self.find_match(position, end) {
+                Some((match_offset, match_length)) => {
+                    write_copy_offset(&mut writer, self.history_size, match_offset);
+                    write_match_length(&mut writer, match_length);
+                    position += match_length;
+                }
+                None => {
+                    write_literal(&mut writer, self.history[position]);
+                    position += 1;
+                }
+            }
+        }
+
+        writer.finish();
+
+        if dst.len() - dst_start >= src.len() {
+            // The packet is sent uncompressed, and since the data was already added to the history of the
+            // compressor but is not added to the history of the receiver, both are reinitialized.
+            dst.truncate(dst_start);
+            dst.extend_from_slice(src);
+            self.flush();
+
+            return PacketFlags::FLUSHED;
+        }
+
+        self.offset = end;
+
+        flags
+    }
+
+    /// Looks for the longest match of the data at `position`, returning its copy-offset and length.
+    fn find_match(&mut self, position: usize, end: usize) -> Option<(usize, usize)> {
+        // The bytes following the data being compressed are left over from the previous packets.
+        if end - position < 3 {
+            return None;
+        }
+
+        let sequence = &self.history[position..position + 3];
+        let key = hash(sequence);
+
+        let candidate = usize::from(self.positions[key]);
+        self.positions[key] = u16::try_from(position).expect("history positions fit in 16 bits");
+
+        // Only the part of the history before `position` is known to the receiver.
+        if candidate >= position || self.history[candidate..candidate + 3] != *sequence {
+            return None;
+        }
+
+        // The match may overlap with the data being compressed, the receiver copying the bytes one by one.
+        let mut length = 3;
+        while position + length < end
+            && length < self.history_size.max_match_length()
+            && self.history[candidate + length] == self.history[position + length]
+        {
+            length += 1;
+        }
+
+        // Positions covered by the match are indexed too, for the next matches to be found further back.
+        for covered in position + 1..(position + length).min(end.saturating_sub(2)) {
+            let key = hash(&self.history[covered..covered + 3]);
+            self.positions[key] = u16::try_from(covered).expect("history positions fit in 16 bits");
+        }
+
+        Some((position - candidate, length))
+    }
+
+    /// Reinitializes the history buffer.
+    fn flush(&mut self) {
+        self.history.fill(0);
+        self.positions.fill(0);
+        self.offset = 0;
+    }
+}
+
+/// MPPC bulk decompressor
+#[derive(Debug, Clone)]
+pub struct MppcDecompressor {
+    history_size: HistorySize,
+    history: Vec<u8>,
+    offset: usize,
+}
+
+impl MppcDecompressor {
+    pub fn new(history_size: HistorySize) -> Self {
+        Self {
+            history_size,
+            history: vec![0; history_size.size()],
+            offset: 0,
+        }
+    }
+
+    pub fn history_size(&self) -> HistorySize {
+        self.history_size
+    }
+
+    /// Decompresses the packet `src`, received along `flags`, appending the decompressed data to `dst`.
+    pub fn decompress(&mut self, src: &[u8], flags: PacketFlags, dst: &mut Vec<u8>) -> Result<(), DecompressError> {
+        if flags.contains(PacketFlags::AT_FRONT) {
+            self.offset = 0;
+        }
+
+        if flags.contains(PacketFlags::FLUSHED) {
+            self.history.fill(0);
+            self.offset = 0;
+        }
+
+        if !flags.contains(PacketFlags::COMPRESSED) {
+            dst.extend_from_slice(src);
+            return Ok(());
+        }
+
+        let start = self.offset;
+        let mut reader = BitReader::new(src);
+
+        // The last byte is padded with zero bits, which never form a whole token: the shortest one is 8 bits long.
+        while reader.remaining() >= 8 {
+            if reader.read_bit()? == 0 {
+                let literal = reader.read_bits(7)?;
+                self.push_literal(literal)?;
+            } else if reader.read_bit()? == 0 {
+                let literal = 0x80 | reader.read_bits(7)?;
+                self.push_literal(literal)?;
+            } else {
+                let copy_offset = read_copy_offset(&mut reader, self.history_size)?;
+                let length = read_match_length(&mut reader, self.history_size)?;
+                self.copy_match(copy_offset, length)?;
+            }
+        }
+
+        dst.extend_from_slice(&self.history[start..self.offset]);
+
+        Ok(())
+    }
+
+    fn push_literal(&mut self, literal: u32) -> Result<(), DecompressError> {
+        let slot = self
+            .history
+            .get_mut(self.offset)
+            .ok_or(DecompressError::HistoryOverflow)?;
+        *slot = literal.to_be_bytes()[3];
+        self.offset += 1;
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, copy_offset: usize, length: usize) -> Result<(), DecompressError> {
+        if copy_offset == 0 || copy_offset > self.offset {
+            return Err(DecompressError::InvalidCopyOffset {
+                offset: copy_offset,
+                position: self.offset,
+            });
+        }
+
+        if self.history.len() - self.offset < length {
+            return Err(DecompressError::HistoryOverflow);
+        }
+
+        // The source and the destination may overlap, in which case the bytes are repeated.
+        for position in self.offset..self.offset + length {
+            self.history[position] = self.history[position - copy_offset];
+        }
+
+        self.offset += length;
+
+        Ok(())
+    }
+}
+
+fn hash(sequence: &[u8]) -> usize {
+    let value = u32::from_le_bytes([sequence[0], sequence[1], sequence[2], 0]);
+    let hash = value.wrapping_mul(0x9E37_79B1) >> (u32::BITS - HASH_BITS);
+    usize::try_from(hash).expect("hash fits in usize")
+}
+
+fn write_literal(writer: &mut BitWriter<'_>, literal: u8) {
+    if literal < 0x80 {
+        // Encoded as is, the most significant bit being 0.
+        writer.write_bits(u32::from(literal), 8);
+    } else {
+        writer.write_bits(0b10, 2);
+        writer.write_bits(u32::from(literal & 0x7F), 7);
+    }
+}
+
+fn write_copy_offset(writer: &mut BitWriter<'_>, history_size: HistorySize, copy_offset: usize) {
+    let copy_offset = u32::try_from(copy_offset).expect("copy-offset fits in the history buffer");
+
+    match history_size {
+        HistorySize::K8 => match copy_offset {
+            0..=63 => {
+                writer.write_bits(0b1111, 4);
+                writer.write_bits(copy_offset, 6);
+            }
+            64..=319 => {
+                writer.write_bits(0b1110, 4);
+                writer.write_bits(copy_offset - 64, 8);
+            }
+            _ => {
+                writer.write_bits(0b110, 3);
+                writer.write_bits(copy_offset - 320, 13);
+            }
+        },
+        HistorySize::K64 => match copy_offset {
+            0..=63 => {
+                writer.write_bits(0b11111, 5);
+                writer.write_bits(copy_offset, 6);
+            }
+            64..=319 => {
+                writer.write_bits(0b11110, 5);
+                writer.write_bits(copy_offset - 64, 8);
+            }
+            320..=2367 => {
+                writer.write_bits(0b1110, 4);
+                writer.write_bits(copy_offset - 320, 11);
+            }
+            _ => {
+                writer.write_bits(0b110, 3);
+                writer.write_bits(copy_offset - 2368, 16);
+            }
+        },
+    }
+}
+
+fn read_copy_offset(reader: &mut BitReader<'_>, history_size: HistorySize) -> Result<usize, DecompressError> {
+    // The "11" prefix was already read.
+    let copy_offset = match history_size {
+        HistorySize::K8 => {
+            if reader.read_bit()? == 0 {
+                reader.read_bits(13)? + 320
+            } else if reader.read_bit()? == 0 {
+                reader.read_bits(8)? + 64
+            } else {
+                reader.read_bits(6)?
+            }
+        }
+        HistorySize::K64 => {
+            if reader.read_bit()? == 0 {
+                reader.read_bits(16)? + 2368
+            } else if reader.read_bit()? == 0 {
+                reader.read_bits(11)? + 320
+            } else if reader.read_bit()? == 0 {
+                reader.read_bits(8)? + 64
+            } else {
+                reader.read_bits(6)?
+            }
+        }
+    };
+
+    Ok(usize::try_from(copy_offset).expect("copy-offset fits in usize"))
+}
+
+/// Writes the length-of-match, which is at least 3.
+///
+/// Apart from 3, which is encoded as a single 0 bit, a length between 2^k and 2^(k+1) - 1 is encoded as
+/// k - 1 bits set to 1 followed by a 0 bit, and then the k lower bits of the length.
+fn write_match_length(writer: &mut BitWriter<'_>, length: usize) {
+    let length = u32::try_from(length).expect("length-of-match fits in the history buffer");
+
+    if length == 3 {
+        writer.write_bits(0, 1);
+    } else {
+        let bits = u32::BITS - 1 - length.leading_zeros();
+        writer.write_bits((1 << bits) - 2, bits);
+        writer.write_bits(length - (1 << bits), bits);
+    }
+}
+
+fn read_match_length(reader: &mut BitReader<'_>, history_size: HistorySize) -> Result<usize, DecompressError> {
+    // The longest encodable length-of-match is one byte shorter than the history buffer.
+    let max_ones = match history_size {
+        HistorySize::K8 => 11,
+        HistorySize::K64 => 14,
+    };
+
+    let mut ones = 0;
+    while reader.read_bit()? == 1 {
+        ones += 1;
+
+        if ones > max_ones {
+            return Err(DecompressError::InvalidMatchLength);
+        }
+    }
+
+    let length = if ones == 0 {
+        3
+    } else {
+        let bits = ones + 1;
+        (1 << bits) + reader.read_bits(bits)?
+    };
+
+    Ok(usize::try_from(length).expect("length-of-match fits in usize"))
+}
+
+/// Writes bits into a byte buffer, most significant bit first
+struct BitWriter<'a> {
+    dst: &'a mut Vec<u8>,
+    pending: u32,
+    pending_len: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(dst: &'a mut Vec<u8>) -> Self {
+        Self {
+            dst,
+            pending: 0,
+            pending_len: 0,
+        }
+    }
+
+    /// Writes the `count` lower bits of `value`, `count` being at most 16.
+    fn write_bits(&mut self, value: u32, count: u32) {
+        debug_assert!(count <= 16);
+
+        self.pending = (self.pending << count) | (value & ((1 << count) - 1));
+        self.pending_len += count;
+
+        while self.pending_len >= 8 {
+            self.pending_len -= 8;
+            self.dst.push((self.pending >> self.pending_len).to_be_bytes()[3]);
+        }
+
+        self.pending &= (1 << self.pending_len) - 1;
+    }
+
+    /// Writes the pending bits, padding the last byte with zero bits.
+    fn finish(self) {
+        if self.pending_len > 0 {
+            self.dst.push((self.pending << (8 - self.pending_len)).to_be_bytes()[3]);
+        }
+    }
+}
+
+/// Reads bits from a byte buffer, most significant bit first
+struct BitReader<'a> {
+    src: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(src: &'a [u8]) -> Self {
+        Self { src, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.src.len() * 8 - self.position
+    }
+
+    fn read_bit(&mut self) -> Result<u32, DecompressError> {
+        let byte = self.src.get(self.position / 8).ok_or(DecompressError::UnexpectedEnd)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+
+        Ok(u32::from(bit))
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32, DecompressError> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()?;
+        }
+
+        Ok(value)
+    }
+}
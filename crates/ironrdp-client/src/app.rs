@@ -5,6 +5,10 @@ use core::time::Duration;
 use std::sync::Arc;
 use std::time::Instant;
 
+use ironrdp::displaycontrol::client::{MonitorLayoutRequest, ResizeDebouncer};
+use ironrdp::graphics::scaling::{Scaler, ScalingMode};
+use ironrdp::input::Modifiers;
+use ironrdp::pdu::geometry::{InclusiveRectangle, Rectangle as _};
 use raw_window_handle::{DisplayHandle, HasDisplayHandle};
 use tokio::sync::mpsc;
 use winit::application::ApplicationHandler;
@@ -19,6 +23,9 @@ use crate::rdp::{RdpInputEvent, RdpOutputEvent};
 
 type WindowSurface = (Arc<Window>, softbuffer::Surface<DisplayHandle<'static>, Arc<Window>>);
 
+/// Key cycling the scaling mode, along with Ctrl and Alt.
+const SCALING_TOGGLE_KEY: ironrdp::input::Scancode = ironrdp::input::Scancode::from_u8(false, 0x1F);
+
 pub struct App {
     input_event_sender: mpsc::UnboundedSender<RdpInputEvent>,
     context: softbuffer::Context<DisplayHandle<'static>>,
@@ -26,14 +33,20 @@ pub struct App {
     buffer: Vec<u32>,
     buffer_size: (u16, u16),
     input_database: ironrdp::input::Database,
-    last_size: Option<PhysicalSize<u32>>,
-    resize_timeout: Option<Instant>,
+    resize_debouncer: ResizeDebouncer,
+    scaling: Option<ScalingMode>,
+    scaler: Option<Scaler>,
+    scaled_buffer: Vec<u32>,
+    /// Region of `buffer` updated since the last draw.
+    dirty_region: Option<InclusiveRectangle>,
 }
 
 impl App {
     pub fn new(
         event_loop: &EventLoop<RdpOutputEvent>,
         input_event_sender: &mpsc::UnboundedSender<RdpInputEvent>,
+        resize_debounce: Duration,
+        scaling: Option<ScalingMode>,
     ) -> anyhow::Result<Self> {
         // SAFETY: We drop the softbuffer context right before the event loop is stopped, thus making this safe.
         // FIXME: This is not a sufficient proof and the API is actually unsound as-is.
@@ -51,29 +64,36 @@ impl App {
             buffer: Vec::new(),
             buffer_size: (0, 0),
             input_database,
-            last_size: None,
-            resize_timeout: None,
+            resize_debouncer: ResizeDebouncer::new(resize_debounce),
+            scaling,
+            scaler: None,
+            scaled_buffer: Vec::new(),
+            dirty_region: None,
         })
     }
 
-    fn send_resize_event(&mut self) {
-        let Some(size) = self.last_size.take() else {
-            return;
-        };
-        let Some((window, _)) = self.window.as_mut() else {
-            return;
-        };
-        let scale_factor = (window.scale_factor() * 100.0) as u32;
+    fn request_resize(&mut self, size: PhysicalSize<u32>, scale_factor: f64) {
+        self.resize_debouncer.request(
+            Instant::now(),
+            MonitorLayoutRequest {
+                width: size.width,
+                height: size.height,
+                scale_factor: Some((scale_factor * 100.0) as u32),
+                // TODO: it should be possible to get the physical size here, however winit doesn't make it straightforward.
+                // FreeRDP does it based on DPI reading grabbed via [`SDL_GetDisplayDPI`](https://wiki.libsdl.org/SDL2/SDL_GetDisplayDPI):
+                // https://github.com/FreeRDP/FreeRDP/blob/ba8cf8cf2158018fb7abbedb51ab245f369be813/client/SDL/sdl_monitor.cpp#L250-L262
+                // See also: https://github.com/rust-windowing/winit/issues/826
+                physical_dims: None,
+            },
+        );
+    }
 
+    fn send_resize_event(&mut self, request: MonitorLayoutRequest) {
         let _ = self.input_event_sender.send(RdpInputEvent::Resize {
-            width: u16::try_from(size.width).unwrap(),
-            height: u16::try_from(size.height).unwrap(),
-            scale_factor,
-            // TODO: it should be possible to get the physical size here, however winit doesn't make it straightforward.
-            // FreeRDP does it based on DPI reading grabbed via [`SDL_GetDisplayDPI`](https://wiki.libsdl.org/SDL2/SDL_GetDisplayDPI):
-            // https://github.com/FreeRDP/FreeRDP/blob/ba8cf8cf2158018fb7abbedb51ab245f369be813/client/SDL/sdl_monitor.cpp#L250-L262
-            // See also: https://github.com/rust-windowing/winit/issues/826
-            physical_size: None,
+            width: u16::try_from(request.width).unwrap_or(u16::MAX),
+            height: u16::try_from(request.height).unwrap_or(u16::MAX),
+            scale_factor: request.scale_factor.unwrap_or(100),
+            physical_size: request.physical_dims,
         });
     }
 
@@ -81,25 +101,91 @@ impl App {
         if self.buffer.is_empty() {
             return;
         }
-        let Some((_, surface)) = self.window.as_mut() else {
+        let Some((window, surface)) = self.window.as_mut() else {
             return;
         };
+
+        let dirty_region = self.dirty_region.take();
+        let window_size = window.inner_size();
+        let window_size = (
+            u16::try_from(window_size.width).unwrap_or(u16::MAX),
+            u16::try_from(window_size.height).unwrap_or(u16::MAX),
+        );
+
+        let (surface_size, pixels) = match self.scaling {
+            Some(mode) if window_size != self.buffer_size && window_size.0 != 0 && window_size.1 != 0 => {
+                let is_stale = self.scaler.as_ref().map_or(true, |scaler| {
+                    scaler.mode() != mode
+                        || scaler.source_size() != self.buffer_size
+                        || scaler.destination_size() != window_size
+                });
+
+                // The whole image is scaled again when the sizes or the mode change, and only the dirty region
+                // otherwise.
+                let region = if is_stale {
+                    self.scaler = Some(Scaler::new(mode, self.buffer_size, window_size));
+                    self.scaled_buffer = vec![0; usize::from(window_size.0) * usize::from(window_size.1)];
+
+                    Some(InclusiveRectangle {
+                        left: 0,
+                        top: 0,
+                        right: self.buffer_size.0 - 1,
+                        bottom: self.buffer_size.1 - 1,
+                    })
+                } else {
+                    dirty_region
+                };
+
+                if let (Some(scaler), Some(region)) = (self.scaler.as_mut(), region) {
+                    scaler.scale(&self.buffer, &mut self.scaled_buffer, &region);
+                }
+
+                (window_size, self.scaled_buffer.as_slice())
+            }
+            _ => {
+                // The dirty regions are not tracked by the scaled image in the meantime.
+                self.scaler = None;
+
+                (self.buffer_size, self.buffer.as_slice())
+            }
+        };
+
+        surface
+            .resize(
+                NonZeroU32::new(u32::from(surface_size.0)).unwrap(),
+                NonZeroU32::new(u32::from(surface_size.1)).unwrap(),
+            )
+            .expect("surface resize");
+
         let mut sb_buffer = surface.buffer_mut().expect("surface buffer");
-        sb_buffer.copy_from_slice(self.buffer.as_slice());
+        sb_buffer.copy_from_slice(pixels);
         sb_buffer.present().expect("buffer present");
     }
+
+    fn cycle_scaling(&mut self) {
+        self.scaling = match self.scaling {
+            None => Some(ScalingMode::Nearest),
+            Some(ScalingMode::Nearest) => Some(ScalingMode::Bilinear),
+            Some(ScalingMode::Bilinear) => Some(ScalingMode::Lanczos3),
+            Some(ScalingMode::Lanczos3) => None,
+        };
+
+        info!(scaling = ?self.scaling, "Scaling mode changed");
+    }
 }
 
 impl ApplicationHandler<RdpOutputEvent> for App {
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        if let Some(timeout) = self.resize_timeout {
-            if let Some(timeout) = timeout.checked_duration_since(Instant::now()) {
-                event_loop.set_control_flow(ControlFlow::wait_duration(timeout));
-            } else {
-                self.send_resize_event();
-                self.resize_timeout = None;
-            }
+        if let Some(request) = self.resize_debouncer.poll(Instant::now()) {
+            self.send_resize_event(request);
         }
+
+        let control_flow = match self.resize_debouncer.deadline() {
+            Some(deadline) => ControlFlow::WaitUntil(deadline),
+            None => ControlFlow::Wait,
+        };
+
+        event_loop.set_control_flow(control_flow);
     }
 
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
@@ -127,8 +213,18 @@ impl ApplicationHandler<RdpOutputEvent> for App {
 
         match event {
             WindowEvent::Resized(size) => {
-                self.last_size = Some(size);
-                self.resize_timeout = Some(Instant::now() + Duration::from_secs(1));
+                // The image is scaled to the new size until the server resizes the desktop, if ever.
+                if self.scaling.is_some() {
+                    window.request_redraw();
+                }
+
+                let scale_factor = window.scale_factor();
+                self.request_resize(size, scale_factor);
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // The window size changes as well, but this is notified by a following `Resized` event.
+                let size = window.inner_size();
+                self.request_resize(size, scale_factor);
             }
             WindowEvent::CloseRequested => {
                 if self.input_event_sender.send(RdpInputEvent::Close).is_err() {
@@ -161,6 +257,16 @@ impl ApplicationHandler<RdpOutputEvent> for App {
                 if let Some(scancode) = event.physical_key.to_scancode() {
                     let scancode = ironrdp::input::Scancode::from_u16(u16::try_from(scancode).unwrap());
 
+                    // The toggle is not sent to the server, and its release is ignored by the input database.
+                    if event.state == event::ElementState::Pressed
+                        && scancode == SCALING_TOGGLE_KEY
+                        && Modifiers::from_database(&self.input_database) == Modifiers::CTRL | Modifiers::ALT
+                    {
+                        window.request_redraw();
+                        self.cycle_scaling();
+                        return;
+                    }
+
                     let operation = match event.state {
                         event::ElementState::Pressed => ironrdp::input::Operation::KeyPressed(scancode),
                         event::ElementState::Released => ironrdp::input::Operation::KeyReleased(scancode),
@@ -299,7 +405,6 @@ impl ApplicationHandler<RdpOutputEvent> for App {
             | WindowEvent::TouchpadPressure { .. }
             | WindowEvent::AxisMotion { .. }
             | WindowEvent::Touch(_)
-            | WindowEvent::ScaleFactorChanged { .. }
             | WindowEvent::ThemeChanged(_)
             | WindowEvent::Occluded(_) => {
                 // ignore
@@ -308,21 +413,24 @@ impl ApplicationHandler<RdpOutputEvent> for App {
     }
 
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: RdpOutputEvent) {
-        let Some((window, surface)) = self.window.as_mut() else {
+        let Some((window, _)) = self.window.as_mut() else {
             return;
         };
         match event {
-            RdpOutputEvent::Image { buffer, width, height } => {
+            RdpOutputEvent::Image {
+                buffer,
+                width,
+                height,
+                region,
+            } => {
                 trace!(width = ?width, height = ?height, "Received image with size");
                 trace!(window_physical_size = ?window.inner_size(), "Drawing image to the window with size");
                 self.buffer_size = (width, height);
                 self.buffer = buffer;
-                surface
-                    .resize(
-                        NonZeroU32::new(u32::from(width)).unwrap(),
-                        NonZeroU32::new(u32::from(height)).unwrap(),
-                    )
-                    .expect("surface resize");
+                self.dirty_region = Some(match self.dirty_region.take() {
+                    Some(dirty_region) => dirty_region.union(&region),
+                    None => region,
+                });
 
                 window.request_redraw();
             }
@@ -0,0 +1,546 @@
+//! Options of the client, layered from the configuration file, the environment and the command line.
+//!
+//! The configuration file is a TOML document defining the same options as the command line, using the same names
+//! (e.g.: `clipboard-policy = "remote-to-host"`). Named connection profiles are defined in `[profiles.<NAME>]` tables,
+//! whose options override the ones at the top level of the file when the profile is selected:
+//!
+//! ```toml
+//! username = "alice"
+//! scaling = "bilinear"
+//!
+//! [bitmap]
+//! color-depth = 16
+//!
+//! [profiles.work]
+//! destination = "rdp.example.com"
+//! domain = "EXAMPLE"
+//! ```
+//!
+//! Each layer only defines some of the options, see [`ClientConfig::overlay`]. The defaults are applied once all the
+//! layers are merged.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Clipboard, ClipboardType, Destination, KeyboardType, Scaling};
+
+/// Value replacing the secrets in the printed configuration.
+const REDACTED: &str = "<redacted>";
+
+pub const DEFAULT_KEYBOARD_TYPE: KeyboardType = KeyboardType::IbmEnhanced;
+pub const DEFAULT_KEYBOARD_FUNCTIONAL_KEYS_COUNT: u32 = 12;
+pub const DEFAULT_COLOR_DEPTH: u32 = 32;
+pub const DEFAULT_CLIPBOARD_POLICY: Clipboard = Clipboard::Bidirectional;
+pub const DEFAULT_RESIZE_DEBOUNCE_MS: u64 = 300;
+pub const DEFAULT_AUDIO_LATENCY_MS: u64 = 60;
+pub const DEFAULT_HEADLESS_FRAMES: usize = 1;
+pub const DEFAULT_HEADLESS_TIMEOUT_MS: u64 = 30_000;
+
+/// Options of the client, `None` meaning that the option is not defined by the layer
+///
+/// The flags of the command line are only ever set to `Some(true)`, since they can't be turned off.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ClientConfig {
+    pub log_file: Option<String>,
+    pub destination: Option<Destination>,
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    pub password: Option<String>,
+    pub use_keyring: Option<bool>,
+    pub save_password: Option<bool>,
+    pub keyboard_type: Option<KeyboardType>,
+    pub keyboard_subtype: Option<u32>,
+    pub keyboard_functional_keys_count: Option<u32>,
+    pub ime_file_name: Option<String>,
+    pub dig_product_id: Option<String>,
+    pub no_server_pointer: Option<bool>,
+    pub capabilities: Option<u32>,
+    pub autologon: Option<bool>,
+    pub no_tls: Option<bool>,
+    pub no_credssp: Option<bool>,
+    pub restricted_admin: Option<bool>,
+    pub console: Option<bool>,
+    pub session_id: Option<u32>,
+    pub clipboard_type: Option<ClipboardType>,
+    pub clipboard_policy: Option<Clipboard>,
+    pub clipboard_primary: Option<bool>,
+    pub drive_commands: Option<bool>,
+    pub resize_debounce_ms: Option<u64>,
+    pub scaling: Option<Scaling>,
+    pub stats_interval_ms: Option<u64>,
+    pub no_audio: Option<bool>,
+    pub audio_latency_ms: Option<u64>,
+    pub heartbeat_warning_count: Option<u8>,
+    pub heartbeat_reconnect_count: Option<u8>,
+    pub remote_app: Option<String>,
+    pub remote_app_working_dir: Option<String>,
+    pub remote_app_args: Option<String>,
+    pub headless: Option<bool>,
+    pub headless_frames: Option<usize>,
+    pub headless_timeout_ms: Option<u64>,
+    pub script: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    /// Bitmap codec options, in the `[bitmap]` table.
+    #[serde(default, skip_serializing_if = "BitmapOptions::is_empty")]
+    pub bitmap: BitmapOptions,
+    /// Connection profiles, only defined at the top level of the configuration file.
+    #[serde(default, skip_serializing)]
+    pub(crate) profiles: BTreeMap<String, ClientConfig>,
+}
+
+/// Bitmap codec options
+///
+/// The bitmap codec is configured when one of the options is defined, the server defaults being used otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BitmapOptions {
+    /// Color depth in bits per pixel, 16 or 32.
+    pub color_depth: Option<u32>,
+    /// Allows the server to reduce the color fidelity of the bitmaps, enabled by default.
+    pub lossy_compression: Option<bool>,
+}
+
+impl BitmapOptions {
+    pub fn is_empty(&self) -> bool {
+        self.color_depth.is_none() && self.lossy_compression.is_none()
+    }
+
+    /// Returns the options defined by `self`, replaced by the ones defined by `other`.
+    #[must_use]
+    pub fn overlay(self, other: Self) -> Self {
+        Self {
+            color_depth: other.color_depth.or(self.color_depth),
+            lossy_compression: other.lossy_compression.or(self.lossy_compression),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Reads the configuration file at `path`, selecting `profile` when set.
+    ///
+    /// The errors name the file, and the key or the profile at fault.
+    pub fn load(path: &Path, profile: Option<&str>) -> anyhow::Result<Self> {
+        let input = std::fs::read_to_string(path).with_context(|| format!("unable to read {}", path.display()))?;
+
+        Self::from_toml(&input, profile).with_context(|| format!("invalid configuration file {}", path.display()))
+    }
+
+    /// Reads the configuration file at the default location, if it exists.
+    ///
+    /// See [`default_path`].
+    pub fn load_default(profile: Option<&str>) -> anyhow::Result<Self> {
+        match default_path().filter(|path| path.is_file()) {
+            Some(path) => Self::load(&path, profile),
+            None => match profile {
+                Some(profile) => anyhow::bail!("profile `{profile}` selected without configuration file"),
+                None => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Parses a configuration file, selecting `profile` when set.
+    pub fn from_toml(input: &str, profile: Option<&str>) -> anyhow::Result<Self> {
+        let mut config: Self = toml::from_str(input)?;
+        let profiles = core::mem::take(&mut config.profiles);
+
+        for (name, profile) in &profiles {
+            if !profile.profiles.is_empty() {
+                anyhow::bail!("`profiles.{name}.profiles`: profiles can't be nested");
+            }
+        }
+
+        match profile {
+            Some(name) => {
+                let Some(profile) = profiles.get(name) else {
+                    let available = profiles.keys().map(String::as_str).collect::<Vec<_>>().join(", ");
+                    anyhow::bail!("profile `{name}` not found (available profiles: {available})");
+                };
+
+                let config = config.overlay(profile.clone());
+                config.validate().with_context(|| format!("profile `{name}`"))?;
+
+                Ok(config)
+            }
+            None => {
+                config.validate()?;
+
+                Ok(config)
+            }
+        }
+    }
+
+    /// Returns the options defined by `self`, replaced by the ones defined by `other`.
+    ///
+    /// The nested tables are merged option by option, e.g.: a layer defining only `bitmap.color-depth` keeps the
+    /// `bitmap.lossy-compression` of the layers below.
+    #[must_use]
+    pub fn overlay(self, other: Self) -> Self {
+        Self {
+            log_file: other.log_file.or(self.log_file),
+            destination: other.destination.or(self.destination),
+            username: other.username.or(self.username),
+            domain: other.domain.or(self.domain),
+            password: other.password.or(self.password),
+            use_keyring: other.use_keyring.or(self.use_keyring),
+            save_password: other.save_password.or(self.save_password),
+            keyboard_type: other.keyboard_type.or(self.keyboard_type),
+            keyboard_subtype: other.keyboard_subtype.or(self.keyboard_subtype),
+            keyboard_functional_keys_count: other
+                .keyboard_functional_keys_count
+                .or(self.keyboard_functional_keys_count),
+            ime_file_name: other.ime_file_name.or(self.ime_file_name),
+            dig_product_id: other.dig_product_id.or(self.dig_product_id),
+            no_server_pointer: other.no_server_pointer.or(self.no_server_pointer),
+            capabilities: other.capabilities.or(self.capabilities),
+            autologon: other.autologon.or(self.autologon),
+            no_tls: other.no_tls.or(self.no_tls),
+            no_credssp: other.no_credssp.or(self.no_credssp),
+            restricted_admin: other.restricted_admin.or(self.restricted_admin),
+            console: other.console.or(self.console),
+            session_id: other.session_id.or(self.session_id),
+            clipboard_type: other.clipboard_type.or(self.clipboard_type),
+            clipboard_policy: other.clipboard_policy.or(self.clipboard_policy),
+            clipboard_primary: other.clipboard_primary.or(self.clipboard_primary),
+            drive_commands: other.drive_commands.or(self.drive_commands),
+            resize_debounce_ms: other.resize_debounce_ms.or(self.resize_debounce_ms),
+            scaling: other.scaling.or(self.scaling),
+            stats_interval_ms: other.stats_interval_ms.or(self.stats_interval_ms),
+            no_audio: other.no_audio.or(self.no_audio),
+            audio_latency_ms: other.audio_latency_ms.or(self.audio_latency_ms),
+            heartbeat_warning_count: other.heartbeat_warning_count.or(self.heartbeat_warning_count),
+            heartbeat_reconnect_count: other.heartbeat_reconnect_count.or(self.heartbeat_reconnect_count),
+            remote_app: other.remote_app.or(self.remote_app),
+            remote_app_working_dir: other.remote_app_working_dir.or(self.remote_app_working_dir),
+            remote_app_args: other.remote_app_args.or(self.remote_app_args),
+            headless: other.headless.or(self.headless),
+            headless_frames: other.headless_frames.or(self.headless_frames),
+            headless_timeout_ms: other.headless_timeout_ms.or(self.headless_timeout_ms),
+            script: other.script.or(self.script),
+            screenshot: other.screenshot.or(self.screenshot),
+            bitmap: self.bitmap.overlay(other.bitmap),
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    /// Checks the values and the combinations of options, naming the keys at fault.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(color_depth) = self.bitmap.color_depth {
+            if color_depth != 16 && color_depth != 32 {
+                anyhow::bail!("`bitmap.color-depth`: only 16 and 32 bit color depths are supported, got {color_depth}");
+            }
+        }
+
+        let is_set = |flag: Option<bool>| flag == Some(true);
+
+        if is_set(self.restricted_admin) && is_set(self.no_credssp) {
+            anyhow::bail!("`restricted-admin` can't be used with `no-credssp`");
+        }
+
+        if is_set(self.console) && self.session_id.is_some() {
+            anyhow::bail!("`console` can't be used with `session-id`");
+        }
+
+        if self.remote_app.is_none() {
+            if self.remote_app_working_dir.is_some() {
+                anyhow::bail!("`remote-app-working-dir` requires `remote-app`");
+            }
+
+            if self.remote_app_args.is_some() {
+                anyhow::bail!("`remote-app-args` requires `remote-app`");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configuration with the defaults applied to the options which are not defined.
+    #[must_use]
+    pub fn with_defaults(self) -> Self {
+        let bitmap = if self.bitmap.is_empty() {
+            self.bitmap
+        } else {
+            BitmapOptions {
+                color_depth: Some(self.bitmap.color_depth.unwrap_or(DEFAULT_COLOR_DEPTH)),
+                lossy_compression: Some(self.bitmap.lossy_compression.unwrap_or(true)),
+            }
+        };
+
+        let headless = self.headless.unwrap_or(false);
+
+        Self {
+            use_keyring: Some(self.use_keyring.unwrap_or(false)),
+            save_password: Some(self.save_password.unwrap_or(false)),
+            keyboard_type: Some(self.keyboard_type.unwrap_or(DEFAULT_KEYBOARD_TYPE)),
+            keyboard_subtype: Some(self.keyboard_subtype.unwrap_or(0)),
+            keyboard_functional_keys_count: Some(
+                self.keyboard_functional_keys_count
+                    .unwrap_or(DEFAULT_KEYBOARD_FUNCTIONAL_KEYS_COUNT),
+            ),
+            ime_file_name: Some(self.ime_file_name.unwrap_or_default()),
+            dig_product_id: Some(self.dig_product_id.unwrap_or_default()),
+            no_server_pointer: Some(self.no_server_pointer.unwrap_or(false)),
+            capabilities: Some(self.capabilities.unwrap_or(0)),
+            autologon: Some(self.autologon.unwrap_or(false)),
+            no_tls: Some(self.no_tls.unwrap_or(false)),
+            no_credssp: Some(self.no_credssp.unwrap_or(false)),
+            restricted_admin: Some(self.restricted_admin.unwrap_or(false)),
+            console: Some(self.console.unwrap_or(false)),
+            clipboard_type: Some(self.clipboard_type.unwrap_or(ClipboardType::Default)),
+            clipboard_policy: Some(self.clipboard_policy.unwrap_or(DEFAULT_CLIPBOARD_POLICY)),
+            clipboard_primary: Some(self.clipboard_primary.unwrap_or(false)),
+            drive_commands: Some(self.drive_commands.unwrap_or(false)),
+            resize_debounce_ms: Some(self.resize_debounce_ms.unwrap_or(DEFAULT_RESIZE_DEBOUNCE_MS)),
+            no_audio: Some(self.no_audio.unwrap_or(false)),
+            audio_latency_ms: Some(self.audio_latency_ms.unwrap_or(DEFAULT_AUDIO_LATENCY_MS)),
+            headless: Some(headless),
+            // The headless options are only relevant in headless mode.
+            headless_frames: headless.then(|| self.headless_frames.unwrap_or(DEFAULT_HEADLESS_FRAMES)),
+            headless_timeout_ms: headless.then(|| self.headless_timeout_ms.unwrap_or(DEFAULT_HEADLESS_TIMEOUT_MS)),
+            bitmap,
+            ..self
+        }
+    }
+
+    /// Returns the configuration with the secrets replaced, suitable for printing.
+    #[must_use]
+    pub fn redacted(self) -> Self {
+        Self {
+            password: self.password.map(|_| REDACTED.to_owned()),
+            ..self
+        }
+    }
+
+    /// Returns the configuration as a TOML document, which can be used as configuration file.
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        toml::to_string(self).context("unable to serialize the configuration")
+    }
+}
+
+/// Returns the path of the default configuration file, `~/.config/ironrdp/config.toml`
+///
+/// `$XDG_CONFIG_HOME` replaces `~/.config` when set.
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+
+    Some(config_dir.join("ironrdp").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG_FILE: &str = r#"
+        username = "alice"
+        password = "hunter2"
+        clipboard-policy = "remote-to-host"
+        resize-debounce-ms = 500
+
+        [bitmap]
+        color-depth = 16
+        lossy-compression = false
+
+        [profiles.work]
+        destination = "rdp.example.com:3390"
+        domain = "EXAMPLE"
+
+        [profiles.work.bitmap]
+        color-depth = 32
+
+        [profiles.lab]
+        username = "admin"
+        no-audio = true
+    "#;
+
+    #[test]
+    fn top_level_options_are_used_without_profile() {
+        let config = ClientConfig::from_toml(CONFIG_FILE, None).unwrap();
+
+        assert_eq!(config.username.as_deref(), Some("alice"));
+        assert_eq!(config.destination, None);
+        assert_eq!(config.clipboard_policy, Some(Clipboard::RemoteToHost));
+        assert_eq!(
+            config.bitmap,
+            BitmapOptions {
+                color_depth: Some(16),
+                lossy_compression: Some(false),
+            }
+        );
+        assert!(config.profiles.is_empty());
+    }
+
+    #[test]
+    fn profile_overrides_top_level_options() {
+        let config = ClientConfig::from_toml(CONFIG_FILE, Some("work")).unwrap();
+
+        assert_eq!(config.username.as_deref(), Some("alice"));
+        assert_eq!(config.domain.as_deref(), Some("EXAMPLE"));
+        assert_eq!(
+            config.destination,
+            Some(Destination::new("rdp.example.com:3390").unwrap())
+        );
+        assert_eq!(config.resize_debounce_ms, Some(500));
+        // Only the color depth is overridden by the profile.
+        assert_eq!(
+            config.bitmap,
+            BitmapOptions {
+                color_depth: Some(32),
+                lossy_compression: Some(false),
+            }
+        );
+
+        let config = ClientConfig::from_toml(CONFIG_FILE, Some("lab")).unwrap();
+
+        assert_eq!(config.username.as_deref(), Some("admin"));
+        assert_eq!(config.no_audio, Some(true));
+        assert_eq!(config.bitmap.color_depth, Some(16));
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let file = ClientConfig::from_toml(CONFIG_FILE, Some("work")).unwrap();
+        let env = ClientConfig {
+            username: Some("bob".to_owned()),
+            scaling: Some(Scaling::Nearest),
+            bitmap: BitmapOptions {
+                color_depth: None,
+                lossy_compression: Some(true),
+            },
+            ..ClientConfig::default()
+        };
+        let cli = ClientConfig {
+            scaling: Some(Scaling::Lanczos3),
+            no_tls: Some(true),
+            bitmap: BitmapOptions {
+                color_depth: Some(16),
+                lossy_compression: None,
+            },
+            ..ClientConfig::default()
+        };
+
+        let config = file.overlay(env).overlay(cli);
+
+        assert_eq!(config.username.as_deref(), Some("bob"));
+        assert_eq!(config.domain.as_deref(), Some("EXAMPLE"));
+        assert_eq!(config.scaling, Some(Scaling::Lanczos3));
+        assert_eq!(config.no_tls, Some(true));
+        assert_eq!(config.resize_debounce_ms, Some(500));
+        assert_eq!(
+            config.bitmap,
+            BitmapOptions {
+                color_depth: Some(16),
+                lossy_compression: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn undefined_options_are_kept() {
+        let file = ClientConfig::from_toml(CONFIG_FILE, None).unwrap();
+
+        assert_eq!(file.clone().overlay(ClientConfig::default()), file);
+        assert_eq!(ClientConfig::default().overlay(file.clone()), file);
+    }
+
+    #[test]
+    fn password_is_redacted() {
+        let config = ClientConfig::from_toml(CONFIG_FILE, Some("work")).unwrap().redacted();
+
+        assert_eq!(config.password.as_deref(), Some(REDACTED));
+        assert_eq!(config.username.as_deref(), Some("alice"));
+
+        let printed = config.with_defaults().to_toml().unwrap();
+        assert!(!printed.contains("hunter2"), "{printed}");
+        assert!(printed.contains(r#"password = "<redacted>""#), "{printed}");
+        assert!(printed.contains(r#"destination = "rdp.example.com:3390""#), "{printed}");
+
+        let config = ClientConfig::default().redacted();
+        assert_eq!(config.password, None);
+    }
+
+    #[test]
+    fn printed_configuration_can_be_read_back() {
+        let config = ClientConfig::from_toml(CONFIG_FILE, Some("work"))
+            .unwrap()
+            .with_defaults();
+
+        let printed = config.to_toml().unwrap();
+
+        assert_eq!(ClientConfig::from_toml(&printed, None).unwrap(), config);
+    }
+
+    #[test]
+    fn defaults_fill_the_undefined_options() {
+        let config = ClientConfig::default().with_defaults();
+
+        assert_eq!(config.keyboard_type, Some(DEFAULT_KEYBOARD_TYPE));
+        assert_eq!(config.resize_debounce_ms, Some(DEFAULT_RESIZE_DEBOUNCE_MS));
+        assert_eq!(config.no_tls, Some(false));
+        assert_eq!(config.headless_frames, None);
+        assert!(config.bitmap.is_empty());
+
+        let config = ClientConfig {
+            headless: Some(true),
+            bitmap: BitmapOptions {
+                color_depth: Some(16),
+                lossy_compression: None,
+            },
+            ..ClientConfig::default()
+        }
+        .with_defaults();
+
+        assert_eq!(config.headless_frames, Some(DEFAULT_HEADLESS_FRAMES));
+        assert_eq!(config.bitmap.lossy_compression, Some(true));
+    }
+
+    #[test]
+    fn errors_name_the_key() {
+        let error = ClientConfig::from_toml("colour-depth = 16", None).unwrap_err();
+        assert!(
+            format!("{error:#}").contains("unknown field `colour-depth`"),
+            "{error:#}"
+        );
+
+        let error = ClientConfig::from_toml("[bitmap]\ncolor-depth = \"high\"", None).unwrap_err();
+        assert!(format!("{error:#}").contains("color-depth"), "{error:#}");
+
+        let error = ClientConfig::from_toml("[bitmap]\ncolor-depth = 24", None).unwrap_err();
+        assert!(format!("{error:#}").contains("`bitmap.color-depth`"), "{error:#}");
+
+        let error = ClientConfig::from_toml("[profiles.work]\nclipboard-policy = \"both\"", None).unwrap_err();
+        assert!(format!("{error:#}").contains("clipboard-policy"), "{error:#}");
+
+        let error = ClientConfig::from_toml(
+            "[profiles.work]\nno-credssp = true\nrestricted-admin = true",
+            Some("work"),
+        )
+        .unwrap_err();
+        assert!(format!("{error:#}").contains("profile `work`"), "{error:#}");
+
+        let error = ClientConfig::from_toml("console = true\nsession-id = 3", None).unwrap_err();
+        assert!(format!("{error:#}").contains("`console`"), "{error:#}");
+
+        let error = ClientConfig::from_toml("[profiles.work.profiles.home]", None).unwrap_err();
+        assert!(format!("{error:#}").contains("`profiles.work.profiles`"), "{error:#}");
+    }
+
+    #[test]
+    fn unknown_profile_is_rejected() {
+        let error = ClientConfig::from_toml(CONFIG_FILE, Some("home")).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "profile `home` not found (available profiles: lab, work)"
+        );
+    }
+}
@@ -1,14 +1,26 @@
 use anyhow::Context as _;
 use clap::clap_derive::ValueEnum;
-use clap::Parser;
+use clap::{CommandFactory as _, FromArgMatches as _, Parser};
+use core::fmt;
 use core::num::ParseIntError;
 use core::str::FromStr;
+use core::time::Duration;
+use ironrdp::cliprdr::ClipboardPolicy;
 use ironrdp::connector::{self, Credentials};
+use ironrdp::graphics::scaling::ScalingMode;
 use ironrdp::pdu::rdp::capability_sets::MajorPlatformType;
 use ironrdp::pdu::rdp::client_info::PerformanceFlags;
+use ironrdp::session::heartbeat::HeartbeatPolicy;
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
 use std::io;
+use std::path::PathBuf;
 use tap::prelude::*;
 
+use crate::client_config::{self, BitmapOptions, ClientConfig};
+use crate::credentials::{OsCredentialStore, PasswordSource};
+use crate::headless::{self, HeadlessConfig};
+
 const DEFAULT_WIDTH: u16 = 1920;
 const DEFAULT_HEIGHT: u16 = 1080;
 
@@ -18,19 +30,42 @@ pub struct Config {
     pub destination: Destination,
     pub connector: connector::Config,
     pub clipboard_type: ClipboardType,
+    /// Directions in which the clipboard is shared with the server.
+    pub clipboard_policy: ClipboardPolicy,
+    /// Also sets the PRIMARY selection when the clipboard of the server changes (X11 and Wayland).
+    pub clipboard_mirror_primary: bool,
+    pub drive_commands: bool,
+    /// Delay without window resize before the new size is sent to the server.
+    pub resize_debounce: Duration,
+    /// Runs without window when set.
+    pub headless: Option<HeadlessConfig>,
+    /// Stores the password in the OS credential store once connected.
+    pub save_password: bool,
+    /// Filter used to scale the remote desktop to the window size, when set.
+    pub scaling: Option<ScalingMode>,
+    /// Interval at which the traffic of the virtual channels is logged, when set.
+    pub stats_interval: Option<Duration>,
+    /// Latency targeted by the audio playback, or `None` if the audio is not played.
+    pub audio_latency: Option<Duration>,
+    /// Thresholds of missed heartbeats overriding the ones sent by the server.
+    pub heartbeat_policy: HeartbeatPolicy,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ClipboardType {
     Default,
     Stub,
     #[cfg(windows)]
     Windows,
+    #[cfg(target_os = "linux")]
+    Linux,
     None,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum KeyboardType {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyboardType {
     IbmPcXt,
     OlivettiIco,
     IbmPcAt,
@@ -54,6 +89,50 @@ impl KeyboardType {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaling {
+    Nearest,
+    Bilinear,
+    Lanczos3,
+}
+
+impl Scaling {
+    fn parse(scaling: Scaling) -> ScalingMode {
+        match scaling {
+            Scaling::Nearest => ScalingMode::Nearest,
+            Scaling::Bilinear => ScalingMode::Bilinear,
+            Scaling::Lanczos3 => ScalingMode::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Clipboard {
+    Disabled,
+    HostToRemote,
+    RemoteToHost,
+    Bidirectional,
+}
+
+impl Clipboard {
+    fn parse(clipboard: Clipboard) -> ClipboardPolicy {
+        match clipboard {
+            Clipboard::Disabled => ClipboardPolicy::Disabled,
+            Clipboard::HostToRemote => ClipboardPolicy::HostToRemote,
+            Clipboard::RemoteToHost => ClipboardPolicy::RemoteToHost,
+            Clipboard::Bidirectional => ClipboardPolicy::Bidirectional,
+        }
+    }
+}
+
+/// Parses a clipboard policy, as accepted by the `--clipboard-policy` argument.
+pub fn parse_clipboard_policy(input: &str) -> anyhow::Result<ClipboardPolicy> {
+    let clipboard = <Clipboard as clap::ValueEnum>::from_str(input, true).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(Clipboard::parse(clipboard))
+}
+
 fn parse_hex(input: &str) -> Result<u32, ParseIntError> {
     if input.starts_with("0x") {
         u32::from_str_radix(input.get(2..).unwrap_or(""), 16)
@@ -99,6 +178,15 @@ impl Destination {
         }
     }
 
+    /// Returns the destination on the same port of another server, e.g.: the target of a server redirection.
+    #[must_use]
+    pub fn with_name(&self, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            port: self.port,
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -124,6 +212,29 @@ impl FromStr for Destination {
     }
 }
 
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.contains(':') {
+            write!(f, "[{}]:{}", self.name, self.port)
+        } else {
+            write!(f, "{}:{}", self.name, self.port)
+        }
+    }
+}
+
+impl Serialize for Destination {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Destination {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let addr = String::deserialize(deserializer)?;
+        Self::new(addr).map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<Destination> for connector::ServerName {
     fn from(value: Destination) -> Self {
         Self::new(value.name)
@@ -141,44 +252,73 @@ impl From<&Destination> for connector::ServerName {
 #[clap(author = "Devolutions", about = "Devolutions-IronRDP client")]
 #[clap(version, long_about = None)]
 struct Args {
+    /// Configuration file, defining the same options as the command line
+    ///
+    /// Defaults to `~/.config/ironrdp/config.toml`, which is only read when it exists.
+    #[clap(long, env = "IRONRDP_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Connection profile of the configuration file to use, defined in the `[profiles.<PROFILE>]` table
+    #[clap(long, env = "IRONRDP_PROFILE")]
+    profile: Option<String>,
+
+    /// Print the configuration merged from the configuration file, the environment and the command line, and exit
+    ///
+    /// The password is redacted.
+    #[clap(long, alias = "print-config")]
+    print_effective_config: bool,
+
     /// A file with IronRDP client logs
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, value_parser, env = "IRONRDP_LOG_FILE")]
     log_file: Option<String>,
 
     /// An address on which the client will connect.
+    #[clap(env = "IRONRDP_DESTINATION")]
     destination: Option<Destination>,
 
     /// A target RDP server user name
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, value_parser, env = "IRONRDP_USERNAME")]
     username: Option<String>,
 
     /// An optional target RDP server domain name
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, value_parser, env = "IRONRDP_DOMAIN")]
     domain: Option<String>,
 
     /// A target RDP server user password
-    #[clap(short, long, value_parser)]
+    ///
+    /// The password is visible in the shell history and the process list, prefer `--use-keyring`.
+    #[clap(short, long, value_parser, env = "IRONRDP_PASSWORD", hide_env_values = true)]
     password: Option<String>,
 
-    /// The keyboard type
-    #[clap(long, value_enum, value_parser, default_value_t = KeyboardType::IbmEnhanced)]
-    keyboard_type: KeyboardType,
+    /// Look up the password in the OS credential store, keyed by server and username
+    ///
+    /// The password is prompted when it is not found, or when the credential store is not available.
+    #[clap(long, env = "IRONRDP_USE_KEYRING")]
+    use_keyring: bool,
+
+    /// Store the password in the OS credential store once connected
+    #[clap(long, env = "IRONRDP_SAVE_PASSWORD")]
+    save_password: bool,
+
+    /// The keyboard type [default: ibm-enhanced]
+    #[clap(long, value_enum, value_parser, env = "IRONRDP_KEYBOARD_TYPE")]
+    keyboard_type: Option<KeyboardType>,
 
-    /// The keyboard subtype (an original equipment manufacturer-dependent value)
-    #[clap(long, value_parser, default_value_t = 0)]
-    keyboard_subtype: u32,
+    /// The keyboard subtype (an original equipment manufacturer-dependent value) [default: 0]
+    #[clap(long, value_parser, env = "IRONRDP_KEYBOARD_SUBTYPE")]
+    keyboard_subtype: Option<u32>,
 
-    /// The number of function keys on the keyboard
-    #[clap(long, value_parser, default_value_t = 12)]
-    keyboard_functional_keys_count: u32,
+    /// The number of function keys on the keyboard [default: 12]
+    #[clap(long, value_parser, env = "IRONRDP_KEYBOARD_FUNCTIONAL_KEYS_COUNT")]
+    keyboard_functional_keys_count: Option<u32>,
 
     /// The input method editor (IME) file name associated with the active input locale
-    #[clap(long, value_parser, default_value_t = String::from(""))]
-    ime_file_name: String,
+    #[clap(long, value_parser, env = "IRONRDP_IME_FILE_NAME")]
+    ime_file_name: Option<String>,
 
     /// Contains a value that uniquely identifies the client
-    #[clap(long, value_parser, default_value_t = String::from(""))]
-    dig_product_id: String,
+    #[clap(long, value_parser, env = "IRONRDP_DIG_PRODUCT_ID")]
+    dig_product_id: Option<String>,
 
     /// Enable thin client
     #[clap(long)]
@@ -189,49 +329,277 @@ struct Args {
     small_cache: bool,
 
     /// Set required color depth. Currently only 32 and 16 bit color depths are supported
-    #[clap(long)]
+    #[clap(long, env = "IRONRDP_COLOR_DEPTH")]
     color_depth: Option<u32>,
 
     /// Ignore mouse pointer messages sent by the server. Increases performance when enabled, as the
     /// client could skip costly software rendering of the pointer with alpha blending
-    #[clap(long)]
+    #[clap(long, env = "IRONRDP_NO_SERVER_POINTER")]
     no_server_pointer: bool,
 
     /// Enabled capability versions. Each bit represents enabling a capability version
-    /// starting from V8 to V10_7
-    #[clap(long, value_parser = parse_hex, default_value_t = 0)]
-    capabilities: u32,
+    /// starting from V8 to V10_7 [default: 0]
+    #[clap(long, value_parser = parse_hex, env = "IRONRDP_CAPABILITIES")]
+    capabilities: Option<u32>,
 
     /// Automatically logon to the server by passing the INFO_AUTOLOGON flag
     ///
     /// This flag is ignored if CredSSP authentication is used.
     /// You can use `--no-credssp` to ensure it’s not.
-    #[clap(long)]
+    #[clap(long, env = "IRONRDP_AUTOLOGON")]
     autologon: bool,
 
     /// Disable TLS + Graphical login (legacy authentication method)
     ///
     /// Disabling this in order to enforce usage of CredSSP (NLA) is recommended.
-    #[clap(long)]
+    #[clap(long, env = "IRONRDP_NO_TLS")]
     no_tls: bool,
 
     /// Disable TLS + Network Level Authentication (NLA) using CredSSP
     ///
     /// NLA is used to authenticates RDP clients and servers before sending credentials over the network.
     /// It’s not recommended to disable this.
-    #[clap(long, alias = "no-nla")]
+    #[clap(long, alias = "no-nla", env = "IRONRDP_NO_CREDSSP")]
     no_credssp: bool,
 
-    /// The clipboard type
-    #[clap(long, value_enum, value_parser, default_value_t = ClipboardType::Default)]
-    clipboard_type: ClipboardType,
+    /// Connect in Restricted Admin mode, without sending the password to the server
+    #[clap(long, conflicts_with = "no_credssp", env = "IRONRDP_RESTRICTED_ADMIN")]
+    restricted_admin: bool,
+
+    /// Connect to the console session
+    #[clap(long, conflicts_with = "session_id", env = "IRONRDP_CONSOLE")]
+    console: bool,
+
+    /// Reconnect to the disconnected session with the given ID
+    #[clap(long, env = "IRONRDP_SESSION_ID")]
+    session_id: Option<u32>,
+
+    /// The clipboard type [default: default]
+    #[clap(long, value_enum, value_parser, env = "IRONRDP_CLIPBOARD_TYPE")]
+    clipboard_type: Option<ClipboardType>,
+
+    /// The directions in which the clipboard is shared with the server [default: bidirectional]
+    #[clap(long, value_enum, env = "IRONRDP_CLIPBOARD_POLICY")]
+    clipboard_policy: Option<Clipboard>,
+
+    /// Also set the PRIMARY selection, pasted with the middle button, when the clipboard of the server changes
+    ///
+    /// Only used by the Linux clipboard.
+    #[clap(long, env = "IRONRDP_CLIPBOARD_PRIMARY")]
+    clipboard_primary: bool,
+
+    /// Read drive redirection and clipboard commands from the standard input during the session
+    ///
+    /// Supported commands are `mount <PATH>`, `unmount <DEVICE ID>` and `clipboard <POLICY>`, the policy taking
+    /// the same values as `--clipboard-policy`. Drives can only be mounted on Linux and macOS.
+    #[clap(long, env = "IRONRDP_DRIVE_COMMANDS")]
+    drive_commands: bool,
+
+    /// Delay in milliseconds without window resize before the new size is sent to the server [default: 300]
+    ///
+    /// The remote desktop is resized using the Display Control Virtual Channel, when the server supports it.
+    #[clap(long, env = "IRONRDP_RESIZE_DEBOUNCE_MS")]
+    resize_debounce_ms: Option<u64>,
+
+    /// Scale the remote desktop to the window size with the given filter, when their sizes differ
+    ///
+    /// The image is scaled on the CPU, which reduces the blur of the scaling by the window system on fractional
+    /// DPI displays. The filter is cycled during the session with Ctrl+Alt+S.
+    #[clap(long, value_enum, value_parser, env = "IRONRDP_SCALING")]
+    scaling: Option<Scaling>,
+
+    /// Interval in milliseconds at which the traffic of each virtual channel is logged, at the debug level
+    #[clap(long, env = "IRONRDP_STATS_INTERVAL_MS")]
+    stats_interval_ms: Option<u64>,
+
+    /// Do not play the audio of the session
+    #[clap(long, env = "IRONRDP_NO_AUDIO")]
+    no_audio: bool,
+
+    /// Latency in milliseconds targeted by the audio playback [default: 60]
+    ///
+    /// The received audio is buffered up to this latency to absorb the network jitter. The measured latency is logged
+    /// along with the virtual channel traffic, see `--stats-interval-ms`.
+    #[clap(long, conflicts_with = "no_audio", env = "IRONRDP_AUDIO_LATENCY_MS")]
+    audio_latency_ms: Option<u64>,
+
+    /// Number of missed server heartbeats after which a warning is logged, 0 to disable
+    ///
+    /// Defaults to the value sent by the server along with the heartbeats.
+    #[clap(long, env = "IRONRDP_HEARTBEAT_WARNING_COUNT")]
+    heartbeat_warning_count: Option<u8>,
+
+    /// Number of missed server heartbeats after which the client reconnects, 0 to disable
+    ///
+    /// Defaults to the value sent by the server along with the heartbeats.
+    #[clap(long, env = "IRONRDP_HEARTBEAT_RECONNECT_COUNT")]
+    heartbeat_reconnect_count: Option<u8>,
+
+    /// Launch a remote application (RemoteApp) instead of a full desktop
+    ///
+    /// Published applications are referred to by their alias prefixed with `||`, e.g.: `||notepad`.
+    #[clap(long, env = "IRONRDP_REMOTE_APP")]
+    remote_app: Option<String>,
+
+    /// Working directory of the remote application
+    #[clap(long, env = "IRONRDP_REMOTE_APP_WORKING_DIR")]
+    remote_app_working_dir: Option<String>,
+
+    /// Command line arguments of the remote application
+    #[clap(long, env = "IRONRDP_REMOTE_APP_ARGS")]
+    remote_app_args: Option<String>,
+
+    /// Connect without opening a window, and exit once the desktop is rendered
+    ///
+    /// The exit code is 0 on success, 1 when the session fails, 2 when the connection fails, 3 when the
+    /// authentication fails, and 4 when the graphics updates are not received in time.
+    #[clap(long, env = "IRONRDP_HEADLESS")]
+    headless: bool,
+
+    /// Number of graphics updates to wait for in headless mode [default: 1]
+    #[clap(long, env = "IRONRDP_HEADLESS_FRAMES")]
+    headless_frames: Option<usize>,
+
+    /// Delay in milliseconds to receive the graphics updates in headless mode [default: 30000]
+    #[clap(long, env = "IRONRDP_HEADLESS_TIMEOUT_MS")]
+    headless_timeout_ms: Option<u64>,
+
+    /// Input script to run in headless mode, once the graphics updates are received
+    ///
+    /// The script contains one command per line: `move <X> <Y>`, `click <left|middle|right>`, `key <SCANCODE>`
+    /// or `wait <MS>`.
+    #[clap(long, env = "IRONRDP_SCRIPT")]
+    script: Option<PathBuf>,
+
+    /// PNG file the final framebuffer is written to in headless mode
+    #[clap(long, env = "IRONRDP_SCREENSHOT")]
+    screenshot: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parses the command line `args`, falling back to the environment variables found with `env`.
+    fn try_parse_with_env<I, T>(args: I, env: impl Fn(&OsStr) -> Option<OsString>) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        // clap reads the environment variables when the command is built, so they are replaced with default values,
+        // which are also overridden by the command line.
+        let mut command = Self::command().mut_args(|arg| {
+            let Some(name) = arg.get_env().map(ToOwned::to_owned) else {
+                return arg;
+            };
+
+            // Keeps mentioning the variable in the help, as clap does.
+            let spec = format!("[env: {}]", name.to_string_lossy());
+            let help = arg
+                .get_help()
+                .map_or_else(|| spec.clone(), |help| format!("{help} {spec}"));
+            let long_help = arg.get_long_help().map(|help| format!("{help}\n\n{spec}"));
+            let hide_value = arg.is_hide_env_values_set();
+
+            let mut arg = arg.env(None).help(help);
+            if let Some(long_help) = long_help {
+                arg = arg.long_help(long_help);
+            }
+
+            match env(&name) {
+                Some(value) => arg.default_value(value).hide_default_value(hide_value),
+                None => arg,
+            }
+        });
+
+        let mut matches = command.try_get_matches_from_mut(args)?;
+        Self::from_arg_matches_mut(&mut matches).map_err(|e| e.format(&mut command))
+    }
+}
+
+impl From<Args> for ClientConfig {
+    fn from(args: Args) -> Self {
+        // The flags can only be turned on, so that they don't override the configuration file when not set.
+        let flag = |set: bool| set.then_some(true);
+
+        Self {
+            log_file: args.log_file,
+            destination: args.destination,
+            username: args.username,
+            domain: args.domain,
+            password: args.password,
+            use_keyring: flag(args.use_keyring),
+            save_password: flag(args.save_password),
+            keyboard_type: args.keyboard_type,
+            keyboard_subtype: args.keyboard_subtype,
+            keyboard_functional_keys_count: args.keyboard_functional_keys_count,
+            ime_file_name: args.ime_file_name,
+            dig_product_id: args.dig_product_id,
+            no_server_pointer: flag(args.no_server_pointer),
+            capabilities: args.capabilities,
+            autologon: flag(args.autologon),
+            no_tls: flag(args.no_tls),
+            no_credssp: flag(args.no_credssp),
+            restricted_admin: flag(args.restricted_admin),
+            console: flag(args.console),
+            session_id: args.session_id,
+            clipboard_type: args.clipboard_type,
+            clipboard_policy: args.clipboard_policy,
+            clipboard_primary: flag(args.clipboard_primary),
+            drive_commands: flag(args.drive_commands),
+            resize_debounce_ms: args.resize_debounce_ms,
+            scaling: args.scaling,
+            stats_interval_ms: args.stats_interval_ms,
+            no_audio: flag(args.no_audio),
+            audio_latency_ms: args.audio_latency_ms,
+            heartbeat_warning_count: args.heartbeat_warning_count,
+            heartbeat_reconnect_count: args.heartbeat_reconnect_count,
+            remote_app: args.remote_app,
+            remote_app_working_dir: args.remote_app_working_dir,
+            remote_app_args: args.remote_app_args,
+            headless: flag(args.headless),
+            headless_frames: args.headless_frames,
+            headless_timeout_ms: args.headless_timeout_ms,
+            script: args.script,
+            screenshot: args.screenshot,
+            bitmap: BitmapOptions {
+                color_depth: args.color_depth,
+                lossy_compression: None,
+            },
+            ..Self::default()
+        }
+    }
 }
 
 impl Config {
     pub fn parse_args() -> anyhow::Result<Self> {
-        let args = Args::parse();
+        let args =
+            Args::try_parse_with_env(std::env::args_os(), |name| std::env::var_os(name)).unwrap_or_else(|e| e.exit());
+
+        let file = match &args.config {
+            Some(path) => ClientConfig::load(path, args.profile.as_deref())?,
+            None => ClientConfig::load_default(args.profile.as_deref())?,
+        };
+
+        let print_effective_config = args.print_effective_config;
+
+        // The environment variables are overridden by the command line.
+        let config = file.overlay(ClientConfig::from(args));
+        config.validate().context("invalid configuration")?;
+
+        if print_effective_config {
+            #[allow(clippy::print_stdout)] // the configuration is the output of the command
+            {
+                print!("{}", config.with_defaults().redacted().to_toml()?);
+            }
+            std::process::exit(0);
+        }
+
+        Self::from_client_config(config)
+    }
 
-        let destination = if let Some(destination) = args.destination {
+    /// Maps the merged options to the configuration of the connector and of the client.
+    ///
+    /// The server address, the username and the password are prompted when not defined.
+    pub fn from_client_config(config: ClientConfig) -> anyhow::Result<Self> {
+        let destination = if let Some(destination) = config.destination {
             destination
         } else {
             inquire::Text::new("Server address:")
@@ -240,58 +608,73 @@ impl Config {
                 .pipe(Destination::new)?
         };
 
-        let username = if let Some(username) = args.username {
+        let username = if let Some(username) = config.username {
             username
         } else {
             inquire::Text::new("Username:").prompt().context("Username prompt")?
         };
 
-        let password = if let Some(password) = args.password {
-            password
-        } else {
+        let password_source = match config.password {
+            Some(password) => PasswordSource::Literal(password),
+            None if config.use_keyring == Some(true) => PasswordSource::Keyring,
+            None => PasswordSource::Prompt,
+        };
+
+        let password = password_source.resolve(&OsCredentialStore, destination.name(), &username, || {
             inquire::Password::new("Password:")
                 .without_confirmation()
                 .prompt()
-                .context("Password prompt")?
-        };
-
-        let bitmap = if let Some(color_depth) = args.color_depth {
-            if color_depth != 16 && color_depth != 32 {
-                anyhow::bail!("Invalid color depth. Only 16 and 32 bit color depths are supported.");
-            }
+                .context("Password prompt")
+        })?;
 
+        let bitmap = if config.bitmap.is_empty() {
+            None
+        } else {
             Some(connector::BitmapConfig {
-                color_depth,
-                lossy_compression: true,
+                color_depth: config.bitmap.color_depth.unwrap_or(client_config::DEFAULT_COLOR_DEPTH),
+                lossy_compression: config.bitmap.lossy_compression.unwrap_or(true),
             })
-        } else {
-            None
         };
 
-        let clipboard_type = if args.clipboard_type == ClipboardType::Default {
-            #[cfg(windows)]
-            {
-                ClipboardType::Windows
+        let clipboard_type = match config.clipboard_type {
+            None | Some(ClipboardType::Default) => {
+                #[cfg(windows)]
+                {
+                    ClipboardType::Windows
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    ClipboardType::Linux
+                }
+                #[cfg(not(any(windows, target_os = "linux")))]
+                {
+                    ClipboardType::None
+                }
             }
-            #[cfg(not(windows))]
-            {
-                ClipboardType::None
-            }
-        } else {
-            args.clipboard_type
+            Some(clipboard_type) => clipboard_type,
         };
 
+        let is_set = |flag: Option<bool>| flag == Some(true);
+
         let connector = connector::Config {
             credentials: Credentials::UsernamePassword { username, password },
-            domain: args.domain,
-            enable_tls: !args.no_tls,
-            enable_credssp: !args.no_credssp,
-            keyboard_type: KeyboardType::parse(args.keyboard_type),
-            keyboard_subtype: args.keyboard_subtype,
+            domain: config.domain,
+            enable_tls: !is_set(config.no_tls),
+            enable_credssp: !is_set(config.no_credssp),
+            credential_delegation: if is_set(config.restricted_admin) {
+                connector::CredentialDelegation::RestrictedAdmin
+            } else {
+                connector::CredentialDelegation::Full
+            },
+            keyboard_type: KeyboardType::parse(config.keyboard_type.unwrap_or(client_config::DEFAULT_KEYBOARD_TYPE)),
+            keyboard_subtype: config.keyboard_subtype.unwrap_or(0),
             keyboard_layout: 0, // the server SHOULD use the default active input locale identifier
-            keyboard_functional_keys_count: args.keyboard_functional_keys_count,
-            ime_file_name: args.ime_file_name,
-            dig_product_id: args.dig_product_id,
+            active_input_locale: None,
+            keyboard_functional_keys_count: config
+                .keyboard_functional_keys_count
+                .unwrap_or(client_config::DEFAULT_KEYBOARD_FUNCTIONAL_KEYS_COUNT),
+            ime_file_name: config.ime_file_name.unwrap_or_default(),
+            dig_product_id: config.dig_product_id.unwrap_or_default(),
             desktop_size: connector::DesktopSize {
                 width: DEFAULT_WIDTH,
                 height: DEFAULT_HEIGHT,
@@ -317,18 +700,172 @@ impl Config {
             },
             hardware_id: None,
             license_cache: None,
-            no_server_pointer: args.no_server_pointer,
-            autologon: args.autologon,
+            remote_app: config.remote_app.map(|program| connector::RemoteAppConfig {
+                program,
+                working_dir: config.remote_app_working_dir,
+                args: config.remote_app_args,
+            }),
+            auto_reconnect_cookie: None,
+            frame_markers: true,
+            timeouts: connector::ConnectTimeouts::default(),
+            bitmap_cache: None,
+            extra_capability_sets: Vec::new(),
+            decline_multitransport: true,
+            redirect_session: if is_set(config.console) {
+                Some(connector::RedirectSession::Console)
+            } else {
+                config.session_id.map(connector::RedirectSession::SessionId)
+            },
+            no_server_pointer: is_set(config.no_server_pointer),
+            autologon: is_set(config.autologon),
             request_data: None,
+            correlation_id: None,
             pointer_software_rendering: true,
             performance_flags: PerformanceFlags::default(),
         };
 
+        let headless = if is_set(config.headless) {
+            let script = if let Some(path) = &config.script {
+                let script =
+                    std::fs::read_to_string(path).with_context(|| format!("unable to read {}", path.display()))?;
+                headless::parse_script(&script).with_context(|| format!("invalid script {}", path.display()))?
+            } else {
+                Vec::new()
+            };
+
+            Some(HeadlessConfig {
+                frames: config.headless_frames.unwrap_or(client_config::DEFAULT_HEADLESS_FRAMES),
+                timeout: Duration::from_millis(
+                    config
+                        .headless_timeout_ms
+                        .unwrap_or(client_config::DEFAULT_HEADLESS_TIMEOUT_MS),
+                ),
+                script,
+                screenshot: config.screenshot,
+            })
+        } else {
+            None
+        };
+
         Ok(Self {
-            log_file: args.log_file,
+            log_file: config.log_file,
             destination,
             connector,
             clipboard_type,
+            clipboard_policy: Clipboard::parse(
+                config
+                    .clipboard_policy
+                    .unwrap_or(client_config::DEFAULT_CLIPBOARD_POLICY),
+            ),
+            clipboard_mirror_primary: is_set(config.clipboard_primary),
+            drive_commands: is_set(config.drive_commands),
+            resize_debounce: Duration::from_millis(
+                config
+                    .resize_debounce_ms
+                    .unwrap_or(client_config::DEFAULT_RESIZE_DEBOUNCE_MS),
+            ),
+            headless,
+            save_password: is_set(config.save_password),
+            scaling: config.scaling.map(Scaling::parse),
+            stats_interval: config.stats_interval_ms.map(Duration::from_millis),
+            audio_latency: (!is_set(config.no_audio)).then(|| {
+                Duration::from_millis(
+                    config
+                        .audio_latency_ms
+                        .unwrap_or(client_config::DEFAULT_AUDIO_LATENCY_MS),
+                )
+            }),
+            heartbeat_policy: HeartbeatPolicy {
+                warning_count: config.heartbeat_warning_count,
+                reconnect_count: config.heartbeat_reconnect_count,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> ClientConfig {
+        parse_with_env(args, &[])
+    }
+
+    fn parse_with_env(args: &[&str], env: &[(&str, &str)]) -> ClientConfig {
+        let args = Args::try_parse_with_env(core::iter::once("ironrdp-client").chain(args.iter().copied()), |name| {
+            env.iter()
+                .find(|(key, _)| OsStr::new(key) == name)
+                .map(|(_, value)| OsString::from(value))
         })
+        .unwrap();
+        ClientConfig::from(args)
+    }
+
+    #[test]
+    fn command_line_only_defines_the_given_options() {
+        let config = parse(&["--color-depth", "16", "--no-tls", "--keyboard-subtype", "2"]);
+
+        assert_eq!(
+            config.bitmap,
+            BitmapOptions {
+                color_depth: Some(16),
+                lossy_compression: None,
+            }
+        );
+        assert_eq!(config.no_tls, Some(true));
+        assert_eq!(config.keyboard_subtype, Some(2));
+        // The flags which are not set, and the options with defaults, don't override the configuration file.
+        assert_eq!(config.no_credssp, None);
+        assert_eq!(config.keyboard_type, None);
+        assert_eq!(config.audio_latency_ms, None);
+    }
+
+    #[test]
+    fn command_line_overrides_environment() {
+        let env = [("IRONRDP_SCALING", "nearest"), ("IRONRDP_STATS_INTERVAL_MS", "1000")];
+
+        let config = parse_with_env(&["--scaling", "lanczos3"], &env);
+
+        assert_eq!(config.scaling, Some(Scaling::Lanczos3));
+        assert_eq!(config.stats_interval_ms, Some(1000));
+    }
+
+    #[test]
+    fn environment_sets_the_flags() {
+        let config = parse_with_env(
+            &[],
+            &[("IRONRDP_NO_TLS", "true"), ("IRONRDP_DESTINATION", "server:3390")],
+        );
+
+        assert_eq!(config.no_tls, Some(true));
+        assert_eq!(config.destination, Some(Destination::new("server:3390").unwrap()));
+        assert_eq!(config.no_credssp, None);
+    }
+
+    #[test]
+    fn configuration_file_uses_the_command_line_values() {
+        fn assert_same_names<T: clap::ValueEnum + Serialize>() {
+            for variant in T::value_variants() {
+                let name = toml::Value::try_from(variant).unwrap();
+                let expected = variant.to_possible_value().unwrap();
+                assert_eq!(name.as_str(), Some(expected.get_name()));
+            }
+        }
+
+        assert_same_names::<KeyboardType>();
+        assert_same_names::<Scaling>();
+        assert_same_names::<Clipboard>();
+        assert_same_names::<ClipboardType>();
+    }
+
+    #[test]
+    fn destination_roundtrip() {
+        for addr in ["rdp.example.com:3390", "10.0.0.1:3389", "[::1]:3389"] {
+            let destination = Destination::new(addr).unwrap();
+            assert_eq!(destination.to_string(), addr);
+        }
+
+        let destination = Destination::new("::1").unwrap();
+        assert_eq!(Destination::new(destination.to_string()).unwrap(), destination);
     }
 }
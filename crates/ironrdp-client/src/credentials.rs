@@ -0,0 +1,190 @@
+//! Retrieval of the user password from the command line, the OS credential store, or an interactive prompt.
+
+/// Service name under which the passwords are stored, along with the server name.
+const KEYRING_SERVICE: &str = "ironrdp-client";
+
+/// Where the password of the target user comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// The password is provided as is, e.g.: on the command line.
+    Literal(String),
+    /// The password is looked up in the credential store, and prompted when not found.
+    Keyring,
+    /// The password is prompted.
+    Prompt,
+}
+
+impl PasswordSource {
+    /// Returns the password for `username` on `server`.
+    ///
+    /// A failure to access the credential store is logged, and the password is prompted instead.
+    pub fn resolve(
+        self,
+        store: &dyn CredentialStore,
+        server: &str,
+        username: &str,
+        prompt: impl FnOnce() -> anyhow::Result<String>,
+    ) -> anyhow::Result<String> {
+        match self {
+            Self::Literal(password) => Ok(password),
+            Self::Keyring => match store.get_password(server, username) {
+                Ok(Some(password)) => Ok(password),
+                Ok(None) => {
+                    debug!(server, username, "No password in the credential store");
+                    prompt()
+                }
+                Err(error) => {
+                    warn!(%error, "Unable to access the credential store, prompting for the password");
+                    prompt()
+                }
+            },
+            Self::Prompt => prompt(),
+        }
+    }
+}
+
+/// Storage of the passwords, keyed by server and username
+pub trait CredentialStore {
+    /// Returns the password for `username` on `server`, or `None` when none is stored.
+    fn get_password(&self, server: &str, username: &str) -> anyhow::Result<Option<String>>;
+
+    /// Stores the password for `username` on `server`, replacing the existing one.
+    fn set_password(&self, server: &str, username: &str, password: &str) -> anyhow::Result<()>;
+}
+
+/// Credential store of the OS: Secret Service on Linux, Keychain on macOS, and Credential Manager on Windows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsCredentialStore;
+
+impl OsCredentialStore {
+    fn entry(server: &str, username: &str) -> keyring::Result<keyring::Entry> {
+        keyring::Entry::new(&format!("{KEYRING_SERVICE}/{server}"), username)
+    }
+}
+
+impl CredentialStore for OsCredentialStore {
+    fn get_password(&self, server: &str, username: &str) -> anyhow::Result<Option<String>> {
+        match Self::entry(server, username)?.get_password() {
+            Ok(password) => Ok(Some(password)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn set_password(&self, server: &str, username: &str, password: &str) -> anyhow::Result<()> {
+        Self::entry(server, username)?.set_password(password)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockStore {
+        passwords: RefCell<HashMap<(String, String), String>>,
+        unavailable: bool,
+        lookups: Cell<usize>,
+    }
+
+    impl CredentialStore for MockStore {
+        fn get_password(&self, server: &str, username: &str) -> anyhow::Result<Option<String>> {
+            self.lookups.set(self.lookups.get() + 1);
+
+            if self.unavailable {
+                anyhow::bail!("store unavailable");
+            }
+
+            let key = (server.to_owned(), username.to_owned());
+            Ok(self.passwords.borrow().get(&key).cloned())
+        }
+
+        fn set_password(&self, server: &str, username: &str, password: &str) -> anyhow::Result<()> {
+            if self.unavailable {
+                anyhow::bail!("store unavailable");
+            }
+
+            let key = (server.to_owned(), username.to_owned());
+            self.passwords.borrow_mut().insert(key, password.to_owned());
+            Ok(())
+        }
+    }
+
+    fn prompted() -> anyhow::Result<String> {
+        Ok("prompted".to_owned())
+    }
+
+    fn unexpected_prompt() -> anyhow::Result<String> {
+        panic!("unexpected prompt")
+    }
+
+    #[test]
+    fn literal_password_is_used_as_is() {
+        let store = MockStore::default();
+
+        let password = PasswordSource::Literal("literal".to_owned())
+            .resolve(&store, "server", "user", unexpected_prompt)
+            .unwrap();
+
+        assert_eq!(password, "literal");
+        assert_eq!(store.lookups.get(), 0);
+    }
+
+    #[test]
+    fn stored_password_is_used() {
+        let store = MockStore::default();
+        store.set_password("server", "user", "stored").unwrap();
+        store.set_password("other", "user", "other").unwrap();
+
+        let password = PasswordSource::Keyring
+            .resolve(&store, "server", "user", unexpected_prompt)
+            .unwrap();
+
+        assert_eq!(password, "stored");
+    }
+
+    #[test]
+    fn missing_password_is_prompted() {
+        let store = MockStore::default();
+        store.set_password("server", "other", "stored").unwrap();
+
+        let password = PasswordSource::Keyring
+            .resolve(&store, "server", "user", prompted)
+            .unwrap();
+
+        assert_eq!(password, "prompted");
+    }
+
+    #[test]
+    fn unavailable_store_falls_back_to_prompt() {
+        let store = MockStore {
+            unavailable: true,
+            ..MockStore::default()
+        };
+
+        let password = PasswordSource::Keyring
+            .resolve(&store, "server", "user", prompted)
+            .unwrap();
+
+        assert_eq!(password, "prompted");
+        assert_eq!(store.lookups.get(), 1);
+    }
+
+    #[test]
+    fn prompt_does_not_use_the_store() {
+        let store = MockStore::default();
+        store.set_password("server", "user", "stored").unwrap();
+
+        let password = PasswordSource::Prompt
+            .resolve(&store, "server", "user", prompted)
+            .unwrap();
+
+        assert_eq!(password, "prompted");
+        assert_eq!(store.lookups.get(), 0);
+    }
+}
@@ -0,0 +1,439 @@
+#![allow(clippy::print_stderr)] // allowed in this module only
+
+//! Headless mode, for smoke-testing RDP servers without opening a window
+//!
+//! The client connects, waits for the first graphics updates, runs the input script, saves a screenshot and
+//! disconnects. The outcome is reported through the exit code of the process.
+
+use core::time::Duration;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::Context as _;
+use ironrdp::connector::{ConnectionResult, ConnectorError};
+use ironrdp::graphics::image_processing::PixelFormat;
+use ironrdp::input::{Database, MouseButton, MousePosition, Operation, Scancode};
+use ironrdp::session::image::DecodedImage;
+use ironrdp::session::{self, ActiveStage, ActiveStageOutput, SessionResult};
+use ironrdp_core::ErrorCategory;
+use ironrdp_tokio::{split_tokio_framed, FramedWrite, TokioFramed};
+use tokio::io::{ReadHalf, WriteHalf};
+
+use crate::config::Config;
+use crate::rdp::{connect, UpgradedFramed, UpgradedStream};
+
+#[derive(Clone, Debug)]
+pub struct HeadlessConfig {
+    /// Number of graphics updates to receive before running the script.
+    pub frames: usize,
+    /// Delay to receive the graphics updates.
+    pub timeout: Duration,
+    pub script: Vec<ScriptCommand>,
+    /// PNG file the final framebuffer is written to.
+    pub screenshot: Option<PathBuf>,
+}
+
+/// Outcome of a headless run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success,
+    /// The session failed after the connection, or the script or the screenshot could not be processed.
+    Failure,
+    ConnectFailure,
+    AuthFailure,
+    /// The graphics updates were not received in time.
+    Timeout,
+}
+
+impl ExitStatus {
+    pub fn from_connector_error(error: &ConnectorError) -> Self {
+        if error.category() == ErrorCategory::Security {
+            Self::AuthFailure
+        } else {
+            Self::ConnectFailure
+        }
+    }
+
+    /// Returns the exit code of the process.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::ConnectFailure => 2,
+            Self::AuthFailure => 3,
+            Self::Timeout => 4,
+        }
+    }
+}
+
+/// Command of an input script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptCommand {
+    /// `move <X> <Y>`
+    MoveMouse { x: u16, y: u16 },
+    /// `click <left|middle|right>`
+    Click(MouseButton),
+    /// `key <SCANCODE>`, the extended scancodes being prefixed with 0xE0 (e.g.: `0xE048`)
+    Key(Scancode),
+    /// `wait <MS>`
+    Wait(Duration),
+}
+
+/// Parses an input script, made of one command per line
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn parse_script(script: &str) -> anyhow::Result<Vec<ScriptCommand>> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| parse_command(line).with_context(|| format!("line {line_number}: `{line}`")))
+        .collect()
+}
+
+fn parse_command(line: &str) -> anyhow::Result<ScriptCommand> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+
+    let command = match (name, args.as_slice()) {
+        ("move", [x, y]) => ScriptCommand::MoveMouse {
+            x: x.parse().context("invalid X coordinate")?,
+            y: y.parse().context("invalid Y coordinate")?,
+        },
+        ("click", [button]) => ScriptCommand::Click(match *button {
+            "left" => MouseButton::Left,
+            "middle" => MouseButton::Middle,
+            "right" => MouseButton::Right,
+            _ => anyhow::bail!("unknown mouse button (expected `left`, `middle` or `right`)"),
+        }),
+        ("key", [scancode]) => {
+            let scancode = if let Some(hex) = scancode.strip_prefix("0x") {
+                u16::from_str_radix(hex, 16)
+            } else {
+                scancode.parse()
+            };
+
+            ScriptCommand::Key(Scancode::from_u16(scancode.context("invalid scancode")?))
+        }
+        ("wait", [ms]) => ScriptCommand::Wait(Duration::from_millis(ms.parse().context("invalid delay")?)),
+        _ => anyhow::bail!(
+            "unknown command (expected `move <X> <Y>`, `click <BUTTON>`, `key <SCANCODE>` or `wait <MS>`)"
+        ),
+    };
+
+    Ok(command)
+}
+
+/// Tracks the graphics updates received before a deadline
+#[derive(Debug, Clone)]
+pub struct FrameWait {
+    expected: usize,
+    received: usize,
+    deadline: Instant,
+}
+
+impl FrameWait {
+    pub fn new(expected: usize, timeout: Duration, now: Instant) -> Self {
+        Self {
+            expected,
+            received: 0,
+            deadline: now + timeout,
+        }
+    }
+
+    pub fn on_graphics_updates(&mut self, count: usize) {
+        self.received += count;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.received >= self.expected
+    }
+
+    /// Returns the time left before the deadline, or `None` if it has passed.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.deadline
+            .checked_duration_since(now)
+            .filter(|time_left| !time_left.is_zero())
+    }
+}
+
+pub async fn run(config: Config, headless: HeadlessConfig) -> ExitStatus {
+    let (connection_result, framed) = match connect(&config, None, None).await {
+        Ok(result) => result,
+        Err(error) => {
+            error!(?error);
+            eprintln!("Connection error: {}", error.report());
+            return ExitStatus::from_connector_error(&error);
+        }
+    };
+
+    match headless_session(framed, connection_result, &headless).await {
+        Ok(status) => status,
+        Err(error) => {
+            error!(?error);
+            eprintln!("Headless session error: {error:#}");
+            ExitStatus::Failure
+        }
+    }
+}
+
+/// Result of processing a frame received from the server
+enum Processed {
+    GraphicsUpdates(usize),
+    Terminated,
+}
+
+struct HeadlessSession {
+    reader: TokioFramed<ReadHalf<UpgradedStream>>,
+    writer: TokioFramed<WriteHalf<UpgradedStream>>,
+    active_stage: ActiveStage,
+    image: DecodedImage,
+}
+
+impl HeadlessSession {
+    /// Reads and processes the next frame, unless `timeout` elapses first.
+    async fn next_frame(&mut self, timeout: Duration) -> SessionResult<Option<Processed>> {
+        let Ok(frame) = tokio::time::timeout(timeout, self.reader.read_pdu()).await else {
+            return Ok(None);
+        };
+
+        let (action, payload) = frame.map_err(|e| session::custom_err!("read frame", e))?;
+        trace!(?action, frame_length = payload.len(), "Frame received");
+
+        let outputs = self.active_stage.process(&mut self.image, action, &payload)?;
+
+        self.handle_outputs(outputs).await.map(Some)
+    }
+
+    async fn handle_outputs(&mut self, outputs: Vec<ActiveStageOutput>) -> SessionResult<Processed> {
+        let mut graphics_updates = 0;
+
+        for output in outputs {
+            match output {
+                ActiveStageOutput::ResponseFrame(frame) => self
+                    .writer
+                    .write_all(&frame)
+                    .await
+                    .map_err(|e| session::custom_err!("write response", e))?,
+                ActiveStageOutput::GraphicsUpdate(_) => graphics_updates += 1,
+                ActiveStageOutput::Terminate(reason) => {
+                    info!(%reason, "Session terminated by the server");
+                    return Ok(Processed::Terminated);
+                }
+                ActiveStageOutput::ServerRedirection(redirection) => {
+                    // The scenario is not replayed against another server.
+                    info!(?redirection.target, "Session redirected by the server");
+                    return Ok(Processed::Terminated);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Processed::GraphicsUpdates(graphics_updates))
+    }
+
+    /// Keeps processing the frames received from the server for `delay`.
+    async fn idle(&mut self, delay: Duration) -> SessionResult<Option<Processed>> {
+        let deadline = Instant::now() + delay;
+
+        while let Some(time_left) = deadline.checked_duration_since(Instant::now()) {
+            match self.next_frame(time_left).await? {
+                Some(Processed::Terminated) => return Ok(Some(Processed::Terminated)),
+                Some(Processed::GraphicsUpdates(_)) => {}
+                None => break,
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn send_input(&mut self, database: &mut Database, operations: Vec<Operation>) -> SessionResult<Processed> {
+        let events = database.apply(operations);
+        let outputs = self.active_stage.process_fastpath_input(&mut self.image, &events)?;
+
+        self.handle_outputs(outputs).await
+    }
+}
+
+async fn headless_session(
+    framed: UpgradedFramed,
+    connection_result: ConnectionResult,
+    headless: &HeadlessConfig,
+) -> anyhow::Result<ExitStatus> {
+    let (reader, writer) = split_tokio_framed(framed);
+    let image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        connection_result.desktop_size.width,
+        connection_result.desktop_size.height,
+    );
+
+    let mut session = HeadlessSession {
+        reader,
+        writer,
+        active_stage: ActiveStage::new(connection_result),
+        image,
+    };
+
+    let mut frame_wait = FrameWait::new(headless.frames, headless.timeout, Instant::now());
+
+    while !frame_wait.is_done() {
+        let Some(time_left) = frame_wait.time_left(Instant::now()) else {
+            eprintln!("Timed out waiting for the graphics updates");
+            return Ok(ExitStatus::Timeout);
+        };
+
+        match session.next_frame(time_left).await? {
+            Some(Processed::GraphicsUpdates(count)) => frame_wait.on_graphics_updates(count),
+            Some(Processed::Terminated) => {
+                eprintln!("Session terminated before the graphics updates were received");
+                return Ok(ExitStatus::Failure);
+            }
+            None => {}
+        }
+    }
+
+    debug!(frames = headless.frames, "Graphics updates received");
+
+    let mut database = Database::new();
+
+    for command in &headless.script {
+        debug!(?command, "Run script command");
+
+        let processed = match *command {
+            ScriptCommand::MoveMouse { x, y } => Some(
+                session
+                    .send_input(&mut database, vec![Operation::MouseMove(MousePosition { x, y })])
+                    .await?,
+            ),
+            ScriptCommand::Click(button) => Some(
+                session
+                    .send_input(
+                        &mut database,
+                        vec![
+                            Operation::MouseButtonPressed(button),
+                            Operation::MouseButtonReleased(button),
+                        ],
+                    )
+                    .await?,
+            ),
+            ScriptCommand::Key(scancode) => Some(
+                session
+                    .send_input(
+                        &mut database,
+                        vec![Operation::KeyPressed(scancode), Operation::KeyReleased(scancode)],
+                    )
+                    .await?,
+            ),
+            ScriptCommand::Wait(delay) => session.idle(delay).await?,
+        };
+
+        if matches!(processed, Some(Processed::Terminated)) {
+            eprintln!("Session terminated while running the script");
+            return Ok(ExitStatus::Failure);
+        }
+    }
+
+    if let Some(path) = &headless.screenshot {
+        let decoded = &session.image;
+        let buffer: image::RgbaImage = image::ImageBuffer::from_raw(
+            u32::from(decoded.width()),
+            u32::from(decoded.height()),
+            decoded.data().to_vec(),
+        )
+        .context("invalid image")?;
+        buffer
+            .save(path)
+            .with_context(|| format!("unable to save {}", path.display()))?;
+
+        info!(path = %path.display(), "Screenshot saved");
+    }
+
+    let outputs = session.active_stage.graceful_shutdown()?;
+    session.handle_outputs(outputs).await?;
+
+    Ok(ExitStatus::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_is_parsed() {
+        let script = "
+            # Open the start menu.
+            move 10 1070
+            click left
+
+            wait 500
+            key 0x1C
+            key 0xE048
+            key 57
+        ";
+
+        let commands = parse_script(script).unwrap();
+
+        assert_eq!(
+            commands,
+            [
+                ScriptCommand::MoveMouse { x: 10, y: 1070 },
+                ScriptCommand::Click(MouseButton::Left),
+                ScriptCommand::Wait(Duration::from_millis(500)),
+                ScriptCommand::Key(Scancode::from_u8(false, 0x1C)),
+                ScriptCommand::Key(Scancode::from_u8(true, 0x48)),
+                ScriptCommand::Key(Scancode::from_u8(false, 57)),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_errors_point_to_the_line() {
+        let error = parse_script("move 1 2\n\nclick up").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: `click up`");
+
+        for line in [
+            "move 1",
+            "move 1 2 3",
+            "move -1 2",
+            "wait soon",
+            "key 0xZZ",
+            "type hello",
+        ] {
+            assert!(parse_script(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn frame_wait_completes_after_the_expected_frames() {
+        let now = Instant::now();
+        let mut frame_wait = FrameWait::new(2, Duration::from_secs(5), now);
+
+        assert!(!frame_wait.is_done());
+        frame_wait.on_graphics_updates(1);
+        assert!(!frame_wait.is_done());
+        frame_wait.on_graphics_updates(3);
+        assert!(frame_wait.is_done());
+    }
+
+    #[test]
+    fn frame_wait_expires() {
+        let now = Instant::now();
+        let frame_wait = FrameWait::new(1, Duration::from_secs(5), now);
+
+        assert_eq!(frame_wait.time_left(now), Some(Duration::from_secs(5)));
+        assert_eq!(
+            frame_wait.time_left(now + Duration::from_secs(2)),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(frame_wait.time_left(now + Duration::from_secs(5)), None);
+        assert_eq!(frame_wait.time_left(now + Duration::from_secs(6)), None);
+    }
+
+    #[test]
+    fn no_frame_to_wait_for() {
+        let frame_wait = FrameWait::new(0, Duration::ZERO, Instant::now());
+        assert!(frame_wait.is_done());
+    }
+}
@@ -13,7 +13,10 @@
 extern crate tracing;
 
 pub mod app;
+pub mod client_config;
 pub mod clipboard;
 pub mod config;
+pub mod credentials;
+pub mod headless;
 pub mod network_client;
 pub mod rdp;
@@ -3,11 +3,14 @@
 #[macro_use]
 extern crate tracing;
 
+use std::path::PathBuf;
+
 use anyhow::Context as _;
 use ironrdp_client::app::App;
-use ironrdp_client::config::{ClipboardType, Config};
+use ironrdp_client::config::{parse_clipboard_policy, ClipboardType, Config};
 use ironrdp_client::rdp::{RdpClient, RdpInputEvent, RdpOutputEvent};
 use tokio::runtime;
+use tokio::sync::mpsc::UnboundedSender;
 use winit::event_loop::EventLoop;
 
 fn main() -> anyhow::Result<()> {
@@ -15,11 +18,22 @@ fn main() -> anyhow::Result<()> {
 
     setup_logging(config.log_file.as_deref()).context("unable to initialize logging")?;
 
+    if let Some(headless) = config.headless.take() {
+        debug!("Run headless");
+        let rt = runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .context("unable to create tokio runtime")?;
+        let status = rt.block_on(ironrdp_client::headless::run(config, headless));
+        std::process::exit(status.code());
+    }
+
     debug!("Initialize App");
     let event_loop = EventLoop::<RdpOutputEvent>::with_user_event().build()?;
     let event_loop_proxy = event_loop.create_proxy();
     let (input_event_sender, input_event_receiver) = RdpInputEvent::create_channel();
-    let mut app = App::new(&event_loop, &input_event_sender).context("unable to initialize App")?;
+    let mut app = App::new(&event_loop, &input_event_sender, config.resize_debounce, config.scaling)
+        .context("unable to initialize App")?;
 
     // TODO: get window size & scale factor from GUI/App
     let window_size = (1024, 768);
@@ -36,6 +50,8 @@ fn main() -> anyhow::Result<()> {
     // starts and clipboard functionality will not be available.
     #[cfg(windows)]
     let _win_clipboard;
+    #[cfg(target_os = "linux")]
+    let _linux_clipboard;
 
     let cliprdr_factory = match config.clipboard_type {
         ClipboardType::Stub => {
@@ -50,15 +66,45 @@ fn main() -> anyhow::Result<()> {
             use ironrdp_client::clipboard::ClientClipboardMessageProxy;
             use ironrdp_cliprdr_native::WinClipboard;
 
-            let cliprdr = WinClipboard::new(ClientClipboardMessageProxy::new(input_event_sender))?;
+            let cliprdr = WinClipboard::new(ClientClipboardMessageProxy::new(input_event_sender.clone()))?;
 
             let factory = cliprdr.backend_factory();
             _win_clipboard = cliprdr;
             Some(factory)
         }
+        #[cfg(target_os = "linux")]
+        ClipboardType::Linux => {
+            use ironrdp_client::clipboard::ClientClipboardMessageProxy;
+            use ironrdp_cliprdr_native::{LinuxClipboard, LinuxClipboardConfig};
+
+            let clipboard_config = LinuxClipboardConfig {
+                display_server: None,
+                mirror_primary: config.clipboard_mirror_primary,
+            };
+
+            // E.g.: no display server when running headless.
+            match LinuxClipboard::new(
+                ClientClipboardMessageProxy::new(input_event_sender.clone()),
+                clipboard_config,
+            ) {
+                Ok(cliprdr) => {
+                    let factory = cliprdr.backend_factory();
+                    _linux_clipboard = cliprdr;
+                    Some(factory)
+                }
+                Err(error) => {
+                    warn!(%error, "Clipboard is not available");
+                    None
+                }
+            }
+        }
         _ => None,
     };
 
+    if config.drive_commands {
+        std::thread::spawn(move || read_commands(input_event_sender));
+    }
+
     let client = RdpClient {
         config,
         event_loop_proxy,
@@ -76,6 +122,43 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn read_commands(input_event_sender: UnboundedSender<RdpInputEvent>) {
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let event = match line.trim().split_once(' ') {
+            Some(("mount", path)) => RdpInputEvent::MountDrive(PathBuf::from(path.trim())),
+            Some(("unmount", device_id)) => match device_id.trim().parse() {
+                Ok(device_id) => RdpInputEvent::UnmountDrive { device_id },
+                Err(e) => {
+                    warn!(error = %e, "Invalid device ID");
+                    continue;
+                }
+            },
+            Some(("clipboard", policy)) => match parse_clipboard_policy(policy.trim()) {
+                Ok(policy) => RdpInputEvent::SetClipboardPolicy(policy),
+                Err(e) => {
+                    warn!(error = %e, "Invalid clipboard policy");
+                    continue;
+                }
+            },
+            _ => {
+                warn!(
+                    %line,
+                    "Unknown command (expected `mount <PATH>`, `unmount <DEVICE ID>` or `clipboard <POLICY>`)"
+                );
+                continue;
+            }
+        };
+
+        if input_event_sender.send(event).is_err() {
+            break;
+        }
+    }
+}
+
 fn setup_logging(log_file: Option<&str>) -> anyhow::Result<()> {
     use std::fs::OpenOptions;
 
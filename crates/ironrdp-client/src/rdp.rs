@@ -1,31 +1,57 @@
+use core::time::Duration;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
 use ironrdp::cliprdr::backend::{ClipboardMessage, CliprdrBackendFactory};
-use ironrdp::connector::connection_activation::ConnectionActivationState;
+use ironrdp::cliprdr::ClipboardPolicy;
+use ironrdp::connector::redirection::ServerRedirection;
 use ironrdp::connector::{ConnectionResult, ConnectorResult};
 use ironrdp::displaycontrol::client::DisplayControlClient;
 use ironrdp::displaycontrol::pdu::MonitorLayoutEntry;
 use ironrdp::graphics::image_processing::PixelFormat;
+use ironrdp::pdu::geometry::InclusiveRectangle;
 use ironrdp::pdu::input::fast_path::FastPathInputEvent;
+use ironrdp::session::heartbeat::{HealthEvent, HeartbeatPolicy};
 use ironrdp::session::image::DecodedImage;
-use ironrdp::session::{fast_path, ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionResult};
-use ironrdp::{cliprdr, connector, rdpdr, rdpsnd, session};
-use ironrdp_core::WriteBuf;
+use ironrdp::session::{ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionResult};
+use ironrdp::{cliprdr, connector, rail, rdpdr, rdpsnd, session};
 use ironrdp_rdpsnd_native::cpal;
-use ironrdp_tokio::{single_sequence_step_read, split_tokio_framed, FramedWrite};
-use rdpdr::NoopRdpdrBackend;
+use ironrdp_tokio::{split_tokio_framed, ConnectError, ConnectOptions, SessionDriver, DEFAULT_OUTBOUND_CAPACITY};
+use rdpdr::{DrivePolicy, NoopRdpdrBackend, Rdpdr};
 use smallvec::SmallVec;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use winit::event_loop::EventLoopProxy;
 
 use crate::config::Config;
+use crate::credentials::{CredentialStore as _, OsCredentialStore};
+
+/// Maximum number of frames processed for each wake-up of the session loop, so the input events are not delayed
+/// by a long stream of updates.
+const MAX_FRAMES_PER_BATCH: usize = 32;
+
+/// Interval at which the missed server heartbeats are checked.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest beep played, so a single Play Sound PDU can't keep the audio output busy.
+const MAX_BEEP_DURATION: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub enum RdpOutputEvent {
-    Image { buffer: Vec<u32>, width: u16, height: u16 },
+    Image {
+        buffer: Vec<u32>,
+        width: u16,
+        height: u16,
+        /// Region of the image updated since the previous one.
+        region: InclusiveRectangle,
+    },
     ConnectionFailure(connector::ConnectorError),
     PointerDefault,
     PointerHidden,
-    PointerPosition { x: u16, y: u16 },
+    PointerPosition {
+        x: u16,
+        y: u16,
+    },
     Terminated(SessionResult<GracefulDisconnectReason>),
 }
 
@@ -41,6 +67,14 @@ pub enum RdpInputEvent {
     FastPath(SmallVec<[FastPathInputEvent; 2]>),
     Close,
     Clipboard(ClipboardMessage),
+    /// Announces a new drive backed by the folder, and named after it
+    MountDrive(PathBuf),
+    /// Removes a previously mounted drive
+    UnmountDrive {
+        device_id: u32,
+    },
+    /// Changes the directions in which the clipboard is shared, from the next copy
+    SetClipboardPolicy(ClipboardPolicy),
 }
 
 impl RdpInputEvent {
@@ -58,8 +92,16 @@ pub struct RdpClient {
 
 impl RdpClient {
     pub async fn run(mut self) {
+        let mut redirection = None;
+
         loop {
-            let (connection_result, framed) = match connect(&self.config, self.cliprdr_factory.as_deref()).await {
+            let connected = connect(
+                &self.config,
+                self.cliprdr_factory.as_deref(),
+                redirection.take().as_ref(),
+            )
+            .await;
+            let (connection_result, framed) = match connected {
                 Ok(result) => result,
                 Err(e) => {
                     let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
@@ -70,6 +112,9 @@ impl RdpClient {
             match active_session(
                 framed,
                 connection_result,
+                self.config.stats_interval,
+                self.config.heartbeat_policy,
+                self.config.audio_latency.is_some(),
                 &self.event_loop_proxy,
                 &mut self.input_event_receiver,
             )
@@ -79,6 +124,13 @@ impl RdpClient {
                     self.config.connector.desktop_size.width = width;
                     self.config.connector.desktop_size.height = height;
                 }
+                Ok(RdpControlFlow::Reconnect) => {}
+                Ok(RdpControlFlow::Redirect(server_redirection)) => {
+                    if let Some(target) = &server_redirection.target {
+                        self.config.destination = self.config.destination.with_name(target);
+                    }
+                    redirection = Some(server_redirection);
+                }
                 Ok(RdpControlFlow::TerminatedGracefully(reason)) => {
                     let _ = self.event_loop_proxy.send_event(RdpOutputEvent::Terminated(Ok(reason)));
                     break;
@@ -93,83 +145,101 @@ impl RdpClient {
 }
 
 enum RdpControlFlow {
-    ReconnectWithNewSize { width: u16, height: u16 },
+    ReconnectWithNewSize {
+        width: u16,
+        height: u16,
+    },
+    /// The server stopped sending heartbeats, the connection is likely broken.
+    Reconnect,
+    /// The server redirected the client to another server.
+    Redirect(ServerRedirection),
     TerminatedGracefully(GracefulDisconnectReason),
 }
 
-type UpgradedFramed = ironrdp_tokio::TokioFramed<ironrdp_tls::TlsStream<TcpStream>>;
+pub(crate) type UpgradedStream = ironrdp_tokio::ErasedStream;
+
+pub(crate) type UpgradedFramed = ironrdp_tokio::TokioFramed<UpgradedStream>;
 
-async fn connect(
+pub(crate) async fn connect(
     config: &Config,
     cliprdr_factory: Option<&(dyn CliprdrBackendFactory + Send)>,
+    redirection: Option<&ServerRedirection>,
 ) -> ConnectorResult<(ConnectionResult, UpgradedFramed)> {
-    let dest = format!("{}:{}", config.destination.name(), config.destination.port());
-
-    let stream = TcpStream::connect(dest)
-        .await
-        .map_err(|e| connector::custom_err!("TCP connect", e))?;
-
-    let server_addr = stream
-        .peer_addr()
-        .map_err(|e| connector::custom_err!("Peer address", e))?;
-
-    let mut framed = ironrdp_tokio::TokioFramed::new(stream);
-
     let mut connector = connector::ClientConnector::new(config.connector.clone())
-        .with_server_addr(server_addr)
         .with_static_channel(
             ironrdp::dvc::DrdynvcClient::new().with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new()))),
         )
-        .with_static_channel(rdpsnd::client::Rdpsnd::new(Box::new(cpal::RdpsndBackend::new())))
-        .with_static_channel(rdpdr::Rdpdr::new(Box::new(NoopRdpdrBackend {}), "IronRDP".to_owned()).with_smartcard(0));
+        .with_static_channel(rdpsnd::client::Rdpsnd::new(match config.audio_latency {
+            Some(latency) => Box::new(cpal::RdpsndBackend::with_target_latency(latency)),
+            // The channel is still joined, as it is required for the device redirection to work.
+            None => Box::new(rdpsnd::client::NoopRdpsndBackend),
+        }))
+        .with_static_channel(rdpdr_channel(config.drive_commands));
+
+    if let Some(remote_app) = &config.connector.remote_app {
+        let exec = rail::pdu::ExecPdu {
+            flags: rail::pdu::ExecFlags::empty(),
+            exe_or_file: remote_app.program.clone(),
+            working_dir: remote_app.working_dir.clone().unwrap_or_default(),
+            arguments: remote_app.args.clone().unwrap_or_default(),
+        };
+        let desktop_size = config.connector.desktop_size;
+
+        connector.attach_static_channel(rail::client::Rail::with_desktop_size(
+            exec,
+            desktop_size.width,
+            desktop_size.height,
+            Box::new(rail::client::NoopRailBackend),
+        ));
+    }
 
     if let Some(builder) = cliprdr_factory {
         let backend = builder.build_cliprdr_backend();
 
-        let cliprdr = cliprdr::Cliprdr::new(backend);
+        let cliprdr = cliprdr::Cliprdr::new(backend).with_policy(config.clipboard_policy);
 
         connector.attach_static_channel(cliprdr);
     }
 
-    let should_upgrade = ironrdp_tokio::connect_begin(&mut framed, &mut connector).await?;
-
-    debug!("TLS upgrade");
-
-    // Ensure there is no leftover
-    let initial_stream = framed.into_inner_no_leftover();
-
-    let (upgraded_stream, server_public_key) = ironrdp_tls::upgrade(initial_stream, config.destination.name())
-        .await
-        .map_err(|e| connector::custom_err!("TLS upgrade", e))?;
-
-    let upgraded = ironrdp_tokio::mark_as_upgraded(should_upgrade, &mut connector);
+    if let Some(redirection) = redirection {
+        connector.redirect(redirection);
+    }
 
-    let mut upgraded_framed = ironrdp_tokio::TokioFramed::new(upgraded_stream);
+    let options = ConnectOptions::new(connector, config.destination.name(), config.destination.port())
+        .with_network_client(Box::new(crate::network_client::ReqwestNetworkClient::new()));
 
-    let mut network_client = crate::network_client::ReqwestNetworkClient::new();
-    let connection_result = ironrdp_tokio::connect_finalize(
-        upgraded,
-        &mut upgraded_framed,
-        connector,
-        (&config.destination).into(),
-        server_public_key,
-        Some(&mut network_client),
-        None,
-    )
-    .await?;
+    let (upgraded_framed, connection_result) = ironrdp_tokio::connect(options).await.map_err(|e| match e {
+        ConnectError::Connector(e) => e,
+        e => connector::custom_err!("connect", e),
+    })?;
 
     debug!(?connection_result);
 
+    // The password is only stored once it is known to be valid.
+    if config.save_password {
+        if let connector::Credentials::UsernamePassword { username, password } = &config.connector.credentials {
+            match OsCredentialStore.set_password(config.destination.name(), username, password) {
+                Ok(()) => info!("Password stored in the credential store"),
+                Err(error) => warn!(%error, "Unable to store the password in the credential store"),
+            }
+        }
+    }
+
     Ok((connection_result, upgraded_framed))
 }
 
 async fn active_session(
     framed: UpgradedFramed,
     connection_result: ConnectionResult,
+    stats_interval: Option<Duration>,
+    heartbeat_policy: HeartbeatPolicy,
+    play_audio: bool,
     event_loop_proxy: &EventLoopProxy<RdpOutputEvent>,
     input_event_receiver: &mut mpsc::UnboundedReceiver<RdpInputEvent>,
 ) -> SessionResult<RdpControlFlow> {
-    let (mut reader, mut writer) = split_tokio_framed(framed);
+    let (reader, writer) = split_tokio_framed(framed);
+    let (mut driver, writer_task) = SessionDriver::new(reader, writer, DEFAULT_OUTBOUND_CAPACITY);
+    let writer_task = tokio::spawn(writer_task.run());
     let mut image = DecodedImage::new(
         PixelFormat::RgbA32,
         connection_result.desktop_size.width,
@@ -177,14 +247,35 @@ async fn active_session(
     );
 
     let mut active_stage = ActiveStage::new(connection_result);
+    active_stage.set_stats_log_interval(stats_interval);
+    active_stage.set_heartbeat_policy(heartbeat_policy);
+
+    let mut health_check = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    health_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // Device ID 0 is used by the smartcard.
+    let mut next_drive_id = 1;
 
     let disconnect_reason = 'outer: loop {
         let outputs = tokio::select! {
-            frame = reader.read_pdu() => {
-                let (action, payload) = frame.map_err(|e| session::custom_err!("read frame", e))?;
-                trace!(?action, frame_length = payload.len(), "Frame received");
-
-                active_stage.process(&mut image, action, &payload)?
+            batch = driver.read_frames_batch(MAX_FRAMES_PER_BATCH) => {
+                let batch = batch.map_err(|e| session::custom_err!("read frames", e))?;
+                trace!(frame_count = batch.len(), "Frames received");
+
+                let mut outputs = Vec::new();
+                for (action, payload) in batch {
+                    trace!(?action, frame_length = payload.len(), "Process frame");
+                    outputs.extend(active_stage.process(&mut image, action, &payload)?);
+
+                    // The frames following the termination of the session are not relevant anymore.
+                    if matches!(outputs.last(), Some(ActiveStageOutput::Terminate(_))) {
+                        break;
+                    }
+                }
+                outputs
+            }
+            _ = health_check.tick() => {
+                active_stage.poll_connection_health(Instant::now()).into_iter().collect()
             }
             input_event = input_event_receiver.recv() => {
                 let input_event = input_event.ok_or_else(|| session::general_err!("GUI is stopped"))?;
@@ -210,7 +301,7 @@ async fn active_session(
                         active_stage.graceful_shutdown()?
                     }
                     RdpInputEvent::Clipboard(event) => {
-                        if let Some(cliprdr) = active_stage.get_svc_processor::<cliprdr::CliprdrClient>() {
+                        if let Some(cliprdr) = active_stage.get_svc_processor_mut::<cliprdr::CliprdrClient>() {
                             if let Some(svc_messages) = match event {
                                 ClipboardMessage::SendInitiateCopy(formats) => {
                                     Some(cliprdr.initiate_copy(&formats)
@@ -241,17 +332,56 @@ async fn active_session(
                             Vec::new()
                         }
                     }
+                    RdpInputEvent::MountDrive(path) => {
+                        let name = path
+                            .file_name()
+                            .unwrap_or(path.as_os_str())
+                            .to_string_lossy()
+                            .into_owned();
+                        let device_id = next_drive_id;
+
+                        if !back_drive(&mut active_stage, device_id, &path) {
+                            warn!(path = %path.display(), "Drive mount requested, but the folder can't be redirected");
+                            Vec::new()
+                        } else if let Some(frame) = active_stage.announce_drive(device_id, name, DrivePolicy::default()) {
+                            next_drive_id += 1;
+                            info!(device_id, path = %path.display(), "Drive announced");
+                            vec![ActiveStageOutput::ResponseFrame(frame?)]
+                        } else {
+                            warn!("Drive mount requested, but Rdpdr is not available");
+                            Vec::new()
+                        }
+                    }
+                    RdpInputEvent::UnmountDrive { device_id } => {
+                        if let Some(frame) = active_stage.remove_device(device_id) {
+                            forget_drive(&mut active_stage, device_id);
+                            info!(device_id, "Drive removed");
+                            vec![ActiveStageOutput::ResponseFrame(frame?)]
+                        } else {
+                            warn!(device_id, "Drive unmount requested, but no such drive is mounted");
+                            Vec::new()
+                        }
+                    }
+                    RdpInputEvent::SetClipboardPolicy(policy) => {
+                        if active_stage.set_clipboard_policy(policy) {
+                            info!(?policy, "Clipboard policy changed");
+                        } else {
+                            warn!("Clipboard policy change requested, but Cliprdr is not available");
+                        }
+
+                        Vec::new()
+                    }
                 }
             }
         };
 
         for out in outputs {
             match out {
-                ActiveStageOutput::ResponseFrame(frame) => writer
-                    .write_all(&frame)
+                ActiveStageOutput::ResponseFrame(frame) => driver
+                    .send(frame)
                     .await
-                    .map_err(|e| session::custom_err!("write response", e))?,
-                ActiveStageOutput::GraphicsUpdate(_region) => {
+                    .map_err(|e| session::custom_err!("send response", e))?,
+                ActiveStageOutput::GraphicsUpdate(region) => {
                     let buffer: Vec<u32> = image
                         .data()
                         .chunks_exact(4)
@@ -268,6 +398,7 @@ async fn active_session(
                             buffer,
                             width: image.width(),
                             height: image.height(),
+                            region,
                         })
                         .map_err(|e| session::custom_err!("event_loop_proxy", e))?;
                 }
@@ -289,52 +420,151 @@ async fn active_session(
                 ActiveStageOutput::PointerBitmap(_) => {
                     // Not applicable, because we use the software cursor rendering.
                 }
-                ActiveStageOutput::DeactivateAll(mut connection_activation) => {
-                    // Execute the Deactivation-Reactivation Sequence:
-                    // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
-                    debug!("Received Server Deactivate All PDU, executing Deactivation-Reactivation Sequence");
-                    let mut buf = WriteBuf::new();
-                    'activation_seq: loop {
-                        let written = single_sequence_step_read(&mut reader, &mut *connection_activation, &mut buf)
-                            .await
-                            .map_err(|e| session::custom_err!("read deactivation-reactivation sequence step", e))?;
-
-                        if written.size().is_some() {
-                            writer.write_all(buf.filled()).await.map_err(|e| {
-                                session::custom_err!("write deactivation-reactivation sequence step", e)
-                            })?;
-                        }
-
-                        if let ConnectionActivationState::Finalized {
-                            io_channel_id,
-                            user_channel_id,
-                            desktop_size,
-                            no_server_pointer,
-                            pointer_software_rendering,
-                        } = connection_activation.state
-                        {
-                            debug!(?desktop_size, "Deactivation-Reactivation Sequence completed");
-                            // Update image size with the new desktop size.
-                            image = DecodedImage::new(PixelFormat::RgbA32, desktop_size.width, desktop_size.height);
-                            // Update the active stage with the new channel IDs and pointer settings.
-                            active_stage.set_fastpath_processor(
-                                fast_path::ProcessorBuilder {
-                                    io_channel_id,
-                                    user_channel_id,
-                                    no_server_pointer,
-                                    pointer_software_rendering,
-                                }
-                                .build(),
-                            );
-                            active_stage.set_no_server_pointer(no_server_pointer);
-                            break 'activation_seq;
-                        }
+                ActiveStageOutput::DeactivationReactivation { new_desktop_size } => {
+                    // The image was resized by the active stage, and the next graphics updates carry the new size.
+                    debug!(?new_desktop_size, "Deactivation-Reactivation Sequence completed");
+                }
+                ActiveStageOutput::SessionInfo(session_info) => {
+                    info!(?session_info, "Received session information");
+                }
+                ActiveStageOutput::MonitorLayoutChanged(monitors) => {
+                    // As for the reactivation, the image was resized by the active stage.
+                    info!(?monitors, "Monitor layout changed");
+                }
+                ActiveStageOutput::FrameBoundary(frame_id) => {
+                    // The graphics updates are presented as they come.
+                    trace!(frame_id, "Frame completed");
+                }
+                ActiveStageOutput::ConnectionHealth(event) => match event {
+                    HealthEvent::Warning { missed } => warn!(missed, "The server stopped sending heartbeats"),
+                    HealthEvent::ReconnectRequired { missed } => {
+                        warn!(missed, "The server stopped sending heartbeats, reconnecting");
+                        return Ok(RdpControlFlow::Reconnect);
                     }
+                    HealthEvent::Recovered => info!("The server is sending heartbeats again"),
+                },
+                ActiveStageOutput::PlaySound {
+                    frequency_hz,
+                    duration_ms,
+                } => {
+                    beep(play_audio, frequency_hz, duration_ms);
+                }
+                ActiveStageOutput::MultitransportRequest(request) => {
+                    debug!(
+                        request.request_id,
+                        "Declined the UDP side channel requested by the server"
+                    );
+                }
+                ActiveStageOutput::ServerRedirection(redirection) => {
+                    info!(?redirection.target, "Redirected to another server");
+                    return Ok(RdpControlFlow::Redirect(redirection));
                 }
                 ActiveStageOutput::Terminate(reason) => break 'outer reason,
             }
         }
+
+        active_stage.log_stats(Instant::now());
     };
 
+    // The last frames, such as the shutdown request, are written before disconnecting.
+    drop(driver);
+    writer_task
+        .await
+        .map_err(|e| session::custom_err!("writer task", e))?
+        .map_err(|e| session::custom_err!("write frames", e))?;
+
     Ok(RdpControlFlow::TerminatedGracefully(disconnect_reason))
 }
+
+/// Plays the beep requested by the server, or rings the terminal bell when the audio is disabled or can't be played.
+/// Builds the RDPDR channel, offering drive redirection when `drives` is set.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn rdpdr_channel(drives: bool) -> Rdpdr {
+    use ironrdp_rdpdr_native::backend::NixRdpdrBackend;
+
+    if drives {
+        // Each mounted folder is registered with the backend before the drive is announced (see `back_drive`).
+        Rdpdr::new(Box::new(NixRdpdrBackend::new(String::new())), "IronRDP".to_owned())
+            .with_smartcard(0)
+            .with_drives(None)
+    } else {
+        Rdpdr::new(Box::new(NoopRdpdrBackend {}), "IronRDP".to_owned()).with_smartcard(0)
+    }
+}
+
+/// Builds the RDPDR channel, offering drive redirection when `drives` is set.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn rdpdr_channel(drives: bool) -> Rdpdr {
+    if drives {
+        warn!("Drive redirection is not supported on this platform");
+    }
+
+    Rdpdr::new(Box::new(NoopRdpdrBackend {}), "IronRDP".to_owned()).with_smartcard(0)
+}
+
+/// Backs the drive `device_id` with the local folder `path`, returning `false` if the folder can't be redirected.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn back_drive(active_stage: &mut ActiveStage, device_id: u32, path: &Path) -> bool {
+    use ironrdp_rdpdr_native::backend::NixRdpdrBackend;
+
+    let Some(backend) = active_stage
+        .get_svc_processor_mut::<Rdpdr>()
+        .and_then(|rdpdr| rdpdr.downcast_backend_mut::<NixRdpdrBackend>())
+    else {
+        debug!("Drive redirection is not enabled");
+        return false;
+    };
+
+    let Some(root) = path.to_str() else {
+        debug!("The paths of the backend must be valid UTF-8");
+        return false;
+    };
+
+    backend.add_drive(device_id, root.to_owned());
+
+    true
+}
+
+/// Backs the drive `device_id` with the local folder `path`, returning `false` if the folder can't be redirected.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn back_drive(_active_stage: &mut ActiveStage, _device_id: u32, _path: &Path) -> bool {
+    debug!("Drive redirection is not supported on this platform");
+    false
+}
+
+/// Forgets the local folder backing the removed drive `device_id`.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn forget_drive(active_stage: &mut ActiveStage, device_id: u32) {
+    use ironrdp_rdpdr_native::backend::NixRdpdrBackend;
+
+    if let Some(backend) = active_stage
+        .get_svc_processor_mut::<Rdpdr>()
+        .and_then(|rdpdr| rdpdr.downcast_backend_mut::<NixRdpdrBackend>())
+    {
+        backend.remove_drive(device_id);
+    }
+}
+
+/// Forgets the local folder backing the removed drive `device_id`.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn forget_drive(_active_stage: &mut ActiveStage, _device_id: u32) {}
+
+fn beep(play_audio: bool, frequency_hz: u32, duration_ms: u32) {
+    let duration = Duration::from_millis(u64::from(duration_ms)).min(MAX_BEEP_DURATION);
+
+    // Opening the output device may take a while.
+    tokio::task::spawn_blocking(move || {
+        if play_audio {
+            match cpal::play_tone(frequency_hz, duration) {
+                Ok(()) => return,
+                Err(error) => debug!(
+                    error = format!("{error:#}"),
+                    "Failed to play the beep, ringing the bell"
+                ),
+            }
+        }
+
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(b"\x07").and_then(|()| stderr.flush());
+    });
+}
@@ -15,5 +15,10 @@ mod windows;
 #[cfg(windows)]
 pub use crate::windows::{WinClipboard, WinCliprdrError, WinCliprdrResult, HWND};
 
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use crate::linux::{DisplayServer, LinuxClipboard, LinuxClipboardConfig, LinuxCliprdrError, LinuxCliprdrResult};
+
 mod stub;
 pub use crate::stub::{StubClipboard, StubCliprdrBackend};
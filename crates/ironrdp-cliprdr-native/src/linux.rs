@@ -0,0 +1,229 @@
+mod backend;
+mod channel;
+mod formats;
+mod remote;
+mod wayland;
+mod x11;
+
+use std::thread::JoinHandle;
+
+use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy, CliprdrBackend, CliprdrBackendFactory};
+use ironrdp_cliprdr::pdu::{ClipboardFormat, FormatDataRequest, FormatDataResponse};
+use ironrdp_cliprdr_format::bitmap::BitmapError;
+use ironrdp_cliprdr_format::html::HtmlError;
+use thiserror::Error;
+use tracing::{debug, error};
+
+use self::backend::LinuxCliprdrBackend;
+use self::channel::{event_channel, EventReceiver, EventSender};
+use self::wayland::WaylandClipboard;
+use self::x11::X11Clipboard;
+
+/// Delay after which a clipboard data transfer is abandoned.
+const TRANSFER_TIMEOUT_SECS: u64 = 10;
+
+pub type LinuxCliprdrResult<T> = Result<T, LinuxCliprdrError>;
+
+#[derive(Debug, Error)]
+pub enum LinuxCliprdrError {
+    #[error("no display server to share the clipboard with, neither WAYLAND_DISPLAY nor DISPLAY is set")]
+    NoDisplayServer,
+
+    #[error("failed to connect to the X11 server")]
+    X11Connect(#[from] x11rb::errors::ConnectError),
+
+    #[error("X11 connection error")]
+    X11Connection(#[from] x11rb::errors::ConnectionError),
+
+    #[error("X11 request failed")]
+    X11Reply(#[from] x11rb::errors::ReplyError),
+
+    #[error("failed to allocate an X11 resource id")]
+    X11Id(#[from] x11rb::errors::ReplyOrIdError),
+
+    #[error("failed to connect to the Wayland compositor")]
+    WaylandConnect(#[from] wayland_client::ConnectError),
+
+    #[error("Wayland connection error")]
+    Wayland(#[from] wayland_client::backend::WaylandError),
+
+    #[error("failed to dispatch the Wayland events")]
+    WaylandDispatch(#[from] wayland_client::DispatchError),
+
+    #[error("failed to list the Wayland globals")]
+    WaylandGlobals(#[from] wayland_client::globals::GlobalError),
+
+    #[error("the Wayland compositor does not support {0}")]
+    WaylandUnsupported(&'static str),
+
+    #[error("invalid bitmap clipboard data")]
+    Bitmap(#[from] BitmapError),
+
+    #[error("invalid HTML clipboard data")]
+    Html(#[from] HtmlError),
+
+    #[error("invalid text clipboard data")]
+    Text(#[from] ironrdp_core::DecodeError),
+
+    #[error("failed to spawn the clipboard event thread")]
+    WorkerSpawn,
+
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+}
+
+/// Display server with which the clipboard is shared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayServer {
+    /// X11 server, `display` defaulting to the `DISPLAY` environment variable.
+    X11 { display: Option<String> },
+    /// Wayland compositor implementing the `wlr-data-control` protocol, `display` defaulting to the
+    /// `WAYLAND_DISPLAY` environment variable.
+    Wayland { display: Option<String> },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinuxClipboardConfig {
+    /// Display server to use, detected from the environment when `None`.
+    ///
+    /// Wayland is preferred when the compositor implements the `wlr-data-control` protocol, otherwise X11 is used,
+    /// through Xwayland when running in a Wayland session.
+    pub display_server: Option<DisplayServer>,
+    /// Also sets the PRIMARY selection (pasted with the middle button) when the remote clipboard changes.
+    pub mirror_primary: bool,
+}
+
+/// Sent from the clipboard backend shim to the clipboard event thread
+#[derive(Debug)]
+pub(crate) enum BackendEvent {
+    RemoteFormatList(Vec<ClipboardFormat>),
+    FormatDataRequest(FormatDataRequest),
+    FormatDataResponse(FormatDataResponse<'static>),
+    RemoteRequestsFormatList,
+    Shutdown,
+}
+
+/// Parts of the clipboard event thread independent of the display server
+pub(crate) struct ClipboardContext {
+    pub(crate) message_proxy: Box<dyn ClipboardMessageProxy>,
+    pub(crate) events: EventReceiver,
+    pub(crate) mirror_primary: bool,
+}
+
+impl ClipboardContext {
+    pub(crate) fn send_message(&self, message: ClipboardMessage) {
+        self.message_proxy.send_clipboard_message(message);
+    }
+}
+
+/// Linux RDP client clipboard implementation.
+///
+/// The clipboard is shared with the X11 server or the Wayland compositor from a dedicated event thread, which
+/// exchanges the messages with the `CLIPRDR` SVC through the message proxy. The data is only transferred when
+/// pasted: the local applications receive the data of the remote once it is received, and large transfers (INCR on
+/// X11, pipes on Wayland) are performed by the event thread without blocking the `CLIPRDR` SVC.
+///
+/// [`LinuxClipboard`] instance holds ownership of the event thread and should be kept alive during the whole
+/// lifetime of the application.
+pub struct LinuxClipboard {
+    events: EventSender,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LinuxClipboard {
+    /// Connects to the display server and starts the clipboard event thread.
+    pub fn new(
+        message_proxy: impl ClipboardMessageProxy + 'static,
+        config: LinuxClipboardConfig,
+    ) -> LinuxCliprdrResult<Self> {
+        let (events, events_rx) = event_channel()?;
+
+        let display_server = match config.display_server {
+            Some(display_server) => display_server,
+            None => detect_display_server()?,
+        };
+
+        let worker = match display_server {
+            DisplayServer::Wayland { display } => match WaylandClipboard::connect(display.as_deref()) {
+                Ok(clipboard) => Worker::Wayland(clipboard),
+                // Without data control, the compositor only gives access to the clipboard to the focused client.
+                Err(LinuxCliprdrError::WaylandUnsupported(protocol))
+                    if display.is_none() && std::env::var_os("DISPLAY").is_some() =>
+                {
+                    debug!(protocol, "Falling back to the X11 clipboard");
+                    Worker::X11(Box::new(X11Clipboard::connect(None)?))
+                }
+                Err(error) => return Err(error),
+            },
+            DisplayServer::X11 { display } => Worker::X11(Box::new(X11Clipboard::connect(display.as_deref())?)),
+        };
+
+        let context = ClipboardContext {
+            message_proxy: Box::new(message_proxy),
+            events: events_rx,
+            mirror_primary: config.mirror_primary,
+        };
+
+        let thread = std::thread::Builder::new()
+            .name("ironrdp-cliprdr-linux".to_owned())
+            .spawn(move || {
+                let result = match worker {
+                    Worker::X11(clipboard) => clipboard.run(context),
+                    Worker::Wayland(clipboard) => clipboard.run(context),
+                };
+
+                if let Err(error) = result {
+                    error!(%error, "Clipboard event thread failed");
+                }
+            })
+            .map_err(|_| LinuxCliprdrError::WorkerSpawn)?;
+
+        Ok(Self {
+            events,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns clipboard backend factory suitable for making backend instances for `CLIPRDR` SVC.
+    pub fn backend_factory(&self) -> Box<dyn CliprdrBackendFactory + Send> {
+        Box::new(LinuxCliprdrBackendFactory {
+            events: self.events.clone(),
+        })
+    }
+}
+
+impl Drop for LinuxClipboard {
+    fn drop(&mut self) {
+        self.events.send(BackendEvent::Shutdown);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+enum Worker {
+    X11(Box<X11Clipboard>),
+    Wayland(WaylandClipboard),
+}
+
+fn detect_display_server() -> LinuxCliprdrResult<DisplayServer> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Ok(DisplayServer::Wayland { display: None })
+    } else if std::env::var_os("DISPLAY").is_some() {
+        Ok(DisplayServer::X11 { display: None })
+    } else {
+        Err(LinuxCliprdrError::NoDisplayServer)
+    }
+}
+
+/// Linux-specific clipboard backend factory
+struct LinuxCliprdrBackendFactory {
+    events: EventSender,
+}
+
+impl CliprdrBackendFactory for LinuxCliprdrBackendFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        Box::new(LinuxCliprdrBackend::new(self.events.clone()))
+    }
+}
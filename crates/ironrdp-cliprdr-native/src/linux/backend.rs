@@ -0,0 +1,72 @@
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse, LockDataId,
+};
+use ironrdp_core::{impl_as_any, IntoOwned};
+use tracing::debug;
+
+use crate::linux::channel::EventSender;
+use crate::linux::BackendEvent;
+
+#[derive(Debug)]
+pub(crate) struct LinuxCliprdrBackend {
+    events: EventSender,
+}
+
+impl_as_any!(LinuxCliprdrBackend);
+
+impl LinuxCliprdrBackend {
+    pub(crate) fn new(events: EventSender) -> Self {
+        Self { events }
+    }
+}
+
+impl CliprdrBackend for LinuxCliprdrBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        // No additional capabilities yet
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, capabilities: ClipboardGeneralCapabilityFlags) {
+        debug!(?capabilities);
+    }
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        self.events
+            .send(BackendEvent::RemoteFormatList(available_formats.to_vec()));
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) {
+        self.events.send(BackendEvent::FormatDataRequest(request));
+    }
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        self.events
+            .send(BackendEvent::FormatDataResponse(response.into_owned()));
+    }
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {
+        // File transfer not implemented yet
+    }
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {
+        // File transfer not implemented yet
+    }
+
+    fn on_lock(&mut self, _data_id: LockDataId) {
+        // File transfer not implemented yet
+    }
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {
+        // File transfer not implemented yet
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.events.send(BackendEvent::RemoteRequestsFormatList);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{self, Read as _, Write as _};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::net::UnixStream;
+use std::sync::{mpsc, Arc};
+
+use crate::linux::BackendEvent;
+
+/// Creates the channel of the events processed by the clipboard event thread.
+///
+/// The receiving half exposes a file descriptor, readable when events are available, so that the thread waits on
+/// the connection to the display server and on the events at the same time.
+pub(crate) fn event_channel() -> io::Result<(EventSender, EventReceiver)> {
+    let (waker, wakeup) = UnixStream::pair()?;
+    waker.set_nonblocking(true)?;
+    wakeup.set_nonblocking(true)?;
+
+    let (tx, rx) = mpsc::channel();
+
+    Ok((
+        EventSender {
+            tx,
+            waker: Arc::new(waker),
+        },
+        EventReceiver { rx, wakeup },
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EventSender {
+    tx: mpsc::Sender<BackendEvent>,
+    waker: Arc<UnixStream>,
+}
+
+impl EventSender {
+    /// Sends an event without blocking; it is dropped if the event thread exited.
+    pub(crate) fn send(&self, event: BackendEvent) {
+        if self.tx.send(event).is_ok() {
+            // A full socket buffer means that a wake up is already pending.
+            let _ = (&*self.waker).write(&[0]);
+        }
+    }
+}
+
+pub(crate) struct EventReceiver {
+    rx: mpsc::Receiver<BackendEvent>,
+    wakeup: UnixStream,
+}
+
+impl EventReceiver {
+    /// File descriptor readable when events may be available.
+    pub(crate) fn fd(&self) -> BorrowedFd<'_> {
+        self.wakeup.as_fd()
+    }
+
+    /// Returns the events received so far, without blocking.
+    ///
+    /// Returns [`BackendEvent::Shutdown`] once all the senders are dropped.
+    pub(crate) fn drain(&mut self) -> Vec<BackendEvent> {
+        let mut buf = [0; 64];
+        while matches!((&self.wakeup).read(&mut buf), Ok(count) if count > 0) {}
+
+        let mut events = Vec::new();
+
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) => events.push(event),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    events.push(BackendEvent::Shutdown);
+                    break;
+                }
+            }
+        }
+
+        events
+    }
+}
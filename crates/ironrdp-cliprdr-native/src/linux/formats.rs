@@ -0,0 +1,347 @@
+//! Mapping between the MIME types (or X11 targets) of the local clipboard and the `CLIPRDR` formats
+//!
+//! The same formats as the web client are exchanged: plain text, HTML and PNG images. The remote formats are
+//! converted when pasted locally, e.g.: a `CF_DIB` bitmap is offered locally as `image/png`.
+
+use ironrdp_cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, ClipboardFormatName, FormatDataResponse};
+use ironrdp_cliprdr_format::bitmap::{dib_to_png, dibv5_to_png, png_to_cf_dibv5};
+use ironrdp_cliprdr_format::html::{cf_html_to_plain_html, plain_html_to_cf_html};
+
+use crate::linux::LinuxCliprdrResult;
+
+const FORMAT_WIN_HTML_ID: ClipboardFormatId = ClipboardFormatId(0xC001);
+const FORMAT_MIME_HTML_ID: ClipboardFormatId = ClipboardFormatId(0xC002);
+const FORMAT_PNG_ID: ClipboardFormatId = ClipboardFormatId(0xC003);
+const FORMAT_MIME_PNG_ID: ClipboardFormatId = ClipboardFormatId(0xC004);
+
+const FORMAT_WIN_HTML_NAME: &str = "HTML Format";
+const FORMAT_MIME_HTML_NAME: &str = "text/html";
+const FORMAT_PNG_NAME: &str = "PNG";
+const FORMAT_MIME_PNG_NAME: &str = "image/png";
+
+/// Text targets, by order of preference.
+///
+/// `STRING` is Latin-1, and `TEXT` is left to the selection owner.
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "TEXT",
+    "STRING",
+];
+const HTML_MIME_TYPES: &[&str] = &["text/html"];
+const PNG_MIME_TYPES: &[&str] = &["image/png"];
+
+const LATIN1_MIME_TYPE: &str = "STRING";
+
+/// Kind of content exchanged with the remote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ContentKind {
+    Text,
+    Html,
+    Png,
+}
+
+impl ContentKind {
+    const ALL: [Self; 3] = [Self::Text, Self::Html, Self::Png];
+
+    pub(crate) fn from_mime_type(mime_type: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.mime_types().contains(&mime_type))
+    }
+
+    /// MIME types under which the content is offered locally, by order of preference.
+    pub(crate) fn mime_types(self) -> &'static [&'static str] {
+        match self {
+            Self::Text => TEXT_MIME_TYPES,
+            Self::Html => HTML_MIME_TYPES,
+            Self::Png => PNG_MIME_TYPES,
+        }
+    }
+
+    fn from_local_format(format: ClipboardFormatId) -> Option<Self> {
+        match format {
+            ClipboardFormatId::CF_UNICODETEXT => Some(Self::Text),
+            FORMAT_WIN_HTML_ID | FORMAT_MIME_HTML_ID => Some(Self::Html),
+            ClipboardFormatId::CF_DIBV5 | FORMAT_PNG_ID | FORMAT_MIME_PNG_ID => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Returns all the MIME types exchanged with the remote.
+pub(crate) fn all_mime_types() -> impl Iterator<Item = &'static str> {
+    ContentKind::ALL
+        .into_iter()
+        .flat_map(|kind| kind.mime_types().iter().copied())
+}
+
+/// Returns the formats announced to the remote for the MIME types offered by the local clipboard.
+pub(crate) fn local_formats(mime_types: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<ClipboardFormat> {
+    let mut kinds = Vec::new();
+    for kind in mime_types
+        .into_iter()
+        .filter_map(|mime_type| ContentKind::from_mime_type(mime_type.as_ref()))
+    {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    let mut formats = Vec::new();
+
+    for kind in kinds {
+        match kind {
+            ContentKind::Text => formats.push(ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)),
+            ContentKind::Html => formats.extend([
+                registered_format(FORMAT_WIN_HTML_ID, FORMAT_WIN_HTML_NAME),
+                registered_format(FORMAT_MIME_HTML_ID, FORMAT_MIME_HTML_NAME),
+            ]),
+            ContentKind::Png => formats.extend([
+                // We don't provide CF_DIB, because it could be synthesized from
+                // CF_DIBV5 on the remote side.
+                ClipboardFormat::new(ClipboardFormatId::CF_DIBV5),
+                registered_format(FORMAT_PNG_ID, FORMAT_PNG_NAME),
+                registered_format(FORMAT_MIME_PNG_ID, FORMAT_MIME_PNG_NAME),
+            ]),
+        }
+    }
+
+    formats
+}
+
+fn registered_format(id: ClipboardFormatId, name: &'static str) -> ClipboardFormat {
+    ClipboardFormat::new(id).with_name(ClipboardFormatName::new_static(name))
+}
+
+/// Returns the MIME type to read from the local clipboard for the format requested by the remote, among the
+/// offered ones.
+pub(crate) fn local_mime_type_for_format(
+    format: ClipboardFormatId,
+    is_offered: impl Fn(&str) -> bool,
+) -> Option<&'static str> {
+    let kind = ContentKind::from_local_format(format)?;
+
+    kind.mime_types()
+        .iter()
+        .copied()
+        .find(|mime_type| is_offered(mime_type))
+}
+
+/// Converts the data read from the local clipboard into the format requested by the remote.
+pub(crate) fn local_to_remote(
+    format: ClipboardFormatId,
+    mime_type: &str,
+    data: &[u8],
+) -> LinuxCliprdrResult<FormatDataResponse<'static>> {
+    let response = match format {
+        ClipboardFormatId::CF_UNICODETEXT => {
+            let text = decode_local_text(mime_type, data);
+            FormatDataResponse::new_unicode_string(&text.replace('\n', "\r\n"))
+        }
+        FORMAT_WIN_HTML_ID => {
+            FormatDataResponse::new_data(plain_html_to_cf_html(&String::from_utf8_lossy(data)).into_bytes())
+        }
+        FORMAT_MIME_HTML_ID => FormatDataResponse::new_string(&String::from_utf8_lossy(data)),
+        ClipboardFormatId::CF_DIBV5 => FormatDataResponse::new_data(png_to_cf_dibv5(data)?),
+        _ => FormatDataResponse::new_data(data.to_vec()),
+    };
+
+    Ok(response)
+}
+
+fn decode_local_text(mime_type: &str, data: &[u8]) -> String {
+    let text = if mime_type == LATIN1_MIME_TYPE {
+        data.iter().copied().map(char::from).collect()
+    } else {
+        String::from_utf8_lossy(data).into_owned()
+    };
+
+    // Windows line endings are restored when sent to the remote.
+    text.replace("\r\n", "\n")
+}
+
+/// Encodes the text received from the remote for the MIME type requested locally.
+pub(crate) fn encode_local_text(mime_type: &str, text: &str) -> Vec<u8> {
+    if mime_type == LATIN1_MIME_TYPE {
+        text.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect()
+    } else {
+        text.as_bytes().to_vec()
+    }
+}
+
+/// Encoding of the data of a remote format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteEncoding {
+    UnicodeText,
+    CfHtml,
+    Html,
+    Dib,
+    DibV5,
+    Png,
+}
+
+/// Remote format fetched for a kind of content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RemoteFormat {
+    pub(crate) id: ClipboardFormatId,
+    encoding: RemoteEncoding,
+}
+
+impl RemoteFormat {
+    /// Converts the data received from the remote for the local clipboard.
+    ///
+    /// Text content is returned as UTF-8, and is re-encoded with [`encode_local_text`].
+    pub(crate) fn to_local(self, response: &FormatDataResponse<'_>) -> LinuxCliprdrResult<Vec<u8>> {
+        let data = match self.encoding {
+            RemoteEncoding::UnicodeText => response.to_unicode_string()?.replace("\r\n", "\n").into_bytes(),
+            RemoteEncoding::CfHtml => cf_html_to_plain_html(response.data())?.as_bytes().to_vec(),
+            RemoteEncoding::Html => response.to_string()?.into_bytes(),
+            RemoteEncoding::Dib => dib_to_png(response.data())?,
+            RemoteEncoding::DibV5 => dibv5_to_png(response.data())?,
+            RemoteEncoding::Png => response.data().to_vec(),
+        };
+
+        Ok(data)
+    }
+}
+
+/// Formats fetched from the remote for each kind of content, picked among the announced ones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RemoteFormats {
+    text: Option<RemoteFormat>,
+    html: Option<RemoteFormat>,
+    png: Option<RemoteFormat>,
+}
+
+impl RemoteFormats {
+    pub(crate) fn new(formats: &[ClipboardFormat]) -> Self {
+        // By order of preference: the formats requiring no conversion come first.
+        let find = |candidates: &[(Option<ClipboardFormatId>, Option<&str>, RemoteEncoding)]| {
+            candidates.iter().find_map(|(id, name, encoding)| {
+                formats
+                    .iter()
+                    .find(|format| match (id, name) {
+                        (Some(id), _) => format.id() == *id,
+                        (None, Some(name)) => format.name().is_some_and(|actual| actual.value() == *name),
+                        (None, None) => false,
+                    })
+                    .map(|format| RemoteFormat {
+                        id: format.id(),
+                        encoding: *encoding,
+                    })
+            })
+        };
+
+        Self {
+            text: find(&[(
+                Some(ClipboardFormatId::CF_UNICODETEXT),
+                None,
+                RemoteEncoding::UnicodeText,
+            )]),
+            html: find(&[
+                (None, Some(FORMAT_MIME_HTML_NAME), RemoteEncoding::Html),
+                (None, Some(FORMAT_WIN_HTML_NAME), RemoteEncoding::CfHtml),
+            ]),
+            png: find(&[
+                (None, Some(FORMAT_PNG_NAME), RemoteEncoding::Png),
+                (None, Some(FORMAT_MIME_PNG_NAME), RemoteEncoding::Png),
+                (Some(ClipboardFormatId::CF_DIBV5), None, RemoteEncoding::DibV5),
+                (Some(ClipboardFormatId::CF_DIB), None, RemoteEncoding::Dib),
+            ]),
+        }
+    }
+
+    pub(crate) fn get(&self, kind: ContentKind) -> Option<RemoteFormat> {
+        match kind {
+            ContentKind::Text => self.text,
+            ContentKind::Html => self.html,
+            ContentKind::Png => self.png,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.mime_types().next().is_none()
+    }
+
+    /// MIME types offered locally for the content of the remote.
+    pub(crate) fn mime_types(&self) -> impl Iterator<Item = &'static str> + '_ {
+        ContentKind::ALL
+            .into_iter()
+            .filter(|kind| self.get(*kind).is_some())
+            .flat_map(|kind| kind.mime_types().iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_text_and_image_are_announced() {
+        let formats = local_formats(["TARGETS", "UTF8_STRING", "STRING", "image/png", "image/bmp"]);
+        let ids: Vec<_> = formats.iter().map(ClipboardFormat::id).collect();
+
+        assert_eq!(
+            ids,
+            [
+                ClipboardFormatId::CF_UNICODETEXT,
+                ClipboardFormatId::CF_DIBV5,
+                FORMAT_PNG_ID,
+                FORMAT_MIME_PNG_ID
+            ]
+        );
+    }
+
+    #[test]
+    fn preferred_local_text_target_is_read() {
+        let offered = ["STRING", "UTF8_STRING", "TEXT"];
+        let is_offered = |mime_type: &str| offered.contains(&mime_type);
+
+        assert_eq!(
+            local_mime_type_for_format(ClipboardFormatId::CF_UNICODETEXT, is_offered),
+            Some("UTF8_STRING")
+        );
+        assert_eq!(local_mime_type_for_format(FORMAT_WIN_HTML_ID, is_offered), None);
+    }
+
+    #[test]
+    fn local_text_is_sent_with_windows_line_endings() {
+        let response = local_to_remote(ClipboardFormatId::CF_UNICODETEXT, "STRING", b"caf\xe9\nbar").unwrap();
+
+        assert_eq!(response, FormatDataResponse::new_unicode_string("café\r\nbar"));
+    }
+
+    #[test]
+    fn remote_image_prefers_png() {
+        let formats = RemoteFormats::new(&[
+            ClipboardFormat::new(ClipboardFormatId::CF_DIB),
+            ClipboardFormat::new(ClipboardFormatId::CF_DIBV5),
+            ClipboardFormat::new(ClipboardFormatId(0xC0AB)).with_name(ClipboardFormatName::new_static("PNG")),
+        ]);
+
+        assert_eq!(
+            formats.get(ContentKind::Png),
+            Some(RemoteFormat {
+                id: ClipboardFormatId(0xC0AB),
+                encoding: RemoteEncoding::Png
+            })
+        );
+        assert_eq!(formats.get(ContentKind::Text), None);
+        assert_eq!(formats.mime_types().collect::<Vec<_>>(), ["image/png"]);
+    }
+
+    #[test]
+    fn remote_text_is_received_with_unix_line_endings() {
+        let formats = RemoteFormats::new(&[ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]);
+        let format = formats.get(ContentKind::Text).unwrap();
+
+        let text = format
+            .to_local(&FormatDataResponse::new_unicode_string("a\r\nb"))
+            .unwrap();
+
+        assert_eq!(text, b"a\nb");
+        assert_eq!(encode_local_text("STRING", "€é"), b"?\xe9");
+    }
+}
@@ -0,0 +1,144 @@
+use core::time::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use ironrdp_cliprdr::pdu::{ClipboardFormatId, FormatDataResponse};
+use tracing::{debug, warn};
+
+use crate::linux::formats::{ContentKind, RemoteFormat, RemoteFormats};
+use crate::linux::TRANSFER_TIMEOUT_SECS;
+
+/// Result of [`RemoteClipboard::fetch`]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Fetch {
+    /// The data was received already, see [`RemoteClipboard::cached`].
+    Ready,
+    /// The data is being received.
+    Pending,
+    /// The data must be requested from the remote, in the given format.
+    Start(ClipboardFormatId),
+    /// The remote does not provide this kind of content.
+    Unavailable,
+}
+
+/// Format data request sent to the remote
+struct InFlight {
+    generation: u64,
+    kind: ContentKind,
+    format: RemoteFormat,
+    deadline: Instant,
+    expired: bool,
+}
+
+/// Content of the remote clipboard, fetched when pasted locally
+///
+/// The data is converted for the local clipboard once received, and kept until the remote clipboard changes.
+#[derive(Default)]
+pub(crate) struct RemoteClipboard {
+    formats: RemoteFormats,
+    /// Incremented each time the remote clipboard changes.
+    generation: u64,
+    cache: HashMap<ContentKind, Vec<u8>>,
+    /// Requests sent to the remote, which answers them in order.
+    in_flight: VecDeque<InFlight>,
+}
+
+impl RemoteClipboard {
+    pub(crate) fn formats(&self) -> &RemoteFormats {
+        &self.formats
+    }
+
+    /// Replaces the content of the remote clipboard; the responses to the pending requests are discarded.
+    pub(crate) fn set_formats(&mut self, formats: RemoteFormats) {
+        self.formats = formats;
+        self.generation = self.generation.wrapping_add(1);
+        self.cache.clear();
+    }
+
+    pub(crate) fn cached(&self, kind: ContentKind) -> Option<&[u8]> {
+        self.cache.get(&kind).map(Vec::as_slice)
+    }
+
+    pub(crate) fn fetch(&mut self, kind: ContentKind) -> Fetch {
+        let Some(format) = self.formats.get(kind) else {
+            return Fetch::Unavailable;
+        };
+
+        if self.cache.contains_key(&kind) {
+            return Fetch::Ready;
+        }
+
+        let pending = self
+            .in_flight
+            .iter()
+            .any(|request| request.generation == self.generation && request.kind == kind && !request.expired);
+
+        if pending {
+            return Fetch::Pending;
+        }
+
+        self.in_flight.push_back(InFlight {
+            generation: self.generation,
+            kind,
+            format,
+            deadline: Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS),
+            expired: false,
+        });
+
+        Fetch::Start(format.id)
+    }
+
+    /// Processes the response to the oldest request.
+    ///
+    /// Returns the kind of the received content, available with [`RemoteClipboard::cached`] unless it could not be
+    /// converted, or `None` if the response is stale.
+    pub(crate) fn on_response(&mut self, response: &FormatDataResponse<'_>) -> Option<ContentKind> {
+        let Some(request) = self.in_flight.pop_front() else {
+            warn!("Remote returned format data, but no formats were requested");
+            return None;
+        };
+
+        if request.generation != self.generation {
+            debug!("Dropping stale format data response");
+            return None;
+        }
+
+        if response.is_error() {
+            // Format is not available anymore.
+            return Some(request.kind);
+        }
+
+        match request.format.to_local(response) {
+            Ok(data) => {
+                self.cache.insert(request.kind, data);
+            }
+            Err(error) => warn!(%error, kind = ?request.kind, "Failed to convert the remote clipboard data"),
+        }
+
+        Some(request.kind)
+    }
+
+    /// Returns the kinds of content whose requests are timed out.
+    ///
+    /// The remote may still answer, the requests are kept to match the responses.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<ContentKind> {
+        let generation = self.generation;
+
+        self.in_flight
+            .iter_mut()
+            .filter(|request| !request.expired && request.deadline <= now)
+            .filter_map(|request| {
+                request.expired = true;
+                (request.generation == generation).then_some(request.kind)
+            })
+            .collect()
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.in_flight
+            .iter()
+            .filter(|request| !request.expired)
+            .map(|request| request.deadline)
+            .min()
+    }
+}
@@ -0,0 +1,1001 @@
+//! Clipboard of the Wayland compositors implementing the `wlr-data-control` protocol
+//!
+//! Unlike `wl_data_device`, which only gives access to the clipboard to the client having the keyboard focus, data
+//! control lets the event thread follow and set the selection at any time.
+
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::io;
+use std::os::fd::{AsFd as _, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use ironrdp_cliprdr::backend::ClipboardMessage;
+use ironrdp_cliprdr::pdu::{ClipboardFormatId, FormatDataRequest, FormatDataResponse};
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::io::Errno;
+use rustix::pipe::{pipe_with, PipeFlags};
+use tracing::{debug, trace, warn};
+use wayland_client::backend::WaylandError;
+use wayland_client::globals::{registry_queue_init, BindError, GlobalListContents};
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{delegate_noop, event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1};
+
+use crate::linux::formats::{self, ContentKind, RemoteFormats};
+use crate::linux::remote::{Fetch, RemoteClipboard};
+use crate::linux::{BackendEvent, ClipboardContext, LinuxCliprdrError, LinuxCliprdrResult, TRANSFER_TIMEOUT_SECS};
+
+/// Offered with the content of the remote, to recognize our own selection.
+const SELECTION_MARKER_MIME_TYPE: &str = "application/x-ironrdp-clipboard";
+
+/// `zwlr_data_control_device_v1::set_primary_selection` was introduced in version 2.
+const PRIMARY_SELECTION_SINCE: u32 = 2;
+
+const PIPE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Connection to the Wayland compositor, with a data control device for the first seat
+pub(crate) struct WaylandClipboard {
+    queue: EventQueue<WaylandState>,
+    connection: Connection,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+}
+
+impl WaylandClipboard {
+    pub(crate) fn connect(display: Option<&str>) -> LinuxCliprdrResult<Self> {
+        let connection = match display {
+            Some(display) => {
+                let mut path = PathBuf::from(display);
+                if path.is_relative() {
+                    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").ok_or(LinuxCliprdrError::Io(
+                        io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"),
+                    ))?;
+                    path = PathBuf::from(runtime_dir).join(path);
+                }
+
+                Connection::from_socket(UnixStream::connect(path)?)?
+            }
+            None => Connection::connect_to_env()?,
+        };
+
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(connection: Connection) -> LinuxCliprdrResult<Self> {
+        let (globals, queue) = registry_queue_init::<WaylandState>(&connection)?;
+        let qh = queue.handle();
+
+        let seat = globals
+            .bind::<WlSeat, _, _>(&qh, 1..=1, ())
+            .map_err(|error| unsupported(error, "wl_seat"))?;
+        let manager = globals
+            .bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=PRIMARY_SELECTION_SINCE, ())
+            .map_err(|error| unsupported(error, "wlr-data-control"))?;
+
+        let device = manager.get_data_device(&seat, &qh, ());
+        connection.flush()?;
+
+        Ok(Self {
+            queue,
+            connection,
+            manager,
+            device,
+        })
+    }
+
+    /// Processes the events until [`BackendEvent::Shutdown`] is received.
+    pub(crate) fn run(self, context: ClipboardContext) -> LinuxCliprdrResult<()> {
+        let Self {
+            mut queue,
+            connection,
+            manager,
+            device,
+        } = self;
+
+        if context.mirror_primary && device.version() < PRIMARY_SELECTION_SINCE {
+            warn!("The Wayland compositor does not support setting the PRIMARY selection");
+        }
+
+        let mut state = WaylandState {
+            context,
+            qh: queue.handle(),
+            manager,
+            device,
+            remote: RemoteClipboard::default(),
+            owner: false,
+            selection: None,
+            local_mime_types: Vec::new(),
+            reads: VecDeque::new(),
+            current_read: None,
+            waiting: Vec::new(),
+            writes: Vec::new(),
+            finished: false,
+        };
+
+        loop {
+            queue.dispatch_pending(&mut state)?;
+
+            if state.finished {
+                warn!("The Wayland data control device was destroyed by the compositor");
+                return Ok(());
+            }
+
+            match connection.flush() {
+                // The remaining requests are sent once the socket is writable again.
+                Err(WaylandError::Io(error)) if error.kind() == io::ErrorKind::WouldBlock => {}
+                result => result?,
+            }
+
+            let Some(guard) = queue.prepare_read() else {
+                // Events were queued in the meantime.
+                continue;
+            };
+
+            let timeout = state.next_deadline().map_or(-1, |deadline| {
+                let timeout = deadline.saturating_duration_since(Instant::now()).as_millis();
+                // Rounded up, the deadline being checked after the wake up.
+                i32::try_from(timeout.saturating_add(1)).unwrap_or(i32::MAX)
+            });
+
+            let readable = {
+                let connection_fd = guard.connection_fd();
+                let events_fd = state.context.events.fd();
+
+                let mut fds = vec![
+                    PollFd::new(&connection_fd, PollFlags::IN),
+                    PollFd::new(&events_fd, PollFlags::IN),
+                ];
+                if let Some(read) = &state.current_read {
+                    fds.push(PollFd::new(&read.pipe, PollFlags::IN));
+                }
+                for write in &state.writes {
+                    fds.push(PollFd::new(&write.pipe, PollFlags::OUT));
+                }
+
+                match poll(&mut fds, timeout) {
+                    Ok(_) | Err(Errno::INTR) => {}
+                    Err(error) => return Err(io::Error::from(error).into()),
+                }
+
+                !fds[0].revents().is_empty()
+            };
+
+            if readable {
+                match guard.read() {
+                    Err(WaylandError::Io(error)) if error.kind() == io::ErrorKind::WouldBlock => {}
+                    result => {
+                        result?;
+                    }
+                }
+            } else {
+                drop(guard);
+            }
+
+            for event in state.context.events.drain() {
+                match event {
+                    BackendEvent::Shutdown => return Ok(()),
+                    event => state.on_backend_event(event)?,
+                }
+            }
+
+            state.progress_read()?;
+            state.progress_writes();
+            state.expire(Instant::now())?;
+        }
+    }
+}
+
+fn unsupported(error: BindError, protocol: &'static str) -> LinuxCliprdrError {
+    match error {
+        BindError::NotPresent | BindError::UnsupportedVersion => LinuxCliprdrError::WaylandUnsupported(protocol),
+    }
+}
+
+/// MIME types of a data offer, received right after its creation
+#[derive(Debug, Default)]
+struct OfferMimeTypes(Mutex<Vec<String>>);
+
+impl OfferMimeTypes {
+    fn get(offer: &ZwlrDataControlOfferV1) -> Vec<String> {
+        offer
+            .data::<Self>()
+            .map(|mime_types| mime_types.0.lock().expect("poisoned").clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Read of the selection of a local application, requested by the remote
+struct LocalRead {
+    format: ClipboardFormatId,
+    mime_type: &'static str,
+}
+
+struct CurrentRead {
+    format: ClipboardFormatId,
+    mime_type: &'static str,
+    pipe: OwnedFd,
+    data: Vec<u8>,
+    deadline: Instant,
+}
+
+/// Local paste waiting for the data of the remote
+struct WaitingWrite {
+    pipe: OwnedFd,
+    kind: ContentKind,
+    mime_type: &'static str,
+}
+
+/// Data of the remote written to a local application
+struct LocalWrite {
+    pipe: OwnedFd,
+    data: Vec<u8>,
+    offset: usize,
+    deadline: Instant,
+}
+
+struct WaylandState {
+    context: ClipboardContext,
+    qh: QueueHandle<Self>,
+    manager: ZwlrDataControlManagerV1,
+    device: ZwlrDataControlDeviceV1,
+    remote: RemoteClipboard,
+    /// Whether the selection holds the content of the remote.
+    owner: bool,
+    /// Selection of a local application.
+    selection: Option<ZwlrDataControlOfferV1>,
+    local_mime_types: Vec<String>,
+    /// Pending reads of the local selection, performed one at a time.
+    reads: VecDeque<LocalRead>,
+    current_read: Option<CurrentRead>,
+    waiting: Vec<WaitingWrite>,
+    writes: Vec<LocalWrite>,
+    finished: bool,
+}
+
+impl WaylandState {
+    fn next_deadline(&self) -> Option<Instant> {
+        let reads = self.current_read.as_ref().map(|read| read.deadline);
+        let writes = self.writes.iter().map(|write| write.deadline);
+
+        reads.into_iter().chain(writes).chain(self.remote.next_deadline()).min()
+    }
+
+    fn on_backend_event(&mut self, event: BackendEvent) -> LinuxCliprdrResult<()> {
+        match event {
+            BackendEvent::RemoteFormatList(formats) => self.on_remote_format_list(RemoteFormats::new(&formats)),
+            BackendEvent::FormatDataRequest(request) => self.on_format_data_request(request)?,
+            BackendEvent::FormatDataResponse(response) => self.on_format_data_response(&response),
+            BackendEvent::RemoteRequestsFormatList => {
+                if !self.owner {
+                    self.announce_local();
+                }
+            }
+            BackendEvent::Shutdown => {}
+        }
+
+        Ok(())
+    }
+
+    fn announce_local(&self) {
+        let formats = formats::local_formats(&self.local_mime_types);
+        trace!(?formats, "Sending clipboard formats");
+        self.context.send_message(ClipboardMessage::SendInitiateCopy(formats));
+    }
+
+    fn on_selection(&mut self, offer: Option<ZwlrDataControlOfferV1>) {
+        if let Some(previous) = self.selection.take() {
+            previous.destroy();
+        }
+
+        let mime_types = offer.as_ref().map(OfferMimeTypes::get).unwrap_or_default();
+
+        if mime_types
+            .iter()
+            .any(|mime_type| mime_type == SELECTION_MARKER_MIME_TYPE)
+        {
+            // Our own selection, holding the content of the remote.
+            if let Some(offer) = offer {
+                offer.destroy();
+            }
+            return;
+        }
+
+        self.owner = false;
+        self.selection = offer;
+        self.local_mime_types = mime_types;
+        self.announce_local();
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) -> LinuxCliprdrResult<()> {
+        let mime_type = if self.owner {
+            // The remote requests the data of an outdated format list.
+            None
+        } else {
+            formats::local_mime_type_for_format(request.format, |mime_type| {
+                self.local_mime_types.iter().any(|offered| offered == mime_type)
+            })
+        };
+
+        match mime_type {
+            Some(mime_type) => {
+                self.reads.push_back(LocalRead {
+                    format: request.format,
+                    mime_type,
+                });
+                self.start_next_read()
+            }
+            None => {
+                self.context
+                    .send_message(ClipboardMessage::SendFormatData(FormatDataResponse::new_error()));
+                Ok(())
+            }
+        }
+    }
+
+    fn start_next_read(&mut self) -> LinuxCliprdrResult<()> {
+        while self.current_read.is_none() {
+            let Some(read) = self.reads.pop_front() else {
+                return Ok(());
+            };
+
+            let Some(offer) = &self.selection else {
+                self.context
+                    .send_message(ClipboardMessage::SendFormatData(FormatDataResponse::new_error()));
+                continue;
+            };
+
+            let (pipe, write_end) = pipe_with(PipeFlags::CLOEXEC | PipeFlags::NONBLOCK).map_err(io::Error::from)?;
+            offer.receive(read.mime_type.to_owned(), write_end.as_fd());
+            // The file descriptor is duplicated when the request is queued: the application closes the last write
+            // end once all the data is written.
+            drop(write_end);
+
+            self.current_read = Some(CurrentRead {
+                format: read.format,
+                mime_type: read.mime_type,
+                pipe,
+                data: Vec::new(),
+                deadline: Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn progress_read(&mut self) -> LinuxCliprdrResult<()> {
+        let Some(current) = self.current_read.as_mut() else {
+            return Ok(());
+        };
+
+        let mut buffer = vec![0; PIPE_BUFFER_SIZE];
+
+        let complete = loop {
+            match rustix::io::read(&current.pipe, &mut buffer) {
+                Ok(0) => break Some(true),
+                Ok(read) => current.data.extend_from_slice(&buffer[..read]),
+                Err(Errno::INTR) => {}
+                Err(Errno::AGAIN) => break None,
+                Err(error) => {
+                    warn!(%error, "Failed to read the local clipboard");
+                    break Some(false);
+                }
+            }
+        };
+
+        match complete {
+            Some(complete) => self.finish_read(complete),
+            None => Ok(()),
+        }
+    }
+
+    fn finish_read(&mut self, complete: bool) -> LinuxCliprdrResult<()> {
+        let Some(read) = self.current_read.take() else {
+            return Ok(());
+        };
+
+        let response = if complete {
+            formats::local_to_remote(read.format, read.mime_type, &read.data).unwrap_or_else(|error| {
+                warn!(%error, mime_type = read.mime_type, "Failed to convert the local clipboard data");
+                FormatDataResponse::new_error()
+            })
+        } else {
+            FormatDataResponse::new_error()
+        };
+
+        self.context.send_message(ClipboardMessage::SendFormatData(response));
+
+        self.start_next_read()
+    }
+
+    fn on_remote_format_list(&mut self, formats: RemoteFormats) {
+        self.remote.set_formats(formats);
+
+        // The local pastes were for the previous content of the remote, closing the pipes cancels them.
+        self.waiting.clear();
+
+        if self.remote.formats().is_empty() {
+            if self.owner {
+                self.device.set_selection(None);
+                if self.context.mirror_primary && self.device.version() >= PRIMARY_SELECTION_SINCE {
+                    self.device.set_primary_selection(None);
+                }
+                self.owner = false;
+            }
+            return;
+        }
+
+        let source = self.create_source();
+        self.device.set_selection(Some(&source));
+
+        if self.context.mirror_primary && self.device.version() >= PRIMARY_SELECTION_SINCE {
+            let source = self.create_source();
+            self.device.set_primary_selection(Some(&source));
+        }
+
+        self.owner = true;
+    }
+
+    fn create_source(&self) -> ZwlrDataControlSourceV1 {
+        let source = self.manager.create_data_source(&self.qh, ());
+
+        for mime_type in self.remote.formats().mime_types() {
+            source.offer(mime_type.to_owned());
+        }
+        source.offer(SELECTION_MARKER_MIME_TYPE.to_owned());
+
+        source
+    }
+
+    fn on_send(&mut self, mime_type: &str, pipe: OwnedFd) {
+        let Some(kind) = ContentKind::from_mime_type(mime_type) else {
+            debug!(mime_type, "Unexpected MIME type requested");
+            return;
+        };
+
+        let Some(mime_type) = kind
+            .mime_types()
+            .iter()
+            .copied()
+            .find(|candidate| *candidate == mime_type)
+        else {
+            return;
+        };
+
+        if let Err(error) = rustix::io::ioctl_fionbio(&pipe, true) {
+            warn!(%error, "Failed to set the pipe as non-blocking");
+            return;
+        }
+
+        match self.remote.fetch(kind) {
+            Fetch::Ready => {
+                let data = self.remote.cached(kind).unwrap_or_default();
+                let data = encode(kind, mime_type, data);
+                self.start_write(pipe, data);
+            }
+            Fetch::Pending => self.waiting.push(WaitingWrite { pipe, kind, mime_type }),
+            Fetch::Start(format) => {
+                self.context.send_message(ClipboardMessage::SendInitiatePaste(format));
+                self.waiting.push(WaitingWrite { pipe, kind, mime_type });
+            }
+            // Closing the pipe cancels the paste.
+            Fetch::Unavailable => {}
+        }
+    }
+
+    fn on_format_data_response(&mut self, response: &FormatDataResponse<'_>) {
+        let Some(kind) = self.remote.on_response(response) else {
+            return;
+        };
+
+        let (ready, waiting) = core::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition::<Vec<_>, _>(|waiting| waiting.kind == kind);
+        self.waiting = waiting;
+
+        let Some(data) = self.remote.cached(kind) else {
+            return;
+        };
+
+        let writes = ready
+            .into_iter()
+            .map(|waiting| (waiting.pipe, encode(kind, waiting.mime_type, data)))
+            .collect::<Vec<_>>();
+
+        for (pipe, data) in writes {
+            self.start_write(pipe, data);
+        }
+    }
+
+    fn start_write(&mut self, pipe: OwnedFd, data: Vec<u8>) {
+        self.writes.push(LocalWrite {
+            pipe,
+            data,
+            offset: 0,
+            deadline: Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS),
+        });
+
+        self.progress_writes();
+    }
+
+    fn progress_writes(&mut self) {
+        self.writes.retain_mut(|write| loop {
+            if write.offset == write.data.len() {
+                return false;
+            }
+
+            match rustix::io::write(&write.pipe, &write.data[write.offset..]) {
+                Ok(written) => write.offset += written,
+                Err(Errno::INTR) => {}
+                Err(Errno::AGAIN) => return true,
+                Err(error) => {
+                    // E.g.: the application closed the pipe.
+                    debug!(%error, "Failed to write the clipboard data");
+                    return false;
+                }
+            }
+        });
+    }
+
+    fn expire(&mut self, now: Instant) -> LinuxCliprdrResult<()> {
+        if self.current_read.as_ref().is_some_and(|read| read.deadline <= now) {
+            warn!("Timed out reading the local clipboard");
+            self.finish_read(false)?;
+        }
+
+        self.writes.retain(|write| {
+            let expired = write.deadline <= now;
+            if expired {
+                warn!("Timed out writing the clipboard data");
+            }
+            !expired
+        });
+
+        for kind in self.remote.expire(now) {
+            warn!(?kind, "Timed out receiving the remote clipboard data");
+            self.waiting.retain(|waiting| waiting.kind != kind);
+        }
+
+        Ok(())
+    }
+}
+
+fn encode(kind: ContentKind, mime_type: &str, data: &[u8]) -> Vec<u8> {
+    if kind == ContentKind::Text {
+        formats::encode_local_text(mime_type, &String::from_utf8_lossy(data))
+    } else {
+        data.to_vec()
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(WaylandState: ignore WlSeat);
+delegate_noop!(WaylandState: ZwlrDataControlManagerV1);
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::Selection { id } => state.on_selection(id),
+            zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                // The PRIMARY selection is only set, not shared with the remote.
+                offer.destroy();
+            }
+            zwlr_data_control_device_v1::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WaylandState, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, OfferMimeTypes::default()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, OfferMimeTypes> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        mime_types: &OfferMimeTypes,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            mime_types.0.lock().expect("poisoned").push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        source: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => state.on_send(&mime_type, fd),
+            zwlr_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::io::Read as _;
+    use std::os::fd::AsRawFd as _;
+    use std::sync::{mpsc, Arc};
+
+    use ironrdp_cliprdr::backend::ClipboardMessageProxy;
+    use ironrdp_cliprdr::pdu::ClipboardFormat;
+    use wayland_backend::protocol::{Argument, Message};
+    use wayland_backend::server::{
+        Backend, ClientData, ClientId, GlobalHandler, GlobalId, Handle, ObjectData, ObjectId,
+    };
+
+    use super::*;
+    use crate::linux::channel::{event_channel, EventSender};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    type Command = Box<dyn FnOnce(&Handle) + Send>;
+
+    /// Request received by the mock compositor
+    #[derive(Debug)]
+    struct Request {
+        interface: &'static str,
+        opcode: u16,
+        args: Vec<Argument<ObjectId, OwnedFd>>,
+    }
+
+    /// Records the requests of all the objects.
+    struct Recorder(mpsc::Sender<Request>);
+
+    impl ObjectData<()> for Recorder {
+        fn request(
+            self: Arc<Self>,
+            _: &Handle,
+            _: &mut (),
+            _: ClientId,
+            msg: Message<ObjectId, OwnedFd>,
+        ) -> Option<Arc<dyn ObjectData<()>>> {
+            let creates_object = msg.args.iter().any(|arg| matches!(arg, Argument::NewId(_)));
+
+            let _ = self.0.send(Request {
+                interface: msg.sender_id.interface().name,
+                opcode: msg.opcode,
+                args: msg.args.into_vec(),
+            });
+
+            creates_object.then_some(self as Arc<dyn ObjectData<()>>)
+        }
+
+        fn destroyed(self: Arc<Self>, _: &Handle, _: &mut (), _: ClientId, _: ObjectId) {}
+    }
+
+    impl GlobalHandler<()> for Recorder {
+        fn bind(
+            self: Arc<Self>,
+            _: &Handle,
+            _: &mut (),
+            _: ClientId,
+            _: GlobalId,
+            _: ObjectId,
+        ) -> Arc<dyn ObjectData<()>> {
+            self
+        }
+    }
+
+    struct NoClientData;
+
+    impl ClientData for NoClientData {}
+
+    #[derive(Debug)]
+    struct MessageRecorder(mpsc::Sender<ClipboardMessage>);
+
+    impl ClipboardMessageProxy for MessageRecorder {
+        fn send_clipboard_message(&self, message: ClipboardMessage) {
+            let _ = self.0.send(message);
+        }
+    }
+
+    /// Compositor advertising a seat and the data control manager, run on its own thread
+    struct MockCompositor {
+        commands: mpsc::Sender<Command>,
+        requests: mpsc::Receiver<Request>,
+        recorder: Arc<Recorder>,
+    }
+
+    impl MockCompositor {
+        fn start(stream: UnixStream) -> Self {
+            let (commands, commands_rx) = mpsc::channel::<Command>();
+            let (requests_tx, requests) = mpsc::channel();
+            let recorder = Arc::new(Recorder(requests_tx));
+
+            let thread_recorder = Arc::clone(&recorder);
+            std::thread::spawn(move || {
+                let mut backend = Backend::<()>::new().unwrap();
+                let mut handle = backend.handle();
+
+                let global_handler: Arc<dyn GlobalHandler<()>> = thread_recorder;
+                handle.create_global::<()>(WlSeat::interface(), 1, Arc::clone(&global_handler));
+                handle.create_global::<()>(
+                    ZwlrDataControlManagerV1::interface(),
+                    PRIMARY_SELECTION_SINCE,
+                    global_handler,
+                );
+                handle.insert_client(stream, Arc::new(NoClientData)).unwrap();
+
+                loop {
+                    match commands_rx.recv_timeout(Duration::from_millis(5)) {
+                        Ok(command) => command(&handle),
+                        Err(mpsc::RecvTimeoutError::Timeout) => {}
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+
+                    let _ = backend.dispatch_all_clients(&mut ());
+                    let _ = backend.flush(None);
+                }
+            });
+
+            Self {
+                commands,
+                requests,
+                recorder,
+            }
+        }
+
+        /// Waits for a request, skipping the other ones.
+        fn expect(&self, interface: &str, opcode: u16) -> Request {
+            loop {
+                let request = self.requests.recv_timeout(TIMEOUT).expect("request");
+                if request.interface == interface && request.opcode == opcode {
+                    return request;
+                }
+            }
+        }
+
+        fn run(&self, command: impl FnOnce(&Handle) + Send + 'static) {
+            self.commands.send(Box::new(command)).unwrap();
+        }
+
+        /// Sets the selection to a new offer of a local application.
+        fn set_selection(&self, device: ObjectId, mime_types: &'static [&'static str]) {
+            let recorder = Arc::clone(&self.recorder);
+
+            self.run(move |handle| {
+                let client = handle.get_client(device.clone()).unwrap();
+                let version = handle.object_info(device.clone()).unwrap().version;
+                let offer = handle
+                    .create_object::<()>(client, ZwlrDataControlOfferV1::interface(), version, recorder)
+                    .unwrap();
+
+                handle
+                    .send_event(Message {
+                        sender_id: device.clone(),
+                        opcode: zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE,
+                        args: [Argument::NewId(offer.clone())].into_iter().collect(),
+                    })
+                    .unwrap();
+
+                for mime_type in mime_types {
+                    handle
+                        .send_event(Message {
+                            sender_id: offer.clone(),
+                            opcode: zwlr_data_control_offer_v1::EVT_OFFER_OPCODE,
+                            args: [Argument::Str(Some(Box::new(CString::new(*mime_type).unwrap())))]
+                                .into_iter()
+                                .collect(),
+                        })
+                        .unwrap();
+                }
+
+                handle
+                    .send_event(Message {
+                        sender_id: device,
+                        opcode: zwlr_data_control_device_v1::EVT_SELECTION_OPCODE,
+                        args: [Argument::Object(offer)].into_iter().collect(),
+                    })
+                    .unwrap();
+            });
+        }
+    }
+
+    struct Harness {
+        compositor: MockCompositor,
+        events: EventSender,
+        messages: mpsc::Receiver<ClipboardMessage>,
+        device: ObjectId,
+    }
+
+    impl Harness {
+        fn start(mirror_primary: bool) -> Self {
+            let (client_stream, server_stream) = UnixStream::pair().unwrap();
+            let compositor = MockCompositor::start(server_stream);
+
+            let clipboard = WaylandClipboard::from_connection(Connection::from_socket(client_stream).unwrap()).unwrap();
+
+            let (events, events_rx) = event_channel().unwrap();
+            let (messages_tx, messages) = mpsc::channel();
+            let context = ClipboardContext {
+                message_proxy: Box::new(MessageRecorder(messages_tx)),
+                events: events_rx,
+                mirror_primary,
+            };
+
+            std::thread::spawn(move || clipboard.run(context).unwrap());
+
+            let request = compositor.expect(ZwlrDataControlManagerV1::interface().name, 1);
+            let device = new_object(&request);
+
+            Self {
+                compositor,
+                events,
+                messages,
+                device,
+            }
+        }
+
+        fn message(&self) -> ClipboardMessage {
+            self.messages.recv_timeout(TIMEOUT).expect("clipboard message")
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            self.events.send(BackendEvent::Shutdown);
+        }
+    }
+
+    fn new_object(request: &Request) -> ObjectId {
+        request
+            .args
+            .iter()
+            .find_map(|arg| match arg {
+                Argument::NewId(id) => Some(id.clone()),
+                _ => None,
+            })
+            .expect("new object")
+    }
+
+    fn string_arg(request: &Request) -> String {
+        request
+            .args
+            .iter()
+            .find_map(|arg| match arg {
+                Argument::Str(Some(value)) => Some(value.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .expect("string argument")
+    }
+
+    #[test]
+    fn local_selection_is_sent_to_remote() {
+        let harness = Harness::start(false);
+
+        harness
+            .compositor
+            .set_selection(harness.device.clone(), &["text/plain;charset=utf-8", "image/x-unknown"]);
+
+        let ClipboardMessage::SendInitiateCopy(formats) = harness.message() else {
+            panic!("expected SendInitiateCopy");
+        };
+        assert_eq!(formats, [ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]);
+
+        harness.events.send(BackendEvent::FormatDataRequest(FormatDataRequest {
+            format: ClipboardFormatId::CF_UNICODETEXT,
+        }));
+
+        let mut receive = harness.compositor.expect(
+            ZwlrDataControlOfferV1::interface().name,
+            zwlr_data_control_offer_v1::REQ_RECEIVE_OPCODE,
+        );
+        assert_eq!(string_arg(&receive), "text/plain;charset=utf-8");
+
+        let Some(Argument::Fd(pipe)) = receive.args.pop() else {
+            panic!("expected a file descriptor");
+        };
+        rustix::io::write(&pipe, b"hello\nworld").unwrap();
+        drop(pipe);
+
+        let ClipboardMessage::SendFormatData(response) = harness.message() else {
+            panic!("expected SendFormatData");
+        };
+        assert_eq!(response, FormatDataResponse::new_unicode_string("hello\r\nworld"));
+    }
+
+    #[test]
+    fn remote_clipboard_is_offered_locally() {
+        let harness = Harness::start(true);
+
+        harness
+            .events
+            .send(BackendEvent::RemoteFormatList(vec![ClipboardFormat::new(
+                ClipboardFormatId::CF_UNICODETEXT,
+            )]));
+
+        let manager = ZwlrDataControlManagerV1::interface().name;
+        let source_interface = ZwlrDataControlSourceV1::interface().name;
+        let device_interface = ZwlrDataControlDeviceV1::interface().name;
+
+        let source = new_object(&harness.compositor.expect(manager, 0));
+        let mut offered = Vec::new();
+        loop {
+            let request = harness.compositor.requests.recv_timeout(TIMEOUT).unwrap();
+            if request.interface == source_interface && request.opcode == zwlr_data_control_source_v1::REQ_OFFER_OPCODE
+            {
+                offered.push(string_arg(&request));
+            } else if request.interface == device_interface {
+                assert_eq!(request.opcode, zwlr_data_control_device_v1::REQ_SET_SELECTION_OPCODE);
+                break;
+            }
+        }
+        assert!(offered.iter().any(|mime_type| mime_type == "UTF8_STRING"));
+        assert!(offered.iter().any(|mime_type| mime_type == SELECTION_MARKER_MIME_TYPE));
+
+        // The PRIMARY selection is set with a second source.
+        harness.compositor.expect(manager, 0);
+        harness.compositor.expect(
+            device_interface,
+            zwlr_data_control_device_v1::REQ_SET_PRIMARY_SELECTION_OPCODE,
+        );
+
+        let (pipe, write_end) = rustix::pipe::pipe().unwrap();
+        harness.compositor.run(move |handle| {
+            handle
+                .send_event(Message {
+                    sender_id: source,
+                    opcode: zwlr_data_control_source_v1::EVT_SEND_OPCODE,
+                    args: [
+                        Argument::Str(Some(Box::new(CString::new("UTF8_STRING").unwrap()))),
+                        Argument::Fd(write_end.as_raw_fd()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                })
+                .unwrap();
+            // Duplicated when queued.
+            drop(write_end);
+        });
+
+        let ClipboardMessage::SendInitiatePaste(format) = harness.message() else {
+            panic!("expected SendInitiatePaste");
+        };
+        assert_eq!(format, ClipboardFormatId::CF_UNICODETEXT);
+
+        harness.events.send(BackendEvent::FormatDataResponse(
+            FormatDataResponse::new_unicode_string("hi\r\nthere"),
+        ));
+
+        let mut data = Vec::new();
+        std::fs::File::from(pipe).read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"hi\nthere");
+    }
+}
@@ -0,0 +1,746 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use ironrdp_cliprdr::backend::ClipboardMessage;
+use ironrdp_cliprdr::pdu::{ClipboardFormatId, FormatDataRequest, FormatDataResponse};
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::io::Errno;
+use tracing::{debug, trace, warn};
+use x11rb::connection::{Connection as _, RequestConnection as _};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, Property,
+    SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+use crate::linux::formats::{self, ContentKind, RemoteFormats};
+use crate::linux::remote::{Fetch, RemoteClipboard};
+use crate::linux::{BackendEvent, ClipboardContext, LinuxCliprdrResult, TRANSFER_TIMEOUT_SECS};
+
+/// Largest property written at once, larger data being sent incrementally.
+const MAX_CHUNK_SIZE: usize = 256 * 1024;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        TIMESTAMP,
+        INCR,
+        IRONRDP_SELECTION,
+    }
+}
+
+/// Connection to the X11 server, owning a hidden window to exchange the selections
+pub(crate) struct X11Clipboard {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+    /// Atoms of the MIME types exchanged with the remote.
+    mime_atoms: Vec<(&'static str, Atom)>,
+    chunk_size: usize,
+}
+
+impl X11Clipboard {
+    pub(crate) fn connect(display: Option<&str>) -> LinuxCliprdrResult<Self> {
+        let (conn, screen_num) = RustConnection::connect(display)?;
+        let root = conn.setup().roots[screen_num].root;
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        let mut mime_atoms = Vec::new();
+        for mime_type in formats::all_mime_types() {
+            let atom = if mime_type == "STRING" {
+                AtomEnum::STRING.into()
+            } else {
+                conn.intern_atom(false, mime_type.as_bytes())?.reply()?.atom
+            };
+            mime_atoms.push((mime_type, atom));
+        }
+
+        // Notifies the changes of the clipboard owner.
+        conn.xfixes_query_version(5, 0)?.reply()?;
+        conn.xfixes_select_selection_input(
+            window,
+            atoms.CLIPBOARD,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )?;
+        conn.flush()?;
+
+        let chunk_size = (conn.maximum_request_bytes() / 4).min(MAX_CHUNK_SIZE);
+
+        Ok(Self {
+            conn,
+            window,
+            atoms,
+            mime_atoms,
+            chunk_size,
+        })
+    }
+
+    /// Processes the events until [`BackendEvent::Shutdown`] is received.
+    pub(crate) fn run(self, context: ClipboardContext) -> LinuxCliprdrResult<()> {
+        X11EventLoop {
+            x11: self,
+            context,
+            remote: RemoteClipboard::default(),
+            owner: false,
+            local_mime_types: Vec::new(),
+            reads: VecDeque::new(),
+            current_read: None,
+            waiting: Vec::new(),
+            sends: Vec::new(),
+        }
+        .run()
+    }
+
+    fn mime_atom(&self, mime_type: &str) -> Option<Atom> {
+        self.mime_atoms
+            .iter()
+            .find_map(|(name, atom)| (*name == mime_type).then_some(*atom))
+    }
+
+    fn mime_type(&self, atom: Atom) -> Option<&'static str> {
+        self.mime_atoms
+            .iter()
+            .find_map(|(name, candidate)| (*candidate == atom).then_some(*name))
+    }
+
+    fn notify(&self, request: &SelectionRequestEvent, property: Atom) -> LinuxCliprdrResult<()> {
+        let event = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property,
+        };
+
+        self.conn
+            .send_event(false, request.requestor, EventMask::NO_EVENT, event)?;
+
+        Ok(())
+    }
+
+    fn refuse(&self, request: &SelectionRequestEvent) -> LinuxCliprdrResult<()> {
+        self.notify(request, NONE)
+    }
+}
+
+/// Conversion of the CLIPBOARD selection owned by a local application
+#[derive(Debug, Clone, Copy)]
+enum LocalRead {
+    Targets,
+    Data {
+        format: ClipboardFormatId,
+        mime_type: &'static str,
+    },
+}
+
+struct CurrentRead {
+    read: LocalRead,
+    deadline: Instant,
+    /// Data received so far, when received incrementally.
+    incremental: Option<Vec<u8>>,
+}
+
+/// Local request for the content of the remote, waiting for its data
+struct WaitingRequest {
+    request: SelectionRequestEvent,
+    property: Atom,
+    kind: ContentKind,
+    mime_type: &'static str,
+}
+
+/// Data sent incrementally to a local application
+struct IncrementalSend {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    data: Vec<u8>,
+    offset: usize,
+    deadline: Instant,
+}
+
+struct X11EventLoop {
+    x11: X11Clipboard,
+    context: ClipboardContext,
+    remote: RemoteClipboard,
+    /// Whether the CLIPBOARD selection holds the content of the remote.
+    owner: bool,
+    /// MIME types offered by the local owner of the CLIPBOARD selection.
+    local_mime_types: Vec<&'static str>,
+    /// Pending conversions of the CLIPBOARD selection, performed one at a time.
+    reads: VecDeque<LocalRead>,
+    current_read: Option<CurrentRead>,
+    waiting: Vec<WaitingRequest>,
+    sends: Vec<IncrementalSend>,
+}
+
+impl X11EventLoop {
+    fn run(mut self) -> LinuxCliprdrResult<()> {
+        self.announce_local()?;
+
+        loop {
+            while let Some(event) = self.x11.conn.poll_for_event()? {
+                self.on_x11_event(event)?;
+            }
+            self.x11.conn.flush()?;
+
+            let timeout = self.next_deadline().map_or(-1, |deadline| {
+                let timeout = deadline.saturating_duration_since(Instant::now()).as_millis();
+                // Rounded up, the deadline being checked after the wake up.
+                i32::try_from(timeout.saturating_add(1)).unwrap_or(i32::MAX)
+            });
+
+            {
+                let events_fd = self.context.events.fd();
+                let mut fds = [
+                    PollFd::new(self.x11.conn.stream(), PollFlags::IN),
+                    PollFd::new(&events_fd, PollFlags::IN),
+                ];
+
+                match poll(&mut fds, timeout) {
+                    Ok(_) | Err(Errno::INTR) => {}
+                    Err(error) => return Err(std::io::Error::from(error).into()),
+                }
+            }
+
+            for event in self.context.events.drain() {
+                match event {
+                    BackendEvent::Shutdown => return Ok(()),
+                    event => self.on_backend_event(event)?,
+                }
+            }
+
+            self.expire(Instant::now())?;
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        let reads = self.current_read.as_ref().map(|read| read.deadline);
+        let sends = self.sends.iter().map(|send| send.deadline);
+
+        reads.into_iter().chain(sends).chain(self.remote.next_deadline()).min()
+    }
+
+    fn on_backend_event(&mut self, event: BackendEvent) -> LinuxCliprdrResult<()> {
+        match event {
+            BackendEvent::RemoteFormatList(formats) => self.on_remote_format_list(RemoteFormats::new(&formats)),
+            BackendEvent::FormatDataRequest(request) => self.on_format_data_request(request),
+            BackendEvent::FormatDataResponse(response) => self.on_format_data_response(&response),
+            BackendEvent::RemoteRequestsFormatList => self.announce_local(),
+            BackendEvent::Shutdown => Ok(()),
+        }
+    }
+
+    fn on_x11_event(&mut self, event: Event) -> LinuxCliprdrResult<()> {
+        let atoms = self.x11.atoms;
+
+        match event {
+            Event::XfixesSelectionNotify(event) if event.selection == atoms.CLIPBOARD => {
+                // While owned, the notifications are stale: the loss of the ownership is notified by `SelectionClear`
+                // first.
+                if !self.owner && event.owner != self.x11.window {
+                    self.on_local_owner_changed(event.owner)?;
+                }
+            }
+            Event::SelectionClear(event) if event.selection == atoms.CLIPBOARD => self.owner = false,
+            Event::SelectionNotify(event) if event.requestor == self.x11.window => {
+                self.on_selection_notify(event.property)?;
+            }
+            Event::SelectionRequest(event) => self.on_selection_request(event)?,
+            Event::PropertyNotify(event) if event.window == self.x11.window => {
+                if event.atom == atoms.IRONRDP_SELECTION && event.state == Property::NEW_VALUE {
+                    self.on_incremental_chunk()?;
+                }
+            }
+            Event::PropertyNotify(event) if event.state == Property::DELETE => {
+                self.on_incremental_send_progress(event.window, event.atom)?;
+            }
+            Event::Error(error) => {
+                // E.g.: the window of a requestor was destroyed during an incremental transfer.
+                debug!(?error, "X11 error");
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Announces the formats of the local clipboard to the remote.
+    fn announce_local(&mut self) -> LinuxCliprdrResult<()> {
+        let owner = self
+            .x11
+            .conn
+            .get_selection_owner(self.x11.atoms.CLIPBOARD)?
+            .reply()?
+            .owner;
+
+        if owner == self.x11.window {
+            // The clipboard holds the content of the remote.
+            return Ok(());
+        }
+
+        self.on_local_owner_changed(owner)
+    }
+
+    fn on_local_owner_changed(&mut self, owner: Window) -> LinuxCliprdrResult<()> {
+        self.local_mime_types.clear();
+        // The data requested by the remote is read from the new owner, but its formats are outdated.
+        self.reads.retain(|read| !matches!(read, LocalRead::Targets));
+
+        if owner == NONE {
+            self.context
+                .send_message(ClipboardMessage::SendInitiateCopy(Vec::new()));
+            return Ok(());
+        }
+
+        self.reads.push_back(LocalRead::Targets);
+        self.start_next_read()
+    }
+
+    fn start_next_read(&mut self) -> LinuxCliprdrResult<()> {
+        if self.current_read.is_some() {
+            return Ok(());
+        }
+
+        let Some(read) = self.reads.pop_front() else {
+            return Ok(());
+        };
+
+        let target = match read {
+            LocalRead::Targets => Some(self.x11.atoms.TARGETS),
+            LocalRead::Data { mime_type, .. } => self.x11.mime_atom(mime_type),
+        };
+
+        let Some(target) = target else {
+            return self.finish_read(read, None);
+        };
+
+        self.x11.conn.convert_selection(
+            self.x11.window,
+            self.x11.atoms.CLIPBOARD,
+            target,
+            self.x11.atoms.IRONRDP_SELECTION,
+            CURRENT_TIME,
+        )?;
+
+        self.current_read = Some(CurrentRead {
+            read,
+            deadline: Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS),
+            incremental: None,
+        });
+
+        Ok(())
+    }
+
+    fn on_selection_notify(&mut self, property: Atom) -> LinuxCliprdrResult<()> {
+        let Some(current) = self.current_read.as_mut() else {
+            return Ok(());
+        };
+
+        if property == NONE {
+            // The owner could not convert the selection.
+            let read = current.read;
+            self.current_read = None;
+            return self.finish_read(read, None);
+        }
+
+        let reply = self
+            .x11
+            .conn
+            .get_property(true, self.x11.window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+
+        if reply.type_ == self.x11.atoms.INCR {
+            // Deleting the property (above) requests the first chunk.
+            trace!("Receiving the selection incrementally");
+            current.incremental = Some(Vec::new());
+            return Ok(());
+        }
+
+        let read = current.read;
+        self.current_read = None;
+        self.finish_read(read, Some(reply.value))
+    }
+
+    fn on_incremental_chunk(&mut self) -> LinuxCliprdrResult<()> {
+        let Some(current) = self.current_read.as_mut() else {
+            return Ok(());
+        };
+
+        let Some(received) = current.incremental.as_mut() else {
+            // The property holding the whole data, or the INCR property, read on `SelectionNotify`.
+            return Ok(());
+        };
+
+        let reply = self
+            .x11
+            .conn
+            .get_property(
+                true,
+                self.x11.window,
+                self.x11.atoms.IRONRDP_SELECTION,
+                AtomEnum::ANY,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+
+        if !reply.value.is_empty() {
+            received.extend_from_slice(&reply.value);
+            current.deadline = Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS);
+            return Ok(());
+        }
+
+        // A zero-length chunk ends the transfer.
+        let data = current.incremental.take();
+        let read = current.read;
+        self.current_read = None;
+        self.finish_read(read, data)
+    }
+
+    fn finish_read(&mut self, read: LocalRead, data: Option<Vec<u8>>) -> LinuxCliprdrResult<()> {
+        match read {
+            LocalRead::Targets => {
+                let targets = data.unwrap_or_default();
+                self.local_mime_types = targets
+                    .chunks_exact(4)
+                    .map(|atom| u32::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+                    .filter_map(|atom| self.x11.mime_type(atom))
+                    .collect();
+
+                let formats = formats::local_formats(&self.local_mime_types);
+                trace!(?formats, "Sending clipboard formats");
+                self.context.send_message(ClipboardMessage::SendInitiateCopy(formats));
+            }
+            LocalRead::Data { format, mime_type } => {
+                let response = match data {
+                    Some(data) => formats::local_to_remote(format, mime_type, &data).unwrap_or_else(|error| {
+                        warn!(%error, mime_type, "Failed to convert the local clipboard data");
+                        FormatDataResponse::new_error()
+                    }),
+                    None => FormatDataResponse::new_error(),
+                };
+
+                self.context.send_message(ClipboardMessage::SendFormatData(response));
+            }
+        }
+
+        self.start_next_read()
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest) -> LinuxCliprdrResult<()> {
+        let mime_type = if self.owner {
+            // The remote requests the data of an outdated format list.
+            None
+        } else {
+            formats::local_mime_type_for_format(request.format, |mime_type| self.local_mime_types.contains(&mime_type))
+        };
+
+        match mime_type {
+            Some(mime_type) => {
+                self.reads.push_back(LocalRead::Data {
+                    format: request.format,
+                    mime_type,
+                });
+                self.start_next_read()
+            }
+            None => {
+                self.context
+                    .send_message(ClipboardMessage::SendFormatData(FormatDataResponse::new_error()));
+                Ok(())
+            }
+        }
+    }
+
+    fn on_remote_format_list(&mut self, formats: RemoteFormats) -> LinuxCliprdrResult<()> {
+        self.remote.set_formats(formats);
+
+        // The local requests were for the previous content of the remote.
+        for waiting in core::mem::take(&mut self.waiting) {
+            self.x11.refuse(&waiting.request)?;
+        }
+
+        let mut selections = vec![self.x11.atoms.CLIPBOARD];
+        if self.context.mirror_primary {
+            selections.push(AtomEnum::PRIMARY.into());
+        }
+
+        let owner = if self.remote.formats().is_empty() {
+            if !self.owner {
+                return Ok(());
+            }
+            NONE
+        } else {
+            self.x11.window
+        };
+
+        for selection in selections {
+            self.x11.conn.set_selection_owner(owner, selection, CURRENT_TIME)?;
+        }
+
+        self.owner = owner != NONE
+            && self
+                .x11
+                .conn
+                .get_selection_owner(self.x11.atoms.CLIPBOARD)?
+                .reply()?
+                .owner
+                == self.x11.window;
+
+        if owner != NONE && !self.owner {
+            warn!("Failed to take the ownership of the clipboard");
+        }
+
+        Ok(())
+    }
+
+    fn on_selection_request(&mut self, request: SelectionRequestEvent) -> LinuxCliprdrResult<()> {
+        let atoms = self.x11.atoms;
+
+        // Obsolete clients do not set the property.
+        let property = if request.property == NONE {
+            request.target
+        } else {
+            request.property
+        };
+
+        let owned = self.owner
+            && (request.selection == atoms.CLIPBOARD
+                || (self.context.mirror_primary && request.selection == Atom::from(AtomEnum::PRIMARY)));
+
+        if !owned {
+            return self.x11.refuse(&request);
+        }
+
+        if request.target == atoms.TARGETS {
+            let mut targets = vec![atoms.TARGETS, atoms.TIMESTAMP];
+            targets.extend(
+                self.remote
+                    .formats()
+                    .mime_types()
+                    .filter_map(|mime_type| self.x11.mime_atom(mime_type)),
+            );
+
+            self.x11.conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )?;
+            return self.x11.notify(&request, property);
+        }
+
+        if request.target == atoms.TIMESTAMP {
+            // The ownership is taken at `CurrentTime`.
+            self.x11.conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::INTEGER,
+                &[CURRENT_TIME],
+            )?;
+            return self.x11.notify(&request, property);
+        }
+
+        let Some(mime_type) = self.x11.mime_type(request.target) else {
+            return self.x11.refuse(&request);
+        };
+        let Some(kind) = ContentKind::from_mime_type(mime_type) else {
+            return self.x11.refuse(&request);
+        };
+
+        match self.remote.fetch(kind) {
+            Fetch::Ready => {
+                let data = self.remote.cached(kind).unwrap_or_default().to_vec();
+                self.send(&request, property, kind, mime_type, data)
+            }
+            Fetch::Pending => {
+                self.waiting.push(WaitingRequest {
+                    request,
+                    property,
+                    kind,
+                    mime_type,
+                });
+                Ok(())
+            }
+            Fetch::Start(format) => {
+                self.context.send_message(ClipboardMessage::SendInitiatePaste(format));
+                self.waiting.push(WaitingRequest {
+                    request,
+                    property,
+                    kind,
+                    mime_type,
+                });
+                Ok(())
+            }
+            Fetch::Unavailable => self.x11.refuse(&request),
+        }
+    }
+
+    fn on_format_data_response(&mut self, response: &FormatDataResponse<'_>) -> LinuxCliprdrResult<()> {
+        let Some(kind) = self.remote.on_response(response) else {
+            return Ok(());
+        };
+
+        let data = self.remote.cached(kind).map(<[u8]>::to_vec);
+        let (ready, waiting) = core::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition(|waiting| waiting.kind == kind);
+        self.waiting = waiting;
+
+        for waiting in ready {
+            match &data {
+                Some(data) => self.send(
+                    &waiting.request,
+                    waiting.property,
+                    kind,
+                    waiting.mime_type,
+                    data.clone(),
+                )?,
+                None => self.x11.refuse(&waiting.request)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the data to a local application, incrementally if it is large.
+    fn send(
+        &mut self,
+        request: &SelectionRequestEvent,
+        property: Atom,
+        kind: ContentKind,
+        mime_type: &'static str,
+        data: Vec<u8>,
+    ) -> LinuxCliprdrResult<()> {
+        let data = if kind == ContentKind::Text {
+            formats::encode_local_text(mime_type, &String::from_utf8_lossy(&data))
+        } else {
+            data
+        };
+
+        if data.len() <= self.x11.chunk_size {
+            self.x11
+                .conn
+                .change_property8(PropMode::REPLACE, request.requestor, property, request.target, &data)?;
+            return self.x11.notify(request, property);
+        }
+
+        // The requestor deletes the property to request each chunk.
+        self.x11.conn.change_window_attributes(
+            request.requestor,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        self.x11.conn.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            self.x11.atoms.INCR,
+            &[u32::try_from(data.len()).unwrap_or(u32::MAX)],
+        )?;
+
+        self.sends.push(IncrementalSend {
+            requestor: request.requestor,
+            property,
+            target: request.target,
+            data,
+            offset: 0,
+            deadline: Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS),
+        });
+
+        self.x11.notify(request, property)
+    }
+
+    fn on_incremental_send_progress(&mut self, window: Window, property: Atom) -> LinuxCliprdrResult<()> {
+        let Some(index) = self
+            .sends
+            .iter()
+            .position(|send| send.requestor == window && send.property == property)
+        else {
+            return Ok(());
+        };
+
+        let send = &mut self.sends[index];
+        let end = send.offset.saturating_add(self.x11.chunk_size).min(send.data.len());
+        let chunk = &send.data[send.offset..end];
+
+        self.x11
+            .conn
+            .change_property8(PropMode::REPLACE, send.requestor, send.property, send.target, chunk)?;
+
+        if chunk.is_empty() {
+            // The zero-length chunk ending the transfer is written.
+            let send = self.sends.swap_remove(index);
+            self.x11.conn.change_window_attributes(
+                send.requestor,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+            )?;
+        } else {
+            send.offset = end;
+            send.deadline = Instant::now() + Duration::from_secs(TRANSFER_TIMEOUT_SECS);
+        }
+
+        Ok(())
+    }
+
+    fn expire(&mut self, now: Instant) -> LinuxCliprdrResult<()> {
+        if let Some(read) = self
+            .current_read
+            .as_ref()
+            .and_then(|current| (current.deadline <= now).then_some(current.read))
+        {
+            warn!(?read, "Timed out reading the local clipboard");
+            self.current_read = None;
+            self.finish_read(read, None)?;
+        }
+
+        self.sends.retain(|send| {
+            let expired = send.deadline <= now;
+            if expired {
+                warn!("Timed out sending the clipboard data incrementally");
+            }
+            !expired
+        });
+
+        for kind in self.remote.expire(now) {
+            warn!(?kind, "Timed out receiving the remote clipboard data");
+
+            let (expired, waiting) = core::mem::take(&mut self.waiting)
+                .into_iter()
+                .partition::<Vec<_>, _>(|waiting| waiting.kind == kind);
+            self.waiting = waiting;
+
+            for waiting in expired {
+                self.x11.refuse(&waiting.request)?;
+            }
+        }
+
+        Ok(())
+    }
+}
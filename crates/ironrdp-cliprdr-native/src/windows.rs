@@ -4,6 +4,7 @@ mod cliprdr_backend;
 mod os_clipboard;
 mod remote_format_registry;
 mod utils;
+mod worker;
 
 use std::sync::mpsc as mpsc_sync;
 
@@ -25,6 +26,7 @@ use windows::Win32::UI::WindowsAndMessaging::{
 
 use self::clipboard_impl::{clipboard_subproc, WinClipboardImpl};
 use self::cliprdr_backend::WinCliprdrBackend;
+use self::worker::{ClipboardWorker, WorkerEvent};
 
 const BACKEND_CHANNEL_SIZE: usize = 8;
 const WM_CLIPRDR_BACKEND_EVENT: u32 = WM_USER;
@@ -69,11 +71,15 @@ pub enum WinCliprdrError {
     #[error("failed to render clipboard format")]
     RenderFormat,
 
+    #[error("failed to spawn the clipboard worker thread")]
+    WorkerSpawn,
+
     #[error("WinAPI error")]
     WinAPI(#[from] Error),
 }
 
-/// Sent from the clipboard backend shim to the actual WinAPI subproc event loop
+/// Sent from the clipboard backend shim to the clipboard worker, and forwarded to the actual WinAPI subproc event
+/// loop when the event needs the window thread
 #[derive(Debug)]
 pub(crate) enum BackendEvent {
     // Events generated by OS event loop
@@ -100,9 +106,12 @@ pub(crate) enum BackendEvent {
 /// This type is not thread safe, it should be used only in the main thread with a windows event
 /// loop. (GUI thread). However, backend factory returned by [`WinClipboard::backend_factory`]
 /// can be safely used in other threads.
+///
+/// A worker thread exchanges the messages with the `CLIPRDR` SVC, so that the remote requests are
+/// answered while the window thread is blocked rendering a delay-rendered format.
 pub struct WinClipboard {
     window: HWND,
-    backend_tx: mpsc_sync::SyncSender<BackendEvent>,
+    worker_tx: mpsc_sync::SyncSender<WorkerEvent>,
 
     /// From MS docs:
     /// ```text
@@ -165,9 +174,18 @@ impl WinClipboard {
         // SAFETY: `window` is a valid window handle
         unsafe { AddClipboardFormatListener(window)? };
 
-        let (backend_tx, backend_rx) = mpsc_sync::sync_channel(BACKEND_CHANNEL_SIZE);
+        let (worker_tx, worker_rx) = mpsc_sync::sync_channel(BACKEND_CHANNEL_SIZE);
+        let (window_tx, window_rx) = mpsc_sync::channel();
+        let (response_tx, response_rx) = mpsc_sync::channel();
+
+        let worker = ClipboardWorker::new(window, message_proxy, worker_rx, window_tx, response_tx);
+
+        std::thread::Builder::new()
+            .name("ironrdp-cliprdr-worker".to_owned())
+            .spawn(move || worker.run())
+            .map_err(|_| WinCliprdrError::WorkerSpawn)?;
 
-        let ctx = Box::new(WinClipboardImpl::new(window, message_proxy, backend_rx));
+        let ctx = Box::new(WinClipboardImpl::new(window, worker_tx.clone(), window_rx, response_rx));
 
         // We need to receive winapi messages in the main thread, so we need to add a subclass to
         // the window.
@@ -183,7 +201,7 @@ impl WinClipboard {
 
         Ok(Self {
             window,
-            backend_tx,
+            worker_tx,
             _thread_marker: Default::default(),
         })
     }
@@ -191,8 +209,7 @@ impl WinClipboard {
     /// Returns clipboard backend factory suitable for making backend instances for `CLIPRDR` SVC.
     pub fn backend_factory(&self) -> Box<dyn CliprdrBackendFactory + Send> {
         Box::new(WinCliprdrBackendFactory {
-            tx: self.backend_tx.clone(),
-            window: self.window,
+            tx: self.worker_tx.clone(),
         })
     }
 }
@@ -210,20 +227,20 @@ impl Drop for WinClipboard {
         if !unsafe { RemoveWindowSubclass(self.window, Some(clipboard_subproc), 0) }.as_bool() {
             error!("Failed to remove window subclass")
         }
+
+        // The worker exits once the remaining events are processed; it is not joined, as it may be blocked on
+        // the clipboard for a short while.
+        let _ = self.worker_tx.try_send(WorkerEvent::Shutdown);
     }
 }
 
 /// Windows-specific clipboard backend factory
 struct WinCliprdrBackendFactory {
-    tx: mpsc_sync::SyncSender<BackendEvent>,
-    window: HWND,
+    tx: mpsc_sync::SyncSender<WorkerEvent>,
 }
 
-// SAFETY: window handle is thread safe for PostMessageW usage
-unsafe impl Send for WinCliprdrBackendFactory {}
-
 impl CliprdrBackendFactory for WinCliprdrBackendFactory {
     fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
-        Box::new(WinCliprdrBackend::new(self.window, self.tx.clone()))
+        Box::new(WinCliprdrBackend::new(self.tx.clone()))
     }
 }
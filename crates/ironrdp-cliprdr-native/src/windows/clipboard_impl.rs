@@ -1,10 +1,11 @@
 use core::time::Duration;
 use std::collections::HashSet;
 use std::sync::mpsc;
+use std::time::Instant;
 
-use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy};
-use ironrdp_cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, FormatDataRequest, FormatDataResponse};
-use tracing::warn;
+use ironrdp_cliprdr::backend::ClipboardMessage;
+use ironrdp_cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, FormatDataResponse};
+use tracing::{debug, warn};
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::DataExchange::GetClipboardOwner;
 use windows::Win32::UI::Shell::DefSubclassProc;
@@ -13,21 +14,25 @@ use windows::Win32::UI::WindowsAndMessaging::{
     WM_RENDERFORMAT, WM_TIMER,
 };
 
-use crate::windows::clipboard_data_ref::ClipboardDataRef;
 use crate::windows::os_clipboard::OwnedOsClipboard;
 use crate::windows::remote_format_registry::RemoteClipboardFormatRegistry;
 use crate::windows::utils::render_format;
+use crate::windows::worker::WorkerEvent;
 use crate::windows::{BackendEvent, WinCliprdrError, WinCliprdrResult, WM_CLIPRDR_BACKEND_EVENT};
 
 const RENDER_FORMAT_TIMEOUT_SECS: u64 = 10;
 const IDT_CLIPBOARD_RETRY: usize = 1;
 
 /// Internal implementation of the clipboard processing logic.
+///
+/// It runs on the thread of the window owning the clipboard, while the events of the `CLIPRDR` channel are received
+/// by the [`ClipboardWorker`](crate::windows::worker::ClipboardWorker).
 pub(crate) struct WinClipboardImpl {
     window: HWND,
-    message_proxy: Box<dyn ClipboardMessageProxy>,
+    worker_tx: mpsc::SyncSender<WorkerEvent>,
     window_is_active: bool,
-    backend_rx: mpsc::Receiver<BackendEvent>,
+    window_rx: mpsc::Receiver<BackendEvent>,
+    response_rx: mpsc::Receiver<FormatDataResponse<'static>>,
     // Number of attempts spent to process current clipboard message
     attempt: u32,
     // Message to retry
@@ -35,41 +40,34 @@ pub(crate) struct WinClipboardImpl {
     // Formats available on the remote (represented as LOCAL format ids)
     available_formats_on_remote: Vec<ClipboardFormatId>,
     remote_format_registry: RemoteClipboardFormatRegistry,
+    // Whether a delay-rendered format is being rendered
+    rendering: bool,
 }
 
 impl WinClipboardImpl {
     pub(crate) fn new(
         window: HWND,
-        message_proxy: impl ClipboardMessageProxy + 'static,
-        backend_rx: mpsc::Receiver<BackendEvent>,
+        worker_tx: mpsc::SyncSender<WorkerEvent>,
+        window_rx: mpsc::Receiver<BackendEvent>,
+        response_rx: mpsc::Receiver<FormatDataResponse<'static>>,
     ) -> Self {
         Self {
             window,
-            message_proxy: Box::new(message_proxy),
+            worker_tx,
             window_is_active: true, // We assume that we start with current window active,
-            backend_rx,
+            window_rx,
+            response_rx,
             attempt: 0,
             retry_message: None,
             available_formats_on_remote: Vec::new(),
             remote_format_registry: Default::default(),
+            rendering: false,
         }
     }
 
-    fn on_format_data_request(&mut self, request: &FormatDataRequest) -> WinCliprdrResult<Option<ClipboardMessage>> {
-        // Get data from the clipboard and send event to the main event loop
-        let clipboard = OwnedOsClipboard::new(self.window)?;
-
-        let buffer = ClipboardDataRef::get(&clipboard, request.format).map(|borrowed| borrowed.data().to_vec());
-
-        let response = match buffer {
-            Some(data) => FormatDataResponse::new_data(data),
-            None => {
-                // No data available for this format
-                FormatDataResponse::new_error()
-            }
-        };
-
-        Ok(Some(ClipboardMessage::SendFormatData(response)))
+    fn send_clipboard_message(&self, message: ClipboardMessage) {
+        // The worker only stops once the clipboard is dropped
+        let _ = self.worker_tx.send(WorkerEvent::Message(message));
     }
 
     fn on_format_data_response(
@@ -104,6 +102,7 @@ impl WinClipboardImpl {
     fn get_remote_format_data(
         &mut self,
         format: ClipboardFormatId,
+        deadline: Instant,
     ) -> WinCliprdrResult<Option<FormatDataResponse<'static>>> {
         let mapped_format = self.remote_format_registry.local_to_remote(format);
 
@@ -117,27 +116,27 @@ impl WinClipboardImpl {
         // We need to receive data from the remote clipboard immediately, because Windows
         // expects us to set clipboard data before returning from `WM_RENDERFORMAT` handler.
         //
-        // Sadly, this will block the GUI thread, while data is being received
-        self.message_proxy
-            .send_clipboard_message(ClipboardMessage::SendInitiatePaste(remote_format));
-        let data = self
-            .backend_rx
-            .recv_timeout(Duration::from_secs(RENDER_FORMAT_TIMEOUT_SECS))
-            .map_err(|_| WinCliprdrError::DataReceiveTimeout)?;
-
-        match data {
-            BackendEvent::FormatDataResponse(response) => Ok(Some(response)),
-            _ => {
-                warn!("Unexpected FormatData response: {:?}", data);
-                // Unexpected message, ignore it
-                Ok(None)
+        // This blocks the GUI thread while data is being received, the worker processing the remote requests
+        // meanwhile.
+        let _ = self.worker_tx.send(WorkerEvent::FetchRemoteData(remote_format));
+
+        match self
+            .response_rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(response) => Ok(Some(response)),
+            Err(_) => {
+                let _ = self.worker_tx.send(WorkerEvent::CancelFetch);
+                Err(WinCliprdrError::DataReceiveTimeout)
             }
         }
     }
 
     fn on_render_format(&mut self, format: ClipboardFormatId) -> WinCliprdrResult<Option<ClipboardMessage>> {
         // Owning clipboard is not required when processing `WM_RENDERFORMAT` message
-        if let Some(response) = self.get_remote_format_data(format)? {
+        let deadline = Instant::now() + Duration::from_secs(RENDER_FORMAT_TIMEOUT_SECS);
+
+        if let Some(response) = self.get_remote_format_data(format, deadline)? {
             Self::on_format_data_response(format, &response)?;
         }
 
@@ -167,10 +166,14 @@ impl WinClipboardImpl {
 
         let formats = core::mem::take(&mut self.available_formats_on_remote);
 
-        // Clearing clipboard is not required, just render all available formats
+        // Clearing clipboard is not required, just render all available formats.
+        //
+        // The clipboard is kept open by the window until all the formats are rendered, so the time spent waiting for
+        // the remote is bounded for all the formats together.
+        let deadline = Instant::now() + Duration::from_secs(RENDER_FORMAT_TIMEOUT_SECS);
 
         for format in formats {
-            if let Some(response) = self.get_remote_format_data(format)? {
+            if let Some(response) = self.get_remote_format_data(format, deadline)? {
                 Self::on_format_data_response(format, &response)?;
             }
         }
@@ -178,6 +181,16 @@ impl WinClipboardImpl {
         Ok(None)
     }
 
+    /// Forgets the remote formats once another application took the ownership of the clipboard.
+    ///
+    /// The ownership is taken again on the next format list of the remote.
+    fn on_clipboard_ownership_lost(&mut self) {
+        if !self.available_formats_on_remote.is_empty() {
+            debug!("Clipboard ownership was taken by another application");
+            self.available_formats_on_remote.clear();
+        }
+    }
+
     fn on_remote_format_list(&mut self, formats: &[ClipboardFormat]) -> WinCliprdrResult<Option<ClipboardMessage>> {
         self.available_formats_on_remote.clear();
 
@@ -215,16 +228,31 @@ impl WinClipboardImpl {
 
     fn handle_event(&mut self, event: BackendEvent) {
         let result = match &event {
-            BackendEvent::FormatDataRequest(request) => self.on_format_data_request(request),
-            BackendEvent::FormatDataResponse(_) => {
-                // Out-of-order message, ignore it.
+            BackendEvent::FormatDataRequest(_) | BackendEvent::FormatDataResponse(_) => {
+                // Processed by the worker
                 Ok(None)
             }
             BackendEvent::RemoteFormatList(formats) => self.on_remote_format_list(formats),
 
             BackendEvent::ClipboardUpdated | BackendEvent::RemoteRequestsFormatList => self.on_clipboard_update(),
-            BackendEvent::RenderFormat(format) => self.on_render_format(*format),
-            BackendEvent::RenderAllFormats => self.on_render_all_formats(),
+            BackendEvent::RenderFormat(_) | BackendEvent::RenderAllFormats if self.rendering => {
+                // Rendering the data of the remote does not require processing window messages, so a nested request
+                // can only be sent by a misbehaving application: the clipboard is only rendered once at a time.
+                warn!(?event, "Ignoring re-entrant clipboard rendering request");
+                Ok(None)
+            }
+            BackendEvent::RenderFormat(format) => {
+                self.rendering = true;
+                let result = self.on_render_format(*format);
+                self.rendering = false;
+                result
+            }
+            BackendEvent::RenderAllFormats => {
+                self.rendering = true;
+                let result = self.on_render_all_formats();
+                self.rendering = false;
+                result
+            }
 
             BackendEvent::DowngradedCapabilities(flags) => {
                 warn!(?flags, "Unhandled downgraded capabilities event");
@@ -234,7 +262,7 @@ impl WinClipboardImpl {
 
         let retry_err = match result {
             Ok(Some(message)) => {
-                self.message_proxy.send_clipboard_message(message);
+                self.send_clipboard_message(message);
                 None
             }
             Ok(None) => {
@@ -246,8 +274,7 @@ impl WinClipboardImpl {
                 if let WinCliprdrError::ClipboardAccessDenied = &err {
                     Some(err)
                 } else {
-                    self.message_proxy
-                        .send_clipboard_message(ClipboardMessage::Error(Box::new(err)));
+                    self.send_clipboard_message(ClipboardMessage::Error(Box::new(err)));
                     None
                 }
             }
@@ -279,8 +306,7 @@ impl WinClipboardImpl {
                     };
                 } else {
                     // Send error, retries limit exceeded
-                    self.message_proxy
-                        .send_clipboard_message(ClipboardMessage::Error(Box::new(err)));
+                    self.send_clipboard_message(ClipboardMessage::Error(Box::new(err)));
                 }
             }
         }
@@ -335,6 +361,10 @@ pub(crate) unsafe extern "system" fn clipboard_subproc(
             let clipboard_owner = unsafe { GetClipboardOwner() };
             let spurious_event = clipboard_owner == Ok(hwnd);
 
+            if !spurious_event {
+                ctx.on_clipboard_ownership_lost();
+            }
+
             // We need to send copy message from remote only when window is NOT active, because if
             // it is active, then user wants to perform copy from remote instead. Also, we need to
             // check that we are not the source of the clipboard change, because if we are,
@@ -352,9 +382,9 @@ pub(crate) unsafe extern "system" fn clipboard_subproc(
         WM_RENDERALLFORMATS => {
             ctx.handle_event(BackendEvent::RenderAllFormats);
         }
-        // User event, a message was sent by the `Cliprdr` backend shim
+        // User event, a message was forwarded by the worker
         WM_CLIPRDR_BACKEND_EVENT => {
-            let message = if let Ok(message) = ctx.backend_rx.try_recv() {
+            let message = if let Ok(message) = ctx.window_rx.try_recv() {
                 message
             } else {
                 // No message has been received, spurious event
@@ -6,41 +6,28 @@ use ironrdp_cliprdr::pdu::{
     FormatDataResponse, LockDataId,
 };
 use ironrdp_core::{impl_as_any, IntoOwned};
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
 
-use crate::windows::{BackendEvent, WM_CLIPRDR_BACKEND_EVENT};
+use crate::windows::worker::WorkerEvent;
+use crate::windows::BackendEvent;
 
 #[derive(Debug)]
 pub(crate) struct WinCliprdrBackend {
-    backend_event_tx: mpsc_sync::SyncSender<BackendEvent>,
-    window: HWND,
+    worker_tx: mpsc_sync::SyncSender<WorkerEvent>,
 }
 
-// SAFETY: window handle is thread safe for PostMessageW usage
-unsafe impl Send for WinCliprdrBackend {}
-
 impl_as_any!(WinCliprdrBackend);
 
 impl WinCliprdrBackend {
-    pub(crate) fn new(window: HWND, backend_event_tx: mpsc_sync::SyncSender<BackendEvent>) -> Self {
-        Self {
-            window,
-            backend_event_tx,
-        }
+    pub(crate) fn new(worker_tx: mpsc_sync::SyncSender<WorkerEvent>) -> Self {
+        Self { worker_tx }
     }
 
     fn send_event(&self, event: BackendEvent) {
-        if self.backend_event_tx.send(event).is_err() {
-            // Channel is closed, backend is dead
-            return;
-        }
-        // Wake up subproc event loop; Dont wait for result
+        // The worker is never blocked on the window thread, so the channel is drained even while a format is
+        // being rendered.
         //
-        // SAFETY: it is safe to call PostMessageW from any thread with a valid window handle
-        if let Err(err) = unsafe { PostMessageW(self.window, WM_CLIPRDR_BACKEND_EVENT, WPARAM(0), LPARAM(0)) } {
-            tracing::error!("Failed to post message to wake up subproc event loop: {}", err);
-        }
+        // An error means that the channel is closed, the clipboard is dropped.
+        let _ = self.worker_tx.send(WorkerEvent::Backend(event));
     }
 }
 
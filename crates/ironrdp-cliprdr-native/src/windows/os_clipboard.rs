@@ -1,6 +1,6 @@
 use ironrdp_cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, ClipboardFormatName};
 use tracing::error;
-use windows::Win32::Foundation::{HANDLE, HWND};
+use windows::Win32::Foundation::{ERROR_ACCESS_DENIED, HANDLE, HWND};
 use windows::Win32::System::DataExchange::{
     CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardFormatNameW, OpenClipboard, SetClipboardData,
 };
@@ -14,7 +14,14 @@ pub(crate) struct OwnedOsClipboard;
 impl OwnedOsClipboard {
     pub(crate) fn new(window: HWND) -> Result<Self, WinCliprdrError> {
         // SAFETY: `window` is valid handle, therefore it is safe to call `OpenClipboard`.
-        unsafe { OpenClipboard(window)? };
+        unsafe { OpenClipboard(window) }.map_err(|err| {
+            // The clipboard is open by another window, which should close it shortly
+            if err.code() == ERROR_ACCESS_DENIED.to_hresult() {
+                WinCliprdrError::ClipboardAccessDenied
+            } else {
+                WinCliprdrError::WinAPI(err)
+            }
+        })?;
         Ok(Self)
     }
 
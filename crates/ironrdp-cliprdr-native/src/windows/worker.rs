@@ -0,0 +1,209 @@
+use core::time::Duration;
+use std::sync::mpsc;
+use std::time::Instant;
+
+use ironrdp_cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy};
+use ironrdp_cliprdr::pdu::{ClipboardFormatId, FormatDataRequest, FormatDataResponse};
+use tracing::{debug, warn};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::DataExchange::GetClipboardOwner;
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+use crate::windows::clipboard_data_ref::ClipboardDataRef;
+use crate::windows::os_clipboard::OwnedOsClipboard;
+use crate::windows::{BackendEvent, WinCliprdrError, WinCliprdrResult, WM_CLIPRDR_BACKEND_EVENT};
+
+const MAX_PROCESSING_ATTEMPTS: u32 = 10;
+const PROCESSING_TIMEOUT_MS: u64 = 100;
+
+/// Sent to the worker, by the clipboard backend shim or by the window thread
+#[derive(Debug)]
+pub(crate) enum WorkerEvent {
+    /// Event received from the `CLIPRDR` backend.
+    Backend(BackendEvent),
+    /// Message to forward to the `CLIPRDR` channel.
+    Message(ClipboardMessage),
+    /// The window thread is rendering a delay-rendered format, and waits for the data of the given remote format.
+    FetchRemoteData(ClipboardFormatId),
+    /// The window thread stopped waiting for the data requested last.
+    CancelFetch,
+    Shutdown,
+}
+
+/// Format data request which could not be processed because the clipboard was busy
+struct PendingRequest {
+    request: FormatDataRequest,
+    attempt: u32,
+    retry_at: Instant,
+}
+
+/// Bridge between the `CLIPRDR` channel and the window thread owning the clipboard.
+///
+/// The window thread blocks while rendering a delay-rendered format, until the data is received from the remote.
+/// The worker keeps processing the other events meanwhile, so that the requests of the remote never wait for the
+/// window thread:
+///
+/// - the format data responses are routed to the rendering window thread;
+/// - the format data requests are processed directly, by reading the clipboard;
+/// - the other events, which need to take the ownership of the clipboard, are forwarded to the window thread.
+pub(crate) struct ClipboardWorker {
+    window: HWND,
+    message_proxy: Box<dyn ClipboardMessageProxy>,
+    worker_rx: mpsc::Receiver<WorkerEvent>,
+    window_tx: mpsc::Sender<BackendEvent>,
+    response_tx: mpsc::Sender<FormatDataResponse<'static>>,
+    /// Whether the window thread is waiting for the response to the last format data request.
+    fetching: bool,
+    /// Number of format data requests sent to the remote and not answered yet.
+    ///
+    /// The remote answers in order, so the response to the last request is the one received when no other
+    /// request is left.
+    outstanding_requests: u32,
+    pending_request: Option<PendingRequest>,
+}
+
+// SAFETY: window handle is thread safe for PostMessageW usage, and the clipboard functions used by the worker are
+// not bound to the thread owning the window.
+unsafe impl Send for ClipboardWorker {}
+
+impl ClipboardWorker {
+    pub(crate) fn new(
+        window: HWND,
+        message_proxy: impl ClipboardMessageProxy + 'static,
+        worker_rx: mpsc::Receiver<WorkerEvent>,
+        window_tx: mpsc::Sender<BackendEvent>,
+        response_tx: mpsc::Sender<FormatDataResponse<'static>>,
+    ) -> Self {
+        Self {
+            window,
+            message_proxy: Box::new(message_proxy),
+            worker_rx,
+            window_tx,
+            response_tx,
+            fetching: false,
+            outstanding_requests: 0,
+            pending_request: None,
+        }
+    }
+
+    /// Processes the events until [`WorkerEvent::Shutdown`] is received, or all the senders are dropped.
+    pub(crate) fn run(mut self) {
+        loop {
+            let event = match &self.pending_request {
+                Some(pending) => {
+                    let timeout = pending.retry_at.saturating_duration_since(Instant::now());
+
+                    match self.worker_rx.recv_timeout(timeout) {
+                        Ok(event) => event,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            let pending = self.pending_request.take().expect("pending request");
+                            self.on_format_data_request(pending.request, pending.attempt);
+                            continue;
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match self.worker_rx.recv() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                },
+            };
+
+            match event {
+                WorkerEvent::Backend(BackendEvent::FormatDataRequest(request)) => {
+                    // Requests are answered in order, the remote does not wait for a stale one.
+                    if self.pending_request.take().is_some() {
+                        self.send_message(ClipboardMessage::SendFormatData(FormatDataResponse::new_error()));
+                    }
+
+                    self.on_format_data_request(request, 0);
+                }
+                WorkerEvent::Backend(BackendEvent::FormatDataResponse(response)) => {
+                    self.outstanding_requests = self.outstanding_requests.saturating_sub(1);
+
+                    if self.outstanding_requests == 0 && core::mem::take(&mut self.fetching) {
+                        let _ = self.response_tx.send(response);
+                    } else {
+                        // The window thread stopped waiting for this response.
+                        debug!("Dropping stale format data response");
+                    }
+                }
+                WorkerEvent::Backend(BackendEvent::DowngradedCapabilities(flags)) => {
+                    warn!(?flags, "Unhandled downgraded capabilities event");
+                }
+                WorkerEvent::Backend(event) => self.forward_to_window(event),
+                WorkerEvent::Message(message) => self.send_message(message),
+                WorkerEvent::FetchRemoteData(format) => {
+                    self.fetching = true;
+                    self.outstanding_requests = self.outstanding_requests.saturating_add(1);
+                    self.send_message(ClipboardMessage::SendInitiatePaste(format));
+                }
+                WorkerEvent::CancelFetch => self.fetching = false,
+                WorkerEvent::Shutdown => break,
+            }
+        }
+    }
+
+    fn send_message(&self, message: ClipboardMessage) {
+        self.message_proxy.send_clipboard_message(message);
+    }
+
+    fn forward_to_window(&self, event: BackendEvent) {
+        if self.window_tx.send(event).is_err() {
+            // Channel is closed, window is destroyed
+            return;
+        }
+
+        // Wake up subproc event loop; Dont wait for result
+        //
+        // SAFETY: it is safe to call PostMessageW from any thread with a valid window handle
+        if let Err(err) = unsafe { PostMessageW(self.window, WM_CLIPRDR_BACKEND_EVENT, WPARAM(0), LPARAM(0)) } {
+            tracing::error!("Failed to post message to wake up subproc event loop: {}", err);
+        }
+    }
+
+    fn on_format_data_request(&mut self, request: FormatDataRequest, attempt: u32) {
+        match self.read_format_data(&request) {
+            Ok(response) => self.send_message(ClipboardMessage::SendFormatData(response)),
+            // Access to the clipboard is temporarily denied while another application has it open.
+            Err(WinCliprdrError::ClipboardAccessDenied) if attempt < MAX_PROCESSING_ATTEMPTS => {
+                #[allow(clippy::arithmetic_side_effects)]
+                // attempt can’t be greater than MAX_PROCESSING_ATTEMPTS, so the arithmetic is safe here
+                let attempt = attempt + 1;
+
+                self.pending_request = Some(PendingRequest {
+                    request,
+                    attempt,
+                    retry_at: Instant::now() + Duration::from_millis(u64::from(attempt) * PROCESSING_TIMEOUT_MS),
+                });
+            }
+            Err(err) => {
+                self.send_message(ClipboardMessage::SendFormatData(FormatDataResponse::new_error()));
+                self.send_message(ClipboardMessage::Error(Box::new(err)));
+            }
+        }
+    }
+
+    fn read_format_data(&self, request: &FormatDataRequest) -> WinCliprdrResult<FormatDataResponse<'static>> {
+        let clipboard = OwnedOsClipboard::new(self.window)?;
+
+        // The window owns the clipboard when its content comes from the remote. Reading a delay-rendered format
+        // would wait for the window thread, itself waiting for the worker if it is rendering: the remote is asking
+        // for the data of an outdated format list, there is nothing to send back.
+        //
+        // SAFETY: `GetClipboardOwner` is always safe to call.
+        if unsafe { GetClipboardOwner() } == Ok(self.window) {
+            return Ok(FormatDataResponse::new_error());
+        }
+
+        let response = match ClipboardDataRef::get(&clipboard, request.format) {
+            Some(data) => FormatDataResponse::new_data(data.data().to_vec()),
+            None => {
+                // No data available for this format
+                FormatDataResponse::new_error()
+            }
+        };
+
+        Ok(response)
+    }
+}
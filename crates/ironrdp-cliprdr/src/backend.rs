@@ -6,6 +6,7 @@ use crate::pdu::{
     ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse,
     FormatDataRequest, FormatDataResponse, LockDataId, OwnedFormatDataResponse,
 };
+use crate::transfer::TransferProgress;
 
 pub trait ClipboardError: std::error::Error + Send + Sync + 'static {}
 
@@ -126,6 +127,15 @@ pub trait CliprdrBackend: AsAny + core::fmt::Debug + Send {
     /// client should respond by calling `submit_file_contents` on [crate::Cliprdr]
     fn on_file_contents_request(&mut self, request: FileContentsRequest);
 
+    /// Processes the progress of a file transfer.
+    ///
+    /// Called by [crate::Cliprdr] before [`CliprdrBackend::on_file_contents_request`], for the data requests of the
+    /// files listed with [`crate::Cliprdr::submit_file_list`].
+    ///
+    /// This method has default implementation which does nothing because it is not required for
+    /// most of the backends.
+    fn on_file_transfer_progress(&mut self, _progress: TransferProgress) {}
+
     /// Processes remote's response to previously sent file contents request.
     ///
     /// Called by [crate::Cliprdr] when server sends file contents to the client clipboard as a response to
@@ -8,6 +8,7 @@
 
 pub mod backend;
 pub mod pdu;
+pub mod transfer;
 
 use backend::CliprdrBackend;
 use ironrdp_core::{decode, AsAny, EncodeResult};
@@ -19,11 +20,12 @@ use ironrdp_svc::{
 };
 use pdu::{
     Capabilities, ClientTemporaryDirectory, ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags,
-    ClipboardPdu, ClipboardProtocolVersion, FileContentsResponse, FormatDataRequest, FormatListResponse,
-    OwnedFormatDataResponse,
+    ClipboardPdu, ClipboardProtocolVersion, FileContentsResponse, FormatDataRequest, FormatListResponse, LockDataId,
+    OwnedFormatDataResponse, PackedFileList,
 };
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::{debug, error, info};
+use transfer::FileTransfers;
 
 #[rustfmt::skip] // do not reorder
 use crate::pdu::FormatList;
@@ -38,6 +40,63 @@ enum ClipboardError {
 
     #[error("sent format list was rejected")]
     FormatListRejected,
+
+    #[error("received clipboard PDU is too large ({length} bytes)")]
+    PduTooLarge { length: usize },
+}
+
+/// Limits on the clipboard data received from the remote
+///
+/// The defaults are generous, and only meant to protect against a remote exhausting the memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CliprdrLimits {
+    /// Maximum number of formats in a format list, larger lists are rejected.
+    pub max_format_list_entries: usize,
+    /// Maximum length of a format name in characters, format lists with longer names are rejected.
+    pub max_format_name_len: usize,
+    /// Maximum size of a clipboard PDU, typically a format data response, in bytes.
+    ///
+    /// Larger PDUs are discarded as they are received, and the channel is transitioned to the failed state.
+    pub max_format_data_size: usize,
+}
+
+impl Default for CliprdrLimits {
+    fn default() -> Self {
+        Self {
+            max_format_list_entries: 4096,
+            max_format_name_len: 1024,
+            max_format_data_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Directions in which the clipboard is shared with the remote
+///
+/// The policy is enforced by [`Cliprdr`], before reaching the [`CliprdrBackend`]: the local copies are not announced
+/// to the remote, and the remote copies are acknowledged without being forwarded to the backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ClipboardPolicy {
+    /// The clipboard is not shared.
+    Disabled,
+    /// Only the local copies can be pasted on the remote.
+    HostToRemote,
+    /// Only the remote copies can be pasted locally.
+    RemoteToHost,
+    /// The clipboard is shared both ways.
+    #[default]
+    Bidirectional,
+}
+
+impl ClipboardPolicy {
+    /// Returns whether the local copies are announced to the remote.
+    pub fn allows_host_to_remote(self) -> bool {
+        matches!(self, Self::HostToRemote | Self::Bidirectional)
+    }
+
+    /// Returns whether the remote copies are forwarded to the backend.
+    pub fn allows_remote_to_host(self) -> bool {
+        matches!(self, Self::RemoteToHost | Self::Bidirectional)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -57,6 +116,17 @@ pub struct Cliprdr<R: Role> {
     backend: Box<dyn CliprdrBackend>,
     capabilities: Capabilities,
     state: CliprdrState,
+    /// Clipboard data locks requested by the remote and not released yet.
+    locks: Vec<LockDataId>,
+    /// File transfers of the last file list submitted with [`Cliprdr::submit_file_list`].
+    transfers: FileTransfers,
+    limits: CliprdrLimits,
+    policy: ClipboardPolicy,
+    /// Formats of the last local copy, as long as the remote did not copy since.
+    local_formats: Option<Vec<ClipboardFormat>>,
+    reannounce_on_ready: bool,
+    /// The channel is initialized again, after having been ready or failed.
+    reinitializing: bool,
     _marker: core::marker::PhantomData<R>,
 }
 
@@ -100,10 +170,63 @@ impl<R: Role> Cliprdr<R> {
             backend,
             state: CliprdrState::Initialization,
             capabilities: Capabilities::new(ClipboardProtocolVersion::V2, flags),
+            locks: Vec::new(),
+            transfers: FileTransfers::default(),
+            limits: CliprdrLimits::default(),
+            policy: ClipboardPolicy::default(),
+            local_formats: None,
+            reannounce_on_ready: true,
+            reinitializing: false,
             _marker: core::marker::PhantomData,
         }
     }
 
+    /// Sets whether the formats of the last local copy are announced again when the channel is re-initialized.
+    ///
+    /// The remote loses the clipboard formats when the channel is re-initialized (e.g.: after a reconnection), and
+    /// pasting would not work until the next local copy. The formats are not announced again if the remote copied
+    /// in the meantime, as it would replace the remote clipboard. Enabled by default.
+    #[must_use]
+    pub fn with_reannounce_on_ready(mut self, reannounce_on_ready: bool) -> Self {
+        self.reannounce_on_ready = reannounce_on_ready;
+        self
+    }
+
+    /// Sets the limits on the clipboard data received from the remote.
+    #[must_use]
+    pub fn with_limits(mut self, limits: CliprdrLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the limits on the clipboard data received from the remote.
+    pub fn limits(&self) -> &CliprdrLimits {
+        &self.limits
+    }
+
+    /// Sets the directions in which the clipboard is shared, [`ClipboardPolicy::Bidirectional`] by default.
+    #[must_use]
+    pub fn with_policy(mut self, policy: ClipboardPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Changes the directions in which the clipboard is shared, taking effect for the next copy.
+    ///
+    /// The formats already announced to the remote stay announced, but their data is not sent anymore if the local
+    /// copies are no longer shared.
+    pub fn set_policy(&mut self, policy: ClipboardPolicy) {
+        if policy != self.policy {
+            info!(from = ?self.policy, to = ?policy, "CLIPRDR(clipboard) policy changed");
+            self.policy = policy;
+        }
+    }
+
+    /// Returns the directions in which the clipboard is shared.
+    pub fn policy(&self) -> ClipboardPolicy {
+        self.policy
+    }
+
     pub fn downcast_backend<T: CliprdrBackend>(&self) -> Option<&T> {
         self.backend.as_any().downcast_ref::<T>()
     }
@@ -122,6 +245,36 @@ impl<R: Role> Cliprdr<R> {
         FormatList::new_unicode(formats, self.are_long_format_names_enabled())
     }
 
+    /// Returns the formats to announce again to the remote, if the channel is being re-initialized.
+    fn take_reannounced_formats(&mut self) -> Option<Vec<ClipboardFormat>> {
+        let reinitializing = core::mem::take(&mut self.reinitializing);
+
+        if reinitializing && self.reannounce_on_ready && self.policy.allows_host_to_remote() {
+            self.local_formats.clone()
+        } else {
+            None
+        }
+    }
+
+    fn reinitialize(&mut self) {
+        info!(state = ?self.state, "CLIPRDR(clipboard) virtual channel is re-initialized");
+        self.state = CliprdrState::Initialization;
+        self.reinitializing = true;
+    }
+
+    /// Returns the PDUs initializing the client side of the channel, along with the first format list.
+    fn client_initialization(&self, available_formats: &[ClipboardFormat]) -> PduResult<Vec<ClipboardPdu<'static>>> {
+        // During initialization state, first copy action is synthetic and should be sent along with
+        // capabilities and temporary directory PDUs.
+        Ok(vec![
+            ClipboardPdu::Capabilities(self.capabilities.clone()),
+            ClipboardPdu::TemporaryDirectory(
+                ClientTemporaryDirectory::new(self.backend.temporary_directory()).map_err(|e| encode_err!(e))?,
+            ),
+            ClipboardPdu::FormatList(self.build_format_list(available_formats).map_err(|e| encode_err!(e))?),
+        ])
+    }
+
     fn handle_error_transition(&mut self, err: ClipboardError) -> PduResult<Vec<SvcMessage>> {
         // Failure of clipboard is not an critical error, but we should properly report it
         // and transition channel to failed state.
@@ -132,6 +285,10 @@ impl<R: Role> Cliprdr<R> {
     }
 
     fn handle_server_capabilities(&mut self, server_capabilities: Capabilities) -> PduResult<Vec<SvcMessage>> {
+        if !R::is_server() && self.state != CliprdrState::Initialization {
+            self.reinitialize();
+        }
+
         self.capabilities.downgrade(&server_capabilities);
         self.backend
             .on_process_negotiated_capabilities(self.capabilities.flags());
@@ -141,6 +298,12 @@ impl<R: Role> Cliprdr<R> {
     }
 
     fn handle_monitor_ready(&mut self) -> PduResult<Vec<SvcMessage>> {
+        if let Some(formats) = self.take_reannounced_formats() {
+            info!("CLIPRDR(clipboard) Announcing the formats of the last copy again");
+            let pdus = self.client_initialization(&formats)?;
+            return Ok(pdus.into_iter().map(into_cliprdr_message).collect());
+        }
+
         // Request client to sent list of initially available formats and wait for the backend
         // response.
         self.backend.on_request_format_list();
@@ -170,17 +333,64 @@ impl<R: Role> Cliprdr<R> {
     }
 
     fn handle_format_list(&mut self, format_list: FormatList<'_>) -> PduResult<Vec<SvcMessage>> {
+        let formats = format_list.get_formats(self.are_long_format_names_enabled())?;
+
+        let mut reannounced = None;
+
         if R::is_server() && self.state == CliprdrState::Initialization {
             info!("CLIPRDR(clipboard) virtual channel has been initialized");
             self.state = CliprdrState::Ready;
+
+            // The first format list of the client is synthetic, and only replaces the clipboard when not empty.
+            if formats.is_empty() {
+                reannounced = self.take_reannounced_formats();
+            }
         }
 
-        let formats = format_list.get_formats(self.are_long_format_names_enabled())?;
-        self.backend.on_remote_copy(&formats);
+        if reannounced.is_none() {
+            // The remote owns the clipboard from now on.
+            self.local_formats = None;
+            self.reinitializing = false;
+        }
+
+        let limits = &self.limits;
+        let response = if formats.len() > limits.max_format_list_entries {
+            error!(
+                count = formats.len(),
+                max = limits.max_format_list_entries,
+                "Rejecting format list with too many formats"
+            );
+            FormatListResponse::Fail
+        } else if let Some(name) = formats
+            .iter()
+            .filter_map(ClipboardFormat::name)
+            .find(|name| name.value().chars().count() > limits.max_format_name_len)
+        {
+            error!(
+                len = name.value().chars().count(),
+                max = limits.max_format_name_len,
+                "Rejecting format list with a too long format name"
+            );
+            FormatListResponse::Fail
+        } else if !self.policy.allows_remote_to_host() {
+            // The format list is acknowledged, so that the remote keeps using the channel.
+            debug!(policy = ?self.policy, "Ignoring remote copy");
+            FormatListResponse::Ok
+        } else {
+            self.backend.on_remote_copy(&formats);
+            FormatListResponse::Ok
+        };
 
-        let pdu = ClipboardPdu::FormatListResponse(FormatListResponse::Ok);
+        let mut pdus = vec![ClipboardPdu::FormatListResponse(response)];
 
-        Ok(vec![into_cliprdr_message(pdu)])
+        if let Some(formats) = reannounced {
+            info!("CLIPRDR(clipboard) Announcing the formats of the last copy again");
+            pdus.push(ClipboardPdu::FormatList(
+                self.build_format_list(&formats).map_err(|e| encode_err!(e))?,
+            ));
+        }
+
+        Ok(pdus.into_iter().map(into_cliprdr_message).collect())
     }
 
     /// Submits the format data response, returning a [`CliprdrSvcMessages`] to send on the channel.
@@ -213,6 +423,36 @@ impl<R: Role> Cliprdr<R> {
         Ok(vec![into_cliprdr_message(pdu)].into())
     }
 
+    /// Submits a file list as the format data response, returning a [`CliprdrSvcMessages`] to send on the channel.
+    ///
+    /// Same as [`Cliprdr::submit_format_data`] for the `FileGroupDescriptorW` format, except that the transfers of
+    /// the listed files are tracked: their progress is reported to [`CliprdrBackend::on_file_transfer_progress`],
+    /// and they can be cancelled with [`Cliprdr::cancel_file_transfer`].
+    pub fn submit_file_list(&mut self, list: &PackedFileList) -> PduResult<CliprdrSvcMessages<R>> {
+        ready_guard!(self, submit_file_list);
+
+        self.transfers.set_file_list(&list.files);
+
+        let response = OwnedFormatDataResponse::new_file_list(list).map_err(|e| encode_err!(e))?;
+        let pdu = ClipboardPdu::FormatDataResponse(response);
+
+        Ok(vec![into_cliprdr_message(pdu)].into())
+    }
+
+    /// Cancels the file transfers on the stream `stream_id`.
+    ///
+    /// The subsequent file contents requests of the remote for this stream are answered with an error response,
+    /// without being forwarded to the backend.
+    pub fn cancel_file_transfer(&mut self, stream_id: u32) {
+        debug!(stream_id, "Cancelling file transfer");
+        self.transfers.cancel(stream_id);
+    }
+
+    /// Returns the file transfers of the last file list submitted with [`Cliprdr::submit_file_list`].
+    pub fn file_transfers(&self) -> &FileTransfers {
+        &self.transfers
+    }
+
     pub fn capabilities(&self) -> PduResult<SvcMessage> {
         let pdu = ClipboardPdu::Capabilities(self.capabilities.clone());
 
@@ -228,31 +468,35 @@ impl<R: Role> Cliprdr<R> {
     /// Starts processing of `CLIPRDR` copy command. Should be called by the clipboard
     /// implementation when user performs OS-specific copy command (e.g. `Ctrl+C` shortcut on
     /// keyboard)
-    pub fn initiate_copy(&self, available_formats: &[ClipboardFormat]) -> PduResult<CliprdrSvcMessages<R>> {
-        let mut pdus = Vec::new();
+    ///
+    /// The formats are remembered, in order to announce them again if the channel is re-initialized.
+    ///
+    /// If the local copies are not shared according to the [`ClipboardPolicy`], the copy is ignored. An empty format
+    /// list is sent instead when it is required to initialize the channel.
+    pub fn initiate_copy(&mut self, available_formats: &[ClipboardFormat]) -> PduResult<CliprdrSvcMessages<R>> {
+        let available_formats = if self.policy.allows_host_to_remote() {
+            available_formats
+        } else if self.state == CliprdrState::Ready {
+            debug!(policy = ?self.policy, "Ignoring local copy");
+            return Ok(Vec::new().into());
+        } else {
+            &[]
+        };
 
-        match (self.state, R::is_server()) {
+        let pdus = match (self.state, R::is_server()) {
             // When user initiates copy, we should send format list to server.
-            (CliprdrState::Ready, _) => {
-                pdus.push(ClipboardPdu::FormatList(
-                    self.build_format_list(available_formats).map_err(|e| encode_err!(e))?,
-                ));
-            }
-            (CliprdrState::Initialization, false) => {
-                // During initialization state, first copy action is synthetic and should be sent along with
-                // capabilities and temporary directory PDUs.
-                pdus.push(ClipboardPdu::Capabilities(self.capabilities.clone()));
-                pdus.push(ClipboardPdu::TemporaryDirectory(
-                    ClientTemporaryDirectory::new(self.backend.temporary_directory()).map_err(|e| encode_err!(e))?,
-                ));
-                pdus.push(ClipboardPdu::FormatList(
-                    self.build_format_list(available_formats).map_err(|e| encode_err!(e))?,
-                ));
-            }
+            (CliprdrState::Ready, _) => vec![ClipboardPdu::FormatList(
+                self.build_format_list(available_formats).map_err(|e| encode_err!(e))?,
+            )],
+            (CliprdrState::Initialization, false) => self.client_initialization(available_formats)?,
             _ => {
                 error!(?self.state, "Attempted to initiate copy in incorrect state");
+                return Ok(Vec::new().into());
             }
-        }
+        };
+
+        self.local_formats = (!available_formats.is_empty()).then(|| available_formats.to_vec());
+        self.reinitializing = false;
 
         Ok(pdus.into_iter().map(into_cliprdr_message).collect::<Vec<_>>().into())
     }
@@ -260,9 +504,16 @@ impl<R: Role> Cliprdr<R> {
     /// Starts processing of `CLIPRDR` paste command. Should be called by the clipboard
     /// implementation when user performs OS-specific paste command (e.g. `Ctrl+V` shortcut on
     /// keyboard)
+    ///
+    /// Nothing is sent if the remote copies are not shared according to the [`ClipboardPolicy`].
     pub fn initiate_paste(&self, requested_format: ClipboardFormatId) -> PduResult<CliprdrSvcMessages<R>> {
         ready_guard!(self, initiate_paste);
 
+        if !self.policy.allows_remote_to_host() {
+            debug!(policy = ?self.policy, "Ignoring paste of the remote clipboard");
+            return Ok(Vec::new().into());
+        }
+
         // When user initiates paste, we should send format data request to server, and expect to
         // receive response with contents via `FormatDataResponse` PDU.
         let pdu = ClipboardPdu::FormatDataRequest(FormatDataRequest {
@@ -280,7 +531,7 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
 
     fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
         if self.state != CliprdrState::Initialization {
-            error!("Attempted to start clipboard static virtual channel in invalid state");
+            self.reinitialize();
         }
 
         if R::is_server() {
@@ -293,7 +544,10 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
     fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
         let pdu = decode::<ClipboardPdu<'_>>(payload).map_err(|e| decode_err!(e))?;
 
-        if self.state == CliprdrState::Failed {
+        // The server may initialize the channel again, by sending its capabilities.
+        let is_reinitialization = !R::is_server() && matches!(pdu, ClipboardPdu::Capabilities(_));
+
+        if self.state == CliprdrState::Failed && !is_reinitialization {
             error!("Attempted to process clipboard static virtual channel in failed state");
             return Ok(Vec::new());
         }
@@ -303,15 +557,27 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
             ClipboardPdu::FormatList(format_list) => self.handle_format_list(format_list),
             ClipboardPdu::FormatListResponse(response) => self.handle_format_list_response(response),
             ClipboardPdu::MonitorReady => self.handle_monitor_ready(),
+            ClipboardPdu::TemporaryDirectory(_) if R::is_server() => {
+                // File transfers are not using the client temporary directory, so it is ignored.
+                Ok(Vec::new())
+            }
             ClipboardPdu::LockData(id) => {
+                self.locks.push(id.clone());
                 self.backend.on_lock(id);
                 Ok(Vec::new())
             }
             ClipboardPdu::UnlockData(id) => {
+                self.locks.retain(|lock| *lock != id);
                 self.backend.on_unlock(id);
                 Ok(Vec::new())
             }
             ClipboardPdu::FormatDataRequest(request) => {
+                if !self.policy.allows_host_to_remote() {
+                    debug!(policy = ?self.policy, "Rejecting format data request");
+                    let pdu = ClipboardPdu::FormatDataResponse(OwnedFormatDataResponse::new_error());
+                    return Ok(vec![into_cliprdr_message(pdu)]);
+                }
+
                 self.backend.on_format_data_request(request);
 
                 // NOTE: An actual data should be sent later via `submit_format_data` method,
@@ -323,6 +589,25 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
                 Ok(Vec::new())
             }
             ClipboardPdu::FileContentsRequest(request) => {
+                if !self.policy.allows_host_to_remote() {
+                    debug!(policy = ?self.policy, "Rejecting file contents request");
+                    let pdu = ClipboardPdu::FileContentsResponse(FileContentsResponse::new_error(request.stream_id));
+                    return Ok(vec![into_cliprdr_message(pdu)]);
+                }
+
+                if self.transfers.is_cancelled(request.stream_id) {
+                    debug!(
+                        stream_id = request.stream_id,
+                        "Rejecting request of a cancelled file transfer"
+                    );
+                    let pdu = ClipboardPdu::FileContentsResponse(FileContentsResponse::new_error(request.stream_id));
+                    return Ok(vec![into_cliprdr_message(pdu)]);
+                }
+
+                if let Some(progress) = self.transfers.on_request(&request) {
+                    self.backend.on_file_transfer_progress(progress);
+                }
+
                 self.backend.on_file_contents_request(request);
                 Ok(Vec::new())
             }
@@ -336,9 +621,28 @@ impl<R: Role> SvcProcessor for Cliprdr<R> {
         }
     }
 
+    fn max_pdu_length(&self) -> Option<usize> {
+        Some(self.limits.max_format_data_size)
+    }
+
+    fn on_oversized_pdu(&mut self, length: usize) -> PduResult<Vec<SvcMessage>> {
+        self.handle_error_transition(ClipboardError::PduTooLarge { length })
+    }
+
     fn compression_condition(&self) -> CompressionCondition {
         CompressionCondition::WhenRdpDataIsCompressed
     }
+
+    fn close(&mut self) -> PduResult<Vec<SvcMessage>> {
+        // The remote will not be able to release the locks it is still holding, so we do it on its behalf.
+        for id in core::mem::take(&mut self.locks) {
+            self.backend.on_unlock(id);
+        }
+
+        self.state = CliprdrState::Failed;
+
+        Ok(Vec::new())
+    }
 }
 
 fn into_cliprdr_message(pdu: ClipboardPdu<'static>) -> SvcMessage {
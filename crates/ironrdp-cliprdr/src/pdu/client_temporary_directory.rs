@@ -1,11 +1,10 @@
 use std::borrow::Cow;
 
 use ironrdp_core::{
-    cast_int, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, IntoOwned, ReadCursor,
-    WriteCursor,
+    cast_int, ensure_size, Decode, DecodeResult, Encode, EncodeResult, IntoOwned, ReadCursor, WriteCursor,
 };
 use ironrdp_pdu::impl_pdu_borrowing;
-use ironrdp_pdu::utils::{read_string_from_cursor, write_string_to_cursor, CharacterSet};
+use ironrdp_pdu::utf16::{decode_utf16le_until_null, encode_utf16le_fixed_size, FixedSizeOverflow};
 
 use crate::pdu::PartialHeader;
 
@@ -39,7 +38,7 @@ impl ClientTemporaryDirectory<'_> {
 
         {
             let mut cursor = WriteCursor::new(&mut buffer);
-            write_string_to_cursor(&mut cursor, path, CharacterSet::Unicode, true)?;
+            encode_utf16le_fixed_size(&mut cursor, path, Self::PATH_BUFFER_SIZE, FixedSizeOverflow::Error)?;
         }
 
         Ok(Self {
@@ -51,8 +50,7 @@ impl ClientTemporaryDirectory<'_> {
     pub fn temporary_directory_path(&self) -> DecodeResult<String> {
         let mut cursor = ReadCursor::new(&self.path_buffer);
 
-        read_string_from_cursor(&mut cursor, CharacterSet::Unicode, true)
-            .map_err(|_| invalid_field_err!("wszTempDir", "failed to decode temp dir path"))
+        Ok(decode_utf16le_until_null(&mut cursor))
     }
 }
 
@@ -2,8 +2,9 @@ use bitflags::bitflags;
 use ironrdp_core::{
     cast_length, ensure_fixed_part_size, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
-use ironrdp_pdu::utils::{combine_u64, decode_string, encode_string, split_u64, CharacterSet};
-use ironrdp_pdu::{impl_pdu_pod, write_padding};
+use ironrdp_pdu::impl_pdu_pod;
+use ironrdp_pdu::utf16::{decode_utf16le_lossy_exact, encode_utf16le_fixed_size, FixedSizeOverflow};
+use ironrdp_pdu::utils::{combine_u64, split_u64};
 
 const NAME_LENGTH: usize = 520;
 
@@ -97,11 +98,8 @@ impl Encode for FileDescriptor {
         dst.write_u32(size_hi);
         dst.write_u32(size_lo);
 
-        let written = encode_string(dst.remaining_mut(), &self.name, CharacterSet::Unicode, true)?;
-        dst.advance(written);
-
-        // Pad with zeroes, overriding any previously written data
-        write_padding!(dst, NAME_LENGTH - written);
+        // The name must not overflow its fixed-size field.
+        encode_utf16le_fixed_size(dst, &self.name, NAME_LENGTH, FixedSizeOverflow::Error)?;
 
         Ok(())
     }
@@ -143,8 +141,7 @@ impl<'de> Decode<'de> for FileDescriptor {
             None
         };
 
-        let name = decode_string(src.remaining(), CharacterSet::Unicode, true)?;
-        src.advance(NAME_LENGTH);
+        let name = decode_utf16le_lossy_exact(src, NAME_LENGTH)?;
 
         Ok(Self {
             attributes,
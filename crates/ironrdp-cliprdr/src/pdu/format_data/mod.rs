@@ -14,7 +14,10 @@ use ironrdp_core::{
     WriteCursor,
 };
 use ironrdp_pdu::impl_pdu_borrowing;
-use ironrdp_pdu::utils::{read_string_from_cursor, to_utf16_bytes, CharacterSet};
+use ironrdp_pdu::utf16::{
+    decode_utf16le_until_null, encode_utf16le_null_terminated, null_terminated_utf16_encoded_len,
+};
+use ironrdp_pdu::utils::{read_string_from_cursor, CharacterSet};
 
 use super::ClipboardFormatId;
 use crate::pdu::{ClipboardPduFlags, PartialHeader};
@@ -113,9 +116,9 @@ impl<'a> FormatDataResponse<'a> {
 
     /// Creates new format data response from string.
     pub fn new_unicode_string(value: &str) -> Self {
-        let mut encoded = to_utf16_bytes(value);
-        encoded.push(b'\0');
-        encoded.push(b'\0');
+        let mut encoded = vec![0u8; null_terminated_utf16_encoded_len(value)];
+        encode_utf16le_null_terminated(&mut WriteCursor::new(&mut encoded), value)
+            .expect("BUG: buffer is allocated with the encoded size");
 
         Self {
             is_error: false,
@@ -161,7 +164,7 @@ impl<'a> FormatDataResponse<'a> {
     /// Reads inner data as unicode string
     pub fn to_unicode_string(&self) -> DecodeResult<String> {
         let mut cursor = ReadCursor::new(&self.data);
-        read_string_from_cursor(&mut cursor, CharacterSet::Unicode, true)
+        Ok(decode_utf16le_until_null(&mut cursor))
     }
 
     pub fn into_data(self) -> Cow<'a, [u8]> {
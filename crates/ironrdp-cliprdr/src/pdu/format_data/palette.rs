@@ -1,4 +1,4 @@
-use ironrdp_core::{Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
+use ironrdp_core::{ensure_size, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
 use ironrdp_pdu::impl_pdu_pod;
 
 /// Represents `PALETTEENTRY`
@@ -30,6 +30,8 @@ impl ClipboardPalette {
 
 impl Encode for ClipboardPalette {
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
         for entry in &self.entries {
             dst.write_u8(entry.red);
             dst.write_u8(entry.green);
@@ -4,7 +4,11 @@ use ironrdp_core::{
     cast_int, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, IntoOwned, ReadCursor,
     WriteCursor,
 };
-use ironrdp_pdu::utils::{read_string_from_cursor, to_utf16_bytes, write_string_to_cursor, CharacterSet};
+use ironrdp_pdu::utf16::{
+    decode_utf16le_lossy_exact, decode_utf16le_until_null, encode_utf16le_fixed_size, encode_utf16le_null_terminated,
+    null_terminated_utf16_encoded_len, FixedSizeOverflow,
+};
+use ironrdp_pdu::utils::{encoded_str_len, read_string_from_cursor, write_string_to_cursor, CharacterSet};
 use ironrdp_pdu::{decode_err, impl_pdu_borrowing, impl_pdu_pod, PduResult};
 
 use crate::pdu::{ClipboardPduFlags, PartialHeader};
@@ -234,7 +238,8 @@ impl FormatList<'_> {
     const NAME: &'static str = "CLIPRDR_FORMAT_LIST";
 
     // `CLIPRDR_SHORT_FORMAT_NAME` size
-    const SHORT_FORMAT_SIZE: usize = 4 /* formatId */ + 32 /* name */;
+    const SHORT_FORMAT_SIZE: usize = 4 /* formatId */ + Self::SHORT_FORMAT_NAME_SIZE;
+    const SHORT_FORMAT_NAME_SIZE: usize = 32;
 
     fn new_impl(formats: &[ClipboardFormat], use_long_format: bool, use_ascii: bool) -> EncodeResult<Self> {
         let charset = if use_ascii {
@@ -251,29 +256,12 @@ impl FormatList<'_> {
             let mut buffer = vec![0u8; DEFAULT_STRING_BUFFER_SIZE];
 
             for format in formats {
-                let encoded_string = match charset {
-                    CharacterSet::Ansi => {
-                        let mut str_buffer = format
-                            .name
-                            .as_ref()
-                            .map(|name| name.value().as_bytes().to_vec())
-                            .unwrap_or_default();
-                        str_buffer.push(b'\0');
-                        str_buffer
-                    }
-                    CharacterSet::Unicode => {
-                        let mut str_buffer = format
-                            .name
-                            .as_ref()
-                            .map(|name| to_utf16_bytes(name.value()))
-                            .unwrap_or_default();
-                        str_buffer.push(b'\0');
-                        str_buffer.push(b'\0');
-                        str_buffer
-                    }
-                };
+                let name = format.name.as_ref().map(|name| name.value()).unwrap_or_default();
 
-                let required_size = 4 + encoded_string.len();
+                let required_size = 4 + match charset {
+                    CharacterSet::Ansi => encoded_str_len(name, charset, true),
+                    CharacterSet::Unicode => null_terminated_utf16_encoded_len(name),
+                };
                 if buffer.len() - bytes_written < required_size {
                     buffer.resize(bytes_written + required_size, 0);
                 }
@@ -282,7 +270,10 @@ impl FormatList<'_> {
 
                 // Write will never fail, as we pre-allocated space in buffer
                 cursor.write_u32(format.id.value());
-                cursor.write_slice(&encoded_string);
+                match charset {
+                    CharacterSet::Ansi => write_string_to_cursor(&mut cursor, name, charset, true)?,
+                    CharacterSet::Unicode => encode_utf16le_null_terminated(&mut cursor, name)?,
+                }
 
                 bytes_written += required_size;
             }
@@ -296,14 +287,30 @@ impl FormatList<'_> {
         } else {
             let mut buffer = vec![0u8; Self::SHORT_FORMAT_SIZE * formats.len()];
             for (idx, format) in formats.iter().enumerate() {
-                let mut cursor = WriteCursor::new(&mut buffer[idx * Self::SHORT_FORMAT_SIZE..]);
+                let offset = idx * Self::SHORT_FORMAT_SIZE;
+                let mut cursor = WriteCursor::new(&mut buffer[offset..offset + Self::SHORT_FORMAT_SIZE]);
                 cursor.write_u32(format.id.value());
-                write_string_to_cursor(
-                    &mut cursor,
-                    format.name.as_ref().map(|name| name.value()).unwrap_or_default(),
-                    charset,
-                    true,
-                )?;
+
+                // Names which do not fit in the fixed-size field are truncated, as required by [MS-RDPECLIP] 2.2.3.1.1.1.
+                let mut name = format.name.as_ref().map(|name| name.value()).unwrap_or_default();
+
+                match charset {
+                    CharacterSet::Ansi => {
+                        while encoded_str_len(name, charset, true) > Self::SHORT_FORMAT_NAME_SIZE {
+                            let mut chars = name.chars();
+                            chars.next_back();
+                            name = chars.as_str();
+                        }
+
+                        write_string_to_cursor(&mut cursor, name, charset, true)?;
+                    }
+                    CharacterSet::Unicode => encode_utf16le_fixed_size(
+                        &mut cursor,
+                        name,
+                        Self::SHORT_FORMAT_NAME_SIZE,
+                        FixedSizeOverflow::Truncate,
+                    )?,
+                }
             }
 
             Ok(Self {
@@ -337,7 +344,12 @@ impl FormatList<'_> {
 
             while src.len() >= MINIMAL_FORMAT_SIZE {
                 let id = src.read_u32();
-                let name = read_string_from_cursor(&mut src, charset, true).map_err(|e| decode_err!(e))?;
+                let name = match charset {
+                    CharacterSet::Ansi => {
+                        read_string_from_cursor(&mut src, charset, true).map_err(|e| decode_err!(e))?
+                    }
+                    CharacterSet::Unicode => decode_utf16le_until_null(&mut src),
+                };
 
                 let format = ClipboardFormat::new(ClipboardFormatId::new(id)).with_name(ClipboardFormatName::new(name));
 
@@ -352,10 +364,14 @@ impl FormatList<'_> {
 
             for _ in 0..items_count {
                 let id = src.read_u32();
-                let name_buffer = src.read_slice(32);
-
-                let mut name_cursor: ReadCursor<'_> = ReadCursor::new(name_buffer);
-                let name = read_string_from_cursor(&mut name_cursor, charset, true).map_err(|e| decode_err!(e))?;
+                let name = match charset {
+                    CharacterSet::Ansi => {
+                        let mut name_cursor = ReadCursor::new(src.read_slice(Self::SHORT_FORMAT_NAME_SIZE));
+                        read_string_from_cursor(&mut name_cursor, charset, true).map_err(|e| decode_err!(e))?
+                    }
+                    CharacterSet::Unicode => decode_utf16le_lossy_exact(&mut src, Self::SHORT_FORMAT_NAME_SIZE)
+                        .map_err(|e| decode_err!(e))?,
+                };
 
                 let format = ClipboardFormat::new(ClipboardFormatId(id)).with_name(ClipboardFormatName::new(name));
 
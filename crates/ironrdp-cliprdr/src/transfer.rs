@@ -0,0 +1,129 @@
+//! Tracking of the file transfers requested by the remote
+//!
+//! The remote retrieves the files listed in a file list (`FileGroupDescriptorW` format data) with a series of
+//! File Contents Request PDUs, each of them asking for a range of a single file. This module correlates these
+//! requests into per-file transfers, in order to report their progress and to cancel them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::pdu::{FileContentsFlags, FileContentsRequest, FileDescriptor};
+
+/// Progress of a file transfer, identified by the stream ID and the index of the file in the file list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub stream_id: u32,
+    /// Index of the file in the file list (`lindex`).
+    pub index: u32,
+    /// Number of distinct bytes of the file requested so far.
+    pub transferred: u64,
+    /// Size of the file, as announced in the file list.
+    pub size: Option<u64>,
+}
+
+impl TransferProgress {
+    pub fn is_complete(&self) -> bool {
+        self.size.is_some_and(|size| self.transferred >= size)
+    }
+}
+
+/// Set of disjoint, non-adjacent byte ranges, sorted by start offset
+#[derive(Debug, Clone, Default)]
+struct RangeSet {
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeSet {
+    /// Inserts the half-open range `[start, end)`, merging it with the overlapping and adjacent ranges.
+    fn insert(&mut self, start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+
+        // Ranges ending before `start` are kept as is, and so are the ranges starting after `end`.
+        let first = self.ranges.partition_point(|&(_, range_end)| range_end < start);
+        let last = self.ranges.partition_point(|&(range_start, _)| range_start <= end);
+
+        let merged = self.ranges[first..last]
+            .iter()
+            .fold((start, end), |(start, end), &(range_start, range_end)| {
+                (start.min(range_start), end.max(range_end))
+            });
+
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    fn len(&self) -> u64 {
+        self.ranges.iter().map(|(start, end)| end - start).sum()
+    }
+}
+
+/// Tracks the file transfers of the last file list sent to the remote
+#[derive(Debug, Clone, Default)]
+pub struct FileTransfers {
+    /// Sizes of the files, indexed by their position in the file list.
+    file_sizes: Vec<Option<u64>>,
+    transfers: BTreeMap<(u32, u32), RangeSet>,
+    cancelled: BTreeSet<u32>,
+}
+
+impl FileTransfers {
+    /// Starts tracking the transfers of a new file list, forgetting about the previous ones.
+    pub fn set_file_list(&mut self, files: &[FileDescriptor]) {
+        self.file_sizes = files.iter().map(|file| file.file_size).collect();
+        self.transfers.clear();
+        self.cancelled.clear();
+    }
+
+    /// Records the range requested by `request`, and returns the updated progress of the transfer.
+    ///
+    /// Returns `None` for size requests, and for requests of cancelled transfers.
+    pub fn on_request(&mut self, request: &FileContentsRequest) -> Option<TransferProgress> {
+        if !request.flags.contains(FileContentsFlags::DATA) || self.is_cancelled(request.stream_id) {
+            return None;
+        }
+
+        let size = self.file_size(request.index);
+
+        let start = request.position;
+        let mut end = start.saturating_add(u64::from(request.requested_size));
+        if let Some(size) = size {
+            // The remote may ask for more than what is left, the response being truncated at the end of the file.
+            end = end.min(size);
+        }
+
+        let ranges = self.transfers.entry((request.stream_id, request.index)).or_default();
+        ranges.insert(start, end);
+
+        Some(TransferProgress {
+            stream_id: request.stream_id,
+            index: request.index,
+            transferred: ranges.len(),
+            size,
+        })
+    }
+
+    /// Returns the progress of the transfer of the file at `index` on the stream `stream_id`, if it started.
+    pub fn progress(&self, stream_id: u32, index: u32) -> Option<TransferProgress> {
+        self.transfers.get(&(stream_id, index)).map(|ranges| TransferProgress {
+            stream_id,
+            index,
+            transferred: ranges.len(),
+            size: self.file_size(index),
+        })
+    }
+
+    /// Cancels the transfers on the stream `stream_id`.
+    pub fn cancel(&mut self, stream_id: u32) {
+        self.transfers.retain(|&(id, _), _| id != stream_id);
+        self.cancelled.insert(stream_id);
+    }
+
+    pub fn is_cancelled(&self, stream_id: u32) -> bool {
+        self.cancelled.contains(&stream_id)
+    }
+
+    fn file_size(&self, index: u32) -> Option<u64> {
+        let index = usize::try_from(index).ok()?;
+        self.file_sizes.get(index).copied().flatten()
+    }
+}
@@ -1,8 +1,10 @@
 use core::mem;
-use ironrdp_core::{decode, encode_vec, Encode, WriteBuf};
+use ironrdp_core::{decode, encode_vec, Encode, ReadCursor, WriteBuf};
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, InputFlags};
 use ironrdp_pdu::rdp::client_info::{OptionalSystemTime, TimezoneInfo};
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
 use ironrdp_pdu::x224::X224;
-use ironrdp_pdu::{gcc, mcs, nego, rdp, PduHint};
+use ironrdp_pdu::{gcc, mcs, nego, rdp, DecodeOptions, DecodeWarning, PduHint};
 use ironrdp_svc::{StaticChannelSet, StaticVirtualChannel, SvcClientProcessor};
 use std::borrow::Cow;
 use std::net::SocketAddr;
@@ -11,21 +13,47 @@ use std::sync::Arc;
 use crate::channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
 use crate::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
 use crate::license_exchange::{LicenseExchangeSequence, NoopLicenseCache};
+use crate::redirection::ServerRedirection;
 use crate::{
-    encode_x224_packet, Config, ConnectorError, ConnectorErrorExt as _, ConnectorResult, DesktopSize, Sequence, State,
-    Written,
+    encode_x224_packet, Config, ConnectorError, ConnectorErrorExt as _, ConnectorErrorKind, ConnectorResult,
+    DesktopSize, RedirectSession, Sequence, State, Written,
 };
 
+const CREDENTIAL_DELEGATION_UNSUPPORTED: &str =
+    "the server does not support the requested credential delegation (enable it on the server, or use full delegation)";
+
+const STANDARD_RDP_SECURITY_UNSUPPORTED: &str =
+    "the server requires the standard RDP security, which is not supported (enable TLS or NLA on the server)";
+
 #[derive(Debug)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConnectionResult {
     pub io_channel_id: u16,
     pub user_channel_id: u16,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub static_channels: StaticChannelSet,
     pub desktop_size: DesktopSize,
+    /// Color depth granted by the server in the Bitmap Capability Set, in bits per pixel.
+    ///
+    /// Connecting fails if the client is not able to render it.
+    pub negotiated_color_depth: u16,
     pub no_server_pointer: bool,
     pub pointer_software_rendering: bool,
+    /// Number of slots of the pointer cache, as advertised in the Pointer Capability Set.
+    pub pointer_cache_size: u16,
+    /// Revision 2 bitmap cache advertised in the capabilities, if any.
+    pub bitmap_cache: Option<BitmapCacheRev2>,
+    /// Input flags advertised by the server in the Input Capability Set.
+    pub server_input_flags: InputFlags,
+    /// Whether the completed frames must be acknowledged, frame markers being negotiated with the server.
+    pub frame_acknowledge: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub connection_activation: ConnectionActivationSequence,
+    /// Deviations from the specification tolerated while connecting, see [`ClientConnector::with_decode_options`].
+    pub decode_warnings: Vec<DecodeWarning>,
+    /// Security settings sent by the server in the Server Security Data GCC block.
+    pub server_security: gcc::ServerSecurityData,
 }
 
 #[derive(Default, Debug)]
@@ -81,6 +109,10 @@ pub enum ClientConnectorState {
     Connected {
         result: ConnectionResult,
     },
+    /// The server redirected the client to another server, see [`ClientConnector::redirect`].
+    Redirected {
+        redirection: ServerRedirection,
+    },
 }
 
 impl State for ClientConnectorState {
@@ -105,11 +137,12 @@ impl State for ClientConnectorState {
                 connection_activation, ..
             } => connection_activation.state().name(),
             Self::Connected { .. } => "Connected",
+            Self::Redirected { .. } => "Redirected",
         }
     }
 
     fn is_terminal(&self) -> bool {
-        matches!(self, Self::Connected { .. })
+        matches!(self, Self::Connected { .. } | Self::Redirected { .. })
     }
 
     fn as_any(&self) -> &dyn core::any::Any {
@@ -124,6 +157,9 @@ pub struct ClientConnector {
     pub state: ClientConnectorState,
     pub server_addr: Option<SocketAddr>,
     pub static_channels: StaticChannelSet,
+    pub decode_options: DecodeOptions,
+    decode_warnings: Vec<DecodeWarning>,
+    server_security: Option<gcc::ServerSecurityData>,
 }
 
 impl ClientConnector {
@@ -133,9 +169,27 @@ impl ClientConnector {
             state: ClientConnectorState::ConnectionInitiationSendRequest,
             server_addr: None,
             static_channels: StaticChannelSet::new(),
+            decode_options: DecodeOptions::STRICT,
+            decode_warnings: Vec::new(),
+            server_security: None,
         }
     }
 
+    /// Returns the security settings sent by the server, once the Basic Settings Exchange is done.
+    pub fn server_security(&self) -> Option<&gcc::ServerSecurityData> {
+        self.server_security.as_ref()
+    }
+
+    /// Sets the strictness of the decoding of the GCC blocks and the server capability sets.
+    ///
+    /// In lenient mode, deviations from the specification which can be safely ignored are logged and reported in
+    /// [`ConnectionResult::decode_warnings`] instead of failing the connection.
+    #[must_use]
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
     /// Must be set to the actual target server address (as opposed to the proxy)
     #[must_use]
     pub fn with_server_addr(mut self, addr: SocketAddr) -> Self {
@@ -178,6 +232,20 @@ impl ClientConnector {
         matches!(self.state, ClientConnectorState::Credssp { .. })
     }
 
+    /// Prepares the connector to connect to the target of a server redirection.
+    ///
+    /// The routing token and the credentials sent by the server replace the configured ones, and the connection
+    /// sequence starts over. The caller is responsible for opening a new connection to [`ServerRedirection::target`],
+    /// and for attaching the new server address.
+    pub fn redirect(&mut self, redirection: &ServerRedirection) {
+        redirection.apply_to(&mut self.config);
+
+        self.state = ClientConnectorState::ConnectionInitiationSendRequest;
+        self.server_addr = None;
+        self.decode_warnings.clear();
+        self.server_security = None;
+    }
+
     pub fn mark_credssp_as_done(&mut self) {
         assert!(self.should_perform_credssp());
         let res = self.step(&[], &mut WriteBuf::new()).expect("transition to next state");
@@ -208,6 +276,7 @@ impl Sequence for ClientConnector {
                 connection_activation, ..
             } => connection_activation.next_pdu_hint(),
             ClientConnectorState::Connected { .. } => None,
+            ClientConnectorState::Redirected { .. } => None,
         }
     }
 
@@ -250,6 +319,18 @@ impl Sequence for ClientConnector {
                     return Err(reason_err!("Initiation", "standard RDP security is not supported",));
                 }
 
+                let correlation_info = self
+                    .config
+                    .correlation_id
+                    .map(|correlation_id| nego::CorrelationInfo { correlation_id });
+
+                if correlation_info.is_some_and(|info| !nego::CorrelationInfo::is_valid_id(&info.correlation_id)) {
+                    return Err(reason_err!(
+                        "Initiation",
+                        "invalid correlation ID: the first byte must be neither 0x00 nor 0xF4, and no byte may be 0x0D",
+                    ));
+                }
+
                 let connection_request = nego::ConnectionRequest {
                     nego_data: self.config.request_data.clone().or_else(|| {
                         self.config
@@ -257,8 +338,9 @@ impl Sequence for ClientConnector {
                             .username()
                             .map(|username| nego::NegoRequestData::cookie(username.to_owned()))
                     }),
-                    flags: nego::RequestFlags::empty(),
+                    flags: self.config.credential_delegation.request_flags(),
                     protocol: security_protocol,
+                    correlation_info,
                 };
 
                 debug!(message = ?connection_request, "Send");
@@ -284,12 +366,19 @@ impl Sequence for ClientConnector {
                     nego::ConnectionConfirm::Response { flags, protocol } => (flags, protocol),
                     nego::ConnectionConfirm::Failure { code } => {
                         error!(?code, "Received connection failure code");
-                        return Err(reason_err!("Initiation", "{code}"));
+                        return Err(ConnectorError::new(
+                            "Initiation",
+                            ConnectorErrorKind::NegotiationFailure(code.into()),
+                        ));
                     }
                 };
 
                 info!(?selected_protocol, ?flags, "Server confirmed connection");
 
+                if selected_protocol.is_standard_rdp_security() {
+                    return Err(reason_err!("Initiation", "{STANDARD_RDP_SECURITY_UNSUPPORTED}"));
+                }
+
                 if !selected_protocol.intersects(requested_protocol) {
                     return Err(reason_err!(
                         "Initiation",
@@ -297,6 +386,22 @@ impl Sequence for ClientConnector {
                     ));
                 }
 
+                let credential_delegation = self.config.credential_delegation;
+
+                // The restricted modes rely on the CredSSP authentication, the server also denies them without NLA.
+                if !credential_delegation.is_supported_by(flags)
+                    || (!credential_delegation.delegates_password()
+                        && !selected_protocol
+                            .intersects(nego::SecurityProtocol::HYBRID | nego::SecurityProtocol::HYBRID_EX))
+                {
+                    return Err(ConnectorError::new(
+                        CREDENTIAL_DELEGATION_UNSUPPORTED,
+                        ConnectorErrorKind::UnsupportedCredentialDelegation {
+                            mode: credential_delegation,
+                        },
+                    ));
+                }
+
                 (
                     Written::Nothing,
                     ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol },
@@ -349,8 +454,18 @@ impl Sequence for ClientConnector {
                 let x224_payload = decode::<X224<crate::x224::X224Data<'_>>>(input)
                     .map_err(ConnectorError::decode)
                     .map(|p| p.0)?;
-                let connect_response =
-                    decode::<mcs::ConnectResponse>(x224_payload.data.as_ref()).map_err(ConnectorError::decode)?;
+                let mut warnings = Vec::new();
+                let connect_response = mcs::ConnectResponse::decode_with_options(
+                    &mut ReadCursor::new(x224_payload.data.as_ref()),
+                    self.decode_options,
+                    &mut warnings,
+                )
+                .map_err(ConnectorError::decode)?;
+
+                for warning in &warnings {
+                    warn!(%warning, "Tolerated non-conformant GCC conference create response");
+                }
+                self.decode_warnings.extend(warnings);
 
                 debug!(message = ?connect_response, "Received");
 
@@ -358,18 +473,48 @@ impl Sequence for ClientConnector {
 
                 let server_gcc_blocks = connect_response.conference_create_response.gcc_blocks;
 
-                if client_gcc_blocks.security == gcc::ClientSecurityData::no_security()
-                    && server_gcc_blocks.security != gcc::ServerSecurityData::no_security()
-                {
-                    return Err(general_err!("can’t satisfy server security settings"));
+                let server_security = &server_gcc_blocks.security;
+
+                debug!(
+                    encryption_method = ?server_security.encryption_method,
+                    encryption_level = ?server_security.encryption_level,
+                    has_server_certificate = server_security.has_server_certificate(),
+                    "Server security settings"
+                );
+
+                let selected_protocol = client_gcc_blocks
+                    .core
+                    .optional_data
+                    .server_selected_protocol
+                    .unwrap_or_else(nego::SecurityProtocol::empty);
+
+                if server_security.is_encrypted() {
+                    if selected_protocol.is_standard_rdp_security() {
+                        return Err(reason_err!(
+                            "Basic Settings Exchange",
+                            "{STANDARD_RDP_SECURITY_UNSUPPORTED}"
+                        ));
+                    }
+
+                    if client_gcc_blocks.security == gcc::ClientSecurityData::no_security() {
+                        return Err(reason_err!(
+                            "Basic Settings Exchange",
+                            "can’t satisfy server security settings (encryption method: {:?}, encryption level: {:?})",
+                            server_security.encryption_method,
+                            server_security.encryption_level,
+                        ));
+                    }
                 }
 
+                self.server_security = Some(server_gcc_blocks.security.clone());
+
                 if server_gcc_blocks.message_channel.is_some() {
                     warn!("Unexpected ServerMessageChannelData GCC block (not supported)");
                 }
 
-                if server_gcc_blocks.multi_transport_channel.is_some() {
-                    warn!("Unexpected MultiTransportChannelData GCC block (not supported)");
+                if let Some(multi_transport_channel) = &server_gcc_blocks.multi_transport_channel {
+                    // The side channels are declined, see `Config::decline_multitransport`.
+                    debug!(flags = ?multi_transport_channel.flags, "Server multitransport channel data");
                 }
 
                 let static_channel_ids = server_gcc_blocks.network.channel_ids;
@@ -500,6 +645,16 @@ impl Sequence for ClientConnector {
             } => {
                 debug!("Licensing Exchange");
 
+                // With the standard RDP security, the server may redirect the client instead of sending a licensing
+                // PDU.
+                if let Some(redirection) = decode_standard_security_redirection(input)? {
+                    let redirection = ServerRedirection::from(redirection);
+                    info!(?redirection.target, "Server redirection");
+
+                    self.state = ClientConnectorState::Redirected { redirection };
+                    return Ok(Written::Nothing);
+                }
+
                 let written = license_exchange.step(input, output)?;
 
                 let next_state = if license_exchange.state.is_terminal() {
@@ -530,7 +685,8 @@ impl Sequence for ClientConnector {
                         self.config.clone(),
                         io_channel_id,
                         user_channel_id,
-                    ),
+                    )
+                    .with_decode_options(self.decode_options),
                 },
             ),
 
@@ -545,6 +701,14 @@ impl Sequence for ClientConnector {
                         written,
                         ClientConnectorState::ConnectionFinalization { connection_activation },
                     ),
+                    ConnectionActivationState::Redirected { redirection } => {
+                        (written, ClientConnectorState::Redirected { redirection })
+                    }
+                    // An Initiate Multitransport Request PDU was received before the Server Demand Active PDU.
+                    ConnectionActivationState::CapabilitiesExchange { .. } => (
+                        written,
+                        ClientConnectorState::CapabilitiesExchange { connection_activation },
+                    ),
                     _ => return Err(general_err!("invalid state (this is a bug)")),
                 }
             }
@@ -565,19 +729,40 @@ impl Sequence for ClientConnector {
                             io_channel_id,
                             user_channel_id,
                             desktop_size,
+                            color_depth,
                             no_server_pointer,
                             pointer_software_rendering,
-                        } => ClientConnectorState::Connected {
-                            result: ConnectionResult {
-                                io_channel_id,
-                                user_channel_id,
-                                static_channels: mem::take(&mut self.static_channels),
-                                desktop_size,
-                                no_server_pointer,
-                                pointer_software_rendering,
-                                connection_activation,
-                            },
-                        },
+                            pointer_cache_size,
+                            ref bitmap_cache,
+                            server_input_flags,
+                            frame_acknowledge,
+                        } => {
+                            let bitmap_cache = bitmap_cache.clone();
+                            let mut decode_warnings = mem::take(&mut self.decode_warnings);
+                            decode_warnings.extend(connection_activation.take_decode_warnings());
+
+                            ClientConnectorState::Connected {
+                                result: ConnectionResult {
+                                    io_channel_id,
+                                    user_channel_id,
+                                    static_channels: mem::take(&mut self.static_channels),
+                                    desktop_size,
+                                    negotiated_color_depth: color_depth,
+                                    no_server_pointer,
+                                    pointer_software_rendering,
+                                    pointer_cache_size,
+                                    bitmap_cache,
+                                    server_input_flags,
+                                    frame_acknowledge,
+                                    connection_activation,
+                                    decode_warnings,
+                                    server_security: self
+                                        .server_security
+                                        .take()
+                                        .unwrap_or_else(gcc::ServerSecurityData::no_security),
+                                },
+                            }
+                        }
                         _ => return Err(general_err!("invalid state (this is a bug)")),
                     }
                 };
@@ -588,6 +773,11 @@ impl Sequence for ClientConnector {
             //== Connected ==//
             // The client connector job is done.
             ClientConnectorState::Connected { .. } => return Err(general_err!("already connected")),
+            ClientConnectorState::Redirected { .. } => {
+                return Err(general_err!(
+                    "redirected to another server, the connection must be restarted"
+                ))
+            }
         };
 
         self.state = next_state;
@@ -661,7 +851,8 @@ fn create_gcc_blocks<'a>(
                     let mut early_capability_flags = ClientEarlyCapabilityFlags::VALID_CONNECTION_TYPE
                         | ClientEarlyCapabilityFlags::SUPPORT_ERR_INFO_PDU
                         | ClientEarlyCapabilityFlags::STRONG_ASYMMETRIC_KEYS
-                        | ClientEarlyCapabilityFlags::SUPPORT_SKIP_CHANNELJOIN;
+                        | ClientEarlyCapabilityFlags::SUPPORT_SKIP_CHANNELJOIN
+                        | ClientEarlyCapabilityFlags::SUPPORT_HEART_BEAT_PDU;
 
                     // TODO(#136): support for ClientEarlyCapabilityFlags::SUPPORT_STATUS_INFO_PDU
 
@@ -698,15 +889,40 @@ fn create_gcc_blocks<'a>(
         } else {
             Some(ClientNetworkData { channels })
         },
-        // TODO(#139): support for Some(ClientClusterData { flags: RedirectionFlags::REDIRECTION_SUPPORTED, redirection_version: RedirectionVersion::V4, redirected_session_id: 0, }),
-        cluster: None,
+        cluster: Some(ClientClusterData {
+            flags: match config.redirect_session {
+                Some(_) => RedirectionFlags::REDIRECTION_SUPPORTED | RedirectionFlags::REDIRECTED_SESSION_FIELD_VALID,
+                None => RedirectionFlags::REDIRECTION_SUPPORTED,
+            },
+            redirection_version: RedirectionVersion::V4,
+            redirected_session_id: config.redirect_session.map_or(0, RedirectSession::session_id),
+        }),
         monitor: None,
         // TODO(#140): support for Client Message Channel Data (https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/f50e791c-de03-4b25-b17e-e914c9020bc3)
         message_channel: None,
-        // TODO(#140): support for Some(MultiTransportChannelData { flags: MultiTransportFlags::empty(), })
-        multi_transport_channel: None,
+        // No transport is advertised, but the server may still request a side channel, which is declined.
+        multi_transport_channel: Some(MultiTransportChannelData {
+            flags: MultiTransportFlags::empty(),
+        }),
         monitor_extended: None,
+        unknown: Vec::new(),
+    }
+}
+
+/// Decodes the Server Redirection PDU sent in place of a licensing PDU, whose flags overlap the security header.
+fn decode_standard_security_redirection(input: &[u8]) -> ConnectorResult<Option<ServerRedirectionPdu>> {
+    let send_data_indication_ctx = crate::legacy::decode_send_data_indication(input)?;
+
+    let Some(flags) = send_data_indication_ctx.user_data.get(..2) else {
+        return Ok(None);
+    };
+    let flags = rdp::headers::BasicSecurityHeaderFlags::from_bits_truncate(u16::from_le_bytes([flags[0], flags[1]]));
+
+    if !flags.contains(rdp::headers::BasicSecurityHeaderFlags::REDIRECTION_PKT) {
+        return Ok(None);
     }
+
+    send_data_indication_ctx.decode_user_data().map(Some)
 }
 
 fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::ClientInfoPdu {
@@ -741,13 +957,45 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
         flags |= ClientInfoFlags::PASSWORD_IS_SC_PIN;
     }
 
+    if config.remote_app.is_some() {
+        flags |= ClientInfoFlags::RAIL;
+    }
+
+    let optional_data = ExtendedClientOptionalInfo::builder()
+        .timezone(TimezoneInfo {
+            bias: 0,
+            standard_name: String::new(),
+            standard_date: OptionalSystemTime(None),
+            standard_bias: 0,
+            daylight_name: String::new(),
+            daylight_date: OptionalSystemTime(None),
+            daylight_bias: 0,
+        })
+        .session_id(0)
+        .performance_flags(config.performance_flags);
+
+    let optional_data = match &config.auto_reconnect_cookie {
+        // Only Enhanced RDP Security is supported, for which the client random is zero-filled.
+        Some(auto_reconnect) => optional_data
+            .reconnect_cookie(auto_reconnect.client_cookie(&[0; 32]))
+            .build(),
+        None => optional_data.build(),
+    };
+
     let client_info = ClientInfo {
         credentials: Credentials {
             username: config.credentials.username().unwrap_or("").to_owned(),
-            password: config.credentials.secret().to_owned(),
+            // The server logs the user on with the identity of the CredSSP authentication in the restricted modes.
+            password: if config.credential_delegation.delegates_password() {
+                config.credentials.secret().to_owned()
+            } else {
+                String::new()
+            },
             domain: config.domain.clone(),
         },
-        code_page: 0, // ignored if the keyboardLayout field of the Client Core Data is set to zero
+        // With INFO_UNICODE, this is the active language identifier, ignored if the keyboardLayout field of the
+        // Client Core Data is set to zero.
+        code_page: config.active_input_locale.unwrap_or(0),
         flags,
         compression_type: CompressionType::K8, // ignored if ClientInfoFlags::COMPRESSION is not set
         alternate_shell: String::new(),
@@ -759,19 +1007,7 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
             },
             address: routing_addr.ip().to_string(),
             dir: config.client_dir.clone(),
-            optional_data: ExtendedClientOptionalInfo::builder()
-                .timezone(TimezoneInfo {
-                    bias: 0,
-                    standard_name: String::new(),
-                    standard_date: OptionalSystemTime(None),
-                    standard_bias: 0,
-                    daylight_name: String::new(),
-                    daylight_date: OptionalSystemTime(None),
-                    daylight_bias: 0,
-                })
-                .session_id(0)
-                .performance_flags(config.performance_flags)
-                .build(),
+            optional_data,
         },
     };
 
@@ -1,8 +1,11 @@
 use core::mem;
 
-use ironrdp_pdu::rdp::capability_sets::CapabilitySet;
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, CapabilitySet, InputFlags};
+use ironrdp_pdu::rdp::multitransport::{MultitransportRequestPdu, MultitransportResponsePdu};
 use ironrdp_pdu::rdp::{self};
+use ironrdp_pdu::{DecodeOptions, DecodeWarning};
 
+use crate::redirection::ServerRedirection;
 use crate::{legacy, Config, ConnectionFinalizationSequence, ConnectorResult, DesktopSize, Sequence, State, Written};
 
 /// Represents the Capability Exchange and Connection Finalization phases
@@ -21,6 +24,8 @@ use crate::{legacy, Config, ConnectionFinalizationSequence, ConnectorResult, Des
 pub struct ConnectionActivationSequence {
     pub state: ConnectionActivationState,
     config: Config,
+    decode_options: DecodeOptions,
+    decode_warnings: Vec<DecodeWarning>,
 }
 
 impl ConnectionActivationSequence {
@@ -31,15 +36,36 @@ impl ConnectionActivationSequence {
                 user_channel_id,
             },
             config,
+            decode_options: DecodeOptions::STRICT,
+            decode_warnings: Vec::new(),
         }
     }
 
+    /// Sets the strictness of the decoding of the server capability sets.
+    #[must_use]
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
+    /// Returns the configuration the sequence was created with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the deviations from the specification tolerated while decoding the server capability sets.
+    pub fn take_decode_warnings(&mut self) -> Vec<DecodeWarning> {
+        mem::take(&mut self.decode_warnings)
+    }
+
     #[must_use]
     pub fn reset_clone(&self) -> Self {
         self.clone().reset()
     }
 
     fn reset(mut self) -> Self {
+        self.decode_warnings.clear();
+
         match &self.state {
             ConnectionActivationState::CapabilitiesExchange {
                 io_channel_id,
@@ -62,7 +88,7 @@ impl ConnectionActivationSequence {
 
                 self
             }
-            ConnectionActivationState::Consumed => self,
+            ConnectionActivationState::Consumed | ConnectionActivationState::Redirected { .. } => self,
         }
     }
 }
@@ -72,6 +98,7 @@ impl Sequence for ConnectionActivationSequence {
         match &self.state {
             ConnectionActivationState::Consumed => None,
             ConnectionActivationState::Finalized { .. } => None,
+            ConnectionActivationState::Redirected { .. } => None,
             ConnectionActivationState::CapabilitiesExchange { .. } => Some(&ironrdp_pdu::X224_HINT),
             ConnectionActivationState::ConnectionFinalization {
                 connection_finalization,
@@ -86,7 +113,9 @@ impl Sequence for ConnectionActivationSequence {
 
     fn step(&mut self, input: &[u8], output: &mut ironrdp_core::WriteBuf) -> ConnectorResult<Written> {
         let (written, next_state) = match mem::take(&mut self.state) {
-            ConnectionActivationState::Consumed | ConnectionActivationState::Finalized { .. } => {
+            ConnectionActivationState::Consumed
+            | ConnectionActivationState::Finalized { .. }
+            | ConnectionActivationState::Redirected { .. } => {
                 return Err(general_err!(
                     "connector sequence state is finalized or consumed (this is a bug)"
                 ));
@@ -98,7 +127,33 @@ impl Sequence for ConnectionActivationSequence {
                 debug!("Capabilities Exchange");
 
                 let send_data_indication_ctx = legacy::decode_send_data_indication(input)?;
-                let share_control_ctx = legacy::decode_share_control(send_data_indication_ctx)?;
+
+                // The Initiate Multitransport Requests are sent before the Server Demand Active PDU.
+                if MultitransportRequestPdu::is_multitransport_request(send_data_indication_ctx.user_data) {
+                    let request = send_data_indication_ctx.decode_user_data::<MultitransportRequestPdu>()?;
+                    debug!(message = ?request, "Received");
+
+                    self.state = ConnectionActivationState::CapabilitiesExchange {
+                        io_channel_id,
+                        user_channel_id,
+                    };
+
+                    if !self.config.decline_multitransport {
+                        return Ok(Written::Nothing);
+                    }
+
+                    let response = MultitransportResponsePdu::abort(request.request_id);
+                    debug!(message = ?response, "Send");
+
+                    let written = legacy::encode_send_data_request(user_channel_id, io_channel_id, &response, output)?;
+                    return Written::from_size(written);
+                }
+
+                let share_control_ctx = legacy::decode_share_control_with_options(
+                    send_data_indication_ctx,
+                    self.decode_options,
+                    &mut self.decode_warnings,
+                )?;
 
                 debug!(message = ?share_control_ctx.pdu, "Received");
 
@@ -109,14 +164,24 @@ impl Sequence for ConnectionActivationSequence {
                     );
                 }
 
-                let capability_sets = if let rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) =
-                    share_control_ctx.pdu
-                {
-                    server_demand_active.pdu.capability_sets
-                } else {
-                    return Err(general_err!(
-                        "unexpected Share Control Pdu (expected ServerDemandActive)",
-                    ));
+                let capability_sets = match share_control_ctx.pdu {
+                    rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) => {
+                        server_demand_active.pdu.capability_sets
+                    }
+                    // With the enhanced RDP security, the server may redirect the client instead of activating the
+                    // connection.
+                    rdp::headers::ShareControlPdu::ServerRedirect(redirection) => {
+                        let redirection = ServerRedirection::from(redirection);
+                        info!(?redirection.target, "Server redirection");
+
+                        self.state = ConnectionActivationState::Redirected { redirection };
+                        return Ok(Written::Nothing);
+                    }
+                    _ => {
+                        return Err(general_err!(
+                            "unexpected Share Control Pdu (expected ServerDemandActive)",
+                        ));
+                    }
                 };
 
                 for c in &capability_sets {
@@ -151,8 +216,53 @@ impl Sequence for ConnectionActivationSequence {
                         height: self.config.desktop_size.height,
                     });
 
+                // The server grants a color depth lower or equal to the one requested in the Client Core Data. A depth
+                // which can't be rendered is refused now, rather than when the first bitmap is received.
+                let color_depth = match capability_sets.iter().find_map(|c| match c {
+                    CapabilitySet::Bitmap(b) => Some(b.color_depth()),
+                    _ => None,
+                }) {
+                    Some(Some(color_depth @ (15 | 16 | 24 | 32))) => color_depth,
+                    Some(Some(color_depth)) => {
+                        return Err(reason_err!(
+                            "CapabilitiesExchange",
+                            "server granted an unsupported color depth: {color_depth} bpp",
+                        ));
+                    }
+                    Some(None) => {
+                        return Err(reason_err!(
+                            "CapabilitiesExchange",
+                            "server granted an invalid color depth"
+                        ));
+                    }
+                    // The Bitmap Capability Set is mandatory, assume the requested color depth is granted otherwise.
+                    None => self
+                        .config
+                        .bitmap
+                        .as_ref()
+                        .and_then(|bitmap| u16::try_from(bitmap.color_depth).ok())
+                        .unwrap_or(32),
+                };
+
+                // Servers without fast-path input support can still be sent input events using the slow-path Input
+                // Event PDU, which every server supports.
+                let server_input_flags = capability_sets
+                    .iter()
+                    .find_map(|c| match c {
+                        CapabilitySet::Input(input) => Some(input.input_flags),
+                        _ => None,
+                    })
+                    .unwrap_or_else(InputFlags::empty);
+
+                // The server requests the completed frames to be acknowledged by advertising the Frame Acknowledge
+                // Capability Set, provided the client advertised it as well.
+                let frame_acknowledge = self.config.frame_markers
+                    && capability_sets
+                        .iter()
+                        .any(|c| matches!(c, CapabilitySet::FrameAcknowledge(_)));
+
                 let client_confirm_active = rdp::headers::ShareControlPdu::ClientConfirmActive(
-                    create_client_confirm_active(&self.config, capability_sets, desktop_size),
+                    create_client_confirm_active(&self.config, capability_sets, desktop_size, color_depth)?,
                 );
 
                 debug!(message = ?client_confirm_active, "Send");
@@ -171,6 +281,9 @@ impl Sequence for ConnectionActivationSequence {
                         io_channel_id,
                         user_channel_id,
                         desktop_size,
+                        color_depth,
+                        server_input_flags,
+                        frame_acknowledge,
                         connection_finalization: ConnectionFinalizationSequence::new(io_channel_id, user_channel_id),
                     },
                 )
@@ -179,6 +292,9 @@ impl Sequence for ConnectionActivationSequence {
                 io_channel_id,
                 user_channel_id,
                 desktop_size,
+                color_depth,
+                server_input_flags,
+                frame_acknowledge,
                 mut connection_finalization,
             } => {
                 debug!("Connection Finalization");
@@ -190,6 +306,9 @@ impl Sequence for ConnectionActivationSequence {
                         io_channel_id,
                         user_channel_id,
                         desktop_size,
+                        color_depth,
+                        server_input_flags,
+                        frame_acknowledge,
                         connection_finalization,
                     }
                 } else {
@@ -197,8 +316,13 @@ impl Sequence for ConnectionActivationSequence {
                         io_channel_id,
                         user_channel_id,
                         desktop_size,
+                        color_depth,
                         no_server_pointer: self.config.no_server_pointer,
                         pointer_software_rendering: self.config.pointer_software_rendering,
+                        pointer_cache_size: DEFAULT_POINTER_CACHE_SIZE,
+                        bitmap_cache: self.config.bitmap_cache.clone(),
+                        server_input_flags,
+                        frame_acknowledge,
                     }
                 };
 
@@ -224,14 +348,31 @@ pub enum ConnectionActivationState {
         io_channel_id: u16,
         user_channel_id: u16,
         desktop_size: DesktopSize,
+        color_depth: u16,
+        server_input_flags: InputFlags,
+        frame_acknowledge: bool,
         connection_finalization: ConnectionFinalizationSequence,
     },
     Finalized {
         io_channel_id: u16,
         user_channel_id: u16,
         desktop_size: DesktopSize,
+        /// Color depth granted by the server in the Bitmap Capability Set, in bits per pixel.
+        color_depth: u16,
         no_server_pointer: bool,
         pointer_software_rendering: bool,
+        /// Number of slots of the pointer cache, as advertised in the Pointer Capability Set.
+        pointer_cache_size: u16,
+        /// Revision 2 bitmap cache advertised in the capabilities, if any.
+        bitmap_cache: Option<BitmapCacheRev2>,
+        /// Input flags advertised by the server in the Input Capability Set.
+        server_input_flags: InputFlags,
+        /// Whether the completed frames must be acknowledged with a Frame Acknowledge PDU.
+        frame_acknowledge: bool,
+    },
+    /// The server sent a Server Redirection PDU instead of the Demand Active PDU.
+    Redirected {
+        redirection: ServerRedirection,
     },
 }
 
@@ -242,11 +383,15 @@ impl State for ConnectionActivationState {
             ConnectionActivationState::CapabilitiesExchange { .. } => "CapabilitiesExchange",
             ConnectionActivationState::ConnectionFinalization { .. } => "ConnectionFinalization",
             ConnectionActivationState::Finalized { .. } => "Finalized",
+            ConnectionActivationState::Redirected { .. } => "Redirected",
         }
     }
 
     fn is_terminal(&self) -> bool {
-        matches!(self, ConnectionActivationState::Finalized { .. })
+        matches!(
+            self,
+            ConnectionActivationState::Finalized { .. } | ConnectionActivationState::Redirected { .. }
+        )
     }
 
     fn as_any(&self) -> &dyn core::any::Any {
@@ -254,108 +399,69 @@ impl State for ConnectionActivationState {
     }
 }
 
-const DEFAULT_POINTER_CACHE_SIZE: u16 = 32;
+const DEFAULT_POINTER_CACHE_SIZE: u16 = rdp::capability_sets::ClientCapabilitiesBuilder::DEFAULT_POINTER_CACHE_SIZE;
+
+/// TS_RAIL_LEVEL_SUPPORTED | TS_RAIL_LEVEL_HANDSHAKE_EX_SUPPORTED
+const RAIL_SUPPORT_LEVEL: u32 = 0x0000_0081;
+
+/// WINDOW_LEVEL_SUPPORTED
+const WINDOW_LEVEL_SUPPORTED: u32 = 0x0000_0001;
+
+const NUM_ICON_CACHES: u8 = 3;
+
+const NUM_ICON_CACHE_ENTRIES: u16 = 12;
 
 fn create_client_confirm_active(
     config: &Config,
-    mut server_capability_sets: Vec<CapabilitySet>,
+    server_capability_sets: Vec<CapabilitySet>,
     desktop_size: DesktopSize,
-) -> rdp::capability_sets::ClientConfirmActive {
+    color_depth: u16,
+) -> ConnectorResult<rdp::capability_sets::ClientConfirmActive> {
     use ironrdp_pdu::rdp::capability_sets::*;
 
-    server_capability_sets.retain(|capability_set| matches!(capability_set, CapabilitySet::MultiFragmentUpdate(_)));
-
     let lossy_bitmap_compression = config
         .bitmap
         .as_ref()
         .map(|bitmap| bitmap.lossy_compression)
         .unwrap_or(false);
 
-    let drawing_flags = if lossy_bitmap_compression {
-        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
-            | BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY
-            | BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING
+    let surface_commands_flags = if config.frame_markers {
+        CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER
     } else {
-        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+        CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS
     };
 
-    server_capability_sets.extend_from_slice(&[
-        CapabilitySet::General(General {
-            major_platform_type: config.platform,
-            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
-            ..Default::default()
-        }),
-        CapabilitySet::Bitmap(Bitmap {
-            pref_bits_per_pix: 32,
-            desktop_width: desktop_size.width,
-            desktop_height: desktop_size.height,
-            // This is required to be true in order for the Microsoft::Windows::RDS::DisplayControl DVC to work.
-            desktop_resize_flag: true,
-            drawing_flags,
-        }),
-        CapabilitySet::Order(Order::new(
-            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
-            OrderSupportExFlags::empty(),
-            0,
-            0,
-        )),
-        CapabilitySet::BitmapCache(BitmapCache {
-            caches: [CacheEntry {
-                entries: 0,
-                max_cell_size: 0,
-            }; BITMAP_CACHE_ENTRIES_NUM],
-        }),
-        CapabilitySet::Input(Input {
-            input_flags: InputFlags::all(),
-            keyboard_layout: 0,
-            keyboard_type: Some(config.keyboard_type),
-            keyboard_subtype: config.keyboard_subtype,
-            keyboard_function_key: config.keyboard_functional_keys_count,
-            keyboard_ime_filename: config.ime_file_name.clone(),
-        }),
-        CapabilitySet::Pointer(Pointer {
-            // Pointer cache should be set to non-zero value to enable client-side pointer rendering.
-            color_pointer_cache_size: DEFAULT_POINTER_CACHE_SIZE,
-            pointer_cache_size: DEFAULT_POINTER_CACHE_SIZE,
-        }),
-        CapabilitySet::Brush(Brush {
-            support_level: SupportLevel::Default,
-        }),
-        CapabilitySet::GlyphCache(GlyphCache {
-            glyph_cache: [CacheDefinition {
-                entries: 0,
-                max_cell_size: 0,
-            }; GLYPH_CACHE_NUM],
-            frag_cache: CacheDefinition {
-                entries: 0,
-                max_cell_size: 0,
-            },
-            glyph_support_level: GlyphSupportLevel::None,
-        }),
-        CapabilitySet::OffscreenBitmapCache(OffscreenBitmapCache {
-            is_supported: false,
-            cache_size: 0,
-            cache_entries: 0,
-        }),
-        CapabilitySet::VirtualChannel(VirtualChannel {
-            flags: VirtualChannelFlags::NO_COMPRESSION,
-            chunk_size: Some(0), // ignored
-        }),
-        CapabilitySet::Sound(Sound {
-            flags: SoundFlags::empty(),
-        }),
-        CapabilitySet::LargePointer(LargePointer {
-            // Setting `LargePointerSupportFlags::UP_TO_384X384_PIXELS` allows server to send
-            // `TS_FP_LARGEPOINTERATTRIBUTE` update messages, which are required for client-side
-            // rendering of pointers bigger than 96x96 pixels.
-            // `LargePointerSupportFlags::UP_TO_96X96_PIXELS` is needed for proper cursor behavior
-            // in Windows 2019 and older
-            flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS | LargePointerSupportFlags::UP_TO_384X384_PIXELS,
-        }),
-        CapabilitySet::SurfaceCommands(SurfaceCommands {
-            flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER,
-        }),
-        CapabilitySet::BitmapCodecs(BitmapCodecs(vec![Codec {
+    // Some servers without a display yet advertise an empty desktop, which the client can't confirm. The requested
+    // size is confirmed instead.
+    let desktop_size = if desktop_size.width == 0 || desktop_size.height == 0 {
+        config.desktop_size
+    } else {
+        desktop_size
+    };
+
+    let mut builder = ClientCapabilitiesBuilder::new(desktop_size.width, desktop_size.height)
+        .with_platform(config.platform)
+        .with_color_depth(color_depth)
+        .with_lossy_bitmap_compression(lossy_bitmap_compression)
+        .with_keyboard(
+            config.keyboard_layout,
+            Some(config.keyboard_type),
+            config.keyboard_subtype,
+            config.keyboard_functional_keys_count,
+            config.ime_file_name.clone(),
+        )
+        // Pointer cache should be set to non-zero value to enable client-side pointer rendering.
+        .with_pointer_cache_size(DEFAULT_POINTER_CACHE_SIZE)
+        // Setting `LargePointerSupportFlags::UP_TO_384X384_PIXELS` allows server to send
+        // `TS_FP_LARGEPOINTERATTRIBUTE` update messages, which are required for client-side
+        // rendering of pointers bigger than 96x96 pixels.
+        // `LargePointerSupportFlags::UP_TO_96X96_PIXELS` is needed for proper cursor behavior
+        // in Windows 2019 and older
+        .with_large_pointer(
+            LargePointerSupportFlags::UP_TO_96X96_PIXELS | LargePointerSupportFlags::UP_TO_384X384_PIXELS,
+        )
+        .with_surface_commands(surface_commands_flags)
+        .with_codecs(vec![Codec {
             id: 0x03, // RemoteFX
             property: CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(RfxClientCapsContainer {
                 capture_flags: CaptureFlags::empty(),
@@ -364,29 +470,53 @@ fn create_client_confirm_active(
                     entropy_bits: EntropyBits::Rlgr3,
                 }])),
             })),
-        }])),
-        CapabilitySet::FrameAcknowledge(FrameAcknowledge {
-            // FIXME(#447): Revert this to 2 per FreeRDP.
-            // This is a temporary hack to fix a resize bug, see:
-            // https://github.com/Devolutions/IronRDP/issues/447
-            max_unacknowledged_frame_count: 20,
-        }),
-    ]);
-
-    if !server_capability_sets
-        .iter()
-        .any(|c| matches!(&c, CapabilitySet::MultiFragmentUpdate(_)))
+        }]);
+
+    if let Some(bitmap_cache) = &config.bitmap_cache {
+        builder = builder.with_bitmap_cache_rev2(bitmap_cache.clone());
+    }
+
+    if config.frame_markers {
+        // FIXME(#447): Revert this to 2 per FreeRDP.
+        // This is a temporary hack to fix a resize bug, see:
+        // https://github.com/Devolutions/IronRDP/issues/447
+        builder = builder.with_frame_acknowledge(20);
+    }
+
+    // The fragmentation limit of the server is echoed back.
+    if let Some(multifragment_update) =
+        server_capability_sets
+            .into_iter()
+            .find_map(|capability_set| match capability_set {
+                CapabilitySet::MultiFragmentUpdate(multifragment_update) => Some(multifragment_update),
+                _ => None,
+            })
     {
-        server_capability_sets.push(CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
-            max_request_size: 8 * 1024 * 1024, // 8 MB
-        }));
+        builder = builder.with_multifragment_update(multifragment_update.max_request_size);
     }
 
-    ClientConfirmActive {
+    if config.remote_app.is_some() {
+        // TS_WINDOW_CAPABILITYSET: WndSupportLevel, NumIconCaches and NumIconCacheEntries.
+        let mut window_list = WINDOW_LEVEL_SUPPORTED.to_le_bytes().to_vec();
+        window_list.push(NUM_ICON_CACHES);
+        window_list.extend_from_slice(&NUM_ICON_CACHE_ENTRIES.to_le_bytes());
+
+        builder = builder
+            .with_raw_capability_set(CapabilitySet::Rail(RAIL_SUPPORT_LEVEL.to_le_bytes().to_vec()))
+            .with_raw_capability_set(CapabilitySet::WindowList(window_list));
+    }
+
+    for capability_set in &config.extra_capability_sets {
+        builder = builder.with_raw_capability_set(capability_set.clone());
+    }
+
+    let capability_sets = builder.build().map_err(|e| custom_err!("client capabilities", e))?;
+
+    Ok(ClientConfirmActive {
         originator_id: SERVER_CHANNEL_ID,
         pdu: DemandActive {
             source_descriptor: "IRONRDP".to_owned(),
-            capability_sets: server_capability_sets,
+            capability_sets,
         },
-    }
+    })
 }
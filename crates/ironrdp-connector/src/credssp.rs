@@ -1,13 +1,18 @@
+use core::fmt;
+use std::net::IpAddr;
+
 use ironrdp_core::{other_err, WriteBuf};
 use ironrdp_pdu::{nego, PduHint};
 use picky::key::PrivateKey;
 use picky_asn1_x509::{oids, Certificate, ExtensionView, GeneralName};
-use sspi::credssp::{self, ClientState, CredSspClient};
+use sspi::credssp::{self, ClientState, CredSspClient, NStatusCode};
 use sspi::generator::{Generator, NetworkRequest};
 use sspi::negotiate::ProtocolConfig;
 use sspi::Username;
 
-use crate::{ConnectorError, ConnectorErrorKind, ConnectorResult, Credentials, ServerName, Written};
+use crate::{
+    ConnectorError, ConnectorErrorKind, ConnectorResult, CredentialDelegation, Credentials, ServerName, Written,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct KerberosConfig {
@@ -70,6 +75,9 @@ pub struct CredsspSequence {
     client: CredSspClient,
     state: CredsspState,
     selected_protocol: nego::SecurityProtocol,
+    mechanism: CredsspMechanism,
+    /// Set once the server passed the public key binding check.
+    public_key_verified: bool,
 }
 
 #[derive(Debug, PartialEq)]
@@ -89,14 +97,23 @@ impl CredsspSequence {
     }
 
     /// `server_name` must be the actual target server hostname (as opposed to the proxy)
+    ///
+    /// The password is not sent to the server when the `credential_delegation` is restricted.
     pub fn init(
         credentials: Credentials,
         domain: Option<&str>,
+        credential_delegation: CredentialDelegation,
         protocol: nego::SecurityProtocol,
         server_name: ServerName,
         server_public_key: Vec<u8>,
         kerberos_config: Option<KerberosConfig>,
     ) -> ConnectorResult<(Self, credssp::TsRequest)> {
+        let credssp_mode = match credential_delegation {
+            CredentialDelegation::Full => credssp::CredSspMode::WithCredentials,
+            // Empty TSPasswordCreds are sent.
+            CredentialDelegation::RestrictedAdmin => credssp::CredSspMode::CredentialLess,
+        };
+
         let credentials: sspi::Credentials = match &credentials {
             Credentials::UsernamePassword { username, password } => {
                 let username = Username::new(username, domain).map_err(|e| custom_err!("invalid username", e))?;
@@ -136,6 +153,13 @@ impl CredsspSequence {
 
         let server_name = server_name.into_inner();
 
+        // The Negotiate package falls back to NTLM when the server is designated by its IP address.
+        let mechanism = if kerberos_config.is_some() && server_name.parse::<IpAddr>().is_err() {
+            CredsspMechanism::Kerberos
+        } else {
+            CredsspMechanism::Ntlm
+        };
+
         let service_principal_name = format!("TERMSRV/{}", &server_name);
 
         let credssp_config: Box<dyn ProtocolConfig>;
@@ -149,7 +173,7 @@ impl CredsspSequence {
         let client = CredSspClient::new(
             server_public_key,
             credentials,
-            credssp::CredSspMode::WithCredentials,
+            credssp_mode,
             credssp::ClientMode::Negotiate(sspi::NegotiateConfig {
                 protocol_config: credssp_config,
                 package_list: None,
@@ -163,6 +187,8 @@ impl CredsspSequence {
             client,
             state: CredsspState::Ongoing,
             selected_protocol: protocol,
+            mechanism,
+            public_key_verified: false,
         };
 
         let initial_request = credssp::TsRequest::default();
@@ -205,19 +231,31 @@ impl CredsspSequence {
         self.client.process(request)
     }
 
+    /// Returns the error to report for `error`, returned by the CredSSP client, with its classified cause.
+    pub fn handle_process_error(&self, error: sspi::Error) -> ConnectorError {
+        let failure = CredsspFailure::classify(&error, self.mechanism, self.public_key_verified);
+
+        ConnectorError::new("CredSSP", ConnectorErrorKind::CredsspFailure(failure)).with_source(error)
+    }
+
     pub fn handle_process_result(&mut self, result: ClientState, output: &mut WriteBuf) -> ConnectorResult<Written> {
         let (size, next_state) = match self.state {
             CredsspState::Ongoing => {
                 let (ts_request_from_client, next_state) = match result {
                     ClientState::ReplyNeeded(ts_request) => (ts_request, CredsspState::Ongoing),
-                    ClientState::FinalMessage(ts_request) => (
-                        ts_request,
-                        if self.selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX) {
+                    ClientState::FinalMessage(ts_request) => {
+                        // The final message, holding the credentials, is sent once the public key echoed by the
+                        // server is verified.
+                        self.public_key_verified = true;
+
+                        let next_state = if self.selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX) {
                             CredsspState::EarlyUserAuthResult
                         } else {
                             CredsspState::Finished
-                        },
-                    ),
+                        };
+
+                        (ts_request, next_state)
+                    }
                 };
 
                 debug!(message = ?ts_request_from_client, "Send");
@@ -236,6 +274,229 @@ impl CredsspSequence {
     }
 }
 
+/// Security package attempted by the CredSSP client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredsspMechanism {
+    Kerberos,
+    /// NTLM, used when Kerberos is not configured, or as the fallback of the Negotiate package.
+    Ntlm,
+}
+
+impl fmt::Display for CredsspMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredsspMechanism::Kerberos => write!(f, "Kerberos"),
+            CredsspMechanism::Ntlm => write!(f, "NTLM"),
+        }
+    }
+}
+
+/// Point of the CredSSP sequence at which a failure happened, relative to the public key binding check
+///
+/// The server proves that it is the endpoint of the TLS connection by echoing the public key of its certificate,
+/// bound to the authentication. Until then, the failures may as well be caused by a machine in the middle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredsspPhase {
+    /// The public key binding check was not passed, either because it was not reached yet or because it failed.
+    BeforePublicKeyBinding,
+    /// The public key binding check was passed, the failure is reported by the authenticated server.
+    AfterPublicKeyBinding,
+}
+
+impl fmt::Display for CredsspPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredsspPhase::BeforePublicKeyBinding => write!(f, "before the public key binding check"),
+            CredsspPhase::AfterPublicKeyBinding => write!(f, "after the public key binding check"),
+        }
+    }
+}
+
+/// Cause of a CredSSP failure, as far as it can be told from the SSPI error
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredsspFailureReason {
+    /// The clocks of the client and of the domain controller are too far apart (`KRB_AP_ERR_SKEW`,
+    /// `STATUS_TIME_DIFFERENCE_AT_DC`).
+    ClockSkew,
+    /// The domain controller supports none of the encryption types offered by the client (`KDC_ERR_ETYPE_NOSUPP`).
+    ///
+    /// Old domain controllers, or accounts without AES keys, only support RC4, which is not offered.
+    UnsupportedEncryptionType,
+    /// The domain controller rejected the pre-authentication data, derived from the password
+    /// (`KDC_ERR_PREAUTH_FAILED`).
+    PreauthenticationFailed,
+    /// The realm of the user is not served by the domain controller (`KDC_ERR_WRONG_REALM`).
+    WrongRealm,
+    /// The user is not known by the domain controller (`KDC_ERR_C_PRINCIPAL_UNKNOWN`).
+    UnknownUser,
+    /// No service principal matches the name of the server (`KDC_ERR_S_PRINCIPAL_UNKNOWN`), or the ticket is
+    /// encrypted for another account than the one of the server (`KRB_AP_ERR_MODIFIED`, `KRB_AP_ERR_NOT_US`).
+    WrongServicePrincipal,
+    /// The domain controller could not be found or reached.
+    KdcUnreachable,
+    /// The server rejected the password (`STATUS_WRONG_PASSWORD`).
+    WrongPassword,
+    /// The server rejected the user name or the password (`STATUS_LOGON_FAILURE`).
+    LogonFailure,
+    /// The account is not allowed to log on: disabled, locked out, expired password, or logon restrictions.
+    AccountRestricted,
+    /// The server could not prove that it holds the private key of its TLS certificate.
+    PublicKeyMismatch,
+    /// Any other failure, described by the SSPI error.
+    Other,
+}
+
+impl CredsspFailureReason {
+    /// Returns the reason of a failure caused by a KRB-ERROR message, or a failure to reach the KDC.
+    ///
+    /// sspi maps the error code of the KRB-ERROR messages to an error kind, shared by several codes, and to a
+    /// description, which is matched as well.
+    fn from_kerberos_error(error: &sspi::Error) -> Option<Self> {
+        use sspi::ErrorKind;
+
+        let description = error.description.as_str();
+
+        let reason = match error.error_type {
+            ErrorKind::TimeSkew => Self::ClockSkew,
+            ErrorKind::KdcUnknownEType | ErrorKind::KdcUnknownEType2 => Self::UnsupportedEncryptionType,
+            ErrorKind::OperationNotSupported if description.starts_with("KDC has no support for encryption type") => {
+                Self::UnsupportedEncryptionType
+            }
+            ErrorKind::KdcInvalidRequest if description.starts_with("pre-authentication information was invalid") => {
+                Self::PreauthenticationFailed
+            }
+            ErrorKind::InvalidParameter if description.starts_with("wrong Realm") => Self::WrongRealm,
+            ErrorKind::InvalidParameter if description.starts_with("password has expired") => Self::AccountRestricted,
+            ErrorKind::UnknownCredentials if description.starts_with("client not found in Kerberos database") => {
+                Self::UnknownUser
+            }
+            ErrorKind::UnknownCredentials if description.starts_with("clients credentials have been revoked") => {
+                Self::AccountRestricted
+            }
+            ErrorKind::UnknownCredentials if description.starts_with("server not found in Kerberos database") => {
+                Self::WrongServicePrincipal
+            }
+            ErrorKind::MessageAltered if description.starts_with("message stream modified") => {
+                Self::WrongServicePrincipal
+            }
+            ErrorKind::InvalidToken if description.starts_with("the ticket isn't for us") => {
+                Self::WrongServicePrincipal
+            }
+            ErrorKind::NoAuthenticatingAuthority => Self::KdcUnreachable,
+            _ => return None,
+        };
+
+        Some(reason)
+    }
+
+    /// Returns the reason of a failure reported by the server with a NTSTATUS code.
+    fn from_nstatus(nstatus: NStatusCode) -> Option<Self> {
+        let reason = match nstatus {
+            NStatusCode::WRONG_PASSWORD => Self::WrongPassword,
+            NStatusCode::LOGON_FAILURE => Self::LogonFailure,
+            NStatusCode::TIME_DIFFERENCE_AT_DC => Self::ClockSkew,
+            NStatusCode::ACCOUNT_DISABLED
+            | NStatusCode::ACCOUNT_LOCKED_OUT
+            | NStatusCode::ACCOUNT_RESTRICTION
+            | NStatusCode::PASSWORD_EXPIRED
+            | NStatusCode::PASSWORD_MUST_CHANGE
+            | NStatusCode::INVALID_LOGON_HOURS
+            | NStatusCode::INVALID_WORKSTATION
+            | NStatusCode::LOGON_TYPE_NOT_GRANTED => Self::AccountRestricted,
+            _ => return None,
+        };
+
+        Some(reason)
+    }
+}
+
+impl fmt::Display for CredsspFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClockSkew => write!(
+                f,
+                "the clocks of the client and of the domain controller are too far apart, synchronize the clock of the client"
+            ),
+            Self::UnsupportedEncryptionType => write!(
+                f,
+                "the domain controller supports none of the offered encryption types, enable AES for the account"
+            ),
+            Self::PreauthenticationFailed => write!(f, "the domain controller rejected the password"),
+            Self::WrongRealm => write!(
+                f,
+                "the domain controller does not serve the realm of the user, check the domain and the KDC URL"
+            ),
+            Self::UnknownUser => write!(f, "the user is not known by the domain controller, check the user name"),
+            Self::WrongServicePrincipal => write!(
+                f,
+                "no service principal matches the server name, connect to the host name registered in the domain"
+            ),
+            Self::KdcUnreachable => write!(f, "the domain controller could not be reached, check the KDC URL"),
+            Self::WrongPassword => write!(f, "the server rejected the password"),
+            Self::LogonFailure => write!(f, "the server rejected the user name or the password"),
+            Self::AccountRestricted => write!(f, "the account is not allowed to log on"),
+            Self::PublicKeyMismatch => write!(
+                f,
+                "the server could not prove that it holds the key of its certificate, the connection may be intercepted"
+            ),
+            Self::Other => write!(f, "unexpected failure"),
+        }
+    }
+}
+
+/// Classified CredSSP failure, see [`CredsspFailure::classify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CredsspFailure {
+    pub reason: CredsspFailureReason,
+    /// Mechanism attempted when the failure happened.
+    pub mechanism: CredsspMechanism,
+    pub phase: CredsspPhase,
+}
+
+impl CredsspFailure {
+    /// Classifies `error`, returned by the CredSSP client.
+    ///
+    /// `mechanism` is the mechanism attempted according to the configuration, superseded by Kerberos for the
+    /// Kerberos errors. `public_key_verified` tells whether the server passed the public key binding check.
+    pub fn classify(error: &sspi::Error, mechanism: CredsspMechanism, public_key_verified: bool) -> Self {
+        let is_public_key_mismatch = matches!(error.error_type, sspi::ErrorKind::MessageAltered)
+            && error.description.starts_with("Could not verify a public key");
+
+        let (reason, mechanism) = if is_public_key_mismatch {
+            (CredsspFailureReason::PublicKeyMismatch, mechanism)
+        } else if let Some(reason) = error.nstatus.and_then(CredsspFailureReason::from_nstatus) {
+            (reason, mechanism)
+        } else if let Some(reason) = CredsspFailureReason::from_kerberos_error(error) {
+            (reason, CredsspMechanism::Kerberos)
+        } else {
+            (CredsspFailureReason::Other, mechanism)
+        };
+
+        let phase = if public_key_verified && !is_public_key_mismatch {
+            CredsspPhase::AfterPublicKeyBinding
+        } else {
+            CredsspPhase::BeforePublicKeyBinding
+        };
+
+        Self {
+            reason,
+            mechanism,
+            phase,
+        }
+    }
+}
+
+impl fmt::Display for CredsspFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} authentication failed {}: {}",
+            self.mechanism, self.phase, self.reason
+        )
+    }
+}
+
 fn extract_user_name(cert: &Certificate) -> Option<String> {
     cert.tbs_certificate.subject.find_common_name().map(ToString::to_string)
 }
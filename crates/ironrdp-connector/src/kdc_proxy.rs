@@ -0,0 +1,107 @@
+//! Framing of Kerberos messages tunneled through a KDC proxy ([MS-KKDCP])
+//!
+//! Kerberos messages sent over a KDC proxy are wrapped into a `KDC-PROXY-MESSAGE` structure.
+//! The wrapped `kerb-message` is framed as for Kerberos over TCP, that is, it is prefixed by
+//! its length as a 4-byte big-endian integer ([RFC 4120 7.2.2]).
+//!
+//! [MS-KKDCP]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-kkdcp/5bcebb8d-b747-4ee5-9453-428aec1c5c38
+//! [RFC 4120 7.2.2]: https://www.rfc-editor.org/rfc/rfc4120#section-7.2.2
+
+use picky_asn1::restricted_string::Ia5String;
+use picky_asn1::wrapper::{ExplicitContextTag0, ExplicitContextTag1, GeneralStringAsn1, OctetStringAsn1, Optional};
+use picky_krb::messages::{AsReq, KdcProxyMessage, TgsReq};
+
+use crate::ConnectorResult;
+
+/// Size of the length prefix of a Kerberos message sent over TCP.
+pub const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Prefixes a Kerberos message with its length, as done for Kerberos over TCP.
+///
+/// This is required for messages which are not already framed, such as the ones destined to be sent over UDP.
+pub fn add_length_prefix(message: &[u8]) -> ConnectorResult<Vec<u8>> {
+    let length = u32::try_from(message.len()).map_err(|_| general_err!("Kerberos message is too big"))?;
+
+    let mut framed = Vec::with_capacity(LENGTH_PREFIX_SIZE + message.len());
+    framed.extend_from_slice(&length.to_be_bytes());
+    framed.extend_from_slice(message);
+
+    Ok(framed)
+}
+
+/// Returns the Kerberos message without its length prefix, after checking the prefix is consistent.
+pub fn strip_length_prefix(framed: &[u8]) -> ConnectorResult<&[u8]> {
+    if framed.len() < LENGTH_PREFIX_SIZE {
+        return Err(general_err!("Kerberos message is missing its length prefix"));
+    }
+
+    let (prefix, message) = framed.split_at(LENGTH_PREFIX_SIZE);
+    let prefix: [u8; LENGTH_PREFIX_SIZE] = prefix.try_into().expect("prefix is LENGTH_PREFIX_SIZE bytes long");
+
+    let length = usize::try_from(u32::from_be_bytes(prefix)).map_err(|_| general_err!("invalid length prefix"))?;
+
+    if length != message.len() {
+        return Err(reason_err!(
+            "KdcProxy",
+            "length prefix mismatch (expected {length} bytes, got {})",
+            message.len()
+        ));
+    }
+
+    Ok(message)
+}
+
+/// Wraps a length-prefixed Kerberos request into a DER-encoded `KDC-PROXY-MESSAGE`.
+///
+/// When `target_domain` is `None`, the realm of the AS-REQ or TGS-REQ is used instead, if any.
+pub fn encode_kdc_proxy_request(framed_request: &[u8], target_domain: Option<&str>) -> ConnectorResult<Vec<u8>> {
+    let request = strip_length_prefix(framed_request)?;
+
+    let target_domain = match target_domain {
+        Some(domain) => Some(domain.to_owned()),
+        None => kdc_request_realm(request),
+    };
+
+    let target_domain = target_domain
+        .map(|domain| {
+            Ia5String::from_string(domain)
+                .map(|domain| ExplicitContextTag1::from(GeneralStringAsn1::from(domain)))
+                .map_err(|e| custom_err!("invalid target domain", e))
+        })
+        .transpose()?;
+
+    let message = KdcProxyMessage {
+        kerb_message: ExplicitContextTag0::from(OctetStringAsn1::from(framed_request.to_vec())),
+        target_domain: Optional::from(target_domain),
+        dclocator_hint: Optional::from(None),
+    };
+
+    message
+        .to_vec()
+        .map_err(|e| custom_err!("KDC-PROXY-MESSAGE encoding", e))
+}
+
+/// Extracts the length-prefixed Kerberos reply from a DER-encoded `KDC-PROXY-MESSAGE`.
+pub fn decode_kdc_proxy_response(response: &[u8]) -> ConnectorResult<Vec<u8>> {
+    let message = KdcProxyMessage::from_raw(response).map_err(|e| custom_err!("KDC-PROXY-MESSAGE decoding", e))?;
+
+    let framed_reply = message.kerb_message.0 .0;
+
+    // Ensure the reply is properly framed before handing it over to the Kerberos client.
+    strip_length_prefix(&framed_reply)?;
+
+    Ok(framed_reply)
+}
+
+/// Returns the realm targeted by an AS-REQ or TGS-REQ message (without length prefix).
+pub fn kdc_request_realm(request: &[u8]) -> Option<String> {
+    if let Ok(as_req) = picky_asn1_der::from_bytes::<AsReq>(request) {
+        return Some(as_req.0.req_body.0.realm.0.to_string());
+    }
+
+    if let Ok(tgs_req) = picky_asn1_der::from_bytes::<TgsReq>(request) {
+        return Some(tgs_req.0.req_body.0.realm.0.to_string());
+    }
+
+    None
+}
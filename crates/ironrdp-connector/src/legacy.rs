@@ -1,9 +1,11 @@
 use std::borrow::Cow;
 
-use ironrdp_core::{decode, encode_vec, Decode, Encode, WriteBuf};
+use ironrdp_core::{decode, encode_vec, Decode, Encode, ReadCursor, WriteBuf};
 use ironrdp_pdu::rdp;
 use ironrdp_pdu::rdp::headers::ServerDeactivateAll;
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
 use ironrdp_pdu::x224::X224;
+use ironrdp_pdu::{DecodeOptions, DecodeWarning};
 
 use crate::{ConnectorError, ConnectorErrorExt as _, ConnectorResult};
 
@@ -105,7 +107,32 @@ pub struct ShareControlCtx {
 }
 
 pub fn decode_share_control(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<ShareControlCtx> {
-    let user_msg = ctx.decode_user_data::<rdp::headers::ShareControlHeader>()?;
+    decode_share_control_with_options(ctx, DecodeOptions::STRICT, &mut Vec::new())
+}
+
+/// Decodes a Share Control PDU, see [`ShareControlHeader::decode_with_options`].
+///
+/// Each tolerated deviation is logged and pushed to `warnings`.
+///
+/// [`ShareControlHeader::decode_with_options`]: rdp::headers::ShareControlHeader::decode_with_options
+pub fn decode_share_control_with_options(
+    ctx: SendDataIndicationCtx<'_>,
+    options: DecodeOptions,
+    warnings: &mut Vec<DecodeWarning>,
+) -> ConnectorResult<ShareControlCtx> {
+    let mut new_warnings = Vec::new();
+
+    let user_msg = rdp::headers::ShareControlHeader::decode_with_options(
+        &mut ReadCursor::new(ctx.user_data),
+        options,
+        &mut new_warnings,
+    )
+    .map_err(ConnectorError::decode)?;
+
+    for warning in &new_warnings {
+        warn!(%warning, "Tolerated non-conformant Share Control PDU");
+    }
+    warnings.extend(new_warnings);
 
     Ok(ShareControlCtx {
         initiator_id: ctx.initiator_id,
@@ -165,6 +192,7 @@ pub fn decode_share_data(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<Shar
 pub enum IoChannelPdu {
     Data(ShareDataCtx),
     DeactivateAll(ServerDeactivateAll),
+    ServerRedirect(ServerRedirectionPdu),
 }
 
 pub fn decode_io_channel(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<IoChannelPdu> {
@@ -174,6 +202,7 @@ pub fn decode_io_channel(ctx: SendDataIndicationCtx<'_>) -> ConnectorResult<IoCh
         rdp::headers::ShareControlPdu::ServerDeactivateAll(deactivate_all) => {
             Ok(IoChannelPdu::DeactivateAll(deactivate_all))
         }
+        rdp::headers::ShareControlPdu::ServerRedirect(redirection) => Ok(IoChannelPdu::ServerRedirect(redirection)),
         rdp::headers::ShareControlPdu::Data(share_data_header) => {
             let share_data_ctx = ShareDataCtx {
                 initiator_id: ctx.initiator_id,
@@ -14,8 +14,11 @@ mod connection;
 pub mod connection_activation;
 mod connection_finalization;
 pub mod credssp;
+pub mod kdc_proxy;
 mod license_exchange;
+pub mod redirection;
 mod server_name;
+mod timeouts;
 
 pub use crate::license_exchange::LicenseCache;
 pub use channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
@@ -23,19 +26,22 @@ pub use connection::{encode_send_data_request, ClientConnector, ClientConnectorS
 pub use connection_finalization::{ConnectionFinalizationSequence, ConnectionFinalizationState};
 use core::any::Any;
 use core::fmt;
-use ironrdp_core::{encode_buf, encode_vec, Encode, WriteBuf};
-use ironrdp_pdu::nego::NegoRequestData;
+use ironrdp_core::{encode_buf, encode_vec, Encode, ErrorCategory, ErrorKindExt, WriteBuf};
+use ironrdp_pdu::nego::{self, NegoRequestData};
 use ironrdp_pdu::rdp::capability_sets;
 use ironrdp_pdu::rdp::client_info::PerformanceFlags;
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
 use ironrdp_pdu::x224::X224;
 use ironrdp_pdu::{gcc, x224, PduHint};
 pub use license_exchange::{LicenseExchangeSequence, LicenseExchangeState};
 pub use server_name::ServerName;
 pub use sspi;
 use std::sync::Arc;
+pub use timeouts::{ConnectStage, ConnectTimeouts};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DesktopSize {
     pub width: u16,
     pub height: u16,
@@ -43,12 +49,25 @@ pub struct DesktopSize {
 
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitmapConfig {
     pub lossy_compression: bool,
     pub color_depth: u32,
 }
 
+/// Remote application (RemoteApp) to launch instead of a full desktop
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemoteAppConfig {
+    /// Executable or file to launch, e.g.: `||notepad` for a published application alias.
+    pub program: String,
+    pub working_dir: Option<String>,
+    pub args: Option<String>,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SmartCardIdentity {
     /// DER-encoded X509 certificate
     pub certificate: Vec<u8>,
@@ -59,16 +78,20 @@ pub struct SmartCardIdentity {
     /// Smart card CSP name
     pub csp_name: String,
     /// DER-encoded RSA 2048-bit private key
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
     pub private_key: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Credentials {
     UsernamePassword {
         username: String,
+        #[cfg_attr(feature = "serde", serde(skip_serializing))]
         password: String,
     },
     SmartCard {
+        #[cfg_attr(feature = "serde", serde(skip_serializing))]
         pin: String,
         config: Option<SmartCardIdentity>,
     },
@@ -90,8 +113,77 @@ impl Credentials {
     }
 }
 
+/// Credentials made available to the server for the session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CredentialDelegation {
+    /// The password is delegated to the server, both by CredSSP and in the Client Info PDU.
+    #[default]
+    Full,
+    /// Restricted Admin mode (`/restrictedAdmin`)
+    ///
+    /// The user is logged on with the network identity of the CredSSP authentication, and no password is sent to the
+    /// server. Requires NLA, and a server with Restricted Admin mode enabled.
+    RestrictedAdmin,
+}
+
+impl CredentialDelegation {
+    /// Flags of the X.224 Connection Request PDU requiring this mode.
+    pub fn request_flags(self) -> nego::RequestFlags {
+        match self {
+            Self::Full => nego::RequestFlags::empty(),
+            Self::RestrictedAdmin => nego::RequestFlags::RESTRICTED_ADMIN_MODE_REQUIRED,
+        }
+    }
+
+    /// Returns `true` if the flags of the X.224 Connection Confirm PDU advertise the support of this mode.
+    pub fn is_supported_by(self, flags: nego::ResponseFlags) -> bool {
+        match self {
+            Self::Full => true,
+            Self::RestrictedAdmin => flags.contains(nego::ResponseFlags::RESTRICTED_ADMIN_MODE_SUPPORTED),
+        }
+    }
+
+    /// Returns `true` if the password is sent to the server.
+    pub fn delegates_password(self) -> bool {
+        matches!(self, Self::Full)
+    }
+}
+
+impl fmt::Display for CredentialDelegation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full credential delegation"),
+            Self::RestrictedAdmin => write!(f, "Restricted Admin mode"),
+        }
+    }
+}
+
+/// Existing session to connect to, announced in the Client Cluster Data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RedirectSession {
+    /// The console session (`/console`), whose ID is zero.
+    Console,
+    /// A disconnected session, by ID.
+    SessionId(u32),
+}
+
+impl RedirectSession {
+    /// ID sent in the `RedirectedSessionID` field of the Client Cluster Data.
+    pub fn session_id(self) -> u32 {
+        match self {
+            Self::Console => 0,
+            Self::SessionId(session_id) => session_id,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// The initial desktop size to request
     pub desktop_size: DesktopSize,
@@ -147,6 +239,8 @@ pub struct Config {
     pub enable_credssp: bool,
     pub credentials: Credentials,
     pub domain: Option<String>,
+    /// Credentials made available to the server, see [`CredentialDelegation`].
+    pub credential_delegation: CredentialDelegation,
     /// The build number of the client.
     pub client_build: u32,
     /// Name of the client computer
@@ -156,7 +250,17 @@ pub struct Config {
     pub keyboard_type: gcc::KeyboardType,
     pub keyboard_subtype: u32,
     pub keyboard_functional_keys_count: u32,
+    /// Active input locale identifier, also known as the HKL (e.g.: 0x00000409 for a US keyboard layout, or
+    /// 0xE0010411 for the Japanese IME).
+    ///
+    /// When zero, the server uses its default active input locale identifier.
     pub keyboard_layout: u32,
+    /// Language identifier of the active input locale (e.g.: 0x0411 for ja-JP).
+    ///
+    /// This becomes the `CodePage` field of the [`ClientInfoPdu`](ironrdp_pdu::rdp::ClientInfoPdu), as the
+    /// client always advertises Unicode support. It is ignored by the server when `keyboard_layout` is zero.
+    /// Zero is sent when `None`.
+    pub active_input_locale: Option<u32>,
     pub ime_file_name: String,
     pub bitmap: Option<BitmapConfig>,
     pub dig_product_id: String,
@@ -173,9 +277,53 @@ pub struct Config {
     /// - A cookie containing the username for a username/password.
     /// - Nothing for a smart card.
     pub request_data: Option<NegoRequestData>,
+    /// Identifier sent in the RDP_NEG_CORRELATION_INFO structure, used by some load balancers to correlate the
+    /// connections.
+    ///
+    /// The first byte must be neither 0x00 nor 0xF4, and none of the bytes may be 0x0D.
+    pub correlation_id: Option<[u8; 16]>,
     /// If true, the INFO_AUTOLOGON flag is set in the [`ClientInfoPdu`](ironrdp_pdu::rdp::ClientInfoPdu)
     pub autologon: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub license_cache: Option<Arc<dyn LicenseCache>>,
+    /// Launches a single remote application instead of a full desktop.
+    ///
+    /// When set, the RAIL flag of the Client Info PDU and the Remote Programs and Window List capability sets are
+    /// sent. The RAIL static channel launching the application must be attached to the [`ClientConnector`] by the
+    /// caller (e.g.: with `ironrdp_rail::client::Rail::with_desktop_size`).
+    pub remote_app: Option<RemoteAppConfig>,
+    /// Auto-reconnect cookie received from the server in a previous connection (see the Save Session Info PDU).
+    ///
+    /// When set, the client auto-reconnect cookie derived from it is sent in the Client Info PDU, so that the user
+    /// is logged back into the same session.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    /// Advertises the support of the surface frame markers and of the Frame Acknowledge PDU.
+    ///
+    /// When the server supports them as well, the graphics updates are delimited into frames, and each completed
+    /// frame is acknowledged so that the server can throttle its output.
+    pub frame_markers: bool,
+    /// Revision 2 bitmap cache advertised instead of the empty revision 1 one.
+    ///
+    /// The MemBlt and Mem3Blt orders are advertised along with it, so that the server can draw from the cache.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub bitmap_cache: Option<capability_sets::BitmapCacheRev2>,
+    /// Time budgets of the connection sequence.
+    pub timeouts: ConnectTimeouts,
+    /// Capability sets appended as-is to the Client Confirm Active PDU.
+    ///
+    /// Meant for the capability sets not otherwise advertised by the connector. A capability set of a type already
+    /// advertised fails the capabilities exchange.
+    pub extra_capability_sets: Vec<capability_sets::CapabilitySet>,
+    /// Answers the Initiate Multitransport Request PDUs with a response declining the UDP side channel.
+    ///
+    /// Otherwise, the requests are only reported, and some servers delay their output until the bootstrapping of
+    /// the side channel times out.
+    pub decline_multitransport: bool,
+    /// Session to connect to instead of a new or the last disconnected session of the user.
+    ///
+    /// Set by [`ClientConnector::redirect`] to the session ID provided by the server.
+    pub redirect_session: Option<RedirectSession>,
 
     // FIXME(@CBenoit): these are client-only options, not part of the connector.
     pub no_server_pointer: bool,
@@ -270,6 +418,26 @@ pub enum ConnectorErrorKind {
     AccessDenied,
     General,
     Custom,
+    /// The connection sequence exceeded its time budget.
+    Timeout {
+        /// Name of the connector state at the time the budget was exceeded.
+        stage: &'static str,
+    },
+    /// The requested [`CredentialDelegation`] mode is not supported, by the server or by this implementation.
+    ///
+    /// The connection may be attempted again with another mode, or once the mode is enabled on the server (e.g.:
+    /// Restricted Admin mode is disabled by default on Windows).
+    UnsupportedCredentialDelegation {
+        mode: CredentialDelegation,
+    },
+    /// The server rejected the security protocols requested by the client.
+    NegotiationFailure(nego::NegotiationFailureCode),
+    /// The server redirected the client to another server.
+    ///
+    /// The connection must be established again, see [`ClientConnector::redirect`].
+    Redirected(Box<redirection::ServerRedirection>),
+    /// The CredSSP authentication failed, the SSPI error being the source of the error.
+    CredsspFailure(credssp::CredsspFailure),
 }
 
 impl fmt::Display for ConnectorErrorKind {
@@ -282,6 +450,14 @@ impl fmt::Display for ConnectorErrorKind {
             ConnectorErrorKind::AccessDenied => write!(f, "access denied"),
             ConnectorErrorKind::General => write!(f, "general error"),
             ConnectorErrorKind::Custom => write!(f, "custom error"),
+            ConnectorErrorKind::Timeout { stage } => write!(f, "timed out during {stage}"),
+            ConnectorErrorKind::UnsupportedCredentialDelegation { mode } => write!(f, "{mode} is not supported"),
+            ConnectorErrorKind::NegotiationFailure(code) => write!(f, "negotiation failure: {code}"),
+            ConnectorErrorKind::Redirected(redirection) => match &redirection.target {
+                Some(target) => write!(f, "redirected to {target}"),
+                None => write!(f, "redirected to the same server"),
+            },
+            ConnectorErrorKind::CredsspFailure(failure) => write!(f, "{failure}"),
         }
     }
 }
@@ -296,6 +472,45 @@ impl std::error::Error for ConnectorErrorKind {
             ConnectorErrorKind::AccessDenied => None,
             ConnectorErrorKind::Custom => None,
             ConnectorErrorKind::General => None,
+            ConnectorErrorKind::Timeout { .. } => None,
+            ConnectorErrorKind::UnsupportedCredentialDelegation { .. } => None,
+            ConnectorErrorKind::NegotiationFailure(_) => None,
+            ConnectorErrorKind::Redirected(_) => None,
+            ConnectorErrorKind::CredsspFailure(_) => None,
+        }
+    }
+}
+
+impl ErrorKindExt for ConnectorErrorKind {
+    fn code(&self) -> u32 {
+        match self {
+            ConnectorErrorKind::Encode(_) => 0x0004_0001,
+            ConnectorErrorKind::Decode(_) => 0x0004_0002,
+            ConnectorErrorKind::Credssp(_) => 0x0004_0003,
+            ConnectorErrorKind::Reason(_) => 0x0004_0004,
+            ConnectorErrorKind::AccessDenied => 0x0004_0005,
+            ConnectorErrorKind::General => 0x0004_0006,
+            ConnectorErrorKind::Custom => 0x0004_0007,
+            ConnectorErrorKind::Timeout { .. } => 0x0004_0008,
+            ConnectorErrorKind::UnsupportedCredentialDelegation { .. } => 0x0004_0009,
+            ConnectorErrorKind::NegotiationFailure(_) => 0x0004_000A,
+            ConnectorErrorKind::Redirected(_) => 0x0004_000B,
+            ConnectorErrorKind::CredsspFailure(_) => 0x0004_000C,
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            ConnectorErrorKind::Encode(_) => ErrorCategory::Encoding,
+            ConnectorErrorKind::Decode(_) => ErrorCategory::Decoding,
+            ConnectorErrorKind::Credssp(_)
+            | ConnectorErrorKind::AccessDenied
+            | ConnectorErrorKind::UnsupportedCredentialDelegation { .. }
+            | ConnectorErrorKind::NegotiationFailure(_)
+            | ConnectorErrorKind::CredsspFailure(_) => ErrorCategory::Security,
+            ConnectorErrorKind::Reason(_) | ConnectorErrorKind::Redirected(_) => ErrorCategory::Protocol,
+            ConnectorErrorKind::Timeout { .. } => ErrorCategory::Timeout,
+            ConnectorErrorKind::General | ConnectorErrorKind::Custom => ErrorCategory::Other,
         }
     }
 }
@@ -0,0 +1,142 @@
+//! Server redirection, used by the load balancers and the servers of a farm to send the client to another server
+//!
+//! When the server sends a [Server Redirection PDU], the connection sequence ends in the
+//! [`ClientConnectorState::Redirected`] state, and the active stage outputs a [`ServerRedirection`] as well. Following
+//! the redirection requires a new connection:
+//!
+//! 1. Close the current connection.
+//! 2. Connect to [`ServerRedirection::target`], or to the same server when it is missing.
+//! 3. Call [`ClientConnector::redirect`] before running the connection sequence again, so that the routing token is
+//!    sent in the X.224 Connection Request PDU, the session ID in the Client Cluster Data, and the credentials
+//!    provided by the server are used.
+//!
+//! [Server Redirection PDU]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7c
+//! [`ClientConnectorState::Redirected`]: crate::ClientConnectorState::Redirected
+//! [`ClientConnector::redirect`]: crate::ClientConnector::redirect
+
+use ironrdp_pdu::nego::NegoRequestData;
+pub use ironrdp_pdu::rdp::server_redirection::RedirectionFlags;
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+
+use crate::{Config, Credentials, RedirectSession};
+
+/// Instruction of the server to connect to another server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRedirection {
+    /// Address of the server to connect to, `None` meaning the same server.
+    ///
+    /// The FQDN is preferred over the IP address, and the IP address over the NetBIOS name.
+    pub target: Option<String>,
+    /// Routing token identifying the target server or session, to send in the X.224 Connection Request PDU.
+    pub load_balance_info: Option<Vec<u8>>,
+    pub credentials: RedirectionCredentials,
+    pub flags: RedirectionFlags,
+    /// ID of the session to reconnect to.
+    pub session_id: u32,
+}
+
+/// Credentials to use on the target server, replacing the configured ones
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct RedirectionCredentials {
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    /// Password, or opaque cookie to send back to the target server.
+    ///
+    /// `None` if the server encrypted it with the public key of the target, which is not supported.
+    pub password: Option<String>,
+}
+
+impl core::fmt::Debug for RedirectionCredentials {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RedirectionCredentials")
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl ServerRedirection {
+    /// Returns the routing token to send in the X.224 Connection Request PDU, if the server provided one.
+    ///
+    /// The load balancing information is opaque, such as a `Cookie: msts=...` line or a
+    /// `tsv://MS Terminal Services Plugin.1.<collection>` URL, and is sent back verbatim.
+    pub fn routing_token(&self) -> Option<NegoRequestData> {
+        let load_balance_info = self.load_balance_info.as_deref()?;
+
+        Some(NegoRequestData::verbatim(load_balance_info.to_vec()))
+    }
+
+    /// Updates `config` for the connection to the target server.
+    pub fn apply_to(&self, config: &mut Config) {
+        config.redirect_session = Some(RedirectSession::SessionId(self.session_id));
+
+        if let Some(routing_token) = self.routing_token() {
+            config.request_data = Some(routing_token);
+        }
+
+        if let Some(domain) = &self.credentials.domain {
+            config.domain = Some(domain.clone());
+        }
+
+        let (username, password) = match &config.credentials {
+            Credentials::UsernamePassword { username, password } => (username, password),
+            // The smart card credentials are kept.
+            Credentials::SmartCard { .. } => return,
+        };
+
+        config.credentials = Credentials::UsernamePassword {
+            username: self.credentials.username.clone().unwrap_or_else(|| username.clone()),
+            password: self.credentials.password.clone().unwrap_or_else(|| password.clone()),
+        };
+    }
+}
+
+impl From<ServerRedirectionPdu> for ServerRedirection {
+    fn from(pdu: ServerRedirectionPdu) -> Self {
+        let target = pdu
+            .target_fqdn
+            .or(pdu.target_net_address)
+            .or(pdu.target_netbios_name)
+            .filter(|target| !target.is_empty());
+
+        let password = pdu.password.and_then(|password| {
+            if pdu.flags.contains(RedirectionFlags::PASSWORD_IS_PK_ENCRYPTED) {
+                warn!("Ignoring the redirection password encrypted with the public key of the target");
+                return None;
+            }
+
+            decode_password(&password).or_else(|| {
+                warn!("Ignoring the redirection password, which is not a valid UTF-16 string");
+                None
+            })
+        });
+
+        Self {
+            target,
+            load_balance_info: pdu.load_balance_info,
+            credentials: RedirectionCredentials {
+                username: pdu.username,
+                domain: pdu.domain,
+                password,
+            },
+            flags: pdu.flags,
+            session_id: pdu.session_id,
+        }
+    }
+}
+
+fn decode_password(password: &[u8]) -> Option<String> {
+    if password.len() % 2 != 0 {
+        return None;
+    }
+
+    let code_units = password
+        .chunks_exact(2)
+        .map(|code_unit| u16::from_le_bytes([code_unit[0], code_unit[1]]))
+        .collect::<Vec<_>>();
+
+    String::from_utf16(&code_units)
+        .ok()
+        .map(|password| password.trim_end_matches('\0').to_owned())
+}
@@ -0,0 +1,104 @@
+use core::time::Duration;
+
+use crate::ClientConnectorState;
+
+/// Stages of the connection sequence, each of them with its own time budget in [`ConnectTimeouts`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectStage {
+    /// Connection initiation (X.224 connection request and confirm).
+    Negotiation,
+    /// Network Level Authentication.
+    Credssp,
+    /// Basic settings exchange and channel connection.
+    McsConnect,
+    /// Security commencement, secure settings exchange, connect-time auto-detection and licensing.
+    Licensing,
+    /// Multitransport bootstrapping, capabilities exchange and connection finalization.
+    Finalization,
+}
+
+impl ConnectStage {
+    /// Returns the stage the connector is at, or `None` if it is not exchanging PDUs with the server.
+    pub fn of(state: &ClientConnectorState) -> Option<Self> {
+        match state {
+            ClientConnectorState::ConnectionInitiationSendRequest
+            | ClientConnectorState::ConnectionInitiationWaitConfirm { .. } => Some(Self::Negotiation),
+            ClientConnectorState::Credssp { .. } => Some(Self::Credssp),
+            ClientConnectorState::BasicSettingsExchangeSendInitial { .. }
+            | ClientConnectorState::BasicSettingsExchangeWaitResponse { .. }
+            | ClientConnectorState::ChannelConnection { .. } => Some(Self::McsConnect),
+            ClientConnectorState::SecureSettingsExchange { .. }
+            | ClientConnectorState::ConnectTimeAutoDetection { .. }
+            | ClientConnectorState::LicensingExchange { .. } => Some(Self::Licensing),
+            ClientConnectorState::MultitransportBootstrapping { .. }
+            | ClientConnectorState::CapabilitiesExchange { .. }
+            | ClientConnectorState::ConnectionFinalization { .. } => Some(Self::Finalization),
+            ClientConnectorState::Consumed
+            | ClientConnectorState::EnhancedSecurityUpgrade { .. }
+            | ClientConnectorState::Connected { .. }
+            | ClientConnectorState::Redirected { .. } => None,
+        }
+    }
+}
+
+/// Time budgets of the connection sequence
+///
+/// The budgets are enforced by the connection drivers (e.g.: the `ironrdp-async` connect helpers), which fail with
+/// [`ConnectorErrorKind::Timeout`](crate::ConnectorErrorKind::Timeout) when one of them is exceeded. `None` means
+/// unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectTimeouts {
+    /// Budget of the [`ConnectStage::Negotiation`] stage.
+    pub negotiation: Option<Duration>,
+    /// Budget of the [`ConnectStage::Credssp`] stage.
+    ///
+    /// This stage may include round trips to a KDC.
+    pub credssp: Option<Duration>,
+    /// Budget of the [`ConnectStage::McsConnect`] stage.
+    pub mcs_connect: Option<Duration>,
+    /// Budget of the [`ConnectStage::Licensing`] stage.
+    pub licensing: Option<Duration>,
+    /// Budget of the [`ConnectStage::Finalization`] stage.
+    pub finalization: Option<Duration>,
+    /// Deadline of the whole connection sequence, security upgrade included.
+    pub overall: Option<Duration>,
+}
+
+impl ConnectTimeouts {
+    /// No time budget at all.
+    pub const UNLIMITED: Self = Self {
+        negotiation: None,
+        credssp: None,
+        mcs_connect: None,
+        licensing: None,
+        finalization: None,
+        overall: None,
+    };
+
+    /// Returns the budget of `stage`.
+    pub fn get(&self, stage: ConnectStage) -> Option<Duration> {
+        match stage {
+            ConnectStage::Negotiation => self.negotiation,
+            ConnectStage::Credssp => self.credssp,
+            ConnectStage::McsConnect => self.mcs_connect,
+            ConnectStage::Licensing => self.licensing,
+            ConnectStage::Finalization => self.finalization,
+        }
+    }
+}
+
+impl Default for ConnectTimeouts {
+    /// Generous budgets, only meant to catch connections which are stuck.
+    fn default() -> Self {
+        Self {
+            negotiation: Some(Duration::from_secs(30)),
+            credssp: Some(Duration::from_secs(120)),
+            mcs_connect: Some(Duration::from_secs(30)),
+            licensing: Some(Duration::from_secs(60)),
+            finalization: Some(Duration::from_secs(60)),
+            overall: Some(Duration::from_secs(300)),
+        }
+    }
+}
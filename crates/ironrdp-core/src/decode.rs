@@ -3,8 +3,8 @@ use alloc::string::String;
 use core::fmt;
 
 use crate::{
-    InvalidFieldErr, NotEnoughBytesErr, OtherErr, ReadCursor, UnexpectedMessageTypeErr, UnsupportedValueErr,
-    UnsupportedVersionErr,
+    ErrorCategory, ErrorKindExt, InvalidFieldErr, NotEnoughBytesErr, OtherErr, ReadCursor, UnexpectedMessageTypeErr,
+    UnsupportedValueErr, UnsupportedVersionErr,
 };
 
 /// A result type for decoding operations, which can either succeed with a value of type `T`
@@ -97,6 +97,23 @@ impl fmt::Display for DecodeErrorKind {
     }
 }
 
+impl ErrorKindExt for DecodeErrorKind {
+    fn code(&self) -> u32 {
+        match self {
+            Self::NotEnoughBytes { .. } => 0x0002_0001,
+            Self::InvalidField { .. } => 0x0002_0002,
+            Self::UnexpectedMessageType { .. } => 0x0002_0003,
+            Self::UnsupportedVersion { .. } => 0x0002_0004,
+            Self::UnsupportedValue { .. } => 0x0002_0005,
+            Self::Other { .. } => 0x0002_0006,
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        ErrorCategory::Decoding
+    }
+}
+
 impl NotEnoughBytesErr for DecodeError {
     fn not_enough_bytes(context: &'static str, received: usize, expected: usize) -> Self {
         Self::new(context, DecodeErrorKind::NotEnoughBytes { received, expected })
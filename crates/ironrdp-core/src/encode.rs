@@ -7,8 +7,8 @@ use core::fmt;
 #[cfg(feature = "alloc")]
 use crate::WriteBuf;
 use crate::{
-    InvalidFieldErr, NotEnoughBytesErr, OtherErr, UnexpectedMessageTypeErr, UnsupportedValueErr, UnsupportedVersionErr,
-    WriteCursor,
+    ErrorCategory, ErrorKindExt, InvalidFieldErr, NotEnoughBytesErr, OtherErr, UnexpectedMessageTypeErr,
+    UnsupportedValueErr, UnsupportedVersionErr, WriteCursor,
 };
 
 /// A result type for encoding operations, which can either succeed with a value of type `T`
@@ -101,6 +101,23 @@ impl fmt::Display for EncodeErrorKind {
     }
 }
 
+impl ErrorKindExt for EncodeErrorKind {
+    fn code(&self) -> u32 {
+        match self {
+            Self::NotEnoughBytes { .. } => 0x0001_0001,
+            Self::InvalidField { .. } => 0x0001_0002,
+            Self::UnexpectedMessageType { .. } => 0x0001_0003,
+            Self::UnsupportedVersion { .. } => 0x0001_0004,
+            Self::UnsupportedValue { .. } => 0x0001_0005,
+            Self::Other { .. } => 0x0001_0006,
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        ErrorCategory::Encoding
+    }
+}
+
 impl NotEnoughBytesErr for EncodeError {
     fn not_enough_bytes(context: &'static str, received: usize, expected: usize) -> Self {
         Self::new(context, EncodeErrorKind::NotEnoughBytes { received, expected })
@@ -2,6 +2,7 @@
 use alloc::string::String;
 
 use ironrdp_error::{Error, Source};
+pub use ironrdp_error::{ErrorCategory, ErrorKindExt};
 
 /// Trait for adding a source to an error type.
 pub trait WithSource {
@@ -18,6 +18,8 @@ mod encode;
 mod error;
 mod into_owned;
 #[cfg(feature = "alloc")]
+mod pool;
+#[cfg(feature = "alloc")]
 mod write_buf;
 
 // Flat API hierarchy of common traits and types
@@ -29,4 +31,6 @@ pub use self::encode::*;
 pub use self::error::*;
 pub use self::into_owned::*;
 #[cfg(feature = "alloc")]
+pub use self::pool::*;
+#[cfg(feature = "alloc")]
 pub use self::write_buf::*;
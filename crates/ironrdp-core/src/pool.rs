@@ -0,0 +1,149 @@
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use crate::WriteBuf;
+
+/// Pool of [`WriteBuf`]s, reused across encoding operations to avoid allocating new buffers every time.
+///
+/// Buffers are handed out as [`PooledWriteBuf`] guards, which give the buffer back to the pool when dropped. Buffers
+/// are always handed out empty: the previous contents are wiped before a buffer is reused.
+///
+/// The total capacity of the idle buffers kept by the pool is bounded by the max retained capacity, so that the memory
+/// is released after a large transfer. Buffers which do not fit in this budget are simply deallocated.
+pub struct BufPool {
+    free: RefCell<Vec<Vec<u8>>>,
+    retained_capacity: Cell<usize>,
+    max_retained_capacity: usize,
+    created: Cell<usize>,
+}
+
+impl BufPool {
+    /// Max retained capacity used by [`BufPool::new`].
+    pub const DEFAULT_MAX_RETAINED_CAPACITY: usize = 64 * 1024; // 64 kib
+
+    /// Constructs a new, empty `BufPool` with the default max retained capacity.
+    pub const fn new() -> Self {
+        Self::with_max_retained_capacity(Self::DEFAULT_MAX_RETAINED_CAPACITY)
+    }
+
+    /// Constructs a new, empty `BufPool` keeping at most `max_retained_capacity` bytes in its idle buffers.
+    pub const fn with_max_retained_capacity(max_retained_capacity: usize) -> Self {
+        Self {
+            free: RefCell::new(Vec::new()),
+            retained_capacity: Cell::new(0),
+            max_retained_capacity,
+            created: Cell::new(0),
+        }
+    }
+
+    /// Returns the max capacity kept in the idle buffers of the pool.
+    pub fn max_retained_capacity(&self) -> usize {
+        self.max_retained_capacity
+    }
+
+    /// Returns the total capacity currently kept in the idle buffers of the pool.
+    pub fn retained_capacity(&self) -> usize {
+        self.retained_capacity.get()
+    }
+
+    /// Returns the number of idle buffers, ready to be reused.
+    pub fn idle_count(&self) -> usize {
+        self.free.borrow().len()
+    }
+
+    /// Returns the number of buffers created by the pool because no idle buffer was available.
+    pub fn created_count(&self) -> usize {
+        self.created.get()
+    }
+
+    /// Takes an empty buffer from the pool, or creates a new one if none is idle.
+    pub fn get(&self) -> PooledWriteBuf<'_> {
+        let buf = match self.free.borrow_mut().pop() {
+            Some(inner) => {
+                self.retained_capacity
+                    .set(self.retained_capacity.get() - inner.capacity());
+                WriteBuf::from_vec(inner)
+            }
+            None => {
+                self.created.set(self.created.get() + 1);
+                WriteBuf::new()
+            }
+        };
+
+        PooledWriteBuf { buf, pool: self }
+    }
+
+    fn give_back(&self, buf: WriteBuf) {
+        // Resetting the length of the inner vector ensures the previous contents are never exposed
+        // by the methods giving access to the initialized part of the buffer (e.g.: `unfilled_to`).
+        let mut inner = buf.into_inner();
+        inner.clear();
+
+        let retained_capacity = self.retained_capacity.get() + inner.capacity();
+
+        if inner.capacity() != 0 && retained_capacity <= self.max_retained_capacity {
+            self.retained_capacity.set(retained_capacity);
+            self.free.borrow_mut().push(inner);
+        }
+    }
+}
+
+impl Default for BufPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for BufPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufPool")
+            .field("idle_count", &self.idle_count())
+            .field("retained_capacity", &self.retained_capacity())
+            .field("max_retained_capacity", &self.max_retained_capacity)
+            .field("created_count", &self.created_count())
+            .finish()
+    }
+}
+
+/// [`WriteBuf`] borrowed from a [`BufPool`], given back to the pool on drop
+pub struct PooledWriteBuf<'a> {
+    buf: WriteBuf,
+    pool: &'a BufPool,
+}
+
+impl PooledWriteBuf<'_> {
+    /// Takes the buffer out of the pool for good.
+    pub fn detach(mut self) -> WriteBuf {
+        core::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledWriteBuf<'_> {
+    type Target = WriteBuf;
+
+    fn deref(&self) -> &WriteBuf {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledWriteBuf<'_> {
+    fn deref_mut(&mut self) -> &mut WriteBuf {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledWriteBuf<'_> {
+    fn drop(&mut self) {
+        self.pool.give_back(core::mem::take(&mut self.buf));
+    }
+}
+
+impl fmt::Debug for PooledWriteBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledWriteBuf")
+            .field("filled_len", &self.buf.filled_len())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,6 +1,9 @@
-use ironrdp_core::{impl_as_any, Decode, EncodeResult, ReadCursor};
-use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
-use ironrdp_pdu::{decode_err, PduResult};
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_core::{impl_as_any, BufPool, Decode, EncodeResult, ReadCursor};
+use ironrdp_dvc::{encode_dvc_messages_pooled, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
 use ironrdp_svc::{ChannelFlags, SvcMessage};
 use tracing::debug;
 
@@ -11,8 +14,10 @@ use crate::CHANNEL_NAME;
 pub struct DisplayControlClient {
     /// A callback that will be called when capabilities are received from the server.
     on_capabilities_received: OnCapabilitiesReceived,
-    /// Indicates whether the capabilities have been received from the server.
-    ready: bool,
+    /// The capabilities received from the server, if any.
+    capabilities: Option<DisplayControlCapabilities>,
+    /// Buffers reused to encode the monitor layouts.
+    buf_pool: BufPool,
 }
 
 impl DisplayControlClient {
@@ -27,12 +32,18 @@ impl DisplayControlClient {
     {
         Self {
             on_capabilities_received: Box::new(callback),
-            ready: false,
+            capabilities: None,
+            buf_pool: BufPool::new(),
         }
     }
 
     pub fn ready(&self) -> bool {
-        self.ready
+        self.capabilities.is_some()
+    }
+
+    /// Returns the capabilities received from the server, if any.
+    pub fn capabilities(&self) -> Option<&DisplayControlCapabilities> {
+        self.capabilities.as_ref()
     }
 
     /// Builds a [`DisplayControlPdu::MonitorLayout`] with a single primary monitor
@@ -47,6 +58,8 @@ impl DisplayControlClient {
     /// Use [`crate::pdu::MonitorLayoutEntry::adjust_display_size`] to adjust `width` and `height` before calling this function
     /// to ensure the display size is within the valid range.
     ///
+    /// The display size is shrunk to fit in the max monitor area advertised by the server, if needed.
+    ///
     /// [2.2.2.2.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedisp/ea2de591-9203-42cd-9908-be7a55237d1c
     pub fn encode_single_primary_monitor(
         &self,
@@ -56,11 +69,15 @@ impl DisplayControlClient {
         scale_factor: Option<u32>,
         physical_dims: Option<(u32, u32)>,
     ) -> EncodeResult<Vec<SvcMessage>> {
-        // TODO: prevent resolution with values greater than max monitor area received in caps.
+        let (width, height) = match &self.capabilities {
+            Some(capabilities) => capabilities.clamp_single_monitor_size(width, height),
+            None => (width, height),
+        };
+
         let pdu: DisplayControlPdu =
             DisplayControlMonitorLayout::new_single_primary_monitor(width, height, scale_factor, physical_dims)?.into();
         debug!(?pdu, "Sending monitor layout");
-        encode_dvc_messages(channel_id, vec![Box::new(pdu)], ChannelFlags::empty())
+        encode_dvc_messages_pooled(&self.buf_pool, channel_id, vec![Box::new(pdu)], ChannelFlags::empty())
     }
 }
 
@@ -76,13 +93,90 @@ impl DvcProcessor for DisplayControlClient {
     }
 
     fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
-        let caps = DisplayControlCapabilities::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        // The capabilities are prefixed with the `DISPLAYCONTROL_HEADER`, like any other PDU of this channel.
+        let DisplayControlPdu::Caps(caps) =
+            DisplayControlPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?
+        else {
+            return Err(pdu_other_err!("unexpected monitor layout PDU sent by the server"));
+        };
         debug!("Received {:?}", caps);
-        self.ready = true;
+        self.capabilities = Some(caps.clone());
         (self.on_capabilities_received)(caps)
     }
 }
 
 impl DvcClientProcessor for DisplayControlClient {}
 
+/// Monitor layout requested for the single primary monitor, see [`DisplayControlClient::encode_single_primary_monitor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorLayoutRequest {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: Option<u32>,
+    pub physical_dims: Option<(u32, u32)>,
+}
+
+/// Debounces the monitor layout requests, e.g.: while the user is resizing a window
+///
+/// Only the last request of a burst is sent, once no other request was made for the debounce delay. Sending a layout
+/// makes the server go through a deactivation-reactivation sequence, so a request identical to the last sent layout is
+/// dropped.
+#[derive(Debug, Clone)]
+pub struct ResizeDebouncer {
+    delay: Duration,
+    pending: Option<(Instant, MonitorLayoutRequest)>,
+    last_sent: Option<MonitorLayoutRequest>,
+}
+
+impl ResizeDebouncer {
+    pub const DEFAULT_DELAY: Duration = Duration::from_millis(300);
+
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: None,
+            last_sent: None,
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Records a layout requested at `now`, superseding the pending one and restarting the delay.
+    pub fn request(&mut self, now: Instant, request: MonitorLayoutRequest) {
+        self.pending = Some((now + self.delay, request));
+    }
+
+    /// Returns the instant at which the pending request is due, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(deadline, _)| deadline)
+    }
+
+    /// Returns the request to send, if the pending one is due at `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<MonitorLayoutRequest> {
+        let (deadline, request) = self.pending?;
+
+        if now < deadline {
+            return None;
+        }
+
+        self.pending = None;
+
+        if self.last_sent == Some(request) {
+            return None;
+        }
+
+        self.last_sent = Some(request);
+
+        Some(request)
+    }
+}
+
+impl Default for ResizeDebouncer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DELAY)
+    }
+}
+
 type OnCapabilitiesReceived = Box<dyn Fn(DisplayControlCapabilities) -> PduResult<Vec<DvcMessage>> + Send>;
@@ -6,6 +6,7 @@ use ironrdp_core::{
     ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
 use ironrdp_dvc::DvcEncode;
+use ironrdp_pdu_derive::PduEncode;
 use tracing::warn;
 
 const DISPLAYCONTROL_PDU_TYPE_CAPS: u32 = 0x00000005;
@@ -126,16 +127,18 @@ impl From<DisplayControlMonitorLayout> for DisplayControlPdu {
 ///     0 <= max_monitor_area_factor_b <= MAX_MONITOR_AREA_FACTOR
 ///
 /// [2.2.2.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedisp/8989a211-984e-4ecc-80f3-60694fc4b476
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode)]
+#[pdu(name = "DISPLAYCONTROL_CAPS_PDU")]
 pub struct DisplayControlCapabilities {
     max_num_monitors: u32,
     max_monitor_area_factor_a: u32,
     max_monitor_area_factor_b: u32,
+    /// Computed from the other fields, and validated when decoding.
+    #[pdu(skip)]
     max_monitor_area: u64,
 }
 
 impl DisplayControlCapabilities {
-    const NAME: &'static str = "DISPLAYCONTROL_CAPS_PDU";
     const FIXED_PART_SIZE: usize = 4 /* MaxNumMonitors */
         + 4 /* MaxMonitorAreaFactorA */
         + 4 /* MaxMonitorAreaFactorB */;
@@ -156,27 +159,45 @@ impl DisplayControlCapabilities {
         })
     }
 
+    pub fn max_num_monitors(&self) -> u32 {
+        self.max_num_monitors
+    }
+
     pub fn max_monitor_area(&self) -> u64 {
         self.max_monitor_area
     }
-}
 
-impl Encode for DisplayControlCapabilities {
-    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
-        ensure_fixed_part_size!(in: dst);
-        dst.write_u32(self.max_num_monitors);
-        dst.write_u32(self.max_monitor_area_factor_a);
-        dst.write_u32(self.max_monitor_area_factor_b);
+    /// Shrinks `width` and `height`, keeping the aspect ratio, for a single monitor of this size to fit in
+    /// [`Self::max_monitor_area`].
+    ///
+    /// The returned width is never an odd value. The display size is returned as is if it already fits.
+    // The products of two `u32` values do not overflow a `u64`, and the search stays within `0..=width`.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn clamp_single_monitor_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if u64::from(width) * u64::from(height) <= self.max_monitor_area || width == 0 {
+            return (width, height);
+        }
 
-        Ok(())
-    }
+        // The result is bounded by `height`, as the candidate width is bounded by `width`.
+        let height_for = |candidate: u32| {
+            u32::try_from(u64::from(candidate) * u64::from(height) / u64::from(width)).unwrap_or(height)
+        };
 
-    fn name(&self) -> &'static str {
-        Self::NAME
-    }
+        // Largest width (the height following the aspect ratio) fitting in the area.
+        let (mut low, mut high) = (0, width);
+        while low < high {
+            let candidate = low + (high - low).div_ceil(2);
 
-    fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE
+            if u64::from(candidate) * u64::from(height_for(candidate)) <= self.max_monitor_area {
+                low = candidate;
+            } else {
+                high = candidate - 1;
+            }
+        }
+
+        let width = low - low % 2;
+
+        (width, height_for(width))
     }
 }
 
@@ -1,10 +1,11 @@
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::any::TypeId;
 use core::fmt;
 
-use ironrdp_core::{impl_as_any, Decode as _, DecodeResult, ReadCursor};
-use ironrdp_pdu::{self as pdu, decode_err, encode_err, pdu_other_err};
-use ironrdp_svc::{ChannelFlags, CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
+use ironrdp_core::{impl_as_any, BufPool, Decode as _, DecodeResult, ReadCursor};
+use ironrdp_pdu::{self as pdu, decode_err, encode_err};
+use ironrdp_svc::{ChannelFlags, ChannelStats, CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
 use pdu::gcc::ChannelName;
 use pdu::PduResult;
 
@@ -12,10 +13,13 @@ use crate::pdu::{
     CapabilitiesResponsePdu, CapsVersion, ClosePdu, CreateResponsePdu, CreationStatus, DrdynvcClientPdu,
     DrdynvcServerPdu,
 };
-use crate::{encode_dvc_messages, DvcProcessor, DynamicChannelSet, DynamicVirtualChannel};
+use crate::{encode_dvc_messages_pooled, DvcProcessor, DynamicChannelSet, DynamicVirtualChannel};
 
 pub trait DvcClientProcessor: DvcProcessor {}
 
+/// Creates the listener of a dynamic channel on demand, see [`DrdynvcClient::with_fallback_factory`]
+pub type DvcFallbackFactory = Box<dyn Fn(&str) -> Option<Box<dyn DvcProcessor>> + Send>;
+
 /// DRDYNVC Static Virtual Channel (the Remote Desktop Protocol: Dynamic Virtual Channel Extension)
 ///
 /// It adds support for dynamic virtual channels (DVC).
@@ -23,6 +27,21 @@ pub struct DrdynvcClient {
     dynamic_channels: DynamicChannelSet,
     /// Indicates whether the capability request/response handshake has been completed.
     cap_handshake_done: bool,
+    /// Buffers reused to encode the messages of the dynamic channels.
+    buf_pool: BufPool,
+    diagnostics: DrdynvcDiagnostics,
+    fallback_factory: Option<DvcFallbackFactory>,
+}
+
+/// Counters of the anomalies handled by the [`DrdynvcClient`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrdynvcDiagnostics {
+    /// Number of data PDUs dropped because they were received for a recently closed channel.
+    pub closed_channel_data: u64,
+    /// Number of data PDUs received for a channel that was never opened, each answered with a Close PDU.
+    pub unknown_channel_data: u64,
+    /// Number of Create Request PDUs for a channel without listener, each refused with a failure status.
+    pub unknown_channel_creations: u64,
 }
 
 impl fmt::Debug for DrdynvcClient {
@@ -47,11 +66,12 @@ impl DrdynvcClient {
         Self {
             dynamic_channels: DynamicChannelSet::new(),
             cap_handshake_done: false,
+            buf_pool: BufPool::new(),
+            diagnostics: DrdynvcDiagnostics::default(),
+            fallback_factory: None,
         }
     }
 
-    // FIXME(#61): it’s likely we want to enable adding dynamic channels at any point during the session (message passing? other approach?)
-
     #[must_use]
     pub fn with_dynamic_channel<T>(mut self, channel: T) -> Self
     where
@@ -61,6 +81,45 @@ impl DrdynvcClient {
         self
     }
 
+    /// Sets the factory consulted when the server requests to create a channel without registered listener.
+    ///
+    /// This allows to instantiate lazily the listeners of dynamically named channels. The listener returned for a
+    /// channel name is registered like with [`Self::attach_dynamic_channel`], so the factory is only consulted again
+    /// for this name if it returned `None`. The channel name of the listener must be the requested one.
+    #[must_use]
+    pub fn with_fallback_factory<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(&str) -> Option<Box<dyn DvcProcessor>> + Send + 'static,
+    {
+        self.fallback_factory = Some(Box::new(factory));
+        self
+    }
+
+    /// Registers a listener for the dynamic channel of `channel`, at any point during the session.
+    ///
+    /// The server requests to create this channel are accepted from now on. A listener already registered under the
+    /// same name is replaced.
+    pub fn attach_dynamic_channel<T>(&mut self, channel: T)
+    where
+        T: DvcProcessor + 'static,
+    {
+        if let Some(replaced) = self.dynamic_channels.insert(channel) {
+            debug!(channel_name = replaced.channel_name(), "Replaced a DVC listener");
+        }
+    }
+
+    /// Unregisters the listener of the dynamic channel named `channel_name`.
+    ///
+    /// The server requests to create this channel are refused from now on. If the channel is opened, the returned
+    /// Close Request PDU must be sent to the server.
+    pub fn detach_dynamic_channel(&mut self, channel_name: &str) -> Option<SvcMessage> {
+        let channel_id = self.dynamic_channels.remove_by_channel_name(channel_name)?;
+
+        let close_request = DrdynvcClientPdu::Close(ClosePdu::new(channel_id));
+        debug!("Send DVC Close Request PDU: {close_request:?}");
+        Some(SvcMessage::from(close_request))
+    }
+
     pub fn get_dvc_by_type_id<T>(&self) -> Option<&DynamicVirtualChannel>
     where
         T: DvcProcessor,
@@ -68,6 +127,42 @@ impl DrdynvcClient {
         self.dynamic_channels.get_by_type_id(TypeId::of::<T>())
     }
 
+    pub fn get_dvc_by_type_id_mut<T>(&mut self) -> Option<&mut DynamicVirtualChannel>
+    where
+        T: DvcProcessor,
+    {
+        self.dynamic_channels.get_by_type_id_mut(TypeId::of::<T>())
+    }
+
+    pub fn diagnostics(&self) -> DrdynvcDiagnostics {
+        self.diagnostics
+    }
+
+    /// Returns the name and the traffic of each dynamic channel, opened or not.
+    pub fn channel_stats(&self) -> impl Iterator<Item = (&str, ChannelStats)> {
+        self.dynamic_channels
+            .values()
+            .map(|channel| (channel.channel_name(), channel.stats()))
+    }
+
+    /// Registers the listener created by the fallback factory for `channel_name`, if any.
+    fn create_fallback_listener(&mut self, channel_name: &str) {
+        let Some(channel) = self.fallback_factory.as_ref().and_then(|factory| factory(channel_name)) else {
+            return;
+        };
+
+        if channel.channel_name() == channel_name {
+            debug!(channel_name, "Created a DVC listener with the fallback factory");
+            self.dynamic_channels.insert_boxed(channel);
+        } else {
+            warn!(
+                channel_name,
+                listener_channel_name = channel.channel_name(),
+                "The fallback factory created a listener for another DVC"
+            );
+        }
+    }
+
     fn create_capabilities_response(&mut self) -> SvcMessage {
         let caps_response = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(CapsVersion::V1));
         debug!("Send DVC Capabilities Response PDU: {caps_response:?}");
@@ -115,16 +210,35 @@ impl SvcProcessor for DrdynvcClient {
                     responses.push(self.create_capabilities_response());
                 }
 
-                let channel_exists = self.dynamic_channels.get_by_channel_name(&channel_name).is_some();
-                let (creation_status, start_messages) = if channel_exists {
+                if self.dynamic_channels.get_by_channel_name_mut(&channel_name).is_none() {
+                    self.create_fallback_listener(&channel_name);
+                }
+
+                let creation_status = match self.dynamic_channels.get_by_channel_name_mut(&channel_name) {
+                    Some(dynamic_channel) => {
+                        let creation_status = dynamic_channel.accept(channel_id);
+                        if !creation_status.is_success() {
+                            warn!(%channel_name, channel_id, ?creation_status, "DVC creation refused");
+                        }
+                        creation_status
+                    }
+                    None => {
+                        // The server waits for a response, even for the channels the client doesn't know about.
+                        debug!(%channel_name, channel_id, "No listener for the requested DVC");
+                        self.diagnostics.unknown_channel_creations += 1;
+                        CreationStatus::NO_LISTENER
+                    }
+                };
+
+                let start_messages = if creation_status.is_success() {
                     // If we have a handler for this channel, attach the channel ID
                     // and get any start messages.
                     self.dynamic_channels
                         .attach_channel_id(channel_name.clone(), channel_id);
                     let dynamic_channel = self.dynamic_channels.get_by_channel_name_mut(&channel_name).unwrap();
-                    (CreationStatus::OK, dynamic_channel.start()?)
+                    dynamic_channel.start()?
                 } else {
-                    (CreationStatus::NO_LISTENER, Vec::new())
+                    Vec::new()
                 };
 
                 let create_response = DrdynvcClientPdu::Create(CreateResponsePdu::new(channel_id, creation_status));
@@ -133,10 +247,14 @@ impl SvcProcessor for DrdynvcClient {
 
                 // If this DVC has start messages, send them.
                 if !start_messages.is_empty() {
-                    responses.extend(
-                        encode_dvc_messages(channel_id, start_messages, ChannelFlags::empty())
-                            .map_err(|e| encode_err!(e))?,
-                    );
+                    let start_messages =
+                        encode_dvc_messages_pooled(&self.buf_pool, channel_id, start_messages, ChannelFlags::empty())
+                            .map_err(|e| encode_err!(e))?;
+
+                    if let Some(dynamic_channel) = self.dynamic_channels.get_by_channel_name_mut(&channel_name) {
+                        dynamic_channel.record_sent(&start_messages);
+                    }
+                    responses.extend(start_messages);
                 }
             }
             DrdynvcServerPdu::Close(close_request) => {
@@ -151,15 +269,31 @@ impl SvcProcessor for DrdynvcClient {
             DrdynvcServerPdu::Data(data) => {
                 let channel_id = data.channel_id();
 
-                let messages = self
-                    .dynamic_channels
-                    .get_by_channel_id_mut(&channel_id)
-                    .ok_or_else(|| pdu_other_err!("access to non existing DVC channel"))?
-                    .process(data)?;
+                let Some(dynamic_channel) = self.dynamic_channels.get_by_channel_id_mut(&channel_id) else {
+                    if self.dynamic_channels.is_recently_closed(&channel_id) {
+                        trace!(channel_id, "Dropped data received for a closed DVC");
+                        self.diagnostics.closed_channel_data += 1;
+                    } else {
+                        // Closing the channel stops the server from sending more data on it. The data received
+                        // meanwhile is dropped like for any closed channel.
+                        warn!(channel_id, "Received data for an unknown DVC, closing it");
+                        self.diagnostics.unknown_channel_data += 1;
+                        self.dynamic_channels.mark_as_closed(channel_id);
+
+                        let close_request = DrdynvcClientPdu::Close(ClosePdu::new(channel_id));
+                        debug!("Send DVC Close Request PDU: {close_request:?}");
+                        responses.push(SvcMessage::from(close_request));
+                    }
+
+                    return Ok(responses);
+                };
+
+                let messages = dynamic_channel.process(data)?;
+                let messages = encode_dvc_messages_pooled(&self.buf_pool, channel_id, messages, ChannelFlags::empty())
+                    .map_err(|e| encode_err!(e))?;
 
-                responses.extend(
-                    encode_dvc_messages(channel_id, messages, ChannelFlags::empty()).map_err(|e| encode_err!(e))?,
-                );
+                dynamic_channel.record_sent(&messages);
+                responses.extend(messages);
             }
         }
 
@@ -1,80 +1,163 @@
 use alloc::vec::Vec;
-use core::cmp;
+use core::fmt;
 
-use ironrdp_core::{cast_length, invalid_field_err, DecodeResult};
+use ironrdp_core::{cast_length, invalid_field_err, DecodeError, DecodeResult};
 
 use crate::pdu::{DataFirstPdu, DataPdu, DrdynvcDataPdu};
+use crate::DynamicChannelId;
 
+/// Violation of the DVC data fragmentation, detected when reassembling the messages of a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReassemblyError {
+    pub channel_id: DynamicChannelId,
+    pub kind: ReassemblyErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReassemblyErrorKind {
+    /// The data received exceeds the total length announced by the DVC Data First PDU.
+    Overflow { total_length: usize, received: usize },
+    /// A DVC Data First PDU was received before the previous message was complete.
+    UnexpectedDataFirst,
+    /// A DVC Data PDU was received after a violation, without a DVC Data First PDU announcing the message length.
+    MissingDataFirst,
+}
+
+impl fmt::Display for ReassemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DVC {}: ", self.channel_id)?;
+
+        match self.kind {
+            ReassemblyErrorKind::Overflow { total_length, received } => {
+                write!(f, "received {received} bytes for a message of {total_length} bytes")
+            }
+            ReassemblyErrorKind::UnexpectedDataFirst => write!(f, "DataFirst PDU received before the message end"),
+            ReassemblyErrorKind::MissingDataFirst => write!(f, "Data PDU received without a DataFirst PDU"),
+        }
+    }
+}
+
+impl core::error::Error for ReassemblyError {}
+
+impl ReassemblyError {
+    fn into_decode_error(self) -> DecodeError {
+        let error: DecodeError = match self.kind {
+            ReassemblyErrorKind::Overflow { .. } => {
+                invalid_field_err!("DVC message", "data", "exceeds the total length")
+            }
+            ReassemblyErrorKind::UnexpectedDataFirst => {
+                invalid_field_err!("DVC message", "DataFirstPdu", "previous message is incomplete")
+            }
+            ReassemblyErrorKind::MissingDataFirst => {
+                invalid_field_err!("DVC message", "DataPdu", "missing DataFirst PDU")
+            }
+        };
+
+        error.with_source(self)
+    }
+}
+
+/// Message being reassembled.
 #[derive(Debug, PartialEq)]
-pub(crate) struct CompleteData {
-    total_size: usize,
+struct Fragmented {
+    /// Total length announced by the DVC Data First PDU.
+    total_length: usize,
     data: Vec<u8>,
 }
 
+#[derive(Debug, PartialEq)]
+pub(crate) struct CompleteData {
+    fragmented: Option<Fragmented>,
+    /// A violation was detected, and the following DVC Data PDUs may be the fragments of the broken message.
+    ///
+    /// Cleared by the next DVC Data First PDU.
+    desynchronized: bool,
+}
+
 impl CompleteData {
     pub(crate) fn new() -> Self {
         Self {
-            total_size: 0,
-            data: Vec::new(),
+            fragmented: None,
+            desynchronized: false,
         }
     }
 
-    pub(crate) fn process_data(&mut self, pdu: DrdynvcDataPdu) -> DecodeResult<Option<Vec<u8>>> {
-        match pdu {
-            DrdynvcDataPdu::DataFirst(data_first) => self.process_data_first_pdu(data_first),
+    /// Returns the message completed by `pdu`, if any.
+    ///
+    /// The length announced by a DVC Data First PDU is reserved up front, up to `reserve_limit` bytes.
+    pub(crate) fn process_data(&mut self, pdu: DrdynvcDataPdu, reserve_limit: usize) -> DecodeResult<Option<Vec<u8>>> {
+        let channel_id = pdu.channel_id();
+
+        let result = match pdu {
+            DrdynvcDataPdu::DataFirst(data_first) => {
+                let total_length = cast_length!("DataFirstPdu::length", data_first.length)?;
+                self.process_data_first_pdu(data_first, total_length, reserve_limit)
+            }
             DrdynvcDataPdu::Data(data) => self.process_data_pdu(data),
-        }
-    }
+        };
+
+        result.map_err(|kind| {
+            self.fragmented = None;
+            self.desynchronized = true;
 
-    fn process_data_first_pdu(&mut self, data_first: DataFirstPdu) -> DecodeResult<Option<Vec<u8>>> {
-        let total_data_size: DecodeResult<_> = cast_length!("DataFirstPdu::length", data_first.length);
-        let total_data_size = total_data_size?;
-        if self.total_size != 0 || !self.data.is_empty() {
-            error!("Incomplete DVC message, it will be skipped");
+            ReassemblyError { channel_id, kind }.into_decode_error()
+        })
+    }
 
-            self.data.clear();
+    fn process_data_first_pdu(
+        &mut self,
+        data_first: DataFirstPdu,
+        total_length: usize,
+        reserve_limit: usize,
+    ) -> Result<Option<Vec<u8>>, ReassemblyErrorKind> {
+        if self.fragmented.is_some() {
+            return Err(ReassemblyErrorKind::UnexpectedDataFirst);
         }
 
-        if total_data_size == data_first.data.len() {
-            Ok(Some(data_first.data))
-        } else {
-            self.total_size = total_data_size;
-            self.data = data_first.data;
+        self.desynchronized = false;
 
-            Ok(None)
+        let received = data_first.data.len();
+
+        if received > total_length {
+            return Err(ReassemblyErrorKind::Overflow { total_length, received });
+        }
+
+        if received == total_length {
+            return Ok(Some(data_first.data));
         }
+
+        // The announced length is not trusted blindly, a larger message growing as its fragments are received.
+        let mut data = Vec::with_capacity(total_length.min(reserve_limit));
+        data.extend_from_slice(&data_first.data);
+
+        self.fragmented = Some(Fragmented { total_length, data });
+
+        Ok(None)
     }
 
-    fn process_data_pdu(&mut self, mut data: DataPdu) -> DecodeResult<Option<Vec<u8>>> {
-        if self.total_size == 0 && self.data.is_empty() {
-            // message is not fragmented
+    fn process_data_pdu(&mut self, data: DataPdu) -> Result<Option<Vec<u8>>, ReassemblyErrorKind> {
+        let Some(fragmented) = self.fragmented.as_mut() else {
+            if self.desynchronized {
+                return Err(ReassemblyErrorKind::MissingDataFirst);
+            }
+
+            // The message is not fragmented.
             return Ok(Some(data.data));
+        };
+
+        let total_length = fragmented.total_length;
+        let received = fragmented.data.len().saturating_add(data.data.len());
+
+        if received > total_length {
+            return Err(ReassemblyErrorKind::Overflow { total_length, received });
         }
 
-        // The message is fragmented and needs to be reassembled.
-        match self.data.len().checked_add(data.data.len()) {
-            Some(actual_data_length) => {
-                match actual_data_length.cmp(&(self.total_size)) {
-                    cmp::Ordering::Less => {
-                        // this is one of the fragmented messages, just append it
-                        self.data.append(&mut data.data);
-                        Ok(None)
-                    }
-                    cmp::Ordering::Equal => {
-                        // this is the last fragmented message, need to return the whole reassembled message
-                        self.total_size = 0;
-                        self.data.append(&mut data.data);
-                        Ok(Some(self.data.drain(..).collect()))
-                    }
-                    cmp::Ordering::Greater => {
-                        error!("Actual DVC message size is grater than expected total DVC message size");
-                        self.total_size = 0;
-                        self.data.clear();
-                        Ok(None)
-                    }
-                }
-            }
-            _ => Err(invalid_field_err!("DVC message", "data", "overflow occurred")),
+        fragmented.data.extend_from_slice(&data.data);
+
+        if received == total_length {
+            Ok(self.fragmented.take().map(|fragmented| fragmented.data))
+        } else {
+            Ok(None)
         }
     }
 }
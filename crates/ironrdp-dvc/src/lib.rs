@@ -8,23 +8,25 @@ extern crate tracing;
 extern crate alloc;
 
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::any::TypeId;
+use core::cell::Cell;
 
-use pdu::DrdynvcDataPdu;
+use pdu::{CreationStatus, DrdynvcDataPdu};
 
 use crate::alloc::borrow::ToOwned;
 // Re-export ironrdp_pdu crate for convenience
 #[rustfmt::skip] // do not re-order this pub use
 pub use ironrdp_pdu;
-use ironrdp_core::{assert_obj_safe, cast_length, encode_vec, other_err, AsAny, Encode, EncodeResult};
+use ironrdp_core::{assert_obj_safe, cast_length, encode_buf, other_err, AsAny, BufPool, Encode, EncodeResult};
 use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
-use ironrdp_svc::{self, SvcMessage};
+use ironrdp_svc::{self, ChannelStats, SvcMessage};
 
 mod complete_data;
 use complete_data::CompleteData;
+pub use complete_data::{ReassemblyError, ReassemblyErrorKind};
 
 mod client;
 pub use client::*;
@@ -32,6 +34,9 @@ pub use client::*;
 mod server;
 pub use server::*;
 
+mod request_tracker;
+pub use request_tracker::{Completion, DvcProcessOutput, DvcRequestTracker, RequestId};
+
 pub mod pdu;
 
 /// Represents a message that, when encoded, forms a complete PDU for a given dynamic virtual channel.
@@ -50,28 +55,59 @@ pub trait DvcProcessor: AsAny + Send {
     /// The name of the channel, e.g. "Microsoft::Windows::RDS::DisplayControl"
     fn channel_name(&self) -> &str;
 
+    /// Called when the server requests the creation of the channel, before [`DvcProcessor::start`].
+    ///
+    /// Returning a failure status (see [`CreationStatus::is_success`]) refuses the channel: the status is sent back
+    /// to the server in the DVC Create Response PDU, and the channel is not opened.
+    fn accept(&mut self, _channel_id: u32) -> CreationStatus {
+        CreationStatus::OK
+    }
+
     /// Returns any messages that should be sent immediately
     /// upon the channel being created.
     fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>>;
 
     fn process(&mut self, channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>>;
 
+    /// Returns the maximum capacity reserved for a fragmented message, when its total length is announced.
+    ///
+    /// A larger message is still reassembled, its buffer growing as the fragments are received. Defaults to
+    /// [`DEFAULT_REASSEMBLY_RESERVE_LIMIT`].
+    fn reassembly_reserve_limit(&self) -> usize {
+        DEFAULT_REASSEMBLY_RESERVE_LIMIT
+    }
+
     fn close(&mut self, _channel_id: u32) {}
 }
 
+/// Default maximum capacity reserved for a fragmented message, see [`DvcProcessor::reassembly_reserve_limit`].
+pub const DEFAULT_REASSEMBLY_RESERVE_LIMIT: usize = 8 * 1024 * 1024; // 8 MiB
+
 assert_obj_safe!(DvcProcessor);
 
 pub fn encode_dvc_messages(
     channel_id: u32,
     messages: Vec<DvcMessage>,
     flags: ironrdp_svc::ChannelFlags,
+) -> EncodeResult<Vec<SvcMessage>> {
+    encode_dvc_messages_pooled(&BufPool::new(), channel_id, messages, flags)
+}
+
+/// Same as [`encode_dvc_messages`], but the messages are encoded in buffers drawn from `pool`.
+pub fn encode_dvc_messages_pooled(
+    pool: &BufPool,
+    channel_id: u32,
+    messages: Vec<DvcMessage>,
+    flags: ironrdp_svc::ChannelFlags,
 ) -> EncodeResult<Vec<SvcMessage>> {
     let mut res = Vec::new();
     for msg in messages {
         let total_length = msg.size();
         let needs_splitting = total_length >= DrdynvcDataPdu::MAX_DATA_SIZE;
 
-        let msg = encode_vec(msg.as_ref())?;
+        let mut encoded = pool.get();
+        encode_buf(msg.as_ref(), &mut encoded)?;
+        let msg = encoded.filled();
         let mut off = 0;
 
         while off < total_length {
@@ -109,14 +145,17 @@ pub struct DynamicVirtualChannel {
     ///
     /// This field is `None` until the server assigns a channel ID.
     channel_id: Option<DynamicChannelId>,
+    /// Updated when the messages are encoded by the caller, which only borrows the channel.
+    stats: Cell<ChannelStats>,
 }
 
 impl DynamicVirtualChannel {
-    fn new<T: DvcProcessor + 'static>(handler: T) -> Self {
+    fn new(channel_processor: Box<dyn DvcProcessor>) -> Self {
         Self {
-            channel_processor: Box::new(handler),
+            channel_processor,
             complete_data: CompleteData::new(),
             channel_id: None,
+            stats: Cell::new(ChannelStats::default()),
         }
     }
 
@@ -128,10 +167,31 @@ impl DynamicVirtualChannel {
         self.channel_id
     }
 
+    /// Returns the traffic of the channel since it was first opened.
+    pub fn stats(&self) -> ChannelStats {
+        self.stats.get()
+    }
+
+    /// Records the DVC Data PDUs of this channel encoded outside of the [`DrdynvcClient`], e.g. by
+    /// [`encode_dvc_messages`].
+    pub fn record_sent(&self, messages: &[SvcMessage]) {
+        let mut stats = self.stats.get();
+        messages.iter().for_each(|message| stats.record_sent(message.size()));
+        self.stats.set(stats);
+    }
+
     pub fn channel_processor_downcast_ref<T: DvcProcessor>(&self) -> Option<&T> {
         self.channel_processor.as_any().downcast_ref()
     }
 
+    pub fn channel_processor_downcast_mut<T: DvcProcessor>(&mut self) -> Option<&mut T> {
+        self.channel_processor.as_any_mut().downcast_mut()
+    }
+
+    fn accept(&mut self, channel_id: DynamicChannelId) -> CreationStatus {
+        self.channel_processor.accept(channel_id)
+    }
+
     fn start(&mut self) -> PduResult<Vec<DvcMessage>> {
         if let Some(channel_id) = self.channel_id {
             self.channel_processor.start(channel_id)
@@ -142,7 +202,16 @@ impl DynamicVirtualChannel {
 
     fn process(&mut self, pdu: DrdynvcDataPdu) -> PduResult<Vec<DvcMessage>> {
         let channel_id = pdu.channel_id();
-        let complete_data = self.complete_data.process_data(pdu).map_err(|e| decode_err!(e))?;
+
+        let mut stats = self.stats.get();
+        stats.record_received(pdu.size());
+        self.stats.set(stats);
+
+        let reserve_limit = self.channel_processor.reassembly_reserve_limit();
+        let complete_data = self
+            .complete_data
+            .process_data(pdu, reserve_limit)
+            .map_err(|e| decode_err!(e))?;
         if let Some(complete_data) = complete_data {
             self.channel_processor.process(channel_id, &complete_data)
         } else {
@@ -150,6 +219,10 @@ impl DynamicVirtualChannel {
         }
     }
 
+    fn close(&mut self, channel_id: DynamicChannelId) {
+        self.channel_processor.close(channel_id);
+    }
+
     fn channel_name(&self) -> &str {
         self.channel_processor.channel_name()
     }
@@ -160,9 +233,16 @@ struct DynamicChannelSet {
     name_to_channel_id: BTreeMap<DynamicChannelName, DynamicChannelId>,
     channel_id_to_name: BTreeMap<DynamicChannelId, DynamicChannelName>,
     type_id_to_name: BTreeMap<TypeId, DynamicChannelName>,
+    /// IDs of the last closed channels, the most recently closed last.
+    ///
+    /// The server may still send data on a channel it just closed, which is expected and not worth reacting to.
+    recently_closed: VecDeque<DynamicChannelId>,
 }
 
 impl DynamicChannelSet {
+    /// Number of closed channel IDs remembered.
+    const RECENTLY_CLOSED_CAPACITY: usize = 32;
+
     #[inline]
     fn new() -> Self {
         Self {
@@ -170,16 +250,23 @@ impl DynamicChannelSet {
             name_to_channel_id: BTreeMap::new(),
             channel_id_to_name: BTreeMap::new(),
             type_id_to_name: BTreeMap::new(),
+            recently_closed: VecDeque::new(),
         }
     }
 
     fn insert<T: DvcProcessor + 'static>(&mut self, channel: T) -> Option<DynamicVirtualChannel> {
+        self.insert_boxed(Box::new(channel))
+    }
+
+    fn insert_boxed(&mut self, channel: Box<dyn DvcProcessor>) -> Option<DynamicVirtualChannel> {
         let name = channel.channel_name().to_owned();
-        self.type_id_to_name.insert(TypeId::of::<T>(), name.clone());
+        self.type_id_to_name
+            .insert(channel.as_ref().as_any().type_id(), name.clone());
         self.channels.insert(name, DynamicVirtualChannel::new(channel))
     }
 
     fn attach_channel_id(&mut self, name: DynamicChannelName, id: DynamicChannelId) -> Option<DynamicChannelId> {
+        self.recently_closed.retain(|closed_id| *closed_id != id);
         self.channel_id_to_name.insert(id, name.clone());
         self.name_to_channel_id.insert(name.clone(), id);
         let dvc = self.get_by_channel_name_mut(&name)?;
@@ -194,8 +281,10 @@ impl DynamicChannelSet {
             .and_then(|name| self.channels.get(name))
     }
 
-    fn get_by_channel_name(&self, name: &DynamicChannelName) -> Option<&DynamicVirtualChannel> {
-        self.channels.get(name)
+    fn get_by_type_id_mut(&mut self, type_id: TypeId) -> Option<&mut DynamicVirtualChannel> {
+        self.type_id_to_name
+            .get(&type_id)
+            .and_then(|name| self.channels.get_mut(name))
     }
 
     fn get_by_channel_name_mut(&mut self, name: &DynamicChannelName) -> Option<&mut DynamicVirtualChannel> {
@@ -210,6 +299,10 @@ impl DynamicChannelSet {
 
     fn remove_by_channel_id(&mut self, id: &DynamicChannelId) -> Option<DynamicChannelId> {
         if let Some(name) = self.channel_id_to_name.remove(id) {
+            self.mark_as_closed(*id);
+            if let Some(channel) = self.channels.get_mut(&name) {
+                channel.close(*id);
+            }
             return self.name_to_channel_id.remove(&name);
             // Channels are retained in the `self.channels` and `self.type_id_to_name` map to allow potential
             // dynamic re-addition by the server.
@@ -217,6 +310,30 @@ impl DynamicChannelSet {
         None
     }
 
+    /// Removes the channel named `name`, returning its ID if it was opened.
+    fn remove_by_channel_name(&mut self, name: &str) -> Option<DynamicChannelId> {
+        let mut channel = self.channels.remove(name)?;
+        self.type_id_to_name.retain(|_, channel_name| channel_name != name);
+        let id = self.name_to_channel_id.remove(name)?;
+        self.channel_id_to_name.remove(&id);
+        self.mark_as_closed(id);
+        channel.close(id);
+        Some(id)
+    }
+
+    /// Remembers `id` as recently closed, forgetting the oldest closed ID when at capacity.
+    fn mark_as_closed(&mut self, id: DynamicChannelId) {
+        self.recently_closed.retain(|closed_id| *closed_id != id);
+        if self.recently_closed.len() == Self::RECENTLY_CLOSED_CAPACITY {
+            self.recently_closed.pop_front();
+        }
+        self.recently_closed.push_back(id);
+    }
+
+    fn is_recently_closed(&self, id: &DynamicChannelId) -> bool {
+        self.recently_closed.contains(id)
+    }
+
     #[inline]
     fn values(&self) -> impl Iterator<Item = &DynamicVirtualChannel> {
         self.channels.values()
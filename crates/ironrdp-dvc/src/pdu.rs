@@ -61,6 +61,7 @@ pub enum DrdynvcClientPdu {
     Create(CreateResponsePdu),
     Close(ClosePdu),
     Data(DrdynvcDataPdu),
+    CompressedData(CompressedDataPdu),
 }
 
 impl Encode for DrdynvcClientPdu {
@@ -70,6 +71,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(pdu) => pdu.encode(dst),
             DrdynvcClientPdu::Data(pdu) => pdu.encode(dst),
             DrdynvcClientPdu::Close(pdu) => pdu.encode(dst),
+            DrdynvcClientPdu::CompressedData(pdu) => pdu.encode(dst),
         }
     }
 
@@ -79,6 +81,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(_) => CreateResponsePdu::name(),
             DrdynvcClientPdu::Data(pdu) => pdu.name(),
             DrdynvcClientPdu::Close(_) => ClosePdu::name(),
+            DrdynvcClientPdu::CompressedData(pdu) => pdu.name(),
         }
     }
 
@@ -88,6 +91,7 @@ impl Encode for DrdynvcClientPdu {
             DrdynvcClientPdu::Create(pdu) => pdu.size(),
             DrdynvcClientPdu::Data(pdu) => pdu.size(),
             DrdynvcClientPdu::Close(pdu) => pdu.size(),
+            DrdynvcClientPdu::CompressedData(pdu) => pdu.size(),
         }
     }
 }
@@ -103,6 +107,9 @@ impl Decode<'_> for DrdynvcClientPdu {
             Cmd::Data => Ok(Self::Data(DrdynvcDataPdu::Data(DataPdu::decode(header, src)?))),
             Cmd::Close => Ok(Self::Close(ClosePdu::decode(header, src)?)),
             Cmd::Capability => Ok(Self::Capabilities(CapabilitiesResponsePdu::decode(header, src)?)),
+            Cmd::DataFirstCompressed | Cmd::DataCompressed => {
+                Ok(Self::CompressedData(CompressedDataPdu::decode(header, src)?))
+            }
             _ => Err(unsupported_value_err!("Cmd", header.cmd.into())),
         }
     }
@@ -361,6 +368,113 @@ impl DataFirstPdu {
     }
 }
 
+/// DVC Data First Compressed PDU (DYNVC_DATA_FIRST_COMPRESSED) or DVC Data Compressed PDU (DYNVC_DATA_COMPRESSED)
+///
+/// The data is encoded with the RDP 8.0 bulk compression, and starts with a RDP8_BULK_ENCODED_DATA header.
+#[derive(Debug, PartialEq)]
+pub struct CompressedDataPdu {
+    header: Header,
+    pub channel_id: DynamicChannelId,
+    /// Total length of the uncompressed data, only sent in the first PDU of a fragmented message.
+    pub length: Option<u32>,
+    pub data: Vec<u8>,
+}
+
+impl CompressedDataPdu {
+    /// Compression type of the RDP 8.0 bulk compression, in the low bits of the RDP8_BULK_ENCODED_DATA header.
+    const PACKET_COMPR_TYPE_RDP8: u8 = 0x04;
+    const PACKET_COMPRESSED: u8 = 0x20;
+
+    /// Creates the first PDU of a fragmented message, `total_length` being the length of the uncompressed data.
+    pub fn new_first(channel_id: DynamicChannelId, total_length: u32, data: Vec<u8>) -> Self {
+        Self {
+            header: Header::new(channel_id, total_length, Cmd::DataFirstCompressed),
+            channel_id,
+            length: Some(total_length),
+            data,
+        }
+    }
+
+    pub fn new(channel_id: DynamicChannelId, data: Vec<u8>) -> Self {
+        Self {
+            header: Header::new(channel_id, 0, Cmd::DataCompressed),
+            channel_id,
+            length: None,
+            data,
+        }
+    }
+
+    /// Returns the data if it was sent as is in the RDP8_BULK_ENCODED_DATA structure, without being compressed.
+    pub fn uncompressed_data(&self) -> Option<&[u8]> {
+        let (&header, data) = self.data.split_first()?;
+
+        (header & 0x0F == Self::PACKET_COMPR_TYPE_RDP8 && header & Self::PACKET_COMPRESSED == 0).then_some(data)
+    }
+
+    /// Returns `true` if the data is actually compressed, as opposed to being sent as is.
+    pub fn is_compressed(&self) -> bool {
+        self.data
+            .first()
+            .is_some_and(|header| header & Self::PACKET_COMPRESSED != 0)
+    }
+
+    fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        let length_size = if header.cmd == Cmd::DataFirstCompressed {
+            header.sp.size_of_val()
+        } else {
+            0
+        };
+        ensure_size!(in: src, size: checked_sum(&[header.cb_id.size_of_val(), length_size])?);
+        let channel_id = header.cb_id.decode_val(src)?;
+        let length = if header.cmd == Cmd::DataFirstCompressed {
+            Some(header.sp.decode_val(src)?)
+        } else {
+            None
+        };
+        let data = src.read_remaining().to_vec();
+        Ok(Self {
+            header,
+            channel_id,
+            length,
+            data,
+        })
+    }
+
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        self.header.encode(dst)?;
+        self.header.cb_id.encode_val(self.channel_id, dst)?;
+        if let Some(length) = self.length {
+            self.header.sp.encode_val(length, dst)?;
+        }
+        dst.write_slice(&self.data);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        if self.length.is_some() {
+            "DYNVC_DATA_FIRST_COMPRESSED"
+        } else {
+            "DYNVC_DATA_COMPRESSED"
+        }
+    }
+
+    fn size(&self) -> usize {
+        let length_size = if self.length.is_some() {
+            self.header.sp.size_of_val()
+        } else {
+            0
+        };
+
+        strict_sum(&[
+            Header::size(),
+            self.header.cb_id.size_of_val(),
+            length_size,
+            self.data.len(),
+        ])
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct FieldType(u8);
 
@@ -533,6 +647,7 @@ impl CreateResponsePdu {
     }
 }
 
+/// HRESULT reporting the outcome of the channel creation
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct CreationStatus(u32);
 
@@ -540,6 +655,19 @@ impl CreationStatus {
     pub const OK: Self = Self(0x00000000);
     pub const NOT_FOUND: Self = Self(0xC0000225);
     pub const NO_LISTENER: Self = Self(0xC0000001);
+    /// E_FAIL
+    pub const FAILED: Self = Self(0x80004005);
+    /// E_ACCESSDENIED
+    pub const ACCESS_DENIED: Self = Self(0x80070005);
+    /// E_OUTOFMEMORY
+    pub const OUT_OF_MEMORY: Self = Self(0x8007000E);
+    /// E_NOTIMPL
+    pub const NOT_IMPLEMENTED: Self = Self(0x80004001);
+
+    /// Returns `true` if the status does not denote a failure, i.e.: it is not a negative HRESULT.
+    pub fn is_success(self) -> bool {
+        self.0 & 0x8000_0000 == 0
+    }
 
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: Self::size());
@@ -552,6 +680,12 @@ impl CreationStatus {
     }
 }
 
+impl From<u32> for CreationStatus {
+    fn from(val: u32) -> Self {
+        Self(val)
+    }
+}
+
 impl From<CreationStatus> for u32 {
     fn from(val: CreationStatus) -> Self {
         val.0
@@ -629,6 +763,10 @@ impl CapabilitiesResponsePdu {
         }
     }
 
+    pub fn version(&self) -> CapsVersion {
+        self.version
+    }
+
     fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
         let _pad = src.read_u8();
@@ -654,7 +792,7 @@ impl CapabilitiesResponsePdu {
 }
 
 #[repr(u16)]
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CapsVersion {
     V1 = 0x0001,
     V2 = 0x0002,
@@ -730,6 +868,14 @@ impl CapabilitiesRequestPdu {
         }
     }
 
+    pub fn version(&self) -> CapsVersion {
+        match self {
+            Self::V1 { .. } => CapsVersion::V1,
+            Self::V2 { .. } => CapsVersion::V2,
+            Self::V3 { .. } => CapsVersion::V3,
+        }
+    }
+
     fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::HEADERLESS_FIXED_PART_SIZE);
         let _pad = src.read_u8();
@@ -811,6 +957,20 @@ impl CreateRequestPdu {
         }
     }
 
+    /// Sets the priority class of the channel, from 0 (highest) to 3, only meaningful from the version 2 of the
+    /// capabilities.
+    #[must_use]
+    pub fn with_priority(self, priority: u8) -> Self {
+        Self {
+            header: self.header.with_sp(FieldType::from(priority & 0b11)),
+            ..self
+        }
+    }
+
+    pub fn priority(&self) -> u8 {
+        u8::from(self.header.sp)
+    }
+
     fn decode(header: Header, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::headerless_fixed_part_size(&header));
         let channel_id = header.cb_id.decode_val(src)?;
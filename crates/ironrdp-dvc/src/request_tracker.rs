@@ -0,0 +1,154 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::DvcMessage;
+
+/// Correlation ID assigned by a [`DvcRequestTracker`] to a pending request
+pub type RequestId = u32;
+
+/// A pending request matched with its response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion<Req, Resp> {
+    pub id: RequestId,
+    pub request: Req,
+    pub response: Resp,
+}
+
+/// Output of a request/response [`DvcProcessor`](crate::DvcProcessor)
+///
+/// The messages are to be returned from [`DvcProcessor::process`](crate::DvcProcessor::process), while the
+/// completions are to be forwarded to the backend of the processor.
+pub struct DvcProcessOutput<Req, Resp> {
+    pub messages: Vec<DvcMessage>,
+    pub completions: Vec<Completion<Req, Resp>>,
+}
+
+impl<Req, Resp> Default for DvcProcessOutput<Req, Resp> {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+            completions: Vec::new(),
+        }
+    }
+}
+
+impl<Req, Resp> core::fmt::Debug for DvcProcessOutput<Req, Resp>
+where
+    Req: core::fmt::Debug,
+    Resp: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DvcProcessOutput")
+            .field("messages", &self.messages.len())
+            .field("completions", &self.completions)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct PendingRequest<Req> {
+    id: RequestId,
+    created_at: u64,
+    request: Req,
+}
+
+/// Correlates the requests sent over a dynamic virtual channel with their responses
+///
+/// The tracker assigns a correlation ID to each request, to be carried by the request and its response on the
+/// wire, and keeps the request around until the response is received or the request expires.
+/// The number of pending requests is bounded, so a peer never answering can't make the tracker grow indefinitely.
+///
+/// The tracker is not reading any clock: the caller passes the current time, in ticks of its choosing, when
+/// tracking a request and when expiring requests.
+#[derive(Debug)]
+pub struct DvcRequestTracker<Req, Resp> {
+    /// Pending requests, in tracking order.
+    pending: VecDeque<PendingRequest<Req>>,
+    max_pending: usize,
+    next_id: RequestId,
+    _response: PhantomData<fn(Resp)>,
+}
+
+impl<Req, Resp> DvcRequestTracker<Req, Resp> {
+    /// Creates a tracker holding at most `max_pending` requests at once.
+    pub fn new(max_pending: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            max_pending,
+            next_id: 0,
+            _response: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.max_pending
+    }
+
+    /// Tracks a request sent at `now`, and returns its correlation ID.
+    ///
+    /// Returns the request back if the maximum number of pending requests is reached.
+    pub fn track(&mut self, request: Req, now: u64) -> Result<RequestId, Req> {
+        if self.is_full() {
+            return Err(request);
+        }
+
+        // Skip the IDs still in use after wrapping around.
+        let mut id = self.next_id;
+        while self.pending.iter().any(|pending| pending.id == id) {
+            id = id.wrapping_add(1);
+        }
+        self.next_id = id.wrapping_add(1);
+
+        self.pending.push_back(PendingRequest {
+            id,
+            created_at: now,
+            request,
+        });
+
+        Ok(id)
+    }
+
+    /// Matches a response with the pending request it is answering.
+    ///
+    /// Returns `None` if no request is pending with this ID, e.g.: the request expired or was already completed.
+    pub fn complete(&mut self, id: RequestId, response: Resp) -> Option<Completion<Req, Resp>> {
+        let position = self.pending.iter().position(|pending| pending.id == id)?;
+        let pending = self.pending.remove(position)?;
+
+        Some(Completion {
+            id,
+            request: pending.request,
+            response,
+        })
+    }
+
+    /// Removes the requests pending for longer than `max_age` ticks at `now`, and returns them oldest first.
+    pub fn expire_older_than(&mut self, max_age: u64, now: u64) -> Vec<Req> {
+        let mut expired = Vec::new();
+        let mut retained = VecDeque::with_capacity(self.pending.len());
+
+        for pending in self.pending.drain(..) {
+            if now.saturating_sub(pending.created_at) > max_age {
+                expired.push(pending);
+            } else {
+                retained.push_back(pending);
+            }
+        }
+
+        self.pending = retained;
+
+        // The sort is stable, so requests tracked at the same time are kept in tracking order.
+        expired.sort_by_key(|pending| pending.created_at);
+
+        expired.into_iter().map(|pending| pending.request).collect()
+    }
+}
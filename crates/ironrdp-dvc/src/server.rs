@@ -2,7 +2,7 @@ use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::fmt;
 
-use ironrdp_core::{cast_length, impl_as_any, invalid_field_err, Decode as _, DecodeResult, ReadCursor};
+use ironrdp_core::{cast_length, impl_as_any, invalid_field_err, BufPool, Decode as _, DecodeResult, ReadCursor};
 use ironrdp_pdu::{self as pdu, decode_err, encode_err, pdu_other_err};
 use ironrdp_svc::{ChannelFlags, CompressionCondition, SvcMessage, SvcProcessor, SvcServerProcessor};
 use pdu::gcc::ChannelName;
@@ -10,9 +10,10 @@ use pdu::PduResult;
 use slab::Slab;
 
 use crate::pdu::{
-    CapabilitiesRequestPdu, CapsVersion, CreateRequestPdu, CreationStatus, DrdynvcClientPdu, DrdynvcServerPdu,
+    CapabilitiesRequestPdu, CapsVersion, ClosePdu, CompressedDataPdu, CreateRequestPdu, CreationStatus, DataFirstPdu,
+    DataPdu, DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu,
 };
-use crate::{encode_dvc_messages, CompleteData, DvcProcessor};
+use crate::{encode_dvc_messages_pooled, CompleteData, DvcProcessor};
 
 pub trait DvcServerProcessor: DvcProcessor {}
 
@@ -21,6 +22,8 @@ enum ChannelState {
     Closed,
     Creation,
     Opened,
+    /// The server sent a Close Request PDU, and waits for the response of the client.
+    Closing,
     CreationFailed(u32),
 }
 
@@ -28,10 +31,11 @@ struct DynamicChannel {
     state: ChannelState,
     processor: Box<dyn DvcProcessor>,
     complete_data: CompleteData,
+    priority: u8,
 }
 
 impl DynamicChannel {
-    fn new<T>(processor: T) -> Self
+    fn new<T>(processor: T, priority: u8) -> Self
     where
         T: DvcServerProcessor + 'static,
     {
@@ -39,6 +43,7 @@ impl DynamicChannel {
             state: ChannelState::Closed,
             processor: Box::new(processor),
             complete_data: CompleteData::new(),
+            priority,
         }
     }
 }
@@ -47,6 +52,12 @@ impl DynamicChannel {
 /// It adds support for dynamic virtual channels (DVC).
 pub struct DrdynvcServer {
     dynamic_channels: Slab<DynamicChannel>,
+    /// Buffers reused to encode the messages of the dynamic channels.
+    buf_pool: BufPool,
+    caps_version: CapsVersion,
+    priority_charges: [u16; 4],
+    /// Version agreed with the client, once the capabilities are exchanged.
+    agreed_version: Option<CapsVersion>,
 }
 
 impl fmt::Debug for DrdynvcServer {
@@ -70,9 +81,29 @@ impl DrdynvcServer {
     pub fn new() -> Self {
         Self {
             dynamic_channels: Slab::new(),
+            buf_pool: BufPool::new(),
+            caps_version: CapsVersion::V3,
+            priority_charges: [0; 4],
+            agreed_version: None,
         }
     }
 
+    /// Sets the version of the capabilities advertised to the client, defaults to the version 3.
+    ///
+    /// The version 2 adds the priority classes of the channels, and the version 3 adds the compression of the data.
+    #[must_use]
+    pub fn with_caps_version(mut self, version: CapsVersion) -> Self {
+        self.caps_version = version;
+        self
+    }
+
+    /// Sets the bandwidth allocated to each priority class, advertised from the version 2 of the capabilities.
+    #[must_use]
+    pub fn with_priority_charges(mut self, charges: [u16; 4]) -> Self {
+        self.priority_charges = charges;
+        self
+    }
+
     // FIXME(#61): it’s likely we want to enable adding dynamic channels at any point during the session (message passing? other approach?)
 
     #[must_use]
@@ -80,16 +111,117 @@ impl DrdynvcServer {
     where
         T: DvcServerProcessor + 'static,
     {
-        self.dynamic_channels.insert(DynamicChannel::new(channel));
+        self.dynamic_channels.insert(DynamicChannel::new(channel, 0));
         self
     }
 
+    /// Same as [`DrdynvcServer::with_dynamic_channel`], with the priority class of the channel (from 0 to 3, 0 being
+    /// the highest priority), sent to the client when the version 2 or higher of the capabilities is agreed on.
+    #[must_use]
+    pub fn with_dynamic_channel_priority<T>(mut self, channel: T, priority: u8) -> Self
+    where
+        T: DvcServerProcessor + 'static,
+    {
+        self.dynamic_channels.insert(DynamicChannel::new(channel, priority));
+        self
+    }
+
+    /// Returns the processor of the first dynamic channel of type `T`.
+    pub fn channel_processor_downcast_ref<T: DvcProcessor>(&self) -> Option<&T> {
+        self.dynamic_channels
+            .iter()
+            .find_map(|(_, channel)| channel.processor.as_any().downcast_ref())
+    }
+
+    /// Returns the version of the capabilities agreed with the client, once the Capabilities Response PDU is received.
+    pub fn agreed_caps_version(&self) -> Option<CapsVersion> {
+        self.agreed_version
+    }
+
+    /// Returns `true` if the priority classes are sent in the Create Request PDUs.
+    pub fn supports_channel_priority(&self) -> bool {
+        self.agreed_version.is_some_and(|version| version >= CapsVersion::V2)
+    }
+
+    /// Returns `true` if the compressed data PDUs sent by the client are accepted.
+    pub fn supports_compression(&self) -> bool {
+        self.agreed_version.is_some_and(|version| version >= CapsVersion::V3)
+    }
+
     fn channel_by_id(&mut self, id: u32) -> DecodeResult<&mut DynamicChannel> {
         let id = cast_length!("DRDYNVC", "", id)?;
         self.dynamic_channels
             .get_mut(id)
             .ok_or_else(|| invalid_field_err!("DRDYNVC", "", "invalid channel id"))
     }
+
+    fn process_data(&mut self, data: DrdynvcDataPdu, resp: &mut Vec<SvcMessage>) -> PduResult<()> {
+        let channel_id = data.channel_id();
+        let c = self.channel_by_id(channel_id).map_err(|e| decode_err!(e))?;
+        if c.state == ChannelState::Closing {
+            trace!(channel_id, "Dropped data received for a closing DVC");
+            return Ok(());
+        }
+        if c.state != ChannelState::Opened {
+            debug!(?channel_id, ?c.state, "Invalid channel state");
+            return Err(pdu_other_err!("invalid channel state"));
+        }
+        let reserve_limit = c.processor.reassembly_reserve_limit();
+        if let Some(complete) = c
+            .complete_data
+            .process_data(data, reserve_limit)
+            .map_err(|e| decode_err!(e))?
+        {
+            let msg = c.processor.process(channel_id, &complete)?;
+            resp.extend(
+                encode_dvc_messages_pooled(&self.buf_pool, channel_id, msg, ChannelFlags::SHOW_PROTOCOL)
+                    .map_err(|e| encode_err!(e))?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Processes the data sent with the RDP 8.0 bulk encoding, which is only supported when sent uncompressed.
+    ///
+    /// The channel is closed when the compression was not negotiated, or when the data is actually compressed.
+    fn process_compressed_data(&mut self, data: CompressedDataPdu, resp: &mut Vec<SvcMessage>) -> PduResult<()> {
+        let channel_id = data.channel_id;
+
+        let uncompressed = if !self.supports_compression() {
+            warn!(
+                channel_id,
+                "Received compressed DVC data while compression is not negotiated"
+            );
+            None
+        } else if data.is_compressed() {
+            warn!(
+                channel_id,
+                "Received compressed DVC data, the decompression is not supported"
+            );
+            None
+        } else {
+            data.uncompressed_data().map(<[u8]>::to_vec)
+        };
+
+        let Some(uncompressed) = uncompressed else {
+            let c = self.channel_by_id(channel_id).map_err(|e| decode_err!(e))?;
+            if c.state == ChannelState::Opened {
+                c.state = ChannelState::Closing;
+                resp.push(as_svc_msg_with_flag(DrdynvcServerPdu::Close(ClosePdu::new(
+                    channel_id,
+                )))?);
+            }
+            return Ok(());
+        };
+
+        let data = match data.length {
+            Some(length) => DrdynvcDataPdu::DataFirst(DataFirstPdu::new(channel_id, length, uncompressed)),
+            None => DrdynvcDataPdu::Data(DataPdu::new(channel_id, uncompressed)),
+        };
+
+        self.process_data(data, resp)
+    }
 }
 
 impl_as_any!(DrdynvcServer);
@@ -110,7 +242,7 @@ impl SvcProcessor for DrdynvcServer {
     }
 
     fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
-        let cap = CapabilitiesRequestPdu::new(CapsVersion::V1, None);
+        let cap = CapabilitiesRequestPdu::new(self.caps_version, Some(self.priority_charges));
         let req = DrdynvcServerPdu::Capabilities(cap);
         let msg = as_svc_msg_with_flag(req)?;
         Ok(alloc::vec![msg])
@@ -123,17 +255,27 @@ impl SvcProcessor for DrdynvcServer {
         match pdu {
             DrdynvcClientPdu::Capabilities(caps_resp) => {
                 debug!("Got DVC Capabilities Response PDU: {caps_resp:?}");
+
+                // The client is not supposed to answer with a higher version than advertised by the server.
+                let version = caps_resp.version().min(self.caps_version);
+                info!(?version, "DVC capabilities agreed");
+                self.agreed_version = Some(version);
+
+                let supports_channel_priority = self.supports_channel_priority();
                 for (id, c) in self.dynamic_channels.iter_mut() {
                     if c.state != ChannelState::Closed {
                         continue;
                     }
-                    let req = DrdynvcServerPdu::Create(CreateRequestPdu::new(
+                    let mut req = CreateRequestPdu::new(
                         id.try_into()
                             .map_err(|e| pdu_other_err!("invalid channel id", source: e))?,
                         c.processor.channel_name().into(),
-                    ));
+                    );
+                    if supports_channel_priority {
+                        req = req.with_priority(c.priority);
+                    }
                     c.state = ChannelState::Creation;
-                    resp.push(as_svc_msg_with_flag(req)?);
+                    resp.push(as_svc_msg_with_flag(DrdynvcServerPdu::Create(req))?);
                 }
             }
             DrdynvcClientPdu::Create(create_resp) => {
@@ -149,31 +291,21 @@ impl SvcProcessor for DrdynvcServer {
                 }
                 c.state = ChannelState::Opened;
                 let msg = c.processor.start(create_resp.channel_id)?;
-                resp.extend(encode_dvc_messages(id, msg, ChannelFlags::SHOW_PROTOCOL).map_err(|e| encode_err!(e))?);
+                resp.extend(
+                    encode_dvc_messages_pooled(&self.buf_pool, id, msg, ChannelFlags::SHOW_PROTOCOL)
+                        .map_err(|e| encode_err!(e))?,
+                );
             }
             DrdynvcClientPdu::Close(close_resp) => {
                 debug!("Got DVC Close Response PDU: {close_resp:?}");
                 let c = self.channel_by_id(close_resp.channel_id).map_err(|e| decode_err!(e))?;
-                if c.state != ChannelState::Opened {
+                if !matches!(c.state, ChannelState::Opened | ChannelState::Closing) {
                     return Err(pdu_other_err!("invalid channel state"));
                 }
                 c.state = ChannelState::Closed;
             }
-            DrdynvcClientPdu::Data(data) => {
-                let channel_id = data.channel_id();
-                let c = self.channel_by_id(channel_id).map_err(|e| decode_err!(e))?;
-                if c.state != ChannelState::Opened {
-                    debug!(?channel_id, ?c.state, "Invalid channel state");
-                    return Err(pdu_other_err!("invalid channel state"));
-                }
-                if let Some(complete) = c.complete_data.process_data(data).map_err(|e| decode_err!(e))? {
-                    let msg = c.processor.process(channel_id, &complete)?;
-                    resp.extend(
-                        encode_dvc_messages(channel_id, msg, ChannelFlags::SHOW_PROTOCOL)
-                            .map_err(|e| encode_err!(e))?,
-                    );
-                }
-            }
+            DrdynvcClientPdu::Data(data) => self.process_data(data, &mut resp)?,
+            DrdynvcClientPdu::CompressedData(data) => self.process_compressed_data(data, &mut resp)?,
         }
 
         Ok(resp)
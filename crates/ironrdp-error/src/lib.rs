@@ -20,6 +20,49 @@ pub trait Source: fmt::Display + fmt::Debug + Send + Sync + 'static {}
 #[cfg(not(feature = "std"))]
 impl<T> Source for T where T: fmt::Display + fmt::Debug + Send + Sync + 'static {}
 
+/// Coarse classification of an error, for consumers mapping errors onto their own types
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// A message could not be encoded.
+    Encoding,
+    /// A received message could not be decoded.
+    Decoding,
+    /// The peer did not behave as specified by the protocol.
+    Protocol,
+    /// Authentication failed, or access was denied.
+    Security,
+    /// An operation did not complete in time.
+    Timeout,
+    /// Any other error.
+    Other,
+}
+
+/// Stable numeric codes of the error kinds
+///
+/// The codes are part of the public API: they are exposed through the FFI bindings and used for aggregating
+/// telemetry, hence they are never reassigned. Each crate is assigned a range of codes, the high 16 bits
+/// identifying the crate:
+///
+/// | Range                       | Crate                    |
+/// |-----------------------------|--------------------------|
+/// | `0x0001_0000..=0x0001_FFFF` | `ironrdp-core` (encode)  |
+/// | `0x0002_0000..=0x0002_FFFF` | `ironrdp-core` (decode)  |
+/// | `0x0003_0000..=0x0003_FFFF` | `ironrdp-pdu`            |
+/// | `0x0004_0000..=0x0004_FFFF` | `ironrdp-connector`      |
+/// | `0x0005_0000..=0x0005_FFFF` | `ironrdp-session`        |
+/// | `0x0006_0000..=0x0006_FFFF` | reserved for RD Gateway  |
+/// | `0x0007_0000..=0x0007_FFFF` | reserved for VMConnect   |
+///
+/// The code `0` is never used.
+pub trait ErrorKindExt {
+    /// Returns the stable numeric code of this error kind.
+    fn code(&self) -> u32;
+
+    /// Returns the category of this error kind.
+    fn category(&self) -> ErrorCategory;
+}
+
 #[derive(Debug)]
 pub struct Error<Kind> {
     pub context: &'static str,
@@ -84,6 +127,26 @@ impl<Kind> Error<Kind> {
     }
 }
 
+impl<Kind> Error<Kind>
+where
+    Kind: ErrorKindExt,
+{
+    /// Returns the stable numeric code of the error kind.
+    pub fn code(&self) -> u32 {
+        self.kind.code()
+    }
+
+    /// Returns the category of the error kind.
+    pub fn category(&self) -> ErrorCategory {
+        self.kind.category()
+    }
+
+    /// Returns a displayable adapter appending the code of the error kind.
+    pub fn with_code(&self) -> ErrorWithCode<'_, Kind> {
+        ErrorWithCode(self)
+    }
+}
+
 impl<Kind> fmt::Display for Error<Kind>
 where
     Kind: fmt::Display,
@@ -161,3 +224,15 @@ where
         Ok(())
     }
 }
+
+/// Displays an [`Error`] followed by the code of its kind, see [`Error::with_code`]
+pub struct ErrorWithCode<'a, Kind>(&'a Error<Kind>);
+
+impl<Kind> fmt::Display for ErrorWithCode<'_, Kind>
+where
+    Kind: fmt::Display + ErrorKindExt,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {:#010x})", self.0, self.0.kind.code())
+    }
+}
@@ -0,0 +1,120 @@
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+use futures_util::io::{AsyncRead, AsyncWrite};
+use futures_util::{Sink, Stream};
+
+/// Default maximum size of a chunk handed over to the sink.
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Byte stream adapter above message-oriented halves
+///
+/// Many browser transports (e.g.: WebTransport streams) are exposing a stream of chunks for reading and a sink of
+/// chunks for writing, instead of a byte stream. This adapter implements [`AsyncRead`] and [`AsyncWrite`] above such
+/// halves, so they can be used with [`LocalFuturesFramed`](crate::LocalFuturesFramed).
+///
+/// Received chunks larger than the caller’s buffer are kept aside and returned across subsequent reads, and written
+/// buffers are split into chunks of at most `max_chunk_size` bytes.
+pub struct ChunkedStream<R, W> {
+    reader: R,
+    writer: W,
+    pending_chunk: Vec<u8>,
+    pending_offset: usize,
+    max_chunk_size: usize,
+}
+
+impl<R, W> ChunkedStream<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader,
+            writer,
+            pending_chunk: Vec::new(),
+            pending_offset: 0,
+            max_chunk_size: DEFAULT_MAX_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of the chunks handed over to the sink.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_chunk_size` is zero.
+    #[must_use]
+    pub fn with_max_chunk_size(mut self, max_chunk_size: usize) -> Self {
+        assert!(max_chunk_size > 0, "chunk size must be non-zero");
+        self.max_chunk_size = max_chunk_size;
+        self
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+
+    /// Number of received bytes not yet returned to the reader.
+    pub fn buffered_len(&self) -> usize {
+        self.pending_chunk.len() - self.pending_offset
+    }
+}
+
+impl<R, W> AsyncRead for ChunkedStream<R, W>
+where
+    R: Stream<Item = io::Result<Vec<u8>>> + Unpin,
+    W: Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        // Empty chunks are skipped, because returning zero bytes would be interpreted as the end of the stream.
+        while this.buffered_len() == 0 {
+            match ready!(Pin::new(&mut this.reader).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    this.pending_chunk = chunk;
+                    this.pending_offset = 0;
+                }
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+
+        let available = &this.pending_chunk[this.pending_offset..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        this.pending_offset += len;
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<R, W> AsyncWrite for ChunkedStream<R, W>
+where
+    R: Unpin,
+    W: Sink<Vec<u8>, Error = io::Error> + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        ready!(Pin::new(&mut this.writer).poll_ready(cx))?;
+
+        let len = buf.len().min(this.max_chunk_size);
+        Pin::new(&mut this.writer).start_send(buf[..len].to_vec())?;
+
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+    }
+}
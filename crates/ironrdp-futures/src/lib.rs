@@ -4,6 +4,12 @@
 #[rustfmt::skip] // do not re-order this pub use
 pub use ironrdp_async::*;
 
+mod chunked;
+mod timer;
+
+pub use self::chunked::*;
+pub use self::timer::*;
+
 use core::pin::Pin;
 use std::io;
 
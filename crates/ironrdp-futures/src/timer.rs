@@ -0,0 +1,30 @@
+use core::time::Duration;
+
+use crate::AsyncTimer;
+
+/// [`AsyncTimer`] independent of the async runtime
+///
+/// The timers are provided by `futures-timer` on native targets, and by the browser (`setTimeout`) on
+/// `wasm32-unknown-unknown`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuturesTimer;
+
+impl AsyncTimer for FuturesTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    type Sleep = futures_timer::Delay;
+
+    #[cfg(target_arch = "wasm32")]
+    type Sleep = gloo_timers::future::TimeoutFuture;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        futures_timer::Delay::new(duration)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        // The browser timers are limited to a 32-bit number of milliseconds.
+        let millis = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        gloo_timers::future::TimeoutFuture::new(millis)
+    }
+}
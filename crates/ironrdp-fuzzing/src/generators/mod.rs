@@ -14,3 +14,75 @@ pub struct BitmapInput<'a> {
     pub width: u8,
     pub height: u8,
 }
+
+/// Sequence of Channel PDUs received on a few static virtual channels
+#[derive(Arbitrary, Debug)]
+pub struct SvcChunksInput {
+    /// Maximum length of the de-chunkified PDUs.
+    pub max_pdu_length: u16,
+    /// Chunks with arbitrary flags and lengths, received on fresh channels.
+    pub chunks: Vec<SvcChunk>,
+    /// Messages chunked as expected, received on other fresh channels.
+    pub messages: Vec<ReassemblyMessage>,
+    /// Order in which the chunks of the messages are interleaved across the channels.
+    pub schedule: Vec<u8>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum SvcChunk {
+    /// Chunk with plausible flags, uncompressed.
+    Plausible {
+        channel: u8,
+        first: bool,
+        last: bool,
+        length: u32,
+        data: Vec<u8>,
+    },
+    /// Chunk with arbitrary flags, including the compression flags.
+    Raw {
+        channel: u8,
+        flags: u32,
+        length: u32,
+        data: Vec<u8>,
+    },
+    /// Payload which may not even hold a Channel PDU Header.
+    Garbage { channel: u8, payload: Vec<u8> },
+}
+
+/// Sequence of DVC data PDUs received on a few dynamic virtual channels
+#[derive(Arbitrary, Debug)]
+pub struct DvcFragmentsInput {
+    /// Maximum capacity reserved for a fragmented message.
+    pub reserve_limit: u16,
+    /// Fragments with arbitrary lengths, received on fresh channels.
+    pub fragments: Vec<DvcFragment>,
+    /// Messages fragmented as expected, received on other fresh channels.
+    pub messages: Vec<ReassemblyMessage>,
+    /// Order in which the fragments of the messages are interleaved across the channels.
+    pub schedule: Vec<u8>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum DvcFragment {
+    DataFirst {
+        channel: u8,
+        total_length: u32,
+        data: Vec<u8>,
+    },
+    Data {
+        channel: u8,
+        data: Vec<u8>,
+    },
+    /// Payload received on the DRDYNVC channel, which may not even hold a DVC header.
+    Garbage(Vec<u8>),
+}
+
+/// Message sent on one of the channels, split in pieces of `piece_length` bytes
+#[derive(Arbitrary, Debug)]
+pub struct ReassemblyMessage {
+    pub channel: u8,
+    pub piece_length: u16,
+    /// Whether a DVC message held in a single piece is sent in a DVC Data First PDU.
+    pub fragmented: bool,
+    pub data: Vec<u8>,
+}
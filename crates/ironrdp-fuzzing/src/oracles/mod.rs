@@ -10,7 +10,18 @@
 //! When an oracle finds a bug, it should report it to the fuzzing engine by
 //! panicking.
 
-use crate::generators::BitmapInput;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::impl_as_any;
+use ironrdp_dvc::pdu::{DataFirstPdu, DataPdu, DrdynvcDataPdu, DrdynvcServerPdu};
+use ironrdp_dvc::DvcProcessor;
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::rdp::vc::ChannelControlFlags;
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage, SvcProcessor};
+
+use crate::generators::{BitmapInput, DvcFragment, DvcFragmentsInput, ReassemblyMessage, SvcChunk, SvcChunksInput};
 
 pub fn pdu_decode(data: &[u8]) {
     use ironrdp_core::*;
@@ -41,6 +52,7 @@ pub fn pdu_decode(data: &[u8]) {
 
     let _ = decode::<vc::ChannelPduHeader>(data);
 
+    let _ = find_size(data);
     let _ = decode::<fast_path::FastPathHeader>(data);
     let _ = decode::<fast_path::FastPathUpdatePdu<'_>>(data);
     let _ = fast_path::FastPathUpdate::decode_with_code(data, fast_path::UpdateCode::Orders);
@@ -130,6 +142,12 @@ pub fn rdp6_decode_bitmap_stream_to_rgb24(input: &BitmapInput<'_>) {
         input.width as usize,
         input.height as usize,
     );
+    let _ = BitmapStreamDecoder::default().decode_bitmap_stream_to_rgba32(
+        input.src,
+        &mut out,
+        input.width as usize,
+        input.height as usize,
+    );
 }
 
 pub fn cliprdr_format(input: &[u8]) {
@@ -158,3 +176,333 @@ pub fn channel_process(input: &[u8]) {
 
     let _ = rdpdr.process(input);
 }
+
+/// Number of channels the chunks and fragments of the reassembly oracles are spread across.
+const REASSEMBLY_CHANNEL_COUNT: usize = 3;
+
+const SVC_NAMES: [ChannelName; REASSEMBLY_CHANNEL_COUNT] = [
+    ChannelName::from_static(b"fuzz0\0\0\0"),
+    ChannelName::from_static(b"fuzz1\0\0\0"),
+    ChannelName::from_static(b"fuzz2\0\0\0"),
+];
+
+const DVC_NAMES: [&str; REASSEMBLY_CHANNEL_COUNT] = ["fuzz0", "fuzz1", "fuzz2"];
+
+/// Outcome of the reassembly of a message, as reported to the channel processor
+#[derive(Debug, PartialEq)]
+enum Reassembled {
+    Complete(Vec<u8>),
+    Oversized { length: usize },
+}
+
+#[derive(Debug)]
+struct SvcRecorder {
+    channel_name: ChannelName,
+    max_pdu_length: usize,
+    received: Vec<Reassembled>,
+}
+
+impl_as_any!(SvcRecorder);
+
+impl SvcProcessor for SvcRecorder {
+    fn channel_name(&self) -> ChannelName {
+        self.channel_name.clone()
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        assert!(payload.len() <= self.max_pdu_length, "PDU exceeding the maximum length");
+        self.received.push(Reassembled::Complete(payload.to_vec()));
+        Ok(Vec::new())
+    }
+
+    fn max_pdu_length(&self) -> Option<usize> {
+        Some(self.max_pdu_length)
+    }
+
+    fn on_oversized_pdu(&mut self, length: usize) -> PduResult<Vec<SvcMessage>> {
+        assert!(length > self.max_pdu_length, "PDU wrongly reported as oversized");
+        self.received.push(Reassembled::Oversized { length });
+        Ok(Vec::new())
+    }
+}
+
+/// Messages received on a dynamic virtual channel, shared with the oracle
+type DvcReceived = Arc<Mutex<Vec<Vec<u8>>>>;
+
+struct DvcRecorder {
+    channel_name: &'static str,
+    reserve_limit: usize,
+    received: DvcReceived,
+}
+
+impl_as_any!(DvcRecorder);
+
+impl DvcProcessor for DvcRecorder {
+    fn channel_name(&self) -> &str {
+        self.channel_name
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<ironrdp_dvc::DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<ironrdp_dvc::DvcMessage>> {
+        self.received.lock().unwrap().push(payload.to_vec());
+        Ok(Vec::new())
+    }
+
+    fn reassembly_reserve_limit(&self) -> usize {
+        self.reserve_limit
+    }
+}
+
+/// Feeds chunks to the de-chunkification of static virtual channels.
+///
+/// The arbitrary chunks must not cause a panic, nor a PDU larger than the maximum length to be reported. The
+/// messages chunked as expected must be reassembled as the reference reassembler does, whatever the interleaving
+/// of the chunks across the channels.
+pub fn svc_reassembly(input: &SvcChunksInput) {
+    let max_pdu_length = usize::from(input.max_pdu_length);
+
+    let mut channels = svc_channels(max_pdu_length);
+
+    for chunk in &input.chunks {
+        let (channel, payload) = match chunk {
+            SvcChunk::Plausible {
+                channel,
+                first,
+                last,
+                length,
+                data,
+            } => {
+                let mut flags = ChannelControlFlags::empty();
+                flags.set(ChannelControlFlags::FLAG_FIRST, *first);
+                flags.set(ChannelControlFlags::FLAG_LAST, *last);
+                (channel, encode_svc_chunk(*length, flags, data))
+            }
+            SvcChunk::Raw {
+                channel,
+                flags,
+                length,
+                data,
+            } => (
+                channel,
+                encode_svc_chunk(*length, ChannelControlFlags::from_bits_retain(*flags), data),
+            ),
+            SvcChunk::Garbage { channel, payload } => (channel, payload.clone()),
+        };
+
+        let _ = channels[usize::from(*channel) % REASSEMBLY_CHANNEL_COUNT].process(&payload);
+    }
+
+    let mut channels = svc_channels(max_pdu_length);
+    let mut chunks: [VecDeque<Vec<u8>>; REASSEMBLY_CHANNEL_COUNT] = Default::default();
+
+    for message in &input.messages {
+        let channel = usize::from(message.channel) % REASSEMBLY_CHANNEL_COUNT;
+        let length = u32::try_from(message.data.len()).unwrap();
+        let pieces = split_message(message, usize::MAX);
+        let last_index = pieces.len() - 1;
+
+        for (index, piece) in pieces.into_iter().enumerate() {
+            let mut flags = ChannelControlFlags::empty();
+            flags.set(ChannelControlFlags::FLAG_FIRST, index == 0);
+            flags.set(ChannelControlFlags::FLAG_LAST, index == last_index);
+            chunks[channel].push_back(encode_svc_chunk(length, flags, piece));
+        }
+    }
+
+    interleave(chunks, &input.schedule, |channel, payload| {
+        channels[channel].process(&payload).expect("valid chunk");
+    });
+
+    let expected = reference_svc_reassembly(&input.messages, max_pdu_length);
+
+    for (channel, expected) in channels.iter().zip(expected) {
+        let recorder = channel.channel_processor_downcast_ref::<SvcRecorder>().unwrap();
+        assert_eq!(recorder.received, expected);
+    }
+}
+
+/// Feeds fragments to the reassembly of dynamic virtual channel messages.
+///
+/// The arbitrary fragments must not cause a panic, nor reserve more than the reserve limit for the announced lengths.
+/// The messages fragmented as expected must be reassembled as the reference reassembler does, whatever the
+/// interleaving of the fragments across the channels.
+pub fn dvc_reassembly(input: &DvcFragmentsInput) {
+    let reserve_limit = usize::from(input.reserve_limit);
+
+    let (mut drdynvc, _) = drdynvc_client(reserve_limit);
+
+    for fragment in &input.fragments {
+        let payload = match fragment {
+            DvcFragment::DataFirst {
+                channel,
+                total_length,
+                data,
+            } => encode_dvc_fragment(DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+                u32::from(*channel),
+                *total_length,
+                data.clone(),
+            ))),
+            DvcFragment::Data { channel, data } => {
+                encode_dvc_fragment(DrdynvcDataPdu::Data(DataPdu::new(u32::from(*channel), data.clone())))
+            }
+            DvcFragment::Garbage(payload) => payload.clone(),
+        };
+
+        let _ = drdynvc.process(&payload);
+    }
+
+    let (mut drdynvc, received) = drdynvc_client(reserve_limit);
+    let mut fragments: [VecDeque<Vec<u8>>; REASSEMBLY_CHANNEL_COUNT] = Default::default();
+
+    for message in &input.messages {
+        let channel = usize::from(message.channel) % REASSEMBLY_CHANNEL_COUNT;
+        let channel_id = u32::try_from(channel).unwrap();
+        let total_length = u32::try_from(message.data.len()).unwrap();
+        let mut pieces = split_message(message, DrdynvcDataPdu::MAX_DATA_SIZE).into_iter();
+
+        let first = pieces.next().unwrap().to_vec();
+        let first = if message.fragmented || pieces.len() > 0 {
+            DrdynvcDataPdu::DataFirst(DataFirstPdu::new(channel_id, total_length, first))
+        } else {
+            DrdynvcDataPdu::Data(DataPdu::new(channel_id, first))
+        };
+        fragments[channel].push_back(encode_dvc_fragment(first));
+
+        for piece in pieces {
+            let data = DrdynvcDataPdu::Data(DataPdu::new(channel_id, piece.to_vec()));
+            fragments[channel].push_back(encode_dvc_fragment(data));
+        }
+    }
+
+    interleave(fragments, &input.schedule, |_, payload| {
+        drdynvc.process(&payload).expect("valid fragment");
+    });
+
+    let expected = reference_dvc_reassembly(&input.messages);
+
+    for (received, expected) in received.iter().zip(expected) {
+        assert_eq!(*received.lock().unwrap(), expected);
+    }
+}
+
+fn svc_channels(max_pdu_length: usize) -> Vec<StaticVirtualChannel> {
+    SVC_NAMES
+        .into_iter()
+        .map(|channel_name| {
+            StaticVirtualChannel::new(SvcRecorder {
+                channel_name,
+                max_pdu_length,
+                received: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Returns a DRDYNVC client with the channels opened, and the messages received on each of them.
+fn drdynvc_client(reserve_limit: usize) -> (ironrdp_dvc::DrdynvcClient, Vec<DvcReceived>) {
+    let mut drdynvc = ironrdp_dvc::DrdynvcClient::new();
+    let mut received = Vec::new();
+
+    for (channel_id, channel_name) in (0..).zip(DVC_NAMES) {
+        let channel_received = Arc::new(Mutex::new(Vec::new()));
+
+        drdynvc.attach_dynamic_channel(DvcRecorder {
+            channel_name,
+            reserve_limit,
+            received: Arc::clone(&channel_received),
+        });
+
+        let create_request = DrdynvcServerPdu::Create(ironrdp_dvc::pdu::CreateRequestPdu::new(
+            channel_id,
+            channel_name.to_owned(),
+        ));
+        drdynvc
+            .process(&ironrdp_core::encode_vec(&create_request).unwrap())
+            .unwrap();
+
+        received.push(channel_received);
+    }
+
+    (drdynvc, received)
+}
+
+fn encode_svc_chunk(length: u32, flags: ChannelControlFlags, data: &[u8]) -> Vec<u8> {
+    let mut payload = ironrdp_core::encode_vec(&ironrdp_pdu::rdp::vc::ChannelPduHeader { length, flags }).unwrap();
+    payload.extend_from_slice(data);
+    payload
+}
+
+fn encode_dvc_fragment(pdu: DrdynvcDataPdu) -> Vec<u8> {
+    ironrdp_core::encode_vec(&DrdynvcServerPdu::Data(pdu)).unwrap()
+}
+
+/// Splits the data of `message` in pieces of at most `max_piece_length` bytes, an empty message having one piece.
+fn split_message(message: &ReassemblyMessage, max_piece_length: usize) -> Vec<&[u8]> {
+    let piece_length = usize::from(message.piece_length).clamp(1, max_piece_length);
+
+    if message.data.is_empty() {
+        vec![&[]]
+    } else {
+        message.data.chunks(piece_length).collect()
+    }
+}
+
+/// Passes the pieces queued for the channels to `process`, the channel of each piece being picked by `schedule`.
+///
+/// The order of the pieces of a channel is kept. Once `schedule` is exhausted, the remaining pieces are passed in the
+/// order of the channels.
+fn interleave(
+    mut queues: [VecDeque<Vec<u8>>; REASSEMBLY_CHANNEL_COUNT],
+    schedule: &[u8],
+    mut process: impl FnMut(usize, Vec<u8>),
+) {
+    let mut schedule = schedule.iter();
+
+    loop {
+        let pending = (0..REASSEMBLY_CHANNEL_COUNT)
+            .filter(|channel| !queues[*channel].is_empty())
+            .collect::<Vec<_>>();
+
+        if pending.is_empty() {
+            break;
+        }
+
+        let pick = schedule.next().map_or(0, |pick| usize::from(*pick) % pending.len());
+        let channel = pending[pick];
+        process(channel, queues[channel].pop_front().unwrap());
+    }
+}
+
+/// Reference reassembler of the static virtual channel messages: the messages of each channel are reported in order,
+/// the ones larger than `max_pdu_length` by their length only.
+fn reference_svc_reassembly(messages: &[ReassemblyMessage], max_pdu_length: usize) -> Vec<Vec<Reassembled>> {
+    let mut reassembled = (0..REASSEMBLY_CHANNEL_COUNT).map(|_| Vec::new()).collect::<Vec<_>>();
+
+    for message in messages {
+        let outcome = if message.data.len() > max_pdu_length {
+            Reassembled::Oversized {
+                length: message.data.len(),
+            }
+        } else {
+            Reassembled::Complete(message.data.clone())
+        };
+
+        reassembled[usize::from(message.channel) % REASSEMBLY_CHANNEL_COUNT].push(outcome);
+    }
+
+    reassembled
+}
+
+/// Reference reassembler of the dynamic virtual channel messages: the messages of each channel are received in order.
+fn reference_dvc_reassembly(messages: &[ReassemblyMessage]) -> Vec<Vec<Vec<u8>>> {
+    let mut reassembled = (0..REASSEMBLY_CHANNEL_COUNT).map(|_| Vec::new()).collect::<Vec<_>>();
+
+    for message in messages {
+        reassembled[usize::from(message.channel) % REASSEMBLY_CHANNEL_COUNT].push(message.data.clone());
+    }
+
+    reassembled
+}
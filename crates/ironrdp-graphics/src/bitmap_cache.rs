@@ -0,0 +1,314 @@
+//! Client side of the revision 2 bitmap cache
+//!
+//! The bitmaps are stored by the Cache Bitmap (Revision 2) orders, in the slots chosen by the server, and drawn on the
+//! screen by the MemBlt and Mem3Blt orders. The server is tracking the content of the cache, so a slot is only
+//! replaced when the server overwrites it.
+//!
+//! # References
+//!
+//! - [Cache Bitmap - Revision 2 (CACHE_BITMAP_REV2_ORDER)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpegdi/3e7f3ae8-d6a4-4c94-b9f1-4ec6fc2ae6a5)
+//! - [Revision 2 Bitmap Cache Capability Set](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/f7e1a5d4-b3ea-4aef-b7ad-ed5e1d9a5fd7)
+
+use ironrdp_pdu::orders::{CacheBitmapRev2, CacheBitmapRev2Flags};
+use ironrdp_pdu::rdp::capability_sets::BitmapCacheRev2;
+use thiserror::Error;
+
+use crate::color_conversion::{rdp_15bit_to_rgb, rdp_16bit_to_rgb};
+use crate::rdp6::{BitmapDecodeError, BitmapStreamDecoder};
+use crate::rle::{self, RleError, RlePixelFormat};
+
+/// Maximum number of pixels of the bitmaps stored in each cache.
+const CELL_SIZES: [usize; 5] = [256, 1024, 4096, 4096, 4096];
+
+#[derive(Debug, Error)]
+pub enum BitmapCacheError {
+    #[error("invalid bitmap cache ID: {cache_id}")]
+    InvalidCacheId { cache_id: u8 },
+    #[error("invalid index {cache_index} in bitmap cache {cache_id}")]
+    InvalidCacheIndex { cache_id: u8, cache_index: u16 },
+    #[error("bitmap of {width}x{height} pixels is too large for bitmap cache {cache_id}")]
+    BitmapTooLarge { cache_id: u8, width: u16, height: u16 },
+    #[error("not supported bitmap bpp: {bpp}")]
+    NotSupportedBpp { bpp: u8 },
+    #[error("invalid uncompressed bitmap size. Expected: {expected}, actual: {actual}")]
+    InvalidBitmapSize { expected: usize, actual: usize },
+    #[error("invalid RLE-compressed bitmap: {0}")]
+    Rle(RleError),
+    #[error(transparent)]
+    BitmapStream(#[from] BitmapDecodeError),
+}
+
+/// Bitmap stored in the bitmap cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedBitmap {
+    width: u16,
+    height: u16,
+    bits_per_pixel: u8,
+    key: Option<u64>,
+    /// Top-down RGB pixels.
+    data: Vec<u8>,
+}
+
+impl CachedBitmap {
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Color depth of the bitmap as sent by the server, which is the color depth of the session.
+    pub fn bits_per_pixel(&self) -> u8 {
+        self.bits_per_pixel
+    }
+
+    /// Persistent key of the bitmap, if sent by the server.
+    pub fn key(&self) -> Option<u64> {
+        self.key
+    }
+
+    /// Top-down RGB pixels of the bitmap, 3 bytes per pixel.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the RGB color of a pixel, if inside the bitmap.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let idx = (usize::from(y) * usize::from(self.width) + usize::from(x)) * 3;
+        Some([self.data[idx], self.data[idx + 1], self.data[idx + 2]])
+    }
+}
+
+/// Persistent key of a cached bitmap, to announce in the Persistent Key List PDU of a later connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PersistentKey {
+    pub cache_id: u8,
+    pub cache_index: u16,
+    pub key: u64,
+}
+
+#[derive(Debug)]
+struct CellCache {
+    persistent: bool,
+    entries: Vec<Option<CachedBitmap>>,
+}
+
+/// Revision 2 bitmap cache, sized as advertised in the capabilities
+#[derive(Debug)]
+pub struct BitmapCache {
+    caches: Vec<CellCache>,
+    bitmap_stream_decoder: BitmapStreamDecoder,
+    buf: Vec<u8>,
+}
+
+impl BitmapCache {
+    pub fn new(capabilities: &BitmapCacheRev2) -> Self {
+        let caches = capabilities
+            .cache_cell_info
+            .iter()
+            .take(usize::from(capabilities.num_cell_caches))
+            .map(|info| CellCache {
+                persistent: info.is_cache_persistent,
+                entries: vec![None; info.num_entries as usize],
+            })
+            .collect();
+
+        Self {
+            caches,
+            bitmap_stream_decoder: BitmapStreamDecoder::default(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Stores the bitmap of a Cache Bitmap (Revision 2) order, replacing the bitmap in its slot.
+    ///
+    /// The bitmaps sent to the waiting list, or not to be cached, are ignored.
+    pub fn insert(&mut self, order: &CacheBitmapRev2) -> Result<(), BitmapCacheError> {
+        let cache_id = order.cache_id;
+        let cache = self
+            .caches
+            .get(usize::from(cache_id))
+            .ok_or(BitmapCacheError::InvalidCacheId { cache_id })?;
+
+        if order.flags.contains(CacheBitmapRev2Flags::DO_NOT_CACHE)
+            || order.cache_index == CacheBitmapRev2::WAITING_LIST_INDEX
+        {
+            return Ok(());
+        }
+
+        if usize::from(order.cache_index) >= cache.entries.len() {
+            return Err(BitmapCacheError::InvalidCacheIndex {
+                cache_id,
+                cache_index: order.cache_index,
+            });
+        }
+
+        if usize::from(order.width) * usize::from(order.height) > CELL_SIZES[usize::from(cache_id)] {
+            return Err(BitmapCacheError::BitmapTooLarge {
+                cache_id,
+                width: order.width,
+                height: order.height,
+            });
+        }
+
+        let data = self.decode_bitmap(order)?;
+
+        self.caches[usize::from(cache_id)].entries[usize::from(order.cache_index)] = Some(CachedBitmap {
+            width: order.width,
+            height: order.height,
+            bits_per_pixel: order.bits_per_pixel,
+            key: order.key,
+            data,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the bitmap stored in a slot.
+    pub fn get(&self, cache_id: u8, cache_index: u16) -> Result<Option<&CachedBitmap>, BitmapCacheError> {
+        let cache = self
+            .caches
+            .get(usize::from(cache_id))
+            .ok_or(BitmapCacheError::InvalidCacheId { cache_id })?;
+
+        let entry = cache
+            .entries
+            .get(usize::from(cache_index))
+            .ok_or(BitmapCacheError::InvalidCacheIndex { cache_id, cache_index })?;
+
+        Ok(entry.as_ref())
+    }
+
+    /// Returns the keys of the bitmaps stored in the persistent caches.
+    pub fn persistent_keys(&self) -> Vec<PersistentKey> {
+        self.caches
+            .iter()
+            .enumerate()
+            .filter(|(_, cache)| cache.persistent)
+            .flat_map(|(cache_id, cache)| {
+                cache
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(cache_index, entry)| {
+                        entry.as_ref()?.key.map(|key| PersistentKey {
+                            cache_id: cache_id as u8,
+                            cache_index: cache_index as u16,
+                            key,
+                        })
+                    })
+            })
+            .collect()
+    }
+
+    /// Decodes the bitmap of the order to top-down RGB pixels.
+    fn decode_bitmap(&mut self, order: &CacheBitmapRev2) -> Result<Vec<u8>, BitmapCacheError> {
+        let width = usize::from(order.width);
+        let height = usize::from(order.height);
+
+        self.buf.clear();
+
+        // Bitmaps are stored bottom-up, with padded rows when uncompressed.
+        let (format, stride) = if !order.compressed {
+            let format = match order.bits_per_pixel {
+                16 => PixelFormat::Rgb16,
+                24 => PixelFormat::Bgr24,
+                32 => PixelFormat::Bgrx32,
+                bpp => return Err(BitmapCacheError::NotSupportedBpp { bpp }),
+            };
+            let stride = (width * format.bytes_per_pixel()).next_multiple_of(4);
+
+            let expected = stride * height;
+            if order.bitmap_data.len() < expected {
+                return Err(BitmapCacheError::InvalidBitmapSize {
+                    expected,
+                    actual: order.bitmap_data.len(),
+                });
+            }
+            self.buf.extend_from_slice(&order.bitmap_data[..expected]);
+
+            (format, stride)
+        } else if order.bits_per_pixel == 32 {
+            self.bitmap_stream_decoder.decode_bitmap_stream_to_rgb24(
+                &order.bitmap_data,
+                &mut self.buf,
+                width,
+                height,
+            )?;
+
+            (PixelFormat::Rgb24, width * 3)
+        } else {
+            let format = match rle::decompress(
+                &order.bitmap_data,
+                &mut self.buf,
+                width,
+                height,
+                usize::from(order.bits_per_pixel),
+            )
+            .map_err(BitmapCacheError::Rle)?
+            {
+                RlePixelFormat::Rgb15 => PixelFormat::Rgb15,
+                RlePixelFormat::Rgb16 => PixelFormat::Rgb16,
+                RlePixelFormat::Rgb24 => PixelFormat::Bgr24,
+                RlePixelFormat::Rgb8 => return Err(BitmapCacheError::NotSupportedBpp { bpp: 8 }),
+            };
+
+            (format, width * format.bytes_per_pixel())
+        };
+
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let mut data = Vec::with_capacity(width * height * 3);
+
+        if stride > 0 {
+            for row in self.buf.chunks_exact(stride).rev().take(height) {
+                for pixel in row.chunks_exact(bytes_per_pixel).take(width) {
+                    data.extend_from_slice(&format.to_rgb(pixel));
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Pixel format of the decoded bitmaps, before their conversion to RGB
+#[derive(Debug, Clone, Copy)]
+enum PixelFormat {
+    Rgb15,
+    Rgb16,
+    Bgr24,
+    Rgb24,
+    Bgrx32,
+}
+
+impl PixelFormat {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb15 | Self::Rgb16 => 2,
+            Self::Bgr24 | Self::Rgb24 => 3,
+            Self::Bgrx32 => 4,
+        }
+    }
+
+    fn to_rgb(self, pixel: &[u8]) -> [u8; 3] {
+        match self {
+            Self::Rgb15 => rdp_15bit_to_rgb(u16::from_le_bytes([pixel[0], pixel[1]])),
+            Self::Rgb16 => rdp_16bit_to_rgb(u16::from_le_bytes([pixel[0], pixel[1]])),
+            Self::Bgr24 | Self::Bgrx32 => [pixel[2], pixel[1], pixel[0]],
+            Self::Rgb24 => [pixel[0], pixel[1], pixel[2]],
+        }
+    }
+}
+
+/// Applies a ternary raster operation to the bits of the pattern, source and destination bytes.
+pub fn rop3(rop: u8, pattern: u8, source: u8, destination: u8) -> u8 {
+    (0..8).fold(0, |result, bit| {
+        let index = ((pattern >> bit) & 1) << 2 | ((source >> bit) & 1) << 1 | ((destination >> bit) & 1);
+        result | ((rop >> index) & 1) << bit
+    })
+}
@@ -3,9 +3,34 @@ use std::io::{self, Write};
 
 use crate::image_processing::PixelFormat;
 
+mod simd;
+
 const ALPHA: u8 = 255;
 
+// The YCbCr to RGB factors are scaled by << 16 into 32-bit integers in order to
+// avoid slower floating point multiplications.
+const YCBCR_DIVISOR: f32 = (1 << 16) as f32;
+const CR_R: i32 = (1.402_525 * YCBCR_DIVISOR) as i32;
+const CB_G: i32 = (0.343_730 * YCBCR_DIVISOR) as i32;
+const CR_G: i32 = (0.714_401 * YCBCR_DIVISOR) as i32;
+const CB_B: i32 = (1.769_905 * YCBCR_DIVISOR) as i32;
+// Rounds down to zero, the term is kept for the record.
+const CR_B: i32 = (0.000_013 * YCBCR_DIVISOR) as i32;
+
 pub fn ycbcr_to_bgra(input: YCbCrBuffer<'_>, mut output: &mut [u8]) -> io::Result<()> {
+    let YCbCrBuffer { y, cb, cr } = input;
+
+    // The SIMD paths only convert the pixels fitting in the output, the scalar loop reporting the error, if any.
+    let len = min(min(y.len(), cb.len()), min(cr.len(), output.len() / 4));
+    let converted = simd::ycbcr_to_bgra(&y[..len], &cb[..len], &cr[..len], &mut output[..len * 4]);
+
+    let input = YCbCrBuffer {
+        y: &y[converted..],
+        cb: &cb[converted..],
+        cr: &cr[converted..],
+    };
+    output = &mut output[converted * 4..];
+
     for ycbcr in input {
         let pixel = Rgb::from(ycbcr);
 
@@ -184,6 +209,36 @@ pub fn rdp_16bit_to_rgb(color: u16) -> [u8; 3] {
     [r, g, b]
 }
 
+/// Convert 16-bit RDP colors to RGBA representation, the alpha channel being opaque. Input values should be
+/// represented in little-endian format.
+///
+/// # Panics
+///
+/// Panics if `output` is shorter than 4 bytes per input pixel.
+pub fn rdp_16bit_to_rgba(input: &[u8], output: &mut [u8]) {
+    let len = input.len() / 2;
+    assert!(output.len() >= len * 4, "output buffer is too small");
+
+    let converted = simd::rdp_16bit_to_rgba(&input[..len * 2], &mut output[..len * 4]);
+
+    for (pixel, rgba) in input[converted * 2..len * 2]
+        .chunks_exact(2)
+        .zip(output[converted * 4..].chunks_exact_mut(4))
+    {
+        let [r, g, b] = rdp_16bit_to_rgb(u16::from_le_bytes([pixel[0], pixel[1]]));
+        rgba.copy_from_slice(&[r, g, b, ALPHA]);
+    }
+}
+
+/// Convert a 15-bit RDP color (RGB555) to RGB representation. Input value should be represented in
+/// little-endian format.
+pub fn rdp_15bit_to_rgb(color: u16) -> [u8; 3] {
+    let r = (((((color >> 10) & 0x1f) * 527) + 23) >> 6) as u8;
+    let g = (((((color >> 5) & 0x1f) * 527) + 23) >> 6) as u8;
+    let b = ((((color & 0x1f) * 527) + 23) >> 6) as u8;
+    [r, g, b]
+}
+
 fn clip(v: i32) -> u8 {
     v.clamp(0, 255) as u8
 }
@@ -233,23 +288,19 @@ impl From<YCbCr> for Rgb {
     fn from(YCbCr { y, cb, cr }: YCbCr) -> Self {
         #![allow(clippy::similar_names)] // It’s hard to find better names here.
 
-        // We scale the factors by << 16 into 32-bit integers in order to
-        // avoid slower floating point multiplications.  Since the final
-        // result needs to be scaled by >> 5 we will extract only the
-        // upper 11 bits (>> 21) from the final sum.
+        // Since the final result needs to be scaled by >> 5 we will extract
+        // only the upper 11 bits (>> 21) from the final sum.
         // Hence we also have to scale the other terms of the sum by << 16.
-        const DIVISOR: f32 = (1 << 16) as f32;
-
         let y = i32::from(y);
         let cb = i32::from(cb);
         let cr = i32::from(cr);
 
         let yy = (y + 4096) << 16;
-        let cr_r = cr.overflowing_mul((1.402_525 * DIVISOR) as i32).0;
-        let cb_g = cb.overflowing_mul((0.343_730 * DIVISOR) as i32).0;
-        let cr_g = cr.overflowing_mul((0.714_401 * DIVISOR) as i32).0;
-        let cb_b = cb.overflowing_mul((1.769_905 * DIVISOR) as i32).0;
-        let cr_b = cb.overflowing_mul((0.000_013 * DIVISOR) as i32).0;
+        let cr_r = cr.overflowing_mul(CR_R).0;
+        let cb_g = cb.overflowing_mul(CB_G).0;
+        let cr_g = cr.overflowing_mul(CR_G).0;
+        let cb_b = cb.overflowing_mul(CB_B).0;
+        let cr_b = cb.overflowing_mul(CR_B).0;
 
         let r = clip((yy.overflowing_add(cr_r).0) >> 21);
         let g = clip((yy.overflowing_sub(cb_g).0.overflowing_sub(cr_g).0) >> 21);
@@ -0,0 +1,205 @@
+//! SIMD paths of the hot color conversion loops
+//!
+//! Each function converts the pixels of the largest whole number of vectors, and returns how many pixels were
+//! converted, the caller converting the remaining ones with the scalar code. The results are bit-exact with the
+//! scalar code, including the wrapping of the 32-bit arithmetic on out-of-range inputs.
+//!
+//! - On x86_64, AVX2 is detected at runtime, SSE2 being part of the baseline.
+//! - On aarch64, NEON is part of the baseline.
+//! - On wasm32, SIMD128 is used when the crate is built with the `simd128` target feature.
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm32;
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+
+use super::CR_B;
+
+// The scalar code multiplies Cb by the Cr factor of blue, which is harmless only because the factor is zero:
+// the SIMD paths leave the term out.
+const _: () = assert!(CR_B == 0);
+
+/// Converts the YCbCr values to BGRA, `output` holding 4 bytes per value.
+pub(super) fn ycbcr_to_bgra(y: &[i16], cb: &[i16], cr: &[i16], output: &mut [u8]) -> usize {
+    debug_assert!(y.len() == cb.len() && y.len() == cr.len() && output.len() == y.len() * 4);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is supported by the CPU.
+            return unsafe { x86_64::ycbcr_to_bgra_avx2(y, cb, cr, output) };
+        }
+
+        x86_64::ycbcr_to_bgra_sse2(y, cb, cr, output)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        aarch64::ycbcr_to_bgra(y, cb, cr, output)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        wasm32::ycbcr_to_bgra(y, cb, cr, output)
+    }
+
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )))]
+    {
+        let _ = (y, cb, cr, output);
+        0
+    }
+}
+
+/// Converts the little-endian 16-bit RDP colors to RGBA, `output` holding 4 bytes per 2 bytes of `input`.
+pub(super) fn rdp_16bit_to_rgba(input: &[u8], output: &mut [u8]) -> usize {
+    debug_assert!(input.len() % 2 == 0 && output.len() == input.len() * 2);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is supported by the CPU.
+            return unsafe { x86_64::rdp_16bit_to_rgba_avx2(input, output) };
+        }
+
+        x86_64::rdp_16bit_to_rgba_sse2(input, output)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        aarch64::rdp_16bit_to_rgba(input, output)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        wasm32::rdp_16bit_to_rgba(input, output)
+    }
+
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )))]
+    {
+        let _ = (input, output);
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{rdp_16bit_to_rgb, Rgb, YCbCr, ALPHA};
+
+    type YCbCrToBgra = fn(&[i16], &[i16], &[i16], &mut [u8]) -> usize;
+    type Rdp16BitToRgba = fn(&[u8], &mut [u8]) -> usize;
+
+    /// Every SIMD path supported by the host, including the ones not picked by the dispatching functions.
+    fn ycbcr_to_bgra_paths() -> Vec<(&'static str, YCbCrToBgra)> {
+        #[allow(unused_mut)]
+        let mut paths: Vec<(&'static str, YCbCrToBgra)> = vec![("dispatch", super::ycbcr_to_bgra)];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            paths.push(("sse2", super::x86_64::ycbcr_to_bgra_sse2));
+            if std::is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 is supported by the CPU.
+                paths.push(("avx2", |y, cb, cr, output| unsafe {
+                    super::x86_64::ycbcr_to_bgra_avx2(y, cb, cr, output)
+                }));
+            }
+        }
+
+        paths
+    }
+
+    fn rdp_16bit_to_rgba_paths() -> Vec<(&'static str, Rdp16BitToRgba)> {
+        #[allow(unused_mut)]
+        let mut paths: Vec<(&'static str, Rdp16BitToRgba)> = vec![("dispatch", super::rdp_16bit_to_rgba)];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            paths.push(("sse2", super::x86_64::rdp_16bit_to_rgba_sse2));
+            if std::is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 is supported by the CPU.
+                paths.push(("avx2", |input, output| unsafe {
+                    super::x86_64::rdp_16bit_to_rgba_avx2(input, output)
+                }));
+            }
+        }
+
+        paths
+    }
+
+    /// Xorshift generator, good enough to spread the inputs over the whole value range.
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next_i16(&mut self) -> i16 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 32) as i16
+        }
+    }
+
+    #[test]
+    fn ycbcr_to_bgra_matches_scalar() {
+        let mut rng = Xorshift(0x9e37_79b9_7f4a_7c15);
+
+        for len in 0..200 {
+            // Half of the buffers hold in-range values, the others any value.
+            let mask = if len % 2 == 0 { 0x1fff } else { -1 };
+            let mut values = || (0..len).map(|_| rng.next_i16() & mask).collect::<Vec<_>>();
+            let (y, cb, cr) = (values(), values(), values());
+
+            let expected: Vec<u8> = (0..len)
+                .flat_map(|i| {
+                    let Rgb { r, g, b } = Rgb::from(YCbCr {
+                        y: y[i],
+                        cb: cb[i],
+                        cr: cr[i],
+                    });
+                    [b, g, r, ALPHA]
+                })
+                .collect();
+
+            for (name, path) in ycbcr_to_bgra_paths() {
+                let mut output = vec![0; len * 4];
+                let converted = path(&y, &cb, &cr, &mut output);
+
+                assert!(converted <= len, "{name}");
+                assert_eq!(
+                    output[..converted * 4],
+                    expected[..converted * 4],
+                    "{name}, {len} values"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rdp_16bit_to_rgba_matches_scalar() {
+        let input: Vec<u8> = (0..=u16::MAX).flat_map(u16::to_le_bytes).collect();
+        let expected: Vec<u8> = (0..=u16::MAX)
+            .flat_map(|color| {
+                let [r, g, b] = rdp_16bit_to_rgb(color);
+                [r, g, b, ALPHA]
+            })
+            .collect();
+
+        for (name, path) in rdp_16bit_to_rgba_paths() {
+            // Offset by one pixel, so that the vectors are not aligned.
+            let input = &input[2..];
+            let mut output = vec![0; input.len() * 2];
+            let converted = path(input, &mut output);
+
+            assert!(converted <= input.len() / 2, "{name}");
+            assert_eq!(output[..converted * 4], expected[4..][..converted * 4], "{name}");
+        }
+    }
+}
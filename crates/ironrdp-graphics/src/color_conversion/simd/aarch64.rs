@@ -0,0 +1,94 @@
+// The intrinsics are only unsafe because of the CPU features they require, NEON being part of the aarch64
+// baseline: each loop is a single unsafe block.
+#![allow(clippy::multiple_unsafe_ops_per_block)]
+
+use core::arch::aarch64::*;
+
+use super::super::{CB_B, CB_G, CR_G, CR_R};
+
+const PIXELS: usize = 8;
+
+pub(super) fn ycbcr_to_bgra(y: &[i16], cb: &[i16], cr: &[i16], output: &mut [u8]) -> usize {
+    let chunks = y
+        .chunks_exact(PIXELS)
+        .zip(cb.chunks_exact(PIXELS))
+        .zip(cr.chunks_exact(PIXELS))
+        .zip(output.chunks_exact_mut(PIXELS * 4));
+
+    // SAFETY: NEON is part of the aarch64 baseline, the loads reading 8 values and the stores writing 32 bytes.
+    unsafe {
+        let alpha = vdup_n_u8(0xff);
+
+        for (((y, cb), cr), output) in chunks {
+            let y = vld1q_s16(y.as_ptr());
+            let cb = vld1q_s16(cb.as_ptr());
+            let cr = vld1q_s16(cr.as_ptr());
+
+            let low = ycbcr_to_rgb(vget_low_s16(y), vget_low_s16(cb), vget_low_s16(cr));
+            let high = ycbcr_to_rgb(vget_high_s16(y), vget_high_s16(cb), vget_high_s16(cr));
+
+            let r = vqmovun_s16(vcombine_s16(low.0, high.0));
+            let g = vqmovun_s16(vcombine_s16(low.1, high.1));
+            let b = vqmovun_s16(vcombine_s16(low.2, high.2));
+
+            vst4_u8(output.as_mut_ptr(), uint8x8x4_t(b, g, r, alpha));
+        }
+    }
+
+    y.len() - y.len() % PIXELS
+}
+
+pub(super) fn rdp_16bit_to_rgba(input: &[u8], output: &mut [u8]) -> usize {
+    let chunks = input.chunks_exact(PIXELS * 2).zip(output.chunks_exact_mut(PIXELS * 4));
+
+    // SAFETY: NEON is part of the aarch64 baseline, the loads reading 16 bytes and the stores writing 32 bytes.
+    unsafe {
+        let alpha = vdup_n_u8(0xff);
+
+        for (input, output) in chunks {
+            // The low and high bytes are deinterleaved, which spares an aligned load of the little-endian values.
+            let bytes = vld2_u8(input.as_ptr());
+            let color = vorrq_u16(vmovl_u8(bytes.0), vshll_n_u8::<8>(bytes.1));
+
+            let r = expand_5bit(vshrq_n_u16::<11>(color));
+            let g = vandq_u16(vshrq_n_u16::<5>(color), vdupq_n_u16(0x3f));
+            let g = vshrq_n_u16::<6>(vaddq_u16(vmulq_n_u16(g, 259), vdupq_n_u16(33)));
+            let b = expand_5bit(vandq_u16(color, vdupq_n_u16(0x1f)));
+
+            vst4_u8(
+                output.as_mut_ptr(),
+                uint8x8x4_t(vmovn_u16(r), vmovn_u16(g), vmovn_u16(b), alpha),
+            );
+        }
+    }
+
+    input.len() / 2 - input.len() / 2 % PIXELS
+}
+
+/// Converts 4 YCbCr values to the red, green and blue channels, as 16-bit lanes.
+#[inline]
+fn ycbcr_to_rgb(y: int16x4_t, cb: int16x4_t, cr: int16x4_t) -> (int16x4_t, int16x4_t, int16x4_t) {
+    // SAFETY: NEON is part of the aarch64 baseline.
+    unsafe {
+        let yy = vshlq_n_s32::<16>(vaddq_s32(vmovl_s16(y), vdupq_n_s32(4096)));
+        let cb = vmovl_s16(cb);
+        let cr = vmovl_s16(cr);
+
+        let r = vaddq_s32(yy, vmulq_n_s32(cr, CR_R));
+        let g = vsubq_s32(vsubq_s32(yy, vmulq_n_s32(cb, CB_G)), vmulq_n_s32(cr, CR_G));
+        let b = vaddq_s32(yy, vmulq_n_s32(cb, CB_B));
+
+        (
+            vqmovn_s32(vshrq_n_s32::<21>(r)),
+            vqmovn_s32(vshrq_n_s32::<21>(g)),
+            vqmovn_s32(vshrq_n_s32::<21>(b)),
+        )
+    }
+}
+
+/// Expands the 5-bit lanes to 8 bits.
+#[inline]
+fn expand_5bit(value: uint16x8_t) -> uint16x8_t {
+    // SAFETY: NEON is part of the aarch64 baseline.
+    unsafe { vshrq_n_u16::<6>(vaddq_u16(vmulq_n_u16(value, 527), vdupq_n_u16(23))) }
+}
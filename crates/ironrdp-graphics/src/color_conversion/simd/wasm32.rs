@@ -0,0 +1,110 @@
+// The loads and stores are the only unsafe operations, each block covering the ones of a chunk.
+#![allow(clippy::multiple_unsafe_ops_per_block)]
+
+use core::arch::wasm32::*;
+
+use super::super::{CB_B, CB_G, CR_G, CR_R};
+
+const PIXELS: usize = 8;
+
+pub(super) fn ycbcr_to_bgra(y: &[i16], cb: &[i16], cr: &[i16], output: &mut [u8]) -> usize {
+    let chunks = y
+        .chunks_exact(PIXELS)
+        .zip(cb.chunks_exact(PIXELS))
+        .zip(cr.chunks_exact(PIXELS))
+        .zip(output.chunks_exact_mut(PIXELS * 4));
+
+    for (((y, cb), cr), output) in chunks {
+        // SAFETY: the chunks hold 8 values, and the loads are unaligned.
+        let (y, cb, cr) = unsafe {
+            (
+                v128_load(y.as_ptr().cast()),
+                v128_load(cb.as_ptr().cast()),
+                v128_load(cr.as_ptr().cast()),
+            )
+        };
+
+        let low = ycbcr_to_rgb(
+            i32x4_extend_low_i16x8(y),
+            i32x4_extend_low_i16x8(cb),
+            i32x4_extend_low_i16x8(cr),
+        );
+        let high = ycbcr_to_rgb(
+            i32x4_extend_high_i16x8(y),
+            i32x4_extend_high_i16x8(cb),
+            i32x4_extend_high_i16x8(cr),
+        );
+
+        let (low, high) = interleave(
+            i16x8_narrow_i32x4(low.2, high.2),
+            i16x8_narrow_i32x4(low.1, high.1),
+            i16x8_narrow_i32x4(low.0, high.0),
+        );
+
+        // SAFETY: the chunk holds 32 bytes, and the stores are unaligned.
+        unsafe {
+            v128_store(output.as_mut_ptr().cast(), low);
+            v128_store(output[16..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    y.len() - y.len() % PIXELS
+}
+
+pub(super) fn rdp_16bit_to_rgba(input: &[u8], output: &mut [u8]) -> usize {
+    let chunks = input.chunks_exact(PIXELS * 2).zip(output.chunks_exact_mut(PIXELS * 4));
+
+    for (input, output) in chunks {
+        // SAFETY: the chunk holds 16 bytes, and the load is unaligned.
+        let color = unsafe { v128_load(input.as_ptr().cast()) };
+
+        let r = expand_5bit(u16x8_shr(color, 11));
+        let g = v128_and(u16x8_shr(color, 5), u16x8_splat(0x3f));
+        let g = u16x8_shr(u16x8_add(u16x8_mul(g, u16x8_splat(259)), u16x8_splat(33)), 6);
+        let b = expand_5bit(v128_and(color, u16x8_splat(0x1f)));
+
+        let (low, high) = interleave(r, g, b);
+
+        // SAFETY: the chunk holds 32 bytes, and the stores are unaligned.
+        unsafe {
+            v128_store(output.as_mut_ptr().cast(), low);
+            v128_store(output[16..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    input.len() / 2 - input.len() / 2 % PIXELS
+}
+
+/// Converts 4 YCbCr values to the red, green and blue channels, as 32-bit lanes scaled by >> 21.
+#[inline]
+fn ycbcr_to_rgb(y: v128, cb: v128, cr: v128) -> (v128, v128, v128) {
+    let yy = i32x4_shl(i32x4_add(y, i32x4_splat(4096)), 16);
+
+    let r = i32x4_add(yy, i32x4_mul(cr, i32x4_splat(CR_R)));
+    let g = i32x4_sub(
+        i32x4_sub(yy, i32x4_mul(cb, i32x4_splat(CB_G))),
+        i32x4_mul(cr, i32x4_splat(CR_G)),
+    );
+    let b = i32x4_add(yy, i32x4_mul(cb, i32x4_splat(CB_B)));
+
+    (i32x4_shr(r, 21), i32x4_shr(g, 21), i32x4_shr(b, 21))
+}
+
+/// Expands the 5-bit lanes to 8 bits.
+#[inline]
+fn expand_5bit(value: v128) -> v128 {
+    u16x8_shr(u16x8_add(u16x8_mul(value, u16x8_splat(527)), u16x8_splat(23)), 6)
+}
+
+/// Saturates the 8 16-bit lanes of the channels to 8 bits, and interleaves them with an opaque alpha channel,
+/// returning the first and last 4 pixels.
+#[inline]
+fn interleave(c0: v128, c1: v128, c2: v128) -> (v128, v128) {
+    let c01 = u8x16_narrow_i16x8(c0, c1);
+    let c23 = u8x16_narrow_i16x8(c2, i16x8_splat(0xff));
+
+    (
+        i8x16_shuffle::<0, 8, 16, 24, 1, 9, 17, 25, 2, 10, 18, 26, 3, 11, 19, 27>(c01, c23),
+        i8x16_shuffle::<4, 12, 20, 28, 5, 13, 21, 29, 6, 14, 22, 30, 7, 15, 23, 31>(c01, c23),
+    )
+}
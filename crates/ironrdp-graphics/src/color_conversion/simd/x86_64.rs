@@ -0,0 +1,265 @@
+// The intrinsics are only unsafe because of the CPU features they require, which are checked once by the
+// dispatching functions: each loop is a single unsafe block.
+#![allow(clippy::multiple_unsafe_ops_per_block)]
+// The loads and stores are unaligned.
+#![allow(clippy::cast_ptr_alignment)]
+
+use core::arch::x86_64::*;
+
+use super::super::{CB_B, CB_G, CR_G, CR_R};
+
+const PIXELS: usize = 8;
+
+pub(super) fn ycbcr_to_bgra_sse2(y: &[i16], cb: &[i16], cr: &[i16], output: &mut [u8]) -> usize {
+    let chunks = y
+        .chunks_exact(PIXELS)
+        .zip(cb.chunks_exact(PIXELS))
+        .zip(cr.chunks_exact(PIXELS))
+        .zip(output.chunks_exact_mut(PIXELS * 4));
+
+    // SAFETY: SSE2 is part of the x86_64 baseline, the loads reading 8 values and the stores writing 32 bytes.
+    unsafe {
+        for (((y, cb), cr), output) in chunks {
+            let (r, g, b) = ycbcr_to_rgb_sse2(
+                _mm_loadu_si128(y.as_ptr().cast()),
+                _mm_loadu_si128(cb.as_ptr().cast()),
+                _mm_loadu_si128(cr.as_ptr().cast()),
+            );
+            let (low, high) = interleave(b, g, r, _mm_set1_epi16(0xff));
+
+            _mm_storeu_si128(output.as_mut_ptr().cast(), low);
+            _mm_storeu_si128(output[16..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    y.len() - y.len() % PIXELS
+}
+
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn ycbcr_to_bgra_avx2(y: &[i16], cb: &[i16], cr: &[i16], output: &mut [u8]) -> usize {
+    let chunks = y
+        .chunks_exact(PIXELS)
+        .zip(cb.chunks_exact(PIXELS))
+        .zip(cr.chunks_exact(PIXELS))
+        .zip(output.chunks_exact_mut(PIXELS * 4));
+
+    // SAFETY: AVX2 is supported as per the contract of the function, the loads reading 8 values and the stores
+    // writing 32 bytes.
+    unsafe {
+        for (((y, cb), cr), output) in chunks {
+            let (r, g, b) = ycbcr_to_rgb_avx2(
+                _mm_loadu_si128(y.as_ptr().cast()),
+                _mm_loadu_si128(cb.as_ptr().cast()),
+                _mm_loadu_si128(cr.as_ptr().cast()),
+            );
+            let (low, high) = interleave(b, g, r, _mm_set1_epi16(0xff));
+
+            _mm_storeu_si128(output.as_mut_ptr().cast(), low);
+            _mm_storeu_si128(output[16..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    y.len() - y.len() % PIXELS
+}
+
+pub(super) fn rdp_16bit_to_rgba_sse2(input: &[u8], output: &mut [u8]) -> usize {
+    let chunks = input.chunks_exact(PIXELS * 2).zip(output.chunks_exact_mut(PIXELS * 4));
+
+    // SAFETY: SSE2 is part of the x86_64 baseline, the loads reading 16 bytes and the stores writing 32 bytes.
+    unsafe {
+        for (input, output) in chunks {
+            let color = _mm_loadu_si128(input.as_ptr().cast());
+
+            let r = expand_5bit_sse2(_mm_srli_epi16::<11>(color));
+            let g = _mm_and_si128(_mm_srli_epi16::<5>(color), _mm_set1_epi16(0x3f));
+            let g = _mm_srli_epi16::<6>(_mm_add_epi16(
+                _mm_mullo_epi16(g, _mm_set1_epi16(259)),
+                _mm_set1_epi16(33),
+            ));
+            let b = expand_5bit_sse2(_mm_and_si128(color, _mm_set1_epi16(0x1f)));
+            let (low, high) = interleave(r, g, b, _mm_set1_epi16(0xff));
+
+            _mm_storeu_si128(output.as_mut_ptr().cast(), low);
+            _mm_storeu_si128(output[16..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    input.len() / 2 - input.len() / 2 % PIXELS
+}
+
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn rdp_16bit_to_rgba_avx2(input: &[u8], output: &mut [u8]) -> usize {
+    let chunks = input.chunks_exact(PIXELS * 4).zip(output.chunks_exact_mut(PIXELS * 8));
+
+    // SAFETY: AVX2 is supported as per the contract of the function, the loads reading 32 bytes and the stores
+    // writing 64 bytes.
+    unsafe {
+        for (input, output) in chunks {
+            let color = _mm256_loadu_si256(input.as_ptr().cast());
+
+            let r = expand_5bit_avx2(_mm256_srli_epi16::<11>(color));
+            let g = _mm256_and_si256(_mm256_srli_epi16::<5>(color), _mm256_set1_epi16(0x3f));
+            let g = _mm256_srli_epi16::<6>(_mm256_add_epi16(
+                _mm256_mullo_epi16(g, _mm256_set1_epi16(259)),
+                _mm256_set1_epi16(33),
+            ));
+            let b = expand_5bit_avx2(_mm256_and_si256(color, _mm256_set1_epi16(0x1f)));
+            let a = _mm_set1_epi16(0xff);
+
+            let (low, high) = interleave(
+                _mm256_castsi256_si128(r),
+                _mm256_castsi256_si128(g),
+                _mm256_castsi256_si128(b),
+                a,
+            );
+            _mm_storeu_si128(output.as_mut_ptr().cast(), low);
+            _mm_storeu_si128(output[16..].as_mut_ptr().cast(), high);
+
+            let (low, high) = interleave(
+                _mm256_extracti128_si256::<1>(r),
+                _mm256_extracti128_si256::<1>(g),
+                _mm256_extracti128_si256::<1>(b),
+                a,
+            );
+            _mm_storeu_si128(output[32..].as_mut_ptr().cast(), low);
+            _mm_storeu_si128(output[48..].as_mut_ptr().cast(), high);
+        }
+    }
+
+    input.len() / 2 - input.len() / 2 % (PIXELS * 2)
+}
+
+/// Converts 8 YCbCr values to the red, green and blue channels, as 16-bit lanes.
+#[inline]
+fn ycbcr_to_rgb_sse2(y: __m128i, cb: __m128i, cr: __m128i) -> (__m128i, __m128i, __m128i) {
+    #![allow(clippy::similar_names)] // Named after the scalar code.
+
+    // SAFETY: SSE2 is part of the x86_64 baseline.
+    unsafe {
+        // Only the low 16 bits of Y + 4096 remain once shifted by 16.
+        let y = _mm_add_epi16(y, _mm_set1_epi16(4096));
+        let zero = _mm_setzero_si128();
+        let yy = (_mm_unpacklo_epi16(zero, y), _mm_unpackhi_epi16(zero, y));
+
+        let cr_r = mul_sse2(cr, CR_R);
+        let cb_g = mul_sse2(cb, CB_G);
+        let cr_g = mul_sse2(cr, CR_G);
+        let cb_b = mul_sse2(cb, CB_B);
+
+        let r = (_mm_add_epi32(yy.0, cr_r.0), _mm_add_epi32(yy.1, cr_r.1));
+        let g = (
+            _mm_sub_epi32(_mm_sub_epi32(yy.0, cb_g.0), cr_g.0),
+            _mm_sub_epi32(_mm_sub_epi32(yy.1, cb_g.1), cr_g.1),
+        );
+        let b = (_mm_add_epi32(yy.0, cb_b.0), _mm_add_epi32(yy.1, cb_b.1));
+
+        (
+            _mm_packs_epi32(_mm_srai_epi32::<21>(r.0), _mm_srai_epi32::<21>(r.1)),
+            _mm_packs_epi32(_mm_srai_epi32::<21>(g.0), _mm_srai_epi32::<21>(g.1)),
+            _mm_packs_epi32(_mm_srai_epi32::<21>(b.0), _mm_srai_epi32::<21>(b.1)),
+        )
+    }
+}
+
+/// Multiplies the 8 16-bit lanes by the factor, returning the wrapping 32-bit products of the low and high lanes.
+#[inline]
+fn mul_sse2(value: __m128i, factor: i32) -> (__m128i, __m128i) {
+    // SSE2 has no 32-bit multiplication: the factor is split into high * 2^16 + low, both halves fitting in 16 bits.
+    let high = (factor + 0x8000) >> 16;
+    let low = factor - (high << 16);
+
+    // SAFETY: SSE2 is part of the x86_64 baseline.
+    unsafe {
+        let low = _mm_set1_epi16(low as i16);
+        let low_product = (_mm_mullo_epi16(value, low), _mm_mulhi_epi16(value, low));
+        // Only the low 16 bits of value * high remain once shifted by 16.
+        let high_product = _mm_mullo_epi16(value, _mm_set1_epi16(high as i16));
+        let zero = _mm_setzero_si128();
+
+        (
+            _mm_add_epi32(
+                _mm_unpacklo_epi16(low_product.0, low_product.1),
+                _mm_unpacklo_epi16(zero, high_product),
+            ),
+            _mm_add_epi32(
+                _mm_unpackhi_epi16(low_product.0, low_product.1),
+                _mm_unpackhi_epi16(zero, high_product),
+            ),
+        )
+    }
+}
+
+/// Converts 8 YCbCr values to the red, green and blue channels, as 16-bit lanes.
+#[target_feature(enable = "avx2")]
+unsafe fn ycbcr_to_rgb_avx2(y: __m128i, cb: __m128i, cr: __m128i) -> (__m128i, __m128i, __m128i) {
+    // SAFETY: AVX2 is supported as per the contract of the function.
+    unsafe {
+        let yy = _mm256_slli_epi32::<16>(_mm256_add_epi32(_mm256_cvtepi16_epi32(y), _mm256_set1_epi32(4096)));
+        let cb = _mm256_cvtepi16_epi32(cb);
+        let cr = _mm256_cvtepi16_epi32(cr);
+
+        let r = _mm256_add_epi32(yy, _mm256_mullo_epi32(cr, _mm256_set1_epi32(CR_R)));
+        let g = _mm256_sub_epi32(
+            _mm256_sub_epi32(yy, _mm256_mullo_epi32(cb, _mm256_set1_epi32(CB_G))),
+            _mm256_mullo_epi32(cr, _mm256_set1_epi32(CR_G)),
+        );
+        let b = _mm256_add_epi32(yy, _mm256_mullo_epi32(cb, _mm256_set1_epi32(CB_B)));
+
+        (pack_avx2(r), pack_avx2(g), pack_avx2(b))
+    }
+}
+
+/// Scales the 8 32-bit lanes of the channel by >> 21, into 16-bit lanes.
+#[target_feature(enable = "avx2")]
+unsafe fn pack_avx2(channel: __m256i) -> __m128i {
+    // SAFETY: AVX2 is supported as per the contract of the function.
+    unsafe {
+        let channel = _mm256_srai_epi32::<21>(channel);
+        _mm_packs_epi32(_mm256_castsi256_si128(channel), _mm256_extracti128_si256::<1>(channel))
+    }
+}
+
+/// Expands the 5-bit lanes to 8 bits.
+#[inline]
+fn expand_5bit_sse2(value: __m128i) -> __m128i {
+    // SAFETY: SSE2 is part of the x86_64 baseline.
+    unsafe {
+        _mm_srli_epi16::<6>(_mm_add_epi16(
+            _mm_mullo_epi16(value, _mm_set1_epi16(527)),
+            _mm_set1_epi16(23),
+        ))
+    }
+}
+
+/// Expands the 5-bit lanes to 8 bits.
+#[target_feature(enable = "avx2")]
+unsafe fn expand_5bit_avx2(value: __m256i) -> __m256i {
+    // SAFETY: AVX2 is supported as per the contract of the function.
+    unsafe {
+        _mm256_srli_epi16::<6>(_mm256_add_epi16(
+            _mm256_mullo_epi16(value, _mm256_set1_epi16(527)),
+            _mm256_set1_epi16(23),
+        ))
+    }
+}
+
+/// Saturates the 8 16-bit lanes of the channels to 8 bits, and interleaves them, returning the first and last
+/// 4 pixels.
+#[inline]
+fn interleave(c0: __m128i, c1: __m128i, c2: __m128i, c3: __m128i) -> (__m128i, __m128i) {
+    // SAFETY: SSE2 is part of the x86_64 baseline.
+    unsafe {
+        let c01 = _mm_packus_epi16(c0, c1);
+        let c23 = _mm_packus_epi16(c2, c3);
+        let c01 = _mm_unpacklo_epi8(c01, _mm_srli_si128::<8>(c01));
+        let c23 = _mm_unpacklo_epi8(c23, _mm_srli_si128::<8>(c23));
+
+        (_mm_unpacklo_epi16(c01, c23), _mm_unpackhi_epi16(c01, c23))
+    }
+}
@@ -6,6 +6,7 @@
 #![allow(clippy::cast_possible_wrap)] // FIXME: remove
 #![allow(clippy::cast_sign_loss)] // FIXME: remove
 
+pub mod bitmap_cache;
 pub mod color_conversion;
 pub mod dwt;
 pub mod image_processing;
@@ -15,6 +16,7 @@ pub mod rdp6;
 pub mod rectangle_processing;
 pub mod rle;
 pub mod rlgr;
+pub mod scaling;
 pub mod subband_reconstruction;
 pub mod zgfx;
 
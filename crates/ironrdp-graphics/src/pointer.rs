@@ -18,6 +18,10 @@
 //! - `andMask == 0` -> dst_color Copy pixel from xorMask
 //! - andMask == 1, xorMask == 0(black color) -> Transparent pixel
 //! - andMask == 1, xorMask == 1(white color) -> Pixel is inverted
+//!
+//! 32 bpp xorMasks may carry an alpha channel, in which case the pixels are blended using it and the
+//! andMask is ignored. When all the alpha values are zero, the pixels are opaque and the andMask
+//! applies as for other color depths.
 
 use ironrdp_core::ReadCursor;
 use ironrdp_pdu::pointer::{ColorPointerAttribute, LargePointerAttribute, PointerAttribute};
@@ -37,7 +41,8 @@ pub enum PointerError {
     Pdu(#[from] ironrdp_pdu::PduError),
 }
 
-/// Represents RDP pointer in decoded form (color channels stored as RGBA pre-multiplied values)
+/// Represents RDP pointer in decoded form, as RGBA pixels whose alpha is premultiplied or not depending on the
+/// [`PointerBitmapTarget`]
 #[derive(Debug)]
 pub struct DecodedPointer {
     pub width: u16,
@@ -158,6 +163,9 @@ impl DecodedPointer {
 
         let flip_vertical = data.xor_bpp != 1;
 
+        // Each 32 bpp scanline is 16-bit aligned already, so there is no padding between the pixels.
+        let has_alpha = data.xor_bpp == 32 && data.xor_mask.chunks_exact(4).any(|pixel| pixel[3] != 0);
+
         let and_stride = Stride::from_bits(data.width.into());
         let xor_stride = Stride::from_bits(usize::from(data.width) * usize::from(data.xor_bpp));
 
@@ -213,7 +221,19 @@ impl DecodedPointer {
 
             for col_idx in 0..data.width {
                 let and_bit = bitmask_reader.next_bit(&mut and_stride_cursor);
-                let color = color_reader.next_pixel(&mut xor_stride_cursor);
+                let mut color = color_reader.next_pixel(&mut xor_stride_cursor);
+
+                if has_alpha {
+                    if target.should_premultiply_alpha() {
+                        bitmap_data.extend_from_slice(&premultiply_alpha(color));
+                    } else {
+                        bitmap_data.extend_from_slice(&color);
+                    }
+                    continue;
+                }
+
+                // Without alpha channel, the pixel is opaque.
+                color[3] = 0xff;
 
                 if and_bit == 1 && color == [0, 0, 0, 0xff] {
                     // Force transparent pixel (The only way to get a transparent pixel with
@@ -222,15 +242,6 @@ impl DecodedPointer {
                 } else if and_bit == 1 && color == [0xff, 0xff, 0xff, 0xff] {
                     // Inverted pixel.
                     bitmap_data.extend_from_slice(&compute_inverted_pixel(row_idx, col_idx));
-                } else if target.should_premultiply_alpha() {
-                    // Calculate premultiplied alpha via integer arithmetic
-                    let with_premultiplied_alpha = [
-                        ((color[0] as u16 * color[0] as u16) >> 8) as u8,
-                        ((color[1] as u16 * color[1] as u16) >> 8) as u8,
-                        ((color[2] as u16 * color[2] as u16) >> 8) as u8,
-                        color[3],
-                    ];
-                    bitmap_data.extend_from_slice(&with_premultiplied_alpha);
                 } else {
                     bitmap_data.extend_from_slice(&color);
                 }
@@ -371,6 +382,12 @@ impl ColorStrideReader {
     }
 }
 
+/// Multiplies the color components by the alpha, rounding to the nearest value.
+fn premultiply_alpha([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    let multiply = |component: u8| ((u16::from(component) * u16::from(a) + 127) / 255) as u8;
+    [multiply(r), multiply(g), multiply(b), a]
+}
+
 fn bit_stride_size_align_u8(size_bits: usize) -> usize {
     (size_bits + 7) / 8
 }
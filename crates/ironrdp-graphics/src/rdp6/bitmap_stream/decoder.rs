@@ -3,7 +3,7 @@ use ironrdp_pdu::bitmap::rdp6::{BitmapStream as BitmapStreamPdu, ColorPlaneDefin
 use thiserror::Error;
 
 use crate::color_conversion::Rgb;
-use crate::rdp6::rle::{decompress_8bpp_plane, RleDecodeError};
+use crate::rdp6::rle::{decompress_8bpp_plane, min_compressed_plane_size, RleDecodeError};
 
 #[derive(Debug, Error)]
 pub enum BitmapDecodeError {
@@ -13,6 +13,23 @@ pub enum BitmapDecodeError {
     Rle(#[from] RleDecodeError),
     #[error("color plane data size provided in PDU is not sufficient to reconstruct the bitmap")]
     InvalidUncompressedDataSize,
+    #[error("RLE-compressed color plane data provided in PDU is too small for the bitmap size")]
+    InvalidCompressedDataSize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Rgb24,
+    Rgba32,
+}
+
+impl OutputFormat {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb24 => 3,
+            Self::Rgba32 => 4,
+        }
+    }
 }
 
 /// Implements decoding of RDP6 bitmap stream PDU (see [`BitmapStreamPdu`])
@@ -31,7 +48,10 @@ struct BitmapStreamDecoderImpl<'a> {
     chroma_height: usize,
     full_plane_size: usize,
     chroma_plane_size: usize,
+    /// Size of the alpha plane, zero when the bitmap has none
+    alpha_plane_size: usize,
     uncompressed_planes_size: usize,
+    /// Offsets of the color planes, following the alpha plane if any
     color_plane_offsets: [usize; 3],
 }
 
@@ -42,7 +62,7 @@ struct AYCoCgParams {
 }
 
 impl<'a> BitmapStreamDecoderImpl<'a> {
-    fn init(bitmap: BitmapStreamPdu<'a>, image_width: usize, image_height: usize) -> Self {
+    fn init(bitmap: BitmapStreamPdu<'a>, image_width: usize, image_height: usize) -> Result<Self, BitmapDecodeError> {
         let (chroma_width, chroma_height) = if bitmap.has_subsampled_chroma() {
             // When image is subsampled, chroma plane has half the size of the luma plane, however
             // its size is rounded up to the nearest greater integer, to take into account odd image
@@ -62,9 +82,32 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
             full_plane_size * 3
         };
 
-        let color_plane_offsets = [0, full_plane_size, full_plane_size + chroma_plane_size];
+        // The alpha plane, if any, is decompressed in front of the color planes.
+        let alpha_plane_size = if bitmap.header.use_alpha { full_plane_size } else { 0 };
+        let color_plane_offsets = [
+            alpha_plane_size,
+            alpha_plane_size + full_plane_size,
+            alpha_plane_size + full_plane_size + chroma_plane_size,
+        ];
+
+        // Check the size of the color planes data before allocating anything, as the image size is not bounded by it.
+        if bitmap.header.enable_rle_compression {
+            let min_full_plane_size = min_compressed_plane_size(image_width, image_height);
+            let min_chroma_plane_size = min_compressed_plane_size(chroma_width, chroma_height);
+            let min_size = if bitmap.header.use_alpha {
+                min_full_plane_size * 2 + min_chroma_plane_size * 2
+            } else {
+                min_full_plane_size + min_chroma_plane_size * 2
+            };
+
+            if bitmap.color_panes_data().len() < min_size {
+                return Err(BitmapDecodeError::InvalidCompressedDataSize);
+            }
+        } else if bitmap.color_panes_data().len() < alpha_plane_size + uncompressed_planes_size {
+            return Err(BitmapDecodeError::InvalidUncompressedDataSize);
+        }
 
-        Self {
+        Ok(Self {
             bitmap,
             image_width,
             image_height,
@@ -72,23 +115,26 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
             chroma_height,
             full_plane_size,
             chroma_plane_size,
+            alpha_plane_size,
             uncompressed_planes_size,
             color_plane_offsets,
-        }
+        })
     }
 
+    /// Returns the alpha plane, if any, followed by the color planes.
     fn decompress_planes(&'a self, aux_buffer: &'a mut Vec<u8>) -> Result<&'a [u8], BitmapDecodeError> {
+        let planes_size = self.alpha_plane_size + self.uncompressed_planes_size;
+
         let planes = if self.bitmap.header.enable_rle_compression {
             // We don't care for the previous content, just resize it to fit the data
-            aux_buffer.resize(self.uncompressed_planes_size, 0);
-            let uncompressed_planes_buffer = &mut aux_buffer[..self.uncompressed_planes_size];
+            aux_buffer.resize(planes_size, 0);
+            let uncompressed_planes_buffer = &mut aux_buffer[..planes_size];
 
             let compressed = self.bitmap.color_panes_data();
             let mut src_offset = 0;
 
             // Decompress Alpha plane
             if self.bitmap.header.use_alpha {
-                // Decompress alpha alpha, but discard it (always 0xFF)
                 src_offset += decompress_8bpp_plane(
                     &compressed[src_offset..],
                     uncompressed_planes_buffer,
@@ -121,28 +167,25 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
                 self.chroma_height,
             )?;
 
-            &uncompressed_planes_buffer[..self.uncompressed_planes_size]
+            uncompressed_planes_buffer
         } else {
-            // Discard alpha plane
-            let color_planes_offset = if self.bitmap.header.use_alpha {
-                self.full_plane_size
-            } else {
-                0
-            };
-
-            let expected_data_size = color_planes_offset + self.uncompressed_planes_size;
-
-            if self.bitmap.color_panes_data().len() < expected_data_size {
-                return Err(BitmapDecodeError::InvalidUncompressedDataSize);
-            }
-
-            &self.bitmap.color_panes_data()[color_planes_offset..]
+            // Size was validated in `init`
+            &self.bitmap.color_panes_data()[..planes_size]
         };
 
         Ok(planes)
     }
 
-    fn write_argb_planes_to_rgb24(&self, planes: &[u8], dst: &mut Vec<u8>) {
+    /// Returns the alpha value of the pixel at `idx`, opaque if the bitmap has no alpha plane.
+    fn alpha(&self, planes: &[u8], idx: usize) -> u8 {
+        if self.bitmap.header.use_alpha {
+            planes[idx]
+        } else {
+            0xFF
+        }
+    }
+
+    fn write_argb_planes(&self, planes: &[u8], format: OutputFormat, dst: &mut Vec<u8>) {
         // For ARGB comversion is simple - just copy data in correct order
         let (r_offset, g_offset, b_offset) = (
             self.color_plane_offsets[0],
@@ -157,11 +200,14 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
         for i in 0..self.full_plane_size {
             let (r, g, b) = (r_plane[i], g_plane[i], b_plane[i]);
 
-            dst.extend_from_slice(&[r, g, b]);
+            match format {
+                OutputFormat::Rgb24 => dst.extend_from_slice(&[r, g, b]),
+                OutputFormat::Rgba32 => dst.extend_from_slice(&[r, g, b, self.alpha(planes, i)]),
+            }
         }
     }
 
-    fn write_aycocg_planes_to_rgb24(&self, params: AYCoCgParams, planes: &[u8], dst: &mut Vec<u8>) {
+    fn write_aycocg_planes(&self, params: AYCoCgParams, planes: &[u8], format: OutputFormat, dst: &mut Vec<u8>) {
         #![allow(clippy::similar_names)] // It’s hard to find better names for co, cg, etc.
         let sample_shift = params.chroma_subsampling as usize;
 
@@ -187,22 +233,28 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
 
             // As described in 3.1.9.1.2 [MS-RDPEGDI], R and B channels are swapped for
             // AYCoCg when 24-bit image is used (no alpha). We swap them back here
-            if params.alpha {
-                dst.extend_from_slice(&[r, g, b]);
-            } else {
-                dst.extend_from_slice(&[b, g, r]);
+            let (r, b) = if params.alpha { (r, b) } else { (b, r) };
+
+            match format {
+                OutputFormat::Rgb24 => dst.extend_from_slice(&[r, g, b]),
+                OutputFormat::Rgba32 => dst.extend_from_slice(&[r, g, b, self.alpha(planes, idx)]),
             }
         }
     }
 
-    fn decode(self, dst: &mut Vec<u8>, aux_buffer: &'a mut Vec<u8>) -> Result<(), BitmapDecodeError> {
-        // Reserve enough space for decoded RGB channels data
-        dst.reserve(self.image_height * self.image_width * 3);
+    fn decode(
+        self,
+        format: OutputFormat,
+        dst: &mut Vec<u8>,
+        aux_buffer: &'a mut Vec<u8>,
+    ) -> Result<(), BitmapDecodeError> {
+        // Reserve enough space for decoded data
+        dst.reserve(self.full_plane_size * format.bytes_per_pixel());
 
         match self.bitmap.header.color_plane_definition {
             ColorPlaneDefinition::Argb => {
-                let color_planes = self.decompress_planes(aux_buffer)?;
-                self.write_argb_planes_to_rgb24(color_planes, dst);
+                let planes = self.decompress_planes(aux_buffer)?;
+                self.write_argb_planes(planes, format, dst);
             }
             ColorPlaneDefinition::AYCoCg {
                 color_loss_level,
@@ -214,8 +266,8 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
                     chroma_subsampling: use_chroma_subsampling,
                     alpha: self.bitmap.header.use_alpha,
                 };
-                let color_planes = self.decompress_planes(aux_buffer)?;
-                self.write_aycocg_planes_to_rgb24(params, color_planes, dst);
+                let planes = self.decompress_planes(aux_buffer)?;
+                self.write_aycocg_planes(params, planes, format, dst);
             }
         }
 
@@ -260,11 +312,36 @@ impl BitmapStreamDecoder {
         dst: &mut Vec<u8>,
         image_width: usize,
         image_height: usize,
+    ) -> Result<(), BitmapDecodeError> {
+        self.decode_bitmap_stream(bitmap_data, OutputFormat::Rgb24, dst, image_width, image_height)
+    }
+
+    /// Performs decoding of bitmap stream PDU from `bitmap_data` and writes decoded rgba32
+    /// image to `dst` buffer.
+    ///
+    /// The alpha channel is taken from the alpha plane, or is opaque if the bitmap has none.
+    pub fn decode_bitmap_stream_to_rgba32(
+        &mut self,
+        bitmap_data: &[u8],
+        dst: &mut Vec<u8>,
+        image_width: usize,
+        image_height: usize,
+    ) -> Result<(), BitmapDecodeError> {
+        self.decode_bitmap_stream(bitmap_data, OutputFormat::Rgba32, dst, image_width, image_height)
+    }
+
+    fn decode_bitmap_stream(
+        &mut self,
+        bitmap_data: &[u8],
+        format: OutputFormat,
+        dst: &mut Vec<u8>,
+        image_width: usize,
+        image_height: usize,
     ) -> Result<(), BitmapDecodeError> {
         let bitmap = decode::<BitmapStreamPdu<'_>>(bitmap_data)?;
 
-        let decoder = BitmapStreamDecoderImpl::init(bitmap, image_width, image_height);
+        let decoder = BitmapStreamDecoderImpl::init(bitmap, image_width, image_height)?;
 
-        decoder.decode(dst, &mut self.planes_buffer)
+        decoder.decode(format, dst, &mut self.planes_buffer)
     }
 }
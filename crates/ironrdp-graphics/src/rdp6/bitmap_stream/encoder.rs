@@ -231,7 +231,7 @@ impl BitmapStreamEncoder {
 
         let header = BitmapStreamHeader {
             enable_rle_compression: rle,
-            use_alpha: false,
+            use_alpha: true,
             color_plane_definition: ColorPlaneDefinition::Argb,
         };
 
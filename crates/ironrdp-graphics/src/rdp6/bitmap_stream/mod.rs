@@ -176,4 +176,100 @@ mod tests {
         // RGB (No alpha), with RLE
         encode_decode_test(include_bytes!("../test_assets/64x64_aycocg_rle.bmp"), 64, 64, true);
     }
+
+    /// Returns the RGBA image of `bmp`, with an alpha gradient.
+    fn rgba_buffer_from_bmp(bmp: &[u8], width: usize, height: usize) -> Vec<u8> {
+        buffer_from_bmp(bmp, width, height)
+            .chunks_exact(3)
+            .enumerate()
+            .flat_map(|(idx, rgb)| [rgb[0], rgb[1], rgb[2], (idx % width * 255 / (width - 1)) as u8])
+            .collect()
+    }
+
+    fn encode_decode_alpha_test(bmp: &[u8], width: usize, height: usize, rle: bool) {
+        let image = rgba_buffer_from_bmp(bmp, width, height);
+
+        let mut pdu = vec![0; width * height * 4 + 2];
+        let written = BitmapStreamEncoder::new(width, height)
+            .encode_bitmap_alpha::<RgbAChannels>(&image, &mut pdu, rle)
+            .unwrap();
+
+        let mut actual = Vec::new();
+        BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_rgba32(&pdu[..written], &mut actual, width, height)
+            .unwrap();
+
+        assert_eq!(&image.as_slice(), &actual.as_slice());
+    }
+
+    #[test]
+    fn encode_decode_32x64_rgba_raw() {
+        // ARGB (With alpha), no RLE
+        encode_decode_alpha_test(include_bytes!("../test_assets/32x64_rgb_raw.bmp"), 32, 64, false);
+    }
+
+    #[test]
+    fn encode_decode_32x64_rgba_rle() {
+        // ARGB (With alpha), with RLE
+        encode_decode_alpha_test(include_bytes!("../test_assets/32x64_rgb_raw.bmp"), 32, 64, true);
+    }
+
+    #[test]
+    fn decode_64x35_ycocg_rle_ss_to_rgba32() {
+        // AYCoCg (No alpha), RLE, with chroma subsampling + odd resolution
+        let expected: Vec<u8> = buffer_from_bmp(include_bytes!("../test_assets/64x35_ycocg_rle_ss.bmp"), 64, 35)
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+            .collect();
+
+        let mut actual = Vec::new();
+        BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_rgba32(
+                include_bytes!("../test_assets/64x35_ycocg_rle_ss.bin"),
+                &mut actual,
+                64,
+                35,
+            )
+            .unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn decode_rle_planes_too_small_for_bitmap_size() {
+        let pdu = include_bytes!("../test_assets/64x64_aycocg_rle.bin");
+
+        // Rejected before allocating anything for the (huge) bitmap
+        let mut actual = Vec::new();
+        let error = BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_rgb24(pdu, &mut actual, 0xFFFF, 0xFFFF)
+            .unwrap_err();
+
+        assert!(matches!(error, BitmapDecodeError::InvalidCompressedDataSize));
+        assert_eq!(actual.capacity(), 0);
+    }
+
+    #[test]
+    fn decode_truncated_rle_planes() {
+        let pdu = include_bytes!("../test_assets/64x64_aycocg_rle.bin");
+
+        for len in [pdu.len() / 2, pdu.len() - 1] {
+            let mut actual = Vec::new();
+            let result = BitmapStreamDecoder::default().decode_bitmap_stream_to_rgb24(&pdu[..len], &mut actual, 64, 64);
+
+            assert!(result.is_err(), "truncated to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn decode_truncated_raw_planes() {
+        let pdu = include_bytes!("../test_assets/64x64_ycocg_raw_ss.bin");
+
+        let mut actual = Vec::new();
+        let error = BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_rgba32(&pdu[..pdu.len() - 2], &mut actual, 64, 64)
+            .unwrap_err();
+
+        assert!(matches!(error, BitmapDecodeError::InvalidUncompressedDataSize));
+    }
 }
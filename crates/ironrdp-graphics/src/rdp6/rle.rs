@@ -153,6 +153,12 @@ impl RlePlaneDecoder {
     }
 }
 
+/// Returns the minimum size of an RLE-compressed 8bpp color plane, each segment expanding to at most
+/// [`MAX_DECODED_SEGMENT_SIZE`] bytes.
+pub(crate) fn min_compressed_plane_size(width: usize, height: usize) -> usize {
+    width.div_ceil(MAX_DECODED_SEGMENT_SIZE) * height
+}
+
 /// Performs decompression of 8bpp color plane into slice.
 /// Slice must have enough space for decompressed data.
 /// Size of data written to dst buffer is exactly equal to `width * height`.
@@ -0,0 +1,332 @@
+//! Software scaling of a framebuffer, restricted to the regions updated since the last frame.
+
+use core::f32::consts::PI;
+
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+/// Resampling filter used to scale the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalingMode {
+    /// Nearest neighbor: fast and sharp, but aliased on fractional scale factors.
+    Nearest,
+    /// Bilinear interpolation over the 2×2 closest pixels (widened when downscaling).
+    Bilinear,
+    /// Lanczos resampling with a 3-lobe window: the sharpest result, at the highest cost.
+    Lanczos3,
+}
+
+impl ScalingMode {
+    /// Radius of the filter, in source pixels, when the image is not downscaled.
+    fn support(self) -> f32 {
+        match self {
+            Self::Nearest => 0.5,
+            Self::Bilinear => 1.0,
+            Self::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, distance: f32) -> f32 {
+        let distance = distance.abs();
+
+        match self {
+            Self::Nearest => 1.0,
+            Self::Bilinear => (1.0 - distance).max(0.0),
+            Self::Lanczos3 if distance < 3.0 => sinc(distance) * sinc(distance / 3.0),
+            Self::Lanczos3 => 0.0,
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * PI;
+        x.sin() / x
+    }
+}
+
+/// Source pixels contributing to a destination pixel, along one axis
+#[derive(Debug, Clone, Copy)]
+struct Taps {
+    /// Index of the first contributing source pixel.
+    first: usize,
+    /// Index of the last contributing source pixel.
+    last: usize,
+}
+
+/// Filter weights of every destination pixel, along one axis
+#[derive(Debug, Clone)]
+struct Axis {
+    taps: Vec<Taps>,
+    /// Weights of each destination pixel, `stride` apart.
+    weights: Vec<f32>,
+    stride: usize,
+}
+
+impl Axis {
+    fn new(mode: ScalingMode, source_len: usize, destination_len: usize) -> Self {
+        let ratio = source_len as f32 / destination_len as f32;
+
+        if mode == ScalingMode::Nearest {
+            let taps = (0..destination_len)
+                .map(|destination| {
+                    let source = (((destination as f32 + 0.5) * ratio) as usize).min(source_len - 1);
+                    Taps {
+                        first: source,
+                        last: source,
+                    }
+                })
+                .collect();
+
+            return Self {
+                taps,
+                weights: vec![1.0; destination_len],
+                stride: 1,
+            };
+        }
+
+        // When downscaling, the filter is stretched to cover all the source pixels of a destination pixel.
+        let filter_scale = ratio.max(1.0);
+        let support = mode.support() * filter_scale;
+
+        // The pixel `i` is centered on `i + 0.5`, in both spaces.
+        let taps: Vec<Taps> = (0..destination_len)
+            .map(|destination| {
+                let center = (destination as f32 + 0.5) * ratio;
+                let first = (center - support - 0.5).floor().max(0.0) as usize;
+                let last = ((center + support - 0.5).ceil() as usize).min(source_len - 1);
+                Taps { first, last }
+            })
+            .collect();
+
+        let stride = taps.iter().map(|taps| taps.last - taps.first + 1).max().unwrap_or(1);
+        let mut weights = vec![0.0; destination_len * stride];
+
+        for (destination, (taps, weights)) in taps.iter().zip(weights.chunks_exact_mut(stride)).enumerate() {
+            let center = (destination as f32 + 0.5) * ratio;
+
+            for (source, weight) in (taps.first..=taps.last).zip(weights.iter_mut()) {
+                *weight = mode.weight((source as f32 + 0.5 - center) / filter_scale);
+            }
+
+            // The filter is truncated at the edges of the image, hence the normalization.
+            let sum: f32 = weights.iter().sum();
+            if sum != 0.0 {
+                weights.iter_mut().for_each(|weight| *weight /= sum);
+            }
+        }
+
+        Self { taps, weights, stride }
+    }
+
+    fn weights(&self, destination: usize) -> (Taps, &[f32]) {
+        let taps = self.taps[destination];
+        let start = destination * self.stride;
+        (taps, &self.weights[start..=start + taps.last - taps.first])
+    }
+
+    /// Returns the first and last destination pixels depending on the source pixels from `first` to `last`.
+    fn destination_range(&self, first: usize, last: usize) -> Option<(usize, usize)> {
+        // Both ends of the taps are increasing with the destination pixel.
+        let start = self.taps.partition_point(|taps| taps.last < first);
+        let end = self.taps.partition_point(|taps| taps.first <= last);
+
+        (start < end).then(|| (start, end - 1))
+    }
+}
+
+/// Scaler of a framebuffer of 32-bit pixels, to another size
+///
+/// The four channels of each pixel are filtered independently, so that any 32-bit pixel format is supported.
+#[derive(Debug, Clone)]
+pub struct Scaler {
+    mode: ScalingMode,
+    source_size: (u16, u16),
+    destination_size: (u16, u16),
+    horizontal: Axis,
+    vertical: Axis,
+    /// Rows of the source image, scaled horizontally.
+    intermediate: Vec<[f32; 4]>,
+    /// Source row held by each row of `intermediate`.
+    intermediate_rows: Vec<Option<usize>>,
+    /// Source row being filtered horizontally.
+    source_row: Vec<[f32; 4]>,
+    /// Offsets in `intermediate` and weights of the rows filtered vertically.
+    row_taps: Vec<(usize, f32)>,
+}
+
+impl Scaler {
+    /// # Panics
+    ///
+    /// Panics if one of the sizes is empty.
+    pub fn new(mode: ScalingMode, source_size: (u16, u16), destination_size: (u16, u16)) -> Self {
+        assert!(source_size.0 != 0 && source_size.1 != 0, "empty source image");
+        assert!(
+            destination_size.0 != 0 && destination_size.1 != 0,
+            "empty destination image"
+        );
+
+        Self {
+            mode,
+            source_size,
+            destination_size,
+            horizontal: Axis::new(mode, usize::from(source_size.0), usize::from(destination_size.0)),
+            vertical: Axis::new(mode, usize::from(source_size.1), usize::from(destination_size.1)),
+            intermediate: Vec::new(),
+            intermediate_rows: Vec::new(),
+            source_row: Vec::new(),
+            row_taps: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> ScalingMode {
+        self.mode
+    }
+
+    pub fn source_size(&self) -> (u16, u16) {
+        self.source_size
+    }
+
+    pub fn destination_size(&self) -> (u16, u16) {
+        self.destination_size
+    }
+
+    /// Returns the destination region affected by a change of the source `region`.
+    ///
+    /// The destination region extends past the transformed source region by the radius of the filter, which is
+    /// what has to be rendered again. Returns `None` if `region` is outside of the source image.
+    pub fn destination_region(&self, region: &InclusiveRectangle) -> Option<InclusiveRectangle> {
+        let (left, right) = self.horizontal.destination_range(
+            usize::from(region.left),
+            usize::from(region.right.min(self.source_size.0 - 1)),
+        )?;
+        let (top, bottom) = self.vertical.destination_range(
+            usize::from(region.top),
+            usize::from(region.bottom.min(self.source_size.1 - 1)),
+        )?;
+
+        // The ranges are within the destination image, whose size is a `u16`.
+        Some(InclusiveRectangle {
+            left: left as u16,
+            top: top as u16,
+            right: right as u16,
+            bottom: bottom as u16,
+        })
+    }
+
+    /// Scales the source `region` of `source` into `destination`, and returns the destination region written.
+    ///
+    /// The pixels of `destination` outside of the returned region are left untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `source` or `destination` does not match its size.
+    pub fn scale(
+        &mut self,
+        source: &[u32],
+        destination: &mut [u32],
+        region: &InclusiveRectangle,
+    ) -> Option<InclusiveRectangle> {
+        let source_width = usize::from(self.source_size.0);
+        let destination_width = usize::from(self.destination_size.0);
+
+        assert_eq!(source.len(), source_width * usize::from(self.source_size.1));
+        assert_eq!(
+            destination.len(),
+            destination_width * usize::from(self.destination_size.1)
+        );
+
+        let written = self.destination_region(region)?;
+
+        let left = usize::from(written.left);
+        let right = usize::from(written.right);
+
+        // Each destination pixel is a copy of a source pixel, which does not need any filtering.
+        if self.mode == ScalingMode::Nearest {
+            for y in usize::from(written.top)..=usize::from(written.bottom) {
+                let source_row = &source[self.vertical.taps[y].first * source_width..][..source_width];
+                let destination_row = &mut destination[y * destination_width..][left..=right];
+
+                for (taps, pixel) in self.horizontal.taps[left..=right].iter().zip(destination_row) {
+                    *pixel = source_row[taps.first];
+                }
+            }
+
+            return Some(written);
+        }
+
+        let width = right - left + 1;
+
+        // The rows of the source image scaled horizontally are kept in a ring buffer, holding enough rows for
+        // the vertical filter of one destination row. Consecutive destination rows share most of their source rows.
+        let ring_len = self.vertical.stride;
+        self.intermediate.clear();
+        self.intermediate.resize(ring_len * width, [0.0; 4]);
+        self.intermediate_rows.clear();
+        self.intermediate_rows.resize(ring_len, None);
+
+        let source_columns = self.horizontal.taps[left].first..=self.horizontal.taps[right].last;
+
+        for y in usize::from(written.top)..=usize::from(written.bottom) {
+            let (taps, weights) = self.vertical.weights(y);
+            self.row_taps.clear();
+
+            for (source_y, weight) in (taps.first..=taps.last).zip(weights) {
+                let slot = source_y % ring_len;
+                self.row_taps.push((slot * width, *weight));
+
+                if self.intermediate_rows[slot] == Some(source_y) {
+                    continue;
+                }
+
+                // Each source pixel contributes to several destination pixels, so it is converted only once.
+                let source_row = &source[source_y * source_width..][source_columns.clone()];
+                self.source_row.clear();
+                self.source_row
+                    .extend(source_row.iter().map(|pixel| pixel.to_le_bytes().map(f32::from)));
+
+                let intermediate_row = &mut self.intermediate[slot * width..][..width];
+                for (x, sum) in (left..=right).zip(intermediate_row.iter_mut()) {
+                    let (taps, weights) = self.horizontal.weights(x);
+                    let pixels = &self.source_row[taps.first - source_columns.start()..];
+                    let mut total = [0.0; 4];
+
+                    for (pixel, weight) in pixels.iter().zip(weights) {
+                        accumulate(&mut total, pixel, *weight);
+                    }
+
+                    *sum = total;
+                }
+
+                self.intermediate_rows[slot] = Some(source_y);
+            }
+
+            let destination_row = &mut destination[y * destination_width..][left..=right];
+            for (x, pixel) in destination_row.iter_mut().enumerate() {
+                let mut total = [0.0; 4];
+
+                for (offset, weight) in &self.row_taps {
+                    accumulate(&mut total, &self.intermediate[offset + x], *weight);
+                }
+
+                *pixel = u32::from_le_bytes(total.map(to_channel));
+            }
+        }
+
+        Some(written)
+    }
+}
+
+fn to_channel(value: f32) -> u8 {
+    // The cast saturates the values out of range, which are produced by the negative lobes of the Lanczos filter.
+    (value + 0.5) as u8
+}
+
+// The channels are spelled out, so that the compiler vectorizes the operation.
+fn accumulate(sum: &mut [f32; 4], pixel: &[f32; 4], weight: f32) {
+    sum[0] += pixel[0] * weight;
+    sum[1] += pixel[1] * weight;
+    sum[2] += pixel[2] * weight;
+    sum[3] += pixel[3] * weight;
+}
@@ -0,0 +1,77 @@
+//! Input of the text composed with an input method editor (IME), typed as unicode keyboard events.
+
+use smallvec::SmallVec;
+
+use crate::{Operation, Scancode};
+
+const BACKSPACE: Scancode = Scancode::from_u8(false, 0x0E);
+
+/// State of the IME composition
+///
+/// While a composition is active, the key presses are consumed by the IME and do not reach the remote session:
+/// the composed text is typed once committed, one character at a time.
+#[derive(Debug, Clone, Default)]
+pub struct Composition {
+    active: bool,
+    /// Number of characters typed by the last commits, which the IME may still replace.
+    replaceable: usize,
+}
+
+impl Composition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn start(&mut self) {
+        self.active = true;
+    }
+
+    /// Ends the composition.
+    ///
+    /// Nothing is typed by a cancelled composition, the composed text being only typed by [`Composition::commit`].
+    pub fn end(&mut self) {
+        self.active = false;
+    }
+
+    /// Returns `operation`, unless it is a key press consumed by the IME.
+    ///
+    /// A key or mouse button pressed outside of a composition may move the caret, and the committed text can no
+    /// longer be replaced.
+    pub fn filter(&mut self, operation: Operation) -> Option<Operation> {
+        match operation {
+            Operation::KeyPressed(_) | Operation::UnicodeKeyPressed(_) if self.active => None,
+            Operation::KeyPressed(_) | Operation::UnicodeKeyPressed(_) | Operation::MouseButtonPressed(_) => {
+                self.replaceable = 0;
+                Some(operation)
+            }
+            _ => Some(operation),
+        }
+    }
+
+    /// Returns the operations typing the committed `text`, after erasing the last `replaced` characters previously
+    /// committed.
+    ///
+    /// The erased characters are bounded by the ones typed by the previous commits, so that the text typed
+    /// otherwise is never erased. Each character is pressed and released in turn, the characters outside of the
+    /// Basic Multilingual Plane being typed as a surrogate pair.
+    pub fn commit(&mut self, text: &str, replaced: usize) -> SmallVec<[Operation; 8]> {
+        let replaced = replaced.min(self.replaceable);
+
+        let backspaces =
+            (0..replaced).flat_map(|_| [Operation::KeyPressed(BACKSPACE), Operation::KeyReleased(BACKSPACE)]);
+        let characters = text.chars().flat_map(|character| {
+            [
+                Operation::UnicodeKeyPressed(character),
+                Operation::UnicodeKeyReleased(character),
+            ]
+        });
+
+        self.replaceable = self.replaceable - replaced + text.chars().count();
+
+        backspaces.chain(characters).collect()
+    }
+}
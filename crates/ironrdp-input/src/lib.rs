@@ -11,6 +11,12 @@ use ironrdp_pdu::input::mouse_x::PointerXFlags;
 use ironrdp_pdu::input::{MousePdu, MouseXPdu};
 use smallvec::SmallVec;
 
+mod composition;
+mod shortcut;
+
+pub use self::composition::Composition;
+pub use self::shortcut::{Chord, KeyboardCapturePolicy, Modifiers, ShortcutAction};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum MouseButton {
@@ -131,7 +137,7 @@ pub struct WheelRotations {
     pub rotation_units: i16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
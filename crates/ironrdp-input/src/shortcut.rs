@@ -0,0 +1,191 @@
+//! Matching of the keyboard shortcuts, to decide which key combinations reach the remote session.
+
+use bitflags::bitflags;
+use smallvec::SmallVec;
+
+use crate::{Database, Operation, Scancode};
+
+bitflags! {
+    /// Modifier keys held down, regardless of the side of the keyboard
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Modifiers: u8 {
+        const CTRL = 0x01;
+        const SHIFT = 0x02;
+        const ALT = 0x04;
+        /// Windows key, or Command key on macOS.
+        const META = 0x08;
+    }
+}
+
+/// Scancodes of the modifier keys, the left one of each pair being pressed when a modifier is synthesized.
+const MODIFIER_KEYS: [(Modifiers, Scancode); 8] = [
+    (Modifiers::CTRL, Scancode::from_u8(false, 0x1D)),
+    (Modifiers::CTRL, Scancode::from_u8(true, 0x1D)),
+    (Modifiers::SHIFT, Scancode::from_u8(false, 0x2A)),
+    (Modifiers::SHIFT, Scancode::from_u8(false, 0x36)),
+    (Modifiers::ALT, Scancode::from_u8(false, 0x38)),
+    (Modifiers::ALT, Scancode::from_u8(true, 0x38)),
+    (Modifiers::META, Scancode::from_u8(true, 0x5B)),
+    (Modifiers::META, Scancode::from_u8(true, 0x5C)),
+];
+
+impl Modifiers {
+    /// Returns the modifiers currently pressed in `database`.
+    pub fn from_database(database: &Database) -> Self {
+        MODIFIER_KEYS
+            .iter()
+            .filter(|(_, scancode)| database.is_key_pressed(*scancode))
+            .fold(Self::empty(), |modifiers, (modifier, _)| modifiers | *modifier)
+    }
+
+    /// Returns the modifier of `scancode`, if it is a modifier key.
+    pub fn from_scancode(scancode: Scancode) -> Option<Self> {
+        MODIFIER_KEYS
+            .iter()
+            .find(|(_, modifier_scancode)| *modifier_scancode == scancode)
+            .map(|(modifier, _)| *modifier)
+    }
+}
+
+/// Key pressed while holding down a set of modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub key: Scancode,
+}
+
+impl Chord {
+    pub const fn new(modifiers: Modifiers, key: Scancode) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+/// What to do with a key combination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutAction {
+    /// The key combination is sent to the remote session, and the browser (or the OS) should not handle it.
+    Forward,
+    /// The key combination is not sent to the remote session, and is left to the browser (or the OS).
+    LetBrowserHandle,
+    /// Another key combination is sent to the remote session instead.
+    Remap(Chord),
+}
+
+/// Policy deciding which key combinations are sent to the remote session
+///
+/// The key combinations without a matching rule are forwarded as is.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardCapturePolicy {
+    rules: Vec<(Chord, ShortcutAction)>,
+    modifier_remaps: Vec<(Modifiers, Modifiers)>,
+}
+
+impl KeyboardCapturePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the action for `chord`, replacing the previous one.
+    pub fn set_action(&mut self, chord: Chord, action: ShortcutAction) {
+        match self.rules.iter_mut().find(|(rule_chord, _)| *rule_chord == chord) {
+            Some((_, rule_action)) => *rule_action = action,
+            None => self.rules.push((chord, action)),
+        }
+    }
+
+    /// Substitutes the `to` modifiers to the `from` modifiers in the key combinations without a matching rule.
+    ///
+    /// For instance, mapping `META` to `CTRL` turns Command+C into Ctrl+C for macOS users.
+    pub fn remap_modifier(&mut self, from: Modifiers, to: Modifiers) {
+        self.modifier_remaps.retain(|(remap_from, _)| *remap_from != from);
+        self.modifier_remaps.push((from, to));
+    }
+
+    /// Returns the action for `key` pressed while holding down `modifiers`.
+    ///
+    /// The modifier keys themselves are always forwarded.
+    pub fn action(&self, modifiers: Modifiers, key: Scancode) -> ShortcutAction {
+        if Modifiers::from_scancode(key).is_some() {
+            return ShortcutAction::Forward;
+        }
+
+        let chord = Chord::new(modifiers, key);
+
+        if let Some((_, action)) = self.rules.iter().find(|(rule_chord, _)| *rule_chord == chord) {
+            return *action;
+        }
+
+        let remapped = self
+            .modifier_remaps
+            .iter()
+            .filter(|(from, _)| modifiers.contains(*from))
+            .fold(modifiers, |remapped, (from, to)| remapped.difference(*from) | *to);
+
+        if remapped == modifiers {
+            ShortcutAction::Forward
+        } else {
+            ShortcutAction::Remap(Chord::new(remapped, key))
+        }
+    }
+
+    /// Returns the operations to apply to `database` in place of `operation`.
+    ///
+    /// A remapped key press is turned into a press and release of the target key combination, after which the
+    /// modifiers are restored as they were: the release of the original key is then ignored by the database.
+    pub fn intercept(&self, database: &Database, operation: Operation) -> SmallVec<[Operation; 4]> {
+        let Operation::KeyPressed(key) = operation else {
+            return smallvec::smallvec![operation];
+        };
+
+        let pressed = Modifiers::from_database(database);
+
+        match self.action(pressed, key) {
+            ShortcutAction::Forward => smallvec::smallvec![operation],
+            ShortcutAction::LetBrowserHandle => SmallVec::new(),
+            ShortcutAction::Remap(target) => {
+                let released: SmallVec<[Scancode; 4]> = MODIFIER_KEYS
+                    .iter()
+                    .filter(|(modifier, scancode)| {
+                        !target.modifiers.contains(*modifier) && database.is_key_pressed(*scancode)
+                    })
+                    .map(|(_, scancode)| *scancode)
+                    .collect();
+
+                // The first scancode of each modifier is the left key.
+                let synthesized: SmallVec<[Scancode; 4]> = target
+                    .modifiers
+                    .difference(pressed)
+                    .iter()
+                    .filter_map(|modifier| {
+                        MODIFIER_KEYS
+                            .iter()
+                            .find(|(key_modifier, _)| *key_modifier == modifier)
+                            .map(|(_, scancode)| *scancode)
+                    })
+                    .collect();
+
+                let mut operations = SmallVec::new();
+                operations.extend(released.iter().copied().map(Operation::KeyReleased));
+                operations.extend(synthesized.iter().copied().map(Operation::KeyPressed));
+                operations.push(Operation::KeyPressed(target.key));
+                operations.push(Operation::KeyReleased(target.key));
+                operations.extend(synthesized.iter().copied().map(Operation::KeyReleased));
+                operations.extend(released.iter().copied().map(Operation::KeyPressed));
+
+                operations
+            }
+        }
+    }
+
+    /// Returns the key combinations for which the default handling of the browser should be prevented.
+    ///
+    /// These are the key combinations forwarded or remapped by a rule. The key combinations remapped with
+    /// [`KeyboardCapturePolicy::remap_modifier`] are not listed, as they are only known when pressed.
+    pub fn prevent_default_chords(&self) -> Vec<Chord> {
+        self.rules
+            .iter()
+            .filter(|(_, action)| !matches!(action, ShortcutAction::LetBrowserHandle))
+            .map(|(chord, _)| *chord)
+            .collect()
+    }
+}
@@ -0,0 +1,480 @@
+#![doc = include_str!("../README.md")]
+#![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned as _;
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitInt, LitStr, PathArguments, Type, TypeArray, TypePath,
+};
+
+/// Implements `ironrdp_core::Encode` for a struct with a fixed layout.
+///
+/// See the crate documentation for the supported fields and attributes.
+#[proc_macro_derive(PduEncode, attributes(pdu))]
+pub fn derive_pdu_encode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+
+    Pdu::parse(&input)
+        .map_or_else(syn::Error::into_compile_error, |pdu| pdu.encode_impl())
+        .into()
+}
+
+/// Implements `ironrdp_core::Decode` for a struct with a fixed layout.
+///
+/// See the crate documentation for the supported fields and attributes.
+#[proc_macro_derive(PduDecode, attributes(pdu))]
+pub fn derive_pdu_decode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+
+    Pdu::parse(&input)
+        .map_or_else(syn::Error::into_compile_error, |pdu| pdu.decode_impl())
+        .into()
+}
+
+const INTEGERS: &[(&str, usize)] = &[
+    ("u8", 1),
+    ("u16", 2),
+    ("u32", 4),
+    ("u64", 8),
+    ("u128", 16),
+    ("i8", 1),
+    ("i16", 2),
+    ("i32", 4),
+    ("i64", 8),
+    ("i128", 16),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+struct Integer {
+    ty: Ident,
+    size: usize,
+}
+
+impl Integer {
+    fn from_type(ty: &Type) -> Option<Self> {
+        let Type::Path(TypePath { qself: None, path }) = ty else {
+            return None;
+        };
+        let ident = path.get_ident()?;
+        let (_, size) = INTEGERS.iter().find(|(name, _)| ident == name)?;
+
+        Some(Self {
+            ty: ident.clone(),
+            size: *size,
+        })
+    }
+
+    fn parse(ty: &Type) -> syn::Result<Self> {
+        Self::from_type(ty).ok_or_else(|| syn::Error::new_spanned(ty, "expected an integer type"))
+    }
+
+    fn read(&self, src: &Ident, endianness: Endianness) -> TokenStream2 {
+        let ty = &self.ty;
+        let from_bytes = match endianness {
+            Endianness::Little => quote!(from_le_bytes),
+            Endianness::Big => quote!(from_be_bytes),
+        };
+
+        quote! { #ty::#from_bytes(#src.read_array()) }
+    }
+}
+
+enum Item<'a> {
+    Integer(Integer),
+    Nested(&'a Type),
+}
+
+impl<'a> Item<'a> {
+    fn from_type(ty: &'a Type) -> Self {
+        Integer::from_type(ty).map_or(Self::Nested(ty), Self::Integer)
+    }
+}
+
+enum FieldKind<'a> {
+    Integer(Integer),
+    Bitflags { ty: &'a Type, bits: Integer },
+    Array(&'a Expr),
+    Vec { len_prefix: Integer, item: Item<'a> },
+    Nested(&'a Type),
+    Skip,
+}
+
+struct Field<'a> {
+    ident: &'a Ident,
+    kind: FieldKind<'a>,
+    endianness: Endianness,
+    padding: usize,
+}
+
+struct Pdu<'a> {
+    ident: &'a Ident,
+    name: String,
+    fields: Vec<Field<'a>>,
+}
+
+impl<'a> Pdu<'a> {
+    fn parse(input: &'a DeriveInput) -> syn::Result<Self> {
+        if !input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.generics,
+                "generic PDUs are not supported",
+            ));
+        }
+
+        let Data::Struct(data) = &input.data else {
+            return Err(syn::Error::new(input.span(), "only structs are supported"));
+        };
+
+        let Fields::Named(fields) = &data.fields else {
+            return Err(syn::Error::new_spanned(&data.fields, "only named fields are supported"));
+        };
+
+        let mut name = input.ident.to_string();
+        let mut endianness = Endianness::Little;
+
+        for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("pdu")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("big_endian") {
+                    endianness = Endianness::Big;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported PDU attribute"))
+                }
+            })?;
+        }
+
+        let fields = fields
+            .named
+            .iter()
+            .map(|field| Field::parse(field, endianness))
+            .collect::<syn::Result<_>>()?;
+
+        Ok(Self {
+            ident: &input.ident,
+            name,
+            fields,
+        })
+    }
+
+    fn encode_impl(&self) -> TokenStream2 {
+        let ident = self.ident;
+        let name = &self.name;
+        let dst = Ident::new("dst", Span::mixed_site());
+
+        let writes = self.fields.iter().map(|field| field.encode(&dst, name));
+        let sizes = self.fields.iter().map(Field::size);
+
+        quote! {
+            #[automatically_derived]
+            impl ::ironrdp_core::Encode for #ident {
+                fn encode(&self, #dst: &mut ::ironrdp_core::WriteCursor<'_>) -> ::ironrdp_core::EncodeResult<()> {
+                    ::ironrdp_core::ensure_size!(ctx: #name, in: #dst, size: ::ironrdp_core::Encode::size(self));
+
+                    #(#writes)*
+
+                    ::core::result::Result::Ok(())
+                }
+
+                fn name(&self) -> &'static str {
+                    #name
+                }
+
+                fn size(&self) -> usize {
+                    0 #(+ #sizes)*
+                }
+            }
+        }
+    }
+
+    fn decode_impl(&self) -> TokenStream2 {
+        let ident = self.ident;
+        let name = &self.name;
+        let src = Ident::new("src", Span::mixed_site());
+
+        // Consecutive reads of a known size are checked at once.
+        let mut statements = Vec::new();
+        let mut pending_sizes = Vec::new();
+        let mut pending_reads = Vec::new();
+
+        let flush = |statements: &mut Vec<TokenStream2>,
+                     pending_sizes: &mut Vec<TokenStream2>,
+                     pending_reads: &mut Vec<TokenStream2>| {
+            if !pending_sizes.is_empty() {
+                let sizes = pending_sizes.drain(..);
+                statements.push(quote! {
+                    ::ironrdp_core::ensure_size!(ctx: #name, in: #src, size: 0 #(+ #sizes)*);
+                });
+            }
+            statements.append(pending_reads);
+        };
+
+        for field in &self.fields {
+            let ident = field.ident;
+
+            match &field.kind {
+                FieldKind::Integer(integer) => {
+                    let size = integer.size;
+                    let read = integer.read(&src, field.endianness);
+                    pending_sizes.push(quote!(#size));
+                    pending_reads.push(quote! { let #ident = #read; });
+                }
+                FieldKind::Bitflags { ty, bits } => {
+                    let size = bits.size;
+                    let read = bits.read(&src, field.endianness);
+                    pending_sizes.push(quote!(#size));
+                    pending_reads.push(quote! { let #ident = <#ty>::from_bits_truncate(#read); });
+                }
+                FieldKind::Array(len) => {
+                    pending_sizes.push(quote!(#len));
+                    pending_reads.push(quote! { let #ident = #src.read_array(); });
+                }
+                FieldKind::Vec { len_prefix, item } => {
+                    let count = format_ident!("{}_count", ident, span = Span::mixed_site());
+                    let size = len_prefix.size;
+                    let read = len_prefix.read(&src, field.endianness);
+                    pending_sizes.push(quote!(#size));
+                    pending_reads.push(quote! { let #count = #read; });
+                    flush(&mut statements, &mut pending_sizes, &mut pending_reads);
+
+                    let read_item = match item {
+                        Item::Integer(integer) => {
+                            let size = integer.size;
+                            let read = integer.read(&src, field.endianness);
+                            quote! {{
+                                ::ironrdp_core::ensure_size!(ctx: #name, in: #src, size: #size);
+                                ::core::result::Result::Ok(#read)
+                            }}
+                        }
+                        Item::Nested(ty) => quote! { <#ty as ::ironrdp_core::Decode<'de>>::decode(#src) },
+                    };
+
+                    statements.push(quote! {
+                        let #ident = (0..#count)
+                            .map(|_| #read_item)
+                            .collect::<::ironrdp_core::DecodeResult<_>>()?;
+                    });
+                }
+                FieldKind::Nested(ty) => {
+                    flush(&mut statements, &mut pending_sizes, &mut pending_reads);
+                    statements.push(quote! {
+                        let #ident = <#ty as ::ironrdp_core::Decode<'de>>::decode(#src)?;
+                    });
+                }
+                FieldKind::Skip => {
+                    pending_reads.push(quote! { let #ident = ::core::default::Default::default(); });
+                }
+            }
+
+            if field.padding > 0 {
+                let padding = field.padding;
+                pending_sizes.push(quote!(#padding));
+                pending_reads.push(quote! { #src.advance(#padding); });
+            }
+        }
+
+        flush(&mut statements, &mut pending_sizes, &mut pending_reads);
+
+        let idents = self.fields.iter().map(|field| field.ident);
+
+        quote! {
+            #[automatically_derived]
+            impl<'de> ::ironrdp_core::Decode<'de> for #ident {
+                fn decode(#src: &mut ::ironrdp_core::ReadCursor<'de>) -> ::ironrdp_core::DecodeResult<Self> {
+                    #(#statements)*
+
+                    ::core::result::Result::Ok(Self { #(#idents),* })
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    fn parse(field: &'a syn::Field, pdu_endianness: Endianness) -> syn::Result<Self> {
+        let ident = field.ident.as_ref().expect("named field");
+
+        let mut endianness = pdu_endianness;
+        let mut padding = 0;
+        let mut len_prefix = None;
+        let mut bitflags = None;
+        let mut skip = false;
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("pdu")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("big_endian") {
+                    endianness = Endianness::Big;
+                } else if meta.path.is_ident("little_endian") {
+                    endianness = Endianness::Little;
+                } else if meta.path.is_ident("padding") {
+                    padding = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                } else if meta.path.is_ident("len_prefix") {
+                    len_prefix = Some(meta.value()?.parse::<Type>()?);
+                } else if meta.path.is_ident("bitflags") {
+                    bitflags = Some(meta.value()?.parse::<Type>()?);
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else {
+                    return Err(meta.error("unsupported PDU field attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        let kind = if skip {
+            FieldKind::Skip
+        } else if let Some(bits) = bitflags {
+            FieldKind::Bitflags {
+                ty: &field.ty,
+                bits: Integer::parse(&bits)?,
+            }
+        } else if let Some(item) = vec_item(&field.ty) {
+            let len_prefix = len_prefix.as_ref().ok_or_else(|| {
+                syn::Error::new_spanned(&field.ty, "missing `#[pdu(len_prefix = ...)]` attribute on `Vec` field")
+            })?;
+
+            FieldKind::Vec {
+                len_prefix: Integer::parse(len_prefix)?,
+                item: Item::from_type(item),
+            }
+        } else if let Type::Array(TypeArray { elem, len, .. }) = &field.ty {
+            if Integer::from_type(elem).map_or(true, |integer| integer.ty != "u8") {
+                return Err(syn::Error::new_spanned(elem, "only byte arrays are supported"));
+            }
+
+            FieldKind::Array(len)
+        } else if let Some(integer) = Integer::from_type(&field.ty) {
+            FieldKind::Integer(integer)
+        } else {
+            FieldKind::Nested(&field.ty)
+        };
+
+        if len_prefix.is_some() && !matches!(kind, FieldKind::Vec { .. }) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`#[pdu(len_prefix = ...)]` is only supported on `Vec` fields",
+            ));
+        }
+
+        Ok(Self {
+            ident,
+            kind,
+            endianness,
+            padding,
+        })
+    }
+
+    fn encode(&self, dst: &Ident, name: &str) -> TokenStream2 {
+        let ident = self.ident;
+
+        let write = match &self.kind {
+            FieldKind::Integer(_) => write_integer(dst, quote!(self.#ident), self.endianness),
+            FieldKind::Bitflags { .. } => write_integer(dst, quote!(self.#ident.bits()), self.endianness),
+            FieldKind::Array(_) => quote! { #dst.write_array(self.#ident); },
+            FieldKind::Vec { len_prefix, item } => {
+                let prefix_ty = &len_prefix.ty;
+                let field_name = ident.to_string();
+                let write_count = write_integer(dst, quote!(count), self.endianness);
+                let write_item = match item {
+                    Item::Integer(_) => write_integer(dst, quote!(*item), self.endianness),
+                    Item::Nested(_) => quote! { ::ironrdp_core::Encode::encode(item, #dst)?; },
+                };
+
+                quote! {
+                    let count = #prefix_ty::try_from(self.#ident.len())
+                        .map_err(|_| ::ironrdp_core::invalid_field_err(#name, #field_name, "too many elements"))?;
+                    #write_count
+                    for item in &self.#ident {
+                        #write_item
+                    }
+                }
+            }
+            FieldKind::Nested(_) => quote! { ::ironrdp_core::Encode::encode(&self.#ident, #dst)?; },
+            FieldKind::Skip => TokenStream2::new(),
+        };
+
+        let padding = (self.padding > 0).then(|| {
+            let padding = self.padding;
+            quote! { #dst.write_slice(&[0; #padding]); }
+        });
+
+        quote! {
+            #write
+            #padding
+        }
+    }
+
+    fn size(&self) -> TokenStream2 {
+        let ident = self.ident;
+        let padding = self.padding;
+
+        let size = match &self.kind {
+            FieldKind::Integer(integer) => {
+                let size = integer.size;
+                quote!(#size)
+            }
+            FieldKind::Bitflags { bits, .. } => {
+                let size = bits.size;
+                quote!(#size)
+            }
+            FieldKind::Array(len) => quote!(#len),
+            FieldKind::Vec { len_prefix, item } => {
+                let prefix_size = len_prefix.size;
+                match item {
+                    Item::Integer(integer) => {
+                        let size = integer.size;
+                        quote!(#prefix_size + self.#ident.len() * #size)
+                    }
+                    Item::Nested(_) => quote! {
+                        #prefix_size + self.#ident.iter().map(::ironrdp_core::Encode::size).sum::<usize>()
+                    },
+                }
+            }
+            FieldKind::Nested(_) => quote!(::ironrdp_core::Encode::size(&self.#ident)),
+            FieldKind::Skip => quote!(0),
+        };
+
+        quote!((#size + #padding))
+    }
+}
+
+/// Writes an integer, whatever its type.
+fn write_integer(dst: &Ident, value: TokenStream2, endianness: Endianness) -> TokenStream2 {
+    let to_bytes = match endianness {
+        Endianness::Little => quote!(to_le_bytes),
+        Endianness::Big => quote!(to_be_bytes),
+    };
+
+    quote! { #dst.write_array((#value).#to_bytes()); }
+}
+
+/// Returns the type of the elements if `ty` is a `Vec`.
+fn vec_item(ty: &Type) -> Option<&Type> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+
+    if segment.ident != "Vec" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first()? {
+        GenericArgument::Type(item) if arguments.args.len() == 1 => Some(item),
+        _ => None,
+    }
+}
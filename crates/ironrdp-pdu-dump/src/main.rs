@@ -0,0 +1,155 @@
+#![allow(clippy::print_stdout)] // printing the log is the purpose of this tool
+
+use core::fmt::Write as _;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use ironrdp_async::{PduLogReader, PduLogRecord, TraceDirection};
+use ironrdp_connector::sspi::credssp::TsRequest;
+use ironrdp_core::decode;
+use ironrdp_pdu::rdp::headers::ShareControlHeader;
+use ironrdp_pdu::x224::{X224Data, X224};
+use ironrdp_pdu::{mcs, nego, Action};
+
+const DER_SEQUENCE_TAG: u8 = 0x30;
+
+const HELP: &str = "\
+Pretty-prints a PDU log written by the IronRDP sequence drivers
+
+USAGE:
+  ironrdp-pdu-dump [--hex] <FILE>
+
+FLAGS:
+  -h, --help  Prints help information
+  --hex       Prints the raw bytes of each PDU
+";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = pico_args::Arguments::from_env();
+
+    if args.contains(["-h", "--help"]) {
+        print!("{HELP}");
+        return Ok(());
+    }
+
+    let hex = args.contains("--hex");
+    let path: PathBuf = args.free_from_str().context("missing log file")?;
+
+    let file = File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+    let reader = PduLogReader::new(BufReader::new(file)).context("failed to read the log header")?;
+
+    let mut first_timestamp = None;
+
+    for (index, record) in reader.enumerate() {
+        let record = record.with_context(|| format!("failed to read record #{index}"))?;
+        let elapsed = record.timestamp - *first_timestamp.get_or_insert(record.timestamp);
+
+        print_record(index, elapsed, &record);
+
+        if hex {
+            print!("{}", hex_dump(&record.bytes));
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
+fn print_record(index: usize, elapsed_micros: u64, record: &PduLogRecord) {
+    let direction = match record.direction {
+        TraceDirection::Sent => "sent",
+        TraceDirection::Received => "received",
+    };
+
+    print!(
+        "#{index} +{}.{:03}ms {direction} [{}] {} bytes",
+        elapsed_micros / 1000,
+        elapsed_micros % 1000,
+        record.state,
+        record.bytes.len()
+    );
+
+    if !record.hint.is_empty() {
+        print!(" (hint: {})", record.hint);
+    }
+
+    println!();
+
+    match describe(record) {
+        Some(description) => println!("{description}"),
+        None => println!("<unknown PDU>"),
+    }
+}
+
+/// Returns the pretty-printed PDU, if it could be decoded.
+fn describe(record: &PduLogRecord) -> Option<String> {
+    let bytes = record.bytes.as_slice();
+
+    // CredSSP messages are not framed by a TPKT header, and their DER SEQUENCE tag would be mistaken for a
+    // Fast-Path header.
+    if bytes.first() == Some(&DER_SEQUENCE_TAG) {
+        if let Ok(ts_request) = TsRequest::from_buffer(bytes) {
+            return Some(format!("{ts_request:#?}"));
+        }
+    }
+
+    match ironrdp_pdu::find_size(bytes).ok()?? {
+        info if info.action == Action::X224 => describe_x224(bytes),
+        _ => Some("Fast-Path PDU".to_owned()),
+    }
+}
+
+fn describe_x224(bytes: &[u8]) -> Option<String> {
+    if let Ok(X224(request)) = decode::<X224<nego::ConnectionRequest>>(bytes) {
+        return Some(format!("{request:#?}"));
+    }
+
+    if let Ok(X224(confirm)) = decode::<X224<nego::ConnectionConfirm>>(bytes) {
+        return Some(format!("{confirm:#?}"));
+    }
+
+    if let Ok(X224(message)) = decode::<X224<mcs::McsMessage<'_>>>(bytes) {
+        let user_data = match &message {
+            mcs::McsMessage::SendDataRequest(request) => Some(request.user_data.as_ref()),
+            mcs::McsMessage::SendDataIndication(indication) => Some(indication.user_data.as_ref()),
+            _ => None,
+        };
+
+        // Share control PDUs are the most common payload, other ones are printed as raw user data.
+        let share_control = user_data.and_then(|user_data| decode::<ShareControlHeader>(user_data).ok());
+
+        return Some(match share_control {
+            Some(share_control) => format!("{message:?}\n{share_control:#?}"),
+            None => format!("{message:#?}"),
+        });
+    }
+
+    let X224(data) = decode::<X224<X224Data<'_>>>(bytes).ok()?;
+
+    if let Ok(connect_initial) = decode::<mcs::ConnectInitial>(&data.data) {
+        return Some(format!("{connect_initial:#?}"));
+    }
+
+    decode::<mcs::ConnectResponse>(&data.data)
+        .ok()
+        .map(|connect_response| format!("{connect_response:#?}"))
+}
+
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(dump, "  {:08x} ", line * 16);
+
+        for byte in chunk {
+            let _ = write!(dump, " {byte:02x}");
+        }
+
+        dump.push('\n');
+    }
+
+    dump
+}
@@ -4,8 +4,8 @@ mod tests;
 use bit_field::BitField;
 use bitflags::bitflags;
 use ironrdp_core::{
-    decode_cursor, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeError, DecodeResult, Encode,
-    EncodeResult, InvalidFieldErr, ReadCursor, WriteCursor,
+    decode_cursor, ensure_fixed_part_size, ensure_size, invalid_field_err, not_enough_bytes_err, Decode, DecodeError,
+    DecodeResult, Encode, EncodeResult, InvalidFieldErr, ReadCursor, WriteCursor,
 };
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
@@ -39,7 +39,9 @@ impl FastPathHeader {
     }
 
     fn minimal_size(&self) -> usize {
-        Self::FIXED_PART_SIZE + per::sizeof_length(self.data_length as u16)
+        // The length field also covers the header, so the short form is used when the whole PDU fits in it.
+        let short_form_length = self.data_length + Self::FIXED_PART_SIZE + 1;
+        Self::FIXED_PART_SIZE + per::sizeof_length(u16::try_from(short_form_length).unwrap_or(u16::MAX))
     }
 }
 
@@ -87,24 +89,28 @@ impl<'de> Decode<'de> for FastPathHeader {
         let header = src.read_u8();
         let flags = EncryptionFlags::from_bits_truncate(header.get_bits(6..8));
 
-        let (length, sizeof_length) = per::read_length(src).map_err(|e| {
-            DecodeError::invalid_field("", "length", "Invalid encoded fast path PDU length").with_source(e)
+        // The length may use the two-byte form even when it fits in one byte.
+        let (length, sizeof_length) = per::read_length(src).map_err(|e| match e {
+            per::PerError::NotEnoughBytes { available, required } => {
+                not_enough_bytes_err!(Self::NAME, available, required)
+            }
+            e => {
+                DecodeError::invalid_field(Self::NAME, "length", "invalid encoded fast-path PDU length").with_source(e)
+            }
         })?;
         if (length as usize) < sizeof_length + Self::FIXED_PART_SIZE {
             return Err(invalid_field_err!(
                 "length",
-                "received fastpath PDU length is smaller than header size"
+                "fast-path PDU length is smaller than the header size"
             ));
         }
         let data_length = length as usize - sizeof_length - Self::FIXED_PART_SIZE;
+
+        let mut header = FastPathHeader::new(flags, data_length);
         // Detect case, when received packet has non-optimal packet length packing
-        let forced_long_length = per::sizeof_length(length) != sizeof_length;
+        header.forced_long_length = header.minimal_size() != Self::FIXED_PART_SIZE + sizeof_length;
 
-        Ok(FastPathHeader {
-            flags,
-            data_length,
-            forced_long_length,
-        })
+        Ok(header)
     }
 }
 
@@ -1,4 +1,4 @@
-use ironrdp_core::{decode, encode};
+use ironrdp_core::{decode, encode, DecodeErrorKind};
 use lazy_static::lazy_static;
 
 use super::*;
@@ -140,3 +140,60 @@ fn to_buffer_correctly_serializes_fast_path_update() {
 fn buffer_length_is_correct_for_fast_path_update() {
     assert_eq!(FAST_PATH_UPDATE_PDU_BUFFER.len(), FAST_PATH_UPDATE_PDU.size());
 }
+
+#[test]
+fn fast_path_header_length_boundaries() {
+    let cases: [(&[u8], usize, bool); 8] = [
+        // The largest length fitting in one byte.
+        (&[0x80, 0x7f], 0x7f - 2, false),
+        (&[0x80, 0x80, 0x7f], 0x7f - 3, true),
+        // The same payload would fit with the one-byte form, for a length of 0x7f.
+        (&[0x80, 0x80, 0x80], 0x80 - 3, true),
+        // The smallest payload requiring the two-byte form.
+        (&[0x80, 0x80, 0x81], 0x81 - 3, false),
+        (&[0x80, 0xbf, 0xff], 0x3fff - 3, false),
+        (&[0x80, 0xff, 0xff], 0x7fff - 3, false),
+        // Zero-payload frames.
+        (&[0x80, 0x02], 0, false),
+        (&[0x80, 0x80, 0x03], 0, true),
+    ];
+
+    for (buffer, data_length, forced_long_length) in cases {
+        let header = decode::<FastPathHeader>(buffer).unwrap();
+        assert_eq!(
+            header,
+            FastPathHeader {
+                flags: EncryptionFlags::ENCRYPTED,
+                data_length,
+                forced_long_length,
+            }
+        );
+
+        // The length encoding is preserved.
+        let mut encoded = vec![0; header.size()];
+        encode(&header, encoded.as_mut_slice()).unwrap();
+        assert_eq!(encoded, buffer);
+    }
+}
+
+#[test]
+fn fast_path_header_with_length_smaller_than_header_is_rejected() {
+    for buffer in [&[0x80, 0x00][..], &[0x80, 0x01], &[0x80, 0x80, 0x02]] {
+        let error = decode::<FastPathHeader>(buffer).unwrap_err();
+        assert!(
+            matches!(error.kind(), DecodeErrorKind::InvalidField { field: "length", .. }),
+            "{error:?}"
+        );
+    }
+}
+
+#[test]
+fn truncated_fast_path_header_is_rejected() {
+    for buffer in [&[0x80][..], &[0x80, 0x80]] {
+        let error = decode::<FastPathHeader>(buffer).unwrap_err();
+        assert!(
+            matches!(error.kind(), DecodeErrorKind::NotEnoughBytes { .. }),
+            "{error:?}"
+        );
+    }
+}
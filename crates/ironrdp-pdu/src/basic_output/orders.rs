@@ -0,0 +1,458 @@
+//! Drawing orders ([MS-RDPEGDI]), received in the orders updates
+//!
+//! Only the orders rendering out of the revision 2 bitmap cache are supported: the MemBlt and Mem3Blt primary orders,
+//! and the Cache Bitmap (Revision 2) secondary orders filling the cache. The other secondary orders are skipped, while
+//! the other primary orders can't be, their length being unknown without decoding them.
+//!
+//! [MS-RDPEGDI]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpegdi/745f2eee-d110-464c-8aca-06fc1814f6ad
+
+use bitflags::bitflags;
+use ironrdp_core::{ensure_size, invalid_field_err, unsupported_value_err, DecodeResult, ReadCursor};
+
+const TS_ENC_MEMBLT_ORDER: u8 = 0x0D;
+const TS_ENC_MEM3BLT_ORDER: u8 = 0x0E;
+
+const TS_CACHE_BITMAP_UNCOMPRESSED_REV2: u8 = 0x04;
+const TS_CACHE_BITMAP_COMPRESSED_REV2: u8 = 0x05;
+
+/// Size of the fields of the secondary order header following the orderLength field.
+const SECONDARY_ORDER_LENGTH_ADJUSTMENT: i32 = 7;
+
+bitflags! {
+    /// Control flags of a drawing order (controlFlags)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ControlFlags: u8 {
+        const STANDARD = 0x01;
+        const SECONDARY = 0x02;
+        const BOUNDS = 0x04;
+        const TYPE_CHANGE = 0x08;
+        const DELTA_COORDINATES = 0x10;
+        const ZERO_BOUNDS_DELTAS = 0x20;
+        const ZERO_FIELD_BYTE_BIT0 = 0x40;
+        const ZERO_FIELD_BYTE_BIT1 = 0x80;
+    }
+}
+
+bitflags! {
+    /// Flags of the Cache Bitmap (Revision 2) order, stored in the high bits of its extraFlags field
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CacheBitmapRev2Flags: u16 {
+        const HEIGHT_SAME_AS_WIDTH = 0x01;
+        const PERSISTENT_KEY_PRESENT = 0x02;
+        const NO_BITMAP_COMPRESSION_HDR = 0x08;
+        const DO_NOT_CACHE = 0x10;
+        const _ = !0;
+    }
+}
+
+/// Drawing order decoded by [`OrdersDecoder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawingOrder {
+    Primary {
+        order: PrimaryOrder,
+        /// Clipping rectangle of the order, if any.
+        bounds: Option<Bounds>,
+    },
+    CacheBitmapRev2(CacheBitmapRev2),
+    /// Secondary order of another type, skipped.
+    UnsupportedSecondary {
+        order_type: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrimaryOrder {
+    MemBlt(MemBlt),
+    Mem3Blt(Mem3Blt),
+}
+
+/// Inclusive clipping rectangle of a primary order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+/// Blits a bitmap from the bitmap cache to the screen (MEMBLT_ORDER)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemBlt {
+    /// ID of the bitmap cache in the low byte, and index of the color table in the high byte.
+    pub cache_id: u16,
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    /// Ternary raster operation combining the bitmap with the screen.
+    pub rop: u8,
+    pub src_x: i16,
+    pub src_y: i16,
+    pub cache_index: u16,
+}
+
+/// Blits a bitmap from the bitmap cache to the screen, combined with a brush (MEM3BLT_ORDER)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mem3Blt {
+    /// Same fields as the MemBlt order.
+    pub blt: MemBlt,
+    /// Background color, in the color depth of the session.
+    pub back_color: [u8; 3],
+    /// Foreground color, in the color depth of the session.
+    pub fore_color: [u8; 3],
+    pub brush: Brush,
+}
+
+/// Brush of a Mem3Blt order (BRUSH)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Brush {
+    pub org_x: u8,
+    pub org_y: u8,
+    pub style: u8,
+    pub hatch: u8,
+    pub extra: [u8; 7],
+}
+
+impl Brush {
+    /// Solid color brush (BS_SOLID), painting with the foreground color.
+    pub const STYLE_SOLID: u8 = 0x00;
+}
+
+/// Bitmap to store in the revision 2 bitmap cache (CACHE_BITMAP_REV2_ORDER)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheBitmapRev2 {
+    pub cache_id: u8,
+    pub bits_per_pixel: u8,
+    pub flags: CacheBitmapRev2Flags,
+    /// Persistent key of the bitmap, made of the key1 and key2 fields.
+    pub key: Option<u64>,
+    pub width: u16,
+    pub height: u16,
+    pub cache_index: u16,
+    /// Whether the bitmap is compressed, with the interleaved RLE at 8 to 24 bpp, or the planar codec at 32 bpp.
+    pub compressed: bool,
+    /// Bottom-up bitmap, without the compression header.
+    pub bitmap_data: Vec<u8>,
+}
+
+impl CacheBitmapRev2 {
+    const NAME: &'static str = "CACHE_BITMAP_REV2_ORDER";
+
+    /// Index of the waiting list, on which the bitmaps are not cached.
+    pub const WAITING_LIST_INDEX: u16 = 0x7FFF;
+
+    fn decode(src: &mut ReadCursor<'_>, compressed: bool, extra_flags: u16) -> DecodeResult<Self> {
+        let cache_id = (extra_flags & 0x0007) as u8;
+        let bits_per_pixel = match (extra_flags & 0x0078) >> 3 {
+            3 => 8,
+            4 => 16,
+            5 => 24,
+            6 => 32,
+            _ => return Err(invalid_field_err!("bitsPerPixelId", "invalid bits per pixel")),
+        };
+        let flags = CacheBitmapRev2Flags::from_bits_retain(extra_flags >> 7);
+
+        let key = if flags.contains(CacheBitmapRev2Flags::PERSISTENT_KEY_PRESENT) {
+            ensure_size!(ctx: Self::NAME, in: src, size: 8);
+            let key1 = src.read_u32();
+            let key2 = src.read_u32();
+            Some(u64::from(key2) << 32 | u64::from(key1))
+        } else {
+            None
+        };
+
+        let width = read_two_byte_unsigned(src)?;
+        let height = if flags.contains(CacheBitmapRev2Flags::HEIGHT_SAME_AS_WIDTH) {
+            width
+        } else {
+            read_two_byte_unsigned(src)?
+        };
+        let mut bitmap_length = read_four_byte_unsigned(src)? as usize;
+        let cache_index = read_two_byte_unsigned(src)?;
+
+        if compressed && !flags.contains(CacheBitmapRev2Flags::NO_BITMAP_COMPRESSION_HDR) {
+            ensure_size!(ctx: Self::NAME, in: src, size: 8);
+            let _first_row_size = src.read_u16();
+            let main_body_size = src.read_u16();
+            let _scan_width = src.read_u16();
+            let _uncompressed_size = src.read_u16();
+            bitmap_length = usize::from(main_body_size);
+        }
+
+        ensure_size!(ctx: Self::NAME, in: src, size: bitmap_length);
+        let bitmap_data = src.read_slice(bitmap_length).to_vec();
+
+        Ok(Self {
+            cache_id,
+            bits_per_pixel,
+            flags,
+            key,
+            width,
+            height,
+            cache_index,
+            compressed,
+            bitmap_data,
+        })
+    }
+}
+
+/// Decodes the drawing orders of the orders updates
+///
+/// The primary orders are encoded as differences from the previous ones, so a single decoder must be used for all
+/// the orders of a session.
+#[derive(Debug, Clone, Default)]
+pub struct OrdersDecoder {
+    order_type: Option<u8>,
+    bounds: Bounds,
+    mem_blt: MemBlt,
+    mem3_blt: Mem3Blt,
+}
+
+impl OrdersDecoder {
+    const NAME: &'static str = "TS_ORDER";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a primary or secondary drawing order.
+    ///
+    /// Fails for the alternate secondary orders, and for the primary orders of the unsupported types: the orders
+    /// following them can't be located, nor decoded, anymore.
+    pub fn decode(&mut self, src: &mut ReadCursor<'_>) -> DecodeResult<DrawingOrder> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 1);
+        let control_flags = ControlFlags::from_bits_retain(src.read_u8());
+
+        if !control_flags.contains(ControlFlags::STANDARD) {
+            return Err(unsupported_value_err!(
+                Self::NAME,
+                "controlFlags",
+                "alternate secondary order".to_owned()
+            ));
+        }
+
+        if control_flags.contains(ControlFlags::SECONDARY) {
+            Self::decode_secondary(src)
+        } else {
+            self.decode_primary(src, control_flags)
+        }
+    }
+
+    fn decode_secondary(src: &mut ReadCursor<'_>) -> DecodeResult<DrawingOrder> {
+        ensure_size!(ctx: Self::NAME, in: src, size: 2 + 2 + 1);
+        let order_length = src.read_i16();
+        let extra_flags = src.read_u16();
+        let order_type = src.read_u8();
+
+        // The order length is not covering the first 13 bytes of the order.
+        let length = usize::try_from(i32::from(order_length) + SECONDARY_ORDER_LENGTH_ADJUSTMENT)
+            .map_err(|_| invalid_field_err!("orderLength", "invalid secondary order length"))?;
+        ensure_size!(ctx: Self::NAME, in: src, size: length);
+        let mut order = ReadCursor::new(src.read_slice(length));
+
+        match order_type {
+            TS_CACHE_BITMAP_UNCOMPRESSED_REV2 | TS_CACHE_BITMAP_COMPRESSED_REV2 => {
+                let compressed = order_type == TS_CACHE_BITMAP_COMPRESSED_REV2;
+                CacheBitmapRev2::decode(&mut order, compressed, extra_flags).map(DrawingOrder::CacheBitmapRev2)
+            }
+            _ => Ok(DrawingOrder::UnsupportedSecondary { order_type }),
+        }
+    }
+
+    fn decode_primary(&mut self, src: &mut ReadCursor<'_>, control_flags: ControlFlags) -> DecodeResult<DrawingOrder> {
+        if control_flags.contains(ControlFlags::TYPE_CHANGE) {
+            ensure_size!(ctx: Self::NAME, in: src, size: 1);
+            self.order_type = Some(src.read_u8());
+        }
+
+        let order_type = self.order_type.unwrap_or_default();
+        let field_bytes = match order_type {
+            TS_ENC_MEMBLT_ORDER => 2,
+            TS_ENC_MEM3BLT_ORDER => 3,
+            _ => {
+                return Err(unsupported_value_err!(
+                    Self::NAME,
+                    "orderType",
+                    format!("unsupported primary order: {order_type:#04x}")
+                ))
+            }
+        };
+
+        let field_flags = read_field_flags(src, control_flags, field_bytes)?;
+
+        let bounds = if control_flags.contains(ControlFlags::BOUNDS) {
+            if !control_flags.contains(ControlFlags::ZERO_BOUNDS_DELTAS) {
+                self.bounds = read_bounds(src, self.bounds)?;
+            }
+            Some(self.bounds)
+        } else {
+            None
+        };
+
+        let mut fields = FieldReader {
+            src,
+            field_flags,
+            delta: control_flags.contains(ControlFlags::DELTA_COORDINATES),
+        };
+
+        let order = if order_type == TS_ENC_MEMBLT_ORDER {
+            let mut order = self.mem_blt;
+            read_mem_blt(&mut fields, &mut order)?;
+            fields.read_u16(0x0100, &mut order.cache_index)?;
+            self.mem_blt = order;
+            PrimaryOrder::MemBlt(order)
+        } else {
+            let mut order = self.mem3_blt;
+            read_mem_blt(&mut fields, &mut order.blt)?;
+            fields.read_array(0x0100, &mut order.back_color)?;
+            fields.read_array(0x0200, &mut order.fore_color)?;
+            fields.read_u8(0x0400, &mut order.brush.org_x)?;
+            fields.read_u8(0x0800, &mut order.brush.org_y)?;
+            fields.read_u8(0x1000, &mut order.brush.style)?;
+            fields.read_u8(0x2000, &mut order.brush.hatch)?;
+            fields.read_array(0x4000, &mut order.brush.extra)?;
+            fields.read_u16(0x8000, &mut order.blt.cache_index)?;
+            self.mem3_blt = order;
+            PrimaryOrder::Mem3Blt(order)
+        };
+
+        Ok(DrawingOrder::Primary { order, bounds })
+    }
+}
+
+/// Reads the fields shared by the MemBlt and Mem3Blt orders, up to the cache index.
+fn read_mem_blt(fields: &mut FieldReader<'_, '_>, order: &mut MemBlt) -> DecodeResult<()> {
+    fields.read_u16(0x0001, &mut order.cache_id)?;
+    fields.read_coord(0x0002, &mut order.left)?;
+    fields.read_coord(0x0004, &mut order.top)?;
+    fields.read_coord(0x0008, &mut order.width)?;
+    fields.read_coord(0x0010, &mut order.height)?;
+    fields.read_u8(0x0020, &mut order.rop)?;
+    fields.read_coord(0x0040, &mut order.src_x)?;
+    fields.read_coord(0x0080, &mut order.src_y)?;
+
+    Ok(())
+}
+
+/// Reads the fields present in a primary order, the absent ones keeping their previous value
+struct FieldReader<'a, 'de> {
+    src: &'a mut ReadCursor<'de>,
+    field_flags: u32,
+    delta: bool,
+}
+
+impl FieldReader<'_, '_> {
+    fn is_present(&self, field: u32) -> bool {
+        self.field_flags & field != 0
+    }
+
+    fn read_u8(&mut self, field: u32, value: &mut u8) -> DecodeResult<()> {
+        if self.is_present(field) {
+            let src = &mut *self.src;
+            ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: 1);
+            *value = src.read_u8();
+        }
+        Ok(())
+    }
+
+    fn read_u16(&mut self, field: u32, value: &mut u16) -> DecodeResult<()> {
+        if self.is_present(field) {
+            let src = &mut *self.src;
+            ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: 2);
+            *value = src.read_u16();
+        }
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self, field: u32, value: &mut [u8; N]) -> DecodeResult<()> {
+        if self.is_present(field) {
+            let src = &mut *self.src;
+            ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: N);
+            *value = src.read_array();
+        }
+        Ok(())
+    }
+
+    /// Reads a coordinate, either absolute or relative to its previous value.
+    fn read_coord(&mut self, field: u32, value: &mut i16) -> DecodeResult<()> {
+        if self.is_present(field) {
+            *value = read_coord(self.src, self.delta, *value)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_coord(src: &mut ReadCursor<'_>, delta: bool, previous: i16) -> DecodeResult<i16> {
+    if delta {
+        ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: 1);
+        Ok(previous.wrapping_add(i16::from(src.read_u8() as i8)))
+    } else {
+        ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: 2);
+        Ok(src.read_i16())
+    }
+}
+
+fn read_field_flags(src: &mut ReadCursor<'_>, control_flags: ControlFlags, field_bytes: usize) -> DecodeResult<u32> {
+    let mut field_bytes = field_bytes;
+    if control_flags.contains(ControlFlags::ZERO_FIELD_BYTE_BIT0) {
+        field_bytes = field_bytes.saturating_sub(1);
+    }
+    if control_flags.contains(ControlFlags::ZERO_FIELD_BYTE_BIT1) {
+        field_bytes = field_bytes.saturating_sub(2);
+    }
+
+    ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: field_bytes);
+    let field_flags = (0..field_bytes).fold(0, |flags, i| flags | u32::from(src.read_u8()) << (8 * i));
+
+    Ok(field_flags)
+}
+
+/// Reads the bounds of a primary order (TS_BOUNDS), each one being either absolute, relative or unchanged.
+fn read_bounds(src: &mut ReadCursor<'_>, previous: Bounds) -> DecodeResult<Bounds> {
+    ensure_size!(ctx: OrdersDecoder::NAME, in: src, size: 1);
+    let description = src.read_u8();
+
+    let mut read_bound = |absolute: u8, previous: i16| -> DecodeResult<i16> {
+        if description & absolute != 0 {
+            read_coord(src, false, previous)
+        } else if description & (absolute << 4) != 0 {
+            read_coord(src, true, previous)
+        } else {
+            Ok(previous)
+        }
+    };
+
+    Ok(Bounds {
+        left: read_bound(0x01, previous.left)?,
+        top: read_bound(0x02, previous.top)?,
+        right: read_bound(0x04, previous.right)?,
+        bottom: read_bound(0x08, previous.bottom)?,
+    })
+}
+
+/// Reads a 15-bit value, encoded on one or two bytes (TWO_BYTE_UNSIGNED_ENCODING).
+fn read_two_byte_unsigned(src: &mut ReadCursor<'_>) -> DecodeResult<u16> {
+    ensure_size!(ctx: CacheBitmapRev2::NAME, in: src, size: 1);
+    let first = src.read_u8();
+
+    if first & 0x80 == 0 {
+        return Ok(u16::from(first));
+    }
+
+    ensure_size!(ctx: CacheBitmapRev2::NAME, in: src, size: 1);
+    Ok(u16::from(first & 0x7F) << 8 | u16::from(src.read_u8()))
+}
+
+/// Reads a 30-bit value, encoded on one to four bytes (FOUR_BYTE_UNSIGNED_ENCODING).
+fn read_four_byte_unsigned(src: &mut ReadCursor<'_>) -> DecodeResult<u32> {
+    ensure_size!(ctx: CacheBitmapRev2::NAME, in: src, size: 1);
+    let first = src.read_u8();
+    let extra_bytes = usize::from(first >> 6);
+
+    ensure_size!(ctx: CacheBitmapRev2::NAME, in: src, size: extra_bytes);
+    let value = (0..extra_bytes).fold(u32::from(first & 0x3F), |value, _| {
+        value << 8 | u32::from(src.read_u8())
+    });
+
+    Ok(value)
+}
@@ -117,6 +117,31 @@ fn buffer_length_is_correct_for_surface_command_frame_marker() {
     assert_eq!(FRAME_MARKER_BUFFER.len(), FRAME_MARKER_PDU.size());
 }
 
+#[test]
+fn end_frame_marker_round_trips() {
+    let marker = SurfaceCommand::FrameMarker(FrameMarkerPdu {
+        frame_action: FrameAction::End,
+        frame_id: Some(0xDEAD_BEEF),
+    });
+    let mut buffer = vec![0; marker.size()];
+
+    encode(&marker, buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(buffer, [0x4, 0x0, 0x1, 0x0, 0xef, 0xbe, 0xad, 0xde]);
+    assert_eq!(marker, decode::<SurfaceCommand<'_>>(&buffer).unwrap());
+}
+
+#[test]
+fn from_buffer_parses_surface_command_frame_marker_without_frame_id() {
+    assert_eq!(
+        SurfaceCommand::FrameMarker(FrameMarkerPdu {
+            frame_action: FrameAction::End,
+            frame_id: None,
+        }),
+        decode::<SurfaceCommand<'_>>([0x4, 0x0, 0x1, 0x0].as_ref()).unwrap()
+    );
+}
+
 #[test]
 fn from_buffer_correctly_parses_surface_command_bits() {
     assert_eq!(
@@ -0,0 +1,65 @@
+use core::fmt;
+
+/// Strictness of the decoding of the connection sequence PDUs
+///
+/// Some servers and VDI brokers emit slightly non-conformant GCC blocks and capability sets. In lenient mode,
+/// the decoder skips what it can't make sense of instead of failing, and records a [`DecodeWarning`] for each
+/// deviation. Lenient mode never skips data required to carry on with the connection (e.g.: the GCC core block).
+///
+/// Strict mode is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub lenient: bool,
+}
+
+impl DecodeOptions {
+    pub const STRICT: Self = Self { lenient: false };
+
+    pub const LENIENT: Self = Self { lenient: true };
+}
+
+/// Deviation from the specification tolerated when decoding in lenient mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DecodeWarning {
+    /// A GCC user data block of unknown type was kept as a raw block.
+    UnknownGccBlock { block_type: u16 },
+    /// An optional GCC user data block which could not be decoded was skipped.
+    MalformedGccBlock { block_type: u16, reason: String },
+    /// A capability set of unknown type was kept as a raw capability set.
+    UnknownCapabilitySet { capability_set_type: u16 },
+    /// A capability set which could not be decoded was skipped.
+    MalformedCapabilitySet { capability_set_type: u16, reason: String },
+    /// Fewer capability sets than advertised were found.
+    MissingCapabilitySets { expected: usize, found: usize },
+    /// A trailing field absent from the data was ignored.
+    MissingField { context: &'static str, field: &'static str },
+    /// Bytes not belonging to any structure were ignored.
+    TrailingBytes { context: &'static str, count: usize },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownGccBlock { block_type } => write!(f, "kept unknown GCC block (type {block_type:#06x})"),
+            Self::MalformedGccBlock { block_type, reason } => {
+                write!(f, "skipped malformed GCC block (type {block_type:#06x}): {reason}")
+            }
+            Self::UnknownCapabilitySet { capability_set_type } => {
+                write!(f, "kept unknown capability set (type {capability_set_type:#06x})")
+            }
+            Self::MalformedCapabilitySet {
+                capability_set_type,
+                reason,
+            } => write!(
+                f,
+                "skipped malformed capability set (type {capability_set_type:#06x}): {reason}"
+            ),
+            Self::MissingCapabilitySets { expected, found } => {
+                write!(f, "expected {expected} capability sets, found {found}")
+            }
+            Self::MissingField { context, field } => write!(f, "ignored missing {field} field in {context}"),
+            Self::TrailingBytes { context, count } => write!(f, "ignored {count} trailing bytes in {context}"),
+        }
+    }
+}
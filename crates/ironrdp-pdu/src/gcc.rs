@@ -1,14 +1,14 @@
 use std::io;
 
 use ironrdp_core::{
-    cast_length, decode, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeErrorKind, DecodeResult,
-    Encode, EncodeResult, ReadCursor, WriteCursor,
+    cast_length, decode, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode,
+    EncodeResult, ReadCursor, WriteCursor,
 };
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 use thiserror::Error;
 
-use crate::PduError;
+use crate::{DecodeOptions, DecodeWarning, PduError};
 
 pub mod conference_create;
 
@@ -42,22 +42,13 @@ pub use self::security_data::{
     ClientSecurityData, EncryptionLevel, EncryptionMethod, SecurityDataError, ServerSecurityData,
 };
 
-macro_rules! user_header_try {
-    ($e:expr) => {
-        match $e {
-            Ok(user_header) => user_header,
-            Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => break,
-            Err(e) => return Err(e),
-        }
-    };
-}
-
 const USER_DATA_HEADER_SIZE: usize = 4;
 
 /// 2.2.1.3 Client MCS Connect Initial PDU with GCC Conference Create Request
 ///
 /// [2.2.1.3]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/db6713ee-1c0e-4064-a3b3-0fac30b4037b
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientGccBlocks {
     pub core: ClientCoreData,
     pub security: ClientSecurityData,
@@ -72,6 +63,8 @@ pub struct ClientGccBlocks {
     pub message_channel: Option<ClientMessageChannelData>,
     pub multi_transport_channel: Option<MultiTransportChannelData>,
     pub monitor_extended: Option<ClientMonitorExtendedData>,
+    /// Blocks of unknown types, in the order they were received, encoded after the known blocks.
+    pub unknown: Vec<RawGccBlock>,
 }
 
 impl ClientGccBlocks {
@@ -107,6 +100,9 @@ impl Encode for ClientGccBlocks {
         if let Some(ref monitor_extended) = self.monitor_extended {
             UserDataHeader::encode(dst, ClientGccType::MonitorExtendedData, monitor_extended)?;
         }
+        for block in &self.unknown {
+            block.encode(dst)?;
+        }
 
         Ok(())
     }
@@ -136,6 +132,7 @@ impl Encode for ClientGccBlocks {
         if let Some(ref monitor_extended) = self.monitor_extended {
             size += monitor_extended.size() + USER_DATA_HEADER_SIZE;
         }
+        size += self.unknown.iter().map(Encode::size).sum::<usize>();
 
         size
     }
@@ -143,50 +140,35 @@ impl Encode for ClientGccBlocks {
 
 impl<'de> Decode<'de> for ClientGccBlocks {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
-        let mut core = None;
-        let mut security = None;
-        let mut network = None;
-        let mut cluster = None;
-        let mut monitor = None;
-        let mut message_channel = None;
-        let mut multi_transport_channel = None;
-        let mut monitor_extended = None;
-
-        loop {
-            let (ty, cur) = user_header_try!(UserDataHeader::decode(src));
-
-            match ty {
-                ClientGccType::CoreData => core = Some(decode(cur)?),
-                ClientGccType::SecurityData => security = Some(decode(cur)?),
-                ClientGccType::NetworkData => network = Some(decode(cur)?),
-                ClientGccType::ClusterData => cluster = Some(decode(cur)?),
-                ClientGccType::MonitorData => monitor = Some(decode(cur)?),
-                ClientGccType::MessageChannelData => message_channel = Some(decode(cur)?),
-                ClientGccType::MonitorExtendedData => monitor_extended = Some(decode(cur)?),
-                ClientGccType::MultiTransportChannelData => multi_transport_channel = Some(decode(cur)?),
-            };
-        }
+        let blocks = UserDataBlocks::decode(src)?;
+
+        let core = blocks.decode_last(ClientGccType::CoreData)?;
+        let security = blocks.decode_last(ClientGccType::SecurityData)?;
 
         Ok(Self {
             core: core.ok_or_else(|| invalid_field_err!("core", "required GCC core is absent"))?,
             security: security.ok_or_else(|| invalid_field_err!("security", "required GCC security is absent"))?,
-            network,
-            cluster,
-            monitor,
-            message_channel,
-            multi_transport_channel,
-            monitor_extended,
+            network: blocks.decode_last(ClientGccType::NetworkData)?,
+            cluster: blocks.decode_last(ClientGccType::ClusterData)?,
+            monitor: blocks.decode_last(ClientGccType::MonitorData)?,
+            message_channel: blocks.decode_last(ClientGccType::MessageChannelData)?,
+            multi_transport_channel: blocks.decode_last(ClientGccType::MultiTransportChannelData)?,
+            monitor_extended: blocks.decode_last(ClientGccType::MonitorExtendedData)?,
+            unknown: blocks.unknown::<ClientGccType>(),
         })
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerGccBlocks {
     pub core: ServerCoreData,
     pub network: ServerNetworkData,
     pub security: ServerSecurityData,
     pub message_channel: Option<ServerMessageChannelData>,
     pub multi_transport_channel: Option<MultiTransportChannelData>,
+    /// Blocks of unknown types, in the order they were received, encoded after the known blocks.
+    pub unknown: Vec<RawGccBlock>,
 }
 
 impl ServerGccBlocks {
@@ -212,6 +194,9 @@ impl Encode for ServerGccBlocks {
         if let Some(ref multi_transport_channel) = self.multi_transport_channel {
             UserDataHeader::encode(dst, ServerGccType::MultiTransportChannelData, multi_transport_channel)?;
         }
+        for block in &self.unknown {
+            block.encode(dst)?;
+        }
 
         Ok(())
     }
@@ -229,29 +214,81 @@ impl Encode for ServerGccBlocks {
         if let Some(ref multi_transport_channel) = self.multi_transport_channel {
             size += multi_transport_channel.size() + USER_DATA_HEADER_SIZE;
         }
+        size += self.unknown.iter().map(Encode::size).sum::<usize>();
 
         size
     }
 }
 
-impl<'de> Decode<'de> for ServerGccBlocks {
-    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+impl ServerGccBlocks {
+    /// Decodes the server GCC blocks, tolerating non-conformant optional blocks in lenient mode.
+    ///
+    /// In lenient mode, malformed optional blocks are skipped, and a block length going past the end of the data ends
+    /// the decoding. A [`DecodeWarning`] is pushed for each deviation, as well as for each unknown block, which is
+    /// kept as in strict mode. The core, network and security blocks are always required to be well-formed.
+    pub fn decode_with_options(
+        src: &mut ReadCursor<'_>,
+        options: DecodeOptions,
+        warnings: &mut Vec<DecodeWarning>,
+    ) -> DecodeResult<Self> {
+        if !options.lenient {
+            return Self::decode(src);
+        }
+
         let mut core = None;
         let mut network = None;
         let mut security = None;
         let mut message_channel = None;
         let mut multi_transport_channel = None;
-
-        loop {
-            let (ty, cur) = user_header_try!(UserDataHeader::decode(src));
-
-            match ty {
-                ServerGccType::CoreData => core = Some(decode(cur)?),
-                ServerGccType::NetworkData => network = Some(decode(cur)?),
-                ServerGccType::SecurityData => security = Some(decode(cur)?),
-                ServerGccType::MessageChannelData => message_channel = Some(decode(cur)?),
-                ServerGccType::MultiTransportChannelData => multi_transport_channel = Some(decode(cur)?),
-            };
+        let mut unknown = Vec::new();
+
+        while !src.is_empty() {
+            if src.len() < USER_DATA_HEADER_SIZE {
+                warnings.push(DecodeWarning::TrailingBytes {
+                    context: Self::NAME,
+                    count: src.len(),
+                });
+                src.advance(src.len());
+                break;
+            }
+
+            let block_type = src.read_u16();
+            let block_length: usize = cast_length!("blockLen", src.read_u16())?;
+
+            if block_length <= USER_DATA_HEADER_SIZE || block_length - USER_DATA_HEADER_SIZE > src.len() {
+                warnings.push(DecodeWarning::TrailingBytes {
+                    context: Self::NAME,
+                    count: src.len() + USER_DATA_HEADER_SIZE,
+                });
+                src.advance(src.len());
+                break;
+            }
+
+            let block = src.read_slice(block_length - USER_DATA_HEADER_SIZE);
+
+            match ServerGccType::from_u16(block_type) {
+                Some(ServerGccType::CoreData) => core = Some(decode(block)?),
+                Some(ServerGccType::NetworkData) => network = Some(decode(block)?),
+                Some(ServerGccType::SecurityData) => security = Some(decode(block)?),
+                Some(ServerGccType::MessageChannelData) => match decode(block) {
+                    Ok(block) => message_channel = Some(block),
+                    Err(e) => warnings.push(DecodeWarning::MalformedGccBlock {
+                        block_type,
+                        reason: e.to_string(),
+                    }),
+                },
+                Some(ServerGccType::MultiTransportChannelData) => match decode(block) {
+                    Ok(block) => multi_transport_channel = Some(block),
+                    Err(e) => warnings.push(DecodeWarning::MalformedGccBlock {
+                        block_type,
+                        reason: e.to_string(),
+                    }),
+                },
+                None => {
+                    warnings.push(DecodeWarning::UnknownGccBlock { block_type });
+                    unknown.push(RawGccBlock::new(block_type, block));
+                }
+            }
         }
 
         Ok(Self {
@@ -260,12 +297,148 @@ impl<'de> Decode<'de> for ServerGccBlocks {
             security: security.ok_or_else(|| invalid_field_err!("security", "required GCC security is absent"))?,
             message_channel,
             multi_transport_channel,
+            unknown,
+        })
+    }
+}
+
+impl<'de> Decode<'de> for ServerGccBlocks {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let blocks = UserDataBlocks::decode(src)?;
+
+        let core = blocks.decode_last(ServerGccType::CoreData)?;
+        let network = blocks.decode_last(ServerGccType::NetworkData)?;
+        let security = blocks.decode_last(ServerGccType::SecurityData)?;
+
+        Ok(Self {
+            core: core.ok_or_else(|| invalid_field_err!("core", "required GCC core is absent"))?,
+            network: network.ok_or_else(|| invalid_field_err!("network", "required GCC network is absent"))?,
+            security: security.ok_or_else(|| invalid_field_err!("security", "required GCC security is absent"))?,
+            message_channel: blocks.decode_last(ServerGccType::MessageChannelData)?,
+            multi_transport_channel: blocks.decode_last(ServerGccType::MultiTransportChannelData)?,
+            unknown: blocks.unknown::<ServerGccType>(),
         })
     }
 }
 
+/// GCC user data blocks, in the order they appear in the user data
+///
+/// A block type may appear several times, e.g.: when a broker appends its own blocks. When decoding the GCC blocks,
+/// the last block of each known type wins, as mstsc does for the server network data, and the blocks of unknown
+/// types are all kept as [`RawGccBlock`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserDataBlocks<'de> {
+    blocks: Vec<(u16, &'de [u8])>,
+}
+
+impl<'de> UserDataBlocks<'de> {
+    /// Splits the user data into blocks.
+    ///
+    /// Each block must lie within the data: a block length shorter than the block header, or going past the end of
+    /// the data, is an error. Fewer trailing bytes than a block header are left in `src`.
+    pub fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let mut blocks = Vec::new();
+
+        while src.len() >= USER_DATA_HEADER_SIZE {
+            let block_type = src.read_u16();
+            let block_length: usize = cast_length!("blockLen", src.read_u16())?;
+
+            if block_length <= USER_DATA_HEADER_SIZE {
+                return Err(invalid_field_err!("blockLen", "invalid UserDataHeader length"));
+            }
+            if block_length - USER_DATA_HEADER_SIZE > src.len() {
+                return Err(invalid_field_err!(
+                    "blockLen",
+                    "block goes past the end of the user data"
+                ));
+            }
+
+            blocks.push((block_type, src.read_slice(block_length - USER_DATA_HEADER_SIZE)));
+        }
+
+        Ok(Self { blocks })
+    }
+
+    /// Returns the type and the data, without header, of the blocks.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &'de [u8])> + '_ {
+        self.blocks.iter().copied()
+    }
+
+    /// Returns the data of the blocks of the given type.
+    pub fn get_all(&self, block_type: u16) -> impl Iterator<Item = &'de [u8]> + '_ {
+        self.iter()
+            .filter(move |(ty, _)| *ty == block_type)
+            .map(|(_, data)| data)
+    }
+
+    /// Returns the data of the last block of the given type.
+    pub fn last(&self, block_type: u16) -> Option<&'de [u8]> {
+        self.get_all(block_type).last()
+    }
+
+    fn decode_last<T, B>(&self, block_type: T) -> DecodeResult<Option<B>>
+    where
+        T: ToPrimitive,
+        B: Decode<'de>,
+    {
+        let block_type = block_type.to_u16().expect("GCC types fit in u16");
+
+        self.last(block_type).map(decode).transpose()
+    }
+
+    fn unknown<T: FromPrimitive>(&self) -> Vec<RawGccBlock> {
+        self.iter()
+            .filter(|(ty, _)| T::from_u16(*ty).is_none())
+            .map(|(ty, data)| RawGccBlock::new(ty, data))
+            .collect()
+    }
+}
+
+/// GCC user data block of a type unknown to IronRDP, e.g.: a proprietary block
+///
+/// The block is re-encoded byte for byte, so it can be forwarded by a proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RawGccBlock {
+    pub block_type: u16,
+    /// Data of the block, without its header.
+    pub data: Vec<u8>,
+}
+
+impl RawGccBlock {
+    const NAME: &'static str = "RawGccBlock";
+
+    pub fn new(block_type: u16, data: &[u8]) -> Self {
+        Self {
+            block_type,
+            data: data.to_vec(),
+        }
+    }
+}
+
+impl Encode for RawGccBlock {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.block_type);
+        dst.write_u16(cast_length!("blockLen", self.size())?);
+        dst.write_slice(&self.data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        USER_DATA_HEADER_SIZE + self.data.len()
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ClientGccType {
     CoreData = 0xC001,
     SecurityData = 0xC002,
@@ -279,6 +452,7 @@ pub enum ClientGccType {
 
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ServerGccType {
     CoreData = 0x0C01,
     SecurityData = 0x0C02,
@@ -288,6 +462,7 @@ pub enum ServerGccType {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UserDataHeader;
 
 impl UserDataHeader {
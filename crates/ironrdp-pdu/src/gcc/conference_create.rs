@@ -4,7 +4,7 @@ use ironrdp_core::{
 };
 
 use super::{ClientGccBlocks, ServerGccBlocks};
-use crate::{mcs, per};
+use crate::{mcs, per, DecodeOptions, DecodeWarning};
 
 const CONFERENCE_REQUEST_OBJECT_ID: [u8; 6] = [0, 0, 20, 124, 0, 1];
 const CONFERENCE_REQUEST_CLIENT_TO_SERVER_H221_NON_STANDARD: &[u8; 4] = b"Duca";
@@ -25,6 +25,7 @@ const H221_NON_STANDARD_MIN_LENGTH: usize = 4;
 const CONFERENCE_NAME: &[u8] = b"1";
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConferenceCreateRequest {
     pub gcc_blocks: ClientGccBlocks,
 }
@@ -166,14 +167,19 @@ impl<'de> Decode<'de> for ConferenceCreateRequest {
             ));
         }
         // H221NonStandardIdentifier (octet string)
-        let (_gcc_blocks_buffer_length, _) = per::read_length(src).map_err(|e| other_err!("len", source: e))?;
-        let gcc_blocks = ClientGccBlocks::decode(src)?;
+        let (gcc_blocks_buffer_length, _) = per::read_length(src).map_err(|e| other_err!("len", source: e))?;
+        // The GCC blocks must not go past the user data
+        ensure_size!(in: src, size: usize::from(gcc_blocks_buffer_length));
+        let gcc_blocks = ClientGccBlocks::decode(&mut ReadCursor::new(
+            src.read_slice(usize::from(gcc_blocks_buffer_length)),
+        ))?;
 
         Ok(Self { gcc_blocks })
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConferenceCreateResponse {
     pub user_id: u16,
     pub gcc_blocks: ServerGccBlocks,
@@ -247,6 +253,17 @@ impl Encode for ConferenceCreateResponse {
 
 impl<'de> Decode<'de> for ConferenceCreateResponse {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Self::decode_with_options(src, DecodeOptions::STRICT, &mut Vec::new())
+    }
+}
+
+impl ConferenceCreateResponse {
+    /// Decodes the conference create response, see [`ServerGccBlocks::decode_with_options`].
+    pub fn decode_with_options(
+        src: &mut ReadCursor<'_>,
+        options: DecodeOptions,
+        warnings: &mut Vec<DecodeWarning>,
+    ) -> DecodeResult<Self> {
         // ConnectData::Key: select type OBJECT_IDENTIFIER
         ensure_size!(in: src, size: per::CHOICE_SIZE);
         if per::read_choice(src) != OBJECT_IDENTIFIER_KEY {
@@ -312,8 +329,14 @@ impl<'de> Decode<'de> for ConferenceCreateResponse {
                 "Got invalid H221NonStandard server-to-client key",
             ));
         }
-        let (_gcc_blocks_buffer_length, _) = per::read_length(src).map_err(|e| other_err!("len", source: e))?;
-        let gcc_blocks = ServerGccBlocks::decode(src)?;
+        let (gcc_blocks_buffer_length, _) = per::read_length(src).map_err(|e| other_err!("len", source: e))?;
+        // The GCC blocks must not go past the user data
+        ensure_size!(in: src, size: usize::from(gcc_blocks_buffer_length));
+        let gcc_blocks = ServerGccBlocks::decode_with_options(
+            &mut ReadCursor::new(src.read_slice(usize::from(gcc_blocks_buffer_length))),
+            options,
+            warnings,
+        )?;
 
         Ok(Self { user_id, gcc_blocks })
     }
@@ -44,6 +44,7 @@ const DEVICE_SCALE_FACTOR_SIZE: usize = 4;
 ///
 /// [2.2.1.3.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/00f1da4a-ee9c-421a-852f-c19f92343d73
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientCoreData {
     pub version: RdpVersion,
     pub desktop_width: u16,
@@ -194,6 +195,7 @@ impl<'de> Decode<'de> for ClientCoreData {
 ///
 /// [2.2.1.3.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/00f1da4a-ee9c-421a-852f-c19f92343d73
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientCoreOptionalData {
     /// The requested color depth. Values in this field MUST be ignored if the highColorDepth field is present.
     pub post_beta2_color_depth: Option<ColorDepth>,
@@ -472,6 +474,7 @@ impl<'de> Decode<'de> for ClientCoreOptionalData {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ClientColorDepth {
     Bpp4,
     Bpp8,
@@ -507,6 +510,7 @@ impl From<HighColorDepth> for ClientColorDepth {
 
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ColorDepth {
     Bpp4 = 0xCA00,
     Bpp8 = 0xCA01,
@@ -517,6 +521,7 @@ pub enum ColorDepth {
 
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, FromPrimitive, ToPrimitive, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum HighColorDepth {
     Bpp4 = 0x0004,
     Bpp8 = 0x0008,
@@ -527,11 +532,13 @@ pub enum HighColorDepth {
 
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SecureAccessSequence {
     Del = 0xAA03,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyboardType {
     IbmPcXt = 1,
     OlivettiIco = 2,
@@ -544,6 +551,7 @@ pub enum KeyboardType {
 
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ConnectionType {
     NotUsed = 0, // not used as ClientEarlyCapabilityFlags::VALID_CONNECTION_TYPE not set
     Modem = 1,
@@ -557,6 +565,7 @@ pub enum ConnectionType {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct SupportedColorDepths: u16 {
         const BPP24 = 1;
         const BPP16 = 2;
@@ -567,6 +576,7 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct ClientEarlyCapabilityFlags: u16 {
         const SUPPORT_ERR_INFO_PDU = 0x0001;
         const WANT_32_BPP_SESSION = 0x0002;
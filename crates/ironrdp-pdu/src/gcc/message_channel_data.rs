@@ -4,6 +4,7 @@ const CLIENT_FLAGS_SIZE: usize = 4;
 const SERVER_MCS_MESSAGE_CHANNEL_ID_SIZE: usize = 2;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientMessageChannelData;
 
 impl ClientMessageChannelData {
@@ -41,6 +42,7 @@ impl<'de> Decode<'de> for ClientMessageChannelData {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerMessageChannelData {
     pub mcs_message_channel_id: u16,
 }
@@ -94,7 +94,19 @@ impl ChannelName {
     }
 }
 
+/// Serialized as a string, or as the raw bytes if the name is not ASCII.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChannelName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_bytes(self.as_bytes()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientNetworkData {
     pub channels: Vec<ChannelDef>,
 }
@@ -147,6 +159,7 @@ impl<'de> Decode<'de> for ClientNetworkData {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerNetworkData {
     pub channel_ids: Vec<u16>,
     pub io_channel: u16,
@@ -224,6 +237,7 @@ impl<'de> Decode<'de> for ServerNetworkData {
 
 /// Channel Definition Structure (CHANNEL_DEF)
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChannelDef {
     pub name: ChannelName,
     pub options: ChannelOptions,
@@ -270,6 +284,7 @@ impl<'de> Decode<'de> for ChannelDef {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct ChannelOptions: u32 {
         const INITIALIZED = 0x8000_0000;
         const ENCRYPT_RDP = 0x4000_0000;
@@ -17,9 +17,9 @@ const SERVER_ENCRYPTION_LEVEL_SIZE: usize = 4;
 const SERVER_RANDOM_LEN_SIZE: usize = 4;
 const SERVER_CERT_LEN_SIZE: usize = 4;
 const SERVER_RANDOM_LEN: usize = 0x20;
-const MAX_SERVER_CERT_LEN: usize = 1024;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientSecurityData {
     pub encryption_methods: EncryptionMethod,
     pub ext_encryption_methods: u32,
@@ -73,6 +73,7 @@ impl<'de> Decode<'de> for ClientSecurityData {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerSecurityData {
     pub encryption_method: EncryptionMethod,
     pub encryption_level: EncryptionLevel,
@@ -93,6 +94,17 @@ impl ServerSecurityData {
             server_cert: Vec::new(),
         }
     }
+
+    /// Returns `true` when the server requires the standard RDP security, in which case the server random and
+    /// certificate fields are present.
+    pub fn is_encrypted(&self) -> bool {
+        !self.encryption_method.is_empty() || self.encryption_level != EncryptionLevel::None
+    }
+
+    /// Returns `true` when the server sent its certificate.
+    pub fn has_server_certificate(&self) -> bool {
+        !self.server_cert.is_empty()
+    }
 }
 
 impl Encode for ServerSecurityData {
@@ -102,7 +114,7 @@ impl Encode for ServerSecurityData {
         dst.write_u32(self.encryption_method.bits());
         dst.write_u32(self.encryption_level.to_u32().unwrap());
 
-        if self.encryption_method.is_empty() && self.encryption_level == EncryptionLevel::None {
+        if !self.is_encrypted() {
             if self.server_random.is_some() || !self.server_cert.is_empty() {
                 Err(invalid_field_err!("serverRandom", "An encryption method and encryption level is none, but the server random or certificate is not empty"))
             } else {
@@ -132,11 +144,12 @@ impl Encode for ServerSecurityData {
     fn size(&self) -> usize {
         let mut size = Self::FIXED_PART_SIZE;
 
-        if let Some(ref server_random) = self.server_random {
-            size += SERVER_RANDOM_LEN_SIZE + server_random.len();
-        }
-        if !self.server_cert.is_empty() {
-            size += SERVER_CERT_LEN_SIZE + self.server_cert.len();
+        if self.is_encrypted() {
+            size += SERVER_RANDOM_LEN_SIZE + SERVER_CERT_LEN_SIZE + self.server_cert.len();
+
+            if let Some(ref server_random) = self.server_random {
+                size += server_random.len();
+            }
         }
 
         size
@@ -156,19 +169,17 @@ impl<'de> Decode<'de> for ServerSecurityData {
         {
             (None, Vec::new())
         } else {
-            ensure_size!(in: src, size: 4 + 4);
+            ensure_size!(in: src, size: SERVER_RANDOM_LEN_SIZE + SERVER_CERT_LEN_SIZE);
 
             let server_random_len: usize = cast_length!("serverRandomLen", src.read_u32())?;
             if server_random_len != SERVER_RANDOM_LEN {
                 return Err(invalid_field_err!("serverRandomLen", "Invalid server random length"));
             }
 
+            // The certificate is either a proprietary certificate or an X.509 certificate chain, the latter is
+            // only bounded by the size of the PDU.
             let server_cert_len = cast_length!("serverCertLen", src.read_u32())?;
 
-            if server_cert_len > MAX_SERVER_CERT_LEN {
-                return Err(invalid_field_err!("serverCetLen", "Invalid server certificate length"));
-            }
-
             ensure_size!(in: src, size: SERVER_RANDOM_LEN);
             let server_random = src.read_array();
 
@@ -189,6 +200,7 @@ impl<'de> Decode<'de> for ServerSecurityData {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct EncryptionMethod: u32 {
         const BIT_40 = 0x0000_0001;
         const BIT_128 = 0x0000_0002;
@@ -198,6 +210,7 @@ bitflags! {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum EncryptionLevel {
     None = 0,
     Low = 1,
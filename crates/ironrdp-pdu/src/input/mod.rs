@@ -8,6 +8,8 @@ use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 use thiserror::Error;
 
+use self::fast_path::FastPathInputEvent;
+
 pub mod fast_path;
 pub mod mouse;
 pub mod mouse_rel;
@@ -32,6 +34,13 @@ impl InputEventPdu {
     const NAME: &'static str = "InputEventPdu";
 
     const FIXED_PART_SIZE: usize = 4 /* nEvents */;
+
+    /// Builds the slow-path equivalent of fast-path input events, e.g.: the ones produced by `ironrdp_input::Database`.
+    ///
+    /// Quality of experience timestamps are dropped, as they can only be sent using fast-path input.
+    pub fn from_fast_path(events: &[FastPathInputEvent]) -> Self {
+        Self(events.iter().filter_map(InputEvent::from_fast_path).collect())
+    }
 }
 
 impl Encode for InputEventPdu {
@@ -87,6 +96,56 @@ impl InputEvent {
     const NAME: &'static str = "InputEvent";
 
     const FIXED_PART_SIZE: usize = 4 /* eventTime */ + 2 /* eventType */;
+
+    /// Converts a fast-path input event into its slow-path equivalent.
+    ///
+    /// Returns `None` for quality of experience timestamps, which have no slow-path equivalent.
+    pub fn from_fast_path(event: &FastPathInputEvent) -> Option<Self> {
+        let event = match *event {
+            FastPathInputEvent::KeyboardEvent(flags, key_code) => {
+                let mut scan_code_flags = scan_code::KeyboardFlags::empty();
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::RELEASE,
+                    flags.contains(fast_path::KeyboardFlags::RELEASE),
+                );
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::EXTENDED,
+                    flags.contains(fast_path::KeyboardFlags::EXTENDED),
+                );
+                scan_code_flags.set(
+                    scan_code::KeyboardFlags::EXTENDED_1,
+                    flags.contains(fast_path::KeyboardFlags::EXTENDED1),
+                );
+
+                Self::ScanCode(ScanCodePdu {
+                    flags: scan_code_flags,
+                    key_code: u16::from(key_code),
+                })
+            }
+            FastPathInputEvent::UnicodeKeyboardEvent(flags, unicode_code) => {
+                let mut unicode_flags = unicode::KeyboardFlags::empty();
+                unicode_flags.set(
+                    unicode::KeyboardFlags::RELEASE,
+                    flags.contains(fast_path::KeyboardFlags::RELEASE),
+                );
+
+                Self::Unicode(UnicodePdu {
+                    flags: unicode_flags,
+                    unicode_code,
+                })
+            }
+            FastPathInputEvent::MouseEvent(ref pdu) => Self::Mouse(pdu.clone()),
+            FastPathInputEvent::MouseEventEx(ref pdu) => Self::MouseX(pdu.clone()),
+            FastPathInputEvent::MouseEventRel(ref pdu) => Self::MouseRel(pdu.clone()),
+            // The toggle flags have the same values in both encodings.
+            FastPathInputEvent::SyncEvent(flags) => Self::Sync(SyncPdu {
+                flags: sync::SyncToggleFlags::from_bits_truncate(u32::from(flags.bits())),
+            }),
+            FastPathInputEvent::QoeEvent(_) => return None,
+        };
+
+        Some(event)
+    }
 }
 
 impl Encode for InputEvent {
@@ -30,12 +30,15 @@ pub mod utf16;
 pub mod utils;
 pub mod x224;
 
+mod decode_options;
+
 pub(crate) mod basic_output;
 pub(crate) mod ber;
 pub(crate) mod crypto;
 pub(crate) mod per;
 
-pub use crate::basic_output::{bitmap, fast_path, pointer, surface_commands};
+pub use crate::basic_output::{bitmap, fast_path, orders, pointer, surface_commands};
+pub use crate::decode_options::{DecodeOptions, DecodeWarning};
 pub use crate::rdp::vc::dvc;
 
 pub type PduResult<T> = Result<T, PduError>;
@@ -84,6 +87,24 @@ impl fmt::Display for PduErrorKind {
     }
 }
 
+impl ErrorKindExt for PduErrorKind {
+    fn code(&self) -> u32 {
+        match self {
+            Self::Encode => 0x0003_0001,
+            Self::Decode => 0x0003_0002,
+            Self::Other { .. } => 0x0003_0003,
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Encode => ErrorCategory::Encoding,
+            Self::Decode => ErrorCategory::Decoding,
+            Self::Other { .. } => ErrorCategory::Other,
+        }
+    }
+}
+
 /// An RDP PDU.
 pub trait Pdu {
     /// Name associated to this PDU.
@@ -138,6 +159,15 @@ pub fn find_size(bytes: &[u8]) -> DecodeResult<Option<PduInfo>> {
             ensure_enough!(bytes, tpkt::TpktHeader::SIZE);
             let tpkt = tpkt::TpktHeader::read(&mut ReadCursor::new(bytes))?;
 
+            // A shorter frame would never be consumed.
+            if tpkt.packet_length() < tpkt::TpktHeader::SIZE {
+                return Err(invalid_field_err(
+                    tpkt::TpktHeader::NAME,
+                    "length",
+                    "TPKT length is smaller than the header size",
+                ));
+            }
+
             Ok(Some(PduInfo {
                 action,
                 length: tpkt.packet_length(),
@@ -147,15 +177,24 @@ pub fn find_size(bytes: &[u8]) -> DecodeResult<Option<PduInfo>> {
             ensure_enough!(bytes, 2);
             let a = bytes[1];
 
-            let fast_path_length = if a & 0x80 != 0 {
+            // The length may use the two-byte form even when it fits in one byte.
+            let (fast_path_length, header_size) = if a & 0x80 != 0 {
                 ensure_enough!(bytes, 3);
                 let b = bytes[2];
 
-                ((u16::from(a) & !0x80) << 8) + u16::from(b)
+                (((u16::from(a) & !0x80) << 8) + u16::from(b), 3)
             } else {
-                u16::from(a)
+                (u16::from(a), 2)
             };
 
+            if usize::from(fast_path_length) < header_size {
+                return Err(invalid_field_err(
+                    "TS_FP_UPDATE_PDU header",
+                    "length",
+                    "fast-path PDU length is smaller than the header size",
+                ));
+            }
+
             Ok(Some(PduInfo {
                 action,
                 length: usize::from(fast_path_length),
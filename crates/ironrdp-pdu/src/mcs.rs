@@ -97,6 +97,17 @@ use crate::{per, DecodeResult, EncodeResult, PduError};
 //     channelId	ChannelId OPTIONAL
 // }
 //
+// ChannelLeaveRequest ::= [APPLICATION 16] IMPLICIT SEQUENCE
+// {
+//     channelIds		SET OF ChannelId
+// }
+//
+// ChannelExpelIndication ::= [APPLICATION 24] IMPLICIT SEQUENCE
+// {
+//     channelId		PrivateChannelId,
+//     userIds			SET OF UserId
+// }
+//
 // SendDataRequest ::= [APPLICATION 25] IMPLICIT SEQUENCE
 // {
 //     initiator		UserId,
@@ -197,6 +208,8 @@ enum DomainMcsPdu {
     AttachUserConfirm = 11,
     ChannelJoinRequest = 14,
     ChannelJoinConfirm = 15,
+    ChannelLeaveRequest = 16,
+    ChannelExpelIndication = 24,
     SendDataRequest = 25,
     SendDataIndication = 26,
 }
@@ -226,6 +239,8 @@ impl DomainMcsPdu {
             11 => Some(Self::AttachUserConfirm),
             14 => Some(Self::ChannelJoinRequest),
             15 => Some(Self::ChannelJoinConfirm),
+            16 => Some(Self::ChannelLeaveRequest),
+            24 => Some(Self::ChannelExpelIndication),
             25 => Some(Self::SendDataRequest),
             26 => Some(Self::SendDataIndication),
             _ => None,
@@ -268,6 +283,8 @@ pub enum McsMessage<'a> {
     AttachUserConfirm(AttachUserConfirm),
     ChannelJoinRequest(ChannelJoinRequest),
     ChannelJoinConfirm(ChannelJoinConfirm),
+    ChannelLeaveRequest(ChannelLeaveRequest),
+    ChannelExpelIndication(ChannelExpelIndication),
     SendDataRequest(SendDataRequest<'a>),
     SendDataIndication(SendDataIndication<'a>),
     DisconnectProviderUltimatum(DisconnectProviderUltimatum),
@@ -285,6 +302,8 @@ impl IntoOwned for McsMessage<'_> {
             Self::AttachUserConfirm(msg) => McsMessage::AttachUserConfirm(msg.into_owned()),
             Self::ChannelJoinRequest(msg) => McsMessage::ChannelJoinRequest(msg.into_owned()),
             Self::ChannelJoinConfirm(msg) => McsMessage::ChannelJoinConfirm(msg.into_owned()),
+            Self::ChannelLeaveRequest(msg) => McsMessage::ChannelLeaveRequest(msg.into_owned()),
+            Self::ChannelExpelIndication(msg) => McsMessage::ChannelExpelIndication(msg.into_owned()),
             Self::SendDataRequest(msg) => McsMessage::SendDataRequest(msg.into_owned()),
             Self::SendDataIndication(msg) => McsMessage::SendDataIndication(msg.into_owned()),
             Self::DisconnectProviderUltimatum(msg) => McsMessage::DisconnectProviderUltimatum(msg.into_owned()),
@@ -302,6 +321,8 @@ impl<'de> McsPdu<'de> for McsMessage<'de> {
             Self::AttachUserConfirm(msg) => msg.mcs_body_encode(dst),
             Self::ChannelJoinRequest(msg) => msg.mcs_body_encode(dst),
             Self::ChannelJoinConfirm(msg) => msg.mcs_body_encode(dst),
+            Self::ChannelLeaveRequest(msg) => msg.mcs_body_encode(dst),
+            Self::ChannelExpelIndication(msg) => msg.mcs_body_encode(dst),
             Self::SendDataRequest(msg) => msg.mcs_body_encode(dst),
             Self::SendDataIndication(msg) => msg.mcs_body_encode(dst),
             Self::DisconnectProviderUltimatum(msg) => msg.mcs_body_encode(dst),
@@ -328,6 +349,12 @@ impl<'de> McsPdu<'de> for McsMessage<'de> {
             DomainMcsPdu::ChannelJoinConfirm => Ok(McsMessage::ChannelJoinConfirm(
                 ChannelJoinConfirm::mcs_body_decode(src, tpdu_user_data_size)?,
             )),
+            DomainMcsPdu::ChannelLeaveRequest => Ok(McsMessage::ChannelLeaveRequest(
+                ChannelLeaveRequest::mcs_body_decode(src, tpdu_user_data_size)?,
+            )),
+            DomainMcsPdu::ChannelExpelIndication => Ok(McsMessage::ChannelExpelIndication(
+                ChannelExpelIndication::mcs_body_decode(src, tpdu_user_data_size)?,
+            )),
             DomainMcsPdu::SendDataRequest => Ok(McsMessage::SendDataRequest(SendDataRequest::mcs_body_decode(
                 src,
                 tpdu_user_data_size,
@@ -348,6 +375,8 @@ impl<'de> McsPdu<'de> for McsMessage<'de> {
             Self::AttachUserConfirm(msg) => msg.mcs_size(),
             Self::ChannelJoinRequest(msg) => msg.mcs_size(),
             Self::ChannelJoinConfirm(msg) => msg.mcs_size(),
+            Self::ChannelLeaveRequest(msg) => msg.mcs_size(),
+            Self::ChannelExpelIndication(msg) => msg.mcs_size(),
             Self::SendDataRequest(msg) => msg.mcs_size(),
             Self::SendDataIndication(msg) => msg.mcs_size(),
             Self::DisconnectProviderUltimatum(msg) => msg.mcs_size(),
@@ -361,6 +390,8 @@ impl<'de> McsPdu<'de> for McsMessage<'de> {
             Self::AttachUserConfirm(msg) => msg.name(),
             Self::ChannelJoinRequest(msg) => msg.name(),
             Self::ChannelJoinConfirm(msg) => msg.name(),
+            Self::ChannelLeaveRequest(msg) => msg.name(),
+            Self::ChannelExpelIndication(msg) => msg.name(),
             Self::SendDataRequest(msg) => msg.name(),
             Self::SendDataIndication(msg) => msg.name(),
             Self::DisconnectProviderUltimatum(msg) => msg.name(),
@@ -549,6 +580,91 @@ impl<'de> McsPdu<'de> for ChannelJoinConfirm {
     }
 }
 
+/// Sent by a user to leave channels it joined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelLeaveRequest {
+    pub channel_ids: Vec<u16>,
+}
+
+impl_x224_pdu_pod!(ChannelLeaveRequest);
+
+impl<'de> McsPdu<'de> for ChannelLeaveRequest {
+    const MCS_NAME: &'static str = "ChannelLeaveRequest";
+
+    fn mcs_body_encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        write_mcspdu_header(dst, DomainMcsPdu::ChannelLeaveRequest, 0);
+
+        per::write_length(dst, cast_length!("channelIdsCount", self.channel_ids.len())?);
+        for channel_id in &self.channel_ids {
+            per::write_u16(dst, *channel_id, 0).map_err(per_field_err!("channelIds"))?;
+        }
+
+        Ok(())
+    }
+
+    fn mcs_body_decode(src: &mut ReadCursor<'de>, _: usize) -> DecodeResult<Self> {
+        read_mcspdu_header(src, Self::MCS_NAME)?.check_expected(Self::MCS_NAME, DomainMcsPdu::ChannelLeaveRequest)?;
+
+        let (count, _) = per::read_length(src).map_err(per_field_err!("channelIdsCount"))?;
+        let channel_ids = (0..count)
+            .map(|_| per::read_u16(src, 0).map_err(per_field_err!("channelIds")))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { channel_ids })
+    }
+
+    fn mcs_size(&self) -> usize {
+        per::CHOICE_SIZE
+            + per::sizeof_length(u16::try_from(self.channel_ids.len()).unwrap_or(u16::MAX))
+            + per::U16_SIZE * self.channel_ids.len()
+    }
+}
+
+/// Sent by the provider to the users removed from a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelExpelIndication {
+    pub channel_id: u16,
+    pub user_ids: Vec<u16>,
+}
+
+impl_x224_pdu_pod!(ChannelExpelIndication);
+
+impl<'de> McsPdu<'de> for ChannelExpelIndication {
+    const MCS_NAME: &'static str = "ChannelExpelIndication";
+
+    fn mcs_body_encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        write_mcspdu_header(dst, DomainMcsPdu::ChannelExpelIndication, 0);
+
+        per::write_u16(dst, self.channel_id, BASE_CHANNEL_ID).map_err(per_field_err!("channelId"))?;
+        per::write_length(dst, cast_length!("userIdsCount", self.user_ids.len())?);
+        for user_id in &self.user_ids {
+            per::write_u16(dst, *user_id, BASE_CHANNEL_ID).map_err(per_field_err!("userIds"))?;
+        }
+
+        Ok(())
+    }
+
+    fn mcs_body_decode(src: &mut ReadCursor<'de>, _: usize) -> DecodeResult<Self> {
+        read_mcspdu_header(src, Self::MCS_NAME)?
+            .check_expected(Self::MCS_NAME, DomainMcsPdu::ChannelExpelIndication)?;
+
+        let channel_id = per::read_u16(src, BASE_CHANNEL_ID).map_err(per_field_err!("channelId"))?;
+        let (count, _) = per::read_length(src).map_err(per_field_err!("userIdsCount"))?;
+        let user_ids = (0..count)
+            .map(|_| per::read_u16(src, BASE_CHANNEL_ID).map_err(per_field_err!("userIds")))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { channel_id, user_ids })
+    }
+
+    fn mcs_size(&self) -> usize {
+        per::CHOICE_SIZE
+            + per::U16_SIZE
+            + per::sizeof_length(u16::try_from(self.user_ids.len()).unwrap_or(u16::MAX))
+            + per::U16_SIZE * self.user_ids.len()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SendDataRequest<'a> {
     pub initiator_id: u16,
@@ -946,6 +1062,7 @@ mod legacy {
     use crate::ber;
     use crate::gcc::conference_create::{ConferenceCreateRequest, ConferenceCreateResponse};
     use crate::gcc::GccError;
+    use crate::{DecodeOptions, DecodeWarning};
 
     // impl<'de> McsPdu<'de> for ConnectInitial {
     //     const MCS_NAME: &'static str = "DisconnectProviderUltimatum";
@@ -1070,12 +1187,25 @@ mod legacy {
 
     impl<'de> Decode<'de> for ConnectResponse {
         fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+            Self::decode_with_options(src, DecodeOptions::STRICT, &mut Vec::new())
+        }
+    }
+
+    impl ConnectResponse {
+        /// Decodes the MCS Connect Response, see [`ServerGccBlocks::decode_with_options`].
+        ///
+        /// [`ServerGccBlocks::decode_with_options`]: crate::gcc::ServerGccBlocks::decode_with_options
+        pub fn decode_with_options(
+            src: &mut ReadCursor<'_>,
+            options: DecodeOptions,
+            warnings: &mut Vec<DecodeWarning>,
+        ) -> DecodeResult<Self> {
             ber::read_application_tag(src, MCS_TYPE_CONNECT_RESPONSE)?;
             ber::read_enumerated(src, RESULT_ENUM_LENGTH)?;
             let called_connect_id = ber::read_integer(src)? as u32;
             let domain_parameters = DomainParameters::decode(src)?;
             let _user_data_buffer_length = ber::read_octet_string_tag(src)?;
-            let conference_create_response = ConferenceCreateResponse::decode(src)?;
+            let conference_create_response = ConferenceCreateResponse::decode_with_options(src, options, warnings)?;
 
             Ok(Self {
                 called_connect_id,
@@ -17,6 +17,7 @@ bitflags! {
     /// Used to negotiate the security protocol to use during the Connection Initiation phase using
     /// the [`ConnectionConfirm`] and [`ConnectionRequest`] messages.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct SecurityProtocol: u32 {
         /// PROTOCOL_SSL, TLS + login subsystem (winlogon.exe)
         const SSL = 0x0000_0001;
@@ -61,6 +62,7 @@ bitflags! {
     ///
     /// * [RDP Negotiation Request (RDP_NEG_REQ)](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/902b090b-9cb3-4efc-92bf-ee13373371e3)
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct RequestFlags: u8 {
         const RESTRICTED_ADMIN_MODE_REQUIRED = 0x01;
         const REDIRECTED_AUTHENTICATION_MODE_REQUIRED = 0x02;
@@ -75,6 +77,7 @@ bitflags! {
     ///
     /// * [RDP Negotiation Response (RDP_NEG_RSP)](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/b2975bdc-6d56-49ee-9c57-f2ff3a0b6817)
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct ResponseFlags: u8 {
         const EXTENDED_CLIENT_DATA_SUPPORTED = 0x01;
         const DYNVC_GFX_PROTOCOL_SUPPORTED = 0x02;
@@ -151,15 +154,109 @@ impl fmt::Display for FailureCode {
     }
 }
 
+/// Negotiation failure code sent by the server in the [`ConnectionConfirm`] message
+///
+/// The typed counterpart of [`FailureCode`], whose display tells how the configuration should be changed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum NegotiationFailureCode {
+    /// SSL_REQUIRED_BY_SERVER
+    SslRequiredByServer,
+    /// SSL_NOT_ALLOWED_BY_SERVER
+    SslNotAllowedByServer,
+    /// SSL_CERT_NOT_ON_SERVER
+    SslCertNotOnServer,
+    /// INCONSISTENT_FLAGS
+    InconsistentFlags,
+    /// HYBRID_REQUIRED_BY_SERVER
+    HybridRequiredByServer,
+    /// SSL_WITH_USER_AUTH_REQUIRED_BY_SERVER
+    SslWithUserAuthRequiredByServer,
+    /// A failure code not defined by the specification.
+    Unknown(u32),
+}
+
+impl From<FailureCode> for NegotiationFailureCode {
+    fn from(value: FailureCode) -> Self {
+        match value {
+            FailureCode::SSL_REQUIRED_BY_SERVER => Self::SslRequiredByServer,
+            FailureCode::SSL_NOT_ALLOWED_BY_SERVER => Self::SslNotAllowedByServer,
+            FailureCode::SSL_CERT_NOT_ON_SERVER => Self::SslCertNotOnServer,
+            FailureCode::INCONSISTENT_FLAGS => Self::InconsistentFlags,
+            FailureCode::HYBRID_REQUIRED_BY_SERVER => Self::HybridRequiredByServer,
+            FailureCode::SSL_WITH_USER_AUTH_REQUIRED_BY_SERVER => Self::SslWithUserAuthRequiredByServer,
+            FailureCode(code) => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<NegotiationFailureCode> for FailureCode {
+    fn from(value: NegotiationFailureCode) -> Self {
+        match value {
+            NegotiationFailureCode::SslRequiredByServer => Self::SSL_REQUIRED_BY_SERVER,
+            NegotiationFailureCode::SslNotAllowedByServer => Self::SSL_NOT_ALLOWED_BY_SERVER,
+            NegotiationFailureCode::SslCertNotOnServer => Self::SSL_CERT_NOT_ON_SERVER,
+            NegotiationFailureCode::InconsistentFlags => Self::INCONSISTENT_FLAGS,
+            NegotiationFailureCode::HybridRequiredByServer => Self::HYBRID_REQUIRED_BY_SERVER,
+            NegotiationFailureCode::SslWithUserAuthRequiredByServer => Self::SSL_WITH_USER_AUTH_REQUIRED_BY_SERVER,
+            NegotiationFailureCode::Unknown(code) => Self(code),
+        }
+    }
+}
+
+impl fmt::Display for NegotiationFailureCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SslRequiredByServer => write!(
+                f,
+                "the server requires TLS security and does not support CredSSP (NLA): enable TLS security"
+            ),
+            Self::SslNotAllowedByServer => write!(
+                f,
+                "the server only allows the standard RDP security: enable TLS or CredSSP (NLA) security on the server"
+            ),
+            Self::SslCertNotOnServer => write!(
+                f,
+                "the server has no valid certificate for TLS: install a certificate on the server"
+            ),
+            Self::InconsistentFlags => write!(
+                f,
+                "the requested security protocols are inconsistent with the one already in effect on the connection"
+            ),
+            Self::HybridRequiredByServer => {
+                write!(f, "the server requires CredSSP (NLA) security: enable CredSSP security")
+            }
+            Self::SslWithUserAuthRequiredByServer => write!(
+                f,
+                "the server requires TLS security with client certificate authentication, which is not supported: \
+                 disable the client authentication on the server"
+            ),
+            Self::Unknown(code) => write!(f, "unknown negotiation failure code {code:#x}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FailureCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 /// The kind of the negotiation request message.
 ///
 /// # MSDN
 ///
 /// * [Client X.224 Connection Request PDU](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/18a27ef9-6f9a-4501-b000-94b1fe3c2c10)
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NegoRequestData {
     RoutingToken(RoutingToken),
     Cookie(Cookie),
+    /// Opaque bytes sent as-is, such as the load balancing information provided by a server redirection.
+    ///
+    /// The terminating CR LF sequence is appended when missing.
+    Verbatim(Vec<u8>),
 }
 
 impl NegoRequestData {
@@ -171,6 +268,10 @@ impl NegoRequestData {
         Self::Cookie(Cookie(value))
     }
 
+    pub fn verbatim(value: Vec<u8>) -> Self {
+        Self::Verbatim(value)
+    }
+
     pub fn read(src: &mut ReadCursor<'_>) -> DecodeResult<Option<Self>> {
         match RoutingToken::read(src)? {
             Some(token) => Ok(Some(Self::RoutingToken(token))),
@@ -182,6 +283,16 @@ impl NegoRequestData {
         match self {
             NegoRequestData::RoutingToken(token) => token.write(dst),
             NegoRequestData::Cookie(cookie) => cookie.write(dst),
+            NegoRequestData::Verbatim(data) => {
+                ensure_size!(ctx: "NegoRequestData", in: dst, size: self.size());
+
+                dst.write_slice(data);
+                if !data.ends_with(b"\r\n") {
+                    dst.write_u16(0x0A0D);
+                }
+
+                Ok(())
+            }
         }
     }
 
@@ -189,11 +300,19 @@ impl NegoRequestData {
         match self {
             NegoRequestData::RoutingToken(token) => token.size(),
             NegoRequestData::Cookie(cookie) => cookie.size(),
+            NegoRequestData::Verbatim(data) => {
+                if data.ends_with(b"\r\n") {
+                    data.len()
+                } else {
+                    data.len() + 2
+                }
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cookie(pub String);
 
 impl Cookie {
@@ -213,6 +332,7 @@ impl Cookie {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RoutingToken(pub String);
 
 impl RoutingToken {
@@ -231,6 +351,79 @@ impl RoutingToken {
     }
 }
 
+/// RDP Correlation Info (RDP_NEG_CORRELATION_INFO)
+///
+/// Sent along the negotiation request, so that the connection can be correlated across the load balancers and the
+/// servers.
+///
+/// # MSDN
+///
+/// * [RDP Correlation Info (RDP_NEG_CORRELATION_INFO)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/d6ac9c5d-1fee-4d8d-bb3c-6c3d9aba6fb5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CorrelationInfo {
+    pub correlation_id: [u8; 16],
+}
+
+impl CorrelationInfo {
+    const NAME: &'static str = "RDP_NEG_CORRELATION_INFO";
+
+    const TYPE: u8 = 0x06;
+
+    const SIZE: u16 = 1 /* type */ + 1 /* flags */ + 2 /* length */ + 16 /* correlationId */ + 16 /* reserved */;
+
+    /// Returns `true` if `id` follows the constraints of the specification: its first byte is neither `0x00` nor
+    /// `0xF4`, and none of its bytes is `0x0D`.
+    pub fn is_valid_id(id: &[u8; 16]) -> bool {
+        !matches!(id[0], 0x00 | 0xF4) && !id.contains(&0x0D)
+    }
+
+    pub fn write(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(ctx: Self::NAME, in: dst, size: usize::from(Self::SIZE));
+
+        if !Self::is_valid_id(&self.correlation_id) {
+            return Err(invalid_field_err(
+                Self::NAME,
+                "correlationId",
+                "starts with 0x00 or 0xF4, or contains 0x0D",
+            ));
+        }
+
+        dst.write_u8(Self::TYPE);
+        dst.write_u8(0); // flags
+        dst.write_u16(Self::SIZE);
+        dst.write_array(self.correlation_id);
+        write_padding!(dst, 16);
+
+        Ok(())
+    }
+
+    pub fn read(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(ctx: Self::NAME, in: src, size: usize::from(Self::SIZE));
+
+        let msg_type = src.read_u8();
+
+        if msg_type != Self::TYPE {
+            return Err(unexpected_message_type_err!(Self::NAME, msg_type));
+        }
+
+        let _flags = src.read_u8();
+
+        if src.read_u16() != Self::SIZE {
+            return Err(invalid_field_err(Self::NAME, "length", "invalid size"));
+        }
+
+        let correlation_id = src.read_array();
+        read_padding!(src, 16);
+
+        Ok(Self { correlation_id })
+    }
+
+    pub fn size(&self) -> usize {
+        usize::from(Self::SIZE)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct NegoMsgType(u8);
 
@@ -253,10 +446,13 @@ impl From<NegoMsgType> for u8 {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConnectionRequest {
     pub nego_data: Option<NegoRequestData>,
+    /// The `CORRELATION_INFO_PRESENT` flag is set when encoding a request with a `correlation_info`.
     pub flags: RequestFlags,
     pub protocol: SecurityProtocol,
+    pub correlation_info: Option<CorrelationInfo>,
 }
 
 impl_x224_pdu_pod!(ConnectionRequest);
@@ -275,20 +471,27 @@ impl<'de> X224Pdu<'de> for ConnectionRequest {
             nego_data.write(dst)?;
         }
 
+        let mut flags = self.flags;
+
+        if self.correlation_info.is_some() {
+            flags.insert(RequestFlags::CORRELATION_INFO_PRESENT);
+        } else if flags.contains(RequestFlags::CORRELATION_INFO_PRESENT) {
+            return Err(invalid_field_err(
+                Self::NAME,
+                "flags",
+                "CORRELATION_INFO_PRESENT flag is set, but the correlation info is missing",
+            ));
+        }
+
         // [MS-RDPBCGR] mentions the following payload as optional, but it appears that on recent
         // versions of Windows, the server always expect to find this payload.
         dst.write_u8(u8::from(NegoMsgType::REQUEST));
-        dst.write_u8(self.flags.bits());
+        dst.write_u8(flags.bits());
         dst.write_u16(Self::RDP_NEG_REQ_SIZE);
         dst.write_u32(self.protocol.bits());
 
-        if self.flags.contains(RequestFlags::CORRELATION_INFO_PRESENT) {
-            // TODO(#111): support for RDP_NEG_CORRELATION_INFO
-            return Err(invalid_field_err(
-                Self::NAME,
-                "flags",
-                "CORRECTION_INFO_PRESENT flag is set, but not supported by IronRDP",
-            ));
+        if let Some(correlation_info) = &self.correlation_info {
+            correlation_info.write(dst)?;
         }
 
         Ok(())
@@ -320,36 +523,36 @@ impl<'de> X224Pdu<'de> for ConnectionRequest {
 
             let flags = RequestFlags::from_bits_truncate(src.read_u8());
 
-            if flags.contains(RequestFlags::CORRELATION_INFO_PRESENT) {
-                // TODO(#111): support for RDP_NEG_CORRELATION_INFO
-                return Err(invalid_field_err(
-                    Self::NAME,
-                    "flags",
-                    "CORRECTION_INFO_PRESENT flag is set, but not supported by IronRDP",
-                ));
-            }
-
             let _length = src.read_u16();
 
             let protocol = SecurityProtocol::from_bits_truncate(src.read_u32());
 
+            let correlation_info = if flags.contains(RequestFlags::CORRELATION_INFO_PRESENT) {
+                Some(CorrelationInfo::read(src)?)
+            } else {
+                None
+            };
+
             Ok(Self {
                 nego_data,
                 flags,
                 protocol,
+                correlation_info,
             })
         } else {
             Ok(Self {
                 nego_data,
                 flags: RequestFlags::empty(),
                 protocol: SecurityProtocol::empty(),
+                correlation_info: None,
             })
         }
     }
 
     fn tpdu_header_variable_part_size(&self) -> usize {
         let optional_nego_data_size = self.nego_data.as_ref().map(|data| data.size()).unwrap_or(0);
-        optional_nego_data_size + usize::from(Self::RDP_NEG_REQ_SIZE)
+        let optional_correlation_info_size = self.correlation_info.as_ref().map(|info| info.size()).unwrap_or(0);
+        optional_nego_data_size + usize::from(Self::RDP_NEG_REQ_SIZE) + optional_correlation_info_size
     }
 
     fn tpdu_user_data_size(&self) -> usize {
@@ -358,6 +561,7 @@ impl<'de> X224Pdu<'de> for ConnectionRequest {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ConnectionConfirm {
     Response {
         flags: ResponseFlags,
@@ -16,9 +16,13 @@ pub mod capability_sets;
 pub mod client_info;
 pub mod finalization_messages;
 pub mod headers;
+pub mod heartbeat;
+pub mod multitransport;
+pub mod play_sound;
 pub mod refresh_rectangle;
 pub mod server_error_info;
 pub mod server_license;
+pub mod server_redirection;
 pub mod session_info;
 pub mod suppress_output;
 pub mod vc;
@@ -1,19 +1,20 @@
 use std::io;
 
 use ironrdp_core::{
-    cast_length, decode, ensure_fixed_part_size, ensure_size, invalid_field_err, unsupported_value_err, Decode,
-    DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+    cast_length, decode, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode,
+    EncodeResult, ReadCursor, WriteCursor,
 };
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive as _, ToPrimitive as _};
 use thiserror::Error;
 
-use crate::{utils, PduError};
+use crate::{utils, DecodeOptions, DecodeWarning, PduError};
 
 mod bitmap;
 mod bitmap_cache;
 mod bitmap_codecs;
 mod brush;
+mod client_capabilities;
 mod frame_acknowledge;
 mod general;
 mod glyph_cache;
@@ -36,6 +37,7 @@ pub use self::bitmap_codecs::{
     RfxCapset, RfxClientCapsContainer, RfxICap, RfxICapFlags,
 };
 pub use self::brush::{Brush, SupportLevel};
+pub use self::client_capabilities::{ClientCapabilitiesBuilder, ClientCapabilitiesError};
 pub use self::frame_acknowledge::FrameAcknowledge;
 pub use self::general::{General, GeneralExtraFlags, MajorPlatformType, MinorPlatformType, PROTOCOL_VER};
 pub use self::glyph_cache::{CacheDefinition, GlyphCache, GlyphSupportLevel, GLYPH_CACHE_NUM};
@@ -66,6 +68,7 @@ const NULL_TERMINATOR: &str = "\0";
 ///
 /// [2.2.1.13.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/a07abad1-38bb-4a1a-96c9-253e3d5440df
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerDemandActive {
     pub pdu: DemandActive,
 }
@@ -95,6 +98,42 @@ impl Encode for ServerDemandActive {
     }
 }
 
+impl ServerDemandActive {
+    /// Decodes the Server Demand Active PDU, see [`DemandActive::decode_with_options`].
+    ///
+    /// In lenient mode, a missing session ID and bytes following it are also tolerated.
+    pub fn decode_with_options(
+        src: &mut ReadCursor<'_>,
+        options: DecodeOptions,
+        warnings: &mut Vec<DecodeWarning>,
+    ) -> DecodeResult<Self> {
+        if !options.lenient {
+            return Self::decode(src);
+        }
+
+        let pdu = DemandActive::decode_with_options(src, options, warnings)?;
+
+        if src.len() < SESSION_ID_FIELD_SIZE {
+            warnings.push(DecodeWarning::MissingField {
+                context: Self::NAME,
+                field: "sessionId",
+            });
+        } else {
+            let _session_id = src.read_u32();
+        }
+
+        if !src.is_empty() {
+            warnings.push(DecodeWarning::TrailingBytes {
+                context: Self::NAME,
+                count: src.len(),
+            });
+            src.advance(src.len());
+        }
+
+        Ok(Self { pdu })
+    }
+}
+
 impl<'de> Decode<'de> for ServerDemandActive {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
         let pdu = DemandActive::decode(src)?;
@@ -110,6 +149,7 @@ impl<'de> Decode<'de> for ServerDemandActive {
 ///
 /// [2.2.1.13.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/4c3c2710-0bf0-4c54-8e69-aff40ffcde66
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientConfirmActive {
     /// According to [MSDN](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/4e9722c3-ad83-43f5-af5a-529f73d88b48),
     /// this field MUST be set to [SERVER_CHANNEL_ID](constant.SERVER_CHANNEL_ID.html).
@@ -160,6 +200,7 @@ impl<'de> Decode<'de> for ClientConfirmActive {
 ///
 /// [2.2.1.13.1.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/bd612af5-cb54-43a2-9646-438bc3ecf5db
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DemandActive {
     pub source_descriptor: String,
     pub capability_sets: Vec<CapabilitySet>,
@@ -210,6 +251,83 @@ impl Encode for DemandActive {
     }
 }
 
+impl DemandActive {
+    /// Decodes the Demand Active PDU data, tolerating non-conformant capability sets in lenient mode.
+    ///
+    /// In lenient mode, malformed capability sets are skipped, and a capability set length going past the end of the
+    /// data ends the decoding. A [`DecodeWarning`] is pushed for each deviation, including unknown capability sets,
+    /// which are kept as [`CapabilitySet::Unknown`].
+    pub fn decode_with_options(
+        src: &mut ReadCursor<'_>,
+        options: DecodeOptions,
+        warnings: &mut Vec<DecodeWarning>,
+    ) -> DecodeResult<Self> {
+        if !options.lenient {
+            return Self::decode(src);
+        }
+
+        ensure_fixed_part_size!(in: src);
+
+        let source_descriptor_length = src.read_u16() as usize;
+        let _combined_capabilities_length = src.read_u16() as usize;
+
+        ensure_size!(in: src, size: source_descriptor_length);
+        let source_descriptor = utils::decode_string(
+            src.read_slice(source_descriptor_length),
+            utils::CharacterSet::Ansi,
+            false,
+        )?;
+
+        ensure_size!(in: src, size: 2 + 2);
+        let capability_sets_count = src.read_u16() as usize;
+        let _padding = src.read_u16();
+
+        let mut capability_sets = Vec::with_capacity(capability_sets_count);
+
+        for found in 0..capability_sets_count {
+            if src.len() < CapabilitySet::FIXED_PART_SIZE {
+                warnings.push(DecodeWarning::MissingCapabilitySets {
+                    expected: capability_sets_count,
+                    found,
+                });
+                break;
+            }
+
+            let header = src.peek_slice(CapabilitySet::FIXED_PART_SIZE);
+            let capability_set_type = u16::from_le_bytes([header[0], header[1]]);
+            let length = usize::from(u16::from_le_bytes([header[2], header[3]]));
+
+            if length < CapabilitySet::FIXED_PART_SIZE || length > src.len() {
+                warnings.push(DecodeWarning::TrailingBytes {
+                    context: Self::NAME,
+                    count: src.len(),
+                });
+                src.advance(src.len());
+                break;
+            }
+
+            let capability_set = src.read_slice(length);
+
+            if CapabilitySetType::from_u16(capability_set_type).is_none() {
+                warnings.push(DecodeWarning::UnknownCapabilitySet { capability_set_type });
+            }
+
+            match decode::<CapabilitySet>(capability_set) {
+                Ok(capability_set) => capability_sets.push(capability_set),
+                Err(e) => warnings.push(DecodeWarning::MalformedCapabilitySet {
+                    capability_set_type,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(Self {
+            source_descriptor,
+            capability_sets,
+        })
+    }
+}
+
 impl<'de> Decode<'de> for DemandActive {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
         ensure_fixed_part_size!(in: src);
@@ -243,6 +361,7 @@ impl<'de> Decode<'de> for DemandActive {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CapabilitySet {
     // mandatory
     General(General),
@@ -278,6 +397,13 @@ pub enum CapabilitySet {
     Rail(Vec<u8>),
     WindowList(Vec<u8>),
     BitmapCacheV3(Vec<u8>),
+
+    /// Capability set of a type unknown to IronRDP, re-encoded byte for byte
+    Unknown {
+        capability_set_type: u16,
+        /// Data of the capability set, without its header.
+        data: Vec<u8>,
+    },
 }
 
 impl CapabilitySet {
@@ -427,6 +553,17 @@ impl Encode for CapabilitySet {
                 )?);
                 capset.encode(dst)?;
             }
+            CapabilitySet::Unknown {
+                capability_set_type,
+                data,
+            } => {
+                dst.write_u16(*capability_set_type);
+                dst.write_u16(cast_length!(
+                    "len",
+                    data.len() + CAPABILITY_SET_TYPE_FIELD_SIZE + CAPABILITY_SET_LENGTH_FIELD_SIZE
+                )?);
+                dst.write_slice(data);
+            }
             _ => {
                 let (capability_set_type, capability_set_buffer) = match self {
                     CapabilitySet::Control(buffer) => (CapabilitySetType::Control, buffer),
@@ -442,6 +579,7 @@ impl Encode for CapabilitySet {
                     CapabilitySet::DrawGdiPlus(buffer) => (CapabilitySetType::DrawGdiPlus, buffer),
                     CapabilitySet::Rail(buffer) => (CapabilitySetType::Rail, buffer),
                     CapabilitySet::WindowList(buffer) => (CapabilitySetType::WindowList, buffer),
+                    CapabilitySet::BitmapCacheV3(buffer) => (CapabilitySetType::BitmapCacheV3CodecID, buffer),
                     _ => unreachable!(),
                 };
 
@@ -491,7 +629,8 @@ impl Encode for CapabilitySet {
                 | CapabilitySet::DrawGdiPlus(buffer)
                 | CapabilitySet::Rail(buffer)
                 | CapabilitySet::WindowList(buffer)
-                | CapabilitySet::BitmapCacheV3(buffer) => buffer.len(),
+                | CapabilitySet::BitmapCacheV3(buffer)
+                | CapabilitySet::Unknown { data: buffer, .. } => buffer.len(),
             }
     }
 }
@@ -501,13 +640,6 @@ impl<'de> Decode<'de> for CapabilitySet {
         ensure_fixed_part_size!(in: src);
 
         let capability_set_type_raw = src.read_u16();
-        let capability_set_type = CapabilitySetType::from_u16(capability_set_type_raw).ok_or_else(|| {
-            unsupported_value_err!(
-                "capabilitySetType",
-                format!("invalid capability set type: {}", capability_set_type_raw)
-            )
-        })?;
-
         let length = src.read_u16() as usize;
 
         if length < CAPABILITY_SET_TYPE_FIELD_SIZE + CAPABILITY_SET_LENGTH_FIELD_SIZE {
@@ -518,6 +650,13 @@ impl<'de> Decode<'de> for CapabilitySet {
         ensure_size!(in: src, size: buffer_length);
         let capability_set_buffer = src.read_slice(buffer_length);
 
+        let Some(capability_set_type) = CapabilitySetType::from_u16(capability_set_type_raw) else {
+            return Ok(CapabilitySet::Unknown {
+                capability_set_type: capability_set_type_raw,
+                data: capability_set_buffer.into(),
+            });
+        };
+
         match capability_set_type {
             CapabilitySetType::General => Ok(CapabilitySet::General(decode(capability_set_buffer)?)),
             CapabilitySetType::Bitmap => Ok(CapabilitySet::Bitmap(decode(capability_set_buffer)?)),
@@ -10,6 +10,7 @@ const BITMAP_LENGTH: usize = 24;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct BitmapDrawingFlags: u8 {
         const ALLOW_DYNAMIC_COLOR_FIDELITY = 0x02;
         const ALLOW_COLOR_SUBSAMPLING = 0x04;
@@ -19,6 +20,7 @@ bitflags! {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Bitmap {
     pub pref_bits_per_pix: u16,
     pub desktop_width: u16,
@@ -31,6 +33,19 @@ impl Bitmap {
     const NAME: &'static str = "Bitmap";
 
     const FIXED_PART_SIZE: usize = BITMAP_LENGTH;
+
+    /// Color depths which can be set in the `preferredBitsPerPixel` field.
+    pub const COLOR_DEPTHS: [u16; 5] = [8, 15, 16, 24, 32];
+
+    /// Returns the color depth in the `preferredBitsPerPixel` field, or `None` if it is not one of
+    /// [`Self::COLOR_DEPTHS`].
+    ///
+    /// In the Server Demand Active PDU, this is the color depth granted by the server for the session.
+    pub fn color_depth(&self) -> Option<u16> {
+        Self::COLOR_DEPTHS
+            .contains(&self.pref_bits_per_pix)
+            .then_some(self.pref_bits_per_pix)
+    }
 }
 
 impl Encode for Bitmap {
@@ -51,3 +51,15 @@ fn buffer_length_is_correct_for_bitmap_capset() {
 
     assert_eq!(correct_buffer_length, BITMAP.size());
 }
+
+#[test]
+fn color_depth_is_validated() {
+    assert_eq!(BITMAP.color_depth(), Some(24));
+
+    let capset = Bitmap {
+        pref_bits_per_pix: 4,
+        ..BITMAP.clone()
+    };
+
+    assert_eq!(capset.color_depth(), None);
+}
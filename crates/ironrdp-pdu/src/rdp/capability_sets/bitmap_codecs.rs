@@ -97,7 +97,19 @@ impl<'de> Decode<'de> for Guid {
     }
 }
 
+/// Serialized in the registry format, e.g.: `ca8d1bb9-000f-154f-589f-ae2d1a87e2d6`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Guid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!(
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            self.0, self.1, self.2, self.3, self.4, self.5, self.6, self.7, self.8, self.9, self.10
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BitmapCodecs(pub Vec<Codec>);
 
 impl BitmapCodecs {
@@ -144,6 +156,7 @@ impl<'de> Decode<'de> for BitmapCodecs {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Codec {
     pub id: u8,
     pub property: CodecProperty,
@@ -279,12 +292,14 @@ impl<'de> Decode<'de> for Codec {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RemoteFxContainer {
     ClientContainer(RfxClientCapsContainer),
     ServerContainer(usize),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CodecProperty {
     NsCodec(NsCodec),
     RemoteFx(RemoteFxContainer),
@@ -307,6 +322,7 @@ pub enum CodecProperty {
 ///
 /// * [NSCodec Capability Set](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpnsc/0eac0ba8-7bdd-4300-ab8d-9bc784c0a669)
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NsCodec {
     pub is_dynamic_fidelity_allowed: bool,
     pub is_subsampling_allowed: bool,
@@ -357,6 +373,7 @@ impl<'de> Decode<'de> for NsCodec {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RfxClientCapsContainer {
     pub capture_flags: CaptureFlags,
     pub caps_data: RfxCaps,
@@ -404,6 +421,7 @@ impl<'de> Decode<'de> for RfxClientCapsContainer {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RfxCaps(pub RfxCapset);
 
 impl RfxCaps {
@@ -459,6 +477,7 @@ impl<'de> Decode<'de> for RfxCaps {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RfxCapset(pub Vec<RfxICap>);
 
 impl RfxCapset {
@@ -529,6 +548,7 @@ impl<'de> Decode<'de> for RfxCapset {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RfxICap {
     pub flags: RfxICapFlags,
     pub entropy_bits: EntropyBits,
@@ -597,6 +617,7 @@ impl<'de> Decode<'de> for RfxICap {
 }
 
 #[derive(PartialEq, Eq, Debug, FromPrimitive, ToPrimitive, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum EntropyBits {
     Rlgr1 = 1,
     Rlgr3 = 4,
@@ -604,6 +625,7 @@ pub enum EntropyBits {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct CaptureFlags: u32 {
         const CARDP_CAPS_CAPTURE_NON_CAC = 1;
     }
@@ -611,6 +633,7 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct RfxICapFlags: u8 {
         const CODEC_MODE = 2;
     }
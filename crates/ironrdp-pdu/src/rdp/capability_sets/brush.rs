@@ -10,6 +10,7 @@ use num_traits::{FromPrimitive, ToPrimitive};
 const BRUSH_LENGTH: usize = 4;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SupportLevel {
     Default = 0,
     Color8x8 = 1,
@@ -17,6 +18,7 @@ pub enum SupportLevel {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Brush {
     pub support_level: SupportLevel,
 }
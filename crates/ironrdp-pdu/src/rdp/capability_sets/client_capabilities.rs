@@ -0,0 +1,382 @@
+use core::mem;
+
+use thiserror::Error;
+
+use super::{
+    Bitmap, BitmapCache, BitmapCacheRev2, BitmapCodecs, BitmapDrawingFlags, Brush, CacheDefinition, CacheEntry,
+    CapabilitySet, CmdFlags, Codec, FrameAcknowledge, General, GeneralExtraFlags, GlyphCache, GlyphSupportLevel, Input,
+    InputFlags, LargePointer, LargePointerSupportFlags, MajorPlatformType, MultifragmentUpdate, OffscreenBitmapCache,
+    Order, OrderFlags, OrderSupportExFlags, OrderSupportIndex, Pointer, Sound, SoundFlags, SupportLevel,
+    SurfaceCommands, VirtualChannel, VirtualChannelFlags, BITMAP_CACHE_ENTRIES_NUM, GLYPH_CACHE_NUM,
+};
+use crate::gcc::KeyboardType;
+
+/// Inconsistent combination of client capabilities, rejected by [`ClientCapabilitiesBuilder::build`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ClientCapabilitiesError {
+    #[error("empty desktop size ({width}x{height})")]
+    EmptyDesktop { width: u16, height: u16 },
+    #[error("unsupported color depth: {0} bpp")]
+    UnsupportedColorDepth(u16),
+    /// The surface bits commands are advertised, but no codec is available to encode the bitmaps they carry.
+    #[error("surface bits commands advertised without any bitmap codec")]
+    SurfaceCommandsWithoutCodec,
+    /// The frames can't be acknowledged without the frame markers delimiting them.
+    #[error("frame acknowledgement advertised without the frame marker command")]
+    FrameAcknowledgeWithoutFrameMarker,
+    /// Pointers larger than 96x96 pixels are only sent in fast-path updates.
+    #[error("large pointers up to 384x384 pixels advertised without fast-path output")]
+    LargePointerWithoutFastPathOutput,
+    /// The raw capability set at `index`, in the order of [`ClientCapabilitiesBuilder::with_raw_capability_set`]
+    /// calls, is of a type already advertised.
+    #[error("raw capability set #{index} is already advertised")]
+    DuplicateCapabilitySet { index: usize },
+}
+
+/// Builds the capability sets of the Client Confirm Active PDU from the features supported by the client
+///
+/// The mandatory capability sets are always generated, in the order used by IronRDP. Capability sets of other types,
+/// such as the RAIL ones, can be appended as-is with [`ClientCapabilitiesBuilder::with_raw_capability_set`].
+#[derive(Debug, Clone)]
+pub struct ClientCapabilitiesBuilder {
+    platform: MajorPlatformType,
+    desktop_width: u16,
+    desktop_height: u16,
+    color_depth: u16,
+    lossy_bitmap_compression: bool,
+    fast_path_output: bool,
+    input_flags: InputFlags,
+    keyboard_layout: u32,
+    keyboard_type: Option<KeyboardType>,
+    keyboard_subtype: u32,
+    keyboard_function_keys: u32,
+    ime_file_name: String,
+    pointer_cache_size: u16,
+    bitmap_cache_rev2: Option<BitmapCacheRev2>,
+    large_pointer: LargePointerSupportFlags,
+    virtual_channel_compression: VirtualChannelFlags,
+    surface_commands: CmdFlags,
+    codecs: Vec<Codec>,
+    frame_acknowledge: Option<u32>,
+    multifragment_max_request_size: u32,
+    raw: Vec<CapabilitySet>,
+}
+
+impl ClientCapabilitiesBuilder {
+    /// Pointer cache size used unless specified otherwise.
+    pub const DEFAULT_POINTER_CACHE_SIZE: u16 = 32;
+
+    /// Maximum size of the fast-path updates reassembled by the client unless specified otherwise.
+    pub const DEFAULT_MULTIFRAGMENT_MAX_REQUEST_SIZE: u32 = 8 * 1024 * 1024;
+
+    /// Advertises a 32 bpp desktop of the given size, with fast-path output and the input of every kind.
+    ///
+    /// Neither surface commands nor bitmap codecs are advertised by default.
+    pub fn new(desktop_width: u16, desktop_height: u16) -> Self {
+        Self {
+            platform: MajorPlatformType::UNSPECIFIED,
+            desktop_width,
+            desktop_height,
+            color_depth: 32,
+            lossy_bitmap_compression: false,
+            fast_path_output: true,
+            input_flags: InputFlags::all(),
+            keyboard_layout: 0,
+            keyboard_type: None,
+            keyboard_subtype: 0,
+            keyboard_function_keys: 0,
+            ime_file_name: String::new(),
+            pointer_cache_size: Self::DEFAULT_POINTER_CACHE_SIZE,
+            bitmap_cache_rev2: None,
+            large_pointer: LargePointerSupportFlags::UP_TO_96X96_PIXELS
+                | LargePointerSupportFlags::UP_TO_384X384_PIXELS,
+            virtual_channel_compression: VirtualChannelFlags::NO_COMPRESSION,
+            surface_commands: CmdFlags::empty(),
+            codecs: Vec::new(),
+            frame_acknowledge: None,
+            multifragment_max_request_size: Self::DEFAULT_MULTIFRAGMENT_MAX_REQUEST_SIZE,
+            raw: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_platform(mut self, platform: MajorPlatformType) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Preferred color depth, in bits per pixel.
+    #[must_use]
+    pub fn with_color_depth(mut self, color_depth: u16) -> Self {
+        self.color_depth = color_depth;
+        self
+    }
+
+    /// Allows the server to reduce the color fidelity of the bitmaps, and to subsample their chroma.
+    #[must_use]
+    pub fn with_lossy_bitmap_compression(mut self, enabled: bool) -> Self {
+        self.lossy_bitmap_compression = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn with_fast_path_output(mut self, enabled: bool) -> Self {
+        self.fast_path_output = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn with_input_flags(mut self, input_flags: InputFlags) -> Self {
+        self.input_flags = input_flags;
+        self
+    }
+
+    /// Keyboard of the client, as described in the Client Core Data.
+    #[must_use]
+    pub fn with_keyboard(
+        mut self,
+        layout: u32,
+        keyboard_type: Option<KeyboardType>,
+        subtype: u32,
+        function_keys: u32,
+        ime_file_name: impl Into<String>,
+    ) -> Self {
+        self.keyboard_layout = layout;
+        self.keyboard_type = keyboard_type;
+        self.keyboard_subtype = subtype;
+        self.keyboard_function_keys = function_keys;
+        self.ime_file_name = ime_file_name.into();
+        self
+    }
+
+    /// Size of the pointer caches, a non-zero size enabling client-side pointer rendering.
+    #[must_use]
+    pub fn with_pointer_cache_size(mut self, size: u16) -> Self {
+        self.pointer_cache_size = size;
+        self
+    }
+
+    /// Advertises the revision 2 bitmap cache instead of an empty revision 1 one, along with the MemBlt and Mem3Blt
+    /// orders drawing from it.
+    #[must_use]
+    pub fn with_bitmap_cache_rev2(mut self, bitmap_cache: BitmapCacheRev2) -> Self {
+        self.bitmap_cache_rev2 = Some(bitmap_cache);
+        self
+    }
+
+    #[must_use]
+    pub fn with_large_pointer(mut self, flags: LargePointerSupportFlags) -> Self {
+        self.large_pointer = flags;
+        self
+    }
+
+    /// Compression of the virtual channel data supported by the client.
+    #[must_use]
+    pub fn with_virtual_channel_compression(mut self, flags: VirtualChannelFlags) -> Self {
+        self.virtual_channel_compression = flags;
+        self
+    }
+
+    #[must_use]
+    pub fn with_surface_commands(mut self, flags: CmdFlags) -> Self {
+        self.surface_commands = flags;
+        self
+    }
+
+    /// Bitmap codecs supported by the client, in order of preference.
+    #[must_use]
+    pub fn with_codecs(mut self, codecs: Vec<Codec>) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Acknowledges the frames, the server sending up to `max_unacknowledged_frame_count` frames in advance.
+    ///
+    /// Requires the [`CmdFlags::FRAME_MARKER`] surface command.
+    #[must_use]
+    pub fn with_frame_acknowledge(mut self, max_unacknowledged_frame_count: u32) -> Self {
+        self.frame_acknowledge = Some(max_unacknowledged_frame_count);
+        self
+    }
+
+    #[must_use]
+    pub fn with_multifragment_update(mut self, max_request_size: u32) -> Self {
+        self.multifragment_max_request_size = max_request_size;
+        self
+    }
+
+    /// Appends `capability_set` as-is, before the Multifragment Update Capability Set closing the generated ones.
+    ///
+    /// This is an escape hatch for the capability sets not covered by this builder: their consistency with the
+    /// generated ones is not checked, beyond the type not being advertised already.
+    #[must_use]
+    pub fn with_raw_capability_set(mut self, capability_set: CapabilitySet) -> Self {
+        self.raw.push(capability_set);
+        self
+    }
+
+    /// Returns the capability sets, in the order they are to be sent.
+    pub fn build(self) -> Result<Vec<CapabilitySet>, ClientCapabilitiesError> {
+        self.validate()?;
+
+        let drawing_flags = if self.lossy_bitmap_compression {
+            BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+                | BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY
+                | BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING
+        } else {
+            BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+        };
+
+        let extra_flags = if self.fast_path_output {
+            GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR
+        } else {
+            GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR
+        };
+
+        let mut order = Order::new(
+            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
+            OrderSupportExFlags::empty(),
+            0,
+            0,
+        );
+
+        let bitmap_cache = if let Some(bitmap_cache) = self.bitmap_cache_rev2 {
+            order.set_support_flag(OrderSupportIndex::MemBlt, true);
+            order.set_support_flag(OrderSupportIndex::Mem3Blt, true);
+
+            CapabilitySet::BitmapCacheRev2(bitmap_cache)
+        } else {
+            CapabilitySet::BitmapCache(BitmapCache {
+                caches: [CacheEntry {
+                    entries: 0,
+                    max_cell_size: 0,
+                }; BITMAP_CACHE_ENTRIES_NUM],
+            })
+        };
+
+        let mut capability_sets = vec![
+            CapabilitySet::General(General {
+                major_platform_type: self.platform,
+                extra_flags,
+                ..Default::default()
+            }),
+            CapabilitySet::Bitmap(Bitmap {
+                pref_bits_per_pix: self.color_depth,
+                desktop_width: self.desktop_width,
+                desktop_height: self.desktop_height,
+                // This is required to be true in order for the Microsoft::Windows::RDS::DisplayControl DVC to work.
+                desktop_resize_flag: true,
+                drawing_flags,
+            }),
+            CapabilitySet::Order(order),
+            bitmap_cache,
+            CapabilitySet::Input(Input {
+                input_flags: self.input_flags,
+                keyboard_layout: self.keyboard_layout,
+                keyboard_type: self.keyboard_type,
+                keyboard_subtype: self.keyboard_subtype,
+                keyboard_function_key: self.keyboard_function_keys,
+                keyboard_ime_filename: self.ime_file_name,
+            }),
+            CapabilitySet::Pointer(Pointer {
+                color_pointer_cache_size: self.pointer_cache_size,
+                pointer_cache_size: self.pointer_cache_size,
+            }),
+            CapabilitySet::Brush(Brush {
+                support_level: SupportLevel::Default,
+            }),
+            CapabilitySet::GlyphCache(GlyphCache {
+                glyph_cache: [CacheDefinition {
+                    entries: 0,
+                    max_cell_size: 0,
+                }; GLYPH_CACHE_NUM],
+                frag_cache: CacheDefinition {
+                    entries: 0,
+                    max_cell_size: 0,
+                },
+                glyph_support_level: GlyphSupportLevel::None,
+            }),
+            CapabilitySet::OffscreenBitmapCache(OffscreenBitmapCache {
+                is_supported: false,
+                cache_size: 0,
+                cache_entries: 0,
+            }),
+            CapabilitySet::VirtualChannel(VirtualChannel {
+                flags: self.virtual_channel_compression,
+                chunk_size: Some(0), // ignored
+            }),
+            CapabilitySet::Sound(Sound {
+                flags: SoundFlags::empty(),
+            }),
+            CapabilitySet::LargePointer(LargePointer {
+                flags: self.large_pointer,
+            }),
+            CapabilitySet::SurfaceCommands(SurfaceCommands {
+                flags: self.surface_commands,
+            }),
+            CapabilitySet::BitmapCodecs(BitmapCodecs(self.codecs)),
+        ];
+
+        if let Some(max_unacknowledged_frame_count) = self.frame_acknowledge {
+            capability_sets.push(CapabilitySet::FrameAcknowledge(FrameAcknowledge {
+                max_unacknowledged_frame_count,
+            }));
+        }
+
+        let multifragment_update = CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+            max_request_size: self.multifragment_max_request_size,
+        });
+
+        for (index, raw) in self.raw.into_iter().enumerate() {
+            if capability_sets
+                .iter()
+                .chain([&multifragment_update])
+                .any(|capability_set| mem::discriminant(capability_set) == mem::discriminant(&raw))
+            {
+                return Err(ClientCapabilitiesError::DuplicateCapabilitySet { index });
+            }
+
+            capability_sets.push(raw);
+        }
+
+        capability_sets.push(multifragment_update);
+
+        Ok(capability_sets)
+    }
+
+    fn validate(&self) -> Result<(), ClientCapabilitiesError> {
+        if self.desktop_width == 0 || self.desktop_height == 0 {
+            return Err(ClientCapabilitiesError::EmptyDesktop {
+                width: self.desktop_width,
+                height: self.desktop_height,
+            });
+        }
+
+        if !matches!(self.color_depth, 8 | 15 | 16 | 24 | 32) {
+            return Err(ClientCapabilitiesError::UnsupportedColorDepth(self.color_depth));
+        }
+
+        if self
+            .surface_commands
+            .intersects(CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS)
+            && self.codecs.is_empty()
+        {
+            return Err(ClientCapabilitiesError::SurfaceCommandsWithoutCodec);
+        }
+
+        if self.frame_acknowledge.is_some() && !self.surface_commands.contains(CmdFlags::FRAME_MARKER) {
+            return Err(ClientCapabilitiesError::FrameAcknowledgeWithoutFrameMarker);
+        }
+
+        if self
+            .large_pointer
+            .contains(LargePointerSupportFlags::UP_TO_384X384_PIXELS)
+            && !self.fast_path_output
+        {
+            return Err(ClientCapabilitiesError::LargePointerWithoutFastPathOutput);
+        }
+
+        Ok(())
+    }
+}
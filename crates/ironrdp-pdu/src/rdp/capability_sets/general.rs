@@ -16,23 +16,52 @@ pub struct MajorPlatformType(u16);
 
 impl fmt::Debug for MajorPlatformType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match *self {
-            Self::UNSPECIFIED => "UNSPECIFIED",
-            Self::WINDOWS => "WINDOWS",
-            Self::OS2 => "OS2",
-            Self::MACINTOSH => "MACINTOSH",
-            Self::UNIX => "UNIX",
-            Self::IOS => "IOS",
-            Self::OSX => "OSX",
-            Self::ANDROID => "ANDROID",
-            Self::CHROMEOS => "CHROMEOS",
-            _ => "UNKNOWN",
-        };
-
+        let name = self.name().unwrap_or("UNKNOWN");
         write!(f, "MajorPlatformType(0x{:02X}-{name})", self.0)
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for MajorPlatformType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_u16(self.0),
+        }
+    }
+}
+
+/// Accepts either the name of a known platform (e.g.: `"WINDOWS"`) or the raw value.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MajorPlatformType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = MajorPlatformType;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a major platform type name or a 16-bit integer")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                u16::try_from(value)
+                    .map(MajorPlatformType)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(value), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                MajorPlatformType::ALL
+                    .into_iter()
+                    .find(|platform| platform.name() == Some(value))
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
 impl MajorPlatformType {
     pub const UNSPECIFIED: Self = Self(0);
     pub const WINDOWS: Self = Self(1);
@@ -43,6 +72,34 @@ impl MajorPlatformType {
     pub const OSX: Self = Self(6);
     pub const ANDROID: Self = Self(7);
     pub const CHROMEOS: Self = Self(8);
+
+    #[cfg(feature = "serde")]
+    const ALL: [Self; 9] = [
+        Self::UNSPECIFIED,
+        Self::WINDOWS,
+        Self::OS2,
+        Self::MACINTOSH,
+        Self::UNIX,
+        Self::IOS,
+        Self::OSX,
+        Self::ANDROID,
+        Self::CHROMEOS,
+    ];
+
+    fn name(self) -> Option<&'static str> {
+        match self {
+            Self::UNSPECIFIED => Some("UNSPECIFIED"),
+            Self::WINDOWS => Some("WINDOWS"),
+            Self::OS2 => Some("OS2"),
+            Self::MACINTOSH => Some("MACINTOSH"),
+            Self::UNIX => Some("UNIX"),
+            Self::IOS => Some("IOS"),
+            Self::OSX => Some("OSX"),
+            Self::ANDROID => Some("ANDROID"),
+            Self::CHROMEOS => Some("CHROMEOS"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -50,24 +107,21 @@ pub struct MinorPlatformType(u16);
 
 impl fmt::Debug for MinorPlatformType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match *self {
-            Self::UNSPECIFIED => "UNSPECIFIED",
-            Self::WINDOWS_31X => "WINDOWS_31X",
-            Self::WINDOWS_95 => "WINDOWS_95",
-            Self::WINDOWS_NT => "WINDOWS_NT",
-            Self::OS2V21 => "OS2_V21",
-            Self::POWER_PC => "POWER_PC",
-            Self::MACINTOSH => "MACINTOSH",
-            Self::NATIVE_XSERVER => "NATIVE_XSERVER",
-            Self::PSEUDO_XSERVER => "PSEUDO_XSERVER",
-            Self::WINDOWS_RT => "WINDOWS_RT",
-            _ => "UNKNOWN",
-        };
-
+        let name = self.name().unwrap_or("UNKNOWN");
         write!(f, "MinorPlatformType(0x{:02X}-{name})", self.0)
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for MinorPlatformType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_u16(self.0),
+        }
+    }
+}
+
 impl MinorPlatformType {
     pub const UNSPECIFIED: Self = Self(0);
     pub const WINDOWS_31X: Self = Self(1);
@@ -79,10 +133,27 @@ impl MinorPlatformType {
     pub const NATIVE_XSERVER: Self = Self(7);
     pub const PSEUDO_XSERVER: Self = Self(8);
     pub const WINDOWS_RT: Self = Self(9);
+
+    fn name(self) -> Option<&'static str> {
+        match self {
+            Self::UNSPECIFIED => Some("UNSPECIFIED"),
+            Self::WINDOWS_31X => Some("WINDOWS_31X"),
+            Self::WINDOWS_95 => Some("WINDOWS_95"),
+            Self::WINDOWS_NT => Some("WINDOWS_NT"),
+            Self::OS2V21 => Some("OS2_V21"),
+            Self::POWER_PC => Some("POWER_PC"),
+            Self::MACINTOSH => Some("MACINTOSH"),
+            Self::NATIVE_XSERVER => Some("NATIVE_XSERVER"),
+            Self::PSEUDO_XSERVER => Some("PSEUDO_XSERVER"),
+            Self::WINDOWS_RT => Some("WINDOWS_RT"),
+            _ => None,
+        }
+    }
 }
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct GeneralExtraFlags: u16 {
         const FASTPATH_OUTPUT_SUPPORTED = 0x0001;
         const NO_BITMAP_COMPRESSION_HDR = 0x0400;
@@ -93,6 +164,7 @@ bitflags! {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct General {
     pub major_platform_type: MajorPlatformType,
     pub minor_platform_type: MinorPlatformType,
@@ -13,6 +13,7 @@ const GLYPH_CACHE_LENGTH: usize = 48;
 const CACHE_DEFINITION_LENGTH: usize = 4;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum GlyphSupportLevel {
     None = 0,
     Partial = 1,
@@ -21,6 +22,7 @@ pub enum GlyphSupportLevel {
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CacheDefinition {
     pub entries: u16,
     pub max_cell_size: u16,
@@ -63,6 +65,7 @@ impl<'de> Decode<'de> for CacheDefinition {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlyphCache {
     pub glyph_cache: [CacheDefinition; GLYPH_CACHE_NUM],
     pub frag_cache: CacheDefinition,
@@ -12,6 +12,7 @@ const INPUT_LENGTH: usize = 84;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct InputFlags: u16 {
         const SCANCODES = 0x0001;
         const MOUSEX = 0x0004;
@@ -25,7 +26,15 @@ bitflags! {
     }
 }
 
+impl InputFlags {
+    /// Returns true if the fast-path input events are supported, in either of their two flavors.
+    pub fn supports_fastpath_input(self) -> bool {
+        self.intersects(Self::FASTPATH_INPUT | Self::FASTPATH_INPUT_2)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Input {
     pub input_flags: InputFlags,
     pub keyboard_layout: u32,
@@ -10,6 +10,7 @@ const SUPPORT_ARRAY_LEN: usize = 32;
 const DESKTOP_SAVE_Y_GRAN_VAL: u16 = 20;
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum OrderSupportIndex {
     DstBlt = 0x00,
     PatBlt = 0x01,
@@ -36,6 +37,7 @@ pub enum OrderSupportIndex {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct OrderFlags: u16 {
         const NEGOTIATE_ORDER_SUPPORT = 0x0002;
         const ZERO_BOUNDS_DELTAS_SUPPORT = 0x0008;
@@ -47,6 +49,7 @@ bitflags! {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct OrderSupportExFlags: u16 {
         const CACHE_BITMAP_REV3_SUPPORT = 2;
         const ALTSEC_FRAME_MARKER_SUPPORT = 4;
@@ -54,6 +57,7 @@ bitflags! {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Order {
     pub order_flags: OrderFlags,
     order_support: [u8; SUPPORT_ARRAY_LEN],
@@ -6,6 +6,7 @@ use ironrdp_core::{ensure_fixed_part_size, Decode, DecodeResult, Encode, EncodeR
 const POINTER_LENGTH: usize = 6;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Pointer {
     pub color_pointer_cache_size: u16,
     pub pointer_cache_size: u16,
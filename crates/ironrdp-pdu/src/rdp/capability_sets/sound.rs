@@ -8,12 +8,14 @@ const SOUND_LENGTH: usize = 4;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct SoundFlags: u16 {
         const BEEPS = 1;
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Sound {
     pub flags: SoundFlags,
 }
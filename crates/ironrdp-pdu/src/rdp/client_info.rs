@@ -10,6 +10,7 @@ use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive as _, ToPrimitive as _};
 use thiserror::Error;
 
+use crate::gcc::ConnectionType;
 use crate::utils::CharacterSet;
 use crate::{utils, PduError};
 
@@ -31,6 +32,9 @@ const CLIENT_DIR_LENGTH_SIZE: usize = 2;
 const SESSION_ID_SIZE: usize = 4;
 const PERFORMANCE_FLAGS_SIZE: usize = 4;
 const RECONNECT_COOKIE_LENGTH_SIZE: usize = 2;
+const RESERVED_SIZE: usize = 2 * 2;
+const DYNAMIC_DST_TIME_ZONE_KEY_NAME_LENGTH_SIZE: usize = 2;
+const DYNAMIC_DAYLIGHT_TIME_DISABLED_SIZE: usize = 2;
 const BIAS_SIZE: usize = 4;
 
 /// [2.2.1.11.1.1] Info Packet (TS_INFO_PACKET)
@@ -264,7 +268,7 @@ pub struct ExtendedClientOptionalInfo {
     session_id: Option<u32>,
     performance_flags: Option<PerformanceFlags>,
     reconnect_cookie: Option<[u8; RECONNECT_COOKIE_LEN]>,
-    // other fields are read by RdpVersion::Ten+
+    dynamic_time_zone: Option<DynamicTimeZone>,
 }
 
 impl ExtendedClientOptionalInfo {
@@ -294,6 +298,16 @@ impl ExtendedClientOptionalInfo {
     pub fn reconnect_cookie(&self) -> Option<&[u8; RECONNECT_COOKIE_LEN]> {
         self.reconnect_cookie.as_ref()
     }
+
+    pub fn dynamic_time_zone(&self) -> Option<&DynamicTimeZone> {
+        self.dynamic_time_zone.as_ref()
+    }
+
+    /// Returns whether the auto-reconnect cookie length field is present, either because there is a cookie or
+    /// because a later field follows it.
+    fn has_reconnect_cookie_field(&self) -> bool {
+        self.reconnect_cookie.is_some() || self.dynamic_time_zone.is_some()
+    }
 }
 
 impl Encode for ExtendedClientOptionalInfo {
@@ -309,9 +323,20 @@ impl Encode for ExtendedClientOptionalInfo {
         if let Some(performance_flags) = self.performance_flags {
             dst.write_u32(performance_flags.bits());
         }
-        if let Some(reconnect_cookie) = self.reconnect_cookie {
-            dst.write_u16(RECONNECT_COOKIE_LEN as u16);
-            dst.write_array(reconnect_cookie);
+        if self.has_reconnect_cookie_field() {
+            match self.reconnect_cookie {
+                Some(reconnect_cookie) => {
+                    dst.write_u16(RECONNECT_COOKIE_LEN as u16);
+                    dst.write_array(reconnect_cookie);
+                }
+                // An empty cookie keeps the following fields at their expected offset.
+                None => dst.write_u16(0),
+            }
+        }
+        if let Some(ref dynamic_time_zone) = self.dynamic_time_zone {
+            dst.write_u16(0); // reserved1
+            dst.write_u16(0); // reserved2
+            dynamic_time_zone.encode(dst)?;
         }
 
         Ok(())
@@ -333,8 +358,14 @@ impl Encode for ExtendedClientOptionalInfo {
         if self.performance_flags.is_some() {
             size += PERFORMANCE_FLAGS_SIZE;
         }
+        if self.has_reconnect_cookie_field() {
+            size += RECONNECT_COOKIE_LENGTH_SIZE;
+        }
         if self.reconnect_cookie.is_some() {
-            size += RECONNECT_COOKIE_LENGTH_SIZE + RECONNECT_COOKIE_LEN;
+            size += RECONNECT_COOKIE_LEN;
+        }
+        if let Some(ref dynamic_time_zone) = self.dynamic_time_zone {
+            size += RESERVED_SIZE + dynamic_time_zone.size();
         }
 
         size
@@ -377,16 +408,94 @@ impl<'de> Decode<'de> for ExtendedClientOptionalInfo {
             optional_data.reconnect_cookie = Some(src.read_array());
         }
 
-        if src.len() < 2 * 2 {
+        if src.len() < RESERVED_SIZE {
             return Ok(optional_data);
         }
         src.read_u16(); // reserved1
         src.read_u16(); // reserved2
 
+        if src.len() < DYNAMIC_DST_TIME_ZONE_KEY_NAME_LENGTH_SIZE {
+            return Ok(optional_data);
+        }
+        optional_data.dynamic_time_zone = Some(DynamicTimeZone::decode(src)?);
+
         Ok(optional_data)
     }
 }
 
+/// Dynamic daylight saving time fields of the extended info packet
+/// (cbDynamicDSTTimeZoneKeyName, dynamicDSTTimeZoneKeyName and dynamicDaylightTimeDisabled).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicTimeZone {
+    /// Time zone key name, such as "Pacific Standard Time", at most 127 UTF-16 code units long.
+    pub key_name: String,
+    pub dynamic_daylight_time_disabled: bool,
+}
+
+impl DynamicTimeZone {
+    const NAME: &'static str = "DynamicTimeZone";
+
+    const MAX_KEY_NAME_SIZE: usize = 254;
+}
+
+impl Encode for DynamicTimeZone {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let key_name = utils::to_utf16_bytes(&self.key_name);
+        if key_name.len() > Self::MAX_KEY_NAME_SIZE {
+            return Err(invalid_field_err!(
+                "dynamicDSTTimeZoneKeyName",
+                "time zone key name is too long"
+            ));
+        }
+
+        dst.write_u16(key_name.len() as u16);
+        dst.write_slice(&key_name);
+        dst.write_u16(u16::from(self.dynamic_daylight_time_disabled));
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        DYNAMIC_DST_TIME_ZONE_KEY_NAME_LENGTH_SIZE
+            + self.key_name.encode_utf16().count() * 2
+            + DYNAMIC_DAYLIGHT_TIME_DISABLED_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for DynamicTimeZone {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: DYNAMIC_DST_TIME_ZONE_KEY_NAME_LENGTH_SIZE);
+        let key_name_size = usize::from(src.read_u16());
+        if key_name_size > Self::MAX_KEY_NAME_SIZE || key_name_size % 2 != 0 {
+            return Err(invalid_field_err!(
+                "cbDynamicDSTTimeZoneKeyName",
+                "invalid time zone key name size"
+            ));
+        }
+
+        ensure_size!(in: src, size: key_name_size);
+        let key_name = utils::decode_string(src.read_slice(key_name_size), CharacterSet::Unicode, false)?;
+
+        // dynamicDaylightTimeDisabled is optional as well.
+        let dynamic_daylight_time_disabled = if src.len() >= DYNAMIC_DAYLIGHT_TIME_DISABLED_SIZE {
+            src.read_u16() != 0
+        } else {
+            false
+        };
+
+        Ok(Self {
+            key_name,
+            dynamic_daylight_time_disabled,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TimezoneInfo {
     pub bias: u32,
@@ -408,6 +517,52 @@ impl TimezoneInfo {
         + TIMEZONE_INFO_NAME_LEN
         + SystemTime::FIXED_PART_SIZE
         + BIAS_SIZE;
+
+    /// Offset of the standard time from UTC, in minutes, e.g.: -480 for the Pacific Standard Time.
+    ///
+    /// The biases are the number of minutes to add to the local time to get the UTC time, hence the opposite sign.
+    pub fn standard_offset_minutes(&self) -> i32 {
+        signed_bias(self.bias)
+            .wrapping_add(signed_bias(self.standard_bias))
+            .wrapping_neg()
+    }
+
+    /// Offset of the daylight saving time from UTC, in minutes, e.g.: -420 for the Pacific Daylight Time.
+    pub fn daylight_offset_minutes(&self) -> i32 {
+        signed_bias(self.bias)
+            .wrapping_add(signed_bias(self.daylight_bias))
+            .wrapping_neg()
+    }
+
+    /// Returns whether the time zone observes a daylight saving time, both transition dates being set.
+    pub fn observes_daylight_time(&self) -> bool {
+        self.standard_date.0.is_some() && self.daylight_date.0.is_some()
+    }
+
+    /// Returns whether the daylight saving time is in effect at the given local time of `year`.
+    ///
+    /// In the southern hemisphere, the daylight saving time starts after the return to the standard time in the
+    /// year, and lasts until the next year.
+    pub fn is_daylight_time(&self, year: u16, month: Month, day: u8, hour: u16, minute: u16) -> bool {
+        let (Some(standard_date), Some(daylight_date)) = (&self.standard_date.0, &self.daylight_date.0) else {
+            return false;
+        };
+
+        let time = (month as u16, u16::from(day), hour, minute);
+        let daylight_start = daylight_date.transition_time(year);
+        let standard_start = standard_date.transition_time(year);
+
+        if daylight_start < standard_start {
+            daylight_start <= time && time < standard_start
+        } else {
+            time < standard_start || daylight_start <= time
+        }
+    }
+}
+
+/// The biases are signed, e.g.: 0xFFFF_FFC4 for -60.
+fn signed_bias(bias: u32) -> i32 {
+    i32::from_le_bytes(bias.to_le_bytes())
 }
 
 impl Encode for TimezoneInfo {
@@ -482,6 +637,58 @@ impl SystemTime {
     const NAME: &'static str = "SystemTime";
 
     const FIXED_PART_SIZE: usize = 2 /* Year */ + 2 /* Month */ + 2 /* DoW */ + 2 /* Day */ + 2 /* Hour */ + 2 /* Minute */ + 2 /* Second */ + 2 /* Ms */;
+
+    /// Resolves the day of the month on which the transition occurs in `year`.
+    ///
+    /// The transition dates hold the occurrence of a day of the week in the month rather than a day of the month,
+    /// e.g.: the last Sunday of March, the fifth occurrence standing for the last one.
+    pub fn day_of_month(&self, year: u16) -> u8 {
+        let first_day_of_week = day_of_week(year, self.month, 1);
+        let first_occurrence = 1 + (self.day_of_week as u8 + 7 - first_day_of_week) % 7;
+        let day = first_occurrence + 7 * (self.day as u8 - 1);
+
+        if day > days_in_month(year, self.month) {
+            day - 7
+        } else {
+            day
+        }
+    }
+
+    /// Local time of the transition in `year`, ordered chronologically.
+    fn transition_time(&self, year: u16) -> (u16, u16, u16, u16) {
+        (
+            self.month as u16,
+            u16::from(self.day_of_month(year)),
+            self.hour,
+            self.minute,
+        )
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: Month) -> u8 {
+    match month {
+        Month::February if is_leap_year(year) => 29,
+        Month::February => 28,
+        Month::April | Month::June | Month::September | Month::November => 30,
+        _ => 31,
+    }
+}
+
+/// Day of the week of a date of the Gregorian calendar, zero being Sunday.
+fn day_of_week(year: u16, month: Month, day: u8) -> u8 {
+    const MONTH_OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+
+    let month = month as usize;
+    let year = u32::from(year);
+    // January and February are counted as the last months of the previous year, after its leap day.
+    let year = if month < 3 { year.saturating_sub(1) } else { year };
+    let days = year + year / 4 - year / 100 + year / 400 + MONTH_OFFSETS[month - 1] + u32::from(day);
+
+    (days % 7) as u8
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -589,6 +796,7 @@ pub enum DayOfWeekOccurrence {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct PerformanceFlags: u32 {
         const DISABLE_WALLPAPER = 0x0000_0001;
         const DISABLE_FULLWINDOWDRAG = 0x0000_0002;
@@ -609,6 +817,29 @@ impl Default for PerformanceFlags {
     }
 }
 
+impl PerformanceFlags {
+    /// Returns the flags recommended for `connection_type`, trading visual effects for bandwidth the same way as
+    /// the Microsoft clients do.
+    ///
+    /// The flags of [`ConnectionType::Lan`] are returned for [`ConnectionType::NotUsed`] and
+    /// [`ConnectionType::Autodetect`], as the effects are then adjusted by the server.
+    pub fn auto_for_connection_type(connection_type: ConnectionType) -> Self {
+        let low_bandwidth = Self::DISABLE_WALLPAPER | Self::DISABLE_FULLWINDOWDRAG | Self::DISABLE_MENUANIMATIONS;
+
+        match connection_type {
+            ConnectionType::Modem => low_bandwidth | Self::DISABLE_THEMING,
+            ConnectionType::BroadbandLow => low_bandwidth,
+            ConnectionType::Satellite | ConnectionType::BroadbandHigh => {
+                low_bandwidth | Self::ENABLE_DESKTOP_COMPOSITION
+            }
+            ConnectionType::Wan => low_bandwidth | Self::ENABLE_FONT_SMOOTHING | Self::ENABLE_DESKTOP_COMPOSITION,
+            ConnectionType::Lan | ConnectionType::NotUsed | ConnectionType::Autodetect => {
+                Self::ENABLE_FONT_SMOOTHING | Self::ENABLE_DESKTOP_COMPOSITION
+            }
+        }
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum AddressFamily {
@@ -713,6 +944,7 @@ pub mod builder {
     pub struct ExtendedClientOptionalInfoBuilderStateSetSessionId;
     pub struct ExtendedClientOptionalInfoBuilderStateSetPerformanceFlags;
     pub struct ExtendedClientOptionalInfoBuilderStateSetReconnectCookie;
+    pub struct ExtendedClientOptionalInfoBuilderStateSetDynamicTimeZone;
     pub struct ExtendedClientOptionalInfoBuilderStateFinal;
 
     // State machine-based builder for [`ExtendedClientOptionalInfo`].
@@ -774,12 +1006,37 @@ pub mod builder {
         pub fn reconnect_cookie(
             mut self,
             reconnect_cookie: [u8; RECONNECT_COOKIE_LEN],
-        ) -> ExtendedClientOptionalInfoBuilder<ExtendedClientOptionalInfoBuilderStateFinal> {
+        ) -> ExtendedClientOptionalInfoBuilder<ExtendedClientOptionalInfoBuilderStateSetDynamicTimeZone> {
             self.inner.reconnect_cookie = Some(reconnect_cookie);
             ExtendedClientOptionalInfoBuilder {
                 inner: self.inner,
                 _phantom_data: Default::default(),
             }
         }
+
+        /// Sets the dynamic time zone without an auto-reconnect cookie, which is then encoded as empty.
+        pub fn dynamic_time_zone(
+            self,
+            dynamic_time_zone: DynamicTimeZone,
+        ) -> ExtendedClientOptionalInfoBuilder<ExtendedClientOptionalInfoBuilderStateFinal> {
+            ExtendedClientOptionalInfoBuilder::<ExtendedClientOptionalInfoBuilderStateSetDynamicTimeZone> {
+                inner: self.inner,
+                _phantom_data: Default::default(),
+            }
+            .dynamic_time_zone(dynamic_time_zone)
+        }
+    }
+
+    impl ExtendedClientOptionalInfoBuilder<ExtendedClientOptionalInfoBuilderStateSetDynamicTimeZone> {
+        pub fn dynamic_time_zone(
+            mut self,
+            dynamic_time_zone: DynamicTimeZone,
+        ) -> ExtendedClientOptionalInfoBuilder<ExtendedClientOptionalInfoBuilderStateFinal> {
+            self.inner.dynamic_time_zone = Some(dynamic_time_zone);
+            ExtendedClientOptionalInfoBuilder {
+                inner: self.inner,
+                _phantom_data: Default::default(),
+            }
+        }
     }
 }
@@ -11,10 +11,13 @@ use crate::input::InputEventPdu;
 use crate::rdp::capability_sets::{ClientConfirmActive, ServerDemandActive};
 use crate::rdp::client_info;
 use crate::rdp::finalization_messages::{ControlPdu, FontPdu, MonitorLayoutPdu, SynchronizePdu};
+use crate::rdp::play_sound::PlaySoundPdu;
 use crate::rdp::refresh_rectangle::RefreshRectanglePdu;
 use crate::rdp::server_error_info::ServerSetErrorInfoPdu;
+use crate::rdp::server_redirection::ServerRedirectionPdu;
 use crate::rdp::session_info::SaveSessionInfoPdu;
 use crate::rdp::suppress_output::SuppressOutputPdu;
+use crate::{DecodeOptions, DecodeWarning};
 
 pub const BASIC_SECURITY_HEADER_SIZE: usize = 4;
 pub const SHARE_DATA_HEADER_COMPRESSION_MASK: u8 = 0xF;
@@ -83,6 +86,11 @@ impl ShareControlHeader {
     const NAME: &'static str = "ShareControlHeader";
 
     const FIXED_PART_SIZE: usize = SHARE_CONTROL_HEADER_SIZE;
+
+    /// The Server Redirection PDU has two bytes of padding instead of the share ID.
+    fn is_server_redirect(&self) -> bool {
+        matches!(self.share_control_pdu, ShareControlPdu::ServerRedirect(_))
+    }
 }
 
 impl Encode for ShareControlHeader {
@@ -91,13 +99,14 @@ impl Encode for ShareControlHeader {
 
         let pdu_type_with_version = PROTOCOL_VERSION | self.share_control_pdu.share_header_type().to_u16().unwrap();
 
-        dst.write_u16(cast_length!(
-            "len",
-            self.share_control_pdu.size() + SHARE_CONTROL_HEADER_SIZE
-        )?);
+        dst.write_u16(cast_length!("len", self.size())?);
         dst.write_u16(pdu_type_with_version);
         dst.write_u16(self.pdu_source);
-        dst.write_u32(self.share_id);
+        if self.is_server_redirect() {
+            write_padding!(dst, 2);
+        } else {
+            dst.write_u32(self.share_id);
+        }
 
         self.share_control_pdu.encode(dst)
     }
@@ -107,18 +116,30 @@ impl Encode for ShareControlHeader {
     }
 
     fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE + self.share_control_pdu.size()
+        let share_id_size = if self.is_server_redirect() { 2 } else { 4 };
+
+        Self::FIXED_PART_SIZE - 4 + share_id_size + self.share_control_pdu.size()
     }
 }
 
 impl<'de> Decode<'de> for ShareControlHeader {
     fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        Self::decode_with_options(src, DecodeOptions::STRICT, &mut Vec::new())
+    }
+}
+
+impl ShareControlHeader {
+    /// Decodes the Share Control Header and its PDU, see [`ServerDemandActive::decode_with_options`].
+    pub fn decode_with_options(
+        src: &mut ReadCursor<'_>,
+        options: DecodeOptions,
+        warnings: &mut Vec<DecodeWarning>,
+    ) -> DecodeResult<Self> {
         ensure_fixed_part_size!(in: src);
 
         let total_length = src.read_u16() as usize;
         let pdu_type_with_version = src.read_u16();
         let pdu_source = src.read_u16();
-        let share_id = src.read_u32();
 
         let pdu_type = ShareControlPduType::from_u16(pdu_type_with_version & SHARE_CONTROL_HEADER_MASK)
             .ok_or_else(|| invalid_field_err!("pdu_type", "invalid pdu type"))?;
@@ -127,16 +148,31 @@ impl<'de> Decode<'de> for ShareControlHeader {
             return Err(invalid_field_err!("pdu_version", "invalid PDU version"));
         }
 
-        let share_pdu = ShareControlPdu::from_type(src, pdu_type)?;
+        let share_id = if pdu_type == ShareControlPduType::ServerRedirect {
+            read_padding!(src, 2);
+            0
+        } else {
+            src.read_u32()
+        };
+
+        let share_pdu = if pdu_type == ShareControlPduType::DemandActivePdu {
+            ShareControlPdu::ServerDemandActive(ServerDemandActive::decode_with_options(src, options, warnings)?)
+        } else {
+            ShareControlPdu::from_type(src, pdu_type)?
+        };
         let header = Self {
             share_control_pdu: share_pdu,
             pdu_source,
             share_id,
         };
 
-        if pdu_type == ShareControlPduType::DataPdu {
+        if matches!(
+            pdu_type,
+            ShareControlPduType::DataPdu | ShareControlPduType::ServerRedirect
+        ) {
             // Some windows version have an issue where
             // there is some padding not part of the inner unit.
+            // The Server Redirection PDU may also end with one byte of padding.
             // Consume that data
             let header_length = header.size();
 
@@ -161,6 +197,7 @@ pub enum ShareControlPdu {
     ClientConfirmActive(ClientConfirmActive),
     Data(ShareDataHeader),
     ServerDeactivateAll(ServerDeactivateAll),
+    ServerRedirect(ServerRedirectionPdu),
 }
 
 impl ShareControlPdu {
@@ -172,6 +209,7 @@ impl ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(_) => "Client Confirm Active PDU",
             ShareControlPdu::Data(_) => "Data PDU",
             ShareControlPdu::ServerDeactivateAll(_) => "Server Deactivate All PDU",
+            ShareControlPdu::ServerRedirect(_) => "Server Redirection PDU",
         }
     }
 
@@ -181,6 +219,7 @@ impl ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(_) => ShareControlPduType::ConfirmActivePdu,
             ShareControlPdu::Data(_) => ShareControlPduType::DataPdu,
             ShareControlPdu::ServerDeactivateAll(_) => ShareControlPduType::DeactivateAllPdu,
+            ShareControlPdu::ServerRedirect(_) => ShareControlPduType::ServerRedirect,
         }
     }
 
@@ -196,7 +235,9 @@ impl ShareControlPdu {
             ShareControlPduType::DeactivateAllPdu => {
                 Ok(ShareControlPdu::ServerDeactivateAll(ServerDeactivateAll::decode(src)?))
             }
-            _ => Err(invalid_field_err!("share_type", "unexpected share control PDU type")),
+            ShareControlPduType::ServerRedirect => {
+                Ok(ShareControlPdu::ServerRedirect(ServerRedirectionPdu::decode(src)?))
+            }
         }
     }
 }
@@ -208,6 +249,7 @@ impl Encode for ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.encode(dst),
             ShareControlPdu::Data(share_data_header) => share_data_header.encode(dst),
             ShareControlPdu::ServerDeactivateAll(deactivate_all) => deactivate_all.encode(dst),
+            ShareControlPdu::ServerRedirect(pdu) => pdu.encode(dst),
         }
     }
 
@@ -221,6 +263,7 @@ impl Encode for ShareControlPdu {
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.size(),
             ShareControlPdu::Data(share_data_header) => share_data_header.size(),
             ShareControlPdu::ServerDeactivateAll(deactivate_all) => deactivate_all.size(),
+            ShareControlPdu::ServerRedirect(pdu) => pdu.size(),
         }
     }
 }
@@ -326,7 +369,7 @@ pub enum ShareDataPdu {
     RefreshRectangle(RefreshRectanglePdu),
     Update(Vec<u8>),
     Pointer(Vec<u8>),
-    PlaySound(Vec<u8>),
+    PlaySound(PlaySoundPdu),
     SetKeyboardIndicators(Vec<u8>),
     BitmapCachePersistentList(Vec<u8>),
     BitmapCacheErrorPdu(Vec<u8>),
@@ -422,7 +465,7 @@ impl ShareDataPdu {
             ShareDataPduType::RefreshRectangle => Ok(ShareDataPdu::RefreshRectangle(RefreshRectanglePdu::decode(src)?)),
             ShareDataPduType::Update => Ok(ShareDataPdu::Update(src.remaining().to_vec())),
             ShareDataPduType::Pointer => Ok(ShareDataPdu::Pointer(src.remaining().to_vec())),
-            ShareDataPduType::PlaySound => Ok(ShareDataPdu::PlaySound(src.remaining().to_vec())),
+            ShareDataPduType::PlaySound => Ok(ShareDataPdu::PlaySound(PlaySoundPdu::decode(src)?)),
             ShareDataPduType::SetKeyboardIndicators => {
                 Ok(ShareDataPdu::SetKeyboardIndicators(src.remaining().to_vec()))
             }
@@ -456,6 +499,7 @@ impl Encode for ShareDataPdu {
             ShareDataPdu::ShutdownRequest | ShareDataPdu::ShutdownDenied => Ok(()),
             ShareDataPdu::SuppressOutput(pdu) => pdu.encode(dst),
             ShareDataPdu::RefreshRectangle(pdu) => pdu.encode(dst),
+            ShareDataPdu::PlaySound(pdu) => pdu.encode(dst),
             _ => Err(other_err!("Encoding not implemented")),
         }
     }
@@ -477,9 +521,9 @@ impl Encode for ShareDataPdu {
             ShareDataPdu::ShutdownRequest | ShareDataPdu::ShutdownDenied => 0,
             ShareDataPdu::SuppressOutput(pdu) => pdu.size(),
             ShareDataPdu::RefreshRectangle(pdu) => pdu.size(),
+            ShareDataPdu::PlaySound(pdu) => pdu.size(),
             ShareDataPdu::Update(buffer)
             | ShareDataPdu::Pointer(buffer)
-            | ShareDataPdu::PlaySound(buffer)
             | ShareDataPdu::SetKeyboardIndicators(buffer)
             | ShareDataPdu::BitmapCachePersistentList(buffer)
             | ShareDataPdu::BitmapCacheErrorPdu(buffer)
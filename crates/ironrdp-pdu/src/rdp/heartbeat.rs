@@ -0,0 +1,90 @@
+use core::time::Duration;
+
+use ironrdp_core::{
+    ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
+
+/// [MS-RDPBCGR] 2.2.16.1 Server Heartbeat PDU (SERVER_HEARTBEAT_PDU)
+///
+/// Sent by the server on the I/O channel, at the given period, so that the client detects a broken connection
+/// faster than the TCP timeouts. It is only sent to clients advertising `RNS_UD_CS_SUPPORT_HEARTBEAT_PDU` in their
+/// early capability flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPdu {
+    /// Time between two heartbeats, in seconds. Zero disables the detection of missed heartbeats.
+    pub period: u8,
+    /// Number of missed heartbeats after which the client should warn the user (`count1`).
+    pub warning_count: u8,
+    /// Number of missed heartbeats after which the client should reconnect (`count2`).
+    pub reconnect_count: u8,
+}
+
+impl HeartbeatPdu {
+    const NAME: &'static str = "HeartbeatPdu";
+
+    pub const FIXED_PART_SIZE: usize =
+        BasicSecurityHeader::FIXED_PART_SIZE + 1 /* reserved */ + 1 /* period */ + 1 /* count1 */ + 1 /* count2 */;
+
+    /// Returns `true` if `user_data`, received on the I/O channel, is a Heartbeat PDU.
+    ///
+    /// The Heartbeat PDU is the only one starting with a Basic Security Header on this channel once the connection
+    /// is established, the others starting with the length of their Share Control Header.
+    pub fn is_heartbeat(user_data: &[u8]) -> bool {
+        user_data.len() == Self::FIXED_PART_SIZE
+            && BasicSecurityHeaderFlags::from_bits_truncate(u16::from_le_bytes([user_data[0], user_data[1]]))
+                .contains(BasicSecurityHeaderFlags::HEARTBEAT)
+    }
+
+    pub fn period_duration(&self) -> Duration {
+        Duration::from_secs(u64::from(self.period))
+    }
+}
+
+impl Encode for HeartbeatPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::HEARTBEAT,
+        }
+        .encode(dst)?;
+        write_padding!(dst, 1);
+        dst.write_u8(self.period);
+        dst.write_u8(self.warning_count);
+        dst.write_u8(self.reconnect_count);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for HeartbeatPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::HEARTBEAT) {
+            return Err(invalid_field_err!("securityHeader", "missing SEC_HEARTBEAT flag"));
+        }
+
+        read_padding!(src, 1);
+        let period = src.read_u8();
+        let warning_count = src.read_u8();
+        let reconnect_count = src.read_u8();
+
+        Ok(Self {
+            period,
+            warning_count,
+            reconnect_count,
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use bitflags::bitflags;
+use ironrdp_core::{
+    ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
+
+/// [MS-RDPBCGR] 2.2.15.1 Initiate Multitransport Request PDU (SERVER_INITIATE_MULTITRANSPORT_REQUEST_PDU)
+///
+/// Sent by the server on the I/O channel to bootstrap a UDP side channel. It is only sent to clients advertising
+/// the Client Multitransport Channel Data GCC block, and the client either connects the side channel, or answers
+/// with a [`MultitransportResponsePdu`] declining it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultitransportRequestPdu {
+    /// Identifies the request, echoed in the response and in the tunnel creation request of the side channel.
+    pub request_id: u32,
+    pub requested_protocol: RequestedProtocol,
+    /// Cookie sent back by the client over the side channel, so that the server can match both transports.
+    pub security_cookie: [u8; 16],
+}
+
+impl MultitransportRequestPdu {
+    const NAME: &'static str = "MultitransportRequestPdu";
+
+    pub const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE
+        + 4 /* requestId */
+        + 2 /* requestedProtocol */
+        + 2 /* reserved */
+        + 16 /* securityCookie */;
+
+    /// Returns `true` if `user_data`, received on the I/O channel, is an Initiate Multitransport Request PDU.
+    ///
+    /// Like the Heartbeat PDU, this PDU starts with a Basic Security Header, and not with the length of a Share
+    /// Control Header.
+    pub fn is_multitransport_request(user_data: &[u8]) -> bool {
+        user_data.len() == Self::FIXED_PART_SIZE
+            && BasicSecurityHeaderFlags::from_bits_truncate(u16::from_le_bytes([user_data[0], user_data[1]]))
+                .contains(BasicSecurityHeaderFlags::TRANSPORT_REQ)
+    }
+}
+
+impl Encode for MultitransportRequestPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::TRANSPORT_REQ,
+        }
+        .encode(dst)?;
+        dst.write_u32(self.request_id);
+        dst.write_u16(self.requested_protocol.bits());
+        write_padding!(dst, 2);
+        dst.write_slice(&self.security_cookie);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MultitransportRequestPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::TRANSPORT_REQ) {
+            return Err(invalid_field_err!("securityHeader", "missing SEC_TRANSPORT_REQ flag"));
+        }
+
+        let request_id = src.read_u32();
+        let requested_protocol = RequestedProtocol::from_bits_retain(src.read_u16());
+        read_padding!(src, 2);
+        let security_cookie = src.read_array();
+
+        Ok(Self {
+            request_id,
+            requested_protocol,
+            security_cookie,
+        })
+    }
+}
+
+bitflags! {
+    /// Transport protocol of the side channel requested by the server.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct RequestedProtocol: u16 {
+        /// RDP-UDP with forward error correction, reliable (`INITITATE_REQUEST_PROTOCOL_UDPFECR`).
+        const UDP_FECR = 0x0001;
+        /// RDP-UDP with forward error correction, lossy (`INITITATE_REQUEST_PROTOCOL_UDPFECL`).
+        const UDP_FECL = 0x0002;
+        // The source may set any bits
+        const _ = !0;
+    }
+}
+
+/// [MS-RDPBCGR] 2.2.15.2 Initiate Multitransport Response PDU (CLIENT_INITIATE_MULTITRANSPORT_RESPONSE_PDU)
+///
+/// Sent by the client on the I/O channel to decline the side channel requested by a [`MultitransportRequestPdu`],
+/// so that the server stops waiting for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultitransportResponsePdu {
+    /// The `request_id` of the answered request.
+    pub request_id: u32,
+    /// HRESULT status of the side channel creation.
+    pub hr_response: u32,
+}
+
+impl MultitransportResponsePdu {
+    const NAME: &'static str = "MultitransportResponsePdu";
+
+    pub const FIXED_PART_SIZE: usize = BasicSecurityHeader::FIXED_PART_SIZE + 4 /* requestId */ + 4 /* hrResponse */;
+
+    /// The side channel was created.
+    pub const S_OK: u32 = 0x0000_0000;
+    /// The client is not able or not willing to create the side channel.
+    pub const E_ABORT: u32 = 0x8000_4004;
+
+    /// Declines the side channel requested with `request_id`.
+    pub fn abort(request_id: u32) -> Self {
+        Self {
+            request_id,
+            hr_response: Self::E_ABORT,
+        }
+    }
+}
+
+impl Encode for MultitransportResponsePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::TRANSPORT_RSP,
+        }
+        .encode(dst)?;
+        dst.write_u32(self.request_id);
+        dst.write_u32(self.hr_response);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MultitransportResponsePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_header = BasicSecurityHeader::decode(src)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::TRANSPORT_RSP) {
+            return Err(invalid_field_err!("securityHeader", "missing SEC_TRANSPORT_RSP flag"));
+        }
+
+        let request_id = src.read_u32();
+        let hr_response = src.read_u32();
+
+        Ok(Self {
+            request_id,
+            hr_response,
+        })
+    }
+}
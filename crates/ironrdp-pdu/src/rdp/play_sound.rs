@@ -0,0 +1,54 @@
+use ironrdp_core::{ensure_fixed_part_size, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
+
+/// [2.2.9.1.1.5.1] Play Sound PDU Data (TS_PLAY_SOUND_PDU_DATA)
+///
+/// The Play Sound PDU is sent by the server to instruct the client to play a
+/// "beep" sound, e.g.: when a console application writes the bell character.
+///
+/// [2.2.9.1.1.5.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/4b7ba3f8-b0c6-4dde-9f5d-f6a9f33ff3d1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaySoundPdu {
+    /// Duration of the beep, in milliseconds.
+    pub duration_ms: u32,
+    /// Frequency of the beep, in hertz.
+    pub frequency_hz: u32,
+}
+
+impl PlaySoundPdu {
+    const NAME: &'static str = "PlaySoundPdu";
+
+    const FIXED_PART_SIZE: usize = 4 /* duration */ + 4 /* frequency */;
+}
+
+impl Encode for PlaySoundPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.duration_ms);
+        dst.write_u32(self.frequency_hz);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for PlaySoundPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let duration_ms = src.read_u32();
+        let frequency_hz = src.read_u32();
+
+        Ok(Self {
+            duration_ms,
+            frequency_hz,
+        })
+    }
+}
@@ -2,8 +2,8 @@ use std::io;
 
 use bitflags::bitflags;
 use ironrdp_core::{
-    cast_length, ensure_fixed_part_size, invalid_field_err, unsupported_value_err, Decode, DecodeResult, Encode,
-    EncodeResult, ReadCursor, WriteCursor,
+    cast_length, ensure_fixed_part_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor,
+    WriteCursor,
 };
 use md5::Digest;
 use num_derive::{FromPrimitive, ToPrimitive};
@@ -12,6 +12,7 @@ use thiserror::Error;
 
 use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
 pub use crate::rdp::server_license::client_license_info::ClientLicenseInfo;
+use crate::rdp::server_license::client_new_license_request::{compute_master_secret, compute_session_key_blob};
 use crate::PduError;
 
 #[cfg(test)]
@@ -57,6 +58,32 @@ pub struct LicenseEncryptionData {
     pub license_key: Vec<u8>,
 }
 
+impl LicenseEncryptionData {
+    /// Derives the licensing keys from the premaster secret and the random values of the client and the server.
+    ///
+    /// The keys are generated as described in
+    /// <https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpele/88061224-4a2f-4a28-a52e-e896b75ed2d3>.
+    pub fn new(premaster_secret: &[u8], client_random: &[u8], server_random: &[u8]) -> Self {
+        let master_secret = compute_master_secret(premaster_secret, client_random, server_random);
+        let session_key_blob = compute_session_key_blob(master_secret.as_slice(), client_random, server_random);
+        let mac_salt_key = &session_key_blob[..16];
+
+        let mut md5 = md5::Md5::new();
+        md5.update(
+            [&session_key_blob[16..32], client_random, server_random]
+                .concat()
+                .as_slice(),
+        );
+        let license_key = md5.finalize().to_vec();
+
+        Self {
+            premaster_secret: Vec::from(premaster_secret),
+            mac_salt_key: Vec::from(mac_salt_key),
+            license_key,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct LicenseHeader {
     pub security_header: BasicSecurityHeader,
@@ -361,10 +388,7 @@ impl<'de> Decode<'de> for LicensePdu {
             PreambleType::NewLicense | PreambleType::UpgradeLicense => {
                 Ok(ServerUpgradeLicense::decode(license_header, src)?.into())
             }
-            PreambleType::LicenseInfo => Err(unsupported_value_err!(
-                "LicensePdu::LicenseInfo",
-                "LicenseInfo is not supported".to_owned()
-            )),
+            PreambleType::LicenseInfo => Ok(ClientLicenseInfo::decode(license_header, src)?.into()),
             PreambleType::NewLicenseRequest => Ok(ClientNewLicenseRequest::decode(license_header, src)?.into()),
             PreambleType::PlatformChallengeResponse => {
                 Ok(ClientPlatformChallengeResponse::decode(license_header, src)?.into())
@@ -1,18 +1,16 @@
 use crate::crypto::rc4::Rc4;
 use crate::crypto::rsa::encrypt_with_public_key;
 use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags};
-use crate::rdp::server_license::client_new_license_request::{compute_master_secret, compute_session_key_blob};
 use crate::rdp::server_license::client_platform_challenge_response::CLIENT_HARDWARE_IDENTIFICATION_SIZE;
 use crate::rdp::server_license::{
-    compute_mac_data, BlobHeader, BlobType, LicenseEncryptionData, LicenseHeader, PreambleFlags, PreambleType,
-    PreambleVersion, ServerLicenseError, ServerLicenseRequest, KEY_EXCHANGE_ALGORITHM_RSA, MAC_SIZE, PLATFORM_ID,
-    PREAMBLE_SIZE, RANDOM_NUMBER_SIZE,
+    compute_mac_data, BlobHeader, BlobType, ClientHardwareIdentification, LicenseEncryptionData, LicenseHeader,
+    PreambleFlags, PreambleType, PreambleVersion, ServerLicenseError, ServerLicenseRequest, KEY_EXCHANGE_ALGORITHM_RSA,
+    MAC_SIZE, PLATFORM_ID, PREAMBLE_SIZE, RANDOM_NUMBER_SIZE,
 };
 use byteorder::{LittleEndian, WriteBytesExt};
 use ironrdp_core::{
     ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
-use md5::Digest;
 use std::io;
 
 const LICENSE_INFO_STATIC_FIELDS_SIZE: usize = 20;
@@ -45,29 +43,11 @@ impl ClientLicenseInfo {
                                           "attempted to retrieve the server public key from a server license request message that does not have a certificate"))?;
         let encrypted_premaster_secret = encrypt_with_public_key(premaster_secret, &public_key)?;
 
-        let master_secret = compute_master_secret(
+        let encryption_data = LicenseEncryptionData::new(
             premaster_secret,
             client_random,
             license_request.server_random.as_slice(),
         );
-        let session_key_blob = compute_session_key_blob(
-            master_secret.as_slice(),
-            client_random,
-            license_request.server_random.as_slice(),
-        );
-        let mac_salt_key = &session_key_blob[..16];
-
-        let mut md5 = md5::Md5::new();
-        md5.update(
-            [
-                &session_key_blob[16..32],
-                client_random,
-                license_request.server_random.as_slice(),
-            ]
-            .concat()
-            .as_slice(),
-        );
-        let license_key = md5.finalize().to_vec();
 
         let mut hardware_id = Vec::with_capacity(CLIENT_HARDWARE_IDENTIFICATION_SIZE);
         hardware_id.write_u32::<LittleEndian>(PLATFORM_ID)?;
@@ -75,10 +55,10 @@ impl ClientLicenseInfo {
             hardware_id.write_u32::<LittleEndian>(data)?;
         }
 
-        let mut rc4 = Rc4::new(&license_key);
+        let mut rc4 = Rc4::new(&encryption_data.license_key);
         let encrypted_hwid = rc4.process(&hardware_id);
 
-        let mac_data = compute_mac_data(mac_salt_key, &hardware_id);
+        let mac_data = compute_mac_data(&encryption_data.mac_salt_key, &hardware_id);
 
         let size = RANDOM_NUMBER_SIZE
             + PREAMBLE_SIZE
@@ -107,15 +87,35 @@ impl ClientLicenseInfo {
                 encrypted_hwid,
                 mac_data,
             },
-            LicenseEncryptionData {
-                premaster_secret: Vec::from(premaster_secret),
-                mac_salt_key: Vec::from(mac_salt_key),
-                license_key,
-            },
+            encryption_data,
         ))
     }
 }
 
+impl ClientLicenseInfo {
+    /// Checks the MAC computed by the client over its hardware identification.
+    pub fn verify_hardware_id(&self, encryption_data: &LicenseEncryptionData) -> Result<(), ServerLicenseError> {
+        let hardware_id = self.decrypted_hardware_id(encryption_data);
+        let mac_data = compute_mac_data(encryption_data.mac_salt_key.as_slice(), hardware_id.as_slice());
+
+        if mac_data != self.mac_data {
+            return Err(ServerLicenseError::InvalidMacData);
+        }
+
+        Ok(())
+    }
+
+    pub fn hardware_id(&self, encryption_data: &LicenseEncryptionData) -> DecodeResult<ClientHardwareIdentification> {
+        let data = self.decrypted_hardware_id(encryption_data);
+        ClientHardwareIdentification::decode(&mut ReadCursor::new(&data))
+    }
+
+    fn decrypted_hardware_id(&self, encryption_data: &LicenseEncryptionData) -> Vec<u8> {
+        let mut rc4 = Rc4::new(encryption_data.license_key.as_slice());
+        rc4.process(self.encrypted_hwid.as_slice())
+    }
+}
+
 impl ClientLicenseInfo {
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
@@ -161,6 +161,7 @@ impl ClientLicenseInfo {
             return Err(invalid_field_err!("preambleMessageType", "unexpected preamble type"));
         }
 
+        ensure_size!(in: src, size: LICENSE_INFO_STATIC_FIELDS_SIZE + RANDOM_NUMBER_SIZE);
         let key_exchange_algorithm = src.read_u32();
         if key_exchange_algorithm != KEY_EXCHANGE_ALGORITHM_RSA {
             return Err(invalid_field_err!("keyExchangeAlgo", "invalid key exchange algorithm"));
@@ -169,7 +170,6 @@ impl ClientLicenseInfo {
         // We can ignore platform ID
         let _platform_id = src.read_u32();
 
-        ensure_size!(in: src, size:  RANDOM_NUMBER_SIZE);
         let client_random = src.read_slice(RANDOM_NUMBER_SIZE).into();
 
         let premaster_secret_blob_header = BlobHeader::decode(src)?;
@@ -187,7 +187,7 @@ impl ClientLicenseInfo {
         let license_info = src.read_slice(license_info_blob_header.length).into();
 
         let encrypted_hwid_blob_header = BlobHeader::decode(src)?;
-        if encrypted_hwid_blob_header.blob_type != BlobType::DATA {
+        if encrypted_hwid_blob_header.blob_type != BlobType::ENCRYPTED_DATA {
             return Err(invalid_field_err!("blobType", "invalid blob type"));
         }
         ensure_size!(in: src, size: encrypted_hwid_blob_header.length);
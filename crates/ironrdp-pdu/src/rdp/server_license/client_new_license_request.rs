@@ -67,29 +67,11 @@ impl ClientNewLicenseRequest {
 
         let encrypted_premaster_secret = encrypt_with_public_key(premaster_secret, &public_key)?;
 
-        let master_secret = compute_master_secret(
+        let encryption_data = LicenseEncryptionData::new(
             premaster_secret,
             client_random,
             license_request.server_random.as_slice(),
         );
-        let session_key_blob = compute_session_key_blob(
-            master_secret.as_slice(),
-            client_random,
-            license_request.server_random.as_slice(),
-        );
-        let mac_salt_key = &session_key_blob[..16];
-
-        let mut md5 = md5::Md5::new();
-        md5.update(
-            [
-                &session_key_blob[16..32],
-                client_random,
-                license_request.server_random.as_slice(),
-            ]
-            .concat()
-            .as_slice(),
-        );
-        let license_key = md5.finalize().to_vec();
 
         let license_header = LicenseHeader {
             security_header: BasicSecurityHeader {
@@ -116,11 +98,7 @@ impl ClientNewLicenseRequest {
                 client_username: client_username.to_owned(),
                 client_machine_name: client_machine_name.to_owned(),
             },
-            LicenseEncryptionData {
-                premaster_secret: Vec::from(premaster_secret),
-                mac_salt_key: Vec::from(mac_salt_key),
-                license_key,
-            },
+            encryption_data,
         ))
     }
 }
@@ -52,7 +52,8 @@ impl ClientPlatformChallengeResponse {
             return Err(ServerLicenseError::InvalidMacData);
         }
 
-        let mut challenge_response_data = vec![0u8; RESPONSE_DATA_STATIC_FIELDS_SIZE];
+        let mut challenge_response_data =
+            Vec::with_capacity(RESPONSE_DATA_STATIC_FIELDS_SIZE + decrypted_challenge.len());
         challenge_response_data.write_u16::<LittleEndian>(RESPONSE_DATA_VERSION)?;
         challenge_response_data.write_u16::<LittleEndian>(ClientType::Other.to_u16().unwrap())?;
         challenge_response_data.write_u16::<LittleEndian>(LicenseDetailLevel::Detail.to_u16().unwrap())?;
@@ -99,6 +100,34 @@ impl ClientPlatformChallengeResponse {
     }
 }
 
+impl ClientPlatformChallengeResponse {
+    /// Checks the MAC computed by the client over the challenge response data and its hardware identification.
+    pub fn verify_challenge_response(&self, encryption_data: &LicenseEncryptionData) -> Result<(), ServerLicenseError> {
+        let mut data = decrypt(encryption_data, &self.encrypted_challenge_response_data);
+        data.extend(decrypt(encryption_data, &self.encrypted_hwid));
+        let mac_data = super::compute_mac_data(encryption_data.mac_salt_key.as_slice(), data.as_slice());
+
+        if mac_data != self.mac_data {
+            return Err(ServerLicenseError::InvalidMacData);
+        }
+
+        Ok(())
+    }
+
+    pub fn challenge_response_data(
+        &self,
+        encryption_data: &LicenseEncryptionData,
+    ) -> DecodeResult<PlatformChallengeResponseData> {
+        let data = decrypt(encryption_data, &self.encrypted_challenge_response_data);
+        PlatformChallengeResponseData::decode(&mut ReadCursor::new(&data))
+    }
+
+    pub fn hardware_id(&self, encryption_data: &LicenseEncryptionData) -> DecodeResult<ClientHardwareIdentification> {
+        let data = decrypt(encryption_data, &self.encrypted_hwid);
+        ClientHardwareIdentification::decode(&mut ReadCursor::new(&data))
+    }
+}
+
 impl ClientPlatformChallengeResponse {
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
@@ -162,6 +191,11 @@ impl ClientPlatformChallengeResponse {
     }
 }
 
+fn decrypt(encryption_data: &LicenseEncryptionData, data: &[u8]) -> Vec<u8> {
+    let mut rc4 = Rc4::new(encryption_data.license_key.as_slice());
+    rc4.process(data)
+}
+
 #[derive(Debug, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum ClientType {
     Win32 = 0x0100,
@@ -191,9 +191,8 @@ fn challenge_response_creates_from_server_challenge_and_encryption_data_correctl
     let mut rc4 = Rc4::new(&encryption_data.license_key);
     let encrypted_hwid = rc4.process(&hardware_id);
 
-    let response_data: [u8; 26] = [
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0xff, 0x03, 0x00, 0x0a, 0x00, 0x54, 0x00,
-        0x45, 0x00, 0x53, 0x00, 0x54, 0x00, 0x00, 0x00,
+    let response_data: [u8; 18] = [
+        0x00, 0x01, 0x00, 0xff, 0x03, 0x00, 0x0a, 0x00, 0x54, 0x00, 0x45, 0x00, 0x53, 0x00, 0x54, 0x00, 0x00, 0x00,
     ];
 
     let mut rc4 = Rc4::new(&encryption_data.license_key);
@@ -10,9 +10,11 @@ use ironrdp_core::{
 };
 
 use super::{
-    BlobHeader, BlobType, LicenseHeader, PreambleType, ServerLicenseError, BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE,
-    KEY_EXCHANGE_ALGORITHM_RSA, RANDOM_NUMBER_SIZE, UTF16_NULL_TERMINATOR_SIZE, UTF8_NULL_TERMINATOR_SIZE,
+    BlobHeader, BlobType, LicenseHeader, PreambleFlags, PreambleType, PreambleVersion, ServerLicenseError,
+    BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, KEY_EXCHANGE_ALGORITHM_RSA, RANDOM_NUMBER_SIZE, UTF16_NULL_TERMINATOR_SIZE,
+    UTF8_NULL_TERMINATOR_SIZE,
 };
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
 use crate::utils;
 
 const CERT_VERSION_FIELD_SIZE: usize = 4;
@@ -42,6 +44,36 @@ pub struct ServerLicenseRequest {
 impl ServerLicenseRequest {
     const NAME: &'static str = "ServerLicenseRequest";
 
+    pub fn new(
+        server_random: Vec<u8>,
+        product_info: ProductInfo,
+        server_certificate: Option<ServerCertificate>,
+        scope_list: Vec<Scope>,
+    ) -> EncodeResult<Self> {
+        let mut this = Self {
+            license_header: LicenseHeader {
+                security_header: BasicSecurityHeader {
+                    flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+                },
+                preamble_message_type: PreambleType::LicenseRequest,
+                preamble_flags: PreambleFlags::empty(),
+                preamble_version: PreambleVersion::V3,
+                preamble_message_size: 0,
+            },
+            server_random,
+            product_info,
+            server_certificate,
+            scope_list,
+        };
+        this.license_header.preamble_message_size = cast_length!(
+            "ServerLicenseRequest",
+            "preamble_message_size",
+            this.size() - BASIC_SECURITY_HEADER_SIZE
+        )?;
+
+        Ok(this)
+    }
+
     pub fn get_public_key(&self) -> Result<Option<Vec<u8>>, ServerLicenseError> {
         self.server_certificate.as_ref().map(|c| c.get_public_key()).transpose()
     }
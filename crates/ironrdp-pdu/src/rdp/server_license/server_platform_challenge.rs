@@ -2,10 +2,15 @@
 mod test;
 
 use ironrdp_core::{
-    ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
+    cast_length, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor,
 };
 
-use super::{BlobHeader, BlobType, LicenseHeader, PreambleType, BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, MAC_SIZE};
+use super::{
+    BlobHeader, BlobType, LicenseEncryptionData, LicenseHeader, PreambleFlags, PreambleType, PreambleVersion,
+    BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, MAC_SIZE,
+};
+use crate::crypto::rc4::Rc4;
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
 
 const CONNECT_FLAGS_FIELD_SIZE: usize = 4;
 
@@ -23,6 +28,34 @@ impl ServerPlatformChallenge {
     const NAME: &'static str = "ServerPlatformChallenge";
 
     const FIXED_PART_SIZE: usize = CONNECT_FLAGS_FIELD_SIZE + MAC_SIZE + BLOB_LENGTH_SIZE + BLOB_TYPE_SIZE;
+
+    /// Encrypts the given challenge with the license key, the client sending it back decrypted.
+    pub fn new(platform_challenge: &[u8], encryption_data: &LicenseEncryptionData) -> EncodeResult<Self> {
+        let mut rc4 = Rc4::new(encryption_data.license_key.as_slice());
+        let encrypted_platform_challenge = rc4.process(platform_challenge);
+        let mac_data = super::compute_mac_data(encryption_data.mac_salt_key.as_slice(), platform_challenge);
+
+        let mut this = Self {
+            license_header: LicenseHeader {
+                security_header: BasicSecurityHeader {
+                    flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+                },
+                preamble_message_type: PreambleType::PlatformChallenge,
+                preamble_flags: PreambleFlags::empty(),
+                preamble_version: PreambleVersion::V3,
+                preamble_message_size: 0,
+            },
+            encrypted_platform_challenge,
+            mac_data,
+        };
+        this.license_header.preamble_message_size = cast_length!(
+            "ServerPlatformChallenge",
+            "preamble_message_size",
+            this.size() - BASIC_SECURITY_HEADER_SIZE
+        )?;
+
+        Ok(this)
+    }
 }
 
 impl ServerPlatformChallenge {
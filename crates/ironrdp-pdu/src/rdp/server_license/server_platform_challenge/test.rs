@@ -3,8 +3,8 @@ use lazy_static::lazy_static;
 
 use super::*;
 use crate::rdp::server_license::{
-    BasicSecurityHeader, BasicSecurityHeaderFlags, LicensePdu, PreambleFlags, PreambleVersion,
-    BASIC_SECURITY_HEADER_SIZE,
+    BasicSecurityHeader, BasicSecurityHeaderFlags, ClientPlatformChallengeResponse, LicensePdu, PreambleFlags,
+    PreambleVersion, BASIC_SECURITY_HEADER_SIZE, PLATFORM_ID,
 };
 
 const PLATFORM_CHALLENGE_BUFFER: [u8; 42] = [
@@ -71,3 +71,44 @@ fn to_buffer_correctly_serializes_server_platform_challenge() {
 fn buffer_length_is_correct_for_server_platform_challenge() {
     assert_eq!(PLATFORM_CHALLENGE_BUFFER.len(), PLATFORM_CHALLENGE.size());
 }
+
+#[test]
+fn new_platform_challenge_is_answered_by_the_client() {
+    let encryption_data = LicenseEncryptionData {
+        premaster_secret: Vec::new(), // premaster secret is not involved in this unit test
+        mac_salt_key: vec![
+            0x1, 0x5b, 0x9e, 0x5f, 0x6, 0x97, 0x71, 0x58, 0xc3, 0xb8, 0x8b, 0x8c, 0x6e, 0x77, 0x21, 0x37,
+        ],
+        license_key: vec![
+            0xe1, 0x78, 0xe4, 0xa0, 0x2a, 0xc5, 0xca, 0xb8, 0xa2, 0xd1, 0x53, 0xb8, 0x7, 0x23, 0xf3, 0xd2,
+        ],
+    };
+    let hardware_data = [0x0102_0304, 0x0506_0708, 0x090a_0b0c, 0x0d0e_0f10];
+
+    let challenge = ServerPlatformChallenge::new(b"T\0E\0S\0T\0\0\0", &encryption_data).unwrap();
+
+    let encoded = encode_vec(&LicensePdu::from(challenge)).unwrap();
+    let LicensePdu::ServerPlatformChallenge(challenge) = decode(encoded.as_slice()).unwrap() else {
+        panic!("unexpected license PDU");
+    };
+
+    let response =
+        ClientPlatformChallengeResponse::from_server_platform_challenge(&challenge, hardware_data, &encryption_data)
+            .unwrap();
+
+    response.verify_challenge_response(&encryption_data).unwrap();
+    assert_eq!(
+        response.challenge_response_data(&encryption_data).unwrap().challenge,
+        b"T\0E\0S\0T\0\0\0"
+    );
+
+    let hardware_id = response.hardware_id(&encryption_data).unwrap();
+    assert_eq!(hardware_id.platform_id, PLATFORM_ID);
+    assert_eq!(
+        hardware_id.data,
+        hardware_data
+            .iter()
+            .flat_map(|data| data.to_le_bytes())
+            .collect::<Vec<_>>()
+    );
+}
@@ -2,15 +2,17 @@
 mod tests;
 
 use ironrdp_core::{
-    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
-    ReadCursor, WriteCursor,
+    cast_length, encode_vec, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode,
+    EncodeResult, ReadCursor, WriteCursor,
 };
 
 use super::{
-    BlobHeader, BlobType, LicenseEncryptionData, LicenseHeader, PreambleType, ServerLicenseError, BLOB_LENGTH_SIZE,
-    BLOB_TYPE_SIZE, MAC_SIZE, UTF16_NULL_TERMINATOR_SIZE, UTF8_NULL_TERMINATOR_SIZE,
+    BlobHeader, BlobType, LicenseEncryptionData, LicenseHeader, PreambleFlags, PreambleType, PreambleVersion,
+    ServerLicenseError, BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, MAC_SIZE, UTF16_NULL_TERMINATOR_SIZE,
+    UTF8_NULL_TERMINATOR_SIZE,
 };
 use crate::crypto::rc4::Rc4;
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
 use crate::utils;
 use crate::utils::CharacterSet;
 
@@ -27,6 +29,39 @@ pub struct ServerUpgradeLicense {
 }
 
 impl ServerUpgradeLicense {
+    /// Encrypts the license issued to the client with the license key.
+    ///
+    /// The message is sent as a Server New License PDU, the preamble message type of the header being changed to
+    /// [`PreambleType::UpgradeLicense`] when the license presented by the client is upgraded.
+    pub fn new(license_info: &LicenseInformation, encryption_data: &LicenseEncryptionData) -> EncodeResult<Self> {
+        let license_info = encode_vec(license_info)?;
+
+        let mut rc4 = Rc4::new(encryption_data.license_key.as_slice());
+        let encrypted_license_info = rc4.process(license_info.as_slice());
+        let mac_data = super::compute_mac_data(encryption_data.mac_salt_key.as_slice(), license_info.as_slice());
+
+        let mut this = Self {
+            license_header: LicenseHeader {
+                security_header: BasicSecurityHeader {
+                    flags: BasicSecurityHeaderFlags::LICENSE_PKT,
+                },
+                preamble_message_type: PreambleType::NewLicense,
+                preamble_flags: PreambleFlags::empty(),
+                preamble_version: PreambleVersion::V3,
+                preamble_message_size: 0,
+            },
+            encrypted_license_info,
+            mac_data,
+        };
+        this.license_header.preamble_message_size = cast_length!(
+            "ServerUpgradeLicense",
+            "preamble_message_size",
+            this.size() - BASIC_SECURITY_HEADER_SIZE
+        )?;
+
+        Ok(this)
+    }
+
     pub fn verify_server_license(&self, encryption_data: &LicenseEncryptionData) -> Result<(), ServerLicenseError> {
         let decrypted_license_info = self.decrypted_license_info(encryption_data);
         let mac_data =
@@ -640,3 +640,29 @@ fn upgrade_license_verifies_correctly() {
 
     upgrade_license.verify_server_license(&encryption_info).unwrap();
 }
+
+#[test]
+fn new_upgrade_license_is_verified_and_decrypted_by_the_client() {
+    let encryption_data = LicenseEncryptionData {
+        premaster_secret: Vec::new(), // this field is not involved in this unit test
+        mac_salt_key: vec![
+            0xd5, 0x2c, 0x7c, 0xd2, 0x71, 0x15, 0x2c, 0x41, 0xbb, 0xd8, 0x36, 0xdb, 0x19, 0x3e, 0xc0, 0xf3,
+        ],
+        license_key: vec![
+            0x88, 0x7d, 0x33, 0xa6, 0x13, 0xd, 0x76, 0xbf, 0x76, 0x2a, 0xf, 0x57, 0x71, 0x1d, 0x40, 0xa3,
+        ],
+    };
+
+    let upgrade_license = ServerUpgradeLicense::new(&NEW_LICENSE_INFORMATION, &encryption_data).unwrap();
+
+    let encoded = encode_vec(&LicensePdu::from(upgrade_license)).unwrap();
+    let LicensePdu::ServerUpgradeLicense(upgrade_license) = decode(encoded.as_slice()).unwrap() else {
+        panic!("unexpected license PDU");
+    };
+
+    upgrade_license.verify_server_license(&encryption_data).unwrap();
+    assert_eq!(
+        upgrade_license.new_license_info(&encryption_data).unwrap(),
+        *NEW_LICENSE_INFORMATION
+    );
+}
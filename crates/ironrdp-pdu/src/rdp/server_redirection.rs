@@ -0,0 +1,302 @@
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use crate::rdp::headers::BasicSecurityHeaderFlags;
+use crate::utils::{self, CharacterSet};
+
+bitflags! {
+    /// Flags of the Server Redirection PDU, telling which fields are present and how to use them
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct RedirectionFlags: u32 {
+        const TARGET_NET_ADDRESS = 0x0000_0001;
+        const LOAD_BALANCE_INFO = 0x0000_0002;
+        const USERNAME = 0x0000_0004;
+        const DOMAIN = 0x0000_0008;
+        const PASSWORD = 0x0000_0010;
+        const DONT_STORE_USERNAME = 0x0000_0020;
+        const SMARTCARD_LOGON = 0x0000_0040;
+        const NO_REDIRECT = 0x0000_0080;
+        const TARGET_FQDN = 0x0000_0100;
+        const TARGET_NETBIOS_NAME = 0x0000_0200;
+        const TARGET_NET_ADDRESSES = 0x0000_0800;
+        const CLIENT_TSV_URL = 0x0000_1000;
+        const SERVER_TSV_CAPABLE = 0x0000_2000;
+        const PASSWORD_IS_PK_ENCRYPTED = 0x0000_4000;
+        const REDIRECTION_GUID = 0x0000_8000;
+        const TARGET_CERTIFICATE = 0x0001_0000;
+        const _ = !0;
+    }
+}
+
+impl RedirectionFlags {
+    /// Flags telling which optional fields are present in the PDU.
+    const FIELDS: Self = Self::TARGET_NET_ADDRESS
+        .union(Self::LOAD_BALANCE_INFO)
+        .union(Self::USERNAME)
+        .union(Self::DOMAIN)
+        .union(Self::PASSWORD)
+        .union(Self::TARGET_FQDN)
+        .union(Self::TARGET_NETBIOS_NAME)
+        .union(Self::CLIENT_TSV_URL)
+        .union(Self::REDIRECTION_GUID)
+        .union(Self::TARGET_CERTIFICATE)
+        .union(Self::TARGET_NET_ADDRESSES);
+}
+
+/// [2.2.13.1] Server Redirection Packet (RDP_SERVER_REDIRECTION_PACKET)
+///
+/// Sent by a load balancer, or by a server of a farm, to instruct the client to connect to another server.
+///
+/// With standard RDP security, the packet is the user data of the MCS Send Data Indication sent during licensing.
+/// With enhanced security, it is wrapped in a Share Control PDU, see [`ShareControlPdu::ServerRedirect`].
+///
+/// When encoding, the flags telling which fields are present are derived from the fields.
+///
+/// [2.2.13.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7c
+/// [`ShareControlPdu::ServerRedirect`]: crate::rdp::headers::ShareControlPdu::ServerRedirect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRedirectionPdu {
+    pub session_id: u32,
+    pub flags: RedirectionFlags,
+    pub target_net_address: Option<String>,
+    /// Routing token to send in the X.224 Connection Request PDU of the new connection.
+    pub load_balance_info: Option<Vec<u8>>,
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    /// Either an opaque cookie to send back as the password of the Client Info PDU, or a password encrypted with the
+    /// public key of the target when [`RedirectionFlags::PASSWORD_IS_PK_ENCRYPTED`] is set.
+    pub password: Option<Vec<u8>>,
+    pub target_fqdn: Option<String>,
+    pub target_netbios_name: Option<String>,
+    pub tsv_url: Option<Vec<u8>>,
+    pub redirection_guid: Option<Vec<u8>>,
+    pub target_certificate: Option<Vec<u8>>,
+    pub target_net_addresses: Option<Vec<String>>,
+}
+
+impl ServerRedirectionPdu {
+    const NAME: &'static str = "ServerRedirectionPdu";
+
+    const FIXED_PART_SIZE: usize = 2 /* flags */ + 2 /* length */ + 4 /* sessionId */ + 4 /* redirFlags */;
+
+    fn present_fields(&self) -> RedirectionFlags {
+        let fields = [
+            (RedirectionFlags::TARGET_NET_ADDRESS, self.target_net_address.is_some()),
+            (RedirectionFlags::LOAD_BALANCE_INFO, self.load_balance_info.is_some()),
+            (RedirectionFlags::USERNAME, self.username.is_some()),
+            (RedirectionFlags::DOMAIN, self.domain.is_some()),
+            (RedirectionFlags::PASSWORD, self.password.is_some()),
+            (RedirectionFlags::TARGET_FQDN, self.target_fqdn.is_some()),
+            (
+                RedirectionFlags::TARGET_NETBIOS_NAME,
+                self.target_netbios_name.is_some(),
+            ),
+            (RedirectionFlags::CLIENT_TSV_URL, self.tsv_url.is_some()),
+            (RedirectionFlags::REDIRECTION_GUID, self.redirection_guid.is_some()),
+            (RedirectionFlags::TARGET_CERTIFICATE, self.target_certificate.is_some()),
+            (
+                RedirectionFlags::TARGET_NET_ADDRESSES,
+                self.target_net_addresses.is_some(),
+            ),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(_, is_present)| *is_present)
+            .fold(RedirectionFlags::empty(), |flags, (flag, _)| flags | flag)
+    }
+}
+
+impl Encode for ServerRedirectionPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(BasicSecurityHeaderFlags::REDIRECTION_PKT.bits());
+        dst.write_u16(cast_length!("length", self.size())?);
+        dst.write_u32(self.session_id);
+        dst.write_u32(((self.flags - RedirectionFlags::FIELDS) | self.present_fields()).bits());
+
+        write_string_field(dst, self.target_net_address.as_deref())?;
+        write_bytes_field(dst, self.load_balance_info.as_deref())?;
+        write_string_field(dst, self.username.as_deref())?;
+        write_string_field(dst, self.domain.as_deref())?;
+        write_bytes_field(dst, self.password.as_deref())?;
+        write_string_field(dst, self.target_fqdn.as_deref())?;
+        write_string_field(dst, self.target_netbios_name.as_deref())?;
+        write_bytes_field(dst, self.tsv_url.as_deref())?;
+        write_bytes_field(dst, self.redirection_guid.as_deref())?;
+        write_bytes_field(dst, self.target_certificate.as_deref())?;
+
+        if let Some(addresses) = &self.target_net_addresses {
+            dst.write_u32(cast_length!("targetNetAddressesLength", net_addresses_size(addresses))?);
+            dst.write_u32(cast_length!("addressCount", addresses.len())?);
+            for address in addresses {
+                write_string_field(dst, Some(address))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let strings = [
+            &self.target_net_address,
+            &self.username,
+            &self.domain,
+            &self.target_fqdn,
+            &self.target_netbios_name,
+        ];
+        let bytes = [
+            &self.load_balance_info,
+            &self.password,
+            &self.tsv_url,
+            &self.redirection_guid,
+            &self.target_certificate,
+        ];
+
+        Self::FIXED_PART_SIZE
+            + strings
+                .into_iter()
+                .flatten()
+                .map(|s| string_field_size(s))
+                .sum::<usize>()
+            + bytes
+                .into_iter()
+                .flatten()
+                .map(|b| 4 /* length */ + b.len())
+                .sum::<usize>()
+            + self
+                .target_net_addresses
+                .as_ref()
+                .map_or(0, |addresses| 4 /* length */ + net_addresses_size(addresses))
+    }
+}
+
+impl<'de> Decode<'de> for ServerRedirectionPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let security_flags = BasicSecurityHeaderFlags::from_bits_truncate(src.read_u16());
+        if !security_flags.contains(BasicSecurityHeaderFlags::REDIRECTION_PKT) {
+            return Err(invalid_field_err!("flags", "SEC_REDIRECTION_PKT flag is missing"));
+        }
+
+        let length = usize::from(src.read_u16());
+        let variable_part_size = length
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("length", "length is smaller than the fixed part"))?;
+        let session_id = src.read_u32();
+        let flags = RedirectionFlags::from_bits_retain(src.read_u32());
+
+        ensure_size!(in: src, size: variable_part_size);
+        // The optional padding at the end of the packet is counted in the length.
+        let src = &mut ReadCursor::new(src.read_slice(variable_part_size));
+
+        let target_net_address = read_string_field(src, flags, RedirectionFlags::TARGET_NET_ADDRESS)?;
+        let load_balance_info = read_bytes_field(src, flags, RedirectionFlags::LOAD_BALANCE_INFO)?;
+        let username = read_string_field(src, flags, RedirectionFlags::USERNAME)?;
+        let domain = read_string_field(src, flags, RedirectionFlags::DOMAIN)?;
+        let password = read_bytes_field(src, flags, RedirectionFlags::PASSWORD)?;
+        let target_fqdn = read_string_field(src, flags, RedirectionFlags::TARGET_FQDN)?;
+        let target_netbios_name = read_string_field(src, flags, RedirectionFlags::TARGET_NETBIOS_NAME)?;
+        let tsv_url = read_bytes_field(src, flags, RedirectionFlags::CLIENT_TSV_URL)?;
+        let redirection_guid = read_bytes_field(src, flags, RedirectionFlags::REDIRECTION_GUID)?;
+        let target_certificate = read_bytes_field(src, flags, RedirectionFlags::TARGET_CERTIFICATE)?;
+
+        let target_net_addresses = read_bytes_field(src, flags, RedirectionFlags::TARGET_NET_ADDRESSES)?
+            .map(|addresses| {
+                let src = &mut ReadCursor::new(&addresses);
+
+                ensure_size!(ctx: "TargetNetAddresses", in: src, size: 4);
+                let address_count = src.read_u32();
+
+                (0..address_count)
+                    .map(|_| read_string(src))
+                    .collect::<DecodeResult<Vec<_>>>()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            session_id,
+            flags,
+            target_net_address,
+            load_balance_info,
+            username,
+            domain,
+            password,
+            target_fqdn,
+            target_netbios_name,
+            tsv_url,
+            redirection_guid,
+            target_certificate,
+            target_net_addresses,
+        })
+    }
+}
+
+fn read_field<'de>(src: &mut ReadCursor<'de>) -> DecodeResult<&'de [u8]> {
+    ensure_size!(in: src, size: 4);
+    let length = cast_length!("length", src.read_u32())?;
+    ensure_size!(in: src, size: length);
+
+    Ok(src.read_slice(length))
+}
+
+fn read_string(src: &mut ReadCursor<'_>) -> DecodeResult<String> {
+    utils::decode_string(read_field(src)?, CharacterSet::Unicode, true)
+}
+
+fn read_bytes_field(
+    src: &mut ReadCursor<'_>,
+    flags: RedirectionFlags,
+    field: RedirectionFlags,
+) -> DecodeResult<Option<Vec<u8>>> {
+    flags
+        .contains(field)
+        .then(|| read_field(src).map(<[u8]>::to_vec))
+        .transpose()
+}
+
+fn read_string_field(
+    src: &mut ReadCursor<'_>,
+    flags: RedirectionFlags,
+    field: RedirectionFlags,
+) -> DecodeResult<Option<String>> {
+    flags.contains(field).then(|| read_string(src)).transpose()
+}
+
+fn write_bytes_field(dst: &mut WriteCursor<'_>, value: Option<&[u8]>) -> EncodeResult<()> {
+    if let Some(value) = value {
+        dst.write_u32(cast_length!("length", value.len())?);
+        dst.write_slice(value);
+    }
+
+    Ok(())
+}
+
+fn write_string_field(dst: &mut WriteCursor<'_>, value: Option<&str>) -> EncodeResult<()> {
+    if let Some(value) = value {
+        dst.write_u32(cast_length!(
+            "length",
+            utils::encoded_str_len(value, CharacterSet::Unicode, true)
+        )?);
+        utils::write_string_to_cursor(dst, value, CharacterSet::Unicode, true)?;
+    }
+
+    Ok(())
+}
+
+fn string_field_size(value: &str) -> usize {
+    4 /* length */ + utils::encoded_str_len(value, CharacterSet::Unicode, true)
+}
+
+fn net_addresses_size(addresses: &[String]) -> usize {
+    4 /* addressCount */ + addresses.iter().map(|address| string_field_size(address)).sum::<usize>()
+}
@@ -3,6 +3,7 @@ use ironrdp_core::{
     cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, Encode, EncodeResult,
     ReadCursor, WriteCursor,
 };
+use md5::Digest as _;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
@@ -15,6 +16,7 @@ const LOGON_INFO_FIELD_DATA_SIZE: usize = 4;
 const AUTO_RECONNECT_VERSION_1: u32 = 0x0000_0001;
 const AUTO_RECONNECT_PACKET_SIZE: usize = 28;
 const AUTO_RECONNECT_RANDOM_BITS_SIZE: usize = 16;
+const HMAC_MD5_BLOCK_SIZE: usize = 64;
 const LOGON_ERRORS_INFO_SIZE: usize = 8;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -106,6 +108,35 @@ impl ServerAutoReconnect {
     const NAME: &'static str = "ServerAutoReconnect";
 
     const FIXED_PART_SIZE: usize = AUTO_RECONNECT_PACKET_SIZE + LOGON_INFO_FIELD_DATA_SIZE;
+
+    /// Computes the client auto-reconnect cookie (ARC_CS_PRIVATE_PACKET) sent in the Client Info PDU when
+    /// reconnecting to the session.
+    ///
+    /// The security verifier is the HMAC-MD5 of `client_random` keyed with the random bits of the server. With
+    /// Enhanced RDP Security, no client random is exchanged and 32 zero bytes are used instead.
+    ///
+    /// [Doc](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7f)
+    pub fn client_cookie(&self, client_random: &[u8]) -> [u8; AUTO_RECONNECT_PACKET_SIZE] {
+        let mut key = [0; HMAC_MD5_BLOCK_SIZE];
+        key[..AUTO_RECONNECT_RANDOM_BITS_SIZE].copy_from_slice(&self.random_bits);
+
+        let inner = md5::Md5::new()
+            .chain_update(key.map(|byte| byte ^ 0x36))
+            .chain_update(client_random)
+            .finalize();
+        let security_verifier = md5::Md5::new()
+            .chain_update(key.map(|byte| byte ^ 0x5c))
+            .chain_update(inner)
+            .finalize();
+
+        let mut cookie = [0; AUTO_RECONNECT_PACKET_SIZE];
+        cookie[0..4].copy_from_slice(&(AUTO_RECONNECT_PACKET_SIZE as u32).to_le_bytes());
+        cookie[4..8].copy_from_slice(&AUTO_RECONNECT_VERSION_1.to_le_bytes());
+        cookie[8..12].copy_from_slice(&self.logon_id.to_le_bytes());
+        cookie[12..].copy_from_slice(&security_verifier);
+
+        cookie
+    }
 }
 
 impl Encode for ServerAutoReconnect {
@@ -196,9 +227,14 @@ impl<'de> Decode<'de> for LogonErrorsInfo {
             .ok_or_else(|| invalid_field_err!("errorType", "invalid logon error type"))?;
 
         let error_notification_data = src.read_u32();
-        let error_data = LogonErrorNotificationDataErrorCode::from_u32(error_notification_data)
-            .map(LogonErrorNotificationData::ErrorCode)
-            .unwrap_or(LogonErrorNotificationData::SessionId(error_notification_data));
+        let error_data = if error_type == LogonErrorNotificationType::SessionContinue {
+            // With LOGON_MSG_SESSION_CONTINUE, the data field holds the session ID.
+            LogonErrorNotificationData::SessionId(error_notification_data)
+        } else {
+            LogonErrorNotificationDataErrorCode::from_u32(error_notification_data)
+                .map(LogonErrorNotificationData::ErrorCode)
+                .unwrap_or(LogonErrorNotificationData::SessionId(error_notification_data))
+        };
 
         Ok(Self { error_type, error_data })
     }
@@ -73,15 +73,17 @@ impl<'de> Decode<'de> for LogonInfoVersion1 {
             return Err(invalid_field_err!("domainNameSize", "invalid domain name size"));
         }
 
+        // The fixed-size buffers may contain garbage after the null terminator, so only the
+        // characters up to the first null terminator are kept.
         let domain_name =
-            utils::decode_string(src.read_slice(DOMAIN_NAME_SIZE_V1), utils::CharacterSet::Unicode, false)?;
+            utils::decode_string(src.read_slice(DOMAIN_NAME_SIZE_V1), utils::CharacterSet::Unicode, true)?;
 
         let user_name_size: usize = cast_length!("userNameSize", src.read_u32())?;
         if user_name_size > USER_NAME_SIZE_V1 {
             return Err(invalid_field_err!("userNameSize", "invalid user name size"));
         }
 
-        let user_name = utils::decode_string(src.read_slice(USER_NAME_SIZE_V1), utils::CharacterSet::Unicode, false)?;
+        let user_name = utils::decode_string(src.read_slice(USER_NAME_SIZE_V1), utils::CharacterSet::Unicode, true)?;
 
         let session_id = src.read_u32();
 
@@ -173,10 +175,10 @@ impl<'de> Decode<'de> for LogonInfoVersion2 {
         read_padding!(src, LOGON_INFO_V2_PADDING_SIZE);
 
         ensure_size!(in: src, size: domain_name_size);
-        let domain_name = utils::decode_string(src.read_slice(domain_name_size), utils::CharacterSet::Unicode, false)?;
+        let domain_name = utils::decode_string(src.read_slice(domain_name_size), utils::CharacterSet::Unicode, true)?;
 
         ensure_size!(in: src, size: user_name_size);
-        let user_name = utils::decode_string(src.read_slice(user_name_size), utils::CharacterSet::Unicode, false)?;
+        let user_name = utils::decode_string(src.read_slice(user_name_size), utils::CharacterSet::Unicode, true)?;
 
         Ok(Self {
             logon_info: LogonInfo {
@@ -230,6 +230,11 @@ const LOGON_EXTENDED_WITH_INVALID_LOGON_ERROR_TYPE_BUFFER: [u8; 50] = [
     0x08, 0x00, 0x00, 0x00, 0xf0, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
 ];
 
+const LOGON_ERRORS_INFO_SESSION_CONTINUE_BUFFER: [u8; 12] =
+    [0x08, 0x00, 0x00, 0x00, 0xfe, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00];
+const LOGON_ERRORS_INFO_UPDATE_PASSWORD_BUFFER: [u8; 12] =
+    [0x08, 0x00, 0x00, 0x00, 0xfa, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00];
+
 const DOMAIN_NAME: &str = "NTDEV";
 const USER_NAME: &str = "eltons";
 const SESSION_ID: u32 = 0x02;
@@ -433,3 +438,62 @@ fn from_buffer_parsing_with_invalid_logon_error_type_fails() {
         res => panic!("Expected InvalidLogonErrorType error, got: {res:?}"),
     };
 }
+
+#[test]
+fn from_buffer_trims_garbage_after_null_terminator_in_logon_info_v1() {
+    let mut buffer = LOGON_INFO_V1_BUFFER;
+    // Garbage right after the null-terminated "NTDEV" domain name.
+    buffer[16..20].copy_from_slice(&[0x41, 0x00, 0x42, 0x00]);
+    // Garbage right after the null-terminated "eltons" user name.
+    buffer[74..78].copy_from_slice(&[0x43, 0x00, 0x44, 0x00]);
+
+    assert_eq!(LOGON_INFO_V1.clone(), decode(buffer.as_ref()).unwrap());
+}
+
+#[test]
+fn from_buffer_correct_parses_logon_errors_info_session_continue() {
+    let errors_info = decode::<LogonErrorsInfo>(LOGON_ERRORS_INFO_SESSION_CONTINUE_BUFFER.as_ref()).unwrap();
+
+    assert_eq!(
+        LogonErrorsInfo {
+            error_type: LogonErrorNotificationType::SessionContinue,
+            error_data: LogonErrorNotificationData::SessionId(1),
+        },
+        errors_info
+    );
+}
+
+#[test]
+fn from_buffer_correct_parses_logon_errors_info_update_password() {
+    let errors_info = decode::<LogonErrorsInfo>(LOGON_ERRORS_INFO_UPDATE_PASSWORD_BUFFER.as_ref()).unwrap();
+
+    assert_eq!(
+        LogonErrorsInfo {
+            error_type: LogonErrorNotificationType::NoPermission,
+            error_data: LogonErrorNotificationData::ErrorCode(
+                LogonErrorNotificationDataErrorCode::FailedUpdatePassword
+            ),
+        },
+        errors_info
+    );
+}
+
+#[test]
+fn server_auto_reconnect_computes_client_cookie() {
+    // The security verifier is checked against the HMAC-MD5 test vector of RFC 2104.
+    let auto_reconnect = ServerAutoReconnect {
+        logon_id: SESSION_ID,
+        random_bits: [0x0b; 16],
+    };
+
+    let cookie = auto_reconnect.client_cookie(b"Hi There");
+
+    assert_eq!(
+        cookie[..12],
+        [0x1c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00]
+    );
+    assert_eq!(
+        cookie[12..],
+        [0x92, 0x94, 0x72, 0x7a, 0x36, 0x38, 0xbb, 0x1c, 0x13, 0xf4, 0x8e, 0xf8, 0x15, 0x8b, 0xfc, 0x9d]
+    );
+}
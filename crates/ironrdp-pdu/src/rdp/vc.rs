@@ -6,61 +6,23 @@ mod tests;
 use std::{io, str};
 
 use bitflags::bitflags;
-use ironrdp_core::{ensure_fixed_part_size, Decode, DecodeResult, Encode, EncodeResult, ReadCursor, WriteCursor};
+use ironrdp_pdu_derive::{PduDecode, PduEncode};
 use thiserror::Error;
 
 use crate::PduError;
 
-const CHANNEL_PDU_HEADER_SIZE: usize = 8;
-
 /// Channel PDU Header (CHANNEL_PDU_HEADER)
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode, PduDecode)]
 pub struct ChannelPduHeader {
     /// The total length in bytes of the uncompressed channel data, excluding this header
     ///
     /// The data can span multiple Virtual Channel PDUs and the individual chunks will need to be
     /// reassembled in that case (section 3.1.5.2.2 of MS-RDPBCGR).
     pub length: u32,
+    #[pdu(bitflags = u32)]
     pub flags: ChannelControlFlags,
 }
 
-impl ChannelPduHeader {
-    const NAME: &'static str = "ChannelPduHeader";
-
-    const FIXED_PART_SIZE: usize = CHANNEL_PDU_HEADER_SIZE;
-}
-
-impl Encode for ChannelPduHeader {
-    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
-        ensure_fixed_part_size!(in: dst);
-
-        dst.write_u32(self.length);
-        dst.write_u32(self.flags.bits());
-        Ok(())
-    }
-
-    fn name(&self) -> &'static str {
-        Self::NAME
-    }
-
-    fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE
-    }
-}
-
-impl<'de> Decode<'de> for ChannelPduHeader {
-    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
-        ensure_fixed_part_size!(in: src);
-
-        let total_length = src.read_u32();
-        let flags = ChannelControlFlags::from_bits_truncate(src.read_u32());
-        Ok(Self {
-            length: total_length,
-            flags,
-        })
-    }
-}
-
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub struct ChannelControlFlags: u32 {
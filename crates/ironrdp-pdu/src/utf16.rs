@@ -1,5 +1,15 @@
+//! UTF-16LE string helpers
+//!
+//! The encoding helpers never fail on valid UTF-8 input, and the decoding helpers are lossy: unpaired surrogates
+//! are replaced by U+FFFD (REPLACEMENT CHARACTER), and an odd trailing byte is ignored. The decoders never panic,
+//! whatever the input.
+
 use std::string::FromUtf16Error;
 
+use ironrdp_core::{ensure_size, invalid_field_err, DecodeResult, EncodeResult, ReadCursor, WriteCursor};
+
+use crate::padding;
+
 pub fn read_utf16_string(utf16_payload: &[u8], utf16_size_hint: Option<usize>) -> Result<String, FromUtf16Error> {
     let mut trimmed_utf16: Vec<u16> = if let Some(size_hint) = utf16_size_hint {
         Vec::with_capacity(size_hint)
@@ -24,3 +34,110 @@ pub fn read_utf16_string(utf16_payload: &[u8], utf16_size_hint: Option<usize>) -
 pub fn null_terminated_utf16_encoded_len(utf8: &str) -> usize {
     utf8.encode_utf16().count() * 2 + 2
 }
+
+/// Behavior of [`encode_utf16le_fixed_size`] when the value does not fit in the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedSizeOverflow {
+    /// Keeps the longest prefix which fits, along with the null terminator.
+    Truncate,
+    /// Fails with an invalid field error.
+    Error,
+}
+
+/// Writes `value` as UTF-16LE, followed by a null terminator.
+///
+/// The value is written as is: if it contains null characters, a reader stops at the first one.
+/// The number of bytes written is given by [`null_terminated_utf16_encoded_len`].
+pub fn encode_utf16le_null_terminated(dst: &mut WriteCursor<'_>, value: &str) -> EncodeResult<()> {
+    ensure_size!(ctx: "encode UTF-16LE string", in: dst, size: null_terminated_utf16_encoded_len(value));
+
+    for code_unit in value.encode_utf16() {
+        dst.write_u16(code_unit);
+    }
+    dst.write_u16(0);
+
+    Ok(())
+}
+
+/// Writes `value` as UTF-16LE in a field of exactly `size` bytes, padded with zeroes.
+///
+/// The field always holds a null terminator, so at most `(size - 2) / 2` code units of the value are written.
+/// When truncating, the value is cut on a character boundary: surrogate pairs are never split.
+/// If `size` is odd, the last byte of the field is padding.
+pub fn encode_utf16le_fixed_size(
+    dst: &mut WriteCursor<'_>,
+    value: &str,
+    size: usize,
+    overflow: FixedSizeOverflow,
+) -> EncodeResult<()> {
+    const CTX: &str = "encode fixed-size UTF-16LE string";
+
+    if size < 2 {
+        return Err(invalid_field_err!(CTX, "size", "no room for the null terminator"));
+    }
+
+    ensure_size!(ctx: CTX, in: dst, size: size);
+
+    let capacity = (size - 2) / 2;
+
+    let mut code_units = 0;
+    let mut truncated = false;
+
+    for ch in value.chars() {
+        if code_units + ch.len_utf16() > capacity {
+            truncated = true;
+            break;
+        }
+
+        code_units += ch.len_utf16();
+    }
+
+    if truncated && overflow == FixedSizeOverflow::Error {
+        return Err(invalid_field_err!(CTX, "value", "string does not fit in the field"));
+    }
+
+    for code_unit in value.encode_utf16().take(code_units) {
+        dst.write_u16(code_unit);
+    }
+
+    // Null terminator, followed by the padding.
+    padding::write(dst, size - code_units * 2);
+
+    Ok(())
+}
+
+/// Reads a null-terminated UTF-16LE string.
+///
+/// - The string ends at the first null code unit, which is consumed: embedded null characters cut the string.
+/// - Without a null terminator, all the remaining bytes are consumed, and an odd trailing byte is ignored.
+/// - Unpaired surrogates are replaced by U+FFFD.
+pub fn decode_utf16le_until_null(src: &mut ReadCursor<'_>) -> String {
+    let mut code_units = Vec::with_capacity(src.len() / 2);
+
+    loop {
+        if src.len() < 2 {
+            // No null terminator, skip the odd trailing byte if any.
+            src.advance(src.len());
+            break;
+        }
+
+        match src.read_u16() {
+            0 => break,
+            code_unit => code_units.push(code_unit),
+        }
+    }
+
+    String::from_utf16_lossy(&code_units)
+}
+
+/// Reads a UTF-16LE string from a field of exactly `size` bytes.
+///
+/// The whole field is consumed. Its content is decoded as with [`decode_utf16le_until_null`]: the string ends at
+/// the first null code unit, the bytes after it are ignored, and so is an odd trailing byte.
+pub fn decode_utf16le_lossy_exact(src: &mut ReadCursor<'_>, size: usize) -> DecodeResult<String> {
+    ensure_size!(ctx: "decode UTF-16LE string", in: src, size: size);
+
+    let mut field = ReadCursor::new(src.read_slice(size));
+
+    Ok(decode_utf16le_until_null(&mut field))
+}
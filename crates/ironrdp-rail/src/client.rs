@@ -0,0 +1,187 @@
+use ironrdp_core::{impl_as_any, Decode as _, DecodeErrorKind, ReadCursor};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_pdu::{decode_err, PduResult};
+use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
+use tracing::{debug, info, warn};
+
+use crate::pdu::{
+    ClientStatusFlags, ClientStatusPdu, ExecPdu, ExecResultPdu, HandshakePdu, HighContrast, LocalMoveSizePdu,
+    MinMaxInfoPdu, RailPdu, SysParam, WindowOrder,
+};
+
+/// Build number announced in the client Handshake PDU
+const CLIENT_BUILD_NUMBER: u32 = 0x0000_1DB0;
+
+/// HCF_AVAILABLE | HCF_HOTKEYACTIVE | HCF_CONFIRMHOTKEY | HCF_HOTKEYSOUND | HCF_INDICATOR | HCF_HOTKEYAVAILABLE
+const DEFAULT_HIGH_CONTRAST_FLAGS: u32 = 0x0000_007E;
+
+pub trait RailBackend: Send + core::fmt::Debug {
+    fn on_window_created(&mut self, window_id: u32);
+
+    fn on_window_deleted(&mut self, window_id: u32);
+
+    /// Called when the server reports the outcome of the launch of the remote application.
+    fn on_exec_result(&mut self, _result: &ExecResultPdu) {}
+
+    fn on_min_max_info(&mut self, _info: &MinMaxInfoPdu) {}
+
+    fn on_local_move_size(&mut self, _move_size: &LocalMoveSizePdu) {}
+}
+
+#[derive(Debug)]
+pub struct NoopRailBackend;
+
+impl RailBackend for NoopRailBackend {
+    fn on_window_created(&mut self, _window_id: u32) {}
+
+    fn on_window_deleted(&mut self, _window_id: u32) {}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RailState {
+    WaitingForHandshake,
+    Ready,
+}
+
+/// Client side of the RAIL static virtual channel, launching a single remote application
+///
+/// The client initialization sequence (handshake, client status, system parameters and execute request) is sent
+/// as soon as the server handshake is received.
+///
+/// The windows of the remote application are described by the windowing orders received on the graphics output
+/// channel, which must be forwarded to [`Rail::handle_window_order`].
+#[derive(Debug)]
+pub struct Rail {
+    backend: Box<dyn RailBackend>,
+    state: RailState,
+    exec: ExecPdu,
+    work_area: ExclusiveRectangle,
+}
+
+impl Rail {
+    pub const NAME: ChannelName = ChannelName::from_static(b"rail\0\0\0\0");
+
+    /// Creates the channel for launching the application described by `exec`.
+    ///
+    /// `work_area` is the area of the client desktop available to the windows of the application.
+    pub fn new(exec: ExecPdu, work_area: ExclusiveRectangle, backend: Box<dyn RailBackend>) -> Self {
+        Self {
+            backend,
+            state: RailState::WaitingForHandshake,
+            exec,
+            work_area,
+        }
+    }
+
+    /// Launches the remote application in a work area covering the whole desktop, of `width` by `height` pixels.
+    pub fn with_desktop_size(exec: ExecPdu, width: u16, height: u16, backend: Box<dyn RailBackend>) -> Self {
+        let work_area = ExclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+
+        Self::new(exec, work_area, backend)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == RailState::Ready
+    }
+
+    /// Notifies the backend of the windows created or deleted by a windowing order.
+    pub fn handle_window_order(&mut self, order: &WindowOrder) {
+        if let Some(window_id) = order.created_window() {
+            debug!(window_id, "Remote application window created");
+            self.backend.on_window_created(window_id);
+        } else if let Some(window_id) = order.deleted_window() {
+            debug!(window_id, "Remote application window deleted");
+            self.backend.on_window_deleted(window_id);
+        }
+    }
+
+    fn initialization_sequence(&self) -> Vec<SvcMessage> {
+        let sys_params = [
+            SysParam::HighContrast(HighContrast {
+                flags: DEFAULT_HIGH_CONTRAST_FLAGS,
+                color_scheme: String::new(),
+            }),
+            SysParam::MouseButtonSwap(false),
+            SysParam::KeyboardPref(false),
+            SysParam::DragFullWindows(false),
+            SysParam::KeyboardCues(false),
+            SysParam::WorkArea(self.work_area.clone()),
+        ];
+
+        let mut messages = vec![
+            SvcMessage::from(RailPdu::Handshake(HandshakePdu {
+                build_number: CLIENT_BUILD_NUMBER,
+            })),
+            SvcMessage::from(RailPdu::ClientStatus(ClientStatusPdu {
+                flags: ClientStatusFlags::empty(),
+            })),
+        ];
+        messages.extend(
+            sys_params
+                .into_iter()
+                .map(|param| SvcMessage::from(RailPdu::SysParam(param))),
+        );
+        messages.push(SvcMessage::from(RailPdu::Exec(self.exec.clone())));
+
+        messages
+    }
+}
+
+impl_as_any!(Rail);
+
+impl SvcProcessor for Rail {
+    fn channel_name(&self) -> ChannelName {
+        Self::NAME
+    }
+
+    fn compression_condition(&self) -> CompressionCondition {
+        CompressionCondition::WhenRdpDataIsCompressed
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        let pdu = match RailPdu::decode(&mut ReadCursor::new(payload)) {
+            Ok(pdu) => pdu,
+            Err(e) if matches!(e.kind, DecodeErrorKind::UnsupportedValue { .. }) => {
+                debug!(error = %e, "Ignoring unsupported RAIL order");
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(decode_err!(e)),
+        };
+
+        debug!(?pdu, ?self.state);
+
+        match (self.state, pdu) {
+            (RailState::WaitingForHandshake, RailPdu::Handshake(_) | RailPdu::HandshakeEx(_)) => {
+                self.state = RailState::Ready;
+                return Ok(self.initialization_sequence());
+            }
+            (RailState::Ready, RailPdu::ExecResult(result)) => {
+                if result.exec_result.is_success() {
+                    info!(exe_or_file = %result.exe_or_file, "Remote application launched");
+                } else {
+                    warn!(
+                        exe_or_file = %result.exe_or_file,
+                        exec_result = result.exec_result.0,
+                        raw_result = result.raw_result,
+                        "Remote application launch failed"
+                    );
+                }
+                self.backend.on_exec_result(&result);
+            }
+            (RailState::Ready, RailPdu::MinMaxInfo(info)) => self.backend.on_min_max_info(&info),
+            (RailState::Ready, RailPdu::LocalMoveSize(move_size)) => self.backend.on_local_move_size(&move_size),
+            (RailState::Ready, RailPdu::SysParam(param)) => debug!(?param, "Received server system parameter"),
+            (state, pdu) => warn!(?state, ?pdu, "Unexpected RAIL PDU"),
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+impl SvcClientProcessor for Rail {}
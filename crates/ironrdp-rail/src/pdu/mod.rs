@@ -0,0 +1,908 @@
+//! Remote Programs Virtual Channel Extension PDUs [MS-RDPERP][1] implementation.
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdperp/
+
+mod window_order;
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, unsupported_value_err, Decode, DecodeResult,
+    Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_pdu::utils::{from_utf16_bytes, to_utf16_bytes};
+use ironrdp_pdu::{read_padding, write_padding};
+use ironrdp_svc::SvcEncode;
+
+pub use self::window_order::{decode_window_orders, WindowOrder, WindowOrderFlags};
+
+const TS_RAIL_ORDER_EXEC: u16 = 0x0001;
+const TS_RAIL_ORDER_SYSPARAM: u16 = 0x0003;
+const TS_RAIL_ORDER_HANDSHAKE: u16 = 0x0005;
+const TS_RAIL_ORDER_WINDOWMOVE: u16 = 0x0008;
+const TS_RAIL_ORDER_LOCALMOVESIZE: u16 = 0x0009;
+const TS_RAIL_ORDER_MINMAXINFO: u16 = 0x000A;
+const TS_RAIL_ORDER_CLIENTSTATUS: u16 = 0x000B;
+const TS_RAIL_ORDER_HANDSHAKE_EX: u16 = 0x0013;
+const TS_RAIL_ORDER_EXEC_RESULT: u16 = 0x0080;
+
+const SPI_SETSCREENSAVEACTIVE: u32 = 0x0000_0011;
+const SPI_SETMOUSEBUTTONSWAP: u32 = 0x0000_0021;
+const SPI_SETDRAGFULLWINDOWS: u32 = 0x0000_0025;
+const SPI_SETWORKAREA: u32 = 0x0000_002F;
+const SPI_SETHIGHCONTRAST: u32 = 0x0000_0043;
+const SPI_SETKEYBOARDPREF: u32 = 0x0000_0045;
+const SPI_SETSCREENSAVESECURE: u32 = 0x0000_0077;
+const SPI_SETKEYBOARDCUES: u32 = 0x0000_100B;
+const RAIL_SPI_TASKBARPOS: u32 = 0x0000_F000;
+const RAIL_SPI_DISPLAYCHANGE: u32 = 0x0000_F001;
+
+/// RAIL PDU, preceded by a TS_RAIL_PDU_HEADER
+///
+/// Both directions are covered by this type, as order types are not overlapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RailPdu {
+    Exec(ExecPdu),
+    ExecResult(ExecResultPdu),
+    SysParam(SysParam),
+    Handshake(HandshakePdu),
+    HandshakeEx(HandshakeExPdu),
+    ClientStatus(ClientStatusPdu),
+    WindowMove(WindowMovePdu),
+    LocalMoveSize(LocalMoveSizePdu),
+    MinMaxInfo(MinMaxInfoPdu),
+}
+
+impl RailPdu {
+    const NAME: &'static str = "TS_RAIL_PDU";
+
+    const FIXED_PART_SIZE: usize = 2 /* orderType */ + 2 /* orderLength */;
+
+    fn order_type(&self) -> u16 {
+        match self {
+            Self::Exec(_) => TS_RAIL_ORDER_EXEC,
+            Self::ExecResult(_) => TS_RAIL_ORDER_EXEC_RESULT,
+            Self::SysParam(_) => TS_RAIL_ORDER_SYSPARAM,
+            Self::Handshake(_) => TS_RAIL_ORDER_HANDSHAKE,
+            Self::HandshakeEx(_) => TS_RAIL_ORDER_HANDSHAKE_EX,
+            Self::ClientStatus(_) => TS_RAIL_ORDER_CLIENTSTATUS,
+            Self::WindowMove(_) => TS_RAIL_ORDER_WINDOWMOVE,
+            Self::LocalMoveSize(_) => TS_RAIL_ORDER_LOCALMOVESIZE,
+            Self::MinMaxInfo(_) => TS_RAIL_ORDER_MINMAXINFO,
+        }
+    }
+
+    fn body(&self) -> &dyn Encode {
+        match self {
+            Self::Exec(pdu) => pdu,
+            Self::ExecResult(pdu) => pdu,
+            Self::SysParam(pdu) => pdu,
+            Self::Handshake(pdu) => pdu,
+            Self::HandshakeEx(pdu) => pdu,
+            Self::ClientStatus(pdu) => pdu,
+            Self::WindowMove(pdu) => pdu,
+            Self::LocalMoveSize(pdu) => pdu,
+            Self::MinMaxInfo(pdu) => pdu,
+        }
+    }
+}
+
+impl Encode for RailPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.order_type());
+        dst.write_u16(cast_length!("orderLength", self.size())?);
+        self.body().encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body().size()
+    }
+}
+
+impl SvcEncode for RailPdu {}
+
+impl<'de> Decode<'de> for RailPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let order_type = src.read_u16();
+        let order_length = usize::from(src.read_u16());
+
+        let body_length = order_length
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("orderLength", "smaller than the header"))?;
+        ensure_size!(in: src, size: body_length);
+
+        // The order length is bounding the body, so that trailing fields added by later versions of the
+        // protocol are skipped.
+        let mut body = ReadCursor::new(src.read_slice(body_length));
+
+        let pdu = match order_type {
+            TS_RAIL_ORDER_EXEC => Self::Exec(ExecPdu::decode(&mut body)?),
+            TS_RAIL_ORDER_EXEC_RESULT => Self::ExecResult(ExecResultPdu::decode(&mut body)?),
+            TS_RAIL_ORDER_SYSPARAM => Self::SysParam(SysParam::decode(&mut body)?),
+            TS_RAIL_ORDER_HANDSHAKE => Self::Handshake(HandshakePdu::decode(&mut body)?),
+            TS_RAIL_ORDER_HANDSHAKE_EX => Self::HandshakeEx(HandshakeExPdu::decode(&mut body)?),
+            TS_RAIL_ORDER_CLIENTSTATUS => Self::ClientStatus(ClientStatusPdu::decode(&mut body)?),
+            TS_RAIL_ORDER_WINDOWMOVE => Self::WindowMove(WindowMovePdu::decode(&mut body)?),
+            TS_RAIL_ORDER_LOCALMOVESIZE => Self::LocalMoveSize(LocalMoveSizePdu::decode(&mut body)?),
+            TS_RAIL_ORDER_MINMAXINFO => Self::MinMaxInfo(MinMaxInfoPdu::decode(&mut body)?),
+            _ => return Err(unsupported_value_err!("orderType", format!("{order_type:#06x}"))),
+        };
+
+        Ok(pdu)
+    }
+}
+
+/// Handshake PDU (TS_RAIL_ORDER_HANDSHAKE), sent by both the client and the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakePdu {
+    pub build_number: u32,
+}
+
+impl HandshakePdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_HANDSHAKE";
+
+    const FIXED_PART_SIZE: usize = 4 /* buildNumber */;
+}
+
+impl Encode for HandshakePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.build_number);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for HandshakePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            build_number: src.read_u32(),
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HandshakeExFlags: u32 {
+        const HIDEF = 0x0000_0001;
+        const EXTENDED_SPI_SUPPORTED = 0x0000_0002;
+        const SNAP_ARRANGE_SUPPORTED = 0x0000_0004;
+        const TEXT_SCALE_SUPPORTED = 0x0000_0008;
+        const CARET_BLINK_SUPPORTED = 0x0000_0010;
+        const EXTENDED_SPI_2_SUPPORTED = 0x0000_0020;
+        const EXTENDED_SPI_3_SUPPORTED = 0x0000_0040;
+        const _ = !0;
+    }
+}
+
+/// HandshakeEx PDU (TS_RAIL_ORDER_HANDSHAKE_EX), sent by the server instead of the Handshake PDU when the client
+/// advertised [`RailSupportLevel::HANDSHAKE_EX_SUPPORTED`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeExPdu {
+    pub build_number: u32,
+    pub flags: HandshakeExFlags,
+}
+
+impl HandshakeExPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_HANDSHAKE_EX";
+
+    const FIXED_PART_SIZE: usize = 4 /* buildNumber */ + 4 /* railHandshakeFlags */;
+}
+
+impl Encode for HandshakeExPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.build_number);
+        dst.write_u32(self.flags.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for HandshakeExPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            build_number: src.read_u32(),
+            flags: HandshakeExFlags::from_bits_retain(src.read_u32()),
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ClientStatusFlags: u32 {
+        const ALLOWLOCALMOVESIZE = 0x0000_0001;
+        const AUTORECONNECT = 0x0000_0002;
+        const ZORDER_SYNC = 0x0000_0004;
+        const WINDOW_RESIZE_MARGIN_SUPPORTED = 0x0000_0010;
+        const HIGH_DPI_ICONS_SUPPORTED = 0x0000_0020;
+        const APPBAR_REMOTING_SUPPORTED = 0x0000_0040;
+        const POWER_DISPLAY_REQUEST_SUPPORTED = 0x0000_0080;
+        const BIDIRECTIONAL_CLOAK_SUPPORTED = 0x0000_0200;
+        const _ = !0;
+    }
+}
+
+/// Client Information PDU (TS_RAIL_ORDER_CLIENTSTATUS)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientStatusPdu {
+    pub flags: ClientStatusFlags,
+}
+
+impl ClientStatusPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_CLIENTSTATUS";
+
+    const FIXED_PART_SIZE: usize = 4 /* flags */;
+}
+
+impl Encode for ClientStatusPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.flags.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for ClientStatusPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            flags: ClientStatusFlags::from_bits_retain(src.read_u32()),
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ExecFlags: u16 {
+        const EXPAND_WORKING_DIRECTORY = 0x0001;
+        const TRANSLATE_FILES = 0x0002;
+        const FILE = 0x0004;
+        const EXPAND_ARGUMENTS = 0x0008;
+        const APP_USER_MODEL_ID = 0x0010;
+        const _ = !0;
+    }
+}
+
+/// Client Execute PDU (TS_RAIL_ORDER_EXEC), requesting the server to launch a remote application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecPdu {
+    pub flags: ExecFlags,
+    /// Executable or file to launch, e.g.: `||notepad` for a published application alias.
+    pub exe_or_file: String,
+    pub working_dir: String,
+    pub arguments: String,
+}
+
+impl ExecPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_EXEC";
+
+    const FIXED_PART_SIZE: usize = 2 /* flags */ + 2 /* exeOrFileLength */ + 2 /* workingDirLength */ + 2 /* argumentsLen */;
+
+    const MAX_EXE_OR_FILE_SIZE: usize = 520;
+
+    const MAX_WORKING_DIR_SIZE: usize = 520;
+
+    const MAX_ARGUMENTS_SIZE: usize = 16000;
+}
+
+impl Encode for ExecPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let exe_or_file = to_utf16_bytes(&self.exe_or_file);
+        let working_dir = to_utf16_bytes(&self.working_dir);
+        let arguments = to_utf16_bytes(&self.arguments);
+
+        if exe_or_file.len() > Self::MAX_EXE_OR_FILE_SIZE {
+            return Err(invalid_field_err!("exeOrFile", "too long"));
+        }
+
+        if working_dir.len() > Self::MAX_WORKING_DIR_SIZE {
+            return Err(invalid_field_err!("workingDir", "too long"));
+        }
+
+        if arguments.len() > Self::MAX_ARGUMENTS_SIZE {
+            return Err(invalid_field_err!("arguments", "too long"));
+        }
+
+        dst.write_u16(self.flags.bits());
+        dst.write_u16(cast_length!("exeOrFileLength", exe_or_file.len())?);
+        dst.write_u16(cast_length!("workingDirLength", working_dir.len())?);
+        dst.write_u16(cast_length!("argumentsLen", arguments.len())?);
+        dst.write_slice(&exe_or_file);
+        dst.write_slice(&working_dir);
+        dst.write_slice(&arguments);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + (self.exe_or_file.encode_utf16().count()
+                + self.working_dir.encode_utf16().count()
+                + self.arguments.encode_utf16().count())
+                * 2
+    }
+}
+
+impl<'de> Decode<'de> for ExecPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let flags = ExecFlags::from_bits_retain(src.read_u16());
+        let exe_or_file_length = usize::from(src.read_u16());
+        let working_dir_length = usize::from(src.read_u16());
+        let arguments_length = usize::from(src.read_u16());
+
+        ensure_size!(in: src, size: exe_or_file_length + working_dir_length + arguments_length);
+
+        Ok(Self {
+            flags,
+            exe_or_file: from_utf16_bytes(src.read_slice(exe_or_file_length)),
+            working_dir: from_utf16_bytes(src.read_slice(working_dir_length)),
+            arguments: from_utf16_bytes(src.read_slice(arguments_length)),
+        })
+    }
+}
+
+/// Result of a [`ExecPdu`], as reported by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExecResult(pub u16);
+
+impl ExecResult {
+    pub const OK: Self = Self(0x0000);
+    pub const HOOK_NOT_LOADED: Self = Self(0x0001);
+    pub const DECODE_FAILED: Self = Self(0x0002);
+    pub const NOT_IN_ALLOWLIST: Self = Self(0x0003);
+    pub const FILE_NOT_FOUND: Self = Self(0x0005);
+    pub const FAIL: Self = Self(0x0006);
+    pub const SESSION_LOCKED: Self = Self(0x0007);
+
+    pub fn is_success(self) -> bool {
+        self == Self::OK
+    }
+}
+
+/// Server Execute Result PDU (TS_RAIL_ORDER_EXEC_RESULT)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecResultPdu {
+    pub flags: ExecFlags,
+    pub exec_result: ExecResult,
+    /// Raw result of the operation, e.g.: the Win32 error code when launching the application failed.
+    pub raw_result: u32,
+    pub exe_or_file: String,
+}
+
+impl ExecResultPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_EXEC_RESULT";
+
+    const FIXED_PART_SIZE: usize = 2 /* flags */ + 2 /* execResult */ + 4 /* rawResult */ + 2 /* padding */ + 2 /* exeOrFileLength */;
+}
+
+impl Encode for ExecResultPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        let exe_or_file = to_utf16_bytes(&self.exe_or_file);
+
+        dst.write_u16(self.flags.bits());
+        dst.write_u16(self.exec_result.0);
+        dst.write_u32(self.raw_result);
+        write_padding!(dst, 2);
+        dst.write_u16(cast_length!("exeOrFileLength", exe_or_file.len())?);
+        dst.write_slice(&exe_or_file);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.exe_or_file.encode_utf16().count() * 2
+    }
+}
+
+impl<'de> Decode<'de> for ExecResultPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let flags = ExecFlags::from_bits_retain(src.read_u16());
+        let exec_result = ExecResult(src.read_u16());
+        let raw_result = src.read_u32();
+        read_padding!(src, 2);
+        let exe_or_file_length = usize::from(src.read_u16());
+
+        ensure_size!(in: src, size: exe_or_file_length);
+        let exe_or_file = from_utf16_bytes(src.read_slice(exe_or_file_length));
+
+        Ok(Self {
+            flags,
+            exec_result,
+            raw_result,
+            exe_or_file,
+        })
+    }
+}
+
+/// High contrast system parameter (TS_HIGHCONTRAST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighContrast {
+    pub flags: u32,
+    pub color_scheme: String,
+}
+
+impl HighContrast {
+    const FIXED_PART_SIZE: usize = 4 /* flags */ + 4 /* colorSchemeLength */;
+
+    fn color_scheme_size(&self) -> usize {
+        // The color scheme is null-terminated.
+        (self.color_scheme.encode_utf16().count() + 1) * 2
+    }
+}
+
+/// System parameter update (TS_RAIL_ORDER_SYSPARAM)
+///
+/// The client is informing the server of its local system parameters, and the server is informing the client of
+/// the parameters it can't change remotely (screen saver related).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SysParam {
+    WorkArea(ExclusiveRectangle),
+    DisplayChange(ExclusiveRectangle),
+    TaskbarPos(ExclusiveRectangle),
+    HighContrast(HighContrast),
+    MouseButtonSwap(bool),
+    KeyboardPref(bool),
+    DragFullWindows(bool),
+    KeyboardCues(bool),
+    ScreenSaveActive(bool),
+    ScreenSaveSecure(bool),
+    /// System parameter not modeled by IronRDP, kept as raw bytes.
+    Unknown {
+        system_param: u32,
+        body: Vec<u8>,
+    },
+}
+
+impl SysParam {
+    const NAME: &'static str = "TS_RAIL_ORDER_SYSPARAM";
+
+    const FIXED_PART_SIZE: usize = 4 /* systemParam */;
+
+    fn system_param(&self) -> u32 {
+        match self {
+            Self::WorkArea(_) => SPI_SETWORKAREA,
+            Self::DisplayChange(_) => RAIL_SPI_DISPLAYCHANGE,
+            Self::TaskbarPos(_) => RAIL_SPI_TASKBARPOS,
+            Self::HighContrast(_) => SPI_SETHIGHCONTRAST,
+            Self::MouseButtonSwap(_) => SPI_SETMOUSEBUTTONSWAP,
+            Self::KeyboardPref(_) => SPI_SETKEYBOARDPREF,
+            Self::DragFullWindows(_) => SPI_SETDRAGFULLWINDOWS,
+            Self::KeyboardCues(_) => SPI_SETKEYBOARDCUES,
+            Self::ScreenSaveActive(_) => SPI_SETSCREENSAVEACTIVE,
+            Self::ScreenSaveSecure(_) => SPI_SETSCREENSAVESECURE,
+            Self::Unknown { system_param, .. } => *system_param,
+        }
+    }
+}
+
+impl Encode for SysParam {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.system_param());
+
+        match self {
+            Self::WorkArea(rect) | Self::DisplayChange(rect) | Self::TaskbarPos(rect) => rect.encode(dst)?,
+            Self::HighContrast(high_contrast) => {
+                dst.write_u32(high_contrast.flags);
+                dst.write_u32(cast_length!("colorSchemeLength", high_contrast.color_scheme_size())?);
+                dst.write_slice(&to_utf16_bytes(&high_contrast.color_scheme));
+                dst.write_u16(0);
+            }
+            Self::MouseButtonSwap(value)
+            | Self::KeyboardPref(value)
+            | Self::DragFullWindows(value)
+            | Self::KeyboardCues(value)
+            | Self::ScreenSaveActive(value)
+            | Self::ScreenSaveSecure(value) => dst.write_u8(u8::from(*value)),
+            Self::Unknown { body, .. } => dst.write_slice(body),
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        let body_size = match self {
+            Self::WorkArea(rect) | Self::DisplayChange(rect) | Self::TaskbarPos(rect) => rect.size(),
+            Self::HighContrast(high_contrast) => HighContrast::FIXED_PART_SIZE + high_contrast.color_scheme_size(),
+            Self::MouseButtonSwap(_)
+            | Self::KeyboardPref(_)
+            | Self::DragFullWindows(_)
+            | Self::KeyboardCues(_)
+            | Self::ScreenSaveActive(_)
+            | Self::ScreenSaveSecure(_) => 1,
+            Self::Unknown { body, .. } => body.len(),
+        };
+
+        Self::FIXED_PART_SIZE + body_size
+    }
+}
+
+impl<'de> Decode<'de> for SysParam {
+    /// Decodes a system parameter update, whose body is spanning the remaining bytes of `src`.
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let system_param = src.read_u32();
+
+        let read_bool = |src: &mut ReadCursor<'de>| -> DecodeResult<bool> {
+            ensure_size!(ctx: Self::NAME, in: src, size: 1);
+            Ok(src.read_u8() != 0)
+        };
+
+        let param = match system_param {
+            SPI_SETWORKAREA => Self::WorkArea(ExclusiveRectangle::decode(src)?),
+            RAIL_SPI_DISPLAYCHANGE => Self::DisplayChange(ExclusiveRectangle::decode(src)?),
+            RAIL_SPI_TASKBARPOS => Self::TaskbarPos(ExclusiveRectangle::decode(src)?),
+            SPI_SETHIGHCONTRAST => {
+                ensure_size!(in: src, size: HighContrast::FIXED_PART_SIZE);
+                let flags = src.read_u32();
+                let color_scheme_length: usize = cast_length!("colorSchemeLength", src.read_u32())?;
+                ensure_size!(in: src, size: color_scheme_length);
+                let color_scheme = from_utf16_bytes(src.read_slice(color_scheme_length))
+                    .trim_end_matches('\0')
+                    .to_owned();
+
+                Self::HighContrast(HighContrast { flags, color_scheme })
+            }
+            SPI_SETMOUSEBUTTONSWAP => Self::MouseButtonSwap(read_bool(src)?),
+            SPI_SETKEYBOARDPREF => Self::KeyboardPref(read_bool(src)?),
+            SPI_SETDRAGFULLWINDOWS => Self::DragFullWindows(read_bool(src)?),
+            SPI_SETKEYBOARDCUES => Self::KeyboardCues(read_bool(src)?),
+            SPI_SETSCREENSAVEACTIVE => Self::ScreenSaveActive(read_bool(src)?),
+            SPI_SETSCREENSAVESECURE => Self::ScreenSaveSecure(read_bool(src)?),
+            _ => Self::Unknown {
+                system_param,
+                body: src.read_remaining().to_vec(),
+            },
+        };
+
+        Ok(param)
+    }
+}
+
+/// Window Move PDU (TS_RAIL_ORDER_WINDOWMOVE), sent by the client once a local move or resize is completed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowMovePdu {
+    pub window_id: u32,
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+impl WindowMovePdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_WINDOWMOVE";
+
+    const FIXED_PART_SIZE: usize = 4 /* windowId */ + 2 * 4 /* left, top, right, bottom */;
+}
+
+impl Encode for WindowMovePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.window_id);
+        dst.write_i16(self.left);
+        dst.write_i16(self.top);
+        dst.write_i16(self.right);
+        dst.write_i16(self.bottom);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for WindowMovePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            window_id: src.read_u32(),
+            left: src.read_i16(),
+            top: src.read_i16(),
+            right: src.read_i16(),
+            bottom: src.read_i16(),
+        })
+    }
+}
+
+/// Server Move/Size Start and End PDU (TS_RAIL_ORDER_LOCALMOVESIZE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalMoveSizePdu {
+    pub window_id: u32,
+    pub is_move_size_start: bool,
+    pub move_size_type: u16,
+    pub pos_x: i16,
+    pub pos_y: i16,
+}
+
+impl LocalMoveSizePdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_LOCALMOVESIZE";
+
+    const FIXED_PART_SIZE: usize = 4 /* windowId */ + 2 /* isMoveSizeStart */ + 2 /* moveSizeType */ + 2 /* posX */ + 2 /* posY */;
+}
+
+impl Encode for LocalMoveSizePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.window_id);
+        dst.write_u16(u16::from(self.is_move_size_start));
+        dst.write_u16(self.move_size_type);
+        dst.write_i16(self.pos_x);
+        dst.write_i16(self.pos_y);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for LocalMoveSizePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            window_id: src.read_u32(),
+            is_move_size_start: src.read_u16() != 0,
+            move_size_type: src.read_u16(),
+            pos_x: src.read_i16(),
+            pos_y: src.read_i16(),
+        })
+    }
+}
+
+/// Server Min Max Info PDU (TS_RAIL_ORDER_MINMAXINFO), describing the constraints on the size of a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinMaxInfoPdu {
+    pub window_id: u32,
+    pub max_width: i16,
+    pub max_height: i16,
+    pub max_pos_x: i16,
+    pub max_pos_y: i16,
+    pub min_track_width: i16,
+    pub min_track_height: i16,
+    pub max_track_width: i16,
+    pub max_track_height: i16,
+}
+
+impl MinMaxInfoPdu {
+    const NAME: &'static str = "TS_RAIL_ORDER_MINMAXINFO";
+
+    const FIXED_PART_SIZE: usize = 4 /* windowId */ + 2 * 8 /* sizes and positions */;
+}
+
+impl Encode for MinMaxInfoPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.window_id);
+        dst.write_i16(self.max_width);
+        dst.write_i16(self.max_height);
+        dst.write_i16(self.max_pos_x);
+        dst.write_i16(self.max_pos_y);
+        dst.write_i16(self.min_track_width);
+        dst.write_i16(self.min_track_height);
+        dst.write_i16(self.max_track_width);
+        dst.write_i16(self.max_track_height);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for MinMaxInfoPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            window_id: src.read_u32(),
+            max_width: src.read_i16(),
+            max_height: src.read_i16(),
+            max_pos_x: src.read_i16(),
+            max_pos_y: src.read_i16(),
+            min_track_width: src.read_i16(),
+            min_track_height: src.read_i16(),
+            max_track_width: src.read_i16(),
+            max_track_height: src.read_i16(),
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RailSupportLevel: u32 {
+        const SUPPORTED = 0x0000_0001;
+        const DOCKED_LANGBAR_SUPPORTED = 0x0000_0002;
+        const SHELL_INTEGRATION_SUPPORTED = 0x0000_0004;
+        const LANGUAGE_IME_SYNC_SUPPORTED = 0x0000_0008;
+        const SERVER_TO_CLIENT_IME_SYNC_SUPPORTED = 0x0000_0010;
+        const HIDE_MINIMIZED_APPS_SUPPORTED = 0x0000_0020;
+        const WINDOW_CLOAKING_SUPPORTED = 0x0000_0040;
+        const HANDSHAKE_EX_SUPPORTED = 0x0000_0080;
+        const _ = !0;
+    }
+}
+
+/// Remote Programs Capability Set (TS_RAIL_CAPABILITYSET), without the capability set header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RailCapabilitySet {
+    pub support_level: RailSupportLevel,
+}
+
+impl RailCapabilitySet {
+    const NAME: &'static str = "TS_RAIL_CAPABILITYSET";
+
+    const FIXED_PART_SIZE: usize = 4 /* RailSupportLevel */;
+}
+
+impl Encode for RailCapabilitySet {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.support_level.bits());
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for RailCapabilitySet {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            support_level: RailSupportLevel::from_bits_retain(src.read_u32()),
+        })
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSupportLevel {
+    NotSupported = 0,
+    Supported = 1,
+    SupportedEx = 2,
+}
+
+/// Window List Capability Set (TS_WINDOW_CAPABILITYSET), without the capability set header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowListCapabilitySet {
+    pub support_level: WindowSupportLevel,
+    pub num_icon_caches: u8,
+    pub num_icon_cache_entries: u16,
+}
+
+impl WindowListCapabilitySet {
+    const NAME: &'static str = "TS_WINDOW_CAPABILITYSET";
+
+    const FIXED_PART_SIZE: usize = 4 /* WndSupportLevel */ + 1 /* NumIconCaches */ + 2 /* NumIconCacheEntries */;
+}
+
+impl Encode for WindowListCapabilitySet {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.support_level as u32);
+        dst.write_u8(self.num_icon_caches);
+        dst.write_u16(self.num_icon_cache_entries);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for WindowListCapabilitySet {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let support_level = match src.read_u32() {
+            0 => WindowSupportLevel::NotSupported,
+            1 => WindowSupportLevel::Supported,
+            2 => WindowSupportLevel::SupportedEx,
+            _ => return Err(invalid_field_err!("WndSupportLevel", "unknown window support level")),
+        };
+
+        Ok(Self {
+            support_level,
+            num_icon_caches: src.read_u8(),
+            num_icon_cache_entries: src.read_u16(),
+        })
+    }
+}
@@ -0,0 +1,130 @@
+//! Windowing Alternate Secondary Drawing Orders, received in the orders updates of the graphics output channel
+//!
+//! Only the common header of the orders is decoded for now, which is enough to track the lifetime of the windows.
+
+use bitflags::bitflags;
+use ironrdp_core::{ensure_fixed_part_size, ensure_size, invalid_field_err, Decode, DecodeResult, ReadCursor};
+use tracing::debug;
+
+const TS_SECONDARY: u8 = 0x02;
+const TS_ALTSEC_WINDOW: u8 = 0x0B;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WindowOrderFlags: u32 {
+        const TYPE_WINDOW = 0x0100_0000;
+        const TYPE_NOTIFY = 0x0200_0000;
+        const TYPE_DESKTOP = 0x0400_0000;
+        const STATE_NEW = 0x1000_0000;
+        const STATE_DELETED = 0x2000_0000;
+        const ICON = 0x4000_0000;
+        const CACHED_ICON = 0x8000_0000;
+        const _ = !0;
+    }
+}
+
+/// Header of a windowing order (TS_WINDOW_ORDER_HEADER), followed by the target window ID if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowOrder {
+    pub fields_present: WindowOrderFlags,
+    /// Absent from desktop orders.
+    pub window_id: Option<u32>,
+}
+
+impl WindowOrder {
+    const NAME: &'static str = "TS_WINDOW_ORDER_HEADER";
+
+    const FIXED_PART_SIZE: usize = 1 /* controlFlags */ + 2 /* OrderSize */ + 4 /* FieldsPresentFlags */;
+
+    /// Returns the ID of the window created by this order, if any.
+    pub fn created_window(&self) -> Option<u32> {
+        if self.is_window_info() && self.fields_present.contains(WindowOrderFlags::STATE_NEW) {
+            self.window_id
+        } else {
+            None
+        }
+    }
+
+    /// Returns the ID of the window deleted by this order, if any.
+    pub fn deleted_window(&self) -> Option<u32> {
+        if self.is_window_info() && self.fields_present.contains(WindowOrderFlags::STATE_DELETED) {
+            self.window_id
+        } else {
+            None
+        }
+    }
+
+    /// Icon orders are also targeting windows, but are not conveying any state change.
+    fn is_window_info(&self) -> bool {
+        self.fields_present.contains(WindowOrderFlags::TYPE_WINDOW)
+            && !self
+                .fields_present
+                .intersects(WindowOrderFlags::ICON | WindowOrderFlags::CACHED_ICON)
+    }
+
+    fn is_window_order(control_flags: u8) -> bool {
+        control_flags & 0x03 == TS_SECONDARY && control_flags >> 2 == TS_ALTSEC_WINDOW
+    }
+}
+
+impl<'de> Decode<'de> for WindowOrder {
+    /// Decodes a whole windowing order, skipping the fields following the header.
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let control_flags = src.read_u8();
+
+        if !Self::is_window_order(control_flags) {
+            return Err(invalid_field_err!("controlFlags", "not a windowing order"));
+        }
+
+        // The order size is covering the whole order, including the control flags.
+        let order_size = usize::from(src.read_u16());
+        let fields_present = WindowOrderFlags::from_bits_retain(src.read_u32());
+
+        let remaining_size = order_size
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("OrderSize", "smaller than the header"))?;
+        ensure_size!(in: src, size: remaining_size);
+        let mut order = ReadCursor::new(src.read_slice(remaining_size));
+
+        let window_id = if fields_present.intersects(WindowOrderFlags::TYPE_WINDOW | WindowOrderFlags::TYPE_NOTIFY) {
+            ensure_size!(in: order, size: 4);
+            Some(order.read_u32())
+        } else {
+            None
+        };
+
+        Ok(Self {
+            fields_present,
+            window_id,
+        })
+    }
+}
+
+/// Decodes the windowing orders of an orders update (TS_FP_UPDATE_ORDERS).
+///
+/// The length of the other drawing orders can't be known without decoding them, so decoding stops at the first
+/// order which is not a windowing order.
+pub fn decode_window_orders(src: &[u8]) -> DecodeResult<Vec<WindowOrder>> {
+    let mut src = ReadCursor::new(src);
+
+    ensure_size!(ctx: "TS_FP_UPDATE_ORDERS", in: src, size: 2);
+    let number_orders = src.read_u16();
+
+    let mut orders = Vec::new();
+
+    for _ in 0..number_orders {
+        ensure_size!(ctx: WindowOrder::NAME, in: src, size: 1);
+
+        let control_flags = src.peek_u8();
+        if !WindowOrder::is_window_order(control_flags) {
+            debug!(control_flags, "Stopping at unsupported drawing order");
+            break;
+        }
+
+        orders.push(WindowOrder::decode(&mut src)?);
+    }
+
+    Ok(orders)
+}
@@ -1,6 +1,8 @@
 #![doc = include_str!("../README.md")]
 #![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
 
+mod snapshot;
+
 use core::fmt;
 
 use der::asn1::OctetString;
@@ -9,11 +11,20 @@ use der::asn1::OctetString;
 #[rustfmt::skip] // do not re-order this pub use
 pub use der;
 
+pub use self::snapshot::{ConnectionSnapshot, ResumeDecision};
+
 pub const BASE_VERSION: u64 = 3389;
 pub const VERSION_1: u64 = BASE_VERSION + 1;
 
 pub const GENERAL_ERROR_CODE: u16 = 1;
 
+// Registry of the bits of `RDCleanPathPdu::capabilities`. The bits not listed here are reserved, and must be ignored.
+
+/// Reserved for the override of the TLS SNI (Server Name Indication) sent by the proxy to the RDP server.
+pub const CAPABILITY_SNI_OVERRIDE: u64 = 1 << 0;
+/// Reserved for the compression of the X.224 connection PDUs.
+pub const CAPABILITY_X224_COMPRESSION: u64 = 1 << 1;
+
 #[derive(Clone, Debug, Eq, PartialEq, der::Sequence)]
 #[asn1(tag_mode = "EXPLICIT")]
 pub struct RDCleanPathErr {
@@ -95,6 +106,14 @@ pub struct RDCleanPathPdu {
     /// Sent from proxy to client only.
     #[asn1(context_specific = "9", optional = "true")]
     pub server_addr: Option<String>,
+    /// Bitfield of the capabilities (`CAPABILITY_*` constants) offered by the client in the request, and of those
+    /// supported by both ends in the proxy response.
+    ///
+    /// The implementations predating this field fail to decode the PDUs where it is present, so the client must
+    /// only offer capabilities to a proxy known to support them, and the proxy only answers with this field when
+    /// it was present in the request (see [`RDCleanPathPdu::negotiate_capabilities`]).
+    #[asn1(context_specific = "10", optional = "true")]
+    pub capabilities: Option<u64>,
 }
 
 impl Default for RDCleanPathPdu {
@@ -109,6 +128,7 @@ impl Default for RDCleanPathPdu {
             x224_connection_pdu: None,
             server_cert_chain: None,
             server_addr: None,
+            capabilities: None,
         }
     }
 }
@@ -154,8 +174,9 @@ impl RDCleanPathPdu {
         };
 
         match der::asn1::ContextSpecific::<u64>::decode_explicit(&mut slice_reader, der::TagNumber::N0) {
-            Ok(Some(version)) if version.value == VERSION_1 => DetectionResult::Detected {
-                version: VERSION_1,
+            // The newer versions are detected too, the caller deciding whether they are supported.
+            Ok(Some(version)) if version.value >= VERSION_1 => DetectionResult::Detected {
+                version: version.value,
                 total_length,
             },
             Ok(Some(_)) => DetectionResult::Failed,
@@ -167,6 +188,27 @@ impl RDCleanPathPdu {
         }
     }
 
+    /// Adds the `capability` bits to the capabilities of the PDU.
+    #[must_use]
+    pub fn with_capability(mut self, capability: u64) -> Self {
+        self.capabilities = Some(self.capabilities.unwrap_or(0) | capability);
+        self
+    }
+
+    /// Returns `true` if all the `capability` bits are set in the capabilities of the PDU.
+    pub fn supports(&self, capability: u64) -> bool {
+        self.capabilities.unwrap_or(0) & capability == capability
+    }
+
+    /// Returns the capabilities of this PDU which are also in `supported`, or `None` if the PDU has no capabilities
+    /// field.
+    ///
+    /// The proxy answers a request with the capabilities negotiated from the request, while the client negotiates
+    /// the capabilities of the response with those it offered, ignoring the capabilities it did not offer.
+    pub fn negotiate_capabilities(&self, supported: u64) -> Option<u64> {
+        self.capabilities.map(|capabilities| capabilities & supported)
+    }
+
     pub fn into_enum(self) -> Result<RDCleanPath, MissingRDCleanPathField> {
         RDCleanPath::try_from(self)
     }
@@ -272,11 +314,13 @@ pub enum RDCleanPath {
         server_auth: Option<String>,
         preconnection_blob: Option<String>,
         x224_connection_request: OctetString,
+        capabilities: Option<u64>,
     },
     Response {
         x224_connection_response: OctetString,
         server_cert_chain: Vec<OctetString>,
         server_addr: String,
+        capabilities: Option<u64>,
     },
     Err(RDCleanPathErr),
 }
@@ -285,6 +329,25 @@ impl RDCleanPath {
     pub fn into_pdu(self) -> RDCleanPathPdu {
         RDCleanPathPdu::from(self)
     }
+
+    /// Same as [`RDCleanPathPdu::with_capability`]. Errors have no capabilities, and are returned as-is.
+    #[must_use]
+    pub fn with_capability(mut self, capability: u64) -> Self {
+        if let Self::Request { capabilities, .. } | Self::Response { capabilities, .. } = &mut self {
+            *capabilities = Some(capabilities.unwrap_or(0) | capability);
+        }
+        self
+    }
+
+    /// Same as [`RDCleanPathPdu::supports`]. Errors have no capabilities.
+    pub fn supports(&self, capability: u64) -> bool {
+        match self {
+            Self::Request { capabilities, .. } | Self::Response { capabilities, .. } => {
+                capabilities.unwrap_or(0) & capability == capability
+            }
+            Self::Err(_) => capability == 0,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -311,6 +374,7 @@ impl TryFrom<RDCleanPathPdu> for RDCleanPath {
                 x224_connection_request: pdu
                     .x224_connection_pdu
                     .ok_or(MissingRDCleanPathField("x224_connection_pdu"))?,
+                capabilities: pdu.capabilities,
             }
         } else if let Some(server_addr) = pdu.server_addr {
             Self::Response {
@@ -321,6 +385,7 @@ impl TryFrom<RDCleanPathPdu> for RDCleanPath {
                     .server_cert_chain
                     .ok_or(MissingRDCleanPathField("server_cert_chain"))?,
                 server_addr,
+                capabilities: pdu.capabilities,
             }
         } else {
             Self::Err(pdu.error.ok_or(MissingRDCleanPathField("error"))?)
@@ -339,6 +404,7 @@ impl From<RDCleanPath> for RDCleanPathPdu {
                 server_auth,
                 preconnection_blob,
                 x224_connection_request,
+                capabilities,
             } => Self {
                 version: VERSION_1,
                 destination: Some(destination),
@@ -346,17 +412,20 @@ impl From<RDCleanPath> for RDCleanPathPdu {
                 server_auth,
                 preconnection_blob,
                 x224_connection_pdu: Some(x224_connection_request),
+                capabilities,
                 ..Default::default()
             },
             RDCleanPath::Response {
                 x224_connection_response,
                 server_cert_chain,
                 server_addr,
+                capabilities,
             } => Self {
                 version: VERSION_1,
                 x224_connection_pdu: Some(x224_connection_response),
                 server_cert_chain: Some(server_cert_chain),
                 server_addr: Some(server_addr),
+                capabilities,
                 ..Default::default()
             },
             RDCleanPath::Err(error) => Self {
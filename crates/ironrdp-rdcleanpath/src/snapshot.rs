@@ -0,0 +1,79 @@
+use der::asn1::OctetString;
+
+use crate::VERSION_1;
+
+/// Outcome of a successful RDCleanPath exchange, which may be cached by the client to speed up reconnections.
+///
+/// The RDCleanPath request is still sent on reconnection, as the proxy needs it to reach the RDP server, but the
+/// server certificate chain does not need to be validated again as long as the proxy sends the cached one.
+#[derive(Clone, Debug, Eq, PartialEq, der::Sequence)]
+#[asn1(tag_mode = "EXPLICIT")]
+pub struct ConnectionSnapshot {
+    /// ConnectionSnapshot version.
+    #[asn1(context_specific = "0")]
+    pub version: u64,
+    /// The RDP server address requested by the client.
+    #[asn1(context_specific = "1")]
+    pub destination: String,
+    /// IPv4 or IPv6 address of the server, as resolved by the proxy.
+    #[asn1(context_specific = "2")]
+    pub server_addr: String,
+    /// The RDP server TLS chain, as sent by the proxy.
+    #[asn1(context_specific = "3")]
+    pub server_cert_chain: Vec<OctetString>,
+}
+
+/// Whether a [`ConnectionSnapshot`] can be reused for a connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResumeDecision {
+    /// The proxy sent the cached certificate chain, which is trusted without being validated again.
+    Resume,
+    /// The snapshot was taken for another destination, and must be discarded.
+    DestinationChanged,
+    /// The proxy sent another certificate chain, which must be validated from scratch.
+    CertificateChainChanged,
+}
+
+impl ConnectionSnapshot {
+    pub fn new(destination: String, server_addr: String, server_cert_chain: Vec<OctetString>) -> Self {
+        Self {
+            version: VERSION_1,
+            destination,
+            server_addr,
+            server_cert_chain,
+        }
+    }
+
+    /// Decodes a snapshot previously produced by [`ConnectionSnapshot::to_der`].
+    pub fn from_der(src: &[u8]) -> der::Result<Self> {
+        let snapshot: Self = der::Decode::from_der(src)?;
+
+        if snapshot.version != VERSION_1 {
+            return Err(der::Tag::Integer.value_error());
+        }
+
+        Ok(snapshot)
+    }
+
+    pub fn to_der(&self) -> der::Result<Vec<u8>> {
+        der::Encode::to_der(self)
+    }
+
+    /// Returns `true` if the snapshot was taken for this destination.
+    ///
+    /// Snapshots taken for another destination must not be reused.
+    pub fn matches_destination(&self, destination: &str) -> bool {
+        self.destination == destination
+    }
+
+    /// Decides whether the snapshot can be reused, given the certificate chain sent by the proxy in its response.
+    pub fn resume_decision(&self, destination: &str, server_cert_chain: &[OctetString]) -> ResumeDecision {
+        if !self.matches_destination(destination) {
+            ResumeDecision::DestinationChanged
+        } else if self.server_cert_chain != server_cert_chain {
+            ResumeDecision::CertificateChainChanged
+        } else {
+            ResumeDecision::Resume
+        }
+    }
+}
@@ -7,8 +7,9 @@ use ironrdp_core::impl_as_any;
 use ironrdp_pdu::{encode_err, PduResult};
 use ironrdp_rdpdr::pdu::efs::*;
 use ironrdp_rdpdr::pdu::esc::{ScardCall, ScardIoCtlCode};
+use ironrdp_rdpdr::pdu::esp::PortIoRequest;
 use ironrdp_rdpdr::pdu::RdpdrPdu;
-use ironrdp_rdpdr::RdpdrBackend;
+use ironrdp_rdpdr::{DrivePolicy, RdpdrBackend};
 use ironrdp_svc::SvcMessage;
 use nix::dir::{Dir, OwningIter};
 
@@ -19,6 +20,10 @@ pub struct NixRdpdrBackend {
     file_map: std::collections::HashMap<u32, std::fs::File>,
     file_path_map: std::collections::HashMap<u32, String>,
     file_dir_map: std::collections::HashMap<u32, OwningIter>,
+    /// Drives inside which the symbolic links must not be followed.
+    no_symlinks_drives: std::collections::HashSet<u32>,
+    /// Local directories backing the drives added with [`NixRdpdrBackend::add_drive`].
+    drive_roots: std::collections::HashMap<u32, String>,
 }
 
 impl NixRdpdrBackend {
@@ -28,6 +33,23 @@ impl NixRdpdrBackend {
             ..Default::default()
         }
     }
+
+    /// Backs the drive `device_id` with the local directory `root`, instead of the base directory of the backend.
+    pub fn add_drive(&mut self, device_id: u32, root: String) {
+        let root = root.trim_end_matches('/').to_owned();
+        self.drive_roots.insert(device_id, root);
+    }
+
+    /// Forgets the local directory of the drive `device_id`, once the drive is removed.
+    pub fn remove_drive(&mut self, device_id: u32) {
+        self.drive_roots.remove(&device_id);
+        self.no_symlinks_drives.remove(&device_id);
+    }
+
+    /// Returns the local directory the paths of the drive `device_id` are relative to.
+    fn drive_root(&self, device_id: u32) -> &str {
+        self.drive_roots.get(&device_id).map_or(&self.file_base, String::as_str)
+    }
 }
 
 impl_as_any!(NixRdpdrBackend);
@@ -39,6 +61,14 @@ impl RdpdrBackend for NixRdpdrBackend {
     fn handle_scard_call(&mut self, _req: DeviceControlRequest<ScardIoCtlCode>, _call: ScardCall) -> PduResult<()> {
         Ok(())
     }
+    fn set_drive_policy(&mut self, device_id: u32, policy: DrivePolicy) {
+        if policy.follow_symlinks {
+            self.no_symlinks_drives.remove(&device_id);
+        } else {
+            self.no_symlinks_drives.insert(device_id);
+        }
+    }
+
     fn handle_drive_io_request(&mut self, req: ServerDriveIoRequest) -> PduResult<Vec<SvcMessage>> {
         debug!("handle_drive_io_request:{:?}", req);
         match req {
@@ -68,6 +98,18 @@ impl RdpdrBackend for NixRdpdrBackend {
             }
         }
     }
+
+    fn handle_port_request(&mut self, req: PortIoRequest) -> PduResult<Vec<SvcMessage>> {
+        // TODO: serial and parallel ports
+        Ok(vec![SvcMessage::from(req.error_response(NtStatus::NOT_SUPPORTED))])
+    }
+
+    fn close(&mut self) {
+        // Dropping the handles closes the files and directories left open by the server.
+        self.file_map.clear();
+        self.file_path_map.clear();
+        self.file_dir_map.clear();
+    }
 }
 
 pub(crate) fn write_device(backend: &mut NixRdpdrBackend, req_inner: DeviceWriteRequest) -> PduResult<Vec<SvcMessage>> {
@@ -380,7 +422,7 @@ pub(crate) fn set_information(
         Some(file) => {
             match &req_inner.set_buffer {
                 FileInformationClass::Rename(info) => {
-                    let mut to = backend.file_base.clone();
+                    let mut to = backend.drive_root(req_inner.device_io_request.device_id).to_owned();
                     to.push_str(&info.file_name.replace('\\', "/"));
                     if let Err(error) = std::fs::rename(file, to) {
                         warn!(?error, "Rename file error");
@@ -551,7 +593,7 @@ pub(crate) fn query_directory(
             let mut find_file_name = None;
             if req_inner.initial_query > 0 {
                 if req_inner.path.ends_with('*') {
-                    let mut parent = backend.file_base.clone();
+                    let mut parent = backend.drive_root(req_inner.device_io_request.device_id).to_owned();
                     let query_path = req_inner.path.replace('\\', "/");
                     let len = query_path.len();
                     // path ends with *, so its len > 0
@@ -577,7 +619,7 @@ pub(crate) fn query_directory(
                         backend.file_dir_map.insert(req_inner.device_io_request.file_id, iter);
                     }
                 } else {
-                    let mut full_path = backend.file_base.clone();
+                    let mut full_path = backend.drive_root(req_inner.device_io_request.device_id).to_owned();
                     let query_path = req_inner.path.replace('\\', "/");
                     full_path.push_str(&query_path);
                     find_file_name = Some(full_path);
@@ -650,8 +692,23 @@ pub(crate) fn create_drive(
 ) -> PduResult<Vec<SvcMessage>> {
     let file_id = backend.file_id;
     backend.file_id += 1;
-    let mut path = String::from(backend.file_base.as_str());
+    let root = backend.drive_root(req_inner.device_io_request.device_id);
+    let mut path = root.to_owned();
     path.push_str(&req_inner.path.replace('\\', "/"));
+    if backend
+        .no_symlinks_drives
+        .contains(&req_inner.device_io_request.device_id)
+        && traverses_symlink(root, &req_inner.path)
+    {
+        warn!("Attempt to follow a symbolic link, path:{}", path);
+        let io_response = DeviceIoResponse::new(req_inner.device_io_request, NtStatus::ACCESS_DENIED);
+        let res = RdpdrPdu::DeviceCreateResponse(DeviceCreateResponse {
+            device_io_reply: io_response,
+            file_id,
+            information: Information::empty(),
+        });
+        return Ok(vec![SvcMessage::from(res)]);
+    }
     // first process directory
     match std::fs::metadata(&path) {
         Ok(meta) => {
@@ -765,6 +822,18 @@ pub(crate) fn create_drive(
     }
 }
 
+/// Returns `true` if any existing component of the normalized drive `path` is a symbolic link.
+fn traverses_symlink(file_base: &str, path: &str) -> bool {
+    let mut current = std::path::PathBuf::from(file_base);
+
+    path.split('\\')
+        .filter(|component| !component.is_empty())
+        .any(|component| {
+            current.push(component);
+            std::fs::symlink_metadata(&current).is_ok_and(|meta| meta.file_type().is_symlink())
+        })
+}
+
 pub(crate) fn process_dependent_file(
     backend: &mut NixRdpdrBackend,
     request: DeviceIoRequest,
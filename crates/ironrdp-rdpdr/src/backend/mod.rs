@@ -8,10 +8,38 @@ use ironrdp_svc::SvcMessage;
 
 use crate::pdu::efs::{DeviceControlRequest, ServerDeviceAnnounceResponse, ServerDriveIoRequest};
 use crate::pdu::esc::{ScardCall, ScardIoCtlCode};
+use crate::pdu::esp::PortIoRequest;
+use crate::policy::DrivePolicy;
 
 /// OS-specific device redirection backend interface.
 pub trait RdpdrBackend: AsAny + fmt::Debug + Send {
     fn handle_server_device_announce_response(&mut self, pdu: ServerDeviceAnnounceResponse) -> PduResult<()>;
+    /// Handles a smart card call, answering it asynchronously with the return structure matching the call.
+    ///
+    /// Transactions (SCARD_IOCTL_BEGINTRANSACTION and SCARD_IOCTL_ENDTRANSACTION) are received as
+    /// [`ScardCall::HCardAndDispositionCall`], and are answered with a `Long_Return`, like SCARD_IOCTL_SETATTRIB.
+    /// A [`ScardCall::GetStatusChangeCall`] may wait forever for a reader state change (see
+    /// [`GetStatusChangeCall::timeout_duration`](crate::pdu::esc::GetStatusChangeCall::timeout_duration)): it must not
+    /// block the backend, and must be cancellable.
     fn handle_scard_call(&mut self, req: DeviceControlRequest<ScardIoCtlCode>, call: ScardCall) -> PduResult<()>;
+    /// Handles a drive I/O request.
+    ///
+    /// The paths of the request were normalized, and the request was checked against the [`DrivePolicy`] of the
+    /// drive (see [`DrivePolicy::read_only`]).
     fn handle_drive_io_request(&mut self, req: ServerDriveIoRequest) -> PduResult<Vec<SvcMessage>>;
+    /// Handles an I/O request targeting a serial or parallel port.
+    ///
+    /// Backends not supporting ports should answer with [`PortIoRequest::error_response`] and STATUS_NOT_SUPPORTED,
+    /// so that the server does not wait for the response forever.
+    fn handle_port_request(&mut self, req: PortIoRequest) -> PduResult<Vec<SvcMessage>>;
+
+    /// Called when a drive is registered, so that the backend can enforce the parts of its policy depending on the
+    /// local file system, such as [`DrivePolicy::follow_symlinks`].
+    fn set_drive_policy(&mut self, _device_id: u32, _policy: DrivePolicy) {}
+
+    /// Called when the RDPDR channel is closed.
+    ///
+    /// Backends should release any resource still associated with the session here (e.g.: open file handles), and
+    /// cancel the pending smart card calls (e.g.: status changes waiting forever).
+    fn close(&mut self) {}
 }
@@ -3,8 +3,9 @@ use ironrdp_pdu::PduResult;
 use ironrdp_svc::SvcMessage;
 
 use super::RdpdrBackend;
-use crate::pdu::efs::{DeviceControlRequest, ServerDeviceAnnounceResponse};
+use crate::pdu::efs::{DeviceControlRequest, NtStatus, ServerDeviceAnnounceResponse};
 use crate::pdu::esc::{ScardCall, ScardIoCtlCode};
+use crate::pdu::esp::PortIoRequest;
 
 #[derive(Debug)]
 pub struct NoopRdpdrBackend;
@@ -21,4 +22,7 @@ impl RdpdrBackend for NoopRdpdrBackend {
     fn handle_drive_io_request(&mut self, _req: crate::pdu::efs::ServerDriveIoRequest) -> PduResult<Vec<SvcMessage>> {
         Ok(Vec::new())
     }
+    fn handle_port_request(&mut self, req: PortIoRequest) -> PduResult<Vec<SvcMessage>> {
+        Ok(vec![SvcMessage::from(req.error_response(NtStatus::NOT_SUPPORTED))])
+    }
 }
@@ -9,23 +9,28 @@
 #[macro_use]
 extern crate tracing;
 
+use std::collections::HashMap;
+
 use ironrdp_core::{decode_cursor, impl_as_any, ReadCursor};
 use ironrdp_pdu::gcc::ChannelName;
 use ironrdp_pdu::{decode_err, pdu_other_err, PduResult};
 use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
 use pdu::efs::{
-    Capabilities, ClientDeviceListAnnounce, ClientNameRequest, ClientNameRequestUnicodeFlag, CoreCapability,
-    CoreCapabilityKind, DeviceControlRequest, DeviceIoRequest, DeviceType, Devices, ServerDeviceAnnounceResponse,
-    VersionAndIdPdu, VersionAndIdPduKind,
+    Capabilities, ClientDeviceListAnnounce, ClientDriveDeviceListRemove, ClientNameRequest,
+    ClientNameRequestUnicodeFlag, CoreCapability, CoreCapabilityKind, DeviceControlRequest, DeviceIoRequest,
+    DeviceType, Devices, ServerDeviceAnnounceResponse, VersionAndIdPdu, VersionAndIdPduKind,
 };
 use pdu::esc::{ScardCall, ScardIoCtlCode};
+use pdu::esp::PortIoRequest;
 use pdu::RdpdrPdu;
 
 pub mod backend;
 pub mod pdu;
+pub mod policy;
 
 pub use self::backend::noop::NoopRdpdrBackend;
 pub use self::backend::RdpdrBackend;
+pub use self::policy::DrivePolicy;
 use crate::pdu::efs::ServerDriveIoRequest;
 
 /// The RDPDR channel as specified in [\[MS-RDPEFS\]].
@@ -48,11 +53,31 @@ pub struct Rdpdr {
     ///
     /// All devices not of the type [`DeviceType::Filesystem`] must be declared here.
     device_list: Devices,
+    /// Policy of each redirected drive, by device ID.
+    drive_policies: HashMap<u32, DrivePolicy>,
+    /// Whether the server allows the Client Drive Device List Remove PDU, see [`Rdpdr::remove_device`].
+    device_remove_allowed: bool,
     backend: Box<dyn RdpdrBackend>,
 }
 
 impl_as_any!(Rdpdr);
 
+/// Serial port to announce to the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialPortConfig {
+    pub device_id: u32,
+    /// Name of the port on the server, such as "COM1", truncated to 7 characters.
+    pub name: String,
+}
+
+/// Parallel port to announce to the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParallelPortConfig {
+    pub device_id: u32,
+    /// Name of the port on the server, such as "LPT1", truncated to 7 characters.
+    pub name: String,
+}
+
 impl Rdpdr {
     pub const NAME: ChannelName = ChannelName::from_static(b"rdpdr\0\0\0");
 
@@ -62,6 +87,8 @@ impl Rdpdr {
             computer_name,
             capabilities: Capabilities::new(),
             device_list: Devices::new(),
+            drive_policies: HashMap::new(),
+            device_remove_allowed: false,
             backend,
         }
     }
@@ -78,24 +105,76 @@ impl Rdpdr {
     /// Callers may also include `initial_drives` to pre-configure the list of drives to announce to the server.
     /// Note that drives do not need to be pre-configured in order to be redirected, a new drive can be announced
     /// at any time during a session by calling [`Self::add_drive`].
+    ///
+    /// The I/O requests targeting a drive are checked against its [`DrivePolicy`] before reaching the backend.
     #[must_use]
-    pub fn with_drives(mut self, initial_drives: Option<Vec<(u32, String)>>) -> Self {
+    pub fn with_drives(mut self, initial_drives: Option<Vec<(u32, String, DrivePolicy)>>) -> Self {
         self.capabilities.add_drive();
         if let Some(initial_drives) = initial_drives {
-            for (device_id, path) in initial_drives {
+            for (device_id, path, policy) in initial_drives {
                 self.device_list.add_drive(device_id, path);
+                self.set_drive_policy(device_id, policy);
             }
         }
         self
     }
 
+    /// Adds port redirection capability, and the serial ports to announce to the server.
+    ///
+    /// The I/O requests targeting the ports are handled by [`RdpdrBackend::handle_port_request`].
+    #[must_use]
+    pub fn with_serial_ports(mut self, ports: Vec<SerialPortConfig>) -> Self {
+        self.capabilities.add_port();
+        for port in ports {
+            self.capabilities.add_serial_port();
+            self.device_list.add_serial_port(port.device_id, port.name);
+        }
+        self
+    }
+
+    /// Adds port redirection capability, and the parallel ports to announce to the server.
+    ///
+    /// The I/O requests targeting the ports are handled by [`RdpdrBackend::handle_port_request`].
+    #[must_use]
+    pub fn with_parallel_ports(mut self, ports: Vec<ParallelPortConfig>) -> Self {
+        self.capabilities.add_port();
+        for port in ports {
+            self.device_list.add_parallel_port(port.device_id, port.name);
+        }
+        self
+    }
+
     /// Users should call this method to announce a new drive to the server. It's the caller's responsibility
     /// to take the returned [`ClientDeviceListAnnounce`] and send it to the server.
-    pub fn add_drive(&mut self, device_id: u32, name: String) -> ClientDeviceListAnnounce {
+    pub fn add_drive(&mut self, device_id: u32, name: String, policy: DrivePolicy) -> ClientDeviceListAnnounce {
         self.device_list.add_drive(device_id, name.clone());
+        self.set_drive_policy(device_id, policy);
         ClientDeviceListAnnounce::new_drive(device_id, name)
     }
 
+    /// Returns the policy of the drive with the given ID, if any.
+    pub fn drive_policy(&self, device_id: u32) -> Option<DrivePolicy> {
+        self.drive_policies.get(&device_id).copied()
+    }
+
+    /// Users should call this method to remove a previously announced device. It's the caller's responsibility
+    /// to take the returned [`ClientDriveDeviceListRemove`] and send it to the server.
+    ///
+    /// Returns `None` if no device with this ID was announced, or if the server did not advertise the
+    /// `RDPDR_DEVICE_REMOVE_PDUS` flag in its General Capability Set. Once removed, I/O requests targeting the device
+    /// are rejected.
+    pub fn remove_device(&mut self, device_id: u32) -> Option<ClientDriveDeviceListRemove> {
+        if !self.device_remove_allowed {
+            warn!(device_id, "The server does not allow removing devices");
+            return None;
+        }
+
+        self.drive_policies.remove(&device_id);
+        self.device_list
+            .remove(device_id)
+            .then(|| ClientDriveDeviceListRemove::new(vec![device_id]))
+    }
+
     pub fn downcast_backend<T: RdpdrBackend>(&self) -> Option<&T> {
         self.backend.as_any().downcast_ref::<T>()
     }
@@ -104,6 +183,11 @@ impl Rdpdr {
         self.backend.as_any_mut().downcast_mut::<T>()
     }
 
+    fn set_drive_policy(&mut self, device_id: u32, policy: DrivePolicy) {
+        self.drive_policies.insert(device_id, policy);
+        self.backend.set_drive_policy(device_id, policy);
+    }
+
     fn handle_server_announce(&mut self, req: VersionAndIdPdu) -> PduResult<Vec<SvcMessage>> {
         let client_announce_reply =
             RdpdrPdu::VersionAndIdPdu(VersionAndIdPdu::new_client_announce_reply(req).map_err(|e| decode_err!(e))?);
@@ -121,7 +205,9 @@ impl Rdpdr {
         ])
     }
 
-    fn handle_server_capability(&mut self, _req: CoreCapability) -> PduResult<Vec<SvcMessage>> {
+    fn handle_server_capability(&mut self, server_capability: CoreCapability) -> PduResult<Vec<SvcMessage>> {
+        self.device_remove_allowed = server_capability.allows_device_remove();
+
         let res = RdpdrPdu::CoreCapability(CoreCapability::new_response(self.capabilities.clone_inner()));
         trace!("sending {:?}", res);
         Ok(vec![SvcMessage::from(res)])
@@ -166,12 +252,24 @@ impl Rdpdr {
                 Ok(Vec::new())
             }
             DeviceType::Filesystem => {
-                let req = ServerDriveIoRequest::decode(dev_io_req, src).map_err(|e| decode_err!(e))?;
+                let policy = self.drive_policy(dev_io_req.device_id).unwrap_or_default();
+                let mut req = ServerDriveIoRequest::decode(dev_io_req, src).map_err(|e| decode_err!(e))?;
 
                 debug!(?req);
 
+                if let Some(denial) = policy.enforce(&mut req)? {
+                    return Ok(vec![SvcMessage::from(denial)]);
+                }
+
                 Ok(self.backend.handle_drive_io_request(req)?)
             }
+            DeviceType::Serial | DeviceType::Parallel => {
+                let req = PortIoRequest::decode(dev_io_req, src).map_err(|e| decode_err!(e))?;
+
+                debug!(?req);
+
+                Ok(self.backend.handle_port_request(req)?)
+            }
             _ => {
                 // This should never happen, as we only announce devices that we support.
                 warn!(?dev_io_req, "received packet for unsupported device type");
@@ -211,6 +309,7 @@ impl SvcProcessor for Rdpdr {
             // to make sure we don't miss handling new RdpdrPdu variants here during active development.
             RdpdrPdu::ClientNameRequest(_)
             | RdpdrPdu::ClientDeviceListAnnounce(_)
+            | RdpdrPdu::ClientDriveDeviceListRemove(_)
             | RdpdrPdu::VersionAndIdPdu(_)
             | RdpdrPdu::CoreCapability(_)
             | RdpdrPdu::DeviceControlResponse(_)
@@ -225,6 +324,11 @@ impl SvcProcessor for Rdpdr {
             | RdpdrPdu::EmptyResponse => Err(pdu_other_err!("Rdpdr", "received unexpected packet")),
         }
     }
+
+    fn close(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.backend.close();
+        Ok(Vec::new())
+    }
 }
 
 impl SvcClientProcessor for Rdpdr {}
@@ -11,7 +11,10 @@ use ironrdp_core::{
     cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, invalid_field_err_with_source,
     unsupported_value_err, DecodeError, DecodeResult, EncodeResult, ReadCursor, WriteCursor,
 };
-use ironrdp_pdu::utils::{decode_string, encoded_str_len, from_utf16_bytes, write_string_to_cursor, CharacterSet};
+use ironrdp_pdu::utf16::{
+    decode_utf16le_lossy_exact, encode_utf16le_null_terminated, null_terminated_utf16_encoded_len,
+};
+use ironrdp_pdu::utils::{encoded_str_len, write_string_to_cursor, CharacterSet};
 use ironrdp_pdu::{read_padding, write_padding, PduError};
 
 use super::esc::rpce;
@@ -145,19 +148,27 @@ impl ClientNameRequest {
         }
     }
 
-    fn computer_name(&self) -> &str {
-        match self {
-            ClientNameRequest::Ascii(name) => name,
-            ClientNameRequest::Unicode(name) => name,
-        }
-    }
-
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
         dst.write_u32(self.unicode_flag().into());
         dst.write_u32(0); // // CodePage (4 bytes): it MUST be set to 0
-        dst.write_u32(encoded_str_len(self.computer_name(), self.unicode_flag().into(), true) as u32);
-        write_string_to_cursor(dst, self.computer_name(), self.unicode_flag().into(), true)
+        dst.write_u32(cast_length!(
+            "ClientNameRequest",
+            "computer_name_len",
+            self.computer_name_len()
+        )?);
+        match self {
+            ClientNameRequest::Ascii(name) => write_string_to_cursor(dst, name, CharacterSet::Ansi, true),
+            ClientNameRequest::Unicode(name) => encode_utf16le_null_terminated(dst, name),
+        }
+    }
+
+    /// Size of the null-terminated computer name, in bytes.
+    fn computer_name_len(&self) -> usize {
+        match self {
+            ClientNameRequest::Ascii(name) => encoded_str_len(name, CharacterSet::Ansi, true),
+            ClientNameRequest::Unicode(name) => null_terminated_utf16_encoded_len(name),
+        }
     }
 
     pub fn name(&self) -> &'static str {
@@ -165,7 +176,7 @@ impl ClientNameRequest {
     }
 
     pub fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE + encoded_str_len(self.computer_name(), self.unicode_flag().into(), true)
+        Self::FIXED_PART_SIZE + self.computer_name_len()
     }
 }
 
@@ -254,6 +265,18 @@ impl CoreCapability {
         Ok(Self { capabilities, kind })
     }
 
+    /// Returns `true` if a General Capability Set allows the client to send Client Drive Device List Remove PDUs.
+    pub fn allows_device_remove(&self) -> bool {
+        self.capabilities
+            .iter()
+            .any(|capability| match &capability.capability_data {
+                CapabilityData::General(general) => {
+                    general.extended_pdu.contains(ExtendedPdu::RDPDR_DEVICE_REMOVE_PDUS)
+                }
+                _ => false,
+            })
+    }
+
     pub fn name(&self) -> &'static str {
         self.kind.name()
     }
@@ -307,6 +330,23 @@ impl Capabilities {
         self.push(CapabilityMessage::new_drive());
     }
 
+    /// Adds the port capability, shared by the serial and parallel ports, unless it was already added.
+    pub fn add_port(&mut self) {
+        if !self
+            .0
+            .iter()
+            .any(|capability| matches!(capability.capability_data, CapabilityData::Port))
+        {
+            self.push(CapabilityMessage::new_port());
+        }
+    }
+
+    /// Counts a serial port in the special devices, which may be redirected before the user is logged on.
+    pub fn add_serial_port(&mut self) {
+        self.add_port();
+        self.increment_special_devices();
+    }
+
     fn add_general(&mut self, special_type_device_cap: u32) {
         self.push(CapabilityMessage::new_general(special_type_device_cap));
     }
@@ -392,6 +432,14 @@ impl CapabilityMessage {
         }
     }
 
+    /// Creates a new `PORT_CAPS_SET`, shared by the serial and parallel ports.
+    pub fn new_port() -> Self {
+        Self {
+            header: CapabilityHeader::new_port(),
+            capability_data: CapabilityData::Port,
+        }
+    }
+
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size());
         self.header.encode(dst)?;
@@ -450,6 +498,14 @@ impl CapabilityHeader {
         }
     }
 
+    fn new_port() -> Self {
+        Self {
+            cap_type: CapabilityType::Port,
+            length: Self::SIZE as u16,
+            version: PORT_CAPABILITY_VERSION_01,
+        }
+    }
+
     fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(in: src, size: Self::SIZE);
         let cap_type: CapabilityType = src.read_u16().try_into()?;
@@ -500,6 +556,8 @@ pub const SMARTCARD_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
 /// DRIVE_CAPABILITY_VERSION_02
 pub const DRIVE_CAPABILITY_VERSION_02: u32 = 0x0000_0002;
 
+pub const PORT_CAPABILITY_VERSION_01: u32 = 0x0000_0001;
+
 impl TryFrom<u16> for CapabilityType {
     type Error = DecodeError;
 
@@ -758,6 +816,7 @@ impl ClientDeviceListAnnounce {
     }
 
     pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
         dst.write_u32(cast_length!(
             "ClientDeviceListAnnounce",
             "DeviceCount",
@@ -780,6 +839,59 @@ impl ClientDeviceListAnnounce {
     }
 }
 
+/// 2.2.3.2 Client Drive Device List Remove (DR_DEVICELIST_REMOVE) of [\[MS-RDPEFS\]]
+///
+/// Sent by the client to notify the server that previously announced devices are no longer available.
+/// Only valid when the server advertised `RDPDR_DEVICE_REMOVE_PDUS` in its General Capability Set.
+///
+/// [\[MS-RDPEFS\]]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/34d9de58-b2b5-40b6-b970-f82d4603bdb5
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClientDriveDeviceListRemove {
+    pub device_ids: Vec<u32>,
+}
+
+impl ClientDriveDeviceListRemove {
+    const NAME: &'static str = "DR_DEVICELIST_REMOVE";
+
+    const FIXED_PART_SIZE: usize = size_of::<u32>(); // DeviceCount
+
+    pub fn new(device_ids: Vec<u32>) -> Self {
+        Self { device_ids }
+    }
+
+    pub fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!(Self::NAME, "DeviceCount", self.device_ids.len())?);
+
+        for device_id in &self.device_ids {
+            dst.write_u32(*device_id);
+        }
+
+        Ok(())
+    }
+
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: Self::FIXED_PART_SIZE);
+
+        let device_count = cast_length!(Self::NAME, "DeviceCount", src.read_u32())?;
+
+        ensure_size!(in: src, size: device_count * size_of::<u32>());
+
+        let device_ids = (0..device_count).map(|_| src.read_u32()).collect();
+
+        Ok(Self { device_ids })
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    pub fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.device_ids.len() * size_of::<u32>()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Devices(Vec<DeviceAnnounceHeader>);
 
@@ -796,6 +908,14 @@ impl Devices {
         self.push(DeviceAnnounceHeader::new_drive(device_id, name));
     }
 
+    pub fn add_serial_port(&mut self, device_id: u32, name: String) {
+        self.push(DeviceAnnounceHeader::new_port(DeviceType::Serial, device_id, name));
+    }
+
+    pub fn add_parallel_port(&mut self, device_id: u32, name: String) {
+        self.push(DeviceAnnounceHeader::new_port(DeviceType::Parallel, device_id, name));
+    }
+
     /// Returns the [`DeviceType`] for the given device ID.
     pub fn for_device_type(&self, device_id: u32) -> DecodeResult<DeviceType> {
         if let Some(device_type) = self.0.iter().find(|d| d.device_id == device_id).map(|d| d.device_type) {
@@ -809,6 +929,13 @@ impl Devices {
         }
     }
 
+    /// Removes the device with the given ID, returning `false` if there is no such device.
+    pub fn remove(&mut self, device_id: u32) -> bool {
+        let len = self.0.len();
+        self.0.retain(|d| d.device_id != device_id);
+        self.0.len() != len
+    }
+
     fn push(&mut self, device: DeviceAnnounceHeader) {
         self.0.push(device);
     }
@@ -867,6 +994,16 @@ impl DeviceAnnounceHeader {
         }
     }
 
+    /// Creates the announce of a serial or parallel port, named e.g. "COM1" or "LPT1".
+    fn new_port(device_type: DeviceType, device_id: u32, name: String) -> Self {
+        Self {
+            device_type,
+            device_id,
+            preferred_dos_name: PreferredDosName(name),
+            device_data: Vec::new(),
+        }
+    }
+
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         dst.write_u32(self.device_type.into());
         dst.write_u32(self.device_id);
@@ -1561,7 +1698,7 @@ impl DeviceCreateRequest {
                                  + 4  // CreateOptions
                                  + 4; // PathLength
 
-    fn decode(dev_io_req: DeviceIoRequest, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+    pub fn decode(dev_io_req: DeviceIoRequest, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
         ensure_size!(ctx: "DeviceCreateRequest", in: src, size: Self::FIXED_PART_SIZE);
         let desired_access = DesiredAccess::from_bits_retain(src.read_u32());
         let allocation_size = src.read_u64();
@@ -1571,10 +1708,7 @@ impl DeviceCreateRequest {
         let create_options = CreateOptions::from_bits_retain(src.read_u32());
         let path_length: usize = cast_length!("DeviceCreateRequest", "path_length", src.read_u32())?;
 
-        ensure_size!(ctx: "DeviceCreateRequest", in: src, size: path_length);
-        let path = from_utf16_bytes(src.read_slice(path_length))
-            .trim_end_matches('\0')
-            .into();
+        let path = decode_utf16le_lossy_exact(src, path_length)?;
 
         Ok(Self {
             device_io_request: dev_io_req,
@@ -1964,11 +2098,10 @@ impl FileInformationClass {
             Self::FullDirectory(f) => f.encode(dst),
             Self::Names(f) => f.encode(dst),
             Self::Directory(f) => f.encode(dst),
-            _ => Err(unsupported_value_err!(
-                "FileInformationClass::encode",
-                "FileInformationClass",
-                self.to_string()
-            )),
+            Self::EndOfFile(f) => f.encode(dst),
+            Self::Disposition(f) => f.encode(dst),
+            Self::Rename(f) => f.encode(dst),
+            Self::Allocation(f) => f.encode(dst),
         }
     }
 
@@ -2616,8 +2749,7 @@ impl ServerDriveQueryDirectoryRequest {
         // Padding (23 bytes): An array of 23 bytes. This field is unused and MUST be ignored.
         read_padding!(src, 23);
 
-        ensure_size!(in: src, size: path_length);
-        let path = decode_string(src.read_slice(path_length), CharacterSet::Unicode, true)?;
+        let path = decode_utf16le_lossy_exact(src, path_length)?;
 
         Ok(Self {
             device_io_request,
@@ -2877,10 +3009,10 @@ impl FileFsVolumeInformation {
         dst.write_u32(cast_length!(
             "FileFsVolumeInformation::encode",
             "volume_label_length",
-            encoded_str_len(&self.volume_label, CharacterSet::Unicode, true)
+            null_terminated_utf16_encoded_len(&self.volume_label)
         )?);
         dst.write_u8(self.supports_objects.into());
-        write_string_to_cursor(dst, &self.volume_label, CharacterSet::Unicode, true)?;
+        encode_utf16le_null_terminated(dst, &self.volume_label)?;
         Ok(())
     }
 
@@ -2889,7 +3021,7 @@ impl FileFsVolumeInformation {
         + 4 // VolumeSerialNumber
         + 4 // VolumeLabelLength
         + 1 // SupportsObjects
-        + encoded_str_len(&self.volume_label, CharacterSet::Unicode, true)
+        + null_terminated_utf16_encoded_len(&self.volume_label)
     }
 }
 
@@ -2940,9 +3072,9 @@ impl FileFsAttributeInformation {
         dst.write_u32(cast_length!(
             "FileFsAttributeInformation::encode",
             "file_system_name_length",
-            encoded_str_len(&self.file_system_name, CharacterSet::Unicode, true)
+            null_terminated_utf16_encoded_len(&self.file_system_name)
         )?);
-        write_string_to_cursor(dst, &self.file_system_name, CharacterSet::Unicode, true)?;
+        encode_utf16le_null_terminated(dst, &self.file_system_name)?;
         Ok(())
     }
 
@@ -2950,7 +3082,7 @@ impl FileFsAttributeInformation {
         4 // FileSystemAttributes
         + 4 // MaximumComponentNameLength
         + 4 // FileSystemNameLength
-        + encoded_str_len(&self.file_system_name, CharacterSet::Unicode, true)
+        + null_terminated_utf16_encoded_len(&self.file_system_name)
     }
 }
 
@@ -3320,6 +3452,12 @@ impl FileEndOfFileInformation {
         Ok(Self { end_of_file })
     }
 
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_i64(self.end_of_file);
+        Ok(())
+    }
+
     fn size() -> usize {
         Self::FIXED_PART_SIZE
     }
@@ -3347,6 +3485,12 @@ impl FileDispositionInformation {
         Ok(Self { delete_pending })
     }
 
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_u8(self.delete_pending);
+        Ok(())
+    }
+
     fn size() -> usize {
         Self::FIXED_PART_SIZE
     }
@@ -3371,8 +3515,7 @@ impl FileRenameInformation {
         let _ = src.read_u8(); // RootDirectory
         let file_name_length = cast_length!("FileRenameInformation", "file_name_length", src.read_u32())?;
 
-        ensure_size!(in: src, size: file_name_length);
-        let file_name = decode_string(src.read_slice(file_name_length), CharacterSet::Unicode, true)?;
+        let file_name = decode_utf16le_lossy_exact(src, file_name_length)?;
 
         Ok(Self {
             replace_if_exists,
@@ -3380,8 +3523,21 @@ impl FileRenameInformation {
         })
     }
 
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_u8(self.replace_if_exists.into());
+        dst.write_u8(0); // RootDirectory
+        dst.write_u32(cast_length!(
+            "FileRenameInformation::encode",
+            "file_name_length",
+            null_terminated_utf16_encoded_len(&self.file_name)
+        )?);
+        encode_utf16le_null_terminated(dst, &self.file_name)?;
+        Ok(())
+    }
+
     fn size(&self) -> usize {
-        Self::FIXED_PART_SIZE + encoded_str_len(&self.file_name, CharacterSet::Unicode, true)
+        Self::FIXED_PART_SIZE + null_terminated_utf16_encoded_len(&self.file_name)
     }
 }
 
@@ -3402,6 +3558,12 @@ impl FileAllocationInformation {
         Ok(Self { allocation_size })
     }
 
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_i64(self.allocation_size);
+        Ok(())
+    }
+
     fn size() -> usize {
         Self::FIXED_PART_SIZE
     }
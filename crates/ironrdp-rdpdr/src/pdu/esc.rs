@@ -6,6 +6,7 @@ pub mod ndr;
 pub mod rpce;
 
 use core::mem::size_of;
+use core::time::Duration;
 
 use bitflags::bitflags;
 use ironrdp_core::{
@@ -37,6 +38,9 @@ pub enum ScardCall {
     ReadCacheCall(ReadCacheCall),
     WriteCacheCall(WriteCacheCall),
     GetReaderIconCall(GetReaderIconCall),
+    ReconnectCall(ReconnectCall),
+    GetAttribCall(GetAttribCall),
+    SetAttribCall(SetAttribCall),
     Unsupported,
 }
 
@@ -103,6 +107,9 @@ impl ScardCall {
                 Some(CharacterSet::Ansi),
             )?)),
             ScardIoCtlCode::GetReaderIcon => Ok(ScardCall::GetReaderIconCall(GetReaderIconCall::decode(src)?)),
+            ScardIoCtlCode::Reconnect => Ok(ScardCall::ReconnectCall(ReconnectCall::decode(src)?)),
+            ScardIoCtlCode::GetAttrib => Ok(ScardCall::GetAttribCall(GetAttribCall::decode(src)?)),
+            ScardIoCtlCode::SetAttrib => Ok(ScardCall::SetAttribCall(SetAttribCall::decode(src)?)),
             _ => {
                 warn!(?io_ctl_code, "Unsupported ScardIoCtlCode");
                 // TODO: maybe this should be an error
@@ -804,9 +811,20 @@ pub struct GetStatusChangeCall {
 }
 
 impl GetStatusChangeCall {
+    /// INFINITE: the call only completes when a reader state changes, or when it is cancelled.
+    pub const INFINITE_TIMEOUT: u32 = 0xFFFF_FFFF;
+
     pub fn decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
         Ok(rpce::Pdu::<Self>::decode(src, charset)?.into_inner())
     }
+
+    /// Returns how long to wait for a reader state change, or `None` if the call may wait forever.
+    ///
+    /// Such calls are completed with [`ReturnCode::Cancelled`] when the context is cancelled (SCARD_IOCTL_CANCEL),
+    /// and must be dropped when the channel is closed.
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        (self.timeout != Self::INFINITE_TIMEOUT).then(|| Duration::from_millis(u64::from(self.timeout)))
+    }
 }
 
 impl rpce::HeaderlessDecode for GetStatusChangeCall {
@@ -875,6 +893,7 @@ impl ReaderStateCommonCall {
     }
 
     fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: Self::size());
         dst.write_u32(self.current_state.bits());
         dst.write_u32(self.event_state.bits());
         dst.write_u32(self.atr_length);
@@ -1149,6 +1168,75 @@ impl rpce::HeaderlessEncode for ConnectReturn {
     }
 }
 
+/// 2.2.2.15 Reconnect_Call
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReconnectCall {
+    pub handle: ScardHandle,
+    pub share_mode: u32,
+    pub preferred_protocols: CardProtocol,
+    pub initialization: u32,
+}
+
+impl ReconnectCall {
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        Ok(rpce::Pdu::<Self>::decode(src, None)?.into_inner())
+    }
+}
+
+impl rpce::HeaderlessDecode for ReconnectCall {
+    fn decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let mut index = 0;
+        let mut handle = ScardHandle::decode_ptr(src, &mut index)?;
+        ensure_size!(in: src, size: size_of::<u32>() * 3);
+        let share_mode = src.read_u32();
+        let preferred_protocols = CardProtocol::from_bits_retain(src.read_u32());
+        let initialization = src.read_u32();
+        handle.decode_value(src, None)?;
+        Ok(Self {
+            handle,
+            share_mode,
+            preferred_protocols,
+            initialization,
+        })
+    }
+}
+
+/// 2.2.3.7 Reconnect_Return
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReconnectReturn {
+    pub return_code: ReturnCode,
+    pub active_protocol: CardProtocol,
+}
+
+impl ReconnectReturn {
+    const NAME: &'static str = "Reconnect_Return";
+
+    pub fn new(return_code: ReturnCode, active_protocol: CardProtocol) -> rpce::Pdu<Self> {
+        rpce::Pdu(Self {
+            return_code,
+            active_protocol,
+        })
+    }
+}
+
+impl rpce::HeaderlessEncode for ReconnectReturn {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_u32(self.return_code.into());
+        dst.write_u32(self.active_protocol.bits());
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.return_code.size() + 4 /* dwActiveProtocol */
+    }
+}
+
 /// [2.2.2.16] HCardAndDisposition_Call
 ///
 /// [2.2.2.16]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesc/f15ae865-9e99-4c5b-bb43-15a6b4885bd0
@@ -1279,7 +1367,9 @@ impl ndr::Encode for SCardIORequest {
     fn encode_ptr(&self, index: &mut u32, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
         ensure_size!(in: dst, size: self.size_ptr());
         dst.write_u32(self.protocol.bits());
-        ndr::encode_ptr(Some(self.extra_bytes_length), index, dst)
+        // `extra_bytes_length` is not trusted, as it may not match the bytes written by `encode_value`.
+        let extra_bytes_length = cast_length!("SCardIORequest", "extra_bytes_length", self.extra_bytes.len())?;
+        ndr::encode_ptr(Some(extra_bytes_length), index, dst)
     }
 
     fn encode_value(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
@@ -1293,7 +1383,7 @@ impl ndr::Encode for SCardIORequest {
     }
 
     fn size_value(&self) -> usize {
-        self.extra_bytes_length as usize
+        self.extra_bytes.len()
     }
 }
 
@@ -1836,6 +1926,143 @@ impl rpce::HeaderlessEncode for GetReaderIconReturn {
     }
 }
 
+/// 2.2.2.21 GetAttrib_Call
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetAttribCall {
+    pub handle: ScardHandle,
+    pub attr_id: u32,
+    /// Only the length of the attribute is requested.
+    pub attr_is_null: bool,
+    pub attr_length: u32,
+}
+
+impl GetAttribCall {
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        Ok(rpce::Pdu::<Self>::decode(src, None)?.into_inner())
+    }
+}
+
+impl rpce::HeaderlessDecode for GetAttribCall {
+    fn decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let mut index = 0;
+        let mut handle = ScardHandle::decode_ptr(src, &mut index)?;
+        ensure_size!(in: src, size: size_of::<u32>() * 2 + size_of::<i32>());
+        let attr_id = src.read_u32();
+        let attr_is_null = src.read_i32() == 1;
+        let attr_length = src.read_u32();
+        handle.decode_value(src, None)?;
+        Ok(Self {
+            handle,
+            attr_id,
+            attr_is_null,
+            attr_length,
+        })
+    }
+}
+
+/// 2.2.3.12 GetAttrib_Return
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetAttribReturn {
+    pub return_code: ReturnCode,
+    pub attr_length: u32,
+    /// `None` when the call only requested the length of the attribute.
+    pub attr: Option<Vec<u8>>,
+}
+
+impl GetAttribReturn {
+    const NAME: &'static str = "GetAttrib_Return";
+
+    pub fn new(return_code: ReturnCode, attr: Vec<u8>) -> EncodeResult<rpce::Pdu<Self>> {
+        let attr_length = cast_length!("GetAttribReturn", "attr_length", attr.len())?;
+        Ok(rpce::Pdu(Self {
+            return_code,
+            attr_length,
+            attr: Some(attr),
+        }))
+    }
+
+    /// Answers a [`GetAttribCall`] with [`GetAttribCall::attr_is_null`] set.
+    pub fn new_length_only(return_code: ReturnCode, attr_length: u32) -> rpce::Pdu<Self> {
+        rpce::Pdu(Self {
+            return_code,
+            attr_length,
+            attr: None,
+        })
+    }
+}
+
+impl rpce::HeaderlessEncode for GetAttribReturn {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+        dst.write_u32(self.return_code.into());
+        if let Some(attr) = &self.attr {
+            let attr_len: u32 = cast_length!("GetAttribReturn", "attr_len", attr.len())?;
+            let mut index = 0;
+            ndr::encode_ptr(Some(attr_len), &mut index, dst)?;
+            dst.write_u32(attr_len);
+            dst.write_slice(attr);
+        } else {
+            dst.write_u32(self.attr_length);
+            dst.write_u32(0); // null pointer
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.return_code.size() // dst.write_u32(self.return_code.into());
+        + ndr::ptr_size(true) // ndr::encode_ptr(Some(attr_len), &mut index, dst)?;
+        + self.attr.as_ref().map_or(0, |attr| size_of::<u32>() + attr.len()) // dst.write_u32(attr_len); dst.write_slice(attr);
+    }
+}
+
+/// 2.2.2.22 SetAttrib_Call
+#[derive(Debug, PartialEq, Clone)]
+pub struct SetAttribCall {
+    pub handle: ScardHandle,
+    pub attr_id: u32,
+    pub attr: Vec<u8>,
+}
+
+impl SetAttribCall {
+    pub fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        Ok(rpce::Pdu::<Self>::decode(src, None)?.into_inner())
+    }
+}
+
+impl rpce::HeaderlessDecode for SetAttribCall {
+    fn decode(src: &mut ReadCursor<'_>, charset: Option<CharacterSet>) -> DecodeResult<Self> {
+        expect_no_charset(charset)?;
+        let mut index = 0;
+        let mut handle = ScardHandle::decode_ptr(src, &mut index)?;
+        ensure_size!(in: src, size: size_of::<u32>() * 2);
+        let attr_id = src.read_u32();
+        let attr_length = src.read_u32();
+        let attr_ptr = ndr::decode_ptr(src, &mut index)?;
+        handle.decode_value(src, None)?;
+
+        let attr = if attr_ptr != 0 {
+            // Conformant array: the maximum count is repeated before the elements.
+            ensure_size!(in: src, size: size_of::<u32>());
+            let max_count = src.read_u32();
+            if max_count != attr_length {
+                return Err(invalid_field_err!("decode", "cbAttrLen", "attribute length mismatch"));
+            }
+            let attr_length: usize = cast_length!("SetAttribCall", "attr_length", attr_length)?;
+            ensure_size!(in: src, size: attr_length);
+            src.read_slice(attr_length).to_vec()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { handle, attr_id, attr })
+    }
+}
+
 fn expect_charset(charset: Option<CharacterSet>) -> DecodeResult<CharacterSet> {
     if charset.is_none() {
         return Err(other_err!("internal error: missing character set"));
@@ -0,0 +1,476 @@
+//! PDUs for [\[MS-RDPESP\]: Remote Desktop Protocol: Serial and Parallel Port Virtual Channel Extension]
+//!
+//! [\[MS-RDPESP\]: Remote Desktop Protocol: Serial and Parallel Port Virtual Channel Extension]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpesp/
+
+use core::fmt::{self, Debug};
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, Decode, DecodeError, DecodeResult, Encode, EncodeResult,
+    ReadCursor, WriteCursor,
+};
+
+use super::efs::{
+    DeviceCloseRequest, DeviceCloseResponse, DeviceControlRequest, DeviceControlResponse, DeviceCreateRequest,
+    DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse, DeviceReadRequest, DeviceReadResponse, DeviceWriteRequest,
+    DeviceWriteResponse, Information, IoCtlCode, MajorFunction, NtStatus,
+};
+use super::esc::rpce;
+use super::RdpdrPdu;
+
+/// I/O request sent by the server to a serial or parallel port
+#[derive(Debug, PartialEq, Clone)]
+pub enum PortIoRequest {
+    DeviceCreateRequest(DeviceCreateRequest),
+    DeviceCloseRequest(DeviceCloseRequest),
+    DeviceReadRequest(DeviceReadRequest),
+    DeviceWriteRequest(DeviceWriteRequest),
+    DeviceControlRequest(DeviceControlRequest<PortIoCtlCode>, PortControlCall),
+    /// A request with a major function that ports do not support.
+    Unsupported(DeviceIoRequest),
+}
+
+impl PortIoRequest {
+    pub fn decode(dev_io_req: DeviceIoRequest, src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        match dev_io_req.major_function {
+            MajorFunction::Create => Ok(Self::DeviceCreateRequest(DeviceCreateRequest::decode(dev_io_req, src)?)),
+            MajorFunction::Close => Ok(Self::DeviceCloseRequest(DeviceCloseRequest::decode(dev_io_req))),
+            MajorFunction::Read => Ok(Self::DeviceReadRequest(DeviceReadRequest::decode(dev_io_req, src)?)),
+            MajorFunction::Write => Ok(Self::DeviceWriteRequest(DeviceWriteRequest::decode(dev_io_req, src)?)),
+            MajorFunction::DeviceControl => {
+                let req = DeviceControlRequest::<PortIoCtlCode>::decode(dev_io_req, src)?;
+                let call = PortControlCall::decode(req.io_control_code, req.input_buffer_length, src)?;
+                Ok(Self::DeviceControlRequest(req, call))
+            }
+            _ => Ok(Self::Unsupported(dev_io_req)),
+        }
+    }
+
+    pub fn device_io_request(&self) -> &DeviceIoRequest {
+        match self {
+            Self::DeviceCreateRequest(req) => &req.device_io_request,
+            Self::DeviceCloseRequest(req) => &req.device_io_request,
+            Self::DeviceReadRequest(req) => &req.device_io_request,
+            Self::DeviceWriteRequest(req) => &req.device_io_request,
+            Self::DeviceControlRequest(req, _) => &req.header,
+            Self::Unsupported(req) => req,
+        }
+    }
+
+    /// Returns the response completing the request with `io_status` and no data, e.g.: STATUS_NOT_SUPPORTED.
+    ///
+    /// The unsupported requests are completed with an empty [`DeviceControlResponse`].
+    pub fn error_response(&self, io_status: NtStatus) -> RdpdrPdu {
+        let device_io_reply = DeviceIoResponse::new(self.device_io_request().clone(), io_status);
+
+        match self {
+            Self::DeviceCreateRequest(_) => RdpdrPdu::DeviceCreateResponse(DeviceCreateResponse {
+                device_io_reply,
+                file_id: 0,
+                information: Information::empty(),
+            }),
+            Self::DeviceCloseRequest(_) => RdpdrPdu::DeviceCloseResponse(DeviceCloseResponse {
+                device_io_response: device_io_reply,
+            }),
+            Self::DeviceReadRequest(_) => RdpdrPdu::DeviceReadResponse(DeviceReadResponse {
+                device_io_reply,
+                read_data: Vec::new(),
+            }),
+            Self::DeviceWriteRequest(_) => RdpdrPdu::DeviceWriteResponse(DeviceWriteResponse {
+                device_io_reply,
+                length: 0,
+            }),
+            Self::DeviceControlRequest(..) | Self::Unsupported(_) => {
+                RdpdrPdu::DeviceControlResponse(DeviceControlResponse {
+                    device_io_reply,
+                    output_buffer: None,
+                })
+            }
+        }
+    }
+}
+
+/// I/O control code of a port device control request
+///
+/// The constants are the serial port codes used by Windows servers, the parallel port ones being left as-is.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PortIoCtlCode(u32);
+
+impl PortIoCtlCode {
+    pub const IOCTL_SERIAL_SET_BAUD_RATE: Self = Self(0x001B_0004);
+    pub const IOCTL_SERIAL_SET_QUEUE_SIZE: Self = Self(0x001B_0008);
+    pub const IOCTL_SERIAL_SET_LINE_CONTROL: Self = Self(0x001B_000C);
+    pub const IOCTL_SERIAL_SET_BREAK_ON: Self = Self(0x001B_0010);
+    pub const IOCTL_SERIAL_SET_BREAK_OFF: Self = Self(0x001B_0014);
+    pub const IOCTL_SERIAL_IMMEDIATE_CHAR: Self = Self(0x001B_0018);
+    pub const IOCTL_SERIAL_SET_TIMEOUTS: Self = Self(0x001B_001C);
+    pub const IOCTL_SERIAL_GET_TIMEOUTS: Self = Self(0x001B_0020);
+    pub const IOCTL_SERIAL_SET_DTR: Self = Self(0x001B_0024);
+    pub const IOCTL_SERIAL_CLR_DTR: Self = Self(0x001B_0028);
+    pub const IOCTL_SERIAL_RESET_DEVICE: Self = Self(0x001B_002C);
+    pub const IOCTL_SERIAL_SET_RTS: Self = Self(0x001B_0030);
+    pub const IOCTL_SERIAL_CLR_RTS: Self = Self(0x001B_0034);
+    pub const IOCTL_SERIAL_SET_XOFF: Self = Self(0x001B_0038);
+    pub const IOCTL_SERIAL_SET_XON: Self = Self(0x001B_003C);
+    pub const IOCTL_SERIAL_GET_WAIT_MASK: Self = Self(0x001B_0040);
+    pub const IOCTL_SERIAL_SET_WAIT_MASK: Self = Self(0x001B_0044);
+    pub const IOCTL_SERIAL_WAIT_ON_MASK: Self = Self(0x001B_0048);
+    pub const IOCTL_SERIAL_PURGE: Self = Self(0x001B_004C);
+    pub const IOCTL_SERIAL_GET_BAUD_RATE: Self = Self(0x001B_0050);
+    pub const IOCTL_SERIAL_GET_LINE_CONTROL: Self = Self(0x001B_0054);
+    pub const IOCTL_SERIAL_GET_CHARS: Self = Self(0x001B_0058);
+    pub const IOCTL_SERIAL_SET_CHARS: Self = Self(0x001B_005C);
+    pub const IOCTL_SERIAL_GET_HANDFLOW: Self = Self(0x001B_0060);
+    pub const IOCTL_SERIAL_SET_HANDFLOW: Self = Self(0x001B_0064);
+    pub const IOCTL_SERIAL_GET_MODEMSTATUS: Self = Self(0x001B_0068);
+    pub const IOCTL_SERIAL_GET_COMMSTATUS: Self = Self(0x001B_006C);
+    pub const IOCTL_SERIAL_XOFF_COUNTER: Self = Self(0x001B_0070);
+    pub const IOCTL_SERIAL_GET_PROPERTIES: Self = Self(0x001B_0074);
+    pub const IOCTL_SERIAL_GET_DTRRTS: Self = Self(0x001B_0078);
+    pub const IOCTL_SERIAL_CONFIG_SIZE: Self = Self(0x001B_0080);
+}
+
+impl Debug for PortIoCtlCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Self::IOCTL_SERIAL_SET_BAUD_RATE => "IOCTL_SERIAL_SET_BAUD_RATE",
+            Self::IOCTL_SERIAL_SET_QUEUE_SIZE => "IOCTL_SERIAL_SET_QUEUE_SIZE",
+            Self::IOCTL_SERIAL_SET_LINE_CONTROL => "IOCTL_SERIAL_SET_LINE_CONTROL",
+            Self::IOCTL_SERIAL_SET_BREAK_ON => "IOCTL_SERIAL_SET_BREAK_ON",
+            Self::IOCTL_SERIAL_SET_BREAK_OFF => "IOCTL_SERIAL_SET_BREAK_OFF",
+            Self::IOCTL_SERIAL_IMMEDIATE_CHAR => "IOCTL_SERIAL_IMMEDIATE_CHAR",
+            Self::IOCTL_SERIAL_SET_TIMEOUTS => "IOCTL_SERIAL_SET_TIMEOUTS",
+            Self::IOCTL_SERIAL_GET_TIMEOUTS => "IOCTL_SERIAL_GET_TIMEOUTS",
+            Self::IOCTL_SERIAL_SET_DTR => "IOCTL_SERIAL_SET_DTR",
+            Self::IOCTL_SERIAL_CLR_DTR => "IOCTL_SERIAL_CLR_DTR",
+            Self::IOCTL_SERIAL_RESET_DEVICE => "IOCTL_SERIAL_RESET_DEVICE",
+            Self::IOCTL_SERIAL_SET_RTS => "IOCTL_SERIAL_SET_RTS",
+            Self::IOCTL_SERIAL_CLR_RTS => "IOCTL_SERIAL_CLR_RTS",
+            Self::IOCTL_SERIAL_SET_XOFF => "IOCTL_SERIAL_SET_XOFF",
+            Self::IOCTL_SERIAL_SET_XON => "IOCTL_SERIAL_SET_XON",
+            Self::IOCTL_SERIAL_GET_WAIT_MASK => "IOCTL_SERIAL_GET_WAIT_MASK",
+            Self::IOCTL_SERIAL_SET_WAIT_MASK => "IOCTL_SERIAL_SET_WAIT_MASK",
+            Self::IOCTL_SERIAL_WAIT_ON_MASK => "IOCTL_SERIAL_WAIT_ON_MASK",
+            Self::IOCTL_SERIAL_PURGE => "IOCTL_SERIAL_PURGE",
+            Self::IOCTL_SERIAL_GET_BAUD_RATE => "IOCTL_SERIAL_GET_BAUD_RATE",
+            Self::IOCTL_SERIAL_GET_LINE_CONTROL => "IOCTL_SERIAL_GET_LINE_CONTROL",
+            Self::IOCTL_SERIAL_GET_CHARS => "IOCTL_SERIAL_GET_CHARS",
+            Self::IOCTL_SERIAL_SET_CHARS => "IOCTL_SERIAL_SET_CHARS",
+            Self::IOCTL_SERIAL_GET_HANDFLOW => "IOCTL_SERIAL_GET_HANDFLOW",
+            Self::IOCTL_SERIAL_SET_HANDFLOW => "IOCTL_SERIAL_SET_HANDFLOW",
+            Self::IOCTL_SERIAL_GET_MODEMSTATUS => "IOCTL_SERIAL_GET_MODEMSTATUS",
+            Self::IOCTL_SERIAL_GET_COMMSTATUS => "IOCTL_SERIAL_GET_COMMSTATUS",
+            Self::IOCTL_SERIAL_XOFF_COUNTER => "IOCTL_SERIAL_XOFF_COUNTER",
+            Self::IOCTL_SERIAL_GET_PROPERTIES => "IOCTL_SERIAL_GET_PROPERTIES",
+            Self::IOCTL_SERIAL_GET_DTRRTS => "IOCTL_SERIAL_GET_DTRRTS",
+            Self::IOCTL_SERIAL_CONFIG_SIZE => "IOCTL_SERIAL_CONFIG_SIZE",
+            _ => return write!(f, "PortIoCtlCode({:#010X})", self.0),
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl TryFrom<u32> for PortIoCtlCode {
+    type Error = DecodeError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}
+
+impl From<PortIoCtlCode> for u32 {
+    fn from(code: PortIoCtlCode) -> Self {
+        code.0
+    }
+}
+
+impl IoCtlCode for PortIoCtlCode {}
+
+/// Input buffer of a port device control request, decoded according to its I/O control code
+#[derive(Debug, PartialEq, Clone)]
+pub enum PortControlCall {
+    SetBaudRate(SerialBaudRate),
+    SetLineControl(SerialLineControl),
+    SetTimeouts(SerialTimeouts),
+    SetWaitMask(SerialWaitMask),
+    /// Input buffer of the other I/O control codes, as-is. It is empty for most of them, e.g.: the ones returning
+    /// a setting, such as [`PortIoCtlCode::IOCTL_SERIAL_GET_BAUD_RATE`].
+    Other(Vec<u8>),
+}
+
+impl PortControlCall {
+    pub fn decode(
+        io_control_code: PortIoCtlCode,
+        input_buffer_length: u32,
+        src: &mut ReadCursor<'_>,
+    ) -> DecodeResult<Self> {
+        let input_buffer_length = cast_length!("PortControlCall", "InputBufferLength", input_buffer_length)?;
+        ensure_size!(ctx: "PortControlCall", in: src, size: input_buffer_length);
+        let input_buffer = src.read_slice(input_buffer_length);
+        let input = &mut ReadCursor::new(input_buffer);
+
+        let call = match io_control_code {
+            PortIoCtlCode::IOCTL_SERIAL_SET_BAUD_RATE => Self::SetBaudRate(SerialBaudRate::decode(input)?),
+            PortIoCtlCode::IOCTL_SERIAL_SET_LINE_CONTROL => Self::SetLineControl(SerialLineControl::decode(input)?),
+            PortIoCtlCode::IOCTL_SERIAL_SET_TIMEOUTS => Self::SetTimeouts(SerialTimeouts::decode(input)?),
+            PortIoCtlCode::IOCTL_SERIAL_SET_WAIT_MASK => Self::SetWaitMask(SerialWaitMask::decode(input)?),
+            _ => Self::Other(input_buffer.to_vec()),
+        };
+
+        Ok(call)
+    }
+}
+
+/// SERIAL_BAUD_RATE
+///
+/// Input of [`PortIoCtlCode::IOCTL_SERIAL_SET_BAUD_RATE`], and output of
+/// [`PortIoCtlCode::IOCTL_SERIAL_GET_BAUD_RATE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialBaudRate {
+    pub baud_rate: u32,
+}
+
+impl SerialBaudRate {
+    const NAME: &'static str = "SERIAL_BAUD_RATE";
+
+    const FIXED_PART_SIZE: usize = 4 /* BaudRate */;
+}
+
+impl Encode for SerialBaudRate {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_u32(self.baud_rate);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl Decode<'_> for SerialBaudRate {
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+        let baud_rate = src.read_u32();
+
+        Ok(Self { baud_rate })
+    }
+}
+
+impl rpce::Encode for SerialBaudRate {}
+
+/// SERIAL_LINE_CONTROL
+///
+/// Input of [`PortIoCtlCode::IOCTL_SERIAL_SET_LINE_CONTROL`], and output of
+/// [`PortIoCtlCode::IOCTL_SERIAL_GET_LINE_CONTROL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialLineControl {
+    pub stop_bits: StopBits,
+    pub parity: Parity,
+    /// Number of data bits, from 5 to 8.
+    pub word_length: u8,
+}
+
+impl SerialLineControl {
+    const NAME: &'static str = "SERIAL_LINE_CONTROL";
+
+    const FIXED_PART_SIZE: usize = 1 /* StopBits */ + 1 /* Parity */ + 1 /* WordLength */;
+}
+
+impl Encode for SerialLineControl {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_u8(self.stop_bits.0);
+        dst.write_u8(self.parity.0);
+        dst.write_u8(self.word_length);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl Decode<'_> for SerialLineControl {
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+        let stop_bits = StopBits(src.read_u8());
+        let parity = Parity(src.read_u8());
+        let word_length = src.read_u8();
+
+        Ok(Self {
+            stop_bits,
+            parity,
+            word_length,
+        })
+    }
+}
+
+impl rpce::Encode for SerialLineControl {}
+
+/// StopBits field of [`SerialLineControl`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct StopBits(pub u8);
+
+impl StopBits {
+    pub const STOP_BIT_1: Self = Self(0);
+    pub const STOP_BITS_1_5: Self = Self(1);
+    pub const STOP_BITS_2: Self = Self(2);
+}
+
+impl Debug for StopBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::STOP_BIT_1 => write!(f, "STOP_BIT_1"),
+            Self::STOP_BITS_1_5 => write!(f, "STOP_BITS_1_5"),
+            Self::STOP_BITS_2 => write!(f, "STOP_BITS_2"),
+            _ => write!(f, "StopBits({:#04X})", self.0),
+        }
+    }
+}
+
+/// Parity field of [`SerialLineControl`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Parity(pub u8);
+
+impl Parity {
+    pub const NO_PARITY: Self = Self(0);
+    pub const ODD_PARITY: Self = Self(1);
+    pub const EVEN_PARITY: Self = Self(2);
+    pub const MARK_PARITY: Self = Self(3);
+    pub const SPACE_PARITY: Self = Self(4);
+}
+
+impl Debug for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NO_PARITY => write!(f, "NO_PARITY"),
+            Self::ODD_PARITY => write!(f, "ODD_PARITY"),
+            Self::EVEN_PARITY => write!(f, "EVEN_PARITY"),
+            Self::MARK_PARITY => write!(f, "MARK_PARITY"),
+            Self::SPACE_PARITY => write!(f, "SPACE_PARITY"),
+            _ => write!(f, "Parity({:#04X})", self.0),
+        }
+    }
+}
+
+/// SERIAL_TIMEOUTS
+///
+/// Input of [`PortIoCtlCode::IOCTL_SERIAL_SET_TIMEOUTS`], and output of [`PortIoCtlCode::IOCTL_SERIAL_GET_TIMEOUTS`].
+/// All the timeouts are in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialTimeouts {
+    pub read_interval_timeout: u32,
+    pub read_total_timeout_multiplier: u32,
+    pub read_total_timeout_constant: u32,
+    pub write_total_timeout_multiplier: u32,
+    pub write_total_timeout_constant: u32,
+}
+
+impl SerialTimeouts {
+    const NAME: &'static str = "SERIAL_TIMEOUTS";
+
+    const FIXED_PART_SIZE: usize = 4 * 5;
+}
+
+impl Encode for SerialTimeouts {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_u32(self.read_interval_timeout);
+        dst.write_u32(self.read_total_timeout_multiplier);
+        dst.write_u32(self.read_total_timeout_constant);
+        dst.write_u32(self.write_total_timeout_multiplier);
+        dst.write_u32(self.write_total_timeout_constant);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl Decode<'_> for SerialTimeouts {
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            read_interval_timeout: src.read_u32(),
+            read_total_timeout_multiplier: src.read_u32(),
+            read_total_timeout_constant: src.read_u32(),
+            write_total_timeout_multiplier: src.read_u32(),
+            write_total_timeout_constant: src.read_u32(),
+        })
+    }
+}
+
+impl rpce::Encode for SerialTimeouts {}
+
+bitflags! {
+    /// Serial events to wait for
+    ///
+    /// Input of [`PortIoCtlCode::IOCTL_SERIAL_SET_WAIT_MASK`], and output of
+    /// [`PortIoCtlCode::IOCTL_SERIAL_GET_WAIT_MASK`] and [`PortIoCtlCode::IOCTL_SERIAL_WAIT_ON_MASK`], the latter
+    /// telling which of the events occurred.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SerialWaitMask: u32 {
+        const SERIAL_EV_RXCHAR = 0x0000_0001;
+        const SERIAL_EV_RXFLAG = 0x0000_0002;
+        const SERIAL_EV_TXEMPTY = 0x0000_0004;
+        const SERIAL_EV_CTS = 0x0000_0008;
+        const SERIAL_EV_DSR = 0x0000_0010;
+        const SERIAL_EV_RLSD = 0x0000_0020;
+        const SERIAL_EV_BREAK = 0x0000_0040;
+        const SERIAL_EV_ERR = 0x0000_0080;
+        const SERIAL_EV_RING = 0x0000_0100;
+        const SERIAL_EV_PERR = 0x0000_0200;
+        const SERIAL_EV_RX80FULL = 0x0000_0400;
+        const SERIAL_EV_EVENT1 = 0x0000_0800;
+        const SERIAL_EV_EVENT2 = 0x0000_1000;
+        const _ = !0;
+    }
+}
+
+impl SerialWaitMask {
+    const NAME: &'static str = "SERIAL_WAIT_MASK";
+
+    const FIXED_PART_SIZE: usize = 4 /* WaitMask */;
+}
+
+impl Encode for SerialWaitMask {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+        dst.write_u32(self.bits());
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl Decode<'_> for SerialWaitMask {
+    fn decode(src: &mut ReadCursor<'_>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self::from_bits_retain(src.read_u32()))
+    }
+}
+
+impl rpce::Encode for SerialWaitMask {}
@@ -8,14 +8,16 @@ use ironrdp_core::{
 use ironrdp_svc::SvcEncode;
 
 use self::efs::{
-    ClientDeviceListAnnounce, ClientDriveQueryDirectoryResponse, ClientDriveQueryInformationResponse,
-    ClientDriveQueryVolumeInformationResponse, ClientDriveSetInformationResponse, ClientNameRequest, CoreCapability,
-    CoreCapabilityKind, DeviceCloseResponse, DeviceControlResponse, DeviceCreateResponse, DeviceIoRequest,
-    DeviceReadResponse, DeviceWriteResponse, ServerDeviceAnnounceResponse, VersionAndIdPdu, VersionAndIdPduKind,
+    ClientDeviceListAnnounce, ClientDriveDeviceListRemove, ClientDriveQueryDirectoryResponse,
+    ClientDriveQueryInformationResponse, ClientDriveQueryVolumeInformationResponse, ClientDriveSetInformationResponse,
+    ClientNameRequest, CoreCapability, CoreCapabilityKind, DeviceCloseResponse, DeviceControlResponse,
+    DeviceCreateResponse, DeviceIoRequest, DeviceReadResponse, DeviceWriteResponse, ServerDeviceAnnounceResponse,
+    VersionAndIdPdu, VersionAndIdPduKind,
 };
 
 pub mod efs;
 pub mod esc;
+pub mod esp;
 
 /// All available RDPDR PDUs.
 pub enum RdpdrPdu {
@@ -23,6 +25,7 @@ pub enum RdpdrPdu {
     ClientNameRequest(ClientNameRequest),
     CoreCapability(CoreCapability),
     ClientDeviceListAnnounce(ClientDeviceListAnnounce),
+    ClientDriveDeviceListRemove(ClientDriveDeviceListRemove),
     ServerDeviceAnnounceResponse(ServerDeviceAnnounceResponse),
     DeviceIoRequest(DeviceIoRequest),
     DeviceControlResponse(DeviceControlResponse),
@@ -73,6 +76,10 @@ impl RdpdrPdu {
                 component: Component::RdpdrCtypCore,
                 packet_id: PacketId::CoreDevicelistAnnounce,
             },
+            RdpdrPdu::ClientDriveDeviceListRemove(_) => SharedHeader {
+                component: Component::RdpdrCtypCore,
+                packet_id: PacketId::CoreDevicelistRemove,
+            },
             RdpdrPdu::ServerDeviceAnnounceResponse(_) => SharedHeader {
                 component: Component::RdpdrCtypCore,
                 packet_id: PacketId::CoreDeviceReply,
@@ -109,6 +116,9 @@ impl Decode<'_> for RdpdrPdu {
                 ServerDeviceAnnounceResponse::decode(src)?,
             )),
             PacketId::CoreDeviceIoRequest => Ok(RdpdrPdu::DeviceIoRequest(DeviceIoRequest::decode(src)?)),
+            PacketId::CoreDevicelistRemove => Ok(RdpdrPdu::ClientDriveDeviceListRemove(
+                ClientDriveDeviceListRemove::decode(src)?,
+            )),
             _ => Err(unsupported_value_err!(
                 "RdpdrPdu",
                 "PacketId",
@@ -127,6 +137,7 @@ impl Encode for RdpdrPdu {
             RdpdrPdu::ClientNameRequest(pdu) => pdu.encode(dst),
             RdpdrPdu::CoreCapability(pdu) => pdu.encode(dst),
             RdpdrPdu::ClientDeviceListAnnounce(pdu) => pdu.encode(dst),
+            RdpdrPdu::ClientDriveDeviceListRemove(pdu) => pdu.encode(dst),
             RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.encode(dst),
             RdpdrPdu::DeviceIoRequest(pdu) => pdu.encode(dst),
             RdpdrPdu::DeviceControlResponse(pdu) => pdu.encode(dst),
@@ -152,6 +163,7 @@ impl Encode for RdpdrPdu {
             RdpdrPdu::ClientNameRequest(pdu) => pdu.name(),
             RdpdrPdu::CoreCapability(pdu) => pdu.name(),
             RdpdrPdu::ClientDeviceListAnnounce(pdu) => pdu.name(),
+            RdpdrPdu::ClientDriveDeviceListRemove(pdu) => pdu.name(),
             RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.name(),
             RdpdrPdu::DeviceIoRequest(pdu) => pdu.name(),
             RdpdrPdu::DeviceControlResponse(pdu) => pdu.name(),
@@ -174,6 +186,7 @@ impl Encode for RdpdrPdu {
                 RdpdrPdu::ClientNameRequest(pdu) => pdu.size(),
                 RdpdrPdu::CoreCapability(pdu) => pdu.size(),
                 RdpdrPdu::ClientDeviceListAnnounce(pdu) => pdu.size(),
+                RdpdrPdu::ClientDriveDeviceListRemove(pdu) => pdu.size(),
                 RdpdrPdu::ServerDeviceAnnounceResponse(pdu) => pdu.size(),
                 RdpdrPdu::DeviceIoRequest(pdu) => pdu.size(),
                 RdpdrPdu::DeviceControlResponse(pdu) => pdu.size(),
@@ -207,6 +220,9 @@ impl fmt::Debug for RdpdrPdu {
             Self::ClientDeviceListAnnounce(it) => {
                 write!(f, "RdpdrPdu({:?})", it)
             }
+            Self::ClientDriveDeviceListRemove(it) => {
+                write!(f, "RdpdrPdu({:?})", it)
+            }
             Self::ServerDeviceAnnounceResponse(it) => {
                 write!(f, "RdpdrPdu({:?})", it)
             }
@@ -0,0 +1,155 @@
+//! Access policy of the redirected drives, enforced before the I/O requests reach the [`RdpdrBackend`].
+//!
+//! [`RdpdrBackend`]: crate::RdpdrBackend
+
+use ironrdp_pdu::{encode_err, PduResult};
+
+use crate::pdu::efs::{
+    ClientDriveQueryDirectoryResponse, ClientDriveSetInformationResponse, CreateDisposition, CreateOptions,
+    DesiredAccess, DeviceCreateResponse, DeviceIoResponse, DeviceWriteResponse, FileInformationClass, Information,
+    NtStatus, ServerDriveIoRequest,
+};
+use crate::pdu::RdpdrPdu;
+
+/// Policy applied to the I/O requests targeting a redirected drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrivePolicy {
+    /// Denies the requests modifying the drive: opening a file for writing, creating, writing to, renaming or
+    /// deleting a file, and changing its attributes.
+    pub read_only: bool,
+    /// Whether symbolic links inside the drive may be followed.
+    ///
+    /// The links can only be resolved on the local file system, so this part of the policy is enforced by the
+    /// backend, see [`RdpdrBackend::set_drive_policy`](crate::RdpdrBackend::set_drive_policy).
+    pub follow_symlinks: bool,
+}
+
+impl Default for DrivePolicy {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            follow_symlinks: true,
+        }
+    }
+}
+
+impl DrivePolicy {
+    const WRITE_ACCESS: DesiredAccess = DesiredAccess::FILE_WRITE_DATA_OR_FILE_ADD_FILE
+        .union(DesiredAccess::FILE_APPEND_DATA_OR_FILE_ADD_SUBDIRECTORY)
+        .union(DesiredAccess::FILE_WRITE_EA)
+        .union(DesiredAccess::FILE_DELETE_CHILD)
+        .union(DesiredAccess::FILE_WRITE_ATTRIBUTES)
+        .union(DesiredAccess::DELETE)
+        .union(DesiredAccess::WRITE_DAC)
+        .union(DesiredAccess::WRITE_OWNER)
+        .union(DesiredAccess::GENERIC_ALL)
+        .union(DesiredAccess::GENERIC_WRITE);
+
+    /// Normalizes the paths of the request in place, see [`normalize_path`].
+    ///
+    /// Returns the STATUS_ACCESS_DENIED response to send back instead of handling the request if it violates the
+    /// policy or contains an invalid path.
+    pub(crate) fn enforce(&self, req: &mut ServerDriveIoRequest) -> PduResult<Option<RdpdrPdu>> {
+        let denied = match req {
+            ServerDriveIoRequest::ServerCreateDriveRequest(req) => {
+                let writes = req.desired_access.intersects(Self::WRITE_ACCESS)
+                    || req.create_disposition != CreateDisposition::FILE_OPEN
+                    || req.create_options.contains(CreateOptions::FILE_DELETE_ON_CLOSE);
+
+                !normalize_path_in_place(&mut req.path) || (self.read_only && writes)
+            }
+            ServerDriveIoRequest::ServerDriveQueryDirectoryRequest(req) => !normalize_path_in_place(&mut req.path),
+            ServerDriveIoRequest::ServerDriveSetInformationRequest(req) => {
+                let valid = match &mut req.set_buffer {
+                    FileInformationClass::Rename(rename) => normalize_path_in_place(&mut rename.file_name),
+                    _ => true,
+                };
+
+                !valid || self.read_only
+            }
+            ServerDriveIoRequest::DeviceWriteRequest(_) => self.read_only,
+            _ => false,
+        };
+
+        if !denied {
+            return Ok(None);
+        }
+
+        warn!(?req, "Denied drive I/O request");
+
+        let response = match req {
+            ServerDriveIoRequest::ServerCreateDriveRequest(req) => {
+                RdpdrPdu::DeviceCreateResponse(DeviceCreateResponse {
+                    device_io_reply: DeviceIoResponse::new(req.device_io_request.clone(), NtStatus::ACCESS_DENIED),
+                    file_id: 0,
+                    information: Information::empty(),
+                })
+            }
+            ServerDriveIoRequest::ServerDriveQueryDirectoryRequest(req) => {
+                RdpdrPdu::ClientDriveQueryDirectoryResponse(ClientDriveQueryDirectoryResponse {
+                    device_io_reply: DeviceIoResponse::new(req.device_io_request.clone(), NtStatus::ACCESS_DENIED),
+                    buffer: None,
+                })
+            }
+            ServerDriveIoRequest::ServerDriveSetInformationRequest(req) => RdpdrPdu::ClientDriveSetInformationResponse(
+                ClientDriveSetInformationResponse::new(req, NtStatus::ACCESS_DENIED).map_err(|e| encode_err!(e))?,
+            ),
+            ServerDriveIoRequest::DeviceWriteRequest(req) => RdpdrPdu::DeviceWriteResponse(DeviceWriteResponse {
+                device_io_reply: DeviceIoResponse::new(req.device_io_request.clone(), NtStatus::ACCESS_DENIED),
+                length: 0,
+            }),
+            _ => unreachable!("only the requests above can be denied"),
+        };
+
+        Ok(Some(response))
+    }
+}
+
+/// Normalizes a path received from the server, relative to the root of the drive.
+///
+/// Both `\` and `/` are accepted as separators, empty and `.` components are removed, and the trailing dots and
+/// spaces of each component are stripped, as Windows does. The result uses `\` as separator and starts with it,
+/// unless the path is empty.
+///
+/// Returns `None` if the path could escape the drive or designate something else than a file: a `..` component, a
+/// component made of dots and spaces only, or containing a `:` (drive letter or alternate data stream) or a NUL
+/// character.
+pub fn normalize_path(path: &str) -> Option<String> {
+    if path.is_empty() {
+        return Some(String::new());
+    }
+
+    let mut normalized = String::with_capacity(path.len() + 1);
+
+    for component in path.split(['\\', '/']) {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+
+        let component = component.trim_end_matches(['.', ' ']);
+
+        if component.is_empty() || component.contains([':', '\0']) {
+            return None;
+        }
+
+        normalized.push('\\');
+        normalized.push_str(component);
+    }
+
+    if normalized.is_empty() {
+        normalized.push('\\');
+    }
+
+    Some(normalized)
+}
+
+/// Returns `false` if the path is invalid, leaving it untouched.
+fn normalize_path_in_place(path: &mut String) -> bool {
+    match normalize_path(path) {
+        Some(normalized) => {
+            *path = normalized;
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,274 @@
+use ironrdp_core::{impl_as_any, BufPool, Decode, EncodeResult, ReadCursor};
+use ironrdp_dvc::{encode_dvc_messages_pooled, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+
+use crate::contact::{ContactPhase, ContactTracker};
+use crate::pdu::{
+    ContactData, CsReadyFlags, CsReadyPdu, PenContactData, PenEventPdu, PenFrame, ProtocolVersion, RdpeiPdu,
+    ScReadyPdu, TouchEventPdu, TouchFrame,
+};
+use crate::CHANNEL_NAME;
+
+/// Touch contact, as reported by the input source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchContact {
+    /// Identifier of the contact in the input source, e.g.: the `pointerId` of a DOM pointer event.
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub phase: ContactPhase,
+}
+
+/// Pen state, as reported by the input source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenContact {
+    pub x: i32,
+    pub y: i32,
+    /// Pressure, from 0 to 1024. The pen is hovering when the pressure is zero.
+    pub pressure: u32,
+    /// Tilt along the X axis in degrees, from -90 to 90.
+    pub tilt_x: i16,
+    /// Tilt along the Y axis in degrees, from -90 to 90.
+    pub tilt_y: i16,
+}
+
+/// A client for the Input Virtual Channel.
+///
+/// The state of each contact is tracked locally, so that the frames sent to the server are always following the
+/// contact state diagram of the specification, regardless of the ordering guarantees of the input source.
+#[derive(Debug)]
+pub struct RdpeiClient {
+    /// The ready PDU received from the server, if any.
+    server_ready: Option<ScReadyPdu>,
+    /// Indicates whether the server asked to stop sending input.
+    suspended: bool,
+    touch_contacts: ContactTracker,
+    pen_contacts: ContactTracker,
+    /// Timestamp of the last frame sent, in microseconds.
+    last_touch_frame: Option<u64>,
+    last_pen_frame: Option<u64>,
+    /// Buffers reused to encode the input events.
+    buf_pool: BufPool,
+}
+
+impl RdpeiClient {
+    /// The version of the protocol implemented by this client.
+    pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V300;
+
+    pub const DEFAULT_MAX_TOUCH_CONTACTS: u8 = 10;
+
+    /// Identifier of the pen contact, used when reporting the pen as a touch contact.
+    const PEN_EXTERNAL_ID: u32 = u32::MAX;
+
+    /// Creates a new [`RdpeiClient`] supporting up to `max_touch_contacts` simultaneous touch contacts.
+    pub fn new(max_touch_contacts: u8) -> Self {
+        Self {
+            server_ready: None,
+            suspended: false,
+            touch_contacts: ContactTracker::new(max_touch_contacts),
+            pen_contacts: ContactTracker::new(1),
+            last_touch_frame: None,
+            last_pen_frame: None,
+            buf_pool: BufPool::new(),
+        }
+    }
+
+    /// Returns whether the server is ready to receive input.
+    pub fn ready(&self) -> bool {
+        self.server_ready.is_some() && !self.suspended
+    }
+
+    /// Returns whether the server supports pen frames.
+    ///
+    /// When pen frames are not supported, the pen is reported as a touch contact.
+    pub fn pen_supported(&self) -> bool {
+        self.server_ready
+            .as_ref()
+            .is_some_and(|ready| ready.protocol_version >= ProtocolVersion::V300)
+    }
+
+    /// Encodes the phase changes of touch contacts occurring at `timestamp`, in microseconds, and wraps them as
+    /// [`SvcMessage`]s.
+    ///
+    /// Returns no message if the server is not ready to receive input.
+    pub fn encode_touch_contacts(
+        &mut self,
+        channel_id: u32,
+        contacts: &[TouchContact],
+        timestamp: u64,
+    ) -> EncodeResult<Vec<SvcMessage>> {
+        if !self.ready() {
+            debug!("Touch contacts dropped: the server is not ready to receive input");
+            return Ok(Vec::new());
+        }
+
+        let mut frames: Vec<Vec<ContactData>> = Vec::new();
+
+        for contact in contacts {
+            let Some(transition) = self.touch_contacts.transition(contact.id, contact.phase) else {
+                continue;
+            };
+
+            // A contact is reported at most once per frame, so it is placed after the last frame reporting it.
+            let first_frame = frames
+                .iter()
+                .rposition(|frame| frame.iter().any(|data| data.contact_id == transition.contact_id))
+                .map_or(0, |position| position + 1);
+
+            for (index, flags) in transition.flags.iter().enumerate() {
+                let frame_index = first_frame + index;
+                if frame_index == frames.len() {
+                    frames.push(Vec::new());
+                }
+
+                frames[frame_index].push(ContactData {
+                    contact_id: transition.contact_id,
+                    x: contact.x,
+                    y: contact.y,
+                    contact_flags: *flags,
+                    contact_rect: None,
+                    orientation: None,
+                    pressure: None,
+                });
+            }
+        }
+
+        if frames.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let frame_offset = frame_offset(&mut self.last_touch_frame, timestamp);
+        let frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, contacts)| TouchFrame {
+                frame_offset: if index == 0 { frame_offset } else { 0 },
+                contacts,
+            })
+            .collect();
+
+        // Frames are encoded as soon as they are generated.
+        let pdu = RdpeiPdu::Touch(TouchEventPdu { encode_time: 0, frames });
+        trace!(?pdu, "Sending touch event");
+        encode_dvc_messages_pooled(&self.buf_pool, channel_id, vec![Box::new(pdu)], ChannelFlags::empty())
+    }
+
+    /// Encodes the state of the pen at `timestamp`, in microseconds, and wraps it as [`SvcMessage`]s.
+    ///
+    /// Returns no message if the server is not ready to receive input.
+    pub fn encode_pen(&mut self, channel_id: u32, pen: &PenContact, timestamp: u64) -> EncodeResult<Vec<SvcMessage>> {
+        let phase = if pen.pressure > 0 {
+            ContactPhase::Down
+        } else {
+            ContactPhase::Hover
+        };
+
+        if !self.pen_supported() {
+            let contact = TouchContact {
+                id: Self::PEN_EXTERNAL_ID,
+                x: pen.x,
+                y: pen.y,
+                phase,
+            };
+            return self.encode_touch_contacts(channel_id, &[contact], timestamp);
+        }
+
+        if !self.ready() {
+            debug!("Pen contact dropped: the server is not ready to receive input");
+            return Ok(Vec::new());
+        }
+
+        let Some(transition) = self.pen_contacts.transition(Self::PEN_EXTERNAL_ID, phase) else {
+            return Ok(Vec::new());
+        };
+
+        let frame_offset = frame_offset(&mut self.last_pen_frame, timestamp);
+        let frames = transition
+            .flags
+            .iter()
+            .enumerate()
+            .map(|(index, flags)| PenFrame {
+                frame_offset: if index == 0 { frame_offset } else { 0 },
+                contacts: vec![PenContactData {
+                    device_id: transition.contact_id,
+                    x: pen.x,
+                    y: pen.y,
+                    contact_flags: *flags,
+                    pen_flags: None,
+                    pressure: Some(pen.pressure.min(PenContactData::MAX_PRESSURE)),
+                    rotation: None,
+                    tilt_x: Some(pen.tilt_x.clamp(-PenContactData::MAX_TILT, PenContactData::MAX_TILT)),
+                    tilt_y: Some(pen.tilt_y.clamp(-PenContactData::MAX_TILT, PenContactData::MAX_TILT)),
+                }],
+            })
+            .collect();
+
+        let pdu = RdpeiPdu::Pen(PenEventPdu { encode_time: 0, frames });
+        trace!(?pdu, "Sending pen event");
+        encode_dvc_messages_pooled(&self.buf_pool, channel_id, vec![Box::new(pdu)], ChannelFlags::empty())
+    }
+}
+
+impl Default for RdpeiClient {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_TOUCH_CONTACTS)
+    }
+}
+
+/// Returns the time elapsed since the last frame, and records `timestamp` as the time of the last frame.
+fn frame_offset(last_frame: &mut Option<u64>, timestamp: u64) -> u64 {
+    let offset = last_frame.map_or(0, |last_frame| timestamp.saturating_sub(last_frame));
+    *last_frame = Some(timestamp);
+    offset
+}
+
+impl_as_any!(RdpeiClient);
+
+impl DvcProcessor for RdpeiClient {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu = RdpeiPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu, "Received");
+
+        match pdu {
+            RdpeiPdu::ScReady(ready) => {
+                let protocol_version = ready.protocol_version.min(Self::PROTOCOL_VERSION);
+                let max_touch_contacts = u16::from(self.touch_contacts.max_contacts());
+
+                self.server_ready = Some(ready);
+                self.suspended = false;
+
+                let response = RdpeiPdu::CsReady(CsReadyPdu {
+                    flags: CsReadyFlags::SHOW_TOUCH_VISUALS,
+                    protocol_version,
+                    max_touch_contacts,
+                });
+                debug!(?response, "Send");
+
+                Ok(vec![Box::new(response)])
+            }
+            RdpeiPdu::SuspendInput => {
+                self.suspended = true;
+                Ok(Vec::new())
+            }
+            RdpeiPdu::ResumeInput => {
+                self.suspended = false;
+                Ok(Vec::new())
+            }
+            _ => {
+                warn!(?pdu, "Unexpected PDU");
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+impl DvcClientProcessor for RdpeiClient {}
@@ -0,0 +1,164 @@
+//! Contact state tracking (3.1.1.1 Touch Contact State Transitions)
+//!
+//! Every contact reported to the server must follow the state diagram of the specification: a contact is first
+//! out of range, may be hovering, and is then engaged until lifted or canceled. Input sources, such as browsers,
+//! do not provide such guarantees, so the transitions requested by the caller are corrected locally, e.g.: a down
+//! is synthesized before an update of a contact that was never engaged.
+
+use crate::pdu::ContactFlags;
+
+/// Phase of a contact, as reported by the input source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactPhase {
+    /// The contact touched the surface.
+    Down,
+    /// The contact moved while touching the surface.
+    Update,
+    /// The contact left the surface.
+    Up,
+    /// The contact is in range, but is not touching the surface.
+    Hover,
+    /// The contact is no longer tracked by the input source.
+    Cancel,
+}
+
+/// State of a contact in the state diagram of the specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactState {
+    OutOfRange,
+    Hovering,
+    Engaged,
+}
+
+/// Contact flags to report for a single phase change
+///
+/// Most phase changes are reported with a single set of flags. When the requested change is not allowed by the
+/// state diagram, the missing transition is synthesized and reported first, in a preceding frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContactTransition {
+    /// Identifier of the contact on the wire.
+    pub contact_id: u8,
+    pub flags: &'static [ContactFlags],
+}
+
+#[derive(Debug)]
+struct TrackedContact {
+    external_id: u32,
+    contact_id: u8,
+    state: ContactState,
+}
+
+/// Tracks the state of the contacts, identified by the IDs of the input source
+///
+/// The IDs of the input source, e.g.: the `pointerId` of DOM pointer events, are mapped to the lowest contact ID
+/// available on the wire. A contact ID is released as soon as its contact goes out of range.
+#[derive(Debug)]
+pub struct ContactTracker {
+    max_contacts: u8,
+    contacts: Vec<TrackedContact>,
+}
+
+impl ContactTracker {
+    /// Creates a tracker for at most `max_contacts` simultaneous contacts.
+    pub fn new(max_contacts: u8) -> Self {
+        Self {
+            max_contacts,
+            contacts: Vec::new(),
+        }
+    }
+
+    pub fn max_contacts(&self) -> u8 {
+        self.max_contacts
+    }
+
+    /// Returns the state of the contact identified by `external_id`.
+    pub fn state(&self, external_id: u32) -> ContactState {
+        self.contacts
+            .iter()
+            .find(|contact| contact.external_id == external_id)
+            .map_or(ContactState::OutOfRange, |contact| contact.state)
+    }
+
+    /// Returns the number of contacts currently in range.
+    pub fn len(&self) -> usize {
+        self.contacts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+
+    /// Applies the phase change of the contact identified by `external_id`, and returns the flags to report.
+    ///
+    /// Returns `None` if there is nothing to report, either because the change is meaningless for a contact out of
+    /// range or because the maximum number of contacts is reached.
+    pub fn transition(&mut self, external_id: u32, phase: ContactPhase) -> Option<ContactTransition> {
+        let position = self
+            .contacts
+            .iter()
+            .position(|contact| contact.external_id == external_id);
+        let state = position.map_or(ContactState::OutOfRange, |position| self.contacts[position].state);
+
+        let (flags, new_state): (&'static [ContactFlags], ContactState) = match (state, phase) {
+            (ContactState::OutOfRange | ContactState::Hovering, ContactPhase::Down) => {
+                (&[ContactFlags::ENGAGE], ContactState::Engaged)
+            }
+            (ContactState::OutOfRange | ContactState::Hovering, ContactPhase::Update) => {
+                (&[ContactFlags::ENGAGE, ContactFlags::MOVE], ContactState::Engaged)
+            }
+            (ContactState::Engaged, ContactPhase::Down | ContactPhase::Update) => {
+                (&[ContactFlags::MOVE], ContactState::Engaged)
+            }
+            (ContactState::OutOfRange | ContactState::Hovering, ContactPhase::Hover) => {
+                (&[ContactFlags::HOVER], ContactState::Hovering)
+            }
+            (ContactState::Engaged, ContactPhase::Hover) => (&[ContactFlags::LIFT], ContactState::Hovering),
+            (ContactState::Engaged, ContactPhase::Up) => (&[ContactFlags::RELEASE], ContactState::OutOfRange),
+            (ContactState::Hovering, ContactPhase::Up) => (&[ContactFlags::LEAVE], ContactState::OutOfRange),
+            (ContactState::Engaged, ContactPhase::Cancel) => {
+                (&[ContactFlags::CANCEL_ENGAGED], ContactState::OutOfRange)
+            }
+            (ContactState::Hovering, ContactPhase::Cancel) => {
+                (&[ContactFlags::CANCEL_HOVERING], ContactState::OutOfRange)
+            }
+            (ContactState::OutOfRange, ContactPhase::Up | ContactPhase::Cancel) => {
+                debug!(external_id, ?phase, "Ignored phase change of a contact out of range");
+                return None;
+            }
+        };
+
+        let contact_id = match position {
+            Some(position) => {
+                let contact_id = self.contacts[position].contact_id;
+                if new_state == ContactState::OutOfRange {
+                    self.contacts.remove(position);
+                } else {
+                    self.contacts[position].state = new_state;
+                }
+                contact_id
+            }
+            None => {
+                let Some(contact_id) =
+                    (0..self.max_contacts).find(|id| self.contacts.iter().all(|contact| contact.contact_id != *id))
+                else {
+                    warn!(
+                        external_id,
+                        max_contacts = self.max_contacts,
+                        "Too many contacts, ignoring"
+                    );
+                    return None;
+                };
+
+                self.contacts.push(TrackedContact {
+                    external_id,
+                    contact_id,
+                    state: new_state,
+                });
+
+                contact_id
+            }
+        };
+
+        Some(ContactTransition { contact_id, flags })
+    }
+}
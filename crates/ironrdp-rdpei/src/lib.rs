@@ -0,0 +1,11 @@
+#![doc = include_str!("../README.md")]
+#![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
+
+#[macro_use]
+extern crate tracing;
+
+pub const CHANNEL_NAME: &str = "Microsoft::Windows::RDS::Input";
+
+pub mod client;
+pub mod contact;
+pub mod pdu;
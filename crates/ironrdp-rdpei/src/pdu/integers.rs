@@ -0,0 +1,154 @@
+//! Variable length integers (2.2.2 Variable-Length Integer Types)
+//!
+//! The first byte of an encoded integer starts with the number of additional bytes, followed by the sign bit for
+//! the signed types. The magnitude of the value is stored big-endian in the remaining bits.
+
+use ironrdp_core::{ensure_size, invalid_field_err, DecodeResult, EncodeResult, ReadCursor, WriteCursor};
+
+pub(crate) struct VarInt {
+    name: &'static str,
+    /// Largest magnitude which can be encoded on each possible length.
+    maxima: &'static [u64],
+    signed: bool,
+}
+
+pub(crate) const TWO_BYTE_UNSIGNED: VarInt = VarInt {
+    name: "TWO_BYTE_UNSIGNED_INTEGER",
+    maxima: &[0x7F, 0x7FFF],
+    signed: false,
+};
+
+pub(crate) const TWO_BYTE_SIGNED: VarInt = VarInt {
+    name: "TWO_BYTE_SIGNED_INTEGER",
+    maxima: &[0x3F, 0x3FFF],
+    signed: true,
+};
+
+pub(crate) const FOUR_BYTE_UNSIGNED: VarInt = VarInt {
+    name: "FOUR_BYTE_UNSIGNED_INTEGER",
+    maxima: &[0x3F, 0x3FFF, 0x3F_FFFF, 0x3FFF_FFFF],
+    signed: false,
+};
+
+pub(crate) const FOUR_BYTE_SIGNED: VarInt = VarInt {
+    name: "FOUR_BYTE_SIGNED_INTEGER",
+    maxima: &[0x1F, 0x1FFF, 0x1F_FFFF, 0x1FFF_FFFF],
+    signed: true,
+};
+
+pub(crate) const EIGHT_BYTE_UNSIGNED: VarInt = VarInt {
+    name: "EIGHT_BYTE_UNSIGNED_INTEGER",
+    maxima: &[
+        0x1F,
+        0x1FFF,
+        0x1F_FFFF,
+        0x1FFF_FFFF,
+        0x1F_FFFF_FFFF,
+        0x1FFF_FFFF_FFFF,
+        0x1F_FFFF_FFFF_FFFF,
+        0x1FFF_FFFF_FFFF_FFFF,
+    ],
+    signed: false,
+};
+
+impl VarInt {
+    /// Position of the field holding the number of additional bytes.
+    fn length_shift(&self) -> u32 {
+        8 - self.maxima.len().trailing_zeros()
+    }
+
+    fn sign_bit(&self) -> u8 {
+        if self.signed {
+            1 << (self.length_shift() - 1)
+        } else {
+            0
+        }
+    }
+
+    fn first_byte_mask(&self) -> u8 {
+        u8::try_from(self.maxima[0]).expect("first byte maximum fits in a byte")
+    }
+
+    pub(crate) fn max(&self) -> u64 {
+        self.maxima[self.maxima.len() - 1]
+    }
+
+    /// Returns the encoded size of `magnitude`, clamped to the maximum value.
+    fn size_of(&self, magnitude: u64) -> usize {
+        self.maxima
+            .iter()
+            .position(|&max| magnitude <= max)
+            .map_or(self.maxima.len(), |index| index + 1)
+    }
+
+    fn encode(&self, dst: &mut WriteCursor<'_>, magnitude: u64, negative: bool) -> EncodeResult<()> {
+        if magnitude > self.max() {
+            return Err(invalid_field_err!(self.name, "value", "out of range"));
+        }
+
+        let len = self.size_of(magnitude);
+        ensure_size!(ctx: self.name, in: dst, size: len);
+
+        let bytes = magnitude.to_be_bytes();
+        let bytes = &bytes[bytes.len() - len..];
+
+        let mut first = bytes[0] | (u8::try_from(len - 1).expect("at most 7 additional bytes") << self.length_shift());
+        if negative {
+            first |= self.sign_bit();
+        }
+
+        dst.write_u8(first);
+        dst.write_slice(&bytes[1..]);
+
+        Ok(())
+    }
+
+    fn decode(&self, src: &mut ReadCursor<'_>) -> DecodeResult<(u64, bool)> {
+        ensure_size!(ctx: self.name, in: src, size: 1);
+        let first = src.read_u8();
+
+        let additional = usize::from(first >> self.length_shift());
+        ensure_size!(ctx: self.name, in: src, size: additional);
+
+        let negative = first & self.sign_bit() != 0;
+        let mut magnitude = u64::from(first & self.first_byte_mask());
+        for _ in 0..additional {
+            magnitude = (magnitude << 8) | u64::from(src.read_u8());
+        }
+
+        Ok((magnitude, negative))
+    }
+
+    pub(crate) fn unsigned_size(&self, value: impl Into<u64>) -> usize {
+        self.size_of(value.into())
+    }
+
+    pub(crate) fn signed_size(&self, value: impl Into<i64>) -> usize {
+        self.size_of(value.into().unsigned_abs())
+    }
+
+    pub(crate) fn write_unsigned(&self, dst: &mut WriteCursor<'_>, value: impl Into<u64>) -> EncodeResult<()> {
+        self.encode(dst, value.into(), false)
+    }
+
+    pub(crate) fn write_signed(&self, dst: &mut WriteCursor<'_>, value: impl Into<i64>) -> EncodeResult<()> {
+        let value = value.into();
+        self.encode(dst, value.unsigned_abs(), value < 0)
+    }
+
+    pub(crate) fn read_unsigned<T: TryFrom<u64>>(&self, src: &mut ReadCursor<'_>) -> DecodeResult<T> {
+        let (magnitude, _) = self.decode(src)?;
+
+        T::try_from(magnitude).map_err(|_| invalid_field_err!(self.name, "value", "out of range"))
+    }
+
+    pub(crate) fn read_signed<T: TryFrom<i64>>(&self, src: &mut ReadCursor<'_>) -> DecodeResult<T> {
+        let (magnitude, negative) = self.decode(src)?;
+
+        // The magnitude is at most 29 bits long for the signed types.
+        let value = i64::try_from(magnitude).map_err(|_| invalid_field_err!(self.name, "value", "out of range"))?;
+        let value = if negative { -value } else { value };
+
+        T::try_from(value).map_err(|_| invalid_field_err!(self.name, "value", "out of range"))
+    }
+}
@@ -0,0 +1,847 @@
+//! Input Virtual Channel Extension PDUs [MS-RDPEI][1] implementation.
+//!
+//! [1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpei/
+
+mod integers;
+
+use bitflags::bitflags;
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, invalid_field_err, unsupported_value_err, Decode, DecodeResult,
+    Encode, EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+
+use self::integers::{EIGHT_BYTE_UNSIGNED, FOUR_BYTE_SIGNED, FOUR_BYTE_UNSIGNED, TWO_BYTE_SIGNED, TWO_BYTE_UNSIGNED};
+
+const EVENTID_SC_READY: u16 = 0x0001;
+const EVENTID_CS_READY: u16 = 0x0002;
+const EVENTID_TOUCH: u16 = 0x0003;
+const EVENTID_SUSPEND_INPUT: u16 = 0x0004;
+const EVENTID_RESUME_INPUT: u16 = 0x0005;
+const EVENTID_DISMISS_HOVERING_TOUCH_CONTACT: u16 = 0x0006;
+const EVENTID_PEN: u16 = 0x0008;
+
+const CONTACT_DATA_CONTACTRECT_PRESENT: u16 = 0x0001;
+const CONTACT_DATA_ORIENTATION_PRESENT: u16 = 0x0002;
+const CONTACT_DATA_PRESSURE_PRESENT: u16 = 0x0004;
+
+const PEN_CONTACT_PENFLAGS_PRESENT: u16 = 0x0001;
+const PEN_CONTACT_PRESSURE_PRESENT: u16 = 0x0002;
+const PEN_CONTACT_ROTATION_PRESENT: u16 = 0x0004;
+const PEN_CONTACT_TILTX_PRESENT: u16 = 0x0008;
+const PEN_CONTACT_TILTY_PRESENT: u16 = 0x0010;
+
+/// Input DVC message, preceded by a RDPINPUT_HEADER
+///
+/// Both directions are covered by this type, as event IDs are not overlapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RdpeiPdu {
+    ScReady(ScReadyPdu),
+    CsReady(CsReadyPdu),
+    Touch(TouchEventPdu),
+    SuspendInput,
+    ResumeInput,
+    DismissHoveringTouchContact(DismissHoveringTouchContactPdu),
+    Pen(PenEventPdu),
+}
+
+impl RdpeiPdu {
+    const NAME: &'static str = "RDPINPUT_HEADER";
+
+    const FIXED_PART_SIZE: usize = 2 /* eventId */ + 4 /* pduLength */;
+
+    fn event_id(&self) -> u16 {
+        match self {
+            Self::ScReady(_) => EVENTID_SC_READY,
+            Self::CsReady(_) => EVENTID_CS_READY,
+            Self::Touch(_) => EVENTID_TOUCH,
+            Self::SuspendInput => EVENTID_SUSPEND_INPUT,
+            Self::ResumeInput => EVENTID_RESUME_INPUT,
+            Self::DismissHoveringTouchContact(_) => EVENTID_DISMISS_HOVERING_TOUCH_CONTACT,
+            Self::Pen(_) => EVENTID_PEN,
+        }
+    }
+
+    fn body(&self) -> Option<&dyn Encode> {
+        match self {
+            Self::ScReady(pdu) => Some(pdu),
+            Self::CsReady(pdu) => Some(pdu),
+            Self::Touch(pdu) => Some(pdu),
+            Self::SuspendInput | Self::ResumeInput => None,
+            Self::DismissHoveringTouchContact(pdu) => Some(pdu),
+            Self::Pen(pdu) => Some(pdu),
+        }
+    }
+}
+
+impl Encode for RdpeiPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u16(self.event_id());
+        dst.write_u32(cast_length!("pduLength", self.size())?);
+
+        if let Some(body) = self.body() {
+            body.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.body().map_or(0, |body| body.size())
+    }
+}
+
+impl DvcEncode for RdpeiPdu {}
+
+impl<'de> Decode<'de> for RdpeiPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let event_id = src.read_u16();
+        let pdu_length: usize = cast_length!("pduLength", src.read_u32())?;
+
+        let body_length = pdu_length
+            .checked_sub(Self::FIXED_PART_SIZE)
+            .ok_or_else(|| invalid_field_err!("pduLength", "smaller than the header"))?;
+        ensure_size!(in: src, size: body_length);
+
+        // The PDU length is bounding the body, so that the optional trailing fields can be detected.
+        let mut body = ReadCursor::new(src.read_slice(body_length));
+
+        let pdu = match event_id {
+            EVENTID_SC_READY => Self::ScReady(ScReadyPdu::decode(&mut body)?),
+            EVENTID_CS_READY => Self::CsReady(CsReadyPdu::decode(&mut body)?),
+            EVENTID_TOUCH => Self::Touch(TouchEventPdu::decode(&mut body)?),
+            EVENTID_SUSPEND_INPUT => Self::SuspendInput,
+            EVENTID_RESUME_INPUT => Self::ResumeInput,
+            EVENTID_DISMISS_HOVERING_TOUCH_CONTACT => {
+                Self::DismissHoveringTouchContact(DismissHoveringTouchContactPdu::decode(&mut body)?)
+            }
+            EVENTID_PEN => Self::Pen(PenEventPdu::decode(&mut body)?),
+            _ => return Err(unsupported_value_err!("eventId", format!("{event_id:#06x}"))),
+        };
+
+        Ok(pdu)
+    }
+}
+
+/// Version of the protocol announced in the ready PDUs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtocolVersion(pub u32);
+
+impl ProtocolVersion {
+    pub const V100: Self = Self(0x0001_0000);
+    pub const V101: Self = Self(0x0001_0001);
+    pub const V200: Self = Self(0x0002_0000);
+    pub const V300: Self = Self(0x0003_0000);
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ScReadyFeatures: u32 {
+        const MULTIPEN_INJECTION_SUPPORTED = 0x0000_0001;
+        const _ = !0;
+    }
+}
+
+/// RDPINPUT_SC_READY_PDU, sent by the server to start the input exchange
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScReadyPdu {
+    pub protocol_version: ProtocolVersion,
+    /// Only sent by servers supporting [`ProtocolVersion::V300`].
+    pub supported_features: Option<ScReadyFeatures>,
+}
+
+impl ScReadyPdu {
+    const NAME: &'static str = "RDPINPUT_SC_READY_PDU";
+
+    const FIXED_PART_SIZE: usize = 4 /* protocolVersion */;
+}
+
+impl Encode for ScReadyPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.protocol_version.0);
+        if let Some(supported_features) = self.supported_features {
+            dst.write_u32(supported_features.bits());
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.supported_features.map_or(0, |_| 4)
+    }
+}
+
+impl<'de> Decode<'de> for ScReadyPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let protocol_version = ProtocolVersion(src.read_u32());
+        let supported_features = if src.len() >= 4 {
+            Some(ScReadyFeatures::from_bits_retain(src.read_u32()))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            protocol_version,
+            supported_features,
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CsReadyFlags: u32 {
+        const SHOW_TOUCH_VISUALS = 0x0000_0001;
+        const DISABLE_TIMESTAMP_INJECTION = 0x0000_0002;
+        const ENABLE_MULTIPEN_INJECTION = 0x0000_0004;
+        const _ = !0;
+    }
+}
+
+/// RDPINPUT_CS_READY_PDU, sent by the client in response to [`ScReadyPdu`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsReadyPdu {
+    pub flags: CsReadyFlags,
+    pub protocol_version: ProtocolVersion,
+    pub max_touch_contacts: u16,
+}
+
+impl CsReadyPdu {
+    const NAME: &'static str = "RDPINPUT_CS_READY_PDU";
+
+    const FIXED_PART_SIZE: usize = 4 /* flags */ + 4 /* protocolVersion */ + 2 /* maxTouchContacts */;
+}
+
+impl Encode for CsReadyPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.flags.bits());
+        dst.write_u32(self.protocol_version.0);
+        dst.write_u16(self.max_touch_contacts);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for CsReadyPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            flags: CsReadyFlags::from_bits_retain(src.read_u32()),
+            protocol_version: ProtocolVersion(src.read_u32()),
+            max_touch_contacts: src.read_u16(),
+        })
+    }
+}
+
+bitflags! {
+    /// State of a touch or pen contact
+    ///
+    /// Only a few combinations are valid, see [`ContactFlags::is_valid`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ContactFlags: u32 {
+        const DOWN = 0x0000_0001;
+        const UPDATE = 0x0000_0002;
+        const UP = 0x0000_0004;
+        const IN_RANGE = 0x0000_0008;
+        const IN_CONTACT = 0x0000_0010;
+        const CANCELED = 0x0000_0020;
+        const _ = !0;
+    }
+}
+
+impl ContactFlags {
+    /// Contact engaged, from the out of range or hovering states.
+    pub const ENGAGE: Self = Self::DOWN.union(Self::IN_RANGE).union(Self::IN_CONTACT);
+    /// Engaged contact moving.
+    pub const MOVE: Self = Self::UPDATE.union(Self::IN_RANGE).union(Self::IN_CONTACT);
+    /// Contact hovering, from the out of range or hovering states.
+    pub const HOVER: Self = Self::UPDATE.union(Self::IN_RANGE);
+    /// Engaged contact lifted, and still hovering.
+    pub const LIFT: Self = Self::UP.union(Self::IN_RANGE);
+    /// Engaged contact lifted, and out of range.
+    pub const RELEASE: Self = Self::UP;
+    /// Hovering contact going out of range.
+    pub const LEAVE: Self = Self::UPDATE;
+    /// Engaged contact canceled.
+    pub const CANCEL_ENGAGED: Self = Self::UP.union(Self::CANCELED);
+    /// Hovering contact canceled.
+    pub const CANCEL_HOVERING: Self = Self::UPDATE.union(Self::CANCELED);
+
+    /// Returns whether the flags are one of the combinations allowed by the contact state diagram.
+    pub fn is_valid(self) -> bool {
+        [
+            Self::ENGAGE,
+            Self::MOVE,
+            Self::HOVER,
+            Self::LIFT,
+            Self::RELEASE,
+            Self::LEAVE,
+            Self::CANCEL_ENGAGED,
+            Self::CANCEL_HOVERING,
+        ]
+        .contains(&self)
+    }
+}
+
+/// Bounding box of a touch contact, relative to its position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactRect {
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+/// RDPINPUT_CONTACT_DATA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactData {
+    pub contact_id: u8,
+    pub x: i32,
+    pub y: i32,
+    pub contact_flags: ContactFlags,
+    pub contact_rect: Option<ContactRect>,
+    /// Orientation in degrees, from 0 to 359.
+    pub orientation: Option<u32>,
+    /// Pressure, from 0 to 1024.
+    pub pressure: Option<u32>,
+}
+
+impl ContactData {
+    const NAME: &'static str = "RDPINPUT_CONTACT_DATA";
+
+    pub const MAX_ORIENTATION: u32 = 359;
+
+    pub const MAX_PRESSURE: u32 = 1024;
+
+    fn fields_present(&self) -> u16 {
+        let mut fields_present = 0;
+        if self.contact_rect.is_some() {
+            fields_present |= CONTACT_DATA_CONTACTRECT_PRESENT;
+        }
+        if self.orientation.is_some() {
+            fields_present |= CONTACT_DATA_ORIENTATION_PRESENT;
+        }
+        if self.pressure.is_some() {
+            fields_present |= CONTACT_DATA_PRESSURE_PRESENT;
+        }
+        fields_present
+    }
+}
+
+impl Encode for ContactData {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.contact_id);
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, self.fields_present())?;
+        FOUR_BYTE_SIGNED.write_signed(dst, self.x)?;
+        FOUR_BYTE_SIGNED.write_signed(dst, self.y)?;
+        FOUR_BYTE_UNSIGNED.write_unsigned(dst, self.contact_flags.bits())?;
+
+        if let Some(rect) = &self.contact_rect {
+            TWO_BYTE_SIGNED.write_signed(dst, rect.left)?;
+            TWO_BYTE_SIGNED.write_signed(dst, rect.top)?;
+            TWO_BYTE_SIGNED.write_signed(dst, rect.right)?;
+            TWO_BYTE_SIGNED.write_signed(dst, rect.bottom)?;
+        }
+
+        if let Some(orientation) = self.orientation {
+            if orientation > Self::MAX_ORIENTATION {
+                return Err(invalid_field_err!("orientation", "out of range"));
+            }
+            FOUR_BYTE_UNSIGNED.write_unsigned(dst, orientation)?;
+        }
+
+        if let Some(pressure) = self.pressure {
+            if pressure > Self::MAX_PRESSURE {
+                return Err(invalid_field_err!("pressure", "out of range"));
+            }
+            FOUR_BYTE_UNSIGNED.write_unsigned(dst, pressure)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        1 /* contactId */
+            + TWO_BYTE_UNSIGNED.unsigned_size(self.fields_present())
+            + FOUR_BYTE_SIGNED.signed_size(self.x)
+            + FOUR_BYTE_SIGNED.signed_size(self.y)
+            + FOUR_BYTE_UNSIGNED.unsigned_size(self.contact_flags.bits())
+            + self.contact_rect.as_ref().map_or(0, |rect| {
+                TWO_BYTE_SIGNED.signed_size(rect.left)
+                    + TWO_BYTE_SIGNED.signed_size(rect.top)
+                    + TWO_BYTE_SIGNED.signed_size(rect.right)
+                    + TWO_BYTE_SIGNED.signed_size(rect.bottom)
+            })
+            + self
+                .orientation
+                .map_or(0, |orientation| FOUR_BYTE_UNSIGNED.unsigned_size(orientation))
+            + self
+                .pressure
+                .map_or(0, |pressure| FOUR_BYTE_UNSIGNED.unsigned_size(pressure))
+    }
+}
+
+impl<'de> Decode<'de> for ContactData {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 1);
+
+        let contact_id = src.read_u8();
+        let fields_present: u16 = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let x = FOUR_BYTE_SIGNED.read_signed(src)?;
+        let y = FOUR_BYTE_SIGNED.read_signed(src)?;
+        let contact_flags = ContactFlags::from_bits_retain(FOUR_BYTE_UNSIGNED.read_unsigned(src)?);
+
+        let contact_rect = if fields_present & CONTACT_DATA_CONTACTRECT_PRESENT != 0 {
+            Some(ContactRect {
+                left: TWO_BYTE_SIGNED.read_signed(src)?,
+                top: TWO_BYTE_SIGNED.read_signed(src)?,
+                right: TWO_BYTE_SIGNED.read_signed(src)?,
+                bottom: TWO_BYTE_SIGNED.read_signed(src)?,
+            })
+        } else {
+            None
+        };
+
+        let orientation = if fields_present & CONTACT_DATA_ORIENTATION_PRESENT != 0 {
+            Some(FOUR_BYTE_UNSIGNED.read_unsigned(src)?)
+        } else {
+            None
+        };
+
+        let pressure = if fields_present & CONTACT_DATA_PRESSURE_PRESENT != 0 {
+            Some(FOUR_BYTE_UNSIGNED.read_unsigned(src)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            contact_id,
+            x,
+            y,
+            contact_flags,
+            contact_rect,
+            orientation,
+            pressure,
+        })
+    }
+}
+
+/// RDPINPUT_TOUCH_FRAME, the state of the touch contacts at a given time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchFrame {
+    /// Time elapsed since the previous frame, in microseconds, or zero for the first frame.
+    pub frame_offset: u64,
+    pub contacts: Vec<ContactData>,
+}
+
+impl TouchFrame {
+    const NAME: &'static str = "RDPINPUT_TOUCH_FRAME";
+}
+
+impl Encode for TouchFrame {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        let contact_count: u16 = cast_length!("contactCount", self.contacts.len())?;
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, contact_count)?;
+        EIGHT_BYTE_UNSIGNED.write_unsigned(dst, self.frame_offset)?;
+        self.contacts.iter().try_for_each(|contact| contact.encode(dst))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        TWO_BYTE_UNSIGNED.unsigned_size(u64::try_from(self.contacts.len()).unwrap_or(u64::MAX))
+            + EIGHT_BYTE_UNSIGNED.unsigned_size(self.frame_offset)
+            + self.contacts.iter().map(Encode::size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for TouchFrame {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let contact_count: usize = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frame_offset = EIGHT_BYTE_UNSIGNED.read_unsigned(src)?;
+        let contacts = (0..contact_count)
+            .map(|_| ContactData::decode(src))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { frame_offset, contacts })
+    }
+}
+
+/// RDPINPUT_TOUCH_EVENT_PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchEventPdu {
+    /// Time elapsed between the generation of the oldest frame and the encoding of the PDU, in milliseconds.
+    pub encode_time: u32,
+    pub frames: Vec<TouchFrame>,
+}
+
+impl TouchEventPdu {
+    const NAME: &'static str = "RDPINPUT_TOUCH_EVENT_PDU";
+}
+
+impl Encode for TouchEventPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        FOUR_BYTE_UNSIGNED.write_unsigned(dst, self.encode_time)?;
+        let frame_count: u16 = cast_length!("frameCount", self.frames.len())?;
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, frame_count)?;
+        self.frames.iter().try_for_each(|frame| frame.encode(dst))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        FOUR_BYTE_UNSIGNED.unsigned_size(self.encode_time)
+            + TWO_BYTE_UNSIGNED.unsigned_size(u64::try_from(self.frames.len()).unwrap_or(u64::MAX))
+            + self.frames.iter().map(Encode::size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for TouchEventPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let encode_time = FOUR_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frame_count: usize = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frames = (0..frame_count)
+            .map(|_| TouchFrame::decode(src))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { encode_time, frames })
+    }
+}
+
+/// RDPINPUT_DISMISS_HOVERING_TOUCH_CONTACT_PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DismissHoveringTouchContactPdu {
+    pub contact_id: u8,
+}
+
+impl DismissHoveringTouchContactPdu {
+    const NAME: &'static str = "RDPINPUT_DISMISS_HOVERING_TOUCH_CONTACT_PDU";
+
+    const FIXED_PART_SIZE: usize = 1 /* contactId */;
+}
+
+impl Encode for DismissHoveringTouchContactPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u8(self.contact_id);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for DismissHoveringTouchContactPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        Ok(Self {
+            contact_id: src.read_u8(),
+        })
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PenFlags: u32 {
+        const BARREL_PRESSED = 0x0000_0001;
+        const ERASER_PRESSED = 0x0000_0002;
+        const INVERTED = 0x0000_0004;
+        const _ = !0;
+    }
+}
+
+/// RDPINPUT_PEN_CONTACT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenContactData {
+    pub device_id: u8,
+    pub x: i32,
+    pub y: i32,
+    pub contact_flags: ContactFlags,
+    pub pen_flags: Option<PenFlags>,
+    /// Pressure, from 0 to 1024.
+    pub pressure: Option<u32>,
+    /// Rotation in degrees, from 0 to 359.
+    pub rotation: Option<u16>,
+    /// Tilt along the X axis in degrees, from -90 to 90.
+    pub tilt_x: Option<i16>,
+    /// Tilt along the Y axis in degrees, from -90 to 90.
+    pub tilt_y: Option<i16>,
+}
+
+impl PenContactData {
+    const NAME: &'static str = "RDPINPUT_PEN_CONTACT";
+
+    pub const MAX_PRESSURE: u32 = 1024;
+
+    pub const MAX_ROTATION: u16 = 359;
+
+    pub const MAX_TILT: i16 = 90;
+
+    fn fields_present(&self) -> u16 {
+        let mut fields_present = 0;
+        if self.pen_flags.is_some() {
+            fields_present |= PEN_CONTACT_PENFLAGS_PRESENT;
+        }
+        if self.pressure.is_some() {
+            fields_present |= PEN_CONTACT_PRESSURE_PRESENT;
+        }
+        if self.rotation.is_some() {
+            fields_present |= PEN_CONTACT_ROTATION_PRESENT;
+        }
+        if self.tilt_x.is_some() {
+            fields_present |= PEN_CONTACT_TILTX_PRESENT;
+        }
+        if self.tilt_y.is_some() {
+            fields_present |= PEN_CONTACT_TILTY_PRESENT;
+        }
+        fields_present
+    }
+}
+
+impl Encode for PenContactData {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.device_id);
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, self.fields_present())?;
+        FOUR_BYTE_SIGNED.write_signed(dst, self.x)?;
+        FOUR_BYTE_SIGNED.write_signed(dst, self.y)?;
+        FOUR_BYTE_UNSIGNED.write_unsigned(dst, self.contact_flags.bits())?;
+
+        if let Some(pen_flags) = self.pen_flags {
+            FOUR_BYTE_UNSIGNED.write_unsigned(dst, pen_flags.bits())?;
+        }
+
+        if let Some(pressure) = self.pressure {
+            if pressure > Self::MAX_PRESSURE {
+                return Err(invalid_field_err!("pressure", "out of range"));
+            }
+            FOUR_BYTE_UNSIGNED.write_unsigned(dst, pressure)?;
+        }
+
+        if let Some(rotation) = self.rotation {
+            if rotation > Self::MAX_ROTATION {
+                return Err(invalid_field_err!("rotation", "out of range"));
+            }
+            TWO_BYTE_UNSIGNED.write_unsigned(dst, rotation)?;
+        }
+
+        for (field, tilt) in [("tiltX", self.tilt_x), ("tiltY", self.tilt_y)] {
+            if let Some(tilt) = tilt {
+                if !(-Self::MAX_TILT..=Self::MAX_TILT).contains(&tilt) {
+                    return Err(invalid_field_err!(field, "out of range"));
+                }
+                TWO_BYTE_SIGNED.write_signed(dst, tilt)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        1 /* deviceId */
+            + TWO_BYTE_UNSIGNED.unsigned_size(self.fields_present())
+            + FOUR_BYTE_SIGNED.signed_size(self.x)
+            + FOUR_BYTE_SIGNED.signed_size(self.y)
+            + FOUR_BYTE_UNSIGNED.unsigned_size(self.contact_flags.bits())
+            + self
+                .pen_flags
+                .map_or(0, |pen_flags| FOUR_BYTE_UNSIGNED.unsigned_size(pen_flags.bits()))
+            + self
+                .pressure
+                .map_or(0, |pressure| FOUR_BYTE_UNSIGNED.unsigned_size(pressure))
+            + self
+                .rotation
+                .map_or(0, |rotation| TWO_BYTE_UNSIGNED.unsigned_size(rotation))
+            + self.tilt_x.map_or(0, |tilt| TWO_BYTE_SIGNED.signed_size(tilt))
+            + self.tilt_y.map_or(0, |tilt| TWO_BYTE_SIGNED.signed_size(tilt))
+    }
+}
+
+impl<'de> Decode<'de> for PenContactData {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_size!(in: src, size: 1);
+
+        let device_id = src.read_u8();
+        let fields_present: u16 = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let x = FOUR_BYTE_SIGNED.read_signed(src)?;
+        let y = FOUR_BYTE_SIGNED.read_signed(src)?;
+        let contact_flags = ContactFlags::from_bits_retain(FOUR_BYTE_UNSIGNED.read_unsigned(src)?);
+
+        let pen_flags = if fields_present & PEN_CONTACT_PENFLAGS_PRESENT != 0 {
+            Some(PenFlags::from_bits_retain(FOUR_BYTE_UNSIGNED.read_unsigned(src)?))
+        } else {
+            None
+        };
+
+        let pressure = if fields_present & PEN_CONTACT_PRESSURE_PRESENT != 0 {
+            Some(FOUR_BYTE_UNSIGNED.read_unsigned(src)?)
+        } else {
+            None
+        };
+
+        let rotation = if fields_present & PEN_CONTACT_ROTATION_PRESENT != 0 {
+            Some(TWO_BYTE_UNSIGNED.read_unsigned(src)?)
+        } else {
+            None
+        };
+
+        let tilt_x = if fields_present & PEN_CONTACT_TILTX_PRESENT != 0 {
+            Some(TWO_BYTE_SIGNED.read_signed(src)?)
+        } else {
+            None
+        };
+
+        let tilt_y = if fields_present & PEN_CONTACT_TILTY_PRESENT != 0 {
+            Some(TWO_BYTE_SIGNED.read_signed(src)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            device_id,
+            x,
+            y,
+            contact_flags,
+            pen_flags,
+            pressure,
+            rotation,
+            tilt_x,
+            tilt_y,
+        })
+    }
+}
+
+/// RDPINPUT_PEN_FRAME, the state of the pen contacts at a given time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenFrame {
+    /// Time elapsed since the previous frame, in microseconds, or zero for the first frame.
+    pub frame_offset: u64,
+    pub contacts: Vec<PenContactData>,
+}
+
+impl PenFrame {
+    const NAME: &'static str = "RDPINPUT_PEN_FRAME";
+}
+
+impl Encode for PenFrame {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        let contact_count: u16 = cast_length!("contactCount", self.contacts.len())?;
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, contact_count)?;
+        EIGHT_BYTE_UNSIGNED.write_unsigned(dst, self.frame_offset)?;
+        self.contacts.iter().try_for_each(|contact| contact.encode(dst))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        TWO_BYTE_UNSIGNED.unsigned_size(u64::try_from(self.contacts.len()).unwrap_or(u64::MAX))
+            + EIGHT_BYTE_UNSIGNED.unsigned_size(self.frame_offset)
+            + self.contacts.iter().map(Encode::size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for PenFrame {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let contact_count: usize = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frame_offset = EIGHT_BYTE_UNSIGNED.read_unsigned(src)?;
+        let contacts = (0..contact_count)
+            .map(|_| PenContactData::decode(src))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { frame_offset, contacts })
+    }
+}
+
+/// RDPINPUT_PEN_EVENT_PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenEventPdu {
+    /// Time elapsed between the generation of the oldest frame and the encoding of the PDU, in milliseconds.
+    pub encode_time: u32,
+    pub frames: Vec<PenFrame>,
+}
+
+impl PenEventPdu {
+    const NAME: &'static str = "RDPINPUT_PEN_EVENT_PDU";
+}
+
+impl Encode for PenEventPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        FOUR_BYTE_UNSIGNED.write_unsigned(dst, self.encode_time)?;
+        let frame_count: u16 = cast_length!("frameCount", self.frames.len())?;
+        TWO_BYTE_UNSIGNED.write_unsigned(dst, frame_count)?;
+        self.frames.iter().try_for_each(|frame| frame.encode(dst))
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        FOUR_BYTE_UNSIGNED.unsigned_size(self.encode_time)
+            + TWO_BYTE_UNSIGNED.unsigned_size(u64::try_from(self.frames.len()).unwrap_or(u64::MAX))
+            + self.frames.iter().map(Encode::size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for PenEventPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let encode_time = FOUR_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frame_count: usize = TWO_BYTE_UNSIGNED.read_unsigned(src)?;
+        let frames = (0..frame_count)
+            .map(|_| PenFrame::decode(src))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { encode_time, frames })
+    }
+}
@@ -1,11 +1,12 @@
 use core::time::Duration;
-use std::sync::mpsc;
+use std::borrow::Cow;
 use std::thread;
 
 use anyhow::Context;
-use cpal::traits::StreamTrait;
+use cpal as _;
+use ironrdp_rdpsnd::client::RdpsndClientHandler as _;
 use ironrdp_rdpsnd::pdu::{AudioFormat, WaveFormat};
-use ironrdp_rdpsnd_native::cpal::make_stream;
+use ironrdp_rdpsnd_native::cpal::RdpsndBackend;
 use tracing::debug;
 
 fn setup_logging() -> anyhow::Result<()> {
@@ -31,7 +32,7 @@ fn setup_logging() -> anyhow::Result<()> {
 
 fn main() -> anyhow::Result<()> {
     setup_logging()?;
-    let rx_format = AudioFormat {
+    let format = AudioFormat {
         format: WaveFormat::PCM,
         n_channels: 2,
         n_samples_per_sec: 22050,
@@ -40,21 +41,28 @@ fn main() -> anyhow::Result<()> {
         bits_per_sample: 16,
         data: None,
     };
-    let (tx, rx) = mpsc::channel();
-    let stream = make_stream(&rx_format, rx).unwrap();
-
-    let producer = thread::spawn(move || {
-        let data_chunks = vec![vec![1u8, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
-        for chunk in data_chunks {
-            tx.send(chunk).expect("failed to send data chunk");
-            debug!("Sent a chunk");
-            thread::sleep(Duration::from_secs(1)); // Simulating work
-        }
-    });
-
-    stream.play()?;
-    thread::sleep(Duration::from_secs(3));
-    let _ = producer.join();
+    let mut backend = RdpsndBackend::new();
+
+    // Plays a 440 Hz tone for 3 seconds, in chunks of 20 ms as sent by the servers.
+    let chunk_frames = 22050 / 50;
+    for chunk in 0..150 {
+        let data: Vec<u8> = (0..chunk_frames)
+            .map(|frame| f64::from(chunk * chunk_frames + frame) / 22050.0)
+            .map(|time| (time * 440.0 * core::f64::consts::TAU).sin() * 8000.0)
+            .flat_map(|sample| {
+                #[allow(clippy::cast_possible_truncation)] // the sample is in the i16 range
+                let sample = sample as i16;
+                [sample, sample]
+            })
+            .flat_map(i16::to_le_bytes)
+            .collect();
+
+        backend.wave(&format, 0, Cow::Owned(data));
+        debug!(latency = ?backend.playback_latency(), "Sent a chunk");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    backend.close();
 
     Ok(())
 }
@@ -1,23 +1,42 @@
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::time::Duration;
 use std::borrow::Cow;
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::Arc;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
 use anyhow::{bail, Context};
-use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{SampleFormat, Stream, StreamConfig};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, OutputCallbackInfo, SampleFormat, SizedSample, Stream, StreamConfig, StreamError};
 use ironrdp_rdpsnd::client::RdpsndClientHandler;
 use ironrdp_rdpsnd::pdu::{AudioFormat, PitchPdu, VolumePdu, WaveFormat};
+use ironrdp_rdpsnd::playback::{self, JitterBuffer, Resampler};
 
+/// Minimum delay between two attempts to open the output device.
+const REOPEN_DELAY: Duration = Duration::from_secs(1);
+
+/// Plays the audio on the default output device
+///
+/// The audio is resampled to the configuration of the device, and buffered to absorb the jitter of its delivery, see
+/// [`JitterBuffer`]. When the device is lost, e.g. unplugged, the default device is opened again with the next audio
+/// data.
 #[derive(Debug)]
 pub struct RdpsndBackend {
+    target_latency: Duration,
     // Unfortunately, Stream is not `Send`, so we move it to a separate thread.
     stream_handle: Option<JoinHandle<()>>,
     stream_ended: Arc<AtomicBool>,
-    tx: Option<Sender<Vec<u8>>>,
+    /// Set by the stream when the device is no longer available.
+    device_lost: Arc<AtomicBool>,
+    output: Option<Output>,
     format: Option<AudioFormat>,
+    gains: [f32; 2],
+    last_open_attempt: Option<Instant>,
+    /// Decoded samples, reused across the wave PDUs.
+    samples: Vec<f32>,
+    /// Resampled samples, reused across the wave PDUs.
+    resampled: Vec<f32>,
 }
 
 impl Default for RdpsndBackend {
@@ -28,11 +47,86 @@ impl Default for RdpsndBackend {
 
 impl RdpsndBackend {
     pub fn new() -> Self {
+        Self::with_target_latency(JitterBuffer::DEFAULT_TARGET_LATENCY)
+    }
+
+    /// Creates a backend buffering `target_latency` of audio before playing it.
+    pub fn with_target_latency(target_latency: Duration) -> Self {
         Self {
-            tx: None,
-            format: None,
+            target_latency,
             stream_handle: None,
             stream_ended: Arc::new(AtomicBool::new(false)),
+            device_lost: Arc::new(AtomicBool::new(false)),
+            output: None,
+            format: None,
+            gains: [1.0; 2],
+            last_open_attempt: None,
+            samples: Vec::new(),
+            resampled: Vec::new(),
+        }
+    }
+
+    fn open(&mut self, format: &AudioFormat) -> anyhow::Result<()> {
+        if format.format != WaveFormat::PCM {
+            bail!("only PCM formats supported");
+        }
+
+        if !matches!(format.bits_per_sample, 8 | 16) {
+            bail!("only PCM 8/16 bits formats supported");
+        }
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        let target_latency = self.target_latency;
+        let gains = self.gains;
+        self.stream_ended.store(false, Ordering::Relaxed);
+        self.device_lost.store(false, Ordering::Relaxed);
+        let stream_ended = Arc::clone(&self.stream_ended);
+        let device_lost = Arc::clone(&self.device_lost);
+
+        self.stream_handle = Some(thread::spawn(move || {
+            let stream = match make_stream(target_latency, gains, device_lost) {
+                Ok((stream, playback)) => {
+                    let _ = tx.send(Ok(playback));
+                    stream
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+            debug!("Stream thread parking loop");
+            while !stream_ended.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            debug!("Stream thread unparked");
+            drop(stream);
+        }));
+
+        let playback = rx.recv().context("stream thread terminated")??;
+        let (channels, sample_rate) = {
+            let playback = lock(&playback);
+            (playback.buffer.channels(), playback.buffer.sample_rate())
+        };
+
+        self.output = Some(Output {
+            playback,
+            resampler: Resampler::new(
+                format.n_samples_per_sec,
+                format.n_channels,
+                sample_rate,
+                u16::try_from(channels).unwrap_or(u16::MAX),
+            ),
+        });
+
+        Ok(())
+    }
+
+    fn close_stream(&mut self) {
+        self.output = None;
+        if let Some(stream) = self.stream_handle.take() {
+            self.stream_ended.store(true, Ordering::Relaxed);
+            stream.thread().unpark();
+            stream.join().unwrap();
         }
     }
 }
@@ -47,153 +141,314 @@ impl RdpsndClientHandler for RdpsndBackend {
     fn wave(&mut self, format: &AudioFormat, _ts: u32, data: Cow<'_, [u8]>) {
         if Some(format) != self.format.as_ref() {
             debug!(?format, "New audio format");
-            self.close();
+            self.close_stream();
+            self.format = Some(format.clone());
+            self.last_open_attempt = None;
         }
 
-        if self.stream_handle.is_none() {
-            let (tx, rx) = mpsc::channel();
-            self.tx = Some(tx);
-            self.format = Some(format.clone());
-            let format = format.clone();
-            self.stream_ended.store(false, Ordering::Relaxed);
-            let stream_ended = Arc::clone(&self.stream_ended);
-            self.stream_handle = Some(thread::spawn(move || {
-                let stream = match make_stream(&format, rx) {
-                    Ok(stream) => stream,
-                    Err(e) => {
-                        error!(error = format!("{e:#}"));
-                        return;
-                    }
-                };
-                debug!("Stream thread parking loop");
-                while !stream_ended.load(Ordering::Relaxed) {
-                    thread::park();
-                }
-                debug!("Stream thread unparked");
-                drop(stream);
-            }));
+        if self.device_lost.swap(false, Ordering::Relaxed) {
+            warn!("Audio output device lost, opening the default device again");
+            self.close_stream();
         }
 
-        if let Some(ref tx) = self.tx {
-            if let Err(error) = tx.send(data.to_vec()) {
-                error!(%error);
+        if self.stream_handle.is_none() {
+            if self
+                .last_open_attempt
+                .is_some_and(|last_open_attempt| last_open_attempt.elapsed() < REOPEN_DELAY)
+            {
+                return;
+            }
+
+            self.last_open_attempt = Some(Instant::now());
+
+            if let Err(e) = self.open(format) {
+                error!(error = format!("{e:#}"), "Failed to open the audio output");
+                self.close_stream();
+                return;
             }
+        }
+
+        let Some(output) = &mut self.output else {
+            return;
         };
+
+        self.samples.clear();
+        playback::decode_pcm(format.bits_per_sample, &data, &mut self.samples);
+
+        self.resampled.clear();
+        output.resampler.process(&self.samples, &mut self.resampled);
+
+        lock(&output.playback).buffer.push(&self.resampled);
     }
 
     fn set_volume(&mut self, volume: VolumePdu) {
         debug!(?volume);
+
+        self.gains = playback::channel_gains(&volume);
+
+        if let Some(output) = &self.output {
+            lock(&output.playback).gains = self.gains;
+        }
     }
 
     fn set_pitch(&mut self, pitch: PitchPdu) {
         debug!(?pitch);
     }
 
+    fn playback_latency(&self) -> Option<Duration> {
+        let output = self.output.as_ref()?;
+        let playback = lock(&output.playback);
+
+        Some(playback.buffer.latency() + playback.device_latency)
+    }
+
     fn close(&mut self) {
-        self.tx = None;
-        if let Some(stream) = self.stream_handle.take() {
-            self.stream_ended.store(true, Ordering::Relaxed);
-            stream.thread().unpark();
-            stream.join().unwrap();
-        }
+        self.close_stream();
+        self.format = None;
     }
 }
 
-#[doc(hidden)]
-pub fn make_stream(rx_format: &AudioFormat, rx: Receiver<Vec<u8>>) -> anyhow::Result<Stream> {
-    if rx_format.format != WaveFormat::PCM {
-        bail!("only PCM formats supported");
-    }
-    let sample_format = match rx_format.bits_per_sample {
-        8 => SampleFormat::U8,
-        16 => SampleFormat::I16,
-        _ => {
-            bail!("only PCM 8/16 bits formats supported");
-        }
-    };
+#[derive(Debug)]
+struct Output {
+    playback: Arc<Mutex<Playback>>,
+    resampler: Resampler,
+}
+
+/// State shared with the stream callback.
+#[derive(Debug)]
+struct Playback {
+    buffer: JitterBuffer,
+    gains: [f32; 2],
+    /// Delay between the callback and the playback of its samples by the device.
+    device_latency: Duration,
+}
 
+fn lock(playback: &Mutex<Playback>) -> MutexGuard<'_, Playback> {
+    // The state stays consistent even if a holder of the lock panicked.
+    playback.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Opens a stream on the default output device, with its default configuration.
+fn make_stream(
+    target_latency: Duration,
+    gains: [f32; 2],
+    device_lost: Arc<AtomicBool>,
+) -> anyhow::Result<(Stream, Arc<Mutex<Playback>>)> {
     let host = cpal::default_host();
     let device = host.default_output_device().context("no default output device")?;
-    let _supported_configs_range = device
-        .supported_output_configs()
-        .context("no supported output config")?;
     let default_config = device.default_output_config()?;
     debug!(?default_config);
 
-    let mut rx = RxBuffer::new(rx);
-    let config = StreamConfig {
-        channels: rx_format.n_channels,
-        sample_rate: cpal::SampleRate(rx_format.n_samples_per_sec),
-        buffer_size: cpal::BufferSize::Default,
-    };
+    let sample_format = default_config.sample_format();
+    let config = default_config.config();
     debug!(?config);
 
-    let stream = device
-        .build_output_stream_raw(
-            &config,
-            sample_format,
-            move |data, _info: &cpal::OutputCallbackInfo| {
-                let data = data.bytes_mut();
-                rx.fill(data)
+    let playback = Arc::new(Mutex::new(Playback {
+        buffer: JitterBuffer::new(config.channels, config.sample_rate.0, target_latency),
+        gains,
+        device_latency: Duration::ZERO,
+    }));
+
+    let stream = match sample_format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, &playback, device_lost),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, &playback, device_lost),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, &playback, device_lost),
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, &playback, device_lost),
+        SampleFormat::U8 => build_stream::<u8>(&device, &config, &playback, device_lost),
+        sample_format => bail!("unsupported output sample format: {sample_format}"),
+    }?;
+
+    stream.play().context("failed to play output stream")?;
+
+    Ok((stream, playback))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    playback: &Arc<Mutex<Playback>>,
+    device_lost: Arc<AtomicBool>,
+) -> anyhow::Result<Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let playback = Arc::clone(playback);
+    let channels = usize::from(config.channels);
+    let mut samples = Vec::new();
+
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], info: &OutputCallbackInfo| {
+                samples.resize(data.len(), 0.0);
+
+                {
+                    let mut playback = lock(&playback);
+                    playback.buffer.pull(&mut samples);
+                    playback::apply_volume(&mut samples, channels, playback.gains);
+
+                    let timestamp = info.timestamp();
+                    playback.device_latency = timestamp
+                        .playback
+                        .duration_since(&timestamp.callback)
+                        .unwrap_or_default();
+                }
+
+                for (sample, value) in data.iter_mut().zip(&samples) {
+                    *sample = T::from_sample(*value);
+                }
+            },
+            move |error| {
+                error!(%error);
+
+                if matches!(error, StreamError::DeviceNotAvailable) {
+                    device_lost.store(true, Ordering::Relaxed);
+                }
             },
-            |error| error!(%error),
             None,
         )
-        .context("failed to setup output stream")?;
+        .context("failed to setup output stream")
+}
+
+/// Duration of the fade in and out of a tone, avoiding the clicks of an abrupt start or stop.
+const TONE_FADE: Duration = Duration::from_millis(5);
+
+/// Plays a sine tone on the default output device, e.g.: for the beeps requested with the Play Sound PDU
+///
+/// The tone is played by a separate thread, as the stream is not `Send`. This function only blocks until the output
+/// device is opened, and fails if the frequency can't be played by the device.
+pub fn play_tone(frequency_hz: u32, duration: Duration) -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::sync_channel(1);
+
+    thread::spawn(move || {
+        let stream = match make_tone_stream(frequency_hz, duration) {
+            Ok(stream) => {
+                let _ = tx.send(Ok(()));
+                stream
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+        };
+
+        // The stream is silent once the tone is over, leave it some time to drain.
+        thread::sleep(duration + TONE_FADE);
+        drop(stream);
+    });
+
+    rx.recv().context("tone thread terminated")?
+}
+
+fn make_tone_stream(frequency_hz: u32, duration: Duration) -> anyhow::Result<Stream> {
+    let host = cpal::default_host();
+    let device = host.default_output_device().context("no default output device")?;
+    let default_config = device.default_output_config()?;
+
+    let sample_format = default_config.sample_format();
+    let config = default_config.config();
+
+    if frequency_hz == 0 || frequency_hz >= config.sample_rate.0 / 2 {
+        bail!(
+            "tone frequency {frequency_hz} Hz can't be played at {} Hz",
+            config.sample_rate.0
+        );
+    }
+
+    let tone = Tone::new(frequency_hz, duration, config.sample_rate.0);
+
+    let stream = match sample_format {
+        SampleFormat::F32 => build_tone_stream::<f32>(&device, &config, tone),
+        SampleFormat::I16 => build_tone_stream::<i16>(&device, &config, tone),
+        SampleFormat::U16 => build_tone_stream::<u16>(&device, &config, tone),
+        SampleFormat::I32 => build_tone_stream::<i32>(&device, &config, tone),
+        SampleFormat::U8 => build_tone_stream::<u8>(&device, &config, tone),
+        sample_format => bail!("unsupported output sample format: {sample_format}"),
+    }?;
+
+    stream.play().context("failed to play tone stream")?;
 
     Ok(stream)
 }
 
-struct RxBuffer {
-    receiver: Receiver<Vec<u8>>,
-    last: Option<Vec<u8>>,
-    idx: usize,
+fn build_tone_stream<T>(device: &cpal::Device, config: &StreamConfig, mut tone: Tone) -> anyhow::Result<Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = usize::from(config.channels);
+
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    let value = T::from_sample(tone.next_sample());
+                    frame.fill(value);
+                }
+            },
+            move |error| error!(%error, "Tone stream error"),
+            None,
+        )
+        .context("failed to setup tone stream")
+}
+
+/// Sine wave generator, faded in and out
+#[derive(Debug)]
+struct Tone {
+    /// Phase increment per sample, in radians.
+    step: f32,
+    phase: f32,
+    total_samples: u64,
+    fade_samples: u64,
+    position: u64,
 }
 
-impl RxBuffer {
-    fn new(receiver: Receiver<Vec<u8>>) -> Self {
+impl Tone {
+    /// Amplitude of the tone, kept below the full scale as beeps are not meant to be loud.
+    const AMPLITUDE: f32 = 0.25;
+
+    fn new(frequency_hz: u32, duration: Duration, sample_rate: u32) -> Self {
+        let samples = |duration: Duration| {
+            u64::try_from(duration.as_micros() * u128::from(sample_rate) / 1_000_000).unwrap_or(u64::MAX)
+        };
+
+        let total_samples = samples(duration);
+
+        #[allow(clippy::cast_precision_loss)] // the frequency is below the sample rate, itself far below 2^24
+        let step = core::f32::consts::TAU * frequency_hz as f32 / sample_rate as f32;
+
         Self {
-            receiver,
-            last: None,
-            idx: 0,
+            step,
+            phase: 0.0,
+            total_samples,
+            fade_samples: samples(TONE_FADE).min(total_samples / 2),
+            position: 0,
         }
     }
 
-    fn fill(&mut self, data: &mut [u8]) {
-        let mut filled = 0;
-
-        while filled < data.len() {
-            if self.last.is_none() {
-                match self.receiver.recv_timeout(Duration::from_millis(4000)) {
-                    Ok(rx) => {
-                        debug!(rx.len = rx.len());
-                        self.last = Some(rx);
-                    }
-                    Err(error) => {
-                        warn!(%error);
-                    }
-                }
-            }
+    /// Returns the next sample, or silence once the tone is over.
+    fn next_sample(&mut self) -> f32 {
+        if self.position >= self.total_samples {
+            return 0.0;
+        }
 
-            let Some(ref last) = self.last else {
-                info!("Playback rx underrun");
-                return;
-            };
+        let remaining = self.total_samples - self.position;
+        let fade = self.position.min(remaining);
 
-            #[allow(clippy::arithmetic_side_effects)]
-            while self.idx < last.len() && filled < data.len() {
-                data[filled] = last[self.idx];
-                assert!(filled < usize::MAX);
-                assert!(self.idx < usize::MAX);
-                filled += 1;
-                self.idx += 1;
-            }
+        let gain = if fade < self.fade_samples {
+            #[allow(clippy::cast_precision_loss)] // the fade is a few hundred samples long
+            let gain = fade as f32 / self.fade_samples as f32;
+            gain
+        } else {
+            1.0
+        };
 
-            // If all elements from last have been consumed, clear `self.last`
-            if self.idx >= last.len() {
-                self.last = None;
-                self.idx = 0;
-            }
-        }
+        let sample = self.phase.sin() * gain * Self::AMPLITUDE;
+
+        // The phase is wrapped, so the precision does not degrade as the tone goes on.
+        self.phase = (self.phase + self.step) % core::f32::consts::TAU;
+        self.position += 1;
+
+        sample
     }
 }
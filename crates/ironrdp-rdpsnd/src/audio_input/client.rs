@@ -0,0 +1,305 @@
+use ironrdp_core::{impl_as_any, Decode, ReadCursor};
+use ironrdp_dvc::{encode_dvc_messages, DvcClientProcessor, DvcMessage, DvcProcessor};
+use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
+use ironrdp_svc::{ChannelFlags, SvcMessage};
+use tracing::{debug, warn};
+
+use super::pdu::{AudioInputPdu, DataPdu, FormatChangePdu, FormatsPdu, OpenPdu, OpenReplyPdu, Version, VersionPdu};
+use super::CHANNEL_NAME;
+use crate::pdu::{AudioFormat, WaveFormat};
+
+/// Supplies the audio captured on the client.
+///
+/// The server picks the capture format among the ones supported by the backend. Converting the captured audio to
+/// this format (e.g.: resampling) is the job of the backend, but the packets it produces are checked against the
+/// format before being sent.
+pub trait AudioInputBackend: Send + core::fmt::Debug {
+    /// Audio formats the backend can capture, ordered by preference.
+    fn formats(&self) -> Vec<AudioFormat>;
+
+    /// Starts capturing audio in `format`, by packets of `frames_per_packet` frames.
+    ///
+    /// Also called when the server changes the format of the stream. Returns `false` if the audio cannot be captured
+    /// in this format.
+    fn open(&mut self, format: &AudioFormat, frames_per_packet: u32) -> bool;
+
+    /// Returns the next captured packet, of at most `max_len` bytes, or `None` if no audio is available.
+    fn poll_frame(&mut self, max_len: usize) -> Option<Vec<u8>>;
+
+    /// Stops capturing audio.
+    fn close(&mut self);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AudioInputState {
+    WaitingForVersion,
+    WaitingForFormats,
+    WaitingForOpen,
+    Open {
+        /// Index of the current format in the client formats.
+        format_idx: usize,
+        frames_per_packet: u32,
+    },
+}
+
+/// A client for the Audio Input Redirection Virtual Channel.
+///
+/// The captured audio is pulled from the [`AudioInputBackend`] by calling [`AudioInputClient::capture`].
+#[derive(Debug)]
+pub struct AudioInputClient {
+    backend: Box<dyn AudioInputBackend>,
+    state: AudioInputState,
+    version: Option<Version>,
+    /// Formats sent to the server, to which the format indices sent by the server refer.
+    formats: Vec<AudioFormat>,
+}
+
+impl AudioInputClient {
+    /// The version of the protocol implemented by this client.
+    pub const VERSION: Version = Version::V2;
+
+    pub fn new(backend: Box<dyn AudioInputBackend>) -> Self {
+        Self {
+            backend,
+            state: AudioInputState::WaitingForVersion,
+            version: None,
+            formats: Vec::new(),
+        }
+    }
+
+    /// Returns the negotiated version, if any.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Returns the formats sent to the server.
+    pub fn formats(&self) -> &[AudioFormat] {
+        &self.formats
+    }
+
+    /// Returns the format of the stream, if the server opened it.
+    pub fn current_format(&self) -> Option<&AudioFormat> {
+        match self.state {
+            AudioInputState::Open { format_idx, .. } => self.formats.get(format_idx),
+            _ => None,
+        }
+    }
+
+    /// Pulls the audio captured by the backend, and wraps it as [`SvcMessage`]s.
+    ///
+    /// Returns no message if the server did not open the stream.
+    pub fn capture(&mut self, channel_id: u32) -> PduResult<Vec<SvcMessage>> {
+        let AudioInputState::Open {
+            format_idx,
+            frames_per_packet,
+        } = self.state
+        else {
+            return Ok(Vec::new());
+        };
+
+        let format = &self.formats[format_idx];
+        let block_align = usize::from(format.n_block_align);
+        let max_len = usize::try_from(frames_per_packet)
+            .unwrap_or(usize::MAX)
+            .saturating_mul(block_align)
+            .max(block_align);
+
+        let mut messages: Vec<DvcMessage> = Vec::new();
+
+        while let Some(data) = self.backend.poll_frame(max_len) {
+            if data.is_empty() {
+                break;
+            }
+
+            if data.len() > max_len || data.len() % block_align != 0 {
+                return Err(pdu_other_err!(
+                    "AudioInputClient",
+                    "captured packet does not fit the negotiated format"
+                ));
+            }
+
+            messages.push(Box::new(AudioInputPdu::IncomingData));
+            messages.push(Box::new(AudioInputPdu::Data(DataPdu { data })));
+        }
+
+        encode_dvc_messages(channel_id, messages, ChannelFlags::empty()).map_err(|e| encode_err!(e))
+    }
+
+    fn negotiate_formats(&mut self, server_formats: &[AudioFormat]) -> Vec<AudioFormat> {
+        let mut formats = Vec::new();
+
+        for supported in self.backend.formats() {
+            let Some(format) = server_formats
+                .iter()
+                .find(|format| format.is_compatible_with(&supported))
+            else {
+                continue;
+            };
+
+            if !is_valid_format(format) {
+                warn!(?format, "Ignoring inconsistent audio input format");
+                continue;
+            }
+
+            if !formats.contains(format) {
+                formats.push(format.clone());
+            }
+        }
+
+        if formats.is_empty() {
+            warn!("None of the server audio input formats is supported");
+        }
+
+        formats
+    }
+
+    fn open(&mut self, open: OpenPdu) -> PduResult<Vec<DvcMessage>> {
+        let format_idx = usize::try_from(open.initial_format).unwrap_or(usize::MAX);
+
+        let Some(format) = self.formats.get(format_idx) else {
+            return Err(pdu_other_err!("AudioInputClient", "invalid initial format"));
+        };
+
+        if !format.is_compatible_with(&open.format) {
+            return Err(pdu_other_err!(
+                "AudioInputClient",
+                "open format does not match the initial format"
+            ));
+        }
+
+        if matches!(self.state, AudioInputState::Open { .. }) {
+            self.backend.close();
+        }
+
+        if !self.backend.open(format, open.frames_per_packet) {
+            warn!(?format, "Failed to open audio input");
+            self.state = AudioInputState::WaitingForOpen;
+
+            return Ok(vec![Box::new(AudioInputPdu::OpenReply(OpenReplyPdu {
+                result: OpenReplyPdu::E_FAIL,
+            }))]);
+        }
+
+        self.state = AudioInputState::Open {
+            format_idx,
+            frames_per_packet: open.frames_per_packet,
+        };
+
+        // The format of the next data PDUs is confirmed before replying.
+        Ok(vec![
+            Box::new(AudioInputPdu::FormatChange(FormatChangePdu {
+                new_format: open.initial_format,
+            })),
+            Box::new(AudioInputPdu::OpenReply(OpenReplyPdu {
+                result: OpenReplyPdu::S_OK,
+            })),
+        ])
+    }
+
+    fn change_format(&mut self, format_change: FormatChangePdu) -> PduResult<Vec<DvcMessage>> {
+        let AudioInputState::Open { frames_per_packet, .. } = self.state else {
+            warn!(?format_change, "Format change received while the stream is not open");
+            return Ok(Vec::new());
+        };
+
+        let format_idx = usize::try_from(format_change.new_format).unwrap_or(usize::MAX);
+
+        let Some(format) = self.formats.get(format_idx) else {
+            return Err(pdu_other_err!("AudioInputClient", "invalid new format"));
+        };
+
+        if !self.backend.open(format, frames_per_packet) {
+            warn!(?format, "Failed to change the audio input format");
+            self.backend.close();
+            self.state = AudioInputState::WaitingForOpen;
+            return Ok(Vec::new());
+        }
+
+        self.state = AudioInputState::Open {
+            format_idx,
+            frames_per_packet,
+        };
+
+        Ok(vec![Box::new(AudioInputPdu::FormatChange(format_change))])
+    }
+}
+
+/// Returns `true` if the fields of the format are consistent.
+///
+/// The block alignment and average byte rate of PCM formats are derived from the other fields.
+fn is_valid_format(format: &AudioFormat) -> bool {
+    if format.n_channels == 0 || format.n_samples_per_sec == 0 || format.n_block_align == 0 {
+        return false;
+    }
+
+    if format.format != WaveFormat::PCM {
+        return true;
+    }
+
+    matches!(format.bits_per_sample, 8 | 16 | 24 | 32)
+        && u32::from(format.n_block_align) * 8 == u32::from(format.n_channels) * u32::from(format.bits_per_sample)
+        && Some(format.n_avg_bytes_per_sec) == format.n_samples_per_sec.checked_mul(u32::from(format.n_block_align))
+}
+
+impl_as_any!(AudioInputClient);
+
+impl DvcProcessor for AudioInputClient {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        // The server speaks first.
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let pdu = AudioInputPdu::decode(&mut ReadCursor::new(payload)).map_err(|e| decode_err!(e))?;
+        debug!(?pdu, "Received");
+
+        match (self.state, pdu) {
+            (_, AudioInputPdu::Version(server_version)) => {
+                if matches!(self.state, AudioInputState::Open { .. }) {
+                    self.backend.close();
+                }
+
+                let version = server_version.version.min(Self::VERSION);
+                self.version = Some(version);
+                self.state = AudioInputState::WaitingForFormats;
+
+                Ok(vec![Box::new(AudioInputPdu::Version(VersionPdu { version }))])
+            }
+            (AudioInputState::WaitingForFormats, AudioInputPdu::Formats(server_formats)) => {
+                self.formats = self.negotiate_formats(&server_formats.formats);
+                self.state = AudioInputState::WaitingForOpen;
+
+                let response = AudioInputPdu::Formats(FormatsPdu {
+                    formats: self.formats.clone(),
+                });
+                debug!(?response, "Send");
+
+                Ok(vec![Box::new(response)])
+            }
+            (AudioInputState::WaitingForOpen | AudioInputState::Open { .. }, AudioInputPdu::Open(open)) => {
+                self.open(open)
+            }
+            (_, AudioInputPdu::FormatChange(format_change)) => self.change_format(format_change),
+            (state, pdu) => {
+                warn!(?state, ?pdu, "Unexpected PDU");
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn close(&mut self, _channel_id: u32) {
+        if matches!(self.state, AudioInputState::Open { .. }) {
+            self.backend.close();
+        }
+
+        self.state = AudioInputState::WaitingForVersion;
+        self.version = None;
+        self.formats.clear();
+    }
+}
+
+impl DvcClientProcessor for AudioInputClient {}
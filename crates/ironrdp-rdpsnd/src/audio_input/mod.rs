@@ -0,0 +1,8 @@
+//! Audio Input Redirection Virtual Channel Extension \[MS-RDPEAI\], used to send the audio captured on the client
+//! (e.g.: a microphone) to the server.
+
+pub mod client;
+pub mod pdu;
+
+/// Name of the dynamic virtual channel.
+pub const CHANNEL_NAME: &str = "AUDIO_INPUT";
@@ -0,0 +1,407 @@
+//! Audio Input Redirection Virtual Channel Extension PDUs \[MS-RDPEAI\] implementation.
+
+use core::fmt;
+
+use ironrdp_core::{
+    cast_length, ensure_fixed_part_size, ensure_size, unexpected_message_type_err, Decode, DecodeResult, Encode,
+    EncodeResult, ReadCursor, WriteCursor,
+};
+use ironrdp_dvc::DvcEncode;
+
+use crate::pdu::AudioFormat;
+
+const MSG_SNDIN_VERSION: u8 = 0x01;
+const MSG_SNDIN_FORMATS: u8 = 0x02;
+const MSG_SNDIN_OPEN: u8 = 0x03;
+const MSG_SNDIN_OPEN_REPLY: u8 = 0x04;
+const MSG_SNDIN_DATA_INCOMING: u8 = 0x05;
+const MSG_SNDIN_DATA: u8 = 0x06;
+const MSG_SNDIN_FORMATCHANGE: u8 = 0x07;
+
+/// Audio input protocol version
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(pub u32);
+
+impl Version {
+    pub const V1: Self = Self(0x0000_0001);
+    pub const V2: Self = Self(0x0000_0002);
+}
+
+impl fmt::Debug for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Version({})", self.0)
+    }
+}
+
+/// [MS-RDPEAI] 2.2.2.1 Version PDU (MSG_SNDIN_VERSION)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionPdu {
+    pub version: Version,
+}
+
+impl VersionPdu {
+    const NAME: &'static str = "MSG_SNDIN_VERSION";
+
+    const FIXED_PART_SIZE: usize = 4 /* Version */;
+}
+
+impl Encode for VersionPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.version.0);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for VersionPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let version = Version(src.read_u32());
+
+        Ok(Self { version })
+    }
+}
+
+/// [MS-RDPEAI] 2.2.2.2 Sound Formats PDU (MSG_SNDIN_FORMATS)
+///
+/// Sent by the server with the formats it supports, and by the client with the subset of these formats it supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatsPdu {
+    pub formats: Vec<AudioFormat>,
+}
+
+impl FormatsPdu {
+    const NAME: &'static str = "MSG_SNDIN_FORMATS";
+
+    const FIXED_PART_SIZE: usize = 4 /* NumFormats */ + 4 /* cbSizeFormatsPacket */;
+}
+
+impl Encode for FormatsPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(cast_length!("NumFormats", self.formats.len())?);
+        // cbSizeFormatsPacket is the size of the whole PDU, including the MessageId field.
+        dst.write_u32(cast_length!("cbSizeFormatsPacket", 1 + self.size())?);
+
+        for format in &self.formats {
+            format.encode(dst)?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.formats.iter().map(Encode::size).sum::<usize>()
+    }
+}
+
+impl<'de> Decode<'de> for FormatsPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let n_formats: usize = cast_length!("NumFormats", src.read_u32())?;
+        // The cbSizeFormatsPacket field must be ignored.
+        let _cb_size_formats_packet = src.read_u32();
+
+        ensure_size!(in: src, size: n_formats.saturating_mul(AudioFormat::FIXED_PART_SIZE));
+
+        let formats = (0..n_formats)
+            .map(|_| AudioFormat::decode(src))
+            .collect::<DecodeResult<_>>()?;
+
+        Ok(Self { formats })
+    }
+}
+
+/// [MS-RDPEAI] 2.2.2.3 Open PDU (MSG_SNDIN_OPEN)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPdu {
+    /// Number of audio frames the client must send in each [`DataPdu`].
+    pub frames_per_packet: u32,
+    /// Index of the format to capture, in the formats sent by the client.
+    pub initial_format: u32,
+    /// The capture format, as a `WAVEFORMATEX` structure.
+    pub format: AudioFormat,
+}
+
+impl OpenPdu {
+    const NAME: &'static str = "MSG_SNDIN_OPEN";
+
+    const FIXED_PART_SIZE: usize = 4 /* FramesPerPacket */ + 4 /* initialFormat */;
+}
+
+impl Encode for OpenPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u32(self.frames_per_packet);
+        dst.write_u32(self.initial_format);
+        self.format.encode(dst)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE + self.format.size()
+    }
+}
+
+impl<'de> Decode<'de> for OpenPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let frames_per_packet = src.read_u32();
+        let initial_format = src.read_u32();
+        let format = AudioFormat::decode(src)?;
+
+        Ok(Self {
+            frames_per_packet,
+            initial_format,
+            format,
+        })
+    }
+}
+
+/// [MS-RDPEAI] 2.2.2.4 Open Reply PDU (MSG_SNDIN_OPEN_REPLY)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenReplyPdu {
+    /// HRESULT of the opening of the capture device.
+    pub result: u32,
+}
+
+impl OpenReplyPdu {
+    const NAME: &'static str = "MSG_SNDIN_OPEN_REPLY";
+
+    const FIXED_PART_SIZE: usize = 4 /* Result */;
+
+    pub const S_OK: u32 = 0x0000_0000;
+    pub const E_FAIL: u32 = 0x8000_4005;
+}
+
+impl Encode for OpenReplyPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.result);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for OpenReplyPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let result = src.read_u32();
+
+        Ok(Self { result })
+    }
+}
+
+/// [MS-RDPEAI] 2.2.3.2 Sound Packet PDU (MSG_SNDIN_DATA)
+#[derive(Clone, PartialEq, Eq)]
+pub struct DataPdu {
+    /// Audio data, in the current format.
+    pub data: Vec<u8>,
+}
+
+impl DataPdu {
+    const NAME: &'static str = "MSG_SNDIN_DATA";
+}
+
+impl fmt::Debug for DataPdu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataPdu").field("data_len", &self.data.len()).finish()
+    }
+}
+
+impl Encode for DataPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_slice(&self.data);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<'de> Decode<'de> for DataPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        let data = src.read_remaining().to_vec();
+
+        Ok(Self { data })
+    }
+}
+
+/// [MS-RDPEAI] 2.2.4 Sound Format Change PDU (MSG_SNDIN_FORMATCHANGE)
+///
+/// Sent by the server to change the capture format, and by the client to confirm the format used by the next
+/// [`DataPdu`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatChangePdu {
+    /// Index of the new format, in the formats sent by the client.
+    pub new_format: u32,
+}
+
+impl FormatChangePdu {
+    const NAME: &'static str = "MSG_SNDIN_FORMATCHANGE";
+
+    const FIXED_PART_SIZE: usize = 4 /* NewFormat */;
+}
+
+impl Encode for FormatChangePdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_fixed_part_size!(in: dst);
+
+        dst.write_u32(self.new_format);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+    }
+}
+
+impl<'de> Decode<'de> for FormatChangePdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let new_format = src.read_u32();
+
+        Ok(Self { new_format })
+    }
+}
+
+/// Audio input PDU, identified by the `MessageId` field of its [2.2.1] SNDIN_PDU header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioInputPdu {
+    Version(VersionPdu),
+    Formats(FormatsPdu),
+    Open(OpenPdu),
+    OpenReply(OpenReplyPdu),
+    /// [MS-RDPEAI] 2.2.3.1 Incoming Data PDU (MSG_SNDIN_DATA_INCOMING), sent by the client before each [`DataPdu`].
+    IncomingData,
+    Data(DataPdu),
+    FormatChange(FormatChangePdu),
+}
+
+impl AudioInputPdu {
+    const NAME: &'static str = "SNDIN_PDU";
+
+    const FIXED_PART_SIZE: usize = 1 /* MessageId */;
+
+    fn message_id(&self) -> u8 {
+        match self {
+            Self::Version(_) => MSG_SNDIN_VERSION,
+            Self::Formats(_) => MSG_SNDIN_FORMATS,
+            Self::Open(_) => MSG_SNDIN_OPEN,
+            Self::OpenReply(_) => MSG_SNDIN_OPEN_REPLY,
+            Self::IncomingData => MSG_SNDIN_DATA_INCOMING,
+            Self::Data(_) => MSG_SNDIN_DATA,
+            Self::FormatChange(_) => MSG_SNDIN_FORMATCHANGE,
+        }
+    }
+}
+
+impl Encode for AudioInputPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.size());
+
+        dst.write_u8(self.message_id());
+
+        match self {
+            Self::Version(pdu) => pdu.encode(dst),
+            Self::Formats(pdu) => pdu.encode(dst),
+            Self::Open(pdu) => pdu.encode(dst),
+            Self::OpenReply(pdu) => pdu.encode(dst),
+            Self::IncomingData => Ok(()),
+            Self::Data(pdu) => pdu.encode(dst),
+            Self::FormatChange(pdu) => pdu.encode(dst),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Version(pdu) => pdu.name(),
+            Self::Formats(pdu) => pdu.name(),
+            Self::Open(pdu) => pdu.name(),
+            Self::OpenReply(pdu) => pdu.name(),
+            Self::IncomingData => "MSG_SNDIN_DATA_INCOMING",
+            Self::Data(pdu) => pdu.name(),
+            Self::FormatChange(pdu) => pdu.name(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        Self::FIXED_PART_SIZE
+            + match self {
+                Self::Version(pdu) => pdu.size(),
+                Self::Formats(pdu) => pdu.size(),
+                Self::Open(pdu) => pdu.size(),
+                Self::OpenReply(pdu) => pdu.size(),
+                Self::IncomingData => 0,
+                Self::Data(pdu) => pdu.size(),
+                Self::FormatChange(pdu) => pdu.size(),
+            }
+    }
+}
+
+impl DvcEncode for AudioInputPdu {}
+
+impl<'de> Decode<'de> for AudioInputPdu {
+    fn decode(src: &mut ReadCursor<'de>) -> DecodeResult<Self> {
+        ensure_fixed_part_size!(in: src);
+
+        let pdu = match src.read_u8() {
+            MSG_SNDIN_VERSION => Self::Version(VersionPdu::decode(src)?),
+            MSG_SNDIN_FORMATS => Self::Formats(FormatsPdu::decode(src)?),
+            MSG_SNDIN_OPEN => Self::Open(OpenPdu::decode(src)?),
+            MSG_SNDIN_OPEN_REPLY => Self::OpenReply(OpenReplyPdu::decode(src)?),
+            MSG_SNDIN_DATA_INCOMING => Self::IncomingData,
+            MSG_SNDIN_DATA => Self::Data(DataPdu::decode(src)?),
+            MSG_SNDIN_FORMATCHANGE => Self::FormatChange(FormatChangePdu::decode(src)?),
+            message_id => return Err(unexpected_message_type_err!(Self::NAME, message_id)),
+        };
+
+        Ok(pdu)
+    }
+}
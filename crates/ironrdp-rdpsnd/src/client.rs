@@ -1,21 +1,45 @@
+use core::time::Duration;
 use std::borrow::Cow;
 
 use ironrdp_core::{cast_length, impl_as_any, Decode, EncodeResult, ReadCursor};
 use ironrdp_pdu::gcc::ChannelName;
 use ironrdp_pdu::{decode_err, encode_err, pdu_other_err, PduResult};
 use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use crate::pdu::{self, AudioFormat, PitchPdu, ServerAudioFormatPdu, TrainingPdu, VolumePdu};
 use crate::server::RdpsndSvcMessages;
 
 pub trait RdpsndClientHandler: Send + core::fmt::Debug {
+    /// Audio formats supported by the backend, ordered by preference.
+    ///
+    /// When building the client format list, the server formats compatible with these are
+    /// announced in this order. When empty (the default), all the server formats are accepted
+    /// in the order proposed by the server, except the passthrough formats (see
+    /// [`AudioFormat::is_passthrough`]): a handler opts into them by listing them here.
+    fn preferred_formats(&self) -> Vec<AudioFormat> {
+        Vec::new()
+    }
+
     fn wave(&mut self, format: &AudioFormat, ts: u32, data: Cow<'_, [u8]>);
 
+    /// Called instead of [`RdpsndClientHandler::wave`] for compressed formats which are not decoded
+    /// by IronRDP (see [`AudioFormat::is_passthrough`]).
+    ///
+    /// Only called for the formats listed in [`RdpsndClientHandler::preferred_formats`].
+    ///
+    /// `format_idx` is the index of the format in the client format list.
+    fn on_compressed_wave(&mut self, _format_idx: u16, _ts: u32, _data: Cow<'_, [u8]>) {}
+
     fn set_volume(&mut self, volume: VolumePdu);
 
     fn set_pitch(&mut self, pitch: PitchPdu);
 
+    /// Latency of the playback measured by the backend, from the reception of the audio to its output, if known.
+    fn playback_latency(&self) -> Option<Duration> {
+        None
+    }
+
     fn close(&mut self);
 }
 
@@ -48,6 +72,7 @@ pub struct Rdpsnd {
     handler: Box<dyn RdpsndClientHandler>,
     state: RdpsndState,
     server_format: Option<ServerAudioFormatPdu>,
+    client_formats: Vec<AudioFormat>,
 }
 
 impl Rdpsnd {
@@ -58,21 +83,26 @@ impl Rdpsnd {
             handler,
             state: RdpsndState::Start,
             server_format: None,
+            client_formats: Vec::new(),
         }
     }
 
+    /// Returns the format at `format_no` in the client format list.
     pub fn get_format(&self, format_no: u16) -> PduResult<&AudioFormat> {
-        let server_format = self
-            .server_format
-            .as_ref()
-            .ok_or_else(|| pdu_other_err!("invalid state - no format"))?;
+        if self.server_format.is_none() {
+            return Err(pdu_other_err!("invalid state - no format"));
+        }
 
-        server_format
-            .formats
-            .get(format_no as usize)
+        self.client_formats
+            .get(usize::from(format_no))
             .ok_or_else(|| pdu_other_err!("invalid format"))
     }
 
+    /// Returns the latency of the playback measured by the handler, if known.
+    pub fn playback_latency(&self) -> Option<Duration> {
+        self.handler.playback_latency()
+    }
+
     pub fn version(&self) -> PduResult<pdu::Version> {
         let server_format = self
             .server_format
@@ -88,10 +118,32 @@ impl Rdpsnd {
             .as_ref()
             .ok_or_else(|| pdu_other_err!("invalid state - no format"))?;
 
+        let preferred_formats = self.handler.preferred_formats();
+
+        self.client_formats = if preferred_formats.is_empty() {
+            // The waves of the passthrough formats would go to the default no-op `on_compressed_wave`.
+            server_format
+                .formats
+                .iter()
+                .filter(|f| !f.is_passthrough())
+                .cloned()
+                .collect()
+        } else {
+            preferred_formats
+                .iter()
+                .filter_map(|preferred| server_format.formats.iter().find(|f| f.is_compatible_with(preferred)))
+                .cloned()
+                .collect()
+        };
+
+        if self.client_formats.is_empty() {
+            warn!("None of the server audio formats is supported");
+        }
+
         let pdu = pdu::ClientAudioFormatPdu {
             version: self.version()?,
             flags: pdu::AudioFormatFlags::empty(),
-            formats: server_format.formats.clone(),
+            formats: self.client_formats.clone(),
             volume_left: 0xFFFF,
             volume_right: 0xFFFF,
             pitch: 0x00010000,
@@ -180,7 +232,11 @@ impl SvcProcessor for Rdpsnd {
                     pdu::ServerAudioOutputPdu::Wave2(pdu) => {
                         let fmt = self.get_format(pdu.format_no)?.clone();
                         let ts = pdu.audio_timestamp;
-                        self.handler.wave(&fmt, ts, pdu.data);
+                        if fmt.is_passthrough() {
+                            self.handler.on_compressed_wave(pdu.format_no, ts, pdu.data);
+                        } else {
+                            self.handler.wave(&fmt, ts, pdu.data);
+                        }
                         return Ok(self.wave_confirm(pdu.timestamp, pdu.block_no)?.into());
                     }
                     pdu::ServerAudioOutputPdu::Volume(pdu) => {
@@ -1,6 +1,8 @@
 #![doc = include_str!("../README.md")]
 #![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
 
+pub mod audio_input;
 pub mod client;
 pub mod pdu;
+pub mod playback;
 pub mod server;
@@ -222,7 +222,64 @@ pub struct AudioFormat {
 impl AudioFormat {
     const NAME: &'static str = "SERVER_AUDIO_VERSION_AND_FORMATS";
 
-    const FIXED_PART_SIZE: usize =
+    /// Size of the `HEAACWAVEINFO` structure following the `WAVEFORMATEX` header for [`WaveFormat::AAC_MS`].
+    const HEAAC_WAVE_INFO_SIZE: usize = 2 /* wPayloadType */
+        + 2 /* wAudioProfileLevelIndication */
+        + 2 /* wStructType */
+        + 2 /* wReserved1 */
+        + 4 /* dwReserved2 */;
+
+    /// Opus audio format (`WAVE_FORMAT_OPUS`, 0x704F).
+    ///
+    /// No format-specific data follows the `WAVEFORMATEX` header: `cbSize` is 0.
+    pub fn opus(n_channels: u16, n_samples_per_sec: u32) -> Self {
+        let n_block_align = n_channels.saturating_mul(2);
+
+        Self {
+            format: WaveFormat::OPUS,
+            n_channels,
+            n_samples_per_sec,
+            n_avg_bytes_per_sec: n_samples_per_sec.saturating_mul(u32::from(n_block_align)),
+            n_block_align,
+            bits_per_sample: 16,
+            data: None,
+        }
+    }
+
+    /// Raw AAC audio format (`WAVE_FORMAT_AAC_MS`, 0xA106).
+    ///
+    /// The `WAVEFORMATEX` header is followed by a `HEAACWAVEINFO` structure describing
+    /// a raw AAC payload (`wPayloadType` = 0) with no profile level preference (0xFE).
+    pub fn aac(n_channels: u16, n_samples_per_sec: u32, n_avg_bytes_per_sec: u32) -> Self {
+        let mut data = vec![0; Self::HEAAC_WAVE_INFO_SIZE];
+        // wAudioProfileLevelIndication: 0xFE means "no audio profile specified".
+        data[2] = 0xFE;
+
+        Self {
+            format: WaveFormat::AAC_MS,
+            n_channels,
+            n_samples_per_sec,
+            n_avg_bytes_per_sec,
+            n_block_align: 1,
+            bits_per_sample: 16,
+            data: Some(data),
+        }
+    }
+
+    /// Returns `true` if the format is a compressed format which IronRDP can hand over undecoded to the backend.
+    pub fn is_passthrough(&self) -> bool {
+        matches!(self.format, WaveFormat::OPUS | WaveFormat::AAC_MS)
+    }
+
+    /// Returns `true` if both formats describe the same stream layout, regardless of format-specific data.
+    pub fn is_compatible_with(&self, other: &AudioFormat) -> bool {
+        self.format == other.format
+            && self.n_channels == other.n_channels
+            && self.n_samples_per_sec == other.n_samples_per_sec
+            && self.bits_per_sample == other.bits_per_sample
+    }
+
+    pub(crate) const FIXED_PART_SIZE: usize =
         2 /* wFormatTag */
         + 2 /* nChannels */
         + 4 /* nSamplesPerSec */
@@ -0,0 +1,179 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+
+use tracing::debug;
+
+/// Corrections applied by a [`JitterBuffer`] since its creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JitterStats {
+    /// Number of times the buffer ran empty during the playback.
+    pub underruns: u64,
+    /// Number of frames dropped to reduce the latency.
+    pub dropped_frames: u64,
+    /// Number of frames played twice to increase the latency.
+    pub stretched_frames: u64,
+}
+
+/// Buffer of interleaved samples between the reception of the wave PDUs and the output device, absorbing the jitter
+/// of their delivery.
+///
+/// The playback starts once the target latency is buffered, and starts over the same way after an underrun. The
+/// buffered latency is then kept around the target: while it drifts away by more than a quarter of the target, a frame
+/// is dropped or played twice every [`JitterBuffer::CORRECTION_PERIOD`] frames, which is barely audible. When more
+/// than [`JitterBuffer::MAX_LATENCY_FACTOR`] times the target is buffered, e.g. after the output device stalled, the
+/// oldest frames are dropped at once.
+#[derive(Debug)]
+pub struct JitterBuffer {
+    channels: usize,
+    sample_rate: u32,
+    target_frames: usize,
+    samples: VecDeque<f32>,
+    playing: bool,
+    /// Number of frames played since the last correction.
+    since_correction: usize,
+    stats: JitterStats,
+}
+
+impl JitterBuffer {
+    pub const DEFAULT_TARGET_LATENCY: Duration = Duration::from_millis(60);
+
+    /// Number of frames played between two corrections of the latency, a 1% change of the playback speed.
+    pub const CORRECTION_PERIOD: usize = 100;
+
+    pub const MAX_LATENCY_FACTOR: usize = 3;
+
+    pub fn new(channels: u16, sample_rate: u32, target_latency: Duration) -> Self {
+        let target_frames = frames_in(target_latency, sample_rate).max(1);
+        let channels = usize::from(channels.max(1));
+
+        Self {
+            channels,
+            sample_rate,
+            target_frames,
+            samples: VecDeque::new(),
+            playing: false,
+            since_correction: 0,
+            stats: JitterStats::default(),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn buffered_frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Returns the duration of the buffered audio.
+    pub fn latency(&self) -> Duration {
+        duration_of(self.buffered_frames(), self.sample_rate)
+    }
+
+    pub fn target_latency(&self) -> Duration {
+        duration_of(self.target_frames, self.sample_rate)
+    }
+
+    /// Returns `true` once the target latency was buffered, until the next underrun.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        self.stats
+    }
+
+    /// Appends interleaved samples, a trailing partial frame being ignored.
+    pub fn push(&mut self, samples: &[f32]) {
+        let len = samples.len() - samples.len() % self.channels;
+        self.samples.extend(&samples[..len]);
+
+        let max_frames = self.target_frames.saturating_mul(Self::MAX_LATENCY_FACTOR);
+        let buffered_frames = self.buffered_frames();
+
+        if buffered_frames > max_frames {
+            let dropped_frames = buffered_frames - self.target_frames;
+            self.samples.drain(..dropped_frames * self.channels);
+            self.stats.dropped_frames += u64::try_from(dropped_frames).unwrap_or(u64::MAX);
+
+            debug!(dropped_frames, "Flushed the audio jitter buffer");
+        }
+    }
+
+    /// Fills `out` with the next interleaved samples to play, or with silence while buffering.
+    pub fn pull(&mut self, out: &mut [f32]) {
+        if !self.playing {
+            if self.buffered_frames() < self.target_frames {
+                out.fill(0.0);
+                return;
+            }
+
+            self.playing = true;
+        }
+
+        let tolerance = self.target_frames / 4;
+        let mut frames = out.chunks_mut(self.channels);
+
+        while let Some(frame) = frames.next() {
+            let buffered_frames = self.buffered_frames();
+
+            if buffered_frames == 0 {
+                self.stats.underruns += 1;
+                self.playing = false;
+                self.since_correction = 0;
+
+                frame.fill(0.0);
+                frames.for_each(|frame| frame.fill(0.0));
+
+                debug!("Audio jitter buffer underrun");
+                return;
+            }
+
+            self.since_correction += 1;
+
+            if self.since_correction >= Self::CORRECTION_PERIOD {
+                self.since_correction = 0;
+
+                if buffered_frames > self.target_frames + tolerance && buffered_frames > 1 {
+                    self.samples.drain(..self.channels);
+                    self.stats.dropped_frames += 1;
+                } else if buffered_frames + tolerance < self.target_frames {
+                    for (sample, buffered) in frame.iter_mut().zip(&self.samples) {
+                        *sample = *buffered;
+                    }
+                    self.stats.stretched_frames += 1;
+                    continue;
+                }
+            }
+
+            for sample in frame.iter_mut() {
+                *sample = self.samples.pop_front().unwrap_or(0.0);
+            }
+        }
+    }
+
+    /// Drops the buffered samples, the playback starting over once the target latency is buffered again.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.playing = false;
+        self.since_correction = 0;
+    }
+}
+
+fn frames_in(duration: Duration, sample_rate: u32) -> usize {
+    let frames = duration.as_micros().saturating_mul(u128::from(sample_rate)) / 1_000_000;
+    usize::try_from(frames).unwrap_or(usize::MAX)
+}
+
+fn duration_of(frames: usize, sample_rate: u32) -> Duration {
+    if sample_rate == 0 {
+        return Duration::ZERO;
+    }
+
+    let micros = u128::try_from(frames).unwrap_or(u128::MAX).saturating_mul(1_000_000) / u128::from(sample_rate);
+    Duration::from_micros(u64::try_from(micros).unwrap_or(u64::MAX))
+}
@@ -0,0 +1,52 @@
+//! Platform-independent building blocks of the audio playback backends.
+//!
+//! The samples are handled as interleaved `f32` in the [-1.0, 1.0] range, from the decoding of the wave PDUs to the
+//! output device.
+
+mod jitter;
+mod resample;
+
+pub use self::jitter::{JitterBuffer, JitterStats};
+pub use self::resample::Resampler;
+
+use crate::pdu::VolumePdu;
+
+/// Decodes interleaved PCM samples, unsigned on 8 bits or signed little-endian on 16 bits, appending them to `out`.
+///
+/// Returns `false` if the number of bits per sample is not supported, leaving `out` untouched.
+pub fn decode_pcm(bits_per_sample: u16, data: &[u8], out: &mut Vec<f32>) -> bool {
+    match bits_per_sample {
+        8 => out.extend(data.iter().map(|&sample| (f32::from(sample) - 128.0) / 128.0)),
+        16 => out.extend(
+            data.chunks_exact(2)
+                .map(|sample| f32::from(i16::from_le_bytes([sample[0], sample[1]])) / 32768.0),
+        ),
+        _ => return false,
+    }
+
+    true
+}
+
+/// Returns the gains of the left and right channels set by the volume PDU.
+pub fn channel_gains(volume: &VolumePdu) -> [f32; 2] {
+    [volume.volume_left, volume.volume_right].map(|volume| f32::from(volume) / f32::from(u16::MAX))
+}
+
+/// Applies the channel gains to interleaved samples.
+///
+/// The first channel is considered as the left one and the second one as the right one, while the other channels,
+/// including the single channel of mono audio, are applied the average gain.
+pub fn apply_volume(samples: &mut [f32], channels: usize, gains: [f32; 2]) {
+    let [left, right] = gains;
+    let average = (left + right) / 2.0;
+
+    for frame in samples.chunks_mut(channels.max(1)) {
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            *sample *= match (channels, channel) {
+                (2.., 0) => left,
+                (2.., 1) => right,
+                _ => average,
+            };
+        }
+    }
+}
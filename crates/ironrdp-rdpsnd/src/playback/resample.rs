@@ -0,0 +1,106 @@
+/// Converts interleaved samples to the sample rate and number of channels of the output device.
+///
+/// The sample rate is converted by linear interpolation, the last frame of each call being kept to interpolate
+/// across the boundary with the next one. A mono input is duplicated on all the output channels, a multichannel input
+/// is mixed down to mono by averaging, and otherwise the first channels are mapped as is, the missing ones being
+/// left silent.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    input_rate: u32,
+    input_channels: usize,
+    output_rate: u32,
+    output_channels: usize,
+    /// Position of the next output frame, in input frames from `previous`.
+    position: f64,
+    /// Last input frame of the previous call, with the output channels.
+    previous: Option<Vec<f32>>,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, input_channels: u16, output_rate: u32, output_channels: u16) -> Self {
+        Self {
+            input_rate: input_rate.max(1),
+            input_channels: usize::from(input_channels.max(1)),
+            output_rate: output_rate.max(1),
+            output_channels: usize::from(output_channels.max(1)),
+            position: 0.0,
+            previous: None,
+        }
+    }
+
+    /// Returns `true` if the samples are passed through, with the channels mapped but without interpolation.
+    pub fn is_passthrough(&self) -> bool {
+        self.input_rate == self.output_rate
+    }
+
+    /// Converts the interleaved `input` samples, appending the result to `out`. A trailing partial frame is ignored.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let mut frames: Vec<f32> = self.previous.take().unwrap_or_default();
+        frames.reserve(input.len() / self.input_channels * self.output_channels);
+
+        for frame in input.chunks_exact(self.input_channels) {
+            self.map_channels(frame, &mut frames);
+        }
+
+        if self.is_passthrough() {
+            out.extend_from_slice(&frames);
+            return;
+        }
+
+        let frame_count = frames.len() / self.output_channels;
+
+        if frame_count == 0 {
+            return;
+        }
+
+        let step = f64::from(self.input_rate) / f64::from(self.output_rate);
+
+        while self.position + 1.0 < to_f64(frame_count) {
+            let index = floor_to_usize(self.position);
+            #[allow(clippy::cast_possible_truncation)] // the fraction is in [0, 1)
+            let fraction = (self.position - to_f64(index)) as f32;
+
+            let current = &frames[index * self.output_channels..][..self.output_channels];
+            let next = &frames[(index + 1) * self.output_channels..][..self.output_channels];
+
+            out.extend(current.iter().zip(next).map(|(a, b)| a + (b - a) * fraction));
+
+            self.position += step;
+        }
+
+        self.position -= to_f64(frame_count - 1);
+        self.previous = Some(frames.split_off((frame_count - 1) * self.output_channels));
+    }
+
+    /// Drops the frame kept from the previous call, e.g. when the playback is interrupted.
+    pub fn reset(&mut self) {
+        self.position = 0.0;
+        self.previous = None;
+    }
+
+    fn map_channels(&self, frame: &[f32], out: &mut Vec<f32>) {
+        let output_channels = self.output_channels;
+
+        match (self.input_channels, output_channels) {
+            (input, output) if input == output => out.extend_from_slice(frame),
+            (1, _) => out.extend(core::iter::repeat(frame[0]).take(output_channels)),
+            (input, 1) => out.push(frame.iter().sum::<f32>() / to_f32(input)),
+            (_, _) => out.extend((0..output_channels).map(|channel| frame.get(channel).copied().unwrap_or(0.0))),
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // the frame counts are far below 2^52
+fn to_f64(value: usize) -> f64 {
+    value as f64
+}
+
+#[allow(clippy::cast_precision_loss)] // the channel counts are at most 65535
+fn to_f32(value: usize) -> f32 {
+    value as f32
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // the position is positive and below the frame count
+fn floor_to_usize(value: f64) -> usize {
+    value.floor() as usize
+}
@@ -1,12 +1,21 @@
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
+use ironrdp_acceptor::{AuthContext, AuthDecision, Authorizer};
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use tokio_rustls::rustls::server::ServerSessionMemoryCache;
+use tokio_rustls::rustls::ServerConfig;
 use tokio_rustls::TlsAcceptor;
 
 use super::clipboard::CliprdrServerFactory;
 use super::display::{DesktopSize, RdpServerDisplay};
 use super::handler::{KeyboardEvent, MouseEvent, RdpServerInputHandler};
+use super::handshake_limit::{HandshakeLimiter, HandshakeLimits};
+use super::input_policy::{FilteredInputHandler, InputFilter, InputPolicy};
+use super::metrics::ServerMetrics;
 use super::server::*;
+use super::shadow::AttachPolicy;
 use crate::{DisplayUpdate, RdpServerDisplayUpdates, SoundServerFactory};
 
 pub struct WantsAddr {}
@@ -26,10 +35,17 @@ pub struct BuilderDone {
     addr: SocketAddr,
     security: RdpServerSecurity,
     with_remote_fx: bool,
+    with_svc_compression: bool,
     handler: Box<dyn RdpServerInputHandler>,
     display: Box<dyn RdpServerDisplay>,
     cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
     sound_factory: Option<Box<dyn SoundServerFactory>>,
+    input_policy: Option<InputPolicy>,
+    handshake_limits: Option<HandshakeLimits>,
+    shadow_policy: Option<AttachPolicy>,
+    authorizer: Option<Arc<Authorizer>>,
+    heartbeat: Option<HeartbeatPdu>,
+    metrics_capacity: Option<usize>,
 }
 
 pub struct RdpServerBuilder<State> {
@@ -76,6 +92,15 @@ impl RdpServerBuilder<WantsSecurity> {
         }
     }
 
+    /// Same as [`Self::with_tls`], with a cache of up to `cache_size` TLS sessions shared by all connections.
+    ///
+    /// Reconnecting clients can then resume their previous TLS session instead of performing a full handshake.
+    /// Session resumption is not supported by CredSSP, which is why there is no such option for hybrid security.
+    pub fn with_tls_session_cache(self, mut config: ServerConfig, cache_size: usize) -> RdpServerBuilder<WantsHandler> {
+        config.session_storage = ServerSessionMemoryCache::new(cache_size);
+        self.with_tls(Arc::new(config))
+    }
+
     pub fn with_hybrid(self, acceptor: impl Into<TlsAcceptor>, pub_key: Vec<u8>) -> RdpServerBuilder<WantsHandler> {
         RdpServerBuilder {
             state: WantsHandler {
@@ -125,6 +150,13 @@ impl RdpServerBuilder<WantsDisplay> {
                 sound_factory: None,
                 cliprdr_factory: None,
                 with_remote_fx: true,
+                with_svc_compression: true,
+                input_policy: None,
+                handshake_limits: None,
+                shadow_policy: None,
+                authorizer: None,
+                heartbeat: None,
+                metrics_capacity: None,
             },
         }
     }
@@ -139,6 +171,13 @@ impl RdpServerBuilder<WantsDisplay> {
                 sound_factory: None,
                 cliprdr_factory: None,
                 with_remote_fx: true,
+                with_svc_compression: true,
+                input_policy: None,
+                handshake_limits: None,
+                shadow_policy: None,
+                authorizer: None,
+                heartbeat: None,
+                metrics_capacity: None,
             },
         }
     }
@@ -160,18 +199,115 @@ impl RdpServerBuilder<BuilderDone> {
         self
     }
 
+    /// Compresses the static channel data sent to the clients supporting it, which is the default.
+    pub fn with_svc_compression(mut self, enabled: bool) -> Self {
+        self.state.with_svc_compression = enabled;
+        self
+    }
+
+    /// Applies the given policy to all client input before it reaches the input handler.
+    pub fn with_input_policy(mut self, policy: InputPolicy) -> Self {
+        self.state.input_policy = Some(policy);
+        self
+    }
+
+    /// Closes the incoming connections exceeding the given limits before starting the connection sequence.
+    pub fn with_handshake_limits(mut self, limits: HandshakeLimits) -> Self {
+        self.state.handshake_limits = Some(limits);
+        self
+    }
+
+    /// Lets new clients attach to an ongoing session with the given policy, instead of waiting for it to end.
+    ///
+    /// The attached clients receive the same display as the client which opened the session, starting with a full
+    /// frame, and are disconnected when the session ends.
+    pub fn with_shadowing(mut self, policy: AttachPolicy) -> Self {
+        self.state.shadow_policy = Some(policy);
+        self
+    }
+
+    /// Lets the given authorizer refuse the authenticated users before the session resources are allocated.
+    ///
+    /// The metadata of an allowed session is reported in [`AttachedClient::metadata`](crate::AttachedClient).
+    pub fn with_authorizer(
+        mut self,
+        authorizer: impl Fn(&AuthContext) -> AuthDecision + Send + Sync + 'static,
+    ) -> Self {
+        self.state.authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    /// Sends Heartbeat PDUs every `period` seconds to the clients supporting them.
+    ///
+    /// The clients should warn the user after `warning_count` missed heartbeats, and reconnect after
+    /// `reconnect_count` ones. A count of zero disables the corresponding action.
+    pub fn with_heartbeat(mut self, period: u8, warning_count: u8, reconnect_count: u8) -> Self {
+        self.state.heartbeat = Some(HeartbeatPdu {
+            period,
+            warning_count,
+            reconnect_count,
+        });
+        self
+    }
+
+    /// Measures the latency of the last `capacity` frames sent to the primary client, see [`RdpServer::metrics`].
+    ///
+    /// The frames are marked and acknowledged by the clients supporting frame acknowledgement.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_metrics(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "frame metrics capacity must not be zero");
+        self.state.metrics_capacity = Some(capacity);
+        self
+    }
+
     pub fn build(self) -> RdpServer {
-        RdpServer::new(
+        let mut handler = self.state.handler;
+        let mut input_filter = None;
+
+        if let Some(policy) = self.state.input_policy {
+            let filter = Arc::new(Mutex::new(InputFilter::new(policy)));
+            handler = Box::new(FilteredInputHandler {
+                inner: handler,
+                filter: Arc::clone(&filter),
+            });
+            input_filter = Some(filter);
+        }
+
+        let mut server = RdpServer::new(
             RdpServerOptions {
                 addr: self.state.addr,
                 security: self.state.security,
                 with_remote_fx: self.state.with_remote_fx,
+                with_svc_compression: self.state.with_svc_compression,
+                shadow_policy: self.state.shadow_policy,
+                heartbeat: self.state.heartbeat,
             },
-            self.state.handler,
+            handler,
             self.state.display,
             self.state.sound_factory,
             self.state.cliprdr_factory,
-        )
+        );
+
+        if let Some(filter) = input_filter {
+            server.set_input_filter(filter);
+        }
+
+        if let Some(limits) = self.state.handshake_limits {
+            server.set_handshake_limiter(HandshakeLimiter::new(limits));
+        }
+
+        if let Some(authorizer) = self.state.authorizer {
+            server.set_authorizer(authorizer);
+        }
+
+        if let Some(capacity) = self.state.metrics_capacity {
+            server.set_metrics(ServerMetrics::new(capacity));
+        }
+
+        server
     }
 }
 
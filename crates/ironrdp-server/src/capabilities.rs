@@ -2,8 +2,12 @@ use ironrdp_pdu::rdp::capability_sets::{self, GeneralExtraFlags};
 
 use crate::{DesktopSize, RdpServerOptions};
 
-pub(crate) fn capabilities(opts: &RdpServerOptions, size: DesktopSize) -> Vec<capability_sets::CapabilitySet> {
-    vec![
+pub(crate) fn capabilities(
+    opts: &RdpServerOptions,
+    size: DesktopSize,
+    frame_acknowledge: bool,
+) -> Vec<capability_sets::CapabilitySet> {
+    let mut capabilities = vec![
         capability_sets::CapabilitySet::General(general_capabilities()),
         capability_sets::CapabilitySet::Bitmap(bitmap_capabilities(&size)),
         capability_sets::CapabilitySet::Order(order_capabilities()),
@@ -13,7 +17,18 @@ pub(crate) fn capabilities(opts: &RdpServerOptions, size: DesktopSize) -> Vec<ca
         capability_sets::CapabilitySet::VirtualChannel(virtual_channel_capabilities()),
         capability_sets::CapabilitySet::MultiFragmentUpdate(multifragment_update()),
         capability_sets::CapabilitySet::BitmapCodecs(bitmap_codecs(opts.with_remote_fx)),
-    ]
+    ];
+
+    // Advertising the capability set requests the clients supporting it to acknowledge the marked frames.
+    if frame_acknowledge {
+        capabilities.push(capability_sets::CapabilitySet::FrameAcknowledge(
+            capability_sets::FrameAcknowledge {
+                max_unacknowledged_frame_count: 0,
+            },
+        ));
+    }
+
+    capabilities
 }
 
 fn general_capabilities() -> capability_sets::General {
@@ -1,4 +1,5 @@
 use core::num::NonZeroU16;
+use std::sync::Arc;
 
 use anyhow::Result;
 use ironrdp_displaycontrol::pdu::DisplayControlMonitorLayout;
@@ -17,6 +18,7 @@ pub use ironrdp_graphics::image_processing::PixelFormat;
 pub enum DisplayUpdate {
     Resize(DesktopSize),
     Bitmap(BitmapUpdate),
+    Frame(SharedFrame),
     PointerPosition(PointerPositionAttribute),
     ColorPointer(ColorPointer),
     RGBAPointer(RGBAPointer),
@@ -91,6 +93,114 @@ impl core::fmt::Debug for BitmapUpdate {
     }
 }
 
+impl BitmapUpdate {
+    pub fn as_frame_ref(&self) -> FrameRef<'_> {
+        FrameRef {
+            top: self.top,
+            left: self.left,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            order: self.order,
+            data: &self.data,
+            stride: self.stride,
+        }
+    }
+}
+
+/// Borrowed frame
+///
+/// View over the pixels of a bitmap update, read in place by the encoders.
+#[derive(Clone, Copy)]
+pub struct FrameRef<'a> {
+    pub top: u16,
+    pub left: u16,
+    pub width: NonZeroU16,
+    pub height: NonZeroU16,
+    pub format: PixelFormat,
+    pub order: PixelOrder,
+    pub data: &'a [u8],
+    pub stride: usize,
+}
+
+impl core::fmt::Debug for FrameRef<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FrameRef")
+            .field("top", &self.top)
+            .field("left", &self.left)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("order", &self.order)
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+/// Memory holding the pixels of a captured frame
+///
+/// Implemented by the capture sources handing out mapped buffers (a DMA-BUF, a shared-memory segment, a VM
+/// framebuffer...), so that the frames are encoded without being copied. The buffer is borrowed by the server
+/// until it is dropped: release it to the capture source in the `Drop` implementation.
+pub trait MappedFrame: Send + Sync {
+    /// Returns the mapped pixels.
+    fn data(&self) -> &[u8];
+}
+
+impl MappedFrame for Vec<u8> {
+    fn data(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Frame Display Update
+///
+/// Bitmap update whose pixels are read in place from a [`MappedFrame`], encoded like a [`BitmapUpdate`].
+///
+/// The frame is shared by the clients of the session instead of being copied, and the buffer is released once
+/// every client encoded it: when the last clone is dropped. A source needing the buffer back sooner should send
+/// an owned [`BitmapUpdate`] instead.
+#[derive(Clone)]
+pub struct SharedFrame {
+    pub top: u16,
+    pub left: u16,
+    pub width: NonZeroU16,
+    pub height: NonZeroU16,
+    pub format: PixelFormat,
+    pub order: PixelOrder,
+    pub buffer: Arc<dyn MappedFrame>,
+    pub stride: usize,
+}
+
+impl SharedFrame {
+    pub fn as_frame_ref(&self) -> FrameRef<'_> {
+        FrameRef {
+            top: self.top,
+            left: self.left,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            order: self.order,
+            data: self.buffer.data(),
+            stride: self.stride,
+        }
+    }
+}
+
+impl core::fmt::Debug for SharedFrame {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedFrame")
+            .field("top", &self.top)
+            .field("left", &self.left)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("order", &self.order)
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
 /// Display Updates receiver for an RDP server
 ///
 /// The RDP server will repeatedly call the `next_update` method to receive
@@ -4,7 +4,7 @@ use ironrdp_graphics::rdp6::{ABgrChannels, ARgbChannels, BgrAChannels, BitmapStr
 use ironrdp_pdu::bitmap::{self, BitmapData, BitmapUpdateData, Compression};
 use ironrdp_pdu::geometry::InclusiveRectangle;
 
-use crate::{BitmapUpdate, PixelOrder};
+use crate::{FrameRef, PixelOrder};
 
 // PERF: we could also remove the need for this buffer
 pub(crate) struct BitmapEncoder {
@@ -18,7 +18,7 @@ impl BitmapEncoder {
         }
     }
 
-    pub(crate) fn encode(&mut self, bitmap: &BitmapUpdate, output: &mut [u8]) -> EncodeResult<usize> {
+    pub(crate) fn encode(&mut self, bitmap: FrameRef<'_>, output: &mut [u8]) -> EncodeResult<usize> {
         // FIXME: support non-multiple of 4 widths.
         //
         // It’s not clear how to achieve that yet, but generally, server uses multiple of 4-widths,
@@ -44,8 +44,17 @@ impl BitmapEncoder {
             let encoder = BitmapStreamEncoder::new(usize::from(bitmap.width.get()), height);
 
             let len = match bitmap.order {
+                PixelOrder::BottomToTop if bitmap.stride == row_len => {
+                    Self::encode_slice(encoder, bitmap.format, chunk, self.buffer.as_mut_slice())
+                }
+
                 PixelOrder::BottomToTop => {
-                    Self::encode_slice(encoder, bitmap.format, &chunk[..row_len], self.buffer.as_mut_slice())
+                    let pixels = chunk
+                        .chunks(bitmap.stride)
+                        .map(|row| &row[..row_len])
+                        .flat_map(|row| row.chunks(bytes_per_pixel));
+
+                    Self::encode_iter(encoder, bitmap.format, pixels, self.buffer.as_mut_slice())
                 }
 
                 PixelOrder::TopToBottom => {
@@ -1,7 +1,8 @@
 mod bitmap;
 pub(crate) mod rfx;
 
-use core::{cmp, mem};
+use core::cmp;
+use std::borrow::Cow;
 
 use anyhow::{Context, Result};
 use ironrdp_core::{Encode, WriteCursor};
@@ -9,12 +10,13 @@ use ironrdp_pdu::fast_path::{EncryptionFlags, FastPathHeader, FastPathUpdatePdu,
 use ironrdp_pdu::geometry::ExclusiveRectangle;
 use ironrdp_pdu::pointer::{ColorPointerAttribute, Point16, PointerAttribute, PointerPositionAttribute};
 use ironrdp_pdu::rdp::capability_sets::{CmdFlags, EntropyBits};
-use ironrdp_pdu::surface_commands::{ExtendedBitmapDataPdu, SurfaceBitsPdu, SurfaceCommand};
+use ironrdp_pdu::surface_commands::{
+    ExtendedBitmapDataPdu, FrameAction, FrameMarkerPdu, SurfaceBitsPdu, SurfaceCommand,
+};
 
 use self::bitmap::BitmapEncoder;
 use self::rfx::RfxEncoder;
-use super::BitmapUpdate;
-use crate::{ColorPointer, PixelOrder, RGBAPointer};
+use crate::{ColorPointer, FrameRef, PixelOrder, RGBAPointer};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
@@ -31,7 +33,11 @@ pub(crate) struct UpdateEncoder {
     buffer: Vec<u8>,
     bitmap: BitmapEncoder,
     remotefx: Option<(RfxEncoder, u8)>,
-    update: for<'a> fn(&'a mut UpdateEncoder, BitmapUpdate) -> Result<UpdateFragmenter<'a>>,
+    update: for<'a> fn(&'a mut UpdateEncoder, FrameRef<'_>) -> Result<UpdateFragmenter<'a>>,
+    /// Whether the bitmap updates are surface commands the client accepts to be delimited by frame markers.
+    frame_markers: bool,
+    /// ID of the frame marking the next surface command.
+    frame_id: Option<u32>,
 }
 
 impl UpdateEncoder {
@@ -49,12 +55,19 @@ impl UpdateEncoder {
             bitmap: BitmapEncoder::new(),
             remotefx: remotefx.map(|(algo, id)| (RfxEncoder::new(algo), id)),
             update,
+            frame_markers: surface_flags.contains(CmdFlags::SET_SURFACE_BITS | CmdFlags::FRAME_MARKER),
+            frame_id: None,
         }
     }
 
     fn encode_pdu(&mut self, pdu: impl Encode) -> Result<usize> {
+        self.encode_pdu_at(0, pdu)
+    }
+
+    /// Encodes `pdu` at `pos` in the buffer, returning the position after it.
+    fn encode_pdu_at(&mut self, pos: usize, pdu: impl Encode) -> Result<usize> {
         loop {
-            let mut cursor = WriteCursor::new(self.buffer.as_mut_slice());
+            let mut cursor = WriteCursor::new(&mut self.buffer[pos..]);
             match pdu.encode(&mut cursor) {
                 Err(e) => match e.kind() {
                     ironrdp_core::EncodeErrorKind::NotEnoughBytes { .. } => {
@@ -64,7 +77,7 @@ impl UpdateEncoder {
 
                     _ => Err(e).context("PDU encode error")?,
                 },
-                Ok(()) => return Ok(cursor.pos()),
+                Ok(()) => return Ok(pos + cursor.pos()),
             }
         }
     }
@@ -124,12 +137,30 @@ impl UpdateEncoder {
         Ok(UpdateFragmenter::new(UpdateCode::PositionPointer, &self.buffer[..len]))
     }
 
-    pub(crate) fn bitmap(&mut self, bitmap: BitmapUpdate) -> Result<UpdateFragmenter<'_>> {
+    /// Encodes a bitmap update, reading the pixels in place.
+    pub(crate) fn bitmap(&mut self, bitmap: FrameRef<'_>) -> Result<UpdateFragmenter<'_>> {
         let update = self.update;
 
         update(self, bitmap)
     }
 
+    /// Whether the bitmap updates can be delimited by frame markers, see [`Self::marked_bitmap`].
+    pub(crate) fn supports_frame_markers(&self) -> bool {
+        self.frame_markers
+    }
+
+    /// Encodes a bitmap update, delimited by the begin and end markers of the frame `frame_id` if any.
+    pub(crate) fn marked_bitmap(
+        &mut self,
+        bitmap: FrameRef<'_>,
+        frame_id: Option<u32>,
+    ) -> Result<UpdateFragmenter<'_>> {
+        debug_assert!(frame_id.is_none() || self.frame_markers);
+        self.frame_id = frame_id;
+
+        self.bitmap(bitmap)
+    }
+
     pub(crate) fn fragmenter_from_owned(&self, res: UpdateFragmenterOwned) -> UpdateFragmenter<'_> {
         UpdateFragmenter {
             code: res.code,
@@ -138,9 +169,9 @@ impl UpdateEncoder {
         }
     }
 
-    fn bitmap_update(&mut self, bitmap: BitmapUpdate) -> Result<UpdateFragmenter<'_>> {
+    fn bitmap_update(&mut self, bitmap: FrameRef<'_>) -> Result<UpdateFragmenter<'_>> {
         let len = loop {
-            match self.bitmap.encode(&bitmap, self.buffer.as_mut_slice()) {
+            match self.bitmap.encode(bitmap, self.buffer.as_mut_slice()) {
                 Err(e) => match e.kind() {
                     ironrdp_core::EncodeErrorKind::NotEnoughBytes { .. } => {
                         self.buffer.resize(self.buffer.len() * 2, 0);
@@ -156,7 +187,7 @@ impl UpdateEncoder {
         Ok(UpdateFragmenter::new(UpdateCode::Bitmap, &self.buffer[..len]))
     }
 
-    fn set_surface(&mut self, bitmap: BitmapUpdate, codec_id: u8, data: Vec<u8>) -> Result<UpdateFragmenter<'_>> {
+    fn set_surface(&mut self, bitmap: FrameRef<'_>, codec_id: u8, data: &[u8]) -> Result<UpdateFragmenter<'_>> {
         let destination = ExclusiveRectangle {
             left: bitmap.left,
             top: bitmap.top,
@@ -169,37 +200,52 @@ impl UpdateEncoder {
             height: bitmap.height.get(),
             codec_id,
             header: None,
-            data: &data,
+            data,
         };
         let pdu = SurfaceBitsPdu {
             destination,
             extended_bitmap_data,
         };
         let cmd = SurfaceCommand::SetSurfaceBits(pdu);
-        let len = self.encode_pdu(cmd)?;
+
+        let len = match self.frame_id.take() {
+            Some(frame_id) => {
+                let marker = |frame_action| {
+                    SurfaceCommand::FrameMarker(FrameMarkerPdu {
+                        frame_action,
+                        frame_id: Some(frame_id),
+                    })
+                };
+                let len = self.encode_pdu(marker(FrameAction::Begin))?;
+                let len = self.encode_pdu_at(len, cmd)?;
+                self.encode_pdu_at(len, marker(FrameAction::End))?
+            }
+            None => self.encode_pdu(cmd)?,
+        };
+
         Ok(UpdateFragmenter::new(UpdateCode::SurfaceCommands, &self.buffer[..len]))
     }
 
-    fn remotefx_update(&mut self, bitmap: BitmapUpdate) -> Result<UpdateFragmenter<'_>> {
+    fn remotefx_update(&mut self, bitmap: FrameRef<'_>) -> Result<UpdateFragmenter<'_>> {
         let (remotefx, codec_id) = self.remotefx.as_mut().unwrap();
         let codec_id = *codec_id;
-        let data = remotefx.encode(&bitmap).context("RemoteFX encoding")?;
+        let data = remotefx.encode(bitmap).context("RemoteFX encoding")?;
 
-        self.set_surface(bitmap, codec_id, data)
+        self.set_surface(bitmap, codec_id, &data)
     }
 
-    fn none_update(&mut self, mut bitmap: BitmapUpdate) -> Result<UpdateFragmenter<'_>> {
+    fn none_update(&mut self, bitmap: FrameRef<'_>) -> Result<UpdateFragmenter<'_>> {
         let stride = usize::from(bitmap.format.bytes_per_pixel()) * usize::from(bitmap.width.get());
         let data = match bitmap.order {
             PixelOrder::BottomToTop => {
                 if stride == bitmap.stride {
-                    mem::take(&mut bitmap.data)
+                    Cow::Borrowed(bitmap.data)
                 } else {
                     let mut data = Vec::with_capacity(stride * usize::from(bitmap.height.get()));
                     for row in bitmap.data.chunks(bitmap.stride) {
                         data.extend_from_slice(&row[..stride]);
                     }
-                    data
+                    Cow::Owned(data)
                 }
             }
             PixelOrder::TopToBottom => {
@@ -207,11 +253,11 @@ impl UpdateEncoder {
                 for row in bitmap.data.chunks(bitmap.stride).rev() {
                     data.extend_from_slice(&row[..stride]);
                 }
-                data
+                Cow::Owned(data)
             }
         };
 
-        self.set_surface(bitmap, CodecId::None as u8, data)
+        self.set_surface(bitmap, CodecId::None as u8, &data)
     }
 }
 
@@ -298,3 +344,29 @@ impl<'a> UpdateFragmenter<'a> {
         Some(cursor.pos())
     }
 }
+
+#[cfg(feature = "__bench")]
+pub(crate) mod bench {
+    use super::*;
+
+    pub struct FrameEncoder(UpdateEncoder);
+
+    impl FrameEncoder {
+        pub fn new(surface_flags: CmdFlags, remotefx: Option<(EntropyBits, u8)>) -> Self {
+            Self(UpdateEncoder::new(surface_flags, remotefx))
+        }
+
+        /// Returns the fast-path updates of `frame`.
+        pub fn encode(&mut self, frame: FrameRef<'_>) -> Vec<u8> {
+            let mut fragmenter = self.0.bitmap(frame).unwrap();
+            let mut buffer = vec![0; fragmenter.size_hint()];
+            let mut output = Vec::new();
+
+            while let Some(len) = fragmenter.next(&mut buffer) {
+                output.extend_from_slice(&buffer[..len]);
+            }
+
+            output
+        }
+    }
+}
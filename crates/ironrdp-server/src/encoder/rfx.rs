@@ -6,7 +6,7 @@ use ironrdp_pdu::codecs::rfx::{self, OperatingMode, RfxChannel, RfxChannelHeight
 use ironrdp_pdu::rdp::capability_sets::EntropyBits;
 use ironrdp_pdu::PduBufferParsing;
 
-use crate::BitmapUpdate;
+use crate::FrameRef;
 
 #[derive(Debug)]
 pub(crate) struct RfxEncoder {
@@ -23,7 +23,7 @@ impl RfxEncoder {
     }
 
     // FIXME: rewrite to use WriteCursor
-    pub(crate) fn encode(&mut self, bitmap: &BitmapUpdate) -> EncodeResult<Vec<u8>> {
+    pub(crate) fn encode(&mut self, bitmap: FrameRef<'_>) -> EncodeResult<Vec<u8>> {
         let width = bitmap.width.get();
         let height = bitmap.height.get();
         let entropy_algorithm = self.entropy_algorithm;
@@ -101,7 +101,7 @@ impl RfxEncoder {
 }
 
 pub(crate) struct UpdateEncoder<'a> {
-    bitmap: &'a BitmapUpdate,
+    bitmap: FrameRef<'a>,
     quant: rfx::Quant,
     entropy_algorithm: rfx::EntropyAlgorithm,
 }
@@ -116,7 +116,7 @@ struct EncodedTile<'a> {
 
 impl<'a> UpdateEncoder<'a> {
     fn new(
-        bitmap: &'a BitmapUpdate,
+        bitmap: FrameRef<'a>,
         quant: rfx::Quant,
         entropy_algorithm: rfx::EntropyAlgorithm,
     ) -> (Self, UpdateEncoderData) {
@@ -232,6 +232,7 @@ impl<'a> UpdateEncoder<'a> {
 #[cfg(feature = "__bench")]
 pub(crate) mod bench {
     use super::*;
+    use crate::BitmapUpdate;
 
     pub fn rfx_enc_tile(
         bitmap: &BitmapUpdate,
@@ -240,13 +241,13 @@ pub(crate) mod bench {
         tile_x: usize,
         tile_y: usize,
     ) {
-        let (enc, mut data) = UpdateEncoder::new(bitmap, quant.clone(), algo);
+        let (enc, mut data) = UpdateEncoder::new(bitmap.as_frame_ref(), quant.clone(), algo);
 
         enc.encode_tile(tile_x, tile_y, &mut data.0).unwrap();
     }
 
     pub fn rfx_enc(bitmap: &BitmapUpdate, quant: &rfx::Quant, algo: rfx::EntropyAlgorithm) {
-        let (enc, mut data) = UpdateEncoder::new(bitmap, quant.clone(), algo);
+        let (enc, mut data) = UpdateEncoder::new(bitmap.as_frame_ref(), quant.clone(), algo);
 
         enc.encode(&mut data).unwrap();
     }
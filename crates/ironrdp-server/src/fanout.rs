@@ -0,0 +1,360 @@
+use core::cell::RefCell;
+use core::num::NonZeroU16;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::Result;
+use ironrdp_pdu::pointer::PointerPositionAttribute;
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
+
+use crate::{
+    BitmapUpdate, DesktopSize, DisplayUpdate, FrameRef, PixelFormat, PixelOrder, RdpServerDisplay,
+    RdpServerDisplayUpdates,
+};
+
+/// Number of updates queued for a subscriber, before its pending updates are replaced by a full-frame refresh
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 8;
+
+/// Distributes the updates of a single display to all the clients of the session
+///
+/// There is no dedicated task pulling the updates: the first subscriber waiting for an update while none is
+/// queued pulls the next one from the display, and broadcasts it to every subscriber. The updates are pulled
+/// while there is at least one subscriber, and the display updates receiver is dropped with the last one.
+///
+/// The queue of each subscriber is bounded: when a client does not keep up with the display, its pending updates
+/// are dropped, and replaced by a full frame taken from the framebuffer. Without a framebuffer, there is a single
+/// subscriber, pulling an update only once its queue is empty.
+#[derive(Clone)]
+pub(crate) struct DisplayFanOut {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    display: Arc<AsyncMutex<Box<dyn RdpServerDisplay>>>,
+    source: AsyncMutex<Option<Box<dyn RdpServerDisplayUpdates>>>,
+    state: RefCell<State>,
+    /// Whether the display is composed into a framebuffer, to refresh the clients attaching to the session.
+    keep_framebuffer: bool,
+}
+
+#[derive(Default)]
+struct State {
+    subscribers: Vec<Subscriber>,
+    next_id: u64,
+    /// Set while the display updates receiver exists.
+    active: bool,
+    framebuffer: Option<Framebuffer>,
+    pointer: Option<DisplayUpdate>,
+    pointer_position: Option<PointerPositionAttribute>,
+}
+
+struct Subscriber {
+    id: u64,
+    tx: mpsc::Sender<DisplayUpdate>,
+    /// Set when the queue overflowed, until the pending updates are replaced by a refresh.
+    lagging: bool,
+    /// Whether a resize was dropped while lagging, and must be part of the refresh.
+    missed_resize: bool,
+}
+
+impl Subscriber {
+    fn send(&mut self, update: DisplayUpdate) {
+        if self.lagging {
+            self.missed_resize |= matches!(update, DisplayUpdate::Resize(_));
+            return;
+        }
+
+        if let Err(mpsc::error::TrySendError::Full(update)) = self.tx.try_send(update) {
+            debug!(
+                id = self.id,
+                "Subscriber is lagging behind the display, dropping its pending updates"
+            );
+            self.lagging = true;
+            self.missed_resize = matches!(update, DisplayUpdate::Resize(_));
+        }
+    }
+}
+
+impl DisplayFanOut {
+    pub(crate) fn new(display: Arc<AsyncMutex<Box<dyn RdpServerDisplay>>>, keep_framebuffer: bool) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                display,
+                source: AsyncMutex::new(None),
+                state: RefCell::new(State::default()),
+                keep_framebuffer,
+            }),
+        }
+    }
+
+    /// Subscribes to the display updates.
+    ///
+    /// When the display is already being distributed to other subscribers, the updates start with the current
+    /// pointer and a full frame, if known.
+    pub(crate) fn subscribe(&self) -> DisplaySubscription {
+        let mut state = self.inner.state.borrow_mut();
+        let (tx, rx) = mpsc::channel(SUBSCRIBER_QUEUE_CAPACITY);
+
+        if state.active {
+            for update in state.refresh(false) {
+                let _ = tx.try_send(update);
+            }
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.subscribers.push(Subscriber {
+            id,
+            tx,
+            lagging: false,
+            missed_resize: false,
+        });
+
+        DisplaySubscription {
+            id,
+            inner: Rc::clone(&self.inner),
+            updates: rx,
+        }
+    }
+}
+
+impl Inner {
+    fn broadcast(&self, update: Option<DisplayUpdate>) {
+        let mut state = self.state.borrow_mut();
+
+        let Some(update) = update else {
+            // Closing the queues ends the updates of all the subscribers.
+            state.subscribers.clear();
+            return;
+        };
+
+        if self.keep_framebuffer {
+            state.record(&update);
+        }
+
+        // Only the updates sent to the other subscribers are cloned, which is the common case of a single
+        // subscriber.
+        if let Some((last, others)) = state.subscribers.split_last_mut() {
+            for subscriber in others {
+                subscriber.send(update.clone());
+            }
+            last.send(update);
+        }
+    }
+
+    /// Replaces the pending updates of the subscriber `id` by a full-frame refresh, if it was lagging.
+    fn catch_up(&self, id: u64, updates: &mut mpsc::Receiver<DisplayUpdate>) {
+        let mut state = self.state.borrow_mut();
+
+        let Some(subscriber) = state.subscribers.iter_mut().find(|subscriber| subscriber.id == id) else {
+            return;
+        };
+        if !subscriber.lagging {
+            return;
+        }
+
+        let mut missed_resize = core::mem::take(&mut subscriber.missed_resize);
+        subscriber.lagging = false;
+
+        while let Ok(update) = updates.try_recv() {
+            missed_resize |= matches!(update, DisplayUpdate::Resize(_));
+        }
+
+        let refresh = state.refresh(missed_resize);
+        if let Some(subscriber) = state.subscribers.iter().find(|subscriber| subscriber.id == id) {
+            for update in refresh {
+                let _ = subscriber.tx.try_send(update);
+            }
+        }
+    }
+}
+
+impl State {
+    /// Updates bringing a client up to date with the display: the size if `resize` is set, the pointer and a full
+    /// frame, if known.
+    fn refresh(&self, resize: bool) -> impl Iterator<Item = DisplayUpdate> {
+        let framebuffer = self.framebuffer.as_ref();
+
+        [
+            framebuffer
+                .filter(|_| resize)
+                .map(|framebuffer| DisplayUpdate::Resize(framebuffer.size)),
+            self.pointer.clone(),
+            self.pointer_position.map(DisplayUpdate::PointerPosition),
+            framebuffer.and_then(Framebuffer::full_frame).map(DisplayUpdate::Bitmap),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn record(&mut self, update: &DisplayUpdate) {
+        match update {
+            DisplayUpdate::Resize(size) => self.framebuffer = Some(Framebuffer::new(*size)),
+            DisplayUpdate::Bitmap(bitmap) => {
+                if let Some(framebuffer) = &mut self.framebuffer {
+                    framebuffer.apply(bitmap.as_frame_ref());
+                }
+            }
+            DisplayUpdate::Frame(frame) => {
+                if let Some(framebuffer) = &mut self.framebuffer {
+                    framebuffer.apply(frame.as_frame_ref());
+                }
+            }
+            DisplayUpdate::PointerPosition(position) => self.pointer_position = Some(*position),
+            DisplayUpdate::ColorPointer(_)
+            | DisplayUpdate::RGBAPointer(_)
+            | DisplayUpdate::HidePointer
+            | DisplayUpdate::DefaultPointer => self.pointer = Some(update.clone()),
+        }
+    }
+}
+
+/// Display updates received by a client, see [`DisplayFanOut::subscribe`]
+pub(crate) struct DisplaySubscription {
+    id: u64,
+    inner: Rc<Inner>,
+    updates: mpsc::Receiver<DisplayUpdate>,
+}
+
+impl DisplaySubscription {
+    /// Returns the next update, or `None` when the display has no more updates.
+    pub(crate) async fn next_update(&mut self) -> Result<Option<DisplayUpdate>> {
+        loop {
+            self.inner.catch_up(self.id, &mut self.updates);
+
+            tokio::select! {
+                biased;
+
+                update = self.updates.recv() => return Ok(update),
+
+                mut source = self.inner.source.lock() => {
+                    // Another subscriber may have broadcast an update while the lock was being acquired.
+                    if !self.updates.is_empty() {
+                        continue;
+                    }
+
+                    let source = match &mut *source {
+                        Some(source) => source,
+                        None => {
+                            let mut display = self.inner.display.lock().await;
+                            let size = display.size().await;
+                            let updates = display.updates().await?;
+
+                            let mut state = self.inner.state.borrow_mut();
+                            state.active = true;
+                            state.framebuffer = self.inner.keep_framebuffer.then(|| Framebuffer::new(size));
+
+                            source.insert(updates)
+                        }
+                    };
+
+                    let update = source.next_update().await;
+                    self.inner.broadcast(update);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for DisplaySubscription {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.borrow_mut();
+
+        state.subscribers.retain(|subscriber| subscriber.id != self.id);
+
+        if state.subscribers.is_empty() {
+            // The lock is only held by subscribers waiting for an update.
+            if let Ok(mut source) = self.inner.source.try_lock() {
+                *source = None;
+                state.active = false;
+                state.framebuffer = None;
+                state.pointer = None;
+                state.pointer_position = None;
+            }
+        }
+    }
+}
+
+/// Image of the whole display, composed from the bitmap updates
+struct Framebuffer {
+    size: DesktopSize,
+    /// Format of the first bitmap update, the data is allocated along.
+    format: Option<PixelFormat>,
+    data: Vec<u8>,
+}
+
+impl Framebuffer {
+    fn new(size: DesktopSize) -> Self {
+        Self {
+            size,
+            format: None,
+            data: Vec::new(),
+        }
+    }
+
+    fn stride(&self, format: PixelFormat) -> usize {
+        usize::from(self.size.width) * usize::from(format.bytes_per_pixel())
+    }
+
+    fn apply(&mut self, bitmap: FrameRef<'_>) {
+        let format = match self.format {
+            Some(format) => format,
+            None => {
+                self.data = vec![0; self.stride(bitmap.format) * usize::from(self.size.height)];
+                *self.format.insert(bitmap.format)
+            }
+        };
+
+        let stride = self.stride(format);
+        let dst_bpp = usize::from(format.bytes_per_pixel());
+        let src_bpp = usize::from(bitmap.format.bytes_per_pixel());
+
+        let left = usize::from(bitmap.left);
+        let top = usize::from(bitmap.top);
+        let width = usize::from(bitmap.width.get()).min(usize::from(self.size.width).saturating_sub(left));
+        let height = usize::from(bitmap.height.get()).min(usize::from(self.size.height).saturating_sub(top));
+
+        for row in 0..height {
+            let src_row = match bitmap.order {
+                PixelOrder::TopToBottom => row,
+                PixelOrder::BottomToTop => usize::from(bitmap.height.get()) - 1 - row,
+            };
+            let Some(src) = bitmap
+                .data
+                .get(src_row * bitmap.stride..)
+                .and_then(|data| data.get(..width * src_bpp))
+            else {
+                warn!(?bitmap, "Bitmap data is too short");
+                return;
+            };
+            let dst_start = (top + row) * stride + left * dst_bpp;
+            let dst = &mut self.data[dst_start..dst_start + width * dst_bpp];
+
+            if bitmap.format == format {
+                dst.copy_from_slice(src);
+            } else {
+                for (src, dst) in src.chunks_exact(src_bpp).zip(dst.chunks_exact_mut(dst_bpp)) {
+                    if let Ok(color) = bitmap.format.read_color(src) {
+                        let _ = format.write_color(color, dst);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a bitmap update of the whole display, or `None` if no update was received yet.
+    fn full_frame(&self) -> Option<BitmapUpdate> {
+        let format = self.format?;
+
+        Some(BitmapUpdate {
+            top: 0,
+            left: 0,
+            width: NonZeroU16::new(self.size.width)?,
+            height: NonZeroU16::new(self.size.height)?,
+            format,
+            order: PixelOrder::TopToBottom,
+            data: self.data.clone(),
+            stride: self.stride(format),
+        })
+    }
+}
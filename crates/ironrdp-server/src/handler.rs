@@ -6,6 +6,8 @@ use ironrdp_pdu::input::mouse_x::PointerXFlags;
 use ironrdp_pdu::input::sync::SyncToggleFlags;
 use ironrdp_pdu::input::{scan_code, unicode, MousePdu, MouseRelPdu, MouseXPdu};
 
+use crate::{ClientEnvironment, InputEvent, KeyboardInfo};
+
 /// Keyboard Event
 ///
 /// Describes a keyboard event received from the client
@@ -19,6 +21,109 @@ pub enum KeyboardEvent {
     Synchronize(SynchronizeFlags),
 }
 
+/// State of the lock keys, sent by the client in Synchronize events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyboardSync {
+    pub scroll_lock: bool,
+    pub num_lock: bool,
+    pub caps_lock: bool,
+    pub kana_lock: bool,
+}
+
+impl From<SynchronizeFlags> for KeyboardSync {
+    fn from(flags: SynchronizeFlags) -> Self {
+        Self {
+            scroll_lock: flags.contains(SynchronizeFlags::SCROLL_LOCK),
+            num_lock: flags.contains(SynchronizeFlags::NUM_LOCK),
+            caps_lock: flags.contains(SynchronizeFlags::CAPS_LOCK),
+            kana_lock: flags.contains(SynchronizeFlags::KANA_LOCK),
+        }
+    }
+}
+
+impl From<SyncToggleFlags> for KeyboardSync {
+    fn from(flags: SyncToggleFlags) -> Self {
+        Self {
+            scroll_lock: flags.contains(SyncToggleFlags::SCROLL_LOCK),
+            num_lock: flags.contains(SyncToggleFlags::NUM_LOCK),
+            caps_lock: flags.contains(SyncToggleFlags::CAPS_LOCK),
+            kana_lock: flags.contains(SyncToggleFlags::KANA_LOCK),
+        }
+    }
+}
+
+impl From<KeyboardSync> for SynchronizeFlags {
+    fn from(sync: KeyboardSync) -> Self {
+        let mut flags = SynchronizeFlags::empty();
+        flags.set(SynchronizeFlags::SCROLL_LOCK, sync.scroll_lock);
+        flags.set(SynchronizeFlags::NUM_LOCK, sync.num_lock);
+        flags.set(SynchronizeFlags::CAPS_LOCK, sync.caps_lock);
+        flags.set(SynchronizeFlags::KANA_LOCK, sync.kana_lock);
+        flags
+    }
+}
+
+/// Tracks the state of the lock keys from the keyboard events of a client
+///
+/// The state is set by each Synchronize event, and toggled by the presses of the lock keys in between. Handlers
+/// feed it all their keyboard events, and query it whenever they need to.
+#[derive(Debug, Clone)]
+pub struct LockKeyTracker {
+    state: SynchronizeFlags,
+    // Lock keys currently held down, whose repeated presses do not toggle the state
+    held: SynchronizeFlags,
+}
+
+impl Default for LockKeyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LockKeyTracker {
+    pub fn new() -> Self {
+        Self {
+            state: SynchronizeFlags::empty(),
+            held: SynchronizeFlags::empty(),
+        }
+    }
+
+    pub fn state(&self) -> KeyboardSync {
+        self.state.into()
+    }
+
+    pub fn update(&mut self, event: &KeyboardEvent) {
+        match *event {
+            KeyboardEvent::Synchronize(flags) => self.state = flags,
+            KeyboardEvent::Pressed { code, extended } => {
+                if let Some(key) = lock_key(code, extended) {
+                    if !self.held.contains(key) {
+                        self.state.toggle(key);
+                        self.held.insert(key);
+                    }
+                }
+            }
+            KeyboardEvent::Released { code, extended } => {
+                if let Some(key) = lock_key(code, extended) {
+                    self.held.remove(key);
+                }
+            }
+            KeyboardEvent::UnicodePressed(_) | KeyboardEvent::UnicodeReleased(_) => {}
+        }
+    }
+}
+
+/// Returns the lock toggled by the key with the given scancode, if any.
+fn lock_key(code: u8, extended: bool) -> Option<SynchronizeFlags> {
+    match (code, extended) {
+        (0x3a, false) => Some(SynchronizeFlags::CAPS_LOCK),
+        (0x45, false) => Some(SynchronizeFlags::NUM_LOCK),
+        (0x46, false) => Some(SynchronizeFlags::SCROLL_LOCK),
+        (0x70, false) => Some(SynchronizeFlags::KANA_LOCK),
+        _ => None,
+    }
+}
+
 /// Mouse Event
 ///
 /// Describes a mouse event received from the client
@@ -73,6 +178,43 @@ pub enum MouseEvent {
 pub trait RdpServerInputHandler: Send {
     fn keyboard(&mut self, event: KeyboardEvent);
     fn mouse(&mut self, event: MouseEvent);
+
+    /// Called with the time the keyboard event was received at, for latency measurements.
+    ///
+    /// Forwards the event to [`Self::keyboard`] by default.
+    fn timed_keyboard(&mut self, event: InputEvent<KeyboardEvent>) {
+        self.keyboard(event.event);
+    }
+
+    /// Called with the time the mouse event was received at, for latency measurements.
+    ///
+    /// Forwards the event to [`Self::mouse`] by default.
+    fn timed_mouse(&mut self, event: InputEvent<MouseEvent>) {
+        self.mouse(event.event);
+    }
+
+    /// Called when a client is accepted, with the keyboard it declared, before any of its input events.
+    ///
+    /// Not called for the view-only clients of a shadowed session. Ignored by default.
+    fn keyboard_info(&mut self, _info: &KeyboardInfo) {}
+
+    /// Called when a client is accepted, with the time zone and language it sent, before any of its input events.
+    ///
+    /// Meant to set up the environment of the hosted session, e.g.: with [`ClientTimeZone::to_iana`]. Not called for
+    /// the view-only clients of a shadowed session. Ignored by default.
+    ///
+    /// [`ClientTimeZone::to_iana`]: crate::ClientTimeZone::to_iana
+    fn client_environment(&mut self, _environment: &ClientEnvironment) {}
+
+    /// Called when the client synchronizes the state of its lock keys, e.g.: when its window gets the focus.
+    ///
+    /// Forwards a [`KeyboardEvent::Synchronize`] to [`Self::timed_keyboard`] by default.
+    fn keyboard_sync(&mut self, event: InputEvent<KeyboardSync>) {
+        self.timed_keyboard(InputEvent::new(
+            KeyboardEvent::Synchronize(event.event.into()),
+            event.received_at,
+        ));
+    }
 }
 
 impl From<(u8, fast_path::KeyboardFlags)> for KeyboardEvent {
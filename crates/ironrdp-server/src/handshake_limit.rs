@@ -0,0 +1,103 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::TokenBucket;
+
+/// Limits applied to incoming connections before the connection sequence starts
+///
+/// Connections exceeding the limits are closed right away, before the expensive X.224, TLS and CredSSP work.
+/// The default limits let everything through.
+#[derive(Debug, Clone, Default)]
+pub struct HandshakeLimits {
+    /// Maximum number of handshakes started per second.
+    ///
+    /// `None` disables rate limiting.
+    pub max_handshakes_per_second: Option<u32>,
+    /// Maximum number of handshakes in progress at the same time.
+    ///
+    /// `None` disables this limit.
+    pub max_pending_handshakes: Option<usize>,
+}
+
+/// Counters of the connections accepted or closed by a [`HandshakeLimiter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HandshakeStats {
+    pub accepted: u64,
+    pub rate_limited: u64,
+    pub pending_limited: u64,
+}
+
+impl HandshakeStats {
+    /// Total number of connections closed before starting the handshake.
+    pub fn rejected(&self) -> u64 {
+        self.rate_limited + self.pending_limited
+    }
+}
+
+/// Applies [`HandshakeLimits`] to incoming connections
+#[derive(Debug)]
+pub struct HandshakeLimiter {
+    limits: HandshakeLimits,
+    bucket: Option<TokenBucket>,
+    pending: Arc<AtomicUsize>,
+    stats: HandshakeStats,
+}
+
+impl HandshakeLimiter {
+    pub fn new(limits: HandshakeLimits) -> Self {
+        Self {
+            bucket: limits.max_handshakes_per_second.map(TokenBucket::new),
+            limits,
+            pending: Arc::new(AtomicUsize::new(0)),
+            stats: HandshakeStats::default(),
+        }
+    }
+
+    /// Returns a permit if a new handshake can start at `now`, or `None` if the connection should be closed.
+    ///
+    /// The handshake is considered pending until the permit is dropped.
+    pub fn try_acquire(&mut self, now: Instant) -> Option<HandshakePermit> {
+        if let Some(max_pending) = self.limits.max_pending_handshakes {
+            if self.pending.load(Ordering::Acquire) >= max_pending {
+                self.stats.pending_limited += 1;
+                return None;
+            }
+        }
+
+        if let Some(bucket) = &mut self.bucket {
+            if !bucket.try_acquire(now) {
+                self.stats.rate_limited += 1;
+                return None;
+            }
+        }
+
+        self.stats.accepted += 1;
+        self.pending.fetch_add(1, Ordering::AcqRel);
+
+        Some(HandshakePermit {
+            pending: Arc::clone(&self.pending),
+        })
+    }
+
+    /// Number of handshakes currently in progress.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    pub fn stats(&self) -> HandshakeStats {
+        self.stats
+    }
+}
+
+/// A handshake in progress, see [`HandshakeLimiter::try_acquire`]
+#[derive(Debug)]
+pub struct HandshakePermit {
+    pending: Arc<AtomicUsize>,
+}
+
+impl Drop for HandshakePermit {
+    fn drop(&mut self) {
+        self.pending.fetch_sub(1, Ordering::AcqRel);
+    }
+}
@@ -0,0 +1,320 @@
+use core::time::Duration;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::{
+    ClientEnvironment, DesktopSize, InputEvent, KeyboardEvent, KeyboardInfo, KeyboardSync, MouseEvent,
+    RdpServerInputHandler,
+};
+
+/// Minimum delay between two debug reports of dropped input events.
+const VIOLATION_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Set of scancodes a client is allowed to inject
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyAllowList {
+    keys: BTreeSet<(u8, bool)>,
+}
+
+impl KeyAllowList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the key identified by its scancode and extended flag.
+    #[must_use]
+    pub fn with_key(mut self, code: u8, extended: bool) -> Self {
+        self.allow(code, extended);
+        self
+    }
+
+    pub fn allow(&mut self, code: u8, extended: bool) {
+        self.keys.insert((code, extended));
+    }
+
+    pub fn contains(&self, code: u8, extended: bool) -> bool {
+        self.keys.contains(&(code, extended))
+    }
+}
+
+/// Input policy applied to client input before it reaches the [`RdpServerInputHandler`]
+///
+/// The default policy lets everything through.
+#[derive(Debug, Clone, Default)]
+pub struct InputPolicy {
+    /// Maximum number of events per second, applied separately to keyboard and mouse events.
+    ///
+    /// Key and button releases are never rate limited, so that no key or button is left pressed on the server.
+    ///
+    /// `None` disables rate limiting.
+    pub max_events_per_second: Option<u32>,
+    /// Clamps absolute mouse coordinates to the current desktop size.
+    pub clamp_mouse_to_desktop: bool,
+    /// When set, only the listed scancodes are forwarded.
+    ///
+    /// Unicode and synchronize events are not affected by this list.
+    pub allowed_keys: Option<KeyAllowList>,
+    /// Drops all Unicode keyboard events.
+    pub block_unicode: bool,
+}
+
+/// Counters of the input events dropped or altered by an [`InputPolicy`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputStats {
+    pub keyboard_rate_limited: u64,
+    pub mouse_rate_limited: u64,
+    pub keys_blocked: u64,
+    pub unicode_blocked: u64,
+    pub mouse_clamped: u64,
+}
+
+impl InputStats {
+    /// Total number of events which were not forwarded to the input handler.
+    pub fn dropped(&self) -> u64 {
+        self.keyboard_rate_limited + self.mouse_rate_limited + self.keys_blocked + self.unicode_blocked
+    }
+}
+
+/// Token bucket refilled at a constant rate, allowing bursts of up to one second worth of tokens
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(tokens_per_second: u32) -> Self {
+        let rate = f64::from(tokens_per_second);
+
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: None,
+        }
+    }
+
+    /// Takes one token from the bucket, returning `false` if none is available at `now`.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        if let Some(last_refill) = self.last_refill {
+            let elapsed = now.saturating_duration_since(last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        }
+        self.last_refill = Some(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Applies an [`InputPolicy`] to keyboard and mouse events
+#[derive(Debug)]
+pub struct InputFilter {
+    policy: InputPolicy,
+    desktop_size: Option<DesktopSize>,
+    keyboard_bucket: Option<TokenBucket>,
+    mouse_bucket: Option<TokenBucket>,
+    stats: InputStats,
+    unreported_violations: u64,
+    last_report: Option<Instant>,
+}
+
+impl InputFilter {
+    pub fn new(policy: InputPolicy) -> Self {
+        Self {
+            keyboard_bucket: policy.max_events_per_second.map(TokenBucket::new),
+            mouse_bucket: policy.max_events_per_second.map(TokenBucket::new),
+            policy,
+            desktop_size: None,
+            stats: InputStats::default(),
+            unreported_violations: 0,
+            last_report: None,
+        }
+    }
+
+    pub fn policy(&self) -> &InputPolicy {
+        &self.policy
+    }
+
+    /// Updates the desktop size used to clamp mouse coordinates.
+    ///
+    /// Must be called again after each resize.
+    pub fn set_desktop_size(&mut self, desktop_size: DesktopSize) {
+        self.desktop_size = Some(desktop_size);
+    }
+
+    pub fn stats(&self) -> InputStats {
+        self.stats
+    }
+
+    /// Returns the event to forward, or `None` if the policy drops it.
+    pub fn filter_keyboard(&mut self, event: KeyboardEvent, now: Instant) -> Option<KeyboardEvent> {
+        let allowed = match &event {
+            KeyboardEvent::Pressed { code, extended } | KeyboardEvent::Released { code, extended } => self
+                .policy
+                .allowed_keys
+                .as_ref()
+                .map_or(true, |keys| keys.contains(*code, *extended)),
+            _ => true,
+        };
+        if !allowed {
+            self.stats.keys_blocked += 1;
+            self.record_violation(now);
+            return None;
+        }
+
+        if self.policy.block_unicode
+            && matches!(
+                event,
+                KeyboardEvent::UnicodePressed(_) | KeyboardEvent::UnicodeReleased(_)
+            )
+        {
+            self.stats.unicode_blocked += 1;
+            self.record_violation(now);
+            return None;
+        }
+
+        let is_release = matches!(
+            event,
+            KeyboardEvent::Released { .. } | KeyboardEvent::UnicodeReleased(_)
+        );
+
+        if let Some(bucket) = self.keyboard_bucket.as_mut().filter(|_| !is_release) {
+            if !bucket.try_acquire(now) {
+                self.stats.keyboard_rate_limited += 1;
+                self.record_violation(now);
+                return None;
+            }
+        }
+
+        Some(event)
+    }
+
+    /// Returns the event to forward, or `None` if the policy drops it.
+    pub fn filter_mouse(&mut self, event: MouseEvent, now: Instant) -> Option<MouseEvent> {
+        let is_release = matches!(
+            event,
+            MouseEvent::LeftReleased
+                | MouseEvent::RightReleased
+                | MouseEvent::MiddleReleased
+                | MouseEvent::Button4Released
+                | MouseEvent::Button5Released
+        );
+
+        if let Some(bucket) = self.mouse_bucket.as_mut().filter(|_| !is_release) {
+            if !bucket.try_acquire(now) {
+                self.stats.mouse_rate_limited += 1;
+                self.record_violation(now);
+                return None;
+            }
+        }
+
+        match (event, self.desktop_size) {
+            (MouseEvent::Move { x, y }, Some(size))
+                if self.policy.clamp_mouse_to_desktop && size.width > 0 && size.height > 0 =>
+            {
+                let clamped_x = x.min(size.width - 1);
+                let clamped_y = y.min(size.height - 1);
+
+                if (clamped_x, clamped_y) != (x, y) {
+                    self.stats.mouse_clamped += 1;
+                    self.record_violation(now);
+                }
+
+                Some(MouseEvent::Move {
+                    x: clamped_x,
+                    y: clamped_y,
+                })
+            }
+            (event, _) => Some(event),
+        }
+    }
+
+    fn record_violation(&mut self, now: Instant) {
+        self.unreported_violations += 1;
+
+        // Violations are aggregated to avoid flooding the logs when a client misbehaves.
+        let should_report = self.last_report.map_or(true, |last| {
+            now.saturating_duration_since(last) >= VIOLATION_REPORT_INTERVAL
+        });
+
+        if should_report {
+            debug!(
+                count = self.unreported_violations,
+                stats = ?self.stats,
+                "Input events rejected or altered by the input policy"
+            );
+            self.unreported_violations = 0;
+            self.last_report = Some(now);
+        }
+    }
+}
+
+/// Input handler applying an [`InputFilter`] before forwarding events to the inner handler
+pub(crate) struct FilteredInputHandler {
+    pub(crate) inner: Box<dyn RdpServerInputHandler>,
+    pub(crate) filter: Arc<Mutex<InputFilter>>,
+}
+
+impl RdpServerInputHandler for FilteredInputHandler {
+    fn keyboard(&mut self, event: KeyboardEvent) {
+        self.timed_keyboard(InputEvent::new(event, Instant::now()));
+    }
+
+    fn mouse(&mut self, event: MouseEvent) {
+        self.timed_mouse(InputEvent::new(event, Instant::now()));
+    }
+
+    fn timed_keyboard(&mut self, event: InputEvent<KeyboardEvent>) {
+        let received_at = event.received_at;
+        let event = self
+            .filter
+            .lock()
+            .expect("poisoned")
+            .filter_keyboard(event.event, received_at);
+
+        if let Some(event) = event {
+            self.inner.timed_keyboard(InputEvent::new(event, received_at));
+        }
+    }
+
+    fn timed_mouse(&mut self, event: InputEvent<MouseEvent>) {
+        let received_at = event.received_at;
+        let event = self
+            .filter
+            .lock()
+            .expect("poisoned")
+            .filter_mouse(event.event, received_at);
+
+        if let Some(event) = event {
+            self.inner.timed_mouse(InputEvent::new(event, received_at));
+        }
+    }
+
+    fn keyboard_info(&mut self, info: &KeyboardInfo) {
+        self.inner.keyboard_info(info);
+    }
+
+    fn client_environment(&mut self, environment: &ClientEnvironment) {
+        self.inner.client_environment(environment);
+    }
+
+    fn keyboard_sync(&mut self, event: InputEvent<KeyboardSync>) {
+        let received_at = event.received_at;
+        let forwarded = self
+            .filter
+            .lock()
+            .expect("poisoned")
+            .filter_keyboard(KeyboardEvent::Synchronize(event.event.into()), received_at)
+            .is_some();
+
+        if forwarded {
+            self.inner.keyboard_sync(event);
+        }
+    }
+}
@@ -2,6 +2,10 @@
 #![doc(html_logo_url = "https://cdnweb.devolutions.net/images/projects/devolutions/logos/devolutions-icon-shadow.svg")]
 #![allow(clippy::arithmetic_side_effects)] // TODO: should we enable this lint back?
 
+pub use ironrdp_acceptor::{
+    windows_to_iana, AuthContext, AuthDecision, ClientEnvironment, ClientTimeZone, IanaTimeZone, KeyboardInfo,
+    SessionMetadata,
+};
 pub use {tokio, tokio_rustls};
 
 #[macro_use]
@@ -12,23 +16,34 @@ mod capabilities;
 mod clipboard;
 mod display;
 mod encoder;
+mod fanout;
 mod handler;
+mod handshake_limit;
 #[cfg(feature = "helper")]
 mod helper;
+mod input_policy;
+mod metrics;
 mod server;
+mod shadow;
 mod sound;
 
 pub use clipboard::*;
 pub use display::*;
 pub use handler::*;
+pub use handshake_limit::*;
 #[cfg(feature = "helper")]
 pub use helper::*;
+pub use input_policy::*;
+pub use metrics::*;
 pub use server::*;
+pub use shadow::*;
 pub use sound::*;
 
 #[cfg(feature = "__bench")]
 pub mod bench {
     pub mod encoder {
+        pub use crate::encoder::bench::FrameEncoder;
+
         pub mod rfx {
             pub use crate::encoder::rfx::bench::{rfx_enc, rfx_enc_tile};
         }
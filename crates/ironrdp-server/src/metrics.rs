@@ -0,0 +1,197 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Input event received from the client, along with the time it was decoded at
+#[derive(Debug, Clone)]
+pub struct InputEvent<E> {
+    pub event: E,
+    pub received_at: Instant,
+}
+
+impl<E> InputEvent<E> {
+    pub fn new(event: E, received_at: Instant) -> Self {
+        Self { event, received_at }
+    }
+}
+
+/// Percentiles of a set of durations, using the nearest-rank method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyPercentiles {
+    /// Returns the percentiles of `samples`, or `None` if there is no sample.
+    pub fn from_samples(samples: impl IntoIterator<Item = Duration>) -> Option<Self> {
+        let mut samples: Vec<Duration> = samples.into_iter().collect();
+        samples.sort_unstable();
+
+        let max = *samples.last()?;
+
+        Some(Self {
+            p50: percentile(&samples, 50),
+            p90: percentile(&samples, 90),
+            p99: percentile(&samples, 99),
+            max,
+        })
+    }
+}
+
+/// Returns the smallest sample greater than or equal to `percent` percent of the sorted, non-empty `samples`.
+fn percentile(samples: &[Duration], percent: usize) -> Duration {
+    let rank = (samples.len() * percent).div_ceil(100).max(1);
+    samples[rank - 1]
+}
+
+/// Latency measurements of the frames recorded by [`FrameMetrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerMetricsSnapshot {
+    /// Number of frames recorded since the metrics were enabled.
+    pub frames_sent: u64,
+    /// Number of Frame Acknowledge PDUs matched with a recorded frame.
+    pub frames_acknowledged: u64,
+    /// Number of Frame Acknowledge PDUs for an unknown, evicted or already acknowledged frame.
+    pub unmatched_acknowledgements: u64,
+    /// Time from the capture to the end of the encoding of the frames in the ring buffer.
+    pub encode: Option<LatencyPercentiles>,
+    /// Time from the end of the encoding to the acknowledgement of the frames in the ring buffer.
+    pub round_trip: Option<LatencyPercentiles>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FrameRecord {
+    frame_id: u32,
+    captured_at: Instant,
+    encoded_at: Instant,
+    acknowledged_at: Option<Instant>,
+}
+
+/// Ring buffer of the last frames sent to a client, matched with the client frame acknowledgements
+#[derive(Debug)]
+pub struct FrameMetrics {
+    records: VecDeque<FrameRecord>,
+    capacity: usize,
+    next_frame_id: u32,
+    frames_sent: u64,
+    frames_acknowledged: u64,
+    unmatched_acknowledgements: u64,
+}
+
+impl FrameMetrics {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    /// Frame ID sent by the clients to suspend the acknowledgements, instead of acknowledging a frame.
+    pub const SUSPEND_FRAME_ID: u32 = 0xFFFF_FFFF;
+
+    /// Creates metrics remembering the last `capacity` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "frame metrics capacity must not be zero");
+
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            next_frame_id: 0,
+            frames_sent: 0,
+            frames_acknowledged: 0,
+            unmatched_acknowledgements: 0,
+        }
+    }
+
+    /// Returns the ID of the next frame, monotonically increasing.
+    pub fn next_frame_id(&mut self) -> u32 {
+        let frame_id = self.next_frame_id;
+        self.next_frame_id = match self.next_frame_id.wrapping_add(1) {
+            Self::SUSPEND_FRAME_ID => 0,
+            next => next,
+        };
+        frame_id
+    }
+
+    /// Records the frame `frame_id`, forgetting the oldest frame when at capacity.
+    pub fn record_frame(&mut self, frame_id: u32, captured_at: Instant, encoded_at: Instant) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(FrameRecord {
+            frame_id,
+            captured_at,
+            encoded_at,
+            acknowledged_at: None,
+        });
+        self.frames_sent += 1;
+    }
+
+    /// Matches the acknowledgement of `frame_id`, received at `now`, with a recorded frame.
+    ///
+    /// Returns `false` if no unacknowledged frame has this ID.
+    pub fn acknowledge(&mut self, frame_id: u32, now: Instant) -> bool {
+        let record = self
+            .records
+            .iter_mut()
+            .rev()
+            .find(|record| record.frame_id == frame_id && record.acknowledged_at.is_none());
+
+        match record {
+            Some(record) => {
+                record.acknowledged_at = Some(now);
+                self.frames_acknowledged += 1;
+                true
+            }
+            None => {
+                self.unmatched_acknowledgements += 1;
+                false
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> ServerMetricsSnapshot {
+        ServerMetricsSnapshot {
+            frames_sent: self.frames_sent,
+            frames_acknowledged: self.frames_acknowledged,
+            unmatched_acknowledgements: self.unmatched_acknowledgements,
+            encode: LatencyPercentiles::from_samples(
+                self.records
+                    .iter()
+                    .map(|record| record.encoded_at.saturating_duration_since(record.captured_at)),
+            ),
+            round_trip: LatencyPercentiles::from_samples(self.records.iter().filter_map(|record| {
+                record
+                    .acknowledged_at
+                    .map(|acknowledged_at| acknowledged_at.saturating_duration_since(record.encoded_at))
+            })),
+        }
+    }
+}
+
+/// Handle on the frame metrics of an [`RdpServer`](crate::RdpServer), usable while the server runs
+#[derive(Debug, Clone)]
+pub struct ServerMetrics {
+    frames: Arc<Mutex<FrameMetrics>>,
+}
+
+impl ServerMetrics {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            frames: Arc::new(Mutex::new(FrameMetrics::new(capacity))),
+        }
+    }
+
+    pub(crate) fn frames(&self) -> MutexGuard<'_, FrameMetrics> {
+        self.frames.lock().expect("poisoned")
+    }
+
+    /// Returns the latency measurements of the frames in the ring buffer.
+    pub fn snapshot(&self) -> ServerMetricsSnapshot {
+        self.frames().snapshot()
+    }
+}
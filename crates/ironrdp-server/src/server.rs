@@ -1,37 +1,48 @@
 use std::net::SocketAddr;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{anyhow, bail, Context, Result};
-use ironrdp_acceptor::{self, Acceptor, AcceptorResult, BeginResult, DesktopSize};
+use ironrdp_acceptor::{self, Acceptor, AcceptorResult, Authorizer, BeginResult, DesktopSize};
 use ironrdp_async::{bytes, Framed};
 use ironrdp_cliprdr::backend::ClipboardMessage;
 use ironrdp_cliprdr::CliprdrServer;
 use ironrdp_core::{decode, encode_vec, impl_as_any};
 use ironrdp_displaycontrol::pdu::DisplayControlMonitorLayout;
 use ironrdp_displaycontrol::server::{DisplayControlHandler, DisplayControlServer};
+use ironrdp_pdu::gcc::{ChannelOptions, ClientEarlyCapabilityFlags};
 use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
 use ironrdp_pdu::input::InputEventPdu;
 use ironrdp_pdu::mcs::{SendDataIndication, SendDataRequest};
-use ironrdp_pdu::rdp::capability_sets::{BitmapCodecs, CapabilitySet, CmdFlags, GeneralExtraFlags};
+use ironrdp_pdu::rdp::capability_sets::{
+    BitmapCodecs, CapabilitySet, CmdFlags, GeneralExtraFlags, VirtualChannelFlags,
+};
 pub use ironrdp_pdu::rdp::client_info::Credentials;
 use ironrdp_pdu::rdp::headers::{ServerDeactivateAll, ShareControlPdu};
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
 use ironrdp_pdu::x224::X224;
 use ironrdp_pdu::{self, decode_err, mcs, nego, rdp, Action, PduResult};
-use ironrdp_svc::{server_encode_svc_messages, StaticChannelId, StaticChannelSet, SvcProcessor};
+use ironrdp_svc::{StaticChannelId, StaticChannelSet, SvcMessage, SvcProcessor};
 use ironrdp_tokio::{split_tokio_framed, unsplit_tokio_framed, FramedRead, FramedWrite, TokioFramed};
 use rdpsnd::server::{RdpsndServer, RdpsndServerMessage};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::task;
+use tokio::time::MissedTickBehavior;
 use tokio_rustls::TlsAcceptor;
 use {ironrdp_dvc as dvc, ironrdp_rdpsnd as rdpsnd};
 
 use crate::clipboard::CliprdrServerFactory;
 use crate::display::{DisplayUpdate, RdpServerDisplay};
 use crate::encoder::UpdateEncoder;
+use crate::fanout::DisplayFanOut;
 use crate::handler::RdpServerInputHandler;
+use crate::handshake_limit::{HandshakeLimiter, HandshakeStats};
+use crate::input_policy::{InputFilter, InputStats};
+use crate::metrics::{FrameMetrics, InputEvent, ServerMetrics};
+use crate::shadow::{AttachPolicy, AttachedClients, ClientRegistration};
 use crate::{builder, capabilities, time_warn, SoundServerFactory};
 
 #[derive(Clone)]
@@ -39,6 +50,14 @@ pub struct RdpServerOptions {
     pub addr: SocketAddr,
     pub security: RdpServerSecurity,
     pub with_remote_fx: bool,
+    /// Compresses the static channel data sent to the clients supporting it.
+    pub with_svc_compression: bool,
+    /// Policy of the clients attaching to an ongoing session.
+    ///
+    /// When `None`, a new connection waits for the ongoing session to end.
+    pub shadow_policy: Option<AttachPolicy>,
+    /// Heartbeat sent at its period to the clients supporting it, so that they detect a broken connection.
+    pub heartbeat: Option<HeartbeatPdu>,
 }
 
 #[derive(Clone)]
@@ -86,8 +105,9 @@ impl dvc::DvcProcessor for AInputHandler {
         match decode(payload).map_err(|e| decode_err!(e))? {
             ClientPdu::Mouse(pdu) => {
                 let handler = Arc::clone(&self.handler);
+                let event = InputEvent::new(pdu.into(), Instant::now());
                 task::spawn_blocking(move || {
-                    handler.blocking_lock().mouse(pdu.into());
+                    handler.blocking_lock().timed_mouse(event);
                 });
             }
         }
@@ -179,7 +199,14 @@ pub struct RdpServer {
     opts: RdpServerOptions,
     // FIXME: replace with a channel and poll/process the handler?
     handler: Arc<Mutex<Box<dyn RdpServerInputHandler>>>,
+    input_filter: Option<Arc<std::sync::Mutex<InputFilter>>>,
+    handshake_limiter: Option<Arc<std::sync::Mutex<HandshakeLimiter>>>,
+    authorizer: Option<Arc<Authorizer>>,
+    metrics: Option<ServerMetrics>,
     display: Arc<Mutex<Box<dyn RdpServerDisplay>>>,
+    display_fanout: DisplayFanOut,
+    attached_clients: AttachedClients,
+    role: SessionRole,
     static_channels: StaticChannelSet,
     sound_factory: Option<Box<dyn SoundServerFactory>>,
     cliprdr_factory: Option<Box<dyn CliprdrServerFactory>>,
@@ -208,6 +235,24 @@ impl ServerEvent {
     }
 }
 
+/// Role of the clients handled by an [`RdpServer`] instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionRole {
+    /// The client opening the session.
+    Primary,
+    /// A client attaching to the session opened by the primary client.
+    Attached(AttachPolicy),
+}
+
+impl SessionRole {
+    fn policy(self) -> AttachPolicy {
+        match self {
+            SessionRole::Primary => AttachPolicy::Interactive,
+            SessionRole::Attached(policy) => policy,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum RunState {
     Continue,
@@ -230,10 +275,19 @@ impl RdpServer {
         if let Some(snd) = sound_factory.as_mut() {
             snd.set_sender(ev_sender.clone());
         }
+        let display = Arc::new(Mutex::new(display));
+        let display_fanout = DisplayFanOut::new(Arc::clone(&display), opts.shadow_policy.is_some());
         Self {
             opts,
             handler: Arc::new(Mutex::new(handler)),
-            display: Arc::new(Mutex::new(display)),
+            input_filter: None,
+            handshake_limiter: None,
+            authorizer: None,
+            metrics: None,
+            display,
+            display_fanout,
+            attached_clients: AttachedClients::default(),
+            role: SessionRole::Primary,
             static_channels: StaticChannelSet::new(),
             sound_factory,
             cliprdr_factory,
@@ -252,6 +306,104 @@ impl RdpServer {
         &self.ev_sender
     }
 
+    /// Returns a handle on the clients attached to the session.
+    pub fn attached_clients(&self) -> &AttachedClients {
+        &self.attached_clients
+    }
+
+    /// Creates the server handling a client attaching to the ongoing session.
+    ///
+    /// It shares the input handler and the display of the session, and has no clipboard or sound channel.
+    fn attached_server(&self, policy: AttachPolicy) -> Self {
+        let (ev_sender, ev_receiver) = ServerEvent::create_channel();
+
+        Self {
+            opts: self.opts.clone(),
+            handler: Arc::clone(&self.handler),
+            input_filter: self.input_filter.clone(),
+            handshake_limiter: self.handshake_limiter.clone(),
+            authorizer: self.authorizer.clone(),
+            // The frames of the attached clients are not measured.
+            metrics: None,
+            display: Arc::clone(&self.display),
+            display_fanout: self.display_fanout.clone(),
+            attached_clients: self.attached_clients.clone(),
+            role: SessionRole::Attached(policy),
+            static_channels: StaticChannelSet::new(),
+            sound_factory: None,
+            cliprdr_factory: None,
+            ev_sender,
+            ev_receiver: Arc::new(Mutex::new(ev_receiver)),
+            creds: self.creds.clone(),
+            local_addr: self.local_addr,
+        }
+    }
+
+    /// Accepts the clients attaching to the ongoing session, until the returned future is dropped.
+    ///
+    /// Must be polled within a [`task::LocalSet`], where the attached clients are spawned.
+    async fn accept_attached_clients(self, listener: &TcpListener) {
+        let SessionRole::Attached(policy) = self.role else {
+            unreachable!("not an attached server");
+        };
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    debug!(?peer, ?policy, "Attaching client to the ongoing session");
+                    let mut server = self.attached_server(policy);
+                    task::spawn_local(async move {
+                        if let Err(error) = server.run_connection(stream).await {
+                            error!(?error, "Attached client connection error");
+                        }
+                    });
+                }
+                Err(error) => warn!(?error, "Failed to accept connection"),
+            }
+        }
+    }
+
+    /// Returns the counters of the input policy, if one is configured.
+    pub fn input_stats(&self) -> Option<InputStats> {
+        self.input_filter
+            .as_ref()
+            .map(|filter| filter.lock().expect("poisoned").stats())
+    }
+
+    pub(crate) fn set_input_filter(&mut self, filter: Arc<std::sync::Mutex<InputFilter>>) {
+        self.input_filter = Some(filter);
+    }
+
+    /// Returns the counters of the handshake limits, if configured.
+    pub fn handshake_stats(&self) -> Option<HandshakeStats> {
+        self.handshake_limiter
+            .as_ref()
+            .map(|limiter| limiter.lock().expect("poisoned").stats())
+    }
+
+    pub(crate) fn set_handshake_limiter(&mut self, limiter: HandshakeLimiter) {
+        self.handshake_limiter = Some(Arc::new(std::sync::Mutex::new(limiter)));
+    }
+
+    pub(crate) fn set_authorizer(&mut self, authorizer: Arc<Authorizer>) {
+        self.authorizer = Some(authorizer);
+    }
+
+    /// Returns a handle on the latency measurements of the frames sent to the primary client, if enabled.
+    pub fn metrics(&self) -> Option<&ServerMetrics> {
+        self.metrics.as_ref()
+    }
+
+    pub(crate) fn set_metrics(&mut self, metrics: ServerMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    fn update_input_desktop_size(&self, desktop_size: DesktopSize) {
+        if let Some(filter) = &self.input_filter {
+            filter.lock().expect("poisoned").set_desktop_size(desktop_size);
+        }
+    }
+
     fn attach_channels(&mut self, acceptor: &mut Acceptor) {
         if let Some(cliprdr_factory) = self.cliprdr_factory.as_deref() {
             let backend = cliprdr_factory.build_cliprdr_backend();
@@ -267,21 +419,48 @@ impl RdpServer {
             acceptor.attach_static_channel(RdpsndServer::new(backend));
         }
 
-        let dcs_backend = DisplayControlBackend::new(Arc::clone(&self.display));
-        let dvc = dvc::DrdynvcServer::new()
-            .with_dynamic_channel(AInputHandler {
+        let mut dvc = dvc::DrdynvcServer::new();
+        if self.role.policy() == AttachPolicy::Interactive {
+            dvc = dvc.with_dynamic_channel(AInputHandler {
                 handler: Arc::clone(&self.handler),
-            })
-            .with_dynamic_channel(DisplayControlServer::new(Box::new(dcs_backend)));
+            });
+        }
+        // Only the primary client controls the layout of the display.
+        if self.role == SessionRole::Primary {
+            let dcs_backend = DisplayControlBackend::new(Arc::clone(&self.display));
+            dvc = dvc.with_dynamic_channel(DisplayControlServer::new(Box::new(dcs_backend)));
+        }
         acceptor.attach_static_channel(dvc);
     }
 
     pub async fn run_connection(&mut self, stream: TcpStream) -> Result<()> {
+        let permit = match &self.handshake_limiter {
+            Some(limiter) => {
+                let mut limiter = limiter.lock().expect("poisoned");
+                match limiter.try_acquire(Instant::now()) {
+                    Some(permit) => Some(permit),
+                    None => {
+                        debug!(stats = ?limiter.stats(), "Handshake limit reached, closing the connection");
+                        return Ok(());
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let peer_addr = stream.peer_addr().ok();
         let framed = TokioFramed::new(stream);
 
         let size = self.display.lock().await.size().await;
-        let capabilities = capabilities::capabilities(&self.opts, size);
+        self.update_input_desktop_size(size);
+        let capabilities = capabilities::capabilities(&self.opts, size, self.metrics.is_some());
         let mut acceptor = Acceptor::new(self.opts.security.flag(), size, capabilities, self.creds.clone());
+        if let Some(authorizer) = &self.authorizer {
+            acceptor = acceptor.with_authorizer(Arc::clone(authorizer));
+        }
+        if let Some(peer_addr) = peer_addr {
+            acceptor = acceptor.with_client_addr(peer_addr);
+        }
 
         self.attach_channels(&mut acceptor);
 
@@ -322,17 +501,26 @@ impl RdpServer {
                     .await?;
                 }
 
-                self.accept_finalize(framed, acceptor).await?;
+                drop(permit);
+                let client = self.register_client(peer_addr);
+                self.accept_finalize(framed, acceptor, &client).await?;
             }
 
             BeginResult::Continue(framed) => {
-                self.accept_finalize(framed, acceptor).await?;
+                drop(permit);
+                let client = self.register_client(peer_addr);
+                self.accept_finalize(framed, acceptor, &client).await?;
             }
         };
 
         Ok(())
     }
 
+    fn register_client(&self, peer_addr: Option<SocketAddr>) -> ClientRegistration {
+        self.attached_clients
+            .register(peer_addr, self.role.policy(), self.role == SessionRole::Primary)
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         let listener = TcpListener::bind(self.opts.addr).await?;
         let local_addr = listener.local_addr()?;
@@ -364,7 +552,23 @@ impl RdpServer {
                 Ok((stream, peer)) = listener.accept() => {
                     debug!(?peer, "Received connection");
                     drop(ev_receiver);
-                    if let Err(error) = self.run_connection(stream).await {
+                    let result = match self.opts.shadow_policy {
+                        Some(policy) => {
+                            // The attached clients are dropped along with the local set, when the session ends.
+                            let attached = self.attached_server(policy);
+                            let local = task::LocalSet::new();
+                            local
+                                .run_until(async {
+                                    tokio::select! {
+                                        result = self.run_connection(stream) => result,
+                                        () = attached.accept_attached_clients(&listener) => unreachable!(),
+                                    }
+                                })
+                                .await
+                        }
+                        None => self.run_connection(stream).await,
+                    };
+                    if let Err(error) = result {
                         error!(?error, "Connection error");
                     }
                     self.static_channels = StaticChannelSet::new();
@@ -386,6 +590,23 @@ impl RdpServer {
         self.static_channels.get_channel_id_by_type::<T>()
     }
 
+    /// Encodes `messages` to be sent on the static channel of type `T`, reusing the buffers of the channel.
+    fn encode_svc_messages<T: SvcProcessor + 'static>(
+        &mut self,
+        messages: Vec<SvcMessage>,
+        user_channel_id: u16,
+    ) -> Result<Vec<u8>> {
+        let channel_id = self
+            .get_channel_id_by_type::<T>()
+            .ok_or_else(|| anyhow!("SVC channel not found"))?;
+        let channel = self
+            .static_channels
+            .get_by_type_mut::<T>()
+            .ok_or_else(|| anyhow!("SVC channel not found"))?;
+
+        Ok(channel.server_encode(messages, channel_id, user_channel_id)?)
+    }
+
     async fn dispatch_pdu(
         &mut self,
         action: Action,
@@ -415,6 +636,7 @@ impl RdpServer {
         Ok(RunState::Continue)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn dispatch_display_update(
         update: DisplayUpdate,
         writer: &mut impl FramedWrite,
@@ -422,11 +644,40 @@ impl RdpServer {
         io_channel_id: u16,
         buffer: &mut Vec<u8>,
         mut encoder: UpdateEncoder,
+        metrics: Option<&ServerMetrics>,
     ) -> Result<(RunState, UpdateEncoder)> {
+        let is_frame = matches!(update, DisplayUpdate::Bitmap(_) | DisplayUpdate::Frame(_));
+        let measured_frame = metrics
+            .filter(|_| is_frame && encoder.supports_frame_markers())
+            .map(|metrics| (metrics, metrics.frames().next_frame_id(), Instant::now()));
+        let frame_id = measured_frame.map(|(_, frame_id, _)| frame_id);
+
         let mut fragmenter = match update {
             DisplayUpdate::Bitmap(bitmap) => {
                 let (enc, res) = task::spawn_blocking(move || {
-                    let res = time_warn!("Encoding bitmap", 10, encoder.bitmap(bitmap).map(|r| r.into_owned()));
+                    let res = time_warn!(
+                        "Encoding bitmap",
+                        10,
+                        encoder
+                            .marked_bitmap(bitmap.as_frame_ref(), frame_id)
+                            .map(|r| r.into_owned())
+                    );
+                    (encoder, res)
+                })
+                .await?;
+                encoder = enc;
+                res.map(|r| encoder.fragmenter_from_owned(r))
+            }
+            DisplayUpdate::Frame(frame) => {
+                // The frame is released when dropped, once encoded.
+                let (enc, res) = task::spawn_blocking(move || {
+                    let res = time_warn!(
+                        "Encoding frame",
+                        10,
+                        encoder
+                            .marked_bitmap(frame.as_frame_ref(), frame_id)
+                            .map(|r| r.into_owned())
+                    );
                     (encoder, res)
                 })
                 .await?;
@@ -446,6 +697,11 @@ impl RdpServer {
         }
         .context("error during update encoding")?;
 
+        // The frame is recorded before being sent, so that its acknowledgement can be matched.
+        if let Some((metrics, frame_id, captured_at)) = measured_frame {
+            metrics.frames().record_frame(frame_id, captured_at, Instant::now());
+        }
+
         if fragmenter.size_hint() > buffer.len() {
             buffer.resize(fragmenter.size_hint(), 0);
         }
@@ -504,10 +760,7 @@ impl RdpServer {
                         }
                     }
                     .context("failed to send rdpsnd event")?;
-                    let channel_id = self
-                        .get_channel_id_by_type::<RdpsndServer>()
-                        .ok_or_else(|| anyhow!("SVC channel not found"))?;
-                    let data = server_encode_svc_messages(msgs.into(), channel_id, user_channel_id)?;
+                    let data = self.encode_svc_messages::<RdpsndServer>(msgs.into(), user_channel_id)?;
                     writer.write_all(&data).await?;
                 }
                 ServerEvent::Clipboard(c) => {
@@ -525,10 +778,7 @@ impl RdpServer {
                         }
                     }
                     .context("failed to send clipboard event")?;
-                    let channel_id = self
-                        .get_channel_id_by_type::<CliprdrServer>()
-                        .ok_or_else(|| anyhow!("SVC channel not found"))?;
-                    let data = server_encode_svc_messages(msgs.into(), channel_id, user_channel_id)?;
+                    let data = self.encode_svc_messages::<CliprdrServer>(msgs.into(), user_channel_id)?;
                     writer.write_all(&data).await?;
                 }
             }
@@ -537,6 +787,7 @@ impl RdpServer {
         Ok(RunState::Continue)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn client_loop<R, W>(
         &mut self,
         reader: &mut Framed<R>,
@@ -544,17 +795,21 @@ impl RdpServer {
         io_channel_id: u16,
         user_channel_id: u16,
         mut encoder: UpdateEncoder,
+        client: &ClientRegistration,
+        heartbeat: Option<HeartbeatPdu>,
     ) -> Result<RunState>
     where
         R: FramedRead,
         W: FramedWrite,
     {
         debug!("Starting client loop");
-        let mut display_updates = self.display.lock().await.updates().await?;
+        let mut display_updates = self.display_fanout.subscribe();
         let mut writer = SharedWriter::new(writer);
         let mut display_writer = writer.clone();
         let mut event_writer = writer.clone();
+        let mut heartbeat_writer = writer.clone();
         let ev_receiver = Arc::clone(&self.ev_receiver);
+        let metrics = self.metrics.clone();
         let s = Rc::new(Mutex::new(self));
 
         let this = Rc::clone(&s);
@@ -575,7 +830,7 @@ impl RdpServer {
         let dispatch_display = async move {
             let mut buffer = vec![0u8; 4096];
             loop {
-                if let Some(update) = display_updates.next_update().await {
+                if let Some(update) = display_updates.next_update().await? {
                     match Self::dispatch_display_update(
                         update,
                         &mut display_writer,
@@ -583,6 +838,7 @@ impl RdpServer {
                         io_channel_id,
                         &mut buffer,
                         encoder,
+                        metrics.as_ref(),
                     )
                     .await?
                     {
@@ -624,10 +880,29 @@ impl RdpServer {
             }
         };
 
+        let send_heartbeats = async move {
+            let Some(heartbeat) = heartbeat.filter(|heartbeat| heartbeat.period != 0) else {
+                return core::future::pending::<Result<RunState>>().await;
+            };
+
+            let mut interval = tokio::time::interval(heartbeat.period_duration());
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                send_heartbeat(heartbeat, io_channel_id, user_channel_id, &mut heartbeat_writer).await?;
+            }
+        };
+
         let state = tokio::select!(
             state = dispatch_pdu => state,
             state = dispatch_display => state,
             state = dispatch_events => state,
+            state = send_heartbeats => state,
+            () = client.disconnected() => {
+                debug!("Disconnecting the client");
+                Ok(RunState::Disconnect)
+            }
         );
 
         debug!("End of client loop: {state:?}");
@@ -639,6 +914,7 @@ impl RdpServer {
         reader: &mut Framed<R>,
         writer: &mut Framed<W>,
         result: AcceptorResult,
+        client: &ClientRegistration,
     ) -> Result<RunState>
     where
         R: FramedRead,
@@ -646,6 +922,19 @@ impl RdpServer {
     {
         debug!("Client accepted");
 
+        if !result.reactivation {
+            client.set_metadata(result.session_metadata.clone());
+
+            if !self.drops_input() {
+                let mut handler = self.handler.lock().await;
+
+                if let Some(keyboard) = result.keyboard.as_ref() {
+                    handler.keyboard_info(keyboard);
+                }
+                handler.client_environment(&result.environment);
+            }
+        }
+
         if !result.input_events.is_empty() {
             debug!("Handling input event backlog from acceptor sequence");
             self.handle_input_backlog(
@@ -658,14 +947,41 @@ impl RdpServer {
         }
 
         self.static_channels = result.static_channels;
+
+        // The data of the channels joined with one of the compression options is compressed if the client
+        // advertised support for server-to-client compression, and negotiated a compression type.
+        let compression_supported = result.capabilities.iter().any(|c| match c {
+            CapabilitySet::VirtualChannel(c) => c.flags.contains(VirtualChannelFlags::COMPRESSION_SERVER_TO_CLIENT),
+            _ => false,
+        });
+        if let Some(compression_type) = result
+            .compression_type
+            .filter(|_| self.opts.with_svc_compression && compression_supported)
+        {
+            for (channel_id, channel_def) in &result.channels {
+                if !channel_def
+                    .options
+                    .intersects(ChannelOptions::COMPRESS_RDP | ChannelOptions::COMPRESS)
+                {
+                    continue;
+                }
+
+                if let Some(channel) = self.static_channels.get_by_channel_id_mut(*channel_id) {
+                    debug!(?channel, ?compression_type, "Enable compression");
+                    channel.enable_compression(compression_type);
+                }
+            }
+        }
+
         if !result.reactivation {
-            for (_type_id, channel, channel_id) in self.static_channels.iter_mut() {
-                debug!(?channel, ?channel_id, "Start");
-                let Some(channel_id) = channel_id else {
+            // The channels are started in the order they were requested by the client.
+            for (_, channel_id) in &result.static_channel_ids {
+                let Some(channel) = self.static_channels.get_by_channel_id_mut(*channel_id) else {
                     continue;
                 };
+                debug!(?channel, channel_id, "Start");
                 let svc_responses = channel.start()?;
-                let response = server_encode_svc_messages(svc_responses, channel_id, result.user_channel_id)?;
+                let response = channel.server_encode(svc_responses, *channel_id, result.user_channel_id)?;
                 writer.write_all(&response).await?;
             }
         }
@@ -742,8 +1058,22 @@ impl RdpServer {
 
         let encoder = UpdateEncoder::new(surface_flags, rfxcodec);
 
+        let heartbeat = self.opts.heartbeat.filter(|_| {
+            result
+                .early_capability
+                .is_some_and(|flags| flags.contains(ClientEarlyCapabilityFlags::SUPPORT_HEART_BEAT_PDU))
+        });
+
         let state = self
-            .client_loop(reader, writer, result.io_channel_id, result.user_channel_id, encoder)
+            .client_loop(
+                reader,
+                writer,
+                result.io_channel_id,
+                result.user_channel_id,
+                encoder,
+                client,
+                heartbeat,
+            )
             .await
             .context("client loop failure")?;
 
@@ -777,32 +1107,46 @@ impl RdpServer {
         Ok(())
     }
 
+    fn drops_input(&self) -> bool {
+        let drops_input = self.role.policy() == AttachPolicy::ViewOnly;
+        if drops_input {
+            trace!("Dropping the input of a view-only client");
+        }
+        drops_input
+    }
+
     async fn handle_fastpath(&mut self, input: FastPathInput) {
+        if self.drops_input() {
+            return;
+        }
+
+        let received_at = Instant::now();
+
         for event in input.0 {
             let mut handler = self.handler.lock().await;
             match event {
                 FastPathInputEvent::KeyboardEvent(flags, key) => {
-                    handler.keyboard((key, flags).into());
+                    handler.timed_keyboard(InputEvent::new((key, flags).into(), received_at));
                 }
 
                 FastPathInputEvent::UnicodeKeyboardEvent(flags, key) => {
-                    handler.keyboard((key, flags).into());
+                    handler.timed_keyboard(InputEvent::new((key, flags).into(), received_at));
                 }
 
                 FastPathInputEvent::SyncEvent(flags) => {
-                    handler.keyboard(flags.into());
+                    handler.keyboard_sync(InputEvent::new(flags.into(), received_at));
                 }
 
                 FastPathInputEvent::MouseEvent(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 FastPathInputEvent::MouseEventEx(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 FastPathInputEvent::MouseEventRel(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 FastPathInputEvent::QoeEvent(quality) => {
@@ -825,6 +1169,16 @@ impl RdpServer {
                     return Ok(true);
                 }
 
+                rdp::headers::ShareDataPdu::FrameAcknowledge(pdu) => {
+                    if pdu.frame_id == FrameMetrics::SUSPEND_FRAME_ID {
+                        debug!("Client suspended the frame acknowledgements");
+                    } else if let Some(metrics) = &self.metrics {
+                        if !metrics.frames().acknowledge(pdu.frame_id, Instant::now()) {
+                            trace!(frame_id = pdu.frame_id, "Unmatched frame acknowledgement");
+                        }
+                    }
+                }
+
                 unexpected => {
                     warn!(?unexpected, "Unexpected share data pdu");
                 }
@@ -855,7 +1209,7 @@ impl RdpServer {
 
                 if let Some(svc) = self.static_channels.get_by_channel_id_mut(data.channel_id) {
                     let response_pdus = svc.process(&data.user_data)?;
-                    let response = server_encode_svc_messages(response_pdus, data.channel_id, user_channel_id)?;
+                    let response = svc.server_encode(response_pdus, data.channel_id, user_channel_id)?;
                     writer.write_all(&response).await?;
                 } else {
                     warn!(channel_id = data.channel_id, "Unexpected channel received: ID",);
@@ -877,31 +1231,37 @@ impl RdpServer {
     }
 
     async fn handle_input_event(&mut self, input: InputEventPdu) {
+        if self.drops_input() {
+            return;
+        }
+
+        let received_at = Instant::now();
+
         for event in input.0 {
             let mut handler = self.handler.lock().await;
             match event {
                 ironrdp_pdu::input::InputEvent::ScanCode(key) => {
-                    handler.keyboard((key.key_code, key.flags).into());
+                    handler.timed_keyboard(InputEvent::new((key.key_code, key.flags).into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::Unicode(key) => {
-                    handler.keyboard((key.unicode_code, key.flags).into());
+                    handler.timed_keyboard(InputEvent::new((key.unicode_code, key.flags).into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::Sync(sync) => {
-                    handler.keyboard(sync.flags.into());
+                    handler.keyboard_sync(InputEvent::new(sync.flags.into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::Mouse(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::MouseX(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::MouseRel(mouse) => {
-                    handler.mouse(mouse.into());
+                    handler.timed_mouse(InputEvent::new(mouse.into(), received_at));
                 }
 
                 ironrdp_pdu::input::InputEvent::Unused(_) => {}
@@ -909,7 +1269,12 @@ impl RdpServer {
         }
     }
 
-    async fn accept_finalize<S>(&mut self, mut framed: TokioFramed<S>, mut acceptor: Acceptor) -> Result<()>
+    async fn accept_finalize<S>(
+        &mut self,
+        mut framed: TokioFramed<S>,
+        mut acceptor: Acceptor,
+        client: &ClientRegistration,
+    ) -> Result<()>
     where
         S: AsyncRead + AsyncWrite + Sync + Send + Unpin,
     {
@@ -920,11 +1285,13 @@ impl RdpServer {
 
             let (mut reader, mut writer) = split_tokio_framed(new_framed);
 
-            match self.client_accepted(&mut reader, &mut writer, result).await? {
+            match self.client_accepted(&mut reader, &mut writer, result, client).await? {
                 RunState::Continue => {
                     unreachable!();
                 }
                 RunState::DeactivationReactivation { desktop_size } => {
+                    self.update_input_desktop_size(desktop_size);
+
                     // No description of such behavior was found in the
                     // specification, but apparently, we must keep the channel
                     // state as they were during reactivation. This fixes
@@ -950,6 +1317,22 @@ impl RdpServer {
     }
 }
 
+async fn send_heartbeat(
+    heartbeat: HeartbeatPdu,
+    io_channel_id: u16,
+    user_channel_id: u16,
+    writer: &mut impl FramedWrite,
+) -> Result<(), anyhow::Error> {
+    let pdu = SendDataIndication {
+        initiator_id: user_channel_id,
+        channel_id: io_channel_id,
+        user_data: encode_vec(&heartbeat)?.into(),
+    };
+    let msg = encode_vec(&X224(pdu))?;
+    writer.write_all(&msg).await?;
+    Ok(())
+}
+
 async fn deactivate_all(
     io_channel_id: u16,
     user_channel_id: u16,
@@ -0,0 +1,130 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_acceptor::SessionMetadata;
+use tokio::sync::Notify;
+
+/// Policy applied to a client attached to the session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttachPolicy {
+    /// The input of the client is forwarded to the input handler, merged with the input of the other
+    /// interactive clients in arrival order.
+    #[default]
+    Interactive,
+    /// The client only receives the display updates, its input is dropped.
+    ViewOnly,
+}
+
+/// Identifier of a client attached to the session, unique for the lifetime of the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientId(u64);
+
+/// Description of a client attached to the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachedClient {
+    pub id: ClientId,
+    pub peer_addr: Option<SocketAddr>,
+    pub policy: AttachPolicy,
+    /// Whether this client opened the session, which ends when it disconnects.
+    pub primary: bool,
+    /// Metadata attached by the authorizer, empty until the client is authorized.
+    pub metadata: SessionMetadata,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    clients: Vec<(AttachedClient, Arc<Notify>)>,
+    next_id: u64,
+}
+
+/// Handle on the clients attached to the session of an [`RdpServer`](crate::RdpServer)
+///
+/// The handle is cheap to clone, and can be used from any task.
+#[derive(Debug, Clone, Default)]
+pub struct AttachedClients {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl AttachedClients {
+    /// Returns the clients currently attached, in attach order.
+    pub fn list(&self) -> Vec<AttachedClient> {
+        let registry = self.registry.lock().expect("poisoned");
+
+        registry.clients.iter().map(|(client, _)| client.clone()).collect()
+    }
+
+    /// Disconnects a client, returning `false` if it is not attached.
+    ///
+    /// Disconnecting the primary client ends the session, and thus disconnects all the attached clients.
+    pub fn disconnect(&self, id: ClientId) -> bool {
+        let registry = self.registry.lock().expect("poisoned");
+
+        match registry.clients.iter().find(|(client, _)| client.id == id) {
+            Some((_, disconnect)) => {
+                disconnect.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn register(
+        &self,
+        peer_addr: Option<SocketAddr>,
+        policy: AttachPolicy,
+        primary: bool,
+    ) -> ClientRegistration {
+        let mut registry = self.registry.lock().expect("poisoned");
+
+        let id = ClientId(registry.next_id);
+        registry.next_id += 1;
+
+        let disconnect = Arc::new(Notify::new());
+        registry.clients.push((
+            AttachedClient {
+                id,
+                peer_addr,
+                policy,
+                primary,
+                metadata: SessionMetadata::new(),
+            },
+            Arc::clone(&disconnect),
+        ));
+
+        ClientRegistration {
+            id,
+            registry: Arc::clone(&self.registry),
+            disconnect,
+        }
+    }
+}
+
+/// Entry of a client in [`AttachedClients`], removed when dropped
+pub(crate) struct ClientRegistration {
+    id: ClientId,
+    registry: Arc<Mutex<Registry>>,
+    disconnect: Arc<Notify>,
+}
+
+impl ClientRegistration {
+    pub(crate) fn set_metadata(&self, metadata: SessionMetadata) {
+        let mut registry = self.registry.lock().expect("poisoned");
+
+        if let Some((client, _)) = registry.clients.iter_mut().find(|(client, _)| client.id == self.id) {
+            client.metadata = metadata;
+        }
+    }
+
+    /// Completes when the client is disconnected with [`AttachedClients::disconnect`].
+    pub(crate) async fn disconnected(&self) {
+        self.disconnect.notified().await;
+    }
+}
+
+impl Drop for ClientRegistration {
+    fn drop(&mut self) {
+        let mut registry = self.registry.lock().expect("poisoned");
+
+        registry.clients.retain(|(client, _)| client.id != self.id);
+    }
+}
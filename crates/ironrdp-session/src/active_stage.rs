@@ -1,25 +1,49 @@
+use core::time::Duration;
 use std::rc::Rc;
+use std::time::Instant;
 
-use ironrdp_connector::connection_activation::ConnectionActivationSequence;
-use ironrdp_connector::ConnectionResult;
+use ironrdp_cliprdr::{ClipboardPolicy, CliprdrClient};
+use ironrdp_connector::redirection::ServerRedirection;
+use ironrdp_connector::{ConnectionResult, DesktopSize};
 use ironrdp_core::WriteBuf;
 use ironrdp_displaycontrol::client::DisplayControlClient;
 use ironrdp_dvc::{DrdynvcClient, DvcProcessor, DynamicVirtualChannel};
+use ironrdp_graphics::bitmap_cache::PersistentKey;
 use ironrdp_graphics::pointer::DecodedPointer;
+use ironrdp_pdu::gcc::Monitor;
 use ironrdp_pdu::geometry::InclusiveRectangle;
 use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::input::InputEventPdu;
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_pdu::rdp::multitransport::MultitransportRequestPdu;
+use ironrdp_pdu::rdp::session_info::{InfoData, LogonErrorsInfo, LogonInfo, ServerAutoReconnect};
 use ironrdp_pdu::{mcs, Action};
-use ironrdp_svc::{SvcProcessor, SvcProcessorMessages};
+use ironrdp_rail::client::Rail;
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::{DrivePolicy, Rdpdr};
+use ironrdp_rdpei::client::{PenContact, RdpeiClient, TouchContact};
+use ironrdp_svc::{SvcMessage, SvcProcessor, SvcProcessorMessages};
 
 use crate::fast_path::UpdateKind;
+use crate::heartbeat::{HealthEvent, HeartbeatMonitor, HeartbeatPolicy};
 use crate::image::DecodedImage;
+use crate::pointer::PointerCacheStats;
+use crate::stats::{SessionStats, StatsLog};
 use crate::{fast_path, x224, SessionError, SessionErrorExt, SessionResult};
 
 pub struct ActiveStage {
     x224_processor: x224::Processor,
     fast_path_processor: fast_path::Processor,
     no_server_pointer: bool,
+    /// Whether the server supports fast-path input, the slow-path Input Event PDU being used otherwise.
+    fastpath_input: bool,
+    /// Kept across the deactivation-reactivation sequences, which rebuild the fast-path processor.
+    reassembly_limits: fast_path::ReassemblyLimits,
+    stats_log: Option<StatsLog>,
+    heartbeat_monitor: HeartbeatMonitor,
+    /// Heartbeat received since the last call to [`ActiveStage::poll_connection_health`], which records its receipt.
+    pending_heartbeat: Option<HeartbeatPdu>,
 }
 
 impl ActiveStage {
@@ -36,6 +60,10 @@ impl ActiveStage {
             user_channel_id: connection_result.user_channel_id,
             no_server_pointer: connection_result.no_server_pointer,
             pointer_software_rendering: connection_result.pointer_software_rendering,
+            pointer_cache_size: connection_result.pointer_cache_size,
+            bitmap_cache: connection_result.bitmap_cache,
+            frame_acknowledge: connection_result.frame_acknowledge,
+            reassembly_limits: fast_path::ReassemblyLimits::default(),
         }
         .build();
 
@@ -43,6 +71,11 @@ impl ActiveStage {
             x224_processor,
             fast_path_processor,
             no_server_pointer: connection_result.no_server_pointer,
+            fastpath_input: connection_result.server_input_flags.supports_fastpath_input(),
+            reassembly_limits: fast_path::ReassemblyLimits::default(),
+            stats_log: None,
+            heartbeat_monitor: HeartbeatMonitor::new(HeartbeatPolicy::default()),
+            pending_heartbeat: None,
         }
     }
 
@@ -52,6 +85,8 @@ impl ActiveStage {
 
     /// Encodes outgoing input events and modifies image if necessary (e.g for client-side pointer
     /// rendering).
+    ///
+    /// The events are sent using the slow-path Input Event PDU when the server does not support fast-path input.
     pub fn process_fastpath_input(
         &mut self,
         image: &mut DecodedImage,
@@ -65,10 +100,16 @@ impl ActiveStage {
         // response frame + graphics update
         let mut output = Vec::with_capacity(2);
 
-        // Encoding fastpath response frame
-        // PERF: unnecessary copy
-        let fastpath_input = FastPathInput(events.to_vec());
-        let frame = ironrdp_core::encode_vec(&fastpath_input).map_err(SessionError::encode)?;
+        let frame = if self.fastpath_input {
+            // PERF: unnecessary copy
+            let fastpath_input = FastPathInput(events.to_vec());
+            ironrdp_core::encode_vec(&fastpath_input).map_err(SessionError::encode)?
+        } else {
+            let mut frame = WriteBuf::new();
+            self.x224_processor
+                .encode_static(&mut frame, ShareDataPdu::Input(InputEventPdu::from_fast_path(events)))?;
+            frame.into_inner()
+        };
         output.push(ActiveStageOutput::ResponseFrame(frame));
 
         // If pointer rendering is disabled - we can skip the rest
@@ -114,12 +155,53 @@ impl ActiveStage {
                 )
             }
             Action::X224 => {
-                let outputs = self
-                    .x224_processor
-                    .process(frame)?
-                    .into_iter()
-                    .map(TryFrom::try_from)
-                    .collect::<Result<Vec<_>, _>>()?;
+                let mut outputs = Vec::new();
+
+                for output in self.x224_processor.process(frame)? {
+                    if let x224::ProcessorOutput::DeactivationReactivation {
+                        io_channel_id,
+                        user_channel_id,
+                        desktop_size,
+                        no_server_pointer,
+                        pointer_software_rendering,
+                        pointer_cache_size,
+                        ref bitmap_cache,
+                        server_input_flags,
+                        frame_acknowledge,
+                    } = output
+                    {
+                        // The static and dynamic channels are left untouched, only the state depending on the
+                        // re-exchanged capabilities is rebuilt. In particular, the pointer and bitmap caches are
+                        // invalidated.
+                        self.fast_path_processor = fast_path::ProcessorBuilder {
+                            io_channel_id,
+                            user_channel_id,
+                            no_server_pointer,
+                            pointer_software_rendering,
+                            pointer_cache_size,
+                            bitmap_cache: bitmap_cache.clone(),
+                            frame_acknowledge,
+                            reassembly_limits: self.reassembly_limits,
+                        }
+                        .build();
+                        self.no_server_pointer = no_server_pointer;
+                        self.fastpath_input = server_input_flags.supports_fastpath_input();
+
+                        resize_image(image, desktop_size);
+                    }
+
+                    if let x224::ProcessorOutput::MonitorLayout { desktop_size, .. } = &output {
+                        resize_image(image, *desktop_size);
+                    }
+
+                    if let x224::ProcessorOutput::Heartbeat(heartbeat) = output {
+                        self.pending_heartbeat = Some(heartbeat);
+                        continue;
+                    }
+
+                    outputs.push(ActiveStageOutput::try_from(output)?);
+                }
+
                 (outputs, Vec::new())
             }
         };
@@ -142,6 +224,14 @@ impl ActiveStage {
                 UpdateKind::PointerBitmap(pointer) => {
                     stage_outputs.push(ActiveStageOutput::PointerBitmap(pointer));
                 }
+                UpdateKind::FrameBoundary(frame_id) => {
+                    stage_outputs.push(ActiveStageOutput::FrameBoundary(frame_id));
+                }
+                UpdateKind::WindowOrder(order) => {
+                    if let Some(rail) = self.get_svc_processor_mut::<Rail>() {
+                        rail.handle_window_order(&order);
+                    }
+                }
             }
         }
 
@@ -152,22 +242,93 @@ impl ActiveStage {
         self.fast_path_processor = processor;
     }
 
+    /// Sets the limits of the reassembly of the fragmented fast-path updates, an update exceeding them being skipped.
+    pub fn set_reassembly_limits(&mut self, limits: fast_path::ReassemblyLimits) {
+        self.reassembly_limits = limits;
+        self.fast_path_processor.set_reassembly_limits(limits);
+    }
+
     pub fn set_no_server_pointer(&mut self, no_server_pointer: bool) {
         self.no_server_pointer = no_server_pointer;
     }
 
+    /// Returns the statistics of the pointer cache since the last (re)activation.
+    pub fn pointer_cache_stats(&self) -> PointerCacheStats {
+        self.fast_path_processor.pointer_cache_stats()
+    }
+
+    /// Returns the keys of the bitmaps stored in the persistent bitmap caches, to send in the Persistent Key List PDU
+    /// of a later connection.
+    pub fn bitmap_cache_persistent_keys(&self) -> Vec<PersistentKey> {
+        self.fast_path_processor.bitmap_cache_persistent_keys()
+    }
+
+    /// Returns the traffic of the virtual channels since the beginning of the session.
+    pub fn stats_snapshot(&self) -> SessionStats {
+        self.x224_processor.stats()
+    }
+
+    /// Sets the interval at which [`ActiveStage::log_stats`] summarizes the traffic of the virtual channels, or
+    /// disables the summary if `None`.
+    pub fn set_stats_log_interval(&mut self, interval: Option<Duration>) {
+        self.stats_log = interval.map(StatsLog::new);
+    }
+
+    /// Logs the traffic of the virtual channels since the last summary at the debug level, if the interval set with
+    /// [`ActiveStage::set_stats_log_interval`] elapsed.
+    ///
+    /// Meant to be called after each processed frame, `now` being the current time.
+    pub fn log_stats(&mut self, now: Instant) {
+        if let Some(stats_log) = &mut self.stats_log {
+            stats_log.poll(now, || self.x224_processor.stats());
+        }
+    }
+
+    /// Sets the thresholds of missed heartbeats overriding the ones sent by the server.
+    pub fn set_heartbeat_policy(&mut self, policy: HeartbeatPolicy) {
+        self.heartbeat_monitor.set_policy(policy);
+    }
+
+    /// Returns [`ActiveStageOutput::ConnectionHealth`] if a threshold of missed heartbeats was reached, or if a
+    /// heartbeat was received again after that, see [`HeartbeatMonitor`].
+    ///
+    /// The heartbeats processed since the last call are recorded as received at `now`, the current time. As no frame
+    /// is received when the connection is broken, this must be called periodically, e.g. every second, and not only
+    /// after each processed frame.
+    pub fn poll_connection_health(&mut self, now: Instant) -> Option<ActiveStageOutput> {
+        let recovered = self
+            .pending_heartbeat
+            .take()
+            .and_then(|heartbeat| self.heartbeat_monitor.on_heartbeat(heartbeat, now));
+
+        recovered
+            .or_else(|| self.heartbeat_monitor.poll(now))
+            .map(ActiveStageOutput::ConnectionHealth)
+    }
+
     /// Encodes client-side graceful shutdown request. Note that upon sending this request,
     /// client should wait for server's ShutdownDenied PDU before closing the connection.
     ///
+    /// The static channels are closed first, so that the frames with their final PDUs are sent before the shutdown
+    /// request.
+    ///
     /// Client-side graceful shutdown is defined in [MS-RDPBCGR]
     ///
     /// [MS-RDPBCGR]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/27915739-8f77-487e-9927-55008af7fd68
-    pub fn graceful_shutdown(&self) -> SessionResult<Vec<ActiveStageOutput>> {
+    pub fn graceful_shutdown(&mut self) -> SessionResult<Vec<ActiveStageOutput>> {
+        let mut outputs = self
+            .x224_processor
+            .close_static_channels()?
+            .into_iter()
+            .map(ActiveStageOutput::try_from)
+            .collect::<SessionResult<Vec<_>>>()?;
+
         let mut frame = WriteBuf::new();
         self.x224_processor
             .encode_static(&mut frame, ShareDataPdu::ShutdownRequest)?;
+        outputs.push(ActiveStageOutput::ResponseFrame(frame.into_inner()));
 
-        Ok(vec![ActiveStageOutput::ResponseFrame(frame.into_inner())])
+        Ok(outputs)
     }
 
     /// Send a pdu on the static global channel. Typically used to send input events
@@ -187,6 +348,10 @@ impl ActiveStage {
         self.x224_processor.get_dvc::<T>()
     }
 
+    pub fn get_dvc_mut<T: DvcProcessor + 'static>(&mut self) -> Option<&mut DynamicVirtualChannel> {
+        self.x224_processor.get_dvc_mut::<T>()
+    }
+
     /// Completes user's SVC request with data, required to sent it over the network and returns
     /// a buffer with encoded data.
     pub fn process_svc_processor_messages<C: SvcProcessor + 'static>(
@@ -232,6 +397,7 @@ impl ActiveStage {
                     Ok(messages) => messages,
                     Err(e) => return Some(Err(SessionError::encode(e))),
                 };
+                dvc.record_sent(&svc_messages);
 
                 return Some(
                     self.process_svc_processor_messages(SvcProcessorMessages::<DrdynvcClient>::new(svc_messages)),
@@ -245,6 +411,129 @@ impl ActiveStage {
 
         None
     }
+
+    /// Fully encodes the phase changes of touch contacts for sending over the Input Virtual Channel.
+    ///
+    /// The `timestamp` is the time at which the changes occurred, in microseconds.
+    ///
+    /// If the Input Virtual Channel is not available, or not yet connected, this method will return `None`.
+    pub fn encode_touch_contacts(
+        &mut self,
+        contacts: &[TouchContact],
+        timestamp: u64,
+    ) -> Option<SessionResult<Vec<u8>>> {
+        let Some(dvc) = self.get_dvc_mut::<RdpeiClient>() else {
+            debug!("Could not encode touch contacts: Input Virtual Channel is not available");
+            return None;
+        };
+
+        let Some(channel_id) = dvc.channel_id() else {
+            debug!("Could not encode touch contacts: Input Virtual Channel is not yet connected");
+            return None;
+        };
+
+        let rdpei = dvc.channel_processor_downcast_mut::<RdpeiClient>()?;
+        let svc_messages = match rdpei.encode_touch_contacts(channel_id, contacts, timestamp) {
+            Ok(messages) => messages,
+            Err(e) => return Some(Err(SessionError::encode(e))),
+        };
+        dvc.record_sent(&svc_messages);
+
+        Some(self.process_svc_processor_messages(SvcProcessorMessages::<DrdynvcClient>::new(svc_messages)))
+    }
+
+    /// Fully encodes the state of the pen for sending over the Input Virtual Channel.
+    ///
+    /// The `timestamp` is the time at which the state was sampled, in microseconds.
+    ///
+    /// If the Input Virtual Channel is not available, or not yet connected, this method will return `None`.
+    pub fn encode_pen(&mut self, pen: &PenContact, timestamp: u64) -> Option<SessionResult<Vec<u8>>> {
+        let Some(dvc) = self.get_dvc_mut::<RdpeiClient>() else {
+            debug!("Could not encode pen input: Input Virtual Channel is not available");
+            return None;
+        };
+
+        let Some(channel_id) = dvc.channel_id() else {
+            debug!("Could not encode pen input: Input Virtual Channel is not yet connected");
+            return None;
+        };
+
+        let rdpei = dvc.channel_processor_downcast_mut::<RdpeiClient>()?;
+        let svc_messages = match rdpei.encode_pen(channel_id, pen, timestamp) {
+            Ok(messages) => messages,
+            Err(e) => return Some(Err(SessionError::encode(e))),
+        };
+        dvc.record_sent(&svc_messages);
+
+        Some(self.process_svc_processor_messages(SvcProcessorMessages::<DrdynvcClient>::new(svc_messages)))
+    }
+
+    /// Fully encodes the announcement of a new drive for sending over the RDPDR static virtual channel.
+    ///
+    /// The drive is registered on the [`Rdpdr`] processor, so that subsequent I/O requests from the server targeting
+    /// `device_id` are accepted. Drive redirection must have been enabled using [`Rdpdr::with_drives`].
+    ///
+    /// The I/O requests targeting the drive are checked against `policy` before reaching the RDPDR backend.
+    ///
+    /// If the RDPDR channel is not available, this method will return `None`.
+    pub fn announce_drive(
+        &mut self,
+        device_id: u32,
+        name: String,
+        policy: DrivePolicy,
+    ) -> Option<SessionResult<Vec<u8>>> {
+        let Some(rdpdr) = self.get_svc_processor_mut::<Rdpdr>() else {
+            debug!("Could not announce a drive: RDPDR channel is not available");
+            return None;
+        };
+
+        let pdu = RdpdrPdu::ClientDeviceListAnnounce(rdpdr.add_drive(device_id, name, policy));
+
+        Some(self.process_svc_processor_messages(SvcProcessorMessages::<Rdpdr>::new(vec![SvcMessage::from(pdu)])))
+    }
+
+    /// Fully encodes the removal of a previously announced device for sending over the RDPDR static virtual channel.
+    ///
+    /// Once removed, I/O requests from the server targeting `device_id` are rejected.
+    ///
+    /// If the RDPDR channel is not available, or if no device with this ID was announced, this method will
+    /// return `None`.
+    pub fn remove_device(&mut self, device_id: u32) -> Option<SessionResult<Vec<u8>>> {
+        let Some(rdpdr) = self.get_svc_processor_mut::<Rdpdr>() else {
+            debug!("Could not remove a device: RDPDR channel is not available");
+            return None;
+        };
+
+        let Some(pdu) = rdpdr.remove_device(device_id) else {
+            debug!(device_id, "Could not remove a device: no such device");
+            return None;
+        };
+
+        let pdu = RdpdrPdu::ClientDriveDeviceListRemove(pdu);
+
+        Some(self.process_svc_processor_messages(SvcProcessorMessages::<Rdpdr>::new(vec![SvcMessage::from(pdu)])))
+    }
+
+    /// Changes the directions in which the clipboard is shared, taking effect for the next copy.
+    ///
+    /// Returns `false` if the CLIPRDR channel is not available.
+    pub fn set_clipboard_policy(&mut self, policy: ClipboardPolicy) -> bool {
+        let Some(cliprdr) = self.get_svc_processor_mut::<CliprdrClient>() else {
+            debug!("Could not set the clipboard policy: CLIPRDR channel is not available");
+            return false;
+        };
+
+        cliprdr.set_policy(policy);
+
+        true
+    }
+}
+
+/// Reallocates `image` if its dimensions do not match the `desktop_size`.
+fn resize_image(image: &mut DecodedImage, desktop_size: DesktopSize) {
+    if image.width() != desktop_size.width || image.height() != desktop_size.height {
+        *image = DecodedImage::new(image.pixel_format(), desktop_size.width, desktop_size.height);
+    }
 }
 
 #[derive(Debug)]
@@ -253,10 +542,50 @@ pub enum ActiveStageOutput {
     GraphicsUpdate(InclusiveRectangle),
     PointerDefault,
     PointerHidden,
-    PointerPosition { x: u16, y: u16 },
+    PointerPosition {
+        x: u16,
+        y: u16,
+    },
     PointerBitmap(Rc<DecodedPointer>),
     Terminate(GracefulDisconnectReason),
-    DeactivateAll(Box<ConnectionActivationSequence>),
+    /// The [Deactivation-Reactivation Sequence] initiated by the server completed, and the session resumed.
+    ///
+    /// The image passed to [`ActiveStage::process`] was already resized if needed, but the UI may have to be
+    /// resized as well.
+    ///
+    /// [Deactivation-Reactivation Sequence]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
+    DeactivationReactivation {
+        new_desktop_size: DesktopSize,
+    },
+    SessionInfo(SessionInfo),
+    /// The server sent a new monitor layout.
+    ///
+    /// The image passed to [`ActiveStage::process`] was already resized to the bounding box of the monitors if
+    /// needed. Malformed layouts are ignored.
+    MonitorLayoutChanged(Vec<Monitor>),
+    /// The frame with the given ID is complete, as delimited by the surface frame markers.
+    ///
+    /// The graphics updates of the frame were all reported before, so this is the right time to present them.
+    FrameBoundary(u32),
+    /// The health of the connection changed, as seen from the Heartbeat PDUs sent by the server.
+    ///
+    /// Only reported by [`ActiveStage::poll_connection_health`].
+    ConnectionHealth(HealthEvent),
+    /// The server asked for a beep, e.g.: when a console application wrote the bell character.
+    PlaySound {
+        frequency_hz: u32,
+        duration_ms: u32,
+    },
+    /// The server requested a UDP side channel with an Initiate Multitransport Request PDU.
+    ///
+    /// The side channels are not supported: unless [`ironrdp_connector::Config::decline_multitransport`] is disabled,
+    /// the response declining it was already returned as a [`ActiveStageOutput::ResponseFrame`].
+    MultitransportRequest(MultitransportRequestPdu),
+    /// The server redirected the client to another server.
+    ///
+    /// The session is over: the client should close the connection, and connect to the target server after calling
+    /// [`ironrdp_connector::ClientConnector::redirect`].
+    ServerRedirection(ServerRedirection),
 }
 
 impl TryFrom<x224::ProcessorOutput> for ActiveStageOutput {
@@ -270,14 +599,62 @@ impl TryFrom<x224::ProcessorOutput> for ActiveStageOutput {
                     x224::DisconnectDescription::McsDisconnect(reason) => match reason {
                         mcs::DisconnectReason::ProviderInitiated => GracefulDisconnectReason::ServerInitiated,
                         mcs::DisconnectReason::UserRequested => GracefulDisconnectReason::UserInitiated,
-                        other => GracefulDisconnectReason::Other(other.description().to_owned()),
+                        other => GracefulDisconnectReason::McsDisconnect(other),
                     },
                     x224::DisconnectDescription::ErrorInfo(info) => GracefulDisconnectReason::Other(info.description()),
                 };
 
                 Ok(Self::Terminate(desc))
             }
-            x224::ProcessorOutput::DeactivateAll(cas) => Ok(Self::DeactivateAll(cas)),
+            x224::ProcessorOutput::DeactivationReactivation { desktop_size, .. } => {
+                Ok(Self::DeactivationReactivation {
+                    new_desktop_size: desktop_size,
+                })
+            }
+            x224::ProcessorOutput::SessionInfo(info_data) => Ok(Self::SessionInfo(SessionInfo::from(info_data))),
+            x224::ProcessorOutput::MonitorLayout { monitors, .. } => Ok(Self::MonitorLayoutChanged(monitors)),
+            x224::ProcessorOutput::PlaySound(pdu) => Ok(Self::PlaySound {
+                frequency_hz: pdu.frequency_hz,
+                duration_ms: pdu.duration_ms,
+            }),
+            x224::ProcessorOutput::MultitransportRequest(request) => Ok(Self::MultitransportRequest(request)),
+            x224::ProcessorOutput::ServerRedirection(redirection) => Ok(Self::ServerRedirection(redirection)),
+            x224::ProcessorOutput::Heartbeat(_) => Err(reason_err!(
+                "ActiveStage",
+                "Heartbeat PDU must be tracked by the active stage"
+            )),
+        }
+    }
+}
+
+/// Session information sent by the server using the [Save Session Info PDU].
+///
+/// [Save Session Info PDU]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/a21a1bd9-2303-49c1-90ec-3932435c248c
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionInfo {
+    /// The user logged on (`INFOTYPE_LOGON` or `INFOTYPE_LOGON_LONG`).
+    Logon(LogonInfo),
+    /// The user logged on, without further details (`INFOTYPE_LOGON_PLAINNOTIFY`).
+    PlainNotify,
+    /// Extended logon information (`INFOTYPE_LOGON_EXTENDED`).
+    LogonExtended {
+        /// Auto-reconnect cookie to use when reconnecting to this session.
+        auto_reconnect: Option<ServerAutoReconnect>,
+        /// Logon error or warning notification, e.g. the password must be updated.
+        errors_info: Option<LogonErrorsInfo>,
+    },
+}
+
+impl From<InfoData> for SessionInfo {
+    fn from(info_data: InfoData) -> Self {
+        match info_data {
+            InfoData::LogonInfoV1(info) => Self::Logon(info.logon_info),
+            InfoData::LogonInfoV2(info) => Self::Logon(info.logon_info),
+            InfoData::PlainNotify => Self::PlainNotify,
+            InfoData::LogonExtended(extended) => Self::LogonExtended {
+                auto_reconnect: extended.auto_reconnect,
+                errors_info: extended.errors_info,
+            },
         }
     }
 }
@@ -288,6 +665,9 @@ impl TryFrom<x224::ProcessorOutput> for ActiveStageOutput {
 pub enum GracefulDisconnectReason {
     UserInitiated,
     ServerInitiated,
+    /// The server sent an MCS Disconnect Provider Ultimatum with another reason than the user or server
+    /// initiated disconnect.
+    McsDisconnect(mcs::DisconnectReason),
     Other(String),
 }
 
@@ -296,6 +676,7 @@ impl GracefulDisconnectReason {
         match self {
             GracefulDisconnectReason::UserInitiated => "user initiated disconnect".to_owned(),
             GracefulDisconnectReason::ServerInitiated => "server initiated disconnect".to_owned(),
+            GracefulDisconnectReason::McsDisconnect(reason) => reason.description().to_owned(),
             GracefulDisconnectReason::Other(description) => description.clone(),
         }
     }
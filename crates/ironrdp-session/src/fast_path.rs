@@ -1,19 +1,24 @@
 use std::rc::Rc;
 
-use ironrdp_core::{decode_cursor, DecodeErrorKind, ReadCursor, WriteBuf};
+use ironrdp_core::{decode_cursor, Decode as _, DecodeErrorKind, ReadCursor, WriteBuf};
+use ironrdp_graphics::bitmap_cache::{BitmapCache, PersistentKey};
+use ironrdp_graphics::color_conversion::{rdp_15bit_to_rgb, rdp_16bit_to_rgb};
 use ironrdp_graphics::image_processing::PixelFormat;
 use ironrdp_graphics::pointer::{DecodedPointer, PointerBitmapTarget};
 use ironrdp_graphics::rdp6::BitmapStreamDecoder;
 use ironrdp_graphics::rle::RlePixelFormat;
 use ironrdp_pdu::codecs::rfx::FrameAcknowledgePdu;
-use ironrdp_pdu::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation};
+use ironrdp_pdu::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation, UpdateCode};
 use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
+use ironrdp_pdu::orders::{Bounds, Brush, ControlFlags, DrawingOrder, MemBlt, OrdersDecoder, PrimaryOrder};
 use ironrdp_pdu::pointer::PointerUpdateData;
+use ironrdp_pdu::rdp::capability_sets::BitmapCacheRev2;
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
 use ironrdp_pdu::surface_commands::{FrameAction, FrameMarkerPdu, SurfaceCommand};
+use ironrdp_rail::pdu::WindowOrder;
 
-use crate::image::DecodedImage;
-use crate::pointer::PointerCache;
+use crate::image::{BitmapPixelFormat, DecodedImage};
+use crate::pointer::{PointerCache, PointerCacheStats};
 use crate::utils::CodecId;
 use crate::{rfx, SessionError, SessionErrorExt, SessionResult};
 
@@ -23,8 +28,14 @@ pub enum UpdateKind {
     Region(InclusiveRectangle),
     PointerDefault,
     PointerHidden,
-    PointerPosition { x: u16, y: u16 },
+    PointerPosition {
+        x: u16,
+        y: u16,
+    },
     PointerBitmap(Rc<DecodedPointer>),
+    /// End of the frame with the given ID, the regions updated by the frame being reported before.
+    FrameBoundary(u32),
+    WindowOrder(WindowOrder),
 }
 
 pub struct Processor {
@@ -33,6 +44,8 @@ pub struct Processor {
     marker_processor: FrameMarkerProcessor,
     bitmap_stream_decoder: BitmapStreamDecoder,
     pointer_cache: PointerCache,
+    orders_decoder: OrdersDecoder,
+    bitmap_cache: Option<BitmapCache>,
     use_system_pointer: bool,
     mouse_pos_update: Option<(u16, u16)>,
     no_server_pointer: bool,
@@ -44,6 +57,23 @@ impl Processor {
         self.mouse_pos_update = Some((x, y));
     }
 
+    pub fn pointer_cache_stats(&self) -> PointerCacheStats {
+        self.pointer_cache.stats()
+    }
+
+    /// Returns the keys of the bitmaps stored in the persistent bitmap caches.
+    pub fn bitmap_cache_persistent_keys(&self) -> Vec<PersistentKey> {
+        self.bitmap_cache
+            .as_ref()
+            .map(BitmapCache::persistent_keys)
+            .unwrap_or_default()
+    }
+
+    /// Sets the limits of the reassembly, applied from the next fragmented update.
+    pub fn set_reassembly_limits(&mut self, limits: ReassemblyLimits) {
+        self.complete_data.limits = limits;
+    }
+
     /// Process input fast path frame and return list of updates.
     pub fn process(
         &mut self,
@@ -64,6 +94,12 @@ impl Processor {
         let header = decode_cursor::<FastPathHeader>(&mut input).map_err(SessionError::decode)?;
         debug!(fast_path_header = ?header, "Received Fast-Path packet");
 
+        // Some servers are sending packets without any update, which are ignored.
+        if header.data_length == 0 {
+            trace!("Skipped empty Fast-Path packet");
+            return Ok(processor_updates);
+        }
+
         let update_pdu = decode_cursor::<FastPathUpdatePdu<'_>>(&mut input).map_err(SessionError::decode)?;
         trace!(fast_path_update_fragmentation = ?update_pdu.fragmentation);
 
@@ -77,13 +113,18 @@ impl Processor {
             return Ok(Vec::new());
         };
 
+        if update_code == UpdateCode::Orders {
+            self.process_orders(image, data.as_slice(), &mut processor_updates)?;
+
+            return Ok(processor_updates);
+        }
+
         let update = FastPathUpdate::decode_with_code(data.as_slice(), update_code);
 
         match update {
             Ok(FastPathUpdate::SurfaceCommands(surface_commands)) => {
                 trace!("Received Surface Commands: {} pieces", surface_commands.len());
-                let update_region = self.process_surface_commands(image, output, surface_commands)?;
-                processor_updates.push(UpdateKind::Region(update_region));
+                self.process_surface_commands(image, output, surface_commands, &mut processor_updates)?;
             }
             Ok(FastPathUpdate::Bitmap(bitmap_update)) => {
                 trace!("Received bitmap update");
@@ -114,7 +155,12 @@ impl Processor {
                                 usize::from(update.width),
                                 usize::from(update.height),
                             ) {
-                                Ok(()) => image.apply_rgb24_bitmap(&buf, &update.rectangle)?,
+                                Ok(()) => image.apply_bitmap(
+                                    &buf,
+                                    BitmapPixelFormat::Rgb24,
+                                    usize::from(update.width) * BitmapPixelFormat::Rgb24.bytes_per_pixel(),
+                                    &update.rectangle,
+                                )?,
                                 Err(err) => {
                                     warn!("Invalid RDP6_BITMAP_STREAM: {err}");
                                     update.rectangle.clone()
@@ -133,12 +179,23 @@ impl Processor {
                                 usize::from(update.height),
                                 usize::from(update.bits_per_pixel),
                             ) {
-                                Ok(RlePixelFormat::Rgb16) => image.apply_rgb16_bitmap(&buf, &update.rectangle)?,
-
-                                // TODO: support other pixel formats…
-                                Ok(format @ (RlePixelFormat::Rgb8 | RlePixelFormat::Rgb15 | RlePixelFormat::Rgb24)) => {
-                                    warn!("Received RLE-compressed bitmap with unsupported color depth: {format:?}");
-                                    update.rectangle.clone()
+                                Ok(format) => {
+                                    let format = match format {
+                                        RlePixelFormat::Rgb15 => Some(BitmapPixelFormat::Rgb15),
+                                        RlePixelFormat::Rgb16 => Some(BitmapPixelFormat::Rgb16),
+                                        RlePixelFormat::Rgb24 => Some(BitmapPixelFormat::Bgr24),
+                                        // Palette-based color depths are refused when connecting.
+                                        RlePixelFormat::Rgb8 => None,
+                                    };
+
+                                    if let Some(format) = format {
+                                        // The decompressed rows are not padded.
+                                        let stride = usize::from(update.width) * format.bytes_per_pixel();
+                                        image.apply_bitmap(&buf, format, stride, &update.rectangle)?
+                                    } else {
+                                        warn!("Received RLE-compressed bitmap with unsupported color depth: 8 bpp");
+                                        update.rectangle.clone()
+                                    }
                                 }
 
                                 Err(e) => {
@@ -153,11 +210,21 @@ impl Processor {
                         // four bytes (including up to three bytes of padding, as necessary).
                         trace!("Uncompressed raw bitmap");
 
-                        match update.bits_per_pixel {
-                            16 => image.apply_rgb16_bitmap(update.bitmap_data, &update.rectangle)?,
-                            // TODO: support other pixel formats…
-                            unsupported => {
-                                warn!("Invalid raw bitmap with {unsupported} bytes per pixels");
+                        let format = match update.bits_per_pixel {
+                            15 => Some(BitmapPixelFormat::Rgb15),
+                            16 => Some(BitmapPixelFormat::Rgb16),
+                            24 => Some(BitmapPixelFormat::Bgr24),
+                            32 => Some(BitmapPixelFormat::Bgrx32),
+                            _ => None,
+                        };
+
+                        match format {
+                            Some(format) => {
+                                let stride = (usize::from(update.width) * format.bytes_per_pixel()).next_multiple_of(4);
+                                image.apply_bitmap(update.bitmap_data, format, stride, &update.rectangle)?
+                            }
+                            None => {
+                                warn!("Invalid raw bitmap with {} bits per pixel", update.bits_per_pixel);
                                 update.rectangle.clone()
                             }
                         }
@@ -216,10 +283,14 @@ impl Processor {
                     PointerUpdateData::Color(pointer) => {
                         let cache_index = pointer.cache_index;
 
-                        let decoded_pointer = Rc::new(
-                            DecodedPointer::decode_color_pointer_attribute(&pointer, bitmap_target)
-                                .expect("Failed to decode color pointer attribute"),
-                        );
+                        let decoded_pointer =
+                            match DecodedPointer::decode_color_pointer_attribute(&pointer, bitmap_target) {
+                                Ok(decoded_pointer) => Rc::new(decoded_pointer),
+                                Err(e) => {
+                                    warn!(error = %e, "Ignoring color pointer which could not be decoded");
+                                    return Ok(processor_updates);
+                                }
+                            };
 
                         let _ = self
                             .pointer_cache
@@ -250,16 +321,19 @@ impl Processor {
                                 }
                             }
                         } else {
-                            warn!("Cached pointer not found {}", cache_index);
+                            warn!(cache_index, "Cached pointer not found");
                         }
                     }
                     PointerUpdateData::New(pointer) => {
                         let cache_index = pointer.color_pointer.cache_index;
 
-                        let decoded_pointer = Rc::new(
-                            DecodedPointer::decode_pointer_attribute(&pointer, bitmap_target)
-                                .expect("Failed to decode pointer attribute"),
-                        );
+                        let decoded_pointer = match DecodedPointer::decode_pointer_attribute(&pointer, bitmap_target) {
+                            Ok(decoded_pointer) => Rc::new(decoded_pointer),
+                            Err(e) => {
+                                warn!(error = %e, "Ignoring new pointer which could not be decoded");
+                                return Ok(processor_updates);
+                            }
+                        };
 
                         let _ = self
                             .pointer_cache
@@ -274,10 +348,14 @@ impl Processor {
                     PointerUpdateData::Large(pointer) => {
                         let cache_index = pointer.cache_index;
 
-                        let decoded_pointer: Rc<DecodedPointer> = Rc::new(
-                            DecodedPointer::decode_large_pointer_attribute(&pointer, bitmap_target)
-                                .expect("Failed to decode large pointer attribute"),
-                        );
+                        let decoded_pointer =
+                            match DecodedPointer::decode_large_pointer_attribute(&pointer, bitmap_target) {
+                                Ok(decoded_pointer) => Rc::new(decoded_pointer),
+                                Err(e) => {
+                                    warn!(error = %e, "Ignoring large pointer which could not be decoded");
+                                    return Ok(processor_updates);
+                                }
+                            };
 
                         let _ = self
                             .pointer_cache
@@ -304,12 +382,131 @@ impl Processor {
         Ok(processor_updates)
     }
 
+    /// Processes the drawing orders of an orders update.
+    ///
+    /// Among the drawing orders, only the windowing orders used by remote applications and the orders of the
+    /// revision 2 bitmap cache are supported. The processing of the update stops at the first order which can't be
+    /// decoded.
+    fn process_orders(
+        &mut self,
+        image: &mut DecodedImage,
+        data: &[u8],
+        processor_updates: &mut Vec<UpdateKind>,
+    ) -> SessionResult<()> {
+        let mut src = ReadCursor::new(data);
+
+        if src.len() < 2 {
+            warn!("Received invalid orders update");
+            return Ok(());
+        }
+        let number_orders = src.read_u16();
+
+        for _ in 0..number_orders {
+            if src.is_empty() {
+                warn!("Received truncated orders update");
+                break;
+            }
+
+            let control_flags = ControlFlags::from_bits_retain(src.peek_u8());
+
+            if !control_flags.contains(ControlFlags::STANDARD) {
+                match WindowOrder::decode(&mut src) {
+                    Ok(order) => processor_updates.push(UpdateKind::WindowOrder(order)),
+                    Err(error) => {
+                        debug!(%error, "Stopping at unsupported drawing order");
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            match self.orders_decoder.decode(&mut src) {
+                Ok(DrawingOrder::Primary { order, bounds }) => {
+                    if let Some(rect) = self.draw_primary_order(image, order, bounds)? {
+                        processor_updates.push(UpdateKind::Region(rect));
+                    }
+                }
+                Ok(DrawingOrder::CacheBitmapRev2(order)) => match &mut self.bitmap_cache {
+                    Some(bitmap_cache) => {
+                        if let Err(error) = bitmap_cache.insert(&order) {
+                            warn!(%error, "Received invalid cached bitmap");
+                        }
+                    }
+                    None => warn!("Received cached bitmap without advertising the bitmap cache"),
+                },
+                Ok(DrawingOrder::UnsupportedSecondary { order_type }) => {
+                    debug!(order_type, "Skipped unsupported secondary drawing order");
+                }
+                Err(error) => {
+                    warn!(%error, "Stopping at invalid or unsupported drawing order");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws a MemBlt or Mem3Blt order, returning the updated region, if any.
+    fn draw_primary_order(
+        &mut self,
+        image: &mut DecodedImage,
+        order: PrimaryOrder,
+        bounds: Option<Bounds>,
+    ) -> SessionResult<Option<InclusiveRectangle>> {
+        let (blt, pattern) = match order {
+            PrimaryOrder::MemBlt(blt) => (blt, None),
+            PrimaryOrder::Mem3Blt(mem3_blt) => {
+                if mem3_blt.brush.style != Brush::STYLE_SOLID {
+                    warn!(
+                        style = mem3_blt.brush.style,
+                        "Skipped Mem3Blt order with unsupported brush"
+                    );
+                    return Ok(None);
+                }
+                (mem3_blt.blt, Some(mem3_blt.fore_color))
+            }
+        };
+
+        let Some(bitmap_cache) = &self.bitmap_cache else {
+            warn!("Received MemBlt order without advertising the bitmap cache");
+            return Ok(None);
+        };
+
+        // The high byte is the index of the color table, used at 8 bpp only.
+        let cache_id = blt.cache_id.to_le_bytes()[0];
+        let bitmap = match bitmap_cache.get(cache_id, blt.cache_index) {
+            Ok(Some(bitmap)) => bitmap,
+            Ok(None) => {
+                warn!(cache_id, cache_index = blt.cache_index, "Bitmap cache slot is empty");
+                return Ok(None);
+            }
+            Err(error) => {
+                warn!(%error, "Received MemBlt order with invalid cache slot");
+                return Ok(None);
+            }
+        };
+
+        let Some((dest, src_x, src_y)) = clip_blt(&blt, bounds, image.width(), image.height()) else {
+            return Ok(None);
+        };
+
+        let pattern = pattern
+            .map(|color| order_color_to_rgb(color, bitmap.bits_per_pixel()))
+            .unwrap_or_default();
+
+        image
+            .apply_cached_bitmap(bitmap, src_x, src_y, blt.rop, pattern, &dest)
+            .map(Some)
+    }
+
     fn process_surface_commands(
         &mut self,
         image: &mut DecodedImage,
         output: &mut WriteBuf,
         surface_commands: Vec<SurfaceCommand<'_>>,
-    ) -> SessionResult<InclusiveRectangle> {
+        processor_updates: &mut Vec<UpdateKind>,
+    ) -> SessionResult<()> {
         let mut update_rectangle = InclusiveRectangle::empty();
 
         for command in surface_commands {
@@ -363,12 +560,87 @@ impl Processor {
                         marker.frame_action,
                         marker.frame_id.unwrap_or(0)
                     );
-                    self.marker_processor.process(&marker, output)?;
+
+                    if let Some(frame_id) = self.marker_processor.process(&marker, output)? {
+                        // The region updated so far belongs to the frame being ended.
+                        let region = core::mem::replace(&mut update_rectangle, InclusiveRectangle::empty());
+                        processor_updates.push(UpdateKind::Region(region));
+                        processor_updates.push(UpdateKind::FrameBoundary(frame_id));
+                    }
                 }
             }
         }
 
-        Ok(update_rectangle)
+        processor_updates.push(UpdateKind::Region(update_rectangle));
+
+        Ok(())
+    }
+}
+
+/// Clips the destination of a MemBlt order to its bounds and to the image, returning the clipped destination along
+/// with the matching position in the source bitmap.
+fn clip_blt(
+    blt: &MemBlt,
+    bounds: Option<Bounds>,
+    image_width: u16,
+    image_height: u16,
+) -> Option<(InclusiveRectangle, u16, u16)> {
+    if blt.width <= 0 || blt.height <= 0 || image_width == 0 || image_height == 0 {
+        return None;
+    }
+
+    let left = i32::from(blt.left);
+    let top = i32::from(blt.top);
+    let mut clipped = [
+        left,
+        top,
+        left + i32::from(blt.width) - 1,
+        top + i32::from(blt.height) - 1,
+    ];
+
+    let mut limits = vec![[0, 0, i32::from(image_width) - 1, i32::from(image_height) - 1]];
+    if let Some(bounds) = bounds {
+        limits.push([
+            i32::from(bounds.left),
+            i32::from(bounds.top),
+            i32::from(bounds.right),
+            i32::from(bounds.bottom),
+        ]);
+    }
+
+    for [limit_left, limit_top, limit_right, limit_bottom] in limits {
+        clipped = [
+            clipped[0].max(limit_left),
+            clipped[1].max(limit_top),
+            clipped[2].min(limit_right),
+            clipped[3].min(limit_bottom),
+        ];
+    }
+
+    let [clipped_left, clipped_top, clipped_right, clipped_bottom] = clipped;
+    if clipped_left > clipped_right || clipped_top > clipped_bottom {
+        return None;
+    }
+
+    let src_x = u16::try_from(i32::from(blt.src_x) + clipped_left - left).ok()?;
+    let src_y = u16::try_from(i32::from(blt.src_y) + clipped_top - top).ok()?;
+
+    let dest = InclusiveRectangle {
+        left: u16::try_from(clipped_left).ok()?,
+        top: u16::try_from(clipped_top).ok()?,
+        right: u16::try_from(clipped_right).ok()?,
+        bottom: u16::try_from(clipped_bottom).ok()?,
+    };
+
+    Some((dest, src_x, src_y))
+}
+
+/// Converts a color of a drawing order, in the color depth of the session, to RGB.
+fn order_color_to_rgb(color: [u8; 3], bits_per_pixel: u8) -> [u8; 3] {
+    match bits_per_pixel {
+        15 => rdp_15bit_to_rgb(u16::from_le_bytes([color[0], color[1]])),
+        16 => rdp_16bit_to_rgb(u16::from_le_bytes([color[0], color[1]])),
+        _ => color,
     }
 }
 
@@ -381,16 +653,30 @@ pub struct ProcessorBuilder {
     /// `UpdateKind::PointerBitmap` will not be generated. Remote pointer will be drawn
     /// via software rendering on top of the output image.
     pub pointer_software_rendering: bool,
+    /// Number of slots of the pointer cache. When the cache is full, the least recently used pointer is evicted.
+    pub pointer_cache_size: u16,
+    /// Revision 2 bitmap cache advertised in the capabilities, the MemBlt and Mem3Blt orders drawing from it.
+    pub bitmap_cache: Option<BitmapCacheRev2>,
+    /// Acknowledge the completed frames with a Frame Acknowledge PDU, as requested by the server.
+    pub frame_acknowledge: bool,
+    /// Limits of the reassembly of the fragmented updates.
+    pub reassembly_limits: ReassemblyLimits,
 }
 
 impl ProcessorBuilder {
     pub fn build(self) -> Processor {
         Processor {
-            complete_data: CompleteData::new(),
+            complete_data: CompleteData::new(self.reassembly_limits),
             rfx_handler: rfx::DecodingContext::new(),
-            marker_processor: FrameMarkerProcessor::new(self.user_channel_id, self.io_channel_id),
+            marker_processor: FrameMarkerProcessor::new(
+                self.user_channel_id,
+                self.io_channel_id,
+                self.frame_acknowledge,
+            ),
             bitmap_stream_decoder: BitmapStreamDecoder::default(),
-            pointer_cache: PointerCache::default(),
+            pointer_cache: PointerCache::new(usize::from(self.pointer_cache_size)),
+            orders_decoder: OrdersDecoder::new(),
+            bitmap_cache: self.bitmap_cache.as_ref().map(BitmapCache::new),
             use_system_pointer: true,
             mouse_pos_update: None,
             no_server_pointer: self.no_server_pointer,
@@ -399,14 +685,48 @@ impl ProcessorBuilder {
     }
 }
 
+/// Limits of the reassembly of the fragmented Fast-Path updates.
+///
+/// An update exceeding them is skipped, and the processing resumes with the next update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReassemblyLimits {
+    /// Maximum size of a reassembled update, in bytes.
+    pub max_reassembled_update_size: usize,
+    /// Maximum number of fragments of an update.
+    pub max_outstanding_fragments: usize,
+}
+
+impl ReassemblyLimits {
+    pub const DEFAULT_MAX_REASSEMBLED_UPDATE_SIZE: usize = 16 * 1024 * 1024; // 16 MiB
+    pub const DEFAULT_MAX_OUTSTANDING_FRAGMENTS: usize = 4096;
+}
+
+impl Default for ReassemblyLimits {
+    fn default() -> Self {
+        Self {
+            max_reassembled_update_size: Self::DEFAULT_MAX_REASSEMBLED_UPDATE_SIZE,
+            max_outstanding_fragments: Self::DEFAULT_MAX_OUTSTANDING_FRAGMENTS,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct CompleteData {
+    limits: ReassemblyLimits,
     fragmented_data: Option<Vec<u8>>,
+    fragment_count: usize,
+    /// The update being received exceeded the limits, and its remaining fragments are discarded.
+    discarding: bool,
 }
 
 impl CompleteData {
-    fn new() -> Self {
-        Self { fragmented_data: None }
+    fn new(limits: ReassemblyLimits) -> Self {
+        Self {
+            limits,
+            fragmented_data: None,
+            fragment_count: 0,
+            discarding: false,
+        }
     }
 
     fn process_data(&mut self, data: &[u8], fragmentation: Fragmentation) -> Option<Vec<u8>> {
@@ -419,7 +739,9 @@ impl CompleteData {
             Fragmentation::First => {
                 self.check_data_is_empty();
 
-                self.fragmented_data = Some(data.to_vec());
+                self.fragmented_data = Some(Vec::new());
+                self.fragment_count = 0;
+                self.append_data(data);
 
                 None
             }
@@ -430,6 +752,7 @@ impl CompleteData {
             }
             Fragmentation::Last => {
                 self.append_data(data);
+                self.fragment_count = 0;
 
                 self.fragmented_data.take()
             }
@@ -441,46 +764,88 @@ impl CompleteData {
             warn!("Skipping pending Fast-Path Update internal multiple elements data");
             self.fragmented_data = None;
         }
+
+        self.discarding = false;
     }
 
     fn append_data(&mut self, data: &[u8]) {
-        if let Some(fragmented_data) = self.fragmented_data.as_mut() {
-            fragmented_data.extend_from_slice(data);
-        } else {
+        if self.discarding {
+            return;
+        }
+
+        let Some(fragmented_data) = self.fragmented_data.as_mut() else {
             warn!("Got unexpected Next fragmentation PDU without prior First fragmentation PDU");
+            return;
+        };
+
+        self.fragment_count += 1;
+        let size = fragmented_data.len().saturating_add(data.len());
+
+        if size > self.limits.max_reassembled_update_size || self.fragment_count > self.limits.max_outstanding_fragments
+        {
+            warn!(
+                size,
+                fragment_count = self.fragment_count,
+                limits = ?self.limits,
+                "Skipping fragmented Fast-Path update exceeding the reassembly limits"
+            );
+
+            self.fragmented_data = None;
+            self.discarding = true;
+            return;
         }
+
+        fragmented_data.extend_from_slice(data);
     }
 }
 
 struct FrameMarkerProcessor {
     user_channel_id: u16,
     io_channel_id: u16,
+    acknowledge: bool,
+    /// ID of the frame in progress, between the begin and end markers.
+    current_frame_id: Option<u32>,
 }
 
 impl FrameMarkerProcessor {
-    fn new(user_channel_id: u16, io_channel_id: u16) -> Self {
+    fn new(user_channel_id: u16, io_channel_id: u16, acknowledge: bool) -> Self {
         Self {
             user_channel_id,
             io_channel_id,
+            acknowledge,
+            current_frame_id: None,
         }
     }
 
-    fn process(&mut self, marker: &FrameMarkerPdu, output: &mut WriteBuf) -> SessionResult<()> {
+    /// Returns the ID of the frame ended by the marker, if any.
+    fn process(&mut self, marker: &FrameMarkerPdu, output: &mut WriteBuf) -> SessionResult<Option<u32>> {
         match marker.frame_action {
-            FrameAction::Begin => Ok(()),
+            FrameAction::Begin => {
+                if let Some(frame_id) = self.current_frame_id {
+                    warn!(frame_id, "Frame began before the previous one ended");
+                }
+
+                self.current_frame_id = Some(marker.frame_id.unwrap_or(0));
+
+                Ok(None)
+            }
             FrameAction::End => {
-                ironrdp_connector::legacy::encode_share_data(
-                    self.user_channel_id,
-                    self.io_channel_id,
-                    0,
-                    ShareDataPdu::FrameAcknowledge(FrameAcknowledgePdu {
-                        frame_id: marker.frame_id.unwrap_or(0),
-                    }),
-                    output,
-                )
-                .map_err(crate::legacy::map_error)?;
-
-                Ok(())
+                let begin_frame_id = self.current_frame_id.take();
+                // Some servers are omitting the frame ID of the end marker.
+                let frame_id = marker.frame_id.or(begin_frame_id).unwrap_or(0);
+
+                if self.acknowledge {
+                    ironrdp_connector::legacy::encode_share_data(
+                        self.user_channel_id,
+                        self.io_channel_id,
+                        0,
+                        ShareDataPdu::FrameAcknowledge(FrameAcknowledgePdu { frame_id }),
+                        output,
+                    )
+                    .map_err(crate::legacy::map_error)?;
+                }
+
+                Ok(Some(frame_id))
             }
         }
     }
@@ -0,0 +1,115 @@
+//! Detection of a broken connection from the missed [`HeartbeatPdu`]s.
+
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+
+/// Thresholds overriding the ones sent by the server in the [`HeartbeatPdu`]s
+///
+/// A count of zero disables the corresponding event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    /// Number of missed heartbeats after which [`HealthEvent::Warning`] is reported.
+    pub warning_count: Option<u8>,
+    /// Number of missed heartbeats after which [`HealthEvent::ReconnectRequired`] is reported.
+    pub reconnect_count: Option<u8>,
+}
+
+/// Change of the health of the connection, as seen from the heartbeats of the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthEvent {
+    /// The warning threshold of missed heartbeats was reached, the user should be notified.
+    Warning { missed: u32 },
+    /// The reconnect threshold of missed heartbeats was reached, the connection should be considered broken.
+    ReconnectRequired { missed: u32 },
+    /// A heartbeat was received again after a warning or a reconnect request.
+    Recovered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Health {
+    Healthy,
+    Warned,
+    ReconnectRequired,
+}
+
+/// Tracks the receipt times of the [`HeartbeatPdu`]s
+///
+/// The monitor is idle until the first heartbeat is received, and whenever the server sends a period of zero. Each
+/// event is reported once per outage, the missed heartbeats being counted in whole periods since the last one.
+#[derive(Debug, Clone)]
+pub struct HeartbeatMonitor {
+    policy: HeartbeatPolicy,
+    /// Last received heartbeat and its receipt time.
+    last: Option<(HeartbeatPdu, Instant)>,
+    health: Health,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(policy: HeartbeatPolicy) -> Self {
+        Self {
+            policy,
+            last: None,
+            health: Health::Healthy,
+        }
+    }
+
+    pub fn policy(&self) -> HeartbeatPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: HeartbeatPolicy) {
+        self.policy = policy;
+    }
+
+    /// Records a heartbeat received at `now`, returning [`HealthEvent::Recovered`] if an event was reported since
+    /// the previous one.
+    pub fn on_heartbeat(&mut self, heartbeat: HeartbeatPdu, now: Instant) -> Option<HealthEvent> {
+        self.last = Some((heartbeat, now));
+
+        if core::mem::replace(&mut self.health, Health::Healthy) == Health::Healthy {
+            None
+        } else {
+            Some(HealthEvent::Recovered)
+        }
+    }
+
+    /// Returns the number of heartbeats missed at `now`.
+    pub fn missed(&self, now: Instant) -> u32 {
+        let Some((heartbeat, received_at)) = self.last else {
+            return 0;
+        };
+
+        let period = heartbeat.period_duration();
+
+        if period.is_zero() {
+            return 0;
+        }
+
+        let elapsed = now.saturating_duration_since(received_at);
+        u32::try_from(elapsed.as_nanos() / period.as_nanos()).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the event to report at `now`, if a threshold was reached since the last call.
+    ///
+    /// When both thresholds are reached at once, only [`HealthEvent::ReconnectRequired`] is reported.
+    pub fn poll(&mut self, now: Instant) -> Option<HealthEvent> {
+        let (heartbeat, _) = self.last?;
+        let missed = self.missed(now);
+
+        let reached = |count: u8| count != 0 && missed >= u32::from(count);
+
+        let warning_count = self.policy.warning_count.unwrap_or(heartbeat.warning_count);
+        let reconnect_count = self.policy.reconnect_count.unwrap_or(heartbeat.reconnect_count);
+
+        if self.health < Health::ReconnectRequired && reached(reconnect_count) {
+            self.health = Health::ReconnectRequired;
+            Some(HealthEvent::ReconnectRequired { missed })
+        } else if self.health < Health::Warned && reached(warning_count) {
+            self.health = Health::Warned;
+            Some(HealthEvent::Warning { missed })
+        } else {
+            None
+        }
+    }
+}
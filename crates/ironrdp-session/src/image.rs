@@ -1,7 +1,11 @@
+use core::num::NonZeroUsize;
 use std::rc::Rc;
 
-use ironrdp_graphics::color_conversion::rdp_16bit_to_rgb;
-use ironrdp_graphics::image_processing::{ImageRegion, ImageRegionMut, PixelFormat};
+use ironrdp_core::{BufPool, PooledWriteBuf};
+
+use ironrdp_graphics::bitmap_cache::{rop3, CachedBitmap};
+use ironrdp_graphics::color_conversion::{rdp_15bit_to_rgb, rdp_16bit_to_rgb};
+use ironrdp_graphics::image_processing::{ImageRegion, ImageRegionMut, PixelFormat, Rgba};
 use ironrdp_graphics::pointer::DecodedPointer;
 use ironrdp_graphics::rectangle_processing::Region;
 use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
@@ -12,6 +16,40 @@ const TILE_SIZE: u16 = 64;
 const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
 const SOURCE_STRIDE: u16 = TILE_SIZE * SOURCE_PIXEL_FORMAT.bytes_per_pixel() as u16;
 
+/// Pixel format of a bitmap received in a bitmap update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BitmapPixelFormat {
+    /// 15 bpp, the 5-bit red, green and blue channels being packed in a little-endian `u16`.
+    Rgb15,
+    /// 16 bpp, with 5-bit red, 6-bit green and 5-bit blue channels packed in a little-endian `u16`.
+    Rgb16,
+    /// 24 bpp, as stored in the bitmap updates.
+    Bgr24,
+    /// 24 bpp, as output by the RDP 6.0 bitmap stream decoder.
+    Rgb24,
+    /// 32 bpp, the last byte being ignored.
+    Bgrx32,
+}
+
+impl BitmapPixelFormat {
+    pub(crate) fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb15 | Self::Rgb16 => 2,
+            Self::Bgr24 | Self::Rgb24 => 3,
+            Self::Bgrx32 => 4,
+        }
+    }
+
+    fn to_rgb(self, pixel: &[u8]) -> [u8; 3] {
+        match self {
+            Self::Rgb15 => rdp_15bit_to_rgb(u16::from_le_bytes([pixel[0], pixel[1]])),
+            Self::Rgb16 => rdp_16bit_to_rgb(u16::from_le_bytes([pixel[0], pixel[1]])),
+            Self::Bgr24 | Self::Bgrx32 => [pixel[2], pixel[1], pixel[0]],
+            Self::Rgb24 => [pixel[0], pixel[1], pixel[2]],
+        }
+    }
+}
+
 pub struct DecodedImage {
     pixel_format: PixelFormat,
     data: Vec<u8>,
@@ -513,78 +551,101 @@ impl DecodedImage {
         Ok(update_rectangle)
     }
 
-    // FIXME: this assumes PixelFormat::RgbA32
-    pub(crate) fn apply_rgb16_bitmap(
+    /// Applies a bottom-up bitmap, made of rows of `stride` bytes, to the update rectangle.
+    ///
+    /// Rows and pixels beyond the update rectangle are ignored, the bitmap width being possibly padded.
+    pub(crate) fn apply_bitmap(
         &mut self,
-        rgb16: &[u8],
+        bitmap: &[u8],
+        format: BitmapPixelFormat,
+        stride: usize,
         update_rectangle: &InclusiveRectangle,
     ) -> SessionResult<InclusiveRectangle> {
-        const SRC_COLOR_DEPTH: usize = 2;
         const DST_COLOR_DEPTH: usize = 4;
 
         let image_width = self.width as usize;
         let rectangle_width = usize::from(update_rectangle.width());
+        let rectangle_height = usize::from(update_rectangle.height());
         let top = usize::from(update_rectangle.top);
         let left = usize::from(update_rectangle.left);
+        let src_color_depth = format.bytes_per_pixel();
+
+        if stride < rectangle_width * src_color_depth {
+            return Err(reason_err!(
+                "apply_bitmap",
+                "bitmap stride is too small for the update rectangle"
+            ));
+        }
 
         let pointer_rendering_state = self.pointer_rendering_begin(update_rectangle)?;
 
-        rgb16
-            .chunks_exact(rectangle_width * SRC_COLOR_DEPTH)
-            .rev()
-            .enumerate()
-            .for_each(|(row_idx, row)| {
-                row.chunks_exact(SRC_COLOR_DEPTH)
-                    .enumerate()
-                    .for_each(|(col_idx, src_pixel)| {
-                        let rgb16_value = u16::from_le_bytes(src_pixel.try_into().unwrap());
-                        let dst_idx = ((top + row_idx) * image_width + left + col_idx) * DST_COLOR_DEPTH;
-
-                        let [r, g, b] = rdp_16bit_to_rgb(rgb16_value);
-                        self.data[dst_idx] = r;
-                        self.data[dst_idx + 1] = g;
-                        self.data[dst_idx + 2] = b;
-                        self.data[dst_idx + 3] = 0xff;
-                    })
-            });
+        for (row_idx, row) in bitmap.chunks_exact(stride).rev().take(rectangle_height).enumerate() {
+            for (col_idx, src_pixel) in row.chunks_exact(src_color_depth).take(rectangle_width).enumerate() {
+                let dst_idx = ((top + row_idx) * image_width + left + col_idx) * DST_COLOR_DEPTH;
+                let [r, g, b] = format.to_rgb(src_pixel);
+
+                self.pixel_format
+                    .write_color(
+                        Rgba { r, g, b, a: 0xff },
+                        &mut self.data[dst_idx..dst_idx + DST_COLOR_DEPTH],
+                    )
+                    .map_err(|e| custom_err!("write_color", e))?;
+            }
+        }
 
         let update_rectangle = self.pointer_rendering_end(pointer_rendering_state)?;
 
         Ok(update_rectangle)
     }
 
-    // FIXME: this assumes PixelFormat::RgbA32
-    pub(crate) fn apply_rgb24_bitmap(
+    /// Draws the part of a cached bitmap starting at (`src_x`, `src_y`), combining it with the pattern color and the
+    /// screen through the ternary raster operation `rop`.
+    ///
+    /// The destination rectangle must be inside the image, and the pixels outside the bitmap are left untouched.
+    pub(crate) fn apply_cached_bitmap(
         &mut self,
-        rgb24: &[u8],
+        bitmap: &CachedBitmap,
+        src_x: u16,
+        src_y: u16,
+        rop: u8,
+        pattern: [u8; 3],
         update_rectangle: &InclusiveRectangle,
     ) -> SessionResult<InclusiveRectangle> {
-        const SRC_COLOR_DEPTH: usize = 3;
         const DST_COLOR_DEPTH: usize = 4;
 
-        let image_width = self.width as usize;
-        let rectangle_width = usize::from(update_rectangle.width());
-        let top = usize::from(update_rectangle.top);
-        let left = usize::from(update_rectangle.left);
+        let image_width = usize::from(self.width);
 
         let pointer_rendering_state = self.pointer_rendering_begin(update_rectangle)?;
 
-        rgb24
-            .chunks_exact(rectangle_width * SRC_COLOR_DEPTH)
-            .rev()
-            .enumerate()
-            .for_each(|(row_idx, row)| {
-                row.chunks_exact(SRC_COLOR_DEPTH)
-                    .enumerate()
-                    .for_each(|(col_idx, src_pixel)| {
-                        let dst_idx = ((top + row_idx) * image_width + left + col_idx) * DST_COLOR_DEPTH;
-
-                        // Copy RGB channels as is
-                        self.data[dst_idx..dst_idx + SRC_COLOR_DEPTH].copy_from_slice(src_pixel);
-                        // Set alpha channel to opaque(0xFF)
-                        self.data[dst_idx + 3] = 0xFF;
-                    })
-            });
+        for (row_idx, y) in (update_rectangle.top..=update_rectangle.bottom).enumerate() {
+            for (col_idx, x) in (update_rectangle.left..=update_rectangle.right).enumerate() {
+                let src_pixel = u16::try_from(col_idx)
+                    .ok()
+                    .zip(u16::try_from(row_idx).ok())
+                    .and_then(|(col, row)| bitmap.pixel(src_x.checked_add(col)?, src_y.checked_add(row)?));
+                let Some(src_pixel) = src_pixel else {
+                    continue;
+                };
+
+                let dst_idx = (usize::from(y) * image_width + usize::from(x)) * DST_COLOR_DEPTH;
+                let dst = &mut self.data[dst_idx..dst_idx + DST_COLOR_DEPTH];
+                let dst_color = self
+                    .pixel_format
+                    .read_color(dst)
+                    .map_err(|e| custom_err!("read_color", e))?;
+
+                let [r, g, b] = [
+                    (pattern[0], src_pixel[0], dst_color.r),
+                    (pattern[1], src_pixel[1], dst_color.g),
+                    (pattern[2], src_pixel[2], dst_color.b),
+                ]
+                .map(|(p, s, d)| rop3(rop, p, s, d));
+
+                self.pixel_format
+                    .write_color(Rgba { r, g, b, a: 0xff }, dst)
+                    .map_err(|e| custom_err!("write_color", e))?;
+            }
+        }
 
         let update_rectangle = self.pointer_rendering_end(pointer_rendering_state)?;
 
@@ -644,3 +705,201 @@ impl DecodedImage {
         Ok(update_rectangle)
     }
 }
+
+/// Row pitch of the regions extracted by an [`ImageRegionExtractor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowAlignment {
+    /// The rows are tightly packed.
+    Tight,
+    /// Each row is padded to a multiple of the given number of bytes (e.g.: `GL_UNPACK_ALIGNMENT`, or the
+    /// `COPY_BYTES_PER_ROW_ALIGNMENT` of wgpu).
+    Bytes(NonZeroUsize),
+}
+
+/// Order of the channels of the pixels output by an [`ImageRegionExtractor`], the alpha channel being last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelOrder {
+    /// Matches `GL_RGBA` and `TextureFormat::Rgba8Unorm`.
+    RgbA,
+    /// Matches `GL_BGRA` and `TextureFormat::Bgra8Unorm`.
+    BgrA,
+}
+
+impl PixelOrder {
+    fn pixel_format(self) -> PixelFormat {
+        match self {
+            Self::RgbA => PixelFormat::RgbA32,
+            Self::BgrA => PixelFormat::BgrA32,
+        }
+    }
+}
+
+/// Region extracted into a pooled buffer by [`ImageRegionExtractor::extract_pooled`]
+#[derive(Debug)]
+pub struct ExtractedRegion<'a> {
+    pub buffer: PooledWriteBuf<'a>,
+    /// Number of bytes between the start of two consecutive rows in `buffer`.
+    pub bytes_per_row: usize,
+}
+
+impl ExtractedRegion<'_> {
+    pub fn data(&self) -> &[u8] {
+        self.buffer.filled()
+    }
+}
+
+/// Extracts the updated regions of a [`DecodedImage`], ready to be uploaded to a GPU texture
+///
+/// The pixels of a region are written directly in the buffer given to `glTexSubImage2D` or wgpu's `write_texture`,
+/// converted to the requested pixel order, and each row being padded to the requested alignment.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageRegionExtractor {
+    alignment: RowAlignment,
+    order: PixelOrder,
+}
+
+impl ImageRegionExtractor {
+    pub fn new(alignment: RowAlignment, order: PixelOrder) -> Self {
+        Self { alignment, order }
+    }
+
+    pub fn alignment(&self) -> RowAlignment {
+        self.alignment
+    }
+
+    pub fn order(&self) -> PixelOrder {
+        self.order
+    }
+
+    /// Returns the number of bytes between the start of two consecutive rows of a region `width` pixels wide.
+    pub fn bytes_per_row(&self, width: u16) -> usize {
+        let row_len = usize::from(width) * usize::from(self.order.pixel_format().bytes_per_pixel());
+
+        match self.alignment {
+            RowAlignment::Tight => row_len,
+            RowAlignment::Bytes(alignment) => row_len.next_multiple_of(alignment.get()),
+        }
+    }
+
+    /// Returns the size of the buffer holding `region`, the last row being padded as well.
+    pub fn buffer_len(&self, region: &InclusiveRectangle) -> usize {
+        self.bytes_per_row(region.width()) * usize::from(region.height())
+    }
+
+    /// Extracts `region` of `image` into `dst`, returning the effective bytes per row.
+    ///
+    /// `dst` must hold at least [`ImageRegionExtractor::buffer_len`] bytes. The padding bytes are zeroed.
+    pub fn extract_into(
+        &self,
+        image: &DecodedImage,
+        region: &InclusiveRectangle,
+        dst: &mut [u8],
+    ) -> SessionResult<usize> {
+        if region.left > region.right
+            || region.top > region.bottom
+            || region.right >= image.width()
+            || region.bottom >= image.height()
+        {
+            return Err(reason_err!(
+                "ImageRegionExtractor",
+                "region {region:?} is out of the {}x{} image",
+                image.width(),
+                image.height()
+            ));
+        }
+
+        let bytes_per_row = self.bytes_per_row(region.width());
+        let buffer_len = self.buffer_len(region);
+
+        if dst.len() < buffer_len {
+            return Err(reason_err!(
+                "ImageRegionExtractor",
+                "buffer of {} bytes is too small for the region, expected {buffer_len} bytes",
+                dst.len()
+            ));
+        }
+
+        let src_pixel_format = image.pixel_format();
+        let dst_pixel_format = self.order.pixel_format();
+        let src_pixel_size = usize::from(src_pixel_format.bytes_per_pixel());
+        let dst_pixel_size = usize::from(dst_pixel_format.bytes_per_pixel());
+
+        let src_stride = usize::from(image.width()) * src_pixel_size;
+        let src_begin = usize::from(region.top) * src_stride + usize::from(region.left) * src_pixel_size;
+        let src_row_len = usize::from(region.width()) * src_pixel_size;
+        let dst_row_len = usize::from(region.width()) * dst_pixel_size;
+
+        let src_rows = image.data()[src_begin..].chunks(src_stride);
+        let dst_rows = dst[..buffer_len].chunks_exact_mut(bytes_per_row);
+
+        for (src_row, dst_row) in src_rows.zip(dst_rows) {
+            let (dst_pixels, padding) = dst_row.split_at_mut(dst_row_len);
+            let src_pixels = &src_row[..src_row_len];
+
+            if src_pixel_format == dst_pixel_format {
+                dst_pixels.copy_from_slice(src_pixels);
+            } else {
+                let channels = Channels::of(src_pixel_format);
+
+                for (src, dst) in src_pixels
+                    .chunks_exact(src_pixel_size)
+                    .zip(dst_pixels.chunks_exact_mut(dst_pixel_size))
+                {
+                    let (r, g, b) = (src[channels.r], src[channels.g], src[channels.b]);
+                    let a = channels.a.map_or(0xFF, |a| src[a]);
+
+                    match self.order {
+                        PixelOrder::RgbA => dst.copy_from_slice(&[r, g, b, a]),
+                        PixelOrder::BgrA => dst.copy_from_slice(&[b, g, r, a]),
+                    }
+                }
+            }
+
+            padding.fill(0);
+        }
+
+        Ok(bytes_per_row)
+    }
+
+    /// Extracts `region` of `image` into a buffer taken from `pool`.
+    pub fn extract_pooled<'a>(
+        &self,
+        image: &DecodedImage,
+        region: &InclusiveRectangle,
+        pool: &'a BufPool,
+    ) -> SessionResult<ExtractedRegion<'a>> {
+        let buffer_len = self.buffer_len(region);
+        let mut buffer = pool.get();
+
+        let bytes_per_row = self.extract_into(image, region, buffer.unfilled_to(buffer_len))?;
+        buffer.advance(buffer_len);
+
+        Ok(ExtractedRegion { buffer, bytes_per_row })
+    }
+}
+
+/// Offsets of the channels of a pixel
+struct Channels {
+    r: usize,
+    g: usize,
+    b: usize,
+    /// `None` when the channel is ignored, the pixel being opaque.
+    a: Option<usize>,
+}
+
+impl Channels {
+    fn of(pixel_format: PixelFormat) -> Self {
+        let (r, g, b, a) = match pixel_format {
+            PixelFormat::ARgb32 => (1, 2, 3, Some(0)),
+            PixelFormat::XRgb32 => (1, 2, 3, None),
+            PixelFormat::ABgr32 => (3, 2, 1, Some(0)),
+            PixelFormat::XBgr32 => (3, 2, 1, None),
+            PixelFormat::BgrA32 => (2, 1, 0, Some(3)),
+            PixelFormat::BgrX32 => (2, 1, 0, None),
+            PixelFormat::RgbA32 => (0, 1, 2, Some(3)),
+            PixelFormat::RgbX32 => (0, 1, 2, None),
+        };
+
+        Self { r, g, b, a }
+    }
+}
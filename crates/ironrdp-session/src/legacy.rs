@@ -6,6 +6,7 @@ impl From<ironrdp_connector::ConnectorErrorKind> for crate::SessionErrorKind {
     fn from(value: ironrdp_connector::ConnectorErrorKind) -> Self {
         match value {
             ironrdp_connector::ConnectorErrorKind::Credssp(_) => panic!("unexpected"),
+            ironrdp_connector::ConnectorErrorKind::CredsspFailure(_) => panic!("unexpected"),
             ironrdp_connector::ConnectorErrorKind::AccessDenied => panic!("unexpected"),
             ironrdp_connector::ConnectorErrorKind::General => crate::SessionErrorKind::General,
             ironrdp_connector::ConnectorErrorKind::Custom => crate::SessionErrorKind::Custom,
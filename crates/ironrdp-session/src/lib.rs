@@ -9,10 +9,13 @@ extern crate tracing;
 mod macros;
 
 pub mod fast_path;
+pub mod heartbeat;
 pub mod image;
 pub mod legacy;
 pub mod pointer;
+pub mod presentation;
 pub mod rfx; // FIXME: maybe this module should not be in this crate
+pub mod stats;
 pub mod utils;
 pub mod x224;
 
@@ -20,7 +23,9 @@ mod active_stage;
 
 use core::fmt;
 
-pub use active_stage::{ActiveStage, ActiveStageOutput, GracefulDisconnectReason};
+use ironrdp_core::{ErrorCategory, ErrorKindExt};
+
+pub use active_stage::{ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionInfo};
 
 pub type SessionResult<T> = Result<T, SessionError>;
 
@@ -61,6 +66,29 @@ impl std::error::Error for SessionErrorKind {
     }
 }
 
+impl ErrorKindExt for SessionErrorKind {
+    fn code(&self) -> u32 {
+        match self {
+            SessionErrorKind::Pdu(_) => 0x0005_0001,
+            SessionErrorKind::Encode(_) => 0x0005_0002,
+            SessionErrorKind::Decode(_) => 0x0005_0003,
+            SessionErrorKind::Reason(_) => 0x0005_0004,
+            SessionErrorKind::General => 0x0005_0005,
+            SessionErrorKind::Custom => 0x0005_0006,
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            SessionErrorKind::Pdu(e) => e.category(),
+            SessionErrorKind::Encode(_) => ErrorCategory::Encoding,
+            SessionErrorKind::Decode(_) => ErrorCategory::Decoding,
+            SessionErrorKind::Reason(_) => ErrorCategory::Protocol,
+            SessionErrorKind::General | SessionErrorKind::Custom => ErrorCategory::Other,
+        }
+    }
+}
+
 pub type SessionError = ironrdp_error::Error<SessionErrorKind>;
 
 pub trait SessionErrorExt {
@@ -3,22 +3,129 @@ use std::rc::Rc;
 
 use ironrdp_graphics::pointer::DecodedPointer;
 
-#[derive(Debug, Clone, Default)]
+/// Pointer cache statistics, for diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PointerCacheStats {
+    /// Number of cached pointer updates referring to a cached pointer.
+    pub hits: u64,
+    /// Number of cached pointer updates referring to an empty slot.
+    pub misses: u64,
+    /// Number of pointers evicted to make room for a new one.
+    pub evictions: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    pointer: Rc<DecodedPointer>,
+    last_used: u64,
+}
+
+/// Cache of the decoded pointers, shared between the Color, New and Large Pointer Updates
+///
+/// The cache holds at most as many pointers as the number of slots advertised in the Pointer Capability Set. Well
+/// behaved servers never use more slots than advertised, but when a pointer is inserted in a full cache anyway, the
+/// least recently used pointer is evicted.
+#[derive(Debug, Clone)]
 pub struct PointerCache {
-    // TODO(@pacancoder) maybe use Vec<Optional<...>> instead?
-    cache: HashMap<usize, Rc<DecodedPointer>>,
+    capacity: usize,
+    cache: HashMap<usize, CacheEntry>,
+    /// Monotonic counter used to order the entries by last use.
+    clock: u64,
+    stats: PointerCacheStats,
 }
 
 impl PointerCache {
+    /// Number of slots used when the negotiated cache size is unknown.
+    pub const DEFAULT_CAPACITY: usize = 32;
+
+    /// Creates a cache holding at most `capacity` pointers.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            cache: HashMap::with_capacity(capacity),
+            clock: 0,
+            stats: PointerCacheStats::default(),
+        }
+    }
+
+    /// Inserts the pointer in the slot `id`, and returns the pointer previously stored in this slot, if any.
     pub fn insert(&mut self, id: usize, pointer: Rc<DecodedPointer>) -> Option<Rc<DecodedPointer>> {
-        self.cache.insert(id, pointer)
+        if self.capacity == 0 {
+            debug!(id, "Pointer cache is disabled, the pointer is not cached");
+            return None;
+        }
+
+        if !self.cache.contains_key(&id) && self.cache.len() >= self.capacity {
+            let evicted = self
+                .cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(evicted, _)| *evicted);
+
+            if let Some(evicted) = evicted {
+                warn!(
+                    id,
+                    evicted,
+                    capacity = self.capacity,
+                    "Pointer cache is full, evicting the least recently used pointer"
+                );
+                self.cache.remove(&evicted);
+                self.stats.evictions += 1;
+            }
+        }
+
+        let last_used = self.tick();
+
+        self.cache
+            .insert(id, CacheEntry { pointer, last_used })
+            .map(|entry| entry.pointer)
     }
 
-    pub fn get(&self, id: usize) -> Option<Rc<DecodedPointer>> {
-        self.cache.get(&id).cloned()
+    /// Returns the pointer stored in the slot `id`, and marks it as the most recently used.
+    pub fn get(&mut self, id: usize) -> Option<Rc<DecodedPointer>> {
+        let last_used = self.tick();
+
+        match self.cache.get_mut(&id) {
+            Some(entry) => {
+                entry.last_used = last_used;
+                self.stats.hits += 1;
+                Some(Rc::clone(&entry.pointer))
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
     }
 
     pub fn is_cached(&self, id: usize) -> bool {
         self.cache.contains_key(&id)
     }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn stats(&self) -> PointerCacheStats {
+        self.stats
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl Default for PointerCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
 }
@@ -0,0 +1,64 @@
+use ironrdp_pdu::geometry::{InclusiveRectangle, Rectangle as _};
+
+/// Coalesces the graphics updates while the rendering surface is not displayed
+///
+/// While the surface is hidden (e.g.: in a background browser tab), the [`DecodedImage`](crate::image::DecodedImage)
+/// must still be kept up to date, but drawing the updated regions is wasted work. The updated regions are instead
+/// merged into a single dirty region, which is drawn at once when the surface is displayed again.
+#[derive(Debug, Clone)]
+pub struct UpdateCoalescer {
+    visible: bool,
+    dirty: Option<InclusiveRectangle>,
+}
+
+impl UpdateCoalescer {
+    /// Creates a coalescer for a visible surface.
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            dirty: None,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Returns the region accumulated while the surface was hidden, without drawing it.
+    pub fn dirty_region(&self) -> Option<&InclusiveRectangle> {
+        self.dirty.as_ref()
+    }
+
+    /// Changes the visibility of the surface.
+    ///
+    /// Returns the region to draw when the surface becomes visible again, if it was updated in the meantime.
+    pub fn set_visible(&mut self, visible: bool) -> Option<InclusiveRectangle> {
+        self.visible = visible;
+
+        if visible {
+            self.dirty.take()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the region to draw for the updated `region`, or `None` if the surface is hidden.
+    pub fn update(&mut self, region: InclusiveRectangle) -> Option<InclusiveRectangle> {
+        if self.visible {
+            return Some(region);
+        }
+
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+
+        None
+    }
+}
+
+impl Default for UpdateCoalescer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
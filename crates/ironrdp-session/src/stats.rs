@@ -0,0 +1,83 @@
+//! Traffic statistics of the virtual channels, and latency of the audio playback.
+
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+pub use ironrdp_svc::ChannelStats;
+
+/// Traffic of the virtual channels of a session, by channel name
+///
+/// See [`ChannelStats`] for what is counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SessionStats {
+    pub static_channels: BTreeMap<String, ChannelStats>,
+    /// The dynamic channels are listed even if they were never opened by the server.
+    pub dynamic_channels: BTreeMap<String, ChannelStats>,
+    /// Latency of the audio playback measured by the RDPSND backend, if known.
+    pub audio_latency: Option<Duration>,
+}
+
+impl SessionStats {
+    /// Returns the traffic since the `earlier` statistics of the same session, omitting the idle channels.
+    ///
+    /// The audio latency is the current one.
+    #[must_use]
+    pub fn since(&self, earlier: &SessionStats) -> SessionStats {
+        let since = |current: &BTreeMap<String, ChannelStats>, earlier: &BTreeMap<String, ChannelStats>| {
+            current
+                .iter()
+                .map(|(name, stats)| (name, stats.since(&earlier.get(name).copied().unwrap_or_default())))
+                .filter(|(_, stats)| *stats != ChannelStats::default())
+                .map(|(name, stats)| (name.clone(), stats))
+                .collect()
+        };
+
+        SessionStats {
+            static_channels: since(&self.static_channels, &earlier.static_channels),
+            dynamic_channels: since(&self.dynamic_channels, &earlier.dynamic_channels),
+            audio_latency: self.audio_latency,
+        }
+    }
+}
+
+/// Logs the traffic of the virtual channels at a regular interval
+#[derive(Debug, Clone)]
+pub(crate) struct StatsLog {
+    interval: Duration,
+    /// Time and statistics of the last summary.
+    last: Option<(Instant, SessionStats)>,
+}
+
+impl StatsLog {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval, last: None }
+    }
+
+    /// Logs the traffic since the last summary if the interval elapsed, the first call only starting the interval.
+    pub(crate) fn poll(&mut self, now: Instant, stats: impl FnOnce() -> SessionStats) {
+        if let Some((last, earlier)) = &self.last {
+            let elapsed = now.saturating_duration_since(*last);
+
+            if elapsed < self.interval {
+                return;
+            }
+
+            let stats = stats();
+            let delta = stats.since(earlier);
+
+            debug!(
+                ?elapsed,
+                static_channels = ?delta.static_channels,
+                dynamic_channels = ?delta.dynamic_channels,
+                audio_latency = ?delta.audio_latency,
+                "Virtual channel traffic"
+            );
+
+            self.last = Some((now, stats));
+        } else {
+            self.last = Some((now, stats()));
+        }
+    }
+}
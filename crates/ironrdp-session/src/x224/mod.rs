@@ -1,13 +1,26 @@
-use ironrdp_connector::connection_activation::ConnectionActivationSequence;
+use std::borrow::Cow;
+
+use ironrdp_connector::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
 use ironrdp_connector::legacy::SendDataIndicationCtx;
+use ironrdp_connector::redirection::ServerRedirection;
+use ironrdp_connector::{DesktopSize, Sequence as _, State as _};
 use ironrdp_core::WriteBuf;
+use ironrdp_displaycontrol::client::DisplayControlClient;
 use ironrdp_dvc::{DrdynvcClient, DvcProcessor, DynamicVirtualChannel};
+use ironrdp_pdu::gcc;
 use ironrdp_pdu::mcs::{DisconnectProviderUltimatum, DisconnectReason, McsMessage};
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, InputFlags};
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_pdu::rdp::multitransport::{MultitransportRequestPdu, MultitransportResponsePdu};
+use ironrdp_pdu::rdp::play_sound::PlaySoundPdu;
 use ironrdp_pdu::rdp::server_error_info::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
+use ironrdp_pdu::rdp::session_info::InfoData;
 use ironrdp_pdu::x224::X224;
-use ironrdp_svc::{client_encode_svc_messages, StaticChannelSet, SvcMessage, SvcProcessor, SvcProcessorMessages};
+use ironrdp_rdpsnd::client::Rdpsnd;
+use ironrdp_svc::{StaticChannelSet, SvcProcessor, SvcProcessorMessages};
 
+use crate::stats::SessionStats;
 use crate::{SessionError, SessionErrorExt as _, SessionResult};
 
 /// X224 Processor output
@@ -17,11 +30,41 @@ pub enum ProcessorOutput {
     ResponseFrame(Vec<u8>),
     /// A graceful disconnect notification. Client should close the connection upon receiving this.
     Disconnect(DisconnectDescription),
-    /// Received a [`ironrdp_pdu::rdp::headers::ServerDeactivateAll`] PDU. Client should execute the
-    /// [Deactivation-Reactivation Sequence].
+    /// The [Deactivation-Reactivation Sequence] initiated by the server with a
+    /// [`ironrdp_pdu::rdp::headers::ServerDeactivateAll`] PDU completed.
     ///
     /// [Deactivation-Reactivation Sequence]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
-    DeactivateAll(Box<ConnectionActivationSequence>),
+    DeactivationReactivation {
+        io_channel_id: u16,
+        user_channel_id: u16,
+        desktop_size: DesktopSize,
+        no_server_pointer: bool,
+        pointer_software_rendering: bool,
+        pointer_cache_size: u16,
+        bitmap_cache: Option<BitmapCacheRev2>,
+        server_input_flags: InputFlags,
+        frame_acknowledge: bool,
+    },
+    /// Received a [`ironrdp_pdu::rdp::session_info::SaveSessionInfoPdu`] with logon or auto-reconnect information.
+    SessionInfo(InfoData),
+    /// Received a valid [`ironrdp_pdu::rdp::finalization_messages::MonitorLayoutPdu`].
+    MonitorLayout {
+        monitors: Vec<gcc::Monitor>,
+        /// Size of the bounding box of the monitors.
+        desktop_size: DesktopSize,
+    },
+    /// Received a [`HeartbeatPdu`], whose receipt time is tracked by the active stage.
+    Heartbeat(HeartbeatPdu),
+    /// Received a [`MultitransportRequestPdu`].
+    ///
+    /// Unless [`ironrdp_connector::Config::decline_multitransport`] is disabled, it is preceded by the frame declining
+    /// the side channel.
+    MultitransportRequest(MultitransportRequestPdu),
+    /// Received a [`PlaySoundPdu`], the client should beep.
+    PlaySound(PlaySoundPdu),
+    /// The server redirected the client to another server. Client should close the connection and connect to the
+    /// target, see [`ironrdp_connector::redirection`].
+    ServerRedirection(ServerRedirection),
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +84,10 @@ pub struct Processor {
     user_channel_id: u16,
     io_channel_id: u16,
     connection_activation: ConnectionActivationSequence,
+    /// The Deactivation-Reactivation Sequence in progress, if any.
+    ///
+    /// The static channels are kept open during the sequence, as their IDs remain valid.
+    reactivation: Option<ConnectionActivationSequence>,
 }
 
 impl Processor {
@@ -55,9 +102,15 @@ impl Processor {
             user_channel_id,
             io_channel_id,
             connection_activation,
+            reactivation: None,
         }
     }
 
+    /// Returns whether a Deactivation-Reactivation Sequence is in progress.
+    pub fn is_reactivating(&self) -> bool {
+        self.reactivation.is_some()
+    }
+
     pub fn get_svc_processor<T: SvcProcessor + 'static>(&self) -> Option<&T> {
         self.static_channels
             .get_by_type::<T>()
@@ -76,37 +129,189 @@ impl Processor {
         &self,
         messages: SvcProcessorMessages<C>,
     ) -> SessionResult<Vec<u8>> {
-        let channel_id = self
+        let channel = self
             .static_channels
-            .get_channel_id_by_type::<C>()
+            .get_by_type::<C>()
             .ok_or_else(|| reason_err!("SVC", "channel not found"))?;
+        let channel_id = self.static_channels.get_channel_id_by_type::<C>().ok_or_else(|| {
+            if channel.is_closed() {
+                reason_err!(
+                    "SVC",
+                    "channel {:?} was left and can't be used anymore",
+                    channel.channel_name()
+                )
+            } else {
+                reason_err!("SVC", "channel {:?} is not joined", channel.channel_name())
+            }
+        })?;
 
-        process_svc_messages(messages.into(), channel_id, self.user_channel_id)
+        channel
+            .client_encode(messages.into(), channel_id, self.user_channel_id)
+            .map_err(SessionError::encode)
     }
 
     pub fn get_dvc<T: DvcProcessor + 'static>(&self) -> Option<&DynamicVirtualChannel> {
         self.get_svc_processor::<DrdynvcClient>()?.get_dvc_by_type_id::<T>()
     }
 
+    pub fn get_dvc_mut<T: DvcProcessor + 'static>(&mut self) -> Option<&mut DynamicVirtualChannel> {
+        self.get_svc_processor_mut::<DrdynvcClient>()?
+            .get_dvc_by_type_id_mut::<T>()
+    }
+
+    /// Returns the traffic of the static channels, and of the dynamic channels if the DRDYNVC channel is joined, along
+    /// with the latency of the audio playback.
+    pub fn stats(&self) -> SessionStats {
+        let static_channels = self
+            .static_channels
+            .values()
+            .map(|svc| {
+                let name = svc.channel_name();
+                let name = name
+                    .as_str()
+                    .map_or_else(|| format!("{:?}", name.as_bytes()), ToOwned::to_owned);
+                (name, svc.stats())
+            })
+            .collect();
+
+        let dynamic_channels = self
+            .get_svc_processor::<DrdynvcClient>()
+            .map(|drdynvc| {
+                drdynvc
+                    .channel_stats()
+                    .map(|(name, stats)| (name.to_owned(), stats))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        SessionStats {
+            static_channels,
+            dynamic_channels,
+            audio_latency: self.get_svc_processor::<Rdpsnd>().and_then(Rdpsnd::playback_latency),
+        }
+    }
+
     /// Processes a received PDU. Returns a vector of [`ProcessorOutput`] that must be processed
     /// in the returned order.
     pub fn process(&mut self, frame: &[u8]) -> SessionResult<Vec<ProcessorOutput>> {
-        let data_ctx: SendDataIndicationCtx<'_> =
-            ironrdp_connector::legacy::decode_send_data_indication(frame).map_err(crate::legacy::map_error)?;
+        let mcs_msg = ironrdp_core::decode::<X224<McsMessage<'_>>>(frame).map_err(SessionError::decode)?;
+
+        let data_ctx = match mcs_msg.0 {
+            McsMessage::SendDataIndication(msg) => {
+                let Cow::Borrowed(user_data) = msg.user_data else {
+                    unreachable!()
+                };
+
+                SendDataIndicationCtx {
+                    initiator_id: msg.initiator_id,
+                    channel_id: msg.channel_id,
+                    user_data,
+                }
+            }
+            McsMessage::DisconnectProviderUltimatum(msg) => {
+                debug!(reason = %msg.reason, "Received Disconnect Provider Ultimatum");
+
+                let mut outputs = self.close_static_channels()?;
+                outputs.push(ProcessorOutput::Disconnect(DisconnectDescription::McsDisconnect(
+                    msg.reason,
+                )));
+                return Ok(outputs);
+            }
+            McsMessage::ChannelExpelIndication(msg) => {
+                if msg.user_ids.contains(&self.user_channel_id) {
+                    self.leave_static_channel(msg.channel_id);
+                }
+                return Ok(Vec::new());
+            }
+            other => {
+                return Err(reason_err!(
+                    "X224",
+                    "unexpected MCS message: {}",
+                    ironrdp_core::name(&X224(other))
+                ))
+            }
+        };
         let channel_id = data_ctx.channel_id;
 
         if channel_id == self.io_channel_id {
-            self.process_io_channel(data_ctx)
+            // The heartbeats keep coming during a Deactivation-Reactivation Sequence.
+            if HeartbeatPdu::is_heartbeat(data_ctx.user_data) {
+                let heartbeat =
+                    ironrdp_core::decode::<HeartbeatPdu>(data_ctx.user_data).map_err(SessionError::decode)?;
+                trace!(?heartbeat, "Received Heartbeat PDU");
+                return Ok(vec![ProcessorOutput::Heartbeat(heartbeat)]);
+            }
+
+            if MultitransportRequestPdu::is_multitransport_request(data_ctx.user_data) {
+                return self.process_multitransport_request(data_ctx.user_data);
+            }
+
+            if let Some(reactivation) = self.reactivation.take() {
+                self.process_reactivation(reactivation, frame)
+            } else {
+                self.process_io_channel(data_ctx)
+            }
         } else if let Some(svc) = self.static_channels.get_by_channel_id_mut(channel_id) {
             let response_pdus = svc.process(data_ctx.user_data).map_err(SessionError::pdu)?;
-            process_svc_messages(response_pdus, channel_id, data_ctx.initiator_id)
+            svc.client_encode(response_pdus, channel_id, data_ctx.initiator_id)
                 .map(|data| vec![ProcessorOutput::ResponseFrame(data)])
+                .map_err(SessionError::encode)
         } else {
             Err(reason_err!("X224", "unexpected channel received: ID {channel_id}"))
         }
     }
 
-    fn process_io_channel(&self, data_ctx: SendDataIndicationCtx<'_>) -> SessionResult<Vec<ProcessorOutput>> {
+    /// Closes all the static channels, returning the frames with their final PDUs.
+    ///
+    /// Called when the session terminates gracefully. Failures are logged, and do not prevent
+    /// the other channels from being closed.
+    pub fn close_static_channels(&mut self) -> SessionResult<Vec<ProcessorOutput>> {
+        let mut outputs = Vec::new();
+
+        for (channel_id, result) in self.static_channels.close_all() {
+            let messages = match result {
+                Ok(messages) => messages,
+                Err(error) => {
+                    warn!(?channel_id, %error, "Failed to close static channel");
+                    continue;
+                }
+            };
+
+            let Some(channel_id) = channel_id.filter(|_| !messages.is_empty()) else {
+                continue;
+            };
+
+            if let Some(svc) = self.static_channels.get_by_channel_id(channel_id) {
+                let data = svc
+                    .client_encode(messages, channel_id, self.user_channel_id)
+                    .map_err(SessionError::encode)?;
+                outputs.push(ProcessorOutput::ResponseFrame(data));
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Closes the static channel left by the server, which no longer accepts data on this channel.
+    fn leave_static_channel(&mut self, channel_id: u16) {
+        match self.static_channels.leave_channel_id(channel_id) {
+            Some(Ok(messages)) => {
+                debug!(
+                    channel_id,
+                    discarded = messages.len(),
+                    "Static channel left by the server"
+                );
+            }
+            Some(Err(error)) => {
+                warn!(channel_id, %error, "Failed to close static channel left by the server");
+            }
+            None => {
+                debug!(channel_id, "Ignored the leave of an unknown channel");
+            }
+        }
+    }
+
+    fn process_io_channel(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> SessionResult<Vec<ProcessorOutput>> {
         debug_assert_eq!(data_ctx.channel_id, self.io_channel_id);
 
         let io_channel = ironrdp_connector::legacy::decode_io_channel(data_ctx).map_err(crate::legacy::map_error)?;
@@ -116,12 +321,31 @@ impl Processor {
                 match ctx.pdu {
                     ShareDataPdu::SaveSessionInfo(session_info) => {
                         debug!("Got Session Save Info PDU: {session_info:?}");
-                        Ok(Vec::new())
+                        Ok(vec![ProcessorOutput::SessionInfo(session_info.info_data)])
                     }
-                    // FIXME: workaround fix to not terminate the session on "unhandled PDU: Set Keyboard Indicators PDU"
-                    ShareDataPdu::SetKeyboardIndicators(data) => {
-                        debug!("Got Keyboard Indicators PDU: {data:?}");
-                        Ok(Vec::new())
+                    ShareDataPdu::MonitorLayout(layout) => {
+                        debug!(?layout, "Got Monitor Layout PDU");
+
+                        let max_monitors = self
+                            .get_dvc::<DisplayControlClient>()
+                            .and_then(|dvc| dvc.channel_processor_downcast_ref::<DisplayControlClient>())
+                            .and_then(|display_control| display_control.capabilities())
+                            .map(|caps| caps.max_num_monitors());
+
+                        match monitor_layout_desktop_size(&layout.monitors, max_monitors) {
+                            Ok(desktop_size) => Ok(vec![ProcessorOutput::MonitorLayout {
+                                monitors: layout.monitors,
+                                desktop_size,
+                            }]),
+                            Err(reason) => {
+                                warn!(reason, ?layout, "Ignored invalid Monitor Layout PDU");
+                                Ok(Vec::new())
+                            }
+                        }
+                    }
+                    ShareDataPdu::PlaySound(play_sound) => {
+                        debug!(?play_sound, "Got Play Sound PDU");
+                        Ok(vec![ProcessorOutput::PlaySound(play_sound)])
                     }
                     ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
                         ProtocolIndependentCode::None,
@@ -135,7 +359,9 @@ impl Processor {
                         //
                         // [MS-RDPBCGR]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/149070b0-ecec-4c20-af03-934bbc48adb8
                         let desc = DisconnectDescription::ErrorInfo(e);
-                        Ok(vec![ProcessorOutput::Disconnect(desc)])
+                        let mut outputs = self.close_static_channels()?;
+                        outputs.push(ProcessorOutput::Disconnect(desc));
+                        Ok(outputs)
                     }
                     ShareDataPdu::ShutdownDenied => {
                         debug!("ShutdownDenied received, session will be closed");
@@ -151,24 +377,122 @@ impl Processor {
 
                         let encoded_pdu = ironrdp_core::encode_vec(&X224(ultimatum)).map_err(SessionError::encode);
 
-                        Ok(vec![
-                            ProcessorOutput::ResponseFrame(encoded_pdu?),
-                            ProcessorOutput::Disconnect(DisconnectDescription::McsDisconnect(
-                                DisconnectReason::UserRequested,
-                            )),
-                        ])
+                        // Give the static channels a chance to send their final PDUs before the ultimatum.
+                        let mut outputs = self.close_static_channels()?;
+                        outputs.push(ProcessorOutput::ResponseFrame(encoded_pdu?));
+                        outputs.push(ProcessorOutput::Disconnect(DisconnectDescription::McsDisconnect(
+                            DisconnectReason::UserRequested,
+                        )));
+
+                        Ok(outputs)
+                    }
+                    // The PDUs which are not handled (e.g.: Set Keyboard Indicators) do not affect the session.
+                    _ => {
+                        debug!(pdu = ctx.pdu.as_short_name(), "Skipped unhandled Share Data PDU");
+                        Ok(Vec::new())
                     }
-                    _ => Err(reason_err!(
-                        "IO channel",
-                        "unhandled PDU: {:?}",
-                        ctx.pdu.as_short_name()
-                    )),
                 }
             }
-            ironrdp_connector::legacy::IoChannelPdu::DeactivateAll(_) => Ok(vec![ProcessorOutput::DeactivateAll(
-                Box::new(self.connection_activation.reset_clone()),
-            )]),
+            ironrdp_connector::legacy::IoChannelPdu::DeactivateAll(_) => {
+                debug!("Received Server Deactivate All PDU, executing Deactivation-Reactivation Sequence");
+                self.reactivation = Some(self.connection_activation.reset_clone());
+                Ok(Vec::new())
+            }
+            ironrdp_connector::legacy::IoChannelPdu::ServerRedirect(redirection) => {
+                let redirection = ServerRedirection::from(redirection);
+                info!(?redirection.target, "Server redirection");
+                Ok(vec![ProcessorOutput::ServerRedirection(redirection)])
+            }
+        }
+    }
+
+    fn process_multitransport_request(&self, user_data: &[u8]) -> SessionResult<Vec<ProcessorOutput>> {
+        let request = ironrdp_core::decode::<MultitransportRequestPdu>(user_data).map_err(SessionError::decode)?;
+        debug!(?request, "Received Initiate Multitransport Request PDU");
+
+        let mut outputs = Vec::new();
+
+        if self.connection_activation.config().decline_multitransport {
+            let response = MultitransportResponsePdu::abort(request.request_id);
+
+            let mut buf = WriteBuf::new();
+            ironrdp_connector::legacy::encode_send_data_request(
+                self.user_channel_id,
+                self.io_channel_id,
+                &response,
+                &mut buf,
+            )
+            .map_err(crate::legacy::map_error)?;
+            outputs.push(ProcessorOutput::ResponseFrame(buf.filled().to_vec()));
         }
+
+        outputs.push(ProcessorOutput::MultitransportRequest(request));
+
+        Ok(outputs)
+    }
+
+    fn process_reactivation(
+        &mut self,
+        mut reactivation: ConnectionActivationSequence,
+        frame: &[u8],
+    ) -> SessionResult<Vec<ProcessorOutput>> {
+        let mut buf = WriteBuf::new();
+        reactivation.step(frame, &mut buf).map_err(crate::legacy::map_error)?;
+
+        // The client side of the connection finalization is sent without waiting for any server PDU.
+        while !reactivation.state.is_terminal() && reactivation.next_pdu_hint().is_none() {
+            reactivation.step_no_input(&mut buf).map_err(crate::legacy::map_error)?;
+        }
+
+        let mut outputs = Vec::new();
+
+        if buf.filled_len() > 0 {
+            outputs.push(ProcessorOutput::ResponseFrame(buf.filled().to_vec()));
+        }
+
+        if let ConnectionActivationState::Finalized {
+            io_channel_id,
+            user_channel_id,
+            desktop_size,
+            color_depth,
+            no_server_pointer,
+            pointer_software_rendering,
+            pointer_cache_size,
+            ref bitmap_cache,
+            server_input_flags,
+            frame_acknowledge,
+        } = reactivation.state
+        {
+            let bitmap_cache = bitmap_cache.clone();
+
+            debug!(
+                ?desktop_size,
+                color_depth, "Deactivation-Reactivation Sequence completed"
+            );
+
+            self.io_channel_id = io_channel_id;
+            self.user_channel_id = user_channel_id;
+            self.connection_activation = reactivation;
+
+            outputs.push(ProcessorOutput::DeactivationReactivation {
+                io_channel_id,
+                user_channel_id,
+                desktop_size,
+                no_server_pointer,
+                pointer_software_rendering,
+                pointer_cache_size,
+                bitmap_cache,
+                server_input_flags,
+                frame_acknowledge,
+            });
+        } else if let ConnectionActivationState::Redirected { redirection } = reactivation.state {
+            // The server may redirect the client instead of reactivating the connection.
+            outputs.push(ProcessorOutput::ServerRedirection(redirection));
+        } else {
+            self.reactivation = Some(reactivation);
+        }
+
+        Ok(outputs)
     }
 
     /// Send a pdu on the static global channel. Typically used to send input events
@@ -180,12 +504,62 @@ impl Processor {
     }
 }
 
-/// Processes a vector of [`SvcMessage`] in preparation for sending them to the server on the `channel_id` channel.
-///
-/// This includes chunkifying the messages, adding MCS, x224, and tpkt headers, and encoding them into a buffer.
-/// The messages returned here are ready to be sent to the server.
+/// Validates the monitor layout sent by the server, returning the size of the bounding box of the monitors.
 ///
-/// The caller is responsible for ensuring that the `channel_id` corresponds to the correct channel.
-fn process_svc_messages(messages: Vec<SvcMessage>, channel_id: u16, initiator_id: u16) -> SessionResult<Vec<u8>> {
-    client_encode_svc_messages(messages, channel_id, initiator_id).map_err(SessionError::encode)
+/// `max_monitors` is the maximum number of monitors supported by the server, if known.
+fn monitor_layout_desktop_size(
+    monitors: &[gcc::Monitor],
+    max_monitors: Option<u32>,
+) -> Result<DesktopSize, &'static str> {
+    if monitors.is_empty() {
+        return Err("no monitor");
+    }
+
+    if max_monitors.is_some_and(|max| u32::try_from(monitors.len()).map_or(true, |count| count > max)) {
+        return Err("more monitors than supported by the server");
+    }
+
+    // The right and bottom coordinates are inclusive.
+    if monitors
+        .iter()
+        .any(|monitor| monitor.left > monitor.right || monitor.top > monitor.bottom)
+    {
+        return Err("invalid monitor rectangle");
+    }
+
+    let overlaps = |a: &gcc::Monitor, b: &gcc::Monitor| {
+        a.left <= b.right && b.left <= a.right && a.top <= b.bottom && b.top <= a.bottom
+    };
+
+    for (i, monitor) in monitors.iter().enumerate() {
+        if monitors[i + 1..].iter().any(|other| overlaps(monitor, other)) {
+            return Err("overlapping monitors");
+        }
+    }
+
+    let left = monitors
+        .iter()
+        .map(|monitor| i64::from(monitor.left))
+        .min()
+        .unwrap_or(0);
+    let top = monitors.iter().map(|monitor| i64::from(monitor.top)).min().unwrap_or(0);
+    let right = monitors
+        .iter()
+        .map(|monitor| i64::from(monitor.right))
+        .max()
+        .unwrap_or(0);
+    let bottom = monitors
+        .iter()
+        .map(|monitor| i64::from(monitor.bottom))
+        .max()
+        .unwrap_or(0);
+
+    // Cannot overflow: the coordinates are 32-bit integers.
+    #[allow(clippy::arithmetic_side_effects)]
+    let (width, height) = (right - left + 1, bottom - top + 1);
+
+    Ok(DesktopSize {
+        width: u16::try_from(width).map_err(|_| "desktop too large")?,
+        height: u16::try_from(height).map_err(|_| "desktop too large")?,
+    })
 }
@@ -3,22 +3,29 @@
 
 // TODO: #![warn(missing_docs)]
 
+#[macro_use]
+extern crate tracing;
+
 extern crate alloc;
 
 use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
-use core::any::TypeId;
+use core::any::{Any, TypeId};
+use core::cell::Cell;
 use core::fmt;
 use core::marker::PhantomData;
 use std::borrow::Cow;
 
 use bitflags::bitflags;
+use ironrdp_bulk::mppc::{HistorySize, MppcCompressor, MppcDecompressor};
+use ironrdp_bulk::PacketFlags;
 use ironrdp_core::{
-    assert_obj_safe, decode_cursor, encode_buf, AsAny, DecodeResult, Encode, EncodeResult, ReadCursor, WriteBuf,
-    WriteCursor,
+    assert_obj_safe, decode_cursor, encode_buf, invalid_field_err, other_err, AsAny, BufPool, DecodeResult, Encode,
+    EncodeResult, PooledWriteBuf, ReadCursor, WriteBuf, WriteCursor,
 };
 use ironrdp_pdu::gcc::ChannelDef;
 use ironrdp_pdu::gcc::{ChannelName, ChannelOptions};
+use ironrdp_pdu::rdp::client_info::CompressionType;
 use ironrdp_pdu::rdp::vc::ChannelControlFlags;
 use ironrdp_pdu::x224::X224;
 use ironrdp_pdu::{decode_err, mcs, PduResult};
@@ -78,19 +85,66 @@ impl SvcEncode for Vec<u8> {}
 
 /// Encodable PDU to be sent over a static virtual channel.
 ///
+/// PDUs of at most [`SvcMessage::INLINE_CAPACITY`] bytes are encoded right away and stored inline, without any
+/// allocation. Larger PDUs are boxed and encoded when the message is chunkified. Already encoded PDUs
+/// ([`SvcMessage::from_encoded`] and the legacy [`Vec<u8>`] PDUs) are not copied before being chunkified.
+///
 /// Additional SVC header flags can be added via [`SvcMessage::with_flags`] method.
 pub struct SvcMessage {
-    pdu: Box<dyn SvcEncode>,
+    pdu: SvcMessagePdu,
     flags: ChannelFlags,
 }
 
+enum SvcMessagePdu {
+    Boxed(Box<dyn SvcEncode>),
+    Inline {
+        data: [u8; SvcMessage::INLINE_CAPACITY],
+        len: usize,
+    },
+    Encoded(Vec<u8>),
+}
+
 impl SvcMessage {
+    /// Maximum size of the PDUs stored inline.
+    pub const INLINE_CAPACITY: usize = 64;
+
+    /// Wraps a PDU already encoded by the caller, which is moved into the message as-is.
+    pub fn from_encoded(data: Vec<u8>) -> Self {
+        Self {
+            pdu: SvcMessagePdu::Encoded(data),
+            flags: ChannelFlags::empty(),
+        }
+    }
+
+    /// Same as the [`From`] conversion, but the PDU is always boxed and encoded when the message is chunkified,
+    /// regardless of its size.
+    pub fn boxed<T: SvcEncode + 'static>(pdu: T) -> Self {
+        Self {
+            pdu: SvcMessagePdu::Boxed(Box::new(pdu)),
+            flags: ChannelFlags::empty(),
+        }
+    }
+
     /// Adds additional SVC header flags to the message.
     #[must_use]
     pub fn with_flags(mut self, flags: ChannelFlags) -> Self {
         self.flags |= flags;
         self
     }
+
+    /// Returns `true` if the PDU is stored inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.pdu, SvcMessagePdu::Inline { .. })
+    }
+
+    /// Returns the size of the encoded PDU, without the Channel PDU Header.
+    pub fn size(&self) -> usize {
+        match &self.pdu {
+            SvcMessagePdu::Boxed(pdu) => pdu.size(),
+            SvcMessagePdu::Inline { len, .. } => *len,
+            SvcMessagePdu::Encoded(data) => data.len(),
+        }
+    }
 }
 
 impl<T> From<T> for SvcMessage
@@ -98,10 +152,33 @@ where
     T: SvcEncode + 'static,
 {
     fn from(pdu: T) -> Self {
-        Self {
-            pdu: Box::new(pdu),
-            flags: ChannelFlags::empty(),
+        // Legacy `Vec<u8>` PDUs are already encoded, and are moved into the message instead of being copied.
+        let mut pdu = Some(pdu);
+        if let Some(data) = (&mut pdu as &mut dyn Any).downcast_mut::<Option<Vec<u8>>>() {
+            return Self::from_encoded(data.take().expect("PDU is taken once"));
+        }
+        let pdu = pdu.expect("PDU is taken once");
+
+        let len = pdu.size();
+
+        if len <= Self::INLINE_CAPACITY {
+            let mut data = [0; Self::INLINE_CAPACITY];
+
+            let mut cursor = WriteCursor::new(&mut data[..len]);
+
+            // On failure, the PDU is boxed so the error is reported when the message is chunkified. The length is
+            // the one actually written, so that no padding is sent if the size is over-reported.
+            if pdu.encode(&mut cursor).is_ok() {
+                let len = cursor.pos();
+
+                return Self {
+                    pdu: SvcMessagePdu::Inline { data, len },
+                    flags: ChannelFlags::empty(),
+                };
+            }
         }
+
+        Self::boxed(pdu)
     }
 }
 
@@ -116,11 +193,58 @@ pub enum CompressionCondition {
     Always,
 }
 
+/// Traffic of a virtual channel
+///
+/// The PDUs are counted as exchanged on the channel, including their header: for a static channel, the chunks with
+/// their Channel PDU Header, and for a dynamic channel, the DVC Data PDUs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChannelStats {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub pdus_received: u64,
+    pub pdus_sent: u64,
+}
+
+impl ChannelStats {
+    /// Records a PDU of `size` bytes received on the channel.
+    pub fn record_received(&mut self, size: usize) {
+        self.pdus_received = self.pdus_received.saturating_add(1);
+        self.bytes_received = self.bytes_received.saturating_add(size as u64);
+    }
+
+    /// Records a PDU of `size` bytes sent on the channel.
+    pub fn record_sent(&mut self, size: usize) {
+        self.pdus_sent = self.pdus_sent.saturating_add(1);
+        self.bytes_sent = self.bytes_sent.saturating_add(size as u64);
+    }
+
+    /// Returns the traffic since the `earlier` statistics of the same channel.
+    #[must_use]
+    pub fn since(&self, earlier: &ChannelStats) -> ChannelStats {
+        ChannelStats {
+            bytes_received: self.bytes_received.saturating_sub(earlier.bytes_received),
+            bytes_sent: self.bytes_sent.saturating_sub(earlier.bytes_sent),
+            pdus_received: self.pdus_received.saturating_sub(earlier.pdus_received),
+            pdus_sent: self.pdus_sent.saturating_sub(earlier.pdus_sent),
+        }
+    }
+}
+
 /// A static virtual channel.
+///
+/// The channel is closed when dropped if [`StaticVirtualChannel::close`] was not called before.
 #[derive(Debug)]
 pub struct StaticVirtualChannel {
     channel_processor: Box<dyn SvcProcessor>,
     chunk_processor: ChunkProcessor,
+    /// Buffers reused for the chunks of the outgoing messages.
+    buf_pool: BufPool,
+    /// Compressor of the data sent by the server, sharing its history across all the chunks sent on this channel.
+    compressor: Option<MppcCompressor>,
+    closed: bool,
+    /// Updated when encoding, which only borrows the channel.
+    stats: Cell<ChannelStats>,
 }
 
 impl StaticVirtualChannel {
@@ -128,6 +252,10 @@ impl StaticVirtualChannel {
         Self {
             channel_processor: Box::new(channel_processor),
             chunk_processor: ChunkProcessor::new(),
+            buf_pool: BufPool::new(),
+            compressor: None,
+            closed: false,
+            stats: Cell::new(ChannelStats::default()),
         }
     }
 
@@ -139,22 +267,146 @@ impl StaticVirtualChannel {
         self.channel_processor.compression_condition()
     }
 
+    /// Compresses the chunks encoded by [`StaticVirtualChannel::server_encode`] using the given compression type.
+    ///
+    /// The RDP 6.0 and RDP 6.1 bulk compressors are not implemented, and the RDP 5.0 one is used in their stead:
+    /// the compression type negotiated by the client being the highest it supports, the lower ones are supported too.
+    /// Enabling the compression again with the same history size keeps the compression history.
+    pub fn enable_compression(&mut self, compression_type: CompressionType) {
+        let history_size = match compression_type {
+            CompressionType::K8 => HistorySize::K8,
+            CompressionType::K64 | CompressionType::Rdp6 | CompressionType::Rdp61 => HistorySize::K64,
+        };
+
+        if self
+            .compressor
+            .as_ref()
+            .map_or(true, |compressor| compressor.history_size() != history_size)
+        {
+            self.compressor = Some(MppcCompressor::new(history_size));
+        }
+    }
+
+    /// Returns `true` if the chunks encoded by [`StaticVirtualChannel::server_encode`] are compressed.
+    pub fn is_compression_enabled(&self) -> bool {
+        self.compressor.is_some()
+    }
+
     pub fn start(&mut self) -> PduResult<Vec<SvcMessage>> {
         self.channel_processor.start()
     }
 
     /// Processes a payload received on the virtual channel. Returns a vector of PDUs to be sent back
     /// to the server. If no PDUs are to be sent, an empty vector is returned.
+    ///
+    /// PDUs larger than [`SvcProcessor::max_pdu_length`] are discarded without being buffered, and reported to
+    /// [`SvcProcessor::on_oversized_pdu`] instead.
     pub fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
-        if let Some(payload) = self.dechunkify(payload).map_err(|e| decode_err!(e))? {
-            return self.channel_processor.process(&payload);
+        self.record(|stats| stats.record_received(payload.len()));
+
+        let max_length = self.channel_processor.max_pdu_length();
+
+        match self
+            .chunk_processor
+            .dechunkify(payload, max_length)
+            .map_err(|e| decode_err!(e))?
+        {
+            Dechunkified::Complete(payload) => self.channel_processor.process(&payload),
+            Dechunkified::Oversized { length } => self.channel_processor.on_oversized_pdu(length),
+            Dechunkified::Incomplete => Ok(Vec::new()),
         }
+    }
 
-        Ok(Vec::new())
+    /// Closes the channel, giving the processor a chance to release its resources.
+    ///
+    /// Returns the final PDUs to be sent, if any. The processor is closed at most once:
+    /// subsequent calls return an empty vector.
+    pub fn close(&mut self) -> PduResult<Vec<SvcMessage>> {
+        if self.closed {
+            return Ok(Vec::new());
+        }
+
+        self.closed = true;
+        self.channel_processor.close()
+    }
+
+    /// Returns `true` if the channel was closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
     }
 
+    /// Returns the traffic of the channel, counting the chunks processed and encoded by this channel.
+    ///
+    /// The chunks encoded by [`StaticVirtualChannel::chunkify`] and the free encoding functions are not counted.
+    pub fn stats(&self) -> ChannelStats {
+        self.stats.get()
+    }
+
+    fn record(&self, f: impl FnOnce(&mut ChannelStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    /// Takes a vector of PDUs and breaks them into chunks prefixed with a Channel PDU Header (`CHANNEL_PDU_HEADER`).
+    ///
+    /// Prefer [`StaticVirtualChannel::chunkify_pooled`] when a channel is at hand, to reuse the buffers of the chunks.
     pub fn chunkify(messages: Vec<SvcMessage>) -> EncodeResult<Vec<WriteBuf>> {
-        ChunkProcessor::chunkify(messages, CHANNEL_CHUNK_LENGTH)
+        let pool = BufPool::new();
+        let chunks = ChunkProcessor::chunkify(&pool, messages, CHANNEL_CHUNK_LENGTH, None)?;
+        Ok(chunks.into_iter().map(PooledWriteBuf::detach).collect())
+    }
+
+    /// Same as [`StaticVirtualChannel::chunkify`], but the chunks are drawn from the buffer pool of this channel.
+    ///
+    /// The buffers go back to the pool once the chunks are dropped.
+    pub fn chunkify_pooled(&self, messages: Vec<SvcMessage>) -> EncodeResult<Vec<PooledWriteBuf<'_>>> {
+        let chunks = ChunkProcessor::chunkify(&self.buf_pool, messages, CHANNEL_CHUNK_LENGTH, None)?;
+        self.record(|stats| chunks.iter().for_each(|chunk| stats.record_sent(chunk.filled_len())));
+        Ok(chunks)
+    }
+
+    /// Returns the pool of buffers used to encode the messages sent on this channel.
+    pub fn buf_pool(&self) -> &BufPool {
+        &self.buf_pool
+    }
+
+    /// Same as [`client_encode_svc_messages`], but reusing the buffers of this channel.
+    pub fn client_encode(
+        &self,
+        messages: Vec<SvcMessage>,
+        channel_id: u16,
+        initiator_id: u16,
+    ) -> EncodeResult<Vec<u8>> {
+        encode_svc_messages(
+            &self.buf_pool,
+            None,
+            Some(&self.stats),
+            messages,
+            channel_id,
+            initiator_id,
+            true,
+        )
+    }
+
+    /// Same as [`server_encode_svc_messages`], but reusing the buffers of this channel.
+    ///
+    /// The chunks are compressed if [`StaticVirtualChannel::enable_compression`] was called.
+    pub fn server_encode(
+        &mut self,
+        messages: Vec<SvcMessage>,
+        channel_id: u16,
+        initiator_id: u16,
+    ) -> EncodeResult<Vec<u8>> {
+        encode_svc_messages(
+            &self.buf_pool,
+            self.compressor.as_mut(),
+            Some(&self.stats),
+            messages,
+            channel_id,
+            initiator_id,
+            false,
+        )
     }
 
     pub fn channel_processor_downcast_ref<T: SvcProcessor + 'static>(&self) -> Option<&T> {
@@ -164,22 +416,35 @@ impl StaticVirtualChannel {
     pub fn channel_processor_downcast_mut<T: SvcProcessor + 'static>(&mut self) -> Option<&mut T> {
         self.channel_processor.as_any_mut().downcast_mut()
     }
+}
 
-    fn dechunkify(&mut self, payload: &[u8]) -> DecodeResult<Option<Vec<u8>>> {
-        self.chunk_processor.dechunkify(payload)
+impl Drop for StaticVirtualChannel {
+    fn drop(&mut self) {
+        // Best-effort: there is no way to send the final PDUs at this point.
+        let _ = self.close();
     }
 }
 
 fn encode_svc_messages(
+    pool: &BufPool,
+    compressor: Option<&mut MppcCompressor>,
+    stats: Option<&Cell<ChannelStats>>,
     messages: Vec<SvcMessage>,
     channel_id: u16,
     initiator_id: u16,
     client: bool,
 ) -> EncodeResult<Vec<u8>> {
-    let mut fully_encoded_responses = WriteBuf::new(); // TODO(perf): reuse this buffer using `clear` and `filled` as appropriate
+    // The fully encoded PDUs are handed over to the caller, so this buffer is not drawn from the pool.
+    let mut fully_encoded_responses = WriteBuf::new();
 
     // For each response PDU, chunkify it and add appropriate static channel headers.
-    let chunks = StaticVirtualChannel::chunkify(messages)?;
+    let chunks = ChunkProcessor::chunkify(pool, messages, CHANNEL_CHUNK_LENGTH, compressor)?;
+
+    if let Some(stats) = stats {
+        let mut updated = stats.get();
+        chunks.iter().for_each(|chunk| updated.record_sent(chunk.filled_len()));
+        stats.set(updated);
+    }
 
     // SendData is [`McsPdu`], which is [`x224Pdu`], which is [`Encode`]. [`Encode`] for [`x224Pdu`]
     // also takes care of adding the Tpkt header, so therefore we can just call `encode_buf` on each of these and
@@ -223,7 +488,7 @@ pub fn client_encode_svc_messages(
     channel_id: u16,
     initiator_id: u16,
 ) -> EncodeResult<Vec<u8>> {
-    encode_svc_messages(messages, channel_id, initiator_id, true)
+    encode_svc_messages(&BufPool::new(), None, None, messages, channel_id, initiator_id, true)
 }
 
 /// Encode a vector of [`SvcMessage`] in preparation for sending them on the `channel_id` channel.
@@ -237,7 +502,7 @@ pub fn server_encode_svc_messages(
     channel_id: u16,
     initiator_id: u16,
 ) -> EncodeResult<Vec<u8>> {
-    encode_svc_messages(messages, channel_id, initiator_id, false)
+    encode_svc_messages(&BufPool::new(), None, None, messages, channel_id, initiator_id, false)
 }
 
 /// A type that is a Static Virtual Channel
@@ -267,6 +532,36 @@ pub trait SvcProcessor: AsAny + fmt::Debug + Send {
     ///
     /// Returns a list of PDUs to be sent back.
     fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>>;
+
+    /// Returns the maximum length of the de-chunkified PDUs accepted on the channel, if any.
+    ///
+    /// A PDU announcing a larger length in its first chunk is discarded as it is received, instead of being buffered.
+    /// Defaults to [`DEFAULT_MAX_PDU_LENGTH`].
+    fn max_pdu_length(&self) -> Option<usize> {
+        Some(DEFAULT_MAX_PDU_LENGTH)
+    }
+
+    /// Called instead of [`SvcProcessor::process`] when a PDU larger than [`SvcProcessor::max_pdu_length`] is
+    /// received, with the length of the PDU.
+    ///
+    /// Returns a list of PDUs to be sent back. By default, the PDU is skipped, and the processing resumes with the
+    /// next one.
+    fn on_oversized_pdu(&mut self, length: usize) -> PduResult<Vec<SvcMessage>> {
+        warn!(channel = ?self.channel_name(), length, "Skipped PDU exceeding the maximum length");
+
+        Ok(Vec::new())
+    }
+
+    /// Closes the channel, when the session terminates or the channel is removed.
+    ///
+    /// This is the place to release external resources held by the processor.
+    /// Called at most once by [`StaticVirtualChannel`].
+    ///
+    /// Returns a list of final PDUs to be sent back. These may be discarded when the
+    /// channel is closed on drop.
+    fn close(&mut self) -> PduResult<Vec<SvcMessage>> {
+        Ok(Vec::new())
+    }
 }
 
 assert_obj_safe!(SvcProcessor);
@@ -279,28 +574,51 @@ pub trait SvcServerProcessor: SvcProcessor {}
 
 assert_obj_safe!(SvcServerProcessor);
 
+/// Outcome of the de-chunkification of a payload, see [`ChunkProcessor::dechunkify`]
+#[derive(Debug, PartialEq, Eq)]
+enum Dechunkified {
+    /// More chunks are expected.
+    Incomplete,
+    /// The last chunk of a PDU was received.
+    Complete(Vec<u8>),
+    /// The PDU exceeds the maximum length, and its remaining chunks are discarded.
+    Oversized { length: usize },
+}
+
 /// ChunkProcessor is used to chunkify/de-chunkify static virtual channel PDUs.
 #[derive(Debug)]
 struct ChunkProcessor {
     /// Buffer for de-chunkification of clipboard PDUs. Everything bigger than ~1600 bytes is
     /// usually chunked when transferred over svc.
     chunked_pdu: Vec<u8>,
+    /// Decompressor of the received chunks, created when the first compressed chunk is received.
+    decompressor: Option<MppcDecompressor>,
+    /// Set while the chunks of an oversized PDU are received.
+    discarding: bool,
 }
 
 impl ChunkProcessor {
     fn new() -> Self {
         Self {
             chunked_pdu: Vec::new(),
+            decompressor: None,
+            discarding: false,
         }
     }
 
     /// Takes a vector of PDUs and breaks them into chunks prefixed with a Channel PDU Header (`CHANNEL_PDU_HEADER`).
     ///
     /// Each chunk is at most `max_chunk_len` bytes long (not including the Channel PDU Header).
-    fn chunkify(messages: Vec<SvcMessage>, max_chunk_len: usize) -> EncodeResult<Vec<WriteBuf>> {
+    /// The chunks are compressed by `compressor`, if any.
+    fn chunkify<'pool>(
+        pool: &'pool BufPool,
+        messages: Vec<SvcMessage>,
+        max_chunk_len: usize,
+        mut compressor: Option<&mut MppcCompressor>,
+    ) -> EncodeResult<Vec<PooledWriteBuf<'pool>>> {
         let mut results = Vec::new();
         for message in messages {
-            results.extend(Self::chunkify_one(message, max_chunk_len)?);
+            Self::chunkify_one(pool, message, max_chunk_len, compressor.as_deref_mut(), &mut results)?;
         }
         Ok(results)
     }
@@ -308,30 +626,80 @@ impl ChunkProcessor {
     /// Dechunkify a payload received on the virtual channel.
     ///
     /// If the payload is not chunked, returns the payload as-is.
-    /// For chunked payloads, returns `Ok(None)` until the last chunk is received, at which point
-    /// it returns `Ok(Some(payload))`.
-    fn dechunkify(&mut self, payload: &[u8]) -> DecodeResult<Option<Vec<u8>>> {
+    /// For chunked payloads, returns [`Dechunkified::Incomplete`] until the last chunk is received, at which point
+    /// it returns the whole payload.
+    ///
+    /// PDUs longer than `max_length`, either announced in the header of their first chunk or as received, are
+    /// reported once and their chunks are no longer buffered.
+    fn dechunkify(&mut self, payload: &[u8], max_length: Option<usize>) -> DecodeResult<Dechunkified> {
         let mut cursor = ReadCursor::new(payload);
-        let last = Self::process_header(&mut cursor)?;
+        let header = Self::process_header(&mut cursor)?;
+        let flags = header.flags;
+
+        if flags.contains(ChannelControlFlags::FLAG_FIRST) {
+            self.discarding = false;
+            self.chunked_pdu.clear();
+        }
+
+        // The compression type and flags are laid out as in the compressedType field of the Share Data Header,
+        // shifted by 16 bits.
+        let [_, _, compressed_type, _] = flags.bits().to_le_bytes();
+        let packet_flags = PacketFlags::from_bits_truncate(compressed_type);
+
+        if packet_flags.is_empty() {
+            // Extend the chunked_pdu buffer with the payload
+            self.chunked_pdu.extend_from_slice(cursor.remaining());
+        } else {
+            let history_size = HistorySize::from_compression_type(compressed_type & 0x0F)
+                .ok_or_else(|| invalid_field_err!(ChannelPduHeader::NAME, "flags", "unsupported compression type"))?;
+
+            if self
+                .decompressor
+                .as_ref()
+                .is_some_and(|decompressor| decompressor.history_size() != history_size)
+            {
+                self.decompressor = None;
+            }
+
+            self.decompressor
+                .get_or_insert_with(|| MppcDecompressor::new(history_size))
+                .decompress(cursor.remaining(), packet_flags, &mut self.chunked_pdu)
+                .map_err(|e| other_err!(ChannelPduHeader::NAME, source: e))?;
+        }
+
+        let last = flags.contains(ChannelControlFlags::FLAG_LAST);
 
-        // Extend the chunked_pdu buffer with the payload
-        self.chunked_pdu.extend_from_slice(cursor.remaining());
+        if self.discarding {
+            // The compressed chunks are still decompressed above, to keep the compression history in sync.
+            self.chunked_pdu.clear();
+            self.discarding = !last;
+            return Ok(Dechunkified::Incomplete);
+        }
+
+        if let Some(max_length) = max_length {
+            let announced_length = usize::try_from(header.length).unwrap_or(usize::MAX);
+            let length = announced_length.max(self.chunked_pdu.len());
+
+            if length > max_length {
+                self.chunked_pdu = Vec::new();
+                self.discarding = !last;
+                return Ok(Dechunkified::Oversized { length });
+            }
+        }
 
         // If this was an unchunked message, or the last in a series of chunks, return the payload
         if last {
             // Take the chunked_pdu buffer and replace it with an empty one
-            return Ok(Some(core::mem::take(&mut self.chunked_pdu)));
+            return Ok(Dechunkified::Complete(core::mem::take(&mut self.chunked_pdu)));
         }
 
-        // This was an intermediate chunk, return None
-        Ok(None)
+        // This was an intermediate chunk
+        Ok(Dechunkified::Incomplete)
     }
 
-    /// Returns whether this was the last chunk based on the flags in the channel header.
-    fn process_header(payload: &mut ReadCursor<'_>) -> DecodeResult<bool> {
-        let channel_header: ironrdp_pdu::rdp::vc::ChannelPduHeader = decode_cursor(payload)?;
-
-        Ok(channel_header.flags.contains(ChannelControlFlags::FLAG_LAST))
+    /// Returns the channel header.
+    fn process_header(payload: &mut ReadCursor<'_>) -> DecodeResult<ironrdp_pdu::rdp::vc::ChannelPduHeader> {
+        decode_cursor(payload)
     }
 
     /// Takes a single PDU and breaks it into chunks prefixed with a [`ChannelPduHeader`].
@@ -342,26 +710,53 @@ impl ChunkProcessor {
     /// return 3 chunks, each 1600 bytes long, and the last chunk will be 800 bytes long.
     ///
     /// [[ Channel PDU Header | 1600 bytes of PDU data ] [ Channel PDU Header | 1600 bytes of PDU data ] [ Channel PDU Header | 800 bytes of PDU data ]]
-    fn chunkify_one(message: SvcMessage, max_chunk_len: usize) -> EncodeResult<Vec<WriteBuf>> {
-        let mut encoded_pdu = WriteBuf::new(); // TODO(perf): reuse this buffer using `clear` and `filled` as appropriate
-        encode_buf(message.pdu.as_ref(), &mut encoded_pdu)?;
+    fn chunkify_one<'pool>(
+        pool: &'pool BufPool,
+        message: SvcMessage,
+        max_chunk_len: usize,
+        mut compressor: Option<&mut MppcCompressor>,
+        chunks: &mut Vec<PooledWriteBuf<'pool>>,
+    ) -> EncodeResult<()> {
+        let mut boxed_pdu_buf;
+        let encoded_pdu: &[u8] = match &message.pdu {
+            SvcMessagePdu::Boxed(pdu) => {
+                boxed_pdu_buf = pool.get();
+                encode_buf(pdu.as_ref(), &mut boxed_pdu_buf)?;
+                boxed_pdu_buf.filled()
+            }
+            SvcMessagePdu::Inline { data, len } => &data[..*len],
+            SvcMessagePdu::Encoded(data) => data,
+        };
 
-        let mut chunks = Vec::new();
+        // Holds the compressed data of the current chunk.
+        let mut compressed = Vec::new();
 
-        let total_len = encoded_pdu.filled_len();
+        let total_len = encoded_pdu.len();
         let mut chunk_start_index: usize = 0;
         let mut chunk_end_index = core::cmp::min(total_len, max_chunk_len);
         loop {
-            // Create a buffer to hold this next chunk.
-            // TODO(perf): Reuse this buffer using `clear` and `filled` as appropriate.
-            //             This one will be a bit trickier because we'll need to grow
-            //             the number of chunk buffers if we run out.
-            let mut chunk = WriteBuf::new();
+            // Take a buffer to hold this next chunk.
+            let mut chunk = pool.get();
 
             // Set the first and last flags if this is the first and/or last chunk for this PDU.
             let first = chunk_start_index == 0;
             let last = chunk_end_index == total_len;
 
+            let mut data = &encoded_pdu[chunk_start_index..chunk_end_index];
+            let mut compression_flags = ChannelFlags::empty();
+
+            if let Some(compressor) = compressor.as_deref_mut().filter(|_| data.len() > COMPRESSION_THRESHOLD) {
+                compressed.clear();
+                let packet_flags = compressor.compress(data, &mut compressed);
+
+                if !packet_flags.is_empty() {
+                    let compressed_type = packet_flags.bits() | compressor.history_size().compression_type();
+                    compression_flags = ChannelFlags::from_bits_retain(u32::from(compressed_type) << 16);
+                }
+
+                data = &compressed;
+            }
+
             // Create the header for this chunk.
             let header = {
                 let mut flags = ChannelFlags::empty();
@@ -372,7 +767,7 @@ impl ChunkProcessor {
                     flags |= ChannelFlags::LAST;
                 }
 
-                flags |= message.flags;
+                flags |= message.flags | compression_flags;
 
                 ChannelPduHeader {
                     length: ironrdp_core::cast_int!(ChannelPduHeader::NAME, "length", total_len)?,
@@ -383,7 +778,7 @@ impl ChunkProcessor {
             // Encode the header for this chunk.
             encode_buf(&header, &mut chunk)?;
             // Append the piece of the encoded_pdu that belongs in this chunk.
-            chunk.write_slice(&encoded_pdu[chunk_start_index..chunk_end_index]);
+            chunk.write_slice(data);
             // Push the chunk onto the results.
             chunks.push(chunk);
 
@@ -397,7 +792,7 @@ impl ChunkProcessor {
             chunk_end_index = core::cmp::min(total_len, chunk_end_index.saturating_add(max_chunk_len));
         }
 
-        Ok(chunks)
+        Ok(())
     }
 }
 
@@ -500,6 +895,8 @@ impl StaticChannelSet {
     /// Removes a [`StaticVirtualChannel`] from this [`StaticChannelSet`].
     ///
     /// If a static virtual channel of this type existed, it will be returned.
+    /// The returned channel is closed when dropped, unless [`StaticVirtualChannel::close`]
+    /// is called explicitly in order to retrieve its final PDUs.
     pub fn remove_by_type_id(&mut self, type_id: TypeId) -> Option<StaticVirtualChannel> {
         let svc = self.channels.remove(&type_id);
         if let Some(channel_id) = self.to_channel_id.remove(&type_id) {
@@ -511,6 +908,7 @@ impl StaticChannelSet {
     /// Removes a [`StaticVirtualChannel`] from this [`StaticChannelSet`].
     ///
     /// If a static virtual channel of this type existed, it will be returned.
+    /// See [`Self::remove_by_type_id()`].
     pub fn remove_by_type<T: SvcProcessor + 'static>(&mut self) -> Option<StaticVirtualChannel> {
         let type_id = TypeId::of::<T>();
         self.remove_by_type_id(type_id)
@@ -549,6 +947,17 @@ impl StaticChannelSet {
         }
     }
 
+    /// Detaches a channel ID left by the user or the server, and closes the associated static virtual channel.
+    ///
+    /// The channel is kept in this [`StaticChannelSet`], so that its processor remains accessible. Returns `None`
+    /// if no channel is attached to this ID, or the result of [`StaticVirtualChannel::close`] otherwise. The final
+    /// PDUs can't be sent on the left channel, and are meant to be discarded.
+    pub fn leave_channel_id(&mut self, channel_id: StaticChannelId) -> Option<PduResult<Vec<SvcMessage>>> {
+        let type_id = self.get_type_id_by_channel_id(channel_id)?;
+        self.detach_channel_id(type_id);
+        self.get_by_type_id_mut(type_id).map(StaticVirtualChannel::close)
+    }
+
     #[inline]
     pub fn iter(&self) -> impl Iterator<Item = (TypeId, &StaticVirtualChannel)> {
         self.channels.iter().map(|(type_id, svc)| (*type_id, svc))
@@ -577,8 +986,23 @@ impl StaticChannelSet {
         self.to_channel_id.values().copied()
     }
 
+    /// Closes all the channels, returning for each of them the attached channel ID, if any,
+    /// and the result of [`StaticVirtualChannel::close`].
+    pub fn close_all(&mut self) -> Vec<(Option<StaticChannelId>, PduResult<Vec<SvcMessage>>)> {
+        self.iter_mut()
+            .map(|(_, svc, channel_id)| (channel_id, svc.close()))
+            .collect()
+    }
+
+    /// Removes all the channels from this [`StaticChannelSet`], closing them.
+    ///
+    /// The final PDUs emitted by the channels are discarded, use [`Self::close_all()`] beforehand
+    /// to retrieve them.
     #[inline]
     pub fn clear(&mut self) {
+        for (_, svc, _) in self.iter_mut() {
+            let _ = svc.close();
+        }
         self.channels.clear();
         self.to_channel_id.clear();
         self.to_type_id.clear();
@@ -602,6 +1026,12 @@ impl Default for StaticChannelSet {
 /// - <https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/a8593178-80c0-4b80-876c-cb77e62cecfc>
 pub const CHANNEL_CHUNK_LENGTH: usize = 1600;
 
+/// Default maximum length of the de-chunkified PDUs, see [`SvcProcessor::max_pdu_length`].
+pub const DEFAULT_MAX_PDU_LENGTH: usize = 16 * 1024 * 1024; // 16 MiB
+
+/// Chunks up to this length are never compressed, the compressed form of so little data being rarely shorter.
+const COMPRESSION_THRESHOLD: usize = 64;
+
 bitflags! {
     /// Channel control flags, as specified in [section 2.2.6.1.1 of MS-RDPBCGR].
     ///
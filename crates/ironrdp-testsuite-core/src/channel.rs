@@ -0,0 +1,61 @@
+//! Decoding of the messages sent over the virtual channels, as they are written on the wire.
+//!
+//! The messages are expected to fit in a single channel chunk.
+
+use ironrdp_core::{decode, Decode};
+use ironrdp_dvc::pdu::{DrdynvcClientPdu, DrdynvcDataPdu};
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage};
+
+/// Size of the Channel PDU Header preceding the payload of each chunk.
+const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+
+/// Chunks the messages, and returns the payload of each chunk, without its channel PDU header.
+///
+/// # Panics
+///
+/// Panics if a message can't be encoded.
+#[track_caller]
+pub fn channel_payloads(messages: Vec<SvcMessage>) -> Vec<Vec<u8>> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| chunk.filled()[CHANNEL_PDU_HEADER_SIZE..].to_vec())
+        .collect()
+}
+
+/// Decodes the PDU carried by each message.
+///
+/// # Panics
+///
+/// Panics if a message can't be encoded, or if its payload can't be decoded as `T`.
+#[track_caller]
+pub fn decode_channel_pdus<T>(messages: Vec<SvcMessage>) -> Vec<T>
+where
+    T: for<'de> Decode<'de>,
+{
+    channel_payloads(messages)
+        .iter()
+        .map(|payload| decode::<T>(payload).unwrap())
+        .collect()
+}
+
+/// Decodes the PDU of the dynamic virtual channel carried by each DRDYNVC Data PDU sent by the client.
+///
+/// # Panics
+///
+/// Panics if a message is not a DRDYNVC Data PDU, or if its data can't be decoded as `T`.
+#[track_caller]
+pub fn decode_dvc_client_pdus<T>(messages: Vec<SvcMessage>) -> Vec<T>
+where
+    T: for<'de> Decode<'de>,
+{
+    decode_channel_pdus::<DrdynvcClientPdu>(messages)
+        .into_iter()
+        .map(|pdu| {
+            let DrdynvcClientPdu::Data(DrdynvcDataPdu::Data(data)) = pdu else {
+                panic!("unexpected DRDYNVC PDU: {pdu:?}");
+            };
+            decode::<T>(&data.data).unwrap()
+        })
+        .collect()
+}
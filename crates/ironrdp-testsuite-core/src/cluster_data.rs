@@ -2,10 +2,26 @@ use ironrdp_pdu::gcc::{ClientClusterData, RedirectionFlags, RedirectionVersion};
 
 pub const CLUSTER_DATA_BUFFER: [u8; 8] = [0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
 
+/// Sent by mstsc with `/admin`, to connect to the console session.
+pub const CONSOLE_CLUSTER_DATA_BUFFER: [u8; 8] = [0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+/// Sent by mstsc to reconnect to the disconnected session 3.
+pub const SESSION_ID_CLUSTER_DATA_BUFFER: [u8; 8] = [0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00];
+
 lazy_static! {
     pub static ref CLUSTER_DATA: ClientClusterData = ClientClusterData {
         flags: RedirectionFlags::REDIRECTION_SUPPORTED,
         redirection_version: RedirectionVersion::V4,
         redirected_session_id: 0,
     };
+    pub static ref CONSOLE_CLUSTER_DATA: ClientClusterData = ClientClusterData {
+        flags: RedirectionFlags::REDIRECTION_SUPPORTED | RedirectionFlags::REDIRECTED_SESSION_FIELD_VALID,
+        redirection_version: RedirectionVersion::V4,
+        redirected_session_id: 0,
+    };
+    pub static ref SESSION_ID_CLUSTER_DATA: ClientClusterData = ClientClusterData {
+        flags: RedirectionFlags::REDIRECTION_SUPPORTED | RedirectionFlags::REDIRECTED_SESSION_FIELD_VALID,
+        redirection_version: RedirectionVersion::V4,
+        redirected_session_id: 3,
+    };
 }
@@ -0,0 +1,55 @@
+//! Strict checks of the [`Encode`] contract: `encode` must write exactly `size()` bytes.
+//!
+//! PDUs opt into these checks with the [`encoded_size_test!`](crate::encoded_size_test) and
+//! [`round_trip_test!`](crate::round_trip_test) macros.
+
+use ironrdp_core::{Decode, Encode, ReadCursor, WriteCursor};
+
+/// Encodes the PDU into a buffer of exactly `size()` bytes, and returns the buffer.
+///
+/// # Panics
+///
+/// Panics if encoding fails, or if the PDU does not fill the whole buffer.
+#[track_caller]
+pub fn assert_encoded_size<T: Encode + ?Sized>(pdu: &T) -> Vec<u8> {
+    let size = pdu.size();
+    let mut buf = vec![0; size];
+    let mut cursor = WriteCursor::new(&mut buf);
+
+    if let Err(e) = pdu.encode(&mut cursor) {
+        panic!("failed to encode {} into {size} bytes: {e}", pdu.name());
+    }
+
+    assert_eq!(
+        cursor.pos(),
+        size,
+        "{} wrote {} bytes, but its size is {size}",
+        pdu.name(),
+        cursor.pos()
+    );
+
+    buf
+}
+
+/// Decodes the captured PDU, re-encodes it and checks that the same bytes are produced.
+///
+/// # Panics
+///
+/// Panics if decoding fails, if the capture is not fully consumed, or if the re-encoded PDU differs.
+#[track_caller]
+pub fn assert_round_trip<'de, T>(encoded: &'de [u8])
+where
+    T: Decode<'de> + Encode,
+{
+    let mut cursor = ReadCursor::new(encoded);
+    let pdu = match T::decode(&mut cursor) {
+        Ok(pdu) => pdu,
+        Err(e) => panic!("failed to decode the capture: {e}"),
+    };
+
+    assert!(cursor.is_empty(), "{} left trailing bytes undecoded", pdu.name());
+
+    let reencoded = assert_encoded_size(&pdu);
+
+    crate::assert_eq_hex!(reencoded, encoded);
+}
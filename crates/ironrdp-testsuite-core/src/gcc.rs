@@ -133,6 +133,7 @@ lazy_static! {
         message_channel: None,
         multi_transport_channel: None,
         monitor_extended: None,
+        unknown: Vec::new(),
     };
     pub static ref CLIENT_GCC_WITH_CLUSTER_OPTIONAL_FIELD: ClientGccBlocks = {
         let mut data = CLIENT_GCC_WITHOUT_OPTIONAL_FIELDS.clone();
@@ -151,6 +152,7 @@ lazy_static! {
         security: SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS.clone(),
         message_channel: None,
         multi_transport_channel: None,
+        unknown: Vec::new(),
     };
     pub static ref SERVER_GCC_WITH_OPTIONAL_FIELDS: ServerGccBlocks = {
         let mut data = SERVER_GCC_WITHOUT_OPTIONAL_FIELDS.clone();
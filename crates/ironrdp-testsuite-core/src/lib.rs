@@ -15,10 +15,12 @@ extern crate lazy_static;
 mod macros;
 
 pub mod capsets;
+pub mod channel;
 pub mod client_info;
 pub mod cluster_data;
 pub mod conference_create;
 pub mod core_data;
+pub mod encoding;
 pub mod gcc;
 pub mod gfx;
 pub mod graphics_messages;
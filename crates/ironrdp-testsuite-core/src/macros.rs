@@ -168,3 +168,41 @@ macro_rules! mcs_encode_decode_test {
         )+
     };
 }
+
+/// Registers PDU samples whose `size()` must exactly match the number of bytes written by `encode`.
+///
+/// Each sample generates a `<name>_encoded_size` test calling [`assert_encoded_size`](crate::encoding::assert_encoded_size).
+#[macro_export]
+macro_rules! encoded_size_test {
+    ($( $test_name:ident : $pdu:expr ; )+) => {
+        $(
+            $crate::paste! {
+                #[test]
+                fn [< $test_name _encoded_size >]() {
+                    let pdu = $pdu;
+
+                    $crate::encoding::assert_encoded_size(&pdu);
+                }
+            }
+        )+
+    };
+}
+
+/// Registers captured PDUs which must be re-encoded into the exact same bytes after decoding.
+///
+/// Each capture generates a `<name>_round_trip` test calling [`assert_round_trip`](crate::encoding::assert_round_trip).
+#[macro_export]
+macro_rules! round_trip_test {
+    ($( $test_name:ident : $pdu_type:ty , $encoded_pdu:expr ; )+) => {
+        $(
+            $crate::paste! {
+                #[test]
+                fn [< $test_name _round_trip >]() {
+                    let encoded: &[u8] = &$encoded_pdu;
+
+                    $crate::encoding::assert_round_trip::<$pdu_type>(encoded);
+                }
+            }
+        )+
+    };
+}
@@ -42,6 +42,10 @@ pub const CHANNEL_JOIN_CONFIRM_PDU: ChannelJoinConfirm = ChannelJoinConfirm {
     channel_id: 1007,
 };
 
+pub const CHANNEL_LEAVE_REQUEST_PDU_BUFFER: [u8; 4] = [0x40, 0x01, 0x03, 0xec];
+
+pub const CHANNEL_EXPEL_INDICATION_PDU_BUFFER: [u8; 6] = [0x60, 0x00, 0x03, 0x01, 0x00, 0x06];
+
 pub const DISCONNECT_PROVIDER_ULTIMATUM_PDU_BUFFER: [u8; 2] = [0x21, 0x80];
 
 pub const DISCONNECT_PROVIDER_ULTIMATUM_PDU: DisconnectProviderUltimatum = DisconnectProviderUltimatum {
@@ -100,6 +104,13 @@ pub const CONNECT_RESPONSE_BUFFER: [u8; concat_arrays_size!(
 )] = concat_arrays!(CONNECT_RESPONSE_PREFIX_BUFFER, CONFERENCE_CREATE_RESPONSE_BUFFER);
 
 lazy_static! {
+    pub static ref CHANNEL_LEAVE_REQUEST_PDU: ChannelLeaveRequest = ChannelLeaveRequest {
+        channel_ids: vec![1004],
+    };
+    pub static ref CHANNEL_EXPEL_INDICATION_PDU: ChannelExpelIndication = ChannelExpelIndication {
+        channel_id: 1004,
+        user_ids: vec![1007],
+    };
     pub static ref CONNECT_INITIAL: ConnectInitial = ConnectInitial {
         calling_domain_selector: vec![0x01],
         called_domain_selector: vec![0x01],
@@ -1,6 +1,7 @@
 use ironrdp_pdu::gcc;
 use ironrdp_pdu::rdp::finalization_messages::*;
 use ironrdp_pdu::rdp::headers::*;
+use ironrdp_pdu::rdp::play_sound::PlaySoundPdu;
 use ironrdp_pdu::rdp::server_license::*;
 use ironrdp_pdu::rdp::*;
 
@@ -139,6 +140,21 @@ pub const SERVER_FONT_MAP_BUFFER: [u8; 26] = [
     0x04, 0x00, // entry size
 ];
 
+pub const SERVER_PLAY_SOUND_BUFFER: [u8; 26] = [
+    0x1a, 0x00, // ShareControlHeader::totalLength
+    0x17, 0x00, // ShareControlHeader::pduType
+    0xea, 0x03, // ShareControlHeader::PduSource
+    0xea, 0x03, 0x01, 0x00, // share id
+    0x00, // padding
+    0x01, // stream id
+    0x0c, 0x00, // uncompressed length
+    0x22, // pdu type
+    0x00, // compression type
+    0x00, 0x00, // compressed length
+    0xc8, 0x00, 0x00, 0x00, // duration
+    0xee, 0x02, 0x00, 0x00, // frequency
+];
+
 pub const SERVER_LICENSE_BUFFER: [u8; 20] = [
     0x80, 0x00, // flags
     0x00, 0x00, // flagsHi
@@ -265,6 +281,19 @@ lazy_static! {
         pdu_source: 1002,
         share_id: 66_538,
     };
+    pub static ref SERVER_PLAY_SOUND: ShareControlHeader = ShareControlHeader {
+        share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
+            share_data_pdu: ShareDataPdu::PlaySound(PlaySoundPdu {
+                duration_ms: 200,
+                frequency_hz: 750,
+            }),
+            stream_priority: StreamPriority::Low,
+            compression_flags: CompressionFlags::empty(),
+            compression_type: client_info::CompressionType::K8,
+        }),
+        pdu_source: 1002,
+        share_id: 66_538,
+    };
     pub static ref MONITOR_LAYOUT_PDU: ShareControlHeader = ShareControlHeader {
         share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
             share_data_pdu: ShareDataPdu::MonitorLayout(MonitorLayoutPdu {
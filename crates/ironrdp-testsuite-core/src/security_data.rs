@@ -53,6 +53,12 @@ lazy_static! {
         server_random: Some(SERVER_RANDOM_BUFFER),
         server_cert: SERVER_CERT_BUFFER.to_vec(),
     };
+    pub static ref SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE: ServerSecurityData = ServerSecurityData {
+        encryption_method: EncryptionMethod::BIT_128,
+        encryption_level: EncryptionLevel::ClientCompatible,
+        server_random: Some(SERVER_RANDOM_BUFFER),
+        server_cert: Vec::new(),
+    };
     pub static ref SERVER_SECURITY_DATA_WITH_MISMATCH_OF_REQUIRED_AND_OPTIONAL_FIELDS: ServerSecurityData =
         ServerSecurityData {
             encryption_method: EncryptionMethod::empty(),
@@ -70,6 +76,13 @@ pub const SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS_BUFFER: [u8; 232] = concat_a
     SERVER_CERT_BUFFER
 );
 
+pub const SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE_BUFFER: [u8; 48] = concat_arrays!(
+    SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS_PREFIX_BUFFER,
+    (SERVER_RANDOM_BUFFER.len() as u32).to_le_bytes(),
+    [0x00, 0x00, 0x00, 0x00],
+    SERVER_RANDOM_BUFFER
+);
+
 pub const SERVER_SECURITY_DATA_WITH_INVALID_SERVER_RANDOM_BUFFER: [u8; 233] = concat_arrays!(
     SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS_PREFIX_BUFFER,
     (SERVER_RANDOM_BUFFER.len() as u32 + 1).to_le_bytes(),
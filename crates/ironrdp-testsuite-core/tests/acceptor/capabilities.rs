@@ -0,0 +1,139 @@
+use ironrdp_acceptor::DesktopSize;
+use ironrdp_pdu::gcc::KeyboardType;
+use ironrdp_pdu::rdp::capability_sets::*;
+
+use super::{acceptor, client_config, connect};
+
+const DESKTOP_SIZE: DesktopSize = DesktopSize {
+    width: 1024,
+    height: 768,
+};
+
+/// Capability sets advertised by the connector for [`client_config`], before the builder was introduced.
+fn expected_capability_sets() -> Vec<CapabilitySet> {
+    vec![
+        CapabilitySet::General(General {
+            major_platform_type: MajorPlatformType::UNIX,
+            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
+            ..Default::default()
+        }),
+        CapabilitySet::Bitmap(Bitmap {
+            pref_bits_per_pix: 32,
+            desktop_width: DESKTOP_SIZE.width,
+            desktop_height: DESKTOP_SIZE.height,
+            desktop_resize_flag: true,
+            drawing_flags: BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+                | BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY
+                | BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING,
+        }),
+        CapabilitySet::Order(Order::new(
+            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
+            OrderSupportExFlags::empty(),
+            0,
+            0,
+        )),
+        CapabilitySet::BitmapCache(BitmapCache {
+            caches: [CacheEntry {
+                entries: 0,
+                max_cell_size: 0,
+            }; BITMAP_CACHE_ENTRIES_NUM],
+        }),
+        CapabilitySet::Input(Input {
+            input_flags: InputFlags::all(),
+            keyboard_layout: 0,
+            keyboard_type: Some(KeyboardType::IbmEnhanced),
+            keyboard_subtype: 0,
+            keyboard_function_key: 12,
+            keyboard_ime_filename: String::new(),
+        }),
+        CapabilitySet::Pointer(Pointer {
+            color_pointer_cache_size: 32,
+            pointer_cache_size: 32,
+        }),
+        CapabilitySet::Brush(Brush {
+            support_level: SupportLevel::Default,
+        }),
+        CapabilitySet::GlyphCache(GlyphCache {
+            glyph_cache: [CacheDefinition {
+                entries: 0,
+                max_cell_size: 0,
+            }; GLYPH_CACHE_NUM],
+            frag_cache: CacheDefinition {
+                entries: 0,
+                max_cell_size: 0,
+            },
+            glyph_support_level: GlyphSupportLevel::None,
+        }),
+        CapabilitySet::OffscreenBitmapCache(OffscreenBitmapCache {
+            is_supported: false,
+            cache_size: 0,
+            cache_entries: 0,
+        }),
+        CapabilitySet::VirtualChannel(VirtualChannel {
+            flags: VirtualChannelFlags::NO_COMPRESSION,
+            chunk_size: Some(0),
+        }),
+        CapabilitySet::Sound(Sound {
+            flags: SoundFlags::empty(),
+        }),
+        CapabilitySet::LargePointer(LargePointer {
+            flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS | LargePointerSupportFlags::UP_TO_384X384_PIXELS,
+        }),
+        CapabilitySet::SurfaceCommands(SurfaceCommands {
+            flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER,
+        }),
+        CapabilitySet::BitmapCodecs(BitmapCodecs(vec![Codec {
+            id: 0x03,
+            property: CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(RfxClientCapsContainer {
+                capture_flags: CaptureFlags::empty(),
+                caps_data: RfxCaps(RfxCapset(vec![RfxICap {
+                    flags: RfxICapFlags::empty(),
+                    entropy_bits: EntropyBits::Rlgr3,
+                }])),
+            })),
+        }])),
+        CapabilitySet::FrameAcknowledge(FrameAcknowledge {
+            max_unacknowledged_frame_count: 20,
+        }),
+        CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+            max_request_size: 8 * 1024 * 1024,
+        }),
+    ]
+}
+
+#[test]
+fn default_client_capabilities_are_unchanged() {
+    let (_, server_result) = connect(client_config(DESKTOP_SIZE, 32), acceptor()).unwrap();
+
+    assert_eq!(server_result.capabilities, expected_capability_sets());
+}
+
+#[test]
+fn extra_capability_sets_are_advertised() {
+    let desktop_composition = CapabilitySet::DesktopComposition(vec![0x01, 0x00]);
+    let mut config = client_config(DESKTOP_SIZE, 32);
+    config.extra_capability_sets = vec![desktop_composition.clone()];
+
+    let (_, server_result) = connect(config, acceptor()).unwrap();
+
+    let mut expected = expected_capability_sets();
+    expected.insert(expected.len() - 1, desktop_composition);
+    assert_eq!(server_result.capabilities, expected);
+}
+
+#[test]
+fn extra_capability_set_already_advertised_is_refused() {
+    let mut config = client_config(DESKTOP_SIZE, 32);
+    config.extra_capability_sets = vec![CapabilitySet::Sound(Sound {
+        flags: SoundFlags::BEEPS,
+    })];
+
+    let Err(error) = connect(config, acceptor()) else {
+        panic!("duplicate capability set");
+    };
+
+    assert!(
+        error.to_string().contains("client capabilities"),
+        "unexpected error: {error}"
+    );
+}
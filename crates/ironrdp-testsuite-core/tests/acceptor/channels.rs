@@ -0,0 +1,173 @@
+use ironrdp_acceptor::ChannelConnectionSequence;
+use ironrdp_connector::{ClientConnector, ConnectorResult, Sequence as _};
+use ironrdp_core::{encode_vec, AsAny, Encode, WriteBuf};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::x224::X224;
+use ironrdp_pdu::{mcs, PduResult};
+use ironrdp_svc::{SvcClientProcessor, SvcMessage, SvcProcessor, SvcServerProcessor};
+
+use super::{acceptor, client_config, connect, connect_with, SERVER_DESKTOP_SIZE};
+
+const USER_CHANNEL_ID: u16 = 1002;
+const IO_CHANNEL_ID: u16 = 1003;
+
+/// Static channel named after `NAME`, ignoring its payloads.
+#[derive(Debug)]
+struct Channel<const NAME: u8>;
+
+impl<const NAME: u8> AsAny for Channel<NAME> {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
+
+impl<const NAME: u8> SvcProcessor for Channel<NAME> {
+    fn channel_name(&self) -> ChannelName {
+        ChannelName::new([NAME, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    fn process(&mut self, _payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        Ok(Vec::new())
+    }
+}
+
+impl<const NAME: u8> SvcClientProcessor for Channel<NAME> {}
+
+impl<const NAME: u8> SvcServerProcessor for Channel<NAME> {}
+
+#[test]
+fn static_channel_ids_follow_channel_definitions() {
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.frame_markers = false;
+
+    let mut acceptor = acceptor();
+    acceptor.attach_static_channel(Channel::<b'a'>);
+    acceptor.attach_static_channel(Channel::<b'c'>);
+
+    let connector = ClientConnector::new(config)
+        .with_static_channel(Channel::<b'a'>)
+        .with_static_channel(Channel::<b'b'>)
+        .with_static_channel(Channel::<b'c'>);
+
+    let (client, server) = connect_with(connector, acceptor).unwrap();
+
+    // Every requested channel is assigned an ID, including the one without a processor on the server side.
+    let ids: Vec<u16> = server.static_channel_ids.iter().map(|(_, id)| *id).collect();
+    assert_eq!(ids, [1004, 1005, 1006]);
+
+    for (name, id) in &server.static_channel_ids {
+        let (type_id, _) = client.static_channels.get_by_channel_name(name).unwrap();
+        assert_eq!(client.static_channels.get_channel_id_by_type_id(type_id), Some(*id));
+
+        if let Some((type_id, _)) = server.static_channels.get_by_channel_name(name) {
+            assert_eq!(server.static_channels.get_channel_id_by_type_id(type_id), Some(*id));
+        }
+    }
+
+    let (_, b_id) = server
+        .static_channel_ids
+        .iter()
+        .find(|(name, _)| *name == Channel::<b'b'>.channel_name())
+        .unwrap();
+    assert!(server.static_channels.get_by_channel_id(*b_id).is_none());
+}
+
+#[test]
+fn no_static_channel_ids_without_channels() {
+    let (_, server) = connect(client_config(SERVER_DESKTOP_SIZE, 32), acceptor()).unwrap();
+
+    assert!(server.static_channel_ids.is_empty());
+}
+
+/// Steps `sequence` with `pdu`, then sends the response if any.
+fn step(sequence: &mut ChannelConnectionSequence, pdu: &impl Encode) -> ConnectorResult<()> {
+    let mut output = WriteBuf::new();
+
+    sequence.step(&encode_vec(pdu).unwrap(), &mut output)?;
+
+    if sequence.next_pdu_hint().is_none() && !sequence.is_done() {
+        sequence.step(&[], &mut output)?;
+    }
+
+    Ok(())
+}
+
+fn join(channel_id: u16) -> X224<mcs::ChannelJoinRequest> {
+    X224(mcs::ChannelJoinRequest {
+        initiator_id: USER_CHANNEL_ID,
+        channel_id,
+    })
+}
+
+/// Returns a sequence announcing the channels 1004 and 1005, the user attached.
+fn attached_sequence() -> ChannelConnectionSequence {
+    let mut sequence = ChannelConnectionSequence::new(USER_CHANNEL_ID, IO_CHANNEL_ID, vec![1004, 1005]);
+
+    step(
+        &mut sequence,
+        &X224(mcs::ErectDomainPdu {
+            sub_height: 0,
+            sub_interval: 0,
+        }),
+    )
+    .unwrap();
+    step(&mut sequence, &X224(mcs::AttachUserRequest)).unwrap();
+
+    sequence
+}
+
+#[test]
+fn out_of_order_joins_are_accepted() {
+    let mut sequence = attached_sequence();
+
+    for channel_id in [1005, IO_CHANNEL_ID, 1004] {
+        step(&mut sequence, &join(channel_id)).unwrap();
+        assert!(!sequence.is_done());
+    }
+    step(&mut sequence, &join(USER_CHANNEL_ID)).unwrap();
+
+    assert!(sequence.is_done());
+}
+
+#[test]
+fn missing_join_is_reported() {
+    let mut sequence = attached_sequence();
+
+    for channel_id in [USER_CHANNEL_ID, IO_CHANNEL_ID, 1004] {
+        step(&mut sequence, &join(channel_id)).unwrap();
+    }
+
+    let client_info = X224(mcs::SendDataRequest {
+        initiator_id: USER_CHANNEL_ID,
+        channel_id: IO_CHANNEL_ID,
+        user_data: vec![0; 4].into(),
+    });
+    let e = step(&mut sequence, &client_info).unwrap_err();
+
+    assert!(!sequence.is_done());
+    assert!(e.to_string().contains("did not join the channels {1005}"), "{e}");
+}
+
+#[test]
+fn bogus_join_is_rejected() {
+    let mut sequence = attached_sequence();
+
+    step(&mut sequence, &join(USER_CHANNEL_ID)).unwrap();
+    let e = step(&mut sequence, &join(1010)).unwrap_err();
+
+    assert!(e.to_string().contains("channel 1010 was never announced"), "{e}");
+}
+
+#[test]
+fn repeated_join_is_rejected() {
+    let mut sequence = attached_sequence();
+
+    step(&mut sequence, &join(IO_CHANNEL_ID)).unwrap();
+    let e = step(&mut sequence, &join(IO_CHANNEL_ID)).unwrap_err();
+
+    assert!(e.to_string().contains("channel 1003 is joined twice"), "{e}");
+}
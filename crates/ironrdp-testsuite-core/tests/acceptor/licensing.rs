@@ -0,0 +1,294 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_acceptor::{AutoValid, LicensingStep, ServerLicensingHandler};
+use ironrdp_connector::{
+    custom_err, reason_err, ConnectorError, ConnectorErrorExt as _, ConnectorErrorKind, ConnectorResult, LicenseCache,
+};
+use ironrdp_core::decode;
+use ironrdp_pdu::mcs;
+use ironrdp_pdu::rdp::server_license::cert::{CertificateType, X509CertificateChain};
+use ironrdp_pdu::rdp::server_license::{
+    ClientLicenseInfo, ClientNewLicenseRequest, ClientPlatformChallengeResponse, LicenseEncryptionData,
+    LicenseInformation, LicensingErrorMessage, ProductInfo, Scope, ServerCertificate, ServerLicenseRequest,
+    ServerPlatformChallenge, ServerUpgradeLicense, PREMASTER_SECRET_SIZE,
+};
+use ironrdp_pdu::x224::X224;
+use num_bigint::BigUint;
+
+use super::{acceptor, client_config, connect, connect_recording, SERVER_DESKTOP_SIZE, USERNAME};
+
+/// Self-signed certificate of the test license server, holding a 1024-bit RSA key.
+const SERVER_CERTIFICATE: &[u8] = include_bytes!("../../test_data/license/server-cert.der");
+
+/// Modulus of the test license server key.
+const SERVER_KEY_MODULUS: &[u8] = b"c66d6ed47747db3d674a88ae6eb21c52d72e882bae5ed2a54f3bac1f832c27b02af73fd0939ba2d26cc8fcbddd986fa1ca6e55e0a830c043a5497bcc40c7c03e48fc48c771ff61216ded55d62f7754ca997f43af68bb8ebba29a3ae82d82435397a53e4f61cd26974cdaf99eec84d6c1829e31ffc7809144090fec3df1384725";
+
+/// Private exponent of the test license server key.
+const SERVER_KEY_PRIVATE_EXPONENT: &[u8] = b"b25773106530e13cfc14426abcb53abdbe07657e1517c6adab5c0fa4b709bd9619d7e5b43aa6dc9dd206871ac30b19f24976079b86af626eaa2534ae6cdedbc9c8d3e3eb83f5e8d9a2e3175d2ed664c464f62da3951e7c890721e4cd73d741491793c8d2665d62342c791479b57df3b28229b18d3c7b4324c016611d6a9ced15";
+
+const SERVER_RANDOM: [u8; 32] = [0x5a; 32];
+const PLATFORM_CHALLENGE: &[u8] = b"c\0h\0a\0l\0l\0e\0n\0g\0e\0\0\0";
+const PRODUCT_VERSION: u32 = 0x0006_0000;
+const COMPANY_NAME: &str = "Devolutions";
+const PRODUCT_ID: &str = "IRDP";
+const SCOPE: &str = "ironrdp.test";
+
+const HARDWARE_ID: [u32; 4] = [0x0102_0304, 0x0506_0708, 0x090a_0b0c, 0x0d0e_0f10];
+
+/// Licensing Error Message with STATUS_VALID_CLIENT, as sent by the acceptor before it was configurable.
+const VALID_CLIENT_LICENSE: [u8; 20] = [
+    0x80, 0x00, 0x00, 0x00, // flags, flagsHi
+    0xff, 0x03, 0x10, 0x00, // preamble
+    0x07, 0x00, 0x00, 0x00, // dwErrorCode: STATUS_VALID_CLIENT
+    0x02, 0x00, 0x00, 0x00, // dwStateTransition: ST_NO_TRANSITION
+    0x04, 0x00, 0x00, 0x00, // bbErrorInfo: empty error blob
+];
+
+fn hardware_data() -> Vec<u8> {
+    HARDWARE_ID.iter().flat_map(|data| data.to_le_bytes()).collect()
+}
+
+/// Decrypts the premaster secret sent by the client with the private key of the server.
+fn decrypt_premaster_secret(encrypted_premaster_secret: &[u8]) -> Vec<u8> {
+    let modulus = BigUint::parse_bytes(SERVER_KEY_MODULUS, 16).unwrap();
+    let private_exponent = BigUint::parse_bytes(SERVER_KEY_PRIVATE_EXPONENT, 16).unwrap();
+
+    let ciphertext = BigUint::from_bytes_le(encrypted_premaster_secret);
+    let mut premaster_secret = ciphertext.modpow(&private_exponent, &modulus).to_bytes_le();
+    premaster_secret.resize(PREMASTER_SECRET_SIZE, 0);
+
+    premaster_secret
+}
+
+fn encryption_data(encrypted_premaster_secret: &[u8], client_random: &[u8]) -> LicenseEncryptionData {
+    let premaster_secret = decrypt_premaster_secret(encrypted_premaster_secret);
+    LicenseEncryptionData::new(&premaster_secret, client_random, &SERVER_RANDOM)
+}
+
+/// Issues per-device client access licenses, bound to the hardware identification of the client.
+#[derive(Default)]
+struct CalIssuer {
+    encryption_data: Option<LicenseEncryptionData>,
+    /// Hardware identification of the devices to which a license was issued.
+    issued: Arc<Mutex<Vec<Vec<u8>>>>,
+    /// Hardware identification of the devices which presented a valid license.
+    presented: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl ServerLicensingHandler for CalIssuer {
+    fn start(&mut self) -> ConnectorResult<LicensingStep> {
+        let request = ServerLicenseRequest::new(
+            SERVER_RANDOM.to_vec(),
+            ProductInfo {
+                version: PRODUCT_VERSION,
+                company_name: COMPANY_NAME.to_owned(),
+                product_id: PRODUCT_ID.to_owned(),
+            },
+            Some(ServerCertificate {
+                issued_permanently: true,
+                // A chain holds at least two certificates, the client using the public key of the last one.
+                certificate: CertificateType::X509(X509CertificateChain {
+                    certificate_array: vec![SERVER_CERTIFICATE.to_vec(), SERVER_CERTIFICATE.to_vec()],
+                }),
+            }),
+            vec![Scope(SCOPE.to_owned())],
+        )
+        .map_err(ConnectorError::encode)?;
+
+        Ok(LicensingStep::Continue(request.into()))
+    }
+
+    fn on_new_license_request(&mut self, request: ClientNewLicenseRequest) -> ConnectorResult<LicensingStep> {
+        assert_eq!(request.client_username, USERNAME);
+
+        let encryption_data = encryption_data(&request.encrypted_premaster_secret, &request.client_random);
+        let challenge =
+            ServerPlatformChallenge::new(PLATFORM_CHALLENGE, &encryption_data).map_err(ConnectorError::encode)?;
+        self.encryption_data = Some(encryption_data);
+
+        Ok(LicensingStep::Continue(challenge.into()))
+    }
+
+    fn on_platform_challenge_response(
+        &mut self,
+        response: ClientPlatformChallengeResponse,
+    ) -> ConnectorResult<LicensingStep> {
+        let encryption_data = self.encryption_data.take().expect("platform challenge sent");
+
+        response
+            .verify_challenge_response(&encryption_data)
+            .map_err(|e| custom_err!("platform challenge response", e))?;
+
+        let response_data = response
+            .challenge_response_data(&encryption_data)
+            .map_err(ConnectorError::decode)?;
+        if response_data.challenge != PLATFORM_CHALLENGE {
+            return Err(reason_err!("LicensingExchange", "invalid platform challenge response"));
+        }
+
+        let hardware_id = response.hardware_id(&encryption_data).map_err(ConnectorError::decode)?;
+
+        let license = LicenseInformation {
+            version: PRODUCT_VERSION,
+            scope: SCOPE.to_owned(),
+            company_name: COMPANY_NAME.to_owned(),
+            product_id: PRODUCT_ID.to_owned(),
+            license_info: hardware_id.data.clone(),
+        };
+        let new_license = ServerUpgradeLicense::new(&license, &encryption_data).map_err(ConnectorError::encode)?;
+
+        self.issued.lock().unwrap().push(hardware_id.data);
+
+        Ok(LicensingStep::Complete(new_license.into()))
+    }
+
+    fn on_license_info(&mut self, info: ClientLicenseInfo) -> ConnectorResult<LicensingStep> {
+        let encryption_data = encryption_data(&info.encrypted_premaster_secret, &info.client_random);
+
+        info.verify_hardware_id(&encryption_data)
+            .map_err(|e| custom_err!("client license info", e))?;
+
+        let hardware_id = info.hardware_id(&encryption_data).map_err(ConnectorError::decode)?;
+        if info.license_info != hardware_id.data {
+            return Err(reason_err!("LicensingExchange", "license issued to another device"));
+        }
+
+        self.presented.lock().unwrap().push(hardware_id.data);
+
+        let valid_client = LicensingErrorMessage::new_valid_client().map_err(ConnectorError::encode)?;
+
+        Ok(LicensingStep::Complete(valid_client.into()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct MemoryLicenseCache {
+    licenses: Mutex<Vec<LicenseInformation>>,
+}
+
+impl LicenseCache for MemoryLicenseCache {
+    fn get_license(&self, license_info: LicenseInformation) -> ConnectorResult<Option<Vec<u8>>> {
+        let license = self
+            .licenses
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|license| {
+                license.scope == license_info.scope
+                    && license.company_name == license_info.company_name
+                    && license.product_id == license_info.product_id
+            })
+            .map(|license| license.license_info.clone());
+
+        Ok(license)
+    }
+
+    fn store_license(&self, license_info: LicenseInformation) -> ConnectorResult<()> {
+        self.licenses.lock().unwrap().push(license_info);
+        Ok(())
+    }
+}
+
+/// Returns the user data of the licensing PDU, which is the first MCS Send Data Indication sent by the server.
+fn licensing_data(server_pdus: &[Vec<u8>]) -> Vec<u8> {
+    let indication = server_pdus
+        .iter()
+        .find_map(|pdu| decode::<X224<mcs::SendDataIndication<'_>>>(pdu).ok())
+        .expect("licensing PDU");
+
+    assert_eq!(indication.0.initiator_id, 1002);
+    assert_eq!(indication.0.channel_id, 1003);
+
+    indication.0.user_data.into_owned()
+}
+
+#[test]
+fn default_licensing_sends_valid_client() {
+    let mut server_pdus = Vec::new();
+    connect_recording(
+        client_config(SERVER_DESKTOP_SIZE, 32),
+        acceptor(),
+        &mut Vec::new(),
+        &mut server_pdus,
+    )
+    .unwrap();
+
+    assert_eq!(licensing_data(&server_pdus), VALID_CLIENT_LICENSE);
+
+    let mut auto_valid_pdus = Vec::new();
+    connect_recording(
+        client_config(SERVER_DESKTOP_SIZE, 32),
+        acceptor().with_licensing_handler(AutoValid),
+        &mut Vec::new(),
+        &mut auto_valid_pdus,
+    )
+    .unwrap();
+
+    assert_eq!(auto_valid_pdus, server_pdus);
+}
+
+#[test]
+fn licensing_handler_issues_per_device_license() {
+    let license_cache = Arc::new(MemoryLicenseCache::default());
+    let issuer = CalIssuer::default();
+    let issued_devices = Arc::clone(&issuer.issued);
+    let presented_devices = Arc::clone(&issuer.presented);
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.hardware_id = Some(HARDWARE_ID);
+    config.license_cache = Some(Arc::clone(&license_cache) as Arc<dyn LicenseCache>);
+
+    connect(config, acceptor().with_licensing_handler(issuer)).unwrap();
+
+    assert_eq!(*issued_devices.lock().unwrap(), [hardware_data()]);
+    assert!(presented_devices.lock().unwrap().is_empty());
+    assert_eq!(
+        *license_cache.licenses.lock().unwrap(),
+        [LicenseInformation {
+            version: PRODUCT_VERSION,
+            scope: SCOPE.to_owned(),
+            company_name: COMPANY_NAME.to_owned(),
+            product_id: PRODUCT_ID.to_owned(),
+            license_info: hardware_data(),
+        }]
+    );
+}
+
+#[test]
+fn licensing_handler_accepts_license_presented_by_the_client() {
+    let license_cache = Arc::new(MemoryLicenseCache::default());
+    let issuer = CalIssuer::default();
+    let issued_devices = Arc::clone(&issuer.issued);
+    let presented_devices = Arc::clone(&issuer.presented);
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.hardware_id = Some(HARDWARE_ID);
+    config.license_cache = Some(Arc::clone(&license_cache) as Arc<dyn LicenseCache>);
+    connect(config, acceptor().with_licensing_handler(CalIssuer::default())).unwrap();
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.hardware_id = Some(HARDWARE_ID);
+    config.license_cache = Some(Arc::clone(&license_cache) as Arc<dyn LicenseCache>);
+    connect(config, acceptor().with_licensing_handler(issuer)).unwrap();
+
+    assert!(issued_devices.lock().unwrap().is_empty());
+    assert_eq!(*presented_devices.lock().unwrap(), [hardware_data()]);
+    assert_eq!(license_cache.licenses.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn licensing_handler_refuses_license_of_another_device() {
+    let license_cache = Arc::new(MemoryLicenseCache::default());
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.hardware_id = Some([0xdead_beef; 4]);
+    config.license_cache = Some(Arc::clone(&license_cache) as Arc<dyn LicenseCache>);
+    connect(config, acceptor().with_licensing_handler(CalIssuer::default())).unwrap();
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.hardware_id = Some(HARDWARE_ID);
+    config.license_cache = Some(license_cache);
+    let error = connect(config, acceptor().with_licensing_handler(CalIssuer::default())).unwrap_err();
+
+    assert!(matches!(error.kind(), ConnectorErrorKind::Reason(reason) if reason.contains("another device")));
+}
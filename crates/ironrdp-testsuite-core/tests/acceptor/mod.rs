@@ -0,0 +1,739 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_acceptor::{
+    Acceptor, AcceptorPolicy, AcceptorResult, AuthContext, AuthDecision, DesktopSize, SessionMetadata,
+};
+use ironrdp_connector::redirection::{RedirectionCredentials, RedirectionFlags, ServerRedirection};
+use ironrdp_connector::{
+    BitmapConfig, ClientConnector, ClientConnectorState, Config, ConnectTimeouts, ConnectionResult, ConnectorErrorKind,
+    ConnectorResult, CredentialDelegation, Credentials, RedirectSession, Sequence,
+};
+use ironrdp_core::{decode, encode_vec, WriteBuf};
+use ironrdp_pdu::gcc::KeyboardType;
+use ironrdp_pdu::mcs;
+use ironrdp_pdu::nego::{self, SecurityProtocol};
+use ironrdp_pdu::rdp::capability_sets::{
+    Bitmap, BitmapCodecs, BitmapDrawingFlags, CapabilitySet, CmdFlags, CodecProperty, MajorPlatformType,
+};
+use ironrdp_pdu::rdp::{client_info, ClientInfoPdu};
+use ironrdp_pdu::x224::{X224Data, X224};
+
+mod capabilities;
+mod channels;
+mod licensing;
+mod negotiated;
+mod time_zone;
+
+const USERNAME: &str = "user";
+const PASSWORD: &str = "password";
+
+const SERVER_DESKTOP_SIZE: DesktopSize = DesktopSize {
+    width: 1024,
+    height: 768,
+};
+
+struct ClampingPolicy {
+    max_size: DesktopSize,
+    max_color_depth: u32,
+}
+
+impl AcceptorPolicy for ClampingPolicy {
+    fn adjust_desktop_size(&self, requested: DesktopSize) -> DesktopSize {
+        DesktopSize {
+            width: requested.width.min(self.max_size.width),
+            height: requested.height.min(self.max_size.height),
+        }
+    }
+
+    fn allowed_codecs(&self, client: &BitmapCodecs) -> BitmapCodecs {
+        BitmapCodecs(
+            client
+                .0
+                .iter()
+                .filter(|codec| !matches!(codec.property, CodecProperty::RemoteFx(_)))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn color_depth(&self, requested: u32) -> u32 {
+        requested.min(self.max_color_depth)
+    }
+}
+
+/// Refuses any session below 32 bits per pixel.
+struct TrueColorOnlyPolicy;
+
+impl AcceptorPolicy for TrueColorOnlyPolicy {
+    fn color_depth(&self, requested: u32) -> u32 {
+        if requested == 32 {
+            requested
+        } else {
+            0
+        }
+    }
+}
+
+fn client_config(desktop_size: DesktopSize, color_depth: u32) -> Config {
+    Config {
+        desktop_size,
+        desktop_scale_factor: 0,
+        enable_tls: true,
+        enable_credssp: false,
+        credentials: Credentials::UsernamePassword {
+            username: USERNAME.to_owned(),
+            password: PASSWORD.to_owned(),
+        },
+        domain: None,
+        credential_delegation: CredentialDelegation::Full,
+        client_build: 0,
+        client_name: "ironrdp".to_owned(),
+        keyboard_type: KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_layout: 0,
+        active_input_locale: None,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        bitmap: Some(BitmapConfig {
+            lossy_compression: true,
+            color_depth,
+        }),
+        dig_product_id: String::new(),
+        client_dir: String::new(),
+        platform: MajorPlatformType::UNIX,
+        hardware_id: None,
+        request_data: None,
+        correlation_id: None,
+        autologon: false,
+        license_cache: None,
+        remote_app: None,
+        auto_reconnect_cookie: None,
+        frame_markers: true,
+        timeouts: ConnectTimeouts::default(),
+        bitmap_cache: None,
+        extra_capability_sets: Vec::new(),
+        decline_multitransport: true,
+        redirect_session: None,
+        no_server_pointer: true,
+        pointer_software_rendering: true,
+        performance_flags: Default::default(),
+    }
+}
+
+fn acceptor() -> Acceptor {
+    acceptor_with_security(SecurityProtocol::SSL)
+}
+
+fn acceptor_with_security(security: SecurityProtocol) -> Acceptor {
+    let capabilities = vec![CapabilitySet::Bitmap(Bitmap {
+        pref_bits_per_pix: 32,
+        desktop_width: SERVER_DESKTOP_SIZE.width,
+        desktop_height: SERVER_DESKTOP_SIZE.height,
+        desktop_resize_flag: true,
+        drawing_flags: BitmapDrawingFlags::empty(),
+    })];
+
+    let creds = client_info::Credentials {
+        username: USERNAME.to_owned(),
+        password: PASSWORD.to_owned(),
+        domain: None,
+    };
+
+    Acceptor::new(security, SERVER_DESKTOP_SIZE, capabilities, Some(creds))
+}
+
+/// Steps the sequence once if it is not waiting for more input, and returns whether it made progress.
+fn step(sequence: &mut dyn Sequence, input: &mut Vec<u8>, output: &mut Vec<u8>) -> ConnectorResult<bool> {
+    if sequence.state().is_terminal() {
+        return Ok(false);
+    }
+
+    let mut buf = WriteBuf::new();
+
+    match sequence.next_pdu_hint() {
+        Some(hint) => {
+            let Some((_, length)) = hint.find_size(input).expect("valid PDU") else {
+                return Ok(false);
+            };
+            let pdu: Vec<u8> = input.drain(..length).collect();
+            sequence.step(&pdu, &mut buf)?;
+        }
+        None => {
+            sequence.step(&[], &mut buf)?;
+        }
+    }
+
+    output.extend_from_slice(buf.filled());
+
+    Ok(true)
+}
+
+/// Runs the connection sequence in memory, the TLS upgrade being a no-op.
+fn connect(config: Config, acceptor: Acceptor) -> ConnectorResult<(ConnectionResult, AcceptorResult)> {
+    connect_recording(config, acceptor, &mut Vec::new(), &mut Vec::new())
+}
+
+/// Same as [`connect`], but also records the PDUs sent by the client and the server.
+fn connect_recording(
+    config: Config,
+    acceptor: Acceptor,
+    client_pdus: &mut Vec<Vec<u8>>,
+    server_pdus: &mut Vec<Vec<u8>>,
+) -> ConnectorResult<(ConnectionResult, AcceptorResult)> {
+    connect_intercepting(config, acceptor, client_pdus, server_pdus, |_| {})
+}
+
+/// Same as [`connect_recording`], `intercept` being able to alter the PDUs sent by the server in place.
+fn connect_intercepting(
+    config: Config,
+    acceptor: Acceptor,
+    client_pdus: &mut Vec<Vec<u8>>,
+    server_pdus: &mut Vec<Vec<u8>>,
+    intercept: impl FnMut(&mut [u8]),
+) -> ConnectorResult<(ConnectionResult, AcceptorResult)> {
+    connect_connector(
+        ClientConnector::new(config),
+        acceptor,
+        client_pdus,
+        server_pdus,
+        intercept,
+    )
+}
+
+/// Same as [`connect`], with a connector set up by the caller.
+fn connect_with(connector: ClientConnector, acceptor: Acceptor) -> ConnectorResult<(ConnectionResult, AcceptorResult)> {
+    connect_connector(connector, acceptor, &mut Vec::new(), &mut Vec::new(), |_| {})
+}
+
+fn connect_connector(
+    connector: ClientConnector,
+    mut acceptor: Acceptor,
+    client_pdus: &mut Vec<Vec<u8>>,
+    server_pdus: &mut Vec<Vec<u8>>,
+    mut intercept: impl FnMut(&mut [u8]),
+) -> ConnectorResult<(ConnectionResult, AcceptorResult)> {
+    let mut connector = connector.with_server_addr("127.0.0.1:3389".parse().unwrap());
+
+    let mut client_to_server = Vec::new();
+    let mut server_to_client = Vec::new();
+
+    while !(connector.state().is_terminal() && acceptor.state().is_terminal()) {
+        let sent = client_to_server.len();
+        let client_progress = step(&mut connector, &mut server_to_client, &mut client_to_server)?;
+        if client_to_server.len() > sent {
+            client_pdus.push(client_to_server[sent..].to_vec());
+        }
+        let sent = server_to_client.len();
+        let server_progress = step(&mut acceptor, &mut client_to_server, &mut server_to_client)?;
+        if server_to_client.len() > sent {
+            intercept(&mut server_to_client[sent..]);
+            server_pdus.push(server_to_client[sent..].to_vec());
+        }
+        assert!(client_progress || server_progress, "connection sequence is stuck");
+    }
+
+    let ClientConnectorState::Connected { result } = connector.state else {
+        unreachable!("connector is in a terminal state");
+    };
+    let acceptor_result = acceptor.get_result().expect("acceptor is in a terminal state");
+
+    Ok((result, acceptor_result))
+}
+
+#[test]
+fn no_policy_keeps_server_settings() {
+    let requested_size = DesktopSize {
+        width: 2560,
+        height: 1440,
+    };
+
+    let (client_result, server_result) = connect(client_config(requested_size, 32), acceptor()).unwrap();
+
+    assert_eq!(client_result.desktop_size, SERVER_DESKTOP_SIZE);
+    assert_eq!(client_result.negotiated_color_depth, 32);
+    assert_eq!(server_result.desktop_size, SERVER_DESKTOP_SIZE);
+    assert_eq!(server_result.color_depth, 32);
+}
+
+#[test]
+fn policy_clamps_client_settings() {
+    let policy = ClampingPolicy {
+        max_size: DesktopSize {
+            width: 1920,
+            height: 1080,
+        },
+        max_color_depth: 16,
+    };
+    let requested_size = DesktopSize {
+        width: 2560,
+        height: 1440,
+    };
+
+    let (client_result, server_result) =
+        connect(client_config(requested_size, 32), acceptor().with_policy(policy)).unwrap();
+
+    let expected_size = DesktopSize {
+        width: 1920,
+        height: 1080,
+    };
+    assert_eq!(client_result.desktop_size, expected_size);
+    assert_eq!(client_result.negotiated_color_depth, 16);
+    assert_eq!(server_result.desktop_size, expected_size);
+    assert_eq!(server_result.color_depth, 16);
+
+    let codecs = server_result
+        .capabilities
+        .iter()
+        .find_map(|cap| match cap {
+            CapabilitySet::BitmapCodecs(codecs) => Some(codecs),
+            _ => None,
+        })
+        .expect("client BitmapCodecs capability set");
+    assert!(codecs.0.is_empty());
+}
+
+#[test]
+fn policy_keeps_settings_within_limits() {
+    let policy = ClampingPolicy {
+        max_size: DesktopSize {
+            width: 1920,
+            height: 1080,
+        },
+        max_color_depth: 32,
+    };
+    let requested_size = DesktopSize {
+        width: 1280,
+        height: 720,
+    };
+
+    let (client_result, server_result) =
+        connect(client_config(requested_size, 32), acceptor().with_policy(policy)).unwrap();
+
+    assert_eq!(client_result.desktop_size, requested_size);
+    assert_eq!(server_result.desktop_size, requested_size);
+    assert_eq!(server_result.color_depth, 32);
+}
+
+#[test]
+fn policy_refuses_connection() {
+    let requested_size = DesktopSize {
+        width: 1280,
+        height: 720,
+    };
+
+    let error = connect(
+        client_config(requested_size, 24),
+        acceptor().with_policy(TrueColorOnlyPolicy),
+    )
+    .unwrap_err();
+
+    assert!(matches!(error.kind(), ConnectorErrorKind::Reason(reason) if reason.contains("color depth")));
+}
+
+#[test]
+fn client_refuses_palette_color_depth() {
+    let policy = ClampingPolicy {
+        max_size: SERVER_DESKTOP_SIZE,
+        max_color_depth: 8,
+    };
+
+    let error = connect(client_config(SERVER_DESKTOP_SIZE, 32), acceptor().with_policy(policy)).unwrap_err();
+
+    assert!(matches!(error.kind(), ConnectorErrorKind::Reason(reason) if reason.contains("8 bpp")));
+}
+
+/// Returns the user data of the Client Info PDU, which is the first MCS Send Data Request sent by the client.
+fn client_info_data(client_pdus: &[Vec<u8>]) -> Vec<u8> {
+    client_pdus
+        .iter()
+        .find_map(|pdu| decode::<X224<mcs::SendDataRequest<'_>>>(pdu).ok())
+        .map(|request| request.0.user_data.into_owned())
+        .expect("client info PDU")
+}
+
+#[test]
+fn active_input_locale_is_sent_in_client_info() {
+    let desktop_size = DesktopSize {
+        width: 1024,
+        height: 768,
+    };
+
+    let mut default_pdus = Vec::new();
+    connect_recording(
+        client_config(desktop_size, 32),
+        acceptor(),
+        &mut default_pdus,
+        &mut Vec::new(),
+    )
+    .unwrap();
+    let default_client_info = client_info_data(&default_pdus);
+
+    let mut config = client_config(desktop_size, 32);
+    config.keyboard_layout = 0xE001_0411;
+    config.active_input_locale = Some(0x0411);
+
+    let mut locale_pdus = Vec::new();
+    let (_, server_result) = connect_recording(config, acceptor(), &mut locale_pdus, &mut Vec::new()).unwrap();
+    let locale_client_info = client_info_data(&locale_pdus);
+
+    // The CodePage field follows the 4-byte basic security header.
+    assert_eq!(default_client_info[4..8], [0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(locale_client_info[4..8], [0x11, 0x04, 0x00, 0x00]);
+    assert_eq!(default_client_info[..4], locale_client_info[..4]);
+    assert_eq!(default_client_info[8..], locale_client_info[8..]);
+
+    let input = server_result
+        .capabilities
+        .iter()
+        .find_map(|cap| match cap {
+            CapabilitySet::Input(input) => Some(input),
+            _ => None,
+        })
+        .expect("client Input capability set");
+    assert_eq!(input.keyboard_layout, 0xE001_0411);
+
+    let connect_initial = locale_pdus
+        .iter()
+        .filter_map(|pdu| decode::<X224<X224Data<'_>>>(pdu).ok())
+        .find_map(|data| decode::<mcs::ConnectInitial>(&data.0.data).ok())
+        .expect("MCS Connect Initial PDU");
+    let core = &connect_initial.conference_create_request.gcc_blocks.core;
+    assert_eq!(core.keyboard_layout, 0xE001_0411);
+}
+
+#[test]
+fn frame_markers_are_advertised_when_enabled() {
+    let desktop_size = DesktopSize {
+        width: 1024,
+        height: 768,
+    };
+
+    for frame_markers in [true, false] {
+        let mut config = client_config(desktop_size, 32);
+        config.frame_markers = frame_markers;
+
+        let (client_result, server_result) = connect(config, acceptor()).unwrap();
+
+        let surface_commands = server_result
+            .capabilities
+            .iter()
+            .find_map(|cap| match cap {
+                CapabilitySet::SurfaceCommands(surface_commands) => Some(surface_commands),
+                _ => None,
+            })
+            .expect("client Surface Commands capability set");
+        let frame_acknowledge = server_result.capabilities.iter().find_map(|cap| match cap {
+            CapabilitySet::FrameAcknowledge(frame_acknowledge) => Some(frame_acknowledge),
+            _ => None,
+        });
+
+        assert_eq!(surface_commands.flags.contains(CmdFlags::FRAME_MARKER), frame_markers);
+        assert_eq!(frame_acknowledge.is_some(), frame_markers);
+        if let Some(frame_acknowledge) = frame_acknowledge {
+            assert!(frame_acknowledge.max_unacknowledged_frame_count > 0);
+        }
+
+        // The acceptor does not advertise the Frame Acknowledge Capability Set, so no acknowledgement is expected.
+        assert!(!client_result.frame_acknowledge);
+    }
+}
+
+#[test]
+fn authorizer_allows_session_with_metadata() {
+    let client_addr: SocketAddr = "192.0.2.7:50000".parse().unwrap();
+    let contexts = Arc::new(Mutex::new(Vec::new()));
+
+    let acceptor = acceptor().with_client_addr(client_addr).with_authorizer(Arc::new({
+        let contexts = Arc::clone(&contexts);
+        move |context: &AuthContext| {
+            contexts.lock().unwrap().push(context.clone());
+            AuthDecision::Allow(SessionMetadata::from([("tenant".to_owned(), "contoso".to_owned())]))
+        }
+    }));
+
+    let (_, server_result) = connect(client_config(SERVER_DESKTOP_SIZE, 32), acceptor).unwrap();
+
+    assert_eq!(
+        *contexts.lock().unwrap(),
+        [AuthContext {
+            username: USERNAME.to_owned(),
+            domain: None,
+            client_addr: Some(client_addr),
+            protocol: SecurityProtocol::SSL,
+        }]
+    );
+    assert_eq!(server_result.session_metadata["tenant"], "contoso");
+}
+
+#[test]
+fn authorizer_denies_session_without_hybrid_ex() {
+    let acceptor = acceptor().with_authorizer(Arc::new(|_: &AuthContext| {
+        AuthDecision::Deny("outside of office hours".to_owned())
+    }));
+
+    let error = connect(client_config(SERVER_DESKTOP_SIZE, 32), acceptor).unwrap_err();
+
+    assert!(matches!(error.kind(), ConnectorErrorKind::AccessDenied));
+}
+
+#[test]
+fn no_authorizer_leaves_metadata_empty() {
+    let (_, server_result) = connect(client_config(SERVER_DESKTOP_SIZE, 32), acceptor()).unwrap();
+
+    assert!(server_result.session_metadata.is_empty());
+}
+
+/// Sends the X.224 Connection Request PDU of `credential_delegation`, and answers it with `confirm`.
+fn negotiate(
+    credential_delegation: CredentialDelegation,
+    confirm: nego::ConnectionConfirm,
+) -> (nego::RequestFlags, ConnectorResult<ClientConnector>) {
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.enable_credssp = true;
+    config.credential_delegation = credential_delegation;
+
+    let mut connector = ClientConnector::new(config).with_server_addr("127.0.0.1:3389".parse().unwrap());
+
+    let mut buf = WriteBuf::new();
+    connector.step_no_input(&mut buf).unwrap();
+    let request = decode::<X224<nego::ConnectionRequest>>(buf.filled()).unwrap().0;
+
+    let confirm = encode_vec(&X224(confirm)).unwrap();
+    let result = connector.step(&confirm, &mut WriteBuf::new()).map(|_| connector);
+
+    (request.flags, result)
+}
+
+#[test]
+fn redirection_routing_token_is_sent() {
+    let mut connector = ClientConnector::new(client_config(SERVER_DESKTOP_SIZE, 32))
+        .with_server_addr("127.0.0.1:3389".parse().unwrap());
+
+    let redirection = ServerRedirection {
+        target: Some("srv.example.com".to_owned()),
+        load_balance_info: Some(b"Cookie: msts=3640205228.15629.0000\r\n".to_vec()),
+        credentials: RedirectionCredentials {
+            username: Some("redirected".to_owned()),
+            domain: Some("EXAMPLE".to_owned()),
+            password: Some("cookie".to_owned()),
+        },
+        flags: RedirectionFlags::LOAD_BALANCE_INFO,
+        session_id: 2,
+    };
+    connector.redirect(&redirection);
+
+    let mut buf = WriteBuf::new();
+    connector.step_no_input(&mut buf).unwrap();
+    let request = decode::<X224<nego::ConnectionRequest>>(buf.filled()).unwrap().0;
+
+    assert_eq!(
+        request.nego_data,
+        Some(nego::NegoRequestData::routing_token("3640205228.15629.0000".to_owned()))
+    );
+    assert_eq!(connector.config.domain.as_deref(), Some("EXAMPLE"));
+    assert_eq!(connector.config.redirect_session, Some(RedirectSession::SessionId(2)));
+    assert!(matches!(
+        &connector.config.credentials,
+        Credentials::UsernamePassword { username, password } if username == "redirected" && password == "cookie"
+    ));
+}
+
+#[test]
+fn redirection_load_balance_info_is_sent_verbatim() {
+    const TSV_URL: &[u8] = b"tsv://MS Terminal Services Plugin.1.Sessions\xff";
+
+    let mut connector = ClientConnector::new(client_config(SERVER_DESKTOP_SIZE, 32))
+        .with_server_addr("127.0.0.1:3389".parse().unwrap());
+
+    let redirection = ServerRedirection {
+        target: None,
+        load_balance_info: Some(TSV_URL.to_vec()),
+        credentials: RedirectionCredentials::default(),
+        flags: RedirectionFlags::LOAD_BALANCE_INFO,
+        session_id: 2,
+    };
+    connector.redirect(&redirection);
+
+    let mut buf = WriteBuf::new();
+    connector.step_no_input(&mut buf).unwrap();
+
+    // The token follows the TPKT (4 bytes) and X.224 Connection Request (7 bytes) headers, terminated by CR LF.
+    let mut expected = TSV_URL.to_vec();
+    expected.extend_from_slice(b"\r\n");
+    assert_eq!(&buf.filled()[11..11 + expected.len()], expected.as_slice());
+}
+
+fn hybrid_confirm(flags: nego::ResponseFlags) -> nego::ConnectionConfirm {
+    nego::ConnectionConfirm::Response {
+        flags,
+        protocol: SecurityProtocol::HYBRID,
+    }
+}
+
+#[test]
+fn credential_delegation_is_requested() {
+    let cases = [
+        (CredentialDelegation::Full, nego::RequestFlags::empty()),
+        (
+            CredentialDelegation::RestrictedAdmin,
+            nego::RequestFlags::RESTRICTED_ADMIN_MODE_REQUIRED,
+        ),
+    ];
+
+    for (credential_delegation, expected_flags) in cases {
+        let (flags, _) = negotiate(credential_delegation, hybrid_confirm(nego::ResponseFlags::empty()));
+        assert_eq!(flags, expected_flags, "{credential_delegation}");
+    }
+}
+
+#[test]
+fn credential_delegation_supported_by_server_is_accepted() {
+    let cases = [
+        (CredentialDelegation::Full, nego::ResponseFlags::empty()),
+        (
+            CredentialDelegation::RestrictedAdmin,
+            nego::ResponseFlags::RESTRICTED_ADMIN_MODE_SUPPORTED,
+        ),
+    ];
+
+    for (credential_delegation, flags) in cases {
+        let (_, result) = negotiate(credential_delegation, hybrid_confirm(flags));
+        let connector = result.unwrap();
+        assert!(matches!(
+            connector.state,
+            ClientConnectorState::EnhancedSecurityUpgrade {
+                selected_protocol: SecurityProtocol::HYBRID
+            }
+        ));
+    }
+}
+
+#[test]
+fn credential_delegation_unsupported_by_server_is_reported() {
+    let cases = [
+        // The server does not advertise the requested mode.
+        (
+            CredentialDelegation::RestrictedAdmin,
+            hybrid_confirm(nego::ResponseFlags::REDIRECTED_AUTHENTICATION_MODE_SUPPORTED),
+        ),
+        // NLA is not selected, there is no CredSSP authentication to log the user on.
+        (
+            CredentialDelegation::RestrictedAdmin,
+            nego::ConnectionConfirm::Response {
+                flags: nego::ResponseFlags::RESTRICTED_ADMIN_MODE_SUPPORTED,
+                protocol: SecurityProtocol::SSL,
+            },
+        ),
+    ];
+
+    for (credential_delegation, confirm) in cases {
+        let (_, result) = negotiate(credential_delegation, confirm);
+        let error = result.unwrap_err();
+        assert!(
+            matches!(error.kind(), ConnectorErrorKind::UnsupportedCredentialDelegation { mode } if *mode == credential_delegation),
+            "{error}"
+        );
+    }
+}
+
+/// Runs the connection sequence over NLA, the server advertising `response_flags`, and returns the Client Info PDU.
+fn nla_client_info(credential_delegation: CredentialDelegation, response_flags: nego::ResponseFlags) -> ClientInfoPdu {
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.enable_tls = false;
+    config.enable_credssp = true;
+    config.credential_delegation = credential_delegation;
+
+    // The acceptor does not support the restricted modes, the flags are added to its X.224 Connection Confirm PDU.
+    let advertise_flags = |pdu: &mut [u8]| {
+        if let Ok(X224(nego::ConnectionConfirm::Response { protocol, .. })) =
+            decode::<X224<nego::ConnectionConfirm>>(pdu)
+        {
+            let confirm = nego::ConnectionConfirm::Response {
+                flags: response_flags,
+                protocol,
+            };
+            pdu.copy_from_slice(&encode_vec(&X224(confirm)).unwrap());
+        }
+    };
+
+    let mut client_pdus = Vec::new();
+    connect_intercepting(
+        config,
+        acceptor_with_security(SecurityProtocol::HYBRID),
+        &mut client_pdus,
+        &mut Vec::new(),
+        advertise_flags,
+    )
+    .unwrap();
+
+    decode(&client_info_data(&client_pdus)).unwrap()
+}
+
+#[test]
+fn full_credential_delegation_sends_password_in_client_info() {
+    let client_info = nla_client_info(CredentialDelegation::Full, nego::ResponseFlags::empty());
+
+    assert_eq!(client_info.client_info.credentials.username, USERNAME);
+    assert_eq!(client_info.client_info.credentials.password, PASSWORD);
+}
+
+#[test]
+fn restricted_admin_does_not_send_password_in_client_info() {
+    let client_info = nla_client_info(
+        CredentialDelegation::RestrictedAdmin,
+        nego::ResponseFlags::RESTRICTED_ADMIN_MODE_SUPPORTED,
+    );
+
+    assert_eq!(client_info.client_info.credentials.username, USERNAME);
+    assert!(client_info.client_info.credentials.password.is_empty());
+}
+
+#[test]
+fn negotiation_failure_code_is_reported() {
+    let confirm = nego::ConnectionConfirm::Failure {
+        code: nego::FailureCode::HYBRID_REQUIRED_BY_SERVER,
+    };
+
+    let (_, result) = negotiate(CredentialDelegation::Full, confirm);
+    let error = result.unwrap_err();
+
+    assert!(
+        matches!(
+            error.kind(),
+            ConnectorErrorKind::NegotiationFailure(nego::NegotiationFailureCode::HybridRequiredByServer)
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn correlation_id_is_sent_in_request() {
+    let correlation_id = *b"\x01correlation-id!";
+
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.correlation_id = Some(correlation_id);
+
+    let mut connector = ClientConnector::new(config.clone()).with_server_addr("127.0.0.1:3389".parse().unwrap());
+    let mut buf = WriteBuf::new();
+    connector.step_no_input(&mut buf).unwrap();
+    let request = decode::<X224<nego::ConnectionRequest>>(buf.filled()).unwrap().0;
+
+    assert!(request.flags.contains(nego::RequestFlags::CORRELATION_INFO_PRESENT));
+    assert_eq!(request.correlation_info, Some(nego::CorrelationInfo { correlation_id }));
+
+    connect(config, acceptor()).unwrap();
+}
+
+#[test]
+fn invalid_correlation_id_is_rejected() {
+    for correlation_id in [[0x00; 16], [0xF4; 16], *b"\x01correlation\rid!"] {
+        let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+        config.correlation_id = Some(correlation_id);
+
+        let mut connector = ClientConnector::new(config).with_server_addr("127.0.0.1:3389".parse().unwrap());
+        let error = connector.step_no_input(&mut WriteBuf::new()).unwrap_err();
+
+        assert!(matches!(error.kind(), ConnectorErrorKind::Reason(_)), "{error}");
+    }
+}
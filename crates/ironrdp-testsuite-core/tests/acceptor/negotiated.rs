@@ -0,0 +1,142 @@
+use ironrdp_acceptor::{ClientTimeZone, DesktopSize, IanaTimeZone, NegotiatedChannel};
+use ironrdp_connector::{ClientConnector, RedirectSession};
+use ironrdp_core::AsAny;
+use ironrdp_pdu::gcc::{ChannelName, ChannelOptions, ClientClusterData, KeyboardType, RedirectionFlags};
+use ironrdp_pdu::rdp::capability_sets::CapabilitySet;
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::{CompressionCondition, SvcClientProcessor, SvcMessage, SvcProcessor, SvcServerProcessor};
+use ironrdp_testsuite_core::cluster_data::{CLUSTER_DATA, CONSOLE_CLUSTER_DATA, SESSION_ID_CLUSTER_DATA};
+use rstest::rstest;
+
+use super::{acceptor, client_config, connect, connect_with, ClampingPolicy, SERVER_DESKTOP_SIZE};
+
+/// Static channel named `cliprdr`, always compressed and ignoring its payloads.
+#[derive(Debug)]
+struct Cliprdr;
+
+impl AsAny for Cliprdr {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
+
+impl SvcProcessor for Cliprdr {
+    fn channel_name(&self) -> ChannelName {
+        ChannelName::from_static(b"cliprdr\0")
+    }
+
+    fn compression_condition(&self) -> CompressionCondition {
+        CompressionCondition::Always
+    }
+
+    fn process(&mut self, _payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        Ok(Vec::new())
+    }
+}
+
+impl SvcClientProcessor for Cliprdr {}
+
+impl SvcServerProcessor for Cliprdr {}
+
+#[test]
+fn negotiated_session_is_populated() {
+    let policy = ClampingPolicy {
+        max_size: DesktopSize {
+            width: 1280,
+            height: 720,
+        },
+        max_color_depth: 16,
+    };
+    let requested_size = DesktopSize {
+        width: 1920,
+        height: 1080,
+    };
+
+    let mut config = client_config(requested_size, 32);
+    config.client_name = "negotiated".to_owned();
+
+    let mut acceptor = acceptor().with_policy(policy);
+    acceptor.attach_static_channel(Cliprdr);
+
+    let connector = ClientConnector::new(config).with_static_channel(Cliprdr);
+    let (_, server_result) = connect_with(connector, acceptor).unwrap();
+    let negotiated = server_result.negotiated;
+
+    let core = &negotiated.client_core;
+    assert_eq!(core.client_name, "negotiated");
+    assert_eq!((core.desktop_width, core.desktop_height), (1920, 1080));
+    assert_eq!(core.keyboard_type, KeyboardType::IbmEnhanced);
+    assert_eq!(core.keyboard_functional_keys_count, 12);
+    // The connector advertises its support of the server redirection.
+    assert_eq!(
+        negotiated.client_cluster.map(|cluster| cluster.flags),
+        Some(RedirectionFlags::REDIRECTION_SUPPORTED)
+    );
+    assert!(negotiated.client_time_zone.is_some());
+    // The connector sends UTC.
+    assert_eq!(
+        server_result
+            .environment
+            .time_zone
+            .as_ref()
+            .map(ClientTimeZone::to_iana),
+        Some(IanaTimeZone::Name("Etc/UTC"))
+    );
+
+    assert_eq!(negotiated.client_capabilities, server_result.capabilities);
+    assert!(negotiated.server_capabilities.iter().any(|cap| matches!(
+        cap,
+        CapabilitySet::Bitmap(bitmap)
+            if (bitmap.desktop_width, bitmap.desktop_height, bitmap.pref_bits_per_pix) == (1280, 720, 16)
+    )));
+
+    let expected_size = DesktopSize {
+        width: 1280,
+        height: 720,
+    };
+    assert_eq!(negotiated.desktop_size, expected_size);
+    assert_eq!(negotiated.color_depth, 16);
+
+    assert_eq!(
+        negotiated.static_channels,
+        [NegotiatedChannel {
+            name: Cliprdr.channel_name(),
+            id: server_result.static_channel_ids[0].1,
+            options: ChannelOptions::COMPRESS,
+        }]
+    );
+}
+
+#[test]
+fn unknown_capability_sets_are_kept_raw() {
+    let unknown = CapabilitySet::Unknown {
+        capability_set_type: 0x7f,
+        data: vec![0x01, 0x02, 0x03],
+    };
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.extra_capability_sets = vec![unknown.clone()];
+
+    let (_, server_result) = connect(config, acceptor()).unwrap();
+
+    assert!(server_result.negotiated.client_capabilities.contains(&unknown));
+}
+
+#[rstest]
+#[case::new_session(None, &CLUSTER_DATA)]
+#[case::console(Some(RedirectSession::Console), &CONSOLE_CLUSTER_DATA)]
+#[case::session_id(Some(RedirectSession::SessionId(3)), &SESSION_ID_CLUSTER_DATA)]
+fn redirect_session_is_sent_in_cluster_data(
+    #[case] redirect_session: Option<RedirectSession>,
+    #[case] expected: &ClientClusterData,
+) {
+    let mut config = client_config(SERVER_DESKTOP_SIZE, 32);
+    config.redirect_session = redirect_session;
+
+    let (_, server_result) = connect(config, acceptor()).unwrap();
+
+    assert_eq!(server_result.negotiated.client_cluster.as_ref(), Some(expected));
+}
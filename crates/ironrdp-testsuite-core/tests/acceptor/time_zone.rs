@@ -0,0 +1,130 @@
+use ironrdp_acceptor::{windows_to_iana, ClientEnvironment, ClientTimeZone, IanaTimeZone};
+use ironrdp_pdu::rdp::client_info::{
+    DayOfWeek, DayOfWeekOccurrence, DynamicTimeZone, Month, OptionalSystemTime, SystemTime, TimezoneInfo,
+};
+use ironrdp_testsuite_core::client_info::{CLIENT_INFO_ANSI, CLIENT_INFO_UNICODE};
+use rstest::rstest;
+
+fn time_zone(bias: i32, standard_name: &str, daylight_saving: bool) -> TimezoneInfo {
+    let transition = |month| {
+        OptionalSystemTime(daylight_saving.then_some(SystemTime {
+            month,
+            day_of_week: DayOfWeek::Sunday,
+            day: DayOfWeekOccurrence::Last,
+            hour: 2,
+            minute: 0,
+            second: 0,
+            milliseconds: 0,
+        }))
+    };
+
+    TimezoneInfo {
+        bias: u32::from_le_bytes(bias.to_le_bytes()),
+        standard_name: standard_name.to_owned(),
+        standard_date: transition(Month::October),
+        standard_bias: 0,
+        daylight_name: String::new(),
+        daylight_date: transition(Month::March),
+        daylight_bias: if daylight_saving { 0xffff_ffc4 } else { 0 },
+    }
+}
+
+fn dynamic_time_zone(key_name: &str) -> DynamicTimeZone {
+    DynamicTimeZone {
+        key_name: key_name.to_owned(),
+        dynamic_daylight_time_disabled: false,
+    }
+}
+
+#[rstest]
+#[case("Pacific Standard Time", "America/Los_Angeles")]
+#[case("Pacific Standard Time (Mexico)", "America/Tijuana")]
+#[case("Eastern Standard Time", "America/New_York")]
+#[case("GMT Standard Time", "Europe/London")]
+#[case("Romance Standard Time", "Europe/Paris")]
+#[case("W. Europe Standard Time", "Europe/Berlin")]
+#[case("India Standard Time", "Asia/Kolkata")]
+#[case("Tokyo Standard Time", "Asia/Tokyo")]
+#[case("AUS Eastern Standard Time", "Australia/Sydney")]
+#[case("New Zealand Standard Time", "Pacific/Auckland")]
+#[case("E. South America Standard Time", "America/Sao_Paulo")]
+#[case("UTC", "Etc/UTC")]
+fn windows_time_zone_is_mapped(#[case] windows_name: &str, #[case] iana_name: &'static str) {
+    assert_eq!(windows_to_iana(windows_name), Some(iana_name));
+}
+
+#[rstest]
+#[case::unknown("Mars Standard Time")]
+#[case::case_sensitive("pacific standard time")]
+#[case::empty("")]
+fn windows_time_zone_is_not_mapped(#[case] windows_name: &str) {
+    assert_eq!(windows_to_iana(windows_name), None);
+}
+
+#[test]
+fn key_name_is_preferred_over_the_localized_name() {
+    let time_zone = ClientTimeZone::new(
+        time_zone(-60, "Paris, Madrid (heure d’été)", true),
+        Some(&dynamic_time_zone("Romance Standard Time")),
+    );
+
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Name("Europe/Paris"));
+}
+
+#[test]
+fn standard_name_is_used_without_key_name() {
+    let time_zone = ClientTimeZone::new(time_zone(-600, "AUS Eastern Standard Time", true), None);
+
+    assert_eq!(time_zone.key_name, None);
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Name("Australia/Sydney"));
+
+    // An empty key name is not sent.
+    let time_zone = ClientTimeZone::new(
+        self::time_zone(-600, "AUS Eastern Standard Time", true),
+        Some(&dynamic_time_zone("")),
+    );
+    assert_eq!(time_zone.key_name, None);
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Name("Australia/Sydney"));
+}
+
+#[rstest]
+#[case::utc(0, "Etc/UTC")]
+#[case::west(300, "Etc/GMT+5")]
+#[case::east(-540, "Etc/GMT-9")]
+#[case::dateline(720, "Etc/GMT+12")]
+#[case::line_islands(-840, "Etc/GMT-14")]
+fn fixed_offset_is_mapped_to_etc_gmt(#[case] bias: i32, #[case] iana_name: &'static str) {
+    let time_zone = ClientTimeZone::new(time_zone(bias, "Zone inconnue", false), None);
+
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Name(iana_name));
+}
+
+#[rstest]
+#[case::daylight_saving_time(time_zone(-60, "Zone inconnue", true))]
+#[case::half_hour_offset(time_zone(-330, "Zone inconnue", false))]
+#[case::out_of_range_offset(time_zone(-900, "Zone inconnue", false))]
+fn unknown_time_zone_is_unmapped(#[case] info: TimezoneInfo) {
+    let time_zone = ClientTimeZone::new(info.clone(), None);
+
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Unmapped(&info));
+}
+
+#[test]
+fn environment_from_client_info() {
+    let environment = ClientEnvironment::from_client_info(&CLIENT_INFO_UNICODE);
+
+    assert_eq!(environment.language_id, Some(0x0409));
+    let time_zone = environment.time_zone.unwrap();
+    assert_eq!(time_zone.info.standard_name, "Pacific Standard Time");
+    assert_eq!(time_zone.key_name, None);
+    assert!(!time_zone.dynamic_daylight_time_disabled);
+    assert_eq!(time_zone.to_iana(), IanaTimeZone::Name("America/Los_Angeles"));
+}
+
+#[test]
+fn language_is_unknown_without_unicode() {
+    let environment = ClientEnvironment::from_client_info(&CLIENT_INFO_ANSI);
+
+    assert_eq!(environment.language_id, None);
+    assert!(environment.time_zone.is_some());
+}
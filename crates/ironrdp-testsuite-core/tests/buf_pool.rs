@@ -0,0 +1,198 @@
+use ironrdp_core::{ensure_size, BufPool, Encode, EncodeResult, WriteCursor};
+use ironrdp_displaycontrol::client::DisplayControlClient;
+use ironrdp_displaycontrol::pdu::{DisplayControlMonitorLayout, DisplayControlPdu};
+use ironrdp_dvc::{encode_dvc_messages, encode_dvc_messages_pooled, DvcEncode, DvcMessage};
+use ironrdp_svc::{ChannelFlags, StaticVirtualChannel, SvcMessage};
+
+use crate::allocations::count_allocations;
+
+struct Payload(Vec<u8>);
+
+impl Encode for Payload {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.0.len());
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Payload"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl DvcEncode for Payload {}
+
+fn dvc_messages(payloads: &[&[u8]]) -> Vec<DvcMessage> {
+    payloads
+        .iter()
+        .map(|payload| Box::new(Payload(payload.to_vec())) as DvcMessage)
+        .collect()
+}
+
+fn encoded_chunks(messages: Vec<SvcMessage>) -> Vec<Vec<u8>> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| chunk.filled().to_vec())
+        .collect()
+}
+
+#[test]
+fn reused_buffer_is_wiped() {
+    let pool = BufPool::new();
+
+    let mut buf = pool.get();
+    buf.write_slice(&[0xAA; 64]);
+    drop(buf);
+
+    assert_eq!(pool.idle_count(), 1);
+
+    let mut buf = pool.get();
+    assert_eq!(buf.filled_len(), 0);
+    assert!(buf.unfilled_to(64).iter().all(|byte| *byte == 0));
+    assert_eq!(pool.created_count(), 1);
+}
+
+#[test]
+fn large_buffers_are_not_retained() {
+    let pool = BufPool::with_max_retained_capacity(1024);
+
+    let mut large = pool.get();
+    large.write_slice(&[0xAA; 4096]);
+    let mut small = pool.get();
+    small.write_slice(&[0x55; 16]);
+
+    drop(large);
+    drop(small);
+
+    assert_eq!(pool.idle_count(), 1);
+    assert!(pool.retained_capacity() <= pool.max_retained_capacity());
+}
+
+#[test]
+fn detached_buffer_leaves_the_pool() {
+    let pool = BufPool::new();
+
+    let mut buf = pool.get();
+    buf.write_slice(b"detached");
+    let buf = buf.detach();
+
+    assert_eq!(buf.filled(), b"detached");
+    assert_eq!(pool.idle_count(), 0);
+    assert_eq!(pool.retained_capacity(), 0);
+}
+
+#[test]
+fn svc_encoding_reuses_the_channel_buffers() {
+    let channel = StaticVirtualChannel::new(ironrdp_dvc::DrdynvcClient::new());
+
+    // Three chunks, the legacy `Vec<u8>` PDU being chunkified without being copied in a buffer first.
+    let message = || vec![SvcMessage::from(vec![0xAB; 4000])];
+
+    let messages = message();
+    let (expected, unpooled) =
+        count_allocations(|| ironrdp_svc::client_encode_svc_messages(messages, 1004, 1002).unwrap());
+
+    // Warms up the pool of the channel.
+    drop(channel.client_encode(message(), 1004, 1002).unwrap());
+
+    for _ in 0..10 {
+        let messages = message();
+        let (encoded, pooled) = count_allocations(|| channel.client_encode(messages, 1004, 1002).unwrap());
+        assert_eq!(encoded, expected);
+
+        // The buffers of the three chunks are not allocated anymore.
+        assert!(
+            pooled + 3 <= unpooled,
+            "{pooled} allocations, {unpooled} without the pool"
+        );
+    }
+
+    assert_eq!(channel.buf_pool().created_count(), 3);
+}
+
+#[test]
+fn pooled_chunks_do_not_leak_prior_contents() {
+    let channel = StaticVirtualChannel::new(ironrdp_dvc::DrdynvcClient::new());
+
+    drop(
+        channel
+            .chunkify_pooled(vec![SvcMessage::from(vec![0xAA; 4000])])
+            .unwrap(),
+    );
+
+    let messages = || vec![SvcMessage::from(vec![0x55; 10]), SvcMessage::from(vec![0x66; 1])];
+
+    let pooled = channel
+        .chunkify_pooled(messages())
+        .unwrap()
+        .iter()
+        .map(|chunk| chunk.filled().to_vec())
+        .collect::<Vec<_>>();
+
+    assert_eq!(pooled, encoded_chunks(messages()));
+    assert!(pooled.iter().flatten().all(|byte| *byte != 0xAA));
+}
+
+#[test]
+fn dvc_encoding_reuses_the_pool_buffers() {
+    let pool = BufPool::new();
+    let payloads: [&[u8]; 3] = [&[0xAA; 2000], b"short", &[0x55; 100]];
+
+    let messages = dvc_messages(&payloads);
+    let (expected, unpooled) = count_allocations(|| encode_dvc_messages(7, messages, ChannelFlags::empty()).unwrap());
+    let expected = encoded_chunks(expected);
+
+    // Warms up the pool.
+    drop(encode_dvc_messages_pooled(&pool, 7, dvc_messages(&payloads), ChannelFlags::empty()).unwrap());
+
+    for _ in 0..10 {
+        let messages = dvc_messages(&payloads);
+        let (encoded, pooled) =
+            count_allocations(|| encode_dvc_messages_pooled(&pool, 7, messages, ChannelFlags::empty()).unwrap());
+        assert_eq!(encoded_chunks(encoded), expected);
+
+        // The buffer in which the messages are encoded is not allocated anymore.
+        assert!(pooled < unpooled, "{pooled} allocations, {unpooled} without the pool");
+    }
+
+    assert_eq!(pool.created_count(), 1);
+}
+
+#[test]
+fn display_control_reuses_its_buffers() {
+    let client = DisplayControlClient::new(|_| Ok(Vec::new()));
+
+    let layout = || -> DvcMessage {
+        let pdu: DisplayControlPdu = DisplayControlMonitorLayout::new_single_primary_monitor(1920, 1080, None, None)
+            .unwrap()
+            .into();
+        Box::new(pdu)
+    };
+
+    let message = layout();
+    let (expected, unpooled) =
+        count_allocations(|| encode_dvc_messages(7, vec![message], ChannelFlags::empty()).unwrap());
+    let expected = encoded_chunks(expected);
+
+    // Warms up the pool of the client.
+    drop(client.encode_single_primary_monitor(7, 1920, 1080, None, None).unwrap());
+
+    for _ in 0..10 {
+        // Building the PDU is part of the pooled path, and allocates as much as `layout`.
+        let (encoded, pooled) =
+            count_allocations(|| client.encode_single_primary_monitor(7, 1920, 1080, None, None).unwrap());
+        assert_eq!(encoded_chunks(encoded), expected);
+
+        let (_, layout_allocations) = count_allocations(layout);
+        assert!(
+            pooled < unpooled + layout_allocations,
+            "{pooled} allocations, {} without the pool",
+            unpooled + layout_allocations
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use ironrdp_bulk::mppc::{HistorySize, MppcCompressor, MppcDecompressor};
+use ironrdp_bulk::{DecompressError, PacketFlags};
+
+const WORDS: [&str; 8] = [
+    "clipboard",
+    "format",
+    "data",
+    "response",
+    "request",
+    "list",
+    "file",
+    "contents",
+];
+
+/// Generates pseudo-random, compressible, text.
+fn text(seed: u32, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    let mut text = Vec::with_capacity(len);
+
+    while text.len() < len {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+
+        text.extend_from_slice(WORDS[usize::try_from(state % 8).unwrap()].as_bytes());
+        text.push(if state % 5 == 0 { 0xA0 } else { b' ' });
+    }
+
+    text.truncate(len);
+    text
+}
+
+/// Compresses then decompresses each packet, returning the flags of each packet.
+fn round_trip(history_size: HistorySize, packets: &[Vec<u8>]) -> Vec<PacketFlags> {
+    let mut compressor = MppcCompressor::new(history_size);
+    let mut decompressor = MppcDecompressor::new(history_size);
+
+    packets
+        .iter()
+        .map(|packet| {
+            let mut compressed = Vec::new();
+            let flags = compressor.compress(packet, &mut compressed);
+
+            let mut decompressed = Vec::new();
+            decompressor.decompress(&compressed, flags, &mut decompressed).unwrap();
+            assert_eq!(decompressed, *packet);
+
+            flags
+        })
+        .collect()
+}
+
+#[test]
+fn copy_tokens_are_encoded_as_specified() {
+    let mut compressed = Vec::new();
+    let flags = MppcCompressor::new(HistorySize::K64).compress(b"abcabcabc", &mut compressed);
+
+    // Three literals, then a copy-offset of 3 ("11111" + "000011") and a length-of-match of 6 ("10" + "10").
+    assert_eq!(flags, PacketFlags::COMPRESSED);
+    assert_eq!(compressed, [0x61, 0x62, 0x63, 0xF8, 0x74]);
+
+    let mut compressed = Vec::new();
+    let flags = MppcCompressor::new(HistorySize::K8).compress(b"abcabcabc", &mut compressed);
+
+    // The copy-offset is encoded as "1111" + "000011" by the RDP 4.0 compressor.
+    assert_eq!(flags, PacketFlags::COMPRESSED);
+    assert_eq!(compressed, [0x61, 0x62, 0x63, 0xF0, 0xE8]);
+}
+
+#[test]
+fn packets_round_trip() {
+    for history_size in [HistorySize::K8, HistorySize::K64] {
+        let packets: Vec<_> = (1..20).map(|seed| text(seed, 1600)).collect();
+
+        let flags = round_trip(history_size, &packets);
+
+        assert!(flags.iter().all(|flags| flags.contains(PacketFlags::COMPRESSED)));
+    }
+}
+
+#[test]
+fn history_restarts_at_front_when_full() {
+    let packets: Vec<_> = (1..100).map(|seed| text(seed, 1600)).collect();
+
+    let flags = round_trip(HistorySize::K8, &packets);
+
+    assert!(flags.iter().any(|flags| flags.contains(PacketFlags::AT_FRONT)));
+}
+
+#[test]
+fn long_matches_round_trip() {
+    let packets = [vec![0x55; 8191], vec![0xAA; 60_000], text(1, 65_536)];
+
+    round_trip(HistorySize::K8, &packets[..1]);
+    round_trip(HistorySize::K64, &packets);
+}
+
+#[test]
+fn incompressible_data_is_sent_as_is() {
+    // Bytes which are all different, without any repeated 3-byte sequence.
+    let incompressible: Vec<u8> = (0..=255).collect();
+    let packets = [text(1, 1000), incompressible.clone(), text(1, 1000)];
+
+    let flags = round_trip(HistorySize::K64, &packets);
+    assert_eq!(
+        flags,
+        [PacketFlags::COMPRESSED, PacketFlags::FLUSHED, PacketFlags::COMPRESSED]
+    );
+
+    let mut compressed = Vec::new();
+    MppcCompressor::new(HistorySize::K64).compress(&incompressible, &mut compressed);
+    assert_eq!(compressed, incompressible);
+}
+
+#[test]
+fn truncated_data_is_rejected() {
+    let mut decompressor = MppcDecompressor::new(HistorySize::K64);
+
+    // Copy-offset prefix, without the offset.
+    let result = decompressor.decompress(&[0xFF], PacketFlags::COMPRESSED, &mut Vec::new());
+
+    assert_eq!(result, Err(DecompressError::UnexpectedEnd));
+}
+
+#[test]
+fn copy_before_history_start_is_rejected() {
+    let mut decompressor = MppcDecompressor::new(HistorySize::K64);
+
+    // Copy-offset of 3 and length-of-match of 3, without any previous data.
+    let result = decompressor.decompress(&[0xF8, 0x60], PacketFlags::COMPRESSED, &mut Vec::new());
+
+    assert_eq!(
+        result,
+        Err(DecompressError::InvalidCopyOffset { offset: 3, position: 0 })
+    );
+}
+
+#[test]
+fn flushed_packet_resets_the_history() {
+    let mut compressor = MppcCompressor::new(HistorySize::K64);
+    let mut compressed = Vec::new();
+    let flags = compressor.compress(b"abcdef abcdef abcdef", &mut compressed);
+
+    let mut decompressor = MppcDecompressor::new(HistorySize::K64);
+    let mut decompressed = Vec::new();
+    decompressor
+        .decompress(b"abc", PacketFlags::empty(), &mut decompressed)
+        .unwrap();
+    decompressor
+        .decompress(&compressed, flags | PacketFlags::FLUSHED, &mut decompressed)
+        .unwrap();
+
+    assert_eq!(decompressed, b"abcabcdef abcdef abcdef");
+}
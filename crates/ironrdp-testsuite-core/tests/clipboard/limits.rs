@@ -0,0 +1,189 @@
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatId, ClipboardFormatName, ClipboardGeneralCapabilityFlags, ClipboardPdu,
+    FileContentsRequest, FileContentsResponse, FormatDataRequest, FormatDataResponse, FormatList, FormatListResponse,
+    LockDataId,
+};
+use ironrdp_cliprdr::{CliprdrClient, CliprdrLimits};
+use ironrdp_core::impl_as_any;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage};
+
+const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+const FORMAT_DATA_RESPONSE_HEADER_SIZE: usize = 8;
+
+const LIMITS: CliprdrLimits = CliprdrLimits {
+    max_format_list_entries: 8,
+    max_format_name_len: 16,
+    max_format_data_size: 4000,
+};
+
+#[derive(Debug, Default)]
+struct RecordingBackend {
+    remote_copies: Vec<Vec<ClipboardFormat>>,
+    format_data_sizes: Vec<usize>,
+}
+
+impl_as_any!(RecordingBackend);
+
+impl CliprdrBackend for RecordingBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_request_format_list(&mut self) {}
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        self.remote_copies.push(available_formats.to_vec());
+    }
+
+    fn on_format_data_request(&mut self, _format: FormatDataRequest) {}
+
+    fn on_format_data_response(&mut self, response: FormatDataResponse<'_>) {
+        self.format_data_sizes.push(response.data().len());
+    }
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {}
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {}
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+/// Returns a clipboard channel in the ready state, with the test limits.
+fn ready_channel() -> StaticVirtualChannel {
+    let mut cliprdr = CliprdrClient::new(Box::new(RecordingBackend::default())).with_limits(LIMITS);
+
+    cliprdr.initiate_copy(&[]).unwrap();
+    let mut channel = StaticVirtualChannel::new(cliprdr);
+    assert!(send(&mut channel, &ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).is_empty());
+
+    channel
+}
+
+/// Sends a PDU on the channel, chunk by chunk, and returns the clipboard PDUs sent back.
+fn send(channel: &mut StaticVirtualChannel, pdu: &ClipboardPdu<'_>) -> Vec<Vec<u8>> {
+    let message = SvcMessage::from_encoded(ironrdp_core::encode_vec(pdu).unwrap());
+
+    let mut responses = Vec::new();
+    for chunk in StaticVirtualChannel::chunkify(vec![message]).unwrap() {
+        responses.extend(channel.process(chunk.filled()).unwrap());
+    }
+
+    StaticVirtualChannel::chunkify(responses)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| chunk.filled()[CHANNEL_PDU_HEADER_SIZE..].to_vec())
+        .collect()
+}
+
+fn backend(channel: &StaticVirtualChannel) -> &RecordingBackend {
+    channel
+        .channel_processor_downcast_ref::<CliprdrClient>()
+        .and_then(|cliprdr| cliprdr.downcast_backend::<RecordingBackend>())
+        .unwrap()
+}
+
+fn is_ready(channel: &StaticVirtualChannel) -> bool {
+    let cliprdr = channel.channel_processor_downcast_ref::<CliprdrClient>().unwrap();
+    let messages: Vec<SvcMessage> = cliprdr
+        .initiate_paste(ClipboardFormatId::CF_UNICODETEXT)
+        .unwrap()
+        .into();
+
+    !messages.is_empty()
+}
+
+fn format_list_response(response: FormatListResponse) -> Vec<u8> {
+    ironrdp_core::encode_vec(&ClipboardPdu::FormatListResponse(response)).unwrap()
+}
+
+fn format_list(count: usize, name: &str) -> ClipboardPdu<'static> {
+    let formats: Vec<_> = (0..count)
+        .map(|i| {
+            ClipboardFormat::new(ClipboardFormatId::new(0xc000 + u32::try_from(i).unwrap()))
+                .with_name(ClipboardFormatName::new(name.to_owned()))
+        })
+        .collect();
+
+    ClipboardPdu::FormatList(FormatList::new_unicode(&formats, true).unwrap())
+}
+
+/// Returns a format data response PDU of `size` bytes.
+fn format_data_response(size: usize) -> ClipboardPdu<'static> {
+    let data = vec![0x42; size - FORMAT_DATA_RESPONSE_HEADER_SIZE];
+
+    ClipboardPdu::FormatDataResponse(FormatDataResponse::new_data(data))
+}
+
+#[test]
+fn format_list_entries_limit() {
+    let mut channel = ready_channel();
+
+    let responses = send(&mut channel, &format_list(LIMITS.max_format_list_entries, "Format"));
+    assert_eq!(responses, [format_list_response(FormatListResponse::Ok)]);
+
+    let responses = send(&mut channel, &format_list(LIMITS.max_format_list_entries + 1, "Format"));
+    assert_eq!(responses, [format_list_response(FormatListResponse::Fail)]);
+
+    let remote_copies = &backend(&channel).remote_copies;
+    assert_eq!(remote_copies.len(), 1);
+    assert_eq!(remote_copies[0].len(), LIMITS.max_format_list_entries);
+}
+
+#[test]
+fn format_name_length_limit() {
+    let mut channel = ready_channel();
+
+    let name = "é".repeat(LIMITS.max_format_name_len);
+    let responses = send(&mut channel, &format_list(1, &name));
+    assert_eq!(responses, [format_list_response(FormatListResponse::Ok)]);
+
+    let name = "é".repeat(LIMITS.max_format_name_len + 1);
+    let responses = send(&mut channel, &format_list(1, &name));
+    assert_eq!(responses, [format_list_response(FormatListResponse::Fail)]);
+
+    assert_eq!(backend(&channel).remote_copies.len(), 1);
+}
+
+#[test]
+fn format_data_size_limit() {
+    let mut channel = ready_channel();
+
+    assert!(send(&mut channel, &format_data_response(LIMITS.max_format_data_size)).is_empty());
+    assert_eq!(
+        backend(&channel).format_data_sizes,
+        [LIMITS.max_format_data_size - FORMAT_DATA_RESPONSE_HEADER_SIZE]
+    );
+    assert!(is_ready(&channel));
+
+    // The oversized PDU is chunked, and all its chunks are discarded.
+    assert!(send(&mut channel, &format_data_response(LIMITS.max_format_data_size + 1)).is_empty());
+    assert_eq!(backend(&channel).format_data_sizes.len(), 1);
+    assert!(!is_ready(&channel));
+
+    // The failed channel ignores the subsequent PDUs.
+    assert!(send(&mut channel, &format_list(1, "Format")).is_empty());
+    assert!(backend(&channel).remote_copies.is_empty());
+}
+
+#[test]
+fn oversized_pdu_is_rejected_on_first_chunk() {
+    let mut channel = ready_channel();
+
+    let message = SvcMessage::from_encoded(
+        ironrdp_core::encode_vec(&format_data_response(LIMITS.max_format_data_size + 1)).unwrap(),
+    );
+    let chunks = StaticVirtualChannel::chunkify(vec![message]).unwrap();
+    assert!(chunks.len() > 1);
+
+    channel.process(chunks[0].filled()).unwrap();
+    assert!(!is_ready(&channel));
+}
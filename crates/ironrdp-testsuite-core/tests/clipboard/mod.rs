@@ -1,13 +1,17 @@
 mod format;
+mod limits;
+mod policy;
+mod reannounce;
+mod transfer;
 
 use expect_test::expect;
 use ironrdp_cliprdr::pdu::{
-    Capabilities, CapabilitySet, ClipboardFormat, ClipboardFormatId, ClipboardFormatName,
-    ClipboardGeneralCapabilityFlags, ClipboardPdu, ClipboardProtocolVersion, FileContentsFlags, FileContentsRequest,
-    FileContentsResponse, FormatDataRequest, FormatDataResponse, FormatList, FormatListResponse, GeneralCapabilitySet,
-    LockDataId, PackedMetafileMappingMode,
+    Capabilities, CapabilitySet, ClipboardFileAttributes, ClipboardFormat, ClipboardFormatId, ClipboardFormatName,
+    ClipboardGeneralCapabilityFlags, ClipboardPalette, ClipboardPdu, ClipboardProtocolVersion, FileContentsFlags,
+    FileContentsRequest, FileContentsResponse, FileDescriptor, FormatDataRequest, FormatDataResponse, FormatList,
+    FormatListResponse, GeneralCapabilitySet, LockDataId, PackedFileList, PackedMetafileMappingMode, PaletteEntry,
 };
-use ironrdp_testsuite_core::encode_decode_test;
+use ironrdp_testsuite_core::{encode_decode_test, encoded_size_test, round_trip_test};
 
 // Test blobs from [MS-RDPECLIP]
 encode_decode_test! {
@@ -443,3 +447,107 @@ fn file_list_pdu_ms() {
 
     assert_eq!(&encoded, input);
 }
+
+round_trip_test! {
+    client_temp_dir_ms: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/client_temp_dir.pdu");
+    format_list_ms_1: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/format_list.pdu");
+    format_list_ms_2: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/format_list_2.pdu");
+    metafile_ms: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/metafile.pdu");
+    palette_ms: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/palette.pdu");
+    file_list_ms: ClipboardPdu<'_>, *include_bytes!("../../test_data/pdu/clipboard/file_list.pdu");
+}
+
+fn file_descriptor(name: &str) -> FileDescriptor {
+    FileDescriptor {
+        attributes: Some(ClipboardFileAttributes::ARCHIVE),
+        last_write_time: None,
+        file_size: Some(44),
+        name: name.to_owned(),
+    }
+}
+
+encoded_size_test! {
+    format_list_ascii_short: ClipboardPdu::FormatList(fake_format_list(true, false));
+    format_list_ascii_long: ClipboardPdu::FormatList(fake_format_list(true, true));
+    format_list_unicode_short: ClipboardPdu::FormatList(fake_format_list(false, false));
+    format_list_unicode_long: ClipboardPdu::FormatList(fake_format_list(false, true));
+    file_descriptor: file_descriptor("Fïle1.txt");
+    file_list: PackedFileList {
+        files: vec![file_descriptor("File1.txt"), file_descriptor(&"a".repeat(259))],
+    };
+    palette: ClipboardPalette {
+        entries: vec![
+            PaletteEntry {
+                red: 0xff,
+                green: 0x66,
+                blue: 0x33,
+                extra: 0x00,
+            };
+            3
+        ],
+    };
+    file_list_data_response: ClipboardPdu::FormatDataResponse(
+        FormatDataResponse::new_file_list(&PackedFileList {
+            files: vec![file_descriptor("File1.txt")],
+        })
+        .unwrap()
+    );
+}
+
+#[test]
+fn short_format_names_are_truncated() {
+    let long_name = "Rich Text Format Without Objects";
+    let formats = [
+        ClipboardFormat::new(ClipboardFormatId::new(49477)).with_name(ClipboardFormatName::new(long_name)),
+        ClipboardFormat::new(ClipboardFormatId::new(1)),
+    ];
+
+    for (list, truncated) in [
+        (FormatList::new_ascii(&formats, false).unwrap(), &long_name[..31]),
+        (FormatList::new_unicode(&formats, false).unwrap(), &long_name[..15]),
+    ] {
+        // Each short format name entry is exactly 36 bytes long.
+        assert_eq!(ironrdp_core::Encode::size(&list), 6 + 2 * 36);
+
+        let decoded = list.get_formats(false).unwrap();
+        assert_eq!(decoded[0].name, Some(ClipboardFormatName::new(truncated)));
+        assert_eq!(decoded[1].id, ClipboardFormatId::new(1));
+    }
+}
+
+#[test]
+fn short_format_name_truncation_does_not_split_surrogate_pairs() {
+    // 14 code units, followed by a surrogate pair which does not fit in the 15 remaining ones.
+    let name = format!("{}😀", "a".repeat(14));
+    let formats = [ClipboardFormat::new(ClipboardFormatId::new(49477)).with_name(ClipboardFormatName::new(name))];
+
+    let list = FormatList::new_unicode(&formats, false).unwrap();
+    let decoded = list.get_formats(false).unwrap();
+
+    assert_eq!(decoded[0].name, Some(ClipboardFormatName::new("a".repeat(14))));
+}
+
+#[test]
+fn long_format_names_round_trip() {
+    let long_name = format!("{}😀 ünïcode", "FileGroupDescriptorW ".repeat(16));
+    let formats = [
+        ClipboardFormat::new(ClipboardFormatId::new(49158)).with_name(ClipboardFormatName::new(long_name.clone())),
+        ClipboardFormat::new(ClipboardFormatId::new(13)),
+    ];
+
+    let list = FormatList::new_unicode(&formats, true).unwrap();
+    let decoded = list.get_formats(true).unwrap();
+
+    assert_eq!(decoded[0].name, Some(ClipboardFormatName::new(long_name)));
+    assert_eq!(decoded[1].id, ClipboardFormatId::new(13));
+}
+
+#[test]
+fn file_descriptor_name_too_long() {
+    // 260 characters, without room for the null terminator.
+    let pdu = PackedFileList {
+        files: vec![file_descriptor(&"a".repeat(260))],
+    };
+
+    assert!(ironrdp_core::encode_vec(&pdu).is_err());
+}
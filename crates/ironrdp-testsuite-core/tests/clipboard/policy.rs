@@ -0,0 +1,208 @@
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    Capabilities, ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, ClipboardPdu,
+    ClipboardProtocolVersion, FileContentsFlags, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse, FormatList, FormatListResponse, LockDataId,
+};
+use ironrdp_cliprdr::{ClipboardPolicy, CliprdrClient};
+use ironrdp_core::impl_as_any;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage, SvcProcessor as _};
+
+const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+
+fn formats() -> Vec<ClipboardFormat> {
+    vec![ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]
+}
+
+#[derive(Debug, Default)]
+struct RecordingBackend {
+    remote_copies: usize,
+    format_data_requests: usize,
+    file_contents_requests: usize,
+}
+
+impl_as_any!(RecordingBackend);
+
+impl CliprdrBackend for RecordingBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_request_format_list(&mut self) {}
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, _available_formats: &[ClipboardFormat]) {
+        self.remote_copies += 1;
+    }
+
+    fn on_format_data_request(&mut self, _format: FormatDataRequest) {
+        self.format_data_requests += 1;
+    }
+
+    fn on_format_data_response(&mut self, _response: FormatDataResponse<'_>) {}
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {
+        self.file_contents_requests += 1;
+    }
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {}
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+/// Clipboard PDU sent on the channel, summarized for comparison.
+#[derive(Debug, PartialEq)]
+enum Sent {
+    FormatList(Vec<ClipboardFormat>),
+    FormatListResponse(FormatListResponse),
+    FormatDataError,
+    FileContentsError,
+    Other(&'static str),
+}
+
+fn sent(messages: Vec<SvcMessage>) -> Vec<Sent> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .iter()
+        .map(|chunk| {
+            let pdu = ironrdp_core::decode::<ClipboardPdu<'_>>(&chunk.filled()[CHANNEL_PDU_HEADER_SIZE..]).unwrap();
+
+            match pdu {
+                ClipboardPdu::FormatList(list) => Sent::FormatList(list.get_formats(true).unwrap()),
+                ClipboardPdu::FormatListResponse(response) => Sent::FormatListResponse(response),
+                ClipboardPdu::FormatDataResponse(response) if response.is_error() => Sent::FormatDataError,
+                ClipboardPdu::FileContentsResponse(response)
+                    if response == FileContentsResponse::new_error(response.stream_id()) =>
+                {
+                    Sent::FileContentsError
+                }
+                pdu => Sent::Other(pdu.message_name()),
+            }
+        })
+        .collect()
+}
+
+fn receive(cliprdr: &mut CliprdrClient, pdu: ClipboardPdu<'_>) -> Vec<Sent> {
+    sent(cliprdr.process(&ironrdp_core::encode_vec(&pdu).unwrap()).unwrap())
+}
+
+fn copy(cliprdr: &mut CliprdrClient) -> Vec<Sent> {
+    sent(cliprdr.initiate_copy(&formats()).unwrap().into())
+}
+
+fn backend(cliprdr: &CliprdrClient) -> &RecordingBackend {
+    cliprdr.downcast_backend::<RecordingBackend>().unwrap()
+}
+
+/// Initializes the client channel with the given policy, the first copy announcing the test formats.
+fn ready_client(policy: ClipboardPolicy) -> (CliprdrClient, Vec<Sent>) {
+    let mut cliprdr = CliprdrClient::new(Box::new(RecordingBackend::default())).with_policy(policy);
+
+    assert!(receive(
+        &mut cliprdr,
+        ClipboardPdu::Capabilities(Capabilities::new(
+            ClipboardProtocolVersion::V2,
+            ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES,
+        ))
+    )
+    .is_empty());
+    assert!(receive(&mut cliprdr, ClipboardPdu::MonitorReady).is_empty());
+    let initialization = copy(&mut cliprdr);
+    assert!(receive(&mut cliprdr, ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).is_empty());
+
+    (cliprdr, initialization)
+}
+
+fn remote_copy() -> ClipboardPdu<'static> {
+    ClipboardPdu::FormatList(FormatList::new_unicode(&formats(), true).unwrap())
+}
+
+fn file_contents_request() -> ClipboardPdu<'static> {
+    ClipboardPdu::FileContentsRequest(FileContentsRequest {
+        stream_id: 1,
+        index: 0,
+        flags: FileContentsFlags::SIZE,
+        position: 0,
+        requested_size: 8,
+        data_id: None,
+    })
+}
+
+#[test]
+fn host_to_remote_ignores_remote_copy() {
+    let (mut cliprdr, _) = ready_client(ClipboardPolicy::HostToRemote);
+
+    assert_eq!(
+        receive(&mut cliprdr, remote_copy()),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+    assert_eq!(backend(&cliprdr).remote_copies, 0);
+    assert!(sent(
+        cliprdr
+            .initiate_paste(ClipboardFormatId::CF_UNICODETEXT)
+            .unwrap()
+            .into()
+    )
+    .is_empty());
+
+    // The channel is still ready, the local copies are announced.
+    assert_eq!(copy(&mut cliprdr), [Sent::FormatList(formats())]);
+}
+
+#[test]
+fn remote_to_host_ignores_local_copy() {
+    let (mut cliprdr, initialization) = ready_client(ClipboardPolicy::RemoteToHost);
+
+    // The channel is initialized with an empty format list.
+    assert_eq!(
+        initialization,
+        [
+            Sent::Other("CLIPRDR_CAPABILITIES"),
+            Sent::Other("CLIPRDR_TEMP_DIRECTORY"),
+            Sent::FormatList(Vec::new()),
+        ]
+    );
+    assert!(copy(&mut cliprdr).is_empty());
+
+    let request = ClipboardPdu::FormatDataRequest(FormatDataRequest {
+        format: ClipboardFormatId::CF_UNICODETEXT,
+    });
+    assert_eq!(receive(&mut cliprdr, request), [Sent::FormatDataError]);
+    assert_eq!(
+        receive(&mut cliprdr, file_contents_request()),
+        [Sent::FileContentsError]
+    );
+    assert_eq!(backend(&cliprdr).format_data_requests, 0);
+    assert_eq!(backend(&cliprdr).file_contents_requests, 0);
+
+    assert_eq!(
+        receive(&mut cliprdr, remote_copy()),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+    assert_eq!(backend(&cliprdr).remote_copies, 1);
+}
+
+#[test]
+fn policy_change_applies_to_next_copy() {
+    let (mut cliprdr, _) = ready_client(ClipboardPolicy::Bidirectional);
+
+    cliprdr.set_policy(ClipboardPolicy::Disabled);
+    assert!(copy(&mut cliprdr).is_empty());
+    assert_eq!(
+        receive(&mut cliprdr, remote_copy()),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+    assert_eq!(backend(&cliprdr).remote_copies, 0);
+
+    cliprdr.set_policy(ClipboardPolicy::Bidirectional);
+    assert_eq!(copy(&mut cliprdr), [Sent::FormatList(formats())]);
+    receive(&mut cliprdr, remote_copy());
+    assert_eq!(backend(&cliprdr).remote_copies, 1);
+}
@@ -0,0 +1,245 @@
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    Capabilities, ClipboardFormat, ClipboardFormatId, ClipboardFormatName, ClipboardGeneralCapabilityFlags,
+    ClipboardPdu, ClipboardProtocolVersion, FileContentsRequest, FileContentsResponse, FormatDataRequest,
+    FormatDataResponse, FormatList, FormatListResponse, LockDataId,
+};
+use ironrdp_cliprdr::{Cliprdr, CliprdrClient, CliprdrServer, Role};
+use ironrdp_core::impl_as_any;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage, SvcProcessor as _};
+
+const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+
+fn formats() -> Vec<ClipboardFormat> {
+    vec![
+        ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ClipboardFormat::new(ClipboardFormatId::new(0xC0FE)).with_name(ClipboardFormatName::new("HTML Format")),
+    ]
+}
+
+#[derive(Debug, Default)]
+struct RequestCounter {
+    format_list_requests: usize,
+}
+
+impl_as_any!(RequestCounter);
+
+impl CliprdrBackend for RequestCounter {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.format_list_requests += 1;
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, _available_formats: &[ClipboardFormat]) {}
+
+    fn on_format_data_request(&mut self, _format: FormatDataRequest) {}
+
+    fn on_format_data_response(&mut self, _response: FormatDataResponse<'_>) {}
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {}
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {}
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+/// Clipboard PDU sent on the channel, summarized for comparison.
+#[derive(Debug, PartialEq)]
+enum Sent {
+    FormatList(Vec<ClipboardFormat>),
+    FormatListResponse(FormatListResponse),
+    Other(&'static str),
+}
+
+fn sent(messages: Vec<SvcMessage>) -> Vec<Sent> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .iter()
+        .map(|chunk| {
+            let pdu = ironrdp_core::decode::<ClipboardPdu<'_>>(&chunk.filled()[CHANNEL_PDU_HEADER_SIZE..]).unwrap();
+
+            match pdu {
+                ClipboardPdu::FormatList(list) => Sent::FormatList(list.get_formats(true).unwrap()),
+                ClipboardPdu::FormatListResponse(response) => Sent::FormatListResponse(response),
+                pdu => Sent::Other(pdu.message_name()),
+            }
+        })
+        .collect()
+}
+
+fn receive<R: Role>(cliprdr: &mut Cliprdr<R>, pdu: ClipboardPdu<'_>) -> Vec<Sent> {
+    sent(cliprdr.process(&ironrdp_core::encode_vec(&pdu).unwrap()).unwrap())
+}
+
+fn capabilities() -> ClipboardPdu<'static> {
+    ClipboardPdu::Capabilities(Capabilities::new(
+        ClipboardProtocolVersion::V2,
+        ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES,
+    ))
+}
+
+fn format_list(formats: &[ClipboardFormat]) -> ClipboardPdu<'static> {
+    ClipboardPdu::FormatList(FormatList::new_unicode(formats, true).unwrap())
+}
+
+fn format_list_requests(cliprdr: &CliprdrClient) -> usize {
+    cliprdr
+        .downcast_backend::<RequestCounter>()
+        .unwrap()
+        .format_list_requests
+}
+
+/// Initializes the client channel, then copies the test formats.
+fn client_after_copy(mut cliprdr: CliprdrClient) -> CliprdrClient {
+    assert!(receive(&mut cliprdr, capabilities()).is_empty());
+    assert!(receive(&mut cliprdr, ClipboardPdu::MonitorReady).is_empty());
+    cliprdr.initiate_copy(&[]).unwrap();
+    assert!(receive(&mut cliprdr, ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).is_empty());
+
+    let copy = sent(cliprdr.initiate_copy(&formats()).unwrap().into());
+    assert_eq!(copy, [Sent::FormatList(formats())]);
+
+    cliprdr
+}
+
+/// Sends the capabilities and the monitor ready PDU of a server re-initializing the channel.
+fn reinitialize(cliprdr: &mut CliprdrClient) -> Vec<Sent> {
+    assert!(receive(cliprdr, capabilities()).is_empty());
+    receive(cliprdr, ClipboardPdu::MonitorReady)
+}
+
+#[test]
+fn reinitialized_client_reannounces_last_copy() {
+    let mut cliprdr = client_after_copy(CliprdrClient::new(Box::new(RequestCounter::default())));
+
+    // The format list is rejected, and the channel fails.
+    assert!(receive(&mut cliprdr, ClipboardPdu::FormatListResponse(FormatListResponse::Fail)).is_empty());
+    assert!(sent(
+        cliprdr
+            .initiate_paste(ClipboardFormatId::CF_UNICODETEXT)
+            .unwrap()
+            .into()
+    )
+    .is_empty());
+
+    let reinitialization = reinitialize(&mut cliprdr);
+    assert_eq!(
+        reinitialization,
+        [
+            Sent::Other("CLIPRDR_CAPABILITIES"),
+            Sent::Other("CLIPRDR_TEMP_DIRECTORY"),
+            Sent::FormatList(formats()),
+        ]
+    );
+    assert!(receive(&mut cliprdr, ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).is_empty());
+
+    // The formats are announced only once, without asking the backend.
+    assert_eq!(format_list_requests(&cliprdr), 1);
+    assert_eq!(
+        sent(
+            cliprdr
+                .initiate_paste(ClipboardFormatId::CF_UNICODETEXT)
+                .unwrap()
+                .into()
+        )
+        .len(),
+        1
+    );
+    assert!(receive(&mut cliprdr, ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).is_empty());
+}
+
+#[test]
+fn remote_copy_is_not_clobbered_by_reinitialization() {
+    let mut cliprdr = client_after_copy(CliprdrClient::new(Box::new(RequestCounter::default())));
+
+    let response = receive(&mut cliprdr, format_list(&formats()[..1]));
+    assert_eq!(response, [Sent::FormatListResponse(FormatListResponse::Ok)]);
+
+    assert!(reinitialize(&mut cliprdr).is_empty());
+    assert_eq!(format_list_requests(&cliprdr), 2);
+}
+
+#[test]
+fn reannounce_can_be_disabled() {
+    let mut cliprdr =
+        client_after_copy(CliprdrClient::new(Box::new(RequestCounter::default())).with_reannounce_on_ready(false));
+
+    assert!(reinitialize(&mut cliprdr).is_empty());
+    assert_eq!(format_list_requests(&cliprdr), 2);
+}
+
+#[test]
+fn empty_copy_is_not_reannounced() {
+    let mut cliprdr = client_after_copy(CliprdrClient::new(Box::new(RequestCounter::default())));
+    cliprdr.initiate_copy(&[]).unwrap();
+
+    assert!(reinitialize(&mut cliprdr).is_empty());
+    assert_eq!(format_list_requests(&cliprdr), 2);
+}
+
+/// Initializes the server channel, the client announcing no format, then copies the test formats.
+fn server_after_copy() -> CliprdrServer {
+    let mut cliprdr = CliprdrServer::new(Box::new(RequestCounter::default()));
+
+    assert_eq!(sent(cliprdr.start().unwrap()).len(), 2);
+    assert!(receive(&mut cliprdr, capabilities()).is_empty());
+    assert_eq!(
+        receive(&mut cliprdr, format_list(&[])),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+
+    let copy = sent(cliprdr.initiate_copy(&formats()).unwrap().into());
+    assert_eq!(copy, [Sent::FormatList(formats())]);
+
+    cliprdr
+}
+
+#[test]
+fn restarted_server_reannounces_last_copy() {
+    let mut cliprdr = server_after_copy();
+
+    assert_eq!(
+        sent(cliprdr.start().unwrap()),
+        [
+            Sent::Other("CLIPRDR_CAPABILITIES"),
+            Sent::Other("CLIPRDR_MONITOR_READY")
+        ]
+    );
+    assert!(receive(&mut cliprdr, capabilities()).is_empty());
+
+    assert_eq!(
+        receive(&mut cliprdr, format_list(&[])),
+        [
+            Sent::FormatListResponse(FormatListResponse::Ok),
+            Sent::FormatList(formats()),
+        ]
+    );
+    assert_eq!(
+        receive(&mut cliprdr, format_list(&[])),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+}
+
+#[test]
+fn restarted_server_keeps_client_copy() {
+    let mut cliprdr = server_after_copy();
+
+    cliprdr.start().unwrap();
+    assert!(receive(&mut cliprdr, capabilities()).is_empty());
+
+    assert_eq!(
+        receive(&mut cliprdr, format_list(&formats()[..1])),
+        [Sent::FormatListResponse(FormatListResponse::Ok)]
+    );
+}
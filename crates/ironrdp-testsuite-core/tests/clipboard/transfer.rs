@@ -0,0 +1,214 @@
+use ironrdp_cliprdr::backend::CliprdrBackend;
+use ironrdp_cliprdr::pdu::{
+    ClipboardFormat, ClipboardGeneralCapabilityFlags, ClipboardPdu, FileContentsFlags, FileContentsRequest,
+    FileContentsResponse, FileDescriptor, FormatDataRequest, FormatDataResponse, FormatListResponse, LockDataId,
+    PackedFileList,
+};
+use ironrdp_cliprdr::transfer::{FileTransfers, TransferProgress};
+use ironrdp_cliprdr::CliprdrClient;
+use ironrdp_core::impl_as_any;
+use ironrdp_svc::{StaticVirtualChannel, SvcMessage, SvcProcessor as _};
+
+const CHANNEL_PDU_HEADER_SIZE: usize = 8;
+
+fn file(name: &str, size: u64) -> FileDescriptor {
+    FileDescriptor {
+        attributes: None,
+        last_write_time: None,
+        file_size: Some(size),
+        name: name.to_owned(),
+    }
+}
+
+fn data_request(stream_id: u32, index: u32, position: u64, requested_size: u32) -> FileContentsRequest {
+    FileContentsRequest {
+        stream_id,
+        index,
+        flags: FileContentsFlags::DATA,
+        position,
+        requested_size,
+        data_id: None,
+    }
+}
+
+fn progress(stream_id: u32, index: u32, transferred: u64, size: u64) -> TransferProgress {
+    TransferProgress {
+        stream_id,
+        index,
+        transferred,
+        size: Some(size),
+    }
+}
+
+/// Encodes the messages sent on the channel, and returns their clipboard PDUs.
+fn encoded_pdus(messages: Vec<SvcMessage>) -> Vec<Vec<u8>> {
+    StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| chunk.filled()[CHANNEL_PDU_HEADER_SIZE..].to_vec())
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct TransferBackend {
+    progress: Vec<TransferProgress>,
+    requests: Vec<FileContentsRequest>,
+}
+
+impl_as_any!(TransferBackend);
+
+impl CliprdrBackend for TransferBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+    }
+
+    fn on_request_format_list(&mut self) {}
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, _available_formats: &[ClipboardFormat]) {}
+
+    fn on_format_data_request(&mut self, _format: FormatDataRequest) {}
+
+    fn on_format_data_response(&mut self, _response: FormatDataResponse<'_>) {}
+
+    fn on_file_contents_request(&mut self, request: FileContentsRequest) {
+        self.requests.push(request);
+    }
+
+    fn on_file_transfer_progress(&mut self, progress: TransferProgress) {
+        self.progress.push(progress);
+    }
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {}
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+/// Returns a clipboard channel in the ready state, which submitted a file list.
+fn ready_cliprdr(files: Vec<FileDescriptor>) -> CliprdrClient {
+    let mut cliprdr = CliprdrClient::new(Box::new(TransferBackend::default()));
+
+    cliprdr.initiate_copy(&[]).unwrap();
+    let response = ironrdp_core::encode_vec(&ClipboardPdu::FormatListResponse(FormatListResponse::Ok)).unwrap();
+    cliprdr.process(&response).unwrap();
+
+    cliprdr.submit_file_list(&PackedFileList { files }).unwrap();
+
+    cliprdr
+}
+
+fn request_bytes(request: FileContentsRequest) -> Vec<u8> {
+    ironrdp_core::encode_vec(&ClipboardPdu::FileContentsRequest(request)).unwrap()
+}
+
+#[test]
+fn out_of_order_chunks_progress() {
+    let mut transfers = FileTransfers::default();
+    transfers.set_file_list(&[file("a.txt", 10), file("b.txt", 25)]);
+
+    // The last chunk is requested first, and asks for more than what is left.
+    assert_eq!(
+        transfers.on_request(&data_request(1, 1, 20, 10)),
+        Some(progress(1, 1, 5, 25))
+    );
+    assert_eq!(
+        transfers.on_request(&data_request(1, 1, 0, 10)),
+        Some(progress(1, 1, 15, 25))
+    );
+    assert!(!transfers.progress(1, 1).unwrap().is_complete());
+
+    let last = transfers.on_request(&data_request(1, 1, 10, 10)).unwrap();
+    assert_eq!(last, progress(1, 1, 25, 25));
+    assert!(last.is_complete());
+
+    assert!(transfers.progress(1, 0).is_none());
+}
+
+#[test]
+fn overlapping_chunks_are_counted_once() {
+    let mut transfers = FileTransfers::default();
+    transfers.set_file_list(&[file("a.txt", 30)]);
+
+    transfers.on_request(&data_request(7, 0, 0, 10));
+    transfers.on_request(&data_request(7, 0, 20, 10));
+    transfers.on_request(&data_request(7, 0, 5, 10));
+
+    // Retried chunk.
+    transfers.on_request(&data_request(7, 0, 20, 10));
+
+    assert_eq!(transfers.progress(7, 0), Some(progress(7, 0, 25, 30)));
+}
+
+#[test]
+fn size_requests_are_not_counted() {
+    let mut transfers = FileTransfers::default();
+    transfers.set_file_list(&[file("a.txt", 30)]);
+
+    let request = FileContentsRequest {
+        flags: FileContentsFlags::SIZE,
+        ..data_request(1, 0, 0, 8)
+    };
+
+    assert!(transfers.on_request(&request).is_none());
+    assert!(transfers.progress(1, 0).is_none());
+}
+
+#[test]
+fn three_chunks_transfer_reports_progress() {
+    let mut cliprdr = ready_cliprdr(vec![file("a.txt", 24)]);
+
+    for position in [8, 0, 16] {
+        let messages = cliprdr
+            .process(&request_bytes(data_request(3, 0, position, 8)))
+            .unwrap();
+        assert!(messages.is_empty());
+    }
+
+    let backend = cliprdr.downcast_backend::<TransferBackend>().unwrap();
+    assert_eq!(
+        backend.progress,
+        [progress(3, 0, 8, 24), progress(3, 0, 16, 24), progress(3, 0, 24, 24)]
+    );
+    assert_eq!(backend.requests.len(), 3);
+    assert!(cliprdr.file_transfers().progress(3, 0).unwrap().is_complete());
+}
+
+#[test]
+fn cancelled_transfer_is_rejected() {
+    let mut cliprdr = ready_cliprdr(vec![file("a.txt", 24), file("b.txt", 24)]);
+
+    cliprdr.process(&request_bytes(data_request(3, 0, 0, 8))).unwrap();
+    cliprdr.process(&request_bytes(data_request(4, 1, 0, 8))).unwrap();
+
+    cliprdr.cancel_file_transfer(3);
+
+    let messages = cliprdr.process(&request_bytes(data_request(3, 0, 8, 8))).unwrap();
+    let expected =
+        ironrdp_core::encode_vec(&ClipboardPdu::FileContentsResponse(FileContentsResponse::new_error(3))).unwrap();
+    assert_eq!(encoded_pdus(messages), [expected]);
+
+    // Other streams are not affected.
+    let messages = cliprdr.process(&request_bytes(data_request(4, 1, 8, 8))).unwrap();
+    assert!(messages.is_empty());
+
+    let backend = cliprdr.downcast_backend::<TransferBackend>().unwrap();
+    assert_eq!(
+        backend.progress,
+        [progress(3, 0, 8, 24), progress(4, 1, 8, 24), progress(4, 1, 16, 24)]
+    );
+    assert!(backend
+        .requests
+        .iter()
+        .all(|request| request.position == 0 || request.stream_id == 4));
+    assert_eq!(backend.requests.len(), 3);
+
+    assert!(cliprdr.file_transfers().is_cancelled(3));
+    assert!(cliprdr.file_transfers().progress(3, 0).is_none());
+}
@@ -0,0 +1,128 @@
+use ironrdp_connector::credssp::{CredsspFailure, CredsspFailureReason, CredsspMechanism, CredsspPhase};
+use ironrdp_connector::sspi::credssp::NStatusCode;
+use ironrdp_connector::sspi::{self, ErrorKind};
+use rstest::rstest;
+
+// The descriptions are the ones of the KRB-ERROR codes mapped by sspi.
+#[rstest]
+#[case(ErrorKind::TimeSkew, "clock skew too great", CredsspFailureReason::ClockSkew)]
+#[case(
+    ErrorKind::OperationNotSupported,
+    "KDC has no support for encryption type",
+    CredsspFailureReason::UnsupportedEncryptionType
+)]
+#[case(
+    ErrorKind::KdcInvalidRequest,
+    "pre-authentication information was invalid. Additional error text: bad password",
+    CredsspFailureReason::PreauthenticationFailed
+)]
+#[case(ErrorKind::InvalidParameter, "wrong Realm", CredsspFailureReason::WrongRealm)]
+#[case(
+    ErrorKind::UnknownCredentials,
+    "client not found in Kerberos database",
+    CredsspFailureReason::UnknownUser
+)]
+#[case(
+    ErrorKind::UnknownCredentials,
+    "server not found in Kerberos database",
+    CredsspFailureReason::WrongServicePrincipal
+)]
+#[case(
+    ErrorKind::MessageAltered,
+    "message stream modified",
+    CredsspFailureReason::WrongServicePrincipal
+)]
+#[case(
+    ErrorKind::InvalidToken,
+    "the ticket isn't for us",
+    CredsspFailureReason::WrongServicePrincipal
+)]
+#[case(
+    ErrorKind::InvalidParameter,
+    "password has expired; change password to reset",
+    CredsspFailureReason::AccountRestricted
+)]
+#[case(
+    ErrorKind::NoAuthenticatingAuthority,
+    "can not detect KDC url",
+    CredsspFailureReason::KdcUnreachable
+)]
+fn kerberos_errors(#[case] kind: ErrorKind, #[case] description: &str, #[case] reason: CredsspFailureReason) {
+    let error = sspi::Error::new(kind, description);
+
+    // The Negotiate package may have attempted Kerberos anyway, the error tells.
+    let failure = CredsspFailure::classify(&error, CredsspMechanism::Ntlm, false);
+
+    assert_eq!(
+        failure,
+        CredsspFailure {
+            reason,
+            mechanism: CredsspMechanism::Kerberos,
+            phase: CredsspPhase::BeforePublicKeyBinding,
+        }
+    );
+}
+
+#[rstest]
+#[case(NStatusCode::WRONG_PASSWORD, CredsspFailureReason::WrongPassword)]
+#[case(NStatusCode::LOGON_FAILURE, CredsspFailureReason::LogonFailure)]
+#[case(NStatusCode::TIME_DIFFERENCE_AT_DC, CredsspFailureReason::ClockSkew)]
+#[case(NStatusCode::ACCOUNT_LOCKED_OUT, CredsspFailureReason::AccountRestricted)]
+#[case(NStatusCode::PASSWORD_EXPIRED, CredsspFailureReason::AccountRestricted)]
+fn server_error_statuses(#[case] nstatus: NStatusCode, #[case] reason: CredsspFailureReason) {
+    let error = sspi::Error::new_with_nstatus(
+        ErrorKind::InvalidToken,
+        "CredSSP server returned an error status",
+        nstatus,
+    );
+
+    let failure = CredsspFailure::classify(&error, CredsspMechanism::Ntlm, true);
+
+    assert_eq!(
+        failure,
+        CredsspFailure {
+            reason,
+            mechanism: CredsspMechanism::Ntlm,
+            phase: CredsspPhase::AfterPublicKeyBinding,
+        }
+    );
+}
+
+#[rstest]
+#[case("Could not verify a public key echo")]
+#[case("Could not verify a public key hash")]
+fn public_key_mismatch_is_before_binding(#[case] description: &str) {
+    let error = sspi::Error::new(ErrorKind::MessageAltered, description);
+
+    let failure = CredsspFailure::classify(&error, CredsspMechanism::Kerberos, true);
+
+    assert_eq!(failure.reason, CredsspFailureReason::PublicKeyMismatch);
+    assert_eq!(failure.mechanism, CredsspMechanism::Kerberos);
+    assert_eq!(failure.phase, CredsspPhase::BeforePublicKeyBinding);
+}
+
+#[test]
+fn unknown_errors_keep_the_configured_mechanism() {
+    let error = sspi::Error::new(ErrorKind::InternalError, "something went wrong");
+
+    let failure = CredsspFailure::classify(&error, CredsspMechanism::Ntlm, false);
+
+    assert_eq!(failure.reason, CredsspFailureReason::Other);
+    assert_eq!(failure.mechanism, CredsspMechanism::Ntlm);
+    assert_eq!(failure.phase, CredsspPhase::BeforePublicKeyBinding);
+}
+
+#[test]
+fn display_is_actionable() {
+    let failure = CredsspFailure {
+        reason: CredsspFailureReason::ClockSkew,
+        mechanism: CredsspMechanism::Kerberos,
+        phase: CredsspPhase::BeforePublicKeyBinding,
+    };
+
+    assert_eq!(
+        failure.to_string(),
+        "Kerberos authentication failed before the public key binding check: the clocks of the client and of the domain \
+         controller are too far apart, synchronize the clock of the client"
+    );
+}
@@ -0,0 +1,283 @@
+mod failure;
+
+use ironrdp_connector::credssp::{
+    CredsspFailureReason, CredsspMechanism, CredsspPhase, CredsspProcessGenerator, CredsspSequence, KerberosConfig,
+};
+use ironrdp_connector::sspi;
+use ironrdp_connector::sspi::credssp::{
+    ClientMode, ClientState, CredSspServer, CredentialsProxy, ServerState, TsRequest,
+};
+use ironrdp_connector::sspi::generator::{GeneratorState, NetworkRequest};
+use ironrdp_connector::sspi::network_client::NetworkProtocol;
+use ironrdp_connector::sspi::{AuthIdentity, Username};
+use ironrdp_connector::{kdc_proxy, ConnectorErrorKind, CredentialDelegation, Credentials, ServerName};
+use ironrdp_pdu::nego;
+use picky_asn1::date::Date;
+use picky_asn1::restricted_string::Ia5String;
+use picky_asn1::wrapper::{
+    Asn1SequenceOf, ExplicitContextTag0, ExplicitContextTag1, ExplicitContextTag10, ExplicitContextTag4,
+    ExplicitContextTag5, ExplicitContextTag6, ExplicitContextTag9, GeneralStringAsn1, IntegerAsn1,
+    ObjectIdentifierAsn1, Optional,
+};
+use picky_asn1_der::Asn1RawDer;
+use picky_asn1_x509::oids;
+use picky_krb::constants::error_codes::KDC_ERR_C_PRINCIPAL_UNKNOWN;
+use picky_krb::constants::gss_api::ACCEPT_INCOMPLETE;
+use picky_krb::data_types::{KerberosTime, PrincipalName};
+use picky_krb::gss_api::{NegTokenTarg, NegTokenTarg1};
+use picky_krb::messages::{KdcProxyMessage, KrbError, KrbErrorInner};
+
+const KDC_PROXY_URL: &str = "https://gateway.example.com/KdcProxy";
+const REALM: &str = "EXAMPLE.COM";
+
+#[test]
+fn length_prefix_roundtrip() {
+    let framed = kdc_proxy::add_length_prefix(&[0x6a, 0x03, 0x02, 0x01, 0x05]).unwrap();
+
+    assert_eq!(framed, [0x00, 0x00, 0x00, 0x05, 0x6a, 0x03, 0x02, 0x01, 0x05]);
+    assert_eq!(
+        kdc_proxy::strip_length_prefix(&framed).unwrap(),
+        [0x6a, 0x03, 0x02, 0x01, 0x05]
+    );
+}
+
+#[test]
+fn length_prefix_mismatch_is_rejected() {
+    kdc_proxy::strip_length_prefix(&[0x00, 0x00]).unwrap_err();
+    kdc_proxy::strip_length_prefix(&[0x00, 0x00, 0x00, 0x02, 0x30]).unwrap_err();
+    kdc_proxy::strip_length_prefix(&[0x00, 0x00, 0x00, 0x00, 0x30]).unwrap_err();
+}
+
+#[test]
+fn kdc_proxy_request_framing() {
+    let framed_request = kdc_proxy::add_length_prefix(&[0x30, 0x00]).unwrap();
+
+    let encoded = kdc_proxy::encode_kdc_proxy_request(&framed_request, Some(REALM)).unwrap();
+    let message = KdcProxyMessage::from_raw(&encoded).unwrap();
+
+    assert_eq!(message.kerb_message.0 .0, framed_request);
+    assert_eq!(message.target_domain.0.unwrap().0.to_string(), REALM);
+    assert!(message.dclocator_hint.0.is_none());
+}
+
+#[test]
+fn kdc_proxy_request_without_prefix_is_rejected() {
+    kdc_proxy::encode_kdc_proxy_request(&[0x30, 0x00], None).unwrap_err();
+}
+
+#[test]
+fn kdc_proxy_response_framing() {
+    let framed_reply = kdc_proxy::add_length_prefix(&[0x7e, 0x00]).unwrap();
+    let response = KdcProxyMessage::from_raw_kerb_message(&framed_reply)
+        .unwrap()
+        .to_vec()
+        .unwrap();
+
+    assert_eq!(kdc_proxy::decode_kdc_proxy_response(&response).unwrap(), framed_reply);
+}
+
+#[test]
+fn kdc_proxy_response_with_bad_prefix_is_rejected() {
+    let response = KdcProxyMessage::from_raw_kerb_message(&[0x00, 0x00, 0x00, 0x08, 0x7e, 0x00])
+        .unwrap()
+        .to_vec()
+        .unwrap();
+
+    kdc_proxy::decode_kdc_proxy_response(&response).unwrap_err();
+}
+
+/// Network client answering KDC requests with canned replies, recording the requests
+#[derive(Default)]
+struct MockKdcProxy {
+    requests: Vec<(NetworkProtocol, String, Vec<u8>)>,
+}
+
+impl MockKdcProxy {
+    fn send(&mut self, request: &NetworkRequest) -> Vec<u8> {
+        self.requests
+            .push((request.protocol, request.url.to_string(), request.data.clone()));
+
+        let framed_reply = kdc_proxy::add_length_prefix(&principal_unknown_error()).unwrap();
+
+        KdcProxyMessage::from_raw_kerb_message(&framed_reply)
+            .unwrap()
+            .to_vec()
+            .unwrap()
+    }
+}
+
+#[test]
+fn credssp_kerberos_through_kdc_proxy() {
+    let (mut sequence, ts_request) = CredsspSequence::init(
+        Credentials::UsernamePassword {
+            username: "alice".to_owned(),
+            password: "password".to_owned(),
+        },
+        Some(REALM),
+        CredentialDelegation::Full,
+        nego::SecurityProtocol::HYBRID,
+        ServerName::new("rdp.example.com"),
+        Vec::new(),
+        Some(KerberosConfig::new(Some(KDC_PROXY_URL.to_owned()), Some("client".to_owned())).unwrap()),
+    )
+    .unwrap();
+
+    let mut kdc = MockKdcProxy::default();
+
+    // The first client message is sent to the RDP server without contacting the KDC.
+    let client_state = resolve(sequence.process_ts_request(ts_request), &mut kdc).unwrap();
+    assert!(matches!(client_state, ClientState::ReplyNeeded(_)));
+    assert!(kdc.requests.is_empty());
+
+    // The server answers without a TGT, so the client must get one from the KDC.
+    let server_ts_request = TsRequest {
+        nego_tokens: Some(server_neg_token_targ()),
+        ..TsRequest::default()
+    };
+    let result = resolve(sequence.process_ts_request(server_ts_request), &mut kdc);
+
+    // The canned KDC reply is a KRB-ERROR, so the sequence must not succeed.
+    let error = sequence.handle_process_error(result.unwrap_err());
+    let ConnectorErrorKind::CredsspFailure(failure) = error.kind else {
+        panic!("unexpected error: {error:?}");
+    };
+    assert_eq!(failure.reason, CredsspFailureReason::UnknownUser);
+    assert_eq!(failure.mechanism, CredsspMechanism::Kerberos);
+    assert_eq!(failure.phase, CredsspPhase::BeforePublicKeyBinding);
+
+    let (protocol, url, data) = kdc.requests.first().expect("at least one KDC request");
+    assert_eq!(*protocol, NetworkProtocol::Https);
+    assert_eq!(url, KDC_PROXY_URL);
+
+    // The Kerberos client tunnels a length-prefixed AS-REQ targeting the user realm.
+    let framed_request = kdc_proxy::decode_kdc_proxy_response(data).unwrap();
+    let as_req = kdc_proxy::strip_length_prefix(&framed_request).unwrap();
+    assert_eq!(kdc_proxy::kdc_request_realm(as_req).as_deref(), Some(REALM));
+}
+
+/// Server-side credentials of the NTLM authentication
+struct UserCredentials(AuthIdentity);
+
+impl CredentialsProxy for UserCredentials {
+    type AuthenticationData = AuthIdentity;
+
+    fn auth_data_by_user(&mut self, username: &Username) -> std::io::Result<AuthIdentity> {
+        assert_eq!(username.account_name(), self.0.username.account_name());
+        Ok(self.0.clone())
+    }
+}
+
+/// Runs a NTLM CredSSP sequence in memory, and returns the credentials received by the server.
+fn delegated_credentials(credential_delegation: CredentialDelegation) -> AuthIdentity {
+    const PUBLIC_KEY: &[u8] = &[0x30, 0x0a, 0x02, 0x03, 0x01, 0x00, 0x01];
+
+    let (mut client, mut ts_request) = CredsspSequence::init(
+        Credentials::UsernamePassword {
+            username: "alice".to_owned(),
+            password: "password".to_owned(),
+        },
+        None,
+        credential_delegation,
+        nego::SecurityProtocol::HYBRID,
+        ServerName::new("rdp.example.com"),
+        PUBLIC_KEY.to_vec(),
+        None,
+    )
+    .unwrap();
+
+    let mut server = CredSspServer::new(
+        PUBLIC_KEY.to_vec(),
+        UserCredentials(AuthIdentity {
+            username: Username::parse("alice").unwrap(),
+            password: "password".to_owned().into(),
+        }),
+        ClientMode::Negotiate(sspi::NegotiateConfig {
+            protocol_config: Box::<sspi::ntlm::NtlmConfig>::default(),
+            package_list: None,
+            client_computer_name: "client".to_owned(),
+        }),
+    )
+    .unwrap();
+
+    loop {
+        let client_request = match client.process_ts_request(ts_request).resolve_to_result().unwrap() {
+            ClientState::ReplyNeeded(request) | ClientState::FinalMessage(request) => request,
+        };
+
+        match server.process(client_request).unwrap() {
+            ServerState::ReplyNeeded(response) => ts_request = response,
+            ServerState::Finished(identity) => break identity,
+        }
+    }
+}
+
+#[test]
+fn full_delegation_sends_password() {
+    let identity = delegated_credentials(CredentialDelegation::Full);
+
+    assert_eq!(identity.username.account_name(), "alice");
+    assert_eq!(identity.password.as_ref(), "password");
+}
+
+#[test]
+fn restricted_admin_sends_empty_credentials() {
+    let identity = delegated_credentials(CredentialDelegation::RestrictedAdmin);
+
+    assert_eq!(identity.username.account_name(), "");
+    assert_eq!(identity.password.as_ref(), "");
+}
+
+fn resolve(mut generator: CredsspProcessGenerator<'_>, kdc: &mut MockKdcProxy) -> sspi::Result<ClientState> {
+    let mut state = generator.start();
+
+    loop {
+        match state {
+            GeneratorState::Suspended(request) => {
+                let response = kdc.send(&request);
+                state = generator.resume(Ok(response));
+            }
+            GeneratorState::Completed(result) => break result,
+        }
+    }
+}
+
+fn server_neg_token_targ() -> Vec<u8> {
+    let neg_token_targ = NegTokenTarg1::from(NegTokenTarg {
+        neg_result: Optional::from(Some(ExplicitContextTag0::from(Asn1RawDer(ACCEPT_INCOMPLETE.to_vec())))),
+        supported_mech: Optional::from(Some(ExplicitContextTag1::from(ObjectIdentifierAsn1::from(
+            oids::ms_krb5(),
+        )))),
+        response_token: Optional::from(None),
+        mech_list_mic: Optional::from(None),
+    });
+
+    picky_asn1_der::to_vec(&neg_token_targ).unwrap()
+}
+
+fn principal_unknown_error() -> Vec<u8> {
+    let realm = || GeneralStringAsn1::from(Ia5String::from_string(REALM.to_owned()).unwrap());
+
+    let error = KrbError::from(KrbErrorInner {
+        pvno: ExplicitContextTag0::from(IntegerAsn1::from(vec![5])),
+        msg_type: ExplicitContextTag1::from(IntegerAsn1::from(vec![0x1e])),
+        ctime: Optional::from(None),
+        cusec: Optional::from(None),
+        stime: ExplicitContextTag4::from(KerberosTime::from(Date::new(2024, 1, 1, 0, 0, 0).unwrap())),
+        susec: ExplicitContextTag5::from(IntegerAsn1::from(vec![0])),
+        error_code: ExplicitContextTag6::from(KDC_ERR_C_PRINCIPAL_UNKNOWN),
+        crealm: Optional::from(None),
+        cname: Optional::from(None),
+        realm: ExplicitContextTag9::from(realm()),
+        sname: ExplicitContextTag10::from(PrincipalName {
+            name_type: ExplicitContextTag0::from(IntegerAsn1::from(vec![2])),
+            name_string: ExplicitContextTag1::from(Asn1SequenceOf::from(vec![
+                GeneralStringAsn1::from(Ia5String::from_string("krbtgt".to_owned()).unwrap()),
+                realm(),
+            ])),
+        }),
+        e_text: Optional::from(None),
+        e_data: Optional::from(None),
+    });
+
+    picky_asn1_der::to_vec(&error).unwrap()
+}
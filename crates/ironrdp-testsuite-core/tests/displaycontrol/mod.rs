@@ -1,7 +1,16 @@
-use ironrdp_core::decode;
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_displaycontrol::client::{DisplayControlClient, MonitorLayoutRequest, ResizeDebouncer};
 use ironrdp_displaycontrol::pdu;
+use ironrdp_dvc::DvcProcessor as _;
+use ironrdp_svc::SvcMessage;
+use ironrdp_testsuite_core::channel::decode_dvc_client_pdus;
 use ironrdp_testsuite_core::encode_decode_test;
 
+const CHANNEL_ID: u32 = 3;
+
 encode_decode_test! {
     capabilities: pdu::DisplayControlPdu::Caps(pdu::DisplayControlCapabilities::new(
         3, 1920, 1080
@@ -125,3 +134,113 @@ fn only_non_optional_layout_fields_required_to_be_valid() {
     assert!(decoded.physical_dimensions().is_none());
     assert!(decoded.position().is_none())
 }
+
+fn resize(width: u32, height: u32) -> MonitorLayoutRequest {
+    MonitorLayoutRequest {
+        width,
+        height,
+        scale_factor: Some(100),
+        physical_dims: None,
+    }
+}
+
+fn ready_client(capabilities: pdu::DisplayControlCapabilities) -> DisplayControlClient {
+    let mut client = DisplayControlClient::new(|_| Ok(Vec::new()));
+    let caps = encode_vec(&pdu::DisplayControlPdu::Caps(capabilities)).unwrap();
+    client.process(CHANNEL_ID, &caps).unwrap();
+    assert!(client.ready());
+    client
+}
+
+fn send_request(client: &DisplayControlClient, request: MonitorLayoutRequest) -> Vec<SvcMessage> {
+    let (width, height) = pdu::MonitorLayoutEntry::adjust_display_size(request.width, request.height);
+    client
+        .encode_single_primary_monitor(CHANNEL_ID, width, height, request.scale_factor, request.physical_dims)
+        .unwrap()
+}
+
+fn sent_layouts(messages: Vec<SvcMessage>) -> Vec<pdu::DisplayControlMonitorLayout> {
+    decode_dvc_client_pdus::<pdu::DisplayControlPdu>(messages)
+        .into_iter()
+        .map(|pdu| match pdu {
+            pdu::DisplayControlPdu::MonitorLayout(layout) => layout,
+            pdu => panic!("unexpected display control PDU: {pdu:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn burst_of_resizes_sends_final_size_only() {
+    let client = ready_client(pdu::DisplayControlCapabilities::new(1, 3840, 2160).unwrap());
+    let mut debouncer = ResizeDebouncer::new(Duration::from_millis(300));
+    let start = Instant::now();
+
+    let mut messages = Vec::new();
+
+    for (elapsed, width, height) in [(0, 800, 600), (50, 900, 650), (120, 1100, 700), (250, 1280, 720)] {
+        let now = start + Duration::from_millis(elapsed);
+        if let Some(request) = debouncer.poll(now) {
+            messages.extend(send_request(&client, request));
+        }
+        debouncer.request(now, resize(width, height));
+    }
+
+    assert!(debouncer.poll(start + Duration::from_millis(500)).is_none());
+    assert_eq!(debouncer.deadline(), Some(start + Duration::from_millis(550)));
+
+    let request = debouncer.poll(start + Duration::from_millis(550)).unwrap();
+    messages.extend(send_request(&client, request));
+    assert!(debouncer.poll(start + Duration::from_secs(10)).is_none());
+
+    let layouts = sent_layouts(messages);
+    assert_eq!(layouts.len(), 1);
+    assert_eq!(layouts[0].monitors()[0].dimensions(), (1280, 720));
+    assert_eq!(layouts[0].monitors()[0].desktop_scale_factor(), Some(100));
+}
+
+#[test]
+fn resize_to_last_sent_size_is_dropped() {
+    let mut debouncer = ResizeDebouncer::default();
+    let start = Instant::now();
+
+    debouncer.request(start, resize(1024, 768));
+    assert_eq!(
+        debouncer.poll(start + ResizeDebouncer::DEFAULT_DELAY),
+        Some(resize(1024, 768))
+    );
+
+    // Resized then restored before the delay expires.
+    debouncer.request(start + Duration::from_secs(1), resize(1280, 1024));
+    debouncer.request(start + Duration::from_secs(1), resize(1024, 768));
+    assert!(debouncer.poll(start + Duration::from_secs(2)).is_none());
+    assert!(debouncer.deadline().is_none());
+
+    // A scale factor change is a different layout.
+    let scaled = MonitorLayoutRequest {
+        scale_factor: Some(150),
+        ..resize(1024, 768)
+    };
+    debouncer.request(start + Duration::from_secs(3), scaled);
+    assert_eq!(debouncer.poll(start + Duration::from_secs(4)), Some(scaled));
+}
+
+#[test]
+fn resize_is_clamped_to_max_monitor_area() {
+    let client = ready_client(pdu::DisplayControlCapabilities::new(1, 1024, 768).unwrap());
+
+    let layouts = sent_layouts(send_request(&client, resize(3840, 2160)));
+    let (width, height) = layouts[0].monitors()[0].dimensions();
+
+    assert!(u64::from(width) * u64::from(height) <= 1024 * 768);
+    assert_eq!(width % 2, 0);
+    // The aspect ratio is kept.
+    assert_eq!(width * 9 / 16, height);
+}
+
+#[test]
+fn clamp_keeps_fitting_size() {
+    let caps = pdu::DisplayControlCapabilities::new(2, 1920, 1080).unwrap();
+
+    assert_eq!(caps.clamp_single_monitor_size(1920, 1080), (1920, 1080));
+    assert_eq!(caps.clamp_single_monitor_size(3840, 1080), (3840, 1080));
+}
@@ -0,0 +1,469 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{encode_vec, impl_as_any};
+use ironrdp_dvc::pdu::{
+    CapabilitiesRequestPdu, CapsVersion, ClosePdu, CreateRequestPdu, CreationStatus, DataFirstPdu, DataPdu,
+    DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu,
+};
+use ironrdp_dvc::{DrdynvcClient, DrdynvcDiagnostics, DvcMessage, DvcProcessor, ReassemblyError, ReassemblyErrorKind};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+use ironrdp_testsuite_core::channel::{channel_payloads, decode_channel_pdus};
+
+use crate::allocations::count_allocations;
+
+const CHANNEL_NAME: &str = "Test::Channel";
+const CHANNEL_ID: u32 = 7;
+const UNKNOWN_CHANNEL_ID: u32 = 42;
+
+#[derive(Default)]
+struct RecordingDvc {
+    received: Vec<Vec<u8>>,
+    closed: Vec<u32>,
+}
+
+impl_as_any!(RecordingDvc);
+
+impl DvcProcessor for RecordingDvc {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.received.push(payload.to_vec());
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self, channel_id: u32) {
+        self.closed.push(channel_id);
+    }
+}
+
+/// Processes a PDU received from the server and returns the PDUs sent back.
+fn process(client: &mut DrdynvcClient, pdu: DrdynvcServerPdu) -> Vec<DrdynvcClientPdu> {
+    let messages = client.process(&encode_vec(&pdu).unwrap()).unwrap();
+    decode_channel_pdus(messages)
+}
+
+fn data(channel_id: u32, payload: &[u8]) -> DrdynvcServerPdu {
+    DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(channel_id, payload.to_vec())))
+}
+
+fn data_first(channel_id: u32, payload: &[u8]) -> DrdynvcServerPdu {
+    let length = u32::try_from(payload.len()).unwrap() + 1;
+    DrdynvcServerPdu::Data(DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+        channel_id,
+        length,
+        payload.to_vec(),
+    )))
+}
+
+fn recording(client: &DrdynvcClient) -> &RecordingDvc {
+    client
+        .get_dvc_by_type_id::<RecordingDvc>()
+        .unwrap()
+        .channel_processor_downcast_ref::<RecordingDvc>()
+        .unwrap()
+}
+
+fn received(client: &DrdynvcClient) -> &[Vec<u8>] {
+    &recording(client).received
+}
+
+fn opened_client() -> DrdynvcClient {
+    let mut client = DrdynvcClient::new().with_dynamic_channel(RecordingDvc::default());
+
+    let responses = process(
+        &mut client,
+        DrdynvcServerPdu::Create(CreateRequestPdu::new(CHANNEL_ID, CHANNEL_NAME.to_owned())),
+    );
+    let Some(DrdynvcClientPdu::Create(response)) = responses.last() else {
+        panic!("unexpected responses: {responses:?}");
+    };
+    assert_eq!(response.creation_status, CreationStatus::OK);
+
+    client
+}
+
+#[test]
+fn data_after_close_is_dropped() {
+    let mut client = opened_client();
+
+    assert!(process(&mut client, data(CHANNEL_ID, b"open")).is_empty());
+
+    let responses = process(&mut client, DrdynvcServerPdu::Close(ClosePdu::new(CHANNEL_ID)));
+    assert_eq!(responses, [DrdynvcClientPdu::Close(ClosePdu::new(CHANNEL_ID))]);
+    assert_eq!(recording(&client).closed, [CHANNEL_ID]);
+
+    assert!(process(&mut client, data(CHANNEL_ID, b"closed")).is_empty());
+    assert!(process(&mut client, data_first(CHANNEL_ID, b"closed")).is_empty());
+
+    assert_eq!(received(&client), [b"open".to_vec()]);
+    assert_eq!(
+        client.diagnostics(),
+        DrdynvcDiagnostics {
+            closed_channel_data: 2,
+            unknown_channel_data: 0,
+            unknown_channel_creations: 0,
+        }
+    );
+}
+
+#[test]
+fn data_for_unknown_channel_is_answered_with_close() {
+    let mut client = opened_client();
+
+    let responses = process(&mut client, data_first(UNKNOWN_CHANNEL_ID, b"unknown"));
+    assert_eq!(responses, [DrdynvcClientPdu::Close(ClosePdu::new(UNKNOWN_CHANNEL_ID))]);
+
+    // The data in flight until the server processes the Close PDU is dropped.
+    assert!(process(&mut client, data(UNKNOWN_CHANNEL_ID, b"unknown")).is_empty());
+
+    assert!(received(&client).is_empty());
+    assert_eq!(
+        client.diagnostics(),
+        DrdynvcDiagnostics {
+            closed_channel_data: 1,
+            unknown_channel_data: 1,
+            unknown_channel_creations: 0,
+        }
+    );
+}
+
+#[test]
+fn reopened_channel_receives_data() {
+    let mut client = opened_client();
+
+    process(&mut client, DrdynvcServerPdu::Close(ClosePdu::new(CHANNEL_ID)));
+    process(
+        &mut client,
+        DrdynvcServerPdu::Create(CreateRequestPdu::new(CHANNEL_ID, CHANNEL_NAME.to_owned())),
+    );
+
+    assert!(process(&mut client, data(CHANNEL_ID, b"reopened")).is_empty());
+
+    assert_eq!(received(&client), [b"reopened".to_vec()]);
+    assert_eq!(client.diagnostics(), DrdynvcDiagnostics::default());
+}
+
+fn create(client: &mut DrdynvcClient) -> CreationStatus {
+    let responses = process(
+        client,
+        DrdynvcServerPdu::Create(CreateRequestPdu::new(CHANNEL_ID, CHANNEL_NAME.to_owned())),
+    );
+    let Some(DrdynvcClientPdu::Create(response)) = responses.last() else {
+        panic!("unexpected responses: {responses:?}");
+    };
+    response.creation_status
+}
+
+#[test]
+fn channel_attached_after_connection_is_created() {
+    let mut client = DrdynvcClient::new();
+
+    let responses = process(
+        &mut client,
+        DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(CapsVersion::V1, None)),
+    );
+    assert_eq!(responses.len(), 1);
+    assert_eq!(create(&mut client), CreationStatus::NO_LISTENER);
+
+    client.attach_dynamic_channel(RecordingDvc::default());
+
+    assert_eq!(create(&mut client), CreationStatus::OK);
+    assert!(process(&mut client, data(CHANNEL_ID, b"late")).is_empty());
+    assert_eq!(received(&client), [b"late".to_vec()]);
+}
+
+#[test]
+fn detached_channel_is_closed_and_refused() {
+    let mut client = opened_client();
+
+    let close_request = client.detach_dynamic_channel(CHANNEL_NAME).expect("opened channel");
+    assert_eq!(
+        decode_channel_pdus::<DrdynvcClientPdu>(vec![close_request]),
+        [DrdynvcClientPdu::Close(ClosePdu::new(CHANNEL_ID))]
+    );
+
+    assert!(client.get_dvc_by_type_id::<RecordingDvc>().is_none());
+    assert!(process(&mut client, data(CHANNEL_ID, b"in flight")).is_empty());
+    assert_eq!(create(&mut client), CreationStatus::NO_LISTENER);
+    assert!(client.detach_dynamic_channel(CHANNEL_NAME).is_none());
+}
+
+#[test]
+fn create_request_for_unknown_channel_is_refused() {
+    let mut client = opened_client();
+
+    let messages = client
+        .process(
+            &encode_vec(&DrdynvcServerPdu::Create(CreateRequestPdu::new(
+                UNKNOWN_CHANNEL_ID,
+                "Unknown::Channel".to_owned(),
+            )))
+            .unwrap(),
+        )
+        .unwrap();
+    let response = channel_payloads(messages);
+
+    #[rustfmt::skip]
+    let expected = [
+        0x10, // Cmd (Create), Sp, cbChId (1 byte)
+        0x2a, // ChannelId
+        0x01, 0x00, 0x00, 0xc0, // CreationStatus (NO_LISTENER)
+    ];
+    assert_eq!(response, [expected]);
+
+    assert_eq!(
+        client.diagnostics(),
+        DrdynvcDiagnostics {
+            unknown_channel_creations: 1,
+            ..DrdynvcDiagnostics::default()
+        }
+    );
+}
+
+/// Listener of a dynamically named channel
+struct NamedDvc(String);
+
+impl_as_any!(NamedDvc);
+
+impl DvcProcessor for NamedDvc {
+    fn channel_name(&self) -> &str {
+        &self.0
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+}
+
+fn create_named(client: &mut DrdynvcClient, channel_id: u32, channel_name: &str) -> CreationStatus {
+    let responses = process(
+        client,
+        DrdynvcServerPdu::Create(CreateRequestPdu::new(channel_id, channel_name.to_owned())),
+    );
+    let Some(DrdynvcClientPdu::Create(response)) = responses.last() else {
+        panic!("unexpected responses: {responses:?}");
+    };
+    response.creation_status
+}
+
+#[test]
+fn fallback_factory_is_consulted_once_per_new_name() {
+    let consulted = Arc::new(Mutex::new(Vec::new()));
+
+    let mut client = DrdynvcClient::new()
+        .with_dynamic_channel(RecordingDvc::default())
+        .with_fallback_factory({
+            let consulted = Arc::clone(&consulted);
+            move |channel_name: &str| -> Option<Box<dyn DvcProcessor>> {
+                consulted.lock().unwrap().push(channel_name.to_owned());
+                match channel_name {
+                    "Vendor::Mismatch" => Some(Box::new(NamedDvc("Vendor::Other".to_owned()))),
+                    _ if channel_name.starts_with("Vendor::") => Some(Box::new(NamedDvc(channel_name.to_owned()))),
+                    _ => None,
+                }
+            }
+        });
+
+    assert_eq!(create_named(&mut client, CHANNEL_ID, CHANNEL_NAME), CreationStatus::OK);
+    assert_eq!(create_named(&mut client, 1, "Vendor::Monitor1"), CreationStatus::OK);
+    process(&mut client, DrdynvcServerPdu::Close(ClosePdu::new(1)));
+    assert_eq!(create_named(&mut client, 2, "Vendor::Monitor1"), CreationStatus::OK);
+    assert_eq!(create_named(&mut client, 3, "Vendor::Monitor2"), CreationStatus::OK);
+    assert_eq!(
+        create_named(&mut client, 4, "Vendor::Mismatch"),
+        CreationStatus::NO_LISTENER
+    );
+    assert_eq!(create_named(&mut client, 5, "Other"), CreationStatus::NO_LISTENER);
+
+    assert_eq!(
+        *consulted.lock().unwrap(),
+        ["Vendor::Monitor1", "Vendor::Monitor2", "Vendor::Mismatch", "Other"]
+    );
+    assert_eq!(client.diagnostics().unknown_channel_creations, 2);
+
+    let mut names = client.channel_stats().map(|(name, _)| name).collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(names, [CHANNEL_NAME, "Vendor::Monitor1", "Vendor::Monitor2"]);
+}
+
+fn fragment(channel_id: u32, total_length: u32, payload: &[u8]) -> DrdynvcServerPdu {
+    DrdynvcServerPdu::Data(DrdynvcDataPdu::DataFirst(DataFirstPdu::new(
+        channel_id,
+        total_length,
+        payload.to_vec(),
+    )))
+}
+
+/// Processes a PDU violating the fragmentation, and returns the reassembly error.
+fn reassembly_error(client: &mut DrdynvcClient, pdu: DrdynvcServerPdu) -> ReassemblyError {
+    let Err(error) = client.process(&encode_vec(&pdu).unwrap()) else {
+        panic!("PDU processed without error");
+    };
+
+    let mut source = core::error::Error::source(&error);
+    while let Some(error) = source {
+        if let Some(reassembly_error) = error.downcast_ref::<ReassemblyError>() {
+            return *reassembly_error;
+        }
+        source = error.source();
+    }
+
+    panic!("not a reassembly error: {error:?}");
+}
+
+#[test]
+fn fragmented_message_is_reassembled() {
+    let mut client = opened_client();
+
+    assert!(process(&mut client, fragment(CHANNEL_ID, 10, b"abcd")).is_empty());
+    assert!(process(&mut client, data(CHANNEL_ID, b"efg")).is_empty());
+    assert!(received(&client).is_empty());
+    assert!(process(&mut client, data(CHANNEL_ID, b"hij")).is_empty());
+    assert!(process(&mut client, data(CHANNEL_ID, b"single")).is_empty());
+
+    assert_eq!(received(&client), [b"abcdefghij".to_vec(), b"single".to_vec()]);
+}
+
+/// Drops the reassembled messages, reserving at most `reserve_limit` bytes for a fragmented one.
+struct ReservingDvc {
+    reserve_limit: usize,
+}
+
+impl_as_any!(ReservingDvc);
+
+impl DvcProcessor for ReservingDvc {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn reassembly_reserve_limit(&self) -> usize {
+        self.reserve_limit
+    }
+}
+
+/// Returns the number of allocations made to receive the fragments of a message.
+fn reassembly_allocations(reserve_limit: usize, fragments: &[Vec<u8>]) -> usize {
+    let mut client = DrdynvcClient::new().with_dynamic_channel(ReservingDvc { reserve_limit });
+    let create = DrdynvcServerPdu::Create(CreateRequestPdu::new(CHANNEL_ID, CHANNEL_NAME.to_owned()));
+    client.process(&encode_vec(&create).unwrap()).unwrap();
+
+    let (_, allocations) = count_allocations(|| {
+        for fragment in fragments {
+            assert!(client.process(fragment).unwrap().is_empty());
+        }
+    });
+    allocations
+}
+
+#[test]
+fn reserved_reassembly_buffer_is_not_grown() {
+    const MESSAGE_SIZE: usize = 256 * 1024;
+
+    let message = vec![0xA5; MESSAGE_SIZE];
+    let fragments: Vec<_> = message
+        .chunks(DrdynvcDataPdu::MAX_DATA_SIZE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let pdu = if i == 0 {
+                fragment(CHANNEL_ID, u32::try_from(MESSAGE_SIZE).unwrap(), chunk)
+            } else {
+                data(CHANNEL_ID, chunk)
+            };
+            encode_vec(&pdu).unwrap()
+        })
+        .collect();
+
+    let reserved = reassembly_allocations(MESSAGE_SIZE, &fragments);
+    let unreserved = reassembly_allocations(0, &fragments);
+
+    // Without any reservation, the buffer is reallocated as the fragments are received.
+    assert!(
+        reserved < unreserved,
+        "{reserved} reserved and {unreserved} unreserved allocations"
+    );
+}
+
+#[test]
+fn data_beyond_total_length_is_rejected() {
+    let mut client = opened_client();
+
+    process(&mut client, fragment(CHANNEL_ID, 6, b"abcd"));
+
+    assert_eq!(
+        reassembly_error(&mut client, data(CHANNEL_ID, b"efg")),
+        ReassemblyError {
+            channel_id: CHANNEL_ID,
+            kind: ReassemblyErrorKind::Overflow {
+                total_length: 6,
+                received: 7,
+            },
+        }
+    );
+    assert_eq!(
+        reassembly_error(&mut client, fragment(CHANNEL_ID, 2, b"abc")).kind,
+        ReassemblyErrorKind::Overflow {
+            total_length: 2,
+            received: 3,
+        }
+    );
+    assert!(received(&client).is_empty());
+}
+
+#[test]
+fn data_first_before_message_end_is_rejected() {
+    let mut client = opened_client();
+
+    process(&mut client, fragment(CHANNEL_ID, 6, b"abcd"));
+
+    assert_eq!(
+        reassembly_error(&mut client, fragment(CHANNEL_ID, 6, b"ABCD")),
+        ReassemblyError {
+            channel_id: CHANNEL_ID,
+            kind: ReassemblyErrorKind::UnexpectedDataFirst,
+        }
+    );
+    assert!(received(&client).is_empty());
+}
+
+#[test]
+fn data_without_data_first_is_rejected_after_violation() {
+    let mut client = opened_client();
+
+    process(&mut client, fragment(CHANNEL_ID, 6, b"abcd"));
+    reassembly_error(&mut client, fragment(CHANNEL_ID, 6, b"ABCD"));
+
+    // The remaining fragment of the rejected message can't be told apart from a message that is not fragmented.
+    assert_eq!(
+        reassembly_error(&mut client, data(CHANNEL_ID, b"EF")),
+        ReassemblyError {
+            channel_id: CHANNEL_ID,
+            kind: ReassemblyErrorKind::MissingDataFirst,
+        }
+    );
+
+    // The next Data First PDU resumes the reassembly.
+    process(&mut client, fragment(CHANNEL_ID, 4, b"ab"));
+    process(&mut client, data(CHANNEL_ID, b"cd"));
+    process(&mut client, data(CHANNEL_ID, b"single"));
+
+    assert_eq!(received(&client), [b"abcd".to_vec(), b"single".to_vec()]);
+}
@@ -1,3 +1,9 @@
+use ironrdp_core::impl_as_any;
+use ironrdp_dvc::{DrdynvcClient, DvcMessage, DvcProcessor};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+use ironrdp_testsuite_core::channel::decode_channel_pdus;
+
 use super::*;
 
 const CHANNEL_ID: u32 = 0x0000_0003;
@@ -35,3 +41,66 @@ fn decodes_create_response() {
 fn encodes_create_response() {
     test_encodes(resp_decoded_client(), &RESP_ENCODED);
 }
+
+/// Processor declining every channel creation, as a plugin refusing the connection would
+struct RejectingProcessor {
+    started: bool,
+}
+
+impl_as_any!(RejectingProcessor);
+
+impl DvcProcessor for RejectingProcessor {
+    fn channel_name(&self) -> &str {
+        "testdvc"
+    }
+
+    fn accept(&mut self, _channel_id: u32) -> CreationStatus {
+        CreationStatus::ACCESS_DENIED
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.started = true;
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, _payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn rejected_creation_is_reported_to_the_server() {
+    let mut client = DrdynvcClient::new().with_dynamic_channel(RejectingProcessor { started: false });
+
+    let responses = client.process(&REQ_ENCODED).unwrap();
+
+    let create_response = decode_channel_pdus::<DrdynvcClientPdu>(responses)
+        .into_iter()
+        .find_map(|pdu| match pdu {
+            DrdynvcClientPdu::Create(response) => Some(response),
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(create_response.creation_status, CreationStatus::ACCESS_DENIED);
+    test_encodes(
+        &DrdynvcClientPdu::Create(create_response),
+        &[0x10, 0x03, 0x05, 0x00, 0x07, 0x80],
+    );
+
+    let channel = client.get_dvc_by_type_id::<RejectingProcessor>().unwrap();
+    assert!(!channel.is_open());
+    assert!(
+        !channel
+            .channel_processor_downcast_ref::<RejectingProcessor>()
+            .unwrap()
+            .started
+    );
+
+    // The refused channel is not usable, and its data is answered with a Close PDU.
+    let data = [0x30, 0x03, 0x01, 0x02];
+    assert_eq!(
+        decode_channel_pdus::<DrdynvcClientPdu>(client.process(&data).unwrap()),
+        [DrdynvcClientPdu::Close(ClosePdu::new(CHANNEL_ID))]
+    );
+}
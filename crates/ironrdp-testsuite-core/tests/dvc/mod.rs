@@ -21,7 +21,10 @@ fn test_decodes<'a, T: Decode<'a> + PartialEq + core::fmt::Debug>(encoded: &'a [
 }
 
 mod capabilities;
+mod client;
 mod close;
 mod create;
 mod data;
 mod data_first;
+mod request_tracker;
+mod server;
@@ -0,0 +1,139 @@
+use ironrdp_core::{impl_as_any, ReadCursor};
+use ironrdp_dvc::{Completion, DvcMessage, DvcProcessOutput, DvcProcessor, DvcRequestTracker};
+use ironrdp_pdu::PduResult;
+
+#[test]
+fn assigns_distinct_ids() {
+    let mut tracker = DvcRequestTracker::<&str, ()>::new(4);
+
+    let first = tracker.track("first", 0).unwrap();
+    let second = tracker.track("second", 0).unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(tracker.len(), 2);
+}
+
+#[test]
+fn refuses_requests_when_full() {
+    let mut tracker = DvcRequestTracker::<u8, ()>::new(2);
+
+    let first = tracker.track(1, 0).unwrap();
+    tracker.track(2, 0).unwrap();
+
+    assert!(tracker.is_full());
+    assert_eq!(tracker.track(3, 0), Err(3));
+
+    tracker.complete(first, ()).unwrap();
+
+    assert!(!tracker.is_full());
+    tracker.track(3, 0).unwrap();
+    assert_eq!(tracker.track(4, 0), Err(4));
+}
+
+#[test]
+fn completes_pending_requests_once() {
+    let mut tracker = DvcRequestTracker::<&str, u8>::new(4);
+
+    let first = tracker.track("first", 0).unwrap();
+    let second = tracker.track("second", 0).unwrap();
+
+    assert_eq!(
+        tracker.complete(second, 2),
+        Some(Completion {
+            id: second,
+            request: "second",
+            response: 2,
+        })
+    );
+    assert_eq!(tracker.complete(second, 2), None);
+    assert_eq!(tracker.complete(first.wrapping_add(42), 0), None);
+    assert_eq!(tracker.len(), 1);
+}
+
+#[test]
+fn expires_oldest_requests_first() {
+    let mut tracker = DvcRequestTracker::<&str, ()>::new(8);
+
+    tracker.track("b", 20).unwrap();
+    tracker.track("a", 10).unwrap();
+    let recent = tracker.track("recent", 95).unwrap();
+    tracker.track("c", 20).unwrap();
+
+    assert_eq!(tracker.expire_older_than(50, 100), ["a", "b", "c"]);
+    assert_eq!(tracker.len(), 1);
+    assert!(tracker.expire_older_than(50, 100).is_empty());
+    assert!(tracker.complete(recent, ()).is_some());
+}
+
+#[test]
+fn expiry_tolerates_clock_going_backward() {
+    let mut tracker = DvcRequestTracker::<&str, ()>::new(8);
+
+    tracker.track("future", 100).unwrap();
+
+    assert!(tracker.expire_older_than(0, 50).is_empty());
+    assert_eq!(tracker.len(), 1);
+}
+
+/// Processor answering requests carrying a little-endian correlation ID followed by a single byte response.
+#[derive(Debug)]
+struct PingProcessor {
+    tracker: DvcRequestTracker<&'static str, u8>,
+    completed: Vec<Completion<&'static str, u8>>,
+}
+
+impl_as_any!(PingProcessor);
+
+impl PingProcessor {
+    fn process_tracked(&mut self, payload: &[u8]) -> DvcProcessOutput<&'static str, u8> {
+        let mut src = ReadCursor::new(payload);
+        let id = src.read_u32();
+        let response = src.read_u8();
+
+        let mut output = DvcProcessOutput::default();
+        output.completions.extend(self.tracker.complete(id, response));
+        output
+    }
+}
+
+impl DvcProcessor for PingProcessor {
+    fn channel_name(&self) -> &str {
+        "Ping"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        let output = self.process_tracked(payload);
+        self.completed.extend(output.completions);
+        Ok(output.messages)
+    }
+}
+
+#[test]
+fn processor_resolves_completions() {
+    let mut processor = PingProcessor {
+        tracker: DvcRequestTracker::new(4),
+        completed: Vec::new(),
+    };
+
+    let id = processor.tracker.track("ping", 0).unwrap();
+
+    let mut payload = id.to_le_bytes().to_vec();
+    payload.push(7);
+
+    assert!(processor.process(1, &payload).unwrap().is_empty());
+    assert!(processor.process(1, &payload).unwrap().is_empty());
+
+    assert_eq!(
+        processor.completed,
+        [Completion {
+            id,
+            request: "ping",
+            response: 7,
+        }]
+    );
+    assert!(processor.tracker.is_empty());
+}
@@ -0,0 +1,153 @@
+use ironrdp_core::{encode_vec, impl_as_any};
+use ironrdp_dvc::pdu::{
+    CapabilitiesRequestPdu, CapabilitiesResponsePdu, CapsVersion, ClosePdu, CompressedDataPdu, CreateResponsePdu,
+    CreationStatus, DataPdu, DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu,
+};
+use ironrdp_dvc::{DrdynvcServer, DvcMessage, DvcProcessor, DvcServerProcessor};
+use ironrdp_pdu::PduResult;
+use ironrdp_svc::SvcProcessor as _;
+use ironrdp_testsuite_core::channel::decode_channel_pdus;
+
+const CHANNEL_NAME: &str = "Test::Channel";
+const CHANNEL_PRIORITY: u8 = 2;
+const PRIORITY_CHARGES: [u16; 4] = [936, 3276, 9362, 51];
+
+/// RDP8_BULK_ENCODED_DATA header of uncompressed data.
+const BULK_UNCOMPRESSED: u8 = 0x04;
+/// RDP8_BULK_ENCODED_DATA header of compressed data.
+const BULK_COMPRESSED: u8 = 0x24;
+
+#[derive(Default)]
+struct RecordingDvc {
+    received: Vec<Vec<u8>>,
+}
+
+impl_as_any!(RecordingDvc);
+
+impl DvcProcessor for RecordingDvc {
+    fn channel_name(&self) -> &str {
+        CHANNEL_NAME
+    }
+
+    fn start(&mut self, _channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.received.push(payload.to_vec());
+        Ok(Vec::new())
+    }
+}
+
+impl DvcServerProcessor for RecordingDvc {}
+
+/// Processes a PDU received from the client and returns the PDUs sent back.
+fn process(server: &mut DrdynvcServer, pdu: DrdynvcClientPdu) -> Vec<DrdynvcServerPdu> {
+    let messages = server.process(&encode_vec(&pdu).unwrap()).unwrap();
+    decode_channel_pdus(messages)
+}
+
+fn received(server: &DrdynvcServer) -> &[Vec<u8>] {
+    &server
+        .channel_processor_downcast_ref::<RecordingDvc>()
+        .unwrap()
+        .received
+}
+
+/// Runs the capabilities exchange with a client answering `client_version`, then opens the test channel.
+///
+/// Returns the server, along with the priority sent in the Create Request PDU.
+fn negotiate(server_version: CapsVersion, client_version: CapsVersion) -> (DrdynvcServer, u8) {
+    let mut server = DrdynvcServer::new()
+        .with_caps_version(server_version)
+        .with_priority_charges(PRIORITY_CHARGES)
+        .with_dynamic_channel_priority(RecordingDvc::default(), CHANNEL_PRIORITY);
+
+    let request = decode_channel_pdus::<DrdynvcServerPdu>(server.start().unwrap());
+    assert_eq!(
+        request,
+        [DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(
+            server_version,
+            Some(PRIORITY_CHARGES)
+        ))]
+    );
+
+    let response = DrdynvcClientPdu::Capabilities(CapabilitiesResponsePdu::new(client_version));
+    let mut responses = process(&mut server, response);
+    assert_eq!(responses.len(), 1);
+    let DrdynvcServerPdu::Create(create_request) = responses.remove(0) else {
+        panic!("expected a Create Request PDU");
+    };
+    assert_eq!(create_request.channel_name, CHANNEL_NAME);
+
+    let create_response =
+        DrdynvcClientPdu::Create(CreateResponsePdu::new(create_request.channel_id, CreationStatus::OK));
+    assert!(process(&mut server, create_response).is_empty());
+
+    (server, create_request.priority())
+}
+
+fn compressed_data(bulk_header: u8, payload: &[u8]) -> DrdynvcClientPdu {
+    let mut data = vec![bulk_header];
+    data.extend_from_slice(payload);
+    DrdynvcClientPdu::CompressedData(CompressedDataPdu::new(0, data))
+}
+
+#[test]
+fn negotiation_matrix() {
+    let cases = [
+        (CapsVersion::V3, CapsVersion::V1, CapsVersion::V1, false, false),
+        (CapsVersion::V3, CapsVersion::V2, CapsVersion::V2, true, false),
+        (CapsVersion::V3, CapsVersion::V3, CapsVersion::V3, true, true),
+        (CapsVersion::V1, CapsVersion::V3, CapsVersion::V1, false, false),
+    ];
+
+    for (server_version, client_version, agreed, priority, compression) in cases {
+        let (server, sent_priority) = negotiate(server_version, client_version);
+
+        assert_eq!(
+            server.agreed_caps_version(),
+            Some(agreed),
+            "{server_version:?} x {client_version:?}"
+        );
+        assert_eq!(server.supports_channel_priority(), priority);
+        assert_eq!(server.supports_compression(), compression);
+        assert_eq!(sent_priority, if priority { CHANNEL_PRIORITY } else { 0 });
+    }
+}
+
+#[test]
+fn uncompressed_bulk_data_is_accepted_when_compression_is_negotiated() {
+    let (mut server, _) = negotiate(CapsVersion::V3, CapsVersion::V3);
+
+    assert!(process(&mut server, compressed_data(BULK_UNCOMPRESSED, b"hello")).is_empty());
+
+    assert_eq!(received(&server), [b"hello".to_vec()]);
+}
+
+#[test]
+fn compressed_data_closes_the_channel_when_compression_is_not_negotiated() {
+    for client_version in [CapsVersion::V1, CapsVersion::V2] {
+        let (mut server, _) = negotiate(CapsVersion::V3, client_version);
+
+        let responses = process(&mut server, compressed_data(BULK_UNCOMPRESSED, b"hello"));
+        assert_eq!(responses, [DrdynvcServerPdu::Close(ClosePdu::new(0))]);
+
+        // The data received until the client acknowledges the closing is dropped.
+        let data = DrdynvcClientPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(0, b"late".to_vec())));
+        assert!(process(&mut server, data).is_empty());
+        assert!(process(&mut server, DrdynvcClientPdu::Close(ClosePdu::new(0))).is_empty());
+
+        assert!(received(&server).is_empty());
+    }
+}
+
+#[test]
+fn compressed_data_closes_the_channel_when_decompression_is_required() {
+    let (mut server, _) = negotiate(CapsVersion::V3, CapsVersion::V3);
+
+    let responses = process(&mut server, compressed_data(BULK_COMPRESSED, &[0x12, 0x34]));
+    assert_eq!(responses, [DrdynvcServerPdu::Close(ClosePdu::new(0))]);
+
+    assert!(received(&server).is_empty());
+}
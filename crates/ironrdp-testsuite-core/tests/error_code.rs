@@ -0,0 +1,230 @@
+//! The error codes are exposed through the FFI bindings and aggregated by the telemetry, so they must never change.
+
+use ironrdp_connector::credssp::{CredsspFailure, CredsspFailureReason, CredsspMechanism, CredsspPhase};
+use ironrdp_connector::{sspi, ConnectorError, ConnectorErrorExt, ConnectorErrorKind, CredentialDelegation};
+use ironrdp_core::{
+    invalid_field_err, other_err, DecodeError, DecodeErrorKind, EncodeErrorKind, ErrorCategory, ErrorKindExt,
+};
+use ironrdp_pdu::nego::NegotiationFailureCode;
+use ironrdp_pdu::{PduError, PduErrorKind};
+use ironrdp_session::{SessionError, SessionErrorExt, SessionErrorKind};
+
+#[test]
+fn encode_error_codes() {
+    let kinds = [
+        EncodeErrorKind::NotEnoughBytes {
+            received: 0,
+            expected: 1,
+        },
+        EncodeErrorKind::InvalidField { field: "", reason: "" },
+        EncodeErrorKind::UnexpectedMessageType { got: 0 },
+        EncodeErrorKind::UnsupportedVersion { got: 0 },
+        EncodeErrorKind::UnsupportedValue {
+            name: "",
+            value: String::new(),
+        },
+        EncodeErrorKind::Other { description: "" },
+    ];
+
+    let codes: Vec<_> = kinds.iter().map(ErrorKindExt::code).collect();
+    assert_eq!(
+        codes,
+        [
+            0x0001_0001,
+            0x0001_0002,
+            0x0001_0003,
+            0x0001_0004,
+            0x0001_0005,
+            0x0001_0006
+        ]
+    );
+    assert!(kinds.iter().all(|kind| kind.category() == ErrorCategory::Encoding));
+}
+
+#[test]
+fn decode_error_codes() {
+    let kinds = [
+        DecodeErrorKind::NotEnoughBytes {
+            received: 0,
+            expected: 1,
+        },
+        DecodeErrorKind::InvalidField { field: "", reason: "" },
+        DecodeErrorKind::UnexpectedMessageType { got: 0 },
+        DecodeErrorKind::UnsupportedVersion { got: 0 },
+        DecodeErrorKind::UnsupportedValue {
+            name: "",
+            value: String::new(),
+        },
+        DecodeErrorKind::Other { description: "" },
+    ];
+
+    let codes: Vec<_> = kinds.iter().map(ErrorKindExt::code).collect();
+    assert_eq!(
+        codes,
+        [
+            0x0002_0001,
+            0x0002_0002,
+            0x0002_0003,
+            0x0002_0004,
+            0x0002_0005,
+            0x0002_0006
+        ]
+    );
+    assert!(kinds.iter().all(|kind| kind.category() == ErrorCategory::Decoding));
+}
+
+#[test]
+fn pdu_error_codes() {
+    let kinds = [
+        (PduErrorKind::Encode, 0x0003_0001, ErrorCategory::Encoding),
+        (PduErrorKind::Decode, 0x0003_0002, ErrorCategory::Decoding),
+        (
+            PduErrorKind::Other { description: "" },
+            0x0003_0003,
+            ErrorCategory::Other,
+        ),
+    ];
+
+    for (kind, code, category) in kinds {
+        assert_eq!(kind.code(), code, "{kind:?}");
+        assert_eq!(kind.category(), category, "{kind:?}");
+    }
+}
+
+#[test]
+fn connector_error_codes() {
+    let kinds = [
+        (
+            ConnectorErrorKind::Encode(other_err!("")),
+            0x0004_0001,
+            ErrorCategory::Encoding,
+        ),
+        (
+            ConnectorErrorKind::Decode(other_err!("")),
+            0x0004_0002,
+            ErrorCategory::Decoding,
+        ),
+        (
+            ConnectorErrorKind::Credssp(sspi::Error::new(sspi::ErrorKind::LogonDenied, "")),
+            0x0004_0003,
+            ErrorCategory::Security,
+        ),
+        (
+            ConnectorErrorKind::Reason(String::new()),
+            0x0004_0004,
+            ErrorCategory::Protocol,
+        ),
+        (ConnectorErrorKind::AccessDenied, 0x0004_0005, ErrorCategory::Security),
+        (ConnectorErrorKind::General, 0x0004_0006, ErrorCategory::Other),
+        (ConnectorErrorKind::Custom, 0x0004_0007, ErrorCategory::Other),
+        (
+            ConnectorErrorKind::Timeout { stage: "" },
+            0x0004_0008,
+            ErrorCategory::Timeout,
+        ),
+        (
+            ConnectorErrorKind::UnsupportedCredentialDelegation {
+                mode: CredentialDelegation::RestrictedAdmin,
+            },
+            0x0004_0009,
+            ErrorCategory::Security,
+        ),
+        (
+            ConnectorErrorKind::NegotiationFailure(NegotiationFailureCode::SslRequiredByServer),
+            0x0004_000A,
+            ErrorCategory::Security,
+        ),
+        (
+            ConnectorErrorKind::CredsspFailure(CredsspFailure {
+                reason: CredsspFailureReason::Other,
+                mechanism: CredsspMechanism::Ntlm,
+                phase: CredsspPhase::BeforePublicKeyBinding,
+            }),
+            0x0004_000C,
+            ErrorCategory::Security,
+        ),
+    ];
+
+    for (kind, code, category) in kinds {
+        assert_eq!(kind.code(), code, "{kind:?}");
+        assert_eq!(kind.category(), category, "{kind:?}");
+    }
+}
+
+#[test]
+fn session_error_codes() {
+    let kinds = [
+        (
+            SessionErrorKind::Pdu(PduError::new("", PduErrorKind::Decode)),
+            0x0005_0001,
+            ErrorCategory::Decoding,
+        ),
+        (
+            SessionErrorKind::Encode(other_err!("")),
+            0x0005_0002,
+            ErrorCategory::Encoding,
+        ),
+        (
+            SessionErrorKind::Decode(other_err!("")),
+            0x0005_0003,
+            ErrorCategory::Decoding,
+        ),
+        (
+            SessionErrorKind::Reason(String::new()),
+            0x0005_0004,
+            ErrorCategory::Protocol,
+        ),
+        (SessionErrorKind::General, 0x0005_0005, ErrorCategory::Other),
+        (SessionErrorKind::Custom, 0x0005_0006, ErrorCategory::Other),
+    ];
+
+    for (kind, code, category) in kinds {
+        assert_eq!(kind.code(), code, "{kind:?}");
+        assert_eq!(kind.category(), category, "{kind:?}");
+    }
+}
+
+#[test]
+fn error_passes_the_code_through() {
+    let error = ConnectorError::general("connect");
+    assert_eq!(error.code(), 0x0004_0006);
+    assert_eq!(error.category(), ErrorCategory::Other);
+
+    let error = SessionError::reason("process", "unexpected PDU");
+    assert_eq!(error.code(), 0x0005_0004);
+}
+
+#[test]
+fn display_with_code() {
+    let error: DecodeError = invalid_field_err!("ShareControlHeader", "pduType", "invalid PDU type");
+
+    assert_eq!(
+        error.to_string(),
+        "[ShareControlHeader] invalid `pduType`: invalid PDU type"
+    );
+    assert_eq!(
+        error.with_code().to_string(),
+        "[ShareControlHeader] invalid `pduType`: invalid PDU type (code 0x00020002)"
+    );
+}
+
+#[test]
+fn error_kinds_without_code_are_supported() {
+    #[derive(Debug)]
+    struct CustomKind;
+
+    impl core::fmt::Display for CustomKind {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str("custom")
+        }
+    }
+
+    impl std::error::Error for CustomKind {}
+
+    let error = ironrdp_error::Error::new("context", CustomKind);
+    assert_eq!(error.to_string(), "[context] custom");
+    assert_eq!(error.report().to_string(), "[context] custom");
+
+    let error = std::io::Error::from(error);
+    assert_eq!(error.to_string(), "[context] custom");
+}
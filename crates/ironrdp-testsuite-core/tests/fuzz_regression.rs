@@ -1,5 +1,16 @@
 macro_rules! check {
     ($oracle:ident) => {{
+        check!(@for_each_test_case $oracle, |test_case| oracles::$oracle(test_case))
+    }};
+    ($oracle:ident: $input:ty) => {{
+        use arbitrary::{Arbitrary as _, Unstructured};
+
+        check!(@for_each_test_case $oracle, |test_case| {
+            let input = <$input>::arbitrary_take_rest(Unstructured::new(test_case)).unwrap();
+            oracles::$oracle(&input);
+        })
+    }};
+    (@for_each_test_case $oracle:ident, |$test_case:ident| $check:expr) => {{
         use ironrdp_fuzzing::oracles;
 
         const REGRESSION_DATA_FOLDER: &str = concat!(
@@ -12,7 +23,8 @@ macro_rules! check {
             let entry = entry.unwrap();
             println!("Check {}", entry.path().display());
             let test_case = std::fs::read(entry.path()).unwrap();
-            oracles::$oracle(&test_case);
+            let $test_case = test_case.as_slice();
+            $check;
         }
     }};
 }
@@ -26,3 +38,13 @@ fn check_pdu_decode() {
 fn check_cliprdr_format() {
     check!(cliprdr_format);
 }
+
+#[test]
+fn check_svc_reassembly() {
+    check!(svc_reassembly: ironrdp_fuzzing::generators::SvcChunksInput);
+}
+
+#[test]
+fn check_dvc_reassembly() {
+    check!(dvc_reassembly: ironrdp_fuzzing::generators::DvcFragmentsInput);
+}
@@ -0,0 +1,152 @@
+use ironrdp_graphics::bitmap_cache::{rop3, BitmapCache, BitmapCacheError, PersistentKey};
+use ironrdp_pdu::orders::{CacheBitmapRev2, CacheBitmapRev2Flags};
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, CacheFlags, CellInfo};
+
+fn bitmap_cache() -> BitmapCache {
+    let cell = |num_entries, is_cache_persistent| CellInfo {
+        num_entries,
+        is_cache_persistent,
+    };
+
+    BitmapCache::new(&BitmapCacheRev2 {
+        cache_flags: CacheFlags::PERSISTENT_KEYS_EXPECTED_FLAG,
+        num_cell_caches: 2,
+        cache_cell_info: [
+            cell(2, true),
+            cell(1, false),
+            cell(0, false),
+            cell(0, false),
+            cell(0, false),
+        ],
+    })
+}
+
+/// Uncompressed 32 bpp bitmap of a single color.
+fn cache_bitmap(cache_id: u8, cache_index: u16, width: u16, height: u16, color: [u8; 3]) -> CacheBitmapRev2 {
+    let [r, g, b] = color;
+
+    CacheBitmapRev2 {
+        cache_id,
+        bits_per_pixel: 32,
+        flags: CacheBitmapRev2Flags::PERSISTENT_KEY_PRESENT,
+        key: Some(u64::from(cache_index) << 32 | u64::from(r)),
+        width,
+        height,
+        cache_index,
+        compressed: false,
+        bitmap_data: [b, g, r, 0xff].repeat(usize::from(width) * usize::from(height)),
+    }
+}
+
+#[test]
+fn slots_are_replaced_as_directed_by_the_server() {
+    let mut cache = bitmap_cache();
+
+    cache.insert(&cache_bitmap(0, 0, 2, 2, [1, 2, 3])).unwrap();
+    cache.insert(&cache_bitmap(0, 1, 2, 2, [4, 5, 6])).unwrap();
+    cache.insert(&cache_bitmap(0, 0, 4, 1, [7, 8, 9])).unwrap();
+
+    let replaced = cache.get(0, 0).unwrap().unwrap();
+    assert_eq!((replaced.width(), replaced.height()), (4, 1));
+    assert_eq!(replaced.pixel(3, 0), Some([7, 8, 9]));
+    assert_eq!(replaced.pixel(0, 1), None);
+
+    assert_eq!(cache.get(0, 1).unwrap().unwrap().pixel(1, 1), Some([4, 5, 6]));
+    assert!(cache.get(1, 0).unwrap().is_none());
+}
+
+#[test]
+fn bitmaps_are_stored_top_down() {
+    let mut cache = bitmap_cache();
+
+    let mut order = cache_bitmap(0, 0, 1, 2, [0, 0, 0]);
+    // Bottom row, then top row.
+    order.bitmap_data = vec![0x03, 0x02, 0x01, 0xff, 0x06, 0x05, 0x04, 0xff];
+    cache.insert(&order).unwrap();
+
+    let bitmap = cache.get(0, 0).unwrap().unwrap();
+    assert_eq!(bitmap.data(), [0x04, 0x05, 0x06, 0x01, 0x02, 0x03]);
+}
+
+#[test]
+fn negotiated_entry_counts_and_cell_sizes_are_enforced() {
+    let mut cache = bitmap_cache();
+
+    assert!(matches!(
+        cache.insert(&cache_bitmap(0, 2, 2, 2, [0, 0, 0])),
+        Err(BitmapCacheError::InvalidCacheIndex {
+            cache_id: 0,
+            cache_index: 2
+        })
+    ));
+    assert!(matches!(
+        cache.insert(&cache_bitmap(2, 0, 2, 2, [0, 0, 0])),
+        Err(BitmapCacheError::InvalidCacheId { cache_id: 2 })
+    ));
+
+    // The cells of the first cache are holding up to 256 pixels, and the ones of the second cache up to 1024.
+    cache.insert(&cache_bitmap(0, 0, 16, 16, [0, 0, 0])).unwrap();
+    assert!(matches!(
+        cache.insert(&cache_bitmap(0, 0, 17, 16, [0, 0, 0])),
+        Err(BitmapCacheError::BitmapTooLarge { cache_id: 0, .. })
+    ));
+    cache.insert(&cache_bitmap(1, 0, 32, 32, [0, 0, 0])).unwrap();
+
+    assert!(matches!(
+        cache.get(0, 2),
+        Err(BitmapCacheError::InvalidCacheIndex { .. })
+    ));
+}
+
+#[test]
+fn uncached_bitmaps_are_ignored() {
+    let mut cache = bitmap_cache();
+
+    let mut order = cache_bitmap(0, 0, 2, 2, [0, 0, 0]);
+    order.flags |= CacheBitmapRev2Flags::DO_NOT_CACHE;
+    cache.insert(&order).unwrap();
+
+    cache
+        .insert(&cache_bitmap(0, CacheBitmapRev2::WAITING_LIST_INDEX, 2, 2, [0, 0, 0]))
+        .unwrap();
+
+    assert!(cache.get(0, 0).unwrap().is_none());
+    assert_eq!(cache.persistent_keys(), []);
+}
+
+#[test]
+fn keys_of_persistent_caches_are_exported() {
+    let mut cache = bitmap_cache();
+
+    cache.insert(&cache_bitmap(0, 1, 2, 2, [0xaa, 0, 0])).unwrap();
+    cache.insert(&cache_bitmap(1, 0, 2, 2, [0xbb, 0, 0])).unwrap();
+
+    let mut without_key = cache_bitmap(0, 0, 2, 2, [0xcc, 0, 0]);
+    without_key.flags = CacheBitmapRev2Flags::empty();
+    without_key.key = None;
+    cache.insert(&without_key).unwrap();
+
+    assert_eq!(
+        cache.persistent_keys(),
+        [PersistentKey {
+            cache_id: 0,
+            cache_index: 1,
+            key: 0x0000_0001_0000_00aa,
+        }]
+    );
+}
+
+#[test]
+fn ternary_raster_operations() {
+    let (p, s, d) = (0xf0, 0xcc, 0xaa);
+
+    // The operation codes are the results for these pattern, source and destination values.
+    for rop in [0x00, 0x33, 0x55, 0x66, 0x88, 0xc0, 0xcc, 0xee, 0xf0, 0xff] {
+        assert_eq!(rop3(rop, p, s, d), rop);
+    }
+
+    assert_eq!(rop3(0xcc, 0x12, 0x34, 0x56), 0x34); // SRCCOPY
+    assert_eq!(rop3(0x66, 0x12, 0x34, 0x56), 0x34 ^ 0x56); // SRCINVERT
+    assert_eq!(rop3(0xc0, 0x12, 0x34, 0x56), 0x12 & 0x34); // MERGECOPY
+    assert_eq!(rop3(0x55, 0x12, 0x34, 0x56), !0x56); // DSTINVERT
+}
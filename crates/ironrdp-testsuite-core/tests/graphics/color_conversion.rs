@@ -1654,3 +1654,21 @@ const RGB_BUFFER: [u8; 4 * 64 * 64] = [
     0xFF, 0xF6, 0x9D, 0x13, 0xFF, 0xF6, 0x9C, 0x12, 0xFF, 0xF5, 0x9A, 0x11, 0xFF, 0xF5, 0x9A, 0x11, 0xFF, 0xF5, 0x9A,
     0x11, 0xFF, 0xF5, 0x9A, 0x11, 0xFF,
 ];
+
+#[test]
+fn rdp_15bit_to_rgb_expands_channels() {
+    assert_eq!(rdp_15bit_to_rgb(0x7c00), [0xff, 0x00, 0x00]);
+    assert_eq!(rdp_15bit_to_rgb(0x03e0), [0x00, 0xff, 0x00]);
+    assert_eq!(rdp_15bit_to_rgb(0x001f), [0x00, 0x00, 0xff]);
+    assert_eq!(rdp_15bit_to_rgb(0x4210), [0x84, 0x84, 0x84]);
+    // The unused high bit is ignored.
+    assert_eq!(rdp_15bit_to_rgb(0x8000), [0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn rdp_16bit_to_rgb_expands_channels() {
+    assert_eq!(rdp_16bit_to_rgb(0xf800), [0xff, 0x00, 0x00]);
+    assert_eq!(rdp_16bit_to_rgb(0x07e0), [0x00, 0xff, 0x00]);
+    assert_eq!(rdp_16bit_to_rgb(0x001f), [0x00, 0x00, 0xff]);
+    assert_eq!(rdp_16bit_to_rgb(0x8410), [0x84, 0x82, 0x84]);
+}
@@ -1,5 +1,7 @@
+mod bitmap_cache;
 mod color_conversion;
 mod dwt;
 mod image_processing;
 mod rle;
 mod rlgr;
+mod scaling;
@@ -165,6 +165,19 @@ fn encode_correctly_encodes_rlgr1() {
     assert_eq!(expected.as_ref(), output.as_slice());
 }
 
+#[test]
+fn rlgr1_round_trips_zeros_in_golomb_rice_mode() {
+    let input = [-1, 2, 0, 0, -1, 0, -1, 0, 3, 0, 0, 0, 1, -2];
+    let mode = EntropyAlgorithm::Rlgr1;
+
+    let mut encoded = vec![0; 64];
+    let len = encode(mode, input.as_ref(), encoded.as_mut_slice()).unwrap();
+
+    let mut output = vec![0i16; input.len()];
+    decode(mode, &encoded[..len], output.as_mut_slice()).unwrap();
+    assert_eq!(input.as_ref(), output.as_slice());
+}
+
 const Y_DATA_ENCODED: [u8; 942] = [
     0xc0, 0x01, 0x01, 0x15, 0x48, 0x99, 0xc7, 0x41, 0xa1, 0x12, 0x68, 0x11, 0xdc, 0x22, 0x29, 0x74, 0xef, 0xfd, 0x20,
     0x92, 0xe0, 0x4e, 0xa8, 0x69, 0x3b, 0xfd, 0x41, 0x83, 0xbf, 0x28, 0x53, 0x0c, 0x1f, 0xe2, 0x54, 0x0c, 0x77, 0x7c,
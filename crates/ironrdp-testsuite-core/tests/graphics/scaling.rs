@@ -0,0 +1,200 @@
+use ironrdp_graphics::scaling::{Scaler, ScalingMode};
+use ironrdp_pdu::geometry::InclusiveRectangle;
+
+const MODES: [ScalingMode; 3] = [ScalingMode::Nearest, ScalingMode::Bilinear, ScalingMode::Lanczos3];
+
+fn rectangle(left: u16, top: u16, right: u16, bottom: u16) -> InclusiveRectangle {
+    InclusiveRectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+fn full(size: (u16, u16)) -> InclusiveRectangle {
+    rectangle(0, 0, size.0 - 1, size.1 - 1)
+}
+
+/// Returns an image with a distinct value in each channel of each pixel.
+fn gradient(size: (u16, u16), seed: u32) -> Vec<u32> {
+    (0..u32::from(size.0) * u32::from(size.1))
+        .map(|i| i.wrapping_mul(0x0101_0101).wrapping_add(seed.wrapping_mul(0x2f3b_4d17)))
+        .collect()
+}
+
+fn pixel_count(size: (u16, u16)) -> usize {
+    usize::from(size.0) * usize::from(size.1)
+}
+
+#[test]
+fn identity_nearest_region_is_unchanged() {
+    let scaler = Scaler::new(ScalingMode::Nearest, (64, 48), (64, 48));
+
+    let region = rectangle(3, 5, 17, 21);
+    assert_eq!(scaler.destination_region(&region), Some(region));
+}
+
+#[test]
+fn integer_upscale_nearest_region() {
+    let scaler = Scaler::new(ScalingMode::Nearest, (64, 48), (128, 96));
+
+    assert_eq!(
+        scaler.destination_region(&rectangle(1, 1, 2, 3)),
+        Some(rectangle(2, 2, 5, 7))
+    );
+}
+
+#[test]
+fn full_region_maps_to_full_destination() {
+    for mode in MODES {
+        for (source, destination) in [
+            ((1920, 1080), (2880, 1620)),
+            ((1920, 1080), (2560, 1440)),
+            ((1366, 768), (1024, 576)),
+            ((7, 5), (11, 3)),
+        ] {
+            let scaler = Scaler::new(mode, source, destination);
+
+            assert_eq!(
+                scaler.destination_region(&full(source)),
+                Some(full(destination)),
+                "{mode:?} {source:?} -> {destination:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn edge_pixels_reach_the_destination_edges() {
+    for mode in MODES {
+        let source = (1920, 1080);
+        let destination = (2880, 1620);
+        let scaler = Scaler::new(mode, source, destination);
+
+        let bottom_right = scaler
+            .destination_region(&rectangle(source.0 - 1, source.1 - 1, source.0 - 1, source.1 - 1))
+            .unwrap();
+        assert_eq!(
+            (bottom_right.right, bottom_right.bottom),
+            (destination.0 - 1, destination.1 - 1)
+        );
+        assert!(bottom_right.left > destination.0 - 8, "{mode:?} {bottom_right:?}");
+
+        let top_left = scaler.destination_region(&rectangle(0, 0, 0, 0)).unwrap();
+        assert_eq!((top_left.left, top_left.top), (0, 0));
+        assert!(top_left.right < 8, "{mode:?} {top_left:?}");
+    }
+}
+
+#[test]
+fn region_grows_with_the_filter_radius() {
+    let region = rectangle(100, 100, 109, 109);
+    let width = |mode| {
+        let region = Scaler::new(mode, (1920, 1080), (2880, 1620))
+            .destination_region(&region)
+            .unwrap();
+        region.right - region.left + 1
+    };
+
+    assert_eq!(width(ScalingMode::Nearest), 15);
+    assert!(width(ScalingMode::Bilinear) > width(ScalingMode::Nearest));
+    assert!(width(ScalingMode::Lanczos3) > width(ScalingMode::Bilinear));
+}
+
+#[test]
+fn region_outside_of_the_source_is_ignored() {
+    let scaler = Scaler::new(ScalingMode::Bilinear, (64, 48), (96, 72));
+
+    assert_eq!(scaler.destination_region(&rectangle(64, 0, 80, 10)), None);
+
+    // Regions overlapping the edge are clipped.
+    assert_eq!(
+        scaler.destination_region(&rectangle(0, 0, 200, 200)),
+        Some(full((96, 72)))
+    );
+}
+
+#[test]
+fn region_scaling_matches_full_scaling() {
+    let source_size = (97, 61);
+    let destination_size = (143, 89);
+    let region = rectangle(40, 20, 55, 33);
+
+    for mode in MODES {
+        let mut scaler = Scaler::new(mode, source_size, destination_size);
+
+        let before = gradient(source_size, 1);
+        let mut after = before.clone();
+        let updated = gradient(source_size, 2);
+        for y in region.top..=region.bottom {
+            let start = usize::from(y) * usize::from(source_size.0);
+            let columns = start + usize::from(region.left)..=start + usize::from(region.right);
+            after[columns.clone()].copy_from_slice(&updated[columns]);
+        }
+
+        let mut expected = vec![0; pixel_count(destination_size)];
+        scaler.scale(&after, &mut expected, &full(source_size));
+
+        let mut actual = vec![0; pixel_count(destination_size)];
+        scaler.scale(&before, &mut actual, &full(source_size));
+        let written = scaler.scale(&after, &mut actual, &region);
+
+        assert_eq!(written, scaler.destination_region(&region));
+        assert!(actual == expected, "{mode:?}");
+    }
+}
+
+#[test]
+fn uniform_image_stays_uniform() {
+    const COLOR: u32 = 0x00c0_8040;
+
+    for mode in MODES {
+        for destination_size in [(150, 90), (70, 40)] {
+            let source_size = (100, 60);
+            let mut scaler = Scaler::new(mode, source_size, destination_size);
+
+            let source = vec![COLOR; pixel_count(source_size)];
+            let mut destination = vec![0; pixel_count(destination_size)];
+            scaler.scale(&source, &mut destination, &full(source_size));
+
+            assert!(destination.iter().all(|pixel| *pixel == COLOR), "{mode:?}");
+        }
+    }
+}
+
+#[test]
+fn identity_scaling_copies_the_image() {
+    let size = (33, 17);
+    let source = gradient(size, 3);
+
+    for mode in MODES {
+        let mut scaler = Scaler::new(mode, size, size);
+        let mut destination = vec![0; pixel_count(size)];
+        scaler.scale(&source, &mut destination, &full(size));
+
+        assert!(destination == source, "{mode:?}");
+    }
+}
+
+#[test]
+fn pixels_outside_of_the_written_region_are_untouched() {
+    const UNTOUCHED: u32 = 0xdead_beef;
+
+    let source_size = (40, 30);
+    let destination_size = (60, 45);
+    let mut scaler = Scaler::new(ScalingMode::Lanczos3, source_size, destination_size);
+
+    let source = gradient(source_size, 4);
+    let mut destination = vec![UNTOUCHED; pixel_count(destination_size)];
+    let written = scaler
+        .scale(&source, &mut destination, &rectangle(10, 10, 12, 12))
+        .unwrap();
+
+    for (i, pixel) in destination.iter().enumerate() {
+        let x = u16::try_from(i % usize::from(destination_size.0)).unwrap();
+        let y = u16::try_from(i / usize::from(destination_size.0)).unwrap();
+        let inside = (written.left..=written.right).contains(&x) && (written.top..=written.bottom).contains(&y);
+        assert_eq!(*pixel == UNTOUCHED, !inside, "({x}, {y})");
+    }
+}
@@ -0,0 +1,199 @@
+use ironrdp_input::*;
+use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
+
+const A: u8 = 0x1E;
+const KEY_A: Scancode = Scancode::from_u8(false, A);
+const BACKSPACE: u8 = 0x0E;
+
+fn unicode(flags: KeyboardFlags, code: u16) -> FastPathInputEvent {
+    FastPathInputEvent::UnicodeKeyboardEvent(flags, code)
+}
+
+fn typed(text: &str) -> Vec<FastPathInputEvent> {
+    text.encode_utf16()
+        .flat_map(|code| {
+            [
+                unicode(KeyboardFlags::empty(), code),
+                unicode(KeyboardFlags::RELEASE, code),
+            ]
+        })
+        .collect()
+}
+
+fn backspaces(count: usize) -> Vec<FastPathInputEvent> {
+    (0..count)
+        .flat_map(|_| {
+            [
+                FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), BACKSPACE),
+                FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, BACKSPACE),
+            ]
+        })
+        .collect()
+}
+
+/// Returns the events of the operations let through by `composition`.
+fn filtered(
+    composition: &mut Composition,
+    database: &mut Database,
+    operations: impl IntoIterator<Item = Operation>,
+) -> Vec<FastPathInputEvent> {
+    let operations: Vec<_> = operations
+        .into_iter()
+        .filter_map(|operation| composition.filter(operation))
+        .collect();
+    database.apply(operations).into_vec()
+}
+
+#[test]
+fn multi_character_commit_is_typed_in_order() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    composition.start();
+    composition.end();
+    let events = database.apply(composition.commit("日本語", 0));
+
+    assert_eq!(events.into_vec(), typed("日本語"));
+}
+
+#[test]
+fn repeated_characters_are_each_typed() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    let events = database.apply(composition.commit("ああ", 0));
+
+    assert_eq!(events.into_vec(), typed("ああ"));
+}
+
+#[test]
+fn surrogate_pair_is_not_split() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    let events = database.apply(composition.commit("𠮷", 0));
+
+    assert_eq!(
+        events.into_vec(),
+        [
+            unicode(KeyboardFlags::empty(), 0xD842),
+            unicode(KeyboardFlags::empty(), 0xDFB7),
+            unicode(KeyboardFlags::RELEASE, 0xD842),
+            unicode(KeyboardFlags::RELEASE, 0xDFB7),
+        ]
+    );
+}
+
+#[test]
+fn key_presses_are_suppressed_during_composition() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    composition.start();
+    let events = filtered(
+        &mut composition,
+        &mut database,
+        [
+            Operation::KeyPressed(KEY_A),
+            Operation::UnicodeKeyPressed('a'),
+            Operation::KeyReleased(KEY_A),
+            Operation::UnicodeKeyReleased('a'),
+        ],
+    );
+
+    assert!(composition.is_active());
+    assert!(events.is_empty());
+}
+
+#[test]
+fn key_pressed_before_composition_is_released() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    filtered(&mut composition, &mut database, [Operation::KeyPressed(KEY_A)]);
+    composition.start();
+    let events = filtered(&mut composition, &mut database, [Operation::KeyReleased(KEY_A)]);
+
+    assert_eq!(events, [FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, A)]);
+}
+
+#[test]
+fn cancelled_composition_types_nothing() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    composition.start();
+    let suppressed = filtered(&mut composition, &mut database, [Operation::KeyPressed(KEY_A)]);
+    composition.end();
+    let events = filtered(
+        &mut composition,
+        &mut database,
+        [Operation::KeyPressed(KEY_A), Operation::KeyReleased(KEY_A)],
+    );
+
+    assert!(suppressed.is_empty());
+    assert!(!composition.is_active());
+    assert_eq!(
+        events,
+        [
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), A),
+            FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, A),
+        ]
+    );
+}
+
+#[test]
+fn correction_erases_the_replaced_characters() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    database.apply(composition.commit("かん", 0));
+    let events = database.apply(composition.commit("漢", 2));
+
+    let mut expected = backspaces(2);
+    expected.extend(typed("漢"));
+    assert_eq!(events.into_vec(), expected);
+}
+
+#[test]
+fn correction_is_bounded_by_the_committed_text() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    database.apply(composition.commit("か", 0));
+    let events = database.apply(composition.commit("蚊", 5));
+
+    let mut expected = backspaces(1);
+    expected.extend(typed("蚊"));
+    assert_eq!(events.into_vec(), expected);
+}
+
+#[test]
+fn correction_spans_the_consecutive_commits() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    database.apply(composition.commit("か", 0));
+    database.apply(composition.commit("ん", 0));
+    let events = database.apply(composition.commit("漢", 2));
+
+    let mut expected = backspaces(2);
+    expected.extend(typed("漢"));
+    assert_eq!(events.into_vec(), expected);
+}
+
+#[test]
+fn typing_outside_composition_prevents_correction() {
+    let mut composition = Composition::new();
+    let mut database = Database::new();
+
+    database.apply(composition.commit("かん", 0));
+    filtered(
+        &mut composition,
+        &mut database,
+        [Operation::KeyPressed(KEY_A), Operation::KeyReleased(KEY_A)],
+    );
+    let events = database.apply(composition.commit("漢", 2));
+
+    assert_eq!(events.into_vec(), typed("漢"));
+}
@@ -1,2 +1,4 @@
+mod composition;
 mod fastpath_packets;
+mod shortcut;
 mod smoke;
@@ -0,0 +1,163 @@
+use ironrdp_input::*;
+
+const LEFT_CTRL: Scancode = Scancode::from_u8(false, 0x1D);
+const RIGHT_CTRL: Scancode = Scancode::from_u8(true, 0x1D);
+const LEFT_SHIFT: Scancode = Scancode::from_u8(false, 0x2A);
+const LEFT_META: Scancode = Scancode::from_u8(true, 0x5B);
+const KEY_C: Scancode = Scancode::from_u8(false, 0x2E);
+const KEY_T: Scancode = Scancode::from_u8(false, 0x14);
+const KEY_W: Scancode = Scancode::from_u8(false, 0x11);
+
+fn database_with(keys: &[Scancode]) -> Database {
+    let mut database = Database::new();
+    database.apply(keys.iter().copied().map(Operation::KeyPressed));
+    database
+}
+
+fn browser_policy() -> KeyboardCapturePolicy {
+    let mut policy = KeyboardCapturePolicy::new();
+    policy.set_action(
+        Chord::new(Modifiers::CTRL | Modifiers::SHIFT, KEY_T),
+        ShortcutAction::LetBrowserHandle,
+    );
+    policy.set_action(Chord::new(Modifiers::CTRL, KEY_W), ShortcutAction::Forward);
+    policy
+}
+
+#[test]
+fn modifiers_are_matched_regardless_of_the_press_order() {
+    let policy = browser_policy();
+
+    for keys in [[LEFT_CTRL, LEFT_SHIFT], [LEFT_SHIFT, LEFT_CTRL]] {
+        let database = database_with(&keys);
+        let modifiers = Modifiers::from_database(&database);
+
+        assert_eq!(modifiers, Modifiers::SHIFT | Modifiers::CTRL);
+        assert_eq!(policy.action(modifiers, KEY_T), ShortcutAction::LetBrowserHandle);
+    }
+}
+
+#[test]
+fn both_sides_of_a_modifier_are_matched() {
+    let policy = browser_policy();
+
+    let modifiers = Modifiers::from_database(&database_with(&[RIGHT_CTRL]));
+
+    assert_eq!(modifiers, Modifiers::CTRL);
+    assert_eq!(policy.action(modifiers, KEY_W), ShortcutAction::Forward);
+}
+
+#[test]
+fn chord_is_matched_with_the_exact_modifiers() {
+    let mut policy = KeyboardCapturePolicy::new();
+    policy.set_action(Chord::new(Modifiers::CTRL, KEY_W), ShortcutAction::LetBrowserHandle);
+
+    assert_eq!(policy.action(Modifiers::CTRL, KEY_W), ShortcutAction::LetBrowserHandle);
+    assert_eq!(
+        policy.action(Modifiers::CTRL | Modifiers::SHIFT, KEY_W),
+        ShortcutAction::Forward
+    );
+    assert_eq!(policy.action(Modifiers::empty(), KEY_W), ShortcutAction::Forward);
+}
+
+#[test]
+fn later_action_replaces_the_previous_one() {
+    let mut policy = browser_policy();
+    policy.set_action(
+        Chord::new(Modifiers::SHIFT | Modifiers::CTRL, KEY_T),
+        ShortcutAction::Forward,
+    );
+
+    assert_eq!(
+        policy.action(Modifiers::CTRL | Modifiers::SHIFT, KEY_T),
+        ShortcutAction::Forward
+    );
+    assert_eq!(policy.prevent_default_chords().len(), 2);
+}
+
+#[test]
+fn modifier_keys_are_always_forwarded() {
+    let mut policy = KeyboardCapturePolicy::new();
+    policy.set_action(
+        Chord::new(Modifiers::CTRL, LEFT_SHIFT),
+        ShortcutAction::LetBrowserHandle,
+    );
+
+    assert_eq!(policy.action(Modifiers::CTRL, LEFT_SHIFT), ShortcutAction::Forward);
+}
+
+#[test]
+fn browser_handled_key_press_is_dropped() {
+    let policy = browser_policy();
+    let mut database = database_with(&[LEFT_CTRL, LEFT_SHIFT]);
+
+    let operations = policy.intercept(&database, Operation::KeyPressed(KEY_T));
+    assert!(operations.is_empty());
+
+    // Since the key press was dropped, the release is ignored.
+    let operations = policy.intercept(&database, Operation::KeyReleased(KEY_T));
+    assert!(database.apply(operations).is_empty());
+}
+
+#[test]
+fn remapped_modifier_is_substituted_and_restored() {
+    let mut policy = KeyboardCapturePolicy::new();
+    policy.remap_modifier(Modifiers::META, Modifiers::CTRL);
+
+    assert_eq!(
+        policy.action(Modifiers::META, KEY_C),
+        ShortcutAction::Remap(Chord::new(Modifiers::CTRL, KEY_C))
+    );
+
+    let mut database = database_with(&[LEFT_META]);
+    let operations = policy.intercept(&database, Operation::KeyPressed(KEY_C));
+
+    let keys: Vec<_> = operations
+        .iter()
+        .map(|operation| match operation {
+            Operation::KeyPressed(scancode) => (true, *scancode),
+            Operation::KeyReleased(scancode) => (false, *scancode),
+            _ => panic!("unexpected operation: {operation:?}"),
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            (false, LEFT_META),
+            (true, LEFT_CTRL),
+            (true, KEY_C),
+            (false, KEY_C),
+            (false, LEFT_CTRL),
+            (true, LEFT_META),
+        ]
+    );
+
+    database.apply(operations);
+    assert_eq!(Modifiers::from_database(&database), Modifiers::META);
+    assert!(!database.is_key_pressed(KEY_C));
+}
+
+#[test]
+fn explicit_rule_takes_precedence_over_modifier_remap() {
+    let mut policy = KeyboardCapturePolicy::new();
+    policy.remap_modifier(Modifiers::META, Modifiers::CTRL);
+    policy.set_action(Chord::new(Modifiers::META, KEY_W), ShortcutAction::LetBrowserHandle);
+
+    assert_eq!(policy.action(Modifiers::META, KEY_W), ShortcutAction::LetBrowserHandle);
+    assert_eq!(
+        policy.action(Modifiers::META | Modifiers::SHIFT, KEY_W),
+        ShortcutAction::Remap(Chord::new(Modifiers::CTRL | Modifiers::SHIFT, KEY_W))
+    );
+}
+
+#[test]
+fn prevent_default_chords_lists_the_captured_chords() {
+    let mut policy = browser_policy();
+    let remapped = Chord::new(Modifiers::ALT, KEY_T);
+    policy.set_action(remapped, ShortcutAction::Remap(Chord::new(Modifiers::CTRL, KEY_T)));
+
+    assert_eq!(
+        policy.prevent_default_chords(),
+        [Chord::new(Modifiers::CTRL, KEY_W), remapped]
+    );
+}
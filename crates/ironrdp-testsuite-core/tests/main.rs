@@ -11,15 +11,27 @@
 //! Cargo will run all tests from a single binary in parallel, but
 //! binaries themselves are run sequentally.
 
+mod acceptor;
+mod allocations;
+mod buf_pool;
+mod bulk;
 mod clipboard;
+mod credssp;
 mod displaycontrol;
 mod dvc;
+mod error_code;
 mod fuzz_regression;
 mod graphics;
 mod input;
 mod pcb;
 mod pdu;
+mod pdu_derive;
+mod rail;
 mod rdcleanpath;
+mod rdpdr;
+mod rdpei;
 mod rdpsnd;
+mod server;
 mod server_name;
 mod session;
+mod svc;
@@ -0,0 +1,174 @@
+use ironrdp_pdu::rdp::capability_sets::{
+    BitmapCacheRev2, CacheFlags, CapabilitySet, CellInfo, ClientCapabilitiesBuilder, ClientCapabilitiesError, CmdFlags,
+    Codec, CodecProperty, General, GeneralExtraFlags, LargePointerSupportFlags, MultifragmentUpdate, NsCodec,
+    OrderSupportIndex,
+};
+use rstest::rstest;
+
+fn nscodec() -> Codec {
+    Codec {
+        id: 0x01,
+        property: CodecProperty::NsCodec(NsCodec {
+            is_dynamic_fidelity_allowed: true,
+            is_subsampling_allowed: true,
+            color_loss_level: 3,
+        }),
+    }
+}
+
+fn general(capability_sets: &[CapabilitySet]) -> &General {
+    capability_sets
+        .iter()
+        .find_map(|capability_set| match capability_set {
+            CapabilitySet::General(general) => Some(general),
+            _ => None,
+        })
+        .expect("General capability set")
+}
+
+#[test]
+fn default_builder_is_consistent() {
+    let capability_sets = ClientCapabilitiesBuilder::new(1024, 768).build().unwrap();
+
+    assert!(general(&capability_sets)
+        .extra_flags
+        .contains(GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED));
+    assert_eq!(
+        capability_sets.last(),
+        Some(&CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+            max_request_size: ClientCapabilitiesBuilder::DEFAULT_MULTIFRAGMENT_MAX_REQUEST_SIZE,
+        }))
+    );
+}
+
+#[test]
+fn fast_path_output_sets_the_general_flag() {
+    let capability_sets = ClientCapabilitiesBuilder::new(1024, 768)
+        .with_fast_path_output(false)
+        .with_large_pointer(LargePointerSupportFlags::UP_TO_96X96_PIXELS)
+        .build()
+        .unwrap();
+
+    assert!(!general(&capability_sets)
+        .extra_flags
+        .contains(GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED));
+}
+
+#[test]
+fn bitmap_cache_rev2_replaces_the_empty_bitmap_cache() {
+    let bitmap_cache = BitmapCacheRev2 {
+        cache_flags: CacheFlags::PERSISTENT_KEYS_EXPECTED_FLAG,
+        num_cell_caches: 1,
+        cache_cell_info: [CellInfo {
+            num_entries: 600,
+            is_cache_persistent: true,
+        }; 5],
+    };
+
+    let capability_sets = ClientCapabilitiesBuilder::new(1024, 768)
+        .with_bitmap_cache_rev2(bitmap_cache.clone())
+        .build()
+        .unwrap();
+
+    assert!(capability_sets.contains(&CapabilitySet::BitmapCacheRev2(bitmap_cache)));
+    assert!(!capability_sets
+        .iter()
+        .any(|capability_set| matches!(capability_set, CapabilitySet::BitmapCache(_))));
+
+    let mut order = capability_sets
+        .iter()
+        .find_map(|capability_set| match capability_set {
+            CapabilitySet::Order(order) => Some(order.clone()),
+            _ => None,
+        })
+        .expect("Order capability set");
+    assert!(order.get_support_flag(OrderSupportIndex::MemBlt));
+    assert!(order.get_support_flag(OrderSupportIndex::Mem3Blt));
+}
+
+#[test]
+fn raw_capability_sets_precede_the_multifragment_update() {
+    let rail = CapabilitySet::Rail(vec![0x03, 0x00, 0x00, 0x00]);
+
+    let capability_sets = ClientCapabilitiesBuilder::new(1024, 768)
+        .with_raw_capability_set(rail.clone())
+        .with_multifragment_update(65535)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        capability_sets[capability_sets.len() - 2..],
+        [
+            rail,
+            CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+                max_request_size: 65535
+            })
+        ]
+    );
+}
+
+#[rstest]
+#[case::empty_width(
+    ClientCapabilitiesBuilder::new(0, 768),
+    ClientCapabilitiesError::EmptyDesktop { width: 0, height: 768 }
+)]
+#[case::empty_height(
+    ClientCapabilitiesBuilder::new(1024, 0),
+    ClientCapabilitiesError::EmptyDesktop { width: 1024, height: 0 }
+)]
+#[case::color_depth(
+    ClientCapabilitiesBuilder::new(1024, 768).with_color_depth(12),
+    ClientCapabilitiesError::UnsupportedColorDepth(12)
+)]
+#[case::surface_commands_without_codec(
+    ClientCapabilitiesBuilder::new(1024, 768).with_surface_commands(CmdFlags::SET_SURFACE_BITS),
+    ClientCapabilitiesError::SurfaceCommandsWithoutCodec
+)]
+#[case::frame_acknowledge_without_frame_marker(
+    ClientCapabilitiesBuilder::new(1024, 768)
+        .with_surface_commands(CmdFlags::SET_SURFACE_BITS)
+        .with_codecs(vec![nscodec()])
+        .with_frame_acknowledge(2),
+    ClientCapabilitiesError::FrameAcknowledgeWithoutFrameMarker
+)]
+#[case::large_pointer_without_fast_path_output(
+    ClientCapabilitiesBuilder::new(1024, 768).with_fast_path_output(false),
+    ClientCapabilitiesError::LargePointerWithoutFastPathOutput
+)]
+#[case::duplicate_generated_set(
+    ClientCapabilitiesBuilder::new(1024, 768)
+        .with_raw_capability_set(CapabilitySet::Rail(vec![0x03, 0x00, 0x00, 0x00]))
+        .with_raw_capability_set(CapabilitySet::General(General::default())),
+    ClientCapabilitiesError::DuplicateCapabilitySet { index: 1 }
+)]
+#[case::duplicate_multifragment_update(
+    ClientCapabilitiesBuilder::new(1024, 768)
+        .with_raw_capability_set(CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate { max_request_size: 0 })),
+    ClientCapabilitiesError::DuplicateCapabilitySet { index: 0 }
+)]
+#[case::duplicate_raw_set(
+    ClientCapabilitiesBuilder::new(1024, 768)
+        .with_raw_capability_set(CapabilitySet::Rail(vec![0x03, 0x00, 0x00, 0x00]))
+        .with_raw_capability_set(CapabilitySet::Rail(vec![0x01, 0x00, 0x00, 0x00])),
+    ClientCapabilitiesError::DuplicateCapabilitySet { index: 1 }
+)]
+fn inconsistent_capabilities_are_rejected(
+    #[case] builder: ClientCapabilitiesBuilder,
+    #[case] expected: ClientCapabilitiesError,
+) {
+    assert_eq!(builder.build(), Err(expected));
+}
+
+#[test]
+fn surface_commands_with_codec_are_accepted() {
+    let capability_sets = ClientCapabilitiesBuilder::new(1024, 768)
+        .with_surface_commands(CmdFlags::SET_SURFACE_BITS | CmdFlags::FRAME_MARKER)
+        .with_codecs(vec![nscodec()])
+        .with_frame_acknowledge(2)
+        .build()
+        .unwrap();
+
+    assert!(capability_sets
+        .iter()
+        .any(|capability_set| matches!(capability_set, CapabilitySet::FrameAcknowledge(_))));
+}
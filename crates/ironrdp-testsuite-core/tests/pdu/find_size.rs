@@ -0,0 +1,42 @@
+use ironrdp_pdu::{find_size, Action, PduInfo};
+
+#[test]
+fn fast_path_length_forms() {
+    let cases: [(&[u8], usize); 5] = [
+        (&[0x00, 0x7f], 0x7f),
+        (&[0x00, 0x80, 0x7f], 0x7f),
+        (&[0x00, 0x80, 0x80], 0x80),
+        (&[0x00, 0xbf, 0xff], 0x3fff),
+        // Zero-payload frames.
+        (&[0x00, 0x02], 2),
+    ];
+
+    for (bytes, length) in cases {
+        assert_eq!(
+            find_size(bytes).unwrap(),
+            Some(PduInfo {
+                action: Action::FastPath,
+                length
+            })
+        );
+    }
+}
+
+#[test]
+fn incomplete_header_has_unknown_size() {
+    for bytes in [&[][..], &[0x00], &[0x00, 0x80], &[0x03, 0x00, 0x00]] {
+        assert_eq!(find_size(bytes).unwrap(), None);
+    }
+}
+
+#[test]
+fn length_smaller_than_header_is_rejected() {
+    for bytes in [
+        &[0x00, 0x00][..],
+        &[0x00, 0x01],
+        &[0x00, 0x80, 0x02],
+        &[0x03, 0x00, 0x00, 0x03],
+    ] {
+        assert!(find_size(bytes).is_err());
+    }
+}
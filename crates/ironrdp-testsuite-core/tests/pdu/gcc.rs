@@ -1,11 +1,14 @@
 use ironrdp_core::{decode, encode_vec, DecodeErrorKind, Encode, EncodeErrorKind, ReadCursor};
 use ironrdp_pdu::gcc::*;
+use ironrdp_pdu::{DecodeOptions, DecodeWarning};
 use ironrdp_testsuite_core::cluster_data::*;
 use ironrdp_testsuite_core::conference_create::*;
 use ironrdp_testsuite_core::core_data::*;
 use ironrdp_testsuite_core::gcc::*;
 use ironrdp_testsuite_core::network_data::*;
 use ironrdp_testsuite_core::security_data::*;
+use proptest::collection::vec;
+use proptest::prelude::*;
 
 #[test]
 fn from_buffer_correctly_parses_client_gcc_blocks_without_optional_data_blocks() {
@@ -135,6 +138,169 @@ fn from_buffer_fails_on_invalid_gcc_type_for_server_gcc_blocks() {
     assert!(decode::<ServerGccBlocks>(buffer.as_slice()).is_err());
 }
 
+// The non-conformant blobs below are synthetic: they are built from the conformant test vectors to exercise the
+// lenient decoding paths, and are not captures of real servers. They don't prove interoperability with any server.
+
+#[test]
+fn unknown_server_gcc_block_is_kept() {
+    let unknown_block = [0xff, 0x0c, 0x08, 0x00, 0x01, 0x02, 0x03, 0x04];
+    let buffer = [
+        SERVER_GCC_CORE_BLOCK_BUFFER.as_slice(),
+        unknown_block.as_slice(),
+        SERVER_GCC_NETWORK_BLOCK_BUFFER.as_slice(),
+        SERVER_GCC_SECURITY_BLOCK_BUFFER.as_slice(),
+    ]
+    .concat();
+
+    let mut expected = SERVER_GCC_WITHOUT_OPTIONAL_FIELDS.clone();
+    expected
+        .unknown
+        .push(RawGccBlock::new(0x0cff, &[0x01, 0x02, 0x03, 0x04]));
+
+    assert_eq!(expected, decode::<ServerGccBlocks>(&buffer).unwrap());
+
+    let mut warnings = Vec::new();
+    let blocks =
+        ServerGccBlocks::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut warnings)
+            .unwrap();
+
+    assert_eq!(expected, blocks);
+    assert_eq!(warnings, [DecodeWarning::UnknownGccBlock { block_type: 0x0cff }]);
+}
+
+#[test]
+fn last_duplicated_server_gcc_block_wins_and_unknown_blocks_are_kept_in_order() {
+    // Synthetic: models a broker appending its own network block and a vendor block after the server ones.
+    let vendor_block = [0xff, 0x0c, 0x06, 0x00, 0xaa, 0xbb];
+    let second_network_block = [0x03, 0x0c, 0x08, 0x00, 0xec, 0x03, 0x00, 0x00];
+    let other_vendor_block = [0xfe, 0x0c, 0x05, 0x00, 0xcc];
+    let buffer = [
+        SERVER_GCC_CORE_BLOCK_BUFFER.as_slice(),
+        SERVER_GCC_NETWORK_BLOCK_BUFFER.as_slice(),
+        vendor_block.as_slice(),
+        SERVER_GCC_SECURITY_BLOCK_BUFFER.as_slice(),
+        second_network_block.as_slice(),
+        other_vendor_block.as_slice(),
+    ]
+    .concat();
+
+    let mut src = ReadCursor::new(&buffer);
+    let user_data = UserDataBlocks::decode(&mut src).unwrap();
+    assert!(src.is_empty());
+    assert_eq!(user_data.get_all(0x0c03).count(), 2);
+    assert_eq!(user_data.last(0x0c03), Some(&second_network_block[4..]));
+
+    let blocks = decode::<ServerGccBlocks>(&buffer).unwrap();
+
+    assert_eq!(
+        blocks.network,
+        ServerNetworkData {
+            channel_ids: Vec::new(),
+            io_channel: 1004,
+        }
+    );
+    assert_eq!(
+        blocks.unknown,
+        [
+            RawGccBlock::new(0x0cff, &[0xaa, 0xbb]),
+            RawGccBlock::new(0x0cfe, &[0xcc])
+        ]
+    );
+
+    // Unknown blocks are re-encoded byte for byte, after the known blocks.
+    let encoded = encode_vec(&blocks).unwrap();
+    assert_eq!(encoded.len(), blocks.size());
+    assert!(encoded.ends_with(&[vendor_block.as_slice(), other_vendor_block.as_slice()].concat()));
+    assert_eq!(blocks, decode::<ServerGccBlocks>(&encoded).unwrap());
+}
+
+#[test]
+fn gcc_block_length_shorter_than_its_header_is_rejected() {
+    let buffer = [
+        SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.as_slice(),
+        [0xff, 0x0c, 0x04, 0x00, 0x00, 0x00].as_slice(),
+    ]
+    .concat();
+
+    match decode::<ServerGccBlocks>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::InvalidField { field: "blockLen", .. }) => (),
+        res => panic!("Expected the invalid block length error, got: {res:?}"),
+    };
+}
+
+#[test]
+fn lenient_decoding_skips_malformed_optional_server_gcc_block() {
+    let truncated_multi_transport_block = [0x08, 0x0c, 0x06, 0x00, 0x01, 0x03];
+    let buffer = [
+        SERVER_GCC_WITHOUT_OPTIONAL_FIELDS_BUFFER.as_slice(),
+        SERVER_GCC_MESSAGE_CHANNEL_BLOCK_BUFFER.as_slice(),
+        truncated_multi_transport_block.as_slice(),
+    ]
+    .concat();
+
+    match decode::<ServerGccBlocks>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => (),
+        res => panic!("Expected the not enough bytes error, got: {res:?}"),
+    };
+
+    let mut warnings = Vec::new();
+    let blocks =
+        ServerGccBlocks::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut warnings)
+            .unwrap();
+
+    assert_eq!(blocks.message_channel, SERVER_GCC_WITH_OPTIONAL_FIELDS.message_channel);
+    assert_eq!(blocks.multi_transport_channel, None);
+    assert!(matches!(
+        warnings.as_slice(),
+        [DecodeWarning::MalformedGccBlock { block_type: 0x0c08, .. }]
+    ));
+}
+
+#[test]
+fn lenient_decoding_ignores_trailing_bytes_after_server_gcc_blocks() {
+    let buffer = [
+        SERVER_GCC_WITH_OPTIONAL_FIELDS_BUFFER.as_slice(),
+        [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00].as_slice(),
+    ]
+    .concat();
+
+    match decode::<ServerGccBlocks>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::InvalidField { field: "blockLen", .. }) => (),
+        res => panic!("Expected the invalid block length error, got: {res:?}"),
+    };
+
+    let mut warnings = Vec::new();
+    let mut src = ReadCursor::new(&buffer);
+    let blocks = ServerGccBlocks::decode_with_options(&mut src, DecodeOptions::LENIENT, &mut warnings).unwrap();
+
+    assert_eq!(*SERVER_GCC_WITH_OPTIONAL_FIELDS, blocks);
+    assert!(src.is_empty());
+    assert_eq!(
+        warnings,
+        [DecodeWarning::TrailingBytes {
+            context: "ServerGccBlocks",
+            count: 6
+        }]
+    );
+}
+
+#[test]
+fn lenient_decoding_still_requires_server_gcc_core_block() {
+    let buffer = [
+        SERVER_GCC_NETWORK_BLOCK_BUFFER.as_slice(),
+        SERVER_GCC_SECURITY_BLOCK_BUFFER.as_slice(),
+    ]
+    .concat();
+
+    let result =
+        ServerGccBlocks::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut Vec::new());
+
+    match result {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::InvalidField { field: "core", .. }) => (),
+        res => panic!("Expected the missing GCC core error, got: {res:?}"),
+    };
+}
+
 #[test]
 fn to_buffer_correctly_serializes_server_gcc_blocks_without_optional_data_blocks() {
     let data = SERVER_GCC_WITHOUT_OPTIONAL_FIELDS.clone();
@@ -210,6 +376,27 @@ fn buffer_length_is_correct_for_client_cluster_data() {
     assert_eq!(expected_buffer_len, len);
 }
 
+#[test]
+fn client_cluster_data_with_session_is_encoded_as_mstsc() {
+    assert_eq!(encode_vec(&*CONSOLE_CLUSTER_DATA).unwrap(), CONSOLE_CLUSTER_DATA_BUFFER);
+    assert_eq!(
+        encode_vec(&*SESSION_ID_CLUSTER_DATA).unwrap(),
+        SESSION_ID_CLUSTER_DATA_BUFFER
+    );
+}
+
+#[test]
+fn client_cluster_data_with_session_is_decoded() {
+    assert_eq!(
+        *CONSOLE_CLUSTER_DATA,
+        decode(CONSOLE_CLUSTER_DATA_BUFFER.as_ref()).unwrap()
+    );
+    assert_eq!(
+        *SESSION_ID_CLUSTER_DATA,
+        decode(SESSION_ID_CLUSTER_DATA_BUFFER.as_ref()).unwrap()
+    );
+}
+
 #[test]
 fn from_buffer_correctly_parses_client_core_data_without_optional_fields() {
     let buffer = CLIENT_CORE_DATA_BUFFER.as_ref();
@@ -760,6 +947,32 @@ fn from_buffer_correctly_parses_server_security_data_with_all_fields() {
     );
 }
 
+#[test]
+fn from_buffer_correctly_parses_server_security_data_without_certificate() {
+    let buffer = SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE_BUFFER;
+
+    let security_data = decode::<ServerSecurityData>(buffer.as_slice()).unwrap();
+
+    assert_eq!(*SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE, security_data);
+    assert!(security_data.is_encrypted());
+    assert!(!security_data.has_server_certificate());
+}
+
+#[test]
+fn from_buffer_correctly_parses_server_security_data_with_large_certificate() {
+    let server_cert = vec![0x30; 4096];
+    let mut buffer = SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS_PREFIX_BUFFER.to_vec();
+    buffer.extend_from_slice(&u32::try_from(SERVER_RANDOM_BUFFER.len()).unwrap().to_le_bytes());
+    buffer.extend_from_slice(&u32::try_from(server_cert.len()).unwrap().to_le_bytes());
+    buffer.extend_from_slice(&SERVER_RANDOM_BUFFER);
+    buffer.extend_from_slice(&server_cert);
+
+    let security_data = decode::<ServerSecurityData>(buffer.as_slice()).unwrap();
+
+    assert!(security_data.has_server_certificate());
+    assert_eq!(security_data.server_cert, server_cert);
+}
+
 #[test]
 fn from_buffer_server_security_data_fails_with_invalid_server_random_length() {
     let buffer = SERVER_SECURITY_DATA_WITH_INVALID_SERVER_RANDOM_BUFFER;
@@ -788,6 +1001,18 @@ fn to_buffer_correctly_serializes_server_security_data_with_optional_fields() {
     assert_eq!(buf, SERVER_SECURITY_DATA_WITH_OPTIONAL_FIELDS_BUFFER.as_slice());
 }
 
+#[test]
+fn to_buffer_correctly_serializes_server_security_data_without_certificate() {
+    let security_data = SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE.clone();
+
+    let buf = encode_vec(&security_data).unwrap();
+    assert_eq!(buf, SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE_BUFFER.as_slice());
+    assert_eq!(
+        security_data.size(),
+        SERVER_SECURITY_DATA_WITHOUT_CERTIFICATE_BUFFER.len()
+    );
+}
+
 #[test]
 fn to_buffer_server_security_data_fails_on_mismatch_of_required_and_optional_fields() {
     let security_data = SERVER_SECURITY_DATA_WITH_MISMATCH_OF_REQUIRED_AND_OPTIONAL_FIELDS.clone();
@@ -867,3 +1092,48 @@ fn buffer_length_is_correct_for_conference_create_response() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+#[test]
+fn conference_create_request_gcc_blocks_are_bounded_by_the_user_data_length() {
+    // Hand-built: the user data length is one byte short of the GCC blocks.
+    let mut buffer = CONFERENCE_CREATE_REQUEST_BUFFER.to_vec();
+    let length_offset = CONFERENCE_CREATE_REQUEST_PREFIX_BUFFER.len() - 1;
+    buffer[length_offset] -= 1;
+
+    match decode::<ConferenceCreateRequest>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::InvalidField { field: "blockLen", .. }) => (),
+        res => panic!("Expected the invalid block length error, got: {res:?}"),
+    };
+}
+
+#[test]
+fn conference_create_response_user_data_length_past_the_end_is_rejected() {
+    let buffer = &CONFERENCE_CREATE_RESPONSE_BUFFER[..CONFERENCE_CREATE_RESPONSE_BUFFER.len() - 1];
+
+    match decode::<ConferenceCreateResponse>(buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => (),
+        res => panic!("Expected the not enough bytes error, got: {res:?}"),
+    };
+}
+
+#[test]
+fn client_gcc_blocks_with_unknown_blocks_round_trip() {
+    // A block holds at least one byte after its header.
+    let unknown_block = (any::<u16>(), vec(any::<u8>(), 1..64))
+        .prop_filter("known client GCC type", |(block_type, _)| {
+            !(0xc001..=0xc008).contains(block_type) && *block_type != 0xc00a
+        })
+        .prop_map(|(block_type, data)| RawGccBlock { block_type, data });
+
+    proptest!(|(unknown in vec(unknown_block, 0..8))| {
+        let blocks = ClientGccBlocks {
+            unknown,
+            ..CLIENT_GCC_WITH_ALL_OPTIONAL_FIELDS.clone()
+        };
+
+        let encoded = encode_vec(&blocks).unwrap();
+
+        prop_assert_eq!(encoded.len(), blocks.size());
+        prop_assert_eq!(blocks, decode::<ClientGccBlocks>(&encoded).unwrap());
+    });
+}
@@ -1,7 +1,13 @@
-use ironrdp_core::{decode_cursor, encode_vec, ReadCursor};
-use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_core::{decode, decode_cursor, encode_vec, ReadCursor};
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
 use ironrdp_pdu::input::mouse::PointerFlags;
-use ironrdp_pdu::input::MousePdu;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
+use ironrdp_pdu::input::mouse_x::PointerXFlags;
+use ironrdp_pdu::input::sync::SyncToggleFlags;
+use ironrdp_pdu::input::{
+    scan_code, unicode, InputEvent, InputEventPdu, MousePdu, MouseRelPdu, MouseXPdu, ScanCodePdu, SyncPdu, UnicodePdu,
+};
+use ironrdp_pdu::rdp::capability_sets::InputFlags;
 
 const FASTPATH_INPUT_MESSAGE: [u8; 44] = [
     0x18, 0x2c, 0x20, 0x0, 0x90, 0x1a, 0x0, 0x26, 0x4, 0x20, 0x0, 0x8, 0x1b, 0x0, 0x26, 0x4, 0x20, 0x0, 0x10, 0x1b,
@@ -65,3 +71,108 @@ fn to_buffer_correctly_serializes_fastpath_input_message() {
 
     assert_eq!(buffer, FASTPATH_INPUT_MESSAGE.as_ref());
 }
+
+fn slow_path_round_trip(event: FastPathInputEvent, expected: InputEvent) {
+    let pdu = InputEventPdu::from_fast_path(&[event]);
+    assert_eq!(pdu, InputEventPdu(vec![expected]));
+
+    let encoded = encode_vec(&pdu).unwrap();
+    assert_eq!(decode::<InputEventPdu>(&encoded).unwrap(), pdu);
+}
+
+#[test]
+fn slow_path_keyboard_event_round_trip() {
+    slow_path_round_trip(
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE | KeyboardFlags::EXTENDED, 0x1d),
+        InputEvent::ScanCode(ScanCodePdu {
+            flags: scan_code::KeyboardFlags::RELEASE | scan_code::KeyboardFlags::EXTENDED,
+            key_code: 0x1d,
+        }),
+    );
+}
+
+#[test]
+fn slow_path_unicode_event_round_trip() {
+    slow_path_round_trip(
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0x00e9),
+        InputEvent::Unicode(UnicodePdu {
+            flags: unicode::KeyboardFlags::RELEASE,
+            unicode_code: 0x00e9,
+        }),
+    );
+}
+
+#[test]
+fn slow_path_mouse_event_round_trip() {
+    let mouse = MousePdu {
+        flags: PointerFlags::VERTICAL_WHEEL,
+        number_of_wheel_rotation_units: 120,
+        x_position: 640,
+        y_position: 480,
+    };
+
+    slow_path_round_trip(FastPathInputEvent::MouseEvent(mouse.clone()), InputEvent::Mouse(mouse));
+}
+
+#[test]
+fn slow_path_extended_mouse_event_round_trip() {
+    let mouse = MouseXPdu {
+        flags: PointerXFlags::DOWN | PointerXFlags::BUTTON2,
+        x_position: 10,
+        y_position: 20,
+    };
+
+    slow_path_round_trip(
+        FastPathInputEvent::MouseEventEx(mouse.clone()),
+        InputEvent::MouseX(mouse),
+    );
+}
+
+#[test]
+fn slow_path_relative_mouse_event_round_trip() {
+    let mouse = MouseRelPdu {
+        flags: PointerRelFlags::MOVE,
+        x_delta: -5,
+        y_delta: 7,
+    };
+
+    slow_path_round_trip(
+        FastPathInputEvent::MouseEventRel(mouse.clone()),
+        InputEvent::MouseRel(mouse),
+    );
+}
+
+#[test]
+fn slow_path_synchronize_event_round_trip() {
+    slow_path_round_trip(
+        FastPathInputEvent::SyncEvent(SynchronizeFlags::NUM_LOCK | SynchronizeFlags::CAPS_LOCK),
+        InputEvent::Sync(SyncPdu {
+            flags: SyncToggleFlags::NUM_LOCK | SyncToggleFlags::CAPS_LOCK,
+        }),
+    );
+}
+
+#[test]
+fn slow_path_drops_qoe_timestamps() {
+    let pdu = InputEventPdu::from_fast_path(&[
+        FastPathInputEvent::QoeEvent(42),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+    ]);
+
+    assert_eq!(
+        pdu,
+        InputEventPdu(vec![InputEvent::ScanCode(ScanCodePdu {
+            flags: scan_code::KeyboardFlags::empty(),
+            key_code: 0x1e,
+        })])
+    );
+}
+
+#[test]
+fn fastpath_input_selection_from_server_input_flags() {
+    assert!(!InputFlags::empty().supports_fastpath_input());
+    assert!(!(InputFlags::SCANCODES | InputFlags::MOUSEX | InputFlags::UNICODE).supports_fastpath_input());
+    assert!((InputFlags::SCANCODES | InputFlags::FASTPATH_INPUT).supports_fastpath_input());
+    assert!((InputFlags::SCANCODES | InputFlags::FASTPATH_INPUT_2).supports_fastpath_input());
+    assert!((InputFlags::FASTPATH_INPUT | InputFlags::FASTPATH_INPUT_2).supports_fastpath_input());
+}
@@ -34,11 +34,59 @@ mcs_encode_decode_test! {
     attach_user_confirm: ATTACH_USER_CONFIRM_PDU, ATTACH_USER_CONFIRM_PDU_BUFFER;
     channel_join_request: CHANNEL_JOIN_REQUEST_PDU, CHANNEL_JOIN_REQUEST_PDU_BUFFER;
     channel_join_confirm: CHANNEL_JOIN_CONFIRM_PDU, CHANNEL_JOIN_CONFIRM_PDU_BUFFER;
+    channel_leave_request: CHANNEL_LEAVE_REQUEST_PDU.clone(), CHANNEL_LEAVE_REQUEST_PDU_BUFFER;
+    channel_expel_indication: CHANNEL_EXPEL_INDICATION_PDU.clone(), CHANNEL_EXPEL_INDICATION_PDU_BUFFER;
     send_data_request: SEND_DATA_REQUEST_PDU, SEND_DATA_REQUEST_PDU_BUFFER;
     send_data_indication: SEND_DATA_INDICATION_PDU, SEND_DATA_INDICATION_PDU_BUFFER;
     disconnect_ultimatum: DISCONNECT_PROVIDER_ULTIMATUM_PDU, DISCONNECT_PROVIDER_ULTIMATUM_PDU_BUFFER;
 }
 
+#[test]
+fn disconnect_provider_ultimatum_reasons() {
+    let cases = [
+        ([0x20, 0x00], DisconnectReason::DomainDisconnected),
+        ([0x20, 0x80], DisconnectReason::ProviderInitiated),
+        ([0x21, 0x00], DisconnectReason::TokenPurged),
+        ([0x21, 0x80], DisconnectReason::UserRequested),
+        ([0x22, 0x00], DisconnectReason::ChannelPurged),
+    ];
+
+    for (buffer, reason) in cases {
+        let decoded = mcs_decode::<McsMessage<'_>>(&buffer).unwrap();
+        assert_eq!(
+            decoded,
+            McsMessage::DisconnectProviderUltimatum(DisconnectProviderUltimatum::from_reason(reason))
+        );
+        assert_eq!(DisconnectReason::from_u8(reason.as_u8()), Some(reason));
+    }
+}
+
+#[test]
+fn disconnect_provider_ultimatum_unknown_reason() {
+    let e = mcs_decode::<DisconnectProviderUltimatum>(&[0x22, 0x80]).err().unwrap();
+
+    expect![[r#"
+        Error {
+            context: "DisconnectProviderUltimatum",
+            kind: InvalidField {
+                field: "reason",
+                reason: "unknown variant",
+            },
+            source: None,
+        }
+    "#]]
+    .assert_debug_eq(&e);
+}
+
+#[test]
+fn channel_expel_indication_is_decoded_as_mcs_message() {
+    let decoded = mcs_decode::<McsMessage<'_>>(&CHANNEL_EXPEL_INDICATION_PDU_BUFFER).unwrap();
+    assert_eq!(
+        decoded,
+        McsMessage::ChannelExpelIndication(CHANNEL_EXPEL_INDICATION_PDU.clone())
+    );
+}
+
 #[test]
 fn from_buffer_correct_parses_connect_initial() {
     let blocks: ConnectInitial = decode(CONNECT_INITIAL_BUFFER.as_slice()).unwrap();
@@ -1,8 +1,14 @@
+mod client_capabilities;
+mod find_size;
 mod gcc;
 mod gfx;
 mod input;
 mod mcs;
+mod orders;
 mod pointer;
 mod rdp;
 mod rfx;
+mod server_redirection;
+mod time_zone;
+mod utf16;
 mod x224;
@@ -0,0 +1,119 @@
+use ironrdp_core::ReadCursor;
+use ironrdp_pdu::orders::{
+    Bounds, CacheBitmapRev2, CacheBitmapRev2Flags, DrawingOrder, MemBlt, OrdersDecoder, PrimaryOrder,
+};
+
+#[test]
+fn primary_orders_are_relative_to_the_previous_ones() {
+    #[rustfmt::skip]
+    let orders = [
+        // Standard, bounds, type change.
+        0x0d, 0x0d, 0xff, 0x01,
+        // Absolute bounds.
+        0x0f, 0x01, 0x00, 0x02, 0x00, 0x64, 0x00, 0xc8, 0x00,
+        // Cache ID, position, size, raster operation, source position, cache index.
+        0x01, 0x00, 0x0a, 0x00, 0x14, 0x00, 0x1e, 0x00, 0x28, 0x00, 0xcc, 0x00, 0x00, 0x05, 0x00, 0x03, 0x00,
+        // Standard, bounds, delta coordinates.
+        0x15, 0x06, 0x00,
+        // Relative left and bottom bounds.
+        0x90, 0x01, 0xf6,
+        // Relative position.
+        0xfb, 0x02,
+    ];
+
+    let mut decoder = OrdersDecoder::new();
+    let mut src = ReadCursor::new(&orders);
+
+    let first = MemBlt {
+        cache_id: 1,
+        left: 10,
+        top: 20,
+        width: 30,
+        height: 40,
+        rop: 0xcc,
+        src_x: 0,
+        src_y: 5,
+        cache_index: 3,
+    };
+    assert_eq!(
+        decoder.decode(&mut src).unwrap(),
+        DrawingOrder::Primary {
+            order: PrimaryOrder::MemBlt(first),
+            bounds: Some(Bounds {
+                left: 1,
+                top: 2,
+                right: 100,
+                bottom: 200,
+            }),
+        }
+    );
+
+    assert_eq!(
+        decoder.decode(&mut src).unwrap(),
+        DrawingOrder::Primary {
+            order: PrimaryOrder::MemBlt(MemBlt {
+                left: 5,
+                top: 22,
+                ..first
+            }),
+            bounds: Some(Bounds {
+                left: 2,
+                top: 2,
+                right: 100,
+                bottom: 190,
+            }),
+        }
+    );
+    assert!(src.is_empty());
+}
+
+#[test]
+fn cache_bitmap_rev2_order() {
+    #[rustfmt::skip]
+    let mut order = vec![
+        // Secondary order header: cache 2, 32 bpp, with a persistent key.
+        0x03, 0x28, 0x03, 0x32, 0x01, 0x04,
+        // Key.
+        0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88,
+        // Width and height, bitmap length, cache index.
+        0x80, 0xc8, 0x01, 0x43, 0x20, 0x81, 0x2c,
+    ];
+    order.extend_from_slice(&[0xab; 800]);
+
+    let mut src = ReadCursor::new(&order);
+    assert_eq!(
+        OrdersDecoder::new().decode(&mut src).unwrap(),
+        DrawingOrder::CacheBitmapRev2(CacheBitmapRev2 {
+            cache_id: 2,
+            bits_per_pixel: 32,
+            flags: CacheBitmapRev2Flags::PERSISTENT_KEY_PRESENT,
+            key: Some(0x8877_6655_4433_2211),
+            width: 200,
+            height: 1,
+            cache_index: 300,
+            compressed: false,
+            bitmap_data: vec![0xab; 800],
+        })
+    );
+    assert!(src.is_empty());
+}
+
+#[test]
+fn unsupported_orders() {
+    // Cache Color Table, skipped.
+    let order = [
+        0x03, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(
+        OrdersDecoder::new().decode(&mut ReadCursor::new(&order)).unwrap(),
+        DrawingOrder::UnsupportedSecondary { order_type: 0x01 }
+    );
+
+    // PatBlt, whose length is unknown.
+    let order = [0x09, 0x01, 0x00];
+    OrdersDecoder::new().decode(&mut ReadCursor::new(&order)).unwrap_err();
+
+    // Windowing order.
+    let order = [0x2e, 0x0b, 0x00];
+    OrdersDecoder::new().decode(&mut ReadCursor::new(&order)).unwrap_err();
+}
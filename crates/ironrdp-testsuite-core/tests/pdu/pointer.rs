@@ -65,6 +65,158 @@ fn new_pointer_32bpp() {
     .assert_debug_eq(&parsed);
 }
 
+#[test]
+fn new_pointer_32bpp_straight_alpha() {
+    let data = include_bytes!("../../test_data/pdu/pointer/new_pointer_32bpp.bin");
+    let parsed = ironrdp_core::decode::<PointerAttribute<'_>>(data).unwrap();
+
+    let straight = DecodedPointer::decode_pointer_attribute(&parsed, PointerBitmapTarget::Accelerated).unwrap();
+    expect_pointer_png(&straight, "pdu/pointer/new_pointer_32bpp_straight.png");
+
+    let premultiplied = DecodedPointer::decode_pointer_attribute(&parsed, PointerBitmapTarget::Software).unwrap();
+    for (straight, premultiplied) in straight
+        .bitmap_data
+        .chunks_exact(4)
+        .zip(premultiplied.bitmap_data.chunks_exact(4))
+    {
+        let alpha = u16::from(straight[3]);
+        let expected: Vec<u8> = straight[..3]
+            .iter()
+            .map(|&component| u8::try_from((u16::from(component) * alpha + 127) / 255).unwrap())
+            .chain([straight[3]])
+            .collect();
+        assert_eq!(premultiplied, expected);
+    }
+}
+
+/// 7x11 I-beam: black stem and serifs, surrounded by a half-transparent white halo.
+fn ibeam_32bpp() -> PointerAttribute<'static> {
+    const WIDTH: usize = 7;
+    const HEIGHT: usize = 11;
+
+    let is_stem = |x: usize, y: usize| x == 3 || ((y == 0 || y == HEIGHT - 1) && (1..=5).contains(&x));
+    let is_halo = |x: usize, y: usize| {
+        !is_stem(x, y)
+            && (x.saturating_sub(1)..=x + 1)
+                .any(|nx| (y.saturating_sub(1)..=y + 1).any(|ny| nx < WIDTH && ny < HEIGHT && is_stem(nx, ny)))
+    };
+
+    // Scanlines are stored bottom-up, as BGRA pixels.
+    let mut xor_mask = Vec::new();
+    for y in (0..HEIGHT).rev() {
+        for x in 0..WIDTH {
+            let pixel = if is_stem(x, y) {
+                [0x00, 0x00, 0x00, 0xFF]
+            } else if is_halo(x, y) {
+                [0xFF, 0xFF, 0xFF, 0x80]
+            } else {
+                [0x00, 0x00, 0x00, 0x00]
+            };
+            xor_mask.extend_from_slice(&pixel);
+        }
+    }
+
+    PointerAttribute {
+        xor_bpp: 32,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 1,
+            hot_spot: Point16 { x: 3, y: 5 },
+            width: 7,
+            height: 11,
+            xor_mask: xor_mask.leak(),
+            // Set bits would make black pixels transparent, the mask must be ignored.
+            and_mask: &[0xFF; 2 * HEIGHT],
+        },
+    }
+}
+
+#[test]
+fn ibeam_32bpp_alpha() {
+    let value = ibeam_32bpp();
+
+    let encoded = ironrdp_core::encode_vec(&value).unwrap();
+    let decoded = ironrdp_core::decode::<PointerAttribute<'_>>(&encoded).unwrap();
+    assert_eq!(&decoded, &value);
+
+    let pixel = |pointer: &DecodedPointer, x: usize, y: usize| {
+        let offset = (y * usize::from(pointer.width) + x) * 4;
+        <[u8; 4]>::try_from(&pointer.bitmap_data[offset..offset + 4]).unwrap()
+    };
+
+    let straight = DecodedPointer::decode_pointer_attribute(&value, PointerBitmapTarget::Accelerated).unwrap();
+    assert_eq!(pixel(&straight, 0, 5), [0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(pixel(&straight, 2, 5), [0xFF, 0xFF, 0xFF, 0x80]);
+    assert_eq!(pixel(&straight, 3, 5), [0x00, 0x00, 0x00, 0xFF]);
+    expect_pointer_png(&straight, "pdu/pointer/ibeam_32bpp_straight.png");
+
+    let premultiplied = DecodedPointer::decode_pointer_attribute(&value, PointerBitmapTarget::Software).unwrap();
+    assert_eq!(pixel(&premultiplied, 2, 5), [0x80, 0x80, 0x80, 0x80]);
+    assert_eq!(pixel(&premultiplied, 3, 5), [0x00, 0x00, 0x00, 0xFF]);
+    expect_pointer_png(&premultiplied, "pdu/pointer/ibeam_32bpp_premultiplied.png");
+}
+
+#[test]
+fn pointer_32bpp_without_alpha_applies_and_mask() {
+    // Opaque black then opaque white pixels, whose alpha values are all zero.
+    const XOR_MASK_32BPP: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF, 0x00,
+    ];
+    // Transparent or inverted first pixel, opaque second pixel.
+    const AND_MASK: &[u8] = &[0b10000000, 0b00000000, 0b10000000, 0b00000000];
+
+    let value = PointerAttribute {
+        xor_bpp: 32,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 0,
+            hot_spot: Point16 { x: 0, y: 0 },
+            width: 2,
+            height: 2,
+            xor_mask: XOR_MASK_32BPP,
+            and_mask: AND_MASK,
+        },
+    };
+
+    let decoded = DecodedPointer::decode_pointer_attribute(&value, PointerBitmapTarget::Software).unwrap();
+    assert_eq!(
+        decoded.bitmap_data,
+        [
+            // Top row, stored last: inverted then white pixels.
+            0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, //
+            // Bottom row, stored first: transparent then black pixels.
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF,
+        ]
+    );
+}
+
+#[test]
+fn new_pointer_96x96() {
+    let xor_mask = [0x20, 0x40, 0x60, 0xFF].repeat(96 * 96);
+    let and_mask = [0x00; 12 * 96];
+
+    let value = PointerAttribute {
+        xor_bpp: 32,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 0,
+            hot_spot: Point16 { x: 48, y: 48 },
+            width: 96,
+            height: 96,
+            xor_mask: &xor_mask,
+            and_mask: &and_mask,
+        },
+    };
+
+    let encoded = ironrdp_core::encode_vec(&value).unwrap();
+    let parsed = ironrdp_core::decode::<PointerAttribute<'_>>(&encoded).unwrap();
+    assert_eq!(&parsed, &value);
+
+    let decoded = DecodedPointer::decode_pointer_attribute(&parsed, PointerBitmapTarget::Accelerated).unwrap();
+    assert_eq!((decoded.width, decoded.height), (96, 96));
+    assert!(decoded
+        .bitmap_data
+        .chunks_exact(4)
+        .all(|pixel| pixel == [0x60, 0x40, 0x20, 0xFF]));
+}
+
 #[test]
 fn large_pointer_32bpp() {
     let data = include_bytes!("../../test_data/pdu/pointer/large_pointer_32bpp.bin");
@@ -1,7 +1,16 @@
-use ironrdp_core::{decode, encode_vec, Encode};
+use ironrdp_core::{decode, encode_vec, DecodeErrorKind, Encode, ReadCursor};
+use ironrdp_pdu::gcc;
+use ironrdp_pdu::gcc::ConnectionType;
+use ironrdp_pdu::rdp::capability_sets::{CapabilitySet, ServerDemandActive};
+use ironrdp_pdu::rdp::client_info::{DynamicTimeZone, ExtendedClientOptionalInfo, PerformanceFlags};
+use ironrdp_pdu::rdp::finalization_messages::MonitorLayoutPdu;
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_pdu::rdp::multitransport::{MultitransportRequestPdu, MultitransportResponsePdu, RequestedProtocol};
+use ironrdp_pdu::{DecodeOptions, DecodeWarning};
 use ironrdp_testsuite_core::capsets::*;
 use ironrdp_testsuite_core::client_info::*;
 use ironrdp_testsuite_core::rdp::*;
+use rstest::rstest;
 
 #[test]
 fn from_buffer_correctly_parses_rdp_pdu_client_info() {
@@ -71,6 +80,11 @@ fn from_buffer_correctly_parses_rdp_pdu_server_font_map() {
     assert_eq!(SERVER_FONT_MAP.clone(), decode(buf).unwrap());
 }
 
+#[test]
+fn from_buffer_correctly_parses_rdp_pdu_server_play_sound() {
+    assert_eq!(*SERVER_PLAY_SOUND, decode(SERVER_PLAY_SOUND_BUFFER.as_slice()).unwrap());
+}
+
 #[test]
 fn from_buffer_correctly_parses_rdp_pdu_server_monitor_layout() {
     let buf = MONITOR_LAYOUT_PDU_BUFFER.clone();
@@ -163,6 +177,14 @@ fn to_buffer_correctly_serializes_rdp_pdu_server_font_map() {
     assert_eq!(expected_buf, buf);
 }
 
+#[test]
+fn to_buffer_correctly_serializes_rdp_pdu_server_play_sound() {
+    let buffer = encode_vec(&*SERVER_PLAY_SOUND).unwrap();
+
+    assert_eq!(buffer, SERVER_PLAY_SOUND_BUFFER);
+    assert_eq!(SERVER_PLAY_SOUND.size(), SERVER_PLAY_SOUND_BUFFER.len());
+}
+
 #[test]
 fn to_buffer_correctly_serializes_rdp_pdu_server_monitor_layout() {
     let pdu = MONITOR_LAYOUT_PDU.clone();
@@ -371,6 +393,335 @@ fn from_buffer_correctly_parses_server_demand_active() {
     assert_eq!(*SERVER_DEMAND_ACTIVE, decode(buffer).unwrap());
 }
 
+#[test]
+fn server_demand_active_serializes_to_json() {
+    let json = serde_json::to_string_pretty(&*SERVER_DEMAND_ACTIVE).unwrap();
+
+    expect_test::expect![[r#"
+        {
+          "pdu": {
+            "source_descriptor": "RDP",
+            "capability_sets": [
+              {
+                "Share": [
+                  234,
+                  3,
+                  220,
+                  226
+                ]
+              },
+              {
+                "General": {
+                  "major_platform_type": "WINDOWS",
+                  "minor_platform_type": "WINDOWS_NT",
+                  "protocol_version": 512,
+                  "extra_flags": "FASTPATH_OUTPUT_SUPPORTED | NO_BITMAP_COMPRESSION_HDR | LONG_CREDENTIALS_SUPPORTED | AUTORECONNECT_SUPPORTED | ENC_SALTED_CHECKSUM",
+                  "refresh_rect_support": true,
+                  "suppress_output_support": true
+                }
+              },
+              {
+                "VirtualChannel": {
+                  "flags": "COMPRESSION_CLIENT_TO_SERVER_8K",
+                  "chunk_size": 1600
+                }
+              },
+              {
+                "DrawGdiPlus": [
+                  0,
+                  0,
+                  0,
+                  0,
+                  112,
+                  246,
+                  19,
+                  243,
+                  1,
+                  0,
+                  0,
+                  0,
+                  1,
+                  0,
+                  0,
+                  0,
+                  24,
+                  0,
+                  0,
+                  0,
+                  156,
+                  246,
+                  19,
+                  243,
+                  97,
+                  166,
+                  130,
+                  128,
+                  0,
+                  0,
+                  0,
+                  0,
+                  0,
+                  80,
+                  145,
+                  191
+                ]
+              },
+              {
+                "Font": [
+                  0,
+                  1,
+                  0,
+                  0
+                ]
+              },
+              {
+                "Bitmap": {
+                  "pref_bits_per_pix": 24,
+                  "desktop_width": 1280,
+                  "desktop_height": 1024,
+                  "desktop_resize_flag": true,
+                  "drawing_flags": ""
+                }
+              },
+              {
+                "Order": {
+                  "order_flags": "NEGOTIATE_ORDER_SUPPORT | COLOR_INDEX_SUPPORT",
+                  "order_support": [
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    0,
+                    0,
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    0,
+                    0,
+                    0,
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    1,
+                    0,
+                    1,
+                    1,
+                    1,
+                    1,
+                    0,
+                    0,
+                    0,
+                    0
+                  ],
+                  "order_support_ex_flags": "",
+                  "desktop_save_size": 1000000,
+                  "text_ansi_code_page": 0
+                }
+              },
+              {
+                "ColorCache": [
+                  6,
+                  0,
+                  0,
+                  0
+                ]
+              },
+              {
+                "BitmapCacheHostSupport": [
+                  1,
+                  0,
+                  0,
+                  0
+                ]
+              },
+              {
+                "Pointer": {
+                  "color_pointer_cache_size": 25,
+                  "pointer_cache_size": 25
+                }
+              },
+              {
+                "Input": {
+                  "input_flags": "SCANCODES | MOUSEX | UNICODE | FASTPATH_INPUT_2",
+                  "keyboard_layout": 1697,
+                  "keyboard_type": null,
+                  "keyboard_subtype": 4078171660,
+                  "keyboard_function_key": 4080491155,
+                  "keyboard_ime_filename": "退ᰴ\u0004\u0000呌倈\u0001\u0000倈\u0000\u0000Ԯ倈\u0000\u0000\b\n\u0001"
+                }
+              },
+              {
+                "Rail": [
+                  0,
+                  0,
+                  0,
+                  0
+                ]
+              },
+              {
+                "WindowList": [
+                  0,
+                  0,
+                  0,
+                  0,
+                  0,
+                  0,
+                  0
+                ]
+              }
+            ]
+          }
+        }"#]].assert_eq(&json);
+}
+
+// The non-conformant blobs below are synthetic: they are built from the conformant test vectors to exercise the
+// lenient decoding paths, and are not captures of real servers. They don't prove interoperability with any server.
+
+/// Inserts `capability_set` in the server demand active test vector, at `offset` bytes from the first capability set.
+fn server_demand_active_with_extra_capability_set(offset: usize, capability_set: &[u8]) -> Vec<u8> {
+    const CAPABILITY_SETS_OFFSET: usize = 12;
+
+    let mut buffer = SERVER_DEMAND_ACTIVE_BUFFER.to_vec();
+
+    let combined_length = u16::from_le_bytes([buffer[2], buffer[3]]) + u16::try_from(capability_set.len()).unwrap();
+    buffer[2..4].copy_from_slice(&combined_length.to_le_bytes());
+    buffer[8] += 1;
+
+    let position = CAPABILITY_SETS_OFFSET + offset;
+    buffer.splice(position..position, capability_set.iter().copied());
+
+    buffer
+}
+
+#[test]
+fn unknown_capability_set_is_kept() {
+    let buffer = server_demand_active_with_extra_capability_set(0, &[0xff, 0x00, 0x08, 0x00, 0x01, 0x02, 0x03, 0x04]);
+
+    let mut expected = SERVER_DEMAND_ACTIVE.clone();
+    expected.pdu.capability_sets.insert(
+        0,
+        CapabilitySet::Unknown {
+            capability_set_type: 0xff,
+            data: vec![0x01, 0x02, 0x03, 0x04],
+        },
+    );
+
+    let pdu = decode::<ServerDemandActive>(&buffer).unwrap();
+    assert_eq!(expected, pdu);
+    assert_eq!(buffer, encode_vec(&pdu).unwrap());
+
+    let mut warnings = Vec::new();
+    let pdu =
+        ServerDemandActive::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut warnings)
+            .unwrap();
+
+    assert_eq!(expected, pdu);
+    assert_eq!(
+        warnings,
+        [DecodeWarning::UnknownCapabilitySet {
+            capability_set_type: 0xff
+        }]
+    );
+}
+
+#[test]
+fn lenient_decoding_skips_malformed_capability_set() {
+    // General capability set with a truncated body.
+    let buffer = server_demand_active_with_extra_capability_set(0, &[0x01, 0x00, 0x06, 0x00, 0x01, 0x00]);
+
+    match decode::<ServerDemandActive>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => (),
+        res => panic!("Expected the not enough bytes error, got: {res:?}"),
+    };
+
+    let mut warnings = Vec::new();
+    let pdu =
+        ServerDemandActive::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut warnings)
+            .unwrap();
+
+    assert_eq!(*SERVER_DEMAND_ACTIVE, pdu);
+    assert!(matches!(
+        warnings.as_slice(),
+        [DecodeWarning::MalformedCapabilitySet {
+            capability_set_type: 0x01,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn lenient_decoding_stops_at_overlong_capability_set() {
+    let capability_sets_length = SERVER_DEMAND_ACTIVE_BUFFER.len() - 12 - 4;
+    // Frame Acknowledge capability set advertising 64 bytes, running over the session ID.
+    let buffer =
+        server_demand_active_with_extra_capability_set(capability_sets_length, &[0x1e, 0x00, 0x40, 0x00, 0x00]);
+
+    match decode::<ServerDemandActive>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => (),
+        res => panic!("Expected the not enough bytes error, got: {res:?}"),
+    };
+
+    let mut warnings = Vec::new();
+    let mut src = ReadCursor::new(&buffer);
+    let pdu = ServerDemandActive::decode_with_options(&mut src, DecodeOptions::LENIENT, &mut warnings).unwrap();
+
+    assert_eq!(*SERVER_DEMAND_ACTIVE, pdu);
+    assert!(src.is_empty());
+    assert_eq!(
+        warnings,
+        [
+            DecodeWarning::TrailingBytes {
+                context: "DemandActive",
+                count: 9
+            },
+            DecodeWarning::MissingField {
+                context: "ServerDemandActive",
+                field: "sessionId"
+            },
+        ]
+    );
+}
+
+#[test]
+fn lenient_decoding_reports_missing_capability_sets() {
+    let mut buffer = SERVER_DEMAND_ACTIVE_BUFFER.to_vec();
+    // Drop the session ID, and advertise one more capability set than present.
+    buffer.truncate(buffer.len() - 4);
+    buffer[8] += 1;
+
+    match decode::<ServerDemandActive>(&buffer) {
+        Err(e) if matches!(e.kind(), DecodeErrorKind::NotEnoughBytes { .. }) => (),
+        res => panic!("Expected the not enough bytes error, got: {res:?}"),
+    };
+
+    let mut warnings = Vec::new();
+    let pdu =
+        ServerDemandActive::decode_with_options(&mut ReadCursor::new(&buffer), DecodeOptions::LENIENT, &mut warnings)
+            .unwrap();
+
+    assert_eq!(*SERVER_DEMAND_ACTIVE, pdu);
+    assert_eq!(
+        warnings,
+        [
+            DecodeWarning::MissingCapabilitySets {
+                expected: 14,
+                found: 13
+            },
+            DecodeWarning::MissingField {
+                context: "ServerDemandActive",
+                field: "sessionId"
+            },
+        ]
+    );
+}
+
 #[test]
 fn from_buffer_correctly_parses_client_demand_active_with_incomplete_capability_set() {
     let buffer = CLIENT_DEMAND_ACTIVE_WITH_INCOMPLETE_CAPABILITY_SET_BUFFER.as_ref();
@@ -447,3 +798,317 @@ fn buffer_length_is_correct_for_client_demand_active() {
 
     assert_eq!(expected_buffer_len, len);
 }
+
+const MONITOR_LAYOUT_BUFFER: [u8; 44] = [
+    0x02, 0x00, 0x00, 0x00, // nMonitors
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x07, 0x00, 0x00, 0x37, 0x04, 0x00, 0x00, 0x01, 0x00, 0x00,
+    0x00, // primary monitor
+    0x80, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x0c, 0x00, 0x00, 0xff, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, // secondary monitor
+];
+
+fn monitor_layout() -> MonitorLayoutPdu {
+    MonitorLayoutPdu {
+        monitors: vec![
+            gcc::Monitor {
+                left: 0,
+                top: 0,
+                right: 1919,
+                bottom: 1079,
+                flags: gcc::MonitorFlags::PRIMARY,
+            },
+            gcc::Monitor {
+                left: 1920,
+                top: 0,
+                right: 3199,
+                bottom: 1023,
+                flags: gcc::MonitorFlags::empty(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn from_buffer_correctly_parses_monitor_layout() {
+    assert_eq!(monitor_layout(), decode(MONITOR_LAYOUT_BUFFER.as_slice()).unwrap());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_monitor_layout() {
+    assert_eq!(MONITOR_LAYOUT_BUFFER.as_slice(), encode_vec(&monitor_layout()).unwrap());
+}
+
+#[test]
+fn monitor_layout_with_too_many_monitors_is_rejected() {
+    let mut buffer = MONITOR_LAYOUT_BUFFER.to_vec();
+    buffer[0] = 65;
+
+    let e = decode::<MonitorLayoutPdu>(&buffer).unwrap_err();
+    assert!(matches!(e.kind(), DecodeErrorKind::InvalidField { .. }));
+}
+
+const HEARTBEAT_PDU_BUFFER: [u8; 8] = [
+    0x00, 0x40, // flags: SEC_HEARTBEAT
+    0x00, 0x00, // flagsHi
+    0x00, // reserved
+    0x05, // period
+    0x03, // count1
+    0x0a, // count2
+];
+
+const HEARTBEAT_PDU: HeartbeatPdu = HeartbeatPdu {
+    period: 5,
+    warning_count: 3,
+    reconnect_count: 10,
+};
+
+#[test]
+fn heartbeat_pdu_is_decoded() {
+    assert!(HeartbeatPdu::is_heartbeat(&HEARTBEAT_PDU_BUFFER));
+    assert_eq!(decode::<HeartbeatPdu>(&HEARTBEAT_PDU_BUFFER).unwrap(), HEARTBEAT_PDU);
+    assert_eq!(encode_vec(&HEARTBEAT_PDU).unwrap(), HEARTBEAT_PDU_BUFFER);
+}
+
+#[test]
+fn share_control_pdu_is_not_a_heartbeat() {
+    assert!(!HeartbeatPdu::is_heartbeat(&CLIENT_SYNCHRONIZE_BUFFER));
+
+    let mut buffer = HEARTBEAT_PDU_BUFFER;
+    buffer[1] = 0;
+    assert!(!HeartbeatPdu::is_heartbeat(&buffer));
+
+    let e = decode::<HeartbeatPdu>(&buffer).unwrap_err();
+    assert!(matches!(e.kind(), DecodeErrorKind::InvalidField { .. }));
+}
+
+const MULTITRANSPORT_REQUEST_PDU_BUFFER: [u8; 28] = [
+    0x02, 0x00, // flags: SEC_TRANSPORT_REQ
+    0x00, 0x00, // flagsHi
+    0x2a, 0x00, 0x00, 0x00, // requestId
+    0x01, 0x00, // requestedProtocol: INITITATE_REQUEST_PROTOCOL_UDPFECR
+    0x00, 0x00, // reserved
+    0x9d, 0x4f, 0x1c, 0x6e, 0x35, 0xa2, 0x80, 0x07, 0xe1, 0x53, 0xc8, 0x2b, 0x74, 0x0e, 0xd6,
+    0x91, // securityCookie
+];
+
+const MULTITRANSPORT_REQUEST_PDU: MultitransportRequestPdu = MultitransportRequestPdu {
+    request_id: 42,
+    requested_protocol: RequestedProtocol::UDP_FECR,
+    security_cookie: [
+        0x9d, 0x4f, 0x1c, 0x6e, 0x35, 0xa2, 0x80, 0x07, 0xe1, 0x53, 0xc8, 0x2b, 0x74, 0x0e, 0xd6, 0x91,
+    ],
+};
+
+const MULTITRANSPORT_RESPONSE_PDU_BUFFER: [u8; 12] = [
+    0x04, 0x00, // flags: SEC_TRANSPORT_RSP
+    0x00, 0x00, // flagsHi
+    0x2a, 0x00, 0x00, 0x00, // requestId
+    0x04, 0x40, 0x00, 0x80, // hrResponse: E_ABORT
+];
+
+#[test]
+fn multitransport_request_pdu_is_decoded() {
+    assert!(MultitransportRequestPdu::is_multitransport_request(
+        &MULTITRANSPORT_REQUEST_PDU_BUFFER
+    ));
+    assert!(!MultitransportRequestPdu::is_multitransport_request(
+        &HEARTBEAT_PDU_BUFFER
+    ));
+    assert_eq!(
+        decode::<MultitransportRequestPdu>(&MULTITRANSPORT_REQUEST_PDU_BUFFER).unwrap(),
+        MULTITRANSPORT_REQUEST_PDU
+    );
+    assert_eq!(
+        encode_vec(&MULTITRANSPORT_REQUEST_PDU).unwrap(),
+        MULTITRANSPORT_REQUEST_PDU_BUFFER
+    );
+}
+
+#[test]
+fn multitransport_response_pdu_declines_the_request() {
+    let response = MultitransportResponsePdu::abort(MULTITRANSPORT_REQUEST_PDU.request_id);
+
+    assert_eq!(encode_vec(&response).unwrap(), MULTITRANSPORT_RESPONSE_PDU_BUFFER);
+    assert_eq!(
+        decode::<MultitransportResponsePdu>(&MULTITRANSPORT_RESPONSE_PDU_BUFFER).unwrap(),
+        response
+    );
+}
+
+ironrdp_testsuite_core::encoded_size_test! {
+    client_info_pdu: CLIENT_INFO_PDU.clone();
+    client_info_unicode: CLIENT_INFO_UNICODE.clone();
+    client_info_ansi: CLIENT_INFO_ANSI.clone();
+    server_demand_active_pdu: SERVER_DEMAND_ACTIVE_PDU.clone();
+    client_demand_active_pdu: CLIENT_DEMAND_ACTIVE_PDU.clone();
+    client_synchronize_pdu: CLIENT_SYNCHRONIZE.clone();
+    control_cooperate_pdu: CONTROL_COOPERATE.clone();
+    server_font_map_pdu: SERVER_FONT_MAP.clone();
+    monitor_layout_pdu: MONITOR_LAYOUT_PDU.clone();
+}
+
+ironrdp_testsuite_core::round_trip_test! {
+    client_info_pdu: ironrdp_pdu::rdp::ClientInfoPdu, CLIENT_INFO_PDU_BUFFER;
+    client_info_unicode: ironrdp_pdu::rdp::client_info::ClientInfo, CLIENT_INFO_BUFFER_UNICODE;
+    client_info_ansi: ironrdp_pdu::rdp::client_info::ClientInfo, CLIENT_INFO_BUFFER_ANSI;
+    server_demand_active_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, SERVER_DEMAND_ACTIVE_PDU_BUFFER;
+    client_demand_active_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, CLIENT_DEMAND_ACTIVE_PDU_BUFFER;
+    client_synchronize_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, CLIENT_SYNCHRONIZE_BUFFER;
+    control_request_control_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, CONTROL_REQUEST_CONTROL_BUFFER;
+    server_granted_control_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, SERVER_GRANTED_CONTROL_BUFFER;
+    client_font_list_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, CLIENT_FONT_LIST_BUFFER;
+    monitor_layout_pdu: ironrdp_pdu::rdp::headers::ShareControlHeader, MONITOR_LAYOUT_PDU_BUFFER;
+}
+
+/// Time zone, session ID and performance flags of [`CLIENT_INFO_UNICODE`], without the performance flags.
+fn optional_info_prefix() -> &'static [u8] {
+    &CLIENT_INFO_BUFFER_UNICODE
+        [CLIENT_INFO_BUFFER_UNICODE_WITHOUT_OPTIONAL_FIELDS_LEN..CLIENT_INFO_BUFFER_UNICODE.len() - 4]
+}
+
+fn optional_info_builder(
+    performance_flags: PerformanceFlags,
+) -> ironrdp_pdu::rdp::client_info::builder::ExtendedClientOptionalInfoBuilder<
+    ironrdp_pdu::rdp::client_info::builder::ExtendedClientOptionalInfoBuilderStateSetReconnectCookie,
+> {
+    let optional_data = &CLIENT_INFO_UNICODE.extra_info.optional_data;
+
+    ExtendedClientOptionalInfo::builder()
+        .timezone(optional_data.timezone().unwrap().clone())
+        .session_id(optional_data.session_id().unwrap())
+        .performance_flags(performance_flags)
+}
+
+const RECONNECT_COOKIE: [u8; 28] = [
+    0x1c, 0x00, 0x00, 0x00, // cbLen
+    0x01, 0x00, 0x00, 0x00, // version
+    0x02, 0x00, 0x00, 0x00, // logonId
+    0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xab, 0xac, 0xad, 0xae, 0xaf,
+    0xb0, // securityVerifier
+];
+
+fn pacific_dynamic_time_zone() -> DynamicTimeZone {
+    DynamicTimeZone {
+        key_name: String::from("Pacific Standard Time"),
+        dynamic_daylight_time_disabled: false,
+    }
+}
+
+const PACIFIC_DYNAMIC_TIME_ZONE_BUFFER: [u8; 46] = [
+    0x2a, 0x00, // cbDynamicDSTTimeZoneKeyName
+    0x50, 0x00, 0x61, 0x00, 0x63, 0x00, 0x69, 0x00, 0x66, 0x00, 0x69, 0x00, 0x63, 0x00, 0x20, 0x00, 0x53, 0x00, 0x74,
+    0x00, 0x61, 0x00, 0x6e, 0x00, 0x64, 0x00, 0x61, 0x00, 0x72, 0x00, 0x64, 0x00, 0x20, 0x00, 0x54, 0x00, 0x69, 0x00,
+    0x6d, 0x00, 0x65, 0x00, // dynamicDSTTimeZoneKeyName
+    0x00, 0x00, // dynamicDaylightTimeDisabled
+];
+
+#[rstest]
+#[case::modem(ConnectionType::Modem, [0x0f, 0x00, 0x00, 0x00])]
+#[case::broadband_high(ConnectionType::BroadbandHigh, [0x07, 0x01, 0x00, 0x00])]
+#[case::lan(ConnectionType::Lan, [0x80, 0x01, 0x00, 0x00])]
+fn performance_flags_for_connection_type_are_encoded(
+    #[case] connection_type: ConnectionType,
+    #[case] expected_flags: [u8; 4],
+) {
+    let optional_data = optional_info_builder(PerformanceFlags::auto_for_connection_type(connection_type)).build();
+
+    let expected = [optional_info_prefix(), &expected_flags].concat();
+    assert_eq!(encode_vec(&optional_data).unwrap(), expected);
+    assert_eq!(decode::<ExtendedClientOptionalInfo>(&expected).unwrap(), optional_data);
+}
+
+#[test]
+fn performance_flags_for_autodetect_match_lan() {
+    assert_eq!(
+        PerformanceFlags::auto_for_connection_type(ConnectionType::Autodetect),
+        PerformanceFlags::auto_for_connection_type(ConnectionType::Lan)
+    );
+}
+
+#[test]
+fn extended_info_with_reconnect_cookie_and_dynamic_time_zone() {
+    let optional_data = optional_info_builder(PerformanceFlags::DISABLE_WALLPAPER)
+        .reconnect_cookie(RECONNECT_COOKIE)
+        .dynamic_time_zone(pacific_dynamic_time_zone())
+        .build();
+
+    let expected = [
+        optional_info_prefix(),
+        &[0x01, 0x00, 0x00, 0x00], // performanceFlags
+        &[0x1c, 0x00],             // cbAutoReconnectCookie
+        &RECONNECT_COOKIE,
+        &[0x00, 0x00, 0x00, 0x00], // reserved1, reserved2
+        &PACIFIC_DYNAMIC_TIME_ZONE_BUFFER,
+    ]
+    .concat();
+
+    assert_eq!(optional_data.size(), expected.len());
+    assert_eq!(encode_vec(&optional_data).unwrap(), expected);
+    assert_eq!(decode::<ExtendedClientOptionalInfo>(&expected).unwrap(), optional_data);
+}
+
+#[test]
+fn extended_info_without_reconnect_cookie_keeps_its_length_field() {
+    let optional_data = optional_info_builder(PerformanceFlags::DISABLE_WALLPAPER)
+        .dynamic_time_zone(pacific_dynamic_time_zone())
+        .build();
+
+    let expected = [
+        optional_info_prefix(),
+        &[0x01, 0x00, 0x00, 0x00], // performanceFlags
+        &[0x00, 0x00],             // cbAutoReconnectCookie
+        &[0x00, 0x00, 0x00, 0x00], // reserved1, reserved2
+        &PACIFIC_DYNAMIC_TIME_ZONE_BUFFER,
+    ]
+    .concat();
+
+    assert_eq!(encode_vec(&optional_data).unwrap(), expected);
+    assert_eq!(decode::<ExtendedClientOptionalInfo>(&expected).unwrap(), optional_data);
+}
+
+#[test]
+fn extended_info_with_reconnect_cookie_only_has_no_trailing_fields() {
+    let optional_data = optional_info_builder(PerformanceFlags::DISABLE_WALLPAPER)
+        .reconnect_cookie(RECONNECT_COOKIE)
+        .build();
+
+    let expected = [
+        optional_info_prefix(),
+        &[0x01, 0x00, 0x00, 0x00], // performanceFlags
+        &[0x1c, 0x00],             // cbAutoReconnectCookie
+        &RECONNECT_COOKIE,
+    ]
+    .concat();
+
+    assert_eq!(encode_vec(&optional_data).unwrap(), expected);
+    assert_eq!(decode::<ExtendedClientOptionalInfo>(&expected).unwrap(), optional_data);
+}
+
+#[rstest]
+#[case::odd_size(0x0003)]
+#[case::too_large(0x0100)]
+fn invalid_dynamic_time_zone_key_name_size_is_rejected(#[case] key_name_size: u16) {
+    let buffer = [
+        optional_info_prefix(),
+        &[0x01, 0x00, 0x00, 0x00], // performanceFlags
+        &[0x00, 0x00],             // cbAutoReconnectCookie
+        &[0x00, 0x00, 0x00, 0x00], // reserved1, reserved2
+        &key_name_size.to_le_bytes(),
+        &[0x00; 0x100],
+    ]
+    .concat();
+
+    assert!(decode::<ExtendedClientOptionalInfo>(&buffer).is_err());
+}
+
+#[test]
+fn too_long_dynamic_time_zone_key_name_is_not_encoded() {
+    let optional_data = optional_info_builder(PerformanceFlags::DISABLE_WALLPAPER)
+        .dynamic_time_zone(DynamicTimeZone {
+            key_name: "x".repeat(128),
+            dynamic_daylight_time_disabled: true,
+        })
+        .build();
+
+    assert!(encode_vec(&optional_data).is_err());
+}
@@ -0,0 +1,123 @@
+use ironrdp_connector::redirection::{RedirectionCredentials, ServerRedirection};
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_pdu::nego::NegoRequestData;
+use ironrdp_pdu::rdp::headers::{ShareControlHeader, ShareControlPdu};
+use ironrdp_pdu::rdp::server_redirection::{RedirectionFlags, ServerRedirectionPdu};
+
+const ROUTING_TOKEN: &[u8] = b"Cookie: msts=3640205228.15629.0000\r\n";
+
+#[rustfmt::skip]
+const ENHANCED_SECURITY_REDIRECTION: [u8; 44] = [
+    // Share Control Header: length, Server Redirection PDU type, source, padding.
+    0x2c, 0x00, 0x1a, 0x00, 0xea, 0x03, 0x00, 0x00,
+    // Flags, length, session ID, redirection flags (username and target FQDN).
+    0x00, 0x04, 0x24, 0x00, 0x05, 0x00, 0x00, 0x00, 0x04, 0x01, 0x00, 0x00,
+    // Username.
+    0x08, 0x00, 0x00, 0x00, b'b', 0x00, b'o', 0x00, b'b', 0x00, 0x00, 0x00,
+    // Target FQDN.
+    0x08, 0x00, 0x00, 0x00, b's', 0x00, b'r', 0x00, b'v', 0x00, 0x00, 0x00,
+];
+
+/// Server Redirection Packet sent with standard RDP security, carrying a routing token and a target address.
+fn standard_security_redirection() -> Vec<u8> {
+    #[rustfmt::skip]
+    let mut packet = vec![
+        // Flags, length, session ID, redirection flags (target address and load balancing information).
+        0x00, 0x04, 0x52, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+        // Target address.
+        0x12, 0x00, 0x00, 0x00,
+        b'1', 0x00, b'0', 0x00, b'.', 0x00, b'0', 0x00, b'.', 0x00, b'0', 0x00, b'.', 0x00, b'1', 0x00, 0x00, 0x00,
+        // Load balancing information.
+        0x24, 0x00, 0x00, 0x00,
+    ];
+    packet.extend_from_slice(ROUTING_TOKEN);
+    // Padding.
+    packet.extend_from_slice(&[0; 8]);
+
+    packet
+}
+
+#[test]
+fn enhanced_security_redirection_with_fqdn() {
+    let header = decode::<ShareControlHeader>(&ENHANCED_SECURITY_REDIRECTION).unwrap();
+
+    assert_eq!(header.pdu_source, 0x03ea);
+    assert_eq!(header.share_id, 0);
+    let ShareControlPdu::ServerRedirect(pdu) = header.share_control_pdu else {
+        panic!("Server Redirection PDU expected");
+    };
+    assert_eq!(pdu.session_id, 5);
+    assert_eq!(pdu.flags, RedirectionFlags::USERNAME | RedirectionFlags::TARGET_FQDN);
+    assert_eq!(pdu.username.as_deref(), Some("bob"));
+    assert_eq!(pdu.target_fqdn.as_deref(), Some("srv"));
+
+    let redirection = ServerRedirection::from(pdu);
+    assert_eq!(redirection.target.as_deref(), Some("srv"));
+    assert_eq!(redirection.routing_token(), None);
+    assert_eq!(
+        redirection.credentials,
+        RedirectionCredentials {
+            username: Some("bob".to_owned()),
+            domain: None,
+            password: None,
+        }
+    );
+}
+
+#[test]
+fn enhanced_security_redirection_roundtrip() {
+    let header = decode::<ShareControlHeader>(&ENHANCED_SECURITY_REDIRECTION).unwrap();
+
+    assert_eq!(encode_vec(&header).unwrap(), ENHANCED_SECURITY_REDIRECTION);
+}
+
+#[test]
+fn standard_security_redirection_with_routing_token() {
+    let pdu = decode::<ServerRedirectionPdu>(&standard_security_redirection()).unwrap();
+
+    assert_eq!(pdu.target_net_address.as_deref(), Some("10.0.0.1"));
+    assert_eq!(pdu.load_balance_info.as_deref(), Some(ROUTING_TOKEN));
+
+    let redirection = ServerRedirection::from(pdu);
+    assert_eq!(redirection.target.as_deref(), Some("10.0.0.1"));
+    assert_eq!(
+        redirection.routing_token(),
+        Some(NegoRequestData::verbatim(ROUTING_TOKEN.to_vec()))
+    );
+}
+
+#[test]
+fn present_fields_are_derived_when_encoding() {
+    let pdu = ServerRedirectionPdu {
+        session_id: 7,
+        flags: RedirectionFlags::USERNAME | RedirectionFlags::NO_REDIRECT,
+        target_net_address: None,
+        load_balance_info: None,
+        username: None,
+        domain: Some("EXAMPLE".to_owned()),
+        password: Some(vec![0xaa; 6]),
+        target_fqdn: None,
+        target_netbios_name: None,
+        tsv_url: None,
+        redirection_guid: None,
+        target_certificate: None,
+        target_net_addresses: Some(vec!["10.0.0.1".to_owned(), "10.0.0.2".to_owned()]),
+    };
+
+    let decoded = decode::<ServerRedirectionPdu>(&encode_vec(&pdu).unwrap()).unwrap();
+
+    assert_eq!(
+        decoded.flags,
+        RedirectionFlags::NO_REDIRECT
+            | RedirectionFlags::DOMAIN
+            | RedirectionFlags::PASSWORD
+            | RedirectionFlags::TARGET_NET_ADDRESSES
+    );
+    assert_eq!(
+        decoded,
+        ServerRedirectionPdu {
+            flags: decoded.flags,
+            ..pdu
+        }
+    );
+}
@@ -0,0 +1,141 @@
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_pdu::rdp::client_info::{
+    DayOfWeek, DayOfWeekOccurrence, Month, OptionalSystemTime, SystemTime, TimezoneInfo,
+};
+use rstest::rstest;
+
+fn transition(month: Month, day_of_week: DayOfWeek, day: DayOfWeekOccurrence, hour: u16) -> OptionalSystemTime {
+    OptionalSystemTime(Some(SystemTime {
+        month,
+        day_of_week,
+        day,
+        hour,
+        minute: 0,
+        second: 0,
+        milliseconds: 0,
+    }))
+}
+
+/// Pacific Time: UTC-8, and UTC-7 from the second Sunday of March to the first Sunday of November.
+fn pacific() -> TimezoneInfo {
+    TimezoneInfo {
+        bias: 480,
+        standard_name: "Pacific Standard Time".to_owned(),
+        standard_date: transition(Month::November, DayOfWeek::Sunday, DayOfWeekOccurrence::First, 2),
+        standard_bias: 0,
+        daylight_name: "Pacific Daylight Time".to_owned(),
+        daylight_date: transition(Month::March, DayOfWeek::Sunday, DayOfWeekOccurrence::Second, 2),
+        daylight_bias: 0xffff_ffc4,
+    }
+}
+
+/// Australian Eastern Time: UTC+10, and UTC+11 from the first Sunday of October to the first Sunday of April.
+fn sydney() -> TimezoneInfo {
+    TimezoneInfo {
+        bias: 0xffff_fda8,
+        standard_name: "AUS Eastern Standard Time".to_owned(),
+        standard_date: transition(Month::April, DayOfWeek::Sunday, DayOfWeekOccurrence::First, 3),
+        standard_bias: 0,
+        daylight_name: "AUS Eastern Daylight Time".to_owned(),
+        daylight_date: transition(Month::October, DayOfWeek::Sunday, DayOfWeekOccurrence::First, 2),
+        daylight_bias: 0xffff_ffc4,
+    }
+}
+
+/// Japan Standard Time: UTC+9, without daylight saving time.
+fn tokyo() -> TimezoneInfo {
+    TimezoneInfo {
+        bias: 0xffff_fde4,
+        standard_name: "Tokyo Standard Time".to_owned(),
+        standard_date: OptionalSystemTime(None),
+        standard_bias: 0,
+        daylight_name: "Tokyo Daylight Time".to_owned(),
+        daylight_date: OptionalSystemTime(None),
+        daylight_bias: 0,
+    }
+}
+
+#[rstest]
+#[case::second_sunday_of_march(Month::March, DayOfWeek::Sunday, DayOfWeekOccurrence::Second, 2024, 10)]
+#[case::first_sunday_of_november(Month::November, DayOfWeek::Sunday, DayOfWeekOccurrence::First, 2024, 3)]
+#[case::fourth_thursday_of_november(Month::November, DayOfWeek::Thursday, DayOfWeekOccurrence::Fourth, 2024, 28)]
+#[case::last_sunday_of_march(Month::March, DayOfWeek::Sunday, DayOfWeekOccurrence::Last, 2024, 31)]
+#[case::last_sunday_of_march_next_year(Month::March, DayOfWeek::Sunday, DayOfWeekOccurrence::Last, 2025, 30)]
+#[case::last_sunday_of_october(Month::October, DayOfWeek::Sunday, DayOfWeekOccurrence::Last, 2023, 29)]
+#[case::last_friday_on_the_fifth_week(Month::March, DayOfWeek::Friday, DayOfWeekOccurrence::Last, 2024, 29)]
+#[case::first_day_of_the_month(Month::March, DayOfWeek::Friday, DayOfWeekOccurrence::First, 2024, 1)]
+#[case::last_day_of_the_month(Month::August, DayOfWeek::Saturday, DayOfWeekOccurrence::Last, 2024, 31)]
+#[case::january(Month::January, DayOfWeek::Monday, DayOfWeekOccurrence::First, 2024, 1)]
+#[case::last_thursday_of_leap_february(Month::February, DayOfWeek::Thursday, DayOfWeekOccurrence::Last, 2024, 29)]
+#[case::last_thursday_of_february(Month::February, DayOfWeek::Thursday, DayOfWeekOccurrence::Last, 2023, 23)]
+#[case::last_monday_of_century_february(Month::February, DayOfWeek::Monday, DayOfWeekOccurrence::Last, 2100, 22)]
+#[case::last_tuesday_of_leap_century_february(Month::February, DayOfWeek::Tuesday, DayOfWeekOccurrence::Last, 2000, 29)]
+fn transition_day_of_month(
+    #[case] month: Month,
+    #[case] day_of_week: DayOfWeek,
+    #[case] day: DayOfWeekOccurrence,
+    #[case] year: u16,
+    #[case] expected: u8,
+) {
+    let OptionalSystemTime(Some(transition)) = transition(month, day_of_week, day, 0) else {
+        unreachable!()
+    };
+    assert_eq!(transition.day_of_month(year), expected);
+}
+
+#[test]
+fn utc_offsets() {
+    let pacific = pacific();
+    assert_eq!(pacific.standard_offset_minutes(), -480);
+    assert_eq!(pacific.daylight_offset_minutes(), -420);
+    assert!(pacific.observes_daylight_time());
+
+    let sydney = sydney();
+    assert_eq!(sydney.standard_offset_minutes(), 600);
+    assert_eq!(sydney.daylight_offset_minutes(), 660);
+    assert!(sydney.observes_daylight_time());
+
+    let tokyo = tokyo();
+    assert_eq!(tokyo.standard_offset_minutes(), 540);
+    assert!(!tokyo.observes_daylight_time());
+}
+
+#[rstest]
+#[case::winter(Month::January, 15, 12, false)]
+#[case::before_spring_forward(Month::March, 10, 1, false)]
+#[case::spring_forward(Month::March, 10, 2, true)]
+#[case::summer(Month::July, 4, 12, true)]
+#[case::before_fall_back(Month::November, 3, 1, true)]
+#[case::fall_back(Month::November, 3, 2, false)]
+#[case::end_of_year(Month::December, 31, 23, false)]
+fn northern_hemisphere_daylight_time(#[case] month: Month, #[case] day: u8, #[case] hour: u16, #[case] expected: bool) {
+    assert_eq!(pacific().is_daylight_time(2024, month, day, hour, 0), expected);
+}
+
+#[rstest]
+#[case::start_of_year(Month::January, 1, 0, true)]
+#[case::summer(Month::February, 15, 12, true)]
+#[case::before_fall_back(Month::April, 7, 2, true)]
+#[case::fall_back(Month::April, 7, 3, false)]
+#[case::winter(Month::July, 15, 12, false)]
+#[case::before_spring_forward(Month::October, 6, 1, false)]
+#[case::spring_forward(Month::October, 6, 2, true)]
+#[case::end_of_year(Month::December, 25, 12, true)]
+fn southern_hemisphere_daylight_time(#[case] month: Month, #[case] day: u8, #[case] hour: u16, #[case] expected: bool) {
+    assert_eq!(sydney().is_daylight_time(2024, month, day, hour, 0), expected);
+}
+
+#[test]
+fn no_daylight_time_without_transition_dates() {
+    assert!(!tokyo().is_daylight_time(2024, Month::July, 15, 12, 0));
+}
+
+#[rstest]
+#[case::northern_hemisphere(pacific())]
+#[case::southern_hemisphere(sydney())]
+#[case::no_daylight_time(tokyo())]
+fn time_zone_round_trip(#[case] time_zone: TimezoneInfo) {
+    let encoded = encode_vec(&time_zone).unwrap();
+    assert_eq!(encoded.len(), 172);
+    assert_eq!(decode::<TimezoneInfo>(&encoded).unwrap(), time_zone);
+}
@@ -0,0 +1,165 @@
+use ironrdp_core::{ReadCursor, WriteCursor};
+use ironrdp_pdu::utf16::{
+    decode_utf16le_lossy_exact, decode_utf16le_until_null, encode_utf16le_fixed_size, encode_utf16le_null_terminated,
+    null_terminated_utf16_encoded_len, FixedSizeOverflow,
+};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+fn encode_null_terminated(value: &str) -> Vec<u8> {
+    let mut buffer = vec![0xff; null_terminated_utf16_encoded_len(value)];
+    encode_utf16le_null_terminated(&mut WriteCursor::new(&mut buffer), value).unwrap();
+    buffer
+}
+
+fn encode_fixed_size(value: &str, size: usize, overflow: FixedSizeOverflow) -> Option<Vec<u8>> {
+    // Garbage after the field, which must not be touched.
+    let mut buffer = vec![0xff; size + 2];
+    let mut cursor = WriteCursor::new(&mut buffer);
+    encode_utf16le_fixed_size(&mut cursor, value, size, overflow).ok()?;
+    assert_eq!(cursor.pos(), size);
+    assert_eq!(buffer[size..], [0xff, 0xff]);
+    buffer.truncate(size);
+    Some(buffer)
+}
+
+fn decode_until_null(bytes: &[u8]) -> (String, usize) {
+    let mut cursor = ReadCursor::new(bytes);
+    let value = decode_utf16le_until_null(&mut cursor);
+    (value, cursor.pos())
+}
+
+#[test]
+fn null_terminated_encoding() {
+    assert_eq!(encode_null_terminated(""), [0x00, 0x00]);
+    assert_eq!(
+        encode_null_terminated("é😀"),
+        [0xe9, 0x00, 0x3d, 0xd8, 0x00, 0xde, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn null_terminated_encoding_not_enough_space() {
+    let mut buffer = [0u8; 3];
+    encode_utf16le_null_terminated(&mut WriteCursor::new(&mut buffer), "a").unwrap_err();
+}
+
+#[test]
+fn embedded_null_cuts_the_string() {
+    let encoded = encode_null_terminated("ab\0cd");
+    assert_eq!(encoded.len(), 12);
+    assert_eq!(decode_until_null(&encoded), ("ab".to_owned(), 6));
+}
+
+#[test]
+fn decoding_without_null_terminator() {
+    assert_eq!(decode_until_null(&[0x61, 0x00, 0x62, 0x00]), ("ab".to_owned(), 4));
+}
+
+#[test]
+fn decoding_odd_byte_count() {
+    assert_eq!(decode_until_null(&[0x61, 0x00, 0x62]), ("a".to_owned(), 3));
+    assert_eq!(decode_until_null(&[0x61]), (String::new(), 1));
+    assert_eq!(decode_until_null(&[]), (String::new(), 0));
+}
+
+#[test]
+fn unpaired_surrogates_are_replaced() {
+    // Lone high surrogate, then lone low surrogate.
+    let bytes = [0x3d, 0xd8, 0x61, 0x00, 0x00, 0xde, 0x00, 0x00];
+    assert_eq!(decode_until_null(&bytes), ("\u{fffd}a\u{fffd}".to_owned(), 8));
+
+    // High surrogate right before the null terminator.
+    assert_eq!(decode_until_null(&[0x3d, 0xd8, 0x00, 0x00]), ("\u{fffd}".to_owned(), 4));
+}
+
+#[test]
+fn fixed_size_encoding_pads_with_zeroes() {
+    assert_eq!(
+        encode_fixed_size("ab", 8, FixedSizeOverflow::Error).unwrap(),
+        [0x61, 0x00, 0x62, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+
+    // The last byte of an odd-sized field is padding.
+    assert_eq!(
+        encode_fixed_size("ab", 7, FixedSizeOverflow::Error).unwrap(),
+        [0x61, 0x00, 0x62, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn fixed_size_encoding_keeps_the_null_terminator() {
+    assert!(encode_fixed_size("abc", 6, FixedSizeOverflow::Error).is_none());
+    assert_eq!(
+        encode_fixed_size("abc", 6, FixedSizeOverflow::Truncate).unwrap(),
+        [0x61, 0x00, 0x62, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn fixed_size_truncation_does_not_split_surrogate_pairs() {
+    assert_eq!(
+        encode_fixed_size("a😀", 6, FixedSizeOverflow::Truncate).unwrap(),
+        [0x61, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn fixed_size_encoding_without_room_for_the_null_terminator() {
+    for overflow in [FixedSizeOverflow::Truncate, FixedSizeOverflow::Error] {
+        assert!(encode_fixed_size("", 0, overflow).is_none());
+        assert!(encode_fixed_size("", 1, overflow).is_none());
+    }
+}
+
+#[test]
+fn fixed_size_decoding_consumes_the_whole_field() {
+    let bytes = [0x61, 0x00, 0x00, 0x00, 0x62, 0x00, 0x63];
+    let mut cursor = ReadCursor::new(&bytes);
+
+    assert_eq!(decode_utf16le_lossy_exact(&mut cursor, 6).unwrap(), "a");
+    assert_eq!(cursor.pos(), 6);
+
+    decode_utf16le_lossy_exact(&mut cursor, 2).unwrap_err();
+    assert_eq!(cursor.pos(), 6);
+}
+
+#[test]
+fn null_terminated_round_trip() {
+    proptest!(|(value in "[^\0]*")| {
+        let encoded = encode_null_terminated(&value);
+        prop_assert_eq!(decode_until_null(&encoded), (value, encoded.len()));
+    })
+}
+
+#[test]
+fn fixed_size_round_trip() {
+    proptest!(|(value in "[^\0]{0,16}", size in 2usize..80)| {
+        let capacity = (size - 2) / 2;
+        let fits = value.encode_utf16().count() <= capacity;
+
+        let encoded = encode_fixed_size(&value, size, FixedSizeOverflow::Error);
+        prop_assert_eq!(encoded.is_some(), fits);
+
+        let encoded = encode_fixed_size(&value, size, FixedSizeOverflow::Truncate).unwrap();
+        let decoded = decode_utf16le_lossy_exact(&mut ReadCursor::new(&encoded), size).unwrap();
+
+        prop_assert!(value.starts_with(&decoded));
+        prop_assert!(decoded.encode_utf16().count() <= capacity);
+        if fits {
+            prop_assert_eq!(decoded, value);
+        }
+    })
+}
+
+#[test]
+fn arbitrary_bytes_do_not_panic() {
+    proptest!(|(bytes in vec(any::<u8>(), 0..64), size in 0usize..80)| {
+        let (_, consumed) = decode_until_null(&bytes);
+        prop_assert!(consumed <= bytes.len());
+
+        let mut cursor = ReadCursor::new(&bytes);
+        let result = decode_utf16le_lossy_exact(&mut cursor, size);
+        prop_assert_eq!(result.is_ok(), size <= bytes.len());
+    })
+}
@@ -1,8 +1,9 @@
 use expect_test::expect;
+use ironrdp_core::{decode, encode_vec};
 use ironrdp_core::{ReadCursor, WriteCursor};
 use ironrdp_pdu::nego::{
-    ConnectionConfirm, ConnectionRequest, Cookie, FailureCode, NegoRequestData, RequestFlags, ResponseFlags,
-    RoutingToken, SecurityProtocol,
+    ConnectionConfirm, ConnectionRequest, Cookie, CorrelationInfo, FailureCode, NegoRequestData,
+    NegotiationFailureCode, RequestFlags, ResponseFlags, RoutingToken, SecurityProtocol,
 };
 use ironrdp_pdu::tpdu::{TpduCode, TpduHeader};
 use ironrdp_pdu::tpkt::TpktHeader;
@@ -79,6 +80,7 @@ encode_decode_test! {
             nego_data: None,
             flags: RequestFlags::empty(),
             protocol: SecurityProtocol::empty(),
+            correlation_info: None,
         }),
         [
             // tpkt header
@@ -100,6 +102,7 @@ encode_decode_test! {
             nego_data: Some(NegoRequestData::Cookie(Cookie("User".to_owned()))),
             flags: RequestFlags::empty(),
             protocol: SecurityProtocol::empty(),
+            correlation_info: None,
         }),
         [
             // tpkt header
@@ -123,6 +126,7 @@ encode_decode_test! {
             nego_data: Some(NegoRequestData::Cookie(Cookie("User".to_owned()))),
             flags: RequestFlags::empty(),
             protocol: SecurityProtocol::HYBRID | SecurityProtocol::SSL,
+            correlation_info: None,
         }),
         [
             // tpkt header
@@ -146,6 +150,7 @@ encode_decode_test! {
             nego_data: Some(NegoRequestData::Cookie(Cookie("User".to_owned()))),
             flags: RequestFlags::RESTRICTED_ADMIN_MODE_REQUIRED | RequestFlags::REDIRECTED_AUTHENTICATION_MODE_REQUIRED,
             protocol: SecurityProtocol::HYBRID | SecurityProtocol::SSL,
+            correlation_info: None,
         }),
         [
             // tpkt header
@@ -168,6 +173,41 @@ encode_decode_test! {
             0x03, 0x00, 0x00, 0x00, // request message
         ];
 
+    nego_connection_request_with_correlation_info:
+        X224(ConnectionRequest {
+            nego_data: None,
+            flags: RequestFlags::CORRELATION_INFO_PRESENT,
+            protocol: SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX,
+            correlation_info: Some(CorrelationInfo {
+                correlation_id: [
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0E, 0x0F, 0x10, 0x11,
+                ],
+            }),
+        }),
+        [
+            // tpkt header
+            0x03, // version
+            0x00, // reserved
+            0x00, 0x37, // length in BE
+            // tpdu header
+            0x32, // length
+            0xE0, // code
+            0x00, 0x00, // dst_ref
+            0x00, 0x00, // src_ref
+            0x00, // class
+            // RDP_NEG_REQ
+            0x01, // type
+            0x08, // flags
+            0x08, 0x00, // length
+            0x0A, 0x00, 0x00, 0x00, // requested protocols
+            // RDP_NEG_CORRELATION_INFO
+            0x06, // type
+            0x00, // flags
+            0x24, 0x00, // length
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0E, 0x0F, 0x10, 0x11, // correlationId
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // reserved
+        ];
+
     nego_confirm_response:
         X224(ConnectionConfirm::Response {
             flags: ResponseFlags::from_bits_truncate(0x1F),
@@ -237,7 +277,7 @@ fn nego_request_unexpected_rdp_msg_type() {
         0x03, 0x00, 0x00, 0x00, // rest
     ];
 
-    let e = ironrdp_core::decode::<X224<ConnectionRequest>>(&payload).err().unwrap();
+    let e = decode::<X224<ConnectionRequest>>(&payload).err().unwrap();
 
     expect![[r#"
         Error {
@@ -271,7 +311,7 @@ fn nego_confirm_unexpected_rdp_msg_type() {
         0x02, 0x00, 0x00, 0x00, // selected protocol
     ];
 
-    let e = ironrdp_core::decode::<X224<ConnectionConfirm>>(&payload).err().unwrap();
+    let e = decode::<X224<ConnectionConfirm>>(&payload).err().unwrap();
 
     expect![[r#"
         Error {
@@ -347,3 +387,81 @@ fn cookie_without_cr_lf_error_decode() {
     "#]]
     .assert_debug_eq(&e);
 }
+
+#[test]
+fn nego_request_correlation_info_sets_flag() {
+    let request = ConnectionRequest {
+        nego_data: None,
+        flags: RequestFlags::empty(),
+        protocol: SecurityProtocol::HYBRID,
+        correlation_info: Some(CorrelationInfo {
+            correlation_id: [0x42; 16],
+        }),
+    };
+
+    let decoded = decode::<X224<ConnectionRequest>>(&encode_vec(&X224(request.clone())).unwrap())
+        .unwrap()
+        .0;
+
+    assert_eq!(decoded.flags, RequestFlags::CORRELATION_INFO_PRESENT);
+    assert_eq!(decoded.correlation_info, request.correlation_info);
+}
+
+#[test]
+fn nego_request_invalid_correlation_id() {
+    let mut ending_with_cr = [0x42; 16];
+    ending_with_cr[15] = 0x0D;
+
+    for correlation_id in [[0x00; 16], [0xF4; 16], ending_with_cr] {
+        assert!(!CorrelationInfo::is_valid_id(&correlation_id));
+
+        let request = ConnectionRequest {
+            nego_data: None,
+            flags: RequestFlags::empty(),
+            protocol: SecurityProtocol::HYBRID,
+            correlation_info: Some(CorrelationInfo { correlation_id }),
+        };
+
+        encode_vec(&X224(request)).unwrap_err();
+    }
+}
+
+#[test]
+fn nego_request_correlation_info_flag_without_info() {
+    let request = ConnectionRequest {
+        nego_data: None,
+        flags: RequestFlags::CORRELATION_INFO_PRESENT,
+        protocol: SecurityProtocol::HYBRID,
+        correlation_info: None,
+    };
+
+    encode_vec(&X224(request)).unwrap_err();
+}
+
+#[test]
+fn nego_confirm_failure_codes() {
+    let codes = [
+        (0x01, NegotiationFailureCode::SslRequiredByServer),
+        (0x02, NegotiationFailureCode::SslNotAllowedByServer),
+        (0x03, NegotiationFailureCode::SslCertNotOnServer),
+        (0x04, NegotiationFailureCode::InconsistentFlags),
+        (0x05, NegotiationFailureCode::HybridRequiredByServer),
+        (0x06, NegotiationFailureCode::SslWithUserAuthRequiredByServer),
+        (0x42, NegotiationFailureCode::Unknown(0x42)),
+    ];
+
+    for (code, expected) in codes {
+        let payload = [
+            0x03, 0x00, 0x00, 0x13, // tpkt header
+            0x0E, 0xD0, 0x00, 0x00, 0x00, 0x00, 0x00, // tpdu header
+            0x03, 0x00, 0x08, 0x00, code, 0x00, 0x00, 0x00, // RDP_NEG_FAILURE
+        ];
+
+        let ConnectionConfirm::Failure { code } = decode::<X224<ConnectionConfirm>>(&payload).unwrap().0 else {
+            panic!("expected a negotiation failure");
+        };
+
+        assert_eq!(NegotiationFailureCode::from(code), expected);
+        assert_eq!(FailureCode::from(expected), code);
+    }
+}
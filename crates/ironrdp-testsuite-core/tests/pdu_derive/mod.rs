@@ -0,0 +1,129 @@
+use ironrdp_core::{decode, encode_vec, Encode as _};
+use ironrdp_pdu::rdp::vc::{ChannelControlFlags, ChannelPduHeader};
+use ironrdp_pdu_derive::{PduDecode, PduEncode};
+use ironrdp_testsuite_core::encode_decode_test;
+
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode, PduDecode)]
+#[pdu(name = "INNER_PDU")]
+struct Inner {
+    value: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode, PduDecode)]
+#[pdu(name = "TEST_PDU")]
+struct TestPdu {
+    #[pdu(padding = 2)]
+    byte: u8,
+    little: i32,
+    #[pdu(big_endian)]
+    big: u32,
+    #[pdu(bitflags = u32)]
+    flags: ChannelControlFlags,
+    array: [u8; 3],
+    inner: Inner,
+    #[pdu(len_prefix = u8)]
+    values: Vec<u16>,
+    #[pdu(len_prefix = u16, big_endian)]
+    inners: Vec<Inner>,
+    #[pdu(skip)]
+    skipped: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PduEncode, PduDecode)]
+#[pdu(big_endian)]
+struct BigEndianPdu {
+    big: u16,
+    #[pdu(little_endian)]
+    little: u16,
+}
+
+fn test_pdu() -> TestPdu {
+    TestPdu {
+        byte: 0x01,
+        little: -2,
+        big: 0x0304_0506,
+        flags: ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::FLAG_LAST,
+        array: [0x07, 0x08, 0x09],
+        inner: Inner { value: 0x0A0B },
+        values: vec![0x0C0D, 0x0E0F],
+        inners: vec![Inner { value: 0x1011 }],
+        skipped: 0,
+    }
+}
+
+#[rustfmt::skip]
+const TEST_PDU: [u8; 31] = [
+    // byte, followed by padding
+    0x01, 0x00, 0x00,
+    // little
+    0xFE, 0xFF, 0xFF, 0xFF,
+    // big
+    0x03, 0x04, 0x05, 0x06,
+    // flags
+    0x03, 0x00, 0x00, 0x00,
+    // array
+    0x07, 0x08, 0x09,
+    // inner
+    0x0B, 0x0A,
+    // values
+    0x02, 0x0D, 0x0C, 0x0F, 0x0E,
+    // inners
+    0x00, 0x01, 0x11, 0x10,
+    // trailing bytes, left unread
+    0xFF, 0xFF,
+];
+
+encode_decode_test! {
+    all_field_kinds: test_pdu(), TEST_PDU[..29].to_vec();
+    struct_endianness: BigEndianPdu { big: 0x0102, little: 0x0304 }, [0x01, 0x02, 0x04, 0x03];
+    channel_pdu_header: ChannelPduHeader {
+        length: 0x0640,
+        flags: ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::COMPRESSION_TYPE_MASK,
+    },
+    [0x40, 0x06, 0x00, 0x00, 0x01, 0x00, 0x0F, 0x00];
+    ainput_version: ironrdp_ainput::VersionPdu::new(), [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+}
+
+#[test]
+fn name_is_configurable() {
+    assert_eq!(test_pdu().name(), "TEST_PDU");
+    assert_eq!(BigEndianPdu { big: 0, little: 0 }.name(), "BigEndianPdu");
+}
+
+#[test]
+fn decode_ignores_unknown_flags_and_trailing_bytes() {
+    let mut encoded = TEST_PDU.to_vec();
+    encoded[14] = 0x80;
+
+    let decoded = decode::<TestPdu>(&encoded).unwrap();
+
+    assert_eq!(
+        decoded.flags,
+        ChannelControlFlags::FLAG_FIRST | ChannelControlFlags::FLAG_LAST
+    );
+}
+
+#[test]
+fn decode_fails_on_truncated_input() {
+    for len in 0..29 {
+        decode::<TestPdu>(&TEST_PDU[..len]).unwrap_err();
+    }
+}
+
+#[test]
+fn encode_fails_on_too_many_elements() {
+    let pdu = TestPdu {
+        values: vec![0; 256],
+        ..test_pdu()
+    };
+
+    encode_vec(&pdu).unwrap_err();
+}
+
+#[test]
+fn encode_fails_on_small_buffer() {
+    let pdu = test_pdu();
+    let mut buffer = [0; 28];
+
+    ironrdp_core::encode(&pdu, &mut buffer).unwrap_err();
+}
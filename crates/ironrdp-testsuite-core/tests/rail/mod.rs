@@ -0,0 +1,288 @@
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_pdu::geometry::ExclusiveRectangle;
+use ironrdp_rail::client::{Rail, RailBackend};
+use ironrdp_rail::pdu::{self, RailPdu};
+use ironrdp_svc::{StaticVirtualChannel, SvcProcessor as _};
+use ironrdp_testsuite_core::encode_decode_test;
+
+encode_decode_test! {
+    handshake: RailPdu::Handshake(pdu::HandshakePdu { build_number: 0x1db0 }),
+    [0x05, 0x00, 0x08, 0x00, 0xb0, 0x1d, 0x00, 0x00];
+    handshake_ex: RailPdu::HandshakeEx(pdu::HandshakeExPdu {
+        build_number: 0x1db0,
+        flags: pdu::HandshakeExFlags::HIDEF,
+    }),
+    [0x13, 0x00, 0x0c, 0x00, 0xb0, 0x1d, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    client_status: RailPdu::ClientStatus(pdu::ClientStatusPdu {
+        flags: pdu::ClientStatusFlags::ALLOWLOCALMOVESIZE,
+    }),
+    [0x0b, 0x00, 0x08, 0x00, 0x01, 0x00, 0x00, 0x00];
+    exec: RailPdu::Exec(pdu::ExecPdu {
+        flags: pdu::ExecFlags::EXPAND_ARGUMENTS,
+        exe_or_file: "||notepad".to_owned(),
+        working_dir: String::new(),
+        arguments: "a.txt".to_owned(),
+    }),
+    [
+        0x01, 0x00, 0x28, 0x00, 0x08, 0x00, 0x12, 0x00, 0x00, 0x00, 0x0a, 0x00, // header, flags and lengths
+        b'|', 0x00, b'|', 0x00, b'n', 0x00, b'o', 0x00, b't', 0x00, b'e', 0x00, b'p', 0x00, b'a', 0x00, b'd', 0x00,
+        b'a', 0x00, b'.', 0x00, b't', 0x00, b'x', 0x00, b't', 0x00,
+    ];
+    exec_result: RailPdu::ExecResult(pdu::ExecResultPdu {
+        flags: pdu::ExecFlags::empty(),
+        exec_result: pdu::ExecResult::FILE_NOT_FOUND,
+        raw_result: 2,
+        exe_or_file: "||notepad".to_owned(),
+    }),
+    [
+        0x80, 0x00, 0x22, 0x00, 0x00, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x00,
+        b'|', 0x00, b'|', 0x00, b'n', 0x00, b'o', 0x00, b't', 0x00, b'e', 0x00, b'p', 0x00, b'a', 0x00, b'd', 0x00,
+    ];
+    sys_param_work_area: RailPdu::SysParam(pdu::SysParam::WorkArea(ExclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: 1280,
+        bottom: 1024,
+    })),
+    [0x03, 0x00, 0x10, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04];
+    sys_param_high_contrast: RailPdu::SysParam(pdu::SysParam::HighContrast(pdu::HighContrast {
+        flags: 0x7e,
+        color_scheme: String::new(),
+    })),
+    [0x03, 0x00, 0x12, 0x00, 0x43, 0x00, 0x00, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00];
+    sys_param_mouse_button_swap: RailPdu::SysParam(pdu::SysParam::MouseButtonSwap(true)),
+    [0x03, 0x00, 0x09, 0x00, 0x21, 0x00, 0x00, 0x00, 0x01];
+    sys_param_unknown: RailPdu::SysParam(pdu::SysParam::Unknown {
+        system_param: 0xf002,
+        body: vec![0xaa, 0xbb],
+    }),
+    [0x03, 0x00, 0x0a, 0x00, 0x02, 0xf0, 0x00, 0x00, 0xaa, 0xbb];
+    min_max_info: RailPdu::MinMaxInfo(pdu::MinMaxInfoPdu {
+        window_id: 0x2a,
+        max_width: 1280,
+        max_height: 1024,
+        max_pos_x: -8,
+        max_pos_y: -8,
+        min_track_width: 120,
+        min_track_height: 40,
+        max_track_width: 1300,
+        max_track_height: 1040,
+    }),
+    [
+        0x0a, 0x00, 0x18, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0xf8, 0xff, 0xf8, 0xff, 0x78, 0x00,
+        0x28, 0x00, 0x14, 0x05, 0x10, 0x04,
+    ];
+    local_move_size: RailPdu::LocalMoveSize(pdu::LocalMoveSizePdu {
+        window_id: 0x2a,
+        is_move_size_start: true,
+        move_size_type: 0x9,
+        pos_x: 100,
+        pos_y: 200,
+    }),
+    [0x09, 0x00, 0x10, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x64, 0x00, 0xc8, 0x00];
+    window_move: RailPdu::WindowMove(pdu::WindowMovePdu {
+        window_id: 0x2a,
+        left: -10,
+        top: 0,
+        right: 630,
+        bottom: 480,
+    }),
+    [0x08, 0x00, 0x10, 0x00, 0x2a, 0x00, 0x00, 0x00, 0xf6, 0xff, 0x00, 0x00, 0x76, 0x02, 0xe0, 0x01];
+    rail_capability_set: pdu::RailCapabilitySet {
+        support_level: pdu::RailSupportLevel::SUPPORTED | pdu::RailSupportLevel::HANDSHAKE_EX_SUPPORTED,
+    },
+    [0x81, 0x00, 0x00, 0x00];
+    window_list_capability_set: pdu::WindowListCapabilitySet {
+        support_level: pdu::WindowSupportLevel::Supported,
+        num_icon_caches: 3,
+        num_icon_cache_entries: 12,
+    },
+    [0x01, 0x00, 0x00, 0x00, 0x03, 0x0c, 0x00];
+}
+
+#[test]
+fn trailing_fields_are_skipped() {
+    // Client status carrying an extra field from a later version of the protocol.
+    let encoded = [0x0b, 0x00, 0x0c, 0x00, 0x04, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff];
+
+    let pdu = decode::<RailPdu>(&encoded).unwrap();
+
+    assert_eq!(
+        pdu,
+        RailPdu::ClientStatus(pdu::ClientStatusPdu {
+            flags: pdu::ClientStatusFlags::ZORDER_SYNC,
+        })
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WindowEvent {
+    Created(u32),
+    Deleted(u32),
+}
+
+#[derive(Debug, Default)]
+struct RecordingBackend {
+    window_events: Arc<Mutex<Vec<WindowEvent>>>,
+    exec_results: Arc<Mutex<Vec<pdu::ExecResult>>>,
+}
+
+impl RailBackend for RecordingBackend {
+    fn on_window_created(&mut self, window_id: u32) {
+        self.window_events.lock().unwrap().push(WindowEvent::Created(window_id));
+    }
+
+    fn on_window_deleted(&mut self, window_id: u32) {
+        self.window_events.lock().unwrap().push(WindowEvent::Deleted(window_id));
+    }
+
+    fn on_exec_result(&mut self, result: &pdu::ExecResultPdu) {
+        self.exec_results.lock().unwrap().push(result.exec_result);
+    }
+}
+
+fn notepad_rail(backend: RecordingBackend) -> Rail {
+    let exec = pdu::ExecPdu {
+        flags: pdu::ExecFlags::empty(),
+        exe_or_file: "||notepad".to_owned(),
+        working_dir: String::new(),
+        arguments: String::new(),
+    };
+
+    Rail::with_desktop_size(exec, 1280, 1024, Box::new(backend))
+}
+
+#[test]
+fn initialization_sequence_after_server_handshake() {
+    let backend = RecordingBackend::default();
+    let exec_results = Arc::clone(&backend.exec_results);
+    let mut rail = notepad_rail(backend);
+
+    let server_handshake = RailPdu::HandshakeEx(pdu::HandshakeExPdu {
+        build_number: 0x2580,
+        flags: pdu::HandshakeExFlags::HIDEF,
+    });
+    let messages = rail.process(&encode_vec(&server_handshake).unwrap()).unwrap();
+
+    assert!(rail.is_ready());
+
+    let sent = StaticVirtualChannel::chunkify(messages)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| {
+            // Skip the channel PDU header.
+            decode::<RailPdu>(&chunk.filled()[8..]).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(matches!(sent.first(), Some(RailPdu::Handshake(_))));
+    assert!(matches!(sent.get(1), Some(RailPdu::ClientStatus(_))));
+    assert!(
+        sent.contains(&RailPdu::SysParam(pdu::SysParam::WorkArea(ExclusiveRectangle {
+            left: 0,
+            top: 0,
+            right: 1280,
+            bottom: 1024,
+        })))
+    );
+    assert!(matches!(
+        sent.last(),
+        Some(RailPdu::Exec(pdu::ExecPdu { exe_or_file, .. })) if exe_or_file == "||notepad"
+    ));
+
+    let exec_result = RailPdu::ExecResult(pdu::ExecResultPdu {
+        flags: pdu::ExecFlags::empty(),
+        exec_result: pdu::ExecResult::OK,
+        raw_result: 0,
+        exe_or_file: "||notepad".to_owned(),
+    });
+    let messages = rail.process(&encode_vec(&exec_result).unwrap()).unwrap();
+
+    assert!(messages.is_empty());
+    assert_eq!(*exec_results.lock().unwrap(), [pdu::ExecResult::OK]);
+}
+
+#[test]
+fn exec_result_before_handshake_is_ignored() {
+    let backend = RecordingBackend::default();
+    let exec_results = Arc::clone(&backend.exec_results);
+    let mut rail = notepad_rail(backend);
+
+    let exec_result = RailPdu::ExecResult(pdu::ExecResultPdu {
+        flags: pdu::ExecFlags::empty(),
+        exec_result: pdu::ExecResult::OK,
+        raw_result: 0,
+        exe_or_file: "||notepad".to_owned(),
+    });
+    let messages = rail.process(&encode_vec(&exec_result).unwrap()).unwrap();
+
+    assert!(messages.is_empty());
+    assert!(!rail.is_ready());
+    assert!(exec_results.lock().unwrap().is_empty());
+}
+
+#[test]
+fn unsupported_order_is_ignored() {
+    let mut rail = notepad_rail(RecordingBackend::default());
+
+    // TS_RAIL_ORDER_ZORDER_SYNC
+    let messages = rail.process(&[0x14, 0x00, 0x08, 0x00, 0x2a, 0x00, 0x00, 0x00]).unwrap();
+
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn window_orders_notify_backend() {
+    let backend = RecordingBackend::default();
+    let window_events = Arc::clone(&backend.window_events);
+    let mut rail = notepad_rail(backend);
+
+    #[rustfmt::skip]
+    let orders_update = [
+        0x03, 0x00, // numberOrders
+        // New window
+        0x2e, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x11, 0x2a, 0x00, 0x00, 0x00,
+        // Window icon, with the icon info left out
+        0x2e, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x51, 0x2a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // Deleted window
+        0x2e, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x21, 0x2a, 0x00, 0x00, 0x00,
+    ];
+
+    let orders = pdu::decode_window_orders(&orders_update).unwrap();
+    assert_eq!(orders.len(), 3);
+
+    for order in &orders {
+        rail.handle_window_order(order);
+    }
+
+    assert_eq!(
+        *window_events.lock().unwrap(),
+        [WindowEvent::Created(0x2a), WindowEvent::Deleted(0x2a)]
+    );
+}
+
+#[test]
+fn window_orders_decoding_stops_at_other_orders() {
+    #[rustfmt::skip]
+    let orders_update = [
+        0x02, 0x00, // numberOrders
+        // Window title update
+        0x2e, 0x0f, 0x00, 0x04, 0x00, 0x00, 0x01, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // Primary drawing order
+        0x09, 0x0a, 0x00,
+    ];
+
+    let orders = pdu::decode_window_orders(&orders_update).unwrap();
+
+    assert_eq!(
+        orders,
+        [pdu::WindowOrder {
+            fields_present: pdu::WindowOrderFlags::TYPE_WINDOW | pdu::WindowOrderFlags::from_bits_retain(0x4),
+            window_id: Some(7),
+        }]
+    );
+    assert_eq!(orders[0].created_window(), None);
+    assert_eq!(orders[0].deleted_window(), None);
+}
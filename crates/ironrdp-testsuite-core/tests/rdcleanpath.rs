@@ -1,4 +1,8 @@
-use ironrdp_rdcleanpath::{DetectionResult, RDCleanPathPdu, VERSION_1};
+use ironrdp_rdcleanpath::der::asn1::OctetString;
+use ironrdp_rdcleanpath::{
+    ConnectionSnapshot, DetectionResult, RDCleanPath, RDCleanPathPdu, ResumeDecision, CAPABILITY_SNI_OVERRIDE,
+    CAPABILITY_X224_COMPRESSION, VERSION_1,
+};
 use rstest::rstest;
 
 fn request() -> RDCleanPathPdu {
@@ -17,6 +21,25 @@ const REQUEST_DER: &[u8] = &[
     0x3, 0x50, 0x43, 0x42, 0xA6, 0x6, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF,
 ];
 
+fn request_with_capabilities() -> RDCleanPathPdu {
+    request()
+        .with_capability(CAPABILITY_SNI_OVERRIDE)
+        .with_capability(CAPABILITY_X224_COMPRESSION)
+}
+
+const REQUEST_WITH_CAPABILITIES_DER: &[u8] = &[
+    0x30, 0x37, 0xA0, 0x4, 0x2, 0x2, 0xD, 0x3E, 0xA2, 0xD, 0xC, 0xB, 0x64, 0x65, 0x73, 0x74, 0x69, 0x6E, 0x61, 0x74,
+    0x69, 0x6F, 0x6E, 0xA3, 0xC, 0xC, 0xA, 0x70, 0x72, 0x6F, 0x78, 0x79, 0x20, 0x61, 0x75, 0x74, 0x68, 0xA5, 0x5, 0xC,
+    0x3, 0x50, 0x43, 0x42, 0xA6, 0x6, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0xAA, 0x3, 0x2, 0x1, 0x3,
+];
+
+/// Response of a hypothetical version 2 proxy, with an unknown capability (bit 7).
+const RESPONSE_VERSION_2_DER: &[u8] = &[
+    0x30, 0x3A, 0xA0, 0x4, 0x2, 0x2, 0xD, 0x3F, 0xA6, 0x6, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0xA7, 0x14, 0x30, 0x12,
+    0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0xA9, 0xE,
+    0xC, 0xC, 0x31, 0x39, 0x32, 0x2E, 0x31, 0x36, 0x38, 0x2E, 0x37, 0x2E, 0x39, 0x35, 0xAA, 0x4, 0x2, 0x2, 0x0, 0x81,
+];
+
 fn response_success() -> RDCleanPathPdu {
     RDCleanPathPdu::new_response(
         "192.168.7.95".to_owned(),
@@ -56,6 +79,7 @@ const RESPONSE_TLS_ERROR_DER: &[u8] = &[
 
 #[rstest]
 #[case(request())]
+#[case(request_with_capabilities())]
 #[case(response_success())]
 #[case(response_http_error())]
 #[case(response_tls_error())]
@@ -82,6 +106,7 @@ macro_rules! assert_serialization {
 
 #[rstest]
 #[case(request(), REQUEST_DER)]
+#[case(request_with_capabilities(), REQUEST_WITH_CAPABILITIES_DER)]
 #[case(response_success(), RESPONSE_SUCCESS_DER)]
 #[case(response_http_error(), RESPONSE_HTTP_ERROR_DER)]
 #[case(response_tls_error(), RESPONSE_TLS_ERROR_DER)]
@@ -92,6 +117,7 @@ fn serialization(#[case] message: RDCleanPathPdu, #[case] expected_der: &[u8]) {
 
 #[rstest]
 #[case(REQUEST_DER)]
+#[case(REQUEST_WITH_CAPABILITIES_DER)]
 #[case(RESPONSE_SUCCESS_DER)]
 #[case(RESPONSE_HTTP_ERROR_DER)]
 #[case(RESPONSE_TLS_ERROR_DER)]
@@ -110,6 +136,89 @@ fn detect(#[case] der: &[u8]) {
     assert_eq!(detected_length, der.len());
 }
 
+#[test]
+fn detect_newer_version() {
+    let result = RDCleanPathPdu::detect(RESPONSE_VERSION_2_DER);
+
+    assert_eq!(
+        result,
+        DetectionResult::Detected {
+            version: VERSION_1 + 1,
+            total_length: RESPONSE_VERSION_2_DER.len(),
+        }
+    );
+}
+
+#[test]
+fn decode_newer_version() {
+    let pdu = RDCleanPathPdu::from_der(RESPONSE_VERSION_2_DER).unwrap();
+
+    assert_eq!(pdu.version, VERSION_1 + 1);
+    assert_eq!(pdu.capabilities, Some(0x81));
+    assert!(pdu.supports(CAPABILITY_SNI_OVERRIDE));
+    assert!(!pdu.supports(CAPABILITY_SNI_OVERRIDE | CAPABILITY_X224_COMPRESSION));
+
+    let RDCleanPath::Response {
+        server_addr,
+        capabilities,
+        ..
+    } = pdu.into_enum().unwrap()
+    else {
+        panic!("expected a response");
+    };
+    assert_eq!(server_addr, "192.168.7.95");
+    assert_eq!(capabilities, Some(0x81));
+}
+
+#[test]
+fn capabilities_are_absent_by_default() {
+    let request = request();
+
+    assert_eq!(request.capabilities, None);
+    assert!(!request.supports(CAPABILITY_SNI_OVERRIDE));
+    assert!(request.supports(0));
+    assert_eq!(request.negotiate_capabilities(u64::MAX), None);
+}
+
+#[test]
+fn negotiated_capabilities_are_the_intersection() {
+    let offered = CAPABILITY_SNI_OVERRIDE | CAPABILITY_X224_COMPRESSION;
+    let request = request_with_capabilities();
+
+    // The proxy keeps the capabilities it supports.
+    let negotiated = request.negotiate_capabilities(CAPABILITY_X224_COMPRESSION | 0x80);
+    assert_eq!(negotiated, Some(CAPABILITY_X224_COMPRESSION));
+
+    // The client ignores the capabilities it did not offer.
+    let mut response = response_success();
+    response.capabilities = Some(CAPABILITY_X224_COMPRESSION | 0x80);
+    assert_eq!(
+        response.negotiate_capabilities(offered),
+        Some(CAPABILITY_X224_COMPRESSION)
+    );
+}
+
+#[test]
+fn enum_capabilities() {
+    let request = request_with_capabilities().into_enum().unwrap();
+    assert!(request.supports(CAPABILITY_SNI_OVERRIDE | CAPABILITY_X224_COMPRESSION));
+    assert_eq!(request.into_pdu(), request_with_capabilities());
+
+    let response = response_success()
+        .into_enum()
+        .unwrap()
+        .with_capability(CAPABILITY_X224_COMPRESSION);
+    assert!(response.supports(CAPABILITY_X224_COMPRESSION));
+    assert!(!response.supports(CAPABILITY_SNI_OVERRIDE));
+
+    let error = response_http_error()
+        .into_enum()
+        .unwrap()
+        .with_capability(CAPABILITY_SNI_OVERRIDE);
+    assert!(!error.supports(CAPABILITY_SNI_OVERRIDE));
+    assert_eq!(error.into_pdu(), response_http_error());
+}
+
 #[rstest]
 #[case(&[])]
 #[case(&[0x30])]
@@ -123,3 +232,53 @@ fn detect_not_enough(#[case] payload: &[u8]) {
     let result = RDCleanPathPdu::detect(payload);
     assert_eq!(result, DetectionResult::NotEnoughBytes);
 }
+
+fn cert_chain(leaf: u8) -> Vec<OctetString> {
+    vec![
+        OctetString::new(vec![leaf, 0xAD, 0xBE, 0xFF]).unwrap(),
+        OctetString::new(vec![0xCA, 0xFE]).unwrap(),
+    ]
+}
+
+fn snapshot() -> ConnectionSnapshot {
+    ConnectionSnapshot::new("destination".to_owned(), "192.168.7.95".to_owned(), cert_chain(0xDE))
+}
+
+const SNAPSHOT_DER: &[u8] = &[
+    0x30, 0x33, 0xA0, 0x4, 0x2, 0x2, 0xD, 0x3E, 0xA1, 0xD, 0xC, 0xB, 0x64, 0x65, 0x73, 0x74, 0x69, 0x6E, 0x61, 0x74,
+    0x69, 0x6F, 0x6E, 0xA2, 0xE, 0xC, 0xC, 0x31, 0x39, 0x32, 0x2E, 0x31, 0x36, 0x38, 0x2E, 0x37, 0x2E, 0x39, 0x35,
+    0xA3, 0xC, 0x30, 0xA, 0x4, 0x4, 0xDE, 0xAD, 0xBE, 0xFF, 0x4, 0x2, 0xCA, 0xFE,
+];
+
+#[test]
+fn snapshot_serialization() {
+    let encoded = snapshot().to_der().unwrap();
+    assert_serialization!(encoded, SNAPSHOT_DER);
+
+    let decoded = ConnectionSnapshot::from_der(SNAPSHOT_DER).unwrap();
+    assert_eq!(decoded, snapshot());
+}
+
+#[test]
+fn snapshot_unknown_version() {
+    let mut snapshot = snapshot();
+    snapshot.version = VERSION_1 + 1;
+
+    let encoded = snapshot.to_der().unwrap();
+    assert!(ConnectionSnapshot::from_der(&encoded).is_err());
+}
+
+#[rstest]
+#[case("destination", cert_chain(0xDE), ResumeDecision::Resume)]
+#[case("other destination", cert_chain(0xDE), ResumeDecision::DestinationChanged)]
+#[case("other destination", cert_chain(0x42), ResumeDecision::DestinationChanged)]
+#[case("destination", cert_chain(0x42), ResumeDecision::CertificateChainChanged)]
+#[case("destination", cert_chain(0xDE)[..1].to_vec(), ResumeDecision::CertificateChainChanged)]
+#[case("destination", Vec::new(), ResumeDecision::CertificateChainChanged)]
+fn snapshot_resume_decision(
+    #[case] destination: &str,
+    #[case] server_cert_chain: Vec<OctetString>,
+    #[case] expected: ResumeDecision,
+) {
+    assert_eq!(snapshot().resume_decision(destination, &server_cert_chain), expected);
+}
@@ -0,0 +1,346 @@
+use ironrdp_core::{encode_vec, ReadCursor};
+use ironrdp_pdu::utils::CharacterSet;
+use ironrdp_rdpdr::pdu::esc::{
+    CardProtocol, CardState, CardStateFlags, ConnectReturn, EstablishContextReturn, GetAttribReturn,
+    GetDeviceTypeIdReturn, GetReaderIconReturn, GetStatusChangeCall, GetStatusChangeReturn, ListReadersReturn,
+    LongReturn, ReadCacheReturn, ReaderStateCommonCall, ReconnectReturn, ReturnCode, SCardIORequest, ScardCall,
+    ScardContext, ScardHandle, ScardIoCtlCode, StatusReturn, TransmitReturn,
+};
+use ironrdp_testsuite_core::encoded_size_test;
+
+const STREAM_HEADER: [u8; 8] = [
+    0x01, // Version
+    0x10, // Endianness (little-endian)
+    0x08, 0x00, // CommonHeaderLength
+    0xcc, 0xcc, 0xcc, 0xcc, // Filler
+];
+
+/// REDIR_SCARDHANDLE pointers: the context and the handle, both 4 bytes long
+const HANDLE_PTRS: [u8; 16] = [
+    0x04, 0x00, 0x00, 0x00, // Context.cbContext
+    0x00, 0x00, 0x02, 0x00, // Context.pbContext (pointer)
+    0x04, 0x00, 0x00, 0x00, // cbHandle
+    0x04, 0x00, 0x02, 0x00, // pbHandle (pointer)
+];
+
+/// REDIR_SCARDHANDLE values: context 0x11 and handle 0x22
+const HANDLE_VALUES: [u8; 16] = [
+    0x04, 0x00, 0x00, 0x00, // Context.cbContext
+    0x11, 0x00, 0x00, 0x00, // Context.pbContext
+    0x04, 0x00, 0x00, 0x00, // cbHandle
+    0x22, 0x00, 0x00, 0x00, // pbHandle
+];
+
+/// Size of the stream header and of the type header.
+const RPCE_HEADERS_SIZE: usize = 16;
+
+fn handle() -> ScardHandle {
+    ScardHandle::new(ScardContext::new(0x11), 0x22)
+}
+
+/// Wraps an NDR-encoded call in the RPCE headers.
+fn rpce_message(body: &[&[u8]]) -> Vec<u8> {
+    let body = body.concat();
+    let length = u32::try_from(body.len()).unwrap();
+
+    [
+        STREAM_HEADER.as_slice(),
+        &length.to_le_bytes(), // ObjectBufferLength
+        &[0; 4],               // Filler
+        &body,
+    ]
+    .concat()
+}
+
+fn decode_call(io_ctl_code: ScardIoCtlCode, message: &[u8]) -> ScardCall {
+    let mut src = ReadCursor::new(message);
+    let call = ScardCall::decode(io_ctl_code, &mut src).unwrap();
+    assert!(src.is_empty());
+    call
+}
+
+fn reader_state() -> ReaderStateCommonCall {
+    ReaderStateCommonCall {
+        current_state: CardStateFlags::SCARD_STATE_EMPTY,
+        event_state: CardStateFlags::SCARD_STATE_PRESENT | CardStateFlags::SCARD_STATE_CHANGED,
+        atr_length: 3,
+        atr: [0x3b; 36],
+    }
+}
+
+encoded_size_test! {
+    long_return: LongReturn::new(ReturnCode::Success);
+    establish_context_return: EstablishContextReturn::new(ReturnCode::Success, ScardContext::new(0x11));
+    list_readers_return: ListReadersReturn::new(ReturnCode::Success, vec!["R1".to_owned(), "Reader 2".to_owned()]);
+    empty_list_readers_return: ListReadersReturn::new(ReturnCode::NoReadersAvailable, Vec::new());
+    get_status_change_return: GetStatusChangeReturn::new(ReturnCode::Success, vec![reader_state(), reader_state()]);
+    connect_return: ConnectReturn::new(ReturnCode::Success, handle(), CardProtocol::SCARD_PROTOCOL_T1);
+    reconnect_return: ReconnectReturn::new(ReturnCode::Success, CardProtocol::SCARD_PROTOCOL_T1);
+    transmit_return: TransmitReturn::new(ReturnCode::Success, None, vec![0x90, 0x00]);
+    transmit_return_with_recv_pci: TransmitReturn::new(
+        ReturnCode::Success,
+        Some(SCardIORequest {
+            protocol: CardProtocol::SCARD_PROTOCOL_T1,
+            extra_bytes_length: 3,
+            extra_bytes: vec![1, 2, 3],
+        }),
+        vec![0x90, 0x00],
+    );
+    status_return_ascii: StatusReturn::new(
+        ReturnCode::Success,
+        vec!["R1".to_owned()],
+        CardState::SpecificMode,
+        CardProtocol::SCARD_PROTOCOL_T1,
+        [0x3b; 32],
+        3,
+        CharacterSet::Ansi,
+    );
+    status_return_unicode: StatusReturn::new(
+        ReturnCode::Success,
+        vec!["R1".to_owned()],
+        CardState::SpecificMode,
+        CardProtocol::SCARD_PROTOCOL_T1,
+        [0x3b; 32],
+        3,
+        CharacterSet::Unicode,
+    );
+    get_device_type_id_return: GetDeviceTypeIdReturn::new(ReturnCode::Success, 0x0000_0001);
+    read_cache_return: ReadCacheReturn::new(ReturnCode::Success, vec![0xAB; 5]);
+    get_reader_icon_return: GetReaderIconReturn::new(ReturnCode::Success, vec![0xAB; 5]);
+    get_attrib_return: GetAttribReturn::new(ReturnCode::Success, vec![0x3b, 0x8f, 0x80]).unwrap();
+    get_attrib_return_length_only: GetAttribReturn::new_length_only(ReturnCode::Success, 36);
+}
+
+#[test]
+fn reconnect_call_decoding() {
+    let message = rpce_message(&[
+        &HANDLE_PTRS,
+        &[
+            0x02, 0x00, 0x00, 0x00, // dwShareMode (SCARD_SHARE_SHARED)
+            0x03, 0x00, 0x00, 0x00, // dwPreferredProtocols (T0 | T1)
+            0x01, 0x00, 0x00, 0x00, // dwInitialization (SCARD_RESET_CARD)
+        ],
+        &HANDLE_VALUES,
+    ]);
+
+    let ScardCall::ReconnectCall(call) = decode_call(ScardIoCtlCode::Reconnect, &message) else {
+        panic!("unexpected call");
+    };
+
+    assert_eq!(call.handle, handle());
+    assert_eq!(call.share_mode, 2);
+    assert_eq!(call.preferred_protocols, CardProtocol::SCARD_PROTOCOL_TX);
+    assert_eq!(call.initialization, 1);
+}
+
+#[test]
+fn reconnect_return_encoding() {
+    let encoded = encode_vec(&ReconnectReturn::new(
+        ReturnCode::Success,
+        CardProtocol::SCARD_PROTOCOL_T1,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        encoded[RPCE_HEADERS_SIZE..],
+        [
+            0x00, 0x00, 0x00, 0x00, // ReturnCode
+            0x02, 0x00, 0x00, 0x00, // dwActiveProtocol
+        ]
+    );
+}
+
+#[test]
+fn get_attrib_call_decoding() {
+    let message = rpce_message(&[
+        &HANDLE_PTRS,
+        &[
+            0x03, 0x03, 0x09, 0x00, // dwAttrId (SCARD_ATTR_ATR_STRING)
+            0x00, 0x00, 0x00, 0x00, // fpbAttrIsNULL
+            0x24, 0x00, 0x00, 0x00, // cbAttrLen
+        ],
+        &HANDLE_VALUES,
+    ]);
+
+    let ScardCall::GetAttribCall(call) = decode_call(ScardIoCtlCode::GetAttrib, &message) else {
+        panic!("unexpected call");
+    };
+
+    assert_eq!(call.handle, handle());
+    assert_eq!(call.attr_id, 0x0009_0303);
+    assert!(!call.attr_is_null);
+    assert_eq!(call.attr_length, 36);
+}
+
+#[test]
+fn get_attrib_return_encoding() {
+    let encoded = encode_vec(&GetAttribReturn::new(ReturnCode::Success, vec![0x3b, 0x8f, 0x80]).unwrap()).unwrap();
+
+    assert_eq!(
+        encoded[RPCE_HEADERS_SIZE..],
+        [
+            0x00, 0x00, 0x00, 0x00, // ReturnCode
+            0x03, 0x00, 0x00, 0x00, // cbAttrLen
+            0x00, 0x00, 0x02, 0x00, // pbAttr (pointer)
+            0x03, 0x00, 0x00, 0x00, // pbAttr (conformant array size)
+            0x3b, 0x8f, 0x80, // pbAttr
+            0x00, 0x00, 0x00, 0x00, 0x00, // Padding
+        ]
+    );
+
+    let encoded = encode_vec(&GetAttribReturn::new_length_only(ReturnCode::Success, 36)).unwrap();
+
+    assert_eq!(
+        encoded[RPCE_HEADERS_SIZE..],
+        [
+            0x00, 0x00, 0x00, 0x00, // ReturnCode
+            0x24, 0x00, 0x00, 0x00, // cbAttrLen
+            0x00, 0x00, 0x00, 0x00, // pbAttr (null pointer)
+            0x00, 0x00, 0x00, 0x00, // Padding
+        ]
+    );
+}
+
+#[test]
+fn set_attrib_call_decoding() {
+    let message = rpce_message(&[
+        &HANDLE_PTRS,
+        &[
+            0x03, 0x01, 0x09, 0x00, // dwAttrId (SCARD_ATTR_DEVICE_FRIENDLY_NAME_A)
+            0x04, 0x00, 0x00, 0x00, // cbAttrLen
+            0x08, 0x00, 0x02, 0x00, // pbAttr (pointer)
+        ],
+        &HANDLE_VALUES,
+        &[
+            0x04, 0x00, 0x00, 0x00, // pbAttr (conformant array size)
+            0x50, 0x49, 0x56, 0x00, // pbAttr
+        ],
+    ]);
+
+    let ScardCall::SetAttribCall(call) = decode_call(ScardIoCtlCode::SetAttrib, &message) else {
+        panic!("unexpected call");
+    };
+
+    assert_eq!(call.handle, handle());
+    assert_eq!(call.attr_id, 0x0009_0103);
+    assert_eq!(call.attr, b"PIV\0");
+}
+
+#[test]
+fn set_attrib_call_length_mismatch() {
+    let message = rpce_message(&[
+        &HANDLE_PTRS,
+        &[
+            0x03, 0x01, 0x09, 0x00, // dwAttrId
+            0x04, 0x00, 0x00, 0x00, // cbAttrLen
+            0x08, 0x00, 0x02, 0x00, // pbAttr (pointer)
+        ],
+        &HANDLE_VALUES,
+        &[
+            0x08, 0x00, 0x00, 0x00, // pbAttr (conformant array size)
+            0x50, 0x49, 0x56, 0x00, 0x00, 0x00, 0x00, 0x00, // pbAttr
+        ],
+    ]);
+
+    let mut src = ReadCursor::new(&message);
+    ScardCall::decode(ScardIoCtlCode::SetAttrib, &mut src).unwrap_err();
+}
+
+#[test]
+fn transaction_calls_decoding() {
+    let message = rpce_message(&[
+        &HANDLE_PTRS,
+        &[
+            0x00, 0x00, 0x00, 0x00, // dwDisposition (SCARD_LEAVE_CARD)
+        ],
+        &HANDLE_VALUES,
+    ]);
+
+    for io_ctl_code in [ScardIoCtlCode::BeginTransaction, ScardIoCtlCode::EndTransaction] {
+        let ScardCall::HCardAndDispositionCall(call) = decode_call(io_ctl_code, &message) else {
+            panic!("unexpected call");
+        };
+
+        assert_eq!(call.handle, handle());
+        assert_eq!(call.disposition, 0);
+    }
+}
+
+/// GetStatusChangeW_Call for a single reader named "R1"
+fn get_status_change_call(timeout: u32) -> Vec<u8> {
+    rpce_message(&[
+        &[
+            0x04, 0x00, 0x00, 0x00, // Context.cbContext
+            0x00, 0x00, 0x02, 0x00, // Context.pbContext (pointer)
+        ],
+        &timeout.to_le_bytes(), // dwTimeOut
+        &[
+            0x01, 0x00, 0x00, 0x00, // cReaders
+            0x04, 0x00, 0x02, 0x00, // rgReaderStates (pointer)
+            0x04, 0x00, 0x00, 0x00, // Context.cbContext
+            0x11, 0x00, 0x00, 0x00, // Context.pbContext
+            0x01, 0x00, 0x00, 0x00, // rgReaderStates (conformant array size)
+            0x08, 0x00, 0x02, 0x00, // rgReaderStates[0].szReader (pointer)
+        ],
+        &[0; 48], // rgReaderStates[0].Common
+        &[
+            0x03, 0x00, 0x00, 0x00, // szReader (maximum count)
+            0x00, 0x00, 0x00, 0x00, // szReader (offset)
+            0x03, 0x00, 0x00, 0x00, // szReader (actual count)
+            0x52, 0x00, 0x31, 0x00, 0x00, 0x00, // szReader
+            0x00, 0x00, // Padding
+        ],
+    ])
+}
+
+#[test]
+fn get_status_change_timeout() {
+    let decode = |timeout| {
+        let message = get_status_change_call(timeout);
+        let mut src = ReadCursor::new(&message);
+        let call = GetStatusChangeCall::decode(&mut src, Some(CharacterSet::Unicode)).unwrap();
+        assert!(src.is_empty());
+        call
+    };
+
+    let call = decode(GetStatusChangeCall::INFINITE_TIMEOUT);
+    assert_eq!(call.context, ScardContext::new(0x11));
+    assert_eq!(call.states.len(), 1);
+    assert_eq!(call.states[0].reader, "R1");
+    assert!(call.timeout_duration().is_none());
+
+    let call = decode(2000);
+    assert_eq!(call.timeout_duration(), Some(core::time::Duration::from_secs(2)));
+}
+
+#[test]
+fn transmit_return_recv_pci_length_mismatch() {
+    // The `extra_bytes_length` field does not determine how many bytes are written.
+    let recv_pci = SCardIORequest {
+        protocol: CardProtocol::SCARD_PROTOCOL_T1,
+        extra_bytes_length: 8,
+        extra_bytes: vec![0xAA, 0xBB],
+    };
+
+    let encoded = encode_vec(&TransmitReturn::new(
+        ReturnCode::Success,
+        Some(recv_pci),
+        vec![0x90, 0x00],
+    ))
+    .unwrap();
+
+    assert_eq!(
+        encoded[RPCE_HEADERS_SIZE..],
+        [
+            0x00, 0x00, 0x00, 0x00, // ReturnCode
+            0x02, 0x00, 0x00, 0x00, // pioRecvPci.dwProtocol
+            0x02, 0x00, 0x00, 0x00, // pioRecvPci.cbExtraBytes
+            0x00, 0x00, 0x02, 0x00, // pioRecvPci.pbExtraBytes (pointer)
+            0xAA, 0xBB, // pioRecvPci.pbExtraBytes
+            0x02, 0x00, 0x00, 0x00, // cbRecvLength
+            0x04, 0x00, 0x02, 0x00, // pbRecvBuffer (pointer)
+            0x02, 0x00, 0x00, 0x00, // pbRecvBuffer (conformant array size)
+            0x90, 0x00, // pbRecvBuffer
+        ]
+    );
+}
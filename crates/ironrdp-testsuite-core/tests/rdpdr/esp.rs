@@ -0,0 +1,250 @@
+use ironrdp_core::{decode, decode_cursor, encode_vec, ReadCursor};
+use ironrdp_rdpdr::pdu::efs::{DeviceControlResponse, DeviceIoResponse, NtStatus};
+use ironrdp_rdpdr::pdu::esp::{
+    Parity, PortControlCall, PortIoCtlCode, PortIoRequest, SerialBaudRate, SerialLineControl, SerialTimeouts,
+    SerialWaitMask, StopBits,
+};
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::{NoopRdpdrBackend, ParallelPortConfig, Rdpdr, SerialPortConfig};
+use ironrdp_svc::SvcProcessor as _;
+use ironrdp_testsuite_core::channel::channel_payloads;
+use ironrdp_testsuite_core::{encoded_size_test, round_trip_test};
+
+/// DR_CONTROL_REQ header targeting the port with ID 2, without the I/O control code and the input buffer
+const CONTROL_REQUEST_HEADER: [u8; 24] = [
+    0x72, 0x44, // RDPDR_CTYP_CORE
+    0x52, 0x49, // PAKID_CORE_DEVICE_IOREQUEST
+    0x02, 0x00, 0x00, 0x00, // DeviceId
+    0x01, 0x00, 0x00, 0x00, // FileId
+    0x09, 0x00, 0x00, 0x00, // CompletionId
+    0x0e, 0x00, 0x00, 0x00, // MajorFunction (IRP_MJ_DEVICE_CONTROL)
+    0x00, 0x00, 0x00, 0x00, // MinorFunction
+];
+
+/// IOCTL_SERIAL_SET_BAUD_RATE input: 9600 bauds
+const SET_BAUD_RATE_INPUT: [u8; 4] = [0x80, 0x25, 0x00, 0x00];
+
+/// IOCTL_SERIAL_SET_LINE_CONTROL input: 1 stop bit, even parity, 7 data bits
+const SET_LINE_CONTROL_INPUT: [u8; 3] = [0x00, 0x02, 0x07];
+
+/// IOCTL_SERIAL_SET_TIMEOUTS input
+const SET_TIMEOUTS_INPUT: [u8; 20] = [
+    0xff, 0xff, 0xff, 0xff, // ReadIntervalTimeout (MAXDWORD)
+    0x00, 0x00, 0x00, 0x00, // ReadTotalTimeoutMultiplier
+    0x00, 0x00, 0x00, 0x00, // ReadTotalTimeoutConstant
+    0x0a, 0x00, 0x00, 0x00, // WriteTotalTimeoutMultiplier
+    0xe8, 0x03, 0x00, 0x00, // WriteTotalTimeoutConstant
+];
+
+/// IOCTL_SERIAL_SET_WAIT_MASK input: SERIAL_EV_RXCHAR, SERIAL_EV_CTS and SERIAL_EV_DSR
+const SET_WAIT_MASK_INPUT: [u8; 4] = [0x19, 0x00, 0x00, 0x00];
+
+/// DR_CONTROL_RSP to IOCTL_SERIAL_GET_BAUD_RATE: 115200 bauds
+const GET_BAUD_RATE_RESPONSE: [u8; 24] = [
+    0x72, 0x44, // RDPDR_CTYP_CORE
+    0x43, 0x49, // PAKID_CORE_DEVICE_IOCOMPLETION
+    0x02, 0x00, 0x00, 0x00, // DeviceId
+    0x09, 0x00, 0x00, 0x00, // CompletionId
+    0x00, 0x00, 0x00, 0x00, // IoStatus (STATUS_SUCCESS)
+    0x04, 0x00, 0x00, 0x00, // OutputBufferLength
+    0x00, 0xc2, 0x01, 0x00, // OutputBuffer (BaudRate)
+];
+
+fn control_request(io_control_code: PortIoCtlCode, input_buffer: &[u8]) -> Vec<u8> {
+    [
+        CONTROL_REQUEST_HEADER.as_slice(),
+        &[0; 4], // OutputBufferLength
+        &u32::try_from(input_buffer.len()).unwrap().to_le_bytes(),
+        &u32::from(io_control_code).to_le_bytes(),
+        &[0; 20], // Padding
+        input_buffer,
+    ]
+    .concat()
+}
+
+fn decode_port_request(pdu: &[u8]) -> PortIoRequest {
+    let mut src = ReadCursor::new(pdu);
+    let RdpdrPdu::DeviceIoRequest(req) = decode_cursor::<RdpdrPdu>(&mut src).unwrap() else {
+        panic!("device I/O request expected");
+    };
+    let req = PortIoRequest::decode(req, &mut src).unwrap();
+    assert!(src.is_empty());
+    req
+}
+
+fn decode_control_call(io_control_code: PortIoCtlCode, input_buffer: &[u8]) -> PortControlCall {
+    let PortIoRequest::DeviceControlRequest(req, call) =
+        decode_port_request(&control_request(io_control_code, input_buffer))
+    else {
+        panic!("device control request expected");
+    };
+    assert_eq!(req.io_control_code, io_control_code);
+    call
+}
+
+#[test]
+fn set_baud_rate_decoding() {
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_SET_BAUD_RATE, &SET_BAUD_RATE_INPUT),
+        PortControlCall::SetBaudRate(SerialBaudRate { baud_rate: 9600 })
+    );
+}
+
+#[test]
+fn set_line_control_decoding() {
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_SET_LINE_CONTROL, &SET_LINE_CONTROL_INPUT),
+        PortControlCall::SetLineControl(SerialLineControl {
+            stop_bits: StopBits::STOP_BIT_1,
+            parity: Parity::EVEN_PARITY,
+            word_length: 7,
+        })
+    );
+}
+
+#[test]
+fn set_timeouts_decoding() {
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_SET_TIMEOUTS, &SET_TIMEOUTS_INPUT),
+        PortControlCall::SetTimeouts(SerialTimeouts {
+            read_interval_timeout: u32::MAX,
+            read_total_timeout_multiplier: 0,
+            read_total_timeout_constant: 0,
+            write_total_timeout_multiplier: 10,
+            write_total_timeout_constant: 1000,
+        })
+    );
+}
+
+#[test]
+fn set_wait_mask_decoding() {
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_SET_WAIT_MASK, &SET_WAIT_MASK_INPUT),
+        PortControlCall::SetWaitMask(
+            SerialWaitMask::SERIAL_EV_RXCHAR | SerialWaitMask::SERIAL_EV_CTS | SerialWaitMask::SERIAL_EV_DSR
+        )
+    );
+}
+
+#[test]
+fn untyped_control_input_is_kept() {
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_GET_BAUD_RATE, &[]),
+        PortControlCall::Other(Vec::new())
+    );
+    assert_eq!(
+        decode_control_call(PortIoCtlCode::IOCTL_SERIAL_IMMEDIATE_CHAR, &[0x13]),
+        PortControlCall::Other(vec![0x13])
+    );
+}
+
+#[test]
+fn truncated_control_input() {
+    let mut pdu = control_request(PortIoCtlCode::IOCTL_SERIAL_SET_TIMEOUTS, &SET_TIMEOUTS_INPUT);
+    pdu.truncate(pdu.len() - 1);
+
+    let mut src = ReadCursor::new(&pdu);
+    let RdpdrPdu::DeviceIoRequest(req) = decode_cursor::<RdpdrPdu>(&mut src).unwrap() else {
+        panic!("device I/O request expected");
+    };
+    PortIoRequest::decode(req, &mut src).unwrap_err();
+
+    // The input buffer is too short for the structure.
+    let pdu = control_request(
+        PortIoCtlCode::IOCTL_SERIAL_SET_LINE_CONTROL,
+        &SET_LINE_CONTROL_INPUT[..2],
+    );
+    let mut src = ReadCursor::new(&pdu);
+    let RdpdrPdu::DeviceIoRequest(req) = decode_cursor::<RdpdrPdu>(&mut src).unwrap() else {
+        panic!("device I/O request expected");
+    };
+    PortIoRequest::decode(req, &mut src).unwrap_err();
+}
+
+#[test]
+fn get_baud_rate_response_encoding() {
+    let response = RdpdrPdu::DeviceControlResponse(DeviceControlResponse {
+        device_io_reply: DeviceIoResponse {
+            device_id: 2,
+            completion_id: 9,
+            io_status: NtStatus::SUCCESS,
+        },
+        output_buffer: Some(Box::new(SerialBaudRate { baud_rate: 115_200 })),
+    });
+
+    assert_eq!(encode_vec(&response).unwrap(), GET_BAUD_RATE_RESPONSE);
+}
+
+round_trip_test! {
+    serial_baud_rate: SerialBaudRate, SET_BAUD_RATE_INPUT;
+    serial_line_control: SerialLineControl, SET_LINE_CONTROL_INPUT;
+    serial_timeouts: SerialTimeouts, SET_TIMEOUTS_INPUT;
+    serial_wait_mask: SerialWaitMask, SET_WAIT_MASK_INPUT;
+}
+
+encoded_size_test! {
+    serial_line_control: decode::<SerialLineControl>(&SET_LINE_CONTROL_INPUT).unwrap();
+    serial_timeouts: decode::<SerialTimeouts>(&SET_TIMEOUTS_INPUT).unwrap();
+}
+
+#[test]
+fn ports_are_announced() {
+    let mut rdpdr = Rdpdr::new(Box::new(NoopRdpdrBackend), "client".to_owned())
+        .with_serial_ports(vec![SerialPortConfig {
+            device_id: 2,
+            name: "COM1".to_owned(),
+        }])
+        .with_parallel_ports(vec![ParallelPortConfig {
+            device_id: 3,
+            name: "LPT1".to_owned(),
+        }]);
+
+    let client_id_confirm = [
+        0x72, 0x44, // RDPDR_CTYP_CORE
+        0x43, 0x43, // PAKID_CORE_CLIENTID_CONFIRM
+        0x01, 0x00, // VersionMajor
+        0x0c, 0x00, // VersionMinor
+        0x07, 0x00, 0x00, 0x00, // ClientId
+    ];
+    let messages = rdpdr.process(&client_id_confirm).unwrap();
+    let announce = channel_payloads(messages);
+
+    #[rustfmt::skip]
+    let expected = [
+        0x72, 0x44, // RDPDR_CTYP_CORE
+        0x41, 0x44, // PAKID_CORE_DEVICELIST_ANNOUNCE
+        0x02, 0x00, 0x00, 0x00, // DeviceCount
+        // RDPDR_DTYP_SERIAL, DeviceId, PreferredDosName, DeviceDataLength.
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, b'C', b'O', b'M', b'1', 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // RDPDR_DTYP_PARALLEL, DeviceId, PreferredDosName, DeviceDataLength.
+        0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, b'L', b'P', b'T', b'1', 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(announce, [expected]);
+}
+
+#[test]
+fn noop_backend_does_not_support_ports() {
+    let mut rdpdr =
+        Rdpdr::new(Box::new(NoopRdpdrBackend), "client".to_owned()).with_serial_ports(vec![SerialPortConfig {
+            device_id: 2,
+            name: "COM1".to_owned(),
+        }]);
+
+    let messages = rdpdr
+        .process(&control_request(
+            PortIoCtlCode::IOCTL_SERIAL_SET_BAUD_RATE,
+            &SET_BAUD_RATE_INPUT,
+        ))
+        .unwrap();
+    let response = channel_payloads(messages);
+
+    let expected = RdpdrPdu::DeviceControlResponse(DeviceControlResponse {
+        device_io_reply: DeviceIoResponse {
+            device_id: 2,
+            completion_id: 9,
+            io_status: NtStatus::NOT_SUPPORTED,
+        },
+        output_buffer: None,
+    });
+    assert_eq!(response, [encode_vec(&expected).unwrap()]);
+}
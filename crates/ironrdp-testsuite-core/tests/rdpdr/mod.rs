@@ -0,0 +1,363 @@
+mod esc;
+mod esp;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+mod native;
+mod policy;
+
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_rdpdr::pdu::efs::{
+    Boolean, CapabilityMessage, ClientDeviceListAnnounce, ClientDriveDeviceListRemove,
+    ClientDriveQueryDirectoryResponse, ClientDriveQueryInformationResponse, ClientDriveQueryVolumeInformationResponse,
+    ClientNameRequest, ClientNameRequestUnicodeFlag, CoreCapability, DeviceAnnounceHeader, DeviceCloseResponse,
+    DeviceControlResponse, DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse, DeviceReadResponse,
+    DeviceWriteResponse, FileAllocationInformation, FileAttributes, FileBasicInformation, FileBothDirectoryInformation,
+    FileDispositionInformation, FileEndOfFileInformation, FileFsAttributeInformation, FileFsVolumeInformation,
+    FileFullDirectoryInformation, FileNamesInformation, FileRenameInformation, FileStandardInformation,
+    FileSystemAttributes, Information, MajorFunction, MinorFunction, NtStatus, ServerDeviceAnnounceResponse,
+    VersionAndIdPdu, VersionAndIdPduKind,
+};
+use ironrdp_rdpdr::pdu::esc::{LongReturn, ReturnCode};
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::{DrivePolicy, NoopRdpdrBackend, Rdpdr};
+use ironrdp_svc::SvcProcessor;
+use ironrdp_testsuite_core::{encoded_size_test, round_trip_test};
+
+const DEVICE_LIST_REMOVE: [u8; 16] = [
+    0x72, 0x44, // RDPDR_CTYP_CORE
+    0x4d, 0x44, // PAKID_CORE_DEVICELIST_REMOVE
+    0x02, 0x00, 0x00, 0x00, // DeviceCount
+    0x01, 0x00, 0x00, 0x00, // DeviceIds[0]
+    0x03, 0x00, 0x00, 0x00, // DeviceIds[1]
+];
+
+/// DR_CLOSE_REQ targeting the device with ID 1
+const CLOSE_REQUEST: [u8; 24] = [
+    0x72, 0x44, // RDPDR_CTYP_CORE
+    0x52, 0x49, // PAKID_CORE_DEVICE_IOREQUEST
+    0x01, 0x00, 0x00, 0x00, // DeviceId
+    0x05, 0x00, 0x00, 0x00, // FileId
+    0x07, 0x00, 0x00, 0x00, // CompletionId
+    0x02, 0x00, 0x00, 0x00, // MajorFunction (IRP_MJ_CLOSE)
+    0x00, 0x00, 0x00, 0x00, // MinorFunction
+];
+
+/// DR_CORE_SERVER_ANNOUNCE_REQ
+const SERVER_ANNOUNCE: [u8; 12] = [
+    0x72, 0x44, // RDPDR_CTYP_CORE
+    0x6e, 0x49, // PAKID_CORE_SERVER_ANNOUNCE
+    0x01, 0x00, // VersionMajor
+    0x0c, 0x00, // VersionMinor
+    0x02, 0x00, 0x00, 0x00, // ClientId
+];
+
+/// DR_CORE_CAPABILITY_REQ with a GENERAL_CAPS_SET advertising the given `extendedPDU` flags
+fn server_core_capability(extended_pdu: u32) -> Vec<u8> {
+    let mut pdu = vec![
+        0x72, 0x44, // RDPDR_CTYP_CORE
+        0x50, 0x53, // PAKID_CORE_SERVER_CAPABILITY
+        0x01, 0x00, // numCapabilities
+        0x00, 0x00, // Padding
+        0x01, 0x00, // CapabilityType (CAP_GENERAL_TYPE)
+        0x2c, 0x00, // CapabilityLength
+        0x02, 0x00, 0x00, 0x00, // Version (GENERAL_CAPABILITY_VERSION_02)
+        0x02, 0x00, 0x00, 0x00, // osType
+        0x00, 0x00, 0x00, 0x00, // osVersion
+        0x01, 0x00, // protocolMajorVersion
+        0x0c, 0x00, // protocolMinorVersion
+        0xff, 0xff, 0x00, 0x00, // ioCode1
+        0x00, 0x00, 0x00, 0x00, // ioCode2
+    ];
+    pdu.extend_from_slice(&extended_pdu.to_le_bytes()); // extendedPDU
+    pdu.extend_from_slice(&[
+        0x00, 0x00, 0x00, 0x00, // extraFlags1
+        0x00, 0x00, 0x00, 0x00, // extraFlags2
+        0x00, 0x00, 0x00, 0x00, // SpecialTypeDeviceCap
+    ]);
+    pdu
+}
+
+/// Value of the `extendedPDU` field allowing the Client Drive Device List Remove PDU
+const RDPDR_DEVICE_REMOVE_PDUS: u32 = 0x0000_0001;
+
+fn io_response() -> DeviceIoResponse {
+    DeviceIoResponse {
+        device_id: 1,
+        completion_id: 7,
+        io_status: NtStatus::SUCCESS,
+    }
+}
+
+fn query_information_response(buffer: impl Into<ironrdp_rdpdr::pdu::efs::FileInformationClass>) -> RdpdrPdu {
+    RdpdrPdu::ClientDriveQueryInformationResponse(ClientDriveQueryInformationResponse {
+        device_io_response: io_response(),
+        buffer: Some(buffer.into()),
+    })
+}
+
+encoded_size_test! {
+    client_announce_reply: RdpdrPdu::VersionAndIdPdu(VersionAndIdPdu {
+        version_major: 1,
+        version_minor: 12,
+        client_id: 2,
+        kind: VersionAndIdPduKind::ClientAnnounceReply,
+    });
+    client_name_ascii: RdpdrPdu::ClientNameRequest(ClientNameRequest::new(
+        "client".to_owned(),
+        ClientNameRequestUnicodeFlag::Ascii,
+    ));
+    client_name_unicode: RdpdrPdu::ClientNameRequest(ClientNameRequest::new(
+        "clïent".to_owned(),
+        ClientNameRequestUnicodeFlag::Unicode,
+    ));
+    client_core_capability: RdpdrPdu::CoreCapability(CoreCapability::new_response(vec![
+        CapabilityMessage::new_general(1),
+        CapabilityMessage::new_smartcard(),
+        CapabilityMessage::new_drive(),
+    ]));
+    client_device_list_announce: RdpdrPdu::ClientDeviceListAnnounce(ClientDeviceListAnnounce {
+        device_list: vec![DeviceAnnounceHeader::new_smartcard(1)],
+    });
+    device_list_remove: RdpdrPdu::ClientDriveDeviceListRemove(ClientDriveDeviceListRemove::new(vec![1, 3]));
+    server_device_announce_response: RdpdrPdu::ServerDeviceAnnounceResponse(ServerDeviceAnnounceResponse {
+        device_id: 1,
+        result_code: NtStatus::SUCCESS,
+    });
+    device_io_request: RdpdrPdu::DeviceIoRequest(DeviceIoRequest {
+        device_id: 1,
+        file_id: 5,
+        completion_id: 7,
+        major_function: MajorFunction::Close,
+        minor_function: MinorFunction::from(0),
+    });
+    device_control_response: RdpdrPdu::DeviceControlResponse(DeviceControlResponse {
+        device_io_reply: io_response(),
+        output_buffer: Some(Box::new(LongReturn::new(ReturnCode::Success))),
+    });
+    empty_device_control_response: RdpdrPdu::DeviceControlResponse(DeviceControlResponse {
+        device_io_reply: io_response(),
+        output_buffer: None,
+    });
+    device_create_response: RdpdrPdu::DeviceCreateResponse(DeviceCreateResponse {
+        device_io_reply: io_response(),
+        file_id: 5,
+        information: Information::FILE_OPENED,
+    });
+    query_basic_information_response: query_information_response(FileBasicInformation {
+        creation_time: 1,
+        last_access_time: 2,
+        last_write_time: 3,
+        change_time: 4,
+        file_attributes: FileAttributes::FILE_ATTRIBUTE_READONLY,
+    });
+    query_standard_information_response: query_information_response(FileStandardInformation {
+        allocation_size: 4096,
+        end_of_file: 12,
+        number_of_links: 1,
+        delete_pending: Boolean::False,
+        directory: Boolean::True,
+    });
+    query_end_of_file_information_response: query_information_response(FileEndOfFileInformation { end_of_file: 12 });
+    query_disposition_information_response: query_information_response(FileDispositionInformation { delete_pending: 1 });
+    query_rename_information_response: query_information_response(FileRenameInformation {
+        replace_if_exists: Boolean::True,
+        file_name: "\\dïr\\file.txt".to_owned(),
+    });
+    query_allocation_information_response: query_information_response(FileAllocationInformation {
+        allocation_size: 4096,
+    });
+    failed_query_information_response: RdpdrPdu::ClientDriveQueryInformationResponse(
+        ClientDriveQueryInformationResponse {
+            device_io_response: io_response(),
+            buffer: None,
+        }
+    );
+    device_close_response: RdpdrPdu::DeviceCloseResponse(DeviceCloseResponse {
+        device_io_response: io_response(),
+    });
+    query_both_directory_response: RdpdrPdu::ClientDriveQueryDirectoryResponse(ClientDriveQueryDirectoryResponse {
+        device_io_reply: io_response(),
+        buffer: Some(
+            FileBothDirectoryInformation::new(1, 2, 3, 4, 12, FileAttributes::FILE_ATTRIBUTE_ARCHIVE, "fïle.txt".to_owned())
+                .into()
+        ),
+    });
+    query_full_directory_response: RdpdrPdu::ClientDriveQueryDirectoryResponse(ClientDriveQueryDirectoryResponse {
+        device_io_reply: io_response(),
+        buffer: Some(
+            FileFullDirectoryInformation::new(1, 2, 3, 4, 12, FileAttributes::FILE_ATTRIBUTE_ARCHIVE, "fïle.txt".to_owned())
+                .into()
+        ),
+    });
+    query_names_directory_response: RdpdrPdu::ClientDriveQueryDirectoryResponse(ClientDriveQueryDirectoryResponse {
+        device_io_reply: io_response(),
+        buffer: Some(FileNamesInformation::new("fïle.txt".to_owned()).into()),
+    });
+    no_more_files_directory_response: RdpdrPdu::ClientDriveQueryDirectoryResponse(ClientDriveQueryDirectoryResponse {
+        device_io_reply: DeviceIoResponse {
+            io_status: NtStatus::NO_MORE_FILES,
+            ..io_response()
+        },
+        buffer: None,
+    });
+    query_volume_information_response: RdpdrPdu::ClientDriveQueryVolumeInformationResponse(
+        ClientDriveQueryVolumeInformationResponse {
+            device_io_reply: io_response(),
+            buffer: Some(
+                FileFsVolumeInformation {
+                    volume_creation_time: 1,
+                    volume_serial_number: 2,
+                    supports_objects: Boolean::False,
+                    volume_label: "vølume".to_owned(),
+                }
+                .into()
+            ),
+        }
+    );
+    query_volume_attribute_information_response: RdpdrPdu::ClientDriveQueryVolumeInformationResponse(
+        ClientDriveQueryVolumeInformationResponse {
+            device_io_reply: io_response(),
+            buffer: Some(
+                FileFsAttributeInformation {
+                    file_system_attributes: FileSystemAttributes::FILE_UNICODE_ON_DISK,
+                    max_component_name_len: 260,
+                    file_system_name: "FAT32".to_owned(),
+                }
+                .into()
+            ),
+        }
+    );
+    device_read_response: RdpdrPdu::DeviceReadResponse(DeviceReadResponse {
+        device_io_reply: io_response(),
+        read_data: vec![0xAB; 13],
+    });
+    device_write_response: RdpdrPdu::DeviceWriteResponse(DeviceWriteResponse {
+        device_io_reply: io_response(),
+        length: 13,
+    });
+    empty_response: RdpdrPdu::EmptyResponse;
+}
+
+round_trip_test! {
+    server_announce: RdpdrPdu, SERVER_ANNOUNCE;
+    device_list_remove: RdpdrPdu, DEVICE_LIST_REMOVE;
+    close_request: RdpdrPdu, CLOSE_REQUEST;
+}
+
+#[test]
+fn query_end_of_file_information_response_encoding() {
+    let pdu = query_information_response(FileEndOfFileInformation { end_of_file: 12 });
+
+    assert_eq!(
+        encode_vec(&pdu).unwrap(),
+        [
+            0x72, 0x44, // RDPDR_CTYP_CORE
+            0x43, 0x49, // PAKID_CORE_DEVICE_IOCOMPLETION
+            0x01, 0x00, 0x00, 0x00, // DeviceId
+            0x07, 0x00, 0x00, 0x00, // CompletionId
+            0x00, 0x00, 0x00, 0x00, // IoStatus
+            0x08, 0x00, 0x00, 0x00, // Length
+            0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // EndOfFile
+        ]
+    );
+}
+
+#[test]
+fn query_rename_information_response_encoding() {
+    let pdu = query_information_response(FileRenameInformation {
+        replace_if_exists: Boolean::True,
+        file_name: "a".to_owned(),
+    });
+
+    assert_eq!(
+        encode_vec(&pdu).unwrap(),
+        [
+            0x72, 0x44, // RDPDR_CTYP_CORE
+            0x43, 0x49, // PAKID_CORE_DEVICE_IOCOMPLETION
+            0x01, 0x00, 0x00, 0x00, // DeviceId
+            0x07, 0x00, 0x00, 0x00, // CompletionId
+            0x00, 0x00, 0x00, 0x00, // IoStatus
+            0x0a, 0x00, 0x00, 0x00, // Length
+            0x01, // ReplaceIfExists
+            0x00, // RootDirectory
+            0x04, 0x00, 0x00, 0x00, // FileNameLength
+            0x61, 0x00, 0x00, 0x00, // FileName
+        ]
+    );
+}
+
+#[test]
+fn client_name_unicode_encoding() {
+    let pdu = RdpdrPdu::ClientNameRequest(ClientNameRequest::new(
+        "clïent😀".to_owned(),
+        ClientNameRequestUnicodeFlag::Unicode,
+    ));
+
+    assert_eq!(
+        encode_vec(&pdu).unwrap(),
+        [
+            0x72, 0x44, // RDPDR_CTYP_CORE
+            0x4e, 0x43, // PAKID_CORE_CLIENT_NAME
+            0x01, 0x00, 0x00, 0x00, // UnicodeFlag
+            0x00, 0x00, 0x00, 0x00, // CodePage
+            0x12, 0x00, 0x00, 0x00, // ComputerNameLen
+            0x63, 0x00, 0x6c, 0x00, 0xef, 0x00, 0x65, 0x00, 0x6e, 0x00, 0x74, 0x00, // "clïent"
+            0x3d, 0xd8, 0x00, 0xde, // U+1F600, as a surrogate pair
+            0x00, 0x00, // null terminator
+        ]
+    );
+}
+
+#[test]
+fn device_list_remove_encoding() {
+    let pdu = RdpdrPdu::ClientDriveDeviceListRemove(ClientDriveDeviceListRemove::new(vec![1, 3]));
+
+    assert_eq!(encode_vec(&pdu).unwrap(), DEVICE_LIST_REMOVE);
+}
+
+#[test]
+fn device_list_remove_decoding() {
+    let RdpdrPdu::ClientDriveDeviceListRemove(pdu) = decode::<RdpdrPdu>(&DEVICE_LIST_REMOVE).unwrap() else {
+        panic!("unexpected PDU");
+    };
+
+    assert_eq!(pdu.device_ids, [1, 3]);
+}
+
+#[test]
+fn device_list_remove_truncated() {
+    decode::<RdpdrPdu>(&DEVICE_LIST_REMOVE[..12]).unwrap_err();
+}
+
+#[test]
+fn removed_device_io_requests_are_rejected() {
+    let mut rdpdr = Rdpdr::new(Box::new(NoopRdpdrBackend), "client".to_owned()).with_drives(None);
+
+    rdpdr
+        .process(&server_core_capability(RDPDR_DEVICE_REMOVE_PDUS))
+        .unwrap();
+
+    let announce = rdpdr.add_drive(1, "share".to_owned(), DrivePolicy::default());
+    assert_eq!(announce.device_list.len(), 1);
+    rdpdr.process(&CLOSE_REQUEST).unwrap();
+
+    let remove = rdpdr.remove_device(1).unwrap();
+    assert_eq!(remove.device_ids, [1]);
+    assert!(rdpdr.process(&CLOSE_REQUEST).is_err());
+
+    // The device is already removed.
+    assert!(rdpdr.remove_device(1).is_none());
+}
+
+#[test]
+fn device_remove_requires_server_capability() {
+    let mut rdpdr = Rdpdr::new(Box::new(NoopRdpdrBackend), "client".to_owned()).with_drives(None);
+
+    // RDPDR_CLIENT_DISPLAY_NAME_PDU only.
+    rdpdr.process(&server_core_capability(0x0000_0002)).unwrap();
+    rdpdr.add_drive(1, "share".to_owned(), DrivePolicy::default());
+
+    assert!(rdpdr.remove_device(1).is_none());
+
+    // The device is still announced, and its I/O requests are handled.
+    rdpdr.process(&CLOSE_REQUEST).unwrap();
+}
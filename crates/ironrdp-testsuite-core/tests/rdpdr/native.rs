@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use ironrdp_rdpdr::pdu::efs::{
+    CreateDisposition, CreateOptions, DesiredAccess, DeviceCreateRequest, DeviceIoRequest, DeviceIoResponse,
+    DeviceReadRequest, DeviceReadResponse, FileAttributes, MajorFunction, MinorFunction, NtStatus,
+    ServerDriveIoRequest, SharedAccess,
+};
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::RdpdrBackend as _;
+use ironrdp_rdpdr_native::backend::NixRdpdrBackend;
+use ironrdp_testsuite_core::channel::channel_payloads;
+
+/// Local directory removed once the test is done.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str, file_contents: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("ironrdp-rdpdr-native-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("file.txt"), file_contents).unwrap();
+        Self(path)
+    }
+
+    fn root(&self) -> String {
+        self.0.to_str().unwrap().to_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn io_request(device_id: u32, file_id: u32, major_function: MajorFunction) -> DeviceIoRequest {
+    DeviceIoRequest {
+        device_id,
+        file_id,
+        completion_id: 1,
+        major_function,
+        minor_function: MinorFunction::from(0),
+    }
+}
+
+fn open_file(backend: &mut NixRdpdrBackend, device_id: u32) {
+    backend
+        .handle_drive_io_request(ServerDriveIoRequest::ServerCreateDriveRequest(DeviceCreateRequest {
+            device_io_request: io_request(device_id, 0, MajorFunction::Create),
+            desired_access: DesiredAccess::GENERIC_READ,
+            allocation_size: 0,
+            file_attributes: FileAttributes::empty(),
+            shared_access: SharedAccess::empty(),
+            create_disposition: CreateDisposition::FILE_OPEN,
+            create_options: CreateOptions::FILE_NON_DIRECTORY_FILE,
+            path: "\\file.txt".to_owned(),
+        }))
+        .unwrap();
+}
+
+fn read_file(backend: &mut NixRdpdrBackend, device_id: u32, file_id: u32) -> Vec<u8> {
+    let messages = backend
+        .handle_drive_io_request(ServerDriveIoRequest::DeviceReadRequest(DeviceReadRequest {
+            device_io_request: io_request(device_id, file_id, MajorFunction::Read),
+            length: 64,
+            offset: 0,
+        }))
+        .unwrap();
+
+    channel_payloads(messages).concat()
+}
+
+fn read_response(device_id: u32, read_data: &[u8]) -> Vec<u8> {
+    ironrdp_core::encode_vec(&RdpdrPdu::DeviceReadResponse(DeviceReadResponse {
+        device_io_reply: DeviceIoResponse::new(io_request(device_id, 0, MajorFunction::Read), NtStatus::SUCCESS),
+        read_data: read_data.to_vec(),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn drives_are_backed_by_their_own_directory() {
+    let first = TempDir::new("first", b"first drive");
+    let second = TempDir::new("second", b"second drive");
+
+    let mut backend = NixRdpdrBackend::new(String::new());
+    backend.add_drive(1, first.root());
+    backend.add_drive(2, format!("{}/", second.root()));
+
+    // The files are given increasing IDs, starting from 0.
+    open_file(&mut backend, 1);
+    open_file(&mut backend, 2);
+
+    assert_eq!(read_file(&mut backend, 1, 0), read_response(1, b"first drive"));
+    assert_eq!(read_file(&mut backend, 2, 1), read_response(2, b"second drive"));
+}
@@ -0,0 +1,253 @@
+use ironrdp_core::{encode_vec, impl_as_any};
+use ironrdp_pdu::PduResult;
+use ironrdp_rdpdr::pdu::efs::{
+    DeviceControlRequest, DeviceCreateResponse, DeviceIoRequest, DeviceIoResponse, DeviceWriteResponse, Information,
+    MajorFunction, MinorFunction, NtStatus, ServerDeviceAnnounceResponse, ServerDriveIoRequest,
+};
+use ironrdp_rdpdr::pdu::esc::{ScardCall, ScardIoCtlCode};
+use ironrdp_rdpdr::pdu::esp::PortIoRequest;
+use ironrdp_rdpdr::pdu::RdpdrPdu;
+use ironrdp_rdpdr::policy::normalize_path;
+use ironrdp_rdpdr::{DrivePolicy, Rdpdr, RdpdrBackend};
+use ironrdp_svc::{SvcMessage, SvcProcessor as _};
+use ironrdp_testsuite_core::channel::channel_payloads;
+
+use super::{server_core_capability, RDPDR_DEVICE_REMOVE_PDUS};
+
+const DEVICE_ID: u32 = 1;
+
+const GENERIC_READ: u32 = 0x8000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
+const FILE_OPEN: u32 = 0x0000_0001;
+const FILE_OVERWRITE_IF: u32 = 0x0000_0005;
+
+const READ_ONLY: DrivePolicy = DrivePolicy {
+    read_only: true,
+    follow_symlinks: true,
+};
+
+#[derive(Debug, Default)]
+struct RecordingBackend {
+    policies: Vec<(u32, DrivePolicy)>,
+    drive_requests: Vec<ServerDriveIoRequest>,
+}
+
+impl_as_any!(RecordingBackend);
+
+impl RdpdrBackend for RecordingBackend {
+    fn handle_server_device_announce_response(&mut self, _pdu: ServerDeviceAnnounceResponse) -> PduResult<()> {
+        Ok(())
+    }
+
+    fn handle_scard_call(&mut self, _req: DeviceControlRequest<ScardIoCtlCode>, _call: ScardCall) -> PduResult<()> {
+        Ok(())
+    }
+
+    fn handle_drive_io_request(&mut self, req: ServerDriveIoRequest) -> PduResult<Vec<SvcMessage>> {
+        self.drive_requests.push(req);
+        Ok(Vec::new())
+    }
+
+    fn handle_port_request(&mut self, _req: PortIoRequest) -> PduResult<Vec<SvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn set_drive_policy(&mut self, device_id: u32, policy: DrivePolicy) {
+        self.policies.push((device_id, policy));
+    }
+}
+
+fn rdpdr(policy: DrivePolicy) -> Rdpdr {
+    Rdpdr::new(Box::<RecordingBackend>::default(), "client".to_owned()).with_drives(Some(vec![(
+        DEVICE_ID,
+        "share".to_owned(),
+        policy,
+    )]))
+}
+
+fn drive_requests(rdpdr: &Rdpdr) -> &[ServerDriveIoRequest] {
+    &rdpdr.downcast_backend::<RecordingBackend>().unwrap().drive_requests
+}
+
+/// DR_DEVICE_IOREQUEST header followed by `body`
+fn io_request(major_function: u32, body: &[u8]) -> Vec<u8> {
+    let mut request = vec![0x72, 0x44, 0x52, 0x49]; // RDPDR_CTYP_CORE, PAKID_CORE_DEVICE_IOREQUEST
+    request.extend_from_slice(&DEVICE_ID.to_le_bytes());
+    request.extend_from_slice(&5u32.to_le_bytes()); // FileId
+    request.extend_from_slice(&7u32.to_le_bytes()); // CompletionId
+    request.extend_from_slice(&major_function.to_le_bytes());
+    request.extend_from_slice(&0u32.to_le_bytes()); // MinorFunction
+    request.extend_from_slice(body);
+    request
+}
+
+/// DR_CREATE_REQ with a null-terminated UTF-16 path
+fn create_request(path: &str, desired_access: u32, create_disposition: u32) -> Vec<u8> {
+    let path: Vec<u8> = path
+        .encode_utf16()
+        .chain([0])
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&desired_access.to_le_bytes());
+    body.extend_from_slice(&0u64.to_le_bytes()); // AllocationSize
+    body.extend_from_slice(&0u32.to_le_bytes()); // FileAttributes
+    body.extend_from_slice(&0u32.to_le_bytes()); // SharedAccess
+    body.extend_from_slice(&create_disposition.to_le_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes()); // CreateOptions
+    body.extend_from_slice(&u32::try_from(path.len()).unwrap().to_le_bytes());
+    body.extend_from_slice(&path);
+
+    io_request(0x00 /* IRP_MJ_CREATE */, &body)
+}
+
+/// DR_WRITE_REQ writing `data` at offset 0
+fn write_request(data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+    body.extend_from_slice(&0u64.to_le_bytes()); // Offset
+    body.extend_from_slice(&[0; 20]); // Padding
+    body.extend_from_slice(data);
+
+    io_request(0x04 /* IRP_MJ_WRITE */, &body)
+}
+
+fn denied_io_response(major_function: MajorFunction) -> DeviceIoResponse {
+    DeviceIoResponse::new(
+        DeviceIoRequest {
+            device_id: DEVICE_ID,
+            file_id: 5,
+            completion_id: 7,
+            major_function,
+            minor_function: MinorFunction::from(0),
+        },
+        NtStatus::ACCESS_DENIED,
+    )
+}
+
+fn denied_create_response() -> Vec<u8> {
+    encode_vec(&RdpdrPdu::DeviceCreateResponse(DeviceCreateResponse {
+        device_io_reply: denied_io_response(MajorFunction::Create),
+        file_id: 0,
+        information: Information::empty(),
+    }))
+    .unwrap()
+}
+
+/// Returns the encoded PDUs, without the Channel PDU Header.
+#[test]
+fn paths_are_normalized() {
+    assert_eq!(normalize_path("").unwrap(), "");
+    assert_eq!(normalize_path("\\").unwrap(), "\\");
+    assert_eq!(normalize_path("\\dir/sub\\\\file.txt").unwrap(), "\\dir\\sub\\file.txt");
+    assert_eq!(normalize_path("dir\\.\\file").unwrap(), "\\dir\\file");
+    assert_eq!(normalize_path("\\dir.\\file. . ").unwrap(), "\\dir\\file");
+    assert_eq!(normalize_path("\\dir\\*").unwrap(), "\\dir\\*");
+    assert_eq!(normalize_path("\\a..b\\.hidden").unwrap(), "\\a..b\\.hidden");
+}
+
+#[test]
+fn traversal_paths_are_rejected() {
+    for path in [
+        "\\..",
+        "..\\secret",
+        "\\dir/../../secret",
+        "\\dir\\.. \\secret",
+        "\\...\\secret",
+        "\\. .\\secret",
+        "\\C:\\secret",
+        "\\file.txt:stream",
+        "\\file\0.txt",
+    ] {
+        assert!(normalize_path(path).is_none(), "{path:?}");
+    }
+}
+
+#[test]
+fn traversal_attempts_are_denied() {
+    let mut rdpdr = rdpdr(DrivePolicy::default());
+
+    for path in ["\\..\\..\\etc\\passwd", "\\dir/../..", "\\dir\\...\\secret"] {
+        let messages = rdpdr.process(&create_request(path, GENERIC_READ, FILE_OPEN)).unwrap();
+
+        assert_eq!(channel_payloads(messages), [denied_create_response()], "{path:?}");
+    }
+
+    assert!(drive_requests(&rdpdr).is_empty());
+}
+
+#[test]
+fn legitimate_nested_paths_pass_through() {
+    let mut rdpdr = rdpdr(DrivePolicy::default());
+
+    let messages = rdpdr
+        .process(&create_request(
+            "\\dir/sub\\file.txt.",
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_OVERWRITE_IF,
+        ))
+        .unwrap();
+    assert!(messages.is_empty());
+    rdpdr.process(&write_request(b"data")).unwrap();
+
+    let [ServerDriveIoRequest::ServerCreateDriveRequest(create), ServerDriveIoRequest::DeviceWriteRequest(write)] =
+        drive_requests(&rdpdr)
+    else {
+        panic!("unexpected requests: {:?}", drive_requests(&rdpdr));
+    };
+    assert_eq!(create.path, "\\dir\\sub\\file.txt");
+    assert_eq!(write.write_data, b"data");
+}
+
+#[test]
+fn read_only_drive_denies_writes() {
+    let mut rdpdr = rdpdr(READ_ONLY);
+
+    for (desired_access, create_disposition) in [(GENERIC_WRITE, FILE_OPEN), (GENERIC_READ, FILE_OVERWRITE_IF)] {
+        let messages = rdpdr
+            .process(&create_request("\\file.txt", desired_access, create_disposition))
+            .unwrap();
+        assert_eq!(channel_payloads(messages), [denied_create_response()]);
+    }
+
+    let messages = rdpdr.process(&write_request(b"data")).unwrap();
+    let denied_write = encode_vec(&RdpdrPdu::DeviceWriteResponse(DeviceWriteResponse {
+        device_io_reply: denied_io_response(MajorFunction::Write),
+        length: 0,
+    }))
+    .unwrap();
+    assert_eq!(channel_payloads(messages), [denied_write]);
+
+    assert!(drive_requests(&rdpdr).is_empty());
+
+    // Reading is still allowed.
+    let messages = rdpdr
+        .process(&create_request("\\dir\\file.txt", GENERIC_READ, FILE_OPEN))
+        .unwrap();
+    assert!(messages.is_empty());
+    assert_eq!(drive_requests(&rdpdr).len(), 1);
+}
+
+#[test]
+fn drive_policies_are_forwarded_to_the_backend() {
+    let mut rdpdr = rdpdr(READ_ONLY);
+    let no_symlinks = DrivePolicy {
+        read_only: false,
+        follow_symlinks: false,
+    };
+    rdpdr.add_drive(2, "other".to_owned(), no_symlinks);
+
+    assert_eq!(rdpdr.drive_policy(DEVICE_ID), Some(READ_ONLY));
+    assert_eq!(rdpdr.drive_policy(2), Some(no_symlinks));
+    assert_eq!(
+        rdpdr.downcast_backend::<RecordingBackend>().unwrap().policies,
+        [(DEVICE_ID, READ_ONLY), (2, no_symlinks)]
+    );
+
+    rdpdr
+        .process(&server_core_capability(RDPDR_DEVICE_REMOVE_PDUS))
+        .unwrap();
+    rdpdr.remove_device(2).unwrap();
+    assert_eq!(rdpdr.drive_policy(2), None);
+}
@@ -0,0 +1,365 @@
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::DvcProcessor as _;
+use ironrdp_rdpei::client::{PenContact, RdpeiClient, TouchContact};
+use ironrdp_rdpei::contact::{ContactPhase, ContactState, ContactTracker};
+use ironrdp_rdpei::pdu::{
+    ContactFlags, CsReadyFlags, CsReadyPdu, ProtocolVersion, RdpeiPdu, ScReadyFeatures, ScReadyPdu,
+};
+use ironrdp_testsuite_core::channel::decode_dvc_client_pdus;
+
+const CHANNEL_ID: u32 = 7;
+
+/// Phase changes of a single contact, with the flags expected to be reported and the resulting state.
+const TRANSITIONS: &[(ContactState, ContactPhase, &[ContactFlags], ContactState)] = &[
+    (
+        ContactState::OutOfRange,
+        ContactPhase::Down,
+        &[ContactFlags::ENGAGE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::OutOfRange,
+        ContactPhase::Update,
+        &[ContactFlags::ENGAGE, ContactFlags::MOVE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::OutOfRange,
+        ContactPhase::Hover,
+        &[ContactFlags::HOVER],
+        ContactState::Hovering,
+    ),
+    (
+        ContactState::OutOfRange,
+        ContactPhase::Up,
+        &[],
+        ContactState::OutOfRange,
+    ),
+    (
+        ContactState::OutOfRange,
+        ContactPhase::Cancel,
+        &[],
+        ContactState::OutOfRange,
+    ),
+    (
+        ContactState::Hovering,
+        ContactPhase::Down,
+        &[ContactFlags::ENGAGE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::Hovering,
+        ContactPhase::Update,
+        &[ContactFlags::ENGAGE, ContactFlags::MOVE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::Hovering,
+        ContactPhase::Hover,
+        &[ContactFlags::HOVER],
+        ContactState::Hovering,
+    ),
+    (
+        ContactState::Hovering,
+        ContactPhase::Up,
+        &[ContactFlags::LEAVE],
+        ContactState::OutOfRange,
+    ),
+    (
+        ContactState::Hovering,
+        ContactPhase::Cancel,
+        &[ContactFlags::CANCEL_HOVERING],
+        ContactState::OutOfRange,
+    ),
+    (
+        ContactState::Engaged,
+        ContactPhase::Down,
+        &[ContactFlags::MOVE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::Engaged,
+        ContactPhase::Update,
+        &[ContactFlags::MOVE],
+        ContactState::Engaged,
+    ),
+    (
+        ContactState::Engaged,
+        ContactPhase::Hover,
+        &[ContactFlags::LIFT],
+        ContactState::Hovering,
+    ),
+    (
+        ContactState::Engaged,
+        ContactPhase::Up,
+        &[ContactFlags::RELEASE],
+        ContactState::OutOfRange,
+    ),
+    (
+        ContactState::Engaged,
+        ContactPhase::Cancel,
+        &[ContactFlags::CANCEL_ENGAGED],
+        ContactState::OutOfRange,
+    ),
+];
+
+/// Brings a contact to `state` from out of range.
+fn enter_state(tracker: &mut ContactTracker, id: u32, state: ContactState) {
+    match state {
+        ContactState::OutOfRange => {}
+        ContactState::Hovering => {
+            tracker.transition(id, ContactPhase::Hover).unwrap();
+        }
+        ContactState::Engaged => {
+            tracker.transition(id, ContactPhase::Down).unwrap();
+        }
+    }
+    assert_eq!(tracker.state(id), state);
+}
+
+#[test]
+fn contact_state_transitions() {
+    for &(state, phase, expected_flags, expected_state) in TRANSITIONS {
+        let mut tracker = ContactTracker::new(1);
+        enter_state(&mut tracker, 42, state);
+
+        let flags = tracker
+            .transition(42, phase)
+            .map_or(&[][..], |transition| transition.flags);
+
+        assert_eq!(flags, expected_flags, "{state:?} -> {phase:?}");
+        assert_eq!(tracker.state(42), expected_state, "{state:?} -> {phase:?}");
+        assert!(flags.iter().all(|flags| flags.is_valid()), "{state:?} -> {phase:?}");
+    }
+}
+
+#[test]
+fn contact_ids_are_reused() {
+    let mut tracker = ContactTracker::new(4);
+
+    assert_eq!(tracker.transition(100, ContactPhase::Down).unwrap().contact_id, 0);
+    assert_eq!(tracker.transition(200, ContactPhase::Down).unwrap().contact_id, 1);
+    assert_eq!(tracker.transition(100, ContactPhase::Up).unwrap().contact_id, 0);
+    assert_eq!(tracker.len(), 1);
+
+    assert_eq!(tracker.transition(300, ContactPhase::Down).unwrap().contact_id, 0);
+    assert_eq!(tracker.transition(200, ContactPhase::Update).unwrap().contact_id, 1);
+}
+
+#[test]
+fn contacts_beyond_maximum_are_ignored() {
+    let mut tracker = ContactTracker::new(1);
+
+    tracker.transition(1, ContactPhase::Down).unwrap();
+    assert!(tracker.transition(2, ContactPhase::Down).is_none());
+    assert_eq!(tracker.state(2), ContactState::OutOfRange);
+
+    tracker.transition(1, ContactPhase::Up).unwrap();
+    assert!(tracker.is_empty());
+    tracker.transition(2, ContactPhase::Down).unwrap();
+}
+
+fn ready_client(protocol_version: ProtocolVersion) -> RdpeiClient {
+    let mut client = RdpeiClient::new(5);
+
+    let sc_ready = RdpeiPdu::ScReady(ScReadyPdu {
+        protocol_version,
+        supported_features: Some(ScReadyFeatures::MULTIPEN_INJECTION_SUPPORTED),
+    });
+    let responses = client.process(CHANNEL_ID, &encode_vec(&sc_ready).unwrap()).unwrap();
+
+    let [response] = responses.as_slice() else {
+        panic!("expected a single response");
+    };
+    assert_eq!(
+        decode::<RdpeiPdu>(&encode_vec(response.as_ref()).unwrap()).unwrap(),
+        RdpeiPdu::CsReady(CsReadyPdu {
+            flags: CsReadyFlags::SHOW_TOUCH_VISUALS,
+            protocol_version: protocol_version.min(RdpeiClient::PROTOCOL_VERSION),
+            max_touch_contacts: 5,
+        })
+    );
+    assert!(client.ready());
+
+    client
+}
+
+fn touch(id: u32, phase: ContactPhase) -> TouchContact {
+    TouchContact {
+        id,
+        x: 10,
+        y: 20,
+        phase,
+    }
+}
+
+fn touch_frames(pdus: &[RdpeiPdu]) -> Vec<(u64, Vec<(u8, ContactFlags)>)> {
+    pdus.iter()
+        .flat_map(|pdu| {
+            let RdpeiPdu::Touch(touch) = pdu else {
+                panic!("unexpected PDU: {pdu:?}");
+            };
+            touch.frames.clone()
+        })
+        .map(|frame| {
+            let contacts = frame
+                .contacts
+                .iter()
+                .map(|contact| (contact.contact_id, contact.contact_flags))
+                .collect();
+            (frame.frame_offset, contacts)
+        })
+        .collect()
+}
+
+#[test]
+fn client_drops_input_until_ready() {
+    let mut client = RdpeiClient::default();
+
+    assert!(!client.ready());
+    assert!(client
+        .encode_touch_contacts(CHANNEL_ID, &[touch(1, ContactPhase::Down)], 0)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn client_drops_input_while_suspended() {
+    let mut client = ready_client(ProtocolVersion::V200);
+
+    let suspend = encode_vec(&RdpeiPdu::SuspendInput).unwrap();
+    assert!(client.process(CHANNEL_ID, &suspend).unwrap().is_empty());
+    assert!(!client.ready());
+    assert!(client
+        .encode_touch_contacts(CHANNEL_ID, &[touch(1, ContactPhase::Down)], 0)
+        .unwrap()
+        .is_empty());
+
+    let resume = encode_vec(&RdpeiPdu::ResumeInput).unwrap();
+    assert!(client.process(CHANNEL_ID, &resume).unwrap().is_empty());
+    assert!(client.ready());
+    assert_eq!(
+        client
+            .encode_touch_contacts(CHANNEL_ID, &[touch(1, ContactPhase::Down)], 0)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn client_batches_touch_frames() {
+    let mut client = ready_client(ProtocolVersion::V200);
+
+    let messages = client
+        .encode_touch_contacts(
+            CHANNEL_ID,
+            &[
+                touch(1, ContactPhase::Down),
+                // A down is synthesized in the first frame, along with the down of the other contact.
+                touch(2, ContactPhase::Update),
+                // A contact is reported at most once per frame.
+                touch(1, ContactPhase::Update),
+                touch(1, ContactPhase::Up),
+            ],
+            1_000,
+        )
+        .unwrap();
+
+    assert_eq!(
+        touch_frames(&decode_dvc_client_pdus::<RdpeiPdu>(messages)),
+        [
+            (0, vec![(0, ContactFlags::ENGAGE), (1, ContactFlags::ENGAGE)]),
+            (0, vec![(1, ContactFlags::MOVE), (0, ContactFlags::MOVE)]),
+            (0, vec![(0, ContactFlags::RELEASE)]),
+        ]
+    );
+
+    // Frame offsets are relative to the previous frame.
+    let messages = client
+        .encode_touch_contacts(CHANNEL_ID, &[touch(2, ContactPhase::Up)], 17_000)
+        .unwrap();
+
+    assert_eq!(
+        touch_frames(&decode_dvc_client_pdus::<RdpeiPdu>(messages)),
+        [(16_000, vec![(1, ContactFlags::RELEASE)])]
+    );
+
+    // Phase changes not reportable produce no frame.
+    assert!(client
+        .encode_touch_contacts(CHANNEL_ID, &[touch(2, ContactPhase::Up)], 18_000)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn client_encodes_pen_frames() {
+    let mut client = ready_client(ProtocolVersion::V300);
+    assert!(client.pen_supported());
+
+    let pen = |pressure| PenContact {
+        x: 10,
+        y: 20,
+        pressure,
+        tilt_x: -120,
+        tilt_y: 45,
+    };
+
+    let mut pdus = decode_dvc_client_pdus::<RdpeiPdu>(client.encode_pen(CHANNEL_ID, &pen(0), 0).unwrap());
+    pdus.extend(decode_dvc_client_pdus::<RdpeiPdu>(
+        client.encode_pen(CHANNEL_ID, &pen(2048), 5).unwrap(),
+    ));
+    pdus.extend(decode_dvc_client_pdus::<RdpeiPdu>(
+        client.encode_pen(CHANNEL_ID, &pen(512), 6).unwrap(),
+    ));
+
+    let contacts: Vec<_> = pdus
+        .iter()
+        .flat_map(|pdu| {
+            let RdpeiPdu::Pen(pen) = pdu else {
+                panic!("unexpected PDU: {pdu:?}");
+            };
+            pen.frames.clone()
+        })
+        .map(|frame| {
+            let [contact] = frame.contacts.as_slice() else {
+                panic!("expected a single pen contact");
+            };
+            (
+                frame.frame_offset,
+                contact.contact_flags,
+                contact.pressure,
+                contact.tilt_x,
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        contacts,
+        [
+            (0, ContactFlags::HOVER, Some(0), Some(-90)),
+            (5, ContactFlags::ENGAGE, Some(1024), Some(-90)),
+            (1, ContactFlags::MOVE, Some(512), Some(-90)),
+        ]
+    );
+}
+
+#[test]
+fn client_reports_pen_as_touch_without_pen_support() {
+    let mut client = ready_client(ProtocolVersion::V200);
+    assert!(!client.pen_supported());
+
+    let pen = PenContact {
+        x: 10,
+        y: 20,
+        pressure: 100,
+        tilt_x: 0,
+        tilt_y: 0,
+    };
+    let messages = client.encode_pen(CHANNEL_ID, &pen, 0).unwrap();
+
+    assert_eq!(
+        touch_frames(&decode_dvc_client_pdus::<RdpeiPdu>(messages)),
+        [(0, vec![(0, ContactFlags::ENGAGE)])]
+    );
+}
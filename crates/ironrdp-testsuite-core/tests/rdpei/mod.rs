@@ -0,0 +1,212 @@
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_rdpei::pdu::{
+    ContactData, ContactFlags, ContactRect, CsReadyFlags, CsReadyPdu, PenContactData, PenEventPdu, PenFrame,
+    ProtocolVersion, RdpeiPdu, ScReadyFeatures, ScReadyPdu, TouchEventPdu, TouchFrame,
+};
+use ironrdp_testsuite_core::encode_decode_test;
+
+mod client;
+
+encode_decode_test! {
+    sc_ready_v200: RdpeiPdu::ScReady(ScReadyPdu {
+        protocol_version: ProtocolVersion::V200,
+        supported_features: None,
+    }),
+    [
+        // Header
+        0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00,
+        // Payload
+        0x00, 0x00, 0x02, 0x00,
+    ];
+
+    sc_ready_v300: RdpeiPdu::ScReady(ScReadyPdu {
+        protocol_version: ProtocolVersion::V300,
+        supported_features: Some(ScReadyFeatures::MULTIPEN_INJECTION_SUPPORTED),
+    }),
+    [
+        // Header
+        0x01, 0x00,
+        0x0e, 0x00, 0x00, 0x00,
+        // Payload
+        0x00, 0x00, 0x03, 0x00,
+        0x01, 0x00, 0x00, 0x00,
+    ];
+
+    cs_ready: RdpeiPdu::CsReady(CsReadyPdu {
+        flags: CsReadyFlags::SHOW_TOUCH_VISUALS,
+        protocol_version: ProtocolVersion::V300,
+        max_touch_contacts: 10,
+    }),
+    [
+        // Header
+        0x02, 0x00,
+        0x10, 0x00, 0x00, 0x00,
+        // Payload
+        0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x03, 0x00,
+        0x0a, 0x00,
+    ];
+
+    suspend_input: RdpeiPdu::SuspendInput,
+    [
+        0x04, 0x00,
+        0x06, 0x00, 0x00, 0x00,
+    ];
+
+    touch: RdpeiPdu::Touch(TouchEventPdu {
+        encode_time: 0,
+        frames: vec![TouchFrame {
+            frame_offset: 0,
+            contacts: vec![ContactData {
+                contact_id: 0,
+                x: 100,
+                y: -200,
+                contact_flags: ContactFlags::ENGAGE,
+                contact_rect: None,
+                orientation: None,
+                pressure: None,
+            }],
+        }],
+    }),
+    [
+        // Header
+        0x03, 0x00,
+        0x11, 0x00, 0x00, 0x00,
+        // encodeTime, frameCount
+        0x00, 0x01,
+        // contactCount, frameOffset
+        0x01, 0x00,
+        // contactId, fieldsPresent
+        0x00, 0x00,
+        // x, y (two bytes long, the latter being negative)
+        0x40, 0x64,
+        0x60, 0xc8,
+        // contactFlags
+        0x19,
+    ];
+
+    touch_optional_fields: RdpeiPdu::Touch(TouchEventPdu {
+        encode_time: 0,
+        frames: vec![TouchFrame {
+            frame_offset: 1_000_000,
+            contacts: vec![ContactData {
+                contact_id: 1,
+                x: 10,
+                y: 20,
+                contact_flags: ContactFlags::MOVE,
+                contact_rect: Some(ContactRect {
+                    left: -5,
+                    top: -5,
+                    right: 5,
+                    bottom: 5,
+                }),
+                orientation: Some(90),
+                pressure: Some(512),
+            }],
+        }],
+    }),
+    [
+        // Header
+        0x03, 0x00,
+        0x19, 0x00, 0x00, 0x00,
+        // encodeTime, frameCount
+        0x00, 0x01,
+        // contactCount
+        0x01,
+        // frameOffset (three bytes long)
+        0x4f, 0x42, 0x40,
+        // contactId, fieldsPresent
+        0x01, 0x07,
+        // x, y
+        0x0a, 0x14,
+        // contactFlags
+        0x1a,
+        // contactRect
+        0x45, 0x45, 0x05, 0x05,
+        // orientation
+        0x40, 0x5a,
+        // pressure
+        0x42, 0x00,
+    ];
+
+    pen: RdpeiPdu::Pen(PenEventPdu {
+        encode_time: 0,
+        frames: vec![PenFrame {
+            frame_offset: 0,
+            contacts: vec![PenContactData {
+                device_id: 0,
+                x: 10,
+                y: 20,
+                contact_flags: ContactFlags::ENGAGE,
+                pen_flags: None,
+                pressure: Some(1024),
+                rotation: None,
+                tilt_x: Some(-30),
+                tilt_y: Some(45),
+            }],
+        }],
+    }),
+    [
+        // Header
+        0x08, 0x00,
+        0x13, 0x00, 0x00, 0x00,
+        // encodeTime, frameCount
+        0x00, 0x01,
+        // contactCount, frameOffset
+        0x01, 0x00,
+        // deviceId, fieldsPresent
+        0x00, 0x1a,
+        // x, y
+        0x0a, 0x14,
+        // contactFlags
+        0x19,
+        // pressure
+        0x44, 0x00,
+        // tiltX, tiltY
+        0x5e, 0x2d,
+    ];
+}
+
+#[test]
+fn decode_rejects_unknown_event() {
+    let encoded = [0x42, 0x00, 0x06, 0x00, 0x00, 0x00];
+
+    decode::<RdpeiPdu>(&encoded).unwrap_err();
+}
+
+#[test]
+fn decode_rejects_truncated_pdu() {
+    let encoded = [0x02, 0x00, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+
+    decode::<RdpeiPdu>(&encoded).unwrap_err();
+}
+
+#[test]
+fn encode_rejects_out_of_range_values() {
+    let contact = ContactData {
+        contact_id: 0,
+        x: 0x2000_0000,
+        y: 0,
+        contact_flags: ContactFlags::ENGAGE,
+        contact_rect: None,
+        orientation: None,
+        pressure: None,
+    };
+    encode_vec(&contact).unwrap_err();
+
+    let contact = ContactData {
+        x: 0,
+        pressure: Some(ContactData::MAX_PRESSURE + 1),
+        ..contact
+    };
+    encode_vec(&contact).unwrap_err();
+}
+
+#[test]
+fn contact_flags_validity() {
+    assert!(ContactFlags::ENGAGE.is_valid());
+    assert!(ContactFlags::CANCEL_HOVERING.is_valid());
+    assert!(!(ContactFlags::DOWN | ContactFlags::UP).is_valid());
+    assert!(!ContactFlags::IN_CONTACT.is_valid());
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_core::{decode, encode_vec};
+use ironrdp_dvc::DvcProcessor as _;
+use ironrdp_rdpsnd::audio_input::client::{AudioInputBackend, AudioInputClient};
+use ironrdp_rdpsnd::audio_input::pdu::{
+    AudioInputPdu, DataPdu, FormatChangePdu, FormatsPdu, OpenPdu, OpenReplyPdu, Version, VersionPdu,
+};
+use ironrdp_rdpsnd::pdu::{AudioFormat, WaveFormat};
+use ironrdp_testsuite_core::channel::decode_dvc_client_pdus;
+use ironrdp_testsuite_core::encode_decode_test;
+
+const CHANNEL_ID: u32 = 3;
+
+const PCM_44100: AudioFormat = pcm(2, 44100);
+
+const PCM_22050: AudioFormat = pcm(2, 22050);
+
+const fn pcm(n_channels: u16, n_samples_per_sec: u32) -> AudioFormat {
+    AudioFormat {
+        format: WaveFormat::PCM,
+        n_channels,
+        n_samples_per_sec,
+        n_avg_bytes_per_sec: n_samples_per_sec * 2 * n_channels as u32,
+        n_block_align: 2 * n_channels,
+        bits_per_sample: 16,
+        data: None,
+    }
+}
+
+encode_decode_test! {
+    version: AudioInputPdu::Version(VersionPdu { version: Version::V2 }),
+        [0x01, 0x02, 0x00, 0x00, 0x00];
+    formats: AudioInputPdu::Formats(FormatsPdu { formats: vec![PCM_44100] }),
+        [
+            0x02, 0x01, 0x00, 0x00, 0x00, 0x1b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x44, 0xac, 0x00, 0x00, 0x10,
+            0xb1, 0x02, 0x00, 0x04, 0x00, 0x10, 0x00, 0x00, 0x00,
+        ];
+    open: AudioInputPdu::Open(OpenPdu { frames_per_packet: 1024, initial_format: 0, format: PCM_44100 }),
+        [
+            0x03, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x44, 0xac, 0x00, 0x00, 0x10,
+            0xb1, 0x02, 0x00, 0x04, 0x00, 0x10, 0x00, 0x00, 0x00,
+        ];
+    open_reply: AudioInputPdu::OpenReply(OpenReplyPdu { result: OpenReplyPdu::E_FAIL }),
+        [0x04, 0x05, 0x40, 0x00, 0x80];
+    incoming_data: AudioInputPdu::IncomingData,
+        [0x05];
+    data: AudioInputPdu::Data(DataPdu { data: vec![0x01, 0x02, 0x03, 0x04] }),
+        [0x06, 0x01, 0x02, 0x03, 0x04];
+    format_change: AudioInputPdu::FormatChange(FormatChangePdu { new_format: 1 }),
+        [0x07, 0x01, 0x00, 0x00, 0x00];
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    opened: Vec<(AudioFormat, u32)>,
+    closed: usize,
+    frames: VecDeque<Vec<u8>>,
+}
+
+#[derive(Debug, Default)]
+struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl AudioInputBackend for MockBackend {
+    fn formats(&self) -> Vec<AudioFormat> {
+        vec![PCM_44100, PCM_22050]
+    }
+
+    fn open(&mut self, format: &AudioFormat, frames_per_packet: u32) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .opened
+            .push((format.clone(), frames_per_packet));
+        true
+    }
+
+    fn poll_frame(&mut self, _max_len: usize) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().frames.pop_front()
+    }
+
+    fn close(&mut self) {
+        self.state.lock().unwrap().closed += 1;
+    }
+}
+
+fn process(client: &mut AudioInputClient, pdu: AudioInputPdu) -> Vec<AudioInputPdu> {
+    let messages = client.process(CHANNEL_ID, &encode_vec(&pdu).unwrap()).unwrap();
+    messages
+        .into_iter()
+        .map(|message| decode::<AudioInputPdu>(&encode_vec(message.as_ref()).unwrap()).unwrap())
+        .collect()
+}
+
+fn open_client(state: &Arc<Mutex<MockState>>) -> AudioInputClient {
+    let mut client = AudioInputClient::new(Box::new(MockBackend {
+        state: Arc::clone(state),
+    }));
+
+    let responses = process(&mut client, AudioInputPdu::Version(VersionPdu { version: Version(3) }));
+    assert_eq!(responses, [AudioInputPdu::Version(VersionPdu { version: Version::V2 })]);
+
+    let alaw = AudioFormat {
+        format: WaveFormat::ALAW,
+        n_channels: 2,
+        n_samples_per_sec: 22050,
+        n_avg_bytes_per_sec: 44100,
+        n_block_align: 2,
+        bits_per_sample: 8,
+        data: None,
+    };
+    let responses = process(
+        &mut client,
+        AudioInputPdu::Formats(FormatsPdu {
+            formats: vec![alaw, PCM_22050, PCM_44100],
+        }),
+    );
+    // The formats are ordered by preference of the backend.
+    assert_eq!(
+        responses,
+        [AudioInputPdu::Formats(FormatsPdu {
+            formats: vec![PCM_44100, PCM_22050],
+        })]
+    );
+
+    let responses = process(
+        &mut client,
+        AudioInputPdu::Open(OpenPdu {
+            frames_per_packet: 2,
+            initial_format: 0,
+            format: PCM_44100,
+        }),
+    );
+    assert_eq!(
+        responses,
+        [
+            AudioInputPdu::FormatChange(FormatChangePdu { new_format: 0 }),
+            AudioInputPdu::OpenReply(OpenReplyPdu {
+                result: OpenReplyPdu::S_OK
+            }),
+        ]
+    );
+
+    client
+}
+
+#[test]
+fn capture_and_format_change() {
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let mut client = open_client(&state);
+
+    assert_eq!(client.version(), Some(Version::V2));
+    assert_eq!(client.current_format(), Some(&PCM_44100));
+    assert_eq!(state.lock().unwrap().opened, [(PCM_44100, 2)]);
+
+    state.lock().unwrap().frames.extend([vec![0; 8], vec![1; 4]]);
+    let messages = decode_dvc_client_pdus::<AudioInputPdu>(client.capture(CHANNEL_ID).unwrap());
+    assert_eq!(
+        messages,
+        [
+            AudioInputPdu::IncomingData,
+            AudioInputPdu::Data(DataPdu { data: vec![0; 8] }),
+            AudioInputPdu::IncomingData,
+            AudioInputPdu::Data(DataPdu { data: vec![1; 4] }),
+        ]
+    );
+
+    // Nothing is sent when no audio is available.
+    assert!(client.capture(CHANNEL_ID).unwrap().is_empty());
+
+    let responses = process(
+        &mut client,
+        AudioInputPdu::FormatChange(FormatChangePdu { new_format: 1 }),
+    );
+    assert_eq!(
+        responses,
+        [AudioInputPdu::FormatChange(FormatChangePdu { new_format: 1 })]
+    );
+    assert_eq!(client.current_format(), Some(&PCM_22050));
+    assert_eq!(state.lock().unwrap().opened, [(PCM_44100, 2), (PCM_22050, 2)]);
+
+    state.lock().unwrap().frames.push_back(vec![2; 8]);
+    let messages = decode_dvc_client_pdus::<AudioInputPdu>(client.capture(CHANNEL_ID).unwrap());
+    assert_eq!(
+        messages,
+        [
+            AudioInputPdu::IncomingData,
+            AudioInputPdu::Data(DataPdu { data: vec![2; 8] }),
+        ]
+    );
+
+    client.close(CHANNEL_ID);
+    assert_eq!(state.lock().unwrap().closed, 1);
+    assert!(client.current_format().is_none());
+    assert!(client.capture(CHANNEL_ID).unwrap().is_empty());
+}
+
+#[test]
+fn capture_rejects_packets_not_fitting_the_format() {
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let mut client = open_client(&state);
+
+    // Larger than two frames.
+    state.lock().unwrap().frames.push_back(vec![0; 12]);
+    assert!(client.capture(CHANNEL_ID).is_err());
+
+    // Not a whole number of frames.
+    state.lock().unwrap().frames.push_back(vec![0; 6]);
+    assert!(client.capture(CHANNEL_ID).is_err());
+}
+
+#[test]
+fn open_with_invalid_format() {
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let mut client = open_client(&state);
+
+    let open = AudioInputPdu::Open(OpenPdu {
+        frames_per_packet: 2,
+        initial_format: 2,
+        format: PCM_44100,
+    });
+    assert!(client.process(CHANNEL_ID, &encode_vec(&open).unwrap()).is_err());
+
+    let open = AudioInputPdu::Open(OpenPdu {
+        frames_per_packet: 2,
+        initial_format: 1,
+        format: PCM_44100,
+    });
+    assert!(client.process(CHANNEL_ID, &encode_vec(&open).unwrap()).is_err());
+}
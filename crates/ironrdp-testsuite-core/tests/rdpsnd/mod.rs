@@ -1,6 +1,13 @@
+mod audio_input;
+mod playback;
+
 use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
 
+use ironrdp_core::encode_vec;
+use ironrdp_rdpsnd::client::{Rdpsnd, RdpsndClientHandler};
 use ironrdp_rdpsnd::pdu;
+use ironrdp_svc::SvcProcessor;
 use ironrdp_testsuite_core::encode_decode_test;
 
 encode_decode_test! {
@@ -186,4 +193,180 @@ encode_decode_test! {
     [
         0x0D, 0x00, 0x14, 0x00, 0x16, 0xA1, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00, 0xC2, 0xB8, 0xAC, 0x0D, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
     ];
+    client_format_opus: pdu::ClientAudioOutputPdu::AudioFormat(pdu::ClientAudioFormatPdu {
+        version: pdu::Version::V8,
+        flags: pdu::AudioFormatFlags::ALIVE,
+        volume_left: 0xFFFF,
+        volume_right: 0xFFFF,
+        pitch: 0x10000,
+        dgram_port: 0,
+        formats: vec![
+            pdu::AudioFormat::opus(2, 48000),
+            pdu::AudioFormat {
+                format: pdu::WaveFormat::PCM,
+                n_channels: 2,
+                n_samples_per_sec: 44100,
+                n_avg_bytes_per_sec: 176400,
+                n_block_align: 4,
+                bits_per_sample: 16,
+                data: None,
+            },
+        ],
+    }),
+    [
+        0x07, 0x00, 0x38, 0x00, 0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x02, 0x00, 0x00, 0x08, 0x00, 0x00,
+        // Opus
+        0x4f, 0x70, 0x02, 0x00, 0x80, 0xbb, 0x00, 0x00, 0x00, 0xee, 0x02, 0x00, 0x04, 0x00, 0x10, 0x00,
+        0x00, 0x00,
+        // PCM
+        0x01, 0x00, 0x02, 0x00, 0x44, 0xac, 0x00, 0x00, 0x10, 0xb1, 0x02, 0x00, 0x04, 0x00, 0x10, 0x00,
+        0x00, 0x00,
+    ];
+}
+
+type CompressedWaves = Arc<Mutex<Vec<(u16, u32, Vec<u8>)>>>;
+
+#[derive(Debug, Default)]
+struct PassthroughHandler {
+    waves: Arc<Mutex<Vec<(pdu::WaveFormat, u32)>>>,
+    compressed_waves: CompressedWaves,
+}
+
+impl RdpsndClientHandler for PassthroughHandler {
+    fn preferred_formats(&self) -> Vec<pdu::AudioFormat> {
+        vec![pdu::AudioFormat::opus(2, 48000)]
+    }
+
+    fn wave(&mut self, format: &pdu::AudioFormat, ts: u32, _data: Cow<'_, [u8]>) {
+        self.waves.lock().unwrap().push((format.format, ts));
+    }
+
+    fn on_compressed_wave(&mut self, format_idx: u16, ts: u32, data: Cow<'_, [u8]>) {
+        self.compressed_waves
+            .lock()
+            .unwrap()
+            .push((format_idx, ts, data.into_owned()));
+    }
+
+    fn set_volume(&mut self, _volume: pdu::VolumePdu) {}
+
+    fn set_pitch(&mut self, _pitch: pdu::PitchPdu) {}
+
+    fn close(&mut self) {}
+}
+
+#[test]
+fn wave_dispatched_to_passthrough() {
+    let handler = PassthroughHandler::default();
+    let waves = Arc::clone(&handler.waves);
+    let compressed_waves = Arc::clone(&handler.compressed_waves);
+    let mut rdpsnd = Rdpsnd::new(Box::new(handler));
+
+    let server_format = pdu::ServerAudioOutputPdu::AudioFormat(pdu::ServerAudioFormatPdu {
+        version: pdu::Version::V8,
+        formats: vec![
+            pdu::AudioFormat {
+                format: pdu::WaveFormat::PCM,
+                n_channels: 2,
+                n_samples_per_sec: 44100,
+                n_avg_bytes_per_sec: 176400,
+                n_block_align: 4,
+                bits_per_sample: 16,
+                data: None,
+            },
+            pdu::AudioFormat::opus(2, 48000),
+        ],
+    });
+    rdpsnd.process(&encode_vec(&server_format).unwrap()).unwrap();
+
+    // Only the preferred format is kept in the client format list.
+    assert_eq!(rdpsnd.get_format(0).unwrap(), &pdu::AudioFormat::opus(2, 48000));
+    assert!(rdpsnd.get_format(1).is_err());
+
+    let training = pdu::ServerAudioOutputPdu::Training(pdu::TrainingPdu {
+        timestamp: 0x89da,
+        data: vec![],
+    });
+    rdpsnd.process(&encode_vec(&training).unwrap()).unwrap();
+
+    let wave = pdu::ServerAudioOutputPdu::Wave2(pdu::Wave2Pdu {
+        timestamp: 0xa116,
+        audio_timestamp: 0xdacb8c2,
+        format_no: 0,
+        block_no: 2,
+        data: Cow::Borrowed(&[0x1, 0x2, 0x3, 0x4]),
+    });
+    let confirm = rdpsnd.process(&encode_vec(&wave).unwrap()).unwrap();
+
+    assert_eq!(confirm.len(), 1);
+    assert!(waves.lock().unwrap().is_empty());
+    assert_eq!(
+        *compressed_waves.lock().unwrap(),
+        vec![(0, 0xdacb8c2, vec![0x1, 0x2, 0x3, 0x4])]
+    );
+}
+
+#[derive(Debug, Default)]
+struct PcmHandler {
+    waves: Arc<Mutex<Vec<(pdu::WaveFormat, u32)>>>,
+}
+
+impl RdpsndClientHandler for PcmHandler {
+    fn wave(&mut self, format: &pdu::AudioFormat, ts: u32, _data: Cow<'_, [u8]>) {
+        self.waves.lock().unwrap().push((format.format, ts));
+    }
+
+    fn set_volume(&mut self, _volume: pdu::VolumePdu) {}
+
+    fn set_pitch(&mut self, _pitch: pdu::PitchPdu) {}
+
+    fn close(&mut self) {}
+}
+
+#[test]
+fn passthrough_formats_require_opt_in() {
+    let handler = PcmHandler::default();
+    let waves = Arc::clone(&handler.waves);
+    let mut rdpsnd = Rdpsnd::new(Box::new(handler));
+
+    let pcm = pdu::AudioFormat {
+        format: pdu::WaveFormat::PCM,
+        n_channels: 2,
+        n_samples_per_sec: 44100,
+        n_avg_bytes_per_sec: 176400,
+        n_block_align: 4,
+        bits_per_sample: 16,
+        data: None,
+    };
+    let server_format = pdu::ServerAudioOutputPdu::AudioFormat(pdu::ServerAudioFormatPdu {
+        version: pdu::Version::V8,
+        formats: vec![
+            pdu::AudioFormat::opus(2, 48000),
+            pdu::AudioFormat::aac(2, 48000, 24000),
+            pcm.clone(),
+        ],
+    });
+    rdpsnd.process(&encode_vec(&server_format).unwrap()).unwrap();
+
+    // The handler did not list the passthrough formats, so only PCM is announced.
+    assert_eq!(rdpsnd.get_format(0).unwrap(), &pcm);
+    assert!(rdpsnd.get_format(1).is_err());
+
+    let training = pdu::ServerAudioOutputPdu::Training(pdu::TrainingPdu {
+        timestamp: 0x89da,
+        data: vec![],
+    });
+    rdpsnd.process(&encode_vec(&training).unwrap()).unwrap();
+
+    let wave = pdu::ServerAudioOutputPdu::Wave2(pdu::Wave2Pdu {
+        timestamp: 0xa116,
+        audio_timestamp: 0xdacb8c2,
+        format_no: 0,
+        block_no: 2,
+        data: Cow::Borrowed(&[0x1, 0x2, 0x3, 0x4]),
+    });
+    rdpsnd.process(&encode_vec(&wave).unwrap()).unwrap();
+
+    assert_eq!(*waves.lock().unwrap(), vec![(pdu::WaveFormat::PCM, 0xdacb8c2)]);
 }
@@ -0,0 +1,237 @@
+use core::time::Duration;
+
+use ironrdp_rdpsnd::pdu::VolumePdu;
+use ironrdp_rdpsnd::playback::{apply_volume, channel_gains, decode_pcm, JitterBuffer, JitterStats, Resampler};
+
+/// 10 frames per millisecond, to keep the numbers round.
+const SAMPLE_RATE: u32 = 10_000;
+
+/// Mono buffer targeting 100 frames.
+fn jitter_buffer() -> JitterBuffer {
+    JitterBuffer::new(1, SAMPLE_RATE, Duration::from_millis(10))
+}
+
+fn ramp(start: usize, len: usize) -> Vec<f32> {
+    (start..start + len)
+        .map(|i| f32::from(u16::try_from(i).unwrap()))
+        .collect()
+}
+
+fn pull(buffer: &mut JitterBuffer, frames: usize) -> Vec<f32> {
+    let mut out = vec![f32::NAN; frames * buffer.channels()];
+    buffer.pull(&mut out);
+    out
+}
+
+fn assert_samples_eq(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?}");
+    assert!(
+        actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn playback_starts_once_the_target_latency_is_buffered() {
+    let mut buffer = jitter_buffer();
+
+    buffer.push(&ramp(0, 60));
+    assert_eq!(pull(&mut buffer, 10), [0.0; 10]);
+    assert!(!buffer.is_playing());
+    assert_eq!(buffer.latency(), Duration::from_millis(6));
+
+    buffer.push(&ramp(60, 40));
+    assert_eq!(pull(&mut buffer, 10), ramp(0, 10));
+    assert!(buffer.is_playing());
+    assert_eq!(buffer.target_latency(), Duration::from_millis(10));
+    assert_eq!(buffer.latency(), Duration::from_millis(9));
+}
+
+#[test]
+fn underrun_is_filled_with_silence_and_buffers_again() {
+    let mut buffer = jitter_buffer();
+
+    buffer.push(&ramp(0, 100));
+    let out = pull(&mut buffer, 120);
+
+    // The last frame is stretched while the buffer runs empty.
+    let mut expected = ramp(0, 100);
+    expected.push(99.0);
+    assert_eq!(out[..101], expected);
+    assert_eq!(out[101..], [0.0; 19]);
+    assert_eq!(buffer.stats().underruns, 1);
+    assert!(!buffer.is_playing());
+
+    // The playback resumes once the target latency is buffered again.
+    buffer.push(&ramp(100, 50));
+    assert_eq!(pull(&mut buffer, 10), [0.0; 10]);
+    buffer.push(&ramp(150, 50));
+    assert_eq!(pull(&mut buffer, 10), ramp(100, 10));
+}
+
+#[test]
+fn excess_latency_is_reduced_by_dropping_frames() {
+    let mut buffer = jitter_buffer();
+
+    // Still 1.5 times the target when the correction is due, above the tolerance of a quarter of the target.
+    buffer.push(&ramp(0, 250));
+    let out = pull(&mut buffer, JitterBuffer::CORRECTION_PERIOD);
+
+    // The frame preceding the last one is dropped.
+    let mut expected = ramp(0, JitterBuffer::CORRECTION_PERIOD - 1);
+    expected.push(100.0);
+    assert_eq!(out, expected);
+    assert_eq!(
+        buffer.stats(),
+        JitterStats {
+            dropped_frames: 1,
+            ..JitterStats::default()
+        }
+    );
+}
+
+#[test]
+fn insufficient_latency_is_increased_by_stretching_frames() {
+    let mut buffer = jitter_buffer();
+
+    buffer.push(&ramp(0, 100));
+    assert_eq!(pull(&mut buffer, 90), ramp(0, 90));
+
+    // Only a few frames are received, the buffered latency falling below the target minus the tolerance.
+    buffer.push(&ramp(100, 20));
+    let out = pull(&mut buffer, 10);
+
+    // The 100th frame is played twice.
+    let mut expected = ramp(90, 9);
+    expected.push(99.0);
+    assert_eq!(out, expected);
+    assert_eq!(buffer.stats().stretched_frames, 1);
+    assert_eq!(pull(&mut buffer, 2), [99.0, 100.0]);
+}
+
+#[test]
+fn latency_within_the_tolerance_is_left_as_is() {
+    let mut buffer = jitter_buffer();
+
+    buffer.push(&ramp(0, 110));
+    assert_eq!(pull(&mut buffer, 20), ramp(0, 20));
+
+    for start in (110..1000).step_by(10) {
+        buffer.push(&ramp(start, 10));
+        assert_eq!(pull(&mut buffer, 10), ramp(start - 90, 10));
+    }
+
+    assert_eq!(buffer.stats(), JitterStats::default());
+}
+
+#[test]
+fn stalled_playback_is_flushed_down_to_the_target() {
+    let mut buffer = jitter_buffer();
+
+    buffer.push(&ramp(0, 300));
+    assert_eq!(buffer.stats().dropped_frames, 0);
+
+    buffer.push(&ramp(300, 10));
+    assert_eq!(buffer.buffered_frames(), 100);
+    assert_eq!(buffer.stats().dropped_frames, 210);
+    assert_eq!(pull(&mut buffer, 5), ramp(210, 5));
+}
+
+#[test]
+fn multichannel_frames_are_kept_whole() {
+    let mut buffer = JitterBuffer::new(2, SAMPLE_RATE, Duration::from_millis(1));
+
+    // The trailing partial frame is ignored.
+    buffer.push(&ramp(0, 21));
+    assert_eq!(buffer.buffered_frames(), 10);
+
+    let out = pull(&mut buffer, 11);
+    assert_eq!(out[..20], ramp(0, 20));
+    assert_eq!(out[20..], [0.0, 0.0]);
+}
+
+#[test]
+fn same_rate_is_passed_through() {
+    let mut resampler = Resampler::new(44_100, 2, 44_100, 2);
+    let mut out = Vec::new();
+
+    resampler.process(&ramp(0, 8), &mut out);
+    resampler.process(&ramp(8, 8), &mut out);
+
+    assert!(resampler.is_passthrough());
+    assert_eq!(out, ramp(0, 16));
+}
+
+#[test]
+fn channels_are_mapped() {
+    let mut out = Vec::new();
+    Resampler::new(8000, 1, 8000, 2).process(&[0.5, -0.5], &mut out);
+    assert_eq!(out, [0.5, 0.5, -0.5, -0.5]);
+
+    out.clear();
+    Resampler::new(8000, 2, 8000, 1).process(&[0.5, 0.25, -1.0, 0.0], &mut out);
+    assert_eq!(out, [0.375, -0.5]);
+
+    out.clear();
+    Resampler::new(8000, 2, 8000, 4).process(&[0.5, 0.25], &mut out);
+    assert_eq!(out, [0.5, 0.25, 0.0, 0.0]);
+}
+
+#[test]
+fn upsampling_interpolates_across_calls() {
+    let mut resampler = Resampler::new(1000, 1, 2000, 1);
+    let mut out = Vec::new();
+
+    resampler.process(&[0.0, 1.0], &mut out);
+    resampler.process(&[2.0, 3.0], &mut out);
+
+    // The last frame is held back until the next one is received.
+    assert_eq!(out, [0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+}
+
+#[test]
+fn downsampling_keeps_the_rate() {
+    let mut resampler = Resampler::new(48_000, 2, 44_100, 2);
+    let mut out = Vec::new();
+
+    // One second, in chunks of 10 ms.
+    for _ in 0..100 {
+        resampler.process(&[0.0; 960], &mut out);
+    }
+
+    let frames = out.len() / 2;
+    assert!((44_099..=44_100).contains(&frames), "{frames}");
+}
+
+#[test]
+fn pcm_is_decoded() {
+    let mut out = Vec::new();
+
+    assert!(decode_pcm(8, &[0, 128, 192], &mut out));
+    assert_eq!(out, [-1.0, 0.0, 0.5]);
+
+    out.clear();
+    assert!(decode_pcm(16, &[0x00, 0x80, 0x00, 0x00, 0x00, 0x40, 0xFF], &mut out));
+    assert_eq!(out, [-1.0, 0.0, 0.5]);
+
+    out.clear();
+    assert!(!decode_pcm(24, &[0; 6], &mut out));
+    assert!(out.is_empty());
+}
+
+#[test]
+fn volume_is_applied_per_channel() {
+    let gains = channel_gains(&VolumePdu {
+        volume_left: 0xFFFF,
+        volume_right: 0,
+    });
+    assert_samples_eq(&gains, &[1.0, 0.0]);
+
+    let mut stereo = [0.5, 0.5, 0.25, 0.25];
+    apply_volume(&mut stereo, 2, gains);
+    assert_samples_eq(&stereo, &[0.5, 0.0, 0.25, 0.0]);
+
+    let mut mono = [0.5, 1.0];
+    apply_volume(&mut mono, 1, gains);
+    assert_samples_eq(&mono, &[0.25, 0.5]);
+}
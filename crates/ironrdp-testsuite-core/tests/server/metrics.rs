@@ -0,0 +1,120 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_server::{FrameMetrics, LatencyPercentiles};
+
+fn millis(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    // Shuffled to check the samples are sorted.
+    let samples = (1..=100).map(|i| millis(i * 37 % 101));
+
+    assert_eq!(
+        LatencyPercentiles::from_samples(samples),
+        Some(LatencyPercentiles {
+            p50: millis(50),
+            p90: millis(90),
+            p99: millis(99),
+            max: millis(100),
+        })
+    );
+}
+
+#[test]
+fn percentiles_of_few_samples() {
+    assert_eq!(LatencyPercentiles::from_samples([]), None);
+    assert_eq!(
+        LatencyPercentiles::from_samples([millis(7)]),
+        Some(LatencyPercentiles {
+            p50: millis(7),
+            p90: millis(7),
+            p99: millis(7),
+            max: millis(7),
+        })
+    );
+    assert_eq!(
+        LatencyPercentiles::from_samples([millis(3), millis(1)]),
+        Some(LatencyPercentiles {
+            p50: millis(1),
+            p90: millis(3),
+            p99: millis(3),
+            max: millis(3),
+        })
+    );
+}
+
+#[test]
+fn frame_ids_are_monotonic() {
+    let mut metrics = FrameMetrics::new(4);
+
+    let ids: Vec<u32> = (0..3).map(|_| metrics.next_frame_id()).collect();
+
+    assert_eq!(ids, [0, 1, 2]);
+}
+
+#[test]
+fn acknowledgement_is_matched_by_frame_id() {
+    let start = Instant::now();
+    let mut metrics = FrameMetrics::new(4);
+
+    for ms in [0, 10] {
+        let frame_id = metrics.next_frame_id();
+        metrics.record_frame(frame_id, start + millis(ms), start + millis(ms + 2));
+    }
+
+    assert!(metrics.acknowledge(1, start + millis(42)));
+    assert!(metrics.acknowledge(0, start + millis(50)));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.frames_sent, 2);
+    assert_eq!(snapshot.frames_acknowledged, 2);
+    assert_eq!(snapshot.unmatched_acknowledgements, 0);
+    assert_eq!(snapshot.encode.map(|encode| encode.max), Some(millis(2)));
+    assert_eq!(
+        snapshot.round_trip,
+        Some(LatencyPercentiles {
+            p50: millis(30),
+            p90: millis(48),
+            p99: millis(48),
+            max: millis(48),
+        })
+    );
+}
+
+#[test]
+fn unknown_duplicate_and_evicted_acknowledgements_are_unmatched() {
+    let start = Instant::now();
+    let mut metrics = FrameMetrics::new(2);
+
+    for _ in 0..3 {
+        let frame_id = metrics.next_frame_id();
+        metrics.record_frame(frame_id, start, start);
+    }
+
+    // The frame 0 was evicted by the frame 2.
+    assert!(!metrics.acknowledge(0, start));
+    assert!(metrics.acknowledge(2, start));
+    assert!(!metrics.acknowledge(2, start));
+    assert!(!metrics.acknowledge(7, start));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.frames_sent, 3);
+    assert_eq!(snapshot.frames_acknowledged, 1);
+    assert_eq!(snapshot.unmatched_acknowledgements, 3);
+}
+
+#[test]
+fn round_trip_is_none_without_acknowledgement() {
+    let start = Instant::now();
+    let mut metrics = FrameMetrics::new(2);
+
+    let frame_id = metrics.next_frame_id();
+    metrics.record_frame(frame_id, start, start + millis(5));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.encode.map(|encode| encode.p50), Some(millis(5)));
+    assert_eq!(snapshot.round_trip, None);
+}
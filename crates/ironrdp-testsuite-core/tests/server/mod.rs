@@ -0,0 +1,106 @@
+mod metrics;
+
+use core::num::NonZeroU16;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ironrdp_pdu::rdp::capability_sets::{CmdFlags, EntropyBits};
+use ironrdp_server::bench::encoder::FrameEncoder;
+use ironrdp_server::{BitmapUpdate, MappedFrame, PixelFormat, PixelOrder, SharedFrame};
+use rstest::rstest;
+
+const WIDTH: u16 = 68;
+const HEIGHT: u16 = 70;
+/// Rows are padded, as in most capture buffers.
+const STRIDE: usize = WIDTH as usize * 4 + 32;
+
+fn pixels() -> Vec<u8> {
+    (0..STRIDE * usize::from(HEIGHT))
+        .map(|i| u8::try_from(i * 7 % 251).unwrap())
+        .collect()
+}
+
+fn bitmap_update(order: PixelOrder) -> BitmapUpdate {
+    BitmapUpdate {
+        top: 10,
+        left: 20,
+        width: NonZeroU16::new(WIDTH).unwrap(),
+        height: NonZeroU16::new(HEIGHT).unwrap(),
+        format: PixelFormat::BgrX32,
+        order,
+        data: pixels(),
+        stride: STRIDE,
+    }
+}
+
+fn shared_frame(order: PixelOrder, buffer: Arc<dyn MappedFrame>) -> SharedFrame {
+    SharedFrame {
+        top: 10,
+        left: 20,
+        width: NonZeroU16::new(WIDTH).unwrap(),
+        height: NonZeroU16::new(HEIGHT).unwrap(),
+        format: PixelFormat::BgrX32,
+        order,
+        buffer,
+        stride: STRIDE,
+    }
+}
+
+/// Mapped buffer counting its releases.
+struct CountedBuffer {
+    data: Vec<u8>,
+    released: Arc<AtomicUsize>,
+}
+
+impl MappedFrame for CountedBuffer {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for CountedBuffer {
+    fn drop(&mut self) {
+        self.released.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[rstest]
+#[case::bitmap(CmdFlags::empty(), None)]
+#[case::surface_bits(CmdFlags::SET_SURFACE_BITS, None)]
+#[case::remotefx_rlgr1(CmdFlags::SET_SURFACE_BITS, Some((EntropyBits::Rlgr1, 3)))]
+#[case::remotefx_rlgr3(CmdFlags::SET_SURFACE_BITS, Some((EntropyBits::Rlgr3, 3)))]
+fn shared_frame_is_encoded_like_bitmap_update(
+    #[case] surface_flags: CmdFlags,
+    #[case] remotefx: Option<(EntropyBits, u8)>,
+    #[values(PixelOrder::TopToBottom, PixelOrder::BottomToTop)] order: PixelOrder,
+) {
+    let bitmap = bitmap_update(order);
+    let frame = shared_frame(order, Arc::new(pixels()));
+
+    let owned = FrameEncoder::new(surface_flags, remotefx).encode(bitmap.as_frame_ref());
+    let borrowed = FrameEncoder::new(surface_flags, remotefx).encode(frame.as_frame_ref());
+
+    assert!(!owned.is_empty());
+    assert_eq!(owned, borrowed);
+}
+
+#[test]
+fn shared_frame_is_released_with_last_clone() {
+    let released = Arc::new(AtomicUsize::new(0));
+    let frame = shared_frame(
+        PixelOrder::TopToBottom,
+        Arc::new(CountedBuffer {
+            data: pixels(),
+            released: Arc::clone(&released),
+        }),
+    );
+    let other_client = frame.clone();
+
+    FrameEncoder::new(CmdFlags::SET_SURFACE_BITS, None).encode(frame.as_frame_ref());
+    drop(frame);
+    assert_eq!(released.load(Ordering::SeqCst), 0);
+
+    FrameEncoder::new(CmdFlags::SET_SURFACE_BITS, None).encode(other_client.as_frame_ref());
+    drop(other_client);
+    assert_eq!(released.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,137 @@
+use ironrdp_core::WriteBuf;
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::bitmap::{BitmapData, BitmapUpdateData, Compression};
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_session::fast_path::{ProcessorBuilder, ReassemblyLimits};
+use ironrdp_session::image::DecodedImage;
+
+use super::fast_path_frame;
+
+/// Applies a raw 2x2 bitmap to a 2x2 image, the rows of the bitmap being bottom-up.
+fn apply_raw_bitmap(pixel_format: PixelFormat, bits_per_pixel: u16, bitmap_data: &[u8]) -> DecodedImage {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(pixel_format, 2, 2);
+
+    let update = BitmapUpdateData {
+        rectangles: vec![BitmapData {
+            rectangle: InclusiveRectangle {
+                left: 0,
+                top: 0,
+                right: 1,
+                bottom: 1,
+            },
+            width: 2,
+            height: 2,
+            bits_per_pixel,
+            compression_flags: Compression::empty(),
+            compressed_data_header: None,
+            bitmap_data,
+        }],
+    };
+    let frame = fast_path_frame(UpdateCode::Bitmap, &update);
+
+    processor.process(&mut image, &frame, &mut WriteBuf::new()).unwrap();
+
+    image
+}
+
+fn pixels(image: &DecodedImage) -> Vec<[u8; 4]> {
+    image
+        .data()
+        .chunks_exact(4)
+        .map(|pixel| pixel.try_into().unwrap())
+        .collect()
+}
+
+#[test]
+fn raw_15bpp_bitmap_is_converted() {
+    #[rustfmt::skip]
+    let bitmap = [
+        // Bottom row: gray, white.
+        0x10, 0x42, 0xff, 0x7f,
+        // Top row: red, blue.
+        0x00, 0x7c, 0x1f, 0x00,
+    ];
+
+    let image = apply_raw_bitmap(PixelFormat::RgbA32, 15, &bitmap);
+
+    assert_eq!(
+        pixels(&image),
+        [
+            [0xff, 0x00, 0x00, 0xff],
+            [0x00, 0x00, 0xff, 0xff],
+            [0x84, 0x84, 0x84, 0xff],
+            [0xff, 0xff, 0xff, 0xff],
+        ]
+    );
+}
+
+#[test]
+fn raw_16bpp_bitmap_is_converted() {
+    #[rustfmt::skip]
+    let bitmap = [
+        // Bottom row: gray, white.
+        0x10, 0x84, 0xff, 0xff,
+        // Top row: red, green.
+        0x00, 0xf8, 0xe0, 0x07,
+    ];
+
+    let image = apply_raw_bitmap(PixelFormat::RgbA32, 16, &bitmap);
+
+    assert_eq!(
+        pixels(&image),
+        [
+            [0xff, 0x00, 0x00, 0xff],
+            [0x00, 0xff, 0x00, 0xff],
+            [0x84, 0x82, 0x84, 0xff],
+            [0xff, 0xff, 0xff, 0xff],
+        ]
+    );
+}
+
+#[test]
+fn raw_24bpp_bitmap_is_converted_with_padded_rows() {
+    #[rustfmt::skip]
+    let bitmap = [
+        // Bottom row, padded to 4 bytes.
+        0x33, 0x22, 0x11, 0xcc, 0xbb, 0xaa, 0x00, 0x00,
+        // Top row, padded to 4 bytes.
+        0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let image = apply_raw_bitmap(PixelFormat::RgbA32, 24, &bitmap);
+
+    assert_eq!(
+        pixels(&image),
+        [
+            [0xff, 0x00, 0x00, 0xff],
+            [0x00, 0x00, 0xff, 0xff],
+            [0x11, 0x22, 0x33, 0xff],
+            [0xaa, 0xbb, 0xcc, 0xff],
+        ]
+    );
+}
+
+#[test]
+fn bitmap_is_written_in_the_image_pixel_format() {
+    #[rustfmt::skip]
+    let bitmap = [
+        0x33, 0x22, 0x11, 0x33, 0x22, 0x11, 0x00, 0x00,
+        0x33, 0x22, 0x11, 0x33, 0x22, 0x11, 0x00, 0x00,
+    ];
+
+    let image = apply_raw_bitmap(PixelFormat::BgrA32, 24, &bitmap);
+
+    assert_eq!(pixels(&image), [[0x33, 0x22, 0x11, 0xff]; 4]);
+}
@@ -0,0 +1,127 @@
+use ironrdp_core::WriteBuf;
+use ironrdp_graphics::bitmap_cache::PersistentKey;
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::rdp::capability_sets::{BitmapCacheRev2, CacheFlags, CellInfo};
+use ironrdp_session::fast_path::{ProcessorBuilder, ReassemblyLimits, UpdateKind};
+use ironrdp_session::image::DecodedImage;
+
+/// Fast-path orders updates filling the bitmap cache and drawing from it with MemBlt and Mem3Blt orders.
+const ORDERS: &[u8] = include_bytes!("../../test_data/session/bitmap_cache/orders.bin");
+
+fn bitmap_cache_capabilities() -> BitmapCacheRev2 {
+    let cell = |num_entries, is_cache_persistent| CellInfo {
+        num_entries,
+        is_cache_persistent,
+    };
+
+    BitmapCacheRev2 {
+        cache_flags: CacheFlags::PERSISTENT_KEYS_EXPECTED_FLAG,
+        num_cell_caches: 3,
+        cache_cell_info: [
+            cell(4, true),
+            cell(2, false),
+            cell(1, true),
+            cell(0, false),
+            cell(0, false),
+        ],
+    }
+}
+
+fn expected_image() -> Vec<u8> {
+    let png_buffer = std::fs::read(format!(
+        "{}/test_data/session/bitmap_cache/expected.png",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+
+    let mut png_reader = png::Decoder::new(&png_buffer[..]).read_info().unwrap();
+    let mut png_reader_buffer = vec![0u8; png_reader.output_buffer_size()];
+    let frame_size = png_reader.next_frame(&mut png_reader_buffer).unwrap().buffer_size();
+    png_reader_buffer.truncate(frame_size);
+
+    png_reader_buffer
+}
+
+#[test]
+fn replayed_orders_match_reference_image() {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: Some(bitmap_cache_capabilities()),
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 48);
+
+    let mut updated_regions = 0;
+    let mut orders = ORDERS;
+    while !orders.is_empty() {
+        let length = ironrdp_pdu::find_size(orders).unwrap().unwrap().length;
+        let (frame, rest) = orders.split_at(length);
+        orders = rest;
+
+        let updates = processor.process(&mut image, frame, &mut WriteBuf::new()).unwrap();
+        updated_regions += updates
+            .iter()
+            .filter(|update| matches!(update, UpdateKind::Region(_)))
+            .count();
+    }
+
+    // The Mem3Blt order with a hatched brush is skipped.
+    assert_eq!(updated_regions, 7);
+    assert!(
+        image.data() == expected_image(),
+        "rendered image differs from the reference"
+    );
+
+    // The bitmap first stored in the slot 0 of the cache 0 was replaced, and the bitmaps sent to the waiting list
+    // are not cached.
+    assert_eq!(
+        processor.bitmap_cache_persistent_keys(),
+        [
+            PersistentKey {
+                cache_id: 0,
+                cache_index: 0,
+                key: 0xFEDC_BA98_7654_3210,
+            },
+            PersistentKey {
+                cache_id: 2,
+                cache_index: 0,
+                key: 0x1122_3344_5566_7788,
+            },
+        ]
+    );
+}
+
+#[test]
+fn orders_are_skipped_without_bitmap_cache() {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 48);
+
+    let mut orders = ORDERS;
+    while !orders.is_empty() {
+        let length = ironrdp_pdu::find_size(orders).unwrap().unwrap().length;
+        let (frame, rest) = orders.split_at(length);
+        orders = rest;
+
+        let updates = processor.process(&mut image, frame, &mut WriteBuf::new()).unwrap();
+        assert!(updates.is_empty());
+    }
+
+    assert!(image.data().iter().all(|&byte| byte == 0));
+    assert_eq!(processor.bitmap_cache_persistent_keys(), []);
+}
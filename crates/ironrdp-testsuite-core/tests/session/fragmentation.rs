@@ -0,0 +1,142 @@
+use ironrdp_core::{encode_vec, Encode as _, WriteBuf, WriteCursor};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::fast_path::{EncryptionFlags, FastPathHeader, FastPathUpdatePdu, Fragmentation, UpdateCode};
+use ironrdp_pdu::surface_commands::{FrameAction, FrameMarkerPdu, SurfaceCommand};
+use ironrdp_session::fast_path::{Processor, ProcessorBuilder, ReassemblyLimits, UpdateKind};
+use ironrdp_session::image::DecodedImage;
+
+/// Encoded size of a frame marker surface command.
+const MARKER_SIZE: usize = 8;
+
+fn processor(reassembly_limits: ReassemblyLimits) -> Processor {
+    ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits,
+    }
+    .build()
+}
+
+/// Surface commands made of `frames` empty frames, numbered from `first_frame_id`.
+fn frames(first_frame_id: u32, frames: u32) -> Vec<u8> {
+    (first_frame_id..first_frame_id + frames)
+        .flat_map(|frame_id| [FrameAction::Begin, FrameAction::End].map(|action| (frame_id, action)))
+        .flat_map(|(frame_id, frame_action)| {
+            encode_vec(&SurfaceCommand::FrameMarker(FrameMarkerPdu {
+                frame_action,
+                frame_id: Some(frame_id),
+            }))
+            .unwrap()
+        })
+        .collect()
+}
+
+/// Splits the surface commands into fragments of `fragment_size` bytes.
+fn fragments(commands: &[u8], fragment_size: usize) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = commands.chunks(fragment_size).collect();
+    let last = chunks.len() - 1;
+
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, data)| {
+            let fragmentation = match i {
+                _ if last == 0 => Fragmentation::Single,
+                0 => Fragmentation::First,
+                _ if i == last => Fragmentation::Last,
+                _ => Fragmentation::Next,
+            };
+
+            let update = FastPathUpdatePdu {
+                fragmentation,
+                update_code: UpdateCode::SurfaceCommands,
+                compression_flags: None,
+                compression_type: None,
+                data,
+            };
+            let header = FastPathHeader::new(EncryptionFlags::empty(), update.size());
+
+            let mut frame = vec![0; header.size() + update.size()];
+            let mut cursor = WriteCursor::new(&mut frame);
+            header.encode(&mut cursor).unwrap();
+            update.encode(&mut cursor).unwrap();
+
+            frame
+        })
+        .collect()
+}
+
+/// Processes the fragments, returning the IDs of the frames completed.
+fn process(processor: &mut Processor, fragments: &[Vec<u8>]) -> Vec<u32> {
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+    let mut output = WriteBuf::new();
+
+    fragments
+        .iter()
+        .flat_map(|fragment| processor.process(&mut image, fragment, &mut output).unwrap())
+        .filter_map(|update| match update {
+            UpdateKind::FrameBoundary(frame_id) => Some(frame_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn fragmented_update_is_reassembled() {
+    let mut processor = processor(ReassemblyLimits::default());
+
+    let fragments = fragments(&frames(1, 3), 20);
+    assert_eq!(fragments.len(), 3);
+
+    assert_eq!(process(&mut processor, &fragments), [1, 2, 3]);
+}
+
+#[test]
+fn oversized_update_is_skipped() {
+    let mut processor = processor(ReassemblyLimits {
+        max_reassembled_update_size: 4 * MARKER_SIZE,
+        max_outstanding_fragments: 1000,
+    });
+
+    // The update is dropped as soon as it exceeds the limit, its remaining fragments being discarded.
+    let oversized = fragments(&frames(1, 100), MARKER_SIZE);
+    assert!(process(&mut processor, &oversized).is_empty());
+
+    // The processing resumes with the next updates.
+    assert_eq!(
+        process(&mut processor, &fragments(&frames(101, 2), MARKER_SIZE)),
+        [101, 102]
+    );
+    assert_eq!(process(&mut processor, &fragments(&frames(103, 1), 100)), [103]);
+}
+
+#[test]
+fn update_with_too_many_fragments_is_skipped() {
+    let mut processor = processor(ReassemblyLimits {
+        max_reassembled_update_size: ReassemblyLimits::DEFAULT_MAX_REASSEMBLED_UPDATE_SIZE,
+        max_outstanding_fragments: 4,
+    });
+
+    assert!(process(&mut processor, &fragments(&frames(1, 3), 1)).is_empty());
+
+    assert_eq!(process(&mut processor, &fragments(&frames(4, 1), 4)), [4]);
+}
+
+#[test]
+fn next_update_interrupting_a_skipped_one_is_processed() {
+    let mut processor = processor(ReassemblyLimits {
+        max_reassembled_update_size: 2 * MARKER_SIZE,
+        max_outstanding_fragments: 1000,
+    });
+
+    // The last fragment of the oversized update is never received.
+    let oversized = fragments(&frames(1, 10), MARKER_SIZE);
+    assert!(process(&mut processor, &oversized[..oversized.len() - 1]).is_empty());
+
+    assert_eq!(process(&mut processor, &fragments(&frames(11, 1), MARKER_SIZE)), [11]);
+}
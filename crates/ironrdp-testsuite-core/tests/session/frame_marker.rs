@@ -0,0 +1,160 @@
+use ironrdp_core::{encode_vec, Encode, EncodeResult, WriteBuf, WriteCursor};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::codecs::rfx::FrameAcknowledgePdu;
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::surface_commands::{FrameAction, FrameMarkerPdu, SurfaceCommand};
+use ironrdp_session::fast_path::{Processor, ProcessorBuilder, ReassemblyLimits, UpdateKind};
+use ironrdp_session::image::DecodedImage;
+
+use super::fast_path_frame;
+
+const IO_CHANNEL_ID: u16 = 1003;
+const USER_CHANNEL_ID: u16 = 1002;
+
+/// Surface commands, already encoded.
+struct SurfaceCommands(Vec<u8>);
+
+impl SurfaceCommands {
+    fn new(commands: &[SurfaceCommand<'_>]) -> Self {
+        Self(
+            commands
+                .iter()
+                .flat_map(|command| encode_vec(command).unwrap())
+                .collect(),
+        )
+    }
+}
+
+impl Encode for SurfaceCommands {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "SurfaceCommands"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+fn marker(frame_action: FrameAction, frame_id: u32) -> SurfaceCommand<'static> {
+    SurfaceCommand::FrameMarker(FrameMarkerPdu {
+        frame_action,
+        frame_id: Some(frame_id),
+    })
+}
+
+fn processor(frame_acknowledge: bool) -> Processor {
+    ProcessorBuilder {
+        io_channel_id: IO_CHANNEL_ID,
+        user_channel_id: USER_CHANNEL_ID,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        frame_acknowledge,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build()
+}
+
+fn frame_acknowledge_pdus(frame_ids: impl IntoIterator<Item = u32>) -> Vec<u8> {
+    let mut buf = WriteBuf::new();
+
+    for frame_id in frame_ids {
+        ironrdp_connector::legacy::encode_share_data(
+            USER_CHANNEL_ID,
+            IO_CHANNEL_ID,
+            0,
+            ShareDataPdu::FrameAcknowledge(FrameAcknowledgePdu { frame_id }),
+            &mut buf,
+        )
+        .unwrap();
+    }
+
+    buf.filled().to_vec()
+}
+
+fn frame_boundaries(updates: &[UpdateKind]) -> Vec<u32> {
+    updates
+        .iter()
+        .filter_map(|update| match update {
+            UpdateKind::FrameBoundary(frame_id) => Some(*frame_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn completed_frames_are_acknowledged() {
+    let mut processor = processor(true);
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+    let mut output = WriteBuf::new();
+
+    let commands = [marker(FrameAction::Begin, 7), marker(FrameAction::End, 7)];
+    let frame = fast_path_frame(UpdateCode::SurfaceCommands, &SurfaceCommands::new(&commands));
+    let updates = processor.process(&mut image, &frame, &mut output).unwrap();
+
+    assert_eq!(frame_boundaries(&updates), [7]);
+    assert_eq!(output.filled(), frame_acknowledge_pdus([7]));
+}
+
+#[test]
+fn every_frame_is_acknowledged_beyond_the_advertised_window() {
+    let mut processor = processor(true);
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+
+    // The client advertises a maximum of 20 unacknowledged frames, which must never be reached.
+    for frame_id in 0..25 {
+        let mut output = WriteBuf::new();
+
+        for action in [FrameAction::Begin, FrameAction::End] {
+            let frame = fast_path_frame(
+                UpdateCode::SurfaceCommands,
+                &SurfaceCommands::new(&[marker(action, frame_id)]),
+            );
+            processor.process(&mut image, &frame, &mut output).unwrap();
+        }
+
+        assert_eq!(output.filled(), frame_acknowledge_pdus([frame_id]));
+    }
+}
+
+#[test]
+fn frames_are_not_acknowledged_unless_requested() {
+    let mut processor = processor(false);
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+    let mut output = WriteBuf::new();
+
+    let commands = [
+        marker(FrameAction::Begin, 1),
+        marker(FrameAction::End, 1),
+        marker(FrameAction::Begin, 2),
+        marker(FrameAction::End, 2),
+    ];
+    let frame = fast_path_frame(UpdateCode::SurfaceCommands, &SurfaceCommands::new(&commands));
+    let updates = processor.process(&mut image, &frame, &mut output).unwrap();
+
+    assert_eq!(frame_boundaries(&updates), [1, 2]);
+    assert_eq!(output.filled_len(), 0);
+}
+
+#[test]
+fn end_marker_without_frame_id_ends_the_current_frame() {
+    let mut processor = processor(true);
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+    let mut output = WriteBuf::new();
+
+    // Some servers are sending the end marker without the frame ID field.
+    let mut commands = SurfaceCommands::new(&[marker(FrameAction::Begin, 42), marker(FrameAction::End, 0)]);
+    commands.0.truncate(commands.0.len() - 4);
+    let frame = fast_path_frame(UpdateCode::SurfaceCommands, &commands);
+    let updates = processor.process(&mut image, &frame, &mut output).unwrap();
+
+    assert_eq!(frame_boundaries(&updates), [42]);
+    assert_eq!(output.filled(), frame_acknowledge_pdus([42]));
+}
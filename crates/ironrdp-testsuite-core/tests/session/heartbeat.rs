@@ -0,0 +1,115 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp_pdu::rdp::heartbeat::HeartbeatPdu;
+use ironrdp_session::heartbeat::{HealthEvent, HeartbeatMonitor, HeartbeatPolicy};
+
+const HEARTBEAT: HeartbeatPdu = HeartbeatPdu {
+    period: 5,
+    warning_count: 2,
+    reconnect_count: 4,
+};
+
+/// Mocked clock, in seconds since the first heartbeat.
+struct Clock(Instant);
+
+impl Clock {
+    fn at(&self, secs: u64) -> Instant {
+        self.0 + Duration::from_secs(secs)
+    }
+}
+
+/// Polls the monitor every second from `start` to `end`, returning the events along with their time.
+fn poll_every_second(monitor: &mut HeartbeatMonitor, clock: &Clock, start: u64, end: u64) -> Vec<(u64, HealthEvent)> {
+    (start..=end)
+        .filter_map(|secs| monitor.poll(clock.at(secs)).map(|event| (secs, event)))
+        .collect()
+}
+
+#[test]
+fn missed_heartbeats_escalate_then_recover() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy::default());
+
+    assert_eq!(monitor.on_heartbeat(HEARTBEAT, clock.at(0)), None);
+
+    assert_eq!(
+        poll_every_second(&mut monitor, &clock, 0, 30),
+        [
+            (10, HealthEvent::Warning { missed: 2 }),
+            (20, HealthEvent::ReconnectRequired { missed: 4 }),
+        ]
+    );
+
+    assert_eq!(
+        monitor.on_heartbeat(HEARTBEAT, clock.at(31)),
+        Some(HealthEvent::Recovered)
+    );
+    assert_eq!(monitor.missed(clock.at(35)), 0);
+
+    // The next outage is reported again.
+    assert_eq!(
+        poll_every_second(&mut monitor, &clock, 31, 41),
+        [(41, HealthEvent::Warning { missed: 2 })]
+    );
+}
+
+#[test]
+fn regular_heartbeats_report_nothing() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy::default());
+
+    for secs in (0..100).step_by(5) {
+        assert_eq!(monitor.on_heartbeat(HEARTBEAT, clock.at(secs)), None);
+        assert_eq!(poll_every_second(&mut monitor, &clock, secs, secs + 4), []);
+    }
+}
+
+#[test]
+fn nothing_is_reported_before_the_first_heartbeat() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy::default());
+
+    assert_eq!(poll_every_second(&mut monitor, &clock, 0, 100), []);
+}
+
+#[test]
+fn policy_overrides_the_server_thresholds() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy {
+        warning_count: Some(1),
+        reconnect_count: Some(0),
+    });
+
+    monitor.on_heartbeat(HEARTBEAT, clock.at(0));
+
+    // The reconnection is disabled.
+    assert_eq!(
+        poll_every_second(&mut monitor, &clock, 0, 60),
+        [(5, HealthEvent::Warning { missed: 1 })]
+    );
+}
+
+#[test]
+fn late_poll_only_reports_the_reconnection() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy::default());
+
+    monitor.on_heartbeat(HEARTBEAT, clock.at(0));
+
+    assert_eq!(
+        monitor.poll(clock.at(22)),
+        Some(HealthEvent::ReconnectRequired { missed: 4 })
+    );
+    assert_eq!(monitor.poll(clock.at(23)), None);
+}
+
+#[test]
+fn zero_period_disables_the_monitor() {
+    let clock = Clock(Instant::now());
+    let mut monitor = HeartbeatMonitor::new(HeartbeatPolicy::default());
+
+    monitor.on_heartbeat(HeartbeatPdu { period: 0, ..HEARTBEAT }, clock.at(0));
+
+    assert_eq!(poll_every_second(&mut monitor, &clock, 0, 100), []);
+}
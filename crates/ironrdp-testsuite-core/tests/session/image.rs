@@ -0,0 +1,205 @@
+use core::num::NonZeroUsize;
+
+use ironrdp_core::{BufPool, WriteBuf};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::bitmap::{BitmapData, BitmapUpdateData, Compression};
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_session::fast_path::{ProcessorBuilder, ReassemblyLimits};
+use ironrdp_session::image::{DecodedImage, ImageRegionExtractor, PixelOrder, RowAlignment};
+use rstest::rstest;
+
+use super::fast_path_frame;
+
+const WIDTH: u16 = 7;
+const HEIGHT: u16 = 5;
+
+/// Returns the red, green and blue channels of the pixel at (`x`, `y`) in the test image.
+fn rgb(x: u16, y: u16) -> [u8; 3] {
+    [
+        u8::try_from(x * 16 + 1).unwrap(),
+        u8::try_from(y * 16 + 2).unwrap(),
+        u8::try_from(x + y * 16).unwrap(),
+    ]
+}
+
+/// Returns a `WIDTH`x`HEIGHT` image filled with a raw 32 bpp bitmap, each pixel being unique.
+fn test_image(pixel_format: PixelFormat) -> DecodedImage {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(pixel_format, WIDTH, HEIGHT);
+
+    // The rows of the bitmap are bottom-up.
+    let bitmap_data: Vec<u8> = (0..HEIGHT)
+        .rev()
+        .flat_map(|y| (0..WIDTH).map(move |x| rgb(x, y)))
+        .flat_map(|[r, g, b]| [b, g, r, 0x00])
+        .collect();
+
+    let update = BitmapUpdateData {
+        rectangles: vec![BitmapData {
+            rectangle: InclusiveRectangle {
+                left: 0,
+                top: 0,
+                right: WIDTH - 1,
+                bottom: HEIGHT - 1,
+            },
+            width: WIDTH,
+            height: HEIGHT,
+            bits_per_pixel: 32,
+            compression_flags: Compression::empty(),
+            compressed_data_header: None,
+            bitmap_data: &bitmap_data,
+        }],
+    };
+    let frame = fast_path_frame(UpdateCode::Bitmap, &update);
+
+    processor.process(&mut image, &frame, &mut WriteBuf::new()).unwrap();
+
+    image
+}
+
+/// Returns the rows expected when extracting `region`, each one followed by `padding` zeroes.
+fn expected_rows(region: &InclusiveRectangle, order: PixelOrder, padding: usize) -> Vec<u8> {
+    let mut expected = Vec::new();
+
+    for y in region.top..=region.bottom {
+        for x in region.left..=region.right {
+            let [r, g, b] = rgb(x, y);
+
+            match order {
+                PixelOrder::RgbA => expected.extend([r, g, b, 0xFF]),
+                PixelOrder::BgrA => expected.extend([b, g, r, 0xFF]),
+            }
+        }
+
+        expected.extend(core::iter::repeat(0).take(padding));
+    }
+
+    expected
+}
+
+fn alignment(bytes: usize) -> RowAlignment {
+    RowAlignment::Bytes(NonZeroUsize::new(bytes).unwrap())
+}
+
+/// Region 3 pixels wide, its rows of 12 bytes not being aligned.
+const ODD_REGION: InclusiveRectangle = InclusiveRectangle {
+    left: 2,
+    top: 1,
+    right: 4,
+    bottom: 3,
+};
+
+#[rstest]
+#[case::tight(RowAlignment::Tight, 12)]
+#[case::four_bytes(alignment(4), 12)]
+#[case::eight_bytes(alignment(8), 16)]
+#[case::wgpu(alignment(256), 256)]
+fn region_rows_are_aligned(
+    #[case] alignment: RowAlignment,
+    #[case] expected_bytes_per_row: usize,
+    #[values(PixelOrder::RgbA, PixelOrder::BgrA)] order: PixelOrder,
+    #[values(PixelFormat::RgbA32, PixelFormat::BgrA32, PixelFormat::BgrX32)] image_format: PixelFormat,
+) {
+    let image = test_image(image_format);
+    let extractor = ImageRegionExtractor::new(alignment, order);
+
+    let mut dst = vec![0xAA; extractor.buffer_len(&ODD_REGION)];
+    let bytes_per_row = extractor.extract_into(&image, &ODD_REGION, &mut dst).unwrap();
+
+    assert_eq!(bytes_per_row, expected_bytes_per_row);
+    assert_eq!(dst.len(), expected_bytes_per_row * 3);
+    assert_eq!(dst, expected_rows(&ODD_REGION, order, expected_bytes_per_row - 12));
+}
+
+#[rstest]
+fn whole_image_is_extracted(#[values(PixelOrder::RgbA, PixelOrder::BgrA)] order: PixelOrder) {
+    let image = test_image(PixelFormat::RgbA32);
+    let extractor = ImageRegionExtractor::new(alignment(8), order);
+    let region = InclusiveRectangle {
+        left: 0,
+        top: 0,
+        right: WIDTH - 1,
+        bottom: HEIGHT - 1,
+    };
+
+    let mut dst = vec![0; extractor.buffer_len(&region)];
+    let bytes_per_row = extractor.extract_into(&image, &region, &mut dst).unwrap();
+
+    assert_eq!(bytes_per_row, 32);
+    assert_eq!(dst, expected_rows(&region, order, 4));
+}
+
+#[test]
+fn pooled_region_matches_caller_buffer() {
+    let image = test_image(PixelFormat::RgbA32);
+    let extractor = ImageRegionExtractor::new(alignment(8), PixelOrder::BgrA);
+    let pool = BufPool::new();
+
+    let mut dst = vec![0; extractor.buffer_len(&ODD_REGION)];
+    extractor.extract_into(&image, &ODD_REGION, &mut dst).unwrap();
+
+    for _ in 0..2 {
+        let extracted = extractor.extract_pooled(&image, &ODD_REGION, &pool).unwrap();
+
+        assert_eq!(extracted.bytes_per_row, 16);
+        assert_eq!(extracted.data(), dst);
+    }
+
+    assert_eq!(pool.created_count(), 1);
+}
+
+#[test]
+fn single_pixel_region_is_padded() {
+    let image = test_image(PixelFormat::BgrX32);
+    let extractor = ImageRegionExtractor::new(alignment(8), PixelOrder::RgbA);
+    let region = InclusiveRectangle {
+        left: 6,
+        top: 4,
+        right: 6,
+        bottom: 4,
+    };
+
+    let mut dst = vec![0xAA; 8];
+    let bytes_per_row = extractor.extract_into(&image, &region, &mut dst).unwrap();
+
+    let [r, g, b] = rgb(6, 4);
+    assert_eq!(bytes_per_row, 8);
+    assert_eq!(dst, [r, g, b, 0xFF, 0, 0, 0, 0]);
+}
+
+#[test]
+fn too_small_buffer_is_rejected() {
+    let image = test_image(PixelFormat::RgbA32);
+    let extractor = ImageRegionExtractor::new(alignment(8), PixelOrder::RgbA);
+
+    let mut dst = vec![0; extractor.buffer_len(&ODD_REGION) - 1];
+
+    extractor.extract_into(&image, &ODD_REGION, &mut dst).unwrap_err();
+}
+
+#[test]
+fn out_of_bounds_region_is_rejected() {
+    let image = test_image(PixelFormat::RgbA32);
+    let extractor = ImageRegionExtractor::new(RowAlignment::Tight, PixelOrder::RgbA);
+    let region = InclusiveRectangle {
+        left: 5,
+        top: 0,
+        right: WIDTH,
+        bottom: 1,
+    };
+
+    let mut dst = vec![0; extractor.buffer_len(&region)];
+
+    extractor.extract_into(&image, &region, &mut dst).unwrap_err();
+}
@@ -1 +1,33 @@
+use ironrdp_core::{Encode as _, WriteCursor};
+use ironrdp_pdu::fast_path::{EncryptionFlags, FastPathHeader, FastPathUpdatePdu, Fragmentation, UpdateCode};
+
+mod bitmap;
+mod bitmap_cache;
+mod fragmentation;
+mod frame_marker;
+mod heartbeat;
+mod image;
+mod pointer;
+mod presentation;
 mod rfx;
+mod x224;
+
+fn fast_path_frame(update_code: UpdateCode, update: &dyn ironrdp_core::Encode) -> Vec<u8> {
+    let data = ironrdp_core::encode_vec(update).unwrap();
+
+    let update = FastPathUpdatePdu {
+        fragmentation: Fragmentation::Single,
+        update_code,
+        compression_flags: None,
+        compression_type: None,
+        data: &data,
+    };
+    let header = FastPathHeader::new(EncryptionFlags::empty(), update.size());
+
+    let mut frame = vec![0; header.size() + update.size()];
+    let mut cursor = WriteCursor::new(&mut frame);
+    header.encode(&mut cursor).unwrap();
+    update.encode(&mut cursor).unwrap();
+
+    frame
+}
@@ -0,0 +1,212 @@
+use std::rc::Rc;
+
+use ironrdp_core::WriteBuf;
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_graphics::pointer::{DecodedPointer, PointerBitmapTarget};
+use ironrdp_pdu::fast_path::UpdateCode;
+use ironrdp_pdu::pointer::{CachedPointerAttribute, ColorPointerAttribute, Point16, PointerAttribute};
+use ironrdp_session::fast_path::{Processor, ProcessorBuilder, ReassemblyLimits, UpdateKind};
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::pointer::{PointerCache, PointerCacheStats};
+
+use super::fast_path_frame;
+
+const COLOR_POINTER_24BPP: &[u8] = include_bytes!("../../test_data/pdu/pointer/color_pointer_24bpp.bin");
+
+fn pointer(hotspot_x: u16) -> Rc<DecodedPointer> {
+    Rc::new(DecodedPointer {
+        width: 1,
+        height: 1,
+        hotspot_x,
+        hotspot_y: 0,
+        bitmap_data: vec![0xFF; 4],
+    })
+}
+
+fn color_pointer(cache_index: u16, hotspot_x: u16) -> ColorPointerAttribute<'static> {
+    let mut pointer = ironrdp_core::decode::<ColorPointerAttribute<'_>>(COLOR_POINTER_24BPP).unwrap();
+    pointer.cache_index = cache_index;
+    pointer.hot_spot = Point16 { x: hotspot_x, y: 0 };
+    pointer
+}
+
+fn process(processor: &mut Processor, image: &mut DecodedImage, frame: &[u8]) -> Option<Rc<DecodedPointer>> {
+    let mut output = WriteBuf::new();
+
+    processor
+        .process(image, frame, &mut output)
+        .unwrap()
+        .into_iter()
+        .find_map(|update| match update {
+            UpdateKind::PointerBitmap(pointer) => Some(pointer),
+            _ => None,
+        })
+}
+
+#[test]
+fn pointer_cache_evicts_least_recently_used() {
+    let mut cache = PointerCache::new(3);
+
+    for id in 0..3 {
+        assert!(cache.insert(id, pointer(u16::try_from(id).unwrap())).is_none());
+    }
+
+    // Slot 0 is now more recently used than slots 1 and 2.
+    assert_eq!(cache.get(0).unwrap().hotspot_x, 0);
+
+    cache.insert(3, pointer(3));
+    assert!(!cache.is_cached(1));
+
+    cache.insert(4, pointer(4));
+    assert!(!cache.is_cached(2));
+
+    cache.insert(5, pointer(5));
+    assert!(!cache.is_cached(0));
+
+    assert_eq!(cache.len(), 3);
+    assert!(cache.get(1).is_none());
+    assert_eq!(cache.get(5).unwrap().hotspot_x, 5);
+
+    assert_eq!(
+        cache.stats(),
+        PointerCacheStats {
+            hits: 2,
+            misses: 1,
+            evictions: 3,
+        }
+    );
+}
+
+#[test]
+fn pointer_cache_replaces_slot_without_eviction() {
+    let mut cache = PointerCache::new(2);
+
+    cache.insert(0, pointer(0));
+    cache.insert(1, pointer(1));
+
+    let previous = cache.insert(1, pointer(10)).unwrap();
+    assert_eq!(previous.hotspot_x, 1);
+
+    assert!(cache.is_cached(0));
+    assert_eq!(cache.get(1).unwrap().hotspot_x, 10);
+    assert_eq!(cache.stats().evictions, 0);
+}
+
+#[test]
+fn zero_capacity_pointer_cache_stores_nothing() {
+    let mut cache = PointerCache::new(0);
+
+    assert!(cache.insert(0, pointer(0)).is_none());
+    assert!(cache.is_empty());
+    assert!(cache.get(0).is_none());
+}
+
+#[test]
+fn fast_path_cached_pointer_renders_re_cached_slot() {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: false,
+        pointer_software_rendering: false,
+        pointer_cache_size: 2,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+
+    // Fill the cache beyond its capacity: slot 0 is evicted.
+    for (cache_index, hotspot_x) in [(0, 1), (1, 2), (2, 3)] {
+        let frame = fast_path_frame(UpdateCode::ColorPointer, &color_pointer(cache_index, hotspot_x));
+        let decoded = process(&mut processor, &mut image, &frame).unwrap();
+        assert_eq!(decoded.hotspot_x, hotspot_x);
+    }
+
+    let cached = |cache_index| fast_path_frame(UpdateCode::CachedPointer, &CachedPointerAttribute { cache_index });
+
+    assert!(process(&mut processor, &mut image, &cached(0)).is_none());
+    assert_eq!(process(&mut processor, &mut image, &cached(1)).unwrap().hotspot_x, 2);
+
+    // Re-caching slot 0 evicts slot 2, the least recently used one.
+    let re_cached = color_pointer(0, 4);
+    let frame = fast_path_frame(UpdateCode::ColorPointer, &re_cached);
+    process(&mut processor, &mut image, &frame).unwrap();
+
+    assert!(process(&mut processor, &mut image, &cached(2)).is_none());
+
+    let expected =
+        DecodedPointer::decode_color_pointer_attribute(&re_cached, PointerBitmapTarget::Accelerated).unwrap();
+    let decoded = process(&mut processor, &mut image, &cached(0)).unwrap();
+    assert_eq!(decoded.hotspot_x, expected.hotspot_x);
+    assert_eq!(decoded.hotspot_y, expected.hotspot_y);
+    assert_eq!(decoded.bitmap_data, expected.bitmap_data);
+
+    assert_eq!(
+        processor.pointer_cache_stats(),
+        PointerCacheStats {
+            hits: 2,
+            misses: 2,
+            evictions: 2,
+        }
+    );
+}
+
+#[test]
+fn empty_fast_path_frame_is_skipped() {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: false,
+        pointer_software_rendering: false,
+        pointer_cache_size: 2,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+    let mut output = WriteBuf::new();
+
+    for frame in [&[0x00, 0x02][..], &[0x00, 0x80, 0x03]] {
+        let updates = processor.process(&mut image, frame, &mut output).unwrap();
+        assert!(updates.is_empty());
+    }
+    assert_eq!(output.filled_len(), 0);
+}
+
+#[test]
+fn undecodable_pointer_is_ignored() {
+    let mut processor = ProcessorBuilder {
+        io_channel_id: 1003,
+        user_channel_id: 1002,
+        no_server_pointer: false,
+        pointer_software_rendering: false,
+        pointer_cache_size: 2,
+        bitmap_cache: None,
+        frame_acknowledge: false,
+        reassembly_limits: ReassemblyLimits::default(),
+    }
+    .build();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 64, 64);
+
+    // 8 bpp pointers are not supported, as they require a palette.
+    let pointer = PointerAttribute {
+        xor_bpp: 8,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 0,
+            hot_spot: Point16 { x: 0, y: 0 },
+            width: 2,
+            height: 2,
+            xor_mask: &[0x00; 4],
+            and_mask: &[0x00; 4],
+        },
+    };
+    let frame = fast_path_frame(UpdateCode::NewPointer, &pointer);
+
+    assert!(process(&mut processor, &mut image, &frame).is_none());
+
+    // The pointer updates which follow are still processed.
+    let frame = fast_path_frame(UpdateCode::ColorPointer, &color_pointer(0, 1));
+    assert_eq!(process(&mut processor, &mut image, &frame).unwrap().hotspot_x, 1);
+}
@@ -0,0 +1,48 @@
+use ironrdp_pdu::geometry::InclusiveRectangle;
+use ironrdp_session::presentation::UpdateCoalescer;
+
+fn rect(left: u16, top: u16, right: u16, bottom: u16) -> InclusiveRectangle {
+    InclusiveRectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+#[test]
+fn visible_updates_are_passed_through() {
+    let mut coalescer = UpdateCoalescer::new();
+
+    assert!(coalescer.is_visible());
+    assert_eq!(coalescer.update(rect(1, 2, 3, 4)), Some(rect(1, 2, 3, 4)));
+    assert_eq!(coalescer.update(rect(10, 10, 20, 20)), Some(rect(10, 10, 20, 20)));
+    assert!(coalescer.dirty_region().is_none());
+
+    // Nothing to redraw when nothing was hidden.
+    assert!(coalescer.set_visible(true).is_none());
+}
+
+#[test]
+fn hidden_updates_are_coalesced() {
+    let mut coalescer = UpdateCoalescer::new();
+
+    assert!(coalescer.set_visible(false).is_none());
+    assert!(!coalescer.is_visible());
+
+    assert!(coalescer.update(rect(10, 10, 20, 20)).is_none());
+    assert!(coalescer.update(rect(5, 30, 8, 40)).is_none());
+    assert!(coalescer.update(rect(12, 12, 14, 14)).is_none());
+    assert_eq!(coalescer.dirty_region(), Some(&rect(5, 10, 20, 40)));
+
+    // Hiding again does not lose the accumulated region.
+    assert!(coalescer.set_visible(false).is_none());
+
+    assert_eq!(coalescer.set_visible(true), Some(rect(5, 10, 20, 40)));
+    assert!(coalescer.dirty_region().is_none());
+
+    // The region is emitted only once.
+    assert!(coalescer.set_visible(false).is_none());
+    assert!(coalescer.set_visible(true).is_none());
+    assert_eq!(coalescer.update(rect(0, 0, 1, 1)), Some(rect(0, 0, 1, 1)));
+}
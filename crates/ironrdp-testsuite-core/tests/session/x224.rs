@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+
+use ironrdp_connector::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
+use ironrdp_connector::{Config, ConnectTimeouts, CredentialDelegation, Credentials, DesktopSize, Sequence as _};
+use ironrdp_core::WriteBuf;
+use ironrdp_pdu::gcc::KeyboardType;
+use ironrdp_pdu::mcs::{McsMessage, SendDataIndication};
+use ironrdp_pdu::rdp::capability_sets::MajorPlatformType;
+use ironrdp_pdu::rdp::headers::ShareDataPduType;
+use ironrdp_pdu::rdp::multitransport::RequestedProtocol;
+use ironrdp_pdu::rdp::play_sound::PlaySoundPdu;
+use ironrdp_pdu::x224::X224;
+use ironrdp_session::x224::{Processor, ProcessorOutput};
+use ironrdp_svc::StaticChannelSet;
+use ironrdp_testsuite_core::rdp::SERVER_PLAY_SOUND_BUFFER;
+
+const IO_CHANNEL_ID: u16 = 1003;
+const USER_CHANNEL_ID: u16 = 1007;
+
+/// Offset of the type in a Share Data PDU, following the Share Control Header.
+const SHARE_DATA_PDU_TYPE_OFFSET: usize = 14;
+
+fn config() -> Config {
+    Config {
+        desktop_size: DesktopSize {
+            width: 1024,
+            height: 768,
+        },
+        desktop_scale_factor: 0,
+        enable_tls: true,
+        enable_credssp: false,
+        credentials: Credentials::UsernamePassword {
+            username: "user".to_owned(),
+            password: "password".to_owned(),
+        },
+        domain: None,
+        credential_delegation: CredentialDelegation::Full,
+        client_build: 0,
+        client_name: "ironrdp".to_owned(),
+        keyboard_type: KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_layout: 0,
+        active_input_locale: None,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        bitmap: None,
+        dig_product_id: String::new(),
+        client_dir: String::new(),
+        platform: MajorPlatformType::UNIX,
+        hardware_id: None,
+        request_data: None,
+        correlation_id: None,
+        autologon: false,
+        license_cache: None,
+        remote_app: None,
+        auto_reconnect_cookie: None,
+        frame_markers: false,
+        timeouts: ConnectTimeouts::default(),
+        bitmap_cache: None,
+        extra_capability_sets: Vec::new(),
+        decline_multitransport: true,
+        redirect_session: None,
+        no_server_pointer: true,
+        pointer_software_rendering: true,
+        performance_flags: Default::default(),
+    }
+}
+
+fn processor() -> Processor {
+    processor_with(config())
+}
+
+fn processor_with(config: Config) -> Processor {
+    Processor::new(
+        StaticChannelSet::new(),
+        USER_CHANNEL_ID,
+        IO_CHANNEL_ID,
+        ConnectionActivationSequence::new(config, IO_CHANNEL_ID, USER_CHANNEL_ID),
+    )
+}
+
+/// Wraps a Share Control PDU sent by the server on the I/O channel.
+fn io_channel_frame(share_control_pdu: &[u8]) -> Vec<u8> {
+    ironrdp_core::encode_vec(&X224(McsMessage::SendDataIndication(SendDataIndication {
+        initiator_id: USER_CHANNEL_ID,
+        channel_id: IO_CHANNEL_ID,
+        user_data: Cow::Borrowed(share_control_pdu),
+    })))
+    .unwrap()
+}
+
+#[test]
+fn play_sound_pdu_is_reported() {
+    let outputs = processor()
+        .process(&io_channel_frame(&SERVER_PLAY_SOUND_BUFFER))
+        .unwrap();
+
+    let [ProcessorOutput::PlaySound(pdu)] = outputs.as_slice() else {
+        panic!("unexpected outputs: {outputs:?}");
+    };
+    assert_eq!(
+        *pdu,
+        PlaySoundPdu {
+            duration_ms: 200,
+            frequency_hz: 750,
+        }
+    );
+}
+
+/// Initiate Multitransport Request PDU, with the request ID 42.
+const MULTITRANSPORT_REQUEST: [u8; 28] = [
+    0x02, 0x00, 0x00, 0x00, // SEC_TRANSPORT_REQ
+    0x2a, 0x00, 0x00, 0x00, // requestId
+    0x01, 0x00, 0x00, 0x00, // INITITATE_REQUEST_PROTOCOL_UDPFECR, reserved
+    0x9d, 0x4f, 0x1c, 0x6e, 0x35, 0xa2, 0x80, 0x07, 0xe1, 0x53, 0xc8, 0x2b, 0x74, 0x0e, 0xd6,
+    0x91, // securityCookie
+];
+
+/// MCS Send Data Request carrying the response declining the request ID 42.
+#[rustfmt::skip]
+const MULTITRANSPORT_RESPONSE_FRAME: [u8; 26] = [
+    0x03, 0x00, 0x00, 0x1a, // TPKT header
+    0x02, 0xf0, 0x80, // X.224 Data TPDU
+    0x64, 0x00, 0x06, 0x03, 0xeb, 0x70, 0x0c, // Send Data Request: initiator 1007, channel 1003, length 12
+    0x04, 0x00, 0x00, 0x00, // SEC_TRANSPORT_RSP
+    0x2a, 0x00, 0x00, 0x00, // requestId
+    0x04, 0x40, 0x00, 0x80, // E_ABORT
+];
+
+#[test]
+fn multitransport_request_is_declined() {
+    let outputs = processor().process(&io_channel_frame(&MULTITRANSPORT_REQUEST)).unwrap();
+
+    let [ProcessorOutput::ResponseFrame(frame), ProcessorOutput::MultitransportRequest(request)] = outputs.as_slice()
+    else {
+        panic!("unexpected outputs: {outputs:?}");
+    };
+    assert_eq!(frame.as_slice(), MULTITRANSPORT_RESPONSE_FRAME);
+    assert_eq!(request.request_id, 42);
+    assert_eq!(request.requested_protocol, RequestedProtocol::UDP_FECR);
+}
+
+#[test]
+fn multitransport_request_is_only_reported_when_not_declined() {
+    let outputs = processor_with(Config {
+        decline_multitransport: false,
+        ..config()
+    })
+    .process(&io_channel_frame(&MULTITRANSPORT_REQUEST))
+    .unwrap();
+
+    assert!(
+        matches!(outputs.as_slice(), [ProcessorOutput::MultitransportRequest(_)]),
+        "unexpected outputs: {outputs:?}"
+    );
+}
+
+#[rstest::rstest]
+#[case::declined(true, MULTITRANSPORT_RESPONSE_FRAME.as_slice())]
+#[case::not_declined(false, &[])]
+fn multitransport_request_before_demand_active(#[case] decline_multitransport: bool, #[case] expected: &[u8]) {
+    let mut connection_activation = ConnectionActivationSequence::new(
+        Config {
+            decline_multitransport,
+            ..config()
+        },
+        IO_CHANNEL_ID,
+        USER_CHANNEL_ID,
+    );
+
+    let mut output = WriteBuf::new();
+    connection_activation
+        .step(&io_channel_frame(&MULTITRANSPORT_REQUEST), &mut output)
+        .unwrap();
+
+    assert_eq!(output.filled(), expected);
+    // The Server Demand Active PDU is still expected.
+    assert!(matches!(
+        connection_activation.state,
+        ConnectionActivationState::CapabilitiesExchange { .. }
+    ));
+}
+
+#[rstest::rstest]
+#[case::set_keyboard_indicators(ShareDataPduType::SetKeyboardIndicators)]
+#[case::bitmap_cache_error(ShareDataPduType::BitmapCacheErrorPdu)]
+#[case::status_info(ShareDataPduType::StatusInfoPdu)]
+fn unhandled_share_data_pdu_is_skipped(#[case] pdu_type: ShareDataPduType) {
+    let mut share_control_pdu = SERVER_PLAY_SOUND_BUFFER;
+    share_control_pdu[SHARE_DATA_PDU_TYPE_OFFSET] = pdu_type as u8;
+
+    let mut processor = processor();
+    let outputs = processor.process(&io_channel_frame(&share_control_pdu)).unwrap();
+    assert!(outputs.is_empty(), "unexpected outputs: {outputs:?}");
+
+    // The session goes on.
+    let outputs = processor.process(&io_channel_frame(&SERVER_PLAY_SOUND_BUFFER)).unwrap();
+    assert!(matches!(outputs.as_slice(), [ProcessorOutput::PlaySound(_)]));
+}
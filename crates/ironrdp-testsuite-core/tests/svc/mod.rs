@@ -0,0 +1,481 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ironrdp_core::{decode, encode_vec, ensure_size, impl_as_any, Encode, EncodeResult, WriteCursor};
+use ironrdp_pdu::gcc::ChannelName;
+use ironrdp_pdu::rdp::client_info::CompressionType;
+use ironrdp_pdu::x224::X224;
+use ironrdp_pdu::{mcs, PduResult};
+use ironrdp_svc::{
+    ChannelFlags, StaticChannelSet, StaticVirtualChannel, SvcEncode, SvcMessage, SvcProcessor, CHANNEL_CHUNK_LENGTH,
+    DEFAULT_MAX_PDU_LENGTH,
+};
+
+use crate::allocations::count_allocations;
+
+#[derive(Debug)]
+struct CloseCounter {
+    closed: Arc<AtomicUsize>,
+}
+
+impl_as_any!(CloseCounter);
+
+impl SvcProcessor for CloseCounter {
+    fn channel_name(&self) -> ChannelName {
+        ChannelName::from_static(b"counter\0")
+    }
+
+    fn process(&mut self, _payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self) -> PduResult<Vec<SvcMessage>> {
+        self.closed.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![SvcMessage::from(vec![0xDE, 0xAD])])
+    }
+}
+
+fn counter() -> (CloseCounter, Arc<AtomicUsize>) {
+    let closed = Arc::new(AtomicUsize::new(0));
+    let processor = CloseCounter {
+        closed: Arc::clone(&closed),
+    };
+    (processor, closed)
+}
+
+#[test]
+fn close_explicitly_then_drop() {
+    let (processor, closed) = counter();
+    let mut svc = StaticVirtualChannel::new(processor);
+
+    assert_eq!(svc.close().unwrap().len(), 1);
+    assert!(svc.is_closed());
+    assert!(svc.close().unwrap().is_empty());
+    drop(svc);
+
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn close_on_drop() {
+    let (processor, closed) = counter();
+    drop(StaticVirtualChannel::new(processor));
+
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn close_on_remove() {
+    let (processor, closed) = counter();
+    let mut set = StaticChannelSet::new();
+    set.insert(processor);
+
+    let svc = set.remove_by_type::<CloseCounter>().unwrap();
+    assert_eq!(closed.load(Ordering::SeqCst), 0);
+    drop(svc);
+
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn close_on_clear() {
+    let (processor, closed) = counter();
+    let mut set = StaticChannelSet::new();
+    set.insert(processor);
+
+    set.clear();
+
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn close_all_then_drop() {
+    let (processor, closed) = counter();
+    let mut set = StaticChannelSet::new();
+    set.insert(processor);
+    set.attach_channel_id(core::any::TypeId::of::<CloseCounter>(), 1004);
+
+    let results = set.close_all();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, Some(1004));
+    assert_eq!(results[0].1.as_ref().unwrap().len(), 1);
+
+    assert!(set
+        .close_all()
+        .iter()
+        .all(|(_, result)| result.as_ref().unwrap().is_empty()));
+    drop(set);
+
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    received: Vec<Vec<u8>>,
+}
+
+impl_as_any!(Recorder);
+
+impl SvcProcessor for Recorder {
+    fn channel_name(&self) -> ChannelName {
+        ChannelName::from_static(b"recorder")
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        self.received.push(payload.to_vec());
+        Ok(Vec::new())
+    }
+}
+
+/// Splits the encoded frames, returning the user data of each of them.
+fn chunks_of(mut frames: &[u8]) -> Vec<Vec<u8>> {
+    let mut chunks = Vec::new();
+
+    while !frames.is_empty() {
+        let length = ironrdp_pdu::find_size(frames).unwrap().unwrap().length;
+        let (frame, rest) = frames.split_at(length);
+        chunks.push(
+            decode::<X224<mcs::SendDataIndication<'_>>>(frame)
+                .unwrap()
+                .0
+                .user_data
+                .to_vec(),
+        );
+        frames = rest;
+    }
+
+    chunks
+}
+
+fn chunk_flags(chunk: &[u8]) -> ChannelFlags {
+    ChannelFlags::from_bits_retain(u32::from_le_bytes(chunk[4..8].try_into().unwrap()))
+}
+
+#[test]
+fn compressed_server_data_round_trips() {
+    for compression_type in [CompressionType::K8, CompressionType::K64, CompressionType::Rdp61] {
+        let mut server = StaticVirtualChannel::new(Recorder::default());
+        server.enable_compression(compression_type);
+        let mut client = StaticVirtualChannel::new(Recorder::default());
+
+        let messages: Vec<Vec<u8>> = (0..20u8)
+            .map(|i| {
+                format!("Clipboard contents #{i}, ")
+                    .repeat(usize::from(i) * 40)
+                    .into_bytes()
+            })
+            .collect();
+
+        let mut encoded_len = 0;
+        for message in &messages {
+            let encoded = server
+                .server_encode(vec![SvcMessage::from(message.clone())], 1004, 1002)
+                .unwrap();
+            encoded_len += encoded.len();
+
+            for chunk in chunks_of(&encoded) {
+                client.process(&chunk).unwrap();
+            }
+        }
+
+        let received = &client.channel_processor_downcast_ref::<Recorder>().unwrap().received;
+        assert_eq!(*received, messages);
+        assert!(encoded_len < messages.iter().map(Vec::len).sum());
+    }
+}
+
+#[test]
+fn compression_type_is_set_in_the_chunk_header() {
+    let message = || vec![SvcMessage::from(b"compressible ".repeat(200))];
+
+    let mut server = StaticVirtualChannel::new(Recorder::default());
+    server.enable_compression(CompressionType::K64);
+
+    let chunks = chunks_of(&server.server_encode(message(), 1004, 1002).unwrap());
+    assert_eq!(chunks.len(), 2);
+    for chunk in &chunks {
+        let flags = chunk_flags(chunk);
+        assert!(flags.contains(ChannelFlags::COMPRESSED));
+        // PACKET_COMPR_TYPE_64K
+        assert_eq!(flags.bits() & 0x000F_0000, 0x0001_0000);
+    }
+
+    // The data of the second message is found in the history built by the first one.
+    let chunks_again = chunks_of(&server.server_encode(message(), 1004, 1002).unwrap());
+    assert!(chunks_again[0].len() < chunks[0].len());
+}
+
+#[test]
+fn short_chunks_are_not_compressed() {
+    let mut server = StaticVirtualChannel::new(Recorder::default());
+    server.enable_compression(CompressionType::K64);
+
+    let chunks = chunks_of(
+        &server
+            .server_encode(vec![SvcMessage::from(b"aaaaaaaaaaaaaaaa".to_vec())], 1004, 1002)
+            .unwrap(),
+    );
+
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunk_flags(&chunks[0]), ChannelFlags::FIRST | ChannelFlags::LAST);
+    assert_eq!(&chunks[0][8..], b"aaaaaaaaaaaaaaaa");
+}
+
+#[test]
+fn uncompressed_channel_is_left_as_is() {
+    let mut server = StaticVirtualChannel::new(Recorder::default());
+    let message = || vec![SvcMessage::from(b"compressible ".repeat(200))];
+
+    let encoded = server.server_encode(message(), 1004, 1002).unwrap();
+
+    assert!(!server.is_compression_enabled());
+    assert_eq!(
+        encoded,
+        ironrdp_svc::server_encode_svc_messages(message(), 1004, 1002).unwrap()
+    );
+}
+
+#[derive(Debug)]
+struct LimitedRecorder {
+    max_pdu_length: usize,
+    received: Vec<Vec<u8>>,
+}
+
+impl_as_any!(LimitedRecorder);
+
+impl SvcProcessor for LimitedRecorder {
+    fn channel_name(&self) -> ChannelName {
+        ChannelName::from_static(b"limited\0")
+    }
+
+    fn process(&mut self, payload: &[u8]) -> PduResult<Vec<SvcMessage>> {
+        self.received.push(payload.to_vec());
+        Ok(Vec::new())
+    }
+
+    fn max_pdu_length(&self) -> Option<usize> {
+        Some(self.max_pdu_length)
+    }
+}
+
+#[test]
+fn oversized_compressed_pdu_is_discarded() {
+    let mut server = StaticVirtualChannel::new(Recorder::default());
+    server.enable_compression(CompressionType::K64);
+    let mut client = StaticVirtualChannel::new(LimitedRecorder {
+        max_pdu_length: 2000,
+        received: Vec::new(),
+    });
+
+    let oversized = b"compressible ".repeat(400);
+    let chunks = chunks_of(
+        &server
+            .server_encode(vec![SvcMessage::from(oversized)], 1004, 1002)
+            .unwrap(),
+    );
+    assert!(chunks.len() > 1);
+
+    // The PDU is skipped on its first chunk, and the following ones are discarded.
+    for chunk in &chunks {
+        assert!(client.process(chunk).unwrap().is_empty());
+    }
+
+    // The compression history is kept in sync while discarding.
+    let message = b"compressible ".repeat(100);
+    for chunk in chunks_of(
+        &server
+            .server_encode(vec![SvcMessage::from(message.clone())], 1004, 1002)
+            .unwrap(),
+    ) {
+        client.process(&chunk).unwrap();
+    }
+
+    let received = &client
+        .channel_processor_downcast_ref::<LimitedRecorder>()
+        .unwrap()
+        .received;
+    assert_eq!(*received, [message]);
+}
+
+/// Channel PDU Header followed by `data`
+fn raw_chunk(announced_length: u32, flags: ChannelFlags, data: &[u8]) -> Vec<u8> {
+    let mut chunk = announced_length.to_le_bytes().to_vec();
+    chunk.extend_from_slice(&flags.bits().to_le_bytes());
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+#[test]
+fn pdu_exceeding_the_default_limit_is_skipped() {
+    let mut client = StaticVirtualChannel::new(Recorder::default());
+    let announced_length = u32::try_from(DEFAULT_MAX_PDU_LENGTH + 1).unwrap();
+
+    // The announced length is checked on the first chunk, before buffering anything.
+    assert!(client
+        .process(&raw_chunk(announced_length, ChannelFlags::FIRST, &[0xAA; 1600]))
+        .unwrap()
+        .is_empty());
+    assert!(client
+        .process(&raw_chunk(announced_length, ChannelFlags::empty(), &[0xAA; 1600]))
+        .unwrap()
+        .is_empty());
+    assert!(client
+        .process(&raw_chunk(announced_length, ChannelFlags::LAST, &[0xAA; 16]))
+        .unwrap()
+        .is_empty());
+
+    // The processing resumes with the next PDU.
+    client
+        .process(&raw_chunk(4, ChannelFlags::FIRST | ChannelFlags::LAST, b"next"))
+        .unwrap();
+
+    let received = &client.channel_processor_downcast_ref::<Recorder>().unwrap().received;
+    assert_eq!(*received, [b"next".to_vec()]);
+}
+
+/// A PDU made of `len` bytes counting up from zero, bypassing the legacy `Vec<u8>` path.
+struct CountingPdu {
+    len: usize,
+}
+
+impl Encode for CountingPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.len);
+        for i in 0..self.len {
+            dst.write_u8(i.to_le_bytes()[0]);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "CountingPdu"
+    }
+
+    fn size(&self) -> usize {
+        self.len
+    }
+}
+
+impl SvcEncode for CountingPdu {}
+
+/// A PDU whose size is wrong, and which therefore always fails to encode.
+struct MisreportedPdu;
+
+impl Encode for MisreportedPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: 8);
+        dst.write_u64(0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "MisreportedPdu"
+    }
+
+    fn size(&self) -> usize {
+        4
+    }
+}
+
+impl SvcEncode for MisreportedPdu {}
+
+/// A PDU whose size is over-reported, writing fewer bytes than announced.
+struct OversizedPdu;
+
+impl Encode for OversizedPdu {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: 2);
+        dst.write_u16(0xBEEF);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "OversizedPdu"
+    }
+
+    fn size(&self) -> usize {
+        8
+    }
+}
+
+impl SvcEncode for OversizedPdu {}
+
+#[test]
+fn message_representations_are_equivalent() {
+    for len in [
+        0,
+        1,
+        SvcMessage::INLINE_CAPACITY,
+        SvcMessage::INLINE_CAPACITY + 1,
+        CHANNEL_CHUNK_LENGTH * 2 + 1,
+    ] {
+        let flags = ChannelFlags::SHOW_PROTOCOL;
+        let expected =
+            StaticVirtualChannel::chunkify(vec![SvcMessage::boxed(CountingPdu { len }).with_flags(flags)]).unwrap();
+
+        let message = SvcMessage::from(CountingPdu { len }).with_flags(flags);
+        assert_eq!(message.is_inline(), len <= SvcMessage::INLINE_CAPACITY);
+
+        let encoded = encode_vec(&CountingPdu { len }).unwrap();
+
+        for message in [
+            message,
+            SvcMessage::from_encoded(encoded.clone()).with_flags(flags),
+            SvcMessage::from(encoded).with_flags(flags),
+        ] {
+            let chunks = StaticVirtualChannel::chunkify(vec![message]).unwrap();
+            assert_eq!(chunks.len(), expected.len());
+            for (chunk, expected) in chunks.iter().zip(&expected) {
+                assert_eq!(chunk.filled(), expected.filled());
+            }
+        }
+    }
+}
+
+#[test]
+fn legacy_vec_messages_are_not_inlined() {
+    assert!(!SvcMessage::from(vec![0xDE, 0xAD]).is_inline());
+}
+
+#[test]
+fn inline_encoding_error_is_reported_on_chunkify() {
+    let message = SvcMessage::from(MisreportedPdu);
+    assert!(!message.is_inline());
+    assert!(StaticVirtualChannel::chunkify(vec![message]).is_err());
+}
+
+#[test]
+fn over_reported_size_is_not_padded() {
+    let message = SvcMessage::from(OversizedPdu);
+    assert!(message.is_inline());
+    assert_eq!(message.size(), 2);
+
+    let chunks = StaticVirtualChannel::chunkify(vec![message]).unwrap();
+
+    // Channel PDU Header, followed by the 2 bytes actually written.
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].filled().len(), 8 + 2);
+    assert!(chunks[0].filled().ends_with(&0xBEEFu16.to_le_bytes()));
+}
+
+#[test]
+fn inline_messages_are_not_boxed() {
+    const MESSAGES: usize = 32;
+
+    let chunkify_allocations = |message: fn(CountingPdu) -> SvcMessage| {
+        let (_, allocations) = count_allocations(|| {
+            let messages = (0..MESSAGES).map(|_| message(CountingPdu { len: 16 })).collect();
+            StaticVirtualChannel::chunkify(messages).unwrap()
+        });
+        allocations
+    };
+
+    let boxed = chunkify_allocations(SvcMessage::boxed);
+    let inline = chunkify_allocations(SvcMessage::from);
+
+    // Each boxed message makes at least one more allocation than its inline counterpart.
+    assert!(
+        inline + MESSAGES <= boxed,
+        "{inline} inline and {boxed} boxed allocations"
+    );
+}
@@ -0,0 +1,128 @@
+//! `ChunkedStream`, exposing a message-oriented stream (e.g.: WebTransport) as a byte stream
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use futures_util::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use futures_util::{AsyncWrite as _, Sink};
+use ironrdp::pdu;
+use ironrdp_futures::{ChunkedStream, LocalFuturesFramed};
+
+/// Send half of a mocked message-oriented stream, recording the chunks it receives
+#[derive(Default)]
+pub(crate) struct MockSendStream {
+    pub(crate) chunks: Vec<Vec<u8>>,
+    blocked: bool,
+}
+
+impl Sink<Vec<u8>> for MockSendStream {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.blocked {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, chunk: Vec<u8>) -> io::Result<()> {
+        self.get_mut().chunks.push(chunk);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receive half of a mocked message-oriented stream, yielding `chunks` in order
+pub(crate) fn mock_recv_stream(
+    chunks: Vec<io::Result<Vec<u8>>>,
+) -> futures_util::stream::Iter<std::vec::IntoIter<io::Result<Vec<u8>>>> {
+    futures_util::stream::iter(chunks)
+}
+
+#[tokio::test]
+async fn chunked_stream_partial_reads() {
+    let reader = mock_recv_stream(vec![Ok(vec![1, 2, 3, 4, 5]), Ok(Vec::new()), Ok(vec![6])]);
+    let mut stream = ChunkedStream::new(reader, MockSendStream::default());
+
+    let mut buf = [0; 2];
+    let mut received = Vec::new();
+
+    loop {
+        let len = stream.read(&mut buf).await.unwrap();
+        if len == 0 {
+            break;
+        }
+        received.push(buf[..len].to_vec());
+    }
+
+    assert_eq!(received, [vec![1, 2], vec![3, 4], vec![5], vec![6]]);
+    assert_eq!(stream.buffered_len(), 0);
+}
+
+#[tokio::test]
+async fn chunked_stream_read_error_is_forwarded() {
+    let reader = mock_recv_stream(vec![
+        Ok(vec![1]),
+        Err(io::Error::new(io::ErrorKind::ConnectionReset, "stream reset")),
+    ]);
+    let mut stream = ChunkedStream::new(reader, MockSendStream::default());
+
+    let mut buf = [0; 4];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 1);
+
+    let error = stream.read(&mut buf).await.unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+}
+
+#[tokio::test]
+async fn chunked_stream_reads_pdu_split_across_chunks() {
+    // X.224 Data TPKT header followed by a 3-byte payload, received in arbitrary chunks.
+    let bytes = [0x03, 0x00, 0x00, 0x07, 0xaa, 0xbb, 0xcc];
+    let reader = mock_recv_stream(vec![
+        Ok(bytes[..1].to_vec()),
+        Ok(bytes[1..5].to_vec()),
+        Ok(bytes[5..].to_vec()),
+    ]);
+    let mut framed = LocalFuturesFramed::new(ChunkedStream::new(reader, MockSendStream::default()));
+
+    let (action, frame) = framed.read_pdu().await.unwrap();
+
+    assert_eq!(action, pdu::Action::X224);
+    assert_eq!(frame.as_ref(), bytes);
+}
+
+#[tokio::test]
+async fn chunked_stream_splits_large_writes() {
+    let mut stream = ChunkedStream::new(mock_recv_stream(Vec::new()), MockSendStream::default()).with_max_chunk_size(4);
+
+    // A single write is partial when the buffer exceeds the chunk size.
+    assert_eq!(stream.write(&[0; 10]).await.unwrap(), 4);
+
+    stream.write_all(&[1; 6]).await.unwrap();
+    stream.flush().await.unwrap();
+
+    let (_, sink) = stream.into_inner();
+    assert_eq!(sink.chunks, [vec![0; 4], vec![1; 4], vec![1; 2]]);
+}
+
+#[test]
+fn chunked_stream_write_waits_for_sink() {
+    let sink = MockSendStream {
+        blocked: true,
+        ..MockSendStream::default()
+    };
+    let mut stream = ChunkedStream::new(mock_recv_stream(Vec::new()), sink);
+    let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+
+    assert!(Pin::new(&mut stream).poll_write(&mut cx, &[1, 2, 3]).is_pending());
+    assert!(stream.into_inner().1.chunks.is_empty());
+}
@@ -0,0 +1,4 @@
+#[cfg(windows)]
+mod windows;
+#[cfg(target_os = "linux")]
+mod x11;
@@ -0,0 +1,213 @@
+//! Stress tests of the Windows clipboard backend, alternating the local and remote copy/paste.
+
+use core::time::Duration;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{bail, ensure, Context as _};
+use ironrdp::cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy, CliprdrBackend};
+use ironrdp::cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, FormatDataRequest, FormatDataResponse};
+use ironrdp_cliprdr_native::WinClipboard;
+use windows::Win32::Foundation::{HANDLE, HGLOBAL};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE};
+
+const ITERATIONS: usize = 50;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Forwards the messages of the clipboard to the remote thread, like the session does
+#[derive(Debug)]
+struct RemoteProxy {
+    tx: mpsc::Sender<ClipboardMessage>,
+}
+
+impl ClipboardMessageProxy for RemoteProxy {
+    fn send_clipboard_message(&self, message: ClipboardMessage) {
+        let _ = self.tx.send(message);
+    }
+}
+
+// A single clipboard can be created per process, as the window class is registered only once.
+#[test]
+fn alternating_local_and_remote_copy_paste_does_not_time_out() {
+    let (proxy_tx, proxy_rx) = mpsc::channel();
+    let clipboard = WinClipboard::new(RemoteProxy { tx: proxy_tx }).expect("clipboard");
+    let backend = Arc::new(Mutex::new(clipboard.backend_factory().build_cliprdr_backend()));
+    let remote_text = Arc::new(Mutex::new(String::new()));
+
+    let (format_data_tx, format_data_rx) = mpsc::channel();
+    let (error_tx, error_rx) = mpsc::channel();
+
+    // Remote side, answering the paste requests of the clipboard.
+    std::thread::spawn({
+        let backend = Arc::clone(&backend);
+        let remote_text = Arc::clone(&remote_text);
+
+        move || {
+            for message in proxy_rx {
+                match message {
+                    ClipboardMessage::SendInitiatePaste(format) => {
+                        assert_eq!(format, ClipboardFormatId::CF_UNICODETEXT);
+                        let data = encode_text(&remote_text.lock().unwrap());
+                        backend
+                            .lock()
+                            .unwrap()
+                            .on_format_data_response(FormatDataResponse::new_data(data));
+                    }
+                    ClipboardMessage::SendFormatData(response) => {
+                        let _ = format_data_tx.send(response);
+                    }
+                    ClipboardMessage::Error(error) => {
+                        let _ = error_tx.send(error.to_string());
+                    }
+                    ClipboardMessage::SendInitiateCopy(_) => {}
+                }
+            }
+        }
+    });
+
+    let driver = std::thread::spawn(move || -> anyhow::Result<()> {
+        for i in 0..ITERATIONS {
+            // Local copy, then remote paste.
+            let local = format!("local text {i}");
+            set_local_text(&local)?;
+
+            backend.lock().unwrap().on_format_data_request(FormatDataRequest {
+                format: ClipboardFormatId::CF_UNICODETEXT,
+            });
+            let response = format_data_rx
+                .recv_timeout(TIMEOUT)
+                .with_context(|| format!("remote paste {i} timed out"))?;
+            ensure!(!response.is_error(), "remote paste {i} failed");
+            ensure!(
+                decode_text(response.data()) == local,
+                "remote paste {i} returned stale data"
+            );
+
+            // Remote copy, then local paste: the clipboard takes the ownership asynchronously.
+            let remote = format!("remote text {i}");
+            remote_text.lock().unwrap().clone_from(&remote);
+
+            backend
+                .lock()
+                .unwrap()
+                .on_remote_copy(&[ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]);
+
+            let deadline = Instant::now() + TIMEOUT;
+            while get_local_text(deadline)?.as_deref() != Some(remote.as_str()) {
+                if Instant::now() > deadline {
+                    bail!("local paste {i} timed out");
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        Ok(())
+    });
+
+    // The clipboard is processed by the message loop of the thread which created it.
+    let mut msg = MSG::default();
+    while !driver.is_finished() {
+        // SAFETY: `msg` is valid for writes.
+        while unsafe { PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE) }.as_bool() {
+            // SAFETY: `msg` was filled by `PeekMessageW`.
+            let _ = unsafe { TranslateMessage(&msg) };
+            // SAFETY: `msg` was filled by `PeekMessageW`.
+            unsafe { DispatchMessageW(&msg) };
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    if let Err(error) = driver.join().expect("driver thread") {
+        panic!("{error:#}");
+    }
+
+    let errors: Vec<String> = error_rx.try_iter().collect();
+    assert!(errors.is_empty(), "clipboard errors: {errors:?}");
+}
+
+fn encode_text(text: &str) -> Vec<u8> {
+    text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+}
+
+fn decode_text(data: &[u8]) -> String {
+    let text: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+
+    String::from_utf16_lossy(&text)
+}
+
+/// Opens the clipboard, retrying while it is open by the clipboard under test.
+fn with_open_clipboard<T>(deadline: Instant, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    // SAFETY: the clipboard is not associated with any window.
+    while unsafe { OpenClipboard(None) }.is_err() {
+        if Instant::now() > deadline {
+            bail!("failed to open the clipboard");
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let result = f();
+
+    // SAFETY: the clipboard was opened just above.
+    let _ = unsafe { CloseClipboard() };
+
+    result
+}
+
+fn set_local_text(text: &str) -> anyhow::Result<()> {
+    let data = encode_text(text);
+
+    with_open_clipboard(Instant::now() + TIMEOUT, || {
+        // SAFETY: the clipboard is open.
+        unsafe { EmptyClipboard() }?;
+
+        // SAFETY: `GlobalAlloc` has no precondition.
+        let handle = unsafe { GlobalAlloc(GMEM_MOVEABLE, data.len()) }?;
+        // SAFETY: `handle` was just allocated.
+        let dst = unsafe { GlobalLock(handle) };
+        ensure!(!dst.is_null(), "failed to lock the clipboard data");
+        // SAFETY: `dst` is valid for writes of `data.len()` bytes, allocated just above.
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), dst.cast::<u8>(), data.len()) };
+        // SAFETY: `handle` was locked just above.
+        let _ = unsafe { GlobalUnlock(handle) };
+
+        // SAFETY: the clipboard is open, and takes the ownership of `handle` on success.
+        unsafe { SetClipboardData(ClipboardFormatId::CF_UNICODETEXT.value(), HANDLE(handle.0)) }?;
+
+        Ok(())
+    })
+}
+
+/// Reads the text of the clipboard, which renders it if it is delay-rendered.
+fn get_local_text(deadline: Instant) -> anyhow::Result<Option<String>> {
+    with_open_clipboard(deadline, || {
+        // SAFETY: the clipboard is open.
+        let Ok(handle) = (unsafe { GetClipboardData(ClipboardFormatId::CF_UNICODETEXT.value()) }) else {
+            return Ok(None);
+        };
+        let handle = HGLOBAL(handle.0);
+
+        // SAFETY: `handle` is owned by the open clipboard.
+        let data = unsafe { GlobalLock(handle) }.cast::<u8>();
+        if data.is_null() {
+            return Ok(None);
+        }
+
+        // SAFETY: `handle` is owned by the open clipboard.
+        let size = unsafe { GlobalSize(handle) };
+        // SAFETY: `data` is valid for reads of `size` bytes while `handle` is locked.
+        let text = decode_text(unsafe { core::slice::from_raw_parts(data, size) });
+
+        // SAFETY: `handle` was locked just above.
+        let _ = unsafe { GlobalUnlock(handle) };
+
+        Ok(Some(text))
+    })
+}
@@ -0,0 +1,354 @@
+//! Tests of the X11 clipboard backend against Xvfb, skipped when Xvfb is not installed.
+
+use core::time::Duration;
+use std::io::{BufRead as _, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
+
+use ironrdp::cliprdr::backend::{ClipboardMessage, ClipboardMessageProxy, CliprdrBackend};
+use ironrdp::cliprdr::pdu::{ClipboardFormat, ClipboardFormatId, FormatDataRequest, FormatDataResponse};
+use ironrdp_cliprdr_native::{DisplayServer, LinuxClipboard, LinuxClipboardConfig};
+use x11rb::connection::Connection as _;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, Property, SelectionNotifyEvent, Window,
+    WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Virtual X11 server, killed when dropped
+struct Xvfb {
+    process: Child,
+    display: String,
+}
+
+impl Xvfb {
+    fn start() -> Option<Self> {
+        let mut process = match Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(process) => process,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(error) => panic!("failed to start Xvfb: {error}"),
+        };
+
+        // The display number is written once the server is ready.
+        let mut display = String::new();
+        BufReader::new(process.stdout.take().expect("stdout"))
+            .read_line(&mut display)
+            .expect("display number");
+
+        Some(Self {
+            process,
+            display: format!(":{}", display.trim()),
+        })
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Forwards the messages of the clipboard to the remote thread, like the session does
+#[derive(Debug)]
+struct RemoteProxy {
+    tx: mpsc::Sender<ClipboardMessage>,
+}
+
+impl ClipboardMessageProxy for RemoteProxy {
+    fn send_clipboard_message(&self, message: ClipboardMessage) {
+        let _ = self.tx.send(message);
+    }
+}
+
+/// Local X11 application
+struct LocalApp {
+    conn: RustConnection,
+    window: Window,
+    clipboard: Atom,
+    targets: Atom,
+    utf8_string: Atom,
+    incr: Atom,
+    property: Atom,
+}
+
+impl LocalApp {
+    fn connect(display: &str) -> Self {
+        let (conn, screen_num) = RustConnection::connect(Some(display)).expect("connect");
+        let root = conn.setup().roots[screen_num].root;
+
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .unwrap();
+
+        let atom = |name: &str| conn.intern_atom(false, name.as_bytes()).unwrap().reply().unwrap().atom;
+        let clipboard = atom("CLIPBOARD");
+        let targets = atom("TARGETS");
+        let utf8_string = atom("UTF8_STRING");
+        let incr = atom("INCR");
+        let property = atom("TEST_SELECTION");
+
+        Self {
+            conn,
+            window,
+            clipboard,
+            targets,
+            utf8_string,
+            incr,
+            property,
+        }
+    }
+
+    fn wait_for_event(&self, deadline: Instant) -> Event {
+        loop {
+            if let Some(event) = self.conn.poll_for_event().unwrap() {
+                return event;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for an X11 event");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn selection_owner(&self, selection: Atom) -> Window {
+        self.conn.get_selection_owner(selection).unwrap().reply().unwrap().owner
+    }
+
+    /// Copies the text, and serves the requests until it is pasted as `UTF8_STRING`.
+    fn copy_and_serve(self, text: &'static str, copied: mpsc::Sender<()>) {
+        self.conn
+            .set_selection_owner(self.window, self.clipboard, CURRENT_TIME)
+            .unwrap();
+        self.conn.flush().unwrap();
+        copied.send(()).unwrap();
+
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let Event::SelectionRequest(request) = self.wait_for_event(deadline) else {
+                continue;
+            };
+
+            let property = if request.target == self.targets {
+                self.conn
+                    .change_property32(
+                        PropMode::REPLACE,
+                        request.requestor,
+                        request.property,
+                        AtomEnum::ATOM,
+                        &[self.targets, self.utf8_string],
+                    )
+                    .unwrap();
+                request.property
+            } else if request.target == self.utf8_string {
+                self.conn
+                    .change_property8(
+                        PropMode::REPLACE,
+                        request.requestor,
+                        request.property,
+                        self.utf8_string,
+                        text.as_bytes(),
+                    )
+                    .unwrap();
+                request.property
+            } else {
+                NONE
+            };
+
+            let notify = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property,
+            };
+            self.conn
+                .send_event(false, request.requestor, EventMask::NO_EVENT, notify)
+                .unwrap();
+            self.conn.flush().unwrap();
+
+            if request.target == self.utf8_string {
+                return;
+            }
+        }
+    }
+
+    /// Pastes the selection as `UTF8_STRING`, receiving it incrementally if needed.
+    fn paste(&self, selection: Atom) -> (Vec<u8>, bool) {
+        self.conn
+            .convert_selection(self.window, selection, self.utf8_string, self.property, CURRENT_TIME)
+            .unwrap();
+        self.conn.flush().unwrap();
+
+        let deadline = Instant::now() + TIMEOUT;
+        let notify = loop {
+            if let Event::SelectionNotify(notify) = self.wait_for_event(deadline) {
+                break notify;
+            }
+        };
+        assert_ne!(notify.property, NONE, "selection conversion refused");
+
+        let reply = self
+            .conn
+            .get_property(true, self.window, self.property, AtomEnum::ANY, 0, u32::MAX)
+            .unwrap()
+            .reply()
+            .unwrap();
+
+        if reply.type_ != self.incr {
+            return (reply.value, false);
+        }
+
+        let mut data = Vec::new();
+        loop {
+            let Event::PropertyNotify(event) = self.wait_for_event(deadline) else {
+                continue;
+            };
+            if event.atom != self.property || event.state != Property::NEW_VALUE {
+                continue;
+            }
+
+            let chunk = self
+                .conn
+                .get_property(true, self.window, self.property, AtomEnum::ANY, 0, u32::MAX)
+                .unwrap()
+                .reply()
+                .unwrap()
+                .value;
+            self.conn.flush().unwrap();
+
+            if chunk.is_empty() {
+                return (data, true);
+            }
+            data.extend_from_slice(&chunk);
+        }
+    }
+}
+
+fn recv_copy(copies: &mpsc::Receiver<Vec<ClipboardFormat>>) -> Vec<ClipboardFormat> {
+    copies.recv_timeout(TIMEOUT).expect("SendInitiateCopy")
+}
+
+#[test]
+fn local_and_remote_copy_paste() {
+    let Some(xvfb) = Xvfb::start() else {
+        // Installed in CI.
+        return;
+    };
+
+    let (proxy_tx, proxy_rx) = mpsc::channel();
+    let clipboard = LinuxClipboard::new(
+        RemoteProxy { tx: proxy_tx },
+        LinuxClipboardConfig {
+            display_server: Some(DisplayServer::X11 {
+                display: Some(xvfb.display.clone()),
+            }),
+            mirror_primary: true,
+        },
+    )
+    .expect("clipboard");
+    let backend: Arc<Mutex<Box<dyn CliprdrBackend>>> =
+        Arc::new(Mutex::new(clipboard.backend_factory().build_cliprdr_backend()));
+
+    // Large enough to be sent incrementally.
+    let remote_text = "remote text\r\n".repeat(100_000);
+
+    let (copies_tx, copies) = mpsc::channel();
+    let (format_data_tx, format_data) = mpsc::channel();
+
+    // Remote side, answering the paste requests of the clipboard.
+    std::thread::spawn({
+        let backend = Arc::clone(&backend);
+        let remote_text = remote_text.clone();
+
+        move || {
+            for message in proxy_rx {
+                match message {
+                    ClipboardMessage::SendInitiateCopy(formats) => {
+                        let _ = copies_tx.send(formats);
+                    }
+                    ClipboardMessage::SendInitiatePaste(format) => {
+                        assert_eq!(format, ClipboardFormatId::CF_UNICODETEXT);
+                        backend
+                            .lock()
+                            .unwrap()
+                            .on_format_data_response(FormatDataResponse::new_unicode_string(&remote_text));
+                    }
+                    ClipboardMessage::SendFormatData(response) => {
+                        let _ = format_data_tx.send(response);
+                    }
+                    ClipboardMessage::Error(error) => panic!("clipboard error: {error}"),
+                }
+            }
+        }
+    });
+
+    // The clipboard is initially empty.
+    assert_eq!(recv_copy(&copies), []);
+
+    // Local copy, then remote paste.
+    let local_app = LocalApp::connect(&xvfb.display);
+    let (ready_tx, ready) = mpsc::channel();
+    let local_app = std::thread::spawn(move || local_app.copy_and_serve("local\ntext", ready_tx));
+    ready.recv_timeout(TIMEOUT).unwrap();
+
+    assert_eq!(
+        recv_copy(&copies),
+        [ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]
+    );
+
+    backend.lock().unwrap().on_format_data_request(FormatDataRequest {
+        format: ClipboardFormatId::CF_UNICODETEXT,
+    });
+    let response = format_data.recv_timeout(TIMEOUT).expect("SendFormatData");
+    assert_eq!(response, FormatDataResponse::new_unicode_string("local\r\ntext"));
+    local_app.join().unwrap();
+
+    // Remote copy, then local paste.
+    let app = LocalApp::connect(&xvfb.display);
+    backend
+        .lock()
+        .unwrap()
+        .on_remote_copy(&[ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)]);
+
+    let deadline = Instant::now() + TIMEOUT;
+    while app.selection_owner(app.clipboard) == NONE || app.selection_owner(app.clipboard) == app.window {
+        assert!(Instant::now() < deadline, "the clipboard did not take the ownership");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        app.selection_owner(AtomEnum::PRIMARY.into()),
+        app.selection_owner(app.clipboard)
+    );
+
+    let (data, incremental) = app.paste(app.clipboard);
+    assert!(incremental);
+    assert_eq!(String::from_utf8(data).unwrap(), remote_text.replace("\r\n", "\n"));
+
+    // Served from the cache.
+    let (data, _) = app.paste(AtomEnum::PRIMARY.into());
+    assert_eq!(data.len(), remote_text.len() - 100_000);
+}
@@ -0,0 +1,286 @@
+//! Scriptable fake RDP server, used to drive the client connector into unusual situations
+//!
+//! The server runs over an in-memory duplex stream and executes a list of [`Step`]s. Unlike the acceptor, it does
+//! not try to make sense of what the client sends past the basic decoding needed by the expectations: it is up to
+//! the script to send the PDUs the client is waiting for, in the right order or not.
+
+use core::time::Duration;
+use std::borrow::Cow;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use anyhow::Context as _;
+use ironrdp::acceptor::ChannelConnectionSequence;
+use ironrdp::connector::{self, ConnectionResult, ConnectorResult};
+use ironrdp::core::{decode, encode_vec, WriteBuf};
+use ironrdp::pdu::rdp::server_license::{LicensePdu, LicensingErrorMessage};
+use ironrdp::pdu::x224::{X224Data, X224};
+use ironrdp::pdu::{gcc, mcs, nego, rdp};
+use ironrdp_async::bytes::BytesMut;
+use ironrdp_async::FramedWrite as _;
+use ironrdp_tokio::TokioFramed;
+use tokio::io::DuplexStream;
+use tracing::debug;
+
+pub(crate) const USER_CHANNEL_ID: u16 = 1002;
+pub(crate) const IO_CHANNEL_ID: u16 = 1003;
+
+/// Action of the fake server
+#[derive(Debug, Clone)]
+pub(crate) enum Step {
+    /// Waits for the X.224 Connection Request PDU.
+    ExpectX224Request,
+    /// Sends a X.224 Connection Confirm PDU selecting `protocol`.
+    SendX224Confirm {
+        flags: nego::ResponseFlags,
+        protocol: nego::SecurityProtocol,
+    },
+    /// Waits for the MCS Connect Initial PDU.
+    ExpectMcsConnectInitial,
+    /// Sends a MCS Connect Response PDU assigning IDs to the channels requested in the MCS Connect Initial PDU.
+    SendMcsConnectResponse {
+        security: gcc::ServerSecurityData,
+    },
+    /// Answers the MCS Erect Domain, Attach User and Channel Join requests of the client.
+    JoinChannels,
+    /// Waits for the Client Info PDU.
+    ExpectClientInfo,
+    /// Sends a licensing error message with the `STATUS_VALID_CLIENT` code, ending the licensing exchange.
+    SendLicenseValidClient,
+    /// Sends a Demand Active PDU with the given number of capability sets, followed by their raw encoding.
+    SendCapabilities {
+        count: u16,
+        data: Vec<u8>,
+    },
+    /// Sends an already encoded PDU, as is.
+    SendPdu(Vec<u8>),
+    Delay(Duration),
+    /// Closes the connection, without going through any disconnection sequence.
+    CloseAbruptly,
+}
+
+impl Step {
+    /// Steps answering the client until the licensing exchange is complete, the security upgrade being skipped.
+    pub(crate) fn until_capabilities() -> Vec<Self> {
+        vec![
+            Self::ExpectX224Request,
+            Self::SendX224Confirm {
+                flags: nego::ResponseFlags::empty(),
+                protocol: nego::SecurityProtocol::SSL,
+            },
+            Self::ExpectMcsConnectInitial,
+            Self::SendMcsConnectResponse {
+                security: gcc::ServerSecurityData::no_security(),
+            },
+            Self::JoinChannels,
+            Self::ExpectClientInfo,
+            Self::SendLicenseValidClient,
+        ]
+    }
+}
+
+pub(crate) struct FakeRdpServer {
+    script: Vec<Step>,
+    requested_protocol: nego::SecurityProtocol,
+    channel_ids: Vec<u16>,
+    skip_channel_join: bool,
+}
+
+impl FakeRdpServer {
+    pub(crate) fn new(script: Vec<Step>) -> Self {
+        Self {
+            script,
+            requested_protocol: nego::SecurityProtocol::empty(),
+            channel_ids: Vec::new(),
+            skip_channel_join: false,
+        }
+    }
+
+    /// Executes the script, and then keeps the connection open until the client closes it.
+    pub(crate) async fn run(mut self, stream: DuplexStream) -> anyhow::Result<()> {
+        let mut framed = TokioFramed::new(stream);
+
+        for step in core::mem::take(&mut self.script) {
+            debug!(?step, "Fake server step");
+
+            match step {
+                Step::ExpectX224Request => {
+                    let frame = read_frame(&mut framed).await?;
+                    let request = decode::<X224<nego::ConnectionRequest>>(&frame)?.0;
+                    self.requested_protocol = request.protocol;
+                }
+                Step::SendX224Confirm { flags, protocol } => {
+                    let confirm = X224(nego::ConnectionConfirm::Response { flags, protocol });
+                    framed.write_all(&encode_vec(&confirm)?).await?;
+                }
+                Step::ExpectMcsConnectInitial => {
+                    let frame = read_frame(&mut framed).await?;
+                    let data = decode::<X224<X224Data<'_>>>(&frame)?.0;
+                    let initial = decode::<mcs::ConnectInitial>(data.data.as_ref())?;
+                    let gcc_blocks = initial.conference_create_request.gcc_blocks;
+
+                    let channel_count = gcc_blocks.network.map_or(0, |network| network.channels.len());
+                    self.channel_ids = (IO_CHANNEL_ID + 1..).take(channel_count).collect();
+                    self.skip_channel_join = gcc_blocks
+                        .core
+                        .optional_data
+                        .early_capability_flags
+                        .is_some_and(|flags| flags.contains(gcc::ClientEarlyCapabilityFlags::SUPPORT_SKIP_CHANNELJOIN));
+                }
+                Step::SendMcsConnectResponse { security } => {
+                    let response = mcs::ConnectResponse {
+                        conference_create_response: gcc::ConferenceCreateResponse {
+                            user_id: USER_CHANNEL_ID,
+                            gcc_blocks: gcc::ServerGccBlocks {
+                                core: gcc::ServerCoreData {
+                                    version: gcc::RdpVersion::V5_PLUS,
+                                    optional_data: gcc::ServerCoreOptionalData {
+                                        client_requested_protocols: Some(self.requested_protocol),
+                                        early_capability_flags: self
+                                            .skip_channel_join
+                                            .then_some(gcc::ServerEarlyCapabilityFlags::SKIP_CHANNELJOIN_SUPPORTED),
+                                    },
+                                },
+                                security,
+                                network: gcc::ServerNetworkData {
+                                    channel_ids: self.channel_ids.clone(),
+                                    io_channel: IO_CHANNEL_ID,
+                                },
+                                message_channel: None,
+                                multi_transport_channel: None,
+                                unknown: Vec::new(),
+                            },
+                        },
+                        called_connect_id: 1,
+                        domain_parameters: mcs::DomainParameters::target(),
+                    };
+
+                    let mut buf = WriteBuf::new();
+                    connector::encode_x224_packet(&response, &mut buf)?;
+                    framed.write_all(buf.filled()).await?;
+                }
+                Step::JoinChannels => {
+                    let mut sequence = if self.skip_channel_join {
+                        ChannelConnectionSequence::skip_channel_join(USER_CHANNEL_ID)
+                    } else {
+                        ChannelConnectionSequence::new(USER_CHANNEL_ID, IO_CHANNEL_ID, self.channel_ids.clone())
+                    };
+
+                    let mut buf = WriteBuf::new();
+                    while !sequence.is_done() {
+                        ironrdp_async::single_sequence_step(&mut framed, &mut sequence, &mut buf).await?;
+                    }
+                }
+                Step::ExpectClientInfo => {
+                    let frame = read_frame(&mut framed).await?;
+                    let request = decode::<X224<mcs::SendDataRequest<'_>>>(&frame)?.0;
+                    decode::<rdp::ClientInfoPdu>(request.user_data.as_ref())?;
+                }
+                Step::SendLicenseValidClient => {
+                    let license = LicensePdu::from(LicensingErrorMessage::new_valid_client()?);
+                    framed.write_all(&send_data_indication(encode_vec(&license)?)?).await?;
+                }
+                Step::SendCapabilities { count, data } => {
+                    framed
+                        .write_all(&send_data_indication(demand_active(count, &data)?)?)
+                        .await?;
+                }
+                Step::SendPdu(pdu) => framed.write_all(&pdu).await?,
+                Step::Delay(duration) => tokio::time::sleep(duration).await,
+                Step::CloseAbruptly => return Ok(()),
+            }
+        }
+
+        // Wait for the client to give up.
+        let mut buf = [0; 1024];
+        let (stream, _) = framed.get_inner_mut();
+        while tokio::io::AsyncReadExt::read(stream, &mut buf).await? != 0 {}
+
+        Ok(())
+    }
+}
+
+/// Connects a client with `config` (without CredSSP nor security upgrade) to a fake server running `script`.
+///
+/// Returns the result of the connection, along with the result of the script.
+pub(crate) async fn connect(
+    config: connector::Config,
+    script: Vec<Step>,
+) -> (ConnectorResult<ConnectionResult>, anyhow::Result<()>) {
+    let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+
+    let server = FakeRdpServer::new(script).run(server_stream);
+
+    let client = async move {
+        let mut framed = TokioFramed::new(client_stream);
+        let mut connector =
+            connector::ClientConnector::new(config).with_server_addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 3389)));
+        let mut timer = ironrdp_async::ConnectTimer::new(ironrdp_tokio::TokioTimer);
+
+        let should_upgrade = ironrdp_async::connect_begin(&mut framed, &mut connector, &mut timer).await?;
+        let upgraded = ironrdp_async::mark_as_upgraded(should_upgrade, &mut connector);
+
+        ironrdp_async::connect_finalize(
+            upgraded,
+            &mut framed,
+            connector,
+            &mut timer,
+            "localhost".into(),
+            Vec::new(),
+            None,
+            None,
+        )
+        .await
+        // The client stream is dropped here, letting the server finish.
+    };
+
+    tokio::join!(client, server)
+}
+
+async fn read_frame(framed: &mut TokioFramed<DuplexStream>) -> anyhow::Result<BytesMut> {
+    let (_, frame) = framed.read_pdu().await.context("read frame")?;
+    Ok(frame)
+}
+
+/// Wraps `user_data` in a MCS Send Data Indication PDU, sent on the I/O channel.
+fn send_data_indication(user_data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let indication = X224(mcs::SendDataIndication {
+        initiator_id: USER_CHANNEL_ID,
+        channel_id: IO_CHANNEL_ID,
+        user_data: Cow::Owned(user_data),
+    });
+
+    Ok(encode_vec(&indication)?)
+}
+
+/// Encodes a Demand Active PDU carrying `count` capability sets, `data` being their raw encoding.
+fn demand_active(count: u16, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    // PDUTYPE_DEMANDACTIVEPDU, with the protocol version.
+    const PDU_TYPE: u16 = 0x0011;
+    const SHARE_ID: u32 = 0x0001_03ea;
+    // Source descriptor, as sent by Windows servers.
+    const SOURCE_DESCRIPTOR: &[u8] = b"RDP\0";
+    // Share control header, share ID, source descriptor and combined capabilities lengths.
+    const HEADER_SIZE: usize = 6 + 4 + 2 + 2;
+    // Number of capability sets and padding.
+    const COMBINED_CAPABILITIES_HEADER_SIZE: usize = 4;
+    // Session ID.
+    const TRAILER_SIZE: usize = 4;
+
+    let combined_capabilities_len = COMBINED_CAPABILITIES_HEADER_SIZE + data.len();
+    let total_len = HEADER_SIZE + SOURCE_DESCRIPTOR.len() + combined_capabilities_len + TRAILER_SIZE;
+
+    let mut pdu = Vec::with_capacity(total_len);
+    pdu.extend_from_slice(&u16::try_from(total_len)?.to_le_bytes());
+    pdu.extend_from_slice(&PDU_TYPE.to_le_bytes());
+    pdu.extend_from_slice(&IO_CHANNEL_ID.to_le_bytes());
+    pdu.extend_from_slice(&SHARE_ID.to_le_bytes());
+    pdu.extend_from_slice(&u16::try_from(SOURCE_DESCRIPTOR.len())?.to_le_bytes());
+    pdu.extend_from_slice(&u16::try_from(combined_capabilities_len)?.to_le_bytes());
+    pdu.extend_from_slice(SOURCE_DESCRIPTOR);
+    pdu.extend_from_slice(&count.to_le_bytes());
+    pdu.extend_from_slice(&[0; 2]);
+    pdu.extend_from_slice(data);
+    pdu.extend_from_slice(&0u32.to_le_bytes());
+
+    Ok(pdu)
+}
@@ -0,0 +1,134 @@
+//! Rate limiting, clamping and filtering of the input events received by the server
+
+use core::time::Duration;
+use std::time::Instant;
+
+use ironrdp::server::{DesktopSize, InputFilter, InputPolicy, KeyAllowList, KeyboardEvent, MouseEvent, TokenBucket};
+
+use super::{DESKTOP_HEIGHT, DESKTOP_WIDTH};
+
+#[test]
+fn token_bucket_allows_burst_then_refills() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(2);
+
+    assert!(bucket.try_acquire(start));
+    assert!(bucket.try_acquire(start));
+    assert!(!bucket.try_acquire(start));
+
+    // Refills at 2 tokens per second.
+    assert!(bucket.try_acquire(start + Duration::from_millis(500)));
+    assert!(!bucket.try_acquire(start + Duration::from_millis(500)));
+
+    // The bucket never holds more than one second worth of tokens.
+    let later = start + Duration::from_secs(10);
+    assert!(bucket.try_acquire(later));
+    assert!(bucket.try_acquire(later));
+    assert!(!bucket.try_acquire(later));
+}
+
+#[test]
+fn input_filter_rate_limits_per_event_class() {
+    let now = Instant::now();
+    let mut filter = InputFilter::new(InputPolicy {
+        max_events_per_second: Some(1),
+        ..InputPolicy::default()
+    });
+
+    assert!(filter.filter_mouse(MouseEvent::LeftPressed, now).is_some());
+    assert!(filter.filter_mouse(MouseEvent::Move { x: 1, y: 1 }, now).is_none());
+
+    // Releases are forwarded even once the bucket is exhausted, so that no button is left pressed.
+    assert!(filter.filter_mouse(MouseEvent::LeftReleased, now).is_some());
+
+    // Keyboard events have their own bucket.
+    let key_a = |pressed: bool| {
+        if pressed {
+            KeyboardEvent::Pressed {
+                code: 0x1e,
+                extended: false,
+            }
+        } else {
+            KeyboardEvent::Released {
+                code: 0x1e,
+                extended: false,
+            }
+        }
+    };
+    assert!(filter.filter_keyboard(key_a(true), now).is_some());
+    assert!(filter.filter_keyboard(key_a(true), now).is_none());
+    assert!(filter.filter_keyboard(key_a(false), now).is_some());
+    assert!(filter
+        .filter_keyboard(KeyboardEvent::UnicodeReleased(0x41), now)
+        .is_some());
+
+    let stats = filter.stats();
+    assert_eq!(stats.mouse_rate_limited, 1);
+    assert_eq!(stats.keyboard_rate_limited, 1);
+    assert_eq!(stats.dropped(), 2);
+}
+
+#[test]
+fn input_filter_clamps_mouse_at_desktop_edge() {
+    let now = Instant::now();
+    let mut filter = InputFilter::new(InputPolicy {
+        clamp_mouse_to_desktop: true,
+        ..InputPolicy::default()
+    });
+    filter.set_desktop_size(DesktopSize {
+        width: DESKTOP_WIDTH,
+        height: DESKTOP_HEIGHT,
+    });
+
+    let event = filter.filter_mouse(MouseEvent::Move { x: 1023, y: 767 }, now);
+    assert!(matches!(event, Some(MouseEvent::Move { x: 1023, y: 767 })));
+    assert_eq!(filter.stats().mouse_clamped, 0);
+
+    let event = filter.filter_mouse(MouseEvent::Move { x: 1024, y: u16::MAX }, now);
+    assert!(matches!(event, Some(MouseEvent::Move { x: 1023, y: 767 })));
+    assert_eq!(filter.stats().mouse_clamped, 1);
+
+    // After a resize, the new desktop size is used.
+    filter.set_desktop_size(DesktopSize {
+        width: 800,
+        height: 600,
+    });
+    let event = filter.filter_mouse(MouseEvent::Move { x: 1023, y: 767 }, now);
+    assert!(matches!(event, Some(MouseEvent::Move { x: 799, y: 599 })));
+}
+
+#[test]
+fn input_filter_blocks_keys() {
+    let now = Instant::now();
+    let mut filter = InputFilter::new(InputPolicy {
+        allowed_keys: Some(KeyAllowList::new().with_key(0x1e, false)),
+        block_unicode: true,
+        ..InputPolicy::default()
+    });
+
+    assert!(filter
+        .filter_keyboard(
+            KeyboardEvent::Pressed {
+                code: 0x1e,
+                extended: false
+            },
+            now
+        )
+        .is_some());
+    assert!(filter
+        .filter_keyboard(
+            KeyboardEvent::Pressed {
+                code: 0x1e,
+                extended: true
+            },
+            now
+        )
+        .is_none());
+    assert!(filter
+        .filter_keyboard(KeyboardEvent::UnicodePressed(0x41), now)
+        .is_none());
+
+    let stats = filter.stats();
+    assert_eq!(stats.keys_blocked, 1);
+    assert_eq!(stats.unicode_blocked, 1);
+}
@@ -1,165 +1,2238 @@
 #![allow(unused_crate_dependencies)] // false positives because there is both a library and a binary
 
+use core::any::TypeId;
 use core::future::Future;
+use core::num::NonZeroU16;
+use core::time::Duration;
+use std::borrow::Cow;
+use std::io;
+use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
-use ironrdp::connector;
-use ironrdp::pdu::rdp::capability_sets::MajorPlatformType;
-use ironrdp::pdu::{self, gcc};
+use ironrdp::cliprdr::backend::{CliprdrBackend, CliprdrBackendFactory};
+use ironrdp::cliprdr::pdu::{
+    ClipboardFormat, ClipboardFormatId, ClipboardGeneralCapabilityFlags, FileContentsRequest, FileContentsResponse,
+    FormatDataRequest, FormatDataResponse, LockDataId,
+};
+use ironrdp::cliprdr::CliprdrClient;
+use ironrdp::connector::connection_activation::ConnectionActivationSequence;
+use ironrdp::connector::{self, ConnectionResult};
+use ironrdp::core::{decode, encode_vec, impl_as_any, Encode as _, WriteBuf};
+use ironrdp::dvc::pdu::{CreateRequestPdu, DataPdu, DrdynvcDataPdu, DrdynvcServerPdu};
+use ironrdp::dvc::{DrdynvcClient, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor};
+use ironrdp::pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
+use ironrdp::pdu::rdp::capability_sets::{InputFlags, MajorPlatformType};
+use ironrdp::pdu::rdp::client_info::CompressionType;
+use ironrdp::pdu::rdp::finalization_messages::MonitorLayoutPdu;
+use ironrdp::pdu::rdp::headers::{
+    CompressionFlags, ShareControlHeader, ShareControlPdu, ShareDataHeader, ShareDataPdu, StreamPriority,
+};
+use ironrdp::pdu::x224::X224;
+use ironrdp::pdu::{self, gcc, mcs};
+use ironrdp::server::tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use ironrdp::server::tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use ironrdp::server::tokio_rustls::rustls::{self, DigitallySignedStruct, SignatureScheme};
+use ironrdp::server::tokio_rustls::TlsConnector;
 use ironrdp::server::{
-    self, DesktopSize, DisplayUpdate, KeyboardEvent, MouseEvent, PixelFormat, RdpServer, RdpServerDisplay,
-    RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent, TlsIdentityCtx,
+    self, AttachPolicy, BitmapUpdate, DesktopSize, DisplayUpdate, HandshakeLimiter, HandshakeLimits, HandshakeStats,
+    InputEvent, KeyboardEvent, KeyboardInfo, KeyboardSync, LockKeyTracker, MouseEvent, PixelFormat, PixelOrder,
+    RdpServer, RdpServerDisplay, RdpServerDisplayUpdates, RdpServerInputHandler, ServerEvent, TlsIdentityCtx,
 };
 use ironrdp::session::image::DecodedImage;
-use ironrdp::session::{self, ActiveStage, ActiveStageOutput};
-use ironrdp_async::{Framed, FramedWrite};
+use ironrdp::session::stats::ChannelStats;
+use ironrdp::session::{ActiveStage, ActiveStageOutput};
+use ironrdp::svc::{
+    StaticChannelSet, StaticVirtualChannel, SvcClientProcessor, SvcMessage, SvcProcessor, SvcProcessorMessages,
+};
+use ironrdp_async::{
+    FramedWrite, PduLogReader, PduLogWriter, SendError, SessionDriver, TraceDirection, TraceEvent, WriterTask,
+    DEFAULT_OUTBOUND_CAPACITY,
+};
+use ironrdp_futures::{ChunkedStream, LocalFuturesFramed};
+use ironrdp_rdcleanpath::RDCleanPathPdu;
 use ironrdp_testsuite_extra as _;
-use ironrdp_tls::TlsStream;
-use ironrdp_tokio::TokioStream;
+use ironrdp_tokio::{
+    split_tokio_framed, unsplit_tokio_framed, ConnectError, ConnectOptions, ErasedStream, RdCleanPathProxy,
+    TokioFramed, TokioStream,
+};
+use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::sync::{oneshot, Mutex};
 use tracing::debug;
 
-const DESKTOP_WIDTH: u16 = 1024;
-const DESKTOP_HEIGHT: u16 = 768;
-const USERNAME: &str = "";
-const PASSWORD: &str = "";
+mod chunked_stream;
+#[cfg(any(windows, target_os = "linux"))]
+mod cliprdr_native;
+mod fake_server;
+mod input_policy;
+
+use chunked_stream::{mock_recv_stream, MockSendStream};
+use fake_server::Step;
+
+const DESKTOP_WIDTH: u16 = 1024;
+const DESKTOP_HEIGHT: u16 = 768;
+const USERNAME: &str = "";
+const PASSWORD: &str = "";
+
+type UpgradedFramed = TokioFramed<ErasedStream>;
+
+#[tokio::test]
+async fn test_client_server() {
+    client_server(default_client_config(), |stage, framed, _display_tx| async {
+        (stage, framed)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_deactivation_reactivation() {
+    let client_config = default_client_config();
+    let mut image = DecodedImage::new(
+        PixelFormat::RgbA32,
+        client_config.desktop_size.width,
+        client_config.desktop_size.height,
+    );
+    let (remote_copy_tx, mut remote_copy_rx) = mpsc::unbounded_channel();
+    let cliprdr_factory = TestCliprdrFactory { remote_copy_tx };
+
+    client_server_with_cliprdr(
+        client_config,
+        Some(Box::new(cliprdr_factory)),
+        |mut stage, mut framed, display_tx| async move {
+            // Initialize the clipboard channel before the server deactivates the session.
+            process_until(&mut stage, &mut framed, &mut image, |stage| {
+                client_cliprdr_backend(stage).monitor_ready
+            })
+            .await;
+            let messages = stage
+                .get_svc_processor_mut::<CliprdrClient>()
+                .unwrap()
+                .initiate_copy(&[])
+                .unwrap();
+            let frame = stage.process_svc_processor_messages(messages).unwrap();
+            framed.write_all(&frame).await.unwrap();
+            assert!(remote_copy_rx.recv().await.unwrap().is_empty());
+
+            display_tx
+                .send(DisplayUpdate::Resize(DesktopSize {
+                    width: 2048,
+                    height: 2048,
+                }))
+                .unwrap();
+
+            let new_desktop_size = process_until_deactivation_reactivation(&mut stage, &mut framed, &mut image).await;
+            assert_eq!((new_desktop_size.width, new_desktop_size.height), (2048, 2048));
+            assert_eq!((image.width(), image.height()), (2048, 2048));
+
+            // The clipboard channel keeps working without being initialized again.
+            process_until(&mut stage, &mut framed, &mut image, |stage| {
+                client_cliprdr_backend(stage).format_list_received
+            })
+            .await;
+            let formats = [ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT)];
+            let messages = stage
+                .get_svc_processor_mut::<CliprdrClient>()
+                .unwrap()
+                .initiate_copy(&formats)
+                .unwrap();
+            let frame = stage.process_svc_processor_messages(messages).unwrap();
+            framed.write_all(&frame).await.unwrap();
+
+            let remote_copy = tokio::time::timeout(Duration::from_secs(5), remote_copy_rx.recv())
+                .await
+                .expect("format list received by the server")
+                .unwrap();
+            assert_eq!(remote_copy, formats);
+
+            (stage, framed)
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_view_only_client_attaches_to_session() {
+    const FRAME_COLOR: [u8; 3] = [0x10, 0x20, 0x30];
+    const DIFF_COLOR: [u8; 3] = [0xc0, 0xb0, 0xa0];
+
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let cert_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-cert.pem");
+    let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-key.pem");
+    let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
+    let acceptor = identity.make_acceptor().expect("failed to build TLS acceptor");
+
+    let (display_tx, display_rx) = mpsc::unbounded_channel();
+    let (keyboard_tx, mut keyboard_rx) = mpsc::unbounded_channel();
+    let mut server = RdpServer::builder()
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_tls(acceptor)
+        .with_input_handler(RecordingInputHandler { keyboard_tx })
+        .with_display_handler(TestDisplay {
+            rx: Arc::new(Mutex::new(display_rx)),
+        })
+        .with_shadowing(AttachPolicy::ViewOnly)
+        .build();
+    server.set_credentials(Some(server::Credentials {
+        username: USERNAME.into(),
+        password: PASSWORD.into(),
+        domain: None,
+    }));
+    let ev = server.event_sender().clone();
+    let attached_clients = server.attached_clients().clone();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let addr = rx.await.unwrap().unwrap();
+
+                let (mut primary, mut primary_framed) = connect_client(addr, default_client_config(), false).await;
+                let mut primary_image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+                display_tx
+                    .send(solid_bitmap(0, 0, DESKTOP_WIDTH, DESKTOP_HEIGHT, FRAME_COLOR))
+                    .unwrap();
+                process_until_image(&mut primary, &mut primary_framed, &mut primary_image, |image| {
+                    is_filled(image, 0, 0, DESKTOP_WIDTH, DESKTOP_HEIGHT, FRAME_COLOR)
+                })
+                .await;
+
+                // The viewer attaching to the session receives the whole display, then the following updates.
+                let (mut viewer, mut viewer_framed) = connect_client(addr, default_client_config(), false).await;
+                let mut viewer_image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+                process_until_image(&mut viewer, &mut viewer_framed, &mut viewer_image, |image| {
+                    is_filled(image, 0, 0, DESKTOP_WIDTH, DESKTOP_HEIGHT, FRAME_COLOR)
+                })
+                .await;
+                display_tx.send(solid_bitmap(0, 0, 64, 64, DIFF_COLOR)).unwrap();
+                process_until_image(&mut viewer, &mut viewer_framed, &mut viewer_image, |image| {
+                    is_filled(image, 0, 0, 64, 64, DIFF_COLOR)
+                })
+                .await;
+
+                let clients = attached_clients.list();
+                assert_eq!(clients.len(), 2);
+                assert!(clients[0].primary);
+                assert_eq!(clients[1].policy, AttachPolicy::ViewOnly);
+
+                // Only the input of the primary client reaches the input handler.
+                for (stage, framed, image, code) in [
+                    (&mut viewer, &mut viewer_framed, &mut viewer_image, 0x10),
+                    (&mut primary, &mut primary_framed, &mut primary_image, 0x20),
+                ] {
+                    let events = [FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), code)];
+                    for out in stage.process_fastpath_input(image, &events).expect("input") {
+                        if let ActiveStageOutput::ResponseFrame(frame) = out {
+                            framed.write_all(&frame).await.expect("write frame");
+                        }
+                    }
+                }
+                let event = tokio::time::timeout(Duration::from_secs(5), keyboard_rx.recv())
+                    .await
+                    .expect("primary input received by the server")
+                    .unwrap();
+                assert!(matches!(event, KeyboardEvent::Pressed { code: 0x20, .. }));
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                assert!(keyboard_rx.try_recv().is_err());
+
+                assert!(attached_clients.disconnect(clients[1].id));
+                tokio::time::timeout(Duration::from_secs(5), async {
+                    while viewer_framed.read_pdu().await.is_ok() {}
+                })
+                .await
+                .expect("viewer disconnected");
+                assert_eq!(attached_clients.list().len(), 1);
+
+                for out in primary.graceful_shutdown().expect("shutdown") {
+                    if let ActiveStageOutput::ResponseFrame(frame) = out {
+                        primary_framed.write_all(&frame).await.expect("write frame");
+                    }
+                }
+                while primary_framed.read_pdu().await.is_ok() {}
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn frame_acknowledgements_and_input_are_timed() {
+    const FRAME_COLOR: [u8; 3] = [0x40, 0x80, 0xc0];
+
+    let cert_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-cert.pem");
+    let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-key.pem");
+    let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
+    let acceptor = identity.make_acceptor().expect("failed to build TLS acceptor");
+
+    let (display_tx, display_rx) = mpsc::unbounded_channel();
+    let (keyboard_tx, mut keyboard_rx) = mpsc::unbounded_channel();
+    let mut server = RdpServer::builder()
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_tls(acceptor)
+        .with_input_handler(TimedInputHandler { keyboard_tx })
+        .with_display_handler(TestDisplay {
+            rx: Arc::new(Mutex::new(display_rx)),
+        })
+        .with_metrics(16)
+        .build();
+    server.set_credentials(Some(server::Credentials {
+        username: USERNAME.into(),
+        password: PASSWORD.into(),
+        domain: None,
+    }));
+    let ev = server.event_sender().clone();
+    let metrics = server.metrics().expect("metrics enabled").clone();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let addr = rx.await.unwrap().unwrap();
+
+                let (mut stage, mut framed) = connect_client(addr, default_client_config(), false).await;
+                let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+                display_tx.send(solid_bitmap(0, 0, 64, 64, FRAME_COLOR)).unwrap();
+                process_until_image(&mut stage, &mut framed, &mut image, |image| {
+                    is_filled(image, 0, 0, 64, 64, FRAME_COLOR)
+                })
+                .await;
+
+                let sent_at = Instant::now();
+                let events = [FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x20)];
+                for out in stage.process_fastpath_input(&mut image, &events).expect("input") {
+                    if let ActiveStageOutput::ResponseFrame(frame) = out {
+                        framed.write_all(&frame).await.expect("write frame");
+                    }
+                }
+                let event = tokio::time::timeout(Duration::from_secs(5), keyboard_rx.recv())
+                    .await
+                    .expect("input received by the server")
+                    .unwrap();
+                assert!(matches!(event.event, KeyboardEvent::Pressed { code: 0x20, .. }));
+                assert!(event.received_at >= sent_at);
+
+                // The acknowledgement was sent along with the processing of the frame.
+                let snapshot = metrics.snapshot();
+                assert_eq!(snapshot.frames_sent, 1);
+                assert_eq!(snapshot.frames_acknowledged, 1);
+                assert_eq!(snapshot.unmatched_acknowledgements, 0);
+                assert!(snapshot.encode.is_some());
+                assert!(snapshot.round_trip.is_some());
+
+                for out in stage.graceful_shutdown().expect("shutdown") {
+                    if let ActiveStageOutput::ResponseFrame(frame) = out {
+                        framed.write_all(&frame).await.expect("write frame");
+                    }
+                }
+                while framed.read_pdu().await.is_ok() {}
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn keyboard_layout_and_events_reach_the_input_handler() {
+    const FRAME_COLOR: [u8; 3] = [0x20, 0x40, 0x60];
+
+    let cert_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-cert.pem");
+    let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-key.pem");
+    let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
+    let acceptor = identity.make_acceptor().expect("failed to build TLS acceptor");
+
+    let (display_tx, display_rx) = mpsc::unbounded_channel();
+    let (callbacks_tx, mut callbacks_rx) = mpsc::unbounded_channel();
+    let mut server = RdpServer::builder()
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_tls(acceptor)
+        .with_input_handler(KeyboardInputHandler { callbacks_tx })
+        .with_display_handler(TestDisplay {
+            rx: Arc::new(Mutex::new(display_rx)),
+        })
+        .build();
+    server.set_credentials(Some(server::Credentials {
+        username: USERNAME.into(),
+        password: PASSWORD.into(),
+        domain: None,
+    }));
+    let ev = server.event_sender().clone();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let addr = rx.await.unwrap().unwrap();
+
+                let mut config = default_client_config();
+                config.keyboard_layout = 0x0409;
+                config.keyboard_subtype = 2;
+
+                let (mut stage, mut framed) = connect_client(addr, config, false).await;
+                let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+                display_tx.send(solid_bitmap(0, 0, 64, 64, FRAME_COLOR)).unwrap();
+                process_until_image(&mut stage, &mut framed, &mut image, |image| {
+                    is_filled(image, 0, 0, 64, 64, FRAME_COLOR)
+                })
+                .await;
+
+                let events = [
+                    FastPathInputEvent::SyncEvent(SynchronizeFlags::NUM_LOCK | SynchronizeFlags::CAPS_LOCK),
+                    FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0x00e9),
+                    FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0x00e9),
+                    FastPathInputEvent::KeyboardEvent(KeyboardFlags::EXTENDED, 0x1d),
+                ];
+                for out in stage.process_fastpath_input(&mut image, &events).expect("input") {
+                    if let ActiveStageOutput::ResponseFrame(frame) = out {
+                        framed.write_all(&frame).await.expect("write frame");
+                    }
+                }
+
+                let mut callbacks = Vec::new();
+                while callbacks.len() < 5 {
+                    let callback = tokio::time::timeout(Duration::from_secs(5), callbacks_rx.recv())
+                        .await
+                        .expect("keyboard callback")
+                        .unwrap();
+                    callbacks.push(callback);
+                }
+
+                let KeyboardCallback::Info(info) = &callbacks[0] else {
+                    panic!("unexpected callback: {:?}", callbacks[0]);
+                };
+                assert_eq!(info.layout, 0x0409);
+                assert_eq!(info.keyboard_type, gcc::KeyboardType::IbmEnhanced);
+                assert_eq!(info.subtype, 2);
+                assert_eq!(info.functional_keys_count, 12);
+
+                assert!(matches!(
+                    callbacks[1],
+                    KeyboardCallback::Sync(KeyboardSync {
+                        scroll_lock: false,
+                        num_lock: true,
+                        caps_lock: true,
+                        kana_lock: false,
+                    })
+                ));
+                assert!(matches!(
+                    callbacks[2],
+                    KeyboardCallback::Event(KeyboardEvent::UnicodePressed(0x00e9))
+                ));
+                assert!(matches!(
+                    callbacks[3],
+                    KeyboardCallback::Event(KeyboardEvent::UnicodeReleased(0x00e9))
+                ));
+                assert!(matches!(
+                    callbacks[4],
+                    KeyboardCallback::Event(KeyboardEvent::Pressed {
+                        code: 0x1d,
+                        extended: true
+                    })
+                ));
+
+                for out in stage.graceful_shutdown().expect("shutdown") {
+                    if let ActiveStageOutput::ResponseFrame(frame) = out {
+                        framed.write_all(&frame).await.expect("write frame");
+                    }
+                }
+                while framed.read_pdu().await.is_ok() {}
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
+/// Returns a display update filling the given rectangle with `rgb`.
+fn solid_bitmap(left: u16, top: u16, width: u16, height: u16, [r, g, b]: [u8; 3]) -> DisplayUpdate {
+    let stride = usize::from(width) * 4;
+    DisplayUpdate::Bitmap(BitmapUpdate {
+        top,
+        left,
+        width: NonZeroU16::new(width).unwrap(),
+        height: NonZeroU16::new(height).unwrap(),
+        format: PixelFormat::BgrA32,
+        order: PixelOrder::TopToBottom,
+        data: [b, g, r, 0xff].repeat(usize::from(width) * usize::from(height)),
+        stride,
+    })
+}
+
+/// Returns whether the given rectangle of `image` is filled with `rgb`, within the loss of the codec.
+fn is_filled(image: &DecodedImage, left: u16, top: u16, width: u16, height: u16, rgb: [u8; 3]) -> bool {
+    let stride = usize::from(image.width()) * 4;
+    (usize::from(top)..usize::from(top + height)).all(|row| {
+        let start = row * stride + usize::from(left) * 4;
+        image.data()[start..start + usize::from(width) * 4]
+            .chunks_exact(4)
+            .all(|pixel| {
+                pixel
+                    .iter()
+                    .zip(rgb)
+                    .all(|(value, expected)| value.abs_diff(expected) <= 8)
+            })
+    })
+}
+
+/// Processes the PDUs received by the client until `condition` holds for the image.
+async fn process_until_image(
+    stage: &mut ActiveStage,
+    framed: &mut UpgradedFramed,
+    image: &mut DecodedImage,
+    condition: impl Fn(&DecodedImage) -> bool,
+) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !condition(image) {
+            let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+            for out in stage.process(image, action, &payload).expect("stage process") {
+                if let ActiveStageOutput::ResponseFrame(frame) = out {
+                    framed.write_all(&frame).await.expect("write frame");
+                }
+            }
+        }
+    })
+    .await
+    .expect("image updated");
+}
+
+/// Processes the PDUs received by the client until the Deactivation-Reactivation Sequence is completed.
+async fn process_until_deactivation_reactivation(
+    stage: &mut ActiveStage,
+    framed: &mut UpgradedFramed,
+    image: &mut DecodedImage,
+) -> DesktopSize {
+    loop {
+        let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+        for out in stage.process(image, action, &payload).expect("stage process") {
+            match out {
+                ActiveStageOutput::ResponseFrame(frame) => framed.write_all(&frame).await.expect("write frame"),
+                ActiveStageOutput::DeactivationReactivation { new_desktop_size } => return new_desktop_size,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Processes the PDUs received by the client until `condition` holds.
+async fn process_until(
+    stage: &mut ActiveStage,
+    framed: &mut UpgradedFramed,
+    image: &mut DecodedImage,
+    condition: impl Fn(&mut ActiveStage) -> bool,
+) {
+    while !condition(stage) {
+        let (action, payload) = framed.read_pdu().await.expect("valid PDU");
+        for out in stage.process(image, action, &payload).expect("stage process") {
+            if let ActiveStageOutput::ResponseFrame(frame) = out {
+                framed.write_all(&frame).await.expect("write frame");
+            }
+        }
+    }
+}
+
+fn client_cliprdr_backend(stage: &mut ActiveStage) -> &TestCliprdrBackend {
+    stage
+        .get_svc_processor::<CliprdrClient>()
+        .and_then(|cliprdr| cliprdr.downcast_backend::<TestCliprdrBackend>())
+        .expect("cliprdr backend")
+}
+
+/// Clipboard backend recording the events useful to the tests.
+#[derive(Debug, Default)]
+struct TestCliprdrBackend {
+    remote_copy_tx: Option<UnboundedSender<Vec<ClipboardFormat>>>,
+    monitor_ready: bool,
+    format_list_received: bool,
+}
+
+impl_as_any!(TestCliprdrBackend);
+
+impl CliprdrBackend for TestCliprdrBackend {
+    fn temporary_directory(&self) -> &str {
+        ".cliprdr"
+    }
+
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    fn on_request_format_list(&mut self) {
+        self.monitor_ready = true;
+    }
+
+    fn on_format_list_received(&mut self) {
+        self.format_list_received = true;
+    }
+
+    fn on_process_negotiated_capabilities(&mut self, _capabilities: ClipboardGeneralCapabilityFlags) {}
+
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]) {
+        if let Some(tx) = &self.remote_copy_tx {
+            tx.send(available_formats.to_vec()).unwrap();
+        }
+    }
+
+    fn on_format_data_request(&mut self, _format: FormatDataRequest) {}
+
+    fn on_format_data_response(&mut self, _response: FormatDataResponse<'_>) {}
+
+    fn on_file_contents_request(&mut self, _request: FileContentsRequest) {}
+
+    fn on_file_contents_response(&mut self, _response: FileContentsResponse<'_>) {}
+
+    fn on_lock(&mut self, _data_id: LockDataId) {}
+
+    fn on_unlock(&mut self, _data_id: LockDataId) {}
+}
+
+struct TestCliprdrFactory {
+    remote_copy_tx: UnboundedSender<Vec<ClipboardFormat>>,
+}
+
+impl CliprdrBackendFactory for TestCliprdrFactory {
+    fn build_cliprdr_backend(&self) -> Box<dyn CliprdrBackend> {
+        Box::new(TestCliprdrBackend {
+            remote_copy_tx: Some(self.remote_copy_tx.clone()),
+            ..TestCliprdrBackend::default()
+        })
+    }
+}
+
+impl server::ServerEventSender for TestCliprdrFactory {
+    fn set_sender(&mut self, _sender: UnboundedSender<ServerEvent>) {}
+}
+
+impl server::CliprdrServerFactory for TestCliprdrFactory {}
+
+type DisplayUpdatesRx = Arc<Mutex<UnboundedReceiver<DisplayUpdate>>>;
+
+struct TestDisplayUpdates {
+    rx: DisplayUpdatesRx,
+}
+
+#[async_trait::async_trait]
+impl RdpServerDisplayUpdates for TestDisplayUpdates {
+    async fn next_update(&mut self) -> Option<DisplayUpdate> {
+        let mut rx = self.rx.lock().await;
+
+        rx.recv().await
+    }
+}
+
+struct TestDisplay {
+    rx: DisplayUpdatesRx,
+}
+
+#[async_trait::async_trait]
+impl RdpServerDisplay for TestDisplay {
+    async fn size(&mut self) -> DesktopSize {
+        DesktopSize {
+            width: DESKTOP_WIDTH,
+            height: DESKTOP_HEIGHT,
+        }
+    }
+
+    async fn updates(&mut self) -> Result<Box<dyn RdpServerDisplayUpdates>> {
+        Ok(Box::new(TestDisplayUpdates {
+            rx: Arc::clone(&self.rx),
+        }))
+    }
+}
+
+struct TestInputHandler;
+impl RdpServerInputHandler for TestInputHandler {
+    fn keyboard(&mut self, _: KeyboardEvent) {}
+    fn mouse(&mut self, _: MouseEvent) {}
+}
+
+/// Input handler forwarding the keyboard events to a channel
+struct RecordingInputHandler {
+    keyboard_tx: UnboundedSender<KeyboardEvent>,
+}
+
+impl RdpServerInputHandler for RecordingInputHandler {
+    fn keyboard(&mut self, event: KeyboardEvent) {
+        let _ = self.keyboard_tx.send(event);
+    }
+
+    fn mouse(&mut self, _: MouseEvent) {}
+}
+
+#[derive(Debug)]
+enum KeyboardCallback {
+    Info(KeyboardInfo),
+    Sync(KeyboardSync),
+    Event(KeyboardEvent),
+}
+
+/// Input handler forwarding the keyboard callbacks to a channel
+struct KeyboardInputHandler {
+    callbacks_tx: UnboundedSender<KeyboardCallback>,
+}
+
+impl RdpServerInputHandler for KeyboardInputHandler {
+    fn keyboard(&mut self, event: KeyboardEvent) {
+        let _ = self.callbacks_tx.send(KeyboardCallback::Event(event));
+    }
+
+    fn mouse(&mut self, _: MouseEvent) {}
+
+    fn keyboard_info(&mut self, info: &KeyboardInfo) {
+        let _ = self.callbacks_tx.send(KeyboardCallback::Info(info.clone()));
+    }
+
+    fn keyboard_sync(&mut self, event: InputEvent<KeyboardSync>) {
+        let _ = self.callbacks_tx.send(KeyboardCallback::Sync(event.event));
+    }
+}
+
+struct TimedInputHandler {
+    keyboard_tx: UnboundedSender<InputEvent<KeyboardEvent>>,
+}
+
+impl RdpServerInputHandler for TimedInputHandler {
+    fn keyboard(&mut self, _: KeyboardEvent) {
+        unreachable!("timed events are forwarded")
+    }
+
+    fn mouse(&mut self, _: MouseEvent) {}
+
+    fn timed_keyboard(&mut self, event: InputEvent<KeyboardEvent>) {
+        let _ = self.keyboard_tx.send(event);
+    }
+}
+
+#[test]
+fn lock_key_tracker_follows_synchronize_and_lock_keys() {
+    let mut tracker = LockKeyTracker::new();
+    assert_eq!(tracker.state(), KeyboardSync::default());
+
+    tracker.update(&KeyboardEvent::Synchronize(SynchronizeFlags::NUM_LOCK));
+    assert_eq!(
+        tracker.state(),
+        KeyboardSync {
+            num_lock: true,
+            ..KeyboardSync::default()
+        }
+    );
+
+    // Caps Lock is toggled once, even when the press is repeated.
+    for _ in 0..3 {
+        tracker.update(&KeyboardEvent::Pressed {
+            code: 0x3a,
+            extended: false,
+        });
+    }
+    tracker.update(&KeyboardEvent::Released {
+        code: 0x3a,
+        extended: false,
+    });
+    assert!(tracker.state().caps_lock);
+
+    // Extended 0x45 is not Num Lock.
+    tracker.update(&KeyboardEvent::Pressed {
+        code: 0x45,
+        extended: true,
+    });
+    tracker.update(&KeyboardEvent::UnicodePressed(0x61));
+    assert!(tracker.state().num_lock);
+
+    tracker.update(&KeyboardEvent::Pressed {
+        code: 0x3a,
+        extended: false,
+    });
+    assert!(!tracker.state().caps_lock);
+
+    tracker.update(&KeyboardEvent::Synchronize(SynchronizeFlags::SCROLL_LOCK));
+    assert_eq!(
+        tracker.state(),
+        KeyboardSync {
+            scroll_lock: true,
+            ..KeyboardSync::default()
+        }
+    );
+}
+
+#[test]
+fn handshake_limiter_sheds_excess_connections() {
+    let start = Instant::now();
+    let mut limiter = HandshakeLimiter::new(HandshakeLimits {
+        max_handshakes_per_second: Some(2),
+        max_pending_handshakes: Some(1),
+    });
+
+    let permit = limiter.try_acquire(start).expect("first handshake");
+    assert_eq!(limiter.pending(), 1);
+
+    // The first handshake is still in progress.
+    assert!(limiter.try_acquire(start).is_none());
+
+    drop(permit);
+    assert_eq!(limiter.pending(), 0);
+
+    // No token is left for the third connection of the second.
+    assert!(limiter.try_acquire(start).is_some());
+    assert!(limiter.try_acquire(start).is_none());
+
+    assert!(limiter.try_acquire(start + Duration::from_millis(500)).is_some());
+
+    assert_eq!(
+        limiter.stats(),
+        HandshakeStats {
+            accepted: 3,
+            rate_limited: 1,
+            pending_limited: 1,
+        }
+    );
+    assert_eq!(limiter.stats().rejected(), 2);
+}
+
+#[test]
+fn default_handshake_limits_accept_everything() {
+    let now = Instant::now();
+    let mut limiter = HandshakeLimiter::new(HandshakeLimits::default());
+
+    let permits: Vec<_> = (0..100).map(|_| limiter.try_acquire(now).expect("permit")).collect();
+    assert_eq!(limiter.pending(), permits.len());
+    assert_eq!(limiter.stats().rejected(), 0);
+}
+
+#[tokio::test]
+async fn test_tls_session_resumption() {
+    let cert_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-cert.pem");
+    let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-key.pem");
+    let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
+    let tls_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(identity.certs.clone(), identity.priv_key.clone_key())
+        .expect("bad certificate/key");
+
+    let mut server = RdpServer::builder()
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_tls_session_cache(tls_config, 16)
+        .with_no_input()
+        .with_no_display()
+        .build();
+    let ev = server.event_sender().clone();
+
+    // With TLS 1.2, the session is cached as soon as the handshake completes, while TLS 1.3 tickets are only
+    // received after the handshake.
+    let client_tls_config = Arc::new(
+        rustls::ClientConfig::builder_with_protocol_versions(&[&rustls::version::TLS12])
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+            .with_no_client_auth(),
+    );
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let addr = rx.await.unwrap().unwrap();
+
+                let first = tls_handshake_kind(addr, Arc::clone(&client_tls_config)).await;
+                let second = tls_handshake_kind(addr, Arc::clone(&client_tls_config)).await;
+
+                assert_eq!(first, rustls::HandshakeKind::Full);
+                assert_eq!(second, rustls::HandshakeKind::Resumed);
+
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
+/// Negotiates TLS security with the server, and returns the kind of the TLS handshake performed.
+async fn tls_handshake_kind(addr: SocketAddr, tls_config: Arc<rustls::ClientConfig>) -> rustls::HandshakeKind {
+    let client_config = connector::Config {
+        enable_credssp: false,
+        ..default_client_config()
+    };
+
+    let tcp_stream = TcpStream::connect(addr).await.expect("TCP connect");
+    let mut framed = TokioFramed::new(tcp_stream);
+    let mut connector = connector::ClientConnector::new(client_config).with_server_addr(addr);
+    let mut timer = ironrdp_async::ConnectTimer::new(ironrdp_tokio::TokioTimer);
+    ironrdp_async::connect_begin(&mut framed, &mut connector, &mut timer)
+        .await
+        .expect("begin connection");
+
+    let server_name = ServerName::try_from("localhost").expect("server name");
+    let tls_stream = TlsConnector::from(tls_config)
+        .connect(server_name, framed.into_inner_no_leftover())
+        .await
+        .expect("TLS handshake");
+
+    tls_stream.get_ref().1.handshake_kind().expect("handshake completed")
+}
+
+const HYBRID_USERNAME: &str = "user";
+const HYBRID_PASSWORD: &str = "password";
+
+/// Runs a server requiring CredSSP with the given authorizer, and connects a client to it with `clientfn`.
+///
+/// `clientfn` receives the server address and a handle on the clients attached to the session, and is expected to
+/// close its connections before returning.
+async fn with_hybrid_server<F, Fut>(
+    authorizer: impl Fn(&server::AuthContext) -> server::AuthDecision + Send + Sync + 'static,
+    clientfn: F,
+) where
+    F: FnOnce(SocketAddr, server::AttachedClients) -> Fut + 'static,
+    Fut: Future<Output = ()>,
+{
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let cert_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-cert.pem");
+    let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs/server-key.pem");
+    let identity = TlsIdentityCtx::init_from_paths(&cert_path, &key_path).expect("failed to init TLS identity");
+    let acceptor = identity.make_acceptor().expect("failed to build TLS acceptor");
+
+    let mut server = RdpServer::builder()
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_hybrid(acceptor, identity.pub_key.clone())
+        .with_no_input()
+        .with_no_display()
+        .with_authorizer(authorizer)
+        .build();
+    server.set_credentials(Some(server::Credentials {
+        username: HYBRID_USERNAME.into(),
+        password: HYBRID_PASSWORD.into(),
+        domain: None,
+    }));
+    let ev = server.event_sender().clone();
+    let clients = server.attached_clients().clone();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                server.run().await.unwrap();
+            });
+
+            let client = tokio::task::spawn_local(async move {
+                let (tx, rx) = oneshot::channel();
+                ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
+                let addr = rx.await.unwrap().unwrap();
+
+                clientfn(addr, clients.clone()).await;
+
+                // The quit event would be consumed by an ongoing connection instead of the server.
+                while !clients.list().is_empty() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+
+                ev.send(ServerEvent::Quit("bye".into())).unwrap();
+            });
+
+            tokio::try_join!(server, client).expect("join");
+        })
+        .await;
+}
+
+fn hybrid_client_config() -> connector::Config {
+    connector::Config {
+        credentials: connector::Credentials::UsernamePassword {
+            username: HYBRID_USERNAME.into(),
+            password: HYBRID_PASSWORD.into(),
+        },
+        ..default_client_config()
+    }
+}
+
+#[tokio::test]
+async fn authorizer_metadata_is_attached_to_the_client() {
+    let authorizer = |context: &server::AuthContext| {
+        assert_eq!(context.username, HYBRID_USERNAME);
+        assert_eq!(context.protocol, pdu::nego::SecurityProtocol::HYBRID_EX);
+        assert!(context.client_addr.is_some());
+
+        server::AuthDecision::Allow(server::SessionMetadata::from([(
+            "tenant".to_owned(),
+            "contoso".to_owned(),
+        )]))
+    };
+
+    with_hybrid_server(authorizer, |addr, clients| async move {
+        let connection = connect_client(addr, hybrid_client_config(), false).await;
+
+        // The metadata is set by the server once the connection sequence is over on its side.
+        let metadata = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(client) = clients.list().into_iter().find(|client| !client.metadata.is_empty()) {
+                    return client.metadata;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("metadata of the client");
+
+        assert_eq!(metadata["tenant"], "contoso");
+
+        drop(connection);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn authorizer_denial_is_reported_with_hybrid_ex() {
+    let authorizer = |_: &server::AuthContext| server::AuthDecision::Deny("account is disabled".to_owned());
+
+    with_hybrid_server(authorizer, |addr, clients| async move {
+        let error = try_connect_client(addr, hybrid_client_config(), false)
+            .await
+            .err()
+            .expect("denied connection");
+
+        assert!(matches!(error.kind(), connector::ConnectorErrorKind::AccessDenied));
+        assert!(clients.list().is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn connect_timeout_reports_negotiation_stage() {
+    let timeouts = connector::ConnectTimeouts {
+        negotiation: Some(Duration::from_millis(100)),
+        ..connector::ConnectTimeouts::UNLIMITED
+    };
+
+    // The server never answers the connection request.
+    let (client_stream, _server_stream) = tokio::io::duplex(4096);
+    let mut framed = TokioFramed::new(client_stream);
+    let mut connector = connector::ClientConnector::new(timeout_client_config(timeouts));
+    let mut timer = ironrdp_async::ConnectTimer::new(ironrdp_tokio::TokioTimer);
+
+    let Err(error) = ironrdp_async::connect_begin(&mut framed, &mut connector, &mut timer).await else {
+        panic!("connection should time out");
+    };
+
+    assert!(matches!(
+        error.kind(),
+        connector::ConnectorErrorKind::Timeout {
+            stage: "ConnectionInitiationWaitResponse"
+        }
+    ));
+}
+
+#[tokio::test]
+async fn connect_timeout_reports_mcs_connect_stage() {
+    let timeouts = connector::ConnectTimeouts {
+        mcs_connect: Some(Duration::from_millis(100)),
+        ..connector::ConnectTimeouts::UNLIMITED
+    };
+
+    let error = stall_after_negotiation(timeouts, ironrdp_tokio::TokioTimer).await;
+
+    assert!(matches!(
+        error.kind(),
+        connector::ConnectorErrorKind::Timeout {
+            stage: "BasicSettingsExchangeWaitResponse"
+        }
+    ));
+}
+
+#[tokio::test]
+async fn connect_deadline_covers_all_stages() {
+    let timeouts = connector::ConnectTimeouts {
+        overall: Some(Duration::from_millis(200)),
+        ..connector::ConnectTimeouts::UNLIMITED
+    };
+
+    // The runtime-independent timer is driving the budgets as well as the Tokio one.
+    let start = Instant::now();
+    let error = stall_after_negotiation(timeouts, ironrdp_futures::FuturesTimer).await;
+
+    assert!(matches!(
+        error.kind(),
+        connector::ConnectorErrorKind::Timeout {
+            stage: "BasicSettingsExchangeWaitResponse"
+        }
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn connect_reports_refused_tcp_connection() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+
+    let connector = connector::ClientConnector::new(default_client_config());
+    let Err(error) = ironrdp_tokio::connect(ConnectOptions::new(connector, "127.0.0.1", port)).await else {
+        panic!("nothing listens on the port");
+    };
+
+    assert!(matches!(error, ConnectError::Tcp(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn connect_transport_timeout_covers_tls_upgrade() {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let port = listener.local_addr().expect("local address").port();
+
+    // Confirms the connection request, and never answers the TLS handshake.
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accept");
+
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).await.expect("read connection request");
+
+        let confirm = X224(pdu::nego::ConnectionConfirm::Response {
+            flags: pdu::nego::ResponseFlags::empty(),
+            protocol: pdu::nego::SecurityProtocol::SSL,
+        });
+        let confirm = encode_vec(&confirm).expect("encode connection confirm");
+        stream.write_all(&confirm).await.expect("write connection confirm");
+
+        stream
+    });
+
+    let connector = connector::ClientConnector::new(timeout_client_config(connector::ConnectTimeouts::UNLIMITED));
+    let options = ConnectOptions::new(connector, "127.0.0.1", port)
+        .with_tls_server_name("localhost")
+        .with_transport_timeout(Duration::from_millis(100));
+    let Err(error) = ironrdp_tokio::connect(options).await else {
+        panic!("TLS handshake should time out");
+    };
+
+    assert!(
+        matches!(error, ConnectError::TransportTimeout { step: "TLS upgrade" }),
+        "{error:?}"
+    );
+    drop(server.await.expect("server task"));
+}
+
+#[tokio::test]
+async fn connect_reports_rdcleanpath_error() {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let (client_stream, mut proxy_stream) = tokio::io::duplex(4096);
+
+    let proxy = tokio::spawn(async move {
+        let mut request = [0; 4096];
+        let _ = proxy_stream.read(&mut request).await.expect("read RDCleanPath request");
+
+        let response = RDCleanPathPdu::new_http_error(403)
+            .to_der()
+            .expect("encode RDCleanPath error");
+        proxy_stream
+            .write_all(&response)
+            .await
+            .expect("write RDCleanPath error");
+
+        proxy_stream
+    });
+
+    let connector = connector::ClientConnector::new(default_client_config());
+    let options = ConnectOptions::new(connector, "rdp.example", 3389).with_rdcleanpath_proxy(RdCleanPathProxy {
+        stream: Box::new(client_stream),
+        auth_token: "token".to_owned(),
+        pcb: None,
+    });
+    let Err(error) = ironrdp_tokio::connect(options).await else {
+        panic!("the proxy denied the connection");
+    };
+
+    assert!(matches!(error, ConnectError::RdCleanPath(_)), "{error:?}");
+    drop(proxy.await.expect("proxy task"));
+}
+
+#[tokio::test]
+async fn futures_timer_sleeps_for_the_duration() {
+    use ironrdp_async::AsyncTimer as _;
+
+    let start = Instant::now();
+    ironrdp_futures::FuturesTimer.sleep(Duration::from_millis(50)).await;
+
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn timeout_expires_before_a_pending_future() {
+    let start = Instant::now();
+    let result = ironrdp_async::timeout(
+        &ironrdp_futures::FuturesTimer,
+        Duration::from_millis(50),
+        core::future::pending::<()>(),
+    )
+    .await;
+
+    assert_eq!(result, Err(ironrdp_async::Elapsed));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn timeout_returns_the_output_of_a_completed_future() {
+    let start = Instant::now();
+    let result = ironrdp_async::timeout(&ironrdp_tokio::TokioTimer, Duration::from_secs(10), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        42
+    })
+    .await;
+
+    assert_eq!(result, Ok(42));
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    let result = ironrdp_async::timeout(&ironrdp_async::NoTimer, Duration::ZERO, async { 42 }).await;
+    assert_eq!(result, Ok(42));
+}
+
+fn timeout_client_config(timeouts: connector::ConnectTimeouts) -> connector::Config {
+    connector::Config {
+        enable_credssp: false,
+        timeouts,
+        ..default_client_config()
+    }
+}
+
+/// Connects to a server which confirms the connection request, and then stalls.
+///
+/// The security upgrade is skipped: the server never reads what the client sends afterwards.
+async fn stall_after_negotiation(
+    timeouts: connector::ConnectTimeouts,
+    timer: impl ironrdp_async::AsyncTimer,
+) -> connector::ConnectorError {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let (client_stream, mut server_stream) = tokio::io::duplex(4096);
+
+    let server = tokio::spawn(async move {
+        let mut request = [0; 1024];
+        let _ = server_stream.read(&mut request).await.expect("read connection request");
+
+        let confirm = X224(pdu::nego::ConnectionConfirm::Response {
+            flags: pdu::nego::ResponseFlags::empty(),
+            protocol: pdu::nego::SecurityProtocol::SSL,
+        });
+        let confirm = encode_vec(&confirm).expect("encode connection confirm");
+        server_stream
+            .write_all(&confirm)
+            .await
+            .expect("write connection confirm");
+
+        server_stream
+    });
+
+    let mut framed = TokioFramed::new(client_stream);
+    let mut connector = connector::ClientConnector::new(timeout_client_config(timeouts));
+    let mut timer = ironrdp_async::ConnectTimer::new(timer);
+
+    let should_upgrade = ironrdp_async::connect_begin(&mut framed, &mut connector, &mut timer)
+        .await
+        .expect("begin connection");
+    let upgraded = ironrdp_async::mark_as_upgraded(should_upgrade, &mut connector);
+
+    // Keep the server side open, so that the client is waiting instead of failing.
+    let _server_stream = server.await.expect("server task");
+
+    let Err(error) = ironrdp_async::connect_finalize(
+        upgraded,
+        &mut framed,
+        connector,
+        &mut timer,
+        "localhost".into(),
+        Vec::new(),
+        None,
+        None,
+    )
+    .await
+    else {
+        panic!("connection should time out");
+    };
+
+    error
+}
+
+#[tokio::test]
+async fn fake_server_requiring_credssp() {
+    let script = vec![
+        Step::ExpectX224Request,
+        // X.224 Connection Confirm with a RDP Negotiation Failure (HYBRID_REQUIRED_BY_SERVER).
+        Step::SendPdu(vec![
+            0x03, 0x00, 0x00, 0x13, 0x0e, 0xd0, 0x00, 0x00, 0x12, 0x34, 0x00, 0x03, 0x00, 0x08, 0x00, 0x05, 0x00, 0x00,
+            0x00,
+        ]),
+    ];
+
+    let (client, server) = fake_server::connect(fake_server_client_config(), script).await;
+    server.expect("server script");
+
+    let Err(error) = client else {
+        panic!("connection should fail");
+    };
+    assert!(matches!(
+        error.kind(),
+        connector::ConnectorErrorKind::NegotiationFailure(pdu::nego::NegotiationFailureCode::HybridRequiredByServer)
+    ));
+    assert!(error.to_string().contains("CredSSP"), "{error}");
+}
+
+#[tokio::test]
+async fn fake_server_selecting_standard_rdp_security() {
+    let script = vec![
+        Step::ExpectX224Request,
+        Step::SendX224Confirm {
+            flags: pdu::nego::ResponseFlags::empty(),
+            protocol: pdu::nego::SecurityProtocol::empty(),
+        },
+    ];
+
+    let (client, server) = fake_server::connect(fake_server_client_config(), script).await;
+    server.expect("server script");
+
+    let Err(error) = client else {
+        panic!("connection should fail");
+    };
+    assert!(matches!(error.kind(), connector::ConnectorErrorKind::Reason(_)));
+    assert!(error.to_string().contains("standard RDP security"), "{error}");
+}
+
+#[tokio::test]
+async fn fake_server_requiring_encryption_over_tls() {
+    let script = vec![
+        Step::ExpectX224Request,
+        Step::SendX224Confirm {
+            flags: pdu::nego::ResponseFlags::empty(),
+            protocol: pdu::nego::SecurityProtocol::SSL,
+        },
+        Step::ExpectMcsConnectInitial,
+        Step::SendMcsConnectResponse {
+            security: gcc::ServerSecurityData {
+                encryption_method: gcc::EncryptionMethod::BIT_128,
+                encryption_level: gcc::EncryptionLevel::ClientCompatible,
+                server_random: Some([0x42; 32]),
+                server_cert: Vec::new(),
+            },
+        },
+    ];
+
+    let (client, server) = fake_server::connect(fake_server_client_config(), script).await;
+    server.expect("server script");
+
+    let Err(error) = client else {
+        panic!("connection should fail");
+    };
+    assert!(matches!(error.kind(), connector::ConnectorErrorKind::Reason(_)));
+    assert!(
+        error.to_string().contains("encryption level: ClientCompatible"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn fake_server_sending_malformed_capability_set() {
+    // General capability set announcing 24 bytes, but truncated after 8.
+    let malformed = vec![0x01, 0x00, 0x18, 0x00, 0x01, 0x00, 0x03, 0x00];
+
+    let mut script = Step::until_capabilities();
+    script.push(Step::SendCapabilities {
+        count: 1,
+        data: malformed,
+    });
+
+    let (client, server) = fake_server::connect(fake_server_client_config(), script).await;
+    server.expect("server script");
+
+    let Err(error) = client else {
+        panic!("connection should fail");
+    };
+    assert!(
+        matches!(error.kind(), connector::ConnectorErrorKind::Decode(_)),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn fake_server_disconnecting_mid_licensing() {
+    let mut script = Step::until_capabilities();
+    // Drop the licensing message, and let the client wait for it a bit.
+    script.pop();
+    script.push(Step::Delay(Duration::from_millis(50)));
+    script.push(Step::CloseAbruptly);
+
+    let (client, server) = fake_server::connect(fake_server_client_config(), script).await;
+    server.expect("server script");
+
+    let Err(error) = client else {
+        panic!("connection should fail");
+    };
+    let connector::ConnectorErrorKind::Custom = error.kind() else {
+        panic!("unexpected error: {error:?}");
+    };
+    let source = core::error::Error::source(&error).expect("error source");
+    let io_error = source.downcast_ref::<io::Error>().expect("I/O error");
+    assert_eq!(io_error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+fn fake_server_client_config() -> connector::Config {
+    connector::Config {
+        enable_credssp: false,
+        timeouts: connector::ConnectTimeouts {
+            overall: Some(Duration::from_secs(10)),
+            ..connector::ConnectTimeouts::UNLIMITED
+        },
+        ..default_client_config()
+    }
+}
+
+/// Returns an active stage connected to the fake server, without exchanging any PDU.
+fn active_stage(static_channels: StaticChannelSet) -> ActiveStage {
+    let config = default_client_config();
 
-#[tokio::test]
-async fn test_client_server() {
-    client_server(default_client_config(), |stage, framed, _display_tx| async {
-        (stage, framed)
+    ActiveStage::new(ConnectionResult {
+        io_channel_id: fake_server::IO_CHANNEL_ID,
+        user_channel_id: fake_server::USER_CHANNEL_ID,
+        static_channels,
+        desktop_size: config.desktop_size,
+        negotiated_color_depth: 32,
+        no_server_pointer: true,
+        pointer_software_rendering: false,
+        pointer_cache_size: 0,
+        bitmap_cache: None,
+        server_input_flags: InputFlags::empty(),
+        frame_acknowledge: false,
+        connection_activation: ConnectionActivationSequence::new(
+            config,
+            fake_server::IO_CHANNEL_ID,
+            fake_server::USER_CHANNEL_ID,
+        ),
+        decode_warnings: Vec::new(),
+        server_security: gcc::ServerSecurityData::no_security(),
     })
-    .await
+}
+
+/// Encodes a Monitor Layout PDU, as sent by the server on the I/O channel.
+fn monitor_layout_frame(monitors: &[gcc::Monitor]) -> Vec<u8> {
+    let share_control = ShareControlHeader {
+        share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
+            share_data_pdu: ShareDataPdu::MonitorLayout(MonitorLayoutPdu {
+                monitors: monitors.to_vec(),
+            }),
+            stream_priority: StreamPriority::Medium,
+            compression_flags: CompressionFlags::empty(),
+            compression_type: CompressionType::K8,
+        }),
+        pdu_source: fake_server::USER_CHANNEL_ID,
+        share_id: 0,
+    };
+
+    let indication = X224(mcs::SendDataIndication {
+        initiator_id: fake_server::USER_CHANNEL_ID,
+        channel_id: fake_server::IO_CHANNEL_ID,
+        user_data: Cow::Owned(encode_vec(&share_control).unwrap()),
+    });
+
+    encode_vec(&indication).unwrap()
+}
+
+fn monitor(left: i32, top: i32, right: i32, bottom: i32, flags: gcc::MonitorFlags) -> gcc::Monitor {
+    gcc::Monitor {
+        left,
+        top,
+        right,
+        bottom,
+        flags,
+    }
+}
+
+#[test]
+fn monitor_layout_resizes_the_image_to_the_bounding_box() {
+    let mut stage = active_stage(StaticChannelSet::new());
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+    let monitors = [
+        monitor(0, 0, 1919, 1079, gcc::MonitorFlags::PRIMARY),
+        monitor(-1280, 0, -1, 1023, gcc::MonitorFlags::empty()),
+    ];
+
+    let outputs = stage
+        .process(&mut image, pdu::Action::X224, &monitor_layout_frame(&monitors))
+        .unwrap();
+
+    let [ActiveStageOutput::MonitorLayoutChanged(layout)] = outputs.as_slice() else {
+        panic!("unexpected outputs: {outputs:?}");
+    };
+    assert_eq!(layout.as_slice(), monitors.as_slice());
+    assert_eq!((image.width(), image.height()), (3200, 1080));
+}
+
+#[test]
+fn malformed_monitor_layout_is_ignored() {
+    let mut stage = active_stage(StaticChannelSet::new());
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+    let overlapping = [
+        monitor(0, 0, 1919, 1079, gcc::MonitorFlags::PRIMARY),
+        monitor(1000, 0, 2919, 1079, gcc::MonitorFlags::empty()),
+    ];
+    let inverted = [monitor(1919, 0, 0, 1079, gcc::MonitorFlags::PRIMARY)];
+    let too_large = [monitor(0, 0, 70_000, 1079, gcc::MonitorFlags::PRIMARY)];
+
+    for monitors in [&overlapping[..], &inverted, &too_large, &[]] {
+        let outputs = stage
+            .process(&mut image, pdu::Action::X224, &monitor_layout_frame(monitors))
+            .unwrap();
+
+        assert!(outputs.is_empty(), "unexpected outputs: {outputs:?}");
+        assert_eq!((image.width(), image.height()), (DESKTOP_WIDTH, DESKTOP_HEIGHT));
+    }
+}
+
+const ECHO_CHANNEL_ID: u16 = 1004;
+const DRDYNVC_CHANNEL_ID: u16 = 1005;
+const ECHO_DVC_ID: u32 = 7;
+
+/// Static channel sending back each PDU received.
+#[derive(Debug, Default)]
+struct EchoSvc {
+    closed: bool,
+    /// Final PDU sent when the channel is closed, if not empty.
+    farewell: Vec<u8>,
+}
+
+impl_as_any!(EchoSvc);
+
+impl SvcProcessor for EchoSvc {
+    fn channel_name(&self) -> gcc::ChannelName {
+        gcc::ChannelName::from_static(b"echo\0\0\0\0")
+    }
+
+    fn process(&mut self, payload: &[u8]) -> pdu::PduResult<Vec<SvcMessage>> {
+        Ok(vec![SvcMessage::from(payload.to_vec())])
+    }
+
+    fn close(&mut self) -> pdu::PduResult<Vec<SvcMessage>> {
+        assert!(!self.closed, "closed twice");
+        self.closed = true;
+
+        if self.farewell.is_empty() {
+            Ok(Vec::new())
+        } else {
+            Ok(vec![SvcMessage::from(self.farewell.clone())])
+        }
+    }
+}
+
+impl SvcClientProcessor for EchoSvc {}
+
+struct EchoPdu(Vec<u8>);
+
+impl ironrdp::core::Encode for EchoPdu {
+    fn encode(&self, dst: &mut ironrdp::core::WriteCursor<'_>) -> ironrdp::core::EncodeResult<()> {
+        ironrdp::core::ensure_size!(in: dst, size: self.0.len());
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "EchoPdu"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl DvcEncode for EchoPdu {}
+
+/// Dynamic channel sending back each message received.
+struct EchoDvc;
+
+impl_as_any!(EchoDvc);
+
+impl DvcProcessor for EchoDvc {
+    fn channel_name(&self) -> &str {
+        "Test::Echo"
+    }
+
+    fn start(&mut self, _channel_id: u32) -> pdu::PduResult<Vec<DvcMessage>> {
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, _channel_id: u32, payload: &[u8]) -> pdu::PduResult<Vec<DvcMessage>> {
+        Ok(vec![Box::new(EchoPdu(payload.to_vec()))])
+    }
+}
+
+impl DvcClientProcessor for EchoDvc {}
+
+fn echo_active_stage() -> ActiveStage {
+    let mut static_channels = StaticChannelSet::new();
+    static_channels.insert(EchoSvc::default());
+    static_channels.insert(DrdynvcClient::new().with_dynamic_channel(EchoDvc));
+    static_channels.attach_channel_id(TypeId::of::<EchoSvc>(), ECHO_CHANNEL_ID);
+    static_channels.attach_channel_id(TypeId::of::<DrdynvcClient>(), DRDYNVC_CHANNEL_ID);
+
+    active_stage(static_channels)
+}
+
+/// Encodes `payload` as a single chunk sent by the server on a static channel.
+fn svc_frame(channel_id: u16, payload: Vec<u8>) -> Vec<u8> {
+    let chunks = StaticVirtualChannel::chunkify(vec![SvcMessage::from(payload)]).unwrap();
+    let [chunk] = chunks.as_slice() else {
+        panic!("expected a single chunk");
+    };
+
+    encode_vec(&X224(mcs::SendDataIndication {
+        initiator_id: fake_server::USER_CHANNEL_ID,
+        channel_id,
+        user_data: Cow::Borrowed(chunk.filled()),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn channel_traffic_is_counted() {
+    /// Size of the Channel PDU Header.
+    const CHANNEL_HEADER_SIZE: u64 = 8;
+
+    let mut stage = echo_active_stage();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+    let create_request = encode_vec(&DrdynvcServerPdu::Create(CreateRequestPdu::new(
+        ECHO_DVC_ID,
+        "Test::Echo".to_owned(),
+    )))
+    .unwrap();
+    let data = DrdynvcDataPdu::Data(DataPdu::new(ECHO_DVC_ID, b"dynamic".to_vec()));
+    let data_size = u64::try_from(data.size()).unwrap();
+
+    let frames = [
+        svc_frame(ECHO_CHANNEL_ID, b"hello".to_vec()),
+        svc_frame(ECHO_CHANNEL_ID, b"world!".to_vec()),
+        svc_frame(DRDYNVC_CHANNEL_ID, create_request.clone()),
+        svc_frame(DRDYNVC_CHANNEL_ID, encode_vec(&DrdynvcServerPdu::Data(data)).unwrap()),
+    ];
+    for frame in &frames {
+        stage.process(&mut image, pdu::Action::X224, frame).unwrap();
+    }
+
+    let frame = stage
+        .process_svc_processor_messages(SvcProcessorMessages::<EchoSvc>::new(vec![SvcMessage::from(
+            b"request".to_vec(),
+        )]))
+        .unwrap();
+    assert!(!frame.is_empty());
+
+    let stats = stage.stats_snapshot();
+
+    assert_eq!(
+        stats.static_channels["echo"],
+        ChannelStats {
+            bytes_received: 2 * CHANNEL_HEADER_SIZE + 11,
+            bytes_sent: 3 * CHANNEL_HEADER_SIZE + 18,
+            pdus_received: 2,
+            pdus_sent: 3,
+        }
+    );
+
+    let drdynvc = stats.static_channels["drdynvc"];
+    assert_eq!((drdynvc.pdus_received, drdynvc.pdus_sent), (2, 3));
+    assert_eq!(
+        drdynvc.bytes_received,
+        2 * CHANNEL_HEADER_SIZE + u64::try_from(create_request.len()).unwrap() + data_size
+    );
+
+    // The echoed data has the same channel ID and payload, and so the same size.
+    assert_eq!(
+        stats.dynamic_channels["Test::Echo"],
+        ChannelStats {
+            bytes_received: data_size,
+            bytes_sent: data_size,
+            pdus_received: 1,
+            pdus_sent: 1,
+        }
+    );
+}
+
+#[test]
+fn channel_traffic_delta_omits_idle_channels() {
+    let mut stage = echo_active_stage();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+    let frame = svc_frame(ECHO_CHANNEL_ID, b"hello".to_vec());
+    stage.process(&mut image, pdu::Action::X224, &frame).unwrap();
+    let earlier = stage.stats_snapshot();
+    stage.process(&mut image, pdu::Action::X224, &frame).unwrap();
+
+    let delta = stage.stats_snapshot().since(&earlier);
+
+    assert_eq!(delta.static_channels.keys().collect::<Vec<_>>(), ["echo"]);
+    assert_eq!(delta.static_channels["echo"].pdus_received, 1);
+    assert!(delta.dynamic_channels.is_empty());
+
+    // The unopened dynamic channels are listed too.
+    assert_eq!(earlier.dynamic_channels["Test::Echo"], ChannelStats::default());
+}
+
+#[test]
+fn channel_expelled_by_the_server_is_closed() {
+    let mut stage = echo_active_stage();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+    let expel = |user_ids: Vec<u16>| {
+        encode_vec(&X224(mcs::ChannelExpelIndication {
+            channel_id: ECHO_CHANNEL_ID,
+            user_ids,
+        }))
+        .unwrap()
+    };
+
+    // Another user being expelled does not affect the channel.
+    let outputs = stage
+        .process(
+            &mut image,
+            pdu::Action::X224,
+            &expel(vec![fake_server::USER_CHANNEL_ID + 1]),
+        )
+        .unwrap();
+    assert!(outputs.is_empty());
+    assert!(!stage.get_svc_processor::<EchoSvc>().unwrap().closed);
+
+    let outputs = stage
+        .process(
+            &mut image,
+            pdu::Action::X224,
+            &expel(vec![fake_server::USER_CHANNEL_ID]),
+        )
+        .unwrap();
+    assert!(outputs.is_empty());
+    assert!(stage.get_svc_processor::<EchoSvc>().unwrap().closed);
+
+    let error = stage
+        .process_svc_processor_messages(SvcProcessorMessages::<EchoSvc>::new(vec![SvcMessage::from(
+            b"request".to_vec(),
+        )]))
+        .unwrap_err();
+    assert!(error.to_string().contains("was left"), "{error}");
+
+    // The data received afterwards on this channel is rejected.
+    let frame = svc_frame(ECHO_CHANNEL_ID, b"hello".to_vec());
+    assert!(stage.process(&mut image, pdu::Action::X224, &frame).is_err());
+}
+
+#[test]
+fn graceful_shutdown_closes_the_static_channels_first() {
+    let mut static_channels = StaticChannelSet::new();
+    static_channels.insert(EchoSvc {
+        farewell: b"bye".to_vec(),
+        ..EchoSvc::default()
+    });
+    static_channels.attach_channel_id(TypeId::of::<EchoSvc>(), ECHO_CHANNEL_ID);
+    let mut stage = active_stage(static_channels);
+
+    let outputs = stage.graceful_shutdown().unwrap();
+
+    assert!(stage.get_svc_processor::<EchoSvc>().unwrap().closed);
+
+    let [ActiveStageOutput::ResponseFrame(farewell), ActiveStageOutput::ResponseFrame(shutdown)] = outputs.as_slice()
+    else {
+        panic!("unexpected outputs: {outputs:?}");
+    };
+
+    let farewell = decode::<X224<mcs::SendDataRequest<'_>>>(farewell).unwrap().0;
+    assert_eq!(farewell.channel_id, ECHO_CHANNEL_ID);
+    assert!(farewell.user_data.ends_with(b"bye"));
+
+    let shutdown = decode::<X224<mcs::SendDataRequest<'_>>>(shutdown).unwrap().0;
+    assert_eq!(shutdown.channel_id, fake_server::IO_CHANNEL_ID);
+
+    // The channels are closed only once.
+    let outputs = stage.graceful_shutdown().unwrap();
+    assert_eq!(outputs.len(), 1);
+}
+
+#[test]
+fn disconnect_provider_ultimatum_terminates_the_session() {
+    let cases = [
+        (mcs::DisconnectReason::ProviderInitiated, "server initiated disconnect"),
+        (mcs::DisconnectReason::UserRequested, "user initiated disconnect"),
+        (mcs::DisconnectReason::TokenPurged, "token purged"),
+    ];
+
+    for (reason, description) in cases {
+        let mut stage = echo_active_stage();
+        let mut image = DecodedImage::new(PixelFormat::RgbA32, DESKTOP_WIDTH, DESKTOP_HEIGHT);
+
+        let frame = encode_vec(&X224(mcs::DisconnectProviderUltimatum::from_reason(reason))).unwrap();
+        let outputs = stage.process(&mut image, pdu::Action::X224, &frame).unwrap();
+
+        let Some(ActiveStageOutput::Terminate(disconnect_reason)) = outputs.last() else {
+            panic!("unexpected outputs: {outputs:?}");
+        };
+        assert_eq!(disconnect_reason.description(), description);
+        assert!(stage.get_svc_processor::<EchoSvc>().unwrap().closed);
+    }
+}
+
+#[derive(Debug)]
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+        ]
+    }
+}
+
+/// Returns an X.224 Data TPKT frame with a 3-byte payload.
+fn tpkt_frame(id: u8) -> [u8; 7] {
+    [0x03, 0x00, 0x00, 0x07, id, id, id]
 }
 
 #[tokio::test]
-async fn test_deactivation_reactivation() {
-    let client_config = default_client_config();
-    let mut image = DecodedImage::new(
-        PixelFormat::RgbA32,
-        client_config.desktop_size.width,
-        client_config.desktop_size.height,
+async fn read_frames_batch_drains_buffered_frames() {
+    let bytes: Vec<u8> = (0..5).flat_map(tpkt_frame).collect();
+    // Any further read fails, so the batch must be split out of the first chunk only.
+    let reader = mock_recv_stream(vec![
+        Ok(bytes),
+        Err(io::Error::new(io::ErrorKind::ConnectionReset, "stream reset")),
+    ]);
+    let mut framed = LocalFuturesFramed::new(ChunkedStream::new(reader, MockSendStream::default()));
+
+    let batch = framed.read_frames_batch(16).await.unwrap();
+
+    assert_eq!(batch.len(), 5);
+    for (id, (action, frame)) in (0..).zip(&batch) {
+        assert_eq!(*action, pdu::Action::X224);
+        assert_eq!(frame.as_ref(), tpkt_frame(id));
+    }
+    assert!(framed.peek().is_empty());
+}
+
+#[tokio::test]
+async fn read_frames_batch_is_limited_to_max() {
+    let bytes: Vec<u8> = (0..5).flat_map(tpkt_frame).collect();
+    let reader = mock_recv_stream(vec![Ok(bytes)]);
+    let mut framed = LocalFuturesFramed::new(ChunkedStream::new(reader, MockSendStream::default()));
+
+    assert_eq!(framed.read_frames_batch(2).await.unwrap().len(), 2);
+    assert_eq!(framed.peek_frame().unwrap().map(|info| info.length), Some(7));
+    assert_eq!(framed.read_frames_batch(0).await.unwrap().len(), 1);
+    assert_eq!(framed.read_frames_batch(16).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn read_frames_batch_keeps_partial_trailing_frame() {
+    let bytes: Vec<u8> = (0..3).flat_map(tpkt_frame).collect();
+    let reader = mock_recv_stream(vec![Ok(bytes[..10].to_vec()), Ok(bytes[10..].to_vec())]);
+    let mut framed = LocalFuturesFramed::new(ChunkedStream::new(reader, MockSendStream::default()));
+
+    let batch = framed.read_frames_batch(16).await.unwrap();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].1.as_ref(), tpkt_frame(0));
+
+    // The beginning of the second frame is buffered, but it is not complete yet.
+    assert_eq!(framed.peek(), &tpkt_frame(1)[..3]);
+    assert!(framed.peek_frame().unwrap().is_none());
+
+    let batch = framed.read_frames_batch(16).await.unwrap();
+    let batch: Vec<&[u8]> = batch.iter().map(|(_, frame)| frame.as_ref()).collect();
+    assert_eq!(batch, [tpkt_frame(1), tpkt_frame(2)]);
+
+    let error = framed.read_frames_batch(16).await.unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+/// Returns an X.224 Data TPKT frame of `length` bytes, filled with a counter.
+fn large_tpkt_frame(length: u16) -> Vec<u8> {
+    let [high, low] = length.to_be_bytes();
+    let mut frame = vec![0x03, 0x00, high, low];
+    frame.extend((4..length).map(|i| i.to_le_bytes()[0]));
+    frame
+}
+
+type DuplexSessionDriver = SessionDriver<TokioStream<ReadHalf<DuplexStream>>>;
+
+type DuplexWriterTask = WriterTask<TokioStream<WriteHalf<DuplexStream>>>;
+
+fn session_driver(stream: DuplexStream, capacity: usize) -> (DuplexSessionDriver, DuplexWriterTask) {
+    let (reader, writer) = split_tokio_framed(TokioFramed::new(stream));
+    SessionDriver::new(reader, writer, capacity)
+}
+
+#[tokio::test]
+async fn session_driver_writes_input_during_a_large_read() {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let (client, mut server) = tokio::io::duplex(1 << 16);
+    let (mut driver, writer_task) = session_driver(client, DEFAULT_OUTBOUND_CAPACITY);
+    let writer_task = tokio::spawn(writer_task.run());
+    let mut input = driver.sender();
+
+    // Only the beginning of a large frame is received.
+    let large_frame = large_tpkt_frame(60_000);
+    server.write_all(&large_frame[..1000]).await.unwrap();
+
+    let read = tokio::spawn(async move {
+        let batch = driver.read_frames_batch(16).await;
+        (driver, batch)
+    });
+
+    for id in 0..3 {
+        input.send(tpkt_frame(id).to_vec()).await.unwrap();
+    }
+
+    let mut received = [0; 21];
+    tokio::time::timeout(Duration::from_secs(5), server.read_exact(&mut received))
+        .await
+        .expect("input frames written while reading")
+        .unwrap();
+    let expected: Vec<u8> = (0..3).flat_map(tpkt_frame).collect();
+    assert_eq!(received.as_slice(), expected);
+    assert!(!read.is_finished());
+
+    server.write_all(&large_frame[1000..]).await.unwrap();
+
+    let (driver, batch) = read.await.unwrap();
+    let batch = batch.unwrap();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].1.as_ref(), large_frame);
+
+    drop(driver);
+    drop(input);
+    tokio::time::timeout(Duration::from_secs(5), writer_task)
+        .await
+        .expect("writer task ends once the senders are dropped")
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn session_driver_writes_queued_frames_on_shutdown() {
+    use tokio::io::AsyncReadExt as _;
+
+    let (client, mut server) = tokio::io::duplex(1 << 16);
+    let (driver, writer_task) = session_driver(client, 2);
+    let mut sender = driver.sender();
+
+    // The queue holds its capacity, plus one frame for each sender.
+    for id in 0..3 {
+        sender.try_send(tpkt_frame(id).to_vec()).unwrap();
+    }
+    assert_eq!(
+        sender.try_send(tpkt_frame(3).to_vec()),
+        Err(SendError::Full(tpkt_frame(3).to_vec().into()))
     );
-    client_server(client_config, |mut stage, mut framed, display_tx| async move {
-        display_tx
-            .send(DisplayUpdate::Resize(DesktopSize {
-                width: 2048,
-                height: 2048,
-            }))
-            .unwrap();
-        {
-            let (action, payload) = framed.read_pdu().await.expect("valid PDU");
-            let outputs = stage.process(&mut image, action, &payload).expect("stage process");
-            let out = outputs.into_iter().next().unwrap();
-            match out {
-                ActiveStageOutput::DeactivateAll(mut connection_activation) => {
-                    // TODO: factor this out in common client code
-                    // Execute the Deactivation-Reactivation Sequence:
-                    // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
-                    debug!("Received Server Deactivate All PDU, executing Deactivation-Reactivation Sequence");
-                    let mut buf = pdu::WriteBuf::new();
-                    'activation_seq: loop {
-                        let written = ironrdp_async::single_sequence_step_read(
-                            &mut framed,
-                            &mut *connection_activation,
-                            &mut buf,
-                        )
-                        .await
-                        .map_err(|e| session::custom_err!("read deactivation-reactivation sequence step", e))
-                        .unwrap();
 
-                        if written.size().is_some() {
-                            framed
-                                .write_all(buf.filled())
-                                .await
-                                .map_err(|e| session::custom_err!("write deactivation-reactivation sequence step", e))
-                                .unwrap();
-                        }
+    let writer_task = tokio::spawn(writer_task.run());
+    sender.send(tpkt_frame(3).to_vec()).await.unwrap();
 
-                        if let connector::connection_activation::ConnectionActivationState::Finalized {
-                            io_channel_id,
-                            user_channel_id,
-                            desktop_size,
-                            no_server_pointer,
-                            pointer_software_rendering,
-                        } = connection_activation.state
-                        {
-                            debug!(?desktop_size, "Deactivation-Reactivation Sequence completed");
-                            // Update image size with the new desktop size.
-                            // image = DecodedImage::new(PixelFormat::RgbA32, desktop_size.width, desktop_size.height);
-                            // Update the active stage with the new channel IDs and pointer settings.
-                            stage.set_fastpath_processor(
-                                session::fast_path::ProcessorBuilder {
-                                    io_channel_id,
-                                    user_channel_id,
-                                    no_server_pointer,
-                                    pointer_software_rendering,
-                                }
-                                .build(),
-                            );
-                            stage.set_no_server_pointer(no_server_pointer);
-                            break 'activation_seq;
-                        }
-                    }
-                }
-                _ => unreachable!(),
+    drop(sender);
+    let reader = driver.into_reader();
+
+    let writer = tokio::time::timeout(Duration::from_secs(5), writer_task)
+        .await
+        .expect("writer task ends once the senders are dropped")
+        .unwrap()
+        .unwrap();
+
+    let mut received = [0; 28];
+    server.read_exact(&mut received).await.unwrap();
+    let expected: Vec<u8> = (0..4).flat_map(tpkt_frame).collect();
+    assert_eq!(received.as_slice(), expected);
+
+    // The stream can be reunited after the session.
+    let _ = unsplit_tokio_framed(reader, writer);
+}
+
+#[tokio::test]
+async fn session_driver_sender_is_not_blocked_by_a_failed_writer() {
+    let (client, server) = tokio::io::duplex(16);
+    let (driver, writer_task) = session_driver(client, 1);
+    let mut sender = driver.sender();
+    drop(server);
+
+    let writer_task = tokio::spawn(writer_task.run());
+
+    // The queue would be full after two frames if the writer task was not stopped.
+    let sent = tokio::time::timeout(Duration::from_secs(5), async {
+        for id in 0.. {
+            if let Err(error) = sender.send(tpkt_frame(id).to_vec()).await {
+                return error;
             }
         }
-        (stage, framed)
+        unreachable!()
     })
     .await
-}
+    .expect("sending fails instead of waiting forever");
+    assert!(matches!(sent, SendError::Disconnected(_)));
+    assert!(sender.is_closed());
+    assert!(matches!(
+        sender.try_send(tpkt_frame(0).to_vec()),
+        Err(SendError::Disconnected(_))
+    ));
 
-type DisplayUpdatesRx = Arc<Mutex<UnboundedReceiver<DisplayUpdate>>>;
+    let error = writer_task.await.unwrap().err().expect("write error");
+    assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    drop(driver);
+}
 
-struct TestDisplayUpdates {
-    rx: DisplayUpdatesRx,
+#[derive(Debug)]
+enum TwoStepState {
+    SendRequest,
+    WaitResponse,
+    Done,
 }
 
-#[async_trait::async_trait]
-impl RdpServerDisplayUpdates for TestDisplayUpdates {
-    async fn next_update(&mut self) -> Option<DisplayUpdate> {
-        let mut rx = self.rx.lock().await;
+impl connector::State for TwoStepState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::SendRequest => "SendRequest",
+            Self::WaitResponse => "WaitResponse",
+            Self::Done => "Done",
+        }
+    }
 
-        rx.recv().await
+    fn is_terminal(&self) -> bool {
+        matches!(self, Self::Done)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
     }
 }
 
-struct TestDisplay {
-    rx: DisplayUpdatesRx,
+/// Sends `tpkt_frame(1)`, then waits for an X.224 PDU and answers it with `tpkt_frame(3)`.
+struct TwoStepSequence {
+    state: TwoStepState,
 }
 
-#[async_trait::async_trait]
-impl RdpServerDisplay for TestDisplay {
-    async fn size(&mut self) -> DesktopSize {
-        DesktopSize {
-            width: DESKTOP_WIDTH,
-            height: DESKTOP_HEIGHT,
+impl connector::Sequence for TwoStepSequence {
+    fn next_pdu_hint(&self) -> Option<&dyn pdu::PduHint> {
+        match self.state {
+            TwoStepState::WaitResponse => Some(&pdu::X224_HINT),
+            _ => None,
         }
     }
 
-    async fn updates(&mut self) -> Result<Box<dyn RdpServerDisplayUpdates>> {
-        Ok(Box::new(TestDisplayUpdates {
-            rx: Arc::clone(&self.rx),
-        }))
+    fn state(&self) -> &dyn connector::State {
+        &self.state
+    }
+
+    fn step(&mut self, _: &[u8], output: &mut WriteBuf) -> connector::ConnectorResult<connector::Written> {
+        let (frame, next_state) = match self.state {
+            TwoStepState::SendRequest => (tpkt_frame(1), TwoStepState::WaitResponse),
+            TwoStepState::WaitResponse => (tpkt_frame(3), TwoStepState::Done),
+            TwoStepState::Done => unreachable!(),
+        };
+
+        output.write_slice(&frame);
+        self.state = next_state;
+
+        connector::Written::from_size(frame.len())
     }
 }
 
-struct TestInputHandler;
-impl RdpServerInputHandler for TestInputHandler {
-    fn keyboard(&mut self, _: KeyboardEvent) {}
-    fn mouse(&mut self, _: MouseEvent) {}
+type TracedPdu = (TraceDirection, &'static str, Option<String>, Vec<u8>);
+
+fn traced_pdu(event: TraceEvent<'_>) -> TracedPdu {
+    (
+        event.direction,
+        event.state,
+        event.hint.map(|hint| format!("{hint:?}")),
+        event.bytes.to_vec(),
+    )
+}
+
+#[tokio::test]
+async fn pdu_trace_sink_receives_sequence_pdus_in_order() {
+    let reader = mock_recv_stream(vec![Ok(tpkt_frame(2).to_vec())]);
+    let mut framed = LocalFuturesFramed::new(ChunkedStream::new(reader, MockSendStream::default()));
+
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    framed.set_pdu_trace_sink({
+        let events = Arc::clone(&events);
+        move |event| events.lock().unwrap().push(traced_pdu(event))
+    });
+
+    let mut sequence = TwoStepSequence {
+        state: TwoStepState::SendRequest,
+    };
+    let mut buf = WriteBuf::new();
+    ironrdp_async::single_sequence_step(&mut framed, &mut sequence, &mut buf)
+        .await
+        .unwrap();
+    ironrdp_async::single_sequence_step(&mut framed, &mut sequence, &mut buf)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            (TraceDirection::Sent, "SendRequest", None, tpkt_frame(1).to_vec()),
+            (
+                TraceDirection::Received,
+                "WaitResponse",
+                Some("X224Hint".to_owned()),
+                tpkt_frame(2).to_vec()
+            ),
+            (TraceDirection::Sent, "WaitResponse", None, tpkt_frame(3).to_vec()),
+        ]
+    );
+
+    let (stream, _) = framed.into_inner();
+    let (_, sink) = stream.into_inner();
+    assert_eq!(sink.chunks, [tpkt_frame(1).to_vec(), tpkt_frame(3).to_vec()]);
+}
+
+#[test]
+fn pdu_log_round_trips_trace_events() {
+    let request = tpkt_frame(1);
+    let response = tpkt_frame(2);
+    let events = [
+        TraceEvent {
+            direction: TraceDirection::Sent,
+            state: "SendRequest",
+            hint: None,
+            bytes: &request,
+        },
+        TraceEvent {
+            direction: TraceDirection::Received,
+            state: "WaitResponse",
+            hint: Some(&pdu::X224_HINT),
+            bytes: &response,
+        },
+    ];
+
+    let mut writer = PduLogWriter::new(Vec::new()).unwrap();
+    for event in &events {
+        writer.write_event(event).unwrap();
+    }
+    let log = writer.into_inner();
+
+    let records = PduLogReader::new(log.as_slice())
+        .unwrap()
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+
+    let records: Vec<_> = records
+        .into_iter()
+        .map(|record| (record.direction, record.state, record.hint, record.bytes))
+        .collect();
+    assert_eq!(
+        records,
+        [
+            (
+                TraceDirection::Sent,
+                "SendRequest".to_owned(),
+                String::new(),
+                request.to_vec()
+            ),
+            (
+                TraceDirection::Received,
+                "WaitResponse".to_owned(),
+                "X224Hint".to_owned(),
+                response.to_vec()
+            ),
+        ]
+    );
+
+    let Err(error) = PduLogReader::new(&log[1..]) else {
+        panic!("log without its magic number");
+    };
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
 }
 
 async fn client_server<F, Fut>(client_config: connector::Config, clientfn: F)
 where
-    F: FnOnce(ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
-    Fut: Future<Output = (ActiveStage, Framed<TokioStream<TlsStream<TcpStream>>>)>,
+    F: FnOnce(ActiveStage, UpgradedFramed, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
+    Fut: Future<Output = (ActiveStage, UpgradedFramed)>,
+{
+    client_server_with_cliprdr(client_config, None, clientfn).await
+}
+
+/// Same as [`client_server`], with a clipboard channel on both ends when `cliprdr_factory` is provided.
+async fn client_server_with_cliprdr<F, Fut>(
+    client_config: connector::Config,
+    cliprdr_factory: Option<Box<dyn server::CliprdrServerFactory>>,
+    clientfn: F,
+) where
+    F: FnOnce(ActiveStage, UpgradedFramed, UnboundedSender<DisplayUpdate>) -> Fut + 'static,
+    Fut: Future<Output = (ActiveStage, UpgradedFramed)>,
 {
+    let with_cliprdr = cliprdr_factory.is_some();
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .try_init();
@@ -177,6 +2250,7 @@ where
         .with_display_handler(TestDisplay {
             rx: Arc::new(Mutex::new(display_rx)),
         })
+        .with_cliprdr_factory(cliprdr_factory)
         .build();
     server.set_credentials(Some(server::Credentials {
         username: USERNAME.into(),
@@ -196,32 +2270,8 @@ where
                 let (tx, rx) = oneshot::channel();
                 ev.send(ServerEvent::GetLocalAddr(tx)).unwrap();
                 let addr = rx.await.unwrap().unwrap();
-                let tcp_stream = TcpStream::connect(addr).await.expect("TCP connect");
-                let mut framed = ironrdp_tokio::TokioFramed::new(tcp_stream);
-                let mut connector = connector::ClientConnector::new(client_config).with_server_addr(addr);
-                let should_upgrade = ironrdp_async::connect_begin(&mut framed, &mut connector)
-                    .await
-                    .expect("begin connection");
-                let initial_stream = framed.into_inner_no_leftover();
-                let (upgraded_stream, server_public_key) = ironrdp_tls::upgrade(initial_stream, "localhost")
-                    .await
-                    .expect("TLS upgrade");
-                let upgraded = ironrdp_tokio::mark_as_upgraded(should_upgrade, &mut connector);
-                let mut upgraded_framed = ironrdp_tokio::TokioFramed::new(upgraded_stream);
-                let connection_result = ironrdp_async::connect_finalize(
-                    upgraded,
-                    &mut upgraded_framed,
-                    connector,
-                    "localhost".into(),
-                    server_public_key,
-                    None,
-                    None,
-                )
-                .await
-                .expect("finalize connection");
-
-                let active_stage = ActiveStage::new(connection_result);
-                let (active_stage, mut upgraded_framed) = clientfn(active_stage, upgraded_framed, display_tx).await;
+                let (active_stage, upgraded_framed) = connect_client(addr, client_config, with_cliprdr).await;
+                let (mut active_stage, mut upgraded_framed) = clientfn(active_stage, upgraded_framed, display_tx).await;
                 let outputs = active_stage.graceful_shutdown().expect("shutdown");
                 for out in outputs {
                     match out {
@@ -244,6 +2294,38 @@ where
         .await;
 }
 
+/// Connects a client to the server listening on `addr`, with a clipboard channel when `with_cliprdr` is set.
+async fn connect_client(
+    addr: SocketAddr,
+    client_config: connector::Config,
+    with_cliprdr: bool,
+) -> (ActiveStage, UpgradedFramed) {
+    try_connect_client(addr, client_config, with_cliprdr)
+        .await
+        .expect("connection")
+}
+
+/// Same as [`connect_client`], but returns the errors of the connection sequence.
+async fn try_connect_client(
+    addr: SocketAddr,
+    client_config: connector::Config,
+    with_cliprdr: bool,
+) -> connector::ConnectorResult<(ActiveStage, UpgradedFramed)> {
+    let mut connector = connector::ClientConnector::new(client_config);
+    if with_cliprdr {
+        connector.attach_static_channel(CliprdrClient::new(Box::<TestCliprdrBackend>::default()));
+    }
+    let (upgraded_framed, connection_result) =
+        ironrdp_tokio::connect(ConnectOptions::new(connector, "localhost", addr.port()))
+            .await
+            .map_err(|e| match e {
+                ConnectError::Connector(e) => e,
+                e => panic!("connection: {e:?}"),
+            })?;
+
+    Ok((ActiveStage::new(connection_result), upgraded_framed))
+}
+
 // Maybe implement Default for Config
 fn default_client_config() -> connector::Config {
     connector::Config {
@@ -259,6 +2341,7 @@ fn default_client_config() -> connector::Config {
             password: PASSWORD.into(),
         },
         domain: None,
+        credential_delegation: connector::CredentialDelegation::Full,
         client_build: semver::Version::parse(env!("CARGO_PKG_VERSION"))
             .map(|version| version.major * 100 + version.minor * 10 + version.patch)
             .unwrap_or(0)
@@ -268,6 +2351,7 @@ fn default_client_config() -> connector::Config {
         keyboard_type: gcc::KeyboardType::IbmEnhanced,
         keyboard_subtype: 0,
         keyboard_layout: 0,
+        active_input_locale: None,
         keyboard_functional_keys_count: 12,
         ime_file_name: "".into(),
         bitmap: None,
@@ -295,8 +2379,17 @@ fn default_client_config() -> connector::Config {
         platform: MajorPlatformType::UNIX,
         hardware_id: None,
         request_data: None,
+        correlation_id: None,
         autologon: false,
         license_cache: None,
+        remote_app: None,
+        auto_reconnect_cookie: None,
+        frame_markers: true,
+        timeouts: connector::ConnectTimeouts::default(),
+        bitmap_cache: None,
+        extra_capability_sets: Vec::new(),
+        decline_multitransport: true,
+        redirect_session: None,
         no_server_pointer: true,
         pointer_software_rendering: true,
         performance_flags: Default::default(),
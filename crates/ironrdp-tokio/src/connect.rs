@@ -0,0 +1,412 @@
+//! Ready-made connection sequence, from the transport up to the active session.
+
+use core::fmt;
+use core::time::Duration;
+use std::io;
+
+use ironrdp_async::{AsyncNetworkClient, ConnectTimer, ConnectionOutcome, FramedWrite as _};
+use ironrdp_connector::credssp::KerberosConfig;
+use ironrdp_connector::{
+    ClientConnector, ClientConnectorState, ConnectionResult, ConnectorError, ConnectorErrorKind, Sequence as _,
+    ServerName,
+};
+use ironrdp_core::WriteBuf;
+use ironrdp_pdu::PduHint;
+use ironrdp_rdcleanpath::{DetectionResult, RDCleanPath, RDCleanPathPdu};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+use crate::{TokioFramed, TokioTimer};
+
+/// Stream usable as the transport of a connection
+pub trait AsyncReadWrite: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<S> AsyncReadWrite for S where S: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+/// Transport of the connections established by [`connect`]
+///
+/// The concrete stream depends on the route taken to reach the server: a TLS stream over TCP when connecting
+/// directly, or the stream to the proxy when going through RDCleanPath.
+pub type ErasedStream = Box<dyn AsyncReadWrite>;
+
+/// Parameters of the RDCleanPath exchange, when the server is reached through a proxy such as Devolutions Gateway
+///
+/// The proxy performs the TLS handshake with the server on behalf of the client.
+pub struct RdCleanPathProxy {
+    /// Established connection to the proxy, e.g. a WebSocket adapted to the Tokio I/O traits.
+    pub stream: ErasedStream,
+    /// Token authorizing the connection to the destination.
+    pub auth_token: String,
+    /// Preconnection blob, sent by the proxy to the server.
+    pub pcb: Option<String>,
+}
+
+impl fmt::Debug for RdCleanPathProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RdCleanPathProxy")
+            .field("pcb", &self.pcb)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parameters of [`connect`]
+///
+/// The [`ConnectTimeouts`](ironrdp_connector::ConnectTimeouts) of the connector configuration are enforced for the
+/// whole connection sequence.
+pub struct ConnectOptions {
+    connector: ClientConnector,
+    host: String,
+    port: u16,
+    proxy: Option<RdCleanPathProxy>,
+    tls_server_name: Option<String>,
+    transport_timeout: Option<Duration>,
+    network_client: Option<Box<dyn AsyncNetworkClient + Send>>,
+    kerberos_config: Option<KerberosConfig>,
+}
+
+impl ConnectOptions {
+    /// Connects to the server listening on `host`:`port` with `connector`, the static channels being attached
+    /// beforehand.
+    pub fn new(connector: ClientConnector, host: impl Into<String>, port: u16) -> Self {
+        Self {
+            connector,
+            host: host.into(),
+            port,
+            proxy: None,
+            tls_server_name: None,
+            transport_timeout: None,
+            network_client: None,
+            kerberos_config: None,
+        }
+    }
+
+    /// Reaches the server through an RDCleanPath proxy instead of connecting to it directly.
+    #[must_use]
+    pub fn with_rdcleanpath_proxy(mut self, proxy: RdCleanPathProxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Name verified against the certificate of the server, the host by default.
+    #[must_use]
+    pub fn with_tls_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.tls_server_name = Some(server_name.into());
+        self
+    }
+
+    /// Budget of each of the TCP connection, the TLS handshake and the RDCleanPath exchange.
+    ///
+    /// These steps are not covered by the connector timeouts, and are not limited by default.
+    #[must_use]
+    pub fn with_transport_timeout(mut self, timeout: Duration) -> Self {
+        self.transport_timeout = Some(timeout);
+        self
+    }
+
+    /// Client sending the network requests of CredSSP, e.g. to a KDC.
+    #[must_use]
+    pub fn with_network_client(mut self, network_client: Box<dyn AsyncNetworkClient + Send>) -> Self {
+        self.network_client = Some(network_client);
+        self
+    }
+
+    #[must_use]
+    pub fn with_kerberos_config(mut self, kerberos_config: KerberosConfig) -> Self {
+        self.kerberos_config = Some(kerberos_config);
+        self
+    }
+}
+
+impl fmt::Debug for ConnectOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectOptions")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("proxy", &self.proxy)
+            .field("tls_server_name", &self.tls_server_name)
+            .field("transport_timeout", &self.transport_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error returned by [`connect`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConnectError {
+    /// The TCP connection to the server could not be established.
+    Tcp(io::Error),
+    /// The TLS handshake with the server failed.
+    Tls(io::Error),
+    /// The RDCleanPath exchange with the proxy failed.
+    RdCleanPath(io::Error),
+    /// The connection, TLS handshake or RDCleanPath exchange did not complete within the transport timeout.
+    TransportTimeout { step: &'static str },
+    /// The connection sequence failed.
+    Connector(ConnectorError),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(_) => write!(f, "TCP connection failed"),
+            Self::Tls(_) => write!(f, "TLS upgrade failed"),
+            Self::RdCleanPath(_) => write!(f, "RDCleanPath exchange failed"),
+            Self::TransportTimeout { step } => write!(f, "{step} timed out"),
+            Self::Connector(_) => write!(f, "connection sequence failed"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Tcp(e) | Self::Tls(e) | Self::RdCleanPath(e) => Some(e),
+            Self::TransportTimeout { .. } => None,
+            Self::Connector(e) => Some(e),
+        }
+    }
+}
+
+impl From<ConnectorError> for ConnectError {
+    fn from(e: ConnectorError) -> Self {
+        Self::Connector(e)
+    }
+}
+
+/// Maximum number of server redirections followed by [`connect`], guarding against redirection loops.
+const MAX_REDIRECTIONS: usize = 3;
+
+/// Connects to an RDP server, and goes through the whole connection sequence.
+///
+/// The server is reached over TCP and the connection is upgraded to TLS, unless an RDCleanPath proxy is
+/// configured. The CredSSP step is then performed if negotiated, and the returned transport is ready for the active
+/// session.
+///
+/// # Server redirection
+///
+/// When a load balancer or a server of a farm redirects the client, the connection is closed and the whole sequence
+/// is run again against the target server, on the same port. The X.224 Connection Request PDU carries the routing
+/// token provided by the server, and the credentials provided by the server replace the configured ones (see
+/// [`ClientConnector::redirect`]). Up to three redirections are followed.
+///
+/// The connection to the proxy can't be reopened, hence the redirections are not followed when going through
+/// RDCleanPath: the connection fails with a [`ConnectorErrorKind::Redirected`] error instead.
+pub async fn connect(options: ConnectOptions) -> Result<(TokioFramed<ErasedStream>, ConnectionResult), ConnectError> {
+    let ConnectOptions {
+        mut connector,
+        mut host,
+        port,
+        mut proxy,
+        mut tls_server_name,
+        transport_timeout,
+        mut network_client,
+        kerberos_config,
+    } = options;
+
+    let mut timer = ConnectTimer::new(TokioTimer);
+    let mut redirections = 0;
+
+    loop {
+        let is_proxied = proxy.is_some();
+
+        let (mut framed, upgraded, server_public_key) = match proxy.take() {
+            None => {
+                let stream = with_timeout(
+                    transport_timeout,
+                    "TCP connection",
+                    TcpStream::connect((host.as_str(), port)),
+                )
+                .await?
+                .map_err(ConnectError::Tcp)?;
+
+                let server_addr = stream.peer_addr().map_err(ConnectError::Tcp)?;
+                connector.attach_server_addr(server_addr);
+
+                let mut framed = TokioFramed::new(stream);
+                let should_upgrade = crate::connect_begin(&mut framed, &mut connector, &mut timer).await?;
+
+                let server_name = tls_server_name.as_deref().unwrap_or(&host);
+                let (stream, server_public_key) = with_timeout(
+                    transport_timeout,
+                    "TLS upgrade",
+                    ironrdp_tls::upgrade(framed.into_inner_no_leftover(), server_name),
+                )
+                .await?
+                .map_err(ConnectError::Tls)?;
+
+                let upgraded = crate::mark_as_upgraded(should_upgrade, &mut connector);
+
+                (
+                    TokioFramed::new(Box::new(stream) as ErasedStream),
+                    upgraded,
+                    server_public_key,
+                )
+            }
+            Some(proxy) => {
+                let mut framed = TokioFramed::new(proxy.stream);
+                let destination = format!("{host}:{port}");
+
+                let server_public_key = with_timeout(
+                    transport_timeout,
+                    "RDCleanPath exchange",
+                    rdcleanpath_exchange(&mut framed, &mut connector, destination, proxy.auth_token, proxy.pcb),
+                )
+                .await??;
+
+                // The TLS session is established by the proxy.
+                let should_upgrade = crate::skip_connect_begin(&mut connector);
+                let upgraded = crate::mark_as_upgraded(should_upgrade, &mut connector);
+
+                (framed, upgraded, server_public_key)
+            }
+        };
+
+        let outcome = crate::connect_finalize_or_redirect(
+            upgraded,
+            &mut framed,
+            connector,
+            &mut timer,
+            ServerName::new(host.clone()),
+            server_public_key,
+            network_client
+                .as_deref_mut()
+                .map(|client| client as &mut dyn AsyncNetworkClient),
+            kerberos_config.clone(),
+        )
+        .await?;
+
+        let (redirected_connector, redirection) = match outcome {
+            ConnectionOutcome::Connected(connection_result) => return Ok((framed, connection_result)),
+            ConnectionOutcome::Redirected { connector, redirection } => (connector, redirection),
+        };
+
+        if is_proxied || redirections == MAX_REDIRECTIONS {
+            return Err(ConnectError::Connector(ConnectorError::new(
+                "connect",
+                ConnectorErrorKind::Redirected(Box::new(redirection)),
+            )));
+        }
+
+        redirections += 1;
+
+        // The current connection is closed before connecting to the target server.
+        drop(framed);
+
+        connector = *redirected_connector;
+        connector.redirect(&redirection);
+
+        if let Some(target) = redirection.target {
+            host = target;
+            tls_server_name = None;
+        }
+    }
+}
+
+async fn with_timeout<F: core::future::Future>(
+    timeout: Option<Duration>,
+    step: &'static str,
+    future: F,
+) -> Result<F::Output, ConnectError> {
+    match timeout {
+        Some(duration) => crate::timeout(&TokioTimer, duration, future)
+            .await
+            .map_err(|_| ConnectError::TransportTimeout { step }),
+        None => Ok(future.await),
+    }
+}
+
+/// Sends the X.224 connection request through the proxy, and returns the public key of the server.
+async fn rdcleanpath_exchange(
+    framed: &mut TokioFramed<ErasedStream>,
+    connector: &mut ClientConnector,
+    destination: String,
+    auth_token: String,
+    pcb: Option<String>,
+) -> Result<Vec<u8>, ConnectError> {
+    #[derive(Clone, Copy, Debug)]
+    struct RDCleanPathHint;
+
+    impl PduHint for RDCleanPathHint {
+        fn find_size(&self, bytes: &[u8]) -> ironrdp_core::DecodeResult<Option<(bool, usize)>> {
+            match RDCleanPathPdu::detect(bytes) {
+                DetectionResult::Detected { total_length, .. } => Ok(Some((true, total_length))),
+                DetectionResult::NotEnoughBytes => Ok(None),
+                DetectionResult::Failed => Err(ironrdp_core::other_err!(
+                    "RDCleanPathHint",
+                    "detection failed (invalid PDU)"
+                )),
+            }
+        }
+    }
+
+    let error = |context: &str| ConnectError::RdCleanPath(io::Error::other(context.to_owned()));
+
+    let ClientConnectorState::ConnectionInitiationSendRequest = connector.state else {
+        return Err(error("invalid connector state (send request)"));
+    };
+
+    let mut buf = WriteBuf::new();
+    connector.step_no_input(&mut buf)?;
+    let x224_pdu = buf.filled().to_vec();
+
+    let request = RDCleanPathPdu::new_request(x224_pdu, destination, auth_token, pcb)
+        .and_then(|request| request.to_der())
+        .map_err(|e| ConnectError::RdCleanPath(io::Error::other(e)))?;
+
+    framed.write_all(&request).await.map_err(ConnectError::RdCleanPath)?;
+
+    let response = framed
+        .read_by_hint(&RDCleanPathHint)
+        .await
+        .map_err(ConnectError::RdCleanPath)?;
+
+    let response = RDCleanPathPdu::from_der(&response)
+        .map_err(|e| ConnectError::RdCleanPath(io::Error::other(e)))?
+        .into_enum()
+        .map_err(|e| ConnectError::RdCleanPath(io::Error::other(e)))?;
+
+    let (x224_connection_response, server_cert_chain, server_addr) = match response {
+        RDCleanPath::Response {
+            x224_connection_response,
+            server_cert_chain,
+            server_addr,
+            ..
+        } => (x224_connection_response, server_cert_chain, server_addr),
+        RDCleanPath::Request { .. } => return Err(error("received an unexpected RDCleanPath request")),
+        RDCleanPath::Err(e) => return Err(ConnectError::RdCleanPath(io::Error::other(e))),
+    };
+
+    let server_addr = server_addr
+        .parse()
+        .map_err(|e| ConnectError::RdCleanPath(io::Error::other(e)))?;
+    connector.attach_server_addr(server_addr);
+
+    let ClientConnectorState::ConnectionInitiationWaitConfirm { .. } = connector.state else {
+        return Err(error("invalid connector state (wait confirm)"));
+    };
+
+    buf.clear();
+    connector.step(x224_connection_response.as_bytes(), &mut buf)?;
+
+    let server_cert = server_cert_chain
+        .first()
+        .ok_or_else(|| error("server certificate chain missing from the RDCleanPath response"))?;
+
+    server_public_key(server_cert.as_bytes()).map_err(ConnectError::RdCleanPath)
+}
+
+fn server_public_key(cert: &[u8]) -> io::Result<Vec<u8>> {
+    use x509_cert::der::Decode as _;
+
+    let cert = x509_cert::Certificate::from_der(cert).map_err(io::Error::other)?;
+
+    let server_public_key = cert
+        .tbs_certificate
+        .subject_public_key_info
+        .subject_public_key
+        .as_bytes()
+        .ok_or_else(|| io::Error::other("subject public key BIT STRING is not aligned"))?
+        .to_owned();
+
+    Ok(server_public_key)
+}
@@ -4,6 +4,12 @@
 #[rustfmt::skip] // do not re-order this pub use
 pub use ironrdp_async::*;
 
+#[cfg(feature = "connect")]
+mod connect;
+
+#[cfg(feature = "connect")]
+pub use self::connect::*;
+
 use core::pin::Pin;
 use std::io;
 
@@ -155,3 +161,15 @@ where
         })
     }
 }
+
+/// [`AsyncTimer`] backed by the Tokio timer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+impl AsyncTimer for TokioTimer {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, duration: core::time::Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
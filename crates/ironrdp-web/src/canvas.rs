@@ -1,28 +1,60 @@
 use core::num::NonZeroU32;
 
+use anyhow::Context as _;
+use ironrdp::core::BufPool;
 use ironrdp::pdu::geometry::{InclusiveRectangle, Rectangle as _};
+use ironrdp::session::image::{DecodedImage, ImageRegionExtractor, PixelOrder, RowAlignment};
 use softbuffer::{NoDisplayHandle, NoWindowHandle};
-use web_sys::HtmlCanvasElement;
+use web_sys::{HtmlCanvasElement, OffscreenCanvas};
+
+/// Canvas the session is rendered on
+///
+/// An `OffscreenCanvas` is used when the session runs in a worker, see `SessionBuilder::connect_in_worker`.
+#[derive(Clone)]
+pub(crate) enum RenderCanvas {
+    Html(HtmlCanvasElement),
+    Offscreen(OffscreenCanvas),
+}
+
+impl RenderCanvas {
+    pub(crate) fn set_size(&self, width: u32, height: u32) {
+        match self {
+            RenderCanvas::Html(canvas) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+            RenderCanvas::Offscreen(canvas) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+        }
+    }
+}
 
 pub(crate) struct Canvas {
     width: u32,
     surface: softbuffer::Surface<NoDisplayHandle, NoWindowHandle>,
+    extractor: ImageRegionExtractor,
+    /// Buffers the updated regions are extracted into, large enough to hold the whole canvas.
+    region_pool: BufPool,
 }
 
 impl Canvas {
-    pub(crate) fn new(render_canvas: HtmlCanvasElement, width: u32, height: u32) -> anyhow::Result<Self> {
-        render_canvas.set_width(width);
-        render_canvas.set_height(height);
+    pub(crate) fn new(render_canvas: RenderCanvas, width: u32, height: u32) -> anyhow::Result<Self> {
+        render_canvas.set_size(width, height);
 
         #[cfg(target_arch = "wasm32")]
         let mut surface = {
             use softbuffer::SurfaceExtWeb as _;
-            softbuffer::Surface::from_canvas(render_canvas).expect("surface")
+            match render_canvas {
+                RenderCanvas::Html(canvas) => softbuffer::Surface::from_canvas(canvas).expect("surface"),
+                RenderCanvas::Offscreen(canvas) => softbuffer::Surface::from_offscreen_canvas(canvas).expect("surface"),
+            }
         };
 
         #[cfg(not(target_arch = "wasm32"))]
         let mut surface = {
-            fn stub(_: HtmlCanvasElement) -> softbuffer::Surface<NoDisplayHandle, NoWindowHandle> {
+            fn stub(_: RenderCanvas) -> softbuffer::Surface<NoDisplayHandle, NoWindowHandle> {
                 unimplemented!()
             }
 
@@ -33,19 +65,31 @@ impl Canvas {
             .resize(NonZeroU32::new(width).unwrap(), NonZeroU32::new(height).unwrap())
             .expect("surface resize");
 
-        Ok(Self { width, surface })
+        Ok(Self {
+            width,
+            surface,
+            extractor: ImageRegionExtractor::new(RowAlignment::Tight, PixelOrder::RgbA),
+            region_pool: region_pool(width, height),
+        })
     }
 
     pub(crate) fn resize(&mut self, width: NonZeroU32, height: NonZeroU32) {
         self.surface.resize(width, height).expect("surface resize");
         self.width = width.get();
+        self.region_pool = region_pool(width.get(), height.get());
     }
 
-    pub(crate) fn draw(&mut self, buffer: &[u8], region: InclusiveRectangle) -> anyhow::Result<()> {
+    /// Draws the updated `region` of `image`.
+    pub(crate) fn draw(&mut self, image: &DecodedImage, region: InclusiveRectangle) -> anyhow::Result<()> {
         let region_width = region.width();
         let region_height = region.height();
 
-        let mut src = buffer.chunks_exact(4).map(|pixel| {
+        let extracted = self
+            .extractor
+            .extract_pooled(image, &region, &self.region_pool)
+            .context("extract updated region")?;
+
+        let mut src = extracted.data().chunks_exact(4).map(|pixel| {
             let r = pixel[0];
             let g = pixel[1];
             let b = pixel[2];
@@ -90,3 +134,12 @@ impl Canvas {
         Ok(())
     }
 }
+
+fn region_pool(width: u32, height: u32) -> BufPool {
+    let canvas_len = usize::try_from(width)
+        .ok()
+        .zip(usize::try_from(height).ok())
+        .and_then(|(width, height)| width.checked_mul(height)?.checked_mul(4));
+
+    BufPool::with_max_retained_capacity(canvas_len.unwrap_or(usize::MAX))
+}
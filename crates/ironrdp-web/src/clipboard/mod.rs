@@ -24,18 +24,38 @@ use ironrdp::cliprdr::pdu::{
 use ironrdp_cliprdr_format::bitmap::{dib_to_png, dibv5_to_png, png_to_cf_dibv5};
 use ironrdp_cliprdr_format::html::{cf_html_to_plain_html, plain_html_to_cf_html};
 use ironrdp_core::{impl_as_any, IntoOwned};
-use transaction::{ClipboardContent, ClipboardContentValue};
 use wasm_bindgen::prelude::*;
 
 use crate::session::RdpInputEvent;
 
 #[rustfmt::skip]
-pub(crate) use transaction::ClipboardTransaction;
+pub(crate) use transaction::{ClipboardContent, ClipboardContentValue, ClipboardTransaction};
 
 const MIME_TEXT: &str = "text/plain";
 const MIME_HTML: &str = "text/html";
 const MIME_PNG: &str = "image/png";
 
+/// Directions in which the clipboard is shared with the remote
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardPolicy {
+    Disabled,
+    HostToRemote,
+    RemoteToHost,
+    Bidirectional,
+}
+
+impl From<ClipboardPolicy> for ironrdp::cliprdr::ClipboardPolicy {
+    fn from(policy: ClipboardPolicy) -> Self {
+        match policy {
+            ClipboardPolicy::Disabled => Self::Disabled,
+            ClipboardPolicy::HostToRemote => Self::HostToRemote,
+            ClipboardPolicy::RemoteToHost => Self::RemoteToHost,
+            ClipboardPolicy::Bidirectional => Self::Bidirectional,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 struct ClientFormatDescriptor {
     id: ClipboardFormatId,
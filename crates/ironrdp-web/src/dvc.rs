@@ -0,0 +1,292 @@
+//! Dynamic virtual channels implemented by the JavaScript code
+//!
+//! The [`JsDvcProcessor`] registered in the DRDYNVC client forwards the events of its channel to the session loop,
+//! which calls the JavaScript callbacks. This way, the callbacks are never called while the DRDYNVC client is
+//! borrowed, and an exception thrown by one of them only closes its channel.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use futures_channel::mpsc;
+use ironrdp::dvc::{encode_dvc_messages, DvcClientProcessor, DvcEncode, DvcMessage, DvcProcessor};
+use ironrdp::pdu::PduResult;
+use ironrdp::svc::{ChannelFlags, SvcMessage};
+use ironrdp_core::{ensure_size, impl_as_any, Encode, EncodeResult, WriteCursor};
+use wasm_bindgen::prelude::*;
+
+use crate::session::RdpInputEvent;
+
+/// Events of a dynamic channel registered by the JavaScript code, forwarded to the session loop
+#[derive(Debug)]
+pub(crate) enum DvcEvent {
+    Opened { channel_name: String, channel_id: u32 },
+    Data { channel_id: u32, data: Vec<u8> },
+    Closed { channel_id: u32 },
+}
+
+/// Listener of a dynamic channel registered by the JavaScript code
+pub(crate) struct JsDvcProcessor {
+    channel_name: String,
+    tx: mpsc::UnboundedSender<RdpInputEvent>,
+}
+
+impl JsDvcProcessor {
+    pub(crate) fn new(channel_name: String, tx: mpsc::UnboundedSender<RdpInputEvent>) -> Self {
+        Self { channel_name, tx }
+    }
+
+    fn send(&self, event: DvcEvent) {
+        if self.tx.unbounded_send(RdpInputEvent::Dvc(event)).is_err() {
+            error!(
+                channel_name = self.channel_name,
+                "Failed to send DVC event, receiver is closed"
+            );
+        }
+    }
+}
+
+impl_as_any!(JsDvcProcessor);
+
+impl DvcProcessor for JsDvcProcessor {
+    fn channel_name(&self) -> &str {
+        &self.channel_name
+    }
+
+    fn start(&mut self, channel_id: u32) -> PduResult<Vec<DvcMessage>> {
+        self.send(DvcEvent::Opened {
+            channel_name: self.channel_name.clone(),
+            channel_id,
+        });
+        Ok(Vec::new())
+    }
+
+    fn process(&mut self, channel_id: u32, payload: &[u8]) -> PduResult<Vec<DvcMessage>> {
+        self.send(DvcEvent::Data {
+            channel_id,
+            data: payload.to_vec(),
+        });
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self, channel_id: u32) {
+        self.send(DvcEvent::Closed { channel_id });
+    }
+}
+
+impl DvcClientProcessor for JsDvcProcessor {}
+
+/// Message sent by the JavaScript code on a dynamic channel, as-is
+struct DvcData(Vec<u8>);
+
+impl Encode for DvcData {
+    fn encode(&self, dst: &mut WriteCursor<'_>) -> EncodeResult<()> {
+        ensure_size!(in: dst, size: self.0.len());
+        dst.write_slice(&self.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "DvcData"
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl DvcEncode for DvcData {}
+
+/// Wraps `data` in the DVC Data PDUs to be sent by the DRDYNVC client on `channel_id`.
+pub(crate) fn encode_dvc_data(channel_id: u32, data: Vec<u8>) -> anyhow::Result<Vec<SvcMessage>> {
+    encode_dvc_messages(channel_id, vec![Box::new(DvcData(data))], ChannelFlags::empty()).context("encode DVC data")
+}
+
+/// Callbacks of a dynamic channel, passed to `SessionBuilder::register_dvc_channel`
+#[derive(Clone)]
+pub(crate) struct JsDvcCallbacks {
+    on_open: js_sys::Function,
+    on_data: js_sys::Function,
+    on_close: js_sys::Function,
+}
+
+impl JsDvcCallbacks {
+    pub(crate) fn from_js(callbacks: &JsValue) -> anyhow::Result<Self> {
+        fn callback(callbacks: &JsValue, name: &str) -> anyhow::Result<js_sys::Function> {
+            js_sys::Reflect::get(callbacks, &JsValue::from_str(name))
+                .ok()
+                .and_then(|callback| callback.dyn_into::<js_sys::Function>().ok())
+                .with_context(|| format!("{name} callback missing"))
+        }
+
+        Ok(Self {
+            on_open: callback(callbacks, "onOpen")?,
+            on_data: callback(callbacks, "onData")?,
+            on_close: callback(callbacks, "onClose")?,
+        })
+    }
+}
+
+/// Dynamic channels registered by the JavaScript code, and the ones currently opened by the server
+pub(crate) struct JsDvcChannels {
+    callbacks: BTreeMap<String, JsDvcCallbacks>,
+    /// Names of the opened channels, by channel ID.
+    opened: BTreeMap<u32, String>,
+}
+
+impl JsDvcChannels {
+    pub(crate) fn new(callbacks: BTreeMap<String, JsDvcCallbacks>) -> Self {
+        Self {
+            callbacks,
+            opened: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the listeners to register in the DRDYNVC client of each connection.
+    pub(crate) fn processors(&self, tx: &mpsc::UnboundedSender<RdpInputEvent>) -> Vec<JsDvcProcessor> {
+        self.callbacks
+            .keys()
+            .map(|channel_name| JsDvcProcessor::new(channel_name.clone(), tx.clone()))
+            .collect()
+    }
+
+    pub(crate) fn is_open(&self, channel_id: u32) -> bool {
+        self.opened.contains_key(&channel_id)
+    }
+
+    /// Calls the callback matching `event`.
+    ///
+    /// If the callback throws, the error is logged and the name of the channel to close is returned.
+    pub(crate) fn dispatch(&mut self, event: DvcEvent) -> Option<String> {
+        let (channel_name, result) = match event {
+            DvcEvent::Opened {
+                channel_name,
+                channel_id,
+            } => {
+                let callbacks = self.callbacks.get(&channel_name)?;
+                let result = callbacks.on_open.call1(&JsValue::NULL, &JsValue::from(channel_id));
+                self.opened.insert(channel_id, channel_name.clone());
+                (channel_name, result)
+            }
+            DvcEvent::Data { channel_id, data } => {
+                let channel_name = self.opened.get(&channel_id)?;
+                let result = self.callbacks[channel_name]
+                    .on_data
+                    .call1(&JsValue::NULL, &js_sys::Uint8Array::from(data.as_slice()));
+                (channel_name.clone(), result)
+            }
+            DvcEvent::Closed { channel_id } => {
+                let channel_name = self.opened.remove(&channel_id)?;
+                let result = self.callbacks[&channel_name].on_close.call0(&JsValue::NULL);
+                (channel_name, result)
+            }
+        };
+
+        match result {
+            Ok(_) => None,
+            Err(e) => {
+                error!(channel_name, error = ?e, "DVC callback failed, closing the channel");
+                // A channel closed by the server is not closed again.
+                self.opened
+                    .values()
+                    .any(|name| *name == channel_name)
+                    .then_some(channel_name)
+            }
+        }
+    }
+
+    /// Notifies the closing of the opened channels, when the connection is lost.
+    pub(crate) fn close_all(&mut self) {
+        for (channel_id, channel_name) in core::mem::take(&mut self.opened) {
+            if let Err(e) = self.callbacks[&channel_name].on_close.call0(&JsValue::NULL) {
+                error!(channel_name, channel_id, error = ?e, "DVC close callback failed");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp::dvc::pdu::{
+        CapabilitiesRequestPdu, CapsVersion, CreateRequestPdu, CreateResponsePdu, CreationStatus, DataPdu,
+        DrdynvcClientPdu, DrdynvcDataPdu, DrdynvcServerPdu,
+    };
+    use ironrdp::dvc::DrdynvcClient;
+    use ironrdp::svc::{StaticVirtualChannel, SvcProcessor as _};
+    use ironrdp_core::{decode, encode_vec};
+
+    use super::*;
+
+    const CHANNEL_NAME: &str = "Example::Echo";
+    const CHANNEL_ID: u32 = 3;
+
+    fn client_pdus(messages: Vec<SvcMessage>) -> Vec<DrdynvcClientPdu> {
+        StaticVirtualChannel::chunkify(messages)
+            .unwrap()
+            .into_iter()
+            // Skips the channel PDU header.
+            .map(|chunk| decode::<DrdynvcClientPdu>(&chunk.filled()[8..]).unwrap())
+            .collect()
+    }
+
+    fn process(drdynvc: &mut DrdynvcClient, pdu: DrdynvcServerPdu) -> Vec<DrdynvcClientPdu> {
+        client_pdus(drdynvc.process(&encode_vec(&pdu).unwrap()).unwrap())
+    }
+
+    fn next_event(rx: &mut mpsc::UnboundedReceiver<RdpInputEvent>) -> DvcEvent {
+        match rx.try_next() {
+            Ok(Some(RdpInputEvent::Dvc(event))) => event,
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn echo_channel() {
+        let (tx, mut rx) = mpsc::unbounded();
+        let mut drdynvc = DrdynvcClient::new().with_dynamic_channel(JsDvcProcessor::new(CHANNEL_NAME.to_owned(), tx));
+
+        process(
+            &mut drdynvc,
+            DrdynvcServerPdu::Capabilities(CapabilitiesRequestPdu::new(CapsVersion::V1, None)),
+        );
+        let responses = process(
+            &mut drdynvc,
+            DrdynvcServerPdu::Create(CreateRequestPdu::new(CHANNEL_ID, CHANNEL_NAME.to_owned())),
+        );
+        assert_eq!(
+            responses,
+            [DrdynvcClientPdu::Create(CreateResponsePdu::new(
+                CHANNEL_ID,
+                CreationStatus::OK
+            ))]
+        );
+        assert!(matches!(
+            next_event(&mut rx),
+            DvcEvent::Opened { channel_name, channel_id: CHANNEL_ID } if channel_name == CHANNEL_NAME
+        ));
+
+        let responses = process(
+            &mut drdynvc,
+            DrdynvcServerPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(CHANNEL_ID, b"ping".to_vec()))),
+        );
+        assert!(responses.is_empty());
+        let DvcEvent::Data { channel_id, data } = next_event(&mut rx) else {
+            panic!("data event expected");
+        };
+        assert_eq!((channel_id, data.as_slice()), (CHANNEL_ID, b"ping".as_slice()));
+
+        // The JavaScript side sends the data back.
+        assert_eq!(
+            client_pdus(encode_dvc_data(channel_id, data).unwrap()),
+            [DrdynvcClientPdu::Data(DrdynvcDataPdu::Data(DataPdu::new(
+                CHANNEL_ID,
+                b"ping".to_vec()
+            )))]
+        );
+
+        drdynvc.detach_dynamic_channel(CHANNEL_NAME).unwrap();
+        assert!(matches!(
+            next_event(&mut rx),
+            DvcEvent::Closed { channel_id: CHANNEL_ID }
+        ));
+    }
+}
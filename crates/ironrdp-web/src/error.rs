@@ -18,9 +18,35 @@ pub enum IronRdpErrorKind {
     ProxyConnect,
 }
 
+impl IronRdpErrorKind {
+    /// Value of the kind in the messages of a session run in a worker.
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            IronRdpErrorKind::General => 0,
+            IronRdpErrorKind::WrongPassword => 1,
+            IronRdpErrorKind::LogonFailure => 2,
+            IronRdpErrorKind::AccessDenied => 3,
+            IronRdpErrorKind::RDCleanPath => 4,
+            IronRdpErrorKind::ProxyConnect => 5,
+        }
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => IronRdpErrorKind::WrongPassword,
+            2 => IronRdpErrorKind::LogonFailure,
+            3 => IronRdpErrorKind::AccessDenied,
+            4 => IronRdpErrorKind::RDCleanPath,
+            5 => IronRdpErrorKind::ProxyConnect,
+            _ => IronRdpErrorKind::General,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct IronRdpError {
     kind: IronRdpErrorKind,
+    code: u32,
     source: anyhow::Error,
 }
 
@@ -29,6 +55,15 @@ impl IronRdpError {
         self.kind = kind;
         self
     }
+
+    /// Error forwarded by a session run in a worker, where `message` is the backtrace of the original error.
+    pub(crate) fn forwarded(kind: IronRdpErrorKind, code: u32, message: String) -> Self {
+        Self {
+            kind,
+            code,
+            source: anyhow::Error::msg(message),
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -40,13 +75,26 @@ impl IronRdpError {
     pub fn kind(&self) -> IronRdpErrorKind {
         self.kind
     }
+
+    /// Stable numeric code of the error, or 0 if the error has none
+    pub fn code(&self) -> u32 {
+        self.code
+    }
 }
 
 impl From<connector::ConnectorError> for IronRdpError {
     fn from(e: connector::ConnectorError) -> Self {
+        use connector::credssp::CredsspFailureReason;
         use sspi::credssp::NStatusCode;
 
         let kind = match e.kind {
+            ConnectorErrorKind::CredsspFailure(failure) => match failure.reason {
+                CredsspFailureReason::WrongPassword | CredsspFailureReason::PreauthenticationFailed => {
+                    IronRdpErrorKind::WrongPassword
+                }
+                CredsspFailureReason::LogonFailure => IronRdpErrorKind::LogonFailure,
+                _ => IronRdpErrorKind::General,
+            },
             ConnectorErrorKind::Credssp(sspi::Error {
                 nstatus: Some(NStatusCode::WRONG_PASSWORD),
                 ..
@@ -61,6 +109,7 @@ impl From<connector::ConnectorError> for IronRdpError {
 
         Self {
             kind,
+            code: e.code(),
             source: anyhow::Error::new(e),
         }
     }
@@ -70,6 +119,7 @@ impl From<ironrdp::session::SessionError> for IronRdpError {
     fn from(e: ironrdp::session::SessionError) -> Self {
         Self {
             kind: IronRdpErrorKind::General,
+            code: e.code(),
             source: anyhow::Error::new(e),
         }
     }
@@ -79,6 +129,7 @@ impl From<anyhow::Error> for IronRdpError {
     fn from(e: anyhow::Error) -> Self {
         Self {
             kind: IronRdpErrorKind::General,
+            code: 0,
             source: e,
         }
     }
@@ -1,80 +1,217 @@
-use ironrdp::input::{MouseButton, MousePosition, Operation, Scancode, WheelRotations};
+use ironrdp::input::{
+    Chord, Modifiers, MouseButton, MousePosition, Operation, Scancode, ShortcutAction, WheelRotations,
+};
+use ironrdp::rdpei::client::{PenContact, TouchContact};
+use ironrdp::rdpei::contact::ContactPhase;
 use smallvec::SmallVec;
 use wasm_bindgen::prelude::*;
 
+#[derive(Clone)]
+pub(crate) enum DeviceEventKind {
+    Operation(Operation),
+    Touch(TouchContact),
+    Pen(PenContact),
+    CompositionCommit(CompositionCommit),
+}
+
+/// Text committed by the IME, replacing the last `replaced` characters previously committed
+#[derive(Clone)]
+pub(crate) struct CompositionCommit {
+    pub(crate) text: String,
+    pub(crate) replaced: usize,
+}
+
 #[wasm_bindgen]
 #[derive(Clone)]
-pub struct DeviceEvent(pub(crate) Operation);
+pub struct DeviceEvent(pub(crate) DeviceEventKind);
+
+impl From<Operation> for DeviceEvent {
+    fn from(operation: Operation) -> Self {
+        Self(DeviceEventKind::Operation(operation))
+    }
+}
 
 #[wasm_bindgen]
 impl DeviceEvent {
     pub fn new_mouse_button_pressed(button: u8) -> Self {
         match MouseButton::from_web_button(button) {
-            Some(button) => Self(Operation::MouseButtonPressed(button)),
+            Some(button) => Self::from(Operation::MouseButtonPressed(button)),
             None => {
                 warn!("Unknown mouse button ID: {button}");
-                Self(Operation::MouseButtonPressed(MouseButton::Left))
+                Self::from(Operation::MouseButtonPressed(MouseButton::Left))
             }
         }
     }
 
     pub fn new_mouse_button_released(button: u8) -> Self {
         match MouseButton::from_web_button(button) {
-            Some(button) => Self(Operation::MouseButtonReleased(button)),
+            Some(button) => Self::from(Operation::MouseButtonReleased(button)),
             None => {
                 warn!("Unknown mouse button ID: {button}");
-                Self(Operation::MouseButtonReleased(MouseButton::Left))
+                Self::from(Operation::MouseButtonReleased(MouseButton::Left))
             }
         }
     }
 
     pub fn new_mouse_move(x: u16, y: u16) -> Self {
-        Self(Operation::MouseMove(MousePosition { x, y }))
+        Self::from(Operation::MouseMove(MousePosition { x, y }))
     }
 
     pub fn new_wheel_rotations(vertical: bool, rotation_units: i16) -> Self {
-        Self(Operation::WheelRotations(WheelRotations {
+        Self::from(Operation::WheelRotations(WheelRotations {
             is_vertical: vertical,
             rotation_units,
         }))
     }
 
     pub fn new_key_pressed(scancode: u16) -> Self {
-        Self(Operation::KeyPressed(Scancode::from_u16(scancode)))
+        Self::from(Operation::KeyPressed(Scancode::from_u16(scancode)))
     }
 
     pub fn new_key_released(scancode: u16) -> Self {
-        Self(Operation::KeyReleased(Scancode::from_u16(scancode)))
+        Self::from(Operation::KeyReleased(Scancode::from_u16(scancode)))
     }
 
     pub fn new_unicode_pressed(unicode: char) -> Self {
-        Self(Operation::UnicodeKeyPressed(unicode))
+        Self::from(Operation::UnicodeKeyPressed(unicode))
     }
 
     pub fn new_unicode_released(unicode: char) -> Self {
-        Self(Operation::UnicodeKeyReleased(unicode))
+        Self::from(Operation::UnicodeKeyReleased(unicode))
+    }
+
+    /// Creates an event typing the text committed by the IME, on `compositionend`.
+    pub fn new_composition_commit(text: String) -> Self {
+        Self(DeviceEventKind::CompositionCommit(CompositionCommit {
+            text,
+            replaced: 0,
+        }))
+    }
+
+    /// Creates an event typing the text committed by the IME in place of the last `replaced` characters it
+    /// previously committed, which are erased with backspaces.
+    pub fn new_composition_correction(replaced: usize, text: String) -> Self {
+        Self(DeviceEventKind::CompositionCommit(CompositionCommit { text, replaced }))
+    }
+
+    /// Creates a touch contact event, where `phase` is one of `down`, `update`, `up`, `hover` or `cancel`.
+    pub fn new_touch_contact(id: u32, x: i32, y: i32, phase: &str) -> Self {
+        let phase = match phase {
+            "down" => ContactPhase::Down,
+            "update" => ContactPhase::Update,
+            "up" => ContactPhase::Up,
+            "hover" => ContactPhase::Hover,
+            "cancel" => ContactPhase::Cancel,
+            _ => {
+                warn!("Unknown touch contact phase: {phase}");
+                ContactPhase::Cancel
+            }
+        };
+
+        Self(DeviceEventKind::Touch(TouchContact { id, x, y, phase }))
+    }
+
+    /// Creates a pen event, where `pressure` is from 0.0 to 1.0 and the tilts are in degrees.
+    pub fn new_pen_move(x: i32, y: i32, pressure: f32, tilt_x: i16, tilt_y: i16) -> Self {
+        // The pressure is clamped to the valid range first, so the cast can't truncate.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let pressure = (pressure.clamp(0.0, 1.0) * 1024.0).round() as u32;
+
+        Self(DeviceEventKind::Pen(PenContact {
+            x,
+            y,
+            pressure,
+            tilt_x,
+            tilt_y,
+        }))
     }
 }
 
+/// Input events applied together
+///
+/// The text committed by the IME is typed after the other keyboard and mouse events of the transaction.
 #[wasm_bindgen]
-pub struct InputTransaction(pub(crate) SmallVec<[Operation; 3]>);
+pub struct InputTransaction {
+    pub(crate) operations: SmallVec<[Operation; 3]>,
+    pub(crate) touch_contacts: Vec<TouchContact>,
+    pub(crate) pen_contacts: Vec<PenContact>,
+    pub(crate) composition_commits: Vec<CompositionCommit>,
+}
 
 #[wasm_bindgen]
 impl InputTransaction {
     pub fn new() -> Self {
-        Self(SmallVec::new())
+        Self {
+            operations: SmallVec::new(),
+            touch_contacts: Vec::new(),
+            pen_contacts: Vec::new(),
+            composition_commits: Vec::new(),
+        }
     }
 
     pub fn add_event(&mut self, event: DeviceEvent) {
-        self.0.push(event.0);
+        match event.0 {
+            DeviceEventKind::Operation(operation) => self.operations.push(operation),
+            DeviceEventKind::Touch(contact) => self.touch_contacts.push(contact),
+            DeviceEventKind::Pen(contact) => self.pen_contacts.push(contact),
+            DeviceEventKind::CompositionCommit(commit) => self.composition_commits.push(commit),
+        }
+    }
+}
+
+/// Key combination, where the modifiers are a combination of `0x01` (Ctrl), `0x02` (Shift), `0x04` (Alt) and `0x08`
+/// (Meta)
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct KeyboardChord(pub(crate) Chord);
+
+#[wasm_bindgen]
+impl KeyboardChord {
+    pub fn new(modifiers: u8, scancode: u16) -> Self {
+        Self(Chord::new(
+            Modifiers::from_bits_truncate(modifiers),
+            Scancode::from_u16(scancode),
+        ))
+    }
+
+    pub fn modifiers(&self) -> u8 {
+        self.0.modifiers.bits()
+    }
+
+    pub fn scancode(&self) -> u16 {
+        self.0.key.as_u16()
     }
 }
 
-impl IntoIterator for InputTransaction {
-    type IntoIter = smallvec::IntoIter<[Operation; 3]>;
-    type Item = Operation;
+/// Decides which key combinations are sent to the remote session, see `SessionBuilder::keyboard_capture_policy`
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct KeyboardCapturePolicy(pub(crate) ironrdp::input::KeyboardCapturePolicy);
+
+#[wasm_bindgen]
+impl KeyboardCapturePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the key combination to the remote session, the browser should not handle it.
+    pub fn forward_to_remote(&mut self, chord: &KeyboardChord) {
+        self.0.set_action(chord.0, ShortcutAction::Forward);
+    }
+
+    /// Leaves the key combination to the browser, it is not sent to the remote session.
+    pub fn let_browser_handle(&mut self, chord: &KeyboardChord) {
+        self.0.set_action(chord.0, ShortcutAction::LetBrowserHandle);
+    }
+
+    /// Sends the `to` key combination to the remote session instead of `from`.
+    pub fn remap(&mut self, from: &KeyboardChord, to: &KeyboardChord) {
+        self.0.set_action(from.0, ShortcutAction::Remap(to.0));
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+    /// Substitutes the `to` modifiers to the `from` modifiers, e.g.: Meta to Ctrl for macOS users.
+    pub fn remap_modifier(&mut self, from: u8, to: u8) {
+        self.0
+            .remap_modifier(Modifiers::from_bits_truncate(from), Modifiers::from_bits_truncate(to));
     }
 }
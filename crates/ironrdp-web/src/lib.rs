@@ -14,11 +14,15 @@ extern crate tracing;
 
 mod canvas;
 mod clipboard;
+mod dvc;
 mod error;
-mod image;
 mod input;
 mod network_client;
+mod reconnect;
 mod session;
+mod snapshot;
+mod transport;
+mod worker;
 
 use wasm_bindgen::prelude::*;
 
@@ -38,6 +42,28 @@ pub fn ironrdp_init(log_level: &str) {
     }
 }
 
+/// Returns `true` if the session can run in a worker, see `SessionBuilder::connect_in_worker`.
+///
+/// When `false`, `SessionBuilder::connect` must be used instead.
+#[wasm_bindgen]
+pub fn worker_rendering_supported() -> bool {
+    fn has(target: &JsValue, property: &str) -> bool {
+        js_sys::Reflect::has(target, &JsValue::from_str(property)).unwrap_or(false)
+    }
+
+    let global = js_sys::global();
+
+    if !has(&global, "Worker") || !has(&global, "OffscreenCanvas") {
+        return false;
+    }
+
+    // The canvas of the page must be transferable to the worker, which a few browsers did not support at first.
+    js_sys::Reflect::get(&global, &JsValue::from_str("HTMLCanvasElement"))
+        .and_then(|canvas| js_sys::Reflect::get(&canvas, &JsValue::from_str("prototype")))
+        .map(|prototype| has(&prototype, "transferControlToOffscreen"))
+        .unwrap_or(false)
+}
+
 fn set_logger_once(level: tracing::Level) {
     use tracing_subscriber::filter::LevelFilter;
     use tracing_subscriber::fmt::time::UtcTime;
@@ -1,13 +1,29 @@
 use core::pin::Pin;
+use core::time::Duration;
 
 use futures_util::Future;
 use ironrdp::connector::sspi::generator::NetworkRequest;
 use ironrdp::connector::sspi::network_client::NetworkProtocol;
-use ironrdp::connector::{custom_err, reason_err, ConnectorResult};
-use ironrdp_futures::AsyncNetworkClient;
+use ironrdp::connector::{custom_err, kdc_proxy, reason_err, ConnectorResult};
+use ironrdp_futures::{AsyncNetworkClient, AsyncTimer as _, FuturesTimer};
+use url::Url;
 
-#[derive(Debug)]
-pub(crate) struct WasmNetworkClient;
+/// Number of attempts made for a KDC request before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled after each failed attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Default)]
+pub(crate) struct WasmNetworkClient {
+    kdc_proxy_url: Option<Url>,
+}
+
+impl WasmNetworkClient {
+    pub(crate) fn new(kdc_proxy_url: Option<Url>) -> Self {
+        Self { kdc_proxy_url }
+    }
+}
 
 impl AsyncNetworkClient for WasmNetworkClient {
     fn send<'a>(
@@ -18,35 +34,93 @@ impl AsyncNetworkClient for WasmNetworkClient {
             debug!(?network_request.protocol, ?network_request.url);
 
             match &network_request.protocol {
+                // The request is already wrapped into a KDC-PROXY-MESSAGE by the Kerberos client.
                 NetworkProtocol::Http | NetworkProtocol::Https => {
-                    let body = js_sys::Uint8Array::from(&network_request.data[..]);
-
-                    let response = gloo_net::http::Request::post(network_request.url.as_str())
-                        .header("keep-alive", "true")
-                        .body(body)
-                        .map_err(|e| custom_err!("failed to send KDC request", e))?
-                        .send()
-                        .await
-                        .map_err(|e| custom_err!("failed to send KDC request", e))?;
-
-                    if !response.ok() {
+                    post_with_retries(&network_request.url, &network_request.data).await
+                }
+                // Raw TCP and UDP can't be used from the browser, so the request is tunneled through the KDC proxy.
+                protocol @ (NetworkProtocol::Tcp | NetworkProtocol::Udp) => {
+                    let Some(kdc_proxy_url) = self
+                        .kdc_proxy_url
+                        .as_ref()
+                        .filter(|url| matches!(url.scheme(), "http" | "https"))
+                    else {
                         return Err(reason_err!(
-                            "KdcProxy",
-                            "HTTP status error ({} {})",
-                            response.status(),
-                            response.status_text(),
+                            "CredSSP",
+                            "unsupported protocol without a KDC proxy: {protocol:?}"
                         ));
-                    }
+                    };
 
-                    let body = response
-                        .binary()
-                        .await
-                        .map_err(|e| custom_err!("failed to retrieve HTTP response", e))?;
+                    // Requests destined to UDP are not length-prefixed, but KDC proxy messages always are.
+                    let framed_request = if *protocol == NetworkProtocol::Udp {
+                        kdc_proxy::add_length_prefix(&network_request.data)?
+                    } else {
+                        network_request.data.clone()
+                    };
 
-                    Ok(body)
+                    let proxy_request = kdc_proxy::encode_kdc_proxy_request(&framed_request, None)?;
+                    let proxy_response = post_with_retries(kdc_proxy_url, &proxy_request).await?;
+
+                    // The Kerberos client expects a length-prefixed reply for both TCP and UDP.
+                    kdc_proxy::decode_kdc_proxy_response(&proxy_response)
                 }
-                unsupported => Err(reason_err!("CredSSP", "unsupported protocol: {unsupported:?}")),
             }
         })
     }
 }
+
+async fn post_with_retries(url: &Url, data: &[u8]) -> ConnectorResult<Vec<u8>> {
+    let mut attempt = 1;
+    let mut delay = RETRY_BASE_DELAY;
+
+    loop {
+        match post(url, data).await {
+            Ok(body) => return Ok(body),
+            Err(PostError::Transient(e)) if attempt < MAX_ATTEMPTS => {
+                warn!(error = %e.report(), attempt, "KDC request failed, retrying");
+                FuturesTimer.sleep(delay).await;
+                attempt += 1;
+                delay *= 2;
+            }
+            Err(PostError::Transient(e) | PostError::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+enum PostError {
+    /// Network failures and server errors, which may succeed when retried.
+    Transient(ironrdp::connector::ConnectorError),
+    Fatal(ironrdp::connector::ConnectorError),
+}
+
+async fn post(url: &Url, data: &[u8]) -> Result<Vec<u8>, PostError> {
+    let body = js_sys::Uint8Array::from(data);
+
+    let response = gloo_net::http::Request::post(url.as_str())
+        .header("keep-alive", "true")
+        .body(body)
+        .map_err(|e| PostError::Fatal(custom_err!("failed to send KDC request", e)))?
+        .send()
+        .await
+        .map_err(|e| PostError::Transient(custom_err!("failed to send KDC request", e)))?;
+
+    if !response.ok() {
+        let error = reason_err!(
+            "KdcProxy",
+            "HTTP status error ({} {})",
+            response.status(),
+            response.status_text(),
+        );
+
+        return if response.status() >= 500 {
+            Err(PostError::Transient(error))
+        } else {
+            Err(PostError::Fatal(error))
+        };
+    }
+
+    response
+        .binary()
+        .await
+        .map_err(|e| PostError::Transient(custom_err!("failed to retrieve HTTP response", e)))
+}
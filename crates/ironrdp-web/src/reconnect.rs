@@ -0,0 +1,243 @@
+//! Automatic reconnection when the connection to the proxy is lost while the RDP session is still alive
+//!
+//! This module only holds the backoff state machine, the reconnection itself being driven by `Session::run`.
+
+use core::time::Duration;
+use std::io;
+
+/// Attempts are delayed by `initial_delay`, doubled after each failed attempt, up to `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReconnectPolicy {
+    /// Maximum number of consecutive attempts, reconnection being disabled when zero.
+    pub(crate) max_attempts: u32,
+    pub(crate) initial_delay: Duration,
+    pub(crate) max_delay: Duration,
+}
+
+impl ReconnectPolicy {
+    pub(crate) const DISABLED: Self = Self {
+        max_attempts: 0,
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+    };
+
+    pub(crate) fn with_max_attempts(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::DISABLED
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_attempts > 0
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// Returns `true` if the error is caused by the connection to the proxy, rather than by the data received.
+pub(crate) fn is_transport_error(error: &io::Error) -> bool {
+    error.kind() != io::ErrorKind::InvalidData
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReconnectState {
+    Connected,
+    /// The attempt with the given number (starting from 1) is pending or in progress.
+    Reconnecting {
+        attempt: u32,
+    },
+    GaveUp,
+}
+
+/// State change notified to the JavaScript side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReconnectEvent {
+    /// A new attempt is made once `delay` elapsed.
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+    Reconnected {
+        attempts: u32,
+    },
+    GaveUp {
+        attempts: u32,
+    },
+}
+
+impl ReconnectEvent {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Reconnecting { .. } => "reconnecting",
+            Self::Reconnected { .. } => "reconnected",
+            Self::GaveUp { .. } => "gave_up",
+        }
+    }
+
+    /// Number of the attempt being made, or of attempts made before reconnecting or giving up.
+    pub(crate) fn attempt(&self) -> u32 {
+        match *self {
+            Self::Reconnecting { attempt, .. } => attempt,
+            Self::Reconnected { attempts } | Self::GaveUp { attempts } => attempts,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ReconnectController {
+    policy: ReconnectPolicy,
+    state: ReconnectState,
+}
+
+impl ReconnectController {
+    pub(crate) fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            state: ReconnectState::Connected,
+        }
+    }
+
+    pub(crate) fn state(&self) -> ReconnectState {
+        self.state
+    }
+
+    /// Must be called when the connection is lost, and after each failed attempt.
+    ///
+    /// Returns the next attempt to make, or `GaveUp` once the attempts are exhausted.
+    pub(crate) fn connection_lost(&mut self) -> ReconnectEvent {
+        let attempts = match self.state {
+            ReconnectState::Connected => 0,
+            ReconnectState::Reconnecting { attempt } => attempt,
+            ReconnectState::GaveUp => self.policy.max_attempts,
+        };
+
+        if attempts >= self.policy.max_attempts {
+            self.state = ReconnectState::GaveUp;
+            return ReconnectEvent::GaveUp { attempts };
+        }
+
+        let attempt = attempts + 1;
+        self.state = ReconnectState::Reconnecting { attempt };
+
+        ReconnectEvent::Reconnecting {
+            attempt,
+            delay: self.policy.delay(attempt),
+        }
+    }
+
+    /// Must be called when an attempt succeeded, the backoff starting over on the next connection loss.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no attempt is in progress.
+    pub(crate) fn reconnected(&mut self) -> ReconnectEvent {
+        let ReconnectState::Reconnecting { attempt } = self.state else {
+            panic!("no reconnection attempt in progress");
+        };
+
+        self.state = ReconnectState::Connected;
+
+        ReconnectEvent::Reconnected { attempts: attempt }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_attempts: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+
+    #[test]
+    fn delays_double_up_to_the_maximum() {
+        let mut controller = ReconnectController::new(policy(6));
+
+        let delays: Vec<_> = (0..6)
+            .map(|_| match controller.connection_lost() {
+                ReconnectEvent::Reconnecting { delay, .. } => delay.as_millis(),
+                event => panic!("unexpected event: {event:?}"),
+            })
+            .collect();
+
+        assert_eq!(delays, [500, 1000, 2000, 4000, 4000, 4000]);
+    }
+
+    #[test]
+    fn gives_up_once_the_attempts_are_exhausted() {
+        let mut controller = ReconnectController::new(policy(2));
+
+        assert_eq!(controller.connection_lost().attempt(), 1);
+        assert_eq!(controller.connection_lost().attempt(), 2);
+        assert_eq!(controller.connection_lost(), ReconnectEvent::GaveUp { attempts: 2 });
+        assert_eq!(controller.state(), ReconnectState::GaveUp);
+
+        // Giving up is final.
+        assert_eq!(controller.connection_lost(), ReconnectEvent::GaveUp { attempts: 2 });
+    }
+
+    #[test]
+    fn disabled_policy_gives_up_immediately() {
+        let mut controller = ReconnectController::new(ReconnectPolicy::DISABLED);
+
+        let event = controller.connection_lost();
+
+        assert!(!ReconnectPolicy::DISABLED.is_enabled());
+        assert_eq!(event, ReconnectEvent::GaveUp { attempts: 0 });
+        assert_eq!(event.name(), "gave_up");
+    }
+
+    #[test]
+    fn backoff_starts_over_after_reconnecting() {
+        let mut controller = ReconnectController::new(policy(3));
+
+        controller.connection_lost();
+        let event = controller.connection_lost();
+        assert_eq!(event.name(), "reconnecting");
+
+        let event = controller.reconnected();
+        assert_eq!(event, ReconnectEvent::Reconnected { attempts: 2 });
+        assert_eq!(event.name(), "reconnected");
+        assert_eq!(controller.state(), ReconnectState::Connected);
+
+        assert_eq!(
+            controller.connection_lost(),
+            ReconnectEvent::Reconnecting {
+                attempt: 1,
+                delay: Duration::from_millis(500),
+            }
+        );
+    }
+
+    #[test]
+    fn large_attempt_numbers_do_not_overflow() {
+        let policy = policy(u32::MAX);
+
+        assert_eq!(policy.delay(40), Duration::from_secs(4));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn invalid_data_is_not_a_transport_error() {
+        assert!(is_transport_error(&io::Error::from(io::ErrorKind::UnexpectedEof)));
+        assert!(is_transport_error(&io::Error::from(io::ErrorKind::ConnectionReset)));
+        assert!(!is_transport_error(&io::Error::from(io::ErrorKind::InvalidData)));
+    }
+
+    #[test]
+    #[should_panic(expected = "no reconnection attempt in progress")]
+    fn reconnected_requires_an_attempt() {
+        ReconnectController::new(policy(1)).reconnected();
+    }
+}
@@ -1,50 +1,67 @@
 // https://github.com/rustwasm/wasm-bindgen/issues/4080
 #![allow(non_snake_case)]
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::num::NonZeroU32;
-use core::time::Duration;
+use core::pin::pin;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
 use anyhow::Context as _;
 use base64::Engine as _;
 use futures_channel::mpsc;
-use futures_util::io::{ReadHalf, WriteHalf};
-use futures_util::{select, AsyncWriteExt as _, FutureExt as _, StreamExt as _};
-use gloo_net::websocket;
-use gloo_net::websocket::futures::WebSocket;
+use futures_util::io::ReadHalf;
+use futures_util::{select, FutureExt as _, StreamExt as _};
 use ironrdp::cliprdr::backend::ClipboardMessage;
 use ironrdp::cliprdr::CliprdrClient;
-use ironrdp::connector::connection_activation::ConnectionActivationState;
 use ironrdp::connector::credssp::KerberosConfig;
 use ironrdp::connector::{self, ClientConnector, Credentials};
 use ironrdp::displaycontrol::client::DisplayControlClient;
 use ironrdp::dvc::DrdynvcClient;
 use ironrdp::graphics::image_processing::PixelFormat;
+use ironrdp::pdu::geometry::InclusiveRectangle;
 use ironrdp::pdu::input::fast_path::FastPathInputEvent;
 use ironrdp::pdu::rdp::client_info::PerformanceFlags;
+use ironrdp::pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp::rdpei::client::{PenContact, RdpeiClient, TouchContact};
 use ironrdp::session::image::DecodedImage;
-use ironrdp::session::{fast_path, ActiveStage, ActiveStageOutput, GracefulDisconnectReason};
+use ironrdp::session::presentation::UpdateCoalescer;
+use ironrdp::session::stats::{ChannelStats, SessionStats};
+use ironrdp::session::{ActiveStage, ActiveStageOutput, GracefulDisconnectReason, SessionInfo};
+use ironrdp::svc::SvcProcessorMessages;
 use ironrdp_core::WriteBuf;
-use ironrdp_futures::{single_sequence_step_read, FramedWrite};
+use ironrdp_futures::{
+    AsyncTimer as _, FramedWrite, FuturesTimer, LocalFuturesFramed, LocalFuturesStream, SessionDriver,
+    DEFAULT_OUTBOUND_CAPACITY,
+};
 use rgb::AsPixels as _;
 use tap::prelude::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlCanvasElement;
 
-use crate::canvas::Canvas;
-use crate::clipboard::{ClipboardTransaction, WasmClipboard, WasmClipboardBackend, WasmClipboardBackendMessage};
+use crate::canvas::{Canvas, RenderCanvas};
+use crate::clipboard::{
+    ClipboardPolicy, ClipboardTransaction, WasmClipboard, WasmClipboardBackend, WasmClipboardBackendMessage,
+};
+use crate::dvc::{encode_dvc_data, DvcEvent, JsDvcCallbacks, JsDvcChannels, JsDvcProcessor};
 use crate::error::{IronRdpError, IronRdpErrorKind};
-use crate::image::extract_partial_image;
-use crate::input::InputTransaction;
+use crate::input::{InputTransaction, KeyboardCapturePolicy, KeyboardChord};
 use crate::network_client::WasmNetworkClient;
+use crate::reconnect::{is_transport_error, ReconnectController, ReconnectEvent, ReconnectPolicy, ReconnectState};
+use crate::snapshot::ConnectionSnapshot;
+use crate::transport::{Transport, TransportKind};
+use crate::worker::{ConnectRequest, WorkerCallbacks, WorkerSession};
 use crate::{clipboard, DesktopSize};
 
 const DEFAULT_WIDTH: u16 = 1280;
 const DEFAULT_HEIGHT: u16 = 720;
 
+/// Maximum number of frames processed for each wake-up of the session loop, so the input events are not delayed
+/// by a long stream of updates.
+const MAX_FRAMES_PER_BATCH: usize = 32;
+
 #[wasm_bindgen]
 #[derive(Clone, Default)]
 pub struct SessionBuilder(Rc<RefCell<SessionBuilderInner>>);
@@ -61,14 +78,22 @@ struct SessionBuilderInner {
     client_name: String,
     desktop_size: DesktopSize,
 
-    render_canvas: Option<HtmlCanvasElement>,
+    render_canvas: Option<RenderCanvas>,
     set_cursor_style_callback: Option<js_sys::Function>,
     set_cursor_style_callback_context: Option<JsValue>,
     remote_clipboard_changed_callback: Option<js_sys::Function>,
     remote_received_format_list_callback: Option<js_sys::Function>,
     force_clipboard_update_callback: Option<js_sys::Function>,
+    reconnect_state_changed_callback: Option<js_sys::Function>,
+    beep_callback: Option<js_sys::Function>,
+    dvc_channels: BTreeMap<String, JsDvcCallbacks>,
 
     use_display_control: bool,
+    transport: TransportKind,
+    clipboard_policy: ClipboardPolicy,
+    snapshot: Option<ironrdp_rdcleanpath::ConnectionSnapshot>,
+    keyboard_capture_policy: ironrdp::input::KeyboardCapturePolicy,
+    reconnect_policy: ReconnectPolicy,
 }
 
 impl Default for SessionBuilderInner {
@@ -94,8 +119,16 @@ impl Default for SessionBuilderInner {
             remote_clipboard_changed_callback: None,
             remote_received_format_list_callback: None,
             force_clipboard_update_callback: None,
+            reconnect_state_changed_callback: None,
+            beep_callback: None,
+            dvc_channels: BTreeMap::new(),
 
             use_display_control: false,
+            transport: TransportKind::WebSocket,
+            clipboard_policy: ClipboardPolicy::Bidirectional,
+            snapshot: None,
+            keyboard_capture_policy: ironrdp::input::KeyboardCapturePolicy::new(),
+            reconnect_policy: ReconnectPolicy::DISABLED,
         }
     }
 }
@@ -166,7 +199,7 @@ impl SessionBuilder {
 
     /// Optional
     pub fn render_canvas(&self, canvas: HtmlCanvasElement) -> SessionBuilder {
-        self.0.borrow_mut().render_canvas = Some(canvas);
+        self.0.borrow_mut().render_canvas = Some(RenderCanvas::Html(canvas));
         self.clone()
     }
 
@@ -222,6 +255,177 @@ impl SessionBuilder {
         self.clone()
     }
 
+    /// Optional, defaults to WebSocket
+    pub fn transport(&self, kind: TransportKind) -> SessionBuilder {
+        self.0.borrow_mut().transport = kind;
+        self.clone()
+    }
+
+    /// Optional, the clipboard is shared both ways by default
+    ///
+    /// The local copies are not announced to the remote, or the remote copies are not forwarded to
+    /// `remote_clipboard_changed_callback`, depending on the policy.
+    pub fn clipboard_policy(&self, policy: ClipboardPolicy) -> SessionBuilder {
+        self.0.borrow_mut().clipboard_policy = policy;
+        self.clone()
+    }
+
+    /// Optional
+    ///
+    /// Reuses the outcome of the RDCleanPath exchange of a previous session, see `Session::connection_snapshot`.
+    /// The snapshot is ignored if it was taken for another destination.
+    pub fn resume_with(&self, snapshot: &ConnectionSnapshot) -> SessionBuilder {
+        self.0.borrow_mut().snapshot = Some(snapshot.0.clone());
+        self.clone()
+    }
+
+    /// Optional, all the key combinations are forwarded by default
+    ///
+    /// The policy can be changed afterwards with `Session::set_keyboard_capture_policy`.
+    pub fn keyboard_capture_policy(&self, policy: &KeyboardCapturePolicy) -> SessionBuilder {
+        self.0.borrow_mut().keyboard_capture_policy = policy.0.clone();
+        self.clone()
+    }
+
+    /// Optional, disabled by default
+    ///
+    /// When the connection to the proxy is lost while the RDP session is still alive, up to `max_attempts`
+    /// reconnections are attempted with an exponential backoff. The RDCleanPath exchange and the RDP connection are
+    /// redone with the same parameters, and the auto-reconnect cookie sent by the server logs the user back into the
+    /// same session. The proxy must accept the `auth_token` more than once.
+    ///
+    /// The inputs received while reconnecting are dropped, and the canvas keeps the last frame.
+    pub fn auto_reconnect(&self, max_attempts: u32) -> SessionBuilder {
+        self.0.borrow_mut().reconnect_policy = ReconnectPolicy::with_max_attempts(max_attempts);
+        self.clone()
+    }
+
+    /// Optional
+    ///
+    /// # Callback signature:
+    /// ```typescript
+    /// function callback(state: string, attempt: number): void
+    /// ```
+    ///
+    /// # States:
+    /// - `reconnecting`: the connection to the proxy was lost, `attempt` is the number of the upcoming attempt
+    ///   (starting from 1)
+    /// - `reconnected`: the session resumed after `attempt` attempts
+    /// - `gave_up`: no reconnection succeeded after `attempt` attempts, `Session::run` returns the error which
+    ///   interrupted the session
+    pub fn reconnect_state_changed_callback(&self, callback: js_sys::Function) -> SessionBuilder {
+        self.0.borrow_mut().reconnect_state_changed_callback = Some(callback);
+        self.clone()
+    }
+
+    /// Optional
+    ///
+    /// Called when the server asks for a beep (Play Sound PDU), e.g.: when a console application writes the bell
+    /// character. The page may play a tone, or flash the canvas.
+    ///
+    /// # Callback signature:
+    /// ```typescript
+    /// function callback(frequency_hz: number, duration_ms: number): void
+    /// ```
+    pub fn beep_callback(&self, callback: js_sys::Function) -> SessionBuilder {
+        self.0.borrow_mut().beep_callback = Some(callback);
+        self.clone()
+    }
+
+    /// Optional
+    ///
+    /// Registers a listener for the dynamic virtual channel named `name`, implemented by the page. The server
+    /// requests to open this channel are accepted, and its events are forwarded to `callbacks`. A listener already
+    /// registered under the same name is replaced.
+    ///
+    /// The data received on the channel is passed as an `Uint8Array`. Use `Session::send_dvc_data` to send data on
+    /// the opened channel. If a callback throws, the error is logged and the channel is closed.
+    ///
+    /// # Callbacks:
+    /// ```typescript
+    /// {
+    ///     onOpen(channelId: number): void,
+    ///     onData(data: Uint8Array): void,
+    ///     onClose(): void,
+    /// }
+    /// ```
+    pub fn register_dvc_channel(&self, name: String, callbacks: JsValue) -> Result<SessionBuilder, IronRdpError> {
+        let callbacks = JsDvcCallbacks::from_js(&callbacks).with_context(|| format!("invalid callbacks for {name}"))?;
+        self.0.borrow_mut().dvc_channels.insert(name, callbacks);
+        Ok(self.clone())
+    }
+
+    /// Connects in `worker`, which runs the session and renders it on the render canvas, so the decoding does not
+    /// compete with the main thread of the page.
+    ///
+    /// The worker script must forward its messages to a `SessionWorkerHost`. The control of the render canvas is
+    /// transferred to the worker, so the canvas can't be used by another session afterwards. Use `connect` instead
+    /// when `worker_rendering_supported` returns `false`.
+    ///
+    /// The connection snapshot, the keyboard capture policy and the dynamic channels registered with
+    /// `register_dvc_channel` are not supported in this mode.
+    pub async fn connect_in_worker(&self, worker: web_sys::Worker) -> Result<WorkerSession, IronRdpError> {
+        let (request, render_canvas, callbacks);
+
+        {
+            let inner = self.0.borrow();
+
+            request = ConnectRequest {
+                username: inner.username.clone().context("username missing")?,
+                password: inner.password.clone().context("password missing")?,
+                destination: inner.destination.clone().context("destination missing")?,
+                server_domain: inner.server_domain.clone(),
+                proxy_address: inner.proxy_address.clone().context("proxy_address missing")?,
+                auth_token: inner.auth_token.clone().context("auth_token missing")?,
+                pcb: inner.pcb.clone(),
+                kdc_proxy_url: inner.kdc_proxy_url.clone(),
+                desktop_width: inner.desktop_size.width,
+                desktop_height: inner.desktop_size.height,
+                use_display_control: inner.use_display_control,
+                transport: inner.transport,
+                clipboard: inner.remote_clipboard_changed_callback.is_some(),
+                clipboard_policy: inner.clipboard_policy,
+                reconnect_max_attempts: inner.reconnect_policy.max_attempts,
+            };
+
+            render_canvas = match inner.render_canvas.clone().context("render_canvas missing")? {
+                RenderCanvas::Html(canvas) => canvas,
+                RenderCanvas::Offscreen(_) => {
+                    return Err(anyhow::Error::msg("render_canvas is already an OffscreenCanvas").into())
+                }
+            };
+
+            callbacks = WorkerCallbacks {
+                set_cursor_style: inner
+                    .set_cursor_style_callback
+                    .clone()
+                    .context("set_cursor_style_callback missing")?,
+                set_cursor_style_context: inner
+                    .set_cursor_style_callback_context
+                    .clone()
+                    .context("set_cursor_style_callback_context missing")?,
+                remote_clipboard_changed: inner.remote_clipboard_changed_callback.clone(),
+                remote_received_format_list: inner.remote_received_format_list_callback.clone(),
+                force_clipboard_update: inner.force_clipboard_update_callback.clone(),
+                reconnect_state_changed: inner.reconnect_state_changed_callback.clone(),
+                beep: inner.beep_callback.clone(),
+            };
+
+            if inner.snapshot.is_some() {
+                warn!("Connection snapshot ignored, not supported when connecting in a worker");
+            }
+        }
+
+        // The canvas is sized by the worker, once the session is connected.
+        let offscreen_canvas = render_canvas
+            .transfer_control_to_offscreen()
+            .map_err(|e| anyhow::Error::msg(format!("transfer the render canvas to the worker: {e:?}")))?;
+
+        info!("Connect to RDP host from a worker");
+
+        WorkerSession::connect(worker, request, offscreen_canvas, callbacks).await
+    }
+
     pub async fn connect(&self) -> Result<Session, IronRdpError> {
         let (
             username,
@@ -240,6 +444,15 @@ impl SessionBuilder {
             remote_clipboard_changed_callback,
             remote_received_format_list_callback,
             force_clipboard_update_callback,
+            reconnect_state_changed_callback,
+            beep_callback,
+            dvc_channels,
+            snapshot,
+            keyboard_capture_policy,
+            reconnect_policy,
+            transport_kind,
+            use_display_control,
+            clipboard_policy,
         );
 
         {
@@ -269,11 +482,40 @@ impl SessionBuilder {
             remote_clipboard_changed_callback = inner.remote_clipboard_changed_callback.clone();
             remote_received_format_list_callback = inner.remote_received_format_list_callback.clone();
             force_clipboard_update_callback = inner.force_clipboard_update_callback.clone();
+            reconnect_state_changed_callback = inner.reconnect_state_changed_callback.clone();
+            beep_callback = inner.beep_callback.clone();
+            dvc_channels = JsDvcChannels::new(inner.dvc_channels.clone());
+            keyboard_capture_policy = inner.keyboard_capture_policy.clone();
+            reconnect_policy = inner.reconnect_policy;
+            transport_kind = inner.transport;
+            use_display_control = inner.use_display_control;
+            clipboard_policy = inner.clipboard_policy;
+
+            snapshot = inner.snapshot.clone().filter(|snapshot| {
+                let matches = snapshot.matches_destination(&destination);
+                if !matches {
+                    debug!(destination = %snapshot.destination, "Connection snapshot discarded, the destination changed");
+                }
+                matches
+            });
         }
 
         info!("Connect to RDP host");
 
-        let config = build_config(username, password, server_domain, client_name, desktop_size);
+        let parameters = ConnectionParameters {
+            username,
+            password,
+            server_domain,
+            client_name,
+            proxy_address,
+            auth_token,
+            destination,
+            pcb,
+            kdc_proxy_url,
+            transport_kind,
+            use_display_control,
+            clipboard_policy,
+        };
 
         let (input_events_tx, input_events_rx) = mpsc::unbounded();
 
@@ -288,72 +530,71 @@ impl SessionBuilder {
             )
         });
 
-        let ws = WebSocket::open(&proxy_address).context("Couldn’t open WebSocket")?;
-
-        // NOTE: ideally, when the WebSocket can’t be opened, the above call should fail with details on why is that
-        // (e.g., the proxy hostname could not be resolved, proxy service is not running), but errors are neved
-        // bubbled up in practice, so instead we poll the WebSocket state until we know its connected (i.e., the
-        // WebSocket handshake is a success and user data can be exchanged).
-        loop {
-            match ws.state() {
-                websocket::State::Closing | websocket::State::Closed => {
-                    return Err(IronRdpError::from(anyhow::anyhow!(
-                        "Failed to connect to {proxy_address} (WebSocket is `{:?}`)",
-                        ws.state()
-                    ))
-                    .with_kind(IronRdpErrorKind::ProxyConnect));
-                }
-                websocket::State::Connecting => {
-                    trace!("WebSocket is connecting to proxy at {proxy_address}...");
-                    gloo_timers::future::sleep(Duration::from_millis(50)).await;
-                }
-                websocket::State::Open => {
-                    debug!("WebSocket connected to {proxy_address} with success");
-                    break;
-                }
+        let connected = parameters
+            .establish(
+                connector::DesktopSize {
+                    width: desktop_size.width,
+                    height: desktop_size.height,
+                },
+                clipboard.as_ref().map(|clip| clip.backend()),
+                dvc_channels.processors(&input_events_tx),
+                snapshot,
+                None,
+            )
+            .await;
+
+        let Connected {
+            connection_result,
+            transport,
+            snapshot,
+            resumed,
+        } = match connected {
+            Ok(connected) => connected,
+            Err(e) => {
+                // The snapshot may be the reason why the connection failed, a full exchange is done next time.
+                self.0.borrow_mut().snapshot = None;
+                return Err(e);
             }
-        }
-
-        let use_display_control = self.0.borrow().use_display_control;
-
-        let (connection_result, ws) = connect(ConnectParams {
-            ws,
-            config,
-            proxy_auth_token: auth_token,
-            destination,
-            pcb,
-            kdc_proxy_url,
-            clipboard_backend: clipboard.as_ref().map(|clip| clip.backend()),
-            use_display_control,
-        })
-        .await?;
-
-        info!("Connected!");
-
-        let (rdp_reader, rdp_writer) = futures_util::AsyncReadExt::split(ws);
-
-        let (writer_tx, writer_rx) = mpsc::unbounded();
+        };
 
-        spawn_local(writer_task(writer_rx, rdp_writer));
+        info!(resumed, "Connected!");
 
         Ok(Session {
             desktop_size: connection_result.desktop_size,
             input_database: RefCell::new(ironrdp::input::Database::new()),
-            writer_tx,
+            composition: RefCell::new(ironrdp::input::Composition::new()),
+            keyboard_capture_policy: RefCell::new(keyboard_capture_policy),
             input_events_tx,
 
             render_canvas,
             set_cursor_style_callback,
             set_cursor_style_callback_context,
+            reconnect_state_changed_callback,
+            beep_callback,
+            dvc_channels: RefCell::new(dvc_channels),
+
+            parameters,
+            reconnect_policy,
+            snapshot: RefCell::new(snapshot),
+            resumed: Cell::new(resumed),
+            statistics: RefCell::new(SessionStats::default()),
 
             input_events_rx: RefCell::new(Some(input_events_rx)),
-            rdp_reader: RefCell::new(Some(rdp_reader)),
+            transport: RefCell::new(Some(transport)),
             connection_result: RefCell::new(Some(connection_result)),
             clipboard: RefCell::new(Some(clipboard)),
         })
     }
 }
 
+impl SessionBuilder {
+    /// Renders on `canvas`, when the session runs in a worker.
+    pub(crate) fn render_offscreen_canvas(&self, canvas: web_sys::OffscreenCanvas) -> SessionBuilder {
+        self.0.borrow_mut().render_canvas = Some(RenderCanvas::Offscreen(canvas));
+        self.clone()
+    }
+}
+
 pub(crate) type FastPathInputEvents = smallvec::SmallVec<[FastPathInputEvent; 2]>;
 
 #[derive(Debug)]
@@ -367,6 +608,24 @@ pub(crate) enum RdpInputEvent {
         scale_factor: Option<u32>,
         physical_size: Option<(u32, u32)>,
     },
+    Touch {
+        contacts: Vec<TouchContact>,
+        /// Time at which the contacts changed, in microseconds.
+        timestamp: u64,
+    },
+    Pen {
+        contact: PenContact,
+        /// Time at which the pen was sampled, in microseconds.
+        timestamp: u64,
+    },
+    /// The render canvas was shown or hidden (e.g.: the browser tab was put in the background).
+    Visibility(bool),
+    Dvc(DvcEvent),
+    /// Data sent by the page on a dynamic channel registered with `SessionBuilder::register_dvc_channel`.
+    DvcData {
+        channel_id: u32,
+        data: Vec<u8>,
+    },
     TerminateSession,
 }
 
@@ -382,39 +641,67 @@ enum CursorStyle {
 
 #[wasm_bindgen]
 pub struct SessionTerminationInfo {
-    reason: GracefulDisconnectReason,
+    /// Formatted on termination, as the reason may come from a session run in a worker.
+    reason: String,
+}
+
+impl SessionTerminationInfo {
+    pub(crate) fn new(reason: String) -> Self {
+        Self { reason }
+    }
 }
 
 #[wasm_bindgen]
 impl SessionTerminationInfo {
     pub fn reason(&self) -> String {
-        self.reason.to_string()
+        self.reason.clone()
     }
 }
 
+/// Outcome of the reconnection attempts made after the connection to the proxy was lost
+enum ReconnectOutcome {
+    Reconnected(Connected),
+    GaveUp,
+    /// The session was shut down while reconnecting.
+    Terminated,
+}
+
 #[wasm_bindgen]
 pub struct Session {
     desktop_size: connector::DesktopSize,
     input_database: RefCell<ironrdp::input::Database>,
-    writer_tx: mpsc::UnboundedSender<Vec<u8>>,
+    composition: RefCell<ironrdp::input::Composition>,
+    keyboard_capture_policy: RefCell<ironrdp::input::KeyboardCapturePolicy>,
     input_events_tx: mpsc::UnboundedSender<RdpInputEvent>,
 
-    render_canvas: HtmlCanvasElement,
+    render_canvas: RenderCanvas,
     set_cursor_style_callback: js_sys::Function,
     set_cursor_style_callback_context: JsValue,
+    reconnect_state_changed_callback: Option<js_sys::Function>,
+    beep_callback: Option<js_sys::Function>,
+    /// Updated by `run` as the channels are opened and closed.
+    dvc_channels: RefCell<JsDvcChannels>,
+
+    parameters: ConnectionParameters,
+    reconnect_policy: ReconnectPolicy,
+    /// Updated by `run` when reconnecting.
+    snapshot: RefCell<ironrdp_rdcleanpath::ConnectionSnapshot>,
+    resumed: Cell<bool>,
+    /// Refreshed by `run` after each batch of frames or input event.
+    statistics: RefCell<SessionStats>,
 
     // Consumed when `run` is called
     input_events_rx: RefCell<Option<mpsc::UnboundedReceiver<RdpInputEvent>>>,
     connection_result: RefCell<Option<connector::ConnectionResult>>,
-    rdp_reader: RefCell<Option<ReadHalf<WebSocket>>>,
+    transport: RefCell<Option<Transport>>,
     clipboard: RefCell<Option<Option<WasmClipboard>>>,
 }
 
 #[wasm_bindgen]
 impl Session {
     pub async fn run(&self) -> Result<SessionTerminationInfo, IronRdpError> {
-        let rdp_reader = self
-            .rdp_reader
+        let transport = self
+            .transport
             .borrow_mut()
             .take()
             .context("RDP session can be started only once")?;
@@ -433,7 +720,7 @@ impl Session {
 
         let mut clipboard = self.clipboard.borrow_mut().take().expect("run called only once");
 
-        let mut framed = ironrdp_futures::LocalFuturesFramed::new(rdp_reader);
+        let mut driver = start_io(transport);
 
         debug!("Initialize canvas");
 
@@ -456,20 +743,80 @@ impl Session {
 
         let mut active_stage = ActiveStage::new(connection_result);
 
+        // While the canvas is hidden, the image is still updated but only the union of the updated regions is
+        // drawn when it is shown again. Likewise, only the last cursor style is kept.
+        let mut coalescer = UpdateCoalescer::new();
+        let mut cursor_style = None;
+
+        let mut reconnect = ReconnectController::new(self.reconnect_policy);
+        let mut auto_reconnect_cookie = None;
+
         let disconnect_reason = 'outer: loop {
             let outputs = select! {
-                frame = framed.read_pdu().fuse() => {
-                    let (action, payload) = frame.context("read frame")?;
-                    trace!(?action, frame_length = payload.len(), "Frame received");
+                batch = driver.read_frames_batch(MAX_FRAMES_PER_BATCH).fuse() => {
+                    let batch = match batch {
+                        Ok(batch) => batch,
+                        Err(e) if self.reconnect_policy.is_enabled() && is_transport_error(&e) => {
+                            // The RDP session may still be alive on the server, behind the proxy.
+                            warn!(error = %e, "Connection to the proxy lost");
+                            self.dvc_channels.borrow_mut().close_all();
+
+                            let outcome = self
+                                .reconnect(
+                                    &mut reconnect,
+                                    &mut input_events,
+                                    &mut coalescer,
+                                    &mut gui,
+                                    &image,
+                                    auto_reconnect_cookie.clone(),
+                                    clipboard.as_ref(),
+                                )
+                                .await?;
+
+                            let connected = match outcome {
+                                ReconnectOutcome::Reconnected(connected) => connected,
+                                ReconnectOutcome::GaveUp => return Err(anyhow::Error::new(e).context("read frames").into()),
+                                ReconnectOutcome::Terminated => break 'outer GracefulDisconnectReason::UserInitiated,
+                            };
+
+                            let connection_result = connected.connection_result;
+                            let desktop_size = connection_result.desktop_size;
+
+                            // The last frame is kept on the canvas, unless the server changed the desktop size.
+                            if desktop_size.width != image.width() || desktop_size.height != image.height() {
+                                image = DecodedImage::new(PixelFormat::RgbA32, desktop_size.width, desktop_size.height);
+                                self.resize_canvas(&mut gui, &mut coalescer, desktop_size.width, desktop_size.height);
+                            }
 
-                    active_stage.process(&mut image, action, &payload)?
+                            active_stage = ActiveStage::new(connection_result);
+                            driver = start_io(connected.transport);
+                            *self.snapshot.borrow_mut() = connected.snapshot;
+                            self.resumed.set(connected.resumed);
+
+                            continue;
+                        }
+                        Err(e) => return Err(anyhow::Error::new(e).context("read frames").into()),
+                    };
+                    trace!(frame_count = batch.len(), "Frames received");
+
+                    let mut outputs = Vec::new();
+                    for (action, payload) in batch {
+                        trace!(?action, frame_length = payload.len(), "Process frame");
+                        outputs.extend(active_stage.process(&mut image, action, &payload)?);
+
+                        // The frames following the termination of the session are not relevant anymore.
+                        if matches!(outputs.last(), Some(ActiveStageOutput::Terminate(_))) {
+                            break;
+                        }
+                    }
+                    outputs
                 }
                 input_events = input_events.next() => {
                     let event = input_events.context("read next input events")?;
 
                     match event {
                         RdpInputEvent::Cliprdr(message) => {
-                            if let Some(cliprdr) = active_stage.get_svc_processor::<CliprdrClient>() {
+                            if let Some(cliprdr) = active_stage.get_svc_processor_mut::<CliprdrClient>() {
                                 if let Some(svc_messages) = match message {
                                     ClipboardMessage::SendInitiateCopy(formats) => Some(
                                         cliprdr.initiate_copy(&formats)
@@ -517,8 +864,7 @@ impl Session {
                                 warn!("Resize event ignored: width or height is zero");
                                 Vec::new()
                             } else if let Some(response_frame) = active_stage.encode_resize(width, height, scale_factor, physical_size) {
-                                self.render_canvas.set_width(width);
-                                self.render_canvas.set_height(height);
+                                self.render_canvas.set_size(width, height);
                                 gui.resize(NonZeroU32::new(width).unwrap(), NonZeroU32::new(height).unwrap());
                                 vec![ActiveStageOutput::ResponseFrame(response_frame?)]
                             } else {
@@ -526,6 +872,48 @@ impl Session {
                                 Vec::new()
                             }
                         },
+                        RdpInputEvent::Touch { contacts, timestamp } => {
+                            if let Some(response_frame) = active_stage.encode_touch_contacts(&contacts, timestamp) {
+                                vec![ActiveStageOutput::ResponseFrame(response_frame?)]
+                            } else {
+                                debug!("Touch event ignored");
+                                Vec::new()
+                            }
+                        }
+                        RdpInputEvent::Pen { contact, timestamp } => {
+                            if let Some(response_frame) = active_stage.encode_pen(&contact, timestamp) {
+                                vec![ActiveStageOutput::ResponseFrame(response_frame?)]
+                            } else {
+                                debug!("Pen event ignored");
+                                Vec::new()
+                            }
+                        }
+                        RdpInputEvent::Visibility(visible) => {
+                            debug!(visible, "Visibility changed");
+                            if let Some(region) = coalescer.set_visible(visible) {
+                                gui.draw(&image, region).context("draw coalesced region")?;
+                            }
+                            Vec::new()
+                        }
+                        RdpInputEvent::Dvc(event) => {
+                            let channel_to_close = self.dvc_channels.borrow_mut().dispatch(event);
+                            match channel_to_close {
+                                Some(channel_name) => close_dvc_channel(&mut active_stage, &channel_name)?,
+                                None => Vec::new(),
+                            }
+                        }
+                        RdpInputEvent::DvcData { channel_id, data } => {
+                            if self.dvc_channels.borrow().is_open(channel_id) {
+                                let messages = encode_dvc_data(channel_id, data)?;
+                                let frame = active_stage.process_svc_processor_messages(
+                                    SvcProcessorMessages::<DrdynvcClient>::new(messages)
+                                )?;
+                                vec![ActiveStageOutput::ResponseFrame(frame)]
+                            } else {
+                                debug!(channel_id, "DVC data dropped, the channel was closed");
+                                Vec::new()
+                            }
+                        }
                         RdpInputEvent::TerminateSession => {
                             active_stage.graceful_shutdown()
                                 .context("graceful shutdown")?
@@ -537,20 +925,18 @@ impl Session {
             for out in outputs {
                 match out {
                     ActiveStageOutput::ResponseFrame(frame) => {
-                        self.writer_tx
-                            .unbounded_send(frame)
-                            .context("Send frame to writer task")?;
+                        driver.send(frame).await.context("Send frame to writer task")?;
                     }
                     ActiveStageOutput::GraphicsUpdate(region) => {
-                        // PERF: some copies and conversion could be optimized
-                        let (region, buffer) = extract_partial_image(&image, region);
-                        gui.draw(&buffer, region).context("draw updated region")?;
+                        if let Some(region) = coalescer.update(region) {
+                            gui.draw(&image, region).context("draw updated region")?;
+                        }
                     }
                     ActiveStageOutput::PointerDefault => {
-                        self.set_cursor_style(CursorStyle::Default)?;
+                        cursor_style = Some(CursorStyle::Default);
                     }
                     ActiveStageOutput::PointerHidden => {
-                        self.set_cursor_style(CursorStyle::Hidden)?;
+                        cursor_style = Some(CursorStyle::Hidden);
                     }
                     ActiveStageOutput::PointerPosition { .. } => {
                         // Not applicable for web.
@@ -640,64 +1026,129 @@ impl Session {
                         let mut style = "data:image/png;base64,".to_owned();
                         base64::engine::general_purpose::STANDARD.encode_string(png_buffer, &mut style);
 
-                        self.set_cursor_style(CursorStyle::Url {
+                        cursor_style = Some(CursorStyle::Url {
                             data: style,
                             hotspot_x,
                             hotspot_y,
-                        })?;
+                        });
                     }
-                    ActiveStageOutput::DeactivateAll(mut box_connection_activation) => {
-                        // Execute the Deactivation-Reactivation Sequence:
-                        // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
-                        debug!("Received Server Deactivate All PDU, executing Deactivation-Reactivation Sequence");
-                        let mut buf = WriteBuf::new();
-                        'activation_seq: loop {
-                            let written =
-                                single_sequence_step_read(&mut framed, &mut *box_connection_activation, &mut buf)
-                                    .await?;
-
-                            if written.size().is_some() {
-                                self.writer_tx
-                                    .unbounded_send(buf.filled().to_vec())
-                                    .context("Send frame to writer task")?;
-                            }
+                    ActiveStageOutput::DeactivationReactivation { new_desktop_size } => {
+                        debug!(?new_desktop_size, "Deactivation-Reactivation Sequence completed");
+                        // The image was resized by the active stage, the canvas must follow.
+                        self.resize_canvas(
+                            &mut gui,
+                            &mut coalescer,
+                            new_desktop_size.width,
+                            new_desktop_size.height,
+                        );
+                    }
+                    ActiveStageOutput::MonitorLayoutChanged(monitors) => {
+                        debug!(?monitors, "Monitor layout changed");
+                        self.resize_canvas(&mut gui, &mut coalescer, image.width(), image.height());
+                    }
+                    ActiveStageOutput::SessionInfo(session_info) => {
+                        info!(?session_info, "Received session information");
 
-                            if let ConnectionActivationState::Finalized {
-                                io_channel_id,
-                                user_channel_id,
-                                desktop_size,
-                                no_server_pointer,
-                                pointer_software_rendering,
-                            } = box_connection_activation.state
-                            {
-                                debug!("Deactivation-Reactivation Sequence completed");
-                                image = DecodedImage::new(PixelFormat::RgbA32, desktop_size.width, desktop_size.height);
-                                // Create a new [`FastPathProcessor`] with potentially updated
-                                // io/user channel ids.
-                                active_stage.set_fastpath_processor(
-                                    fast_path::ProcessorBuilder {
-                                        io_channel_id,
-                                        user_channel_id,
-                                        no_server_pointer,
-                                        pointer_software_rendering,
-                                    }
-                                    .build(),
-                                );
-                                active_stage.set_no_server_pointer(no_server_pointer);
-                                break 'activation_seq;
-                            }
+                        if let SessionInfo::LogonExtended {
+                            auto_reconnect: Some(cookie),
+                            ..
+                        } = session_info
+                        {
+                            auto_reconnect_cookie = Some(cookie);
                         }
                     }
+                    ActiveStageOutput::FrameBoundary(frame_id) => {
+                        // The graphics updates are drawn as they come.
+                        trace!(frame_id, "Frame completed");
+                    }
+                    ActiveStageOutput::ConnectionHealth(event) => {
+                        // The connection loss is detected by the proxy connection, which triggers the reconnection.
+                        debug!(?event, "Connection health changed");
+                    }
+                    ActiveStageOutput::PlaySound {
+                        frequency_hz,
+                        duration_ms,
+                    } => self.beep(frequency_hz, duration_ms)?,
+                    ActiveStageOutput::MultitransportRequest(request) => {
+                        debug!(
+                            request.request_id,
+                            "Declined the UDP side channel requested by the server"
+                        );
+                    }
+                    ActiveStageOutput::ServerRedirection(redirection) => {
+                        // The destination is chosen by the proxy, hence the redirection can't be followed.
+                        warn!(?redirection.target, "Server redirection is not supported");
+                        break 'outer GracefulDisconnectReason::Other("redirected to another server".to_owned());
+                    }
                     ActiveStageOutput::Terminate(reason) => break 'outer reason,
                 }
             }
+
+            if coalescer.is_visible() {
+                if let Some(style) = cursor_style.take() {
+                    self.set_cursor_style(style)?;
+                }
+            }
+
+            *self.statistics.borrow_mut() = active_stage.stats_snapshot();
         };
 
         info!(%disconnect_reason, "RPD session terminated");
 
-        Ok(SessionTerminationInfo {
-            reason: disconnect_reason,
+        Ok(SessionTerminationInfo::new(disconnect_reason.to_string()))
+    }
+
+    /// Returns the outcome of the RDCleanPath exchange, to be passed to `SessionBuilder::resume_with` when reconnecting.
+    pub fn connection_snapshot(&self) -> ConnectionSnapshot {
+        ConnectionSnapshot(self.snapshot.borrow().clone())
+    }
+
+    /// Returns `true` if the proxy sent the certificate chain cached in the snapshot passed to
+    /// `SessionBuilder::resume_with`, in which case the server identity is the same as in the previous session.
+    pub fn resumed(&self) -> bool {
+        self.resumed.get()
+    }
+
+    /// Returns the traffic of the virtual channels, as an object of the form
+    /// `{ staticChannels: { [name]: ChannelStats }, dynamicChannels: { [name]: ChannelStats } }`, where `ChannelStats`
+    /// is `{ bytesReceived, bytesSent, pdusReceived, pdusSent }`.
+    pub fn statistics(&self) -> Result<js_sys::Object, IronRdpError> {
+        fn channels(channels: &BTreeMap<String, ChannelStats>) -> Result<js_sys::Object, JsValue> {
+            let object = js_sys::Object::new();
+
+            for (name, stats) in channels {
+                let entry = js_sys::Object::new();
+                // The counters are far below 2^53, the largest integer exactly represented by a JavaScript number.
+                #[allow(clippy::cast_precision_loss)]
+                for (key, value) in [
+                    ("bytesReceived", stats.bytes_received),
+                    ("bytesSent", stats.bytes_sent),
+                    ("pdusReceived", stats.pdus_received),
+                    ("pdusSent", stats.pdus_sent),
+                ] {
+                    js_sys::Reflect::set(&entry, &key.into(), &(value as f64).into())?;
+                }
+                js_sys::Reflect::set(&object, &name.into(), &entry)?;
+            }
+
+            Ok(object)
+        }
+
+        let statistics = self.statistics.borrow();
+        let object = js_sys::Object::new();
+
+        [
+            ("staticChannels", &statistics.static_channels),
+            ("dynamicChannels", &statistics.dynamic_channels),
+        ]
+        .into_iter()
+        .try_for_each(|(key, stats)| {
+            let channels = channels(stats)?;
+            js_sys::Reflect::set(&object, &key.into(), &channels).map(drop)
         })
+        .map_err(|e| anyhow::Error::msg(format!("failed to build the statistics object: {e:?}")))?;
+
+        Ok(object)
     }
 
     pub fn desktop_size(&self) -> DesktopSize {
@@ -708,10 +1159,82 @@ impl Session {
     }
 
     pub fn apply_inputs(&self, transaction: InputTransaction) -> Result<(), IronRdpError> {
-        let inputs = self.input_database.borrow_mut().apply(transaction);
+        let InputTransaction {
+            operations,
+            touch_contacts,
+            pen_contacts,
+            composition_commits,
+        } = transaction;
+
+        // The RDPEI frame offsets are in microseconds.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let timestamp = (js_sys::Date::now() * 1000.0) as u64;
+
+        if !touch_contacts.is_empty() {
+            self.input_events_tx
+                .unbounded_send(RdpInputEvent::Touch {
+                    contacts: touch_contacts,
+                    timestamp,
+                })
+                .context("Send touch events to writer task")?;
+        }
+
+        for contact in pen_contacts {
+            self.input_events_tx
+                .unbounded_send(RdpInputEvent::Pen { contact, timestamp })
+                .context("Send pen events to writer task")?;
+        }
+
+        let mut database = self.input_database.borrow_mut();
+        let mut composition = self.composition.borrow_mut();
+        let policy = self.keyboard_capture_policy.borrow();
+
+        // The operations are intercepted one by one, as each of them may change the modifiers of the next ones.
+        let mut inputs = smallvec::SmallVec::new();
+        for operation in operations {
+            let Some(operation) = composition.filter(operation) else {
+                continue;
+            };
+            let intercepted = policy.intercept(&database, operation);
+            inputs.extend(database.apply(intercepted));
+        }
+
+        for commit in composition_commits {
+            inputs.extend(database.apply(composition.commit(&commit.text, commit.replaced)));
+        }
+
         self.h_send_inputs(inputs)
     }
 
+    /// Starts an IME composition, on `compositionstart`.
+    ///
+    /// The key presses are not sent to the remote session until the composition ends, the composed text being
+    /// typed once committed.
+    pub fn start_composition(&self) {
+        self.composition.borrow_mut().start();
+    }
+
+    /// Ends the IME composition, on `compositionend`, whether the text was committed or the composition cancelled.
+    pub fn end_composition(&self) {
+        self.composition.borrow_mut().end();
+    }
+
+    pub fn set_keyboard_capture_policy(&self, policy: &KeyboardCapturePolicy) {
+        *self.keyboard_capture_policy.borrow_mut() = policy.0.clone();
+    }
+
+    /// Returns the key combinations for which `preventDefault` must be called on the keyboard event.
+    ///
+    /// The key combinations remapped with `KeyboardCapturePolicy::remap_modifier` are not listed.
+    pub fn prevent_default_chords(&self) -> Vec<KeyboardChord> {
+        self.keyboard_capture_policy
+            .borrow()
+            .prevent_default_chords()
+            .into_iter()
+            .map(KeyboardChord)
+            .collect()
+    }
+
     pub fn release_all_inputs(&self) -> Result<(), IronRdpError> {
         let inputs = self.input_database.borrow_mut().release_all();
         self.h_send_inputs(inputs)
@@ -736,18 +1259,9 @@ impl Session {
         caps_lock: bool,
         kana_lock: bool,
     ) -> Result<(), IronRdpError> {
-        use ironrdp::pdu::input::fast_path::FastPathInput;
-
         let event = ironrdp::input::synchronize_event(scroll_lock, num_lock, caps_lock, kana_lock);
-        let fastpath_input = FastPathInput(vec![event]);
-
-        let frame = ironrdp::core::encode_vec(&fastpath_input).context("FastPathInput encoding")?;
-
-        self.writer_tx
-            .unbounded_send(frame)
-            .context("Send frame to writer task")?;
 
-        Ok(())
+        self.h_send_inputs(smallvec::smallvec![event])
     }
 
     pub fn shutdown(&self) -> Result<(), IronRdpError> {
@@ -758,6 +1272,20 @@ impl Session {
         Ok(())
     }
 
+    /// Sends `data` on the dynamic channel `channel_id`, opened by the server for a channel registered with
+    /// `SessionBuilder::register_dvc_channel`.
+    pub fn send_dvc_data(&self, channel_id: u32, data: Vec<u8>) -> Result<(), IronRdpError> {
+        if !self.dvc_channels.borrow().is_open(channel_id) {
+            return Err(anyhow::anyhow!("dynamic channel {channel_id} is not opened").into());
+        }
+
+        self.input_events_tx
+            .unbounded_send(RdpInputEvent::DvcData { channel_id, data })
+            .context("Send DVC data event")?;
+
+        Ok(())
+    }
+
     pub async fn on_clipboard_paste(&self, content: ClipboardTransaction) -> Result<(), IronRdpError> {
         self.input_events_tx
             .unbounded_send(RdpInputEvent::ClipboardBackend(
@@ -768,6 +1296,133 @@ impl Session {
         Ok(())
     }
 
+    /// Attempts to reconnect to the session, following the reconnection policy.
+    #[allow(clippy::too_many_arguments)]
+    async fn reconnect(
+        &self,
+        controller: &mut ReconnectController,
+        input_events: &mut mpsc::UnboundedReceiver<RdpInputEvent>,
+        coalescer: &mut UpdateCoalescer,
+        gui: &mut Canvas,
+        image: &DecodedImage,
+        auto_reconnect_cookie: Option<ServerAutoReconnect>,
+        clipboard: Option<&WasmClipboard>,
+    ) -> Result<ReconnectOutcome, IronRdpError> {
+        debug_assert_eq!(controller.state(), ReconnectState::Connected);
+
+        loop {
+            let event = controller.connection_lost();
+            self.notify_reconnect_state(event)?;
+
+            let ReconnectEvent::Reconnecting { attempt, delay } = event else {
+                warn!("Giving up reconnecting");
+                return Ok(ReconnectOutcome::GaveUp);
+            };
+
+            info!(attempt, ?delay, "Reconnecting");
+
+            let snapshot = Some(self.snapshot.borrow().clone());
+            let dvc_channels = self.dvc_channels.borrow().processors(&self.input_events_tx);
+            let mut attempt_future = pin!(async {
+                FuturesTimer.sleep(delay).await;
+
+                self.parameters
+                    .establish(
+                        connector::DesktopSize {
+                            width: image.width(),
+                            height: image.height(),
+                        },
+                        clipboard.map(|clip| clip.backend()),
+                        dvc_channels,
+                        snapshot,
+                        auto_reconnect_cookie.clone(),
+                    )
+                    .await
+            }
+            .fuse());
+
+            let connected = loop {
+                select! {
+                    connected = attempt_future => break connected,
+                    event = input_events.next() => match event.context("read next input events")? {
+                        RdpInputEvent::TerminateSession => return Ok(ReconnectOutcome::Terminated),
+                        RdpInputEvent::Visibility(visible) => {
+                            debug!(visible, "Visibility changed");
+                            if let Some(region) = coalescer.set_visible(visible) {
+                                gui.draw(image, region).context("draw coalesced region")?;
+                            }
+                        }
+                        event => trace!(?event, "Input event dropped while reconnecting"),
+                    },
+                }
+            };
+
+            match connected {
+                Ok(connected) => {
+                    info!(attempt, resumed = connected.resumed, "Reconnected!");
+                    self.notify_reconnect_state(controller.reconnected())?;
+                    return Ok(ReconnectOutcome::Reconnected(connected));
+                }
+                Err(e) => warn!(attempt, error = e.backtrace(), "Reconnection attempt failed"),
+            }
+        }
+    }
+
+    fn notify_reconnect_state(&self, event: ReconnectEvent) -> Result<(), IronRdpError> {
+        let Some(callback) = &self.reconnect_state_changed_callback else {
+            return Ok(());
+        };
+
+        let _ret = callback
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from_str(event.name()),
+                &JsValue::from_f64(event.attempt().into()),
+            )
+            .map_err(|e| anyhow::Error::msg(format!("reconnect state changed callback failed: {e:?}")))?;
+
+        Ok(())
+    }
+
+    fn beep(&self, frequency_hz: u32, duration_ms: u32) -> Result<(), IronRdpError> {
+        let Some(callback) = &self.beep_callback else {
+            debug!(frequency_hz, duration_ms, "Beep ignored, no callback set");
+            return Ok(());
+        };
+
+        let _ret = callback
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from_f64(frequency_hz.into()),
+                &JsValue::from_f64(duration_ms.into()),
+            )
+            .map_err(|e| anyhow::Error::msg(format!("beep callback failed: {e:?}")))?;
+
+        Ok(())
+    }
+
+    /// Resizes the canvas to match the image, after it was resized by the active stage.
+    fn resize_canvas(&self, gui: &mut Canvas, coalescer: &mut UpdateCoalescer, width: u16, height: u16) {
+        let (Some(non_zero_width), Some(non_zero_height)) =
+            (NonZeroU32::new(u32::from(width)), NonZeroU32::new(u32::from(height)))
+        else {
+            return;
+        };
+
+        self.render_canvas.set_size(u32::from(width), u32::from(height));
+        gui.resize(non_zero_width, non_zero_height);
+
+        // The canvas is cleared by the resize, and must be redrawn entirely when shown again.
+        if !coalescer.is_visible() {
+            coalescer.update(InclusiveRectangle {
+                left: 0,
+                top: 0,
+                right: width.saturating_sub(1),
+                bottom: height.saturating_sub(1),
+            });
+        }
+    }
+
     fn set_cursor_style(&self, style: CursorStyle) -> Result<(), IronRdpError> {
         let (kind, data, hotspot_x, hotspot_y) = match style {
             CursorStyle::Default => ("default", None, None, None),
@@ -812,6 +1467,17 @@ impl Session {
             .expect("send resize event to writer task");
     }
 
+    /// Notifies the session that the render canvas was shown or hidden.
+    ///
+    /// While hidden, the canvas is not drawn, and it is redrawn at once when shown again.
+    pub fn set_visibility(&self, visible: bool) -> Result<(), IronRdpError> {
+        self.input_events_tx
+            .unbounded_send(RdpInputEvent::Visibility(visible))
+            .context("send visibility event")?;
+
+        Ok(())
+    }
+
     #[allow(clippy::unused_self)]
     pub fn supports_unicode_keyboard_shortcuts(&self) -> bool {
         // RDP does not support Unicode keyboard shortcuts (When key combinations are executed, only
@@ -820,29 +1486,47 @@ impl Session {
     }
 }
 
+/// Closes the dynamic channel `channel_name`, which stops being listened to for the rest of the connection.
+fn close_dvc_channel(
+    active_stage: &mut ActiveStage,
+    channel_name: &str,
+) -> Result<Vec<ActiveStageOutput>, IronRdpError> {
+    let Some(close_request) = active_stage
+        .get_svc_processor_mut::<DrdynvcClient>()
+        .and_then(|drdynvc| drdynvc.detach_dynamic_channel(channel_name))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let frame =
+        active_stage.process_svc_processor_messages(SvcProcessorMessages::<DrdynvcClient>::new(vec![close_request]))?;
+
+    Ok(vec![ActiveStageOutput::ResponseFrame(frame)])
+}
+
 fn build_config(
     username: String,
     password: String,
     domain: Option<String>,
     client_name: String,
-    desktop_size: DesktopSize,
+    desktop_size: connector::DesktopSize,
+    auto_reconnect_cookie: Option<ServerAutoReconnect>,
 ) -> connector::Config {
     connector::Config {
         credentials: Credentials::UsernamePassword { username, password },
         domain,
+        credential_delegation: connector::CredentialDelegation::Full,
         // TODO(#327): expose these options from the WASM module.
         enable_tls: true,
         enable_credssp: true,
         keyboard_type: ironrdp::pdu::gcc::KeyboardType::IbmEnhanced,
         keyboard_subtype: 0,
         keyboard_layout: 0, // the server SHOULD use the default active input locale identifier
+        active_input_locale: None,
         keyboard_functional_keys_count: 12,
         ime_file_name: String::new(),
         dig_product_id: String::new(),
-        desktop_size: connector::DesktopSize {
-            width: desktop_size.width,
-            height: desktop_size.height,
-        },
+        desktop_size,
         bitmap: Some(connector::BitmapConfig {
             color_depth: 16,
             lossy_compression: true,
@@ -861,96 +1545,210 @@ fn build_config(
         no_server_pointer: false,
         autologon: false,
         request_data: None,
+        correlation_id: None,
         pointer_software_rendering: false,
         performance_flags: PerformanceFlags::default(),
         desktop_scale_factor: 0,
         hardware_id: None,
         license_cache: None,
+        remote_app: None,
+        auto_reconnect_cookie,
+        frame_markers: true,
+        timeouts: connector::ConnectTimeouts::default(),
+        bitmap_cache: None,
+        extra_capability_sets: Vec::new(),
+        decline_multitransport: true,
+        redirect_session: None,
     }
 }
 
-async fn writer_task(rx: mpsc::UnboundedReceiver<Vec<u8>>, rdp_writer: WriteHalf<WebSocket>) {
-    debug!("writer task started");
+/// Splits the transport, spawning the writer task fed by the returned driver.
+fn start_io(transport: Transport) -> SessionDriver<LocalFuturesStream<ReadHalf<Transport>>> {
+    let (rdp_reader, rdp_writer) = futures_util::AsyncReadExt::split(transport);
 
-    async fn inner(
-        mut rx: mpsc::UnboundedReceiver<Vec<u8>>,
-        mut rdp_writer: WriteHalf<WebSocket>,
-    ) -> anyhow::Result<()> {
-        while let Some(frame) = rx.next().await {
-            rdp_writer.write_all(&frame).await.context("Couldn’t write frame")?;
-            rdp_writer.flush().await.context("Couldn’t flush")?;
+    let (driver, writer_task) = SessionDriver::new(
+        LocalFuturesFramed::new(rdp_reader),
+        LocalFuturesFramed::new(rdp_writer),
+        DEFAULT_OUTBOUND_CAPACITY,
+    );
+
+    spawn_local(async move {
+        match writer_task.run().await {
+            Ok(_) => debug!("writer task ended gracefully"),
+            Err(e) => error!("writer task ended unexpectedly: {e:#}"),
         }
+    });
 
-        Ok(())
-    }
+    driver
+}
+
+/// Parameters of the connection, kept by the session to reconnect.
+struct ConnectionParameters {
+    username: String,
+    password: String,
+    server_domain: Option<String>,
+    client_name: String,
+    proxy_address: String,
+    auth_token: String,
+    destination: String,
+    pcb: Option<String>,
+    kdc_proxy_url: Option<String>,
+    transport_kind: TransportKind,
+    use_display_control: bool,
+    clipboard_policy: ClipboardPolicy,
+}
 
-    match inner(rx, rdp_writer).await {
-        Ok(()) => debug!("writer task ended gracefully"),
-        Err(e) => error!("writer task ended unexpectedly: {e:#}"),
+impl ConnectionParameters {
+    /// Connects to the proxy, and goes through the RDCleanPath exchange and the RDP connection sequence.
+    async fn establish(
+        &self,
+        desktop_size: connector::DesktopSize,
+        clipboard_backend: Option<WasmClipboardBackend>,
+        dvc_channels: Vec<JsDvcProcessor>,
+        snapshot: Option<ironrdp_rdcleanpath::ConnectionSnapshot>,
+        auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    ) -> Result<Connected, IronRdpError> {
+        let config = build_config(
+            self.username.clone(),
+            self.password.clone(),
+            self.server_domain.clone(),
+            self.client_name.clone(),
+            desktop_size,
+            auto_reconnect_cookie,
+        );
+
+        let transport = Transport::connect(self.transport_kind, &self.proxy_address).await?;
+
+        connect(ConnectParams {
+            transport,
+            config,
+            proxy_auth_token: self.auth_token.clone(),
+            destination: self.destination.clone(),
+            pcb: self.pcb.clone(),
+            kdc_proxy_url: self.kdc_proxy_url.clone(),
+            clipboard_backend,
+            clipboard_policy: self.clipboard_policy,
+            use_display_control: self.use_display_control,
+            dvc_channels,
+            snapshot,
+        })
+        .await
     }
 }
 
 struct ConnectParams {
-    ws: WebSocket,
+    transport: Transport,
     config: connector::Config,
     proxy_auth_token: String,
     destination: String,
     pcb: Option<String>,
     kdc_proxy_url: Option<String>,
     clipboard_backend: Option<WasmClipboardBackend>,
+    clipboard_policy: ClipboardPolicy,
     use_display_control: bool,
+    dvc_channels: Vec<JsDvcProcessor>,
+    snapshot: Option<ironrdp_rdcleanpath::ConnectionSnapshot>,
+}
+
+struct Connected {
+    connection_result: connector::ConnectionResult,
+    transport: Transport,
+    snapshot: ironrdp_rdcleanpath::ConnectionSnapshot,
+    /// Whether the snapshot passed in the parameters was reused.
+    resumed: bool,
 }
 
 async fn connect(
     ConnectParams {
-        ws,
+        transport,
         config,
         proxy_auth_token,
         destination,
         pcb,
         kdc_proxy_url,
         clipboard_backend,
+        clipboard_policy,
         use_display_control,
+        dvc_channels,
+        snapshot,
     }: ConnectParams,
-) -> Result<(connector::ConnectionResult, WebSocket), IronRdpError> {
-    let mut framed = ironrdp_futures::LocalFuturesFramed::new(ws);
+) -> Result<Connected, IronRdpError> {
+    let mut framed = ironrdp_futures::LocalFuturesFramed::new(transport);
 
     let mut connector = ClientConnector::new(config);
 
     if let Some(clipboard_backend) = clipboard_backend {
-        connector.attach_static_channel(CliprdrClient::new(Box::new(clipboard_backend)));
+        connector.attach_static_channel(
+            CliprdrClient::new(Box::new(clipboard_backend)).with_policy(clipboard_policy.into()),
+        );
     }
 
+    let mut drdynvc = DrdynvcClient::new().with_dynamic_channel(RdpeiClient::default());
+
     if use_display_control {
-        connector.attach_static_channel(
-            DrdynvcClient::new().with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new()))),
-        );
+        drdynvc = drdynvc.with_dynamic_channel(DisplayControlClient::new(|_| Ok(Vec::new())));
     }
 
-    let (upgraded, server_public_key) =
-        connect_rdcleanpath(&mut framed, &mut connector, destination.clone(), proxy_auth_token, pcb).await?;
+    for channel in dvc_channels {
+        drdynvc = drdynvc.with_dynamic_channel(channel);
+    }
+
+    connector.attach_static_channel(drdynvc);
+
+    let RDCleanPathOutcome {
+        upgraded,
+        server_public_key,
+        snapshot,
+        resumed,
+    } = connect_rdcleanpath(
+        &mut framed,
+        &mut connector,
+        destination.clone(),
+        proxy_auth_token,
+        pcb,
+        snapshot,
+    )
+    .await?;
+
+    // If kdc_proxy_url does not exist, give url parser an empty string, it will fail anyway and map to a None.
+    let kdc_proxy_url = url::Url::parse(kdc_proxy_url.unwrap_or_default().as_str()).ok();
+
+    let mut network_client = WasmNetworkClient::new(kdc_proxy_url.clone());
+
+    let mut timer = ironrdp_futures::ConnectTimer::new(FuturesTimer);
 
     let connection_result = ironrdp_futures::connect_finalize(
         upgraded,
         &mut framed,
         connector,
+        &mut timer,
         (&destination).into(),
         server_public_key,
-        Some(&mut WasmNetworkClient),
-        url::Url::parse(kdc_proxy_url.unwrap_or_default().as_str()) // if kdc_proxy_url does not exit, give url parser a empty string, it will fail anyway and map to a None
-            .ok()
-            .map(|url| KerberosConfig {
-                kdc_proxy_url: Some(url),
-                // HACK: It’s supposed to be the computer name of the client, but since it’s not easy to retrieve this information in the browser,
-                // we set the destination hostname instead because it happens to work.
-                hostname: Some(destination),
-            }),
+        Some(&mut network_client),
+        kdc_proxy_url.map(|url| KerberosConfig {
+            kdc_proxy_url: Some(url),
+            // HACK: It’s supposed to be the computer name of the client, but since it’s not easy to retrieve this information in the browser,
+            // we set the destination hostname instead because it happens to work.
+            hostname: Some(destination),
+        }),
     )
     .await?;
 
-    let ws = framed.into_inner_no_leftover();
+    let transport = framed.into_inner_no_leftover();
 
-    Ok((connection_result, ws))
+    Ok(Connected {
+        connection_result,
+        transport,
+        snapshot,
+        resumed,
+    })
+}
+
+struct RDCleanPathOutcome {
+    upgraded: ironrdp_futures::Upgraded,
+    server_public_key: Vec<u8>,
+    snapshot: ironrdp_rdcleanpath::ConnectionSnapshot,
+    resumed: bool,
 }
 
 async fn connect_rdcleanpath<S>(
@@ -959,7 +1757,8 @@ async fn connect_rdcleanpath<S>(
     destination: String,
     proxy_auth_token: String,
     pcb: Option<String>,
-) -> Result<(ironrdp_futures::Upgraded, Vec<u8>), IronRdpError>
+    snapshot: Option<ironrdp_rdcleanpath::ConnectionSnapshot>,
+) -> Result<RDCleanPathOutcome, IronRdpError>
 where
     S: ironrdp_futures::FramedRead + FramedWrite,
 {
@@ -1003,7 +1802,7 @@ where
         let x224_pdu = buf.filled().to_vec();
 
         let rdcleanpath_req =
-            ironrdp_rdcleanpath::RDCleanPathPdu::new_request(x224_pdu, destination, proxy_auth_token, pcb)
+            ironrdp_rdcleanpath::RDCleanPathPdu::new_request(x224_pdu, destination.clone(), proxy_auth_token, pcb)
                 .context("new RDCleanPath request")?;
         debug!(message = ?rdcleanpath_req, "Send RDCleanPath request");
         let rdcleanpath_req = rdcleanpath_req.to_der().context("RDCleanPath request encode")?;
@@ -1036,6 +1835,7 @@ where
                     x224_connection_response,
                     server_cert_chain,
                     server_addr,
+                    ..
                 } => (x224_connection_response, server_cert_chain, server_addr),
                 ironrdp_rdcleanpath::RDCleanPath::Err(error) => {
                     return Err(
@@ -1045,6 +1845,28 @@ where
                 }
             };
 
+        let decision = snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.resume_decision(&destination, &server_cert_chain));
+
+        match decision {
+            Some(ironrdp_rdcleanpath::ResumeDecision::Resume) => {
+                debug!("The proxy sent the cached certificate chain, resuming");
+            }
+            Some(decision) => {
+                info!(
+                    ?decision,
+                    "Connection snapshot discarded, validating the server from scratch"
+                );
+            }
+            None => {}
+        }
+
+        let resumed = decision == Some(ironrdp_rdcleanpath::ResumeDecision::Resume);
+
+        let new_snapshot =
+            ironrdp_rdcleanpath::ConnectionSnapshot::new(destination, server_addr.clone(), server_cert_chain.clone());
+
         let server_addr = server_addr
             .parse()
             .context("failed to parse server address sent by proxy")?;
@@ -1084,7 +1906,12 @@ where
 
         let upgraded = ironrdp_futures::mark_as_upgraded(should_upgrade, connector);
 
-        Ok((upgraded, server_public_key))
+        Ok(RDCleanPathOutcome {
+            upgraded,
+            server_public_key,
+            snapshot: new_snapshot,
+            resumed,
+        })
     }
 }
 
@@ -0,0 +1,38 @@
+use anyhow::Context as _;
+use base64::Engine as _;
+use wasm_bindgen::prelude::*;
+
+use crate::error::IronRdpError;
+
+/// Outcome of the RDCleanPath exchange of a previous session, used to speed up reconnections.
+///
+/// See `Session::connection_snapshot` and `SessionBuilder::resume_with`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct ConnectionSnapshot(pub(crate) ironrdp_rdcleanpath::ConnectionSnapshot);
+
+#[wasm_bindgen]
+impl ConnectionSnapshot {
+    /// Serializes the snapshot into a string, so the application can persist it (e.g.: in the session storage).
+    pub fn serialize(&self) -> Result<String, IronRdpError> {
+        let der = self.0.to_der().context("ConnectionSnapshot encode")?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(der))
+    }
+
+    /// Restores a snapshot previously serialized with `serialize`.
+    pub fn deserialize(serialized: &str) -> Result<ConnectionSnapshot, IronRdpError> {
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(serialized)
+            .context("ConnectionSnapshot base64 decode")?;
+        let snapshot = ironrdp_rdcleanpath::ConnectionSnapshot::from_der(&der).context("ConnectionSnapshot decode")?;
+        Ok(Self(snapshot))
+    }
+
+    pub fn destination(&self) -> String {
+        self.0.destination.clone()
+    }
+
+    pub fn server_addr(&self) -> String {
+        self.0.server_addr.clone()
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(feature = "webtransport")]
+mod webtransport;
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::io;
+
+use anyhow::Context as _;
+use futures_util::io::{AsyncRead, AsyncWrite};
+use gloo_net::websocket;
+use gloo_net::websocket::futures::WebSocket;
+use ironrdp_futures::{AsyncTimer as _, FuturesTimer};
+use wasm_bindgen::prelude::*;
+
+use crate::error::{IronRdpError, IronRdpErrorKind};
+
+/// Transport used to reach the RDP proxy
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    WebSocket,
+    /// WebTransport over HTTP/3, avoiding head-of-line blocking across streams
+    ///
+    /// Requires the `webtransport` feature. WebSocket is used instead when WebTransport is not available.
+    WebTransport,
+}
+
+/// Byte stream to the RDP proxy
+pub(crate) enum Transport {
+    WebSocket(WebSocket),
+    #[cfg(feature = "webtransport")]
+    WebTransport(webtransport::WebTransportStream),
+}
+
+impl Transport {
+    /// Connects to the proxy using the requested transport, falling back to WebSocket if necessary.
+    pub(crate) async fn connect(kind: TransportKind, proxy_address: &str) -> Result<Self, IronRdpError> {
+        match kind {
+            TransportKind::WebSocket => {}
+            #[cfg(feature = "webtransport")]
+            TransportKind::WebTransport => {
+                if webtransport::is_supported() {
+                    match webtransport::connect(proxy_address).await {
+                        Ok(stream) => return Ok(Self::WebTransport(stream)),
+                        Err(e) => warn!("WebTransport connection failed, falling back to WebSocket: {e:#}"),
+                    }
+                } else {
+                    warn!("WebTransport is not supported by this browser, falling back to WebSocket");
+                }
+            }
+            #[cfg(not(feature = "webtransport"))]
+            TransportKind::WebTransport => {
+                warn!("WebTransport support is not enabled, falling back to WebSocket");
+            }
+        }
+
+        connect_websocket(proxy_address).await.map(Self::WebSocket)
+    }
+}
+
+async fn connect_websocket(proxy_address: &str) -> Result<WebSocket, IronRdpError> {
+    let ws = WebSocket::open(proxy_address).context("Couldn’t open WebSocket")?;
+
+    // NOTE: ideally, when the WebSocket can’t be opened, the above call should fail with details on why is that
+    // (e.g., the proxy hostname could not be resolved, proxy service is not running), but errors are neved
+    // bubbled up in practice, so instead we poll the WebSocket state until we know its connected (i.e., the
+    // WebSocket handshake is a success and user data can be exchanged).
+    loop {
+        match ws.state() {
+            websocket::State::Closing | websocket::State::Closed => {
+                return Err(IronRdpError::from(anyhow::anyhow!(
+                    "Failed to connect to {proxy_address} (WebSocket is `{:?}`)",
+                    ws.state()
+                ))
+                .with_kind(IronRdpErrorKind::ProxyConnect));
+            }
+            websocket::State::Connecting => {
+                trace!("WebSocket is connecting to proxy at {proxy_address}...");
+                FuturesTimer.sleep(Duration::from_millis(50)).await;
+            }
+            websocket::State::Open => {
+                debug!("WebSocket connected to {proxy_address} with success");
+                return Ok(ws);
+            }
+        }
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::WebSocket(ws) => Pin::new(ws).poll_read(cx, buf),
+            #[cfg(feature = "webtransport")]
+            Self::WebTransport(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::WebSocket(ws) => Pin::new(ws).poll_write(cx, buf),
+            #[cfg(feature = "webtransport")]
+            Self::WebTransport(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::WebSocket(ws) => Pin::new(ws).poll_flush(cx),
+            #[cfg(feature = "webtransport")]
+            Self::WebTransport(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::WebSocket(ws) => Pin::new(ws).poll_close(cx),
+            #[cfg(feature = "webtransport")]
+            Self::WebTransport(stream) => Pin::new(stream).poll_close(cx),
+        }
+    }
+}
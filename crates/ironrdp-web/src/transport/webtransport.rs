@@ -0,0 +1,133 @@
+//! WebTransport-based transport
+//!
+//! For now, the whole RDP traffic goes through a single bidirectional stream. [`WebTransportSession`] is kept
+//! separate from the stream so that additional streams (e.g.: one per dynamic virtual channel) can be opened on the
+//! same session later.
+//!
+//! The `web_sys` bindings for WebTransport are unstable and require building with `--cfg=web_sys_unstable_apis`.
+
+use core::pin::Pin;
+use std::io;
+
+use anyhow::Context as _;
+use futures_util::stream::LocalBoxStream;
+use futures_util::{Sink, StreamExt as _};
+use ironrdp_futures::ChunkedStream;
+use js_sys::{Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast as _;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ReadableStreamDefaultReader, WritableStreamDefaultWriter};
+
+pub(crate) type WebTransportStream =
+    ChunkedStream<LocalBoxStream<'static, io::Result<Vec<u8>>>, Pin<Box<dyn Sink<Vec<u8>, Error = io::Error>>>>;
+
+/// Returns `true` if the browser exposes the WebTransport API.
+pub(crate) fn is_supported() -> bool {
+    Reflect::has(&js_sys::global(), &JsValue::from_str("WebTransport")).unwrap_or(false)
+}
+
+/// Opens a WebTransport session to the proxy and returns the stream carrying the RDP traffic.
+///
+/// The proxy address is expected to be a `wss://` URL, which is mapped to the equivalent `https://` URL.
+pub(crate) async fn connect(proxy_address: &str) -> anyhow::Result<WebTransportStream> {
+    let mut url = url::Url::parse(proxy_address).context("invalid proxy address")?;
+
+    if url.scheme() != "wss" {
+        anyhow::bail!("WebTransport requires a secure proxy address");
+    }
+
+    url.set_scheme("https")
+        .map_err(|()| anyhow::anyhow!("couldn’t convert proxy address to HTTPS"))?;
+
+    let session = WebTransportSession::open(url.as_str()).await?;
+
+    debug!("WebTransport connected to {url} with success");
+
+    session.open_bidirectional_stream().await
+}
+
+pub(crate) struct WebTransportSession {
+    inner: web_sys::WebTransport,
+}
+
+impl WebTransportSession {
+    pub(crate) async fn open(url: &str) -> anyhow::Result<Self> {
+        let inner = web_sys::WebTransport::new(url).map_err(js_error)?;
+
+        JsFuture::from(inner.ready())
+            .await
+            .map_err(js_error)
+            .context("WebTransport session establishment")?;
+
+        Ok(Self { inner })
+    }
+
+    pub(crate) async fn open_bidirectional_stream(&self) -> anyhow::Result<WebTransportStream> {
+        let stream = JsFuture::from(self.inner.create_bidirectional_stream())
+            .await
+            .map_err(js_error)
+            .context("bidirectional stream creation")?
+            .unchecked_into::<web_sys::WebTransportBidirectionalStream>();
+
+        let reader = stream
+            .readable()
+            .get_reader()
+            .unchecked_into::<ReadableStreamDefaultReader>();
+        let writer = stream.writable().get_writer().map_err(js_error)?;
+
+        Ok(ChunkedStream::new(recv_stream(reader), send_stream(writer)))
+    }
+}
+
+fn recv_stream(reader: ReadableStreamDefaultReader) -> LocalBoxStream<'static, io::Result<Vec<u8>>> {
+    futures_util::stream::unfold(Some(reader), |reader| async move {
+        let reader = reader?;
+
+        match read_chunk(&reader).await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(reader))),
+            Ok(None) => None,
+            // The stream is unusable after an error, so it is terminated.
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+    .boxed_local()
+}
+
+async fn read_chunk(reader: &ReadableStreamDefaultReader) -> io::Result<Option<Vec<u8>>> {
+    let result = JsFuture::from(reader.read()).await.map_err(js_io_error)?;
+
+    let done = Reflect::get(&result, &JsValue::from_str("done"))
+        .map_err(js_io_error)?
+        .as_bool()
+        .unwrap_or(false);
+
+    if done {
+        return Ok(None);
+    }
+
+    let value = Reflect::get(&result, &JsValue::from_str("value")).map_err(js_io_error)?;
+
+    Ok(Some(Uint8Array::new(&value).to_vec()))
+}
+
+fn send_stream(writer: WritableStreamDefaultWriter) -> Pin<Box<dyn Sink<Vec<u8>, Error = io::Error>>> {
+    let sink = futures_util::sink::unfold(writer, |writer, chunk: Vec<u8>| async move {
+        let chunk = Uint8Array::from(chunk.as_slice());
+        JsFuture::from(writer.write_with_chunk(&chunk))
+            .await
+            .map_err(js_io_error)?;
+
+        Ok::<_, io::Error>(writer)
+    });
+
+    Box::pin(sink)
+}
+
+fn js_error(error: JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{error:?}")
+}
+
+fn js_io_error(error: JsValue) -> io::Error {
+    io::Error::other(format!("{error:?}"))
+}
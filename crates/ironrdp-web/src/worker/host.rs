@@ -0,0 +1,238 @@
+use core::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Context as _;
+use wasm_bindgen::closure::WasmClosure;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{DedicatedWorkerGlobalScope, OffscreenCanvas};
+
+use super::protocol::{ConnectRequest, WorkerEvent, WorkerRequest};
+use super::{clipboard_items, clipboard_transaction, message_data, split_message_data};
+use crate::clipboard::ClipboardTransaction;
+use crate::error::IronRdpError;
+use crate::session::{Session, SessionBuilder};
+use crate::DesktopSize;
+
+/// Runs a session inside a Web Worker, on behalf of the `WorkerSession` of the main thread
+///
+/// The worker script initializes the module, and forwards the data of its `message` events:
+/// ```javascript
+/// import init, { ironrdp_init, SessionWorkerHost } from './ironrdp_web.js';
+///
+/// await init();
+/// ironrdp_init('INFO');
+///
+/// const host = SessionWorkerHost.new();
+/// self.onmessage = (event) => host.handle_message(event.data);
+/// ```
+#[wasm_bindgen]
+pub struct SessionWorkerHost(Rc<HostInner>);
+
+struct HostInner {
+    scope: DedicatedWorkerGlobalScope,
+    /// Set while the session is running.
+    session: RefCell<Option<Rc<Session>>>,
+}
+
+impl HostInner {
+    fn post(&self, event: &WorkerEvent) {
+        let (data, transfer) = message_data(event.encode(), None);
+
+        if let Err(error) = self.scope.post_message_with_transfer(&data, &transfer) {
+            error!(?error, "Failed to post a message to the main thread");
+        }
+    }
+
+    fn post_error(&self, error: &IronRdpError) {
+        self.post(&WorkerEvent::Failed {
+            kind: error.kind().to_u8(),
+            code: error.code(),
+            message: error.backtrace(),
+        });
+    }
+}
+
+#[wasm_bindgen]
+impl SessionWorkerHost {
+    /// Must be called from a dedicated worker.
+    pub fn new() -> Self {
+        Self(Rc::new(HostInner {
+            scope: js_sys::global().unchecked_into(),
+            session: RefCell::new(None),
+        }))
+    }
+
+    /// Handles the data of a `message` event posted by the main thread.
+    pub fn handle_message(&self, data: JsValue) -> Result<(), IronRdpError> {
+        let (encoded, transferred) = split_message_data(&data)?;
+        let request = WorkerRequest::decode(&encoded).context("decode worker request")?;
+
+        let session = match request {
+            WorkerRequest::Connect(request) => {
+                let canvas = transferred
+                    .dyn_into::<OffscreenCanvas>()
+                    .ok()
+                    .context("OffscreenCanvas missing from connect request")?;
+                return self.connect(request, canvas);
+            }
+            _ => self.0.session.borrow().clone(),
+        };
+
+        let Some(session) = session else {
+            debug!("Request ignored, no session is running");
+            return Ok(());
+        };
+
+        match request {
+            WorkerRequest::Connect(_) => unreachable!("handled above"),
+            WorkerRequest::ApplyInputs(batch) => session.apply_inputs(batch.into())?,
+            WorkerRequest::ReleaseAllInputs => session.release_all_inputs()?,
+            WorkerRequest::SynchronizeLockKeys {
+                scroll_lock,
+                num_lock,
+                caps_lock,
+                kana_lock,
+            } => session.synchronize_lock_keys(scroll_lock, num_lock, caps_lock, kana_lock)?,
+            WorkerRequest::StartComposition => session.start_composition(),
+            WorkerRequest::EndComposition => session.end_composition(),
+            WorkerRequest::Resize {
+                width,
+                height,
+                scale_factor,
+                physical_size,
+            } => session.resize(
+                width,
+                height,
+                scale_factor,
+                physical_size.map(|(width, _)| width),
+                physical_size.map(|(_, height)| height),
+            ),
+            WorkerRequest::SetVisibility(visible) => session.set_visibility(visible)?,
+            WorkerRequest::ClipboardPaste(items) => {
+                let inner = Rc::clone(&self.0);
+                spawn_local(async move {
+                    if let Err(error) = session.on_clipboard_paste(clipboard_transaction(items)).await {
+                        inner.post_error(&error);
+                    }
+                });
+            }
+            WorkerRequest::Shutdown => session.shutdown()?,
+        }
+
+        Ok(())
+    }
+
+    fn connect(&self, request: ConnectRequest, canvas: OffscreenCanvas) -> Result<(), IronRdpError> {
+        if self.0.session.borrow().is_some() {
+            return Err(anyhow::Error::msg("a session is already running in this worker").into());
+        }
+
+        let on_cursor_style = self.callback::<dyn Fn(String, Option<String>, u16, u16)>(|inner| {
+            Box::new(move |kind, data, hotspot_x, hotspot_y| {
+                inner.post(&WorkerEvent::CursorStyle {
+                    kind,
+                    data,
+                    hotspot_x,
+                    hotspot_y,
+                });
+            })
+        });
+
+        let on_reconnect_state_changed = self.callback::<dyn Fn(String, u32)>(|inner| {
+            Box::new(move |state, attempt| inner.post(&WorkerEvent::ReconnectStateChanged { state, attempt }))
+        });
+
+        let on_beep = self.callback::<dyn Fn(u32, u32)>(|inner| {
+            Box::new(move |frequency_hz, duration_ms| {
+                inner.post(&WorkerEvent::Beep {
+                    frequency_hz,
+                    duration_ms,
+                });
+            })
+        });
+
+        let builder = SessionBuilder::new()
+            .username(request.username)
+            .password(request.password)
+            .destination(request.destination)
+            .proxy_address(request.proxy_address)
+            .auth_token(request.auth_token)
+            .kdc_proxy_url(request.kdc_proxy_url)
+            .desktop_size(DesktopSize::new(request.desktop_width, request.desktop_height))
+            .transport(request.transport)
+            .clipboard_policy(request.clipboard_policy)
+            .auto_reconnect(request.reconnect_max_attempts)
+            .render_offscreen_canvas(canvas)
+            .set_cursor_style_callback_context(JsValue::NULL)
+            .set_cursor_style_callback(on_cursor_style)
+            .reconnect_state_changed_callback(on_reconnect_state_changed)
+            .beep_callback(on_beep);
+
+        if let Some(server_domain) = request.server_domain {
+            builder.server_domain(server_domain);
+        }
+
+        if let Some(pcb) = request.pcb {
+            builder.pcb(pcb);
+        }
+
+        if request.use_display_control {
+            builder.use_display_control();
+        }
+
+        // The clipboard callbacks are only set when the main thread has some, as they enable the clipboard channel.
+        if request.clipboard {
+            builder.remote_clipboard_changed_callback(self.callback::<dyn Fn(ClipboardTransaction)>(|inner| {
+                Box::new(move |transaction| {
+                    inner.post(&WorkerEvent::RemoteClipboardChanged(clipboard_items(&transaction)));
+                })
+            }));
+            builder.remote_received_format_list_callback(
+                self.callback::<dyn Fn()>(|inner| Box::new(move || inner.post(&WorkerEvent::RemoteReceivedFormatList))),
+            );
+            builder.force_clipboard_update_callback(
+                self.callback::<dyn Fn()>(|inner| Box::new(move || inner.post(&WorkerEvent::ForceClipboardUpdate))),
+            );
+        }
+
+        let inner = Rc::clone(&self.0);
+
+        spawn_local(async move {
+            let session = match builder.connect().await {
+                Ok(session) => Rc::new(session),
+                Err(error) => {
+                    inner.post_error(&error);
+                    return;
+                }
+            };
+
+            let desktop_size = session.desktop_size();
+            *inner.session.borrow_mut() = Some(Rc::clone(&session));
+            inner.post(&WorkerEvent::Connected {
+                desktop_width: desktop_size.width,
+                desktop_height: desktop_size.height,
+            });
+
+            let outcome = session.run().await;
+            inner.session.borrow_mut().take();
+
+            match outcome {
+                Ok(info) => inner.post(&WorkerEvent::Terminated { reason: info.reason() }),
+                Err(error) => inner.post_error(&error),
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Returns a JavaScript function calling the closure built by `build`, which posts events to the main thread.
+    fn callback<T>(&self, build: impl FnOnce(Rc<HostInner>) -> Box<T>) -> js_sys::Function
+    where
+        T: ?Sized + WasmClosure,
+    {
+        Closure::wrap(build(Rc::clone(&self.0)))
+            .into_js_value()
+            .unchecked_into()
+    }
+}
@@ -0,0 +1,106 @@
+//! Execution of the session in a Web Worker, rendering on an `OffscreenCanvas`
+//!
+//! The websocket, the decoding and the rendering run inside the worker, so they do not compete with the main thread
+//! of the page. The main thread keeps a `WorkerSession`, which forwards the calls to the `SessionWorkerHost` of
+//! the worker, and invokes the callbacks which need the DOM (cursor style, clipboard) on behalf of the worker.
+
+mod host;
+mod protocol;
+mod proxy;
+
+use anyhow::Context as _;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use self::protocol::{ClipboardItem, ClipboardItemValue, InputBatch};
+use crate::clipboard::{ClipboardContent, ClipboardContentValue, ClipboardTransaction};
+use crate::input::{CompositionCommit, InputTransaction};
+
+pub(crate) use self::protocol::ConnectRequest;
+pub(crate) use self::proxy::{WorkerCallbacks, WorkerSession};
+
+/// Builds the data of a `postMessage` call, and the list of the objects it transfers.
+///
+/// The data is an array holding the encoded message, followed by `transferable` if any.
+fn message_data(encoded: Vec<u8>, transferable: Option<&JsValue>) -> (Array, Array) {
+    let encoded = Uint8Array::from(encoded.as_slice());
+
+    let data = Array::of1(&encoded);
+    let transfer = Array::of1(&encoded.buffer());
+
+    if let Some(transferable) = transferable {
+        data.push(transferable);
+        transfer.push(transferable);
+    }
+
+    (data, transfer)
+}
+
+/// Splits the data of a `message` event built with [`message_data`].
+fn split_message_data(data: &JsValue) -> anyhow::Result<(Vec<u8>, JsValue)> {
+    let data = data.dyn_ref::<Array>().context("message data is not an array")?;
+    let encoded = data
+        .get(0)
+        .dyn_into::<Uint8Array>()
+        .ok()
+        .context("message data does not start with a Uint8Array")?;
+
+    Ok((encoded.to_vec(), data.get(1)))
+}
+
+impl From<InputTransaction> for InputBatch {
+    fn from(transaction: InputTransaction) -> Self {
+        Self {
+            operations: transaction.operations.into_vec(),
+            touch_contacts: transaction.touch_contacts,
+            pen_contacts: transaction.pen_contacts,
+            composition_commits: transaction
+                .composition_commits
+                .into_iter()
+                .map(|commit| (commit.text, u32::try_from(commit.replaced).unwrap_or(u32::MAX)))
+                .collect(),
+        }
+    }
+}
+
+impl From<InputBatch> for InputTransaction {
+    fn from(batch: InputBatch) -> Self {
+        Self {
+            operations: batch.operations.into(),
+            touch_contacts: batch.touch_contacts,
+            pen_contacts: batch.pen_contacts,
+            composition_commits: batch
+                .composition_commits
+                .into_iter()
+                .map(|(text, replaced)| CompositionCommit {
+                    text,
+                    replaced: usize::try_from(replaced).unwrap_or(usize::MAX),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn clipboard_items(transaction: &ClipboardTransaction) -> Vec<ClipboardItem> {
+    transaction
+        .contents()
+        .iter()
+        .map(|content| ClipboardItem {
+            mime_type: content.mime_type().to_owned(),
+            value: match content.value() {
+                ClipboardContentValue::Text(text) => ClipboardItemValue::Text(text.clone()),
+                ClipboardContentValue::Binary(binary) => ClipboardItemValue::Binary(binary.clone()),
+            },
+        })
+        .collect()
+}
+
+fn clipboard_transaction(items: Vec<ClipboardItem>) -> ClipboardTransaction {
+    items
+        .into_iter()
+        .map(|item| match item.value {
+            ClipboardItemValue::Text(text) => ClipboardContent::new_text(&item.mime_type, &text),
+            ClipboardItemValue::Binary(binary) => ClipboardContent::new_binary(&item.mime_type, &binary),
+        })
+        .collect()
+}
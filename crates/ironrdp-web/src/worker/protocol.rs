@@ -0,0 +1,939 @@
+//! Messages exchanged between the main thread and the worker running the session
+//!
+//! Each message is serialized into a standalone buffer, which is transferred (not copied) with `postMessage`.
+//! All the integers are little-endian, the strings and byte arrays are prefixed with their length as a `u32`.
+
+use ironrdp::input::{MouseButton, MousePosition, Operation, Scancode, WheelRotations};
+use ironrdp::rdpei::client::{PenContact, TouchContact};
+use ironrdp::rdpei::contact::ContactPhase;
+use ironrdp_core::{ensure_size, invalid_field_err, unsupported_value_err, DecodeError, DecodeResult, ReadCursor};
+
+use crate::clipboard::ClipboardPolicy;
+use crate::transport::TransportKind;
+
+/// Message sent by the main thread to the worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WorkerRequest {
+    /// Connects to the RDP host, and renders on the `OffscreenCanvas` transferred along with the message.
+    Connect(ConnectRequest),
+    ApplyInputs(InputBatch),
+    ReleaseAllInputs,
+    SynchronizeLockKeys {
+        scroll_lock: bool,
+        num_lock: bool,
+        caps_lock: bool,
+        kana_lock: bool,
+    },
+    StartComposition,
+    EndComposition,
+    Resize {
+        width: u32,
+        height: u32,
+        scale_factor: Option<u32>,
+        physical_size: Option<(u32, u32)>,
+    },
+    SetVisibility(bool),
+    ClipboardPaste(Vec<ClipboardItem>),
+    Shutdown,
+}
+
+/// Parameters of the session started by the worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ConnectRequest {
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) destination: String,
+    pub(crate) server_domain: Option<String>,
+    pub(crate) proxy_address: String,
+    pub(crate) auth_token: String,
+    pub(crate) pcb: Option<String>,
+    pub(crate) kdc_proxy_url: Option<String>,
+    pub(crate) desktop_width: u16,
+    pub(crate) desktop_height: u16,
+    pub(crate) use_display_control: bool,
+    pub(crate) transport: TransportKind,
+    /// Whether the main thread handles the remote clipboard changes.
+    pub(crate) clipboard: bool,
+    pub(crate) clipboard_policy: ClipboardPolicy,
+    /// Zero if the automatic reconnection is disabled.
+    pub(crate) reconnect_max_attempts: u32,
+}
+
+/// Input events applied together, see `InputTransaction`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct InputBatch {
+    pub(crate) operations: Vec<Operation>,
+    pub(crate) touch_contacts: Vec<TouchContact>,
+    pub(crate) pen_contacts: Vec<PenContact>,
+    /// Text committed by the IME, and number of previously committed characters it replaces.
+    pub(crate) composition_commits: Vec<(String, u32)>,
+}
+
+/// Clipboard content in a single format, see `ClipboardContent`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClipboardItem {
+    pub(crate) mime_type: String,
+    pub(crate) value: ClipboardItemValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ClipboardItemValue {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Message sent by the worker to the main thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WorkerEvent {
+    Connected {
+        desktop_width: u16,
+        desktop_height: u16,
+    },
+    /// Arguments of the cursor style callback, see `SessionBuilder::set_cursor_style_callback`.
+    CursorStyle {
+        kind: String,
+        data: Option<String>,
+        hotspot_x: u16,
+        hotspot_y: u16,
+    },
+    RemoteClipboardChanged(Vec<ClipboardItem>),
+    RemoteReceivedFormatList,
+    ForceClipboardUpdate,
+    /// Arguments of the reconnection callback, see `SessionBuilder::reconnect_state_changed_callback`.
+    ReconnectStateChanged {
+        state: String,
+        attempt: u32,
+    },
+    /// Arguments of the beep callback, see `SessionBuilder::beep_callback`.
+    Beep {
+        frequency_hz: u32,
+        duration_ms: u32,
+    },
+    Terminated {
+        reason: String,
+    },
+    /// The connection or the session failed, `kind` being an `IronRdpErrorKind`.
+    Failed {
+        kind: u8,
+        code: u32,
+        message: String,
+    },
+}
+
+mod request_type {
+    pub(super) const CONNECT: u8 = 0x01;
+    pub(super) const APPLY_INPUTS: u8 = 0x02;
+    pub(super) const RELEASE_ALL_INPUTS: u8 = 0x03;
+    pub(super) const SYNCHRONIZE_LOCK_KEYS: u8 = 0x04;
+    pub(super) const START_COMPOSITION: u8 = 0x05;
+    pub(super) const END_COMPOSITION: u8 = 0x06;
+    pub(super) const RESIZE: u8 = 0x07;
+    pub(super) const SET_VISIBILITY: u8 = 0x08;
+    pub(super) const CLIPBOARD_PASTE: u8 = 0x09;
+    pub(super) const SHUTDOWN: u8 = 0x0A;
+}
+
+mod event_type {
+    pub(super) const CONNECTED: u8 = 0x01;
+    pub(super) const CURSOR_STYLE: u8 = 0x02;
+    pub(super) const REMOTE_CLIPBOARD_CHANGED: u8 = 0x03;
+    pub(super) const REMOTE_RECEIVED_FORMAT_LIST: u8 = 0x04;
+    pub(super) const FORCE_CLIPBOARD_UPDATE: u8 = 0x05;
+    pub(super) const RECONNECT_STATE_CHANGED: u8 = 0x06;
+    pub(super) const TERMINATED: u8 = 0x07;
+    pub(super) const FAILED: u8 = 0x08;
+    pub(super) const BEEP: u8 = 0x09;
+}
+
+mod operation_type {
+    pub(super) const MOUSE_BUTTON_PRESSED: u8 = 0x01;
+    pub(super) const MOUSE_BUTTON_RELEASED: u8 = 0x02;
+    pub(super) const MOUSE_MOVE: u8 = 0x03;
+    pub(super) const WHEEL_ROTATIONS: u8 = 0x04;
+    pub(super) const KEY_PRESSED: u8 = 0x05;
+    pub(super) const KEY_RELEASED: u8 = 0x06;
+    pub(super) const UNICODE_KEY_PRESSED: u8 = 0x07;
+    pub(super) const UNICODE_KEY_RELEASED: u8 = 0x08;
+}
+
+impl WorkerRequest {
+    const NAME: &'static str = "WorkerRequest";
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut dst = MessageWriter::default();
+
+        match self {
+            WorkerRequest::Connect(request) => {
+                dst.u8(request_type::CONNECT);
+                dst.string(&request.username);
+                dst.string(&request.password);
+                dst.string(&request.destination);
+                dst.optional_string(request.server_domain.as_deref());
+                dst.string(&request.proxy_address);
+                dst.string(&request.auth_token);
+                dst.optional_string(request.pcb.as_deref());
+                dst.optional_string(request.kdc_proxy_url.as_deref());
+                dst.u16(request.desktop_width);
+                dst.u16(request.desktop_height);
+                dst.bool(request.use_display_control);
+                dst.u8(match request.transport {
+                    TransportKind::WebSocket => 0,
+                    TransportKind::WebTransport => 1,
+                });
+                dst.bool(request.clipboard);
+                dst.u8(match request.clipboard_policy {
+                    ClipboardPolicy::Disabled => 0,
+                    ClipboardPolicy::HostToRemote => 1,
+                    ClipboardPolicy::RemoteToHost => 2,
+                    ClipboardPolicy::Bidirectional => 3,
+                });
+                dst.u32(request.reconnect_max_attempts);
+            }
+            WorkerRequest::ApplyInputs(batch) => {
+                dst.u8(request_type::APPLY_INPUTS);
+                batch.encode(&mut dst);
+            }
+            WorkerRequest::ReleaseAllInputs => dst.u8(request_type::RELEASE_ALL_INPUTS),
+            WorkerRequest::SynchronizeLockKeys {
+                scroll_lock,
+                num_lock,
+                caps_lock,
+                kana_lock,
+            } => {
+                dst.u8(request_type::SYNCHRONIZE_LOCK_KEYS);
+                dst.bool(*scroll_lock);
+                dst.bool(*num_lock);
+                dst.bool(*caps_lock);
+                dst.bool(*kana_lock);
+            }
+            WorkerRequest::StartComposition => dst.u8(request_type::START_COMPOSITION),
+            WorkerRequest::EndComposition => dst.u8(request_type::END_COMPOSITION),
+            WorkerRequest::Resize {
+                width,
+                height,
+                scale_factor,
+                physical_size,
+            } => {
+                dst.u8(request_type::RESIZE);
+                dst.u32(*width);
+                dst.u32(*height);
+                dst.bool(scale_factor.is_some());
+                if let Some(scale_factor) = scale_factor {
+                    dst.u32(*scale_factor);
+                }
+                dst.bool(physical_size.is_some());
+                if let Some((width, height)) = physical_size {
+                    dst.u32(*width);
+                    dst.u32(*height);
+                }
+            }
+            WorkerRequest::SetVisibility(visible) => {
+                dst.u8(request_type::SET_VISIBILITY);
+                dst.bool(*visible);
+            }
+            WorkerRequest::ClipboardPaste(items) => {
+                dst.u8(request_type::CLIPBOARD_PASTE);
+                encode_clipboard_items(&mut dst, items);
+            }
+            WorkerRequest::Shutdown => dst.u8(request_type::SHUTDOWN),
+        }
+
+        dst.0
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut src = MessageReader::new(Self::NAME, bytes);
+
+        let request = match src.u8()? {
+            request_type::CONNECT => WorkerRequest::Connect(ConnectRequest {
+                username: src.string()?,
+                password: src.string()?,
+                destination: src.string()?,
+                server_domain: src.optional_string()?,
+                proxy_address: src.string()?,
+                auth_token: src.string()?,
+                pcb: src.optional_string()?,
+                kdc_proxy_url: src.optional_string()?,
+                desktop_width: src.u16()?,
+                desktop_height: src.u16()?,
+                use_display_control: src.bool()?,
+                transport: match src.u8()? {
+                    0 => TransportKind::WebSocket,
+                    1 => TransportKind::WebTransport,
+                    other => return Err(unsupported_value_err!(Self::NAME, "transport", other.to_string())),
+                },
+                clipboard: src.bool()?,
+                clipboard_policy: match src.u8()? {
+                    0 => ClipboardPolicy::Disabled,
+                    1 => ClipboardPolicy::HostToRemote,
+                    2 => ClipboardPolicy::RemoteToHost,
+                    3 => ClipboardPolicy::Bidirectional,
+                    other => return Err(unsupported_value_err!(Self::NAME, "clipboardPolicy", other.to_string())),
+                },
+                reconnect_max_attempts: src.u32()?,
+            }),
+            request_type::APPLY_INPUTS => WorkerRequest::ApplyInputs(InputBatch::decode(&mut src)?),
+            request_type::RELEASE_ALL_INPUTS => WorkerRequest::ReleaseAllInputs,
+            request_type::SYNCHRONIZE_LOCK_KEYS => WorkerRequest::SynchronizeLockKeys {
+                scroll_lock: src.bool()?,
+                num_lock: src.bool()?,
+                caps_lock: src.bool()?,
+                kana_lock: src.bool()?,
+            },
+            request_type::START_COMPOSITION => WorkerRequest::StartComposition,
+            request_type::END_COMPOSITION => WorkerRequest::EndComposition,
+            request_type::RESIZE => {
+                let width = src.u32()?;
+                let height = src.u32()?;
+                let scale_factor = if src.bool()? { Some(src.u32()?) } else { None };
+                let physical_size = if src.bool()? {
+                    Some((src.u32()?, src.u32()?))
+                } else {
+                    None
+                };
+
+                WorkerRequest::Resize {
+                    width,
+                    height,
+                    scale_factor,
+                    physical_size,
+                }
+            }
+            request_type::SET_VISIBILITY => WorkerRequest::SetVisibility(src.bool()?),
+            request_type::CLIPBOARD_PASTE => WorkerRequest::ClipboardPaste(decode_clipboard_items(&mut src)?),
+            request_type::SHUTDOWN => WorkerRequest::Shutdown,
+            other => return Err(unsupported_value_err!(Self::NAME, "type", other.to_string())),
+        };
+
+        src.finish()?;
+
+        Ok(request)
+    }
+}
+
+impl InputBatch {
+    fn encode(&self, dst: &mut MessageWriter) {
+        dst.length(self.operations.len());
+        for operation in &self.operations {
+            match operation {
+                Operation::MouseButtonPressed(button) => {
+                    dst.u8(operation_type::MOUSE_BUTTON_PRESSED);
+                    dst.mouse_button(*button);
+                }
+                Operation::MouseButtonReleased(button) => {
+                    dst.u8(operation_type::MOUSE_BUTTON_RELEASED);
+                    dst.mouse_button(*button);
+                }
+                Operation::MouseMove(position) => {
+                    dst.u8(operation_type::MOUSE_MOVE);
+                    dst.u16(position.x);
+                    dst.u16(position.y);
+                }
+                Operation::WheelRotations(rotations) => {
+                    dst.u8(operation_type::WHEEL_ROTATIONS);
+                    dst.bool(rotations.is_vertical);
+                    dst.0.extend_from_slice(&rotations.rotation_units.to_le_bytes());
+                }
+                Operation::KeyPressed(scancode) => {
+                    dst.u8(operation_type::KEY_PRESSED);
+                    dst.u16(scancode.as_u16());
+                }
+                Operation::KeyReleased(scancode) => {
+                    dst.u8(operation_type::KEY_RELEASED);
+                    dst.u16(scancode.as_u16());
+                }
+                Operation::UnicodeKeyPressed(character) => {
+                    dst.u8(operation_type::UNICODE_KEY_PRESSED);
+                    dst.u32(u32::from(*character));
+                }
+                Operation::UnicodeKeyReleased(character) => {
+                    dst.u8(operation_type::UNICODE_KEY_RELEASED);
+                    dst.u32(u32::from(*character));
+                }
+            }
+        }
+
+        dst.length(self.touch_contacts.len());
+        for contact in &self.touch_contacts {
+            dst.u32(contact.id);
+            dst.i32(contact.x);
+            dst.i32(contact.y);
+            dst.u8(match contact.phase {
+                ContactPhase::Down => 0,
+                ContactPhase::Update => 1,
+                ContactPhase::Up => 2,
+                ContactPhase::Hover => 3,
+                ContactPhase::Cancel => 4,
+            });
+        }
+
+        dst.length(self.pen_contacts.len());
+        for contact in &self.pen_contacts {
+            dst.i32(contact.x);
+            dst.i32(contact.y);
+            dst.u32(contact.pressure);
+            dst.0.extend_from_slice(&contact.tilt_x.to_le_bytes());
+            dst.0.extend_from_slice(&contact.tilt_y.to_le_bytes());
+        }
+
+        dst.length(self.composition_commits.len());
+        for (text, replaced) in &self.composition_commits {
+            dst.string(text);
+            dst.u32(*replaced);
+        }
+    }
+
+    fn decode(src: &mut MessageReader<'_>) -> DecodeResult<Self> {
+        let operation_count = src.length()?;
+        let mut operations = Vec::with_capacity(operation_count.min(src.remaining()));
+        for _ in 0..operation_count {
+            let operation = match src.u8()? {
+                operation_type::MOUSE_BUTTON_PRESSED => Operation::MouseButtonPressed(src.mouse_button()?),
+                operation_type::MOUSE_BUTTON_RELEASED => Operation::MouseButtonReleased(src.mouse_button()?),
+                operation_type::MOUSE_MOVE => Operation::MouseMove(MousePosition {
+                    x: src.u16()?,
+                    y: src.u16()?,
+                }),
+                operation_type::WHEEL_ROTATIONS => Operation::WheelRotations(WheelRotations {
+                    is_vertical: src.bool()?,
+                    rotation_units: i16::from_le_bytes(src.array()?),
+                }),
+                operation_type::KEY_PRESSED => Operation::KeyPressed(Scancode::from_u16(src.u16()?)),
+                operation_type::KEY_RELEASED => Operation::KeyReleased(Scancode::from_u16(src.u16()?)),
+                operation_type::UNICODE_KEY_PRESSED => Operation::UnicodeKeyPressed(src.char()?),
+                operation_type::UNICODE_KEY_RELEASED => Operation::UnicodeKeyReleased(src.char()?),
+                other => return Err(unsupported_value_err!(src.ctx, "operation", other.to_string())),
+            };
+            operations.push(operation);
+        }
+
+        let touch_count = src.length()?;
+        let mut touch_contacts = Vec::with_capacity(touch_count.min(src.remaining()));
+        for _ in 0..touch_count {
+            touch_contacts.push(TouchContact {
+                id: src.u32()?,
+                x: src.i32()?,
+                y: src.i32()?,
+                phase: match src.u8()? {
+                    0 => ContactPhase::Down,
+                    1 => ContactPhase::Update,
+                    2 => ContactPhase::Up,
+                    3 => ContactPhase::Hover,
+                    4 => ContactPhase::Cancel,
+                    other => return Err(unsupported_value_err!(src.ctx, "phase", other.to_string())),
+                },
+            });
+        }
+
+        let pen_count = src.length()?;
+        let mut pen_contacts = Vec::with_capacity(pen_count.min(src.remaining()));
+        for _ in 0..pen_count {
+            pen_contacts.push(PenContact {
+                x: src.i32()?,
+                y: src.i32()?,
+                pressure: src.u32()?,
+                tilt_x: i16::from_le_bytes(src.array()?),
+                tilt_y: i16::from_le_bytes(src.array()?),
+            });
+        }
+
+        let commit_count = src.length()?;
+        let mut composition_commits = Vec::with_capacity(commit_count.min(src.remaining()));
+        for _ in 0..commit_count {
+            composition_commits.push((src.string()?, src.u32()?));
+        }
+
+        Ok(Self {
+            operations,
+            touch_contacts,
+            pen_contacts,
+            composition_commits,
+        })
+    }
+}
+
+impl WorkerEvent {
+    const NAME: &'static str = "WorkerEvent";
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut dst = MessageWriter::default();
+
+        match self {
+            WorkerEvent::Connected {
+                desktop_width,
+                desktop_height,
+            } => {
+                dst.u8(event_type::CONNECTED);
+                dst.u16(*desktop_width);
+                dst.u16(*desktop_height);
+            }
+            WorkerEvent::CursorStyle {
+                kind,
+                data,
+                hotspot_x,
+                hotspot_y,
+            } => {
+                dst.u8(event_type::CURSOR_STYLE);
+                dst.string(kind);
+                dst.optional_string(data.as_deref());
+                dst.u16(*hotspot_x);
+                dst.u16(*hotspot_y);
+            }
+            WorkerEvent::RemoteClipboardChanged(items) => {
+                dst.u8(event_type::REMOTE_CLIPBOARD_CHANGED);
+                encode_clipboard_items(&mut dst, items);
+            }
+            WorkerEvent::RemoteReceivedFormatList => dst.u8(event_type::REMOTE_RECEIVED_FORMAT_LIST),
+            WorkerEvent::ForceClipboardUpdate => dst.u8(event_type::FORCE_CLIPBOARD_UPDATE),
+            WorkerEvent::ReconnectStateChanged { state, attempt } => {
+                dst.u8(event_type::RECONNECT_STATE_CHANGED);
+                dst.string(state);
+                dst.u32(*attempt);
+            }
+            WorkerEvent::Beep {
+                frequency_hz,
+                duration_ms,
+            } => {
+                dst.u8(event_type::BEEP);
+                dst.u32(*frequency_hz);
+                dst.u32(*duration_ms);
+            }
+            WorkerEvent::Terminated { reason } => {
+                dst.u8(event_type::TERMINATED);
+                dst.string(reason);
+            }
+            WorkerEvent::Failed { kind, code, message } => {
+                dst.u8(event_type::FAILED);
+                dst.u8(*kind);
+                dst.u32(*code);
+                dst.string(message);
+            }
+        }
+
+        dst.0
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut src = MessageReader::new(Self::NAME, bytes);
+
+        let event = match src.u8()? {
+            event_type::CONNECTED => WorkerEvent::Connected {
+                desktop_width: src.u16()?,
+                desktop_height: src.u16()?,
+            },
+            event_type::CURSOR_STYLE => WorkerEvent::CursorStyle {
+                kind: src.string()?,
+                data: src.optional_string()?,
+                hotspot_x: src.u16()?,
+                hotspot_y: src.u16()?,
+            },
+            event_type::REMOTE_CLIPBOARD_CHANGED => {
+                WorkerEvent::RemoteClipboardChanged(decode_clipboard_items(&mut src)?)
+            }
+            event_type::REMOTE_RECEIVED_FORMAT_LIST => WorkerEvent::RemoteReceivedFormatList,
+            event_type::FORCE_CLIPBOARD_UPDATE => WorkerEvent::ForceClipboardUpdate,
+            event_type::RECONNECT_STATE_CHANGED => WorkerEvent::ReconnectStateChanged {
+                state: src.string()?,
+                attempt: src.u32()?,
+            },
+            event_type::BEEP => WorkerEvent::Beep {
+                frequency_hz: src.u32()?,
+                duration_ms: src.u32()?,
+            },
+            event_type::TERMINATED => WorkerEvent::Terminated { reason: src.string()? },
+            event_type::FAILED => WorkerEvent::Failed {
+                kind: src.u8()?,
+                code: src.u32()?,
+                message: src.string()?,
+            },
+            other => return Err(unsupported_value_err!(Self::NAME, "type", other.to_string())),
+        };
+
+        src.finish()?;
+
+        Ok(event)
+    }
+}
+
+fn encode_clipboard_items(dst: &mut MessageWriter, items: &[ClipboardItem]) {
+    dst.length(items.len());
+    for item in items {
+        dst.string(&item.mime_type);
+        match &item.value {
+            ClipboardItemValue::Text(text) => {
+                dst.u8(0);
+                dst.string(text);
+            }
+            ClipboardItemValue::Binary(binary) => {
+                dst.u8(1);
+                dst.bytes(binary);
+            }
+        }
+    }
+}
+
+fn decode_clipboard_items(src: &mut MessageReader<'_>) -> DecodeResult<Vec<ClipboardItem>> {
+    let count = src.length()?;
+    let mut items = Vec::with_capacity(count.min(src.remaining()));
+    for _ in 0..count {
+        let mime_type = src.string()?;
+        let value = match src.u8()? {
+            0 => ClipboardItemValue::Text(src.string()?),
+            1 => ClipboardItemValue::Binary(src.bytes()?.to_vec()),
+            other => return Err(unsupported_value_err!(src.ctx, "value", other.to_string())),
+        };
+        items.push(ClipboardItem { mime_type, value });
+    }
+
+    Ok(items)
+}
+
+#[derive(Default)]
+struct MessageWriter(Vec<u8>);
+
+impl MessageWriter {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(u8::from(value));
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn length(&mut self, length: usize) {
+        // The messages are built from browser data, which can't come close to 4 GiB.
+        self.u32(u32::try_from(length).expect("message field too large"));
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.length(bytes.len());
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, string: &str) {
+        self.bytes(string.as_bytes());
+    }
+
+    fn optional_string(&mut self, string: Option<&str>) {
+        self.bool(string.is_some());
+        if let Some(string) = string {
+            self.string(string);
+        }
+    }
+
+    fn mouse_button(&mut self, button: MouseButton) {
+        // The mouse button indices are the DOM `MouseEvent.button` values.
+        self.u8(u8::try_from(button.as_idx()).expect("mouse button index fits in u8"));
+    }
+}
+
+struct MessageReader<'a> {
+    ctx: &'static str,
+    cursor: ReadCursor<'a>,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(ctx: &'static str, bytes: &'a [u8]) -> Self {
+        Self {
+            ctx,
+            cursor: ReadCursor::new(bytes),
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.cursor.len()
+    }
+
+    fn array<const N: usize>(&mut self) -> DecodeResult<[u8; N]> {
+        let src = &mut self.cursor;
+        ensure_size!(ctx: self.ctx, in: src, size: N);
+        Ok(src.read_array())
+    }
+
+    fn u8(&mut self) -> DecodeResult<u8> {
+        self.array::<1>().map(|[value]| value)
+    }
+
+    fn bool(&mut self) -> DecodeResult<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_field_err!(self.ctx, "bool", "neither 0 nor 1")),
+        }
+    }
+
+    fn u16(&mut self) -> DecodeResult<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> DecodeResult<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> DecodeResult<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn char(&mut self) -> DecodeResult<char> {
+        char::from_u32(self.u32()?).ok_or_else(|| invalid_field_err!(self.ctx, "char", "invalid Unicode scalar value"))
+    }
+
+    fn length(&mut self) -> DecodeResult<usize> {
+        usize::try_from(self.u32()?).map_err(|_| invalid_field_err!(self.ctx, "length", "too large"))
+    }
+
+    fn bytes(&mut self) -> DecodeResult<&'a [u8]> {
+        let length = self.length()?;
+        let src = &mut self.cursor;
+        ensure_size!(ctx: self.ctx, in: src, size: length);
+        Ok(src.read_slice(length))
+    }
+
+    fn string(&mut self) -> DecodeResult<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid_field_err!(self.ctx, "string", "invalid UTF-8"))
+    }
+
+    fn optional_string(&mut self) -> DecodeResult<Option<String>> {
+        if self.bool()? {
+            self.string().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn mouse_button(&mut self) -> DecodeResult<MouseButton> {
+        let button = self.u8()?;
+        MouseButton::from_web_button(button)
+            .ok_or_else(|| unsupported_value_err!(self.ctx, "button", button.to_string()))
+    }
+
+    /// Checks that the whole message was read.
+    fn finish(self) -> Result<(), DecodeError> {
+        if self.cursor.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_field_err!(self.ctx, "length", "trailing bytes"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect_request() -> ConnectRequest {
+        ConnectRequest {
+            username: "Administrator".to_owned(),
+            password: "DevoLabs123!".to_owned(),
+            destination: "10.10.0.3:3389".to_owned(),
+            server_domain: Some("ad.it-help.ninja".to_owned()),
+            proxy_address: "ws://localhost:7171/jet/rdp".to_owned(),
+            auth_token: "token".to_owned(),
+            pcb: None,
+            kdc_proxy_url: None,
+            desktop_width: 1280,
+            desktop_height: 720,
+            use_display_control: true,
+            transport: TransportKind::WebTransport,
+            clipboard: false,
+            clipboard_policy: ClipboardPolicy::RemoteToHost,
+            reconnect_max_attempts: 5,
+        }
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let requests = [
+            WorkerRequest::Connect(connect_request()),
+            WorkerRequest::ApplyInputs(InputBatch {
+                operations: vec![
+                    Operation::MouseButtonPressed(MouseButton::Right),
+                    Operation::MouseButtonReleased(MouseButton::X2),
+                    Operation::MouseMove(MousePosition { x: 640, y: 360 }),
+                    Operation::WheelRotations(WheelRotations {
+                        is_vertical: true,
+                        rotation_units: -120,
+                    }),
+                    Operation::KeyPressed(Scancode::from_u16(0xE05B)),
+                    Operation::KeyReleased(Scancode::from_u16(0x001D)),
+                    Operation::UnicodeKeyPressed('é'),
+                    Operation::UnicodeKeyReleased('🦀'),
+                ],
+                touch_contacts: vec![TouchContact {
+                    id: 7,
+                    x: -3,
+                    y: 12,
+                    phase: ContactPhase::Hover,
+                }],
+                pen_contacts: vec![PenContact {
+                    x: 100,
+                    y: 200,
+                    pressure: 512,
+                    tilt_x: -45,
+                    tilt_y: 90,
+                }],
+                composition_commits: vec![("日本".to_owned(), 1)],
+            }),
+            WorkerRequest::ReleaseAllInputs,
+            WorkerRequest::SynchronizeLockKeys {
+                scroll_lock: false,
+                num_lock: true,
+                caps_lock: false,
+                kana_lock: true,
+            },
+            WorkerRequest::StartComposition,
+            WorkerRequest::EndComposition,
+            WorkerRequest::Resize {
+                width: 1920,
+                height: 1080,
+                scale_factor: Some(150),
+                physical_size: None,
+            },
+            WorkerRequest::Resize {
+                width: 800,
+                height: 600,
+                scale_factor: None,
+                physical_size: Some((211, 158)),
+            },
+            WorkerRequest::SetVisibility(false),
+            WorkerRequest::ClipboardPaste(vec![
+                ClipboardItem {
+                    mime_type: "text/plain".to_owned(),
+                    value: ClipboardItemValue::Text("hello".to_owned()),
+                },
+                ClipboardItem {
+                    mime_type: "image/png".to_owned(),
+                    value: ClipboardItemValue::Binary(vec![0x89, 0x50, 0x4E, 0x47]),
+                },
+            ]),
+            WorkerRequest::Shutdown,
+        ];
+
+        for request in requests {
+            let encoded = request.encode();
+            assert_eq!(WorkerRequest::decode(&encoded).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let events = [
+            WorkerEvent::Connected {
+                desktop_width: 1024,
+                desktop_height: 768,
+            },
+            WorkerEvent::CursorStyle {
+                kind: "url".to_owned(),
+                data: Some("data:image/png;base64,AAAA".to_owned()),
+                hotspot_x: 3,
+                hotspot_y: 4,
+            },
+            WorkerEvent::CursorStyle {
+                kind: "default".to_owned(),
+                data: None,
+                hotspot_x: 0,
+                hotspot_y: 0,
+            },
+            WorkerEvent::RemoteClipboardChanged(vec![ClipboardItem {
+                mime_type: "text/html".to_owned(),
+                value: ClipboardItemValue::Text("<b>bold</b>".to_owned()),
+            }]),
+            WorkerEvent::RemoteReceivedFormatList,
+            WorkerEvent::ForceClipboardUpdate,
+            WorkerEvent::ReconnectStateChanged {
+                state: "reconnecting".to_owned(),
+                attempt: 2,
+            },
+            WorkerEvent::Beep {
+                frequency_hz: 750,
+                duration_ms: 200,
+            },
+            WorkerEvent::Terminated {
+                reason: "user requested disconnect".to_owned(),
+            },
+            WorkerEvent::Failed {
+                kind: 2,
+                code: 0xC000_006D,
+                message: "logon failure".to_owned(),
+            },
+        ];
+
+        for event in events {
+            let encoded = event.encode();
+            assert_eq!(WorkerEvent::decode(&encoded).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn set_visibility_layout() {
+        assert_eq!(WorkerRequest::SetVisibility(true).encode(), [0x08, 0x01]);
+        assert_eq!(
+            WorkerEvent::Terminated {
+                reason: "ok".to_owned()
+            }
+            .encode(),
+            [0x07, 0x02, 0x00, 0x00, 0x00, b'o', b'k']
+        );
+    }
+
+    #[test]
+    fn truncated_messages_are_rejected() {
+        let encoded = WorkerRequest::Connect(connect_request()).encode();
+
+        for length in 0..encoded.len() {
+            assert!(WorkerRequest::decode(&encoded[..length]).is_err(), "length {length}");
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut encoded = WorkerRequest::Shutdown.encode();
+        encoded.push(0);
+
+        assert!(WorkerRequest::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        // Unknown request type.
+        assert!(WorkerRequest::decode(&[0xFF]).is_err());
+        // Boolean which is neither 0 nor 1.
+        assert!(WorkerRequest::decode(&[0x08, 0x02]).is_err());
+
+        let inputs = |operation| {
+            WorkerRequest::ApplyInputs(InputBatch {
+                operations: vec![operation],
+                ..InputBatch::default()
+            })
+            .encode()
+        };
+
+        // Mouse button 5 does not exist.
+        let mut encoded = inputs(Operation::MouseButtonPressed(MouseButton::X2));
+        assert!(WorkerRequest::decode(&encoded).is_ok());
+        encoded[6] = 5;
+        assert!(WorkerRequest::decode(&encoded).is_err());
+
+        // Surrogates are not characters.
+        let mut encoded = inputs(Operation::UnicodeKeyPressed('a'));
+        assert!(WorkerRequest::decode(&encoded).is_ok());
+        encoded[6..10].copy_from_slice(&0xD800u32.to_le_bytes());
+        assert!(WorkerRequest::decode(&encoded).is_err());
+
+        // A huge length must not be trusted for allocations.
+        assert!(WorkerEvent::decode(&[0x03, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+    }
+}
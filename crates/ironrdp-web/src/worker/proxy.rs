@@ -0,0 +1,287 @@
+use core::cell::RefCell;
+
+use anyhow::Context as _;
+use futures_channel::mpsc;
+use futures_util::StreamExt as _;
+use wasm_bindgen::prelude::*;
+use web_sys::{ErrorEvent, MessageEvent, OffscreenCanvas, Worker};
+
+use super::protocol::{ConnectRequest, WorkerEvent, WorkerRequest};
+use super::{clipboard_items, clipboard_transaction, message_data, split_message_data};
+use crate::clipboard::ClipboardTransaction;
+use crate::error::{IronRdpError, IronRdpErrorKind};
+use crate::input::InputTransaction;
+use crate::session::SessionTerminationInfo;
+use crate::DesktopSize;
+
+/// Callbacks of the `SessionBuilder`, invoked on the main thread on behalf of the worker.
+pub(crate) struct WorkerCallbacks {
+    pub(crate) set_cursor_style: js_sys::Function,
+    pub(crate) set_cursor_style_context: JsValue,
+    pub(crate) remote_clipboard_changed: Option<js_sys::Function>,
+    pub(crate) remote_received_format_list: Option<js_sys::Function>,
+    pub(crate) force_clipboard_update: Option<js_sys::Function>,
+    pub(crate) reconnect_state_changed: Option<js_sys::Function>,
+    pub(crate) beep: Option<js_sys::Function>,
+}
+
+impl WorkerCallbacks {
+    /// Invokes the callback matching `event`, or returns the event if it is not handled by a callback.
+    fn dispatch(&self, event: WorkerEvent) -> Result<Option<WorkerEvent>, JsValue> {
+        match event {
+            WorkerEvent::CursorStyle {
+                kind,
+                data,
+                hotspot_x,
+                hotspot_y,
+            } => {
+                let args = js_sys::Array::of4(
+                    &JsValue::from_str(&kind),
+                    &JsValue::from(data),
+                    &JsValue::from_f64(hotspot_x.into()),
+                    &JsValue::from_f64(hotspot_y.into()),
+                );
+                self.set_cursor_style.apply(&self.set_cursor_style_context, &args)?;
+            }
+            WorkerEvent::RemoteClipboardChanged(items) => {
+                if let Some(callback) = &self.remote_clipboard_changed {
+                    callback.call1(&JsValue::NULL, &JsValue::from(clipboard_transaction(items)))?;
+                }
+            }
+            WorkerEvent::RemoteReceivedFormatList => {
+                if let Some(callback) = &self.remote_received_format_list {
+                    callback.call0(&JsValue::NULL)?;
+                }
+            }
+            WorkerEvent::ForceClipboardUpdate => {
+                if let Some(callback) = &self.force_clipboard_update {
+                    callback.call0(&JsValue::NULL)?;
+                }
+            }
+            WorkerEvent::ReconnectStateChanged { state, attempt } => {
+                if let Some(callback) = &self.reconnect_state_changed {
+                    callback.call2(&JsValue::NULL, &JsValue::from_str(&state), &JsValue::from(attempt))?;
+                }
+            }
+            WorkerEvent::Beep {
+                frequency_hz,
+                duration_ms,
+            } => {
+                if let Some(callback) = &self.beep {
+                    callback.call2(
+                        &JsValue::NULL,
+                        &JsValue::from(frequency_hz),
+                        &JsValue::from(duration_ms),
+                    )?;
+                }
+            }
+            event @ (WorkerEvent::Connected { .. } | WorkerEvent::Terminated { .. } | WorkerEvent::Failed { .. }) => {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Session run in a Web Worker, rendering on an `OffscreenCanvas`
+///
+/// Created by `SessionBuilder::connect_in_worker`. The calls are forwarded to the `SessionWorkerHost` of the worker,
+/// which runs the session. The worker is terminated when this object is freed.
+#[wasm_bindgen]
+pub struct WorkerSession {
+    worker: Worker,
+    desktop_size: DesktopSize,
+    // Consumed when `run` is called
+    outcomes: RefCell<Option<mpsc::UnboundedReceiver<WorkerEvent>>>,
+    // Kept alive as long as the worker may call them
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(ErrorEvent)>,
+}
+
+impl WorkerSession {
+    pub(crate) async fn connect(
+        worker: Worker,
+        request: ConnectRequest,
+        canvas: OffscreenCanvas,
+        callbacks: WorkerCallbacks,
+    ) -> Result<Self, IronRdpError> {
+        let (outcomes_tx, mut outcomes) = mpsc::unbounded();
+
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new({
+            let outcomes_tx = outcomes_tx.clone();
+
+            move |message: MessageEvent| {
+                let event = match split_message_data(&message.data())
+                    .and_then(|(encoded, _)| WorkerEvent::decode(&encoded).context("decode worker event"))
+                {
+                    Ok(event) => event,
+                    Err(error) => {
+                        error!(%error, "Invalid message from the session worker");
+                        return;
+                    }
+                };
+
+                match callbacks.dispatch(event) {
+                    Ok(Some(outcome)) => {
+                        let _ = outcomes_tx.unbounded_send(outcome);
+                    }
+                    Ok(None) => {}
+                    Err(error) => error!(?error, "Session callback failed"),
+                }
+            }
+        });
+
+        // Errors thrown by the worker script itself, e.g.: when the module failed to load.
+        let on_error = Closure::<dyn FnMut(ErrorEvent)>::new(move |event: ErrorEvent| {
+            let _ = outcomes_tx.unbounded_send(WorkerEvent::Failed {
+                kind: IronRdpErrorKind::General.to_u8(),
+                code: 0,
+                message: format!("session worker error: {}", event.message()),
+            });
+        });
+
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let mut session = Self {
+            worker,
+            desktop_size: DesktopSize::new(0, 0),
+            outcomes: RefCell::new(None),
+            _on_message: on_message,
+            _on_error: on_error,
+        };
+
+        session.post_with_transfer(&WorkerRequest::Connect(request), Some(&canvas))?;
+
+        // The worker is terminated if the connection fails, as `session` is dropped.
+        session.desktop_size = match outcomes.next().await {
+            Some(WorkerEvent::Connected {
+                desktop_width,
+                desktop_height,
+            }) => DesktopSize::new(desktop_width, desktop_height),
+            Some(WorkerEvent::Failed { kind, code, message }) => {
+                return Err(IronRdpError::forwarded(IronRdpErrorKind::from_u8(kind), code, message))
+            }
+            _ => return Err(anyhow::Error::msg("session worker terminated before connecting").into()),
+        };
+
+        *session.outcomes.borrow_mut() = Some(outcomes);
+
+        Ok(session)
+    }
+
+    fn post(&self, request: &WorkerRequest) -> Result<(), IronRdpError> {
+        self.post_with_transfer(request, None)
+    }
+
+    fn post_with_transfer(&self, request: &WorkerRequest, transferable: Option<&JsValue>) -> Result<(), IronRdpError> {
+        let (data, transfer) = message_data(request.encode(), transferable);
+
+        self.worker
+            .post_message_with_transfer(&data, &transfer)
+            .map_err(|e| anyhow::Error::msg(format!("post message to the session worker: {e:?}")))?;
+
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl WorkerSession {
+    pub async fn run(&self) -> Result<SessionTerminationInfo, IronRdpError> {
+        let mut outcomes = self
+            .outcomes
+            .borrow_mut()
+            .take()
+            .context("RDP session can be started only once")?;
+
+        loop {
+            match outcomes.next().await {
+                Some(WorkerEvent::Terminated { reason }) => return Ok(SessionTerminationInfo::new(reason)),
+                Some(WorkerEvent::Failed { kind, code, message }) => {
+                    return Err(IronRdpError::forwarded(IronRdpErrorKind::from_u8(kind), code, message))
+                }
+                Some(event) => warn!(?event, "Unexpected session worker event"),
+                None => return Err(anyhow::Error::msg("session worker channel closed").into()),
+            }
+        }
+    }
+
+    pub fn desktop_size(&self) -> DesktopSize {
+        self.desktop_size.clone()
+    }
+
+    pub fn apply_inputs(&self, transaction: InputTransaction) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::ApplyInputs(transaction.into()))
+    }
+
+    pub fn start_composition(&self) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::StartComposition)
+    }
+
+    pub fn end_composition(&self) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::EndComposition)
+    }
+
+    pub fn release_all_inputs(&self) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::ReleaseAllInputs)
+    }
+
+    pub fn synchronize_lock_keys(
+        &self,
+        scroll_lock: bool,
+        num_lock: bool,
+        caps_lock: bool,
+        kana_lock: bool,
+    ) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::SynchronizeLockKeys {
+            scroll_lock,
+            num_lock,
+            caps_lock,
+            kana_lock,
+        })
+    }
+
+    pub fn shutdown(&self) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::Shutdown)
+    }
+
+    pub async fn on_clipboard_paste(&self, content: ClipboardTransaction) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::ClipboardPaste(clipboard_items(&content)))
+    }
+
+    pub fn resize(
+        &self,
+        width: u32,
+        height: u32,
+        scale_factor: Option<u32>,
+        physical_width: Option<u32>,
+        physical_height: Option<u32>,
+    ) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::Resize {
+            width,
+            height,
+            scale_factor,
+            physical_size: physical_width.zip(physical_height),
+        })
+    }
+
+    /// Notifies the session that the render canvas was shown or hidden.
+    pub fn set_visibility(&self, visible: bool) -> Result<(), IronRdpError> {
+        self.post(&WorkerRequest::SetVisibility(visible))
+    }
+
+    #[allow(clippy::unused_self)]
+    pub fn supports_unicode_keyboard_shortcuts(&self) -> bool {
+        // Same as `Session::supports_unicode_keyboard_shortcuts`.
+        false
+    }
+}
+
+impl Drop for WorkerSession {
+    fn drop(&mut self) {
+        self.worker.set_onmessage(None);
+        self.worker.set_onerror(None);
+        self.worker.terminate();
+    }
+}
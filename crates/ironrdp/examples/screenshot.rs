@@ -168,11 +168,13 @@ fn build_config(username: String, password: String, domain: Option<String>) -> c
     connector::Config {
         credentials: Credentials::UsernamePassword { username, password },
         domain,
+        credential_delegation: connector::CredentialDelegation::Full,
         enable_tls: false, // This example does not expose any frontend.
         enable_credssp: true,
         keyboard_type: KeyboardType::IbmEnhanced,
         keyboard_subtype: 0,
         keyboard_layout: 0,
+        active_input_locale: None,
         keyboard_functional_keys_count: 12,
         ime_file_name: String::new(),
         dig_product_id: String::new(),
@@ -207,12 +209,21 @@ fn build_config(username: String, password: String, domain: Option<String>) -> c
         // Disable custom pointers (there is no user interaction anyway)
         no_server_pointer: true,
         request_data: None,
+        correlation_id: None,
         autologon: false,
         pointer_software_rendering: true,
         performance_flags: PerformanceFlags::default(),
         desktop_scale_factor: 0,
         hardware_id: None,
         license_cache: None,
+        remote_app: None,
+        auto_reconnect_cookie: None,
+        frame_markers: true,
+        timeouts: connector::ConnectTimeouts::default(),
+        bitmap_cache: None,
+        extra_capability_sets: Vec::new(),
+        decline_multitransport: true,
+        redirect_session: None,
     }
 }
 
@@ -43,10 +43,18 @@ pub use ironrdp_input as input;
 #[doc(inline)]
 pub use ironrdp_pdu as pdu;
 
+#[cfg(feature = "rail")]
+#[doc(inline)]
+pub use ironrdp_rail as rail;
+
 #[cfg(feature = "rdpdr")]
 #[doc(inline)]
 pub use ironrdp_rdpdr as rdpdr;
 
+#[cfg(feature = "rdpei")]
+#[doc(inline)]
+pub use ironrdp_rdpei as rdpei;
+
 #[cfg(feature = "rdpsnd")]
 #[doc(inline)]
 pub use ironrdp_rdpsnd as rdpsnd;
@@ -410,38 +410,12 @@ public partial class MainWindow : Window
                 {
                     Render();
                 }
-                else if (output.GetEnumType() == ActiveStageOutputType.DeactivateAll)
+                else if (output.GetEnumType() == ActiveStageOutputType.DeactivationReactivation)
                 {
-                    var activationSequence = output.GetDeactivateAll();
-                    var writeBuf = WriteBuf.New();
-                    while (true)
-                    {
-                        await Connection.SingleSequenceStep(activationSequence, writeBuf,_framed!);
-
-                        if (activationSequence.GetState().GetType() != ConnectionActivationStateType.Finalized)
-                            continue;
-
-                        var finalized = activationSequence.GetState().GetFinalized();
-                        var desktopSize = finalized.GetDesktopSize();
-                        var ioChannelId = finalized.GetIoChannelId();
-                        var userChannelId = finalized.GetUserChannelId();
-                        var noServerPointer = finalized.GetNoServerPointer();
-                        var pointerSoftwareRendering = finalized.GetPointerSoftwareRendering();
-
-                        _decodedImage = DecodedImage.New(PixelFormat.RgbA32, desktopSize.GetWidth(),
-                            desktopSize.GetHeight());
-
-                        _activeStage!.SetFastpathProcessor(
-                            ioChannelId,
-                            userChannelId,
-                            noServerPointer,
-                            pointerSoftwareRendering
-                        );
-
-                        _activeStage.SetNoServerPointer(noServerPointer);
-
-                        break;
-                    }
+                    // The active stage already went through the Deactivation-Reactivation Sequence.
+                    var desktopSize = output.GetDeactivationReactivation();
+                    _decodedImage = DecodedImage.New(PixelFormat.RgbA32, desktopSize.GetWidth(),
+                        desktopSize.GetHeight());
                 }
                 else
                 {
@@ -265,7 +265,7 @@ public partial class ActiveStage: IDisposable
         }
     }
 
-    public void SetFastpathProcessor(ushort ioChannelId, ushort userChannelId, bool noServerPointer, bool pointerSoftwareRendering)
+    public void SetFastpathProcessor(ushort ioChannelId, ushort userChannelId, bool noServerPointer, bool pointerSoftwareRendering, ushort pointerCacheSize, bool frameAcknowledge)
     {
         unsafe
         {
@@ -273,7 +273,7 @@ public partial class ActiveStage: IDisposable
             {
                 throw new ObjectDisposedException("ActiveStage");
             }
-            Raw.ActiveStage.SetFastpathProcessor(_inner, ioChannelId, userChannelId, noServerPointer, pointerSoftwareRendering);
+            Raw.ActiveStage.SetFastpathProcessor(_inner, ioChannelId, userChannelId, noServerPointer, pointerSoftwareRendering, pointerCacheSize, frameAcknowledge);
         }
     }
 
@@ -15,11 +15,11 @@ public partial class ActiveStageOutput: IDisposable
 {
     private unsafe Raw.ActiveStageOutput* _inner;
 
-    public ConnectionActivationSequence DeactivateAll
+    public DesktopSize DeactivationReactivation
     {
         get
         {
-            return GetDeactivateAll();
+            return GetDeactivationReactivation();
         }
     }
 
@@ -213,9 +213,9 @@ public partial class ActiveStageOutput: IDisposable
 
     /// <exception cref="IronRdpException"></exception>
     /// <returns>
-    /// A <c>ConnectionActivationSequence</c> allocated on Rust side.
+    /// A <c>DesktopSize</c> allocated on Rust side.
     /// </returns>
-    public ConnectionActivationSequence GetDeactivateAll()
+    public DesktopSize GetDeactivationReactivation()
     {
         unsafe
         {
@@ -223,13 +223,13 @@ public partial class ActiveStageOutput: IDisposable
             {
                 throw new ObjectDisposedException("ActiveStageOutput");
             }
-            Raw.SessionFfiResultBoxConnectionActivationSequenceBoxIronRdpError result = Raw.ActiveStageOutput.GetDeactivateAll(_inner);
+            Raw.SessionFfiResultBoxDesktopSizeBoxIronRdpError result = Raw.ActiveStageOutput.GetDeactivationReactivation(_inner);
             if (!result.isOk)
             {
                 throw new IronRdpException(new IronRdpError(result.Err));
             }
-            Raw.ConnectionActivationSequence* retVal = result.Ok;
-            return new ConnectionActivationSequence(retVal);
+            Raw.DesktopSize* retVal = result.Ok;
+            return new DesktopSize(retVal);
         }
     }
 
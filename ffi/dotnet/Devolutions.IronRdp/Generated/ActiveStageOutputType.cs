@@ -20,5 +20,12 @@ public enum ActiveStageOutputType
     PointerPosition = 4,
     PointerBitmap = 5,
     Terminate = 6,
-    DeactivateAll = 7,
+    DeactivationReactivation = 7,
+    SessionInfo = 8,
+    MonitorLayoutChanged = 9,
+    FrameBoundary = 10,
+    ConnectionHealth = 11,
+    PlaySound = 12,
+    ServerRedirection = 13,
+    MultitransportRequest = 14,
 }
@@ -26,6 +26,14 @@ public partial class IronRdpError: IDisposable
         }
     }
 
+    public uint Code
+    {
+        get
+        {
+            return GetCode();
+        }
+    }
+
     /// <summary>
     /// Creates a managed <c>IronRdpError</c> from a raw handle.
     /// </summary>
@@ -93,6 +101,22 @@ public partial class IronRdpError: IDisposable
         }
     }
 
+    /// <summary>
+    /// Returns the stable numeric code of the error, or 0 if the error has none.
+    /// </summary>
+    public uint GetCode()
+    {
+        unsafe
+        {
+            if (_inner == null)
+            {
+                throw new ObjectDisposedException("IronRdpError");
+            }
+            uint retVal = Raw.IronRdpError.GetCode(_inner);
+            return retVal;
+        }
+    }
+
     /// <summary>
     /// Returns the underlying raw handle.
     /// </summary>
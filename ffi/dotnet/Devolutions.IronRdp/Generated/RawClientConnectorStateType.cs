@@ -28,4 +28,5 @@ public enum ClientConnectorStateType
     CapabilitiesExchange = 12,
     ConnectionFinalization = 13,
     Connected = 14,
+    Redirected = 15,
 }
@@ -31,6 +31,12 @@ public partial struct IronRdpError
     [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "IronRdpError_get_kind", ExactSpelling = true)]
     public static unsafe extern IronRdpErrorKind GetKind(IronRdpError* self);
 
+    /// <summary>
+    /// Returns the stable numeric code of the error, or 0 if the error has none.
+    /// </summary>
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "IronRdpError_get_code", ExactSpelling = true)]
+    public static unsafe extern uint GetCode(IronRdpError* self);
+
     [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "IronRdpError_destroy", ExactSpelling = true)]
     public static unsafe extern void Destroy(IronRdpError* self);
 }
@@ -43,6 +43,7 @@ pub mod ffi {
         CapabilitiesExchange,
         ConnectionFinalization,
         Finalized,
+        Redirected,
     }
 
     impl ConnectionActivationState {
@@ -60,6 +61,9 @@ pub mod ffi {
                 ironrdp::connector::connection_activation::ConnectionActivationState::Finalized { .. } => {
                     ConnectionActivationStateType::Finalized
                 }
+                ironrdp::connector::connection_activation::ConnectionActivationState::Redirected { .. } => {
+                    ConnectionActivationStateType::Redirected
+                }
             }
         }
 
@@ -89,6 +93,7 @@ pub mod ffi {
                     user_channel_id,
                     desktop_size,
                     connection_finalization,
+                    ..
                 } => Ok(Box::new(ConnectionActivationStateConnectionFinalization {
                     io_channel_id: *io_channel_id,
                     user_channel_id: *user_channel_id,
@@ -109,6 +114,7 @@ pub mod ffi {
                     desktop_size,
                     no_server_pointer,
                     pointer_software_rendering,
+                    ..
                 } => Ok(Box::new(ConnectionActivationStateFinalized {
                     io_channel_id: *io_channel_id,
                     user_channel_id: *user_channel_id,
@@ -28,6 +28,7 @@ pub mod ffi {
         pub keyboard_type: Option<ironrdp::pdu::gcc::KeyboardType>,
         pub keyboard_subtype: Option<u32>,
         pub keyboard_layout: Option<u32>,
+        pub active_input_locale: Option<u32>,
         pub keyboard_functional_keys_count: Option<u32>,
         pub ime_file_name: Option<String>,
         pub dig_product_id: Option<String>,
@@ -95,6 +96,10 @@ pub mod ffi {
             self.keyboard_layout = Some(keyboard_layout);
         }
 
+        pub fn set_active_input_locale(&mut self, active_input_locale: u32) {
+            self.active_input_locale = Some(active_input_locale);
+        }
+
         pub fn set_keyboard_type(&mut self, keyboard_type: KeyboardType) {
             self.keyboard_type = Some(keyboard_type.into());
         }
@@ -155,9 +160,11 @@ pub mod ffi {
             let inner_config = ironrdp::connector::Config {
                 credentials: self.credentials.clone().ok_or("credentials not set")?,
                 domain: self.domain.clone(),
+                credential_delegation: ironrdp::connector::CredentialDelegation::Full,
                 enable_tls: self.enable_tls.unwrap_or(false),
                 enable_credssp: self.enable_credssp.unwrap_or(true),
                 keyboard_layout: self.keyboard_layout.unwrap_or(0),
+                active_input_locale: self.active_input_locale,
                 keyboard_type: self
                     .keyboard_type
                     .unwrap_or(ironrdp::pdu::gcc::KeyboardType::IbmEnhanced),
@@ -193,11 +200,20 @@ pub mod ffi {
                 no_server_pointer: self.no_server_pointer.unwrap_or(false),
                 autologon: self.autologon.unwrap_or(false),
                 request_data: None,
+                correlation_id: None,
                 pointer_software_rendering: self.pointer_software_rendering.unwrap_or(false),
                 performance_flags: self.performance_flags.ok_or("performance flag is missing")?,
                 desktop_scale_factor: 0,
                 hardware_id: None,
                 license_cache: None,
+                remote_app: None,
+                auto_reconnect_cookie: None,
+                frame_markers: true,
+                bitmap_cache: None,
+                timeouts: ironrdp::connector::ConnectTimeouts::default(),
+                extra_capability_sets: Vec::new(),
+                decline_multitransport: true,
+                redirect_session: None,
             };
             tracing::debug!(config=?inner_config, "Built config");
             Ok(Box::new(Config(inner_config)))
@@ -23,6 +23,7 @@ pub mod ffi {
         CapabilitiesExchange,
         ConnectionFinalization,
         Connected,
+        Redirected,
     }
 
     impl ClientConnectorState {
@@ -71,6 +72,7 @@ pub mod ffi {
                     ClientConnectorStateType::ConnectionFinalization
                 }
                 ironrdp::connector::ClientConnectorState::Connected { .. } => ClientConnectorStateType::Connected,
+                ironrdp::connector::ClientConnectorState::Redirected { .. } => ClientConnectorStateType::Redirected,
                 &_ => return Err("Unknown ClientConnectorStateType".into()),
             };
 
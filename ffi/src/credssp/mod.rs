@@ -64,6 +64,7 @@ pub mod ffi {
                     let (credssp_sequence, ts_request) = ironrdp::connector::credssp::CredsspSequence::init(
                         connector.config.credentials.clone(),
                         connector.config.domain.as_deref(),
+                        connector.config.credential_delegation,
                         selected_protocol,
                         server_name.into(),
                         server_public_key.to_owned(),
@@ -15,6 +15,7 @@ impl From<ConnectorError> for IronRdpErrorKind {
             ironrdp::connector::ConnectorErrorKind::Encode(_) => IronRdpErrorKind::EncodeError,
             ironrdp::connector::ConnectorErrorKind::Decode(_) => IronRdpErrorKind::DecodeError,
             ironrdp::connector::ConnectorErrorKind::Credssp(_) => IronRdpErrorKind::CredsspError,
+            ironrdp::connector::ConnectorErrorKind::CredsspFailure(_) => IronRdpErrorKind::CredsspError,
             ironrdp::connector::ConnectorErrorKind::AccessDenied => IronRdpErrorKind::AccessDenied,
             _ => IronRdpErrorKind::Generic,
         }
@@ -93,20 +94,73 @@ impl From<WrongOSError> for IronRdpErrorKind {
     }
 }
 
+/// Stable numeric code of the errors, as documented by `ironrdp_error::ErrorKindExt`
+///
+/// Errors not originating from IronRDP have no stable code, and are reported with the code `0`.
+trait ErrorCode {
+    fn error_code(&self) -> u32 {
+        0
+    }
+}
+
+impl ErrorCode for ConnectorError {
+    fn error_code(&self) -> u32 {
+        self.code()
+    }
+}
+
+impl ErrorCode for SessionError {
+    fn error_code(&self) -> u32 {
+        self.code()
+    }
+}
+
+impl ErrorCode for ironrdp::pdu::PduError {
+    fn error_code(&self) -> u32 {
+        self.code()
+    }
+}
+
+impl ErrorCode for ironrdp::core::EncodeError {
+    fn error_code(&self) -> u32 {
+        self.code()
+    }
+}
+
+impl ErrorCode for ironrdp::core::DecodeError {
+    fn error_code(&self) -> u32 {
+        self.code()
+    }
+}
+
+impl ErrorCode for IronRdpErrorKind {}
+impl ErrorCode for &str {}
+impl ErrorCode for sspi::Error {}
+impl ErrorCode for std::io::Error {}
+impl ErrorCode for core::fmt::Error {}
+impl ErrorCode for &dyn ClipboardError {}
+#[cfg(target_os = "windows")]
+impl ErrorCode for WinCliprdrError {}
+impl ErrorCode for WrongOSError {}
+impl ErrorCode for ValueConsumedError {}
+impl ErrorCode for IncorrectEnumTypeError {}
+
 impl<T> From<T> for Box<ffi::IronRdpError>
 where
-    T: Into<IronRdpErrorKind> + ToString,
+    T: Into<IronRdpErrorKind> + ToString + ErrorCode,
 {
     fn from(value: T) -> Self {
         let repr = value.to_string();
+        let code = value.error_code();
         let kind = value.into();
-        Box::new(ffi::IronRdpError(IronRdpErrorInner { repr, kind }))
+        Box::new(ffi::IronRdpError(IronRdpErrorInner { repr, kind, code }))
     }
 }
 
 struct IronRdpErrorInner {
     repr: String,
     kind: IronRdpErrorKind,
+    code: u32,
 }
 
 #[diplomat::bridge]
@@ -156,6 +210,11 @@ pub mod ffi {
         pub fn get_kind(&self) -> IronRdpErrorKind {
             self.0.kind
         }
+
+        /// Returns the stable numeric code of the error, or 0 if the error has none.
+        pub fn get_code(&self) -> u32 {
+            self.0.code
+        }
     }
 }
 
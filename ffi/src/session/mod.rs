@@ -5,7 +5,7 @@ pub mod ffi {
 
     use super::image::ffi::DecodedImage;
     use crate::clipboard::message::ffi::{ClipboardFormatId, ClipboardFormatIterator, FormatDataResponse};
-    use crate::connector::activation::ffi::ConnectionActivationSequence;
+    use crate::connector::config::ffi::DesktopSize;
     use crate::connector::result::ffi::ConnectionResult;
     use crate::error::ffi::IronRdpError;
     use crate::error::{IncorrectEnumTypeError, ValueConsumedError};
@@ -74,7 +74,7 @@ pub mod ffi {
             let formats = formats.0.clone();
             let clipboard = self
                 .0
-                .get_svc_processor::<ironrdp::cliprdr::CliprdrClient>()
+                .get_svc_processor_mut::<ironrdp::cliprdr::CliprdrClient>()
                 .ok_or("clipboard svc processor not found in active stage")?;
 
             let result = clipboard.initiate_copy(&formats)?;
@@ -151,6 +151,8 @@ pub mod ffi {
             user_channel_id: u16,
             no_server_pointer: bool,
             pointer_software_rendering: bool,
+            pointer_cache_size: u16,
+            frame_acknowledge: bool,
         ) {
             self.0.set_fastpath_processor(
                 ironrdp::session::fast_path::ProcessorBuilder {
@@ -158,6 +160,10 @@ pub mod ffi {
                     user_channel_id,
                     no_server_pointer,
                     pointer_software_rendering,
+                    pointer_cache_size,
+                    bitmap_cache: None,
+                    frame_acknowledge,
+                    reassembly_limits: ironrdp::session::fast_path::ReassemblyLimits::default(),
                 }
                 .build(),
             );
@@ -176,7 +182,14 @@ pub mod ffi {
         PointerPosition,
         PointerBitmap,
         Terminate,
-        DeactivateAll,
+        DeactivationReactivation,
+        SessionInfo,
+        MonitorLayoutChanged,
+        FrameBoundary,
+        ConnectionHealth,
+        PlaySound,
+        ServerRedirection,
+        MultitransportRequest,
     }
 
     impl ActiveStageOutput {
@@ -189,7 +202,22 @@ pub mod ffi {
                 ironrdp::session::ActiveStageOutput::PointerPosition { .. } => ActiveStageOutputType::PointerPosition,
                 ironrdp::session::ActiveStageOutput::PointerBitmap { .. } => ActiveStageOutputType::PointerBitmap,
                 ironrdp::session::ActiveStageOutput::Terminate { .. } => ActiveStageOutputType::Terminate,
-                ironrdp::session::ActiveStageOutput::DeactivateAll { .. } => ActiveStageOutputType::DeactivateAll,
+                ironrdp::session::ActiveStageOutput::DeactivationReactivation { .. } => {
+                    ActiveStageOutputType::DeactivationReactivation
+                }
+                ironrdp::session::ActiveStageOutput::SessionInfo { .. } => ActiveStageOutputType::SessionInfo,
+                ironrdp::session::ActiveStageOutput::MonitorLayoutChanged { .. } => {
+                    ActiveStageOutputType::MonitorLayoutChanged
+                }
+                ironrdp::session::ActiveStageOutput::FrameBoundary { .. } => ActiveStageOutputType::FrameBoundary,
+                ironrdp::session::ActiveStageOutput::ConnectionHealth { .. } => ActiveStageOutputType::ConnectionHealth,
+                ironrdp::session::ActiveStageOutput::PlaySound { .. } => ActiveStageOutputType::PlaySound,
+                ironrdp::session::ActiveStageOutput::ServerRedirection { .. } => {
+                    ActiveStageOutputType::ServerRedirection
+                }
+                ironrdp::session::ActiveStageOutput::MultitransportRequest { .. } => {
+                    ActiveStageOutputType::MultitransportRequest
+                }
             }
         }
 
@@ -244,12 +272,12 @@ pub mod ffi {
             .map(Box::new)
         }
 
-        pub fn get_deactivate_all(&self) -> Result<Box<ConnectionActivationSequence>, Box<IronRdpError>> {
+        pub fn get_deactivation_reactivation(&self) -> Result<Box<DesktopSize>, Box<IronRdpError>> {
             match &self.0 {
-                ironrdp::session::ActiveStageOutput::DeactivateAll(cas) => {
-                    Ok(ConnectionActivationSequence(cas.clone()))
+                ironrdp::session::ActiveStageOutput::DeactivationReactivation { new_desktop_size } => {
+                    Ok(DesktopSize(*new_desktop_size))
                 }
-                _ => Err(IncorrectEnumTypeError::on_variant("DeactivateAll")
+                _ => Err(IncorrectEnumTypeError::on_variant("DeactivationReactivation")
                     .of_enum("ActiveStageOutput")
                     .into()),
             }
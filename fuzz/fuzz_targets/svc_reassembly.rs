@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ironrdp_fuzzing::generators::SvcChunksInput| {
+    ironrdp_fuzzing::oracles::svc_reassembly(&input);
+});
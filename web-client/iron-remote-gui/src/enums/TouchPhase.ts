@@ -0,0 +1,7 @@
+export enum TouchPhase {
+    Down = 'down',
+    Update = 'update',
+    Up = 'up',
+    Hover = 'hover',
+    Cancel = 'cancel',
+}
@@ -33,5 +33,11 @@ export interface UserInteraction {
 
     onSessionEvent(callback: (event: SessionEvent) => void): void;
 
+    // Called when the server asks for a beep, from the next session.
+    onBeep(callback: (frequencyHz: number, durationMs: number) => void): void;
+
     resize(width: number, height: number, scale?: number): void;
+
+    // Runs the next sessions in a Web Worker when the browser supports OffscreenCanvas, on the main thread otherwise.
+    setWorkerRendering(enabled: boolean): void;
 }
@@ -11,6 +11,7 @@ export enum UserIronRdpErrorKind {
 export interface UserIronRdpError {
     backtrace: () => string;
     kind: () => UserIronRdpErrorKind;
+    code: () => number;
 }
 
 export interface SessionEvent {
@@ -75,6 +75,10 @@ export class PublicAPI {
         this.wasmService.resizeDynamic(width, height, scale);
     }
 
+    private setWorkerRendering(enabled: boolean) {
+        this.wasmService.setWorkerRendering(enabled);
+    }
+
     getExposedFunctions(): UserInteraction {
         return {
             setVisibility: this.setVisibility.bind(this),
@@ -83,12 +87,16 @@ export class PublicAPI {
             onSessionEvent: (callback) => {
                 this.wasmService.sessionObserver.subscribe(callback);
             },
+            onBeep: (callback) => {
+                this.wasmService.setOnBeep(callback);
+            },
             ctrlAltDel: this.ctrlAltDel.bind(this),
             metaKey: this.metaKey.bind(this),
             shutdown: this.shutdown.bind(this),
             setKeyboardUnicodeMode: this.setKeyboardUnicodeMode.bind(this),
             setCursorStyleOverride: this.setCursorStyleOverride.bind(this),
             resize: this.resize.bind(this),
+            setWorkerRendering: this.setWorkerRendering.bind(this),
         };
     }
 }
@@ -0,0 +1,26 @@
+// Runs the IronRDP session off the main thread, see `SessionBuilder::connect_in_worker`.
+import init, { ironrdp_init, SessionWorkerHost } from '../../../../crates/ironrdp-web/pkg/ironrdp_web';
+
+type InitMessage = { logLevel: string };
+
+let host: SessionWorkerHost | undefined;
+
+const ready = new Promise<void>((resolve) => {
+    // The first message only carries the log level, the next ones are forwarded to the host.
+    self.onmessage = async (event: MessageEvent<InitMessage>) => {
+        await init();
+        ironrdp_init(event.data.logLevel);
+        host = SessionWorkerHost.new();
+        resolve();
+    };
+});
+
+ready.then(() => {
+    self.onmessage = (event: MessageEvent) => {
+        try {
+            host!.handle_message(event.data);
+        } catch (err) {
+            console.error('Session worker failed to handle a message', err);
+        }
+    };
+});
@@ -9,6 +9,8 @@ import init, {
     SessionBuilder,
     ClipboardTransaction,
     SessionTerminationInfo,
+    WorkerSession,
+    worker_rendering_supported,
 } from '../../../../crates/ironrdp-web/pkg/ironrdp_web';
 import { loggingService } from './logging.service';
 import { catchError, filter, map } from 'rxjs/operators';
@@ -22,6 +24,7 @@ import type { NewSessionInfo } from '../interfaces/NewSessionInfo';
 import { SpecialCombination } from '../enums/SpecialCombination';
 import type { ResizeEvent } from '../interfaces/ResizeEvent';
 import { ScreenScale } from '../enums/ScreenScale';
+import type { TouchPhase } from '../enums/TouchPhase';
 import type { MousePosition } from '../interfaces/MousePosition';
 import type { SessionEvent, UserIronRdpErrorKind } from '../interfaces/session-event';
 import type { DesktopSize as IDesktopSize } from '../interfaces/DesktopSize';
@@ -29,6 +32,7 @@ import type { DesktopSize as IDesktopSize } from '../interfaces/DesktopSize';
 type OnRemoteClipboardChanged = (transaction: ClipboardTransaction) => void;
 type OnRemoteReceivedFormatsList = () => void;
 type OnForceClipboardUpdate = () => void;
+type OnBeep = (frequencyHz: number, durationMs: number) => void;
 
 export class WasmBridgeService {
     private _resize: Subject<ResizeEvent> = new Subject<ResizeEvent>();
@@ -46,11 +50,17 @@ export class WasmBridgeService {
     private onRemoteClipboardChanged?: OnRemoteClipboardChanged;
     private onRemoteReceivedFormatList?: OnRemoteReceivedFormatsList;
     private onForceClipboardUpdate?: OnForceClipboardUpdate;
+    private onBeep?: OnBeep;
     private cursorHasOverride: boolean = false;
     private lastCursorStyle: string = 'default';
+    private logLevel: LogType = LogType.INFO;
+    private workerRendering: boolean = false;
+    private onDocumentVisibilityChange = () => {
+        this.session?.set_visibility(document.visibilityState === 'visible');
+    };
 
     resize: Observable<ResizeEvent>;
-    session?: Session;
+    session?: Session | WorkerSession;
     modifierKeyPressed: ModifierKey[] = [];
     mousePositionObservable: Observable<MousePosition> = this.mousePosition.asObservable();
     changeVisibilityObservable: Observable<boolean> = this.changeVisibility.asObservable();
@@ -72,6 +82,12 @@ export class WasmBridgeService {
         await init();
         loggingService.info('Initializing IronRDP.');
         ironrdp_init(LogType[debug]);
+        this.logLevel = debug;
+    }
+
+    /// Runs the session in a Web Worker, rendering on an `OffscreenCanvas`, when the browser supports it.
+    setWorkerRendering(enabled: boolean) {
+        this.workerRendering = enabled;
     }
 
     /// Callback to set the local clipboard content to data received from the remote.
@@ -90,6 +106,12 @@ export class WasmBridgeService {
         this.onForceClipboardUpdate = callback;
     }
 
+    /// Callback which is called when the remote asks for a beep (e.g. a console application wrote the bell
+    /// character).
+    setOnBeep(callback: OnBeep) {
+        this.onBeep = callback;
+    }
+
     mouseIn(event: MouseEvent) {
         this.syncModifier(event);
         this.keyboardActive = true;
@@ -106,6 +128,21 @@ export class WasmBridgeService {
         }
     }
 
+    compositionStart(_evt: CompositionEvent) {
+        if (this.keyboardActive) {
+            this.session?.start_composition();
+        }
+    }
+
+    /// The composition is cancelled when the committed text is empty.
+    compositionEnd(evt: CompositionEvent) {
+        this.session?.end_composition();
+
+        if (this.keyboardActive && evt.data) {
+            this.doTransactionFromDeviceEvents([DeviceEvent.new_composition_commit(evt.data)]);
+        }
+    }
+
     shutdown() {
         this.session?.shutdown();
     }
@@ -126,6 +163,15 @@ export class WasmBridgeService {
         this.mousePosition.next(position);
     }
 
+    touchContact(id: number, position: MousePosition, phase: TouchPhase) {
+        this.doTransactionFromDeviceEvents([DeviceEvent.new_touch_contact(id, position.x, position.y, phase)]);
+    }
+
+    // `pressure` is from 0 to 1, and the tilts are in degrees, as reported by pointer events.
+    penMove(position: MousePosition, pressure: number, tiltX: number, tiltY: number) {
+        this.doTransactionFromDeviceEvents([DeviceEvent.new_pen_move(position.x, position.y, pressure, tiltX, tiltY)]);
+    }
+
     connect(
         username: string,
         password: string,
@@ -163,32 +209,37 @@ export class WasmBridgeService {
         if (this.onForceClipboardUpdate != null) {
             sessionBuilder.force_clipboard_update_callback(this.onForceClipboardUpdate);
         }
+        if (this.onBeep != null) {
+            sessionBuilder.beep_callback(this.onBeep);
+        }
 
         if (desktopSize != null) {
             sessionBuilder.desktop_size(DesktopSize.new(desktopSize.width, desktopSize.height));
         }
 
         // Type guard to filter out errors
-        function isSession(result: IronRdpError | Session): result is Session {
-            return result instanceof Session;
+        function isSession(result: IronRdpError | Session | WorkerSession): result is Session | WorkerSession {
+            return result instanceof Session || result instanceof WorkerSession;
         }
 
-        return from(sessionBuilder.connect()).pipe(
+        return from(this.connectSession(sessionBuilder)).pipe(
             catchError((err: IronRdpError) => {
                 this.raiseSessionEvent({
                     type: SessionEventType.ERROR,
                     data: {
                         backtrace: () => err.backtrace(),
                         kind: () => err.kind() as number as UserIronRdpErrorKind,
+                        code: () => err.code(),
                     },
                 });
                 return of(err);
             }),
             filter(isSession),
-            map((session: Session) => {
+            map((session: Session | WorkerSession) => {
                 from(session.run())
                     .pipe(
                         catchError((err) => {
+                            document.removeEventListener('visibilitychange', this.onDocumentVisibilityChange);
                             this.setVisibility(false);
                             this.raiseSessionEvent({
                                 type: SessionEventType.ERROR,
@@ -201,6 +252,7 @@ export class WasmBridgeService {
                             throw err;
                         }),
                         map((termination_info: SessionTerminationInfo) => {
+                            document.removeEventListener('visibilitychange', this.onDocumentVisibilityChange);
                             this.setVisibility(false);
                             this.raiseSessionEvent({
                                 type: SessionEventType.TERMINATED,
@@ -211,9 +263,12 @@ export class WasmBridgeService {
                     .subscribe();
                 return session;
             }),
-            map((session: Session) => {
+            map((session: Session | WorkerSession) => {
                 loggingService.info('Session started.');
                 this.session = session;
+                // The session stops drawing while the tab is in the background, and redraws when it is back.
+                document.addEventListener('visibilitychange', this.onDocumentVisibilityChange);
+                this.onDocumentVisibilityChange();
                 this._resize.next({
                     desktop_size: session.desktop_size(),
                     session_id: 0,
@@ -231,6 +286,21 @@ export class WasmBridgeService {
         );
     }
 
+    private connectSession(sessionBuilder: SessionBuilder): Promise<Session | WorkerSession> {
+        if (!this.workerRendering) {
+            return sessionBuilder.connect();
+        }
+
+        if (!worker_rendering_supported()) {
+            loggingService.info('OffscreenCanvas is not supported, the session runs on the main thread.');
+            return sessionBuilder.connect();
+        }
+
+        const worker = new Worker(new URL('./session.worker.ts', import.meta.url), { type: 'module' });
+        worker.postMessage({ logLevel: LogType[this.logLevel] });
+        return sessionBuilder.connect_in_worker(worker);
+    }
+
     sendSpecialCombination(specialCombination: SpecialCombination): void {
         switch (specialCombination) {
             case SpecialCombination.CTRL_ALT_DEL:
@@ -18,6 +18,11 @@ export default defineConfig({
             strict: false,
         },
     },
+    worker: {
+        // The session worker loads the WebAssembly module too, see `session.worker.ts`.
+        format: 'es',
+        plugins: () => [wasm(), topLevelAwait()],
+    },
     plugins: [
         svelte(),
         wasm(),
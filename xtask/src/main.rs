@@ -40,6 +40,8 @@ pub const FUZZ_TARGETS: &[&str] = &[
     "bitmap_stream",
     "cliprdr_format",
     "channel_processing",
+    "svc_reassembly",
+    "dvc_reassembly",
 ];
 
 fn main() -> anyhow::Result<()> {